@@ -0,0 +1,344 @@
+use anyhow::{bail, Context, Result};
+use poem::http::header::{HOST, ORIGIN};
+use poem::http::{HeaderMap, Method};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::mcp_proxy::{provided_token, tokens_match};
+use crate::dev_setup::config_files;
+
+// config.toml table holding the API tokens, e.g. `[api_auth.tokens.ci]`
+const CONFIG_SECTION: &str = "api_auth";
+// Name the single `token` from `--token` or the setup wizard goes by once named tokens exist
+const LEGACY_TOKEN_NAME: &str = "token";
+/// Principal of loopback requests let through without a token.
+pub const LOOPBACK_PRINCIPAL: &str = "loopback";
+
+/// What an API token may do. `edit` and `exec` include `read`; `admin` includes everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// View files, search, logs, diagnostics and every other GET
+    Read,
+    /// Change project files: editor mutations, patches, formatting and lint fixes, git
+    Edit,
+    /// Run commands: scripts, terminals, validation runs, jobs and MCP tools
+    Exec,
+    /// Reconfigure Galatea: setup, config files, workspaces and runtime services; read the audit log
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Edit => "edit",
+            Scope::Exec => "exec",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+/// The token an API request was authenticated with. Requests from loopback without a token
+/// (and not sent by a page of another origin) are `loopback` with the `admin` scope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiPrincipal {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+impl ApiPrincipal {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.iter().any(|s| *s == Scope::Admin || *s == scope || scope == Scope::Read)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TokenSection {
+    token: String,
+    #[serde(default)]
+    scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct AuthSection {
+    trust_loopback: Option<bool>,
+    #[serde(default)]
+    tokens: BTreeMap<String, TokenSection>,
+}
+
+/// The API tokens, from `[api_auth]` in config.toml.
+///
+/// ```toml
+/// [api_auth]
+/// trust_loopback = true
+///
+/// [api_auth.tokens.ci]
+/// token = "ci-secret"
+/// scopes = ["read"]
+///
+/// [api_auth.tokens.agent]
+/// token = "agent-secret"
+/// scopes = ["edit", "exec"]
+/// ```
+///
+/// Without named tokens the API stays open and the single `token` only guards MCP proxies that
+/// require it. Once a named token exists, every /api request needs a token whose scopes cover
+/// it, and `token` counts as an `admin` token.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiAuthConfig {
+    /// Let loopback requests without a token through, as Galatea's own MCP servers send none.
+    /// Requests a browser sends from a page of another origin still need a token, so a web page
+    /// open on the same machine can't use the API. Turn off when a reverse proxy on the same
+    /// host forwards outside traffic. Defaults to true.
+    pub trust_loopback: bool,
+    tokens: Vec<(String, ApiPrincipal)>,
+}
+
+/// Outcome of checking a request against the API tokens.
+#[derive(Debug, PartialEq)]
+pub enum AuthDecision {
+    /// `None` when no named tokens are configured and the API is open
+    Allowed(Option<ApiPrincipal>),
+    /// No token, or one that isn't configured (401)
+    Unauthenticated(String),
+    /// A valid token without the scope the request needs (403)
+    Forbidden(String),
+}
+
+impl ApiAuthConfig {
+    /// Parses the `api_auth` config table; `legacy_token` is the top-level `token` key.
+    pub fn from_config_value(section: Option<&toml::Value>, legacy_token: Option<&str>) -> Result<Self> {
+        let section: AuthSection = match section {
+            Some(value) => value.clone().try_into().context(format!("Invalid [{}] section in config.toml", CONFIG_SECTION))?,
+            None => AuthSection::default(),
+        };
+        let mut tokens: Vec<(String, ApiPrincipal)> = Vec::new();
+        for (name, entry) in section.tokens {
+            let token = entry.token.trim().to_string();
+            if token.is_empty() {
+                bail!("[{}.tokens.{}] has an empty token", CONFIG_SECTION, name);
+            }
+            if entry.scopes.is_empty() {
+                bail!("[{}.tokens.{}] has no scopes; use read, edit, exec or admin", CONFIG_SECTION, name);
+            }
+            if let Some((_, other)) = tokens.iter().find(|(t, _)| *t == token) {
+                bail!("[{}.tokens.{}] reuses the token of '{}'", CONFIG_SECTION, name, other.name);
+            }
+            tokens.push((token, ApiPrincipal { name, scopes: entry.scopes }));
+        }
+        if let Some(token) = legacy_token.map(str::trim).filter(|t| !t.is_empty() && !tokens.is_empty()) {
+            if !tokens.iter().any(|(t, _)| t == token) {
+                let principal = ApiPrincipal { name: LEGACY_TOKEN_NAME.to_string(), scopes: vec![Scope::Admin] };
+                tokens.push((token.to_string(), principal));
+            }
+        }
+        Ok(Self { trust_loopback: section.trust_loopback.unwrap_or(true), tokens })
+    }
+
+    /// Loads the tokens from config.toml.
+    pub fn load() -> Result<Self> {
+        Self::from_config_value(
+            config_files::get_config_section(CONFIG_SECTION).as_ref(),
+            config_files::get_config_value(LEGACY_TOKEN_NAME).as_deref(),
+        )
+    }
+
+    /// Whether requests need a token at all.
+    pub fn is_enforced(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Checks a request needing `required` against the token in its headers.
+    pub fn authorize(&self, headers: &HeaderMap, loopback: bool, required: Scope) -> AuthDecision {
+        if !self.is_enforced() {
+            return AuthDecision::Allowed(None);
+        }
+        let principal = match provided_token(headers).map(str::trim) {
+            Some(provided) => match self.tokens.iter().find(|(token, _)| tokens_match(provided, token)) {
+                Some((_, principal)) => principal.clone(),
+                None => return AuthDecision::Unauthenticated("Invalid API token".to_string()),
+            },
+            None if loopback && self.trust_loopback && !is_cross_origin(headers) => {
+                ApiPrincipal { name: LOOPBACK_PRINCIPAL.to_string(), scopes: vec![Scope::Admin] }
+            }
+            None => {
+                return AuthDecision::Unauthenticated(
+                    "Missing API token (send 'Authorization: Bearer <token>' or 'X-Galatea-Token')".to_string(),
+                )
+            }
+        };
+        if principal.allows(required) {
+            AuthDecision::Allowed(Some(principal))
+        } else {
+            AuthDecision::Forbidden(format!(
+                "Token '{}' lacks the '{}' scope this request needs",
+                principal.name,
+                required.as_str()
+            ))
+        }
+    }
+}
+
+/// Whether a browser sent the request from a page of another origin: its `Origin` header names
+/// another host than the one the request went to. Requests without `Origin` (MCP servers, curl)
+/// and same-origin ones (the API reference pages) aren't.
+pub fn is_cross_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(ORIGIN).map(|v| v.to_str().unwrap_or_default()) else {
+        return false;
+    };
+    let origin_host = origin.split_once("://").map(|(_, host)| host.trim_end_matches('/'));
+    let host = headers.get(HOST).and_then(|v| v.to_str().ok());
+    !matches!((origin_host, host), (Some(o), Some(h)) if o.eq_ignore_ascii_case(h))
+}
+
+// `/api/editor/command` -> (`editor`, `command`)
+fn split_api_path(path: &str) -> (&str, &str) {
+    let path = path.strip_prefix("/api/").unwrap_or(path).trim_end_matches('/');
+    path.split_once('/').unwrap_or((path, ""))
+}
+
+fn is_read_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Whether `required_scope` needs the JSON body of the request, for endpoints that only read
+/// with some options (`view`, `dry_run`, lint without `fix`, format with `check`).
+pub fn needs_body(method: &Method, path: &str) -> bool {
+    !is_read_method(method)
+        && matches!(
+            split_api_path(path),
+            ("editor", "command" | "lint" | "format" | "apply-patch" | "lint-policy") | ("codegen", _)
+        )
+}
+
+/// Whether `path` goes through the MCP proxy (`/api/<id>/mcp`), whose bodies are streamed.
+pub fn is_mcp_proxy(path: &str) -> bool {
+    let (_, rest) = split_api_path(path);
+    rest == "mcp" || rest.starts_with("mcp/")
+}
+
+/// The scope an /api request needs. `body` is its JSON body when `needs_body` says so.
+pub fn required_scope(method: &Method, path: &str, body: Option<&Value>) -> Scope {
+    let (api, rest) = split_api_path(path);
+    let read = is_read_method(method);
+    let field = |name: &str| body.and_then(|b| b.get(name)).filter(|v| !v.is_null());
+    let flag = |name: &str| field(name).and_then(Value::as_bool).unwrap_or(false);
+
+    if is_mcp_proxy(path) {
+        return Scope::Exec;
+    }
+    match api {
+        "setup" => Scope::Admin,
+        "logs" if rest == "audit" => Scope::Admin,
+        "project" if rest.starts_with("galatea-file/") || rest == "rescaffold" || rest == "openapi/refresh" || ((rest == "reset" || rest == "config") && !read) => Scope::Admin,
+        // Syncing copies files between the project and other directories or hosts
+        "project" if !read && rest.starts_with("sync") => Scope::Admin,
+        // Restoring rewrites galatea_files, config.toml and its API tokens included
+        "project" if rest.starts_with("snapshots/") && rest.ends_with("/restore") => Scope::Admin,
+        "workspaces" | "runtime" | "mcp" if !read => Scope::Admin,
+        "terminal" if !read || rest.starts_with("ws/") => Scope::Exec,
+        "editor" if !read && rest.starts_with("script") => Scope::Exec,
+        "validation" | "jobs" | "codex" if !read => Scope::Exec,
+        // Installing runs the packages' install scripts
+        "project" if !read && rest.starts_with("dependencies") => Scope::Exec,
+        _ if read => Scope::Read,
+        "code-intel" | "lsp" => Scope::Read,
+        "suggestions" if rest == "analyze" => Scope::Read,
+        "codegen" if field("output_path").is_none() => Scope::Read,
+        "editor" => match rest {
+            "find-files" | "search" | "editorconfig" => Scope::Read,
+            "command" if field("command").and_then(Value::as_str) == Some("view") => Scope::Read,
+            "command" | "apply-patch" | "lint-policy" if flag("dry_run") => Scope::Read,
+            "lint" if !flag("fix") => Scope::Read,
+            "format" if flag("check") => Scope::Read,
+            _ => Scope::Edit,
+        },
+        _ => Scope::Edit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poem::http::HeaderValue;
+    use serde_json::json;
+
+    const CONFIG: &str = r#"
+token = "root-secret"
+
+[api_auth.tokens.ci]
+token = "ci-secret"
+scopes = ["read"]
+
+[api_auth.tokens.agent]
+token = "agent-secret"
+scopes = ["edit", "exec"]
+"#;
+
+    #[test]
+    fn test_tokens_and_scopes() {
+        let config: toml::Value = CONFIG.parse().unwrap();
+        let legacy = config.get("token").and_then(|v| v.as_str());
+        let auth = ApiAuthConfig::from_config_value(config.get(CONFIG_SECTION), legacy).unwrap();
+        assert!(auth.is_enforced() && auth.trust_loopback);
+
+        let mut headers = HeaderMap::new();
+        assert!(matches!(auth.authorize(&headers, false, Scope::Read), AuthDecision::Unauthenticated(_)));
+        assert!(matches!(auth.authorize(&headers, true, Scope::Admin), AuthDecision::Allowed(Some(_))));
+        headers.insert("host", HeaderValue::from_static("localhost:3051"));
+        headers.insert("origin", HeaderValue::from_static("http://localhost:3051"));
+        assert!(matches!(auth.authorize(&headers, true, Scope::Admin), AuthDecision::Allowed(Some(_))));
+        headers.insert("origin", HeaderValue::from_static("https://evil.example"));
+        assert!(matches!(auth.authorize(&headers, true, Scope::Read), AuthDecision::Unauthenticated(_)));
+        headers.insert("origin", HeaderValue::from_static("null"));
+        assert!(is_cross_origin(&headers));
+        headers.remove("origin");
+
+        headers.insert("authorization", HeaderValue::from_static("Bearer ci-secret"));
+        assert!(matches!(auth.authorize(&headers, false, Scope::Read), AuthDecision::Allowed(Some(p)) if p.name == "ci"));
+        assert!(matches!(auth.authorize(&headers, true, Scope::Edit), AuthDecision::Forbidden(_)));
+
+        headers.insert("authorization", HeaderValue::from_static("Bearer agent-secret"));
+        assert!(matches!(auth.authorize(&headers, false, Scope::Read), AuthDecision::Allowed(_)));
+        assert!(matches!(auth.authorize(&headers, false, Scope::Exec), AuthDecision::Allowed(_)));
+        assert!(matches!(auth.authorize(&headers, false, Scope::Admin), AuthDecision::Forbidden(_)));
+
+        headers.insert("authorization", HeaderValue::from_static("Bearer root-secret"));
+        assert!(matches!(auth.authorize(&headers, false, Scope::Admin), AuthDecision::Allowed(Some(p)) if p.name == "token"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer nope"));
+        assert!(matches!(auth.authorize(&headers, true, Scope::Read), AuthDecision::Unauthenticated(_)));
+
+        // Only the legacy token: the API stays open
+        let open = ApiAuthConfig::from_config_value(None, legacy).unwrap();
+        assert_eq!(open.authorize(&headers, false, Scope::Admin), AuthDecision::Allowed(None));
+
+        let bad: toml::Value = "[tokens.x]\ntoken = \"t\"\n".parse().unwrap();
+        assert!(ApiAuthConfig::from_config_value(Some(&bad), None).is_err());
+    }
+
+    #[test]
+    fn test_required_scope() {
+        let post = Method::POST;
+        let scope = |method: &Method, path: &str, body: Value| required_scope(method, path, Some(&body));
+        assert_eq!(scope(&Method::GET, "/api/editor/lint-policy", json!(null)), Scope::Read);
+        assert_eq!(scope(&post, "/api/editor/command", json!({"command": "view", "path": "a.ts"})), Scope::Read);
+        assert_eq!(scope(&post, "/api/editor/command", json!({"command": "str_replace"})), Scope::Edit);
+        assert_eq!(scope(&post, "/api/editor/command", json!({"command": "create", "dry_run": true})), Scope::Read);
+        assert_eq!(scope(&post, "/api/editor/lint", json!({"fix": true})), Scope::Edit);
+        assert_eq!(scope(&post, "/api/editor/lint", json!({})), Scope::Read);
+        assert_eq!(scope(&post, "/api/editor/search", json!(null)), Scope::Read);
+        assert_eq!(scope(&post, "/api/editor/script", json!(null)), Scope::Exec);
+        assert_eq!(scope(&post, "/api/project/sync", json!(null)), Scope::Admin);
+        assert_eq!(scope(&Method::DELETE, "/api/project/sync/abc", json!(null)), Scope::Admin);
+        assert_eq!(scope(&Method::GET, "/api/terminal/ws/abc", json!(null)), Scope::Exec);
+        assert_eq!(scope(&post, "/api/editor/mcp", json!(null)), Scope::Exec);
+        assert_eq!(scope(&post, "/api/lsp/hover", json!(null)), Scope::Read);
+        assert_eq!(scope(&post, "/api/git/commit", json!(null)), Scope::Edit);
+        assert_eq!(scope(&Method::GET, "/api/project/galatea-file/config.toml", json!(null)), Scope::Admin);
+        assert_eq!(scope(&Method::DELETE, "/api/workspaces/shop-1a2b3c", json!(null)), Scope::Admin);
+        assert!(needs_body(&post, "/api/editor/command") && !needs_body(&Method::GET, "/api/editor/command"));
+        assert!(!needs_body(&post, "/api/editor/script"));
+    }
+}
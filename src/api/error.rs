@@ -0,0 +1,178 @@
+use poem::error::ResponseError;
+use poem::http::StatusCode;
+use poem::Response;
+use poem_openapi::registry::{MetaMediaType, MetaResponse, MetaResponses, Registry};
+use poem_openapi::types::Type;
+use poem_openapi::{ApiResponse, Object};
+use serde_json::json;
+
+use crate::dev_runtime::capabilities::{self, CapabilityError};
+
+/// Body of every error the API answers with.
+#[derive(Object, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct ErrorBody {
+    /// Stable, machine-readable code: `bad_request`, `invalid`, `forbidden`, `not_found`,
+    /// `conflict`, `stale`, `rate_limited`, `capability_unavailable`, `unavailable` or `internal`
+    pub code: String,
+
+    /// Human-readable explanation
+    pub message: String,
+
+    /// Structured context, depending on `code`
+    ///
+    /// `invalid` lists what failed validation; `stale` has what the caller expected and what
+    /// is there now; `capability_unavailable` has the `capability`, its `state` and
+    /// `retry_after_secs`.
+    pub details: Option<serde_json::Value>,
+}
+
+/// An error a handler answers with, sent as an `ErrorBody`.
+#[derive(Debug)]
+pub enum GalateaError {
+    /// Malformed request, or one asking for something impossible
+    BadRequest(String),
+    /// Well-formed request whose content failed validation; `details` says what failed
+    Invalid { message: String, details: serde_json::Value },
+    Forbidden(String),
+    NotFound(String),
+    /// Conflicts with the current state, like an operation already in progress
+    Conflict(String),
+    /// The resource changed since the caller read it; `details` has its current state
+    Stale { message: String, details: serde_json::Value },
+    /// Over a configured rate or resource limit
+    TooManyRequests(String),
+    /// An optional subsystem the request needs is missing
+    CapabilityUnavailable(CapabilityError),
+    Unavailable(String),
+    Internal(String),
+}
+
+impl GalateaError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GalateaError::BadRequest(_) => "bad_request",
+            GalateaError::Invalid { .. } => "invalid",
+            GalateaError::Forbidden(_) => "forbidden",
+            GalateaError::NotFound(_) => "not_found",
+            GalateaError::Conflict(_) => "conflict",
+            GalateaError::Stale { .. } => "stale",
+            GalateaError::TooManyRequests(_) => "rate_limited",
+            GalateaError::CapabilityUnavailable(_) => capabilities::UNAVAILABLE_CODE,
+            GalateaError::Unavailable(_) => "unavailable",
+            GalateaError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            GalateaError::Invalid { details, .. } | GalateaError::Stale { details, .. } => Some(details.clone()),
+            GalateaError::CapabilityUnavailable(e) => Some(json!({
+                "capability": e.capability.as_str(),
+                "state": e.state.as_str(),
+                "retry_after_secs": e.retry_after_secs,
+            })),
+            _ => None,
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody { code: self.code().to_string(), message: self.to_string(), details: self.details() }
+    }
+}
+
+impl std::fmt::Display for GalateaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GalateaError::BadRequest(message)
+            | GalateaError::Invalid { message, .. }
+            | GalateaError::Forbidden(message)
+            | GalateaError::NotFound(message)
+            | GalateaError::Conflict(message)
+            | GalateaError::Stale { message, .. }
+            | GalateaError::TooManyRequests(message)
+            | GalateaError::Unavailable(message)
+            | GalateaError::Internal(message) => f.write_str(message),
+            GalateaError::CapabilityUnavailable(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for GalateaError {}
+
+impl ResponseError for GalateaError {
+    fn status(&self) -> StatusCode {
+        match self {
+            GalateaError::BadRequest(_) | GalateaError::Invalid { .. } => StatusCode::BAD_REQUEST,
+            GalateaError::Forbidden(_) => StatusCode::FORBIDDEN,
+            GalateaError::NotFound(_) => StatusCode::NOT_FOUND,
+            GalateaError::Conflict(_) | GalateaError::Stale { .. } => StatusCode::CONFLICT,
+            GalateaError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            GalateaError::CapabilityUnavailable(_) | GalateaError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            GalateaError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn as_response(&self) -> Response {
+        let mut response = Response::builder().status(self.status()).content_type("application/json");
+        if let GalateaError::CapabilityUnavailable(CapabilityError { retry_after_secs: Some(secs), .. }) = self {
+            response = response.header("Retry-After", secs.to_string());
+        }
+        response.body(serde_json::to_string(&self.body()).unwrap_or_default())
+    }
+}
+
+// Documents the envelope for any 4XX and 5XX, so handlers can return `Result<_, GalateaError>`
+impl ApiResponse for GalateaError {
+    fn meta() -> MetaResponses {
+        let response = |description, range: &str| MetaResponse {
+            description,
+            status: None,
+            status_range: Some(range.to_string()),
+            content: vec![MetaMediaType { content_type: "application/json; charset=utf-8", schema: ErrorBody::schema_ref() }],
+            headers: vec![],
+        };
+        MetaResponses { responses: vec![response("Client error", "4XX"), response("Server error", "5XX")] }
+    }
+
+    fn register(registry: &mut Registry) {
+        ErrorBody::register(registry);
+    }
+}
+
+// Unexpected failures from the layers below, with their context chain
+impl From<anyhow::Error> for GalateaError {
+    fn from(error: anyhow::Error) -> Self {
+        GalateaError::Internal(format!("{:#}", error))
+    }
+}
+
+impl From<CapabilityError> for GalateaError {
+    fn from(error: CapabilityError) -> Self {
+        GalateaError::CapabilityUnavailable(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev_runtime::capabilities::{Capability, CapabilityState};
+
+    #[tokio::test]
+    async fn test_error_envelope() {
+        let error = GalateaError::from(anyhow::anyhow!("disk full").context("Failed to save"));
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: serde_json::Value = serde_json::from_str(&error.as_response().into_body().into_string().await.unwrap()).unwrap();
+        assert_eq!(body, json!({"code": "internal", "message": "Failed to save: disk full", "details": null}));
+
+        let error = GalateaError::from(CapabilityError {
+            capability: Capability::Lsp,
+            state: CapabilityState::Starting,
+            reason: None,
+            retry_after_secs: Some(5),
+        });
+        let response = error.as_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["Retry-After"], "5");
+        assert_eq!(error.body().details.unwrap()["state"], "starting");
+    }
+}
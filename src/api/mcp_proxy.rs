@@ -0,0 +1,412 @@
+use anyhow::{bail, Context, Result};
+use futures::TryStreamExt;
+use poem::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use poem::{Body, Request, Response};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::dev_setup::config_files;
+
+// config.toml table holding per-server sections, e.g. `[mcp_proxy.project]`
+const CONFIG_SECTION: &str = "mcp_proxy";
+// Section applied to every server, overridden field by field by the server's own section
+const DEFAULT_SERVER_KEY: &str = "default";
+
+/// Header clients may use instead of `Authorization` to pass the Galatea token.
+pub const TOKEN_HEADER: &str = "x-galatea-token";
+
+// Connection-level headers that must not be forwarded by a proxy (RFC 9110, section 7.6.1)
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+// Upper bound on an upstream's handshake response, which is a status line and a few headers
+const MAX_HANDSHAKE_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+struct PolicySection {
+    require_token: Option<bool>,
+    timeout_secs: Option<u64>,
+    connect_timeout_secs: Option<u64>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    strip_headers: Vec<String>,
+}
+
+/// How requests to one MCP server are proxied, from `[mcp_proxy.<server id>]` in config.toml.
+///
+/// ```toml
+/// [mcp_proxy.default]
+/// timeout_secs = 60
+///
+/// [mcp_proxy.project]
+/// require_token = true
+/// strip_headers = ["cookie"]
+/// headers = { Authorization = "Bearer backend-secret" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct McpProxyPolicy {
+    /// Require the Galatea token (config `token`) on requests to this server's proxy route
+    pub require_token: bool,
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// Headers injected into requests toward the backend, replacing client-sent values. The
+    /// only way to give the backend an `Authorization` header: the client's is never forwarded
+    pub inject_headers: BTreeMap<String, String>,
+    /// Additional (lowercase) client headers that are never forwarded
+    pub strip_headers: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuthOutcome {
+    Allowed,
+    Missing,
+    Invalid,
+    // The policy requires a token but none is configured; fail closed
+    NotConfigured,
+}
+
+impl McpProxyPolicy {
+    fn from_sections(default: PolicySection, server: PolicySection) -> Self {
+        let mut inject_headers = default.headers;
+        inject_headers.extend(server.headers);
+        let mut strip_headers: Vec<String> = default
+            .strip_headers
+            .into_iter()
+            .chain(server.strip_headers)
+            .map(|h| h.to_ascii_lowercase())
+            .collect();
+        strip_headers.sort();
+        strip_headers.dedup();
+
+        Self {
+            require_token: server.require_token.or(default.require_token).unwrap_or(false),
+            timeout: server.timeout_secs.or(default.timeout_secs).map(Duration::from_secs),
+            connect_timeout: server
+                .connect_timeout_secs
+                .or(default.connect_timeout_secs)
+                .map(Duration::from_secs),
+            inject_headers,
+            strip_headers,
+        }
+    }
+
+    /// Parses the policy for `server_id` out of the `mcp_proxy` config table.
+    pub fn from_config_value(section: Option<&toml::Value>, server_id: &str) -> Result<Self> {
+        let parse = |key: &str| -> Result<PolicySection> {
+            match section.and_then(|s| s.get(key)) {
+                Some(value) => value
+                    .clone()
+                    .try_into()
+                    .context(format!("Invalid [{}.{}] section in config.toml", CONFIG_SECTION, key)),
+                None => Ok(PolicySection::default()),
+            }
+        };
+        Ok(Self::from_sections(parse(DEFAULT_SERVER_KEY)?, parse(server_id)?))
+    }
+
+    /// Loads the policy for `server_id` from config.toml.
+    pub fn load(server_id: &str) -> Result<Self> {
+        Self::from_config_value(config_files::get_config_section(CONFIG_SECTION).as_ref(), server_id)
+    }
+
+    /// Checks the Galatea token on an incoming proxy request.
+    pub fn check_auth(&self, headers: &HeaderMap, expected_token: Option<&str>) -> AuthOutcome {
+        if !self.require_token {
+            return AuthOutcome::Allowed;
+        }
+        let Some(expected) = expected_token.filter(|t| !t.is_empty()) else {
+            return AuthOutcome::NotConfigured;
+        };
+        match provided_token(headers) {
+            None => AuthOutcome::Missing,
+            Some(token) if tokens_match(token.trim(), expected) => AuthOutcome::Allowed,
+            Some(_) => AuthOutcome::Invalid,
+        }
+    }
+
+    /// Whether a client request header should be forwarded to the backend.
+    pub fn forward_request_header(&self, headers: &HeaderMap, name: &HeaderName) -> bool {
+        let name = name.as_str();
+        if name == "host" || name == "content-length" || name == TOKEN_HEADER || is_hop_by_hop(headers, name) {
+            return false;
+        }
+        // The client's Authorization header is for Galatea (it may carry the Galatea token even
+        // when this route doesn't require it); backend credentials come from `inject_headers`
+        if name == "authorization" {
+            return false;
+        }
+        if self.inject_headers.keys().any(|k| k.eq_ignore_ascii_case(name)) {
+            return false;
+        }
+        !self.strip_headers.iter().any(|h| h == name)
+    }
+
+    /// The headers to send the backend: the forwarded client headers and the injected ones. For
+    /// an upgrade, `Connection`, `Upgrade` and the WebSocket handshake headers are kept too.
+    pub fn upstream_headers(&self, headers: &HeaderMap, upgrade: bool) -> HeaderMap {
+        let mut upstream = HeaderMap::new();
+        for (name, value) in headers {
+            let handshake = upgrade && (name == header::CONNECTION || name == header::UPGRADE || name.as_str().starts_with("sec-websocket-"));
+            if handshake || self.forward_request_header(headers, name) {
+                upstream.append(name, value.clone());
+            }
+        }
+        for (name, value) in &self.inject_headers {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+                upstream.insert(name, value);
+            }
+        }
+        upstream
+    }
+}
+
+/// The Galatea token a request carries, from `X-Galatea-Token` or `Authorization: Bearer`.
+pub fn provided_token(headers: &HeaderMap) -> Option<&str> {
+    headers.get(TOKEN_HEADER).and_then(|v| v.to_str().ok()).or_else(|| {
+        headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+    })
+}
+
+/// Compares a provided token with an expected one in time independent of where they differ,
+/// so response timing doesn't reveal how much of a guess was right.
+pub fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+/// Whether a header is hop-by-hop, either by definition or because `Connection` lists it.
+pub fn is_hop_by_hop(headers: &HeaderMap, name: &str) -> bool {
+    HOP_BY_HOP_HEADERS.contains(&name)
+        || headers
+            .get_all("connection")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|listed| listed.trim().eq_ignore_ascii_case(name))
+}
+
+/// Whether the request asks to switch protocols, as WebSockets do.
+pub fn is_upgrade_request(headers: &HeaderMap) -> bool {
+    let connection_upgrade = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|token| token.trim().eq_ignore_ascii_case("upgrade"));
+    connection_upgrade && headers.contains_key(header::UPGRADE)
+}
+
+// The handshake request as sent upstream, keeping the upgrade headers a plain proxy drops
+fn request_head(method: &str, target: &str, headers: &HeaderMap, port: u16) -> String {
+    let mut head = format!("{} {} HTTP/1.1\r\nhost: 127.0.0.1:{}\r\n", method, target, port);
+    for (name, value) in headers {
+        if name == header::HOST {
+            continue;
+        }
+        if let Ok(value) = value.to_str() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    head.push_str("\r\n");
+    head
+}
+
+// Reads the status and headers of the upstream's response, leaving what follows them (the first
+// WebSocket frames) buffered in `reader`
+async fn read_response_head<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<(StatusCode, HeaderMap)> {
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).await.context("Failed to read the upgrade handshake response")?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .with_context(|| format!("Unexpected upgrade handshake response: {:?}", status_line.trim_end()))?;
+    let mut headers = HeaderMap::new();
+    let mut read = status_line.len();
+    loop {
+        let mut line = String::new();
+        let n = reader.read_line(&mut line).await.context("Failed to read the upgrade handshake headers")?;
+        read += n;
+        if n == 0 || read > MAX_HANDSHAKE_BYTES {
+            bail!("The upgrade handshake response ended early or is too large");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.trim().as_bytes()), HeaderValue::from_str(value.trim())) {
+                headers.append(name, value);
+            }
+        }
+    }
+    Ok((status, headers))
+}
+
+/// Forwards an upgrade request (a WebSocket) with `headers` to the local server on `port` and,
+/// once it agrees to switch protocols, pipes bytes both ways until either side closes.
+pub async fn tunnel_upgrade(req: &Request, target: &str, port: u16, headers: &HeaderMap) -> Result<Response> {
+    let on_upgrade = req.take_upgrade().context("The connection can't be upgraded")?;
+    let stream = TcpStream::connect(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Failed to connect to 127.0.0.1:{}", port))?;
+    let mut upstream = BufReader::new(stream);
+    let head = request_head(req.method().as_str(), target, headers, port);
+    upstream.get_mut().write_all(head.as_bytes()).await.context("Failed to send the upgrade handshake")?;
+    let (status, headers) = read_response_head(&mut upstream).await?;
+
+    let mut response = Response::builder().status(status);
+    for (name, value) in &headers {
+        response = response.header(name, value);
+    }
+    if status != StatusCode::SWITCHING_PROTOCOLS {
+        // The server refused; hand its answer to the client without a body
+        return Ok(response.header(header::CONTENT_LENGTH, "0").finish());
+    }
+    tokio::spawn(async move {
+        let mut client = match on_upgrade.await {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::debug!(target: "api::proxy", error = %e, "Client went away before the upgrade completed.");
+                return;
+            }
+        };
+        // Frames the server sent right after its handshake are still in the reader's buffer
+        let early = upstream.buffer().to_vec();
+        if !early.is_empty() && client.write_all(&early).await.is_err() {
+            return;
+        }
+        let mut upstream = upstream.into_inner();
+        if let Err(e) = tokio::io::copy_bidirectional(&mut client, &mut upstream).await {
+            tracing::debug!(target: "api::proxy", error = %e, "Upgraded connection closed.");
+        }
+    });
+    Ok(response.finish())
+}
+
+/// A local server's response body, passed on as it arrives so event streams and streamed
+/// pages aren't held back until they end.
+pub fn stream_response_body(resp: reqwest::Response) -> Body {
+    Body::from_bytes_stream(resp.bytes_stream().map_err(std::io::Error::other))
+}
+
+/// A client's request body, forwarded as it arrives.
+pub fn stream_request_body(body: Body) -> reqwest::Body {
+    reqwest::Body::wrap_stream(body.into_bytes_stream())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poem::http::HeaderValue;
+
+    const CONFIG: &str = r#"
+token = "secret"
+
+[mcp_proxy.default]
+timeout_secs = 60
+strip_headers = ["Cookie"]
+
+[mcp_proxy.project]
+require_token = true
+connect_timeout_secs = 5
+headers = { Authorization = "Bearer backend" }
+"#;
+
+    #[test]
+    fn test_policy_merges_default_and_server_sections() {
+        let config: toml::Value = CONFIG.parse().unwrap();
+        let section = config.get(CONFIG_SECTION);
+
+        let project = McpProxyPolicy::from_config_value(section, "project").unwrap();
+        assert!(project.require_token);
+        assert_eq!(project.timeout, Some(Duration::from_secs(60)));
+        assert_eq!(project.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(project.inject_headers["Authorization"], "Bearer backend");
+        assert_eq!(project.strip_headers, vec!["cookie".to_string()]);
+
+        let editor = McpProxyPolicy::from_config_value(section, "editor").unwrap();
+        assert!(!editor.require_token);
+        assert!(editor.inject_headers.is_empty());
+        assert_eq!(McpProxyPolicy::from_config_value(None, "editor").unwrap(), McpProxyPolicy::default());
+    }
+
+    #[test]
+    fn test_auth_and_header_filtering() {
+        let config: toml::Value = CONFIG.parse().unwrap();
+        let policy = McpProxyPolicy::from_config_value(config.get(CONFIG_SECTION), "project").unwrap();
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(policy.check_auth(&headers, Some("secret")), AuthOutcome::Missing);
+        headers.insert("authorization", HeaderValue::from_static("Bearer wrong"));
+        assert_eq!(policy.check_auth(&headers, Some("secret")), AuthOutcome::Invalid);
+        headers.insert(TOKEN_HEADER, HeaderValue::from_static("secret"));
+        assert_eq!(policy.check_auth(&headers, Some("secret")), AuthOutcome::Allowed);
+        assert_eq!(policy.check_auth(&headers, None), AuthOutcome::NotConfigured);
+        assert!(!tokens_match("secre", "secret") && !tokens_match("", "secret"));
+
+        headers.insert("connection", HeaderValue::from_static("keep-alive, x-session-hop"));
+        headers.insert("x-session-hop", HeaderValue::from_static("1"));
+        headers.insert("mcp-session-id", HeaderValue::from_static("abc"));
+        let forwarded: Vec<&str> = headers
+            .keys()
+            .filter(|name| policy.forward_request_header(&headers, name))
+            .map(|name| name.as_str())
+            .collect();
+        assert_eq!(forwarded, vec!["mcp-session-id"]);
+
+        headers.insert("upgrade", HeaderValue::from_static("websocket"));
+        headers.insert("connection", HeaderValue::from_static("Upgrade"));
+        headers.insert("sec-websocket-key", HeaderValue::from_static("k"));
+        let upstream = policy.upstream_headers(&headers, true);
+        assert_eq!(upstream["authorization"], "Bearer backend");
+        assert!(upstream.contains_key("upgrade") && upstream.contains_key("sec-websocket-key"));
+        assert!(!upstream.contains_key(TOKEN_HEADER));
+        assert!(!policy.upstream_headers(&headers, false).contains_key("upgrade"));
+
+        // Without a token requirement or injected credentials the client's token still stays behind
+        let open = McpProxyPolicy::default();
+        assert!(!open.forward_request_header(&headers, &header::AUTHORIZATION));
+        assert!(!open.upstream_headers(&headers, false).contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_handshake() {
+        use tokio::io::AsyncReadExt;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("keep-alive, Upgrade"));
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(header::HOST, HeaderValue::from_static("sandbox:8080"));
+        assert!(is_upgrade_request(&headers));
+        let head = request_head("GET", "/_next/webpack-hmr", &headers, 3000);
+        assert!(head.starts_with("GET /_next/webpack-hmr HTTP/1.1\r\nhost: 127.0.0.1:3000\r\n"));
+        assert!(head.contains("upgrade: websocket\r\n") && !head.contains("sandbox"));
+
+        let response: &[u8] = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nSec-WebSocket-Accept: abc\r\n\r\n\x81\x02hi";
+        let mut reader = BufReader::new(response);
+        let (status, headers) = read_response_head(&mut reader).await.unwrap();
+        assert_eq!(status, StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(headers.get("sec-websocket-accept").unwrap(), "abc");
+        let mut frame = Vec::new();
+        reader.read_to_end(&mut frame).await.unwrap();
+        assert_eq!(frame, b"\x81\x02hi");
+    }
+}
@@ -1,4 +1,8 @@
+pub mod auth;
+pub mod error;
+pub mod mcp_proxy;
 pub mod models;
+pub mod preview_proxy;
 pub mod routes;
 
 use poem::{Route, get};
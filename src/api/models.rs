@@ -26,10 +26,14 @@ pub struct ParseFileRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ParseDirectoryRequest {
     pub dir: String,
+    /// May be omitted when the analysis profile lists languages
+    #[serde(default)]
     pub suffixes: Vec<String>,
     pub exclude_dirs: Option<Vec<String>>,
     pub max_snippet_size: Option<usize>,
     pub granularity: Option<String>,
+    /// Named analysis profile from config.toml; defaults to the workspace's `analysis_profile`
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,6 +46,17 @@ pub struct QueryRequest {
     pub qdrant_url: Option<String>,
 }
 
+/// A semantic search hit, ranked by similarity, file location, recency and definition-vs-usage.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RankedCodeEntity {
+    #[serde(flatten)]
+    pub entity: crate::codebase_indexing::parser::CodeEntity,
+    /// Cosine similarity reported by Qdrant
+    pub similarity: f32,
+    pub score: f64,
+    pub score_explanation: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GenerateEmbeddingsRequest {
     pub input_file: String,
@@ -68,6 +83,8 @@ pub struct UpsertEmbeddingsRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BuildIndexRequest {
     pub dir: String,
+    /// May be omitted when the analysis profile lists languages
+    #[serde(default)]
     pub suffixes: Vec<String>,
     pub exclude_dirs: Option<Vec<String>>,
     pub max_snippet_size: Option<usize>,
@@ -77,6 +94,24 @@ pub struct BuildIndexRequest {
     pub api_base: Option<String>,
     pub collection_name: String,
     pub qdrant_url: Option<String>,
+    /// Named analysis profile from config.toml; defaults to the workspace's `analysis_profile`
+    pub profile: Option<String>,
+}
+
+/// An analysis profile as listed by the API.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalysisProfileInfo {
+    pub name: String,
+    /// Whether this is the workspace default (`analysis_profile` in config.toml)
+    pub is_default: bool,
+    pub include: Vec<String>,
+    pub exclude_dirs: Vec<String>,
+    pub languages: Vec<String>,
+    /// File extensions the languages resolve to
+    pub extensions: Vec<String>,
+    pub max_snippet_size: Option<usize>,
+    pub granularity: Option<String>,
+    pub embedding: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
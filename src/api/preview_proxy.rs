@@ -0,0 +1,30 @@
+/// Path the Next.js app is served under through Galatea.
+pub const PREVIEW_PREFIX: &str = "/preview";
+
+/// Path and query to request from the dev server: `/preview/about?x=1` -> `/about?x=1`. Paths
+/// outside the prefix (`/_next/static/...`, which pages reference from the root) are passed as is.
+pub fn upstream_path(path: &str, query: Option<&str>) -> String {
+    let path = match path.strip_prefix(PREVIEW_PREFIX) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => path,
+    };
+    let mut target = format!("/{}", path.trim_start_matches('/'));
+    if let Some(query) = query {
+        target.push('?');
+        target.push_str(query);
+    }
+    target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_path() {
+        assert_eq!(upstream_path("/preview", None), "/");
+        assert_eq!(upstream_path("/preview/about", Some("tab=1")), "/about?tab=1");
+        assert_eq!(upstream_path("/_next/webpack-hmr", Some("id=x")), "/_next/webpack-hmr?id=x");
+        assert_eq!(upstream_path("/previews/x", None), "/previews/x");
+    }
+}
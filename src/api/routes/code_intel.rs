@@ -1,13 +1,33 @@
-use poem::{Route, get, handler, post, web::Json, http::StatusCode, Error as PoemError};
+use poem::{Route, get, handler, post, web::{Data, Json}, http::StatusCode, EndpointExt, Error as PoemError};
 use anyhow::Result;
+use std::sync::Arc;
+use crate::api::error::GalateaError;
 use crate::api::models::*;
+use crate::codebase_indexing::call_graph::{self, Direction};
+use crate::codebase_indexing::context::{self, ContextEntity};
+use crate::codebase_indexing::index_manager;
 use crate::codebase_indexing::parser::{self, CodeEntity};
 use crate::codebase_indexing::postprocessor;
+use crate::codebase_indexing::profiles::{self, AnalysisProfile};
+use crate::codebase_indexing::semantic::{self, EmbeddingConfig, HttpEmbeddingProvider};
+use crate::codebase_indexing::structure as structure_tree;
+use crate::codebase_indexing::styles;
 use crate::codebase_indexing::embedding as embedder;
 use crate::codebase_indexing::vector_db as hoarder;
+use crate::api::routes::runtime::CapabilityUnavailableResponse;
+use crate::dev_operation::diagnostics::{self, DiagnosticSource, SourceStatus};
+use crate::dev_operation::structure;
+use crate::dev_operation::suggestions::Severity;
+use crate::dev_runtime::capabilities::{self, Capability};
+use crate::dev_runtime::lsp_pool::{self, LspPool};
 use crate::file_system;
+use crate::file_system::ranking::{self, RankSignals, RankingWeights};
 use tracing::{error, info, warn};
 use tokio;
+use poem_openapi::{
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
 
 #[handler]
 async fn code_intel_health() -> &'static str {
@@ -30,19 +50,14 @@ async fn parse_file_handler(
         ));
     }
     
-    let extension = file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .ok_or_else(|| {
-            PoemError::from_string("File has no extension", StatusCode::BAD_REQUEST)
-        })?;
-        
-    let parse_result = match extension {
-        "rs" => parser::extract_rust_entities_from_file(&file_path, req.max_snippet_size),
-        "ts" => parser::extract_ts_entities(&file_path, false, req.max_snippet_size),
-        "tsx" => parser::extract_ts_entities(&file_path, true, req.max_snippet_size),
-        _ => Err(anyhow::anyhow!("Unsupported file extension: {}", extension)),
-    };
+    if parser::SourceLanguage::from_path(&file_path).is_none() {
+        return Err(PoemError::from_string(
+            format!("Unsupported file type; supported extensions: {}", parser::SUPPORTED_EXTENSIONS.join(", ")),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let parse_result = parser::extract_entities_from_file(&file_path, req.max_snippet_size);
     
     match parse_result {
         Ok(entities) => Ok(Json(entities)),
@@ -53,40 +68,90 @@ async fn parse_file_handler(
     }
 }
 
+// Scan filters of a request, merged with its analysis profile; explicit request values win
+struct ScanSettings {
+    extensions: Vec<String>,
+    exclude_dirs: Vec<String>,
+    max_snippet_size: Option<usize>,
+    granularity: postprocessor::Granularity,
+    profile: Option<AnalysisProfile>,
+}
+
+fn scan_settings(
+    profile_name: Option<&str>,
+    suffixes: Vec<String>,
+    exclude_dirs: Option<Vec<String>>,
+    max_snippet_size: Option<usize>,
+    granularity: Option<String>,
+) -> Result<ScanSettings, PoemError> {
+    let profile = profiles::resolve_profile(profile_name)
+        .map_err(|e| PoemError::from_string(e.to_string(), StatusCode::BAD_REQUEST))?
+        .map(|(_, profile)| profile);
+    let extensions = match (suffixes.is_empty(), &profile) {
+        (false, _) => suffixes,
+        (true, Some(profile)) if !profile.languages.is_empty() => profile.extensions(),
+        _ => {
+            return Err(PoemError::from_string(
+                "'suffixes' is required unless the analysis profile lists languages",
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+    };
+    let exclude_dirs = exclude_dirs
+        .or_else(|| profile.as_ref().map(|p| p.exclude_dirs.clone()).filter(|dirs| !dirs.is_empty()))
+        // Neither the request nor the profile lists any
+        .unwrap_or_else(|| file_system::dirs::SKIPPED_DIRS.iter().map(|d| d.to_string()).collect());
+    let granularity = match granularity.or_else(|| profile.as_ref().and_then(|p| p.granularity.clone())).as_deref() {
+        Some("coarse") => postprocessor::Granularity::Coarse,
+        Some("medium") => postprocessor::Granularity::Medium,
+        _ => postprocessor::Granularity::Fine,
+    };
+    Ok(ScanSettings {
+        extensions,
+        exclude_dirs,
+        max_snippet_size: max_snippet_size.or_else(|| profile.as_ref().and_then(|p| p.max_snippet_size)),
+        granularity,
+        profile,
+    })
+}
+
+impl ScanSettings {
+    // Finds the files to parse under `dir`, applying the profile's include globs
+    fn find_files(&self, dir: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+        let extensions: Vec<&str> = self.extensions.iter().map(|s| s.as_str()).collect();
+        let exclude_dirs: Vec<&str> = self.exclude_dirs.iter().map(|s| s.as_str()).collect();
+        let mut files = file_system::find_files_by_extensions(dir, &extensions, &exclude_dirs)?;
+        if let Some(profile) = &self.profile {
+            files.retain(|file| {
+                let rel = file.strip_prefix(dir).unwrap_or(file).to_string_lossy().replace('\\', "/");
+                profile.includes(&rel)
+            });
+        }
+        Ok(files)
+    }
+}
+
 #[handler]
 async fn parse_directory_handler(
     Json(req): Json<ParseDirectoryRequest>,
 ) -> Result<Json<Vec<CodeEntity>>, PoemError> {
     let dir = std::path::PathBuf::from(&req.dir);
-    let suffixes_ref: Vec<&str> = req.suffixes.iter().map(|s| s.as_str()).collect();
-    let exclude_dirs = req.exclude_dirs.unwrap_or_else(|| {
-        vec![
-            "node_modules".to_string(),
-            "target".to_string(),
-            "dist".to_string(),
-            "build".to_string(),
-            ".git".to_string(),
-            ".vscode".to_string(),
-            ".idea".to_string(),
-        ]
-    });
-    let exclude_dirs_ref: Vec<&str> = exclude_dirs.iter().map(|s| s.as_str()).collect();
-    
-    let granularity = match req.granularity.as_deref() {
-        Some("coarse") => postprocessor::Granularity::Coarse,
-        Some("medium") => postprocessor::Granularity::Medium,
-        _ => postprocessor::Granularity::Fine,
-    };
-    
-    let files_to_parse =
-        match file_system::find_files_by_extensions(&dir, &suffixes_ref, &exclude_dirs_ref) {
+    let settings = scan_settings(
+        req.profile.as_deref(),
+        req.suffixes,
+        req.exclude_dirs,
+        req.max_snippet_size,
+        req.granularity,
+    )?;
+
+    let files_to_parse = match settings.find_files(&dir) {
         Ok(files) => files,
-            Err(e) => {
-                return Err(PoemError::from_string(
-            format!("Error finding files: {}", e),
-                    StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-            }
+        Err(e) => {
+            return Err(PoemError::from_string(
+                format!("Error finding files: {}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
     };
     
     if files_to_parse.is_empty() {
@@ -95,15 +160,10 @@ async fn parse_directory_handler(
     
     let mut all_entities: Vec<CodeEntity> = Vec::new();
     for file_path in files_to_parse {
-        let extension = file_path.extension().and_then(|ext| ext.to_str());
-        let parse_result = match extension {
-            Some("rs") => {
-                parser::extract_rust_entities_from_file(&file_path, req.max_snippet_size)
-            }
-            Some("ts") => parser::extract_ts_entities(&file_path, false, req.max_snippet_size),
-            Some("tsx") => parser::extract_ts_entities(&file_path, true, req.max_snippet_size),
-            _ => continue,
-        };
+        if parser::SourceLanguage::from_path(&file_path).is_none() {
+            continue;
+        }
+        let parse_result = parser::extract_entities_from_file(&file_path, settings.max_snippet_size);
         
         if let Ok(entities) = parse_result {
             all_entities.extend(entities);
@@ -111,17 +171,47 @@ async fn parse_directory_handler(
     }
     
     let final_entities =
-        postprocessor::post_process_entities(all_entities, granularity, req.max_snippet_size);
+        postprocessor::post_process_entities(all_entities, settings.granularity, settings.max_snippet_size);
     Ok(Json(final_entities))
 }
 
+// Orders semantic hits by similarity plus the configured path, recency and definition signals
+fn rank_semantic_hits(hits: Vec<(CodeEntity, f32)>) -> Vec<RankedCodeEntity> {
+    let weights = RankingWeights::load();
+    let project_root = file_system::get_project_root().unwrap_or_default();
+    ranking::rank(hits, &weights, |(entity, similarity)| RankSignals {
+        path: &entity.context.file_path,
+        modified: ranking::modified_time(&project_root, &entity.context.file_path),
+        is_definition: entity.code_type != "Import",
+        relevance: *similarity as f64,
+    })
+    .into_iter()
+    .map(|ranked| RankedCodeEntity {
+        score_explanation: ranking::explain(&ranked.explanation),
+        score: ranked.score,
+        similarity: ranked.item.1,
+        entity: ranked.item.0,
+    })
+    .collect()
+}
+
+// Requests bringing their own API key don't depend on the configured one
+fn require_embeddings(api_key: Option<&str>) -> Result<(), PoemError> {
+    if api_key.is_some() {
+        return Ok(());
+    }
+    capabilities::require(Capability::Embeddings)
+        .map_err(|e| PoemError::from_response(CapabilityUnavailableResponse::from(e).into_response()))
+}
+
 #[handler]
 async fn query_collection_handler(
     Json(req): Json<QueryRequest>,
-) -> Result<Json<Vec<CodeEntity>>, PoemError> {
+) -> Result<Json<Vec<RankedCodeEntity>>, PoemError> {
     info!(target: "galatea::api::code_intel", collection_name = %req.collection_name, query_text = %req.query_text, "API query request");
 
     let qdrant_url = req.qdrant_url.as_deref().unwrap_or("http://localhost:6334");
+    require_embeddings(req.api_key.as_deref())?;
 
     match hoarder::query(
         &req.collection_name,
@@ -133,7 +223,7 @@ async fn query_collection_handler(
     )
     .await
     {
-        Ok(entities) => Ok(Json(entities)),
+        Ok(hits) => Ok(Json(rank_semantic_hits(hits))),
         Err(e) => {
             error!(target: "galatea::api::code_intel", error = ?e, collection_name = %req.collection_name, "Error in API query_collection");
             Err(PoemError::from_string(
@@ -159,6 +249,7 @@ async fn generate_embeddings_api_handler(
             StatusCode::BAD_REQUEST,
         ));
     }
+    require_embeddings(req.api_key.as_deref())?;
 
     match embedder::generate_embeddings_for_index(
         &input_path, 
@@ -234,11 +325,22 @@ async fn build_index_api_handler(
         .clone()
         .unwrap_or_else(|| "http://localhost:6334".to_string());
 
+    let settings = scan_settings(
+        req.profile.as_deref(),
+        req.suffixes.clone(),
+        req.exclude_dirs.clone(),
+        req.max_snippet_size,
+        req.granularity.clone(),
+    )?;
+    if settings.profile.as_ref().is_some_and(|p| !p.embedding_enabled()) {
+        return Err(PoemError::from_string(
+            "The selected analysis profile disables embedding, so no vector index can be built with it",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
     let dir_clone = req.dir.clone();
-    let suffixes_clone: Vec<String> = req.suffixes.clone();
-    let exclude_dirs_clone = req.exclude_dirs.clone();
-    let max_snippet_size_clone = req.max_snippet_size;
-    let granularity_str_clone = req.granularity.clone();
+    let max_snippet_size_clone = settings.max_snippet_size;
     let embedding_model_clone = req.embedding_model.clone();
     let api_key_clone = req.api_key.clone();
     let api_base_clone = req.api_base.clone();
@@ -247,26 +349,13 @@ async fn build_index_api_handler(
     tokio::spawn(async move {
         let qdrant_url_inner = qdrant_url_for_spawn;
         let dir_path = std::path::PathBuf::from(dir_clone);
-        let suffixes_ref: Vec<&str> = suffixes_clone.iter().map(|s| s.as_str()).collect();
-        
-        let default_exclude_dirs = vec![
-            "node_modules".to_string(), "target".to_string(), "dist".to_string(),
-            "build".to_string(), ".git".to_string(), ".vscode".to_string(), ".idea".to_string(),
-        ];
-        let exclude_dirs_owned = exclude_dirs_clone.unwrap_or(default_exclude_dirs);
-        let exclude_dirs_ref: Vec<&str> = exclude_dirs_owned.iter().map(|s| s.as_str()).collect();
-        
-        let granularity = match granularity_str_clone.as_deref() {
-            Some("coarse") => postprocessor::Granularity::Coarse,
-            Some("medium") => postprocessor::Granularity::Medium,
-            _ => postprocessor::Granularity::Fine,
-        };
+        let granularity = settings.granularity;
 
         info!(target: "galatea::build_index_task", "Starting Full Index Build (API Triggered)");
 
         info!(target: "galatea::build_index_task", "[1/4] Finding files...");
         let files_to_parse =
-            match file_system::find_files_by_extensions(&dir_path, &suffixes_ref, &exclude_dirs_ref) {
+            match settings.find_files(&dir_path) {
             Ok(files) => files,
             Err(e) => {
                 error!(target: "galatea::build_index_task", error = ?e, "Wander step failed");
@@ -282,13 +371,10 @@ async fn build_index_api_handler(
         info!(target: "galatea::build_index_task", "[2/4] Parsing files...");
         let mut all_entities: Vec<CodeEntity> = Vec::new();
         for file_path in files_to_parse {
-            let extension = file_path.extension().and_then(|ext| ext.to_str());
-            let parse_result = match extension {
-                Some("rs") => parser::extract_rust_entities_from_file(&file_path, max_snippet_size_clone),
-                Some("ts") => parser::extract_ts_entities(&file_path, false, max_snippet_size_clone),
-                Some("tsx") => parser::extract_ts_entities(&file_path, true, max_snippet_size_clone),
-                _ => continue,
-            };
+            if parser::SourceLanguage::from_path(&file_path).is_none() {
+                continue;
+            }
+            let parse_result = parser::extract_entities_from_file(&file_path, max_snippet_size_clone);
             match parse_result {
                 Ok(entities) => all_entities.extend(entities),
                 Err(e) => error!(target: "galatea::build_index_task", error = ?e, file_path = %file_path.display(), "Error parsing file. Skipping."),
@@ -340,6 +426,26 @@ async fn build_index_api_handler(
     }))
 }
 
+#[handler]
+async fn list_profiles_handler() -> Json<Vec<AnalysisProfileInfo>> {
+    let default_profile = crate::dev_setup::config_files::get_config_value("analysis_profile");
+    let profiles = profiles::load_profiles()
+        .into_iter()
+        .map(|(name, profile)| AnalysisProfileInfo {
+            is_default: default_profile.as_deref() == Some(name.as_str()),
+            extensions: profile.extensions(),
+            embedding: profile.embedding_enabled(),
+            name,
+            include: profile.include,
+            exclude_dirs: profile.exclude_dirs,
+            languages: profile.languages,
+            max_snippet_size: profile.max_snippet_size,
+            granularity: profile.granularity,
+        })
+        .collect();
+    Json(profiles)
+}
+
 pub fn code_intel_routes() -> Route {
     Route::new()
         .at("/health", get(code_intel_health))
@@ -349,4 +455,866 @@ pub fn code_intel_routes() -> Route {
         .at("/generate-embeddings", post(generate_embeddings_api_handler))
         .at("/upsert-embeddings", post(upsert_embeddings_api_handler))
         .at("/build-index", post(build_index_api_handler))
-} 
\ No newline at end of file
+        .at("/profiles", get(list_profiles_handler))
+} 
+// --- Semantic search (OpenAPI) ---
+
+pub struct CodeIntelApi;
+
+#[derive(ApiResponse)]
+enum CodeIntelHealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct SemanticSearchRequest {
+    /// Natural-language description of the code to find
+    ///
+    /// **Required.** e.g. `where is the session cookie validated` or `hook that loads the cart`.
+    query: String,
+
+    /// Number of entities to return
+    ///
+    /// **Optional.** Defaults to 10, at most 100.
+    k: Option<usize>,
+
+    /// Only return entities in files under this directory, relative to the project root
+    ///
+    /// **Optional.** e.g. `src/app`.
+    path_prefix: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SemanticSearchItem {
+    /// Entity name
+    name: String,
+
+    /// Entity kind as indexed (`Function`, `Class`, `Variable`, ...)
+    kind: String,
+
+    /// File path relative to the project root
+    path: String,
+
+    /// Line of the declaration (1-indexed)
+    line: usize,
+
+    /// Line where the entity ends (1-indexed)
+    line_to: usize,
+
+    /// Declaration line
+    signature: String,
+
+    /// Doc comment, if any
+    docstring: Option<String>,
+
+    /// Name of the enclosing class, interface or impl, if any
+    container_name: Option<String>,
+
+    /// Source code of the entity
+    snippet: String,
+
+    /// Cosine similarity between the query and the entity, from -1 to 1
+    similarity: f32,
+
+    /// Ranking score; results are sorted by it, highest first
+    score: f64,
+
+    /// How the score was computed, one entry per ranking signal
+    score_explanation: Vec<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SemanticSearchResponse {
+    /// The most similar entities, best first
+    results: Vec<SemanticSearchItem>,
+
+    /// Embedding model the index and the query were embedded with
+    model: String,
+
+    /// Entities in the semantic index
+    indexed_entities: usize,
+
+    /// Entity texts embedded while answering this request, because they were new or changed
+    newly_embedded: usize,
+}
+
+#[derive(ApiResponse)]
+enum SemanticSearchApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SemanticSearchResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+    /// No embedding endpoint is usable, see `[embeddings]` in config.toml
+    #[oai(status = 503)]
+    ServiceUnavailable(OpenApiJson<CapabilityUnavailableResponse>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct ReferencesRequest {
+    /// Name of the function, component, class or other symbol
+    ///
+    /// **Required.** e.g. `formatPrice`.
+    symbol: String,
+
+    /// File defining the symbol, relative to the project root
+    ///
+    /// **Optional.** When set, files using a different symbol of the same name (their own
+    /// definition, or an import from another file) are left out. e.g. `src/lib/format.ts`.
+    defined_in: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ReferenceItem {
+    /// File path relative to the project root
+    path: String,
+
+    /// Function, component or import statement containing the reference
+    entity: String,
+
+    /// `call`, `jsx` (rendered as a component), `class` (listed in a `className`) or `import`
+    kind: String,
+
+    /// Line of the reference (1-indexed)
+    line: usize,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ReferencesResponse {
+    /// References in path and line order
+    references: Vec<ReferenceItem>,
+}
+
+#[derive(ApiResponse)]
+enum ReferencesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ReferencesResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CallGraphRequest {
+    /// Name of the function, method or component the graph starts from
+    ///
+    /// **Required.** e.g. `CheckoutPage`.
+    symbol: String,
+
+    /// File defining the symbol, relative to the project root
+    ///
+    /// **Optional.** Picks one definition when several share the name; by default the graph
+    /// starts from all of them.
+    path: Option<String>,
+
+    /// `callees` (what the symbol calls or renders), `callers` (what calls or renders it) or `both`
+    ///
+    /// **Optional.** Defaults to `callees`.
+    direction: Option<String>,
+
+    /// Number of steps to follow from the symbol
+    ///
+    /// **Optional.** Defaults to 2, at most 5.
+    depth: Option<usize>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CallGraphNodeItem {
+    /// `<path>#<name>`, or `<path>#<Class>.<name>` for methods; edges refer to nodes by it
+    id: String,
+
+    /// Entity name
+    name: String,
+
+    /// Entity kind as indexed (`Function`, `Method`, `Function Component`, `Class`)
+    kind: String,
+
+    /// File path relative to the project root
+    path: String,
+
+    /// Line of the declaration (1-indexed)
+    line: usize,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CallGraphEdgeItem {
+    /// Id of the calling node
+    from: String,
+
+    /// Id of the called node
+    to: String,
+
+    /// `call` or `jsx`
+    kind: String,
+
+    /// Line of the first such call in `from`'s file (1-indexed)
+    line: usize,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CallGraphResponse {
+    /// The symbol's definitions and every node reached from them
+    nodes: Vec<CallGraphNodeItem>,
+
+    /// Edges followed, calling node first
+    edges: Vec<CallGraphEdgeItem>,
+}
+
+#[derive(ApiResponse)]
+enum CallGraphApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<CallGraphResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    /// No function, method or component with that name is indexed
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct StylesRequest {
+    /// Selector, class, custom property or component to look up
+    ///
+    /// **Optional.** A case-insensitive substring of selector, property or component names, or a
+    /// class: `.btn` or `btn` returns the rules styling `.btn` and the components using `btn`.
+    /// Omit to list everything.
+    query: Option<String>,
+
+    /// Only search files under this directory, relative to the project root
+    ///
+    /// **Optional.** e.g. `src/components`.
+    path_prefix: Option<String>,
+
+    /// Most results to return per list
+    ///
+    /// **Optional.** Defaults to 50, at most 500.
+    limit: Option<usize>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct StyleRuleItem {
+    /// File path relative to the project root
+    path: String,
+
+    /// Selector (`.card:hover`), at-rule (`@media (min-width: 640px)`), mixin or keyframes name,
+    /// or property name (`--brand`, `$gap`)
+    name: String,
+
+    /// `Selector`, `At Rule`, `Mixin`, `Keyframes`, `Custom Property` or `Variable`
+    kind: String,
+
+    /// Enclosing rule, for nested rules and declarations
+    parent: Option<String>,
+
+    /// First line (1-indexed)
+    line: usize,
+
+    /// Last line (1-indexed)
+    line_to: usize,
+
+    /// Source of the rule or declaration
+    snippet: String,
+}
+
+impl From<styles::StyleRule> for StyleRuleItem {
+    fn from(rule: styles::StyleRule) -> Self {
+        Self { path: rule.path, name: rule.name, kind: rule.kind, parent: rule.parent, line: rule.line, line_to: rule.line_to, snippet: rule.snippet }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct ComponentClassesItem {
+    /// File path relative to the project root
+    path: String,
+
+    /// Component (or function) name
+    component: String,
+
+    /// Line of the declaration (1-indexed)
+    line: usize,
+
+    /// Classes in its `className`s (Tailwind utilities and plain classes), in order of first use
+    classes: Vec<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct StylesResponse {
+    /// Matching selectors, at-rules, mixins and keyframes of CSS/SCSS files
+    rules: Vec<StyleRuleItem>,
+
+    /// Matching custom properties and SCSS variables
+    custom_properties: Vec<StyleRuleItem>,
+
+    /// Components whose name matches or that use the queried class, with all their classes
+    components: Vec<ComponentClassesItem>,
+}
+
+#[derive(ApiResponse)]
+enum StylesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<StylesResponse>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct DiagnosticsRequest {
+    /// File or directory to report on, relative to the project root
+    ///
+    /// **Optional.** e.g. `src/app/page.tsx`. The language server is only asked when this
+    /// names a file; ESLint and tsc always check the whole project and are filtered afterwards.
+    path: Option<String>,
+
+    /// Least severe entries to include: `error`, `warning` or `info`
+    ///
+    /// **Optional.** Defaults to `info` (everything).
+    severity: Option<String>,
+
+    /// Sources to run: `eslint`, `tsc` and/or `lsp`
+    ///
+    /// **Optional.** Defaults to all three.
+    sources: Option<Vec<String>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DiagnosticItem {
+    /// File path relative to the project root
+    path: String,
+
+    /// Start line (1-indexed)
+    line: usize,
+
+    /// Start column (0-indexed characters)
+    column: usize,
+
+    /// End line (1-indexed), when the source reports a range (only `lsp` does)
+    end_line: Option<usize>,
+
+    /// End column (0-indexed characters)
+    end_column: Option<usize>,
+
+    /// `error`, `warning` or `info`
+    severity: String,
+
+    /// `eslint`, `tsc` or `lsp`
+    source: String,
+
+    /// ESLint rule id (`no-unused-vars`) or TypeScript error code (`TS2322`)
+    rule: Option<String>,
+
+    message: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DiagnosticSourceItem {
+    /// `eslint`, `tsc` or `lsp`
+    source: String,
+
+    /// `ok`, `skipped` or `failed`
+    status: String,
+
+    /// Entries the source reported before filtering, when it ran
+    count: Option<usize>,
+
+    /// Why the source was skipped or what went wrong
+    detail: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DiagnosticsResponse {
+    /// Entries sorted by file and position
+    diagnostics: Vec<DiagnosticItem>,
+
+    /// How each requested source fared
+    sources: Vec<DiagnosticSourceItem>,
+}
+
+#[derive(ApiResponse)]
+enum DiagnosticsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<DiagnosticsResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct ContextRequest {
+    /// What the agent is going to do
+    ///
+    /// **Optional** if `files` is set. e.g. `add a discount code field to the checkout form`.
+    /// The entities most similar to it are included, found by semantic search.
+    task: Option<String>,
+
+    /// Files to include, relative to the project root
+    ///
+    /// **Optional** if `task` is set. Their top-level entities come first, in the order given;
+    /// files the code index doesn't parse (e.g. `package.json`) are included whole.
+    files: Option<Vec<String>>,
+
+    /// Token budget for the whole package, estimated at four characters a token
+    ///
+    /// **Optional.** Defaults to 8000, from 500 to 200000.
+    max_tokens: Option<usize>,
+
+    /// Number of entities to find for `task`
+    ///
+    /// **Optional.** Defaults to 20, at most 100.
+    k: Option<usize>,
+
+    /// Include an excerpt of the project tree around the entities' files
+    ///
+    /// **Optional.** Defaults to true.
+    include_tree: Option<bool>,
+
+    /// Include the diagnostics of the entities' files from the last ESLint and tsc run
+    ///
+    /// **Optional.** Defaults to true.
+    include_diagnostics: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ContextEntityItem {
+    /// File path relative to the project root
+    path: String,
+
+    /// Entity name, or the file name for a file included whole
+    name: String,
+
+    /// Entity kind as indexed (`Function`, `Class`, ...), or `File`
+    kind: String,
+
+    /// First line (1-indexed)
+    line: usize,
+
+    /// Last line (1-indexed)
+    line_to: usize,
+
+    /// Declaration line
+    signature: String,
+
+    /// Doc comment, if any
+    docstring: Option<String>,
+
+    /// Name of the enclosing class, interface or impl, if any
+    container_name: Option<String>,
+
+    /// Source code
+    snippet: String,
+
+    /// `file` (from `files`), `semantic` (similar to `task`) or `keyword` (its name contains
+    /// words of `task`, when semantic search is unavailable)
+    reason: String,
+
+    /// Similarity to `task`: cosine similarity for `semantic`, share of the task's words for
+    /// `keyword`
+    score: Option<f32>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ContextResponse {
+    /// Entities in priority order: requested files first, then the best matches for `task`
+    entities: Vec<ContextEntityItem>,
+
+    /// The project tree, indented, with the directories holding the entities expanded
+    tree: Option<String>,
+
+    /// Diagnostics in the entities' files, most severe first
+    diagnostics: Vec<DiagnosticItem>,
+
+    /// Unix timestamp (seconds) of the ESLint and tsc run the diagnostics come from; absent
+    /// when neither ran since Galatea started (run `POST /api/code-intel/diagnostics`)
+    diagnostics_collected_at: Option<u64>,
+
+    /// Estimated tokens of the package
+    estimated_tokens: usize,
+
+    /// The budget it was assembled for
+    max_tokens: usize,
+
+    /// Entities that matched but didn't fit
+    omitted_entities: usize,
+
+    /// Diagnostics that didn't fit
+    omitted_diagnostics: usize,
+
+    /// What was left out or substituted, e.g. semantic search being unavailable
+    notes: Vec<String>,
+}
+
+#[derive(ApiResponse)]
+enum ContextApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ContextResponse>),
+}
+
+fn diagnostic_item(e: diagnostics::DiagnosticEntry) -> DiagnosticItem {
+    DiagnosticItem {
+        path: e.path,
+        line: e.line,
+        column: e.column,
+        end_line: e.end_line,
+        end_column: e.end_column,
+        severity: e.severity.as_str().to_string(),
+        source: e.source.as_str().to_string(),
+        rule: e.rule,
+        message: e.message,
+    }
+}
+
+// Token budgets a context package may be assembled for
+const DEFAULT_CONTEXT_TOKENS: usize = 8000;
+const MIN_CONTEXT_TOKENS: usize = 500;
+const MAX_CONTEXT_TOKENS: usize = 200_000;
+// Unindexed files larger than this aren't included whole
+const MAX_WHOLE_FILE_BYTES: usize = 256 * 1024;
+
+// The project root and the code index snapshot the reference queries run on
+async fn index_snapshot() -> Result<(std::path::PathBuf, Vec<(std::path::PathBuf, std::sync::Arc<Vec<CodeEntity>>)>)> {
+    let project_root = file_system::get_project_root()?;
+    let root = project_root.clone();
+    let snapshot = tokio::task::spawn_blocking(move || index_manager::current_snapshot(&root)).await??;
+    Ok((project_root, snapshot))
+}
+
+#[OpenApi]
+impl CodeIntelApi {
+    /// Health check endpoint for the Code Intel API
+    ///
+    /// Returns a simple status message to verify that the Code Intel API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn code_intel_health(&self) -> CodeIntelHealthResponse {
+        CodeIntelHealthResponse::Ok(PlainText("Code Intel API route is healthy".to_string()))
+    }
+
+    /// Find code by meaning
+    ///
+    /// Embeds the query and returns the `k` code entities (functions, classes, components, ...)
+    /// closest to it, ranked like the other code searches by similarity, file location and
+    /// recency. Entities come from the code index and are embedded with the OpenAI-compatible
+    /// endpoint configured under `[embeddings]` in config.toml (by default OpenAI with
+    /// `OPENAI_API_KEY`). Vectors are stored in the metadata store, so only new or changed
+    /// entities are embedded; the first search of a project embeds all of them and takes longer.
+    #[oai(path = "/semantic-search", method = "post")]
+    async fn semantic_search_handler(&self, req: OpenApiJson<SemanticSearchRequest>) -> SemanticSearchApiResponse {
+        let req = req.0;
+        if req.query.trim().is_empty() {
+            return SemanticSearchApiResponse::BadRequest(PlainText("'query' must not be empty".to_string()));
+        }
+        let config = EmbeddingConfig::load();
+        if let Err(e) = capabilities::require(Capability::Embeddings) {
+            return SemanticSearchApiResponse::ServiceUnavailable(OpenApiJson(e.into()));
+        }
+        let project_root = match file_system::get_project_root() {
+            Ok(root) => root,
+            Err(e) => return SemanticSearchApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let model = config.model.clone();
+        let provider = match HttpEmbeddingProvider::new(config) {
+            Ok(provider) => provider,
+            Err(e) => return SemanticSearchApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        };
+        let k = req.k.unwrap_or(10).clamp(1, 100);
+        let result = semantic::search(&provider, &project_root, &req.query, k, req.path_prefix.as_deref()).await;
+        let (hits, stats) = match result {
+            Ok(found) => found,
+            Err(e) => {
+                error!(target: "galatea::api::code_intel", error = ?e, "Semantic search failed");
+                return SemanticSearchApiResponse::InternalServerError(PlainText(format!("Semantic search failed: {:#}", e)));
+            }
+        };
+
+        let weights = RankingWeights::load();
+        let ranked = ranking::rank(hits, &weights, |hit| RankSignals {
+            path: &hit.path,
+            modified: ranking::modified_time(&project_root, &hit.path),
+            is_definition: true,
+            relevance: hit.similarity as f64,
+        });
+        let results = ranked
+            .into_iter()
+            .map(|ranked| {
+                let hit = ranked.item;
+                SemanticSearchItem {
+                    name: hit.entity.name,
+                    kind: hit.entity.code_type,
+                    path: hit.path,
+                    line: hit.entity.line,
+                    line_to: hit.entity.line_to,
+                    signature: hit.entity.signature,
+                    docstring: hit.entity.docstring,
+                    container_name: hit.entity.context.struct_name,
+                    snippet: hit.entity.context.snippet,
+                    similarity: hit.similarity,
+                    score: ranked.score,
+                    score_explanation: ranking::explain(&ranked.explanation),
+                }
+            })
+            .collect();
+        SemanticSearchApiResponse::Ok(OpenApiJson(SemanticSearchResponse {
+            results,
+            model,
+            indexed_entities: stats.entities,
+            newly_embedded: stats.embedded,
+        }))
+    }
+
+    /// Find references to a symbol
+    ///
+    /// Lists every call, JSX usage and import of `symbol` across the project, using the
+    /// references recorded in the code index. Imports are resolved to files (relative paths and
+    /// the `@/`/`~/` aliases), so with `defined_in` only the uses of that definition are
+    /// returned. Calls are matched by name: `user.save()` counts as a reference to any `save`.
+    #[oai(path = "/references", method = "post")]
+    async fn references_handler(&self, req: OpenApiJson<ReferencesRequest>) -> ReferencesApiResponse {
+        let req = req.0;
+        if req.symbol.trim().is_empty() {
+            return ReferencesApiResponse::BadRequest(PlainText("'symbol' must not be empty".to_string()));
+        }
+        let (project_root, snapshot) = match index_snapshot().await {
+            Ok(found) => found,
+            Err(e) => return ReferencesApiResponse::InternalServerError(PlainText(format!("Failed to index the project: {:#}", e))),
+        };
+        let defined_in = req.defined_in.map(|p| project_root.join(p));
+        let hits = call_graph::find_references(&snapshot, &project_root, &req.symbol, defined_in.as_deref());
+        let references = hits
+            .into_iter()
+            .map(|hit| ReferenceItem { path: hit.path, entity: hit.entity, kind: hit.kind, line: hit.line })
+            .collect();
+        ReferencesApiResponse::Ok(OpenApiJson(ReferencesResponse { references }))
+    }
+
+    /// Get the call graph around a symbol
+    ///
+    /// Returns the functions, methods and components `symbol` calls or renders, or that call or
+    /// render it, up to `depth` steps away. A call resolves to a definition in the same file,
+    /// else in the file it is imported from, else to every definition of that name in the
+    /// project; calls into packages are left out.
+    #[oai(path = "/call-graph", method = "post")]
+    async fn call_graph_handler(&self, req: OpenApiJson<CallGraphRequest>) -> CallGraphApiResponse {
+        let req = req.0;
+        if req.symbol.trim().is_empty() {
+            return CallGraphApiResponse::BadRequest(PlainText("'symbol' must not be empty".to_string()));
+        }
+        let direction = match req.direction.as_deref().unwrap_or("callees") {
+            "callees" => Direction::Callees,
+            "callers" => Direction::Callers,
+            "both" => Direction::Both,
+            other => {
+                return CallGraphApiResponse::BadRequest(PlainText(format!(
+                    "Unknown direction '{}'; expected callees, callers or both",
+                    other
+                )))
+            }
+        };
+        let (project_root, snapshot) = match index_snapshot().await {
+            Ok(found) => found,
+            Err(e) => return CallGraphApiResponse::InternalServerError(PlainText(format!("Failed to index the project: {:#}", e))),
+        };
+        let path = req.path.map(|p| project_root.join(p));
+        let depth = req.depth.unwrap_or(2).clamp(1, call_graph::MAX_DEPTH);
+        let graph = call_graph::call_graph(&snapshot, &project_root, &req.symbol, path.as_deref(), direction, depth);
+        if graph.nodes.is_empty() {
+            return CallGraphApiResponse::NotFound(PlainText(format!("No function, method or component named '{}' is indexed", req.symbol)));
+        }
+        CallGraphApiResponse::Ok(OpenApiJson(CallGraphResponse {
+            nodes: graph
+                .nodes
+                .into_iter()
+                .map(|n| CallGraphNodeItem { id: n.id, name: n.name, kind: n.code_type, path: n.path, line: n.line })
+                .collect(),
+            edges: graph
+                .edges
+                .into_iter()
+                .map(|e| CallGraphEdgeItem { from: e.from, to: e.to, kind: e.kind, line: e.line })
+                .collect(),
+        }))
+    }
+
+    /// Look up styles: CSS/SCSS rules, custom properties and component classes
+    ///
+    /// Searches the stylesheets of the project (selectors, at-rules, mixins, keyframes, custom
+    /// properties and SCSS variables) and the classes components put in `className`, including
+    /// Tailwind utilities passed through `cn(...)`/`clsx(...)`. Query a class to see both where
+    /// it is styled and which components use it, or a component to see its classes.
+    #[oai(path = "/styles", method = "post")]
+    async fn styles_handler(&self, req: OpenApiJson<StylesRequest>) -> StylesApiResponse {
+        let req = req.0;
+        let (project_root, snapshot) = match index_snapshot().await {
+            Ok(found) => found,
+            Err(e) => return StylesApiResponse::InternalServerError(PlainText(format!("Failed to index the project: {:#}", e))),
+        };
+        let limit = req.limit.unwrap_or(50).clamp(1, 500);
+        let found = styles::find_styles(&snapshot, &project_root, req.query.as_deref(), req.path_prefix.as_deref());
+        StylesApiResponse::Ok(OpenApiJson(StylesResponse {
+            rules: found.rules.into_iter().take(limit).map(StyleRuleItem::from).collect(),
+            custom_properties: found.custom_properties.into_iter().take(limit).map(StyleRuleItem::from).collect(),
+            components: found
+                .components
+                .into_iter()
+                .take(limit)
+                .map(|c| ComponentClassesItem { path: c.path, component: c.component, line: c.line, classes: c.classes })
+                .collect(),
+        }))
+    }
+
+    /// Get diagnostics from ESLint, tsc and the language server
+    ///
+    /// Runs `eslint --format json`, `tsc --noEmit` and, for a single file, the language
+    /// server's diagnostics, and returns what they report as one list of entries with the same
+    /// fields. Problems the language server repeats from tsc are listed once. Filter by `path`
+    /// (a file or directory) and minimum `severity`; `sources` tells which tools ran and why
+    /// any of them didn't.
+    #[oai(path = "/diagnostics", method = "post")]
+    async fn diagnostics_handler(&self, pool: Data<&Arc<LspPool>>, req: OpenApiJson<DiagnosticsRequest>) -> DiagnosticsApiResponse {
+        let req = req.0;
+        let severity = match req.severity.as_deref().map(|name| Severity::from_name(name).ok_or(name)).transpose() {
+            Ok(severity) => severity,
+            Err(name) => return DiagnosticsApiResponse::BadRequest(PlainText(format!("Unknown severity '{}'; expected error, warning or info", name))),
+        };
+        let sources = match req.sources {
+            Some(names) => match names.iter().map(|name| DiagnosticSource::from_name(name).ok_or(name)).collect::<Result<Vec<_>, _>>() {
+                Ok(sources) => sources,
+                Err(name) => return DiagnosticsApiResponse::BadRequest(PlainText(format!("Unknown source '{}'; expected eslint, tsc or lsp", name))),
+            },
+            None => DiagnosticSource::ALL.to_vec(),
+        };
+        let project_root = match file_system::get_project_root() {
+            Ok(root) => root,
+            Err(e) => return DiagnosticsApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let path = req.path.as_deref().map(|p| p.trim_start_matches("./")).filter(|p| !p.is_empty());
+        let (entries, statuses) = diagnostics::collect(pool.0, &project_root, &sources, path, severity).await;
+        DiagnosticsApiResponse::Ok(OpenApiJson(DiagnosticsResponse {
+            diagnostics: entries.into_iter().map(diagnostic_item).collect(),
+            sources: statuses
+                .into_iter()
+                .map(|(source, status)| {
+                    let (status, count, detail) = match status {
+                        SourceStatus::Ok(count) => ("ok", Some(count), None),
+                        SourceStatus::Skipped(reason) => ("skipped", None, Some(reason)),
+                        SourceStatus::Failed(error) => ("failed", None, Some(error)),
+                    };
+                    DiagnosticSourceItem { source: source.as_str().to_string(), status: status.to_string(), count, detail }
+                })
+                .collect(),
+        }))
+    }
+
+    /// Assemble prompt context for a task
+    ///
+    /// Bundles what an agent needs to work on `task` or `files` into one package that fits
+    /// `max_tokens`: the requested files' entities, the entities most similar to the task
+    /// (semantic search as in `/semantic-search`, or matching entity names when no embedding
+    /// endpoint is usable), an excerpt of the project tree and the recent diagnostics of the
+    /// files involved. Entities that don't fit are skipped for smaller ones further down, and
+    /// methods of an included class aren't repeated. Diagnostics come from the last run of
+    /// `POST /api/code-intel/diagnostics` with ESLint or tsc; none are run here.
+    #[oai(path = "/context", method = "post")]
+    async fn context_handler(&self, req: OpenApiJson<ContextRequest>) -> Result<ContextApiResponse, GalateaError> {
+        let req = req.0;
+        let task = req.task.filter(|t| !t.trim().is_empty());
+        let files = req.files.unwrap_or_default();
+        if task.is_none() && files.is_empty() {
+            return Err(GalateaError::BadRequest("Pass a 'task', 'files' or both".to_string()));
+        }
+        let max_tokens = req.max_tokens.unwrap_or(DEFAULT_CONTEXT_TOKENS).clamp(MIN_CONTEXT_TOKENS, MAX_CONTEXT_TOKENS);
+        let (project_root, snapshot) =
+            index_snapshot().await.map_err(|e| GalateaError::Internal(format!("Failed to index the project: {:#}", e)))?;
+        let canonical_root = project_root.canonicalize().unwrap_or_else(|_| project_root.clone());
+        let mut notes = Vec::new();
+
+        let mut candidates = Vec::new();
+        for file in &files {
+            let resolved = file_system::paths::resolve_path(file).map_err(|_| GalateaError::NotFound(format!("No file '{}' in the project", file)))?;
+            let rel = resolved.strip_prefix(&canonical_root).unwrap_or(&resolved).to_string_lossy().replace('\\', "/");
+            let indexed = snapshot.iter().find(|(path, _)| path == &project_root.join(&rel)).map(|(_, entities)| entities);
+            match indexed.map(|entities| context::file_entities(&rel, entities)).filter(|found| !found.is_empty()) {
+                Some(found) => candidates.extend(found),
+                None => match std::fs::read(&resolved).ok().filter(|bytes| bytes.len() <= MAX_WHOLE_FILE_BYTES).and_then(|b| String::from_utf8(b).ok()) {
+                    Some(content) => candidates.push(context::whole_file(&rel, content)),
+                    None => notes.push(format!("'{}' is left out: it isn't indexed and isn't a text file under 256 KB", rel)),
+                },
+            }
+        }
+
+        if let Some(task) = &task {
+            let k = req.k.unwrap_or(20).clamp(1, 100);
+            let semantic = match capabilities::require(Capability::Embeddings) {
+                Ok(()) => match HttpEmbeddingProvider::new(EmbeddingConfig::load()) {
+                    Ok(provider) => semantic::search(&provider, &project_root, task, k, None).await.map_err(|e| format!("{:#}", e)),
+                    Err(e) => Err(format!("{:#}", e)),
+                },
+                Err(e) => Err(e.to_string()),
+            };
+            match semantic {
+                Ok((hits, _)) => candidates.extend(hits.into_iter().map(|hit| ContextEntity {
+                    path: hit.path,
+                    entity: hit.entity,
+                    reason: "semantic",
+                    score: Some(hit.similarity),
+                })),
+                Err(reason) => {
+                    notes.push(format!("Semantic search is unavailable, matched entity names instead: {}", reason));
+                    candidates.extend(context::keyword_matches(&snapshot, &project_root, task, k));
+                }
+            }
+        }
+
+        let tree_lines = if req.include_tree.unwrap_or(true) {
+            let focus: Vec<String> = candidates.iter().map(|c| c.path.clone()).collect();
+            let root = project_root.clone();
+            let nodes = tokio::task::spawn_blocking(move || structure_tree::build_tree(&root, structure::SKIPPED_DIRS))
+                .await
+                .map_err(|e| GalateaError::Internal(format!("Failed to build the project tree: {}", e)))?;
+            context::tree_excerpt(&nodes, &focus.iter().map(String::as_str).collect::<Vec<_>>())
+        } else {
+            Vec::new()
+        };
+        let (diagnostics_collected_at, recent) = match diagnostics::recent().filter(|_| req.include_diagnostics.unwrap_or(true)) {
+            Some((at, entries)) => (Some(at), entries),
+            None => (None, Vec::new()),
+        };
+
+        let package = context::assemble(candidates, tree_lines, recent, max_tokens);
+        let entities = package
+            .entities
+            .into_iter()
+            .map(|c| ContextEntityItem {
+                path: c.path,
+                name: c.entity.name,
+                kind: c.entity.code_type,
+                line: c.entity.line,
+                line_to: c.entity.line_to,
+                signature: c.entity.signature,
+                docstring: c.entity.docstring,
+                container_name: c.entity.context.struct_name,
+                snippet: c.entity.context.snippet,
+                reason: c.reason.to_string(),
+                score: c.score,
+            })
+            .collect();
+        Ok(ContextApiResponse::Ok(OpenApiJson(ContextResponse {
+            entities,
+            tree: req.include_tree.unwrap_or(true).then_some(package.tree),
+            diagnostics: package.diagnostics.into_iter().map(diagnostic_item).collect(),
+            diagnostics_collected_at,
+            estimated_tokens: package.estimated_tokens,
+            max_tokens,
+            omitted_entities: package.omitted_entities,
+            omitted_diagnostics: package.omitted_diagnostics,
+            notes,
+        })))
+    }
+}
+
+pub fn code_intel_api_routes() -> Route {
+    let api_service = OpenApiService::new(CodeIntelApi, "Code Intel API", "1.0").server("/api/code-intel");
+    Route::new().nest("/", api_service.data(lsp_pool::shared()))
+}
@@ -0,0 +1,267 @@
+use poem::Route;
+use poem_openapi::{
+    param::Query,
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+use std::path::{Component, Path};
+
+use crate::dev_operation::editor::{self, CommandType, EditorArgs};
+use crate::dev_operation::text_encoding::TextEncoding;
+use crate::dev_operation::fixtures::{self, FixtureGenerator, ModelIndex};
+use crate::file_system::paths::get_project_root;
+
+const DEFAULT_FIXTURE_COUNT: usize = 3;
+const MAX_FIXTURE_COUNT: usize = 100;
+
+// Define an API struct
+pub struct CodegenApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct ModelView {
+    /// Type, enum or schema name
+    name: String,
+
+    /// `interface`, `type`, `enum` or `zod`
+    kind: String,
+
+    /// File path relative to the project root
+    path: String,
+
+    /// Line (1-indexed) of the declaration
+    line: usize,
+
+    /// Whether the declaration is exported, which the `factory` format requires
+    exported: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ModelListResponse {
+    models: Vec<ModelView>,
+    total_count: usize,
+}
+
+#[derive(Object, serde::Deserialize, Debug)]
+struct FixturesRequest {
+    /// **Required.** Interface, type alias, enum or zod schema to generate fixtures for
+    ///
+    /// A zod schema can also be named by its type: `User` finds `UserSchema` or `userSchema`.
+    model: String,
+
+    /// **Optional.** File declaring the model, relative to the project root
+    ///
+    /// Needed when several files declare a model of the same name.
+    path: Option<String>,
+
+    /// **Optional.** Number of fixtures, 1 to 100. Defaults to 3.
+    #[oai(validator(minimum(value = "1"), maximum(value = "100")))]
+    count: Option<usize>,
+
+    /// **Optional.** `json` (default) for the fixtures as JSON, or `factory` for a TypeScript
+    /// module exporting them with a typed `build<Model>(overrides)` factory
+    format: Option<String>,
+
+    /// **Optional.** Seed for the generated values. The same seed and model always produce the
+    /// same fixtures. Defaults to 1.
+    seed: Option<u64>,
+
+    /// **Optional.** Project-relative file to write the output to, e.g.
+    /// `src/test/fixtures/user.ts`
+    ///
+    /// Written through the editor, so it can be reverted with `undo_edit`. The factory's import
+    /// of the model is relative to this file; without it the import is relative to the project
+    /// root and the output is only returned.
+    output_path: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct FixturesResponse {
+    /// The model fixtures were generated for
+    model: ModelView,
+
+    /// Fixtures as JSON values. Dates are ISO 8601 strings.
+    fixtures: Vec<serde_json::Value>,
+
+    /// The generated output: the JSON array, or the TypeScript module for `factory`
+    content: String,
+
+    /// Project-relative path the output was written to, if `output_path` was given
+    written_path: Option<String>,
+
+    /// Referenced types that aren't declared in the project (e.g. imported from a library) and
+    /// were generated as `null`
+    unresolved_types: Vec<String>,
+}
+
+#[derive(ApiResponse)]
+enum ModelListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ModelListResponse>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum FixturesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<Box<FixturesResponse>>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+impl From<&fixtures::Model> for ModelView {
+    fn from(model: &fixtures::Model) -> Self {
+        Self {
+            name: model.name.clone(),
+            kind: model.kind.as_str().to_string(),
+            path: model.path.clone(),
+            line: model.line,
+            exported: model.exported,
+        }
+    }
+}
+
+// Only plain relative paths inside the project can be written to
+fn validate_output_path(path: &str) -> Result<String, String> {
+    let trimmed = path.trim().trim_start_matches("./");
+    let relative = Path::new(trimmed);
+    if trimmed.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("output_path '{}' must be a relative path inside the project", path));
+    }
+    Ok(trimmed.to_string())
+}
+
+#[OpenApi]
+impl CodegenApi {
+    /// Health check endpoint for the Codegen API
+    ///
+    /// Returns a simple status message to verify that the Codegen API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn codegen_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Codegen API route is healthy".to_string()))
+    }
+
+    /// List the project's models
+    ///
+    /// Interfaces, type aliases, enums and zod schemas declared at the top level of the project's
+    /// `.ts` and `.tsx` files, i.e. what `/fixtures` can generate data for. Filter by a
+    /// case-insensitive `query` on the name.
+    #[oai(path = "/models", method = "get")]
+    async fn list_models_handler(&self, query: Query<Option<String>>) -> ModelListApiResponse {
+        let index = match get_project_root().and_then(|root| ModelIndex::scan(&root)) {
+            Ok(index) => index,
+            Err(e) => return ModelListApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        };
+        let needle = query.0.unwrap_or_default().to_lowercase();
+        let models: Vec<ModelView> = index
+            .models()
+            .iter()
+            .filter(|m| m.name.to_lowercase().contains(&needle))
+            .map(ModelView::from)
+            .collect();
+        ModelListApiResponse::Ok(OpenApiJson(ModelListResponse { total_count: models.len(), models }))
+    }
+
+    /// Generate fixture data for a model
+    ///
+    /// Builds realistic values from the model's declaration: property names pick the kind of
+    /// data (`email`, `id`, `createdAt`, `price`, ...), literal unions and enums pick one of
+    /// their members, optional properties are sometimes left out, and referenced types are
+    /// expanded. Zod refinements such as `.email()` and `.uuid()` are honoured. Fixtures follow
+    /// the current declaration, so regenerate them when it changes instead of editing by hand.
+    #[oai(path = "/fixtures", method = "post")]
+    async fn fixtures_handler(&self, req: OpenApiJson<FixturesRequest>) -> FixturesApiResponse {
+        let factory = match req.0.format.as_deref() {
+            None | Some("json") => false,
+            Some("factory") => true,
+            Some(other) => {
+                return FixturesApiResponse::BadRequest(PlainText(format!(
+                    "Unknown format '{}'. Use json or factory.",
+                    other
+                )))
+            }
+        };
+        let output_path = match req.0.output_path.as_deref().map(validate_output_path).transpose() {
+            Ok(path) => path,
+            Err(e) => return FixturesApiResponse::BadRequest(PlainText(e)),
+        };
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return FixturesApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let index = match ModelIndex::scan(&project_root) {
+            Ok(index) => index,
+            Err(e) => return FixturesApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        };
+        let model = match index.find(&req.0.model, req.0.path.as_deref()) {
+            Ok(model) => model,
+            Err(e) => return FixturesApiResponse::NotFound(PlainText(e.to_string())),
+        };
+
+        let count = req.0.count.unwrap_or(DEFAULT_FIXTURE_COUNT).clamp(1, MAX_FIXTURE_COUNT);
+        let mut generator = FixtureGenerator::new(&index, req.0.seed.unwrap_or(1));
+        let samples = generator.generate(model, count);
+        let fixtures: Vec<serde_json::Value> = samples.iter().map(|s| s.to_json()).collect();
+        let content = if factory {
+            match fixtures::render_factory(model, &samples, output_path.as_deref()) {
+                Ok(content) => content,
+                Err(e) => return FixturesApiResponse::BadRequest(PlainText(e.to_string())),
+            }
+        } else {
+            format!("{}\n", serde_json::to_string_pretty(&fixtures).unwrap_or_default())
+        };
+
+        if let Some(path) = &output_path {
+            let args = EditorArgs {
+                command: CommandType::Create,
+                path: Some(project_root.join(path).to_string_lossy().into_owned()),
+                paths: None,
+                file_text: Some(content.clone()),
+                insert_line: None,
+                new_str: None,
+                old_str: None,
+                view_range: None,
+                range: None,
+                expected_hash: None,
+                count: None,
+                use_regex: false,
+                max_replacements: None,
+                dry_run: false,
+                new_path: None,
+                // Regenerating fixtures is expected to rewrite the whole file
+                override_guardrails: true,
+                encoding: TextEncoding::Utf8,
+                view_offset: None,
+                view_length: None,
+            };
+            let paths = editor::command_paths(&args);
+            let result = editor::with_files(paths, move |editor| editor::handle_command(editor, args)).await.and_then(|r| r);
+            if let Err(e) = result {
+                return FixturesApiResponse::InternalServerError(PlainText(e));
+            }
+        }
+
+        FixturesApiResponse::Ok(OpenApiJson(Box::new(FixturesResponse {
+            model: ModelView::from(model),
+            fixtures,
+            content,
+            written_path: output_path,
+            unresolved_types: generator.unresolved(),
+        })))
+    }
+}
+
+pub fn codegen_routes() -> Route {
+    let api_service = OpenApiService::new(CodegenApi, "Codegen API", "1.0").server("/api/codegen");
+    Route::new().nest("/", api_service)
+}
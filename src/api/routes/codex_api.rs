@@ -1,277 +1,343 @@
-use poem::{
-    error::NotFoundError,
-    handler,
-    http::StatusCode,
-    post,
-    get,
-    web::{Data, Json, Path},
-    IntoResponse,
-    Result,
-    Route,
+use futures::stream::{self, BoxStream, StreamExt};
+use poem::web::sse::Event;
+use poem::Route;
+use poem_openapi::{
+    param::{Path as OpenApiPath, Query},
+    payload::{EventStream, Json as OpenApiJson, PlainText},
+    types::ToJSON,
+    ApiResponse, Object, OpenApi, OpenApiService,
 };
-use serde::{Deserialize, Serialize};
-// use serde_json::Value; // Removed: No longer needed for raw output
-use std::sync::Arc;
-use std::time::Instant;
-use tokio::io::AsyncReadExt;
-use tokio::process::Command;
-use uuid::Uuid;
-use dashmap::DashMap;
-
-use crate::file_system;
-
-// New struct for the request body
-#[derive(Deserialize, Debug, Clone)]
-struct CodexQueryRequest {
-    query_text: String,
-}
+use tokio::sync::broadcast::error::RecvError;
 
-// Define the new response structure
-#[derive(Serialize, Debug, Default, Clone)]
-pub struct CodexApiResponse {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    assistant_message: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    function_result: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    raw_codex_output: Option<String>,
-}
+use crate::api::error::GalateaError;
+use crate::dev_runtime::codex_session::{self, Approval, ApprovalDecision, CodexError, SessionEvent, SessionRecord};
+
+// Events returned with a session unless `events_limit` says otherwise
+const DEFAULT_EVENTS_LIMIT: usize = 500;
+const MAX_EVENTS_LIMIT: usize = 5000;
+
+// Define an API struct
+pub struct CodexApi;
 
-#[derive(Serialize, Debug)]
-struct CodexSubmitResponse {
-    task_id: String,
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
 }
 
-#[derive(Serialize, Debug, Clone)]
-#[serde(tag = "status", content = "details")]
-pub enum CodexTaskStatus {
-    Pending { query_text: String, #[serde(skip)] last_updated: Instant },
-    Processing { query_text: String, #[serde(skip)] last_updated: Instant },
-    Completed { query_text: String, response: CodexApiResponse, #[serde(skip)] last_updated: Instant },
-    Failed { query_text: String, error: String, #[serde(skip)] last_updated: Instant },
+#[derive(Object, serde::Serialize)]
+struct ApprovalView {
+    /// Id of the proposed call, for `POST /api/codex/sessions/{id}/approvals/{call_id}`
+    call_id: String,
+
+    /// `exec` for a command, `patch` for file changes
+    kind: String,
+
+    /// The command line, or the files the patch changes
+    summary: String,
+
+    /// Why the agent wants to run it, when it says
+    reason: Option<String>,
+
+    /// Unix timestamp (seconds) the agent asked at
+    requested_at: u64,
+
+    /// `approved`, `approved_for_session` or `denied`; absent while the agent is waiting
+    decision: Option<String>,
+
+    /// Unix timestamp (seconds) it was answered at
+    decided_at: Option<u64>,
 }
 
-impl CodexTaskStatus {
-    pub fn last_updated(&self) -> &Instant {
-        match self {
-            CodexTaskStatus::Pending { last_updated, .. } => last_updated,
-            CodexTaskStatus::Processing { last_updated, .. } => last_updated,
-            CodexTaskStatus::Completed { last_updated, .. } => last_updated,
-            CodexTaskStatus::Failed { last_updated, .. } => last_updated,
+impl From<Approval> for ApprovalView {
+    fn from(approval: Approval) -> Self {
+        Self {
+            call_id: approval.call_id,
+            kind: approval.kind,
+            summary: approval.summary,
+            reason: approval.reason,
+            requested_at: approval.requested_at,
+            decision: approval.decision,
+            decided_at: approval.decided_at,
         }
     }
+}
 
-    pub fn query_text(&self) -> &str {
-        match self {
-            CodexTaskStatus::Pending { query_text, .. } => query_text,
-            CodexTaskStatus::Processing { query_text, .. } => query_text,
-            CodexTaskStatus::Completed { query_text, .. } => query_text,
-            CodexTaskStatus::Failed { query_text, .. } => query_text,
-        }
+#[derive(Object, serde::Serialize)]
+struct SessionEventView {
+    /// Position in the session, from 1
+    seq: u64,
+
+    /// Unix timestamp (seconds)
+    at: u64,
+
+    /// The agent's event type, e.g. `agent_message`, `exec_command_begin`,
+    /// `exec_command_end` or `exec_approval_request`; `approval_decision` for answers given
+    /// through this API; `stderr` and `exit` for the agent's process
+    kind: String,
+
+    /// The event as the agent reported it
+    data: serde_json::Value,
+}
+
+impl From<SessionEvent> for SessionEventView {
+    fn from(event: SessionEvent) -> Self {
+        Self { seq: event.seq, at: event.at, kind: event.kind, data: event.data }
     }
 }
 
-#[derive(Serialize, Debug)]
-struct CodexStatusResponse {
-    task_id: String,
-    task_status: CodexTaskStatus,
+#[derive(Object, serde::Serialize)]
+struct SessionView {
+    /// Session id
+    id: String,
+
+    /// What the agent was asked to do
+    prompt: String,
+
+    /// The model it runs; absent for the CLI's default
+    model: Option<String>,
+
+    /// `running`, `completed`, `failed`, `terminated`, or `interrupted` when Galatea stopped
+    /// while it was running
+    status: String,
+
+    /// Unix timestamp (seconds) the session was started at
+    created_at: u64,
+
+    /// Unix timestamp (seconds) the agent exited at
+    finished_at: Option<u64>,
+
+    /// The agent's exit code; absent while running or when it was killed by a signal
+    exit_code: Option<i32>,
+
+    /// The agent's final message, once it completed the task
+    last_message: Option<String>,
+
+    /// The last error the agent reported
+    error: Option<String>,
+
+    /// Commands and patches the agent asked to run or apply, oldest first
+    approvals: Vec<ApprovalView>,
+
+    /// Number of events recorded so far
+    event_count: u64,
+
+    /// Recorded events, when requested
+    events: Option<Vec<SessionEventView>>,
 }
 
-// Removed try_pretty_print_json_string helper function as it's no longer needed.
-
-async fn run_codex_command_logic(query_text: String) -> Result<CodexApiResponse, String> {
-    let project_root_path = file_system::get_project_root().map_err(|e| {
-        let err_msg = format!("Failed to determine project root for codex command: {}", e);
-        eprintln!("{}", err_msg);
-        err_msg
-    })?;
-
-    // Use bash with nvm to ensure we're running with Node.js 22
-    let mut cmd = Command::new("bash");
-    cmd.arg("-c");
-    cmd.arg("source ~/.nvm/nvm.sh && nvm use 22 > /dev/null && codex -q \"$CODEX_QUERY\"");
-    cmd.env("CODEX_QUERY", &query_text);  // Pass the query as an environment variable to avoid shell escaping issues
-    cmd.current_dir(&project_root_path);
-
-    cmd.stdout(std::process::Stdio::piped());
-    cmd.stderr(std::process::Stdio::piped());
-
-    let mut process = match cmd.spawn() {
-        Ok(p) => p,
-        Err(e) => {
-            let err_msg = format!("Failed to start codex process: {}", e);
-            eprintln!("{}", err_msg);
-            return Err(err_msg);
+impl From<SessionRecord> for SessionView {
+    fn from(record: SessionRecord) -> Self {
+        Self {
+            id: record.id,
+            prompt: record.prompt,
+            model: record.model,
+            status: record.status.as_str().to_string(),
+            created_at: record.created_at,
+            finished_at: record.finished_at,
+            exit_code: record.exit_code,
+            last_message: record.last_message,
+            error: record.error,
+            approvals: record.approvals.into_iter().map(ApprovalView::from).collect(),
+            event_count: record.event_count,
+            events: None,
         }
-    };
-
-    let mut stdout_str = String::new();
-    if let Some(mut stdout) = process.stdout.take() {
-        if let Err(e) = stdout.read_to_string(&mut stdout_str).await {
-            let err_msg = format!("Failed to read codex stdout: {}", e);
-            eprintln!("{}", err_msg);
-            return Err(err_msg);
-        }
-    } else {
-        let err_msg = "Failed to capture codex stdout".to_string();
-        eprintln!("{}", err_msg);
-        return Err(err_msg);
     }
+}
 
-    let mut stderr_str = String::new();
-    if let Some(mut stderr) = process.stderr.take() {
-        if let Err(e) = stderr.read_to_string(&mut stderr_str).await {
-            eprintln!("Failed to read codex stderr: {}", e);
-            // Continue execution as stderr is not critical
-        }
-    }
+#[derive(Object, serde::Serialize)]
+struct SessionListResponse {
+    /// Running and persisted sessions, newest first, without their events
+    sessions: Vec<SessionView>,
+}
 
-    let status = match process.wait().await {
-        Ok(s) => s,
-        Err(e) => {
-            let err_msg = format!("Failed to wait for codex process: {}", e);
-            eprintln!("{}", err_msg);
-            return Err(err_msg);
-        }
-    };
+#[derive(Object, serde::Deserialize)]
+struct StartSessionRequest {
+    /// What the agent should do
+    prompt: String,
 
-    if !status.success() && !stderr_str.is_empty() {
-        let err_msg = format!("Codex process error: {}", stderr_str);
-        eprintln!("Codex process failed. Stderr: {}", stderr_str);
-        return Err(err_msg);
-    }
+    /// Model to run instead of `model` under `[codex]` in config.toml
+    model: Option<String>,
+}
 
-    if !stderr_str.is_empty() {
-        println!("Codex stderr (non-fatal for task, but logged): {}", stderr_str);
-    }
+#[derive(Object, serde::Deserialize)]
+struct ApprovalRequest {
+    /// `approve`, `approve_for_session` (the same command won't be asked about again in this
+    /// session) or `deny`
+    decision: String,
+}
 
-    // Always return a response, even if stdout is empty
-    // This prevents client errors when polling for status
-    Ok(CodexApiResponse {
-        raw_codex_output: Some(if stdout_str.is_empty() {
-            "Command executed successfully but produced no output.".to_string()
-        } else {
-            stdout_str
-        }),
-        ..Default::default()
-    })
+#[derive(ApiResponse)]
+enum SessionListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SessionListResponse>),
 }
 
-#[handler]
-async fn submit_codex_task_handler(
-    query: Json<CodexQueryRequest>,
-    tasks: Data<&Arc<DashMap<String, CodexTaskStatus>>>
-) -> Result<impl IntoResponse> {
-    let task_id = Uuid::new_v4().to_string();
-    let query_text = query.0.query_text;
-
-    tasks.insert(task_id.clone(), CodexTaskStatus::Pending { query_text: query_text.clone(), last_updated: Instant::now() });
-    println!("Task {} submitted for query: \"{}\"", task_id, query_text);
-
-    let tasks_clone: Arc<DashMap<String, CodexTaskStatus>> = Arc::clone(tasks.0);
-    let task_id_clone = task_id.clone();
-    let query_text_clone_for_task = query_text.clone();
-    
-    tokio::spawn(async move {
-        let task_start_time = Instant::now();
-        println!("Task {} (query: \"{}\") processing started...", task_id_clone, query_text_clone_for_task);
-        
-        // Update task status to Processing
-        tasks_clone.insert(task_id_clone.clone(), CodexTaskStatus::Processing { 
-            query_text: query_text_clone_for_task.clone(), 
-            last_updated: Instant::now() 
-        });
+#[derive(ApiResponse)]
+enum SessionApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SessionView>),
+}
 
-        match run_codex_command_logic(query_text_clone_for_task.clone()).await {
-            Ok(response) => {
-                // Update task status to Completed with the current timestamp
-                tasks_clone.insert(task_id_clone.clone(), CodexTaskStatus::Completed { 
-                    query_text: query_text_clone_for_task.clone(), 
-                    response, 
-                    last_updated: Instant::now() 
-                });
-                
-                let duration_ms = task_start_time.elapsed().as_secs_f64() * 1000.0;
-                println!("Task {} (query: \"{}\") completed successfully in {:.2}ms.", task_id_clone, query_text_clone_for_task, duration_ms);
-            }
-            Err(error_message) => {
-                // Update task status to Failed with the current timestamp
-                tasks_clone.insert(task_id_clone.clone(), CodexTaskStatus::Failed { 
-                    query_text: query_text_clone_for_task.clone(), 
-                    error: error_message.clone(), 
-                    last_updated: Instant::now() 
-                });
-                
-                let duration_ms = task_start_time.elapsed().as_secs_f64() * 1000.0;
-                eprintln!("Task {} (query: \"{}\") failed after {:.2}ms: {}", task_id_clone, query_text_clone_for_task, duration_ms, error_message);
-            }
-        }
-    });
+#[derive(ApiResponse)]
+enum StartSessionApiResponse {
+    /// The agent was started
+    #[oai(status = 202)]
+    Accepted(OpenApiJson<SessionView>),
+}
 
-    Ok((StatusCode::ACCEPTED, Json(CodexSubmitResponse { task_id })))
+#[derive(ApiResponse)]
+enum SessionStreamApiResponse {
+    #[oai(status = 200)]
+    Ok(EventStream<BoxStream<'static, SessionEventView>>),
 }
 
-#[handler]
-async fn get_codex_task_status_handler(
-    task_id_param: Path<String>,
-    tasks: Data<&Arc<DashMap<String, CodexTaskStatus>>>
-) -> Result<impl IntoResponse> {
-    let task_id = task_id_param.0;
-    match tasks.get(&task_id) {
-        Some(task_ref) => {
-            let task_status_cloned = task_ref.value().clone();
-            let response = Json(CodexStatusResponse {
-                task_id: task_id.clone(),
-                task_status: task_status_cloned.clone(),
-            });
-
-            match task_ref.value() {
-                CodexTaskStatus::Completed { .. } | CodexTaskStatus::Failed { .. } => {
-                    println!("Task {} queried with Completed/Failed status, will be removed by cleanup process.", task_id);
-                }
-                _ => {}
-            }
-            Ok(response)
+impl From<CodexError> for GalateaError {
+    fn from(error: CodexError) -> Self {
+        match error {
+            CodexError::NotFound(message) => GalateaError::NotFound(message),
+            CodexError::Conflict(message) => GalateaError::Conflict(message),
+            CodexError::Invalid(message) => GalateaError::BadRequest(message),
+            CodexError::Unavailable(message) => GalateaError::Unavailable(message),
+            CodexError::Failed(e) => e.into(),
         }
-        None => Err(NotFoundError.into()),
     }
 }
 
-pub fn codex_routes() -> Route {
-    Route::new()
-        .at("/submit", post(submit_codex_task_handler))
-        .at("/status/:task_id", get(get_codex_task_status_handler))
+fn session_not_found(id: &str) -> GalateaError {
+    GalateaError::NotFound(format!("No codex session '{}'", id))
 }
 
-// --- Memory Management Utilities ---
-
-const TASK_MAX_LIFETIME_SECONDS: u64 = 3600; // 1 hour for pending/processing tasks
-const COMPLETED_TASK_LIFETIME_SECONDS: u64 = 300; // 5 minutes for completed/failed tasks
+#[OpenApi]
+impl CodexApi {
+    /// Health check endpoint for the Codex API
+    ///
+    /// Returns a simple status message to verify that the Codex API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn codex_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Codex API route is healthy".to_string()))
+    }
 
-// This function can be called by a background task in main.rs
-pub fn cleanup_old_tasks(tasks: &Arc<DashMap<String, CodexTaskStatus>>) {
-    let mut tasks_to_remove = Vec::new();
-    let now = Instant::now();
+    /// List agent sessions
+    ///
+    /// Returns running sessions and every session persisted under
+    /// `galatea_files/codex_sessions`, newest first, without their events.
+    #[oai(path = "/sessions", method = "get")]
+    async fn list_sessions_handler(&self) -> SessionListApiResponse {
+        let sessions = codex_session::list().into_iter().map(SessionView::from).collect();
+        SessionListApiResponse::Ok(OpenApiJson(SessionListResponse { sessions }))
+    }
 
-    // Iterate to find tasks to remove. We collect IDs to avoid modifying the map while iterating.
-    for entry in tasks.iter() {
-        let task_id = entry.key();
-        let status = entry.value();
-        
-        let max_lifetime = match status {
-            CodexTaskStatus::Completed { .. } | CodexTaskStatus::Failed { .. } => COMPLETED_TASK_LIFETIME_SECONDS,
-            _ => TASK_MAX_LIFETIME_SECONDS,
-        };
+    /// Start an agent session
+    ///
+    /// Runs the Codex CLI in the project root on `prompt`. The agent works until it completes
+    /// the task, then exits. With the default `approval_policy = "on-request"` it stops to
+    /// ask before running commands outside its sandbox or applying patches: these show up in
+    /// `approvals` and as `exec_approval_request` and `apply_patch_approval_request` events,
+    /// and are answered through `POST /api/codex/sessions/{id}/approvals/{call_id}`. Follow
+    /// the session through `GET /api/codex/sessions/{id}/stream`.
+    ///
+    /// Answers 503 when `enabled = false` under `[codex]` in config.toml, or when
+    /// `max_sessions` (default 2) are already running.
+    #[oai(path = "/sessions", method = "post")]
+    async fn start_session_handler(&self, body: OpenApiJson<StartSessionRequest>) -> Result<StartSessionApiResponse, GalateaError> {
+        let record = codex_session::start(&body.0.prompt, body.0.model)?;
+        Ok(StartSessionApiResponse::Accepted(OpenApiJson(record.into())))
+    }
 
-        if now.duration_since(*status.last_updated()).as_secs() > max_lifetime {
-            tasks_to_remove.push(task_id.clone());
+    /// Get an agent session
+    ///
+    /// Returns the session's status and approval requests. Pass `events=true` to include its
+    /// recorded events after `events_after` (default 0), at most `events_limit` (default 500,
+    /// at most 5000).
+    #[oai(path = "/sessions/:id", method = "get")]
+    async fn get_session_handler(
+        &self,
+        id: OpenApiPath<String>,
+        events: Query<Option<bool>>,
+        events_after: Query<Option<u64>>,
+        events_limit: Query<Option<usize>>,
+    ) -> Result<SessionApiResponse, GalateaError> {
+        let record = codex_session::get(&id.0).ok_or_else(|| session_not_found(&id.0))?;
+        let mut view = SessionView::from(record);
+        if events.0.unwrap_or(false) {
+            let limit = events_limit.0.unwrap_or(DEFAULT_EVENTS_LIMIT).min(MAX_EVENTS_LIMIT);
+            let recorded = codex_session::events(&id.0, events_after.0.unwrap_or(0), limit)?;
+            view.events = Some(recorded.into_iter().map(SessionEventView::from).collect());
         }
+        Ok(SessionApiResponse::Ok(OpenApiJson(view)))
     }
 
-    // Remove the identified tasks
-    for task_id in tasks_to_remove {
-        if tasks.remove(&task_id).is_some() {
-            println!("Task {} removed by TTL cleanup.", task_id);
-        }
+    /// Stream an agent session's events
+    ///
+    /// Sends the recorded events after `after` (default 0, i.e. all of them), then new ones as
+    /// the agent reports them. The stream ends after the `exit` event; for a finished session
+    /// it ends once the recorded events are sent. Reconnect with the last `seq` seen as
+    /// `after` to resume without repeats.
+    ///
+    /// ## Example stream:
+    /// ```text
+    /// event: exec_approval_request
+    /// data: {"seq":12,"at":1760000000,"kind":"exec_approval_request","data":{"type":"exec_approval_request","call_id":"call_3","command":["npm","install"],"cwd":"/project"}}
+    /// ```
+    #[oai(path = "/sessions/:id/stream", method = "get")]
+    async fn stream_session_handler(&self, id: OpenApiPath<String>, after: Query<Option<u64>>) -> Result<SessionStreamApiResponse, GalateaError> {
+        codex_session::get(&id.0).ok_or_else(|| session_not_found(&id.0))?;
+        // Subscribe before reading the recorded events, so nothing reported in between is missed
+        let live = codex_session::subscribe(&id.0);
+        let recorded = codex_session::events(&id.0, after.0.unwrap_or(0), usize::MAX)?;
+        let replayed = recorded.last().map_or(after.0.unwrap_or(0), |e| e.seq);
+        let live = stream::unfold(live, move |live| async move {
+            let mut live = live?;
+            loop {
+                match live.recv().await {
+                    Ok(event) if event.seq > replayed => return Some((event, Some(live))),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+        let events = stream::iter(recorded).chain(live).map(SessionEventView::from).boxed();
+        Ok(SessionStreamApiResponse::Ok(
+            EventStream::new(events)
+                .keep_alive(std::time::Duration::from_secs(15))
+                .to_event(|event| Event::message(event.to_json_string()).event_type(event.kind.clone())),
+        ))
     }
+
+    /// Answer a command or patch the agent proposed
+    ///
+    /// The agent waits for an answer to each `exec_approval_request` and
+    /// `apply_patch_approval_request`. Answering one that was already answered is a conflict,
+    /// as is answering for a session that is no longer running.
+    #[oai(path = "/sessions/:id/approvals/:call_id", method = "post")]
+    async fn approval_handler(
+        &self,
+        id: OpenApiPath<String>,
+        call_id: OpenApiPath<String>,
+        body: OpenApiJson<ApprovalRequest>,
+    ) -> Result<SessionApiResponse, GalateaError> {
+        let decision = ApprovalDecision::parse(&body.0.decision).ok_or_else(|| {
+            GalateaError::BadRequest(format!("Unknown decision '{}': use approve, approve_for_session or deny", body.0.decision))
+        })?;
+        let record = codex_session::decide(&id.0, &call_id.0, decision)?;
+        Ok(SessionApiResponse::Ok(OpenApiJson(record.into())))
+    }
+
+    /// Terminate an agent session
+    ///
+    /// Stops the agent and every command it started: they get SIGTERM, then SIGKILL if they
+    /// are still running 3 seconds later. The session is marked `terminated` right away and
+    /// keeps its record and events. Terminating a finished session changes nothing and
+    /// returns it as it is.
+    #[oai(path = "/sessions/:id", method = "delete")]
+    async fn terminate_session_handler(&self, id: OpenApiPath<String>) -> Result<SessionApiResponse, GalateaError> {
+        let record = codex_session::terminate(&id.0)?;
+        Ok(SessionApiResponse::Ok(OpenApiJson(record.into())))
+    }
+}
+
+pub fn codex_routes() -> Route {
+    let api_service = OpenApiService::new(CodexApi, "Codex API", "1.0").server("/api/codex");
+    Route::new().nest("/", api_service)
 }
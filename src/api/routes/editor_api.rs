@@ -1,10 +1,34 @@
 use poem::Route;
-use poem_openapi::{payload::{Json as OpenApiJson, PlainText}, OpenApi, Object, ApiResponse, OpenApiService, Enum};
+use futures::stream::{self, BoxStream, StreamExt};
+use poem::web::sse::Event;
+use poem::IntoResponse;
+use poem_openapi::{param::Query, payload::{Attachment, AttachmentType, EventStream, Json as OpenApiJson, PlainText}, types::ToJSON, OpenApi, Object, ApiResponse, OpenApiService, Enum, Multipart};
+use poem_openapi::registry::{MetaResponses, Registry};
+use poem_openapi::types::multipart::Upload;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
-use crate::dev_operation::editor::{self, EditorOperationResult, SHARED_EDITOR};
+use crate::api::error::GalateaError;
+use crate::dev_operation::checkpoints;
+use crate::dev_operation::editor::{self, EditorOperationResult};
+use crate::dev_operation::hooks::HookOutcome;
+use crate::dev_operation::editorconfig;
+use crate::dev_operation::format;
+use crate::dev_operation::lint::{self, EslintResult};
+use crate::dev_operation::lint_policy;
+use crate::dev_operation::patch;
+use crate::dev_operation::text_encoding::TextEncoding;
+use crate::dev_operation::transfers;
+use crate::dev_operation::typecheck;
+use crate::dev_runtime::crash;
+use crate::dev_runtime::db;
+use crate::dev_runtime::jobs;
+use crate::dev_runtime::project_manifest;
+use crate::dev_runtime::quotas::{self, QuotaMetric};
 use crate::file_system; // For resolve_path
 use crate::file_system::paths::{get_project_root, resolve_path};
+use crate::file_system::ranking::{self, RankSignals, RankingWeights};
+use crate::terminal::package_manager::PackageManager;
+use crate::terminal::stream::{self as process_stream, ProcessEvent};
 use tokio::process::Command;
 use std::fs;
 
@@ -24,12 +48,14 @@ enum EditorCommand {
     /// Create a new file - Write content to a new or existing file
     /// 
     /// Creates parent directories if needed. Will overwrite existing files.
+    /// Content is adjusted to the project's `.editorconfig`, if any.
     /// Requires `path` and `file_text` parameters.
     Create,
     
     /// Replace text in file - Find and replace text within a file
     /// 
-    /// Performs case-sensitive text replacement. Replaces ALL occurrences.
+    /// Performs case-sensitive text replacement. Replaces ALL occurrences unless
+    /// `max_replacements` is set; with `use_regex`, `old_str` is a regular expression.
     /// Requires `path` and `old_str`. Optional `new_str` (defaults to empty string for deletion).
     StrReplace,
     
@@ -39,11 +65,45 @@ enum EditorCommand {
     /// Requires `path`, `insert_line`, and `new_str` parameters.
     Insert,
     
-    /// Undo last edit - Reverse the most recent edit operation
+    /// Replace a character range - Replace text between two line/column positions
     /// 
-    /// Can undo create, str_replace, or insert operations. Only one level of undo is supported.
-    /// No additional parameters required.
+    /// Lines are 1-indexed, columns are 0-indexed character offsets and the end is exclusive.
+    /// Requires `path`, `start_line`, `start_column`, `end_line`, `end_column` and `expected_hash`
+    /// (the `content_hash` returned when the file was viewed). Optional `new_str` (defaults to empty string for deletion).
+    ReplaceRange,
+    
+    /// Undo edits - Reverse the most recent edit operations
+    /// 
+    /// Can undo create, str_replace, insert and replace_range operations, newest first.
+    /// With `path`, only edits of that file are undone; otherwise the most recent edits of any file.
+    /// Optional `count` (defaults to 1) undoes several edits at once; a change of several files
+    /// (a move, a patch) counts once per file and, without `path`, can only be undone whole.
     UndoEdit,
+    
+    /// Redo edits - Re-apply edits reverted by `undo_edit`
+    /// 
+    /// Optional `path` and `count` as for `undo_edit`. A new edit of a file discards its edits that
+    /// could be redone.
+    RedoEdit,
+
+    /// Delete a file - Remove a single file from the project
+    /// 
+    /// Can be reverted with `undo_edit`. Directories cannot be deleted.
+    /// Requires `path`.
+    DeleteFile,
+
+    /// Move or rename a file - Move a single file to a new path
+    /// 
+    /// Creates missing parent directories; the new path must not exist. Imports of the file are
+    /// not updated, use `POST /api/refactor/move-file` for that and for directories.
+    /// Requires `path` and `new_path`.
+    Move,
+
+    /// Copy a file - Duplicate a single file at a new path
+    /// 
+    /// Creates missing parent directories; the new path must not exist.
+    /// Requires `path` and `new_path`.
+    Copy,
 }
 
 impl std::fmt::Display for EditorCommand {
@@ -53,7 +113,12 @@ impl std::fmt::Display for EditorCommand {
             EditorCommand::Create => write!(f, "create"),
             EditorCommand::StrReplace => write!(f, "str_replace"),
             EditorCommand::Insert => write!(f, "insert"),
+            EditorCommand::ReplaceRange => write!(f, "replace_range"),
             EditorCommand::UndoEdit => write!(f, "undo_edit"),
+            EditorCommand::RedoEdit => write!(f, "redo_edit"),
+            EditorCommand::DeleteFile => write!(f, "delete_file"),
+            EditorCommand::Move => write!(f, "move"),
+            EditorCommand::Copy => write!(f, "copy"),
         }
     }
 }
@@ -65,7 +130,35 @@ impl From<EditorCommand> for editor::CommandType {
             EditorCommand::Create => editor::CommandType::Create,
             EditorCommand::StrReplace => editor::CommandType::StrReplace,
             EditorCommand::Insert => editor::CommandType::Insert,
+            EditorCommand::ReplaceRange => editor::CommandType::ReplaceRange,
             EditorCommand::UndoEdit => editor::CommandType::UndoEdit,
+            EditorCommand::RedoEdit => editor::CommandType::RedoEdit,
+            EditorCommand::DeleteFile => editor::CommandType::DeleteFile,
+            EditorCommand::Move => editor::CommandType::Move,
+            EditorCommand::Copy => editor::CommandType::Copy,
+        }
+    }
+}
+
+/// How file content is read and written
+#[derive(Enum, serde::Deserialize, PartialEq, Clone, Copy)]
+#[oai(rename_all = "snake_case")]
+enum EditorEncoding {
+    /// UTF-8 text. A BOM and CRLF line endings are kept but not shown
+    #[oai(rename = "utf-8")]
+    Utf8,
+    /// ISO-8859-1 text, for files that aren't valid UTF-8
+    Latin1,
+    /// The whole file as base64, for binary files (`view` and `create` only)
+    Base64,
+}
+
+impl From<EditorEncoding> for TextEncoding {
+    fn from(encoding: EditorEncoding) -> Self {
+        match encoding {
+            EditorEncoding::Utf8 => TextEncoding::Utf8,
+            EditorEncoding::Latin1 => TextEncoding::Latin1,
+            EditorEncoding::Base64 => TextEncoding::Base64,
         }
     }
 }
@@ -80,8 +173,8 @@ struct EditorCommandRequest {
     
     /// File path for single-file operations
     /// 
-    /// **Required for:** create, str_replace, insert
-    /// **Optional for:** view (when using single file), undo_edit
+    /// **Required for:** create, str_replace, insert, replace_range, delete_file, move, copy
+    /// **Optional for:** view (when using single file), undo_edit and redo_edit (limits them to that file)
     /// **Not used for:** view with multiple files (use `paths` instead)
     /// 
     /// Can be absolute or relative to the project root. Examples:
@@ -102,11 +195,22 @@ struct EditorCommandRequest {
     /// 
     /// Example: `["src/main.rs", "src/lib.rs", "README.md"]`
     paths: Option<Vec<String>>,
+
+    /// Destination of a moved or copied file
+    /// 
+    /// **Required for:** move, copy
+    /// **Not used for:** any other commands
+    /// 
+    /// Follows the same rules as `path`. Must not exist yet; missing parent directories are
+    /// created.
+    /// 
+    /// Example: `"src/components/ui/Button.tsx"`
+    new_path: Option<String>,
     
     /// Content for new file creation
     /// 
     /// **Required for:** create command
-    /// **Not used for:** view, str_replace, insert, undo_edit
+    /// **Not used for:** view, str_replace, insert, undo_edit, redo_edit
     /// 
     /// The complete text content to write to the file. Supports any text format
     /// including code, markdown, JSON, etc. Line endings will be normalized.
@@ -117,7 +221,7 @@ struct EditorCommandRequest {
     /// Line number (1-indexed) after which to insert text
     /// 
     /// **Required for:** insert command
-    /// **Not used for:** view, create, str_replace, undo_edit
+    /// **Not used for:** view, create, str_replace, undo_edit, redo_edit
     /// 
     /// Must be a positive integer. The text will be inserted AFTER this line:
     /// - Line 1: Insert after the first line (new text becomes line 2)
@@ -131,8 +235,8 @@ struct EditorCommandRequest {
     /// New text content for insert and replace operations
     /// 
     /// **Required for:** insert command
-    /// **Optional for:** str_replace command (defaults to empty string for deletion)
-    /// **Not used for:** view, create, undo_edit
+    /// **Optional for:** str_replace, replace_range commands (defaults to empty string for deletion)
+    /// **Not used for:** view, create, undo_edit, redo_edit
     /// 
     /// For **insert**: The text to insert at the specified line.
     /// For **str_replace**: The replacement text (empty string deletes the matched text).
@@ -145,22 +249,41 @@ struct EditorCommandRequest {
     /// Text to find and replace
     /// 
     /// **Required for:** str_replace command
-    /// **Not used for:** view, create, insert, undo_edit
+    /// **Not used for:** view, create, insert, undo_edit, redo_edit
     /// 
     /// The exact text to search for in the file. Matching is case-sensitive and literal
-    /// (no regex). ALL occurrences will be replaced. Cannot be empty.
+    /// unless `use_regex` is set. ALL occurrences will be replaced unless `max_replacements`
+    /// is set. Cannot be empty.
     /// 
     /// Examples:
     /// - `"oldFunctionName"`
     /// - `"TODO: implement this"`
     /// - `"const oldValue = 42;"`
+    /// - With `use_regex`: `"from ['\"]\\./utils/(\\w+)['\"]"`
     #[oai(validator(min_length = 1))]
     old_str: Option<String>,
+
+    /// Treat `old_str` as a regular expression
+    /// 
+    /// **Optional for:** str_replace command. Defaults to `false`.
+    /// **Not used for:** any other commands
+    /// 
+    /// Uses Rust `regex` syntax; `(?m)` makes `^`/`$` match at line boundaries and `(?s)`
+    /// lets `.` match newlines. `new_str` can refer to capture groups as `$1` or `${name}`
+    /// (write `$$` for a literal `$`). Patterns that can match the empty string are rejected.
+    use_regex: Option<bool>,
+
+    /// Maximum number of matches to replace, first to last
+    /// 
+    /// **Optional for:** str_replace command. Defaults to replacing every match.
+    /// **Not used for:** any other commands
+    #[oai(validator(minimum(value = "1")))]
+    max_replacements: Option<usize>,
     
     /// Line range for viewing files [start_line, end_line]
     /// 
     /// **Optional for:** view command
-    /// **Not used for:** create, str_replace, insert, undo_edit
+    /// **Not used for:** create, str_replace, insert, undo_edit, redo_edit
     /// 
     /// Specifies which lines to return when viewing files. Both numbers are 1-indexed.
     /// Must be exactly 2 elements: `[start_line, end_line]`
@@ -176,7 +299,140 @@ struct EditorCommandRequest {
     /// - end_line must be ≥ start_line or -1
     /// - start_line cannot exceed file length
     /// - If end_line exceeds file length, it's clamped to file end
+    /// 
+    /// Files above `editor_max_view_bytes` (config.toml, default 5 MiB) can only be viewed in
+    /// parts: their line window is streamed and answered with `chunk`, which carries the total
+    /// line count.
     view_range: Option<Vec<i32>>,
+
+    /// Byte offset of the part of the file to view
+    /// 
+    /// **Optional for:** view command with a single `path`. Defaults to `0` when `view_length` is set.
+    /// **Not used for:** any other commands
+    /// 
+    /// Pages through files of any size without reading them whole. The response's `chunk`
+    /// tells where the returned content is and whether the file continues; request the next
+    /// part at `chunk.next_offset`. Cannot be combined with `view_range`.
+    view_offset: Option<u64>,
+
+    /// Number of bytes to view from `view_offset`
+    /// 
+    /// **Optional for:** view command with a single `path`. Defaults to and is capped at `editor_max_view_bytes`.
+    /// **Not used for:** any other commands
+    /// 
+    /// UTF-8 chunks are moved to character boundaries, so `chunk.length` may differ slightly.
+    #[oai(validator(minimum(value = "1")))]
+    view_length: Option<u64>,
+    
+    /// Line (1-indexed) where the replaced range starts
+    /// 
+    /// **Required for:** replace_range command
+    /// **Not used for:** any other commands
+    #[oai(validator(minimum(value = "1")))]
+    start_line: Option<usize>,
+    
+    /// Column where the replaced range starts
+    /// 
+    /// **Required for:** replace_range command
+    /// **Not used for:** any other commands
+    /// 
+    /// 0-indexed offset in characters (not bytes) from the start of `start_line`.
+    start_column: Option<usize>,
+    
+    /// Line (1-indexed) where the replaced range ends
+    /// 
+    /// **Required for:** replace_range command
+    /// **Not used for:** any other commands
+    /// 
+    /// Must not be before `start_line`.
+    #[oai(validator(minimum(value = "1")))]
+    end_line: Option<usize>,
+    
+    /// Column where the replaced range ends (exclusive)
+    /// 
+    /// **Required for:** replace_range command
+    /// **Not used for:** any other commands
+    /// 
+    /// 0-indexed offset in characters from the start of `end_line`. A column equal to the
+    /// line's length addresses the end of the line; line terminators cannot be addressed.
+    /// To join two lines, replace from the end of one line to column 0 of the next.
+    end_column: Option<usize>,
+    
+    /// Content hash of the file the edit was computed against
+    /// 
+    /// **Required for:** replace_range command
+    /// **Optional for:** create, str_replace, insert, delete_file, move, copy
+    /// **Not used for:** view, undo_edit, redo_edit
+    /// 
+    /// Use the `content_hash` returned by a previous `view` or edit of the file. If the file
+    /// changed since then, because a person or a code generator wrote it too, nothing is
+    /// written and the command answers 409 with code `stale`; the error's `details` carry the
+    /// `current_hash` (`null` when the file no longer exists) and the `diff` the edit would
+    /// make to the current content.
+    expected_hash: Option<String>,
+
+    /// Number of edits to undo or redo
+    /// 
+    /// **Optional for:** undo_edit, redo_edit. Defaults to `1`.
+    /// **Not used for:** any other commands
+    /// 
+    /// Fails without changing anything if fewer edits are in the history, or if, without a
+    /// `path`, it would stop partway through a change of several files (which counts once per
+    /// file). Up to `editor_undo_depth` (config.toml, default 50) edits are remembered per file.
+    #[oai(validator(minimum(value = "1")))]
+    count: Option<usize>,
+
+    /// Preview the change instead of applying it
+    /// 
+    /// **Optional for:** create, str_replace, insert commands. Defaults to `false`.
+    /// **Not used for:** any other commands (setting it is an error)
+    /// 
+    /// Nothing is written: the response carries the unified `diff` from the current file and,
+    /// unless `include_content` is `false`, the `content` the file would have. Editor hooks
+    /// don't run, and the edit is neither added to the undo history nor charged to quotas.
+    dry_run: Option<bool>,
+
+    /// Apply an edit the editor guardrails held back
+    /// 
+    /// **Optional for:** create, str_replace, insert, replace_range, delete_file. Defaults to `false`.
+    /// **Not used for:** any other commands
+    /// 
+    /// With `action = "block"` in `[editor_guardrails]` (the default only logs a warning), edits
+    /// that replace a large share of a file, delete many lines or remove every export of a
+    /// module are answered with 409 and not applied. Review the returned `diff` and retry with
+    /// `true` if the edit is intended.
+    override_guardrails: Option<bool>,
+
+    /// How the file's bytes are read and written
+    /// 
+    /// **Optional for:** view, create, str_replace, insert, replace_range. Defaults to `utf-8`.
+    /// **Not used for:** any other commands
+    /// 
+    /// Content is always exchanged with `\n` line endings and without a BOM: a file that
+    /// starts with a BOM or uses CRLF throughout keeps them when edited, so only the edited
+    /// lines change. `latin1` reads files that aren't valid UTF-8. `base64` views and creates
+    /// binary files as one base64 string; the other edits reject it.
+    encoding: Option<EditorEncoding>,
+
+    /// Whether to return file contents in the response
+    /// 
+    /// **Optional for:** all commands. Defaults to `true`.
+    /// 
+    /// Edit commands re-read the file and echo its full content; set this to `false` when only
+    /// the outcome matters. `line_count` and `content_hash` are still returned, so a following
+    /// `replace_range` can be issued without viewing the file again.
+    include_content: Option<bool>,
+
+    /// Response fields to return
+    /// 
+    /// **Optional for:** all commands. Defaults to all fields.
+    /// 
+    /// Fields not listed are returned as `null`; `success` is always returned. Valid names are
+    /// `message`, `content`, `file_path`, `line_count`, `multi_content`, `operation`,
+    /// `modified_at`, `modified_lines`, `content_hash`, `hooks`, `diff` and `chunk`.
+    /// 
+    /// Example: `["content_hash", "hooks"]`
+    fields: Option<Vec<String>>,
 }
 
 #[derive(Object, serde::Serialize, Clone)]
@@ -243,7 +499,8 @@ struct EditorCommandResponse {
     /// 
     /// **Not populated for:**
     /// - `view` command with multiple files (see `multi_content`)
-    /// - `undo_edit` command
+    /// - `undo_edit` and `redo_edit` commands without a `path`
+    /// - `delete_file` command
     /// - Failed operations
     /// 
     /// Contains the complete file content after the operation.
@@ -254,7 +511,8 @@ struct EditorCommandResponse {
     /// **Populated for:** All single-file operations
     /// **Not populated for:** Multi-file view operations
     /// 
-    /// Shows the resolved absolute path that was actually used for the operation.
+    /// Shows the resolved absolute path that was actually used for the operation; the new path
+    /// for `move` and `copy`.
     file_path: Option<String>,
     
     /// Number of lines in the file for single-file operations
@@ -277,7 +535,8 @@ struct EditorCommandResponse {
     /// The operation that was performed
     /// 
     /// **Always populated.** Contains the string representation of the command:
-    /// - `"view"`, `"create"`, `"str_replace"`, `"insert"`, or `"undo_edit"`
+    /// - `"view"`, `"create"`, `"str_replace"`, `"insert"`, `"replace_range"`, `"undo_edit"`, `"redo_edit"`,
+    ///   `"delete_file"`, `"move"` or `"copy"`
     /// 
     /// Useful for logging and debugging to confirm which operation was executed.
     operation: Option<String>,
@@ -292,16 +551,150 @@ struct EditorCommandResponse {
     
     /// Line numbers that were modified for edit operations
     /// 
-    /// **Populated for:** Some edit operations where line tracking is available
-    /// **Not populated for:** View operations or when line tracking is not feasible
-    /// 
-    /// Contains 1-indexed line numbers that were changed by the operation:
-    /// - `str_replace`: Lines where replacements occurred (estimated)
-    /// - `insert`: The line number where text was inserted
-    /// - `create`: Not typically populated
+    /// **Populated for:** single-file edit operations and dry runs
+    /// **Not populated for:** View operations, `undo_edit`/`redo_edit` without a `path`
     /// 
-    /// This is a best-effort field and may not be available for all operations.
+    /// 1-indexed line numbers in the new content, taken from `diff`: every added or changed
+    /// line, and for deletions the line that now sits where the deleted text was. Empty when
+    /// the file didn't change.
     modified_lines: Option<Vec<usize>>,
+    
+    /// Hash of the full file content after the operation
+    /// 
+    /// **Populated for:** single-file `view` and successful edit operations
+    /// **Not populated for:** Multi-file operations or when the file cannot be read
+    /// 
+    /// Pass this as `expected_hash` to `replace_range`. Always covers the whole file,
+    /// even when `view_range` limited the returned content.
+    content_hash: Option<String>,
+
+    /// Editor hooks that ran before and after the operation
+    ///
+    /// **Populated for:** edit operations on paths matched by `[[editor_hooks]]` in config.toml
+    /// **Not populated for:** `view`, or when no hook matched
+    ///
+    /// Post hooks such as `format` may have changed the file; `content` and `content_hash`
+    /// reflect the file after all hooks ran. When a pre hook blocks the edit, the 400
+    /// `invalid` error carries these results in `details.hooks`.
+    hooks: Option<Vec<EditorHookResult>>,
+
+    /// Unified diff against the file's previous content
+    ///
+    /// **Populated for:** single-file edit operations, and dry runs (the change they would make)
+    /// **Not populated for:** View operations, `undo_edit`/`redo_edit` without a `path`
+    ///
+    /// Paths are project-relative (`a/src/app/page.tsx`, `b/src/app/page.tsx`). Includes changes
+    /// post hooks such as `format` made. Empty when the file didn't change. Select it alone
+    /// with `fields: ["diff"]` to avoid echoing the whole file.
+    diff: Option<String>,
+
+    /// Where the viewed part of a file is
+    ///
+    /// **Populated for:** `view` with `view_offset`/`view_length`, and `view_range` on files
+    /// above `editor_max_view_bytes`
+    /// **Not populated for:** All other operations
+    chunk: Option<EditorChunkInfo>,
+}
+
+#[derive(Object, serde::Serialize, Clone)]
+struct EditorChunkInfo {
+    /// Byte offset of `content` in the file
+    offset: u64,
+    /// Bytes of the file `content` covers
+    length: u64,
+    /// Where the next part starts; request it with `view_offset`
+    next_offset: u64,
+    file_size: u64,
+    /// Whether the file continues after this part
+    has_more: bool,
+    /// Lines of the whole file, for `view_range`
+    total_lines: Option<usize>,
+    /// First and last line shown (1-indexed), for `view_range`
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+}
+
+impl From<&editor::FileChunk> for EditorChunkInfo {
+    fn from(chunk: &editor::FileChunk) -> Self {
+        Self {
+            offset: chunk.offset,
+            length: chunk.length,
+            next_offset: chunk.offset + chunk.length,
+            file_size: chunk.file_size,
+            has_more: chunk.has_more,
+            total_lines: chunk.total_lines,
+            start_line: chunk.lines.map(|(start, _)| start),
+            end_line: chunk.lines.map(|(_, end)| end),
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct GuardrailViolationView {
+    /// `max_changed_percent`, `max_deleted_lines` or `block_export_removal`, the config.toml key of the guardrail
+    rule: String,
+
+    /// What the edit would have done
+    message: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct GuardrailResponse {
+    message: String,
+
+    /// Every guardrail the edit tripped
+    violations: Vec<GuardrailViolationView>,
+
+    /// Unified diff of the edit that was held back
+    diff: String,
+}
+
+impl From<editor::GuardrailBlock> for GuardrailResponse {
+    fn from(block: editor::GuardrailBlock) -> Self {
+        Self {
+            message: "The edit was not applied. Review the diff and retry with `override_guardrails: true` if it is intended.".to_string(),
+            violations: block
+                .violations
+                .into_iter()
+                .map(|v| GuardrailViolationView { rule: v.rule.as_str().to_string(), message: v.message })
+                .collect(),
+            diff: block.preview.diff,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct EditorHookResult {
+    /// Hook name from config, or `stage:action` when unnamed
+    name: String,
+
+    /// `pre` or `post`
+    stage: String,
+
+    /// `format`, `lint`, `block_generated` or `shell`
+    action: String,
+
+    /// `passed`, `failed`, `blocked` or `timed_out`
+    status: String,
+
+    /// Hook output (tail only for long output)
+    output: String,
+
+    /// How long the hook took, in milliseconds
+    duration_ms: u64,
+}
+
+impl From<HookOutcome> for EditorHookResult {
+    fn from(outcome: HookOutcome) -> Self {
+        Self {
+            name: outcome.name,
+            stage: outcome.stage.as_str().to_string(),
+            action: outcome.action.as_str().to_string(),
+            status: outcome.status.as_str().to_string(),
+            output: outcome.output,
+            duration_ms: outcome.duration_ms,
+        }
+    }
 }
 
 #[derive(ApiResponse)]
@@ -313,33 +706,776 @@ enum HealthResponse {
 #[derive(ApiResponse)]
 enum EditorCommandApiResponse {
     #[oai(status = 200)]
-    Ok(OpenApiJson<EditorCommandResponse>),
-    #[oai(status = 400)]
-    BadRequest(PlainText<String>),
-    #[oai(status = 404)]
-    NotFound(PlainText<String>),
-    #[oai(status = 500)]
-    InternalServerError(PlainText<String>),
+    Ok(OpenApiJson<Box<EditorCommandResponse>>),
+    /// The edit trips an editor guardrail and was not applied
+    #[oai(status = 409)]
+    ConfirmationRequired(OpenApiJson<Box<GuardrailResponse>>),
 }
 
 #[derive(ApiResponse)]
 enum FindFilesApiResponse {
     #[oai(status = 200)]
     Ok(OpenApiJson<FindFilesResponse>),
-    #[oai(status = 400)]
-    BadRequest(PlainText<String>),
-    #[oai(status = 500)]
-    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct SearchRequest {
+    /// **Required.** Text to search for; a regular expression with `regex`
+    #[oai(validator(min_length = 1))]
+    query: String,
+
+    /// **Optional.** Treat `query` as a regular expression (Rust `regex` syntax, like
+    /// ripgrep). Defaults to false: the query is matched literally.
+    regex: Option<bool>,
+
+    /// **Optional.** Match regardless of case. Defaults to false.
+    case_insensitive: Option<bool>,
+
+    /// **Optional.** Directory to search, relative to the project root. Defaults to the root.
+    path: Option<String>,
+
+    /// **Optional.** Only search files matching one of these globs, e.g. `["*.ts", "*.tsx"]`
+    /// or `["src/components/**"]`. Defaults to every file.
+    include: Option<Vec<String>>,
+
+    /// **Optional.** Leave out files and directories matching these globs, e.g.
+    /// `["*.test.ts", "generated"]`. Files ignored by `.gitignore` and dependency or build
+    /// directories (`node_modules`, `dist`, ...) are always left out.
+    exclude: Option<Vec<String>>,
+
+    /// **Optional.** Lines of context to return before and after each match. Defaults to 0.
+    #[oai(validator(maximum(value = "10")))]
+    context_lines: Option<usize>,
+
+    /// **Optional.** Most matching lines to return. Defaults to 200.
+    #[oai(validator(minimum(value = "1"), maximum(value = "5000")))]
+    max_matches: Option<usize>,
+
+    /// **Optional.** Search hidden files and directories too. Defaults to false.
+    include_hidden: Option<bool>,
+
+    /// **Optional.** Sort the matches best first: lines defining the matched symbol over
+    /// usages, source files over tests over generated output, recently modified files first.
+    /// Weights are tunable in the `[search_ranking]` section of config.toml. Defaults to false
+    /// (path order). Only the matches within `max_matches` are ranked.
+    rank: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SearchMatchView {
+    /// File path relative to the project root
+    file: String,
+
+    /// Line number (1-indexed)
+    line: usize,
+
+    /// Column (1-indexed, in characters) of the first match on the line
+    column: usize,
+
+    /// The matching line; long lines are cut to a window around the match
+    snippet: String,
+
+    /// The text the query matched
+    matched: String,
+
+    /// Lines before the match, with `context_lines`
+    before: Vec<String>,
+
+    /// Lines after the match, with `context_lines`
+    after: Vec<String>,
+
+    /// Ranking score, only set with `rank`
+    score: Option<f64>,
+
+    /// How the score was computed, one entry per ranking signal, only set with `rank`
+    score_explanation: Option<Vec<String>>,
+}
+
+impl From<file_system::grep::GrepMatch> for SearchMatchView {
+    fn from(m: file_system::grep::GrepMatch) -> Self {
+        Self {
+            file: m.path,
+            line: m.line,
+            column: m.column,
+            snippet: m.snippet,
+            matched: m.matched,
+            before: m.before,
+            after: m.after,
+            score: None,
+            score_explanation: None,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct SearchResponse {
+    /// Matching lines in path order (best first with `rank`), one per line even if it matches
+    /// several times
+    matches: Vec<SearchMatchView>,
+
+    /// Number of text files searched
+    files_searched: usize,
+
+    /// Whether `max_matches` cut the search short
+    truncated: bool,
+}
+
+#[derive(ApiResponse)]
+enum SearchApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SearchResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct DirEntryView {
+    /// Path relative to the project root
+    path: String,
+
+    /// `file` or `dir`
+    kind: String,
+
+    /// Nesting level below the listed directory, starting at 1
+    depth: usize,
+
+    /// File size in bytes; `null` for directories
+    size_bytes: Option<u64>,
+
+    /// Number of entries in a directory (hidden ones only with `include_hidden`); `null` for files
+    child_count: Option<usize>,
+
+    /// Whether the directory has contents that weren't listed, because it is at the depth
+    /// limit or is a dependency or build directory (`node_modules`, `.git`, `dist`, ...). List it
+    /// directly to see them.
+    collapsed: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DirListResponse {
+    /// The listed directory, relative to the project root (`.` for the root)
+    path: String,
+
+    entries: Vec<DirEntryView>,
+
+    /// Whether `max_entries` cut the listing short
+    truncated: bool,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CreateDirRequest {
+    /// **Required.** Directory to create, relative to the project root. Missing parents are created.
+    #[oai(validator(min_length = 1))]
+    path: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CreateDirResponse {
+    /// The directory, relative to the project root
+    path: String,
+
+    /// `false` when the directory already existed
+    created: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct RemoveDirResponse {
+    /// The removed directory, relative to the project root
+    path: String,
+
+    /// Number of files deleted with it; `undo_edit` without a path and this `count` restores them
+    files_removed: usize,
+}
+
+#[derive(Object, serde::Serialize)]
+struct EditHistoryItem {
+    /// Unix seconds
+    timestamp: i64,
+
+    /// Editor command, or the tool that wrote the file (`format`, `eslint_fix`, `move_file`, ...)
+    command: String,
+
+    /// File or directory written, relative to the project root; `null` for `undo_edit` without a path
+    path: Option<String>,
+
+    /// Galatea process that made the edit
+    session: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct EditHistoryResponse {
+    edits: Vec<EditHistoryItem>,
+}
+
+#[derive(ApiResponse)]
+enum EditHistoryApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<EditHistoryResponse>),
+}
+
+#[derive(Multipart)]
+struct UploadRequest {
+    /// A file to save; repeat the field to upload several at once
+    file: Vec<Upload>,
+
+    /// Directory to save the files in, relative to the project root (default `public`).
+    /// Created if missing.
+    dir: Option<String>,
+
+    /// Name to save the file as, instead of the one it was sent with; only for a single file
+    name: Option<String>,
+
+    /// Replace files that already exist (default false)
+    overwrite: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct UploadedFileView {
+    /// Path relative to the project root
+    path: String,
+
+    size_bytes: u64,
+
+    /// Media type the file is downloaded with, from its extension
+    content_type: String,
+
+    /// Whether an existing file was replaced
+    replaced: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct UploadResponse {
+    files: Vec<UploadedFileView>,
+}
+
+#[derive(ApiResponse)]
+enum UploadApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<UploadResponse>),
+}
+
+// A file sent as it is on disk, with the media type its extension implies
+struct FileDownload {
+    content: Vec<u8>,
+    content_type: &'static str,
+    file_name: String,
+    inline: bool,
+}
+
+impl IntoResponse for FileDownload {
+    fn into_response(self) -> poem::Response {
+        // Pages and SVGs shown inline would run their scripts with the API's origin
+        let active = matches!(self.content_type, "text/html" | "image/svg+xml");
+        let kind = if self.inline && !active { AttachmentType::Inline } else { AttachmentType::Attachment };
+        let mut response = Attachment::new(self.content).attachment_type(kind).filename(self.file_name).into_response();
+        response.headers_mut().insert(poem::http::header::CONTENT_TYPE, poem::http::HeaderValue::from_static(self.content_type));
+        response
+    }
+}
+
+// Documented as the binary attachment it is; the actual media type varies by file
+impl ApiResponse for FileDownload {
+    fn meta() -> MetaResponses {
+        Attachment::<Vec<u8>>::meta()
+    }
+
+    fn register(registry: &mut Registry) {
+        Attachment::<Vec<u8>>::register(registry);
+    }
+}
+
+#[derive(ApiResponse)]
+enum DirListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<DirListResponse>),
+}
+
+#[derive(ApiResponse)]
+enum CreateDirApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<CreateDirResponse>),
+}
+
+#[derive(ApiResponse)]
+enum RemoveDirApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<RemoveDirResponse>),
+}
+
+#[derive(ApiResponse)]
+enum EditorConfigApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<EditorConfigResponse>),
+}
+
+/// One event of a streamed script run. The SSE event type is the `event` field.
+#[derive(Object, serde::Serialize)]
+struct ScriptStreamEvent {
+    /// `stdout`, `stderr`, or `exit` for the final event
+    event: String,
+
+    /// Output line without its line terminator; `null` for `exit`
+    line: Option<String>,
+
+    /// Exit code, -1 when the process was killed by a signal; only set for `exit`
+    status: Option<i32>,
+
+    /// Whether the script exited with code 0; only set for `exit`
+    success: Option<bool>,
+
+    /// Run time in milliseconds; only set for `exit`
+    duration_ms: Option<u64>,
+}
+
+impl From<ProcessEvent> for ScriptStreamEvent {
+    fn from(event: ProcessEvent) -> Self {
+        let line = |event: &str, line: String| Self {
+            event: event.to_string(),
+            line: Some(line),
+            status: None,
+            success: None,
+            duration_ms: None,
+        };
+        match event {
+            ProcessEvent::Stdout(l) => line("stdout", l),
+            ProcessEvent::Stderr(l) => line("stderr", l),
+            ProcessEvent::Exit { code, duration_ms } => Self {
+                event: "exit".to_string(),
+                line: None,
+                status: Some(code.unwrap_or(-1)),
+                success: Some(code == Some(0)),
+                duration_ms: Some(duration_ms),
+            },
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum ScriptStreamApiResponse {
+    #[oai(status = 200)]
+    Ok(EventStream<BoxStream<'static, ScriptStreamEvent>>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct ScriptJobResponse {
+    /// Id of the background job, for `GET` and `DELETE /api/jobs/{id}`
+    job_id: String,
+
+    /// The script operation that was started
+    operation: String,
+
+    /// The command line the job runs
+    command: String,
 }
 
 #[derive(ApiResponse)]
 enum ScriptApiResponse {
     #[oai(status = 200)]
     Ok(OpenApiJson<ScriptResponse>),
-    #[oai(status = 400)]
-    BadRequest(PlainText<String>),
-    #[oai(status = 500)]
-    InternalServerError(PlainText<String>),
+    /// The script was started as a background job
+    #[oai(status = 202)]
+    Accepted(OpenApiJson<ScriptJobResponse>),
+}
+
+#[derive(ApiResponse)]
+enum LintPolicyApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<Box<LintPolicyResponse>>),
+    #[oai(status = 422)]
+    InvalidPolicy(OpenApiJson<Box<LintPolicyResponse>>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct LintRequest {
+    /// Files or directories to lint, relative to the project root
+    ///
+    /// **Optional.** Lints the whole project when omitted or empty.
+    files: Option<Vec<String>>,
+
+    /// Apply ESLint's autofixes
+    ///
+    /// **Optional.** Defaults to false. Fixes are written through the editor, so `undo_edit`
+    /// without a path and a `count` of the fixed files reverts all of them, and the returned
+    /// messages are those left after fixing.
+    fix: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LintMessageItem {
+    /// Rule that reported the problem; absent for parse errors
+    rule_id: Option<String>,
+
+    /// `error` or `warning`
+    severity: String,
+
+    message: String,
+
+    /// Start line (1-indexed)
+    line: Option<usize>,
+
+    /// Start column (1-indexed)
+    column: Option<usize>,
+
+    end_line: Option<usize>,
+
+    end_column: Option<usize>,
+
+    /// ESLint couldn't check the file, e.g. because it doesn't parse
+    fatal: bool,
+
+    /// An autofix exists for the problem
+    fixable: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LintFileItem {
+    /// File path relative to the project root
+    path: String,
+
+    error_count: usize,
+
+    warning_count: usize,
+
+    /// Errors and warnings an autofix would resolve
+    fixable_count: usize,
+
+    /// Whether autofixes were written to the file
+    fixed: bool,
+
+    messages: Vec<LintMessageItem>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LintResponse {
+    /// Files with problems or fixes, in ESLint's order; clean files are left out
+    files: Vec<LintFileItem>,
+
+    /// Files ESLint checked
+    files_linted: usize,
+
+    error_count: usize,
+
+    warning_count: usize,
+}
+
+#[derive(ApiResponse)]
+enum LintApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<LintResponse>),
+}
+
+fn lint_file_item(result: EslintResult, root: &std::path::Path, fixed: bool) -> LintFileItem {
+    let path = std::path::Path::new(&result.file_path);
+    LintFileItem {
+        path: path.strip_prefix(root).unwrap_or(path).to_string_lossy().into_owned(),
+        error_count: result.error_count,
+        warning_count: result.warning_count,
+        fixable_count: result.fixable_error_count + result.fixable_warning_count,
+        fixed,
+        messages: result
+            .messages
+            .into_iter()
+            .map(|m| LintMessageItem {
+                rule_id: m.rule_id,
+                severity: if m.severity >= 2 { "error" } else { "warning" }.to_string(),
+                message: m.message,
+                line: m.line,
+                column: m.column,
+                end_line: m.end_line,
+                end_column: m.end_column,
+                fatal: m.fatal,
+                fixable: m.fix.is_some(),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Object, serde::Deserialize)]
+struct FormatRequest {
+    /// Files to format, relative to the project root
+    ///
+    /// **Required.** At most 100; use `/script` with `format` for the whole project.
+    files: Vec<String>,
+
+    /// Only report which files differ from Prettier's formatting
+    ///
+    /// **Optional.** Defaults to false.
+    check: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct FormatResponse {
+    /// Files whose formatting differs (with `check`) or that were reformatted, relative to the
+    /// project root. Files Prettier ignores or has no parser for are never listed.
+    files: Vec<String>,
+
+    /// Whether the files were written
+    written: bool,
+}
+
+#[derive(ApiResponse)]
+enum FormatApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<FormatResponse>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct ApplyPatchRequest {
+    /// Unified diff, as printed by `git diff` or `diff -u`
+    ///
+    /// **Required.** May change, create, delete and rename any number of files. Text around
+    /// the diff, such as Markdown fences, is ignored, and hunk line counts needn't be exact.
+    patch: String,
+
+    /// How many context lines a hunk may drop at each end when they don't match
+    ///
+    /// **Optional.** Defaults to 2; 0 requires all context to match.
+    fuzz: Option<usize>,
+
+    /// Check the patch and return the diffs without writing anything
+    ///
+    /// **Optional.** Defaults to false.
+    dry_run: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct PatchHunkView {
+    /// Line (1-indexed) where the hunk matched
+    line: usize,
+
+    /// How many lines away from its header's line number the hunk matched
+    offset: i64,
+
+    /// Context lines ignored to make it match
+    fuzz: usize,
+
+    /// Whether it only matched with whitespace differences ignored
+    whitespace: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct PatchedFileView {
+    /// File path relative to the project root
+    path: String,
+
+    /// Previous path of a renamed file
+    old_path: Option<String>,
+
+    /// `modify`, `create`, `delete` or `rename`
+    kind: String,
+
+    hunks: Vec<PatchHunkView>,
+
+    /// Unified diff of what the patch does to the file, against its current content
+    diff: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ApplyPatchResponse {
+    files: Vec<PatchedFileView>,
+
+    /// Whether the files were written
+    applied: bool,
+}
+
+#[derive(ApiResponse)]
+enum ApplyPatchApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ApplyPatchResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct TypeErrorItem {
+    /// File path relative to the project root
+    path: String,
+
+    /// Line (1-indexed)
+    line: usize,
+
+    /// Column (1-indexed, as tsc prints it)
+    column: usize,
+
+    /// TypeScript diagnostic code, e.g. `TS2322`
+    code: String,
+
+    /// `error`, `warning` or `message`
+    category: String,
+
+    /// The message, with tsc's indented elaboration lines joined by newlines
+    message: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct TypecheckResponse {
+    /// tsc exited cleanly and reported no errors
+    passed: bool,
+
+    /// Errors in the whole project, before `path` filtering
+    error_count: usize,
+
+    /// Diagnostics, filtered by `path`, in tsc's order
+    errors: Vec<TypeErrorItem>,
+
+    /// Whether the result is from an earlier run; the cache is dropped when a TypeScript,
+    /// JavaScript or JSON file changes
+    cached: bool,
+
+    exit_code: Option<i32>,
+
+    /// How long tsc ran
+    duration_ms: u64,
+
+    /// When tsc finished (Unix seconds)
+    finished_at: u64,
+
+    /// tsc output that isn't a file diagnostic, e.g. tsconfig errors
+    output: String,
+}
+
+#[derive(ApiResponse)]
+enum TypecheckApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<TypecheckResponse>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct LintRuleChange {
+    /// ESLint rule name, e.g. `no-console` or `@typescript-eslint/no-unused-vars`
+    #[oai(validator(min_length = 1))]
+    rule: String,
+
+    /// `off`, `warn` or `error` (or `0`, `1`, `2`)
+    severity: String,
+
+    /// **Optional.** Rule options following the severity, e.g. `["always"]`
+    options: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Object, serde::Deserialize, Default)]
+struct LintPolicyRequest {
+    /// **Optional.** Rules to enable, disable or change the severity of
+    #[oai(default)]
+    rules: Vec<LintRuleChange>,
+
+    /// **Optional.** Rules to drop from the editable config, falling back to what it extends
+    #[oai(default)]
+    remove_rules: Vec<String>,
+
+    /// **Optional.** ESLint ignore patterns to add
+    #[oai(default)]
+    add_ignore_patterns: Vec<String>,
+
+    /// **Optional.** ESLint ignore patterns to remove
+    #[oai(default)]
+    remove_ignore_patterns: Vec<String>,
+
+    /// **Optional.** Prettier options to set, e.g. `{"semi": false, "printWidth": 100}`
+    prettier_options: Option<std::collections::BTreeMap<String, serde_json::Value>>,
+
+    /// **Optional.** Prettier options to remove
+    #[oai(default)]
+    remove_prettier_options: Vec<String>,
+
+    /// **Optional.** Patterns to add to `.prettierignore`
+    #[oai(default)]
+    add_prettier_ignore_patterns: Vec<String>,
+
+    /// **Optional.** Patterns to remove from `.prettierignore`
+    #[oai(default)]
+    remove_prettier_ignore_patterns: Vec<String>,
+
+    /// **Optional.** File to validate the change against; defaults to the first source file
+    /// in the project
+    validate_path: Option<String>,
+
+    /// **Optional.** Validate the change, then roll it back. Defaults to `false`.
+    dry_run: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LintValidationResult {
+    /// Whether ESLint (and Prettier, when its policy changed) could load the configuration
+    ok: bool,
+
+    /// File the dry-run linted
+    sample_file: Option<String>,
+
+    errors: usize,
+    warnings: usize,
+
+    /// Tool diagnostics when validation failed
+    output: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LintPolicyResponse {
+    /// `flat`, `legacy_json`, `package_json`, `unsupported` (read-only) or `missing`
+    eslint_config_kind: String,
+    eslint_config_path: Option<String>,
+
+    /// Rules set in the editable config. For flat configs, only those in Galatea's managed block.
+    eslint_rules: serde_json::Value,
+    eslint_ignore_patterns: Vec<String>,
+
+    /// Rules ESLint applies to the sample file after merging every layer, when requested
+    effective_rules: Option<serde_json::Value>,
+
+    prettier_config_path: Option<String>,
+    /// Whether Prettier options can be edited through this API
+    prettier_editable: bool,
+    prettier_options: serde_json::Value,
+    prettier_ignore_patterns: Vec<String>,
+
+    /// Files the change wrote (or would write, for a dry run)
+    changed_files: Vec<String>,
+
+    /// Whether the change was kept; false for dry runs and changes that failed validation
+    applied: bool,
+
+    validation: Option<LintValidationResult>,
+}
+
+fn lint_policy_response(root: &std::path::Path) -> anyhow::Result<LintPolicyResponse> {
+    let config = lint_policy::find_eslint_config(root);
+    let eslint = lint_policy::read_eslint_policy(&config)?;
+    let prettier = lint_policy::read_prettier_policy(root)?;
+    Ok(LintPolicyResponse {
+        eslint_config_kind: config.kind().to_string(),
+        eslint_config_path: config.path().map(|p| p.display().to_string()),
+        eslint_rules: serde_json::Value::Object(eslint.rules),
+        eslint_ignore_patterns: eslint.ignore_patterns,
+        effective_rules: None,
+        prettier_config_path: prettier.config_path.map(|p| p.display().to_string()),
+        prettier_editable: prettier.editable,
+        prettier_options: serde_json::Value::Object(prettier.options),
+        prettier_ignore_patterns: prettier.ignore_patterns,
+        changed_files: Vec::new(),
+        applied: false,
+        validation: None,
+    })
+}
+
+/// Order of `find-files` results
+#[derive(Enum, serde::Deserialize, PartialEq, Clone, Copy, Default)]
+#[oai(rename_all = "snake_case")]
+enum FindFilesSort {
+    /// By path relative to the searched directory
+    #[default]
+    Path,
+
+    /// By file size
+    Size,
+
+    /// By last modification time
+    Modified,
+}
+
+impl From<FindFilesSort> for file_system::search::FileSort {
+    fn from(sort: FindFilesSort) -> Self {
+        match sort {
+            FindFilesSort::Path => Self::Path,
+            FindFilesSort::Size => Self::Size,
+            FindFilesSort::Modified => Self::Modified,
+        }
+    }
 }
 
 /// The type of script operation to execute
@@ -348,25 +1484,25 @@ enum ScriptApiResponse {
 enum ScriptOperation {
     /// Run linting checks on the project
     /// 
-    /// Executes `pnpm run lint` to check code quality and style issues.
+    /// Executes the project's lint command (`pnpm run lint` by default) to check code quality and style issues.
     /// Returns detailed output including any linting errors or warnings.
     Lint,
     
     /// Format code in the project
     /// 
-    /// Executes `pnpm run format` to automatically format code according to
+    /// Executes the project's format command (`pnpm run format` by default) to automatically format code according to
     /// project style guidelines. May modify files in place.
     Format,
     
     /// Build the project
     /// 
-    /// Executes `pnpm run build` to compile and build the project.
+    /// Executes the project's build command (`pnpm run build` by default) to compile and build the project.
     /// Returns build output and any compilation errors.
     Build,
     
     /// Run tests
     /// 
-    /// Executes `pnpm run test` to run the project's test suite.
+    /// Executes the project's test command (`pnpm run test` by default) to run its test suite.
     /// Returns test results and coverage information if available.
     Test,
     
@@ -457,8 +1593,8 @@ struct FindFilesRequest {
     
     /// File extensions to search for
     /// 
-    /// **Required.** List of file extensions to include in the search.
-    /// Extensions should be provided without the leading dot.
+    /// **Optional.** List of file extensions to include in the search.
+    /// Extensions should be provided without the leading dot; `*` matches any file.
     /// 
     /// Examples:
     /// - `["ts", "tsx"]` - TypeScript files
@@ -466,9 +1602,27 @@ struct FindFilesRequest {
     /// - `["md"]` - Markdown files
     /// - `["json", "yaml", "yml"]` - Configuration files
     /// 
-    /// **Note:** Empty list will return no results.
-    #[oai(validator(min_items = 1))]
-    suffixes: Vec<String>,
+    /// **Note:** At least one of `suffixes`, `globs` and `name_contains` is required.
+    suffixes: Option<Vec<String>>,
+
+    /// Glob patterns files must match
+    /// 
+    /// **Optional.** Matched against the path relative to `dir`. `*` and `?` stay within one
+    /// directory, `**` spans any number of them and `{a,b}` matches either. Globs without a
+    /// `/` match the file name at any depth.
+    /// 
+    /// Examples:
+    /// - `["src/**/*.test.ts"]` - Test files under src
+    /// - `["*.{test,spec}.{ts,tsx}"]` - Test files anywhere
+    /// - `["app/**/page.tsx"]` - Next.js pages
+    globs: Option<Vec<String>>,
+
+    /// Substrings of the file name
+    /// 
+    /// **Optional.** Only return files whose name contains one of these, ignoring case.
+    /// 
+    /// Example: `["button"]` finds `Button.tsx` and `icon-button.css`
+    name_contains: Option<Vec<String>>,
     
     /// Directories to exclude from search
     /// 
@@ -485,17 +1639,48 @@ struct FindFilesRequest {
     /// Maximum number of files to return
     /// 
     /// **Optional.** Limits the number of files returned to prevent overwhelming
-    /// responses for large projects. Defaults to 1000 if not specified.
+    /// responses for large projects. Defaults to 1000 if not specified. This is the
+    /// page size when paging through results with `cursor` or `offset`.
     /// 
     /// **Range:** 1 to 10000
     #[oai(validator(minimum(value = "1"), maximum(value = "10000")))]
     max_results: Option<usize>,
+
+    /// Number of files to skip
+    /// 
+    /// **Optional.** Skips this many files of the sorted results. Defaults to 0.
+    /// Ignored when `cursor` is given.
+    offset: Option<usize>,
+
+    /// Where to continue from
+    /// 
+    /// **Optional.** The `next_cursor` of the previous page. Send the same filters and sort
+    /// with it; results are sorted the same way on every call, so pages neither overlap nor
+    /// skip files unless files are added or removed in between.
+    cursor: Option<String>,
+
+    /// Order of the results
+    /// 
+    /// **Optional.** `path` (default), `size` or `modified`. Files that tie are ordered by path.
+    sort_by: Option<FindFilesSort>,
+
+    /// Sort in descending order
+    /// 
+    /// **Optional.** Defaults to `false`. With `sort_by: "modified"` this lists the most
+    /// recently changed files first.
+    descending: Option<bool>,
     
     /// Whether to include file size information
     /// 
     /// **Optional.** If `true`, the response will include file size information
     /// for each found file. Defaults to `false` for faster responses.
     include_file_info: Option<bool>,
+
+    /// Whether to echo the search parameters
+    /// 
+    /// **Optional.** Defaults to `true`. Set to `false` to leave out `search_params`,
+    /// which repeats the request and the full exclusion list on every call.
+    include_search_params: Option<bool>,
 }
 
 #[derive(Object, serde::Serialize)]
@@ -536,30 +1721,90 @@ struct FindFilesResponse {
     
     /// Whether results were truncated
     /// 
-    /// `true` if the search found more files than `max_results` allowed.
-    /// When `true`, consider refining the search criteria or increasing `max_results`.
+    /// `true` if more files follow this page. When `true`, fetch the next page with
+    /// `next_cursor`, or refine the search criteria.
     truncated: bool,
+
+    /// Number of files skipped before this page
+    offset: usize,
+
+    /// Cursor for the next page
+    /// 
+    /// Pass it as `cursor` with the same request to get the files after this page.
+    /// `null` on the last page.
+    next_cursor: Option<String>,
     
     /// Search parameters that were used
     /// 
     /// Echo of the search parameters for reference, useful for debugging
-    /// or confirming what was actually searched.
-    search_params: SearchParams,
+    /// or confirming what was actually searched. `null` when `include_search_params`
+    /// was `false` in the request.
+    search_params: Option<SearchParams>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SearchParams {
+    /// Directory that was searched
+    directory: String,
+    
+    /// File extensions that were searched for
+    extensions: Vec<String>,
+
+    /// Glob patterns that were applied
+    globs: Vec<String>,
+
+    /// File name substrings that were applied
+    name_contains: Vec<String>,
+    
+    /// Directories that were excluded
+    excluded_directories: Vec<String>,
+    
+    /// Maximum results limit that was applied
+    max_results: usize,
 }
 
-#[derive(Object, serde::Serialize)]
-struct SearchParams {
-    /// Directory that was searched
-    directory: String,
-    
-    /// File extensions that were searched for
-    extensions: Vec<String>,
-    
-    /// Directories that were excluded
-    excluded_directories: Vec<String>,
-    
-    /// Maximum results limit that was applied
-    max_results: usize,
+#[derive(Object, serde::Deserialize)]
+struct EditorConfigRequest {
+    /// Path to look up settings for
+    /// 
+    /// **Required.** File path relative to the project root (or absolute within it).
+    /// The file does not need to exist yet, so settings can be checked before a `create`.
+    path: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct EditorConfigResponse {
+    /// Resolved path the settings apply to
+    path: String,
+
+    /// Indentation style: `space` or `tab`
+    /// 
+    /// `null` when no `.editorconfig` section sets it.
+    indent_style: Option<String>,
+
+    /// Number of columns per indentation level
+    indent_size: Option<usize>,
+
+    /// Width of a tab character in columns
+    tab_width: Option<usize>,
+
+    /// Line ending: `lf`, `crlf` or `cr`
+    end_of_line: Option<String>,
+
+    /// Character set, e.g. `utf-8` or `utf-8-bom`
+    charset: Option<String>,
+
+    /// Whether trailing whitespace is trimmed from written lines
+    trim_trailing_whitespace: Option<bool>,
+
+    /// Whether files end with a newline
+    insert_final_newline: Option<bool>,
+
+    /// `.editorconfig` files that contributed to these settings
+    /// 
+    /// Ordered from the outermost to the closest file. Empty when no `.editorconfig` applies,
+    /// in which case the editor writes content unchanged.
+    sources: Vec<String>,
 }
 
 #[derive(Object, serde::Deserialize)]
@@ -567,7 +1812,7 @@ struct ScriptExecutionRequest {
     /// The script operation to execute
     /// 
     /// **Required.** Specifies which script operation to run. Each operation
-    /// corresponds to a specific npm/pnpm script in the project.
+    /// corresponds to a specific package.json script in the project.
     operation: ScriptOperation,
     
     /// Additional arguments to pass to the script
@@ -594,6 +1839,166 @@ struct ScriptExecutionRequest {
     /// 
     /// Example: `{"NODE_ENV": "development", "DEBUG": "true"}`
     env_vars: Option<std::collections::HashMap<String, String>>,
+
+    /// Run the script as a background job
+    /// 
+    /// **Optional.** Answer 202 with a `job_id` as soon as the script has started instead of
+    /// waiting for it to exit. Poll `GET /api/jobs/{id}` for its status and output and cancel
+    /// it with `DELETE /api/jobs/{id}`. Defaults to false. Not used by `/script/stream`.
+    background: Option<bool>,
+}
+
+// Response fields that can be selected with `fields`; `success` is always returned
+const EDITOR_RESPONSE_FIELDS: &[&str] = &[
+    "message",
+    "content",
+    "file_path",
+    "line_count",
+    "multi_content",
+    "operation",
+    "modified_at",
+    "modified_lines",
+    "content_hash",
+    "hooks",
+    "diff",
+    "chunk",
+];
+
+impl EditorCommandResponse {
+    // Clears every field not named in `fields`
+    fn retain_fields(&mut self, fields: &[String]) {
+        let keep = |name: &str| fields.iter().any(|f| f == name);
+        self.message = self.message.take().filter(|_| keep("message"));
+        self.content = self.content.take().filter(|_| keep("content"));
+        self.file_path = self.file_path.take().filter(|_| keep("file_path"));
+        self.line_count = self.line_count.take().filter(|_| keep("line_count"));
+        self.multi_content = self.multi_content.take().filter(|_| keep("multi_content"));
+        self.operation = self.operation.take().filter(|_| keep("operation"));
+        self.modified_at = self.modified_at.take().filter(|_| keep("modified_at"));
+        self.modified_lines = self.modified_lines.take().filter(|_| keep("modified_lines"));
+        self.content_hash = self.content_hash.take().filter(|_| keep("content_hash"));
+        self.hooks = self.hooks.take().filter(|_| keep("hooks"));
+        self.diff = self.diff.take().filter(|_| keep("diff"));
+        self.chunk = self.chunk.take().filter(|_| keep("chunk"));
+    }
+}
+
+// Resolves a path that may not exist yet (a create target, a move destination or a new
+// directory) against the project root, rejecting paths whose parent directory lies outside it
+fn resolve_new_path(p_str: &str) -> Result<PathBuf, GalateaError> {
+    let proj_root = get_project_root()?;
+    let requested_path = std::path::Path::new(p_str);
+    let candidate = if requested_path.is_absolute() {
+        if requested_path.starts_with(&proj_root) {
+            requested_path.to_path_buf()
+        } else {
+            proj_root.join(requested_path.file_name().unwrap_or_default())
+        }
+    } else {
+        let stripped = requested_path.strip_prefix(proj_root.file_name().unwrap_or_default()).unwrap_or(requested_path);
+        proj_root.join(stripped)
+    };
+    // Canonicalize the nearest existing ancestor to check containment; missing parents are created later
+    let parent = candidate.parent().ok_or_else(|| {
+        GalateaError::BadRequest("Invalid path: no parent directory".to_string())
+    })?;
+    let existing_parent = parent.ancestors().find(|p| p.exists()).unwrap_or(parent);
+    let canonical_parent = dunce::canonicalize(existing_parent).map_err(|e| {
+        GalateaError::BadRequest(format!("Failed to canonicalize parent directory: {}", e))
+    })?;
+    if !canonical_parent.starts_with(&proj_root) || candidate.components().any(|c| c == std::path::Component::ParentDir) {
+        return Err(GalateaError::BadRequest("Target path is outside the project root".to_string()));
+    }
+    Ok(candidate)
+}
+
+// The package manager command for a script request, in its working directory
+fn script_command(req: &ScriptExecutionRequest) -> Result<Command, GalateaError> {
+    // Determine working directory
+    let working_dir = if let Some(ref wd) = req.working_dir {
+        match resolve_path(wd) {
+            Ok(path) => {
+                if !path.exists() || !path.is_dir() {
+                    return Err(GalateaError::BadRequest(format!(
+                        "Working directory does not exist or is not a directory: {}",
+                        wd
+                    )));
+                }
+                path
+            }
+            Err(e) => {
+                return Err(GalateaError::BadRequest(format!(
+                    "Failed to resolve working directory '{}': {}",
+                    wd, e
+                )));
+            }
+        }
+    } else {
+        match get_project_root() {
+            Ok(pr) => pr,
+            Err(e) => return Err(GalateaError::Internal(format!("Failed to get project root: {}", e))),
+        }
+    };
+
+    // Build command based on operation, as the project's manifest declares it
+    let manifest = project_manifest::load(&working_dir);
+    let declared = match req.operation {
+        ScriptOperation::Lint => manifest.lint,
+        ScriptOperation::Format => manifest.format,
+        ScriptOperation::Build => manifest.build,
+        ScriptOperation::Test => manifest.test,
+        ScriptOperation::Install => Some(
+            std::iter::once(PackageManager::detect(&working_dir).name())
+                .chain(PackageManager::detect(&working_dir).install_args())
+                .map(str::to_string)
+                .collect(),
+        ),
+    };
+    let Some((program, base_args)) = declared.as_deref().and_then(|command| command.split_first()) else {
+        return Err(GalateaError::BadRequest(format!(
+            "The project has no {} command: add a `{}` script to package.json, or set `{}` under [project] in config.toml",
+            req.operation, req.operation, req.operation
+        )));
+    };
+
+    let mut cmd = Command::new(program);
+    cmd.current_dir(&working_dir);
+    
+    // Add base arguments
+    for arg in base_args {
+        cmd.arg(arg);
+    }
+    
+    // Add custom arguments if provided
+    if let Some(ref args) = req.args {
+        for arg in args {
+            cmd.arg(arg);
+        }
+    }
+    
+    // Set environment variables if provided
+    if let Some(ref env_vars) = req.env_vars {
+        for (key, value) in env_vars {
+            cmd.env(key, value);
+        }
+    }
+
+    Ok(cmd)
+}
+
+// Path relative to the project root for responses, `.` for the root itself
+fn project_relative(root: &std::path::Path, path: &std::path::Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+        Ok(rel) => rel.to_string_lossy().replace('\\', "/"),
+        Err(_) => path.to_string_lossy().into_owned(),
+    }
+}
+
+// Hashes the whole file as the editor decodes it, independent of any view_range applied to
+// the response content
+fn file_content_hash(path: &str, encoding: TextEncoding) -> Option<String> {
+    editor::read_text(std::path::Path::new(path), encoding).ok().map(|content| editor::content_hash(&content))
 }
 
 #[OpenApi]
@@ -615,13 +2020,21 @@ impl EditorApi {
     /// - **create**: Create a new file with specified content
     /// - **str_replace**: Find and replace text within a file
     /// - **insert**: Insert text at a specific line number
-    /// - **undo_edit**: Undo the last edit operation
+    /// - **replace_range**: Replace text between two line/column positions
+    /// - **undo_edit**: Undo the last edit operations
+    /// - **redo_edit**: Re-apply undone edit operations
+    /// - **delete_file**: Delete a file
+    /// - **move**: Move or rename a file
+    /// - **copy**: Copy a file
     /// 
     /// ## Command-specific requirements:
     /// 
     /// ### view
     /// - Requires either `path` (single file) OR `paths` (multiple files), but not both
     /// - Optional `view_range` to specify line range [start, end] (1-indexed, use -1 for end of file)
+    /// - Optional `view_offset`/`view_length` to page through a single file by bytes
+    /// - Files above `editor_max_view_bytes` (config.toml, default 5 MiB) must be viewed with
+    ///   one of those; their responses carry `chunk` instead of a `content_hash`
     /// 
     /// ### create
     /// - Requires `path` (target file path) and `file_text` (content to write)
@@ -630,7 +2043,8 @@ impl EditorApi {
     /// 
     /// ### str_replace
     /// - Requires `path`, `old_str` (text to find), and optionally `new_str` (replacement text, defaults to empty)
-    /// - Replaces ALL occurrences of `old_str` with `new_str`
+    /// - Replaces ALL occurrences of `old_str` with `new_str`, or the first `max_replacements`
+    /// - Set `use_regex: true` to match `old_str` as a regex; `new_str` may use `$1`/`${name}` captures
     /// - Case-sensitive matching
     /// 
     /// ### insert
@@ -638,70 +2052,126 @@ impl EditorApi {
     /// - Inserts text AFTER the specified line number
     /// - Line 1 means insert after the first line (becomes line 2)
     /// 
+    /// ### replace_range
+    /// - Requires `path`, `start_line`, `start_column`, `end_line`, `end_column` and `expected_hash`
+    /// - Lines are 1-indexed, columns are 0-indexed character offsets, the end position is exclusive
+    /// - `expected_hash` must match the file's current `content_hash`, otherwise nothing is written
+    ///   and the command answers 409 (see below)
+    /// - Optional `new_str` (replacement text, defaults to empty)
+    /// 
     /// ### undo_edit
-    /// - No additional parameters required
-    /// - Undoes the last create, str_replace, or insert operation
-    /// - Can only undo one level (no multiple undo history)
+    /// - Optional `path`: undo edits of that file only. Without it, the most recent edits of any file are undone
+    /// - Optional `count` (defaults to 1) of edits to undo, newest first
+    /// - Undoes create, str_replace, insert, replace_range, delete_file, move and copy operations
+    /// - A change of several files counts once per file: a move is two edits, and `count: 2`
+    ///   without a path restores the file at its old path and removes the new one. Without a
+    ///   path, a `count` that would undo only part of such a change is refused
+    /// - History is kept per file and bounded by `editor_undo_depth` in config.toml (default 50 per file)
+    /// 
+    /// ### redo_edit
+    /// - Optional `path` and `count`, as for `undo_edit`
+    /// - A new edit of a file discards its edits that could be redone
+    /// 
+    /// ### delete_file
+    /// - Requires `path` of an existing file; directories are rejected
+    /// 
+    /// ### move / copy
+    /// - Require `path` of an existing file and `new_path`, which must not exist; both must be inside the project
+    /// - Create missing parent directories of `new_path`
+    /// - `move` doesn't update imports of the file; use `POST /api/refactor/move-file` for that and for directories
+    /// - The response reports the file at `new_path`, with its content
+    /// - Pre hooks run for the source file; post hooks don't run
     /// 
     /// ## Response format:
     /// - Single-file operations return content in the `content` field
     /// - Multi-file view operations return an array in the `multi_content` field
-    /// - Edit operations (create, str_replace, insert) will also return the updated file content
+    /// - Edit operations (create, str_replace, insert, replace_range) will also return the updated file content
+    /// - Edit operations return the unified `diff` against the previous content and the `modified_lines` it touched
+    /// - Single-file responses include a `content_hash` to use with `replace_range`
+    /// - Set `include_content: false` or list `fields` to keep responses small in tight edit loops
+    /// - Set `dry_run: true` on create, str_replace or insert to get the `diff` without writing
+    /// - Files keep their BOM and CRLF line endings when edited; content is exchanged with `\n`.
+    ///   Set `encoding` to `latin1` for files that aren't UTF-8, or `base64` to view and create binary files
+    /// - Edits that replace a large share of a file, delete many lines or remove every export of a
+    ///   module are logged; with `action = "block"` in `[editor_guardrails]` they answer 409 with
+    ///   the tripped guardrails and the `diff`, without writing anything, until retried with
+    ///   `override_guardrails: true`
+    /// - Modifying commands given an `expected_hash` that no longer matches the file answer 409
+    ///   with code `stale` and the `current_hash`, without writing anything
+    /// - Modifying commands answer 429 once the caller's `edits_per_hour` or `bytes_written`
+    ///   quota is used up (see `GET /api/usage`)
+    /// - The first change of an edit session is preceded by a checkpoint of the project, which
+    ///   `POST /api/git/checkpoints/restore` brings back (see `[checkpoints]` in config.toml)
     #[oai(path = "/command", method = "post")]
     async fn editor_command_handler(
         &self,
         req: OpenApiJson<EditorCommandRequest>,
-    ) -> EditorCommandApiResponse {
+    ) -> Result<EditorCommandApiResponse, GalateaError> {
         let command_type = match req.0.command {
             EditorCommand::View => editor::CommandType::View,
             EditorCommand::Create => editor::CommandType::Create,
             EditorCommand::StrReplace => editor::CommandType::StrReplace,
             EditorCommand::Insert => editor::CommandType::Insert,
+            EditorCommand::ReplaceRange => editor::CommandType::ReplaceRange,
             EditorCommand::UndoEdit => editor::CommandType::UndoEdit,
+            EditorCommand::RedoEdit => editor::CommandType::RedoEdit,
+            EditorCommand::DeleteFile => editor::CommandType::DeleteFile,
+            EditorCommand::Move => editor::CommandType::Move,
+            EditorCommand::Copy => editor::CommandType::Copy,
         };
+        let moves_or_copies = matches!(command_type, editor::CommandType::Move | editor::CommandType::Copy);
 
         // Path validation for non-view commands
-        if command_type != editor::CommandType::View && req.0.path.is_none() {
-            return EditorCommandApiResponse::BadRequest(
-                PlainText(format!("'path' is required for command type '{}'", req.0.command)),
-            );
+        let is_history_command = matches!(command_type, editor::CommandType::UndoEdit | editor::CommandType::RedoEdit);
+        if command_type != editor::CommandType::View && !is_history_command && req.0.path.is_none() {
+            return Err(GalateaError::BadRequest(format!("'path' is required for command type '{}'", req.0.command)));
         }
         
         // Path validation for view command
         if command_type == editor::CommandType::View && req.0.path.is_none() && req.0.paths.is_none() {
-            return EditorCommandApiResponse::BadRequest(
-                PlainText("For 'view' command, either 'path' or 'paths' must be provided.".to_string()),
-            );
+            return Err(GalateaError::BadRequest("For 'view' command, either 'path' or 'paths' must be provided.".to_string()));
         }
         if command_type == editor::CommandType::View && req.0.path.is_some() && req.0.paths.is_some() {
-            return EditorCommandApiResponse::BadRequest(
-                PlainText("For 'view' command, provide either 'path' or 'paths', not both.".to_string()),
-            );
+            return Err(GalateaError::BadRequest("For 'view' command, provide either 'path' or 'paths', not both.".to_string()));
         }
         if command_type == editor::CommandType::View && req.0.paths.as_ref().map_or(false, |p| p.is_empty()) {
-            return EditorCommandApiResponse::BadRequest(
-                PlainText("For 'view' command with 'paths', the list cannot be empty.".to_string()),
-            );
+            return Err(GalateaError::BadRequest("For 'view' command with 'paths', the list cannot be empty.".to_string()));
+        }
+        if let Some(unknown) = req.0.fields.iter().flatten().find(|f| !EDITOR_RESPONSE_FIELDS.contains(&f.as_str())) {
+            return Err(GalateaError::BadRequest(format!(
+                "Unknown response field '{}'. Valid fields: {}",
+                unknown,
+                EDITOR_RESPONSE_FIELDS.join(", ")
+            )));
         }
+        let include_content = req.0.include_content.unwrap_or(true);
+
+        // Destination of move and copy, which doesn't exist yet
+        let resolved_new_path = match (&req.0.new_path, moves_or_copies) {
+            (Some(p_str), true) => Some(resolve_new_path(p_str)?),
+            (None, true) => {
+                return Err(GalateaError::BadRequest(format!(
+                    "'new_path' is required for command type '{}'",
+                    req.0.command
+                )))
+            }
+            _ => None,
+        };
 
         // Resolve path(s) and check existence for non-create/undo commands
         let mut resolved_single_path: Option<PathBuf> = None;
         let mut resolved_multiple_paths: Option<Vec<PathBuf>> = None;
 
-        if command_type != editor::CommandType::Create && command_type != editor::CommandType::UndoEdit {
+        if command_type != editor::CommandType::Create && !is_history_command {
             if let Some(p_str) = &req.0.path {
                 let resolved_p = match file_system::resolve_path(p_str) {
                     Ok(path) => path,
                     Err(e) => {
-                        return EditorCommandApiResponse::BadRequest(
-                            PlainText(e.to_string()),
-                        );
+                        return Err(GalateaError::BadRequest(e.to_string()));
                     }
                 };
                 if !resolved_p.exists() {
-                    return EditorCommandApiResponse::NotFound(
-                        PlainText(format!("File not found at resolved path: {}", resolved_p.display())),
-                    );
+                    return Err(GalateaError::NotFound(format!("File not found at resolved path: {}", resolved_p.display())));
                 }
                 resolved_single_path = Some(resolved_p);
             } else if let Some(p_strs) = &req.0.paths {
@@ -710,15 +2180,11 @@ impl EditorApi {
                     let resolved_p = match file_system::resolve_path(p_str) {
                         Ok(path) => path,
                         Err(e) => {
-                            return EditorCommandApiResponse::BadRequest(
-                                PlainText(e.to_string()),
-                            );
+                            return Err(GalateaError::BadRequest(e.to_string()));
                         }
                     };
                     if !resolved_p.exists() {
-                        return EditorCommandApiResponse::NotFound(
-                            PlainText(format!("File not found at resolved path: {}", resolved_p.display())),
-                        );
+                        return Err(GalateaError::NotFound(format!("File not found at resolved path: {}", resolved_p.display())));
                     }
                     temp_resolved_paths.push(resolved_p);
                 }
@@ -727,58 +2193,29 @@ impl EditorApi {
         } else if command_type == editor::CommandType::Create {
             // For create, path is needed but doesn't need to exist yet.
             if let Some(p_str) = &req.0.path {
-                // Custom logic for new file creation: join to project root, canonicalize parent, check containment
-                let proj_root = match get_project_root() {
-                    Ok(root) => root,
-                    Err(e) => {
-                        return EditorCommandApiResponse::InternalServerError(
-                            PlainText(e.to_string()),
-                        );
-                    }
-                };
-                let requested_path = std::path::Path::new(p_str);
-                let candidate = if requested_path.is_absolute() {
-                    if requested_path.starts_with(&proj_root) {
-                        requested_path.to_path_buf()
-                    } else {
-                        proj_root.join(requested_path.file_name().unwrap_or_default())
-                    }
-                } else {
-                    let stripped = requested_path.strip_prefix(proj_root.file_name().unwrap_or_default()).unwrap_or(requested_path);
-                    proj_root.join(stripped)
-                };
-                // Canonicalize parent to check containment
-                let parent = match candidate.parent() {
-                    Some(p) => p,
-                    None => {
-                        return EditorCommandApiResponse::BadRequest(
-                            PlainText("Invalid path: no parent directory".to_string()),
-                        );
-                    }
-                };
-                let canonical_parent = match dunce::canonicalize(parent) {
-                    Ok(cp) => cp,
-                    Err(e) => {
-                        return EditorCommandApiResponse::BadRequest(
-                            PlainText(format!("Failed to canonicalize parent directory: {}", e)),
-                        );
-                    }
-                };
-                if !canonical_parent.starts_with(&proj_root) {
-                    return EditorCommandApiResponse::BadRequest(
-                        PlainText("Target path is outside the project root".to_string()),
-                    );
-                }
-                resolved_single_path = Some(candidate);
+                resolved_single_path = Some(resolve_new_path(p_str)?);
             } else {
-                return EditorCommandApiResponse::BadRequest(
-                    PlainText("'path' is required for create.".to_string()),
-                );
+                return Err(GalateaError::BadRequest("'path' is required for create.".to_string()));
             }
-        } else if command_type == editor::CommandType::UndoEdit {
-            // Undo might operate on a path stored in the editor, but API may still provide it for consistency or future use.
+        } else if is_history_command {
+            // With a path, only that file's history is used; without one, the most recent edits of any file
             if let Some(p_str) = &req.0.path {
-                resolved_single_path = file_system::resolve_path(p_str).ok(); // Optional resolution for undo
+                let resolved_p = match file_system::resolve_path(p_str) {
+                    Ok(path) => path,
+                    // A file whose creation was undone no longer exists but still has history
+                    Err(_) => match get_project_root() {
+                        Ok(root) => {
+                            let requested = std::path::Path::new(p_str);
+                            let relative = requested
+                                .strip_prefix(&root)
+                                .or_else(|_| requested.strip_prefix(root.file_name().unwrap_or_default()))
+                                .unwrap_or(requested);
+                            root.join(relative)
+                        }
+                        Err(e) => return Err(GalateaError::Internal(e.to_string())),
+                    },
+                };
+                resolved_single_path = Some(resolved_p);
             }
         }
 
@@ -794,6 +2231,7 @@ impl EditorApi {
         // Convert view_range from i32 to isize
         let view_range_isize = req.0.view_range.as_ref().map(|vr| vr.iter().map(|&x| x as isize).collect());
 
+        let encoding = req.0.encoding.map_or(TextEncoding::Utf8, TextEncoding::from);
         let editor_args = editor::EditorArgs {
             command: command_type.clone(),
             path: editor_args_path.clone(),
@@ -803,118 +2241,253 @@ impl EditorApi {
             new_str: req.0.new_str.clone(),
             old_str: req.0.old_str.clone(),
             view_range: view_range_isize,
+            range: match (req.0.start_line, req.0.start_column, req.0.end_line, req.0.end_column) {
+                (Some(start_line), Some(start_column), Some(end_line), Some(end_column)) => Some(editor::TextRange {
+                    start_line,
+                    start_column,
+                    end_line,
+                    end_column,
+                }),
+                _ => None,
+            },
+            expected_hash: req.0.expected_hash.clone(),
+            count: req.0.count,
+            use_regex: req.0.use_regex.unwrap_or(false),
+            max_replacements: req.0.max_replacements,
+            dry_run: req.0.dry_run.unwrap_or(false),
+            new_path: resolved_new_path.as_ref().map(|p| p.to_string_lossy().into_owned()),
+            override_guardrails: req.0.override_guardrails.unwrap_or(false),
+            encoding,
+            view_offset: req.0.view_offset,
+            view_length: req.0.view_length,
         };
 
-        // Use the shared editor state
-        let mut editor_guard = match SHARED_EDITOR.lock() {
-            Ok(guard) => guard,
-            Err(e) => {
-                return EditorCommandApiResponse::InternalServerError(
-                    PlainText(format!("Failed to acquire editor lock: {}", e)),
-                );
+        if command_type != editor::CommandType::View && !editor_args.dry_run {
+            if let Err(exceeded) = quotas::check(&[QuotaMetric::EditsPerHour, QuotaMetric::BytesWritten]) {
+                return Err(GalateaError::TooManyRequests(exceeded.to_string()));
             }
-        };
-        
-        match editor::handle_command(&mut *editor_guard, editor_args) {
-            Ok(editor_result) => {
-                match editor_result {
-                    EditorOperationResult::Single(Some(content)) => {
-                        EditorCommandApiResponse::Ok(OpenApiJson(EditorCommandResponse {
-                            success: true,
-                            message: Some(format!("Command '{}' executed successfully.", req.0.command)),
-                            content: Some(content.clone()),
-                            file_path: editor_args_path,
-                            operation: Some(req.0.command.to_string()),
-                            line_count: Some(content.lines().count()),
-                            modified_at: Some(timestamp),
-                            multi_content: None,
-                            modified_lines: None,
-                        }))
-                    }
-                    EditorOperationResult::Single(None) => {
-                        let mut response = EditorCommandResponse {
-                            success: true,
-                            message: Some(format!("Command '{}' executed successfully.", req.0.command)),
-                            content: None,
-                            file_path: editor_args_path.clone(),
-                            operation: Some(req.0.command.to_string()),
-                            modified_at: Some(timestamp),
-                            line_count: None,
-                            multi_content: None,
-                            modified_lines: None,
-                        };
-                        
-                        // If it was a mutating command, try to view the file to get its new content and line count
-                        if req.0.command == EditorCommand::Create || req.0.command == EditorCommand::StrReplace || req.0.command == EditorCommand::Insert || req.0.command == EditorCommand::UndoEdit {
-                            if let Some(ref p) = editor_args_path {
-                                let view_args = editor::EditorArgs {
-                                    command: editor::CommandType::View,
-                                    path: Some(p.clone()),
-                                    paths: None,
-                                    file_text: None,
-                                    insert_line: None,
-                                    new_str: None,
-                                    old_str: None,
-                                    view_range: None,
-                                };
-                                if let Ok(EditorOperationResult::Single(Some(updated_content))) = editor::handle_command(&mut *editor_guard, view_args) {
-                                    response.content = Some(updated_content.clone());
-                                    response.line_count = Some(updated_content.lines().count());
-                                    if req.0.command == EditorCommand::StrReplace && req.0.old_str.is_some() {
-                                        if let Some(old_str_val) = &req.0.old_str {
-                                            let line_c = old_str_val.lines().count();
-                                            if line_c > 0 && line_c < 100 {
-                                                response.modified_lines = Some((1..=line_c).collect());
-                                            }
-                                        }
-                                    }
-                                    if req.0.command == EditorCommand::Insert && req.0.insert_line.is_some() {
-                                        response.modified_lines = Some(vec![req.0.insert_line.unwrap()]);
-                                    }
-                                }
+            let label = format!("Before {} {}", req.0.command, req.0.path.as_deref().unwrap_or(""));
+            checkpoints::before_edits(label.trim_end(), 1).await;
+        }
+
+        let _operation = crash::track_operation(format!(
+            "editor {} {}",
+            req.0.command,
+            editor_args_path.as_deref().unwrap_or("")
+        ));
+
+        // Runs under the locks of the files involved, so the content read before and after the
+        // change is this command's own
+        let paths = editor::command_paths(&editor_args);
+        editor::with_files(paths, move |editor| {
+            // Content before the edit, to diff the response against. File operations don't edit text.
+            let previous_content = match &editor_args_path {
+                Some(p) if command_type != editor::CommandType::View && !command_type.is_file_operation() && !editor_args.dry_run => {
+                    Some(editor::read_text(std::path::Path::new(p), encoding).unwrap_or_default())
+                }
+                _ => None,
+            };
+            let (command_result, hook_outcomes) = editor::handle_command_with_hooks(editor, editor_args);
+            let hook_results = (!hook_outcomes.is_empty())
+                .then(|| hook_outcomes.into_iter().map(EditorHookResult::from).collect::<Vec<_>>());
+            let editor_result = match command_result {
+                Ok(result) => result,
+                // The hooks say why a pre hook blocked the edit
+                Err(e) => {
+                    return Err(match hook_results {
+                        Some(hooks) => GalateaError::Invalid { message: e, details: serde_json::json!({ "hooks": hooks }) },
+                        None => GalateaError::BadRequest(e),
+                    })
+                }
+            };
+            let mut response = match editor_result {
+                EditorOperationResult::Single(Some(content)) => EditorCommandResponse {
+                    success: true,
+                    message: Some(format!("Command '{}' executed successfully.", req.0.command)),
+                    line_count: Some(content.lines().count()),
+                    content: include_content.then_some(content),
+                    file_path: editor_args_path.clone(),
+                    operation: Some(req.0.command.to_string()),
+                    modified_at: Some(timestamp),
+                    multi_content: None,
+                    modified_lines: None,
+                    content_hash: editor_args_path.as_deref().and_then(|p| file_content_hash(p, encoding)),
+                    hooks: hook_results,
+                    diff: None,
+                    chunk: None,
+                },
+                EditorOperationResult::Single(None) => {
+                    // A moved or copied file is reported at its new path
+                    let result_path = match &resolved_new_path {
+                        Some(new_path) => Some(new_path.to_string_lossy().into_owned()),
+                        None => editor_args_path.clone(),
+                    };
+                    let mut response = EditorCommandResponse {
+                        success: true,
+                        message: Some(format!("Command '{}' executed successfully.", req.0.command)),
+                        content: None,
+                        file_path: result_path.clone(),
+                        operation: Some(req.0.command.to_string()),
+                        modified_at: Some(timestamp),
+                        line_count: None,
+                        multi_content: None,
+                        modified_lines: None,
+                        content_hash: None,
+                        hooks: hook_results,
+                        diff: None,
+                        chunk: None,
+                    };
+                
+                    // If it was a mutating command, try to view the file to get its new content and line count
+                    if req.0.command != EditorCommand::View && req.0.command != EditorCommand::DeleteFile {
+                        if let Some(ref p) = result_path {
+                            let view_args = editor::EditorArgs {
+                                command: editor::CommandType::View,
+                                path: Some(p.clone()),
+                                paths: None,
+                                file_text: None,
+                                insert_line: None,
+                                new_str: None,
+                                old_str: None,
+                                view_range: None,
+                                range: None,
+                                expected_hash: None,
+                                count: None,
+                                use_regex: false,
+                                max_replacements: None,
+                                dry_run: false,
+                                new_path: None,
+                                override_guardrails: false,
+                                encoding,
+                                view_offset: None,
+                                view_length: None,
+                            };
+                            // Diffed against the file after post hooks, so formatting they applied shows up
+                            if let Some(before) = &previous_content {
+                                let after = editor::read_text(std::path::Path::new(p), encoding).unwrap_or_default();
+                                let diff = editor::unified_diff(std::path::Path::new(p), before, &after);
+                                response.modified_lines = Some(editor::changed_lines(&diff));
+                                response.diff = Some(diff);
+                            }
+                            if let Ok(EditorOperationResult::Single(Some(updated_content))) = editor::handle_command(editor, view_args) {
+                                response.line_count = Some(updated_content.lines().count());
+                                response.content = include_content.then_some(updated_content);
+                                response.content_hash = file_content_hash(p, encoding);
                             }
                         }
-                        EditorCommandApiResponse::Ok(OpenApiJson(response))
                     }
-                    EditorOperationResult::Multi(multi_file_outputs) => {
-                        let api_multi_content: Vec<EditorFileViewResponse> = multi_file_outputs
-                            .into_iter()
-                            .map(|output| EditorFileViewResponse {
-                                path: output.path,
-                                content: output.content,
-                                error: output.error,
-                                line_count: output.line_count,
-                            })
-                            .collect();
-                        EditorCommandApiResponse::Ok(OpenApiJson(EditorCommandResponse {
-                            success: true,
-                            message: Some(format!("Command '{}' (multi-file) executed successfully.", req.0.command)),
-                            multi_content: Some(api_multi_content),
-                            operation: Some(req.0.command.to_string()),
-                            modified_at: Some(timestamp),
-                            content: None,
-                            file_path: None,
-                            line_count: None,
-                            modified_lines: None,
-                        }))
+                    response
+                }
+                EditorOperationResult::Multi(multi_file_outputs) => {
+                    let api_multi_content: Vec<EditorFileViewResponse> = multi_file_outputs
+                        .into_iter()
+                        .map(|output| EditorFileViewResponse {
+                            path: output.path,
+                            content: output.content.filter(|_| include_content),
+                            error: output.error,
+                            line_count: output.line_count,
+                        })
+                        .collect();
+                    EditorCommandResponse {
+                        success: true,
+                        message: Some(format!("Command '{}' (multi-file) executed successfully.", req.0.command)),
+                        multi_content: Some(api_multi_content),
+                        operation: Some(req.0.command.to_string()),
+                        modified_at: Some(timestamp),
+                        content: None,
+                        file_path: None,
+                        line_count: None,
+                        modified_lines: None,
+                        content_hash: None,
+                        hooks: hook_results,
+                        diff: None,
+                        chunk: None,
                     }
                 }
-            },
-            Err(e) => EditorCommandApiResponse::BadRequest(PlainText(e.to_string())),
-        }
+                EditorOperationResult::Preview(preview) => EditorCommandResponse {
+                    success: true,
+                    message: Some(if preview.diff.is_empty() {
+                        format!("Dry run: '{}' would not change the file.", req.0.command)
+                    } else if preview.creates_file {
+                        format!("Dry run: '{}' would create the file. Nothing was written.", req.0.command)
+                    } else {
+                        format!("Dry run: '{}' would modify the file. Nothing was written.", req.0.command)
+                    }),
+                    line_count: Some(preview.content.lines().count()),
+                    content: include_content.then_some(preview.content),
+                    file_path: editor_args_path.clone(),
+                    operation: Some(req.0.command.to_string()),
+                    modified_at: Some(timestamp),
+                    multi_content: None,
+                    content_hash: None,
+                    hooks: hook_results,
+                    modified_lines: Some(editor::changed_lines(&preview.diff)),
+                    diff: Some(preview.diff),
+                    chunk: None,
+                },
+                EditorOperationResult::Chunk(chunk) => EditorCommandResponse {
+                    success: true,
+                    message: Some(if chunk.has_more {
+                        format!("Viewed {} of {} bytes; the file continues at offset {}.", chunk.length, chunk.file_size, chunk.offset + chunk.length)
+                    } else {
+                        format!("Viewed {} of {} bytes, up to the end of the file.", chunk.length, chunk.file_size)
+                    }),
+                    line_count: Some(chunk.content.lines().count()),
+                    chunk: Some(EditorChunkInfo::from(&chunk)),
+                    content: include_content.then_some(chunk.content),
+                    file_path: editor_args_path.clone(),
+                    operation: Some(req.0.command.to_string()),
+                    modified_at: Some(timestamp),
+                    multi_content: None,
+                    // Hashing covers the whole file, which a chunked view avoids reading
+                    content_hash: None,
+                    hooks: hook_results,
+                    modified_lines: None,
+                    diff: None,
+                },
+                EditorOperationResult::Conflict(conflict) => {
+                    return Err(GalateaError::Stale {
+                        message: format!(
+                            "'{}' changed since it was read (expected hash {}). Nothing was written; view it again and retry with the current hash.",
+                            req.0.path.as_deref().unwrap_or_default(),
+                            conflict.expected_hash
+                        ),
+                        details: serde_json::json!({
+                            "expected_hash": conflict.expected_hash,
+                            "current_hash": conflict.current_hash,
+                            "diff": conflict.diff,
+                        }),
+                    });
+                }
+                EditorOperationResult::ConfirmationRequired(block) => {
+                    return Ok(EditorCommandApiResponse::ConfirmationRequired(OpenApiJson(Box::new(block.into()))));
+                }
+            };
+            if let Some(fields) = &req.0.fields {
+                response.retain_fields(fields);
+            }
+            Ok(EditorCommandApiResponse::Ok(OpenApiJson(Box::new(response))))
+        })
+        .await
+        .map_err(GalateaError::Internal)?
     }
 
-    /// Find files in the project by extension
+    /// Find files in the project by extension, glob or name
     /// 
-    /// Searches for files within a specified directory that match given file extensions.
-    /// This is useful for discovering source files, configuration files, or any other
-    /// files of specific types within the project structure.
+    /// Searches for files within a specified directory that match given file extensions,
+    /// glob patterns and file name substrings. This is useful for discovering source files,
+    /// configuration files, or any other files of specific types within the project structure.
     /// 
     /// ## Features:
     /// - **Recursive search**: Searches through all subdirectories
     /// - **Extension filtering**: Only returns files with specified extensions
+    /// - **Glob patterns**: `src/**/*.test.ts` style patterns over the relative path
+    /// - **Name filtering**: Case-insensitive file name substrings
+    /// - **Combined filters**: Files must match every kind of filter given (any entry of each)
     /// - **Directory exclusion**: Skips common build/cache directories by default
     /// - **Result limiting**: Prevents overwhelming responses for large projects
+    /// - **Pagination and sorting**: Page through large results with `next_cursor`, sorted by
+    ///   path, size or modification time
     /// - **File metadata**: Optionally includes file size and modification time
     /// - **Security**: All paths are validated to ensure they're within project boundaries
     /// 
@@ -925,43 +2498,39 @@ impl EditorApi {
     /// - Find all TypeScript files: `{"dir": "src", "suffixes": ["ts", "tsx"]}`
     /// - Find configuration files: `{"dir": ".", "suffixes": ["json", "yaml", "toml"]}`
     /// - Search everything: `{"dir": ".", "suffixes": ["*"], "exclude_dirs": []}`
+    /// - Test files under src: `{"dir": ".", "globs": ["src/**/*.test.ts"]}`
+    /// - Components named like a feature: `{"dir": "src", "suffixes": ["tsx"], "name_contains": ["login"]}`
     #[oai(path = "/find-files", method = "post")]
     async fn find_files_handler(
         &self,
         req: OpenApiJson<FindFilesRequest>,
-    ) -> FindFilesApiResponse {
+    ) -> Result<FindFilesApiResponse, GalateaError> {
         // Validate and resolve directory path
         let dir = match resolve_path(&req.0.dir) {
             Ok(path) => path,
             Err(e) => {
-                return FindFilesApiResponse::BadRequest(
-                    PlainText(format!("Failed to resolve directory '{}': {}", req.0.dir, e)),
-                );
+                return Err(GalateaError::BadRequest(format!("Failed to resolve directory '{}': {}", req.0.dir, e)));
             }
         };
 
         // Validate directory exists
         if !dir.exists() {
-            return FindFilesApiResponse::BadRequest(
-                PlainText(format!("Directory does not exist: {}", dir.display())),
-            );
+            return Err(GalateaError::BadRequest(format!("Directory does not exist: {}", dir.display())));
         }
 
         if !dir.is_dir() {
-            return FindFilesApiResponse::BadRequest(
-                PlainText(format!("Path is not a directory: {}", dir.display())),
-            );
-        }
-
-        // Validate suffixes
-        if req.0.suffixes.is_empty() {
-            return FindFilesApiResponse::BadRequest(
-                PlainText("At least one file extension must be specified".to_string()),
-            );
+            return Err(GalateaError::BadRequest(format!("Path is not a directory: {}", dir.display())));
         }
 
         // Set up search parameters
-        let suffixes_ref: Vec<&str> = req.0.suffixes.iter().map(|s| s.as_str()).collect();
+        let filter = file_system::search::FileFilter {
+            extensions: req.0.suffixes.clone().unwrap_or_default(),
+            globs: req.0.globs.clone().unwrap_or_default(),
+            name_contains: req.0.name_contains.clone().unwrap_or_default(),
+        };
+        if let Err(e) = filter.validate() {
+            return Err(GalateaError::BadRequest(format!("{:#}", e)));
+        }
         let exclude_dirs = req.0.exclude_dirs.clone().unwrap_or_else(|| {
             vec![
                 "node_modules".to_string(),
@@ -979,17 +2548,28 @@ impl EditorApi {
         let exclude_dirs_ref: Vec<&str> = exclude_dirs.iter().map(|s| s.as_str()).collect();
         let max_results = req.0.max_results.unwrap_or(1000);
         let include_file_info = req.0.include_file_info.unwrap_or(false);
+        let include_search_params = req.0.include_search_params.unwrap_or(true);
+        // Cursors are the offset of the next page; kept opaque in the API so that can change
+        let offset = match req.0.cursor.as_deref() {
+            Some(cursor) => match cursor.parse::<usize>() {
+                Ok(offset) => offset,
+                Err(_) => return Err(GalateaError::BadRequest(format!("Invalid cursor: {}", cursor))),
+            },
+            None => req.0.offset.unwrap_or(0),
+        };
 
         // Perform the search
-        match file_system::search::find_files_by_extensions(&dir, &suffixes_ref, &exclude_dirs_ref) {
-            Ok(found_files) => {
+        match file_system::search::find_files(&dir, &filter, &exclude_dirs_ref) {
+            Ok(mut found_files) => {
                 let total_found = found_files.len();
-                let truncated = total_found > max_results;
-                let files_to_process = if truncated {
-                    &found_files[..max_results]
-                } else {
-                    &found_files[..]
-                };
+                file_system::search::sort_files(
+                    &mut found_files,
+                    req.0.sort_by.unwrap_or_default().into(),
+                    req.0.descending.unwrap_or(false),
+                );
+                let page_end = offset.saturating_add(max_results).min(total_found);
+                let truncated = page_end < total_found;
+                let files_to_process = &found_files[offset.min(total_found)..page_end];
 
                 let mut file_infos = Vec::new();
                 for file_path in files_to_process {
@@ -1021,19 +2601,393 @@ impl EditorApi {
                     files: file_infos,
                     total_found,
                     truncated,
-                    search_params: SearchParams {
+                    offset,
+                    next_cursor: truncated.then(|| page_end.to_string()),
+                    search_params: include_search_params.then(|| SearchParams {
                         directory: req.0.dir.clone(),
-                        extensions: req.0.suffixes.clone(),
+                        extensions: filter.extensions,
+                        globs: filter.globs,
+                        name_contains: filter.name_contains,
                         excluded_directories: exclude_dirs,
                         max_results,
-                    },
+                    }),
                 };
 
-                FindFilesApiResponse::Ok(OpenApiJson(response))
+                Ok(FindFilesApiResponse::Ok(OpenApiJson(response)))
+            }
+            Err(e) => Err(GalateaError::Internal(format!("Error searching directory '{}': {}", req.0.dir, e))),
+        }
+    }
+
+    /// Search file contents across the project
+    /// 
+    /// A ripgrep-style search: returns every line matching `query` with its file, line,
+    /// column and a snippet, optionally with surrounding lines. `.gitignore`d files, hidden
+    /// files, dependency and build directories, binary files and files over 2 MB are skipped.
+    /// Narrow the search with `path`, `include` and `exclude` globs. With `rank`, matches come
+    /// best first, each with a `score` and its `score_explanation`; a line that declares the
+    /// matched name (`function useSession`, `struct Config`) ranks above its usages.
+    /// 
+    /// ## Examples:
+    /// - Find usages: `{"query": "useSession("}`
+    /// - Regex in TypeScript files: `{"query": "export (async )?function \\w+", "regex": true, "include": ["*.ts", "*.tsx"]}`
+    /// - With context: `{"query": "TODO", "case_insensitive": true, "context_lines": 2, "path": "src"}`
+    #[oai(path = "/search", method = "post")]
+    async fn search_handler(&self, req: OpenApiJson<SearchRequest>) -> Result<SearchApiResponse, GalateaError> {
+        let req = req.0;
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let dir = match req.path.as_deref().map(str::trim).filter(|p| !p.is_empty() && *p != ".") {
+            Some(p) => match resolve_path(p) {
+                Ok(dir) if dir.is_dir() => dir,
+                Ok(dir) => return Err(GalateaError::BadRequest(format!("Path is not a directory: {}", dir.display()))),
+                Err(e) => return Err(GalateaError::BadRequest(e.to_string())),
+            },
+            None => root.clone(),
+        };
+        let options = file_system::grep::GrepOptions {
+            pattern: req.query,
+            regex: req.regex.unwrap_or(false),
+            case_insensitive: req.case_insensitive.unwrap_or(false),
+            include: req.include.unwrap_or_default(),
+            exclude: req.exclude.unwrap_or_default(),
+            context_lines: req.context_lines.unwrap_or(0).min(10),
+            max_matches: req.max_matches.unwrap_or(200).clamp(1, 5000),
+            include_hidden: req.include_hidden.unwrap_or(false),
+        };
+        let rank = req.rank.unwrap_or(false);
+        let project_root = root.clone();
+        let result = match tokio::task::spawn_blocking(move || file_system::grep::search(&root, &dir, &options)).await {
+            Ok(Ok(result)) => result,
+            // Invalid patterns and globs are the caller's; walking errors are skipped per file
+            Ok(Err(e)) => return Err(GalateaError::BadRequest(format!("{:#}", e))),
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let matches = if rank {
+            let weights = RankingWeights::load();
+            ranking::rank(result.matches, &weights, |m| RankSignals {
+                path: &m.path,
+                modified: ranking::modified_time(&project_root, &m.path),
+                is_definition: ranking::is_definition_line(&m.snippet, m.matched.trim_matches(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))),
+                relevance: 1.0,
+            })
+            .into_iter()
+            .map(|ranked| SearchMatchView {
+                score: Some(ranked.score),
+                score_explanation: Some(ranking::explain(&ranked.explanation)),
+                ..ranked.item.into()
+            })
+            .collect()
+        } else {
+            result.matches.into_iter().map(SearchMatchView::from).collect()
+        };
+        Ok(SearchApiResponse::Ok(OpenApiJson(SearchResponse {
+            matches,
+            files_searched: result.files_searched,
+            truncated: result.truncated,
+        })))
+    }
+
+    /// List a directory
+    /// 
+    /// Returns the entries of `path` (the project root when omitted) depth first, directories
+    /// before files, down to `depth` levels (default 1, at most 10). Hidden entries are left
+    /// out unless `include_hidden` is set. Dependency and build directories such as
+    /// `node_modules` are listed but not descended into. At most `max_entries` (default 500,
+    /// at most 5000) entries are returned.
+    #[oai(path = "/dir", method = "get")]
+    async fn list_dir_handler(
+        &self,
+        path: Query<Option<String>>,
+        depth: Query<Option<usize>>,
+        include_hidden: Query<Option<bool>>,
+        max_entries: Query<Option<usize>>,
+    ) -> Result<DirListApiResponse, GalateaError> {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let dir = match path.0.as_deref().map(str::trim).filter(|p| !p.is_empty() && *p != ".") {
+            Some(p) => match resolve_path(p) {
+                Ok(dir) => dir,
+                Err(e) => return Err(GalateaError::NotFound(e.to_string())),
+            },
+            None => root.clone(),
+        };
+        if !dir.is_dir() {
+            return Err(GalateaError::BadRequest(format!("Path is not a directory: {}", dir.display())));
+        }
+        let depth = depth.0.unwrap_or(1).clamp(1, 10);
+        let max_entries = max_entries.0.unwrap_or(500).clamp(1, 5000);
+        match file_system::dirs::list_dir(&root, &dir, depth, include_hidden.0.unwrap_or(false), max_entries) {
+            Ok(listing) => Ok(DirListApiResponse::Ok(OpenApiJson(DirListResponse {
+                path: project_relative(&root, &dir),
+                entries: listing
+                    .entries
+                    .into_iter()
+                    .map(|e| DirEntryView {
+                        path: e.path,
+                        kind: if e.is_dir { "dir" } else { "file" }.to_string(),
+                        depth: e.depth,
+                        size_bytes: e.size_bytes,
+                        child_count: e.child_count,
+                        collapsed: e.collapsed,
+                    })
+                    .collect(),
+                truncated: listing.truncated,
+            }))),
+            Err(e) => Err(GalateaError::Internal(format!("{:#}", e))),
+        }
+    }
+
+    /// Create a directory
+    /// 
+    /// Creates `path` and any missing parents inside the project root. Succeeds with
+    /// `created: false` when the directory already exists.
+    #[oai(path = "/dir", method = "post")]
+    async fn create_dir_handler(&self, req: OpenApiJson<CreateDirRequest>) -> Result<CreateDirApiResponse, GalateaError> {
+        if let Err(exceeded) = quotas::check(&[QuotaMetric::EditsPerHour]) {
+            return Err(GalateaError::TooManyRequests(exceeded.to_string()));
+        }
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let dir = resolve_new_path(req.0.path.trim().trim_end_matches('/'))?;
+        match editor::create_dir(&dir) {
+            Ok(created) => Ok(CreateDirApiResponse::Ok(OpenApiJson(CreateDirResponse { path: project_relative(&root, &dir), created }))),
+            Err(e) => Err(GalateaError::BadRequest(e)),
+        }
+    }
+
+    /// Remove a directory
+    /// 
+    /// Removes `path`, which must be empty unless `recursive=true`. A recursive removal deletes
+    /// the directory's files as one editor change, so `undo_edit` without a path and a `count`
+    /// of `files_removed` restores them
+    /// (empty subdirectories aren't restored). Symlinks inside it are unlinked, never followed,
+    /// and not restored. At most 1000 files can be removed at once. The project root itself
+    /// can't be removed.
+    #[oai(path = "/dir", method = "delete")]
+    async fn remove_dir_handler(&self, path: Query<String>, recursive: Query<Option<bool>>) -> Result<RemoveDirApiResponse, GalateaError> {
+        if let Err(exceeded) = quotas::check(&[QuotaMetric::EditsPerHour]) {
+            return Err(GalateaError::TooManyRequests(exceeded.to_string()));
+        }
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let dir = match resolve_path(&path.0) {
+            Ok(dir) => dir,
+            Err(e) => return Err(GalateaError::NotFound(e.to_string())),
+        };
+        if dir == root {
+            return Err(GalateaError::BadRequest("The project root can't be removed".to_string()));
+        }
+        let _operation = crash::track_operation(format!("remove dir {}", path.0));
+        // Removing a tree locks the whole editor rather than every file in it
+        let recursive = recursive.0.unwrap_or(false);
+        let paths = (!recursive).then(|| vec![dir.clone()]);
+        let removed_dir = dir.clone();
+        let result = editor::with_files(paths, move |editor| editor::remove_dir(editor, &removed_dir, recursive))
+            .await
+            .map_err(GalateaError::Internal)?;
+        match result {
+            Ok(files_removed) => Ok(RemoveDirApiResponse::Ok(OpenApiJson(RemoveDirResponse { path: project_relative(&root, &dir), files_removed }))),
+            Err(e) => Err(GalateaError::BadRequest(e)),
+        }
+    }
+
+    /// Upload files
+    ///
+    /// Saves files sent as `multipart/form-data` into the project, binary ones (images, fonts,
+    /// media) included, by default under `public/`. Files are saved under the name they were
+    /// sent with, stripped of any directories, or `name` for a single file. Answers `409` when
+    /// a file exists and `overwrite` isn't set, and `400` with `code: invalid` when the files
+    /// together exceed `[editor_transfers] max_upload_bytes` (default 25 MiB); request bodies
+    /// well past the limit are refused with `413` before they are read. The upload is one
+    /// editor change, so `undo_edit` without a path and a `count` of the uploaded files
+    /// reverts it.
+    #[oai(path = "/upload", method = "post")]
+    async fn upload_handler(&self, req: UploadRequest) -> Result<UploadApiResponse, GalateaError> {
+        if let Err(exceeded) = quotas::check(&[QuotaMetric::EditsPerHour, QuotaMetric::BytesWritten]) {
+            return Err(GalateaError::TooManyRequests(exceeded.to_string()));
+        }
+        if req.file.is_empty() {
+            return Err(GalateaError::BadRequest("No file sent; add one or more `file` fields".to_string()));
+        }
+        if req.name.is_some() && req.file.len() > 1 {
+            return Err(GalateaError::BadRequest("`name` can only be set when uploading a single file".to_string()));
+        }
+        let limit = transfers::TransferConfig::load().max_upload_bytes;
+        let size: u64 = req.file.iter().map(|f| f.size() as u64).sum();
+        if size > limit {
+            return Err(GalateaError::Invalid {
+                message: format!("The upload is {} bytes, over the {} byte limit", size, limit),
+                details: serde_json::json!({"size_bytes": size, "limit_bytes": limit}),
+            });
+        }
+        let root = get_project_root()?;
+        let dir = req.dir.as_deref().map(|d| d.trim().trim_matches('/')).filter(|d| !d.is_empty()).unwrap_or("public").to_string();
+        let overwrite = req.overwrite.unwrap_or(false);
+
+        let mut targets = Vec::new();
+        for upload in &req.file {
+            let sent = req.name.as_deref().or(upload.file_name()).unwrap_or_default();
+            let Some(name) = transfers::upload_file_name(sent) else {
+                return Err(GalateaError::BadRequest(format!("Invalid file name '{}'", sent)));
+            };
+            let path = if dir == "." { resolve_new_path(&name)? } else { resolve_new_path(&format!("{}/{}", dir, name))? };
+            if path.is_dir() {
+                return Err(GalateaError::BadRequest(format!("'{}' is a directory", project_relative(&root, &path))));
             }
-            Err(e) => FindFilesApiResponse::InternalServerError(
-                PlainText(format!("Error searching directory '{}': {}", req.0.dir, e)),
-            ),
+            if path.exists() && !overwrite {
+                return Err(GalateaError::Conflict(format!("'{}' already exists; set `overwrite` to replace it", project_relative(&root, &path))));
+            }
+            if targets.contains(&path) {
+                return Err(GalateaError::BadRequest(format!("'{}' was sent twice", name)));
+            }
+            targets.push(path);
+        }
+
+        let mut changes = Vec::new();
+        let mut files = Vec::new();
+        for (upload, path) in req.file.into_iter().zip(targets) {
+            let content = upload.into_vec().await.map_err(|e| GalateaError::BadRequest(format!("Failed to read the upload: {}", e)))?;
+            files.push(UploadedFileView {
+                path: project_relative(&root, &path),
+                size_bytes: content.len() as u64,
+                content_type: transfers::content_type(&path).to_string(),
+                replaced: path.exists(),
+            });
+            changes.push(editor::FileChange::Write { path, content });
+        }
+        let _operation = crash::track_operation(format!("upload ({} files)", changes.len()));
+        checkpoints::before_edits("Before uploading files", changes.len()).await;
+        editor::apply_shared_tool_changes("upload", changes).await.map_err(GalateaError::Internal)?;
+        quotas::charge(QuotaMetric::BytesWritten, size as f64);
+        Ok(UploadApiResponse::Ok(OpenApiJson(UploadResponse { files })))
+    }
+
+    /// Download a file
+    ///
+    /// Sends the file at `path` as it is on disk, binary or not, with the media type its
+    /// extension implies and a `Content-Disposition` naming it; `inline=true` lets browsers
+    /// display it instead of saving it, except HTML and SVG files, which are always sent as
+    /// attachments. Answers `400` with `code: invalid` for files over
+    /// `[editor_transfers] max_download_bytes` (default 100 MiB).
+    #[oai(path = "/download", method = "get")]
+    async fn download_handler(&self, path: Query<String>, inline: Query<Option<bool>>) -> Result<FileDownload, GalateaError> {
+        let file = resolve_path(path.0.trim()).map_err(|e| GalateaError::NotFound(e.to_string()))?;
+        let metadata = fs::metadata(&file).map_err(|e| GalateaError::NotFound(format!("{}: {}", file.display(), e)))?;
+        if !metadata.is_file() {
+            return Err(GalateaError::BadRequest(format!("Path is not a file: {}", file.display())));
+        }
+        let limit = transfers::TransferConfig::load().max_download_bytes;
+        if metadata.len() > limit {
+            return Err(GalateaError::Invalid {
+                message: format!("The file is {} bytes, over the {} byte limit", metadata.len(), limit),
+                details: serde_json::json!({"size_bytes": metadata.len(), "limit_bytes": limit}),
+            });
+        }
+        let content = tokio::fs::read(&file).await.map_err(|e| GalateaError::Internal(format!("Failed to read {}: {}", file.display(), e)))?;
+        Ok(FileDownload {
+            content,
+            content_type: transfers::content_type(&file),
+            file_name: file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            inline: inline.0.unwrap_or(false),
+        })
+    }
+
+    /// Query the edit history
+    ///
+    /// Returns the edits made through Galatea, newest first: editor commands, tool fixes and
+    /// directory operations. `path` keeps edits to that file, or to anything under it when it
+    /// is a directory; it may name a file that no longer exists. `since` and `until` are Unix
+    /// seconds, both inclusive, so `since=<an hour ago>&path=src/app/page.tsx` answers "what
+    /// changed this file in the last hour". `limit` defaults to 100, at most 1000.
+    #[oai(path = "/history", method = "get")]
+    async fn edit_history_handler(
+        &self,
+        path: Query<Option<String>>,
+        since: Query<Option<i64>>,
+        until: Query<Option<i64>>,
+        limit: Query<Option<usize>>,
+    ) -> Result<EditHistoryApiResponse, GalateaError> {
+        let root = get_project_root()?;
+        let filter = match path.0.as_deref().map(|p| p.trim().trim_end_matches('/')).filter(|p| !p.is_empty() && *p != ".") {
+            Some(p) => Some(resolve_new_path(p)?.to_string_lossy().into_owned()),
+            None => None,
+        };
+        let limit = limit.0.unwrap_or(100).clamp(1, 1000);
+        let edits = db::with_db(|db| db.edit_history(filter.as_deref(), since.0, until.0, limit))?;
+        Ok(EditHistoryApiResponse::Ok(OpenApiJson(EditHistoryResponse {
+            edits: edits
+                .into_iter()
+                .map(|edit| EditHistoryItem {
+                    timestamp: edit.timestamp,
+                    command: edit.command,
+                    path: edit.path.map(|p| project_relative(&root, std::path::Path::new(&p))),
+                    session: edit.session,
+                })
+                .collect(),
+        })))
+    }
+
+    /// Get effective .editorconfig settings for a path
+    /// 
+    /// Returns the `.editorconfig` settings the editor applies when it creates the file or
+    /// inserts lines into it. Settings are merged from every `.editorconfig` between the file
+    /// and the nearest one declaring `root = true`, closest file winning.
+    /// 
+    /// ## Applied by the editor:
+    /// - **create**: indentation, trailing whitespace, line endings, final newline and UTF-8 BOM
+    /// - **insert**: indentation and trailing whitespace of the inserted text, line endings
+    ///   and final newline of the file
+    #[oai(path = "/editorconfig", method = "post")]
+    async fn editorconfig_handler(
+        &self,
+        req: OpenApiJson<EditorConfigRequest>,
+    ) -> Result<EditorConfigApiResponse, GalateaError> {
+        // The file may not exist yet, so fall back to joining with the project root
+        let path = match resolve_path(&req.0.path) {
+            Ok(p) => p,
+            Err(_) => match get_project_root() {
+                Ok(root) => root.join(req.0.path.trim_start_matches('/')),
+                Err(e) => {
+                    return Err(GalateaError::Internal(e.to_string()));
+                }
+            },
+        };
+        if path.is_dir() {
+            return Err(GalateaError::BadRequest(format!(
+                "Path is a directory: {}",
+                path.display()
+            )));
+        }
+
+        match editorconfig::resolve_for_path(&path) {
+            Ok(settings) => Ok(EditorConfigApiResponse::Ok(OpenApiJson(EditorConfigResponse {
+                path: path.display().to_string(),
+                indent_style: settings.indent_style.map(|s| match s {
+                    editorconfig::IndentStyle::Space => "space".to_string(),
+                    editorconfig::IndentStyle::Tab => "tab".to_string(),
+                }),
+                indent_size: settings.indent_size,
+                tab_width: settings.tab_width,
+                end_of_line: settings.end_of_line.map(|e| match e {
+                    editorconfig::EndOfLine::Lf => "lf".to_string(),
+                    editorconfig::EndOfLine::Crlf => "crlf".to_string(),
+                    editorconfig::EndOfLine::Cr => "cr".to_string(),
+                }),
+                charset: settings.charset,
+                trim_trailing_whitespace: settings.trim_trailing_whitespace,
+                insert_final_newline: settings.insert_final_newline,
+                sources: settings.sources.iter().map(|p| p.display().to_string()).collect(),
+            }))),
+            Err(e) => Err(GalateaError::Internal(e)),
         }
     }
 
@@ -1050,86 +3004,71 @@ impl EditorApi {
     /// - **test**: Run the test suite (`pnpm run test`)
     /// - **install**: Install/update dependencies (`pnpm install`)
     /// 
+    /// Commands run with the package manager picked at setup (`package_manager` in
+    /// config.toml), or else the one whose lockfile the project has, pnpm by default. A command declared under `[project]` in config.toml
+    /// (e.g. `test = "pnpm exec vitest run"`) replaces the script; an operation whose script
+    /// package.json doesn't define and config.toml doesn't declare answers 400.
+    /// 
     /// ## Features:
     /// - **Custom arguments**: Pass additional flags to the underlying commands
     /// - **Working directory**: Run scripts from specific directories
     /// - **Environment variables**: Set custom environment for script execution
     /// - **Detailed output**: Returns stdout, stderr, exit codes, and timing information
+    /// - **Streaming**: `/script/stream` sends the output as Server-Sent Events while the script runs
+    /// - **Background jobs**: With `background`, answers 202 with a `job_id` right away; poll
+    ///   `GET /api/jobs/{id}` and kill a hung script and everything it started with `DELETE`
     /// - **Error handling**: Graceful handling of script failures with detailed diagnostics
+    /// - **Quotas**: Runs and their CPU time count against the caller's `script_runs_per_hour`
+    ///   and `cpu_seconds` quotas; a used-up quota answers 429 (see `GET /api/usage`)
     /// 
     /// ## Examples:
     /// - Basic lint: `{"operation": "lint"}`
     /// - Lint with auto-fix: `{"operation": "lint", "args": ["--fix"]}`
     /// - Test with coverage: `{"operation": "test", "args": ["--coverage"]}`
     /// - Production build: `{"operation": "build", "env_vars": {"NODE_ENV": "production"}}`
+    /// - Build in the background: `{"operation": "build", "background": true}`
     #[oai(path = "/script", method = "post")]
-    async fn script_handler(&self, req: OpenApiJson<ScriptExecutionRequest>) -> ScriptApiResponse {
+    async fn script_handler(&self, req: OpenApiJson<ScriptExecutionRequest>) -> Result<ScriptApiResponse, GalateaError> {
+        if let Err(exceeded) = quotas::check(&[QuotaMetric::ScriptRunsPerHour, QuotaMetric::CpuSeconds]) {
+            return Err(GalateaError::TooManyRequests(exceeded.to_string()));
+        }
         let start_time = std::time::Instant::now();
+        let _operation = crash::track_operation(format!("script {}", req.0.operation));
         
-        // Determine working directory
-        let working_dir = if let Some(ref wd) = req.0.working_dir {
-            match resolve_path(wd) {
-                Ok(path) => {
-                    if !path.exists() || !path.is_dir() {
-                        return ScriptApiResponse::BadRequest(
-                            PlainText(format!("Working directory does not exist or is not a directory: {}", wd))
-                        );
-                    }
-                    path
-                }
-                Err(e) => {
-                    return ScriptApiResponse::BadRequest(
-                        PlainText(format!("Failed to resolve working directory '{}': {}", wd, e))
-                    );
-                }
-            }
-        } else {
-            match get_project_root() {
-                Ok(pr) => pr,
-                Err(e) => return ScriptApiResponse::InternalServerError(
-                    PlainText(format!("Failed to get project root: {}", e))
-                ),
-            }
-        };
+        let mut cmd = script_command(&req.0)?;
+        let base_cmd = PackageManager::current().name();
 
-        // Build command based on operation
-        let (base_cmd, base_args) = match req.0.operation {
-            ScriptOperation::Lint => ("pnpm", vec!["run", "lint"]),
-            ScriptOperation::Format => ("pnpm", vec!["run", "format"]),
-            ScriptOperation::Build => ("pnpm", vec!["run", "build"]),
-            ScriptOperation::Test => ("pnpm", vec!["run", "test"]),
-            ScriptOperation::Install => ("pnpm", vec!["install"]),
-        };
-
-        let mut cmd = Command::new(base_cmd);
-        cmd.current_dir(&working_dir);
-        
-        // Add base arguments
-        for arg in base_args {
-            cmd.arg(arg);
-        }
-        
-        // Add custom arguments if provided
-        if let Some(ref args) = req.0.args {
-            for arg in args {
-                cmd.arg(arg);
-            }
-        }
-        
-        // Set environment variables if provided
-        if let Some(ref env_vars) = req.0.env_vars {
-            for (key, value) in env_vars {
-                cmd.env(key, value);
-            }
+        if req.0.background.unwrap_or(false) {
+            let std_cmd = cmd.as_std();
+            let command = std::iter::once(std_cmd.get_program())
+                .chain(std_cmd.get_args())
+                .map(|a| a.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ");
+            return match jobs::spawn("script", command.clone(), cmd) {
+                Ok(job_id) => {
+                    quotas::charge(QuotaMetric::ScriptRunsPerHour, 1.0);
+                    Ok(ScriptApiResponse::Accepted(OpenApiJson(ScriptJobResponse {
+                        job_id,
+                        operation: req.0.operation.to_string(),
+                        command,
+                    })))
+                }
+                Err(e) => Err(GalateaError::Internal(format!(
+                    "Failed to execute {} {}: {:#}",
+                    base_cmd, req.0.operation, e
+                ))),
+            };
         }
 
         // Execute the command
+        let cpu_before = quotas::children_cpu_seconds();
         let output = match cmd.output().await {
             Ok(out) => out,
-            Err(e) => return ScriptApiResponse::InternalServerError(
-                PlainText(format!("Failed to execute {} {}: {}", base_cmd, req.0.operation, e))
-            ),
+            Err(e) => return Err(GalateaError::Internal(format!("Failed to execute {} {}: {}", base_cmd, req.0.operation, e))),
         };
+        quotas::charge(QuotaMetric::ScriptRunsPerHour, 1.0);
+        quotas::charge(QuotaMetric::CpuSeconds, quotas::children_cpu_seconds() - cpu_before);
 
         let duration_ms = start_time.elapsed().as_millis() as u64;
         let timestamp = SystemTime::now()
@@ -1138,7 +3077,7 @@ impl EditorApi {
             .as_secs()
             .to_string();
 
-        ScriptApiResponse::Ok(OpenApiJson(ScriptResponse {
+        Ok(ScriptApiResponse::Ok(OpenApiJson(ScriptResponse {
             success: output.status.success(),
             stdout: String::from_utf8_lossy(&output.stdout).to_string(),
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
@@ -1146,37 +3085,413 @@ impl EditorApi {
             operation: req.0.operation.to_string(),
             executed_at: timestamp,
             duration_ms: Some(duration_ms),
-        }))
+        })))
+    }
+
+    /// Execute a project script and stream its output
+    /// 
+    /// Takes the same request as `/script` but answers with Server-Sent Events as the script
+    /// runs instead of buffering its output until it exits, which suits long builds and
+    /// installs. Each `stdout` and `stderr` event carries one output line; the final `exit`
+    /// event carries the exit `status`, `success` and `duration_ms`. Closing the connection
+    /// kills the script.
+    /// 
+    /// ## Example stream:
+    /// ```text
+    /// event: stdout
+    /// data: {"event":"stdout","line":"> next build",...}
+    /// 
+    /// event: exit
+    /// data: {"event":"exit","status":0,"success":true,"duration_ms":41250,...}
+    /// ```
+    #[oai(path = "/script/stream", method = "post")]
+    async fn script_stream_handler(&self, req: OpenApiJson<ScriptExecutionRequest>) -> Result<ScriptStreamApiResponse, GalateaError> {
+        if let Err(exceeded) = quotas::check(&[QuotaMetric::ScriptRunsPerHour, QuotaMetric::CpuSeconds]) {
+            return Err(GalateaError::TooManyRequests(exceeded.to_string()));
+        }
+        let cmd = script_command(&req.0)?;
+        let cpu_before = quotas::children_cpu_seconds();
+        let events = match process_stream::spawn_streaming(cmd) {
+            Ok(process) => process.events,
+            Err(e) => {
+                return Err(GalateaError::Internal(format!(
+                    "Failed to execute {} {}: {:#}",
+                    PackageManager::current().name(),
+                    req.0.operation,
+                    e
+                )))
+            }
+        };
+        quotas::charge(QuotaMetric::ScriptRunsPerHour, 1.0);
+
+        // The operation stays registered for as long as the stream is open
+        let operation = crash::track_operation(format!("script stream {}", req.0.operation));
+        let events = stream::unfold(events, |mut events| async move { events.recv().await.map(|e| (e, events)) })
+            .map(move |event| {
+                let _operation = &operation;
+                if matches!(event, ProcessEvent::Exit { .. }) {
+                    quotas::charge(QuotaMetric::CpuSeconds, quotas::children_cpu_seconds() - cpu_before);
+                }
+                ScriptStreamEvent::from(event)
+            })
+            .boxed();
+        Ok(ScriptStreamApiResponse::Ok(
+            EventStream::new(events)
+                .keep_alive(std::time::Duration::from_secs(15))
+                .to_event(|event| Event::message(event.to_json_string()).event_type(event.event.clone())),
+        ))
+    }
+
+    /// Read the lint and format policy
+    /// 
+    /// Returns the project's ESLint rules and ignore patterns and its Prettier options and
+    /// ignore patterns, as stored in the configuration files this API edits. Pass
+    /// `effective=true` to also get the rules ESLint applies to `file` (or the first source
+    /// file) after every shared config it extends is merged.
+    #[oai(path = "/lint-policy", method = "get")]
+    async fn get_lint_policy_handler(
+        &self,
+        effective: Query<Option<bool>>,
+        file: Query<Option<String>>,
+    ) -> Result<LintPolicyApiResponse, GalateaError> {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let mut response = match lint_policy_response(&root) {
+            Ok(response) => response,
+            Err(e) => return Err(GalateaError::Internal(format!("{:#}", e))),
+        };
+        if effective.0.unwrap_or(false) {
+            let sample = match lint_policy::sample_file(&root, file.0.as_deref()) {
+                Ok(Some(sample)) => sample,
+                Ok(None) => return Err(GalateaError::BadRequest("No source file to resolve rules for".to_string())),
+                Err(e) => return Err(GalateaError::BadRequest(e.to_string())),
+            };
+            match lint_policy::effective_rules(&root, &sample).await {
+                Ok(rules) => response.effective_rules = Some(serde_json::Value::Object(rules)),
+                Err(e) => return Err(GalateaError::Internal(format!("{:#}", e))),
+            }
+        }
+        Ok(LintPolicyApiResponse::Ok(OpenApiJson(Box::new(response))))
+    }
+
+    /// Change the lint and format policy
+    /// 
+    /// Enables, disables or changes the severity of ESLint rules, adds or removes ignore
+    /// patterns, and sets Prettier options, without hand-editing the config files.
+    /// 
+    /// ## How configs are edited:
+    /// - **`.eslintrc.json` / `package.json`**: `rules` and `ignorePatterns` are edited in place
+    /// - **`eslint.config.*` (flat config)**: the project's export is kept as the base and a
+    ///   Galatea-managed block at the end of the file layers the rules and ignores over it
+    /// - **Prettier**: `.prettierrc(.json)` or the `prettier` key of package.json (created as
+    ///   `.prettierrc.json` if missing); ignore patterns go to `.prettierignore`
+    /// 
+    /// ## Validation:
+    /// After writing, ESLint lints one sample file (and Prettier checks it, when its policy
+    /// changed). If either tool can't load the new configuration the change is rolled back
+    /// and a 422 with the tool output is returned. With `dry_run` the change is always
+    /// rolled back after validating.
+    /// 
+    /// ## Examples:
+    /// - Silence a rule: `{"rules": [{"rule": "no-console", "severity": "off"}]}`
+    /// - Ignore generated code: `{"add_ignore_patterns": ["src/generated/**"]}`
+    /// - Try a Prettier option: `{"prettier_options": {"printWidth": 100}, "dry_run": true}`
+    #[oai(path = "/lint-policy", method = "post")]
+    async fn update_lint_policy_handler(&self, req: OpenApiJson<LintPolicyRequest>) -> Result<LintPolicyApiResponse, GalateaError> {
+        let req = req.0;
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let mut set_rules = Vec::new();
+        for change in req.rules {
+            match lint_policy::rule_entry(&change.severity, change.options) {
+                Ok(entry) => set_rules.push((change.rule, entry)),
+                Err(e) => return Err(GalateaError::BadRequest(format!("Rule '{}': {}", change.rule, e))),
+            }
+        }
+        let change = lint_policy::PolicyChange {
+            set_rules,
+            remove_rules: req.remove_rules,
+            add_ignores: req.add_ignore_patterns,
+            remove_ignores: req.remove_ignore_patterns,
+            set_prettier_options: req.prettier_options.unwrap_or_default().into_iter().collect(),
+            remove_prettier_options: req.remove_prettier_options,
+            add_prettier_ignores: req.add_prettier_ignore_patterns,
+            remove_prettier_ignores: req.remove_prettier_ignore_patterns,
+        };
+        let sample = match lint_policy::sample_file(&root, req.validate_path.as_deref()) {
+            Ok(sample) => sample,
+            Err(e) => return Err(GalateaError::BadRequest(e.to_string())),
+        };
+        let _operation = crash::track_operation("lint-policy update".to_string());
+        let (changed, validation, applied) =
+            match lint_policy::apply_and_validate(&root, &change, sample.as_deref(), req.dry_run.unwrap_or(false)).await {
+                Ok(result) => result,
+                Err(e) => return Err(GalateaError::BadRequest(format!("{:#}", e))),
+            };
+        let mut response = match lint_policy_response(&root) {
+            Ok(response) => response,
+            Err(e) => return Err(GalateaError::Internal(format!("{:#}", e))),
+        };
+        response.changed_files = changed.iter().map(|p| p.display().to_string()).collect();
+        response.applied = applied;
+        let valid = validation.ok;
+        response.validation = Some(LintValidationResult {
+            ok: validation.ok,
+            sample_file: validation.sample_file,
+            errors: validation.errors,
+            warnings: validation.warnings,
+            output: validation.output,
+        });
+        if valid {
+            Ok(LintPolicyApiResponse::Ok(OpenApiJson(Box::new(response))))
+        } else {
+            Ok(LintPolicyApiResponse::InvalidPolicy(OpenApiJson(Box::new(response))))
+        }
+    }
+
+    /// Type-check the project
+    /// 
+    /// Runs `tsc --noEmit` and returns its diagnostics as records (file, line, column, code,
+    /// message) instead of raw output. The result is cached until a TypeScript, JavaScript or
+    /// JSON file of the project changes, so repeated checks are cheap; `refresh=true` runs tsc
+    /// anyway. `path` narrows the returned errors to a file or directory, relative to the
+    /// project root.
+    #[oai(path = "/typecheck", method = "get")]
+    async fn typecheck_handler(&self, refresh: Query<Option<bool>>, path: Query<Option<String>>) -> Result<TypecheckApiResponse, GalateaError> {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        if !root.join("tsconfig.json").exists() {
+            return Err(GalateaError::BadRequest("The project has no tsconfig.json".to_string()));
+        }
+        let (run, cached) = match typecheck::typecheck(&root, refresh.0.unwrap_or(false)).await {
+            Ok(found) => found,
+            Err(e) => return Err(GalateaError::Internal(format!("{:#}", e))),
+        };
+        let prefix = path.0.as_deref().map(|p| p.trim_start_matches("./").trim_end_matches('/').to_string()).filter(|p| !p.is_empty());
+        let in_scope = |file: &str| prefix.as_deref().is_none_or(|p| file == p || file.strip_prefix(p).is_some_and(|rest| rest.starts_with('/')));
+        Ok(TypecheckApiResponse::Ok(OpenApiJson(TypecheckResponse {
+            passed: run.passed(),
+            error_count: run.errors.iter().filter(|e| e.category == "error").count(),
+            errors: run
+                .errors
+                .iter()
+                .filter(|e| in_scope(&e.path))
+                .map(|e| TypeErrorItem {
+                    path: e.path.clone(),
+                    line: e.line,
+                    column: e.column,
+                    code: e.code.clone(),
+                    category: e.category.clone(),
+                    message: e.message.clone(),
+                })
+                .collect(),
+            cached,
+            exit_code: run.exit_code,
+            duration_ms: run.duration_ms,
+            finished_at: run.finished_at,
+            output: run.other_output.clone(),
+        })))
+    }
+
+    /// Lint files with ESLint
+    /// 
+    /// Runs ESLint with `--format json` on `files` (or the whole project) and returns its
+    /// problems per file: rule, severity, message and range, and whether an autofix exists.
+    /// With `fix`, the autofixes are applied through the editor (`undo_edit` without a path and
+    /// a `count` of the fixed files reverts them all) and the remaining problems are returned. Unlike `/script` with `lint`, this doesn't
+    /// go through the project's `lint` script, so ESLint must be installed in the project.
+    #[oai(path = "/lint", method = "post")]
+    async fn lint_handler(&self, req: OpenApiJson<LintRequest>) -> Result<LintApiResponse, GalateaError> {
+        let req = req.0;
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let mut files = Vec::new();
+        for file in req.files.unwrap_or_default() {
+            match resolve_path(&file) {
+                Ok(path) if path.exists() => files.push(path),
+                Ok(_) => return Err(GalateaError::BadRequest(format!("'{}' does not exist", file))),
+                Err(e) => return Err(GalateaError::BadRequest(e.to_string())),
+            }
+        }
+        let fix = req.fix.unwrap_or(false);
+        let mut results = match lint::run_eslint(&root, &files, fix).await {
+            Ok(results) => results,
+            Err(e) => return Err(GalateaError::Internal(format!("{:#}", e))),
+        };
+        let fixed: Vec<PathBuf> = if fix {
+            let fixed_files = results.iter().filter(|r| r.output.is_some()).count();
+            if fixed_files > 0 {
+                checkpoints::before_edits("Before eslint --fix", fixed_files).await;
+            }
+            let paths = results.iter().map(|r| PathBuf::from(&r.file_path)).collect();
+            let fixes = editor::with_files(Some(paths), move |editor| lint::apply_fixes(editor, &results).map(|fixed| (fixed, results)))
+                .await
+                .map_err(GalateaError::Internal)?;
+            match fixes {
+                Ok((fixed, linted)) => {
+                    results = linted;
+                    fixed
+                }
+                Err(e) => return Err(GalateaError::Internal(format!("Failed to apply the fixes: {:#}", e))),
+            }
+        } else {
+            Vec::new()
+        };
+        let files_linted = results.len();
+        let error_count = results.iter().map(|r| r.error_count).sum();
+        let warning_count = results.iter().map(|r| r.warning_count).sum();
+        let files = results
+            .into_iter()
+            .filter_map(|result| {
+                let was_fixed = fixed.iter().any(|p| p.as_os_str() == result.file_path.as_str());
+                (was_fixed || !result.messages.is_empty()).then(|| lint_file_item(result, &root, was_fixed))
+            })
+            .collect();
+        Ok(LintApiResponse::Ok(OpenApiJson(LintResponse { files, files_linted, error_count, warning_count })))
     }
 
-    /// Legacy lint endpoint (deprecated)
-    /// 
-    /// **Deprecated**: Use `/script` endpoint with `{"operation": "lint"}` instead.
-    /// This endpoint is maintained for backward compatibility but may be removed in future versions.
-    #[oai(path = "/lint", method = "post", deprecated = true)]
-    async fn lint_handler(&self) -> ScriptApiResponse {
-        let req = ScriptExecutionRequest {
-            operation: ScriptOperation::Lint,
-            args: None,
-            working_dir: None,
-            env_vars: None,
+    /// Format or check individual files with Prettier
+    /// 
+    /// Formats just the given files, e.g. the ones an agent has just edited, instead of the
+    /// whole project. With `check`, nothing is written and the files Prettier would change are
+    /// returned. Otherwise those files are reformatted through the editor, so `undo_edit`
+    /// without a path and a `count` of the reformatted files reverts the formatting. Uses the project's Prettier config and `.prettierignore`.
+    #[oai(path = "/format", method = "post")]
+    async fn format_handler(&self, req: OpenApiJson<FormatRequest>) -> Result<FormatApiResponse, GalateaError> {
+        let req = req.0;
+        if req.files.is_empty() {
+            return Err(GalateaError::BadRequest("'files' must not be empty".to_string()));
+        }
+        if req.files.len() > format::MAX_FILES {
+            return Err(GalateaError::BadRequest(format!(
+                "At most {} files can be formatted at once; use /script with the format operation for the whole project",
+                format::MAX_FILES
+            )));
+        }
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
         };
-        self.script_handler(OpenApiJson(req)).await
+        let mut files = Vec::new();
+        for file in &req.files {
+            match resolve_path(file) {
+                Ok(path) if path.is_file() => files.push(path),
+                Ok(_) => return Err(GalateaError::BadRequest(format!("'{}' is not a file", file))),
+                Err(e) => return Err(GalateaError::BadRequest(e.to_string())),
+            }
+        }
+        let relative = |path: &std::path::Path| path.strip_prefix(&root).unwrap_or(path).to_string_lossy().into_owned();
+
+        if req.check.unwrap_or(false) {
+            return match format::unformatted_files(&root, &files).await {
+                Ok(unformatted) => Ok(FormatApiResponse::Ok(OpenApiJson(FormatResponse {
+                    files: unformatted.iter().map(|p| relative(p)).collect(),
+                    written: false,
+                }))),
+                Err(e) => Err(GalateaError::Internal(format!("{:#}", e))),
+            };
+        }
+        checkpoints::before_edits("Before prettier", files.len()).await;
+        // Formatted under the locks of the files, so prettier's output replaces the content it
+        // was computed from. The closure runs on a blocking thread, which may wait on prettier
+        let (runtime, format_root) = (tokio::runtime::Handle::current(), root.clone());
+        let changes = editor::with_files(Some(files.clone()), move |editor| {
+            let changes = runtime.block_on(format::format_changes(&format_root, &files)).map_err(|e| format!("{:#}", e))?;
+            editor::apply_tool_changes(editor, "format", &changes).map(|_| changes)
+        })
+        .await
+        .and_then(|r| r)
+        .map_err(GalateaError::Internal)?;
+        Ok(FormatApiResponse::Ok(OpenApiJson(FormatResponse {
+            files: changes.iter().map(|c| relative(c.path())).collect(),
+            written: !changes.is_empty(),
+        })))
     }
 
-    /// Legacy format endpoint (deprecated)
-    /// 
-    /// **Deprecated**: Use `/script` endpoint with `{"operation": "format"}` instead.
-    /// This endpoint is maintained for backward compatibility but may be removed in future versions.
-    #[oai(path = "/format", method = "post", deprecated = true)]
-    async fn format_handler(&self) -> ScriptApiResponse {
-        let req = ScriptExecutionRequest {
-            operation: ScriptOperation::Format,
-            args: None,
-            working_dir: None,
-            env_vars: None,
+    /// Apply a unified diff
+    ///
+    /// Applies a patch, as printed by `git diff` or written by a model, across any number of
+    /// files. Each hunk is placed by its context rather than trusting its line numbers: it
+    /// may match away from where its header says (`offset`), with whitespace differences
+    /// (`whitespace`) and, with `fuzz`, with up to that many context lines at either end not
+    /// matching. If any hunk can't be placed, nothing is written and a 409 quotes the lines
+    /// it expected. The patch is applied as one transaction, one edit per file in the editor's
+    /// history: `undo_edit` without a path and a `count` of the changed files reverts it.
+    #[oai(path = "/apply-patch", method = "post")]
+    async fn apply_patch_handler(&self, req: OpenApiJson<ApplyPatchRequest>) -> Result<ApplyPatchApiResponse, GalateaError> {
+        let req = req.0;
+        let dry_run = req.dry_run.unwrap_or(false);
+        if !dry_run {
+            if let Err(exceeded) = quotas::check(&[QuotaMetric::EditsPerHour, QuotaMetric::BytesWritten]) {
+                return Err(GalateaError::TooManyRequests(exceeded.to_string()));
+            }
+        }
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
         };
-        self.script_handler(OpenApiJson(req)).await
+        let file_patches = match patch::parse_patch(&req.patch) {
+            Ok(file_patches) => file_patches,
+            Err(e) => return Err(GalateaError::BadRequest(format!("{:#}", e))),
+        };
+        if !dry_run {
+            checkpoints::before_edits("Before applying a patch", file_patches.len()).await;
+        }
+        // Planned under the locks of the patched files, so nothing changes them between reading
+        // and writing
+        let fuzz = req.fuzz.unwrap_or(patch::DEFAULT_FUZZ);
+        let paths = patch::patch_paths(&root, &file_patches);
+        let plan_root = root.clone();
+        let plan = editor::with_files(Some(paths), move |editor| {
+            let plan = patch::plan_patch(&plan_root, &file_patches, fuzz).map_err(|e| GalateaError::Conflict(format!("{:#}", e)))?;
+            if !dry_run && !plan.changes.is_empty() {
+                let _operation = crash::track_operation(format!("apply-patch ({} files)", plan.changes.len()));
+                editor::apply_tool_changes(editor, "apply_patch", &plan.changes).map_err(GalateaError::Internal)?;
+            }
+            Ok::<_, GalateaError>(plan)
+        })
+        .await
+        .map_err(GalateaError::Internal)??;
+
+        if !dry_run {
+            let bytes: usize = plan
+                .changes
+                .iter()
+                .map(|c| match c {
+                    editor::FileChange::Write { content, .. } => content.len(),
+                    editor::FileChange::Delete { .. } => 0,
+                })
+                .sum();
+            quotas::charge(QuotaMetric::BytesWritten, bytes as f64);
+        }
+
+        let relative = |path: &std::path::Path| path.strip_prefix(&root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        Ok(ApplyPatchApiResponse::Ok(OpenApiJson(ApplyPatchResponse {
+            files: plan
+                .files
+                .iter()
+                .map(|f| PatchedFileView {
+                    path: relative(&f.path),
+                    old_path: f.old_path.as_deref().map(relative),
+                    kind: f.kind.as_str().to_string(),
+                    hunks: f
+                        .hunks
+                        .iter()
+                        .map(|h| PatchHunkView { line: h.line, offset: h.offset as i64, fuzz: h.fuzz, whitespace: h.whitespace })
+                        .collect(),
+                    diff: f.diff.clone(),
+                })
+                .collect(),
+            applied: !dry_run && !plan.changes.is_empty(),
+        })))
     }
 }
 
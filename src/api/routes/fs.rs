@@ -0,0 +1,167 @@
+use futures::{SinkExt, StreamExt};
+use poem::web::websocket::{Message, WebSocket, WebSocketStream};
+use poem::web::Query as PoemQuery;
+use poem::{get, handler, IntoResponse, Route};
+use poem_openapi::{
+    param::Query,
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::file_system::watcher::{self, FsEvent};
+
+// Define an API struct
+pub struct FsApi;
+
+const DEFAULT_CHANGES_LIMIT: usize = 500;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize, Clone)]
+struct FsEventView {
+    /// Sequence number; pass the last one seen as `since` to get only newer changes
+    seq: u64,
+
+    /// Unix timestamp (seconds) the change was published at
+    timestamp: u64,
+
+    /// Path relative to the project root; `.` for `rescan`
+    path: String,
+
+    /// `created`, `modified`, `removed`, or `rescan` when changes were lost and clients should
+    /// rescan what they track
+    kind: String,
+
+    /// Whether the path is a directory
+    is_dir: bool,
+}
+
+impl From<FsEvent> for FsEventView {
+    fn from(event: FsEvent) -> Self {
+        Self { seq: event.seq, timestamp: event.timestamp, path: event.path, kind: event.kind.as_str().to_string(), is_dir: event.is_dir }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct FsChangesResponse {
+    /// Changes after `since`, oldest first
+    events: Vec<FsEventView>,
+
+    /// Sequence number of the newest change; the `since` for the next poll
+    latest_seq: u64,
+
+    /// Whether the watcher is running; when false no changes will be reported
+    watching: bool,
+}
+
+#[derive(ApiResponse)]
+enum FsChangesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<FsChangesResponse>),
+}
+
+#[OpenApi]
+impl FsApi {
+    /// Health check endpoint for the File System API
+    ///
+    /// Returns a simple status message to verify that the File System API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn fs_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("File System API route is healthy".to_string()))
+    }
+
+    /// Poll for file changes in the project
+    ///
+    /// Returns the files and directories created, modified or removed since sequence number
+    /// `since`, whoever changed them. Changes are debounced, so a save or a `git checkout`
+    /// arrives as one batch, and `node_modules`, `.next`, `.git` and other build directories are
+    /// ignored (more via `ignore` under `[fs_watcher]` in config.toml). The last 1000 changes
+    /// are kept; a client that falls further behind should rescan. Use `/api/fs/events` to be
+    /// pushed changes over a WebSocket instead.
+    #[oai(path = "/changes", method = "get")]
+    async fn changes_handler(
+        &self,
+        /// **Optional.** Only return changes with a larger sequence number. Defaults to all kept changes.
+        since: Query<Option<u64>>,
+        /// **Optional.** Most changes to return. Defaults to 500.
+        limit: Query<Option<usize>>,
+    ) -> FsChangesApiResponse {
+        let events = watcher::events(since.0, limit.0.unwrap_or(DEFAULT_CHANGES_LIMIT));
+        FsChangesApiResponse::Ok(OpenApiJson(FsChangesResponse {
+            events: events.into_iter().map(FsEventView::from).collect(),
+            latest_seq: watcher::latest_seq(),
+            watching: watcher::is_watching(),
+        }))
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct FsEventsParams {
+    /// Replay the kept changes after this sequence number before streaming new ones
+    since: Option<u64>,
+}
+
+// Sent when the client fell too far behind to be sent every change
+#[derive(serde::Serialize)]
+struct LaggedMessage {
+    r#type: &'static str,
+    message: String,
+}
+
+fn event_message(event: FsEvent) -> Message {
+    Message::text(serde_json::to_string(&FsEventView::from(event)).unwrap_or_default())
+}
+
+/// Stream file changes in the project over a WebSocket.
+///
+/// Served at `/api/fs/events`. Each change is sent as a JSON text message shaped like the
+/// events of `/api/fs/changes`. With `?since=<seq>` the kept changes after `seq` are replayed
+/// first, so a reconnecting client misses nothing. A client too slow to keep up receives
+/// `{"type":"error",...}` and should resync through `/api/fs/changes?since=`.
+#[handler]
+pub async fn fs_events_ws_handler(PoemQuery(params): PoemQuery<FsEventsParams>, ws: WebSocket) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| stream_events(socket, params.since))
+}
+
+async fn stream_events(socket: WebSocketStream, since: Option<u64>) {
+    let (mut sink, mut stream) = socket.split();
+    let (backlog, mut events) = watcher::subscribe(since);
+    for event in backlog {
+        if sink.send(event_message(event)).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if sink.send(event_message(event)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    let message = format!("Fell behind; {} changes were skipped. Resync through /api/fs/changes?since=", skipped);
+                    let lagged = LaggedMessage { r#type: "error", message };
+                    if sink.send(Message::text(serde_json::to_string(&lagged).unwrap_or_default())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = stream.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+pub fn fs_routes() -> Route {
+    let api_service = OpenApiService::new(FsApi, "File System API", "1.0").server("/api/fs");
+    Route::new().at("/events", get(fs_events_ws_handler)).nest("/", api_service)
+}
@@ -0,0 +1,656 @@
+use poem::Route;
+use poem_openapi::{
+    param::Query,
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+
+use crate::dev_operation::checkpoints::{self, Checkpoint};
+use crate::dev_operation::git::{self, BranchInfo, CommitInfo, DiffTarget, RepoStatus};
+use crate::dev_runtime::events;
+use crate::file_system::paths::get_project_root;
+
+// Define an API struct
+pub struct GitApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct FileStatusView {
+    /// Path relative to the repository root
+    path: String,
+
+    /// Previous path of a renamed or copied file
+    orig_path: Option<String>,
+
+    /// Staged change: `M`, `A`, `D`, `R`, `C`, `U` (conflict), `?` (untracked) or empty
+    index: String,
+
+    /// Unstaged change, with the same codes
+    worktree: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct StatusResponse {
+    /// Current branch; absent on a detached HEAD
+    branch: Option<String>,
+
+    /// Upstream branch, e.g. `origin/main`
+    upstream: Option<String>,
+
+    /// Commits not yet pushed to the upstream
+    ahead: usize,
+
+    /// Upstream commits not yet merged
+    behind: usize,
+
+    /// Changed and untracked files; empty when the working tree is clean
+    files: Vec<FileStatusView>,
+}
+
+impl From<RepoStatus> for StatusResponse {
+    fn from(status: RepoStatus) -> Self {
+        let code = |c: char| c.to_string().trim().to_string();
+        Self {
+            branch: status.branch,
+            upstream: status.upstream,
+            ahead: status.ahead,
+            behind: status.behind,
+            files: status
+                .files
+                .into_iter()
+                .map(|f| FileStatusView { path: f.path, orig_path: f.orig_path, index: code(f.index), worktree: code(f.worktree) })
+                .collect(),
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum StatusApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<StatusResponse>),
+    /// Not a git repository, or git refused the operation
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct DiffResponse {
+    /// Unified diff
+    diff: String,
+
+    /// Whether the diff was cut at 512 KiB; narrow it with `path`
+    truncated: bool,
+}
+
+#[derive(ApiResponse)]
+enum DiffApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<DiffResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct PathsRequest {
+    /// Paths relative to the project root
+    ///
+    /// **Optional.** All changes when omitted or empty.
+    paths: Option<Vec<String>>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CommitRequest {
+    /// Commit message
+    ///
+    /// **Required.** A `Galatea-Session` trailer with the current session id is appended.
+    message: String,
+
+    /// Stage every change (including new and deleted files) before committing
+    ///
+    /// **Optional.** Defaults to false: only what is staged is committed.
+    all: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CommitView {
+    hash: String,
+
+    short_hash: String,
+
+    author: String,
+
+    email: String,
+
+    /// Author date (Unix seconds)
+    timestamp: i64,
+
+    /// First line of the message
+    subject: String,
+
+    /// Galatea session that made the commit, from its `Galatea-Session` trailer
+    session: Option<String>,
+}
+
+impl From<CommitInfo> for CommitView {
+    fn from(c: CommitInfo) -> Self {
+        Self { hash: c.hash, short_hash: c.short_hash, author: c.author, email: c.email, timestamp: c.timestamp, subject: c.subject, session: c.session }
+    }
+}
+
+#[derive(ApiResponse)]
+enum CommitApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<CommitView>),
+    /// Empty message, nothing to commit, or not a git repository
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct LogResponse {
+    /// Newest first
+    commits: Vec<CommitView>,
+}
+
+#[derive(ApiResponse)]
+enum LogApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<LogResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct BranchView {
+    name: String,
+
+    /// Whether it is checked out
+    current: bool,
+
+    upstream: Option<String>,
+
+    /// Abbreviated hash of its latest commit
+    short_hash: String,
+
+    /// Subject of its latest commit
+    subject: String,
+}
+
+impl From<BranchInfo> for BranchView {
+    fn from(b: BranchInfo) -> Self {
+        Self { name: b.name, current: b.current, upstream: b.upstream, short_hash: b.short_hash, subject: b.subject }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct BranchesResponse {
+    /// Local branches by name
+    branches: Vec<BranchView>,
+}
+
+#[derive(ApiResponse)]
+enum BranchesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<BranchesResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CreateBranchRequest {
+    /// Name of the new branch
+    ///
+    /// **Required.**
+    name: String,
+
+    /// Commit, branch or tag the branch starts at
+    ///
+    /// **Optional.** Defaults to HEAD.
+    start_point: Option<String>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CheckoutRequest {
+    /// Branch, tag or commit to switch to
+    ///
+    /// **Required.**
+    target: String,
+
+    /// Create `target` as a new branch at HEAD and switch to it
+    ///
+    /// **Optional.** Defaults to false.
+    create: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct StashPushRequest {
+    /// Description of the stash
+    ///
+    /// **Optional.**
+    message: Option<String>,
+
+    /// Stash untracked files too
+    ///
+    /// **Optional.** Defaults to false.
+    include_untracked: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct StashPopRequest {
+    /// Stash to apply and drop; 0 is the latest
+    ///
+    /// **Optional.** Defaults to 0.
+    index: Option<usize>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct StashEntryView {
+    /// Position in the stash list; 0 is the latest
+    index: usize,
+
+    /// e.g. `On main: wip`
+    message: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct StashListResponse {
+    stashes: Vec<StashEntryView>,
+}
+
+#[derive(ApiResponse)]
+enum StashListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<StashListResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct CheckpointView {
+    /// Creation time in Unix milliseconds; pass it to `/checkpoints/restore`
+    id: String,
+
+    /// Hash of the snapshot commit, usable as `rev` in `/diff`
+    hash: String,
+
+    /// What the checkpoint was taken before, e.g. `Before str_replace src/app/page.tsx`
+    label: String,
+
+    /// Galatea session that took it
+    session: Option<String>,
+
+    /// Unix seconds
+    created_at: i64,
+}
+
+impl From<Checkpoint> for CheckpointView {
+    fn from(c: Checkpoint) -> Self {
+        Self { id: c.id, hash: c.hash, label: c.label, session: c.session, created_at: c.created_at }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct CheckpointsResponse {
+    /// Newest first
+    checkpoints: Vec<CheckpointView>,
+}
+
+#[derive(ApiResponse)]
+enum CheckpointsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<CheckpointsResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CreateCheckpointRequest {
+    /// What the checkpoint is for
+    ///
+    /// **Optional.** Defaults to `Checkpoint`.
+    label: Option<String>,
+}
+
+#[derive(ApiResponse)]
+enum CreateCheckpointApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<CheckpointView>),
+    /// Not a git repository
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct RestoreCheckpointRequest {
+    /// Checkpoint to bring back
+    ///
+    /// **Required.**
+    id: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct RestoreCheckpointResponse {
+    /// Id of the checkpoint restored
+    restored: String,
+
+    /// Checkpoint of the files as they were before the restore; restore it to undo this one
+    backup: CheckpointView,
+
+    /// Files created after the checkpoint, which were deleted
+    removed: Vec<String>,
+}
+
+#[derive(ApiResponse)]
+enum RestoreCheckpointApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<RestoreCheckpointResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+// The repository status after an operation, answered by the endpoints that change it
+async fn status_after(root: &std::path::Path, result: anyhow::Result<()>) -> StatusApiResponse {
+    if let Err(e) = result {
+        return StatusApiResponse::BadRequest(PlainText(format!("{:#}", e)));
+    }
+    match git::status(root).await {
+        Ok(status) => StatusApiResponse::Ok(OpenApiJson(status.into())),
+        Err(e) => StatusApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+    }
+}
+
+#[OpenApi]
+impl GitApi {
+    /// Health check endpoint for the Git API
+    ///
+    /// Returns a simple status message to verify that the Git API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn git_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Git API route is healthy".to_string()))
+    }
+
+    /// Show the working tree status
+    ///
+    /// Returns the current branch, how far it is ahead of or behind its upstream, and every
+    /// changed or untracked file with its staged and unstaged change.
+    #[oai(path = "/status", method = "get")]
+    async fn status_handler(&self) -> StatusApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return StatusApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        status_after(&root, Ok(())).await
+    }
+
+    /// Show changes as a unified diff
+    ///
+    /// Unstaged changes by default; `staged=true` shows what the next commit would contain and
+    /// `rev` compares the working tree with a commit, branch or tag (e.g. `HEAD~3`, to review
+    /// what a session changed since then). `path` limits the diff to a file or directory.
+    #[oai(path = "/diff", method = "get")]
+    async fn diff_handler(&self, staged: Query<Option<bool>>, rev: Query<Option<String>>, path: Query<Option<String>>) -> DiffApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return DiffApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let target = match (staged.0.unwrap_or(false), rev.0.as_deref()) {
+            (true, Some(_)) => return DiffApiResponse::BadRequest(PlainText("Pass either 'staged' or 'rev', not both".to_string())),
+            (true, None) => DiffTarget::Staged,
+            (false, Some(rev)) => DiffTarget::Rev(rev),
+            (false, None) => DiffTarget::Worktree,
+        };
+        let paths: Vec<String> = path.0.into_iter().collect();
+        match git::diff(&root, target, &paths).await {
+            Ok((diff, truncated)) => DiffApiResponse::Ok(OpenApiJson(DiffResponse { diff, truncated })),
+            Err(e) => DiffApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Stage changes
+    ///
+    /// Stages the given paths, or every change (new, modified and deleted files) when none are
+    /// given. Returns the status afterwards.
+    #[oai(path = "/stage", method = "post")]
+    async fn stage_handler(&self, req: OpenApiJson<PathsRequest>) -> StatusApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return StatusApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let result = git::stage(&root, &req.0.paths.unwrap_or_default()).await;
+        status_after(&root, result).await
+    }
+
+    /// Unstage changes
+    ///
+    /// Removes the given paths (or everything) from the index, keeping the changes in the
+    /// working tree. Returns the status afterwards.
+    #[oai(path = "/unstage", method = "post")]
+    async fn unstage_handler(&self, req: OpenApiJson<PathsRequest>) -> StatusApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return StatusApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let result = git::unstage(&root, &req.0.paths.unwrap_or_default()).await;
+        status_after(&root, result).await
+    }
+
+    /// Commit staged changes
+    ///
+    /// Commits what is staged, or with `all` every change, as a checkpoint of the work so far.
+    /// The message gets a `Galatea-Session` trailer, so `GET /log?session=current` lists the
+    /// commits of this session. Repositories without a configured author commit as `Galatea`.
+    #[oai(path = "/commit", method = "post")]
+    async fn commit_handler(&self, req: OpenApiJson<CommitRequest>) -> CommitApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return CommitApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        match git::commit(&root, &req.0.message, req.0.all.unwrap_or(false)).await {
+            Ok(commit) => CommitApiResponse::Ok(OpenApiJson(commit.into())),
+            Err(e) => CommitApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// List commits
+    ///
+    /// The latest `limit` commits (default 20, at most 500) of the current branch, newest
+    /// first. `path` keeps the commits touching a file or directory; `session` keeps those
+    /// made through Galatea in a session (`current` for this one).
+    #[oai(path = "/log", method = "get")]
+    async fn log_handler(&self, limit: Query<Option<usize>>, path: Query<Option<String>>, session: Query<Option<String>>) -> LogApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return LogApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let session = session.0.map(|s| if s == "current" { events::session_id().to_string() } else { s });
+        let limit = limit.0.unwrap_or(20).clamp(1, 500);
+        match git::log(&root, limit, path.0.as_deref(), session.as_deref()).await {
+            Ok(commits) => LogApiResponse::Ok(OpenApiJson(LogResponse { commits: commits.into_iter().map(CommitView::from).collect() })),
+            Err(e) => LogApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// List branches
+    ///
+    /// Local branches with their upstream and latest commit; `current` marks the one checked
+    /// out.
+    #[oai(path = "/branches", method = "get")]
+    async fn branches_handler(&self) -> BranchesApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return BranchesApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        match git::branches(&root).await {
+            Ok(branches) => BranchesApiResponse::Ok(OpenApiJson(BranchesResponse { branches: branches.into_iter().map(BranchView::from).collect() })),
+            Err(e) => BranchesApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Create a branch
+    ///
+    /// Creates a branch at `start_point` (HEAD by default) without switching to it; use
+    /// `/checkout` for that. Returns the branches afterwards.
+    #[oai(path = "/branches", method = "post")]
+    async fn create_branch_handler(&self, req: OpenApiJson<CreateBranchRequest>) -> BranchesApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return BranchesApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        if let Err(e) = git::create_branch(&root, &req.0.name, req.0.start_point.as_deref()).await {
+            return BranchesApiResponse::BadRequest(PlainText(format!("{:#}", e)));
+        }
+        self.branches_handler().await
+    }
+
+    /// Switch branches
+    ///
+    /// Checks out a branch, tag or commit, or with `create` a new branch at HEAD. Git refuses
+    /// when uncommitted changes would be overwritten; commit or stash them first. Open
+    /// documents are re-synced with the language server. Returns the status afterwards.
+    #[oai(path = "/checkout", method = "post")]
+    async fn checkout_handler(&self, req: OpenApiJson<CheckoutRequest>) -> StatusApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return StatusApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let result = git::checkout(&root, &req.0.target, req.0.create.unwrap_or(false)).await;
+        status_after(&root, result).await
+    }
+
+    /// List stashes
+    #[oai(path = "/stash", method = "get")]
+    async fn stash_list_handler(&self) -> StashListApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return StashListApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        match git::stash_list(&root).await {
+            Ok(stashes) => StashListApiResponse::Ok(OpenApiJson(StashListResponse {
+                stashes: stashes.into_iter().map(|s| StashEntryView { index: s.index, message: s.message }).collect(),
+            })),
+            Err(e) => StashListApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Stash uncommitted changes
+    ///
+    /// Saves the uncommitted changes (with `include_untracked`, new files too) and cleans the
+    /// working tree. Returns the status afterwards; stashing a clean tree does nothing.
+    #[oai(path = "/stash", method = "post")]
+    async fn stash_push_handler(&self, req: OpenApiJson<StashPushRequest>) -> StatusApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return StatusApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let result = git::stash_push(&root, req.0.message.as_deref(), req.0.include_untracked.unwrap_or(false)).await;
+        status_after(&root, result.map(drop)).await
+    }
+
+    /// Restore stashed changes
+    ///
+    /// Applies a stash to the working tree and drops it. On conflicts the stash is kept and a
+    /// 400 explains what collided. Returns the status afterwards.
+    #[oai(path = "/stash/pop", method = "post")]
+    async fn stash_pop_handler(&self, req: OpenApiJson<StashPopRequest>) -> StatusApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return StatusApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let result = git::stash_pop(&root, req.0.index.unwrap_or(0)).await;
+        status_after(&root, result).await
+    }
+
+    /// List checkpoints
+    ///
+    /// Snapshots of the project's files, newest first. Galatea takes one automatically before
+    /// the first editor change of an edit session and before every change to several files
+    /// (refactors, lint fixes, formatting); see `[checkpoints]` in config.toml.
+    #[oai(path = "/checkpoints", method = "get")]
+    async fn checkpoints_handler(&self) -> CheckpointsApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return CheckpointsApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        match checkpoints::list(&root).await {
+            Ok(list) => CheckpointsApiResponse::Ok(OpenApiJson(CheckpointsResponse { checkpoints: list.into_iter().map(CheckpointView::from).collect() })),
+            Err(e) => CheckpointsApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Create a checkpoint
+    ///
+    /// Snapshots every file git doesn't ignore, untracked ones included, without committing to
+    /// the current branch or touching the index. Checkpoints live under `refs/galatea/checkpoints`
+    /// and only the latest `max` are kept. When nothing changed since the latest checkpoint,
+    /// that one is returned.
+    #[oai(path = "/checkpoints", method = "post")]
+    async fn create_checkpoint_handler(&self, req: OpenApiJson<CreateCheckpointRequest>) -> CreateCheckpointApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return CreateCheckpointApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        match checkpoints::create(&root, req.0.label.as_deref().unwrap_or("Checkpoint")).await {
+            Ok(checkpoint) => CreateCheckpointApiResponse::Ok(OpenApiJson(checkpoint.into())),
+            Err(e) => CreateCheckpointApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Restore a checkpoint
+    ///
+    /// Rewrites the project's files as they were at the checkpoint and deletes the files
+    /// created since, across any number of edits, unlike the editor's `undo_edit`. Ignored
+    /// files (`node_modules`, `.env`), the branch and the index are left alone. The current
+    /// files are checkpointed first and returned as `backup`, so the restore can be undone.
+    #[oai(path = "/checkpoints/restore", method = "post")]
+    async fn restore_checkpoint_handler(&self, req: OpenApiJson<RestoreCheckpointRequest>) -> RestoreCheckpointApiResponse {
+        let root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return RestoreCheckpointApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        match checkpoints::list(&root).await {
+            Ok(list) if !list.iter().any(|c| c.id == req.0.id) => {
+                return RestoreCheckpointApiResponse::NotFound(PlainText(format!("No checkpoint '{}'", req.0.id)))
+            }
+            Ok(_) => {}
+            Err(e) => return RestoreCheckpointApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+        match checkpoints::restore(&root, &req.0.id).await {
+            Ok(restored) => RestoreCheckpointApiResponse::Ok(OpenApiJson(RestoreCheckpointResponse {
+                restored: restored.checkpoint.id,
+                backup: restored.backup.into(),
+                removed: restored.removed,
+            })),
+            Err(e) => RestoreCheckpointApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        }
+    }
+}
+
+pub fn git_routes() -> Route {
+    let api_service = OpenApiService::new(GitApi, "Git API", "1.0").server("/api/git");
+    Route::new().nest("/", api_service)
+}
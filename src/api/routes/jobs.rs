@@ -0,0 +1,202 @@
+use poem::Route;
+use poem_openapi::{
+    param::Path as OpenApiPath,
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+
+use crate::api::error::GalateaError;
+use crate::dev_runtime::jobs::{self, Job, JobProgress};
+
+// Define an API struct
+pub struct JobsApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct JobProgressView {
+    /// Steps done
+    done: u64,
+
+    /// Steps in all; absent when the job can't tell
+    total: Option<u64>,
+
+    /// What the job is doing, e.g. `Parsing files`
+    message: Option<String>,
+}
+
+impl From<JobProgress> for JobProgressView {
+    fn from(progress: JobProgress) -> Self {
+        Self { done: progress.done, total: progress.total, message: progress.message }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct JobView {
+    /// Job id
+    id: String,
+
+    /// What started the job: `script`, `index` (building the code index) or `mcp_generation`
+    kind: String,
+
+    /// The command line the job runs, or what an in-process job does
+    command: String,
+
+    /// `queued`, `running`, `succeeded`, `failed`, `cancelled`, or `interrupted` when
+    /// Galatea stopped before the job finished
+    status: String,
+
+    /// Unix timestamp (seconds) the job was started at, or while queued, submitted at
+    started_at: u64,
+
+    /// Unix timestamp (seconds) the process exited at; absent while running
+    finished_at: Option<u64>,
+
+    /// Exit code; absent while running or when the process was killed by a signal
+    exit_code: Option<i32>,
+
+    /// Run time in milliseconds; absent while running
+    duration_ms: Option<u64>,
+
+    /// Standard output so far. Only the last 256 KB are kept.
+    stdout: String,
+
+    /// Standard error so far. Only the last 256 KB are kept.
+    stderr: String,
+
+    /// How far an in-process job has got
+    progress: Option<JobProgressView>,
+
+    /// Why an in-process job failed or was interrupted
+    error: Option<String>,
+}
+
+impl From<Job> for JobView {
+    fn from(job: Job) -> Self {
+        Self {
+            id: job.id,
+            kind: job.kind,
+            command: job.description,
+            status: job.status.as_str().to_string(),
+            started_at: job.started_at,
+            finished_at: job.finished_at,
+            exit_code: job.exit_code,
+            duration_ms: job.duration_ms,
+            stdout: job.stdout,
+            stderr: job.stderr,
+            progress: job.progress.map(JobProgressView::from),
+            error: job.error,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct JobSummaryView {
+    /// Job id
+    id: String,
+
+    /// What started the job: `script`, `index` (building the code index) or `mcp_generation`
+    kind: String,
+
+    /// The command line the job runs, or what an in-process job does
+    command: String,
+
+    /// `queued`, `running`, `succeeded`, `failed`, `cancelled`, or `interrupted` when
+    /// Galatea stopped before the job finished
+    status: String,
+
+    /// Unix timestamp (seconds) the job was started at, or while queued, submitted at
+    started_at: u64,
+
+    /// Exit code; absent while running or when the process was killed by a signal
+    exit_code: Option<i32>,
+
+    /// How far an in-process job has got
+    progress: Option<JobProgressView>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct JobListResponse {
+    /// Running jobs and the most recent finished ones, newest first
+    jobs: Vec<JobSummaryView>,
+}
+
+#[derive(ApiResponse)]
+enum JobListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<JobListResponse>),
+}
+
+#[derive(ApiResponse)]
+enum JobApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<JobView>),
+}
+
+#[OpenApi]
+impl JobsApi {
+    /// Health check endpoint for the Jobs API
+    ///
+    /// Returns a simple status message to verify that the Jobs API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn jobs_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Jobs API route is healthy".to_string()))
+    }
+
+    /// List background jobs
+    ///
+    /// Returns queued and running jobs and the most recently finished ones, newest first,
+    /// without their output. Scripts run as jobs when started by `POST /api/editor/script`
+    /// with `background: true`; building the code index at startup and generating MCP servers
+    /// always do. Two in-process jobs run at once and the others wait as `queued`. Jobs are
+    /// kept in the metadata database across restarts, with the last 16 KB of their output.
+    #[oai(path = "/", method = "get")]
+    async fn list_jobs_handler(&self) -> JobListApiResponse {
+        let jobs = jobs::list()
+            .into_iter()
+            .map(|job| JobSummaryView {
+                id: job.id,
+                kind: job.kind,
+                command: job.description,
+                status: job.status.as_str().to_string(),
+                started_at: job.started_at,
+                exit_code: job.exit_code,
+                progress: job.progress.map(JobProgressView::from),
+            })
+            .collect();
+        JobListApiResponse::Ok(OpenApiJson(JobListResponse { jobs }))
+    }
+
+    /// Get a background job's status and output
+    ///
+    /// Poll this until `status` is no longer `queued` or `running`. Output is collected as the
+    /// process prints it, so a running job shows everything it has printed so far; in-process
+    /// jobs report `progress` instead.
+    #[oai(path = "/:id", method = "get")]
+    async fn get_job_handler(&self, id: OpenApiPath<String>) -> Result<JobApiResponse, GalateaError> {
+        let job = jobs::get(&id.0).ok_or_else(|| GalateaError::NotFound(format!("No job with id {}", id.0)))?;
+        Ok(JobApiResponse::Ok(OpenApiJson(job.into())))
+    }
+
+    /// Cancel a background job
+    ///
+    /// Kills the job's process and every process it started (e.g. the `next build` under
+    /// `pnpm run build`): they get SIGTERM, then SIGKILL if they are still running 5 seconds
+    /// later. An in-process job stops at its next step, and a queued one never starts. The
+    /// job is marked `cancelled` right away. Cancelling a finished job changes nothing and
+    /// returns it as it is.
+    #[oai(path = "/:id", method = "delete")]
+    async fn cancel_job_handler(&self, id: OpenApiPath<String>) -> Result<JobApiResponse, GalateaError> {
+        let job = jobs::cancel(&id.0).ok_or_else(|| GalateaError::NotFound(format!("No job with id {}", id.0)))?;
+        Ok(JobApiResponse::Ok(OpenApiJson(job.into())))
+    }
+}
+
+pub fn jobs_routes() -> Route {
+    let api_service = OpenApiService::new(JobsApi, "Jobs API", "1.0").server("/api/jobs");
+    Route::new().nest("/", api_service)
+}
@@ -1,6 +1,23 @@
-use poem::{Route, get, handler, post, web::Json, http::StatusCode, Error as PoemError};
+use poem::{Route, get, handler, post, web::Json, http::StatusCode, Error as PoemError, IntoResponse};
+use poem::web::websocket::{Message, WebSocket, WebSocketStream};
+use poem::web::Query as PoemQuery;
 use crate::api::models::{GetLogsRequest, GetLogsResponse, ClearLogsResponse};
 use crate::dev_runtime::log::{get_shared_logs, clear_shared_logs, LogFilterOptions};
+use crate::dev_runtime::audit::{self, AuditEntry, AuditFilter};
+use crate::dev_runtime::log_hub::{self, HubEntry, LogQuery, SourceStats};
+use futures::stream::{self, BoxStream, StreamExt};
+use futures::SinkExt;
+use poem_openapi::{
+    param::Query,
+    payload::{EventStream, Json as OpenApiJson, PlainText},
+    types::ToJSON,
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+const DEFAULT_TAIL: usize = 200;
+const MAX_TAIL: usize = 2000;
+const DEFAULT_STREAM_TAIL: usize = 50;
 
 #[poem::handler]
 async fn logs_api_health() -> &'static str {
@@ -61,4 +78,437 @@ pub fn logs_routes() -> Route {
         .at("/health", get(logs_api_health))
         .at("/get", post(get_logs_api_handler))
         .at("/clear", post(clear_logs_api_handler))
-} 
\ No newline at end of file
+} 
+// Define an API struct
+pub struct LogsApi;
+
+#[derive(ApiResponse)]
+enum LogsHealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct LogEntryView {
+    /// Position across all sources; pass the last one seen as `after` to page forward
+    seq: u64,
+
+    /// Unix milliseconds
+    timestamp_ms: u64,
+
+    /// `galatea`, `nextjs_dev_server`, `lsp`, `scripts` or `mcp:<server id>`
+    source: String,
+
+    /// `stdout` or `stderr` for a child process's output, `event` for a message Galatea logged
+    stream: String,
+
+    /// `error`, `warn`, `info`, `debug` or `trace`. For child output it is guessed from the line.
+    level: String,
+
+    /// Tracing target of a `galatea` event
+    target: Option<String>,
+
+    message: String,
+}
+
+impl From<HubEntry> for LogEntryView {
+    fn from(e: HubEntry) -> Self {
+        Self {
+            seq: e.seq,
+            timestamp_ms: e.timestamp_ms,
+            source: e.source,
+            stream: e.stream.as_str().to_string(),
+            level: log_hub::level_name(e.level).to_string(),
+            target: e.target,
+            message: e.message,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct LogTailResponse {
+    /// Matching entries, oldest first
+    entries: Vec<LogEntryView>,
+
+    /// `seq` of the last entry, for the next request's `after`; echoes `after` when nothing matched
+    next_after: Option<u64>,
+}
+
+#[derive(ApiResponse)]
+enum LogTailApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<LogTailResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct LogSourceView {
+    name: String,
+
+    /// Entries held now
+    entries: usize,
+
+    /// Entries dropped to stay within `[logs] per_source_capacity`
+    dropped: u64,
+
+    /// `seq` of the newest entry
+    last_seq: u64,
+}
+
+impl From<SourceStats> for LogSourceView {
+    fn from(s: SourceStats) -> Self {
+        Self { name: s.name, entries: s.entries, dropped: s.dropped, last_seq: s.last_seq }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct LogSourcesResponse {
+    sources: Vec<LogSourceView>,
+}
+
+#[derive(ApiResponse)]
+enum LogSourcesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<LogSourcesResponse>),
+}
+
+#[derive(ApiResponse)]
+enum LogStreamApiResponse {
+    #[oai(status = 200)]
+    Ok(EventStream<BoxStream<'static, LogEntryView>>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+}
+
+// Filters shared by the tail and the stream
+fn log_query(source: Option<String>, level: Option<String>, since: Option<u64>, after: Option<u64>, contains: Option<String>) -> Result<LogQuery, String> {
+    let min_level = match level {
+        Some(name) => Some(log_hub::parse_level(&name).ok_or_else(|| format!("Unknown level '{}'; use error, warn, info, debug or trace", name))?),
+        None => None,
+    };
+    Ok(LogQuery {
+        sources: source
+            .map(|s| s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default(),
+        min_level,
+        since_ms: since,
+        after_seq: after,
+        contains: contains.filter(|c| !c.is_empty()),
+    })
+}
+
+#[derive(Object, serde::Serialize)]
+struct AuditEntryView {
+    /// When the call arrived (Unix seconds)
+    timestamp: u64,
+
+    /// Who made the call: `token:<name>` for a named API token (`[api_auth.tokens]` in
+    /// config.toml), `key:<hash>` for an unnamed one, `loopback` or `anonymous`
+    principal: String,
+
+    /// Client session from the `x-galatea-session` header
+    client_session: Option<String>,
+
+    /// Dotted operation name, e.g. `editor.command.str_replace`, `editor.script` or `git.commit`
+    operation: String,
+
+    method: String,
+
+    path: String,
+
+    /// Root of the workspace the call was scoped to, absent for the default project
+    workspace: Option<String>,
+
+    /// Body and query parameters. Values of keys that look like secrets are masked and strings
+    /// over 500 characters (file contents, patches) are cut.
+    params: serde_json::Value,
+
+    /// HTTP status of the response
+    status: u16,
+
+    /// Start of the response body when `status` is 400 or above
+    error: Option<String>,
+
+    duration_ms: u64,
+}
+
+impl From<AuditEntry> for AuditEntryView {
+    fn from(e: AuditEntry) -> Self {
+        Self {
+            timestamp: e.timestamp,
+            principal: e.principal,
+            client_session: e.client_session,
+            operation: e.operation,
+            method: e.method,
+            path: e.path,
+            workspace: e.workspace,
+            params: e.params,
+            status: e.status,
+            error: e.error,
+            duration_ms: e.duration_ms,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct AuditLogResponse {
+    /// Matching entries, newest first
+    entries: Vec<AuditEntryView>,
+
+    count: usize,
+}
+
+#[derive(ApiResponse)]
+enum AuditLogApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<AuditLogResponse>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[OpenApi]
+impl LogsApi {
+    /// Health check endpoint for the Logs API
+    ///
+    /// Returns a simple status message to verify that the Logs API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn logs_health(&self) -> LogsHealthResponse {
+        LogsHealthResponse::Ok(PlainText("Logs API route is healthy".to_string()))
+    }
+
+    /// Tail the runtime logs
+    ///
+    /// Galatea keeps the recent output of the services it runs in memory, one ring buffer per
+    /// source: `nextjs_dev_server` (the dev server's stdout and stderr), `mcp:<server id>` (each
+    /// MCP server's output), `lsp` (language server traffic and stderr), `scripts` and
+    /// `galatea` (Galatea's own log events). Returns the last `limit` entries (default 200, at
+    /// most 2000) matching every filter given, oldest first:
+    /// - `source`: comma-separated source names; `mcp` covers every MCP server
+    /// - `level`: least severe level kept, e.g. `warn` for warnings and errors
+    /// - `since`: Unix milliseconds
+    /// - `after`: only entries after this `seq`; poll with the previous `next_after` to follow
+    /// - `contains`: case-insensitive text the message must contain
+    ///
+    /// Each source keeps `[logs] per_source_capacity` entries (default 2000) in config.toml;
+    /// `/sources` reports how many were dropped.
+    #[oai(path = "/", method = "get")]
+    async fn log_tail_handler(
+        &self,
+        source: Query<Option<String>>,
+        level: Query<Option<String>>,
+        since: Query<Option<u64>>,
+        after: Query<Option<u64>>,
+        contains: Query<Option<String>>,
+        limit: Query<Option<usize>>,
+    ) -> LogTailApiResponse {
+        let query = match log_query(source.0, level.0, since.0, after.0, contains.0) {
+            Ok(query) => query,
+            Err(e) => return LogTailApiResponse::BadRequest(PlainText(e)),
+        };
+        let entries = log_hub::query(&query, limit.0.unwrap_or(DEFAULT_TAIL).clamp(1, MAX_TAIL));
+        LogTailApiResponse::Ok(OpenApiJson(LogTailResponse {
+            next_after: entries.last().map(|e| e.seq).or(after.0),
+            entries: entries.into_iter().map(LogEntryView::from).collect(),
+        }))
+    }
+
+    /// List log sources
+    ///
+    /// Every source that has logged since Galatea started, with how many entries it holds and
+    /// how many it dropped.
+    #[oai(path = "/sources", method = "get")]
+    async fn log_sources_handler(&self) -> LogSourcesApiResponse {
+        LogSourcesApiResponse::Ok(OpenApiJson(LogSourcesResponse {
+            sources: log_hub::sources().into_iter().map(LogSourceView::from).collect(),
+        }))
+    }
+
+    /// Stream the runtime logs
+    ///
+    /// Server-Sent Events with one `log` event per entry, taking the same filters as the tail.
+    /// The last `tail` matching entries (default 50, at most 2000; `0` for none) are sent
+    /// first, then new entries as they are logged. A client that falls too far behind skips
+    /// the entries it missed rather than blocking the services. `/api/logs/ws` streams the
+    /// same entries over a WebSocket, where the filters can be changed without reconnecting.
+    ///
+    /// ## Example stream:
+    /// ```text
+    /// event: log
+    /// data: {"seq":1042,"timestamp_ms":1760000000000,"source":"nextjs_dev_server","stream":"stdout","level":"info","message":" ✓ Compiled in 312ms"}
+    /// ```
+    #[oai(path = "/stream", method = "get")]
+    async fn log_stream_handler(
+        &self,
+        source: Query<Option<String>>,
+        level: Query<Option<String>>,
+        since: Query<Option<u64>>,
+        contains: Query<Option<String>>,
+        tail: Query<Option<usize>>,
+    ) -> LogStreamApiResponse {
+        let query = match log_query(source.0, level.0, since.0, None, contains.0) {
+            Ok(query) => query,
+            Err(e) => return LogStreamApiResponse::BadRequest(PlainText(e)),
+        };
+        // Subscribe before reading the backlog, so nothing logged in between is missed
+        let live = log_hub::subscribe();
+        let backlog = match tail.0.unwrap_or(DEFAULT_STREAM_TAIL).min(MAX_TAIL) {
+            0 => Vec::new(),
+            n => log_hub::query(&query, n),
+        };
+        let replayed = backlog.last().map_or(0, |e| e.seq);
+        let live = stream::unfold((live, query), move |(mut live, query)| async move {
+            loop {
+                match live.recv().await {
+                    Ok(entry) if entry.seq > replayed && query.matches(&entry) => return Some((entry, (live, query))),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+        let events = stream::iter(backlog).chain(live).map(LogEntryView::from).boxed();
+        LogStreamApiResponse::Ok(
+            EventStream::new(events)
+                .keep_alive(std::time::Duration::from_secs(15))
+                .to_event(|entry| poem::web::sse::Event::message(entry.to_json_string()).event_type("log")),
+        )
+    }
+
+    /// Query the audit log
+    ///
+    /// Every state-changing API call (editor mutations, script runs, galatea-file updates, git
+    /// operations and anything else a `read`-scoped token may not do) is recorded with who made
+    /// it, its parameters and its outcome, in the `audit_log` table of the metadata database.
+    /// `operation` matches a prefix of dotted names, so `git` covers `git.commit` and
+    /// `git.stash.pop`. `failed=true` keeps calls that answered with status 400 or above.
+    /// `limit` defaults to 100, at most 1000. Needs an `admin` token once API tokens are
+    /// configured.
+    #[oai(path = "/audit", method = "get")]
+    async fn audit_log_handler(
+        &self,
+        since: Query<Option<u64>>,
+        until: Query<Option<u64>>,
+        principal: Query<Option<String>>,
+        operation: Query<Option<String>>,
+        failed: Query<Option<bool>>,
+        limit: Query<Option<usize>>,
+    ) -> AuditLogApiResponse {
+        let filter = AuditFilter {
+            since: since.0,
+            until: until.0,
+            principal: principal.0,
+            operation: operation.0,
+            failed: failed.0,
+            limit: limit.0,
+        };
+        match audit::query(&filter) {
+            Ok(entries) => AuditLogApiResponse::Ok(OpenApiJson(AuditLogResponse {
+                count: entries.len(),
+                entries: entries.into_iter().map(AuditEntryView::from).collect(),
+            })),
+            Err(e) => AuditLogApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct LogsWsParams {
+    source: Option<String>,
+    level: Option<String>,
+    since: Option<u64>,
+    contains: Option<String>,
+    tail: Option<usize>,
+}
+
+// Sent by the client to replace its filters; fields left out are cleared
+#[derive(serde::Deserialize)]
+struct LogsWsFilter {
+    source: Option<String>,
+    level: Option<String>,
+    contains: Option<String>,
+}
+
+// Sent when the client fell behind or sent something that isn't a filter
+#[derive(serde::Serialize)]
+struct LogsWsNotice {
+    r#type: &'static str,
+    message: String,
+}
+
+fn entry_message(entry: HubEntry) -> Message {
+    Message::text(serde_json::to_string(&LogEntryView::from(entry)).unwrap_or_default())
+}
+
+fn notice_message(r#type: &'static str, message: String) -> Message {
+    Message::text(serde_json::to_string(&LogsWsNotice { r#type, message }).unwrap_or_default())
+}
+
+/// Stream the runtime logs over a WebSocket.
+///
+/// Served at `/api/logs/ws` with the query parameters of `/api/logs/stream`. Each entry is sent
+/// as a JSON text message shaped like the entries of `/api/logs`, after the last `tail` matching
+/// ones. Sending `{"source":"nextjs_dev_server,mcp","level":"warn"}` replaces the filters
+/// (`source`, `level`, `contains`) for the entries that follow, so a console can switch between
+/// services on one connection. A client too slow to keep up receives `{"type":"lagged",...}`;
+/// an unusable filter gets `{"type":"error",...}` and leaves the current one in place.
+#[handler]
+pub async fn logs_ws_handler(PoemQuery(params): PoemQuery<LogsWsParams>, ws: WebSocket) -> poem::Result<impl IntoResponse> {
+    let query = log_query(params.source, params.level, params.since, None, params.contains)
+        .map_err(|e| PoemError::from_string(e, StatusCode::BAD_REQUEST))?;
+    let tail = params.tail.unwrap_or(DEFAULT_STREAM_TAIL).min(MAX_TAIL);
+    Ok(ws.on_upgrade(move |socket| stream_logs(socket, query, tail)))
+}
+
+async fn stream_logs(socket: WebSocketStream, mut query: LogQuery, tail: usize) {
+    let (mut sink, mut stream) = socket.split();
+    // Subscribe before reading the backlog, so nothing logged in between is missed
+    let mut live = log_hub::subscribe();
+    let backlog = if tail == 0 { Vec::new() } else { log_hub::query(&query, tail) };
+    let replayed = backlog.last().map_or(0, |e| e.seq);
+    for entry in backlog {
+        if sink.send(entry_message(entry)).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        tokio::select! {
+            entry = live.recv() => match entry {
+                Ok(entry) if entry.seq > replayed && query.matches(&entry) => {
+                    if sink.send(entry_message(entry)).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    let message = format!("Fell behind; {} log entries were skipped. Fetch them from /api/logs?after=", skipped);
+                    if sink.send(notice_message("lagged", message)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let filter = serde_json::from_str::<LogsWsFilter>(&text)
+                        .map_err(|e| format!("Expected a filter like {{\"source\":\"mcp\",\"level\":\"warn\"}}: {}", e))
+                        .and_then(|f| log_query(f.source, f.level, query.since_ms, None, f.contains));
+                    match filter {
+                        Ok(filter) => query = filter,
+                        Err(e) => {
+                            if sink.send(notice_message("error", e)).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+pub fn logs_api_routes() -> Route {
+    let api_service = OpenApiService::new(LogsApi, "Logs API", "1.0").server("/api/logs");
+    Route::new().at("/ws", get(logs_ws_handler)).nest("/", api_service)
+}
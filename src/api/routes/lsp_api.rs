@@ -1,104 +1,1355 @@
-use poem::{Route, get, handler, post, web::{Json, Data}, http::StatusCode, Error as PoemError};
-use anyhow::Result;
+use poem::{web::Data, EndpointExt, Route};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use lsp_types;
+use poem_openapi::{param::Path as OpenApiPath, payload::{Json as OpenApiJson, PlainText}, ApiResponse, Object, OpenApi, OpenApiService};
 
-use crate::api::models::{GotoDefinitionApiRequest, GotoDefinitionApiResponse};
+use crate::codebase_indexing::index_manager::{self, IndexStats};
+use crate::codebase_indexing::parser::CodeEntity;
+use crate::codebase_indexing::profiles;
+use crate::dev_operation::entity_search::{self, EntityQuery};
+use crate::dev_operation::language_features::{self, RangeInfo};
+use crate::dev_operation::symbols::{self, SymbolInfo};
+use crate::api::error::GalateaError;
+use crate::dev_runtime::capabilities::{self, Capability};
 use crate::dev_runtime::lsp_client::LspClient;
-use crate::file_system::{resolve_path, resolve_path_to_uri};
-
-#[handler]
-async fn lsp_api_health() -> &'static str {
-    "LSP API route is healthy"
-}
-
-#[handler]
-pub async fn lsp_goto_definition_api_handler(
-    lsp_client_data: Data<&Arc<Mutex<LspClient>>>,
-    Json(req): Json<GotoDefinitionApiRequest>,
-) -> Result<Json<GotoDefinitionApiResponse>, PoemError> {
-    let resolved_file_path = match resolve_path(&req.uri) {
-        Ok(p) => p,
-        Err(e) => {
-            return Err(PoemError::from_string(
-                format!(
-                    "Failed to resolve input path/URI '{}' to a project file: {}",
-                    req.uri,
-                    e.to_string()
-                ),
-                StatusCode::BAD_REQUEST,
-            ));
+use crate::dev_runtime::lsp_pool::{self, LspPool};
+use crate::dev_runtime::lsp_trace::{self, LspTrace, LspTraceMessage};
+use crate::file_system::paths::get_project_root;
+use crate::file_system::resolve_path;
+use crate::file_system::ranking::{self, Ranked};
+use crate::file_system::watcher;
+
+// Define an API struct
+pub struct LspApi;
+
+#[derive(Object, serde::Deserialize)]
+struct WorkspaceSymbolsRequest {
+    /// Symbol name query
+    ///
+    /// **Required.** Case-insensitive substring matched against symbol names.
+    /// An empty string matches every symbol (subject to `limit`).
+    query: String,
+
+    /// Maximum number of symbols to return
+    ///
+    /// **Optional.** Defaults to 200.
+    limit: Option<usize>,
+
+    /// Capture the JSON-RPC messages exchanged with the language server
+    ///
+    /// **Optional.** When true, the conversation is attached to the response as `lsp_trace`
+    /// (message bodies truncated) and kept for `GET /traces/{trace_id}`. Defaults to
+    /// `lsp_debug = "true"` in config.toml.
+    debug: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct DocumentSymbolsRequest {
+    /// Path to the file to list symbols for
+    ///
+    /// **Required.** Can be absolute, relative to the project root, or a partial path
+    /// (e.g., `src/app/page.tsx`, `page.tsx`).
+    path: String,
+
+    /// Capture the JSON-RPC messages exchanged with the language server
+    ///
+    /// **Optional.** When true, the conversation is attached to the response as `lsp_trace`
+    /// (message bodies truncated) and kept for `GET /traces/{trace_id}`. Defaults to
+    /// `lsp_debug = "true"` in config.toml.
+    debug: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct GotoDefinitionRequest {
+    /// Path to the file containing the reference
+    ///
+    /// **Required.** Can be absolute, relative to the project root, or a partial path.
+    path: String,
+
+    /// Line of the reference (0-indexed, as in LSP)
+    line: u32,
+
+    /// Character offset within the line (0-indexed, as in LSP)
+    character: u32,
+
+    /// Capture the JSON-RPC messages exchanged with the language server
+    ///
+    /// **Optional.** When true, the conversation is attached to the response as `lsp_trace`
+    /// (message bodies truncated) and kept for `GET /traces/{trace_id}`. Defaults to
+    /// `lsp_debug = "true"` in config.toml.
+    debug: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct EntitySearchRequest {
+    /// Search query: free-text terms plus `key:value` filters
+    ///
+    /// **Required.** Terms are matched against identifiers in the entity's code, split on
+    /// camelCase and snake_case (`user` matches `fetchUserData`); `"quoted phrases"` match
+    /// literally. Filters:
+    /// - `kind:function|class|interface|method|variable|constant|struct|trait|module|hook|component`
+    /// - `exported:true|false`
+    /// - `lang:ts|tsx|rs`
+    /// - `name:<substring>`
+    /// - `path:<glob>` (e.g. `path:src/hooks`, `path:*.tsx`)
+    /// - `calls:<identifier>`: the entity calls it
+    ///
+    /// Example: `kind:hook exported:true calls:fetch` finds exported hooks that call fetch.
+    query: String,
+
+    /// Maximum number of entities to return
+    ///
+    /// **Optional.** Defaults to 50.
+    limit: Option<usize>,
+
+    /// Analysis profile to search with
+    ///
+    /// **Optional.** Name of an `[analysis_profiles.<name>]` table in config.toml; its
+    /// languages, include globs and excluded directories narrow the searched files.
+    /// Defaults to the workspace's `analysis_profile`, if set.
+    profile: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct EntityMatchLine {
+    /// Line number (1-indexed)
+    line: usize,
+
+    /// Trimmed line text
+    text: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct EntitySearchItem {
+    /// Entity name
+    name: String,
+
+    /// Entity kind as indexed (`Function`, `Class`, `Variable`, ...)
+    kind: String,
+
+    /// Name of the enclosing class, interface or impl, if any
+    container_name: Option<String>,
+
+    /// File path relative to the project root
+    path: String,
+
+    /// File language (`ts`, `tsx`, `rs`)
+    lang: String,
+
+    /// Whether the entity is exported (`export` in TypeScript, `pub` in Rust)
+    exported: bool,
+
+    /// Line where the entity starts (1-indexed)
+    line: usize,
+
+    /// Line where the entity ends (1-indexed)
+    line_to: usize,
+
+    /// Lines of the entity that matched the terms, phrases or `calls:` filters (at most 5)
+    matches: Vec<EntityMatchLine>,
+
+    /// Ranking score; results are sorted by it, highest first
+    score: f64,
+
+    /// How the score was computed, one entry per ranking signal
+    score_explanation: Vec<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct EntitySearchResponse {
+    /// Matching entities, best first
+    entities: Vec<EntitySearchItem>,
+
+    /// Number of entities returned
+    total: usize,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct IndexedEntitiesRequest {
+    /// Path to the file to list entities for
+    ///
+    /// **Required.** Can be absolute, relative to the project root, or a partial path
+    /// (e.g., `src/app/page.tsx`, `page.tsx`).
+    path: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct IndexedEntityItem {
+    /// Entity name
+    name: String,
+
+    /// Entity kind as indexed (`Function`, `Class`, `Import`, ...)
+    kind: String,
+
+    /// Declaration line, e.g. `export function useUser(id: string)`
+    signature: String,
+
+    /// Doc comment, if any
+    docstring: Option<String>,
+
+    /// Name of the enclosing class, interface or impl, if any
+    container_name: Option<String>,
+
+    /// Line of the declaration (1-indexed)
+    line: usize,
+
+    /// Line where the entity starts, including its doc comment (1-indexed)
+    line_from: usize,
+
+    /// Line where the entity ends (1-indexed)
+    line_to: usize,
+
+    /// Source code of the entity
+    snippet: String,
+}
+
+impl From<&CodeEntity> for IndexedEntityItem {
+    fn from(entity: &CodeEntity) -> Self {
+        Self {
+            name: entity.name.clone(),
+            kind: entity.code_type.clone(),
+            signature: entity.signature.clone(),
+            docstring: entity.docstring.clone(),
+            container_name: entity.context.struct_name.clone(),
+            line: entity.line,
+            line_from: entity.line_from,
+            line_to: entity.line_to,
+            snippet: entity.context.snippet.clone(),
         }
-    };
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct IndexedEntitiesResponse {
+    /// File path relative to the project root
+    path: String,
+
+    /// Entities of the file, in source order
+    entities: Vec<IndexedEntityItem>,
+}
 
-    let file_uri = match resolve_path_to_uri(&req.uri) {
-        Ok(uri) => uri,
-        Err(e) => {
-            return Err(PoemError::from_string(
-                format!("Failed to resolve input path/URI '{}' to a project file: {}", req.uri, e.to_string()),
-                StatusCode::BAD_REQUEST,
-            ));
+#[derive(ApiResponse)]
+enum IndexedEntitiesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<IndexedEntitiesResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct IndexStatusResponse {
+    /// Whether the first full scan of the project has finished
+    ready: bool,
+
+    /// Number of indexed files
+    files: usize,
+
+    /// Number of indexed entities
+    entities: usize,
+
+    /// Unix timestamp (seconds) of the last change to the index, 0 before the first
+    last_updated: u64,
+
+    /// Whether file changes are applied as they happen; otherwise queries rescan the project
+    watching: bool,
+}
+
+impl From<IndexStats> for IndexStatusResponse {
+    fn from(stats: IndexStats) -> Self {
+        Self {
+            ready: stats.ready,
+            files: stats.files,
+            entities: stats.entities,
+            last_updated: stats.last_updated,
+            watching: watcher::is_watching(),
         }
-    };
-    
-    let file_content = match std::fs::read_to_string(&resolved_file_path) {
-        Ok(content) => content,
-        Err(e) => {
-            return Err(PoemError::from_string(
-                format!(
-                    "Failed to read file for LSP didOpen '{}': {}",
-                    resolved_file_path.display(),
-                    e
-                ),
-                StatusCode::INTERNAL_SERVER_ERROR,
-            ));
+    }
+}
+
+#[derive(ApiResponse)]
+enum IndexStatusApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<IndexStatusResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct DefinitionLocation {
+    /// File path relative to the project root
+    path: String,
+
+    /// Line where the definition starts (0-indexed, as in LSP)
+    line: u32,
+
+    /// Character offset where the definition starts (0-indexed, as in LSP)
+    character: u32,
+}
+
+#[derive(Object, serde::Serialize)]
+struct GotoDefinitionResponse {
+    /// Definition locations reported by the language server
+    locations: Vec<DefinitionLocation>,
+
+    /// Id of the captured LSP trace, for `GET /traces/{trace_id}`
+    ///
+    /// Only set when `debug` was requested.
+    trace_id: Option<String>,
+
+    /// JSON-RPC messages exchanged with the language server during this call
+    ///
+    /// Only set when `debug` was requested.
+    lsp_trace: Option<Vec<LspTraceEntry>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SymbolItem {
+    /// Symbol name
+    name: String,
+
+    /// Symbol kind
+    ///
+    /// PascalCase kind name such as `Function`, `Class`, `Interface`, `Method` or `Struct`.
+    /// The same names are used regardless of which backend answered.
+    kind: String,
+
+    /// Name of the enclosing symbol (class, impl, interface), if any
+    container_name: Option<String>,
+
+    /// File path relative to the project root
+    path: String,
+
+    /// Line where the symbol starts (1-indexed)
+    line: usize,
+
+    /// Line where the symbol ends (1-indexed)
+    line_to: usize,
+
+    /// Ranking score; results are sorted by it, highest first
+    ///
+    /// Only set for workspace symbol searches. Weights are tunable in the `[search_ranking]`
+    /// section of config.toml.
+    score: Option<f64>,
+
+    /// How the score was computed, one entry per ranking signal
+    ///
+    /// e.g. `path_priority 1.00×0.50 = 0.500 (source file)`. Only set alongside `score`.
+    score_explanation: Option<Vec<String>>,
+}
+
+impl From<SymbolInfo> for SymbolItem {
+    fn from(info: SymbolInfo) -> Self {
+        Self {
+            name: info.name,
+            kind: info.kind,
+            container_name: info.container_name,
+            path: info.path,
+            line: info.line,
+            line_to: info.line_to,
+            score: None,
+            score_explanation: None,
         }
-    };
+    }
+}
+
+impl From<Ranked<SymbolInfo>> for SymbolItem {
+    fn from(ranked: Ranked<SymbolInfo>) -> Self {
+        Self {
+            score: Some(ranked.score),
+            score_explanation: Some(ranking::explain(&ranked.explanation)),
+            ..SymbolItem::from(ranked.item)
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct SymbolsResponse {
+    /// Matching symbols
+    symbols: Vec<SymbolItem>,
+
+    /// Backend that produced the result
+    ///
+    /// `lsp` when the language server answered, `index` when the tree-sitter index was used
+    /// because the language server was still starting or unavailable. The schema is identical
+    /// in both cases.
+    backend: String,
+
+    /// Number of symbols returned
+    total: usize,
+
+    /// Id of the captured LSP trace, for `GET /traces/{trace_id}`
+    ///
+    /// Only set when `debug` was requested.
+    trace_id: Option<String>,
+
+    /// JSON-RPC messages exchanged with the language server during this call
+    ///
+    /// Only set when `debug` was requested. Empty when the index answered without the
+    /// language server.
+    lsp_trace: Option<Vec<LspTraceEntry>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LspTraceEntry {
+    /// `sent` (to the language server) or `received` (from it)
+    direction: String,
+
+    /// JSON-RPC method, for requests and notifications
+    method: Option<String>,
+
+    /// JSON-RPC id, for requests and their responses
+    id: Option<String>,
+
+    /// Milliseconds since the call started
+    elapsed_ms: u64,
+
+    /// Raw JSON-RPC message
+    ///
+    /// Cut after 2000 bytes, see `truncated`.
+    body: String,
+
+    /// Whether `body` was cut
+    truncated: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LspTraceResponse {
+    /// Trace id
+    id: String,
+
+    /// Traced operation, e.g. `goto-definition`
+    operation: String,
+
+    /// Unix timestamp when the call started
+    started_at: u64,
+
+    /// How long the call took, in milliseconds
+    duration_ms: u64,
+
+    /// Messages in the order they were exchanged
+    messages: Vec<LspTraceEntry>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LspTraceSummary {
+    /// Trace id
+    id: String,
+
+    /// Traced operation, e.g. `goto-definition`
+    operation: String,
+
+    /// Unix timestamp when the call started
+    started_at: u64,
+
+    /// How long the call took, in milliseconds
+    duration_ms: u64,
+
+    /// Number of messages captured
+    message_count: usize,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LspTraceListResponse {
+    /// Recent traces kept in memory, newest first
+    traces: Vec<LspTraceSummary>,
+}
+
+impl From<LspTraceMessage> for LspTraceEntry {
+    fn from(message: LspTraceMessage) -> Self {
+        Self {
+            direction: message.direction.as_str().to_string(),
+            method: message.method,
+            id: message.id,
+            elapsed_ms: message.elapsed_ms,
+            body: message.body,
+            truncated: message.truncated,
+        }
+    }
+}
+
+impl From<LspTrace> for LspTraceResponse {
+    fn from(trace: LspTrace) -> Self {
+        Self {
+            id: trace.id,
+            operation: trace.operation,
+            started_at: trace.started_at,
+            duration_ms: trace.duration_ms,
+            messages: trace.messages.into_iter().map(LspTraceEntry::from).collect(),
+        }
+    }
+}
+
+// Splits a captured trace into the `trace_id` and `lsp_trace` response fields
+fn trace_fields(trace: Option<LspTrace>) -> (Option<String>, Option<Vec<LspTraceEntry>>) {
+    match trace {
+        Some(trace) => (
+            Some(trace.id),
+            Some(trace.messages.into_iter().map(LspTraceEntry::from).collect()),
+        ),
+        None => (None, None),
+    }
+}
+
+// Points error messages at the stored trace, so failed calls can still be inspected
+fn with_trace_hint(message: String, trace: &Option<LspTrace>) -> String {
+    match trace {
+        Some(trace) => format!("{} (LSP trace: {})", message, trace.id),
+        None => message,
+    }
+}
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum GotoDefinitionApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<GotoDefinitionResponse>),
+}
+
+#[derive(ApiResponse)]
+enum EntitySearchApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<EntitySearchResponse>),
+}
+
+#[derive(ApiResponse)]
+enum LspTraceApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<LspTraceResponse>),
+}
 
-    let position = lsp_types::Position {
-        line: req.line,
-        character: req.character,
+#[derive(ApiResponse)]
+enum SymbolsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SymbolsResponse>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct PositionRequest {
+    /// Path to the file
+    ///
+    /// **Required.** Can be absolute, relative to the project root, or a partial path.
+    path: String,
+
+    /// Line of the symbol (0-indexed, as in LSP)
+    line: u32,
+
+    /// Character offset within the line (0-indexed, as in LSP)
+    character: u32,
+
+    /// Capture the JSON-RPC messages exchanged with the language server
+    ///
+    /// **Optional.** When true, the conversation is attached to the response as `lsp_trace`
+    /// (message bodies truncated) and kept for `GET /traces/{trace_id}`. Defaults to
+    /// `lsp_debug = "true"` in config.toml.
+    debug: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CompletionRequest {
+    /// Path to the file
+    ///
+    /// **Required.** Can be absolute, relative to the project root, or a partial path.
+    path: String,
+
+    /// Line of the cursor (0-indexed, as in LSP)
+    line: u32,
+
+    /// Character offset of the cursor within the line (0-indexed, as in LSP)
+    character: u32,
+
+    /// Maximum number of completions to return
+    ///
+    /// **Optional.** Defaults to 50.
+    limit: Option<usize>,
+
+    /// Capture the JSON-RPC messages exchanged with the language server
+    ///
+    /// **Optional.** When true, the conversation is attached to the response as `lsp_trace`
+    /// (message bodies truncated) and kept for `GET /traces/{trace_id}`. Defaults to
+    /// `lsp_debug = "true"` in config.toml.
+    debug: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct LspReferencesRequest {
+    /// Path to the file containing the symbol
+    ///
+    /// **Required.** Can be absolute, relative to the project root, or a partial path.
+    path: String,
+
+    /// Line of the symbol (0-indexed, as in LSP)
+    line: u32,
+
+    /// Character offset within the line (0-indexed, as in LSP)
+    character: u32,
+
+    /// Include the declaration itself among the references
+    ///
+    /// **Optional.** Defaults to true.
+    include_declaration: Option<bool>,
+
+    /// Capture the JSON-RPC messages exchanged with the language server
+    ///
+    /// **Optional.** When true, the conversation is attached to the response as `lsp_trace`
+    /// (message bodies truncated) and kept for `GET /traces/{trace_id}`. Defaults to
+    /// `lsp_debug = "true"` in config.toml.
+    debug: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct DiagnosticsRequest {
+    /// Path to the file to check
+    ///
+    /// **Required.** Can be absolute, relative to the project root, or a partial path.
+    path: String,
+
+    /// Capture the JSON-RPC messages exchanged with the language server
+    ///
+    /// **Optional.** When true, the conversation is attached to the response as `lsp_trace`
+    /// (message bodies truncated) and kept for `GET /traces/{trace_id}`. Defaults to
+    /// `lsp_debug = "true"` in config.toml.
+    debug: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct RenameRequest {
+    /// Path to the file containing the symbol
+    ///
+    /// **Required.** Can be absolute, relative to the project root, or a partial path.
+    path: String,
+
+    /// Line of the symbol (0-indexed, as in LSP)
+    line: u32,
+
+    /// Character offset within the line (0-indexed, as in LSP)
+    character: u32,
+
+    /// New name for the symbol
+    ///
+    /// **Required.**
+    new_name: String,
+
+    /// Capture the JSON-RPC messages exchanged with the language server
+    ///
+    /// **Optional.** When true, the conversation is attached to the response as `lsp_trace`
+    /// (message bodies truncated) and kept for `GET /traces/{trace_id}`. Defaults to
+    /// `lsp_debug = "true"` in config.toml.
+    debug: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct HoverResponse {
+    /// Type information and documentation of the symbol, as Markdown
+    ///
+    /// `null` when there is no symbol at the position.
+    contents: Option<String>,
+
+    /// Id of the captured LSP trace, for `GET /traces/{trace_id}`
+    ///
+    /// Only set when `debug` was requested.
+    trace_id: Option<String>,
+
+    /// JSON-RPC messages exchanged with the language server during this call
+    ///
+    /// Only set when `debug` was requested.
+    lsp_trace: Option<Vec<LspTraceEntry>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CompletionItemView {
+    /// Text shown in the completion list
+    label: String,
+
+    /// Item kind such as `Function`, `Variable`, `Property` or `Keyword`
+    kind: Option<String>,
+
+    /// Type or signature of the item
+    detail: Option<String>,
+
+    /// Documentation, as Markdown or plain text
+    documentation: Option<String>,
+
+    /// Text to insert, when it differs from `label`
+    insert_text: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CompletionListResponse {
+    /// Completions, best first
+    items: Vec<CompletionItemView>,
+
+    /// Whether more completions exist: the server reported an incomplete list (typing further
+    /// narrows it) or the list was cut at `limit`
+    is_incomplete: bool,
+
+    /// Id of the captured LSP trace, for `GET /traces/{trace_id}`
+    ///
+    /// Only set when `debug` was requested.
+    trace_id: Option<String>,
+
+    /// JSON-RPC messages exchanged with the language server during this call
+    ///
+    /// Only set when `debug` was requested.
+    lsp_trace: Option<Vec<LspTraceEntry>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct RangeView {
+    /// File path relative to the project root
+    path: String,
+
+    /// Start line (0-indexed, as in LSP)
+    line: u32,
+
+    /// Start character (0-indexed, as in LSP)
+    character: u32,
+
+    /// End line (0-indexed, as in LSP)
+    end_line: u32,
+
+    /// End character, exclusive (0-indexed, as in LSP)
+    end_character: u32,
+}
+
+impl From<RangeInfo> for RangeView {
+    fn from(range: RangeInfo) -> Self {
+        Self {
+            path: range.path,
+            line: range.line,
+            character: range.character,
+            end_line: range.end_line,
+            end_character: range.end_character,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct LspReferencesResponse {
+    /// Where the symbol is referenced, in the server's order
+    references: Vec<RangeView>,
+
+    /// Number of references returned
+    total: usize,
+
+    /// Id of the captured LSP trace, for `GET /traces/{trace_id}`
+    ///
+    /// Only set when `debug` was requested.
+    trace_id: Option<String>,
+
+    /// JSON-RPC messages exchanged with the language server during this call
+    ///
+    /// Only set when `debug` was requested.
+    lsp_trace: Option<Vec<LspTraceEntry>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct TextEditView {
+    /// Range to replace
+    range: RangeView,
+
+    /// Replacement text
+    new_text: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct FileEditsView {
+    /// File path relative to the project root
+    path: String,
+
+    /// Edits to the file, each against its current content
+    edits: Vec<TextEditView>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct RenameResponse {
+    /// Files the rename touches, by path
+    files: Vec<FileEditsView>,
+
+    /// Number of edits across all files
+    total_edits: usize,
+
+    /// Id of the captured LSP trace, for `GET /traces/{trace_id}`
+    ///
+    /// Only set when `debug` was requested.
+    trace_id: Option<String>,
+
+    /// JSON-RPC messages exchanged with the language server during this call
+    ///
+    /// Only set when `debug` was requested.
+    lsp_trace: Option<Vec<LspTraceEntry>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DiagnosticView {
+    /// Range the diagnostic applies to
+    range: RangeView,
+
+    /// `error`, `warning`, `information` or `hint`
+    severity: Option<String>,
+
+    /// Diagnostic code, e.g. `2322` for a TypeScript type error
+    code: Option<String>,
+
+    /// Tool that reported it, e.g. `typescript`
+    source: Option<String>,
+
+    message: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DiagnosticsResponse {
+    /// File path relative to the project root
+    path: String,
+
+    /// Diagnostics in the server's order
+    diagnostics: Vec<DiagnosticView>,
+
+    /// Number of diagnostics with severity `error`
+    errors: usize,
+
+    /// Id of the captured LSP trace, for `GET /traces/{trace_id}`
+    ///
+    /// Only set when `debug` was requested.
+    trace_id: Option<String>,
+
+    /// JSON-RPC messages exchanged with the language server during this call
+    ///
+    /// Only set when `debug` was requested.
+    lsp_trace: Option<Vec<LspTraceEntry>>,
+}
+
+#[derive(ApiResponse)]
+enum HoverApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<HoverResponse>),
+}
+
+#[derive(ApiResponse)]
+enum CompletionApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<CompletionListResponse>),
+}
+
+#[derive(ApiResponse)]
+enum LspReferencesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<LspReferencesResponse>),
+}
+
+#[derive(ApiResponse)]
+enum RenameApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<RenameResponse>),
+}
+
+#[derive(ApiResponse)]
+enum DiagnosticsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<DiagnosticsResponse>),
+}
+
+// Resolves the file a language-feature request is about and takes the project's language server
+fn feature_target(pool: &Arc<LspPool>, path: &str) -> Result<(PathBuf, Arc<LspClient>), GalateaError> {
+    let file = match resolve_path(path) {
+        Ok(file) if file.is_file() => file,
+        Ok(file) => return Err(GalateaError::BadRequest(format!("Path is not a file: {}", file.display()))),
+        Err(e) => return Err(GalateaError::BadRequest(format!("Failed to resolve path '{}': {}", path, e))),
     };
+    let project_root = get_project_root()?;
+    let client = pool.client_if_ready(&project_root).ok_or_else(|| capabilities::unavailable(Capability::Lsp))?;
+    Ok((file, client))
+}
+
+fn symbols_response<T: Into<SymbolItem>>(
+    symbols: Vec<T>,
+    backend: symbols::SymbolBackend,
+    trace: Option<LspTrace>,
+) -> Result<SymbolsApiResponse, GalateaError> {
+    let symbols: Vec<SymbolItem> = symbols.into_iter().map(Into::into).collect();
+    let (trace_id, lsp_trace) = trace_fields(trace);
+    Ok(SymbolsApiResponse::Ok(OpenApiJson(SymbolsResponse {
+        total: symbols.len(),
+        symbols,
+        backend: backend.as_str().to_string(),
+        trace_id,
+        lsp_trace,
+    })))
+}
+
+#[OpenApi]
+impl LspApi {
+    /// Health check for the LSP API
+    ///
+    /// Returns a simple status message to verify that the LSP API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn lsp_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("LSP API route is healthy".to_string()))
+    }
+
+    /// Go to the definition of a symbol
+    ///
+    /// Forwards to the language server's `textDocument/definition` request. There is no index
+    /// fallback for this endpoint: while the language server is starting or after it failed,
+    /// `503` with code `capability_unavailable` is returned; retry after
+    /// `details.retry_after_secs`, also sent as `Retry-After`.
+    #[oai(path = "/goto-definition", method = "post")]
+    async fn goto_definition_handler(
+        &self,
+        pool: Data<&Arc<LspPool>>,
+        req: OpenApiJson<GotoDefinitionRequest>,
+    ) -> Result<GotoDefinitionApiResponse, GalateaError> {
+        let path = match resolve_path(&req.0.path) {
+            Ok(p) => p,
+            Err(e) => {
+                return Err(GalateaError::BadRequest(format!(
+                    "Failed to resolve path '{}': {}",
+                    req.0.path, e
+                )));
+            }
+        };
+
+        let unavailable = || {
+            Err(GalateaError::CapabilityUnavailable(capabilities::unavailable(Capability::Lsp)))
+        };
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let Some(client) = pool.client_if_ready(&project_root) else {
+            return unavailable();
+        };
+
+        let position = lsp_types::Position { line: req.0.line, character: req.0.character };
+        let (result, trace) = lsp_trace::capture(
+            "goto-definition",
+            lsp_trace::debug_enabled(req.0.debug),
+            symbols::goto_definition(&client, &path, position),
+        )
+        .await;
+        match result {
+            Ok(locations) => {
+                let (trace_id, lsp_trace) = trace_fields(trace);
+                Ok(GotoDefinitionApiResponse::Ok(OpenApiJson(GotoDefinitionResponse {
+                    locations: locations
+                        .into_iter()
+                        .map(|(path, line, character)| DefinitionLocation { path, line, character })
+                        .collect(),
+                    trace_id,
+                    lsp_trace,
+                })))
+            }
+            Err(e) => Err(GalateaError::Internal(with_trace_hint(
+                format!("LSP goto_definition failed: {}", e),
+                &trace,
+            ))),
+        }
+    }
+
+    /// Search symbols across the project
+    ///
+    /// Forwards the query to the language server's `workspace/symbol` request. If the language
+    /// server is still starting or fails, the project is scanned with the tree-sitter index instead
+    /// (`.ts`, `.tsx` and `.rs` files). The `backend` field reports which one answered.
+    ///
+    /// The first call starts the language server in the background, so it is normally answered
+    /// by the index. The server then stays up, shared by all callers, who may query it
+    /// concurrently; if it crashes it is restarted.
+    ///
+    /// Results are ranked by name match, file location (source over tests over generated
+    /// output) and recency, and each carries a `score` with its `score_explanation`.
+    #[oai(path = "/workspace-symbols", method = "post")]
+    async fn workspace_symbols_handler(
+        &self,
+        pool: Data<&Arc<LspPool>>,
+        req: OpenApiJson<WorkspaceSymbolsRequest>,
+    ) -> Result<SymbolsApiResponse, GalateaError> {
+        let limit = req.0.limit.unwrap_or(200);
+        let (result, trace) = lsp_trace::capture(
+            "workspace-symbols",
+            lsp_trace::debug_enabled(req.0.debug),
+            symbols::workspace_symbols(&pool, &req.0.query, limit),
+        )
+        .await;
+        match result {
+            Ok((symbols, backend)) => symbols_response(symbols, backend, trace),
+            Err(e) => Err(GalateaError::Internal(with_trace_hint(
+                format!("Failed to search workspace symbols: {}", e),
+                &trace,
+            ))),
+        }
+    }
+
+    /// List symbols declared in a file
+    ///
+    /// Forwards to the language server's `textDocument/documentSymbol` request, flattening
+    /// nested symbols and recording the parent in `container_name`. Falls back to the
+    /// tree-sitter index when the language server is cold or unavailable.
+    #[oai(path = "/document-symbols", method = "post")]
+    async fn document_symbols_handler(
+        &self,
+        pool: Data<&Arc<LspPool>>,
+        req: OpenApiJson<DocumentSymbolsRequest>,
+    ) -> Result<SymbolsApiResponse, GalateaError> {
+        let path = match resolve_path(&req.0.path) {
+            Ok(p) => p,
+            Err(e) => {
+                return Err(GalateaError::BadRequest(format!(
+                    "Failed to resolve path '{}': {}",
+                    req.0.path, e
+                )));
+            }
+        };
+        if !path.is_file() {
+            return Err(GalateaError::BadRequest(format!(
+                "Path is not a file: {}",
+                path.display()
+            )));
+        }
 
-    let mut client_guard = lsp_client_data.0.lock().await;
-
-    let language_id = resolved_file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map_or_else(
-            || "plaintext".to_string(),
-            |ext| match ext {
-                "ts" => "typescript".to_string(),
-                "tsx" => "typescriptreact".to_string(),
-                "js" => "javascript".to_string(),
-                "jsx" => "javascriptreact".to_string(),
-                "json" => "json".to_string(),
-                _ => "plaintext".to_string(),
-            },
-        );
-
-    if let Err(e) = client_guard
-        .notify_did_open(file_uri.clone(), &language_id, 0, file_content)
-        .await
-    {
-        eprintln!(
-            "LSP notify_did_open failed (continuing to goto_definition): {}",
-            e
-        );
-    }
-
-    match client_guard.goto_definition(file_uri, position).await {
-        Ok(locations) => Ok(Json(GotoDefinitionApiResponse { locations })),
-        Err(e) => Err(PoemError::from_string(
-            format!("LSP goto_definition failed: {}", e),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        )),
+        let (result, trace) = lsp_trace::capture(
+            "document-symbols",
+            lsp_trace::debug_enabled(req.0.debug),
+            symbols::document_symbols(&pool, &path),
+        )
+        .await;
+        match result {
+            Ok((symbols, backend)) => symbols_response(symbols, backend, trace),
+            Err(e) => Err(GalateaError::Internal(with_trace_hint(
+                format!("Failed to list document symbols for '{}': {}", path.display(), e),
+                &trace,
+            ))),
+        }
+    }
+
+    /// Describe the symbol at a position
+    ///
+    /// Forwards to the language server's `textDocument/hover` request and returns its type
+    /// information and documentation as Markdown. There is no index fallback: while the language
+    /// server is starting or after it failed, `503` with code `capability_unavailable` is
+    /// returned; retry after `details.retry_after_secs`. The same holds for the other
+    /// language-feature endpoints below.
+    #[oai(path = "/hover", method = "post")]
+    async fn hover_handler(&self, pool: Data<&Arc<LspPool>>, req: OpenApiJson<PositionRequest>) -> Result<HoverApiResponse, GalateaError> {
+        let (path, client) = feature_target(&pool, &req.0.path)?;
+        let position = lsp_types::Position { line: req.0.line, character: req.0.character };
+        let (result, trace) = lsp_trace::capture(
+            "hover",
+            lsp_trace::debug_enabled(req.0.debug),
+            language_features::hover(&client, &path, position),
+        )
+        .await;
+        match result {
+            Ok(contents) => {
+                let (trace_id, lsp_trace) = trace_fields(trace);
+                Ok(HoverApiResponse::Ok(OpenApiJson(HoverResponse { contents, trace_id, lsp_trace })))
+            }
+            Err(e) => Err(GalateaError::Internal(with_trace_hint(format!("LSP hover failed: {:#}", e), &trace))),
+        }
+    }
+
+    /// Complete code at a position
+    ///
+    /// Forwards to the language server's `textDocument/completion` request with the file's
+    /// content on disk, so save edits before asking. Items are ordered as the server ranks them.
+    #[oai(path = "/completion", method = "post")]
+    async fn completion_handler(&self, pool: Data<&Arc<LspPool>>, req: OpenApiJson<CompletionRequest>) -> Result<CompletionApiResponse, GalateaError> {
+        let (path, client) = feature_target(&pool, &req.0.path)?;
+        let position = lsp_types::Position { line: req.0.line, character: req.0.character };
+        let (result, trace) = lsp_trace::capture(
+            "completion",
+            lsp_trace::debug_enabled(req.0.debug),
+            language_features::completion(&client, &path, position, req.0.limit.unwrap_or(50)),
+        )
+        .await;
+        match result {
+            Ok((items, is_incomplete)) => {
+                let (trace_id, lsp_trace) = trace_fields(trace);
+                Ok(CompletionApiResponse::Ok(OpenApiJson(CompletionListResponse {
+                    items: items
+                        .into_iter()
+                        .map(|item| CompletionItemView {
+                            label: item.label,
+                            kind: item.kind,
+                            detail: item.detail,
+                            documentation: item.documentation,
+                            insert_text: item.insert_text,
+                        })
+                        .collect(),
+                    is_incomplete,
+                    trace_id,
+                    lsp_trace,
+                })))
+            }
+            Err(e) => Err(GalateaError::Internal(with_trace_hint(format!("LSP completion failed: {:#}", e), &trace))),
+        }
+    }
+
+    /// Find references to the symbol at a position
+    ///
+    /// Forwards to the language server's `textDocument/references` request, which resolves
+    /// imports and re-exports across the project. `/api/code-intel/references` answers from the
+    /// tree-sitter index instead and works without the language server.
+    #[oai(path = "/references", method = "post")]
+    async fn references_handler(&self, pool: Data<&Arc<LspPool>>, req: OpenApiJson<LspReferencesRequest>) -> Result<LspReferencesApiResponse, GalateaError> {
+        let (path, client) = feature_target(&pool, &req.0.path)?;
+        let position = lsp_types::Position { line: req.0.line, character: req.0.character };
+        let (result, trace) = lsp_trace::capture(
+            "references",
+            lsp_trace::debug_enabled(req.0.debug),
+            language_features::references(&client, &path, position, req.0.include_declaration.unwrap_or(true)),
+        )
+        .await;
+        match result {
+            Ok(references) => {
+                let (trace_id, lsp_trace) = trace_fields(trace);
+                Ok(LspReferencesApiResponse::Ok(OpenApiJson(LspReferencesResponse {
+                    total: references.len(),
+                    references: references.into_iter().map(RangeView::from).collect(),
+                    trace_id,
+                    lsp_trace,
+                })))
+            }
+            Err(e) => Err(GalateaError::Internal(with_trace_hint(format!("LSP references failed: {:#}", e), &trace))),
+        }
+    }
+
+    /// Preview renaming the symbol at a position
+    ///
+    /// Forwards to the language server's `textDocument/rename` request and returns the edits
+    /// per file. Nothing is written; apply the edits through the editor API, last edit of each
+    /// file first, so earlier ranges stay valid.
+    #[oai(path = "/rename", method = "post")]
+    async fn rename_handler(&self, pool: Data<&Arc<LspPool>>, req: OpenApiJson<RenameRequest>) -> Result<RenameApiResponse, GalateaError> {
+        if req.0.new_name.trim().is_empty() {
+            return Err(GalateaError::BadRequest("new_name must not be empty".to_string()));
+        }
+        let (path, client) = feature_target(&pool, &req.0.path)?;
+        let position = lsp_types::Position { line: req.0.line, character: req.0.character };
+        let (result, trace) = lsp_trace::capture(
+            "rename",
+            lsp_trace::debug_enabled(req.0.debug),
+            language_features::rename(&client, &path, position, &req.0.new_name),
+        )
+        .await;
+        match result {
+            Ok(files) => {
+                let (trace_id, lsp_trace) = trace_fields(trace);
+                Ok(RenameApiResponse::Ok(OpenApiJson(RenameResponse {
+                    total_edits: files.iter().map(|f| f.edits.len()).sum(),
+                    files: files
+                        .into_iter()
+                        .map(|file| FileEditsView {
+                            path: file.path,
+                            edits: file
+                                .edits
+                                .into_iter()
+                                .map(|(range, new_text)| TextEditView { range: range.into(), new_text })
+                                .collect(),
+                        })
+                        .collect(),
+                    trace_id,
+                    lsp_trace,
+                })))
+            }
+            Err(e) => Err(GalateaError::Internal(with_trace_hint(format!("LSP rename failed: {:#}", e), &trace))),
+        }
+    }
+
+    /// List the diagnostics of a file
+    ///
+    /// Type errors and warnings the language server reports for the file as it is on disk. Uses
+    /// pull diagnostics (`textDocument/diagnostic`) when the server supports them, otherwise
+    /// waits up to 5 seconds for the diagnostics it publishes after the file is synced.
+    #[oai(path = "/diagnostics", method = "post")]
+    async fn diagnostics_handler(&self, pool: Data<&Arc<LspPool>>, req: OpenApiJson<DiagnosticsRequest>) -> Result<DiagnosticsApiResponse, GalateaError> {
+        let (path, client) = feature_target(&pool, &req.0.path)?;
+        let (result, trace) = lsp_trace::capture(
+            "diagnostics",
+            lsp_trace::debug_enabled(req.0.debug),
+            language_features::diagnostics(&client, &path),
+        )
+        .await;
+        match result {
+            Ok(diagnostics) => {
+                let (trace_id, lsp_trace) = trace_fields(trace);
+                let project_root = get_project_root().unwrap_or_default();
+                Ok(DiagnosticsApiResponse::Ok(OpenApiJson(DiagnosticsResponse {
+                    path: symbols::relative_path(&path, &project_root),
+                    errors: diagnostics.iter().filter(|d| d.severity.as_deref() == Some("error")).count(),
+                    diagnostics: diagnostics
+                        .into_iter()
+                        .map(|d| DiagnosticView {
+                            range: d.range.into(),
+                            severity: d.severity,
+                            code: d.code,
+                            source: d.source,
+                            message: d.message,
+                        })
+                        .collect(),
+                    trace_id,
+                    lsp_trace,
+                })))
+            }
+            Err(e) => Err(GalateaError::Internal(with_trace_hint(format!("LSP diagnostics failed: {:#}", e), &trace))),
+        }
+    }
+
+    /// Search code entities by content and index filters
+    ///
+    /// Combines the tree-sitter entity index with text matching in one call, e.g.
+    /// `kind:hook exported:true calls:fetch` or `lang:tsx kind:component "useState"`. Results are
+    /// ranked like workspace symbols, by term coverage, file location and recency.
+    #[oai(path = "/search-entities", method = "post")]
+    async fn search_entities_handler(&self, req: OpenApiJson<EntitySearchRequest>) -> Result<EntitySearchApiResponse, GalateaError> {
+        let query = match EntityQuery::parse(&req.0.query) {
+            Ok(query) => query,
+            Err(e) => return Err(GalateaError::BadRequest(e.to_string())),
+        };
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let profile = match profiles::resolve_profile(req.0.profile.as_deref()) {
+            Ok(profile) => profile.map(|(_, profile)| profile),
+            Err(e) => return Err(GalateaError::BadRequest(e.to_string())),
+        };
+        let limit = req.0.limit.unwrap_or(50);
+        let result = tokio::task::spawn_blocking(move || {
+            entity_search::search_entities(&project_root, &query, profile.as_ref(), limit)
+        })
+        .await;
+        match result {
+            Ok(Ok(matches)) => {
+                let entities: Vec<EntitySearchItem> = matches
+                    .into_iter()
+                    .map(|ranked| {
+                        let m = ranked.item;
+                        EntitySearchItem {
+                            name: m.entity.name,
+                            kind: m.entity.kind,
+                            container_name: m.entity.container_name,
+                            path: m.entity.path,
+                            lang: m.entity.lang,
+                            exported: m.entity.exported,
+                            line: m.entity.line,
+                            line_to: m.entity.line_to,
+                            matches: m
+                                .matched_lines
+                                .into_iter()
+                                .map(|(line, text)| EntityMatchLine { line, text })
+                                .collect(),
+                            score: ranked.score,
+                            score_explanation: ranking::explain(&ranked.explanation),
+                        }
+                    })
+                    .collect();
+                Ok(EntitySearchApiResponse::Ok(OpenApiJson(EntitySearchResponse { total: entities.len(), entities })))
+            }
+            Ok(Err(e)) => Err(GalateaError::Internal(format!("Entity search failed: {:#}", e))),
+            Err(e) => Err(GalateaError::Internal(format!("Entity search task failed: {}", e))),
+        }
+    }
+
+    /// Show the state of the code index
+    ///
+    /// The code index holds the tree-sitter entities of every `.ts`, `.tsx` and `.rs` file
+    /// outside dependency and build directories. It is built when Galatea starts, cached in the
+    /// metadata store, and updated from file changes, so the index fallbacks of the symbol
+    /// endpoints and `/search-entities` don't re-parse files per call. Answers `503` while the
+    /// index hasn't been started, e.g. while the setup wizard runs.
+    #[oai(path = "/index/status", method = "get")]
+    async fn index_status_handler(&self) -> Result<IndexStatusApiResponse, GalateaError> {
+        match index_manager::global() {
+            Some(manager) => Ok(IndexStatusApiResponse::Ok(OpenApiJson(manager.stats().into()))),
+            None => Err(GalateaError::Unavailable("The code index is not running".to_string())),
+        }
+    }
+
+    /// Rescan the project into the code index
+    ///
+    /// Re-indexes new and changed files and drops deleted ones, then returns the index state.
+    /// Only needed when changes were made while file watching is disabled.
+    #[oai(path = "/index/rebuild", method = "post")]
+    async fn index_rebuild_handler(&self) -> Result<IndexStatusApiResponse, GalateaError> {
+        let Some(manager) = index_manager::global() else {
+            return Err(GalateaError::Unavailable("The code index is not running".to_string()));
+        };
+        match tokio::task::spawn_blocking(move || manager.build()).await {
+            Ok(Ok(stats)) => Ok(IndexStatusApiResponse::Ok(OpenApiJson(stats.into()))),
+            Ok(Err(e)) => Err(GalateaError::Internal(format!("Failed to rebuild the code index: {:#}", e))),
+            Err(e) => Err(GalateaError::Internal(format!("Code index task failed: {}", e))),
+        }
+    }
+
+    /// List the indexed entities of a file
+    ///
+    /// Returns every entity the tree-sitter parser found in the file, imports included, with
+    /// signature, doc comment and source. Served from the code index; the file is only parsed
+    /// when it changed since it was last indexed.
+    #[oai(path = "/index/entities", method = "post")]
+    async fn index_entities_handler(&self, req: OpenApiJson<IndexedEntitiesRequest>) -> Result<IndexedEntitiesApiResponse, GalateaError> {
+        let path = match resolve_path(&req.0.path) {
+            Ok(p) if p.is_file() => p,
+            Ok(p) => return Err(GalateaError::BadRequest(format!("Path is not a file: {}", p.display()))),
+            Err(e) => {
+                return Err(GalateaError::BadRequest(format!(
+                    "Failed to resolve path '{}': {}",
+                    req.0.path, e
+                )))
+            }
+        };
+        let project_root = get_project_root().unwrap_or_default();
+        let rel_path = symbols::relative_path(&path, &project_root);
+        match tokio::task::spawn_blocking(move || index_manager::file_entities(&path)).await {
+            Ok(Ok(entities)) => Ok(IndexedEntitiesApiResponse::Ok(OpenApiJson(IndexedEntitiesResponse {
+                path: rel_path,
+                entities: entities.iter().map(IndexedEntityItem::from).collect(),
+            }))),
+            Ok(Err(e)) => Err(GalateaError::Internal(format!("Failed to index '{}': {:#}", rel_path, e))),
+            Err(e) => Err(GalateaError::Internal(format!("Code index task failed: {}", e))),
+        }
+    }
+
+    /// List captured LSP traces
+    ///
+    /// Traces are captured for calls made with `debug: true` (or with `lsp_debug = "true"` in
+    /// config.toml) and kept in memory; only the most recent 50 are retained.
+    #[oai(path = "/traces", method = "get")]
+    async fn list_traces_handler(&self) -> OpenApiJson<LspTraceListResponse> {
+        OpenApiJson(LspTraceListResponse {
+            traces: lsp_trace::list_traces()
+                .into_iter()
+                .map(|t| LspTraceSummary {
+                    message_count: t.messages.len(),
+                    id: t.id,
+                    operation: t.operation,
+                    started_at: t.started_at,
+                    duration_ms: t.duration_ms,
+                })
+                .collect(),
+        })
+    }
+
+    /// Get a captured LSP trace
+    ///
+    /// Returns every JSON-RPC message the traced call exchanged with the language server: its
+    /// requests and notifications and the responses to them. Messages of calls running
+    /// concurrently on the same server are not included.
+    #[oai(path = "/traces/:trace_id", method = "get")]
+    async fn get_trace_handler(&self, trace_id: OpenApiPath<String>) -> Result<LspTraceApiResponse, GalateaError> {
+        match lsp_trace::get_trace(&trace_id.0) {
+            Some(trace) => Ok(LspTraceApiResponse::Ok(OpenApiJson(trace.into()))),
+            None => Err(GalateaError::NotFound(format!(
+                "LSP trace '{}' not found (only the most recent traces are kept)",
+                trace_id.0
+            ))),
+        }
     }
 }
 
 pub fn lsp_routes() -> Route {
-    Route::new()
-        .at("/health", get(lsp_api_health))
-        .at("/goto-definition", post(lsp_goto_definition_api_handler))
-} 
\ No newline at end of file
+    let api_service = OpenApiService::new(LspApi, "LSP API", "1.0")
+        .server("/api/lsp");
+    Route::new().nest("/", api_service.data(lsp_pool::shared()))
+}
@@ -0,0 +1,186 @@
+use poem::Route;
+use poem_openapi::{
+    param::{Path as OpenApiPath, Query},
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+
+use crate::dev_runtime::events;
+use crate::dev_runtime::mcp_health::{self, McpHealthConfig, McpServerStatus};
+use crate::dev_runtime::supervisor::{self, ControlError};
+
+// Define an API struct
+pub struct McpApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct McpServerView {
+    /// Server id, derived from the OpenAPI spec file it was generated from
+    id: String,
+
+    name: String,
+
+    /// Local port the server listens on
+    port: u16,
+
+    /// Galatea route that proxies to it
+    route: String,
+
+    /// `building` (npm install and build), `starting`, `ready`, `unhealthy`, `stopped` or `failed`
+    state: String,
+
+    /// Seconds spent in the current state
+    state_secs: u64,
+
+    /// Why the last build, run or health check failed
+    last_error: Option<String>,
+
+    /// When it was last checked (Unix seconds)
+    last_check_at: Option<u64>,
+
+    /// When it last answered a check (Unix seconds)
+    last_ready_at: Option<u64>,
+
+    /// Times it was launched since Galatea started, counting restarts
+    starts: u32,
+}
+
+impl From<McpServerStatus> for McpServerView {
+    fn from(s: McpServerStatus) -> Self {
+        Self {
+            route: format!("/api/{}/mcp", s.id),
+            state: s.state.as_str().to_string(),
+            id: s.id,
+            name: s.name,
+            port: s.port,
+            state_secs: s.state_secs,
+            last_error: s.last_error,
+            last_check_at: s.last_check_at,
+            last_ready_at: s.last_ready_at,
+            starts: s.starts,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct McpStatusResponse {
+    /// Servers launched at startup, by id; empty unless Galatea runs with MCP enabled
+    servers: Vec<McpServerView>,
+}
+
+#[derive(ApiResponse)]
+enum McpStatusApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<McpStatusResponse>),
+}
+
+#[derive(ApiResponse)]
+enum McpServerApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<McpServerView>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+fn not_found(id: &str) -> McpServerApiResponse {
+    McpServerApiResponse::NotFound(PlainText(format!("No MCP server '{}' was launched", id)))
+}
+
+fn control_response(id: &str, result: Result<(), ControlError>) -> McpServerApiResponse {
+    match result {
+        Ok(()) => match mcp_health::status(id) {
+            Some(status) => McpServerApiResponse::Ok(OpenApiJson(status.into())),
+            None => not_found(id),
+        },
+        Err(ControlError::NotFound(_)) => not_found(id),
+        Err(e @ ControlError::Conflict(_)) => McpServerApiResponse::Conflict(PlainText(e.to_string())),
+        Err(e @ ControlError::Failed(_)) => McpServerApiResponse::InternalServerError(PlainText(e.to_string())),
+    }
+}
+
+#[OpenApi]
+impl McpApi {
+    /// Health check endpoint for the MCP API
+    ///
+    /// Returns a simple status message to verify that the MCP API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn mcp_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("MCP API route is healthy".to_string()))
+    }
+
+    /// MCP server status
+    ///
+    /// Every MCP server launched at startup with its state, port and last error. A launched
+    /// server is installed and built (`building`), then polled on `/mcp` until it answers
+    /// (`starting`, then `ready`). Ready servers are checked every `interval_secs` (default 10)
+    /// and count as `unhealthy` after `unhealthy_after` (default 3) failed checks in a row; see
+    /// `[mcp_health]` in config.toml. Requests to `/api/{id}/mcp` wait up to
+    /// `ready_timeout_secs` (default 30) for a building or starting server, then get a `503`.
+    ///
+    /// - `probe`: check every server now instead of reporting the last check
+    #[oai(path = "/status", method = "get")]
+    async fn mcp_status_handler(&self, probe: Query<Option<bool>>) -> McpStatusApiResponse {
+        let servers = if probe.0.unwrap_or(false) {
+            let unhealthy_after = McpHealthConfig::load().unhealthy_after;
+            let ids: Vec<String> = mcp_health::statuses().into_iter().map(|s| s.id).collect();
+            futures::future::join_all(ids.iter().map(|id| mcp_health::check(id, unhealthy_after)))
+                .await
+                .into_iter()
+                .flatten()
+                .collect()
+        } else {
+            mcp_health::statuses()
+        };
+        McpStatusApiResponse::Ok(OpenApiJson(McpStatusResponse {
+            servers: servers.into_iter().map(McpServerView::from).collect(),
+        }))
+    }
+
+    /// One MCP server's status, checked now
+    #[oai(path = "/status/:id", method = "get")]
+    async fn mcp_server_status_handler(&self, id: OpenApiPath<String>) -> McpServerApiResponse {
+        match mcp_health::check(&id.0, McpHealthConfig::load().unhealthy_after).await {
+            Some(status) => McpServerApiResponse::Ok(OpenApiJson(status.into())),
+            None => not_found(&id.0),
+        }
+    }
+
+    /// Start a stopped MCP server
+    ///
+    /// Installs, builds and runs it again; follow `state` through `/status/{id}`.
+    #[oai(path = "/:id/start", method = "post")]
+    async fn start_mcp_server_handler(&self, id: OpenApiPath<String>) -> McpServerApiResponse {
+        let result = supervisor::start(&events::mcp_service_name(&id.0)).await;
+        control_response(&id.0, result)
+    }
+
+    /// Stop an MCP server
+    #[oai(path = "/:id/stop", method = "post")]
+    async fn stop_mcp_server_handler(&self, id: OpenApiPath<String>) -> McpServerApiResponse {
+        let result = supervisor::stop(&events::mcp_service_name(&id.0)).await;
+        control_response(&id.0, result)
+    }
+
+    /// Restart an MCP server
+    ///
+    /// Stops it if it is running, then installs, builds and runs it again.
+    #[oai(path = "/:id/restart", method = "post")]
+    async fn restart_mcp_server_handler(&self, id: OpenApiPath<String>) -> McpServerApiResponse {
+        let result = supervisor::restart(&events::mcp_service_name(&id.0)).await;
+        control_response(&id.0, result)
+    }
+}
+
+pub fn mcp_routes() -> Route {
+    let api_service = OpenApiService::new(McpApi, "MCP API", "1.0").server("/api/mcp");
+    Route::new().nest("/", api_service)
+}
@@ -1,18 +1,46 @@
 use poem::Route;
 
 pub mod code_intel;
+pub mod codegen;
 pub mod editor_api;
+pub mod fs;
+pub mod git;
+pub mod jobs;
 pub mod logs_api;
 pub mod lsp_api;
+pub mod mcp;
 pub mod project;
+pub mod refactor;
+pub mod runtime;
+pub mod setup;
+pub mod suggestions;
+pub mod system;
+pub mod terminal;
+pub mod validation;
+pub mod workspaces;
 pub mod codex_api;
 
 pub fn all_routes() -> Route {
     Route::new()
         .nest("/project", project::project_routes())
         // .nest("/code-intel", code_intel::code_intel_routes())
+        .nest("/code-intel", code_intel::code_intel_api_routes())
         .nest("/editor", editor_api::editor_routes())
         // .nest("/logs", logs_api::logs_routes())
-        // .nest("/lsp", lsp_api::lsp_routes())
-        // .nest("/codex", codex_api::codex_routes())
+        .nest("/logs", logs_api::logs_api_routes())
+        .nest("/lsp", lsp_api::lsp_routes())
+        .nest("/mcp", mcp::mcp_routes())
+        .nest("/system", system::system_routes())
+        .nest("/runtime", runtime::runtime_routes())
+        .nest("/setup", setup::setup_routes())
+        .nest("/suggestions", suggestions::suggestions_routes())
+        .nest("/codegen", codegen::codegen_routes())
+        .nest("/refactor", refactor::refactor_routes())
+        .nest("/jobs", jobs::jobs_routes())
+        .nest("/terminal", terminal::terminal_routes())
+        .nest("/fs", fs::fs_routes())
+        .nest("/git", git::git_routes())
+        .nest("/validation", validation::validation_routes())
+        .nest("/workspaces", workspaces::workspaces_routes())
+        .nest("/codex", codex_api::codex_routes())
 } 
\ No newline at end of file
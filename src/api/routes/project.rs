@@ -1,13 +1,30 @@
 use poem::Route;
 use poem_openapi::{
-    param::Path as OpenApiPath,
+    param::{Path as OpenApiPath, Query},
     payload::{Json as OpenApiJson, PlainText},
     ApiResponse, Object, OpenApi, OpenApiService,
 };
+use std::collections::HashMap;
 use std::fs;
 use std::time::{SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
+use crate::api::error::GalateaError;
+use crate::codebase_indexing::structure::{self as structure_tree, StructureNode};
+use crate::dev_operation::dependencies::{self, DependencyInfo, PackageManagerRun};
+use crate::dev_operation::reset::{self, ResetError, ResetMode, ResetOptions, ResetProgress};
+use crate::dev_operation::editor;
+use crate::dev_operation::{assets, changelog, checkpoints, health, snapshots, structure};
+use crate::dev_runtime::capabilities::{self, Capability};
+use crate::dev_runtime::jobs;
+use crate::dev_runtime::mcp_server;
+use crate::dev_runtime::quotas::{self, QuotaMetric};
+use crate::dev_operation::sync::{self, ConflictPolicy, SyncDirection, SyncOptions, SyncReport, SyncSessionInfo};
+use crate::dev_setup::config_schema::{self, ConfigProblem, ConfigUpdateError};
+use crate::dev_setup::{config_files, nextjs, template, template_registry};
+use crate::file_system::{get_project_root, paths};
+use crate::terminal::package_manager::PackageManager;
+
 // Define an API struct
 pub struct ProjectApi;
 
@@ -38,10 +55,268 @@ pub struct UpdateFileRequest {
     pub backup_existing: Option<bool>,
 }
 
+#[derive(Object, serde::Serialize)]
+struct HealthSampleView {
+    /// Validation run the sample was taken from
+    run_id: String,
+
+    /// Unix timestamp (seconds since epoch) when the run finished
+    timestamp: u64,
+
+    /// Composite score from 0 to 100, higher is healthier
+    score: f64,
+
+    /// Whether the build passed, if the run included the `build` step
+    build_passed: Option<bool>,
+
+    /// Share of tests that passed (0 to 1), if tests ran
+    test_pass_rate: Option<f64>,
+
+    /// Failing tests, if the runner reported counts
+    tests_failed: Option<u32>,
+
+    /// ESLint errors, if lint ran
+    lint_errors: Option<u32>,
+
+    /// TypeScript errors, if typecheck ran
+    type_errors: Option<u32>,
+
+    /// Size of the build output (`.next/static`, `dist` or `build`), after a passing build
+    bundle_bytes: Option<u64>,
+}
+
+impl From<health::HealthSample> for HealthSampleView {
+    fn from(sample: health::HealthSample) -> Self {
+        Self {
+            run_id: sample.run_id,
+            timestamp: sample.timestamp,
+            score: sample.score,
+            build_passed: sample.metrics.build_passed,
+            test_pass_rate: sample.metrics.test_pass_rate,
+            tests_failed: sample.metrics.tests_failed,
+            lint_errors: sample.metrics.lint_errors,
+            type_errors: sample.metrics.type_errors,
+            bundle_bytes: sample.metrics.bundle_bytes,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct HealthDeltaView {
+    /// Score change since the previous run; negative means the project got worse
+    score: f64,
+
+    /// Test pass rate change, when both runs measured it
+    test_pass_rate: Option<f64>,
+
+    /// Lint error count change, when both runs measured it
+    lint_errors: Option<i64>,
+
+    /// Type error count change, when both runs measured it
+    type_errors: Option<i64>,
+
+    /// Bundle size change in bytes, when both runs measured it
+    bundle_bytes: Option<i64>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ProjectHealthResponse {
+    /// Health of the most recent validation run, `null` before the first run
+    latest: Option<HealthSampleView>,
+
+    /// Change from the run before it
+    delta: Option<HealthDeltaView>,
+
+    /// What got worse since the previous run, e.g. `Lint errors increased from 0 to 3`
+    regressions: Vec<String>,
+
+    /// Recent samples, newest first
+    history: Vec<HealthSampleView>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ConfigLayerView {
+    /// File name, e.g. `config.toml` or `config.ci.toml`
+    name: String,
+
+    /// Absolute path of the file
+    path: String,
+
+    /// Whether the file exists; a missing overlay contributes nothing
+    present: bool,
+
+    /// Parse error, if the file exists but isn't valid TOML (the layer is then ignored)
+    error: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct EffectiveConfigValue {
+    /// Dotted key, e.g. `offline` or `search_ranking.recency`
+    key: String,
+
+    /// Merged value; `********` for secret-looking keys (`*key*`, `*token*`, `*secret*`, `*password*`)
+    value: serde_json::Value,
+
+    /// Name of the layer the value came from
+    source: String,
+
+    /// Whether `value` is a placeholder for a secret
+    masked: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct EffectiveConfigResponse {
+    /// Environment selected with `--env` or `GALATEA_ENV`, `null` when only config.toml applies
+    environment: Option<String>,
+
+    /// Config files in precedence order, lowest first
+    layers: Vec<ConfigLayerView>,
+
+    /// Every value of the merged configuration, sorted by key
+    values: Vec<EffectiveConfigValue>,
+}
+
+#[derive(ApiResponse)]
+enum EffectiveConfigApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<EffectiveConfigResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct ConfigProblemView {
+    /// Top-level key, e.g. `offline` or `dev_server_watchdog`
+    key: String,
+
+    message: String,
+}
+
+impl From<ConfigProblem> for ConfigProblemView {
+    fn from(p: ConfigProblem) -> Self {
+        Self { key: p.key, message: p.message }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct ConfigResponse {
+    /// Environment selected with `--env` or `GALATEA_ENV`, `null` when only config.toml applies
+    environment: Option<String>,
+
+    /// The merged configuration; `********` for secret-looking keys
+    config: serde_json::Value,
+
+    /// What is wrong with it, unknown keys included. Components fall back to their defaults
+    /// for invalid values.
+    problems: Vec<ConfigProblemView>,
+}
+
+#[derive(ApiResponse)]
+enum ConfigApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ConfigResponse>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct ConfigPatchRequest {
+    /// Changes as a JSON merge patch: objects merge key by key, `null` removes a key, anything
+    /// else replaces it. `********` keeps the current value.
+    ///
+    /// **Required.** e.g. `{"offline": true, "dev_server_watchdog": {"interval_secs": 10}, "registry_upstream": null}`
+    changes: serde_json::Value,
+
+    /// Write keys Galatea doesn't know instead of refusing them
+    ///
+    /// **Optional.** Defaults to `false`.
+    allow_unknown: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ConfigUpdateResponse {
+    /// Top-level keys whose effective value changed
+    changed: Vec<String>,
+
+    /// Components reloaded to pick up the changes, e.g. `log_hub` or `nextjs_dev_server`
+    reloaded: Vec<String>,
+
+    /// Changed keys that are only read at startup and apply after a restart
+    restart_required: Vec<String>,
+
+    /// The merged configuration after the update; `********` for secret-looking keys
+    config: serde_json::Value,
+}
+
+#[derive(ApiResponse)]
+enum ConfigUpdateApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ConfigUpdateResponse>),
+}
+
+// Refuses an update, listing its problems under `details.problems`
+fn invalid_config(problems: Vec<ConfigProblem>) -> GalateaError {
+    let problems: Vec<ConfigProblemView> = problems.into_iter().map(ConfigProblemView::from).collect();
+    GalateaError::Invalid {
+        message: format!("{} problem(s) with the changed keys; nothing was written", problems.len()),
+        details: serde_json::json!({ "problems": problems }),
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct McpRegenerationView {
+    /// MCP server id, e.g. `project` for `project_api.json`
+    id: String,
+
+    /// Why regenerating or restarting it failed; unset when it succeeded
+    error: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct OpenApiRefreshResponse {
+    /// Spec files in `openapi_specification/` written or removed because the API changed
+    changed: Vec<String>,
+
+    /// MCP servers launched at startup that were regenerated from a changed spec
+    mcp_servers: Vec<McpRegenerationView>,
+}
+
+#[derive(ApiResponse)]
+enum OpenApiRefreshApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<OpenApiRefreshResponse>),
+}
+
+fn masked_config_json() -> serde_json::Value {
+    toml_to_json(toml::Value::Table(config_schema::masked(&config_files::merged_config().unwrap_or_default())))
+}
+
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => serde_json::Value::from(i),
+        toml::Value::Float(f) => serde_json::Value::from(f),
+        toml::Value::Boolean(b) => serde_json::Value::Bool(b),
+        toml::Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        toml::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => {
+            serde_json::Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect())
+        }
+    }
+}
+
 #[derive(ApiResponse)]
 enum HealthResponse {
     #[oai(status = 200)]
-    Ok(PlainText<String>),
+    Ok(OpenApiJson<ProjectHealthResponse>),
+}
+
+fn health_delta(latest: &health::HealthSample, previous: &health::HealthSample) -> HealthDeltaView {
+    let (now, before) = (&latest.metrics, &previous.metrics);
+    let count_delta = |now: Option<u32>, before: Option<u32>| Some(now? as i64 - before? as i64);
+    HealthDeltaView {
+        score: ((latest.score - previous.score) * 10.0).round() / 10.0,
+        test_pass_rate: now.test_pass_rate.zip(before.test_pass_rate).map(|(now, before)| now - before),
+        lint_errors: count_delta(now.lint_errors, before.lint_errors),
+        type_errors: count_delta(now.type_errors, before.type_errors),
+        bundle_bytes: now.bundle_bytes.zip(before.bundle_bytes).map(|(now, before)| now as i64 - before as i64),
+    }
 }
 
 #[derive(Object, serde::Serialize)]
@@ -87,98 +362,1047 @@ pub struct ScriptResponse {
     /// the script execution finished.
     pub executed_at: String,
 
-    /// Duration of the script execution in milliseconds
-    ///
-    /// How long the script took to execute, useful for performance monitoring
-    /// and identifying slow operations.
-    pub duration_ms: Option<u64>,
+    /// Duration of the script execution in milliseconds
+    ///
+    /// How long the script took to execute, useful for performance monitoring
+    /// and identifying slow operations.
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(ApiResponse)]
+enum GalateaFileUpdateResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ScriptResponse>),
+}
+
+#[derive(ApiResponse)]
+enum GalateaFileGetResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+pub struct GalateaFilesListResponse {
+    /// List of files and directories in galatea_files
+    ///
+    /// Array of relative paths from the galatea_files directory root.
+    /// Includes both files and directories, listed recursively.
+    /// Paths use forward slashes as separators for consistency.
+    pub entries: Vec<GalateaFileEntry>,
+
+    /// Total number of entries found
+    ///
+    /// Count of all files and directories found in the galatea_files directory.
+    pub total_count: usize,
+
+    /// Timestamp when the listing was generated
+    ///
+    /// Unix timestamp (seconds since epoch) when this directory listing was created.
+    pub generated_at: String,
+}
+
+#[derive(Object, serde::Serialize)]
+pub struct GalateaFileEntry {
+    /// Relative path from galatea_files root
+    ///
+    /// Path to the file or directory, relative to the galatea_files directory.
+    /// Uses forward slashes as path separators.
+    pub path: String,
+
+    /// Whether this entry is a directory
+    ///
+    /// `true` if this entry represents a directory, `false` if it's a file.
+    pub is_directory: bool,
+
+    /// File size in bytes (files only)
+    ///
+    /// Size of the file in bytes. Will be `null` for directories or if
+    /// the size could not be determined.
+    pub size_bytes: Option<u64>,
+
+    /// Last modified timestamp
+    ///
+    /// Unix timestamp (seconds since epoch) when this file or directory
+    /// was last modified. May be `null` if timestamp is unavailable.
+    pub modified_at: Option<u64>,
+}
+
+#[derive(ApiResponse)]
+enum GalateaFilesListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<GalateaFilesListResponse>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct GenerateChangelogRequest {
+    /// Limit the changelog to the last N days
+    ///
+    /// **Optional.** When omitted, the whole git history of the project is summarized.
+    days: Option<u32>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ChangelogDayEntry {
+    /// Day covered by this entry (`YYYY-MM-DD`)
+    date: String,
+
+    /// Galatea session that made the commits, from their `Galatea-Session` trailer
+    ///
+    /// Commits made outside Galatea share one entry per day without a session.
+    session: Option<String>,
+
+    /// Tasks worked on that day
+    ///
+    /// Collected from `Task: <name>` trailers in commit messages, in first-seen order.
+    tasks: Vec<String>,
+
+    /// Commit subjects, newest first
+    commits: Vec<String>,
+
+    /// Files changed that day, sorted and de-duplicated
+    files_changed: Vec<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ChangelogResponse {
+    /// Path of the written CHANGELOG.md
+    path: String,
+
+    /// Full markdown content of the changelog
+    markdown: String,
+
+    /// Structured entries, one per session and day, newest day first
+    ///
+    /// Empty when returning a previously generated file via `GET`.
+    days: Vec<ChangelogDayEntry>,
+}
+
+#[derive(ApiResponse)]
+enum ChangelogApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ChangelogResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct StructureDiffView {
+    routes_added: Vec<String>,
+    routes_removed: Vec<String>,
+    api_routes_added: Vec<String>,
+    api_routes_removed: Vec<String>,
+    /// Component files, relative to the project root
+    components_added: Vec<String>,
+    components_removed: Vec<String>,
+    directories_added: Vec<String>,
+    directories_removed: Vec<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct StructureChangeView {
+    /// Sequence number; pass the last one seen as `since` to poll for newer changes
+    seq: u64,
+
+    /// Unix timestamp (seconds since epoch) when the change was detected
+    timestamp: u64,
+
+    /// `startup`, `api`, `scheduled` or `file_update`
+    trigger: String,
+
+    diff: StructureDiffView,
+}
+
+impl From<structure::StructureChange> for StructureChangeView {
+    fn from(change: structure::StructureChange) -> Self {
+        let diff = change.diff;
+        Self {
+            seq: change.seq,
+            timestamp: change.timestamp,
+            trigger: change.trigger,
+            diff: StructureDiffView {
+                routes_added: diff.routes_added,
+                routes_removed: diff.routes_removed,
+                api_routes_added: diff.api_routes_added,
+                api_routes_removed: diff.api_routes_removed,
+                components_added: diff.components_added,
+                components_removed: diff.components_removed,
+                directories_added: diff.directories_added,
+                directories_removed: diff.directories_removed,
+            },
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct StructureChangesResponse {
+    /// Changes, oldest first
+    changes: Vec<StructureChangeView>,
+
+    /// Sequence number of the newest change returned, to pass as `since` next time
+    last_seq: Option<u64>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct StructureRefreshResponse {
+    /// Path of the rewritten project_structure.json
+    path: String,
+
+    /// What changed since the previous snapshot; `null` if nothing did (or there was none)
+    change: Option<StructureChangeView>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct StructureNodeView {
+    /// `dir` or `file`
+    #[oai(rename = "type")]
+    node_type: String,
+
+    name: String,
+
+    /// Path relative to the project root
+    path: String,
+
+    /// Parser language of a file (`tsx`, `rust`, `markdown`, ...); `null` for directories and
+    /// files that aren't indexed
+    language: Option<String>,
+
+    /// File size in bytes; `null` for directories
+    size: Option<u64>,
+
+    /// Files anywhere below a directory; 1 for files
+    file_count: usize,
+
+    /// Indexed entities (functions, components, headings, ...) in the file or below the directory
+    entity_count: usize,
+
+    /// Names a code file exports (`export`ed in TS/JS, `pub` in Rust)
+    exports: Vec<String>,
+
+    /// Directories first, then files; empty for files and for directories beyond `max_depth`
+    children: Vec<StructureNodeView>,
+}
+
+impl From<StructureNode> for StructureNodeView {
+    fn from(node: StructureNode) -> Self {
+        match node {
+            StructureNode::Dir { name, path, file_count, entity_count, children } => Self {
+                node_type: "dir".to_string(),
+                name,
+                path,
+                language: None,
+                size: None,
+                file_count,
+                entity_count,
+                exports: Vec::new(),
+                children: children.into_iter().map(Into::into).collect(),
+            },
+            StructureNode::File { name, path, language, size, entity_count, exports } => Self {
+                node_type: "file".to_string(),
+                name,
+                path,
+                language,
+                size: Some(size),
+                file_count: 1,
+                entity_count,
+                exports,
+                children: Vec::new(),
+            },
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct ProjectStructureResponse {
+    /// Unix timestamp (seconds since epoch) of the snapshot
+    generated_at: u64,
+
+    /// Page routes of the app and pages routers, e.g. `/blog/[slug]`
+    routes: Vec<String>,
+
+    /// Route handlers (`app/**/route.ts`) and `pages/api` endpoints
+    api_routes: Vec<String>,
+
+    /// Component files under any `components` directory
+    components: Vec<String>,
+
+    /// Top-level directories and files of the project tree
+    tree: Vec<StructureNodeView>,
+}
+
+#[derive(ApiResponse)]
+enum ProjectStructureApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ProjectStructureResponse>),
+}
+
+#[derive(ApiResponse)]
+enum StructureChangesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<StructureChangesResponse>),
+}
+
+#[derive(ApiResponse)]
+enum StructureRefreshApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<Box<StructureRefreshResponse>>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct SyncRequest {
+    /// Directory to mirror the project with
+    ///
+    /// **Required.** A local path, or an rsync-style remote such as `user@host:/srv/app`
+    /// (remote targets require `rsync` and support `push` and `pull` only). Local paths must be
+    /// absolute and inside one of `allowed_roots` in the `[sync]` section of config.toml; remote
+    /// hosts must be listed in its `allowed_remotes`. Neither may reach galatea_files.
+    target: String,
+
+    /// `push` (project → target, default), `pull` (target → project) or `both`
+    direction: Option<String>,
+
+    /// What to do with files changed on both sides since the last sync
+    ///
+    /// **Optional.** `newer` (default, most recently modified copy wins), `project`, `target`,
+    /// or `skip` (leave both and report the conflict).
+    conflict_policy: Option<String>,
+
+    /// Glob patterns to exclude, in addition to `node_modules`, `.next`, `.git` and `.turbo`
+    ///
+    /// Patterns without `/` match any path component (e.g. `*.log`); patterns with `/` match
+    /// from the project root (e.g. `public/uploads`). `**` matches across directories.
+    exclude: Option<Vec<String>>,
+
+    /// Propagate deletions
+    ///
+    /// **Optional.** Defaults to `false`, so files are only ever added or updated.
+    delete: Option<bool>,
+
+    /// Keep syncing in the background until stopped with `DELETE /sync/{id}`
+    ///
+    /// **Optional.** Defaults to `false` (one-shot).
+    continuous: Option<bool>,
+
+    /// Seconds between passes of a continuous sync
+    ///
+    /// **Optional.** Defaults to 2.
+    #[oai(validator(minimum(value = "1")))]
+    interval_secs: Option<u64>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SyncConflictView {
+    /// File path relative to the synced roots
+    path: String,
+
+    /// `kept project`, `kept target` or `skipped`
+    resolution: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SyncReportView {
+    /// Files copied from the project to the target
+    copied_to_target: Vec<String>,
+
+    /// Files copied from the target into the project
+    copied_to_project: Vec<String>,
+
+    /// Files deleted to propagate deletions
+    deleted: Vec<String>,
+
+    /// Files changed on both sides, and how each was resolved
+    conflicts: Vec<SyncConflictView>,
+
+    /// Files already identical on both sides
+    unchanged: usize,
+
+    /// How long the pass took, in milliseconds
+    duration_ms: u64,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SyncSessionView {
+    /// Session identifier, used to stop the sync
+    id: String,
+
+    /// Sync target
+    target: String,
+
+    /// `push`, `pull` or `both`
+    direction: String,
+
+    /// Conflict policy in effect
+    conflict_policy: String,
+
+    /// Seconds between passes
+    interval_secs: u64,
+
+    /// Unix timestamp when the sync started
+    started_at: u64,
+
+    /// Passes run so far
+    passes: u64,
+
+    /// Result of the most recent successful pass
+    last_report: Option<SyncReportView>,
+
+    /// Error of the most recent pass, if it failed
+    last_error: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SyncResponse {
+    /// Result of the (first) sync pass
+    report: SyncReportView,
+
+    /// The background session, for continuous syncs
+    session: Option<SyncSessionView>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SyncSessionsResponse {
+    /// Running continuous syncs, oldest first
+    sessions: Vec<SyncSessionView>,
+}
+
+#[derive(ApiResponse)]
+enum SyncApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<Box<SyncResponse>>),
+}
+
+#[derive(ApiResponse)]
+enum SyncSessionsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SyncSessionsResponse>),
+}
+
+#[derive(ApiResponse)]
+enum SyncStopApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<Box<SyncSessionView>>),
+}
+
+impl From<SyncReport> for SyncReportView {
+    fn from(report: SyncReport) -> Self {
+        Self {
+            copied_to_target: report.copied_to_target,
+            copied_to_project: report.copied_to_project,
+            deleted: report.deleted,
+            conflicts: report
+                .conflicts
+                .into_iter()
+                .map(|c| SyncConflictView { path: c.path, resolution: c.resolution })
+                .collect(),
+            unchanged: report.unchanged,
+            duration_ms: report.duration_ms,
+        }
+    }
+}
+
+impl From<SyncSessionInfo> for SyncSessionView {
+    fn from(info: SyncSessionInfo) -> Self {
+        Self {
+            id: info.id,
+            target: info.options.target,
+            direction: info.options.direction.as_str().to_string(),
+            conflict_policy: info.options.conflict_policy.as_str().to_string(),
+            interval_secs: info.interval.as_secs(),
+            started_at: info.started_at,
+            passes: info.passes,
+            last_report: info.last_report.map(SyncReportView::from),
+            last_error: info.last_error,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct TemplateVariableInfo {
+    /// Variable name, used as `{{name}}` placeholder in template files
+    name: String,
+
+    /// Human-readable description for prompts
+    description: Option<String>,
+
+    /// Value type: `string`, `number`, `bool`, `color` or `choice`
+    #[oai(rename = "type")]
+    var_type: String,
+
+    /// Default value, rendered as a string
+    default: Option<String>,
+
+    /// Allowed values for `choice` variables
+    options: Vec<String>,
+
+    /// Whether scaffolding fails when no value (and no default) is given
+    required: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct TemplateVariablesResponse {
+    /// Template name as requested
+    template: String,
+
+    /// Git URL the template resolves to
+    source_url: String,
+
+    /// Whether the template ships a `galatea.template.toml`
+    has_metadata: bool,
+
+    /// Declared variables, in declaration order
+    variables: Vec<TemplateVariableInfo>,
+}
+
+#[derive(ApiResponse)]
+enum TemplateVariablesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<TemplateVariablesResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct TemplateScriptInfo {
+    /// package.json script name
+    name: String,
+
+    description: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct TemplateSummary {
+    /// Name to pass as `--template` or `template`
+    name: String,
+
+    display_name: String,
+
+    description: String,
+
+    /// `nextjs`, `vite`, `remix` or `astro`
+    framework: String,
+
+    /// Git URL the template is cloned from, with `#<dir>` when it is a directory of the repository
+    source_url: String,
+
+    /// Command Galatea starts the dev server with
+    dev_command: String,
+
+    /// Port the dev server listens on
+    port: u16,
+
+    /// Scripts the template's package.json provides
+    scripts: Vec<TemplateScriptInfo>,
+
+    /// Whether the current project was scaffolded from this template
+    current: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct TemplatesResponse {
+    /// Template used when none is given
+    default_template: String,
+
+    /// Built-in templates, the default first
+    templates: Vec<TemplateSummary>,
+}
+
+#[derive(ApiResponse)]
+enum TemplatesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<TemplatesResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct ScaffoldStatusResponse {
+    /// Whether a previous clone or install was interrupted and has not been resumed yet
+    incomplete: bool,
+
+    /// Stage the interrupted scaffold reached: `cloning` or `installing`
+    stage: Option<String>,
+
+    /// Template URL the interrupted scaffold was using
+    template_url: Option<String>,
+}
+
+#[derive(ApiResponse)]
+enum ScaffoldStatusApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ScaffoldStatusResponse>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct RescaffoldRequest {
+    /// Template to scaffold from: a built-in template name (see `/templates`) or a git URL
+    ///
+    /// **Optional.** Defaults to the template the project was started with.
+    template: Option<String>,
+
+    /// Values for the template's `galatea.template.toml` variables
+    ///
+    /// **Optional.**
+    template_vars: Option<HashMap<String, String>>,
+
+    /// Discard the existing project and scaffold from scratch
+    ///
+    /// **Optional.** Defaults to `false`, which only resumes an interrupted scaffold.
+    force: Option<bool>,
+}
+
+#[derive(ApiResponse)]
+enum RescaffoldApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ScaffoldStatusResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct DependencyView {
+    name: String,
+
+    /// package.json section: `dependencies`, `devDependencies`, `optionalDependencies` or
+    /// `peerDependencies`
+    section: String,
+
+    /// Version range declared in package.json
+    range: String,
+
+    /// Version installed in node_modules; `null` when not installed
+    installed: Option<String>,
+
+    /// Highest version within `range`, when outdated versions were checked
+    wanted: Option<String>,
+
+    /// Latest published version, when outdated versions were checked
+    latest: Option<String>,
+
+    /// Whether `latest` differs from `installed`
+    outdated: bool,
+
+    deprecated: bool,
+}
+
+impl From<DependencyInfo> for DependencyView {
+    fn from(d: DependencyInfo) -> Self {
+        Self {
+            outdated: d.outdated(),
+            name: d.name,
+            section: d.section,
+            range: d.wanted_range,
+            installed: d.installed,
+            wanted: d.wanted,
+            latest: d.latest,
+            deprecated: d.deprecated,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct DependenciesResponse {
+    /// Package manager commands run with (`package_manager` in config.toml)
+    package_manager: String,
+
+    /// Whether `wanted`, `latest` and `outdated` were checked against the registry
+    outdated_checked: bool,
+
+    dependencies: Vec<DependencyView>,
+}
+
+#[derive(ApiResponse)]
+enum DependenciesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<DependenciesResponse>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct AddDependenciesRequest {
+    /// Packages to add, as `name` or `name@version`
+    ///
+    /// **Required.** e.g. `["zod", "@tanstack/react-query@^5"]`
+    packages: Vec<String>,
+
+    /// Add to `devDependencies`
+    ///
+    /// **Optional.** Defaults to `false`.
+    dev: Option<bool>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct UpgradeDependenciesRequest {
+    /// Packages to upgrade
+    ///
+    /// **Optional.** Defaults to all of them.
+    packages: Option<Vec<String>>,
+
+    /// Upgrade to the latest versions, rewriting the ranges in package.json
+    ///
+    /// **Optional.** Defaults to `false`, which stays within the declared ranges.
+    latest: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DependencyChangeView {
+    name: String,
+
+    section: String,
+
+    /// Declared range before the command; `null` when it was added
+    before: Option<String>,
+
+    /// Declared range after the command; `null` when it was removed
+    after: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct PackageManagerRunResponse {
+    /// Command that ran, e.g. `pnpm add zod`
+    command: String,
+
+    success: bool,
+
+    exit_code: i32,
+
+    /// Output, cut to the last 64 KiB
+    stdout: String,
+
+    stderr: String,
+
+    duration_ms: u64,
+
+    /// package.json changes the command made
+    changes: Vec<DependencyChangeView>,
+}
+
+impl From<PackageManagerRun> for PackageManagerRunResponse {
+    fn from(r: PackageManagerRun) -> Self {
+        Self {
+            command: r.command,
+            success: r.success,
+            exit_code: r.exit_code,
+            stdout: r.stdout,
+            stderr: r.stderr,
+            duration_ms: r.duration_ms,
+            changes: r
+                .changes
+                .into_iter()
+                .map(|c| DependencyChangeView { name: c.name, section: c.section, before: c.before, after: c.after })
+                .collect(),
+        }
+    }
+}
+
+#[derive(ApiResponse)]
+enum PackageManagerApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<PackageManagerRunResponse>),
+}
+
+fn package_manager_response(result: anyhow::Result<PackageManagerRun>) -> Result<PackageManagerApiResponse, GalateaError> {
+    match result {
+        Ok(run) => Ok(PackageManagerApiResponse::Ok(OpenApiJson(run.into()))),
+        Err(e) => Err(GalateaError::Internal(format!("{:#}", e))),
+    }
+}
+
+#[derive(Object, serde::Deserialize)]
+struct ResetRequest {
+    /// `rescaffold` or `template_initial`
+    ///
+    /// **Optional.** `rescaffold` (the default) deletes the project directory and scaffolds it
+    /// again from the template. `template_initial` resets the project's git repository to the
+    /// template commit it was cloned from and removes untracked files, keeping ignored ones
+    /// such as `node_modules` and `.env` files, then reinstalls dependencies.
+    mode: Option<String>,
+
+    /// Token from the `428` answer to an unconfirmed request
+    ///
+    /// **Optional.** Required unless `require_confirmation = false` under `[project_reset]` in
+    /// config.toml.
+    confirm_token: Option<String>,
+
+    /// Template to scaffold from, for `rescaffold`: a built-in template name or a git URL
+    ///
+    /// **Optional.** Defaults to the template the project was started with.
+    template: Option<String>,
+
+    /// Values for the template's `galatea.template.toml` variables, for `rescaffold`
+    ///
+    /// **Optional.**
+    template_vars: Option<HashMap<String, String>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ResetStageInfo {
+    stage: String,
+
+    /// When the stage began (Unix seconds)
+    at: u64,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ResetProgressResponse {
+    id: String,
+
+    mode: String,
+
+    /// Template the project is scaffolded from
+    template_url: String,
+
+    /// `stopping_dev_server`, `cloning`, `resetting_git`, `installing`, `restarting_dev_server`,
+    /// then `done` or `failed`
+    stage: String,
+
+    /// Stages passed through, oldest first
+    history: Vec<ResetStageInfo>,
+
+    started_at: u64,
+
+    finished_at: Option<u64>,
+
+    /// Why the reset failed
+    error: Option<String>,
+}
+
+impl From<ResetProgress> for ResetProgressResponse {
+    fn from(p: ResetProgress) -> Self {
+        Self {
+            id: p.id,
+            mode: p.mode.as_str().to_string(),
+            template_url: p.template_url,
+            stage: p.stage.as_str().to_string(),
+            history: p.history.into_iter().map(|(stage, at)| ResetStageInfo { stage: stage.as_str().to_string(), at }).collect(),
+            started_at: p.started_at,
+            finished_at: p.finished_at,
+            error: p.error,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct ResetConfirmationResponse {
+    /// Pass as `confirm_token` to go ahead; single-use
+    confirm_token: String,
+
+    expires_in_secs: u64,
+
+    mode: String,
+
+    /// Uncommitted files the reset would discard
+    uncommitted_files: Option<usize>,
+
+    /// Commits made on top of the template that the reset would drop
+    commits_since_template: Option<usize>,
+}
+
+#[derive(ApiResponse)]
+enum ResetApiResponse {
+    /// The reset started; follow it with `GET /reset`
+    #[oai(status = 202)]
+    Accepted(OpenApiJson<ResetProgressResponse>),
+    /// Repeat the request with `confirm_token`
+    #[oai(status = 428)]
+    ConfirmationRequired(OpenApiJson<ResetConfirmationResponse>),
+}
+
+#[derive(ApiResponse)]
+enum ResetProgressApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ResetProgressResponse>),
+}
+
+fn scaffold_status(project_root: &std::path::Path) -> ScaffoldStatusResponse {
+    match nextjs::read_scaffold_marker(project_root) {
+        Some(marker) => ScaffoldStatusResponse {
+            incomplete: true,
+            stage: serde_json::to_value(marker.stage).ok().and_then(|v| v.as_str().map(str::to_string)),
+            template_url: Some(marker.template_url).filter(|url| !url.is_empty()),
+        },
+        None => ScaffoldStatusResponse { incomplete: false, stage: None, template_url: None },
+    }
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CreateSnapshotRequest {
+    /// What the snapshot is for, e.g. `Before switching to the app router`
+    label: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SnapshotView {
+    /// Creation time in Unix milliseconds
+    id: String,
+    label: String,
+    /// Unix seconds
+    created_at: i64,
+    /// Project root the snapshot was taken of, and is restored to
+    project_root: String,
+    project_files: usize,
+    galatea_files_files: usize,
+    /// Compressed size of the snapshot
+    size_bytes: u64,
+    /// Directory names left out of the project, and left alone when restoring it
+    excluded: Vec<String>,
+}
+
+impl From<snapshots::Snapshot> for SnapshotView {
+    fn from(s: snapshots::Snapshot) -> Self {
+        Self {
+            id: s.id,
+            label: s.label,
+            created_at: s.created_at,
+            project_root: s.project_root,
+            project_files: s.project_files,
+            galatea_files_files: s.galatea_files_files,
+            size_bytes: s.size_bytes,
+            excluded: s.excluded,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct SnapshotListResponse {
+    /// Newest first
+    snapshots: Vec<SnapshotView>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SnapshotRestoreResponse {
+    snapshot: SnapshotView,
+    /// Snapshot of the state before the restore; restore it to undo this one
+    backup: SnapshotView,
+    /// Files created after the snapshot and deleted by the restore, as `project/<path>` or
+    /// `galatea_files/<path>`
+    removed: Vec<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SnapshotDeleteResponse {
+    id: String,
 }
 
 #[derive(ApiResponse)]
-enum GalateaFileUpdateResponse {
+enum SnapshotApiResponse {
     #[oai(status = 200)]
-    Ok(OpenApiJson<ScriptResponse>),
-    #[oai(status = 400)]
-    BadRequest(PlainText<String>),
-    #[oai(status = 500)]
-    InternalServerError(PlainText<String>),
+    Ok(OpenApiJson<SnapshotView>),
 }
 
 #[derive(ApiResponse)]
-enum GalateaFileGetResponse {
+enum SnapshotListApiResponse {
     #[oai(status = 200)]
-    Ok(PlainText<String>),
-    #[oai(status = 400)]
-    BadRequest(PlainText<String>),
-    #[oai(status = 404)]
-    NotFound(PlainText<String>),
-    #[oai(status = 500)]
-    InternalServerError(PlainText<String>),
+    Ok(OpenApiJson<SnapshotListResponse>),
 }
 
-#[derive(Object, serde::Serialize)]
-pub struct GalateaFilesListResponse {
-    /// List of files and directories in galatea_files
-    ///
-    /// Array of relative paths from the galatea_files directory root.
-    /// Includes both files and directories, listed recursively.
-    /// Paths use forward slashes as separators for consistency.
-    pub entries: Vec<GalateaFileEntry>,
+#[derive(ApiResponse)]
+enum SnapshotRestoreApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SnapshotRestoreResponse>),
+}
 
-    /// Total number of entries found
-    ///
-    /// Count of all files and directories found in the galatea_files directory.
-    pub total_count: usize,
+#[derive(ApiResponse)]
+enum SnapshotDeleteApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SnapshotDeleteResponse>),
+}
 
-    /// Timestamp when the listing was generated
-    ///
-    /// Unix timestamp (seconds since epoch) when this directory listing was created.
-    pub generated_at: String,
+#[derive(Object, serde::Serialize)]
+struct AssetView {
+    /// Path relative to the project root
+    path: String,
+    size_bytes: u64,
+    /// Media type from the extension, e.g. `image/png`
+    content_type: String,
+    /// Pixel width, for PNG, JPEG, GIF, WebP, BMP and ICO images
+    width: Option<u32>,
+    height: Option<u32>,
+    /// Last modification, in Unix seconds
+    modified: Option<u64>,
 }
 
 #[derive(Object, serde::Serialize)]
-pub struct GalateaFileEntry {
-    /// Relative path from galatea_files root
-    ///
-    /// Path to the file or directory, relative to the galatea_files directory.
-    /// Uses forward slashes as path separators.
-    pub path: String,
+struct AssetListResponse {
+    /// The listed directory, relative to the project root
+    dir: String,
+    assets: Vec<AssetView>,
+    /// Size of the listed assets together
+    total_bytes: u64,
+    /// Whether `[assets] max_entries` cut the listing short
+    truncated: bool,
+}
 
-    /// Whether this entry is a directory
-    ///
-    /// `true` if this entry represents a directory, `false` if it's a file.
-    pub is_directory: bool,
+#[derive(Object, serde::Deserialize)]
+struct OptimizeAssetRequest {
+    /// **Required.** Image to optimize, relative to the project root
+    #[oai(validator(min_length = 1))]
+    path: String,
+    /// Scale the image down to at most this many pixels wide, keeping its aspect ratio
+    #[oai(validator(minimum(value = "1")))]
+    max_width: Option<u32>,
+    /// Scale the image down to at most this many pixels high, keeping its aspect ratio
+    #[oai(validator(minimum(value = "1")))]
+    max_height: Option<u32>,
+    /// `webp` to convert the image to lossless WebP, saved next to it with a `.webp` extension
+    format: Option<String>,
+    /// Replace an existing `.webp` file when converting (default false)
+    overwrite: Option<bool>,
+}
 
-    /// File size in bytes (files only)
-    ///
-    /// Size of the file in bytes. Will be `null` for directories or if
-    /// the size could not be determined.
-    pub size_bytes: Option<u64>,
+#[derive(Object, serde::Serialize)]
+struct OptimizeAssetResponse {
+    /// The optimized image, relative to the project root
+    path: String,
+    /// The image it was made from
+    source: String,
+    size_bytes: u64,
+    width: u32,
+    height: u32,
+    original_size_bytes: u64,
+    original_width: u32,
+    original_height: u32,
+}
 
-    /// Last modified timestamp
-    ///
-    /// Unix timestamp (seconds since epoch) when this file or directory
-    /// was last modified. May be `null` if timestamp is unavailable.
-    pub modified_at: Option<u64>,
+#[derive(ApiResponse)]
+enum AssetListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<AssetListResponse>),
 }
 
 #[derive(ApiResponse)]
-enum GalateaFilesListApiResponse {
+enum OptimizeAssetApiResponse {
     #[oai(status = 200)]
-    Ok(OpenApiJson<GalateaFilesListResponse>),
-    #[oai(status = 500)]
-    InternalServerError(PlainText<String>),
+    Ok(OpenApiJson<OptimizeAssetResponse>),
 }
 
 #[OpenApi]
 impl ProjectApi {
-    /// Health check endpoint for the Project API
+    /// Get the project health score
+    ///
+    /// After each validation run (`POST /api/validation/run`) Galatea scores the project from 0
+    /// to 100: build passing, test pass rate, TypeScript and lint error counts, and bundle size
+    /// growth. Components a run didn't measure are left out of its score. Returns the latest
+    /// sample, its change from the previous run, what regressed, and the recent history, so
+    /// autonomous sessions have one number to keep from going down.
     ///
-    /// Returns a simple status message to verify that the Project API is running and accessible.
-    /// This endpoint can be used for monitoring and health checks.
+    /// Always returns 200 while the Project API is up, so it doubles as its health check.
     #[oai(path = "/health", method = "get")]
-    async fn project_health(&self) -> HealthResponse {
-        HealthResponse::Ok(PlainText("Project API route is healthy".to_string()))
+    async fn project_health(
+        &self,
+        /// Number of history samples to return (default 20, at most 500)
+        limit: Query<Option<usize>>,
+    ) -> Result<HealthResponse, GalateaError> {
+        let limit = limit.0.unwrap_or(20).clamp(1, 500);
+        // The previous sample is needed for the delta even when only one is asked for
+        match health::history(limit.max(2)) {
+            Ok(mut samples) => {
+                let (delta, regressions) = match (samples.first(), samples.get(1)) {
+                    (Some(latest), Some(previous)) => {
+                        (Some(health_delta(latest, previous)), health::regressions(latest, previous))
+                    }
+                    _ => (None, Vec::new()),
+                };
+                let latest = samples.first().cloned().map(Into::into);
+                samples.truncate(limit);
+                Ok(HealthResponse::Ok(OpenApiJson(ProjectHealthResponse {
+                    latest,
+                    delta,
+                    regressions,
+                    history: samples.into_iter().map(Into::into).collect(),
+                })))
+            }
+            Err(e) => Err(GalateaError::Internal(format!("Failed to read health history: {:#}", e))),
+        }
     }
 
     /// Update or create a galatea configuration file
@@ -212,46 +1436,30 @@ impl ProjectApi {
         &self,
         filename: OpenApiPath<String>,
         req: OpenApiJson<UpdateFileRequest>,
-    ) -> GalateaFileUpdateResponse {
+    ) -> Result<GalateaFileUpdateResponse, GalateaError> {
         // Validate filename
         if filename.0.is_empty() {
-            return GalateaFileUpdateResponse::BadRequest(PlainText(
+            return Err(GalateaError::BadRequest(
                 "Filename cannot be empty".to_string(),
             ));
         }
 
         // Check for path traversal attempts
         if filename.0.contains("..") || filename.0.contains("\\") {
-            return GalateaFileUpdateResponse::BadRequest(PlainText(
+            return Err(GalateaError::BadRequest(
                 "Invalid filename: path traversal not allowed".to_string(),
             ));
         }
 
-        let exe_path = match std::env::current_exe() {
-            Ok(ep) => ep,
-            Err(e) => {
-                return GalateaFileUpdateResponse::InternalServerError(PlainText(format!(
-                    "Failed to get executable path: {}",
-                    e
-                )))
-            }
-        };
-
-        let exe_dir = match exe_path.parent() {
-            Some(ed) => ed,
-            None => {
-                return GalateaFileUpdateResponse::InternalServerError(PlainText(
-                    "Failed to get executable directory".to_string(),
-                ))
-            }
+        let galatea_files_dir = match paths::galatea_files_dir() {
+            Ok(dir) => dir,
+            Err(e) => return Err(GalateaError::Internal(format!("{:#}", e))),
         };
-
-        let galatea_files_dir = exe_dir.join("galatea_files");
         let file_path = galatea_files_dir.join(&filename.0);
 
         // Security check: ensure the resolved path is within galatea_files
         if !file_path.starts_with(&galatea_files_dir) {
-            return GalateaFileUpdateResponse::BadRequest(PlainText(
+            return Err(GalateaError::BadRequest(
                 "Invalid file path: must be within galatea_files directory".to_string(),
             ));
         }
@@ -265,7 +1473,7 @@ impl ProjectApi {
             if let Some(parent) = file_path.parent() {
                 if !parent.exists() {
                     if let Err(e) = fs::create_dir_all(parent) {
-                        return GalateaFileUpdateResponse::InternalServerError(PlainText(format!(
+                        return Err(GalateaError::Internal(format!(
                             "Failed to create parent directories for '{}': {}",
                             filename.0, e
                         )));
@@ -281,20 +1489,28 @@ impl ProjectApi {
                 file_path.extension().and_then(|s| s.to_str()).unwrap_or("")
             ));
             if let Err(e) = fs::copy(&file_path, &backup_path) {
-                return GalateaFileUpdateResponse::InternalServerError(PlainText(format!(
+                return Err(GalateaError::Internal(format!(
                     "Failed to create backup of '{}': {}",
                     filename.0, e
                 )));
             }
         }
 
+        // A client-written structure is diffed like a refresh, so subscribers see the change
+        let previous_structure = (filename.0 == "project_structure.json")
+            .then(structure::load_structure)
+            .flatten();
+
         // Write the file
         if let Err(e) = fs::write(&file_path, &req.0.content) {
-            return GalateaFileUpdateResponse::InternalServerError(PlainText(format!(
+            return Err(GalateaError::Internal(format!(
                 "Failed to write file '{}': {}",
                 filename.0, e
             )));
         }
+        if previous_structure.is_some() {
+            structure::record_file_update(previous_structure, &req.0.content);
+        }
 
         let action = if file_existed { "updated" } else { "created" };
         let timestamp = SystemTime::now()
@@ -303,7 +1519,7 @@ impl ProjectApi {
             .as_secs()
             .to_string();
 
-        GalateaFileUpdateResponse::Ok(OpenApiJson(ScriptResponse {
+        Ok(GalateaFileUpdateResponse::Ok(OpenApiJson(ScriptResponse {
             success: true,
             stdout: format!("File '{}' {} successfully", filename.0, action),
             stderr: String::new(),
@@ -311,7 +1527,7 @@ impl ProjectApi {
             operation: format!("galatea_file_{}", action),
             executed_at: timestamp,
             duration_ms: Some(0), // File operations are typically very fast
-        }))
+        })))
     }
 
     /// Get the contents of a galatea configuration file
@@ -343,53 +1559,37 @@ impl ProjectApi {
     async fn get_galatea_file_handler(
         &self,
         filename: OpenApiPath<String>,
-    ) -> GalateaFileGetResponse {
+    ) -> Result<GalateaFileGetResponse, GalateaError> {
         // Validate filename
         if filename.0.is_empty() {
-            return GalateaFileGetResponse::BadRequest(PlainText(
+            return Err(GalateaError::BadRequest(
                 "Filename cannot be empty".to_string(),
             ));
         }
 
         // Check for path traversal attempts
         if filename.0.contains("..") || filename.0.contains("\\") {
-            return GalateaFileGetResponse::BadRequest(PlainText(
+            return Err(GalateaError::BadRequest(
                 "Invalid filename: path traversal not allowed".to_string(),
             ));
         }
 
-        let exe_path = match std::env::current_exe() {
-            Ok(ep) => ep,
-            Err(e) => {
-                return GalateaFileGetResponse::InternalServerError(PlainText(format!(
-                    "Failed to get executable path: {}",
-                    e
-                )))
-            }
-        };
-
-        let exe_dir = match exe_path.parent() {
-            Some(ed) => ed,
-            None => {
-                return GalateaFileGetResponse::InternalServerError(PlainText(
-                    "Failed to get executable directory".to_string(),
-                ))
-            }
+        let galatea_files_dir = match paths::galatea_files_dir() {
+            Ok(dir) => dir,
+            Err(e) => return Err(GalateaError::Internal(format!("{:#}", e))),
         };
-
-        let galatea_files_dir = exe_dir.join("galatea_files");
         let file_path = galatea_files_dir.join(&filename.0);
 
         // Security check: ensure the resolved path is within galatea_files
         if !file_path.starts_with(&galatea_files_dir) {
-            return GalateaFileGetResponse::BadRequest(PlainText(
+            return Err(GalateaError::BadRequest(
                 "Invalid file path: must be within galatea_files directory".to_string(),
             ));
         }
 
         // Check if file exists
         if !file_path.exists() {
-            return GalateaFileGetResponse::NotFound(PlainText(format!(
+            return Err(GalateaError::NotFound(format!(
                 "File not found: {}",
                 filename.0
             )));
@@ -397,7 +1597,7 @@ impl ProjectApi {
 
         // Check if it's actually a file (not a directory)
         if !file_path.is_file() {
-            return GalateaFileGetResponse::BadRequest(PlainText(format!(
+            return Err(GalateaError::BadRequest(format!(
                 "Path is not a file: {}",
                 filename.0
             )));
@@ -405,16 +1605,16 @@ impl ProjectApi {
 
         // Read and return file content
         match fs::read_to_string(&file_path) {
-            Ok(content) => GalateaFileGetResponse::Ok(PlainText(content)),
+            Ok(content) => Ok(GalateaFileGetResponse::Ok(PlainText(content))),
             Err(e) => {
                 // Determine appropriate error response based on error type
                 if e.kind() == std::io::ErrorKind::PermissionDenied {
-                    GalateaFileGetResponse::BadRequest(PlainText(format!(
+                    Err(GalateaError::BadRequest(format!(
                         "Permission denied reading file '{}': {}",
                         filename.0, e
                     )))
                 } else {
-                    GalateaFileGetResponse::InternalServerError(PlainText(format!(
+                    Err(GalateaError::Internal(format!(
                         "Failed to read file '{}': {}",
                         filename.0, e
                     )))
@@ -463,30 +1663,14 @@ impl ProjectApi {
     /// }
     /// ```
     #[oai(path = "/list-galatea-files", method = "get")]
-    async fn list_galatea_files_handler(&self) -> GalateaFilesListApiResponse {
-        let exe_path = match std::env::current_exe() {
-            Ok(ep) => ep,
-            Err(e) => {
-                return GalateaFilesListApiResponse::InternalServerError(PlainText(format!(
-                    "Failed to get executable path: {}",
-                    e
-                )))
-            }
+    async fn list_galatea_files_handler(&self) -> Result<GalateaFilesListApiResponse, GalateaError> {
+        let galatea_files_dir = match paths::galatea_files_dir() {
+            Ok(dir) => dir,
+            Err(e) => return Err(GalateaError::Internal(format!("{:#}", e))),
         };
 
-        let exe_dir = match exe_path.parent() {
-            Some(ed) => ed,
-            None => {
-                return GalateaFilesListApiResponse::InternalServerError(PlainText(
-                    "Failed to get executable directory".to_string(),
-                ))
-            }
-        };
-
-        let galatea_files_dir = exe_dir.join("galatea_files");
-
         if !galatea_files_dir.exists() {
-            return GalateaFilesListApiResponse::InternalServerError(PlainText(
+            return Err(GalateaError::Internal(
                 "galatea_files directory does not exist".to_string(),
             ));
         }
@@ -579,11 +1763,817 @@ impl ProjectApi {
             .as_secs()
             .to_string();
 
-        GalateaFilesListApiResponse::Ok(OpenApiJson(GalateaFilesListResponse {
+        Ok(GalateaFilesListApiResponse::Ok(OpenApiJson(GalateaFilesListResponse {
             entries,
             total_count,
             generated_at: timestamp,
-        }))
+        })))
+    }
+
+    /// Refresh the OpenAPI specs of Galatea's APIs
+    ///
+    /// Writes the specs of Galatea's own APIs, as served by this binary, to
+    /// `galatea_files/openapi_specification/`, as is done at startup. Only files whose
+    /// contents changed are rewritten, and specs of APIs that no longer exist are removed;
+    /// spec files added by hand are left alone. MCP servers launched from a changed spec are
+    /// regenerated and restarted, which can take a while; the regeneration is listed in
+    /// `/api/jobs` with its progress meanwhile. Servers for new specs are launched at the next
+    /// start.
+    #[oai(path = "/openapi/refresh", method = "post")]
+    async fn refresh_openapi_handler(&self) -> Result<OpenApiRefreshApiResponse, GalateaError> {
+        let changed = config_files::refresh_openapi_specs()?;
+        let specs = changed.clone();
+        let regenerated = jobs::run("mcp_generation", "Regenerate MCP servers from refreshed OpenAPI specs".to_string(), move |job| async move {
+            let results = mcp_server::regenerate_servers(&specs, &job).await?;
+            let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+            let summary = format!("Regenerated {} MCP server(s), {} failed", results.len() - failed, failed);
+            Ok((results, summary))
+        })
+        .await?;
+        let mcp_servers = regenerated
+            .into_iter()
+            .map(|(id, result)| McpRegenerationView { id, error: result.err().map(|e| format!("{:#}", e)) })
+            .collect();
+        Ok(OpenApiRefreshApiResponse::Ok(OpenApiJson(OpenApiRefreshResponse { changed, mcp_servers })))
+    }
+
+    /// Show the effective configuration
+    ///
+    /// Galatea reads `galatea_files/config.toml` and, when an environment is selected with
+    /// `--env <name>` or `GALATEA_ENV=<name>`, overlays `galatea_files/config.<name>.toml` on it.
+    /// Precedence, lowest first: `config.toml`, then the environment overlay; tables are merged
+    /// key by key and any other value in the overlay replaces the base value. Returns every
+    /// merged value with the layer it came from. Writes through the API always go to
+    /// `config.toml`, so a value set in the overlay keeps winning.
+    #[oai(path = "/config/effective", method = "get")]
+    async fn effective_config_handler(&self) -> Result<EffectiveConfigApiResponse, GalateaError> {
+        match config_files::effective_config() {
+            Ok(config) => Ok(EffectiveConfigApiResponse::Ok(OpenApiJson(EffectiveConfigResponse {
+                environment: config.environment,
+                layers: config
+                    .layers
+                    .into_iter()
+                    .map(|layer| ConfigLayerView {
+                        name: layer.name,
+                        path: layer.path.display().to_string(),
+                        present: layer.present,
+                        error: layer.error,
+                    })
+                    .collect(),
+                values: config
+                    .values
+                    .into_iter()
+                    .map(|v| EffectiveConfigValue {
+                        key: v.key,
+                        value: toml_to_json(v.value),
+                        source: v.source,
+                        masked: v.masked,
+                    })
+                    .collect(),
+            }))),
+            Err(e) => Err(GalateaError::Internal(format!("{:#}", e))),
+        }
+    }
+
+    /// Show the configuration
+    ///
+    /// The merged configuration (see `/config/effective` for where each value comes from)
+    /// and what is wrong with it: values of the wrong type, unknown templates or package
+    /// managers, malformed URLs, and keys Galatea doesn't read.
+    #[oai(path = "/config", method = "get")]
+    async fn config_handler(&self) -> Result<ConfigApiResponse, GalateaError> {
+        let (_, problems) = config_schema::check(&config_files::merged_config().unwrap_or_default(), false);
+        Ok(ConfigApiResponse::Ok(OpenApiJson(ConfigResponse {
+            environment: config_files::config_environment(),
+            config: masked_config_json(),
+            problems: problems.into_iter().map(ConfigProblemView::from).collect(),
+        })))
+    }
+
+    /// Update the configuration
+    ///
+    /// Applies `changes` to `config.toml` as a JSON merge patch and validates the result before
+    /// writing it. Changed keys that are invalid, or unknown without `allow_unknown`, refuse
+    /// the whole update with a `400` of code `invalid` listing them in `details.problems`;
+    /// problems elsewhere in the file don't. Top-level values are
+    /// stored as strings (`offline = "true"`), as Galatea reads them.
+    ///
+    /// Most settings are read when used and apply at once. `[logs]` is reloaded, and a change
+    /// to `[project]` or `package_manager` restarts a running dev server. Settings read at
+    /// startup are listed in `restart_required`. A value set in the active `config.<env>.toml`
+    /// overlay keeps winning over the one written.
+    #[oai(path = "/config", method = "patch")]
+    async fn update_config_handler(&self, req: OpenApiJson<ConfigPatchRequest>) -> Result<ConfigUpdateApiResponse, GalateaError> {
+        let Some(changes) = req.0.changes.as_object() else {
+            return Err(invalid_config(vec![ConfigProblem { key: "changes".to_string(), message: "Must be a JSON object".to_string() }]));
+        };
+        match config_schema::update(changes, req.0.allow_unknown.unwrap_or(false)).await {
+            Ok(update) => Ok(ConfigUpdateApiResponse::Ok(OpenApiJson(ConfigUpdateResponse {
+                changed: update.changed,
+                reloaded: update.reloaded,
+                restart_required: update.restart_required,
+                config: masked_config_json(),
+            }))),
+            Err(ConfigUpdateError::Invalid(problems)) => Err(invalid_config(problems)),
+            Err(ConfigUpdateError::Failed(e)) => Err(e.into()),
+        }
+    }
+
+    /// List the built-in project templates
+    ///
+    /// Templates that can be passed by name to `--template`, the setup wizard and
+    /// `/rescaffold`, with how their dev server is run. A git URL is also accepted as a
+    /// template and runs like `nextjs`. `/templates/{name}/variables` lists what a
+    /// template can be customised with.
+    #[oai(path = "/templates", method = "get")]
+    async fn templates_handler(&self) -> Result<TemplatesApiResponse, GalateaError> {
+        let package_manager = PackageManager::current();
+        let current = config_files::get_config_value("template");
+        let templates = template_registry::all()
+            .iter()
+            .map(|t| TemplateSummary {
+                name: t.name.to_string(),
+                display_name: t.display_name.to_string(),
+                description: t.description.to_string(),
+                framework: t.framework.to_string(),
+                source_url: t.source.to_string(),
+                dev_command: t.dev_command(package_manager).join(" "),
+                port: t.port,
+                scripts: t
+                    .scripts
+                    .iter()
+                    .map(|(name, description)| TemplateScriptInfo { name: name.to_string(), description: description.to_string() })
+                    .collect(),
+                current: current.as_deref().unwrap_or(template_registry::DEFAULT_TEMPLATE) == t.name,
+            })
+            .collect();
+        Ok(TemplatesApiResponse::Ok(OpenApiJson(TemplatesResponse {
+            default_template: template_registry::DEFAULT_TEMPLATE.to_string(),
+            templates,
+        })))
+    }
+
+    /// List the variables a template accepts
+    ///
+    /// Reads the template's `galatea.template.toml` so UIs can collect values before
+    /// scaffolding. `name` is either a built-in template (see `/templates`) or a git URL
+    /// (URL-encoded). Values are passed to Galatea as `--template-var key=value` and
+    /// substituted into `{{key}}` placeholders of the scaffolded files.
+    ///
+    /// For the template the current project was scaffolded from, the local copy is read;
+    /// otherwise the template is fetched with a shallow clone.
+    #[oai(path = "/templates/:name/variables", method = "get")]
+    async fn template_variables_handler(&self, name: OpenApiPath<String>) -> Result<TemplateVariablesApiResponse, GalateaError> {
+        let template_name = name.0;
+        let source_url = template::resolve_template_url(Some(&template_name)).to_string();
+
+        let is_current_template = config_files::get_config_value("template")
+            .map(|current| template::resolve_template_url(Some(&current)) == source_url)
+            .unwrap_or(false);
+        let local_metadata = if is_current_template {
+            get_project_root().ok().and_then(|root| template::read_metadata(&root).transpose())
+        } else {
+            None
+        };
+        let metadata = match local_metadata {
+            Some(result) => result.map(Some),
+            None => template::fetch_template_metadata(&template_name).await,
+        };
+
+        match metadata {
+            Ok(metadata) => {
+                let has_metadata = metadata.is_some();
+                let variables = metadata
+                    .map(|m| m.variables)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|v| TemplateVariableInfo {
+                        default: v.default_as_string(),
+                        var_type: format!("{:?}", v.var_type).to_lowercase(),
+                        name: v.name,
+                        description: v.description,
+                        options: v.options,
+                        required: v.required,
+                    })
+                    .collect();
+                Ok(TemplateVariablesApiResponse::Ok(OpenApiJson(TemplateVariablesResponse {
+                    template: template_name,
+                    source_url,
+                    has_metadata,
+                    variables,
+                })))
+            }
+            Err(e) => Err(GalateaError::Internal(format!(
+                "Failed to read template variables: {:#}",
+                e
+            ))),
+        }
+    }
+
+    /// Get the scaffold status
+    ///
+    /// Reports whether the project was left half-scaffolded by an interrupted clone or install.
+    /// Galatea resumes such scaffolds automatically at startup.
+    #[oai(path = "/scaffold", method = "get")]
+    async fn scaffold_status_handler(&self) -> Result<ScaffoldStatusApiResponse, GalateaError> {
+        match get_project_root() {
+            Ok(root) => Ok(ScaffoldStatusApiResponse::Ok(OpenApiJson(scaffold_status(&root)))),
+            Err(e) => Err(GalateaError::Internal(e.to_string())),
+        }
+    }
+
+    /// Resume or redo the project scaffold
+    ///
+    /// Resumes an interrupted scaffold, or with `force: true` deletes the project and scaffolds
+    /// it again from the template, like the `--force-rescaffold` flag. Uncommitted work in the
+    /// project is lost when forcing. Restart the dev server afterwards.
+    ///
+    /// Returns `409` when the scaffold is already complete and `force` is not set.
+    #[oai(path = "/rescaffold", method = "post")]
+    async fn rescaffold_handler(&self, req: OpenApiJson<RescaffoldRequest>) -> Result<RescaffoldApiResponse, GalateaError> {
+        let req = req.0;
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let force = req.force.unwrap_or(false);
+        if !force && !nextjs::is_scaffold_incomplete(&project_root) {
+            return Err(GalateaError::Conflict(
+                "The project scaffold is already complete. Pass force: true to scaffold it again.".to_string(),
+            ));
+        }
+
+        let template_name = req.template.or_else(|| config_files::get_config_value("template"));
+        let template_url = template::resolve_template_url(template_name.as_deref());
+        let template_vars = req.template_vars.unwrap_or_default();
+        let result = if force {
+            nextjs::rescaffold_nextjs_project(&project_root, template_url, &template_vars).await
+        } else {
+            nextjs::scaffold_nextjs_project(&project_root, template_url, &template_vars).await
+        };
+        match result {
+            Ok(()) => Ok(RescaffoldApiResponse::Ok(OpenApiJson(scaffold_status(&project_root)))),
+            Err(e) => Err(GalateaError::Internal(format!(
+                "Failed to scaffold project: {:#}",
+                e
+            ))),
+        }
+    }
+
+    /// List the project's dependencies
+    ///
+    /// Every package declared in package.json, with the version installed in `node_modules`.
+    /// With `outdated` (the default), also what the package manager's `outdated` reports: the
+    /// highest version within the declared range and the latest published one. Checking needs
+    /// the registry, so it is skipped in offline mode unless the registry cache runs.
+    ///
+    /// - `outdated`: check for newer versions (default `true`)
+    #[oai(path = "/dependencies", method = "get")]
+    async fn list_dependencies_handler(&self, outdated: Query<Option<bool>>) -> Result<DependenciesApiResponse, GalateaError> {
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let check_outdated = outdated.0.unwrap_or(true)
+            && (!crate::dev_setup::offline::is_offline() || crate::dev_setup::registry_cache::registry_url().is_some());
+        match dependencies::list(&project_root, check_outdated).await {
+            Ok(deps) => Ok(DependenciesApiResponse::Ok(OpenApiJson(DependenciesResponse {
+                package_manager: PackageManager::detect(&project_root).name().to_string(),
+                outdated_checked: check_outdated,
+                dependencies: deps.into_iter().map(DependencyView::from).collect(),
+            }))),
+            Err(e) => Err(GalateaError::Internal(format!("Failed to list dependencies: {:#}", e))),
+        }
+    }
+
+    /// Add dependencies
+    ///
+    /// Adds packages to package.json and installs them (`pnpm add`, or `npm install` when
+    /// npm is the package manager). Answers `200` with the command's output and the
+    /// package.json changes whether or not it succeeded; check `success`. Answers `400` for
+    /// anything that is not a registry package name with an optional `@version`.
+    #[oai(path = "/dependencies", method = "post")]
+    async fn add_dependencies_handler(&self, req: OpenApiJson<AddDependenciesRequest>) -> Result<PackageManagerApiResponse, GalateaError> {
+        if let Err(e) = req.0.packages.iter().try_for_each(|p| dependencies::validate_package_spec(p)) {
+            return Err(GalateaError::BadRequest(e.to_string()));
+        }
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        package_manager_response(dependencies::add(&project_root, &req.0.packages, req.0.dev.unwrap_or(false)).await)
+    }
+
+    /// Remove dependencies
+    ///
+    /// Removes packages from package.json and `node_modules` (`pnpm remove` or
+    /// `npm uninstall`).
+    ///
+    /// - `packages`: comma-separated package names, e.g. `lodash,@types/lodash`
+    #[oai(path = "/dependencies", method = "delete")]
+    async fn remove_dependencies_handler(&self, packages: Query<String>) -> Result<PackageManagerApiResponse, GalateaError> {
+        let packages: Vec<String> = packages.0.split(',').map(str::trim).filter(|p| !p.is_empty()).map(str::to_string).collect();
+        if packages.is_empty() {
+            return Err(GalateaError::BadRequest("No packages given".to_string()));
+        }
+        if let Err(e) = packages.iter().try_for_each(|p| dependencies::validate_package_spec(p)) {
+            return Err(GalateaError::BadRequest(e.to_string()));
+        }
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        package_manager_response(dependencies::remove(&project_root, &packages).await)
+    }
+
+    /// Upgrade dependencies
+    ///
+    /// Upgrades the given packages, or all of them, within their declared ranges; with
+    /// `latest`, to the latest versions, rewriting the ranges in package.json.
+    #[oai(path = "/dependencies/upgrade", method = "post")]
+    async fn upgrade_dependencies_handler(&self, req: OpenApiJson<UpgradeDependenciesRequest>) -> Result<PackageManagerApiResponse, GalateaError> {
+        let packages = req.0.packages.unwrap_or_default();
+        if let Err(e) = packages.iter().try_for_each(|p| dependencies::validate_package_spec(p)) {
+            return Err(GalateaError::BadRequest(e.to_string()));
+        }
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        package_manager_response(dependencies::upgrade(&project_root, &packages, req.0.latest.unwrap_or(false)).await)
+    }
+
+    /// Reset the project
+    ///
+    /// Wipes the project and scaffolds it again from the template (`rescaffold`), or resets it
+    /// to the template commit it was cloned from (`template_initial`). All work in the project
+    /// is lost either way. The dev server is stopped meanwhile and started again afterwards if
+    /// it was running.
+    ///
+    /// A request without `confirm_token` changes nothing and answers `428` with a token and
+    /// what the reset would discard; repeat it with the token within `expires_in_secs` to go
+    /// ahead. The reset then runs in the background: this answers `202` and `GET /reset`
+    /// reports its progress.
+    ///
+    /// Answers `409` while another reset is running, or when `template_initial` is not
+    /// possible because the project has no record of its template commit, and `403` for an
+    /// unknown or expired token.
+    #[oai(path = "/reset", method = "post")]
+    async fn reset_handler(&self, req: OpenApiJson<ResetRequest>) -> Result<ResetApiResponse, GalateaError> {
+        let req = req.0;
+        let mode = match req.mode.as_deref() {
+            None => None,
+            Some(name) => match ResetMode::from_name(name) {
+                Some(mode) => Some(mode),
+                None => {
+                    return Err(GalateaError::BadRequest(format!(
+                        "Unknown mode '{}'. Use rescaffold or template_initial.",
+                        name
+                    )))
+                }
+            },
+        };
+        let options = ResetOptions {
+            mode,
+            template: req.template,
+            template_vars: req.template_vars.unwrap_or_default(),
+        };
+        match reset::start(options, req.confirm_token.as_deref()).await {
+            Ok(progress) => Ok(ResetApiResponse::Accepted(OpenApiJson(progress.into()))),
+            Err(ResetError::ConfirmationRequired(c)) => Ok(ResetApiResponse::ConfirmationRequired(OpenApiJson(ResetConfirmationResponse {
+                confirm_token: c.token,
+                expires_in_secs: c.expires_in_secs,
+                mode: c.mode.as_str().to_string(),
+                uncommitted_files: c.uncommitted_files,
+                commits_since_template: c.commits_since_template,
+            }))),
+            Err(e @ ResetError::InvalidConfirmation) => Err(GalateaError::Forbidden(e.to_string())),
+            Err(e @ (ResetError::InProgress(_) | ResetError::Unavailable(_))) => Err(GalateaError::Conflict(e.to_string())),
+            Err(e @ ResetError::Failed(_)) => Err(GalateaError::Internal(e.to_string())),
+        }
+    }
+
+    /// Get the reset progress
+    ///
+    /// The running reset, or the last one since Galatea started. Returns `404` if there was none.
+    #[oai(path = "/reset", method = "get")]
+    async fn reset_progress_handler(&self) -> Result<ResetProgressApiResponse, GalateaError> {
+        match reset::progress() {
+            Some(progress) => Ok(ResetProgressApiResponse::Ok(OpenApiJson(progress.into()))),
+            None => Err(GalateaError::NotFound("No reset has run since Galatea started".to_string())),
+        }
+    }
+
+    /// Get the current changelog
+    ///
+    /// Returns the content of `galatea_files/CHANGELOG.md` as last generated.
+    /// Returns `404` if no changelog has been generated yet.
+    #[oai(path = "/changelog", method = "get")]
+    async fn get_changelog_handler(&self) -> Result<ChangelogApiResponse, GalateaError> {
+        let path = match changelog::changelog_path() {
+            Ok(p) => p,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        match fs::read_to_string(&path) {
+            Ok(markdown) => Ok(ChangelogApiResponse::Ok(OpenApiJson(ChangelogResponse {
+                path: path.display().to_string(),
+                markdown,
+                days: Vec::new(),
+            }))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(GalateaError::NotFound("Changelog has not been generated yet".to_string())),
+            Err(e) => Err(GalateaError::Internal(format!(
+                "Failed to read changelog: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Generate the changelog
+    ///
+    /// Summarizes the project's git history into `galatea_files/CHANGELOG.md`, one entry per
+    /// session and day listing tasks, commits and files changed. The file is rewritten on every
+    /// call; a repository without commits gets an empty changelog.
+    ///
+    /// Tasks come from `Task: <name>` trailers in commit messages. The changelog can also be
+    /// regenerated periodically by setting `changelog_interval_minutes` in `config.toml`.
+    /// Answers `503` with code `capability_unavailable` when git is not installed.
+    #[oai(path = "/changelog", method = "post")]
+    async fn generate_changelog_handler(
+        &self,
+        req: OpenApiJson<GenerateChangelogRequest>,
+    ) -> Result<ChangelogApiResponse, GalateaError> {
+        if let Err(e) = capabilities::require(Capability::Git) {
+            return Err(GalateaError::CapabilityUnavailable(e));
+        }
+        let project_dir = match get_project_root() {
+            Ok(dir) => dir,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        match changelog::generate_changelog(&project_dir, req.0.days).await {
+            Ok(summary) => Ok(ChangelogApiResponse::Ok(OpenApiJson(ChangelogResponse {
+                path: summary.path.display().to_string(),
+                markdown: summary.markdown,
+                days: summary
+                    .days
+                    .into_iter()
+                    .map(|day| ChangelogDayEntry {
+                        date: day.date,
+                        session: day.session,
+                        tasks: day.tasks,
+                        commits: day.commits.into_iter().map(|c| c.subject).collect(),
+                        files_changed: day.files,
+                    })
+                    .collect(),
+            }))),
+            Err(e) => Err(GalateaError::Internal(format!(
+                "Failed to generate changelog: {:#}",
+                e
+            ))),
+        }
+    }
+
+    /// List project structure changes
+    ///
+    /// Every refresh of `project_structure.json` is diffed against the previous snapshot, and
+    /// non-empty diffs (routes, API routes, components and directories added or removed) are
+    /// recorded here. Pass the last `seq` seen as `since` to get only newer changes; without
+    /// it, the most recent `limit` changes (default 50) are returned.
+    ///
+    /// The structure is refreshed at startup, by `POST /structure/refresh`, every
+    /// `structure_refresh_interval_minutes` (config.toml) if set, and whenever a client writes
+    /// `project_structure.json` through `PUT /galatea-file/project_structure.json`.
+    #[oai(path = "/structure-changes", method = "get")]
+    async fn structure_changes_handler(
+        &self,
+        since: Query<Option<u64>>,
+        limit: Query<Option<usize>>,
+    ) -> Result<StructureChangesApiResponse, GalateaError> {
+        let changes = structure::changes(since.0, limit.0.unwrap_or(50).max(1));
+        Ok(StructureChangesApiResponse::Ok(OpenApiJson(StructureChangesResponse {
+            last_seq: changes.last().map(|c| c.seq).or(since.0),
+            changes: changes.into_iter().map(Into::into).collect(),
+        })))
+    }
+
+    /// Get the project structure
+    ///
+    /// Returns the last snapshot of the project: its routes, API routes and component files, and
+    /// its directory tree with, per file, the parser language, entity count and exported names
+    /// from the code index. Dependency and build directories are left out. With no snapshot yet
+    /// (or `refresh=true`), the project is scanned first.
+    #[oai(path = "/structure", method = "get")]
+    async fn structure_handler(
+        &self,
+        /// **Optional.** Rescan the project before answering. Defaults to false.
+        refresh: Query<Option<bool>>,
+        /// **Optional.** Levels of the tree to return, 1 for top-level entries only. Deeper
+        /// directories come without children but with their counts. Defaults to the whole tree.
+        max_depth: Query<Option<usize>>,
+    ) -> Result<ProjectStructureApiResponse, GalateaError> {
+        let snapshot = match structure::load_structure().filter(|_| !refresh.0.unwrap_or(false)) {
+            Some(snapshot) => snapshot,
+            None => {
+                let project_dir = match get_project_root() {
+                    Ok(dir) => dir,
+                    Err(e) => return Err(GalateaError::Internal(e.to_string())),
+                };
+                if let Err(e) = structure::refresh(&project_dir, "api").await {
+                    return Err(GalateaError::Internal(format!(
+                        "Failed to refresh project structure: {:#}",
+                        e
+                    )));
+                }
+                match structure::load_structure() {
+                    Some(snapshot) => snapshot,
+                    None => {
+                        return Err(GalateaError::Internal(
+                            "project_structure.json could not be read back after the refresh".to_string(),
+                        ))
+                    }
+                }
+            }
+        };
+        let tree = match max_depth.0 {
+            Some(depth) => structure_tree::truncate(snapshot.tree, depth.max(1)),
+            None => snapshot.tree,
+        };
+        Ok(ProjectStructureApiResponse::Ok(OpenApiJson(ProjectStructureResponse {
+            generated_at: snapshot.generated_at,
+            routes: snapshot.routes,
+            api_routes: snapshot.api_routes,
+            components: snapshot.components,
+            tree: tree.into_iter().map(Into::into).collect(),
+        })))
+    }
+
+    /// Refresh the project structure
+    ///
+    /// Rescans the project, rewrites `galatea_files/project_structure.json` (served by
+    /// `GET /structure`) and returns what changed since the previous snapshot, also recording it
+    /// for `GET /structure-changes`.
+    #[oai(path = "/structure/refresh", method = "post")]
+    async fn refresh_structure_handler(&self) -> Result<StructureRefreshApiResponse, GalateaError> {
+        let project_dir = match get_project_root() {
+            Ok(dir) => dir,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let path = match structure::structure_path() {
+            Ok(p) => p,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        match structure::refresh(&project_dir, "api").await {
+            Ok(change) => Ok(StructureRefreshApiResponse::Ok(OpenApiJson(Box::new(StructureRefreshResponse {
+                path: path.display().to_string(),
+                change: change.map(Into::into),
+            })))),
+            Err(e) => Err(GalateaError::Internal(format!(
+                "Failed to refresh project structure: {:#}",
+                e
+            ))),
+        }
+    }
+
+    /// Sync the project with another directory
+    ///
+    /// Mirrors the project to or from `target`, once or continuously, so a copy edited in a local
+    /// IDE stays in lockstep with the sandbox. Changes are detected by size and modification time;
+    /// a file changed on both sides since the last pass is a conflict, resolved by
+    /// `conflict_policy`. Dependency and build directories are never synced.
+    ///
+    /// With `continuous: true` a background session is started after the first pass and
+    /// returned in `session`; list sessions with `GET /sync` and stop one with `DELETE /sync/{id}`.
+    /// Needs an `admin` token once API tokens are configured.
+    #[oai(path = "/sync", method = "post")]
+    async fn sync_handler(&self, req: OpenApiJson<SyncRequest>) -> Result<SyncApiResponse, GalateaError> {
+        let req = req.0;
+        let direction = match req.direction.as_deref() {
+            None => SyncDirection::Push,
+            Some(name) => match SyncDirection::from_name(name) {
+                Some(d) => d,
+                None => {
+                    return Err(GalateaError::BadRequest(format!(
+                        "Unknown direction '{}'. Use push, pull or both.",
+                        name
+                    )))
+                }
+            },
+        };
+        let conflict_policy = match req.conflict_policy.as_deref() {
+            None => ConflictPolicy::Newer,
+            Some(name) => match ConflictPolicy::from_name(name) {
+                Some(p) => p,
+                None => {
+                    return Err(GalateaError::BadRequest(format!(
+                        "Unknown conflict policy '{}'. Use newer, project, target or skip.",
+                        name
+                    )))
+                }
+            },
+        };
+        if req.target.trim().is_empty() {
+            return Err(GalateaError::BadRequest("target must not be empty".to_string()));
+        }
+        let project_dir = match get_project_root() {
+            Ok(dir) => dir,
+            Err(e) => return Err(GalateaError::Internal(e.to_string())),
+        };
+        let options = SyncOptions {
+            target: req.target,
+            direction,
+            conflict_policy,
+            excludes: req.exclude.unwrap_or_default(),
+            delete: req.delete.unwrap_or(false),
+        };
+
+        if req.continuous.unwrap_or(false) {
+            let interval = std::time::Duration::from_secs(req.interval_secs.unwrap_or(2));
+            match sync::start_session(project_dir, options, interval).await {
+                Ok(mut info) => Ok(SyncApiResponse::Ok(OpenApiJson(Box::new(SyncResponse {
+                    report: info.last_report.take().unwrap_or_default().into(),
+                    session: Some(info.into()),
+                })))),
+                Err(e) => Err(GalateaError::BadRequest(format!("Sync failed: {:#}", e))),
+            }
+        } else {
+            match sync::sync_once(&project_dir, &options).await {
+                Ok(report) => Ok(SyncApiResponse::Ok(OpenApiJson(Box::new(SyncResponse {
+                    report: report.into(),
+                    session: None,
+                })))),
+                Err(e) => Err(GalateaError::BadRequest(format!("Sync failed: {:#}", e))),
+            }
+        }
+    }
+
+    /// List continuous syncs
+    #[oai(path = "/sync", method = "get")]
+    async fn list_syncs_handler(&self) -> Result<SyncSessionsApiResponse, GalateaError> {
+        Ok(SyncSessionsApiResponse::Ok(OpenApiJson(SyncSessionsResponse {
+            sessions: sync::list_sessions().into_iter().map(SyncSessionView::from).collect(),
+        })))
+    }
+
+    /// Stop a continuous sync
+    ///
+    /// Returns the session's final state.
+    #[oai(path = "/sync/:id", method = "delete")]
+    async fn stop_sync_handler(&self, id: OpenApiPath<String>) -> Result<SyncStopApiResponse, GalateaError> {
+        match sync::stop_session(&id.0) {
+            Some(info) => Ok(SyncStopApiResponse::Ok(OpenApiJson(Box::new(info.into())))),
+            None => Err(GalateaError::NotFound(format!("Sync session '{}' not found", id.0))),
+        }
+    }
+
+    /// Take a snapshot of the workspace
+    ///
+    /// Archives every file of the project, untracked and ignored ones such as `.env` included,
+    /// together with `galatea_files` (config, changelog, specs, validation runs...), under
+    /// `galatea_files/snapshots/`. Left out are the directories named in `[snapshots] exclude`
+    /// (by default `node_modules` and `.next`, at any depth) and galatea_files' database,
+    /// caches, event log and other workspaces. The oldest snapshots beyond `[snapshots] max`
+    /// (default 10) are deleted. Runs as a `snapshot` job, listed in `/api/jobs`.
+    #[oai(path = "/snapshots", method = "post")]
+    async fn create_snapshot_handler(&self, req: OpenApiJson<CreateSnapshotRequest>) -> Result<SnapshotApiResponse, GalateaError> {
+        let label = req.0.label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).unwrap_or_else(|| "Snapshot".to_string());
+        let snapshot = jobs::run("snapshot", format!("Snapshot: {}", label), move |_job| async move {
+            let snapshot = snapshots::create(&label).await?;
+            let summary = format!("Snapshot {} of {} project files", snapshot.id, snapshot.project_files);
+            Ok((snapshot, summary))
+        })
+        .await?;
+        Ok(SnapshotApiResponse::Ok(OpenApiJson(snapshot.into())))
+    }
+
+    /// List workspace snapshots
+    #[oai(path = "/snapshots", method = "get")]
+    async fn list_snapshots_handler(&self) -> Result<SnapshotListApiResponse, GalateaError> {
+        let snapshots = snapshots::list()?.into_iter().map(SnapshotView::from).collect();
+        Ok(SnapshotListApiResponse::Ok(OpenApiJson(SnapshotListResponse { snapshots })))
+    }
+
+    /// Restore a workspace snapshot
+    ///
+    /// Brings the project the snapshot was taken of and `galatea_files` back to it: files are
+    /// rewritten and files created since are deleted, while what snapshots leave out (e.g.
+    /// `node_modules`) is left alone. The current state is snapshotted first, and that
+    /// snapshot is returned as `backup` so the restore can be undone. Settings restored with
+    /// config.toml apply from the next request that reads them; a changed port or API token
+    /// needs a restart. Runs as a `snapshot` job. Returns `404` for an unknown snapshot.
+    #[oai(path = "/snapshots/:id/restore", method = "post")]
+    async fn restore_snapshot_handler(&self, id: OpenApiPath<String>) -> Result<SnapshotRestoreApiResponse, GalateaError> {
+        if !snapshots::list()?.iter().any(|s| s.id == id.0) {
+            return Err(GalateaError::NotFound(format!("Snapshot '{}' not found", id.0)));
+        }
+        let restored = jobs::run("snapshot", format!("Restore snapshot {}", id.0), move |_job| async move {
+            let restored = snapshots::restore(&id.0).await?;
+            let summary = format!("Restored snapshot {}, deleted {} file(s)", restored.snapshot.id, restored.removed.len());
+            Ok((restored, summary))
+        })
+        .await?;
+        Ok(SnapshotRestoreApiResponse::Ok(OpenApiJson(SnapshotRestoreResponse {
+            snapshot: restored.snapshot.into(),
+            backup: restored.backup.into(),
+            removed: restored.removed,
+        })))
+    }
+
+    /// Delete a workspace snapshot
+    #[oai(path = "/snapshots/:id", method = "delete")]
+    async fn delete_snapshot_handler(&self, id: OpenApiPath<String>) -> Result<SnapshotDeleteApiResponse, GalateaError> {
+        if !snapshots::delete(&id.0).await? {
+            return Err(GalateaError::NotFound(format!("Snapshot '{}' not found", id.0)));
+        }
+        Ok(SnapshotDeleteApiResponse::Ok(OpenApiJson(SnapshotDeleteResponse { id: id.0 })))
+    }
+
+    /// List project assets
+    ///
+    /// Lists the files under the assets directory (`dir`, else `[assets] dir`, default
+    /// `public`) by path, with their size and media type, and the pixel dimensions of images.
+    /// Hidden files are left out. At most `[assets] max_entries` (default 2000) are listed.
+    #[oai(path = "/assets", method = "get")]
+    async fn list_assets_handler(&self, dir: Query<Option<String>>) -> Result<AssetListApiResponse, GalateaError> {
+        let config = assets::AssetConfig::load();
+        let root = get_project_root()?;
+        let relative = dir.0.unwrap_or(config.dir);
+        let relative = relative.trim().trim_matches('/');
+        if std::path::Path::new(relative).components().any(|c| !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir)) {
+            return Err(GalateaError::BadRequest(format!("The assets directory must be inside the project: '{}'", relative)));
+        }
+        let assets_dir = root.join(relative);
+        let listing = tokio::task::spawn_blocking(move || assets::list(&assets_dir, config.max_entries))
+            .await
+            .map_err(|e| GalateaError::Internal(e.to_string()))??;
+        let assets: Vec<AssetView> = listing
+            .assets
+            .into_iter()
+            .map(|asset| AssetView {
+                path: asset.path.strip_prefix(&root).unwrap_or(&asset.path).to_string_lossy().replace('\\', "/"),
+                size_bytes: asset.size_bytes,
+                content_type: asset.content_type.to_string(),
+                width: asset.dimensions.map(|(w, _)| w),
+                height: asset.dimensions.map(|(_, h)| h),
+                modified: asset.modified,
+            })
+            .collect();
+        Ok(AssetListApiResponse::Ok(OpenApiJson(AssetListResponse {
+            dir: relative.to_string(),
+            total_bytes: assets.iter().map(|a| a.size_bytes).sum(),
+            assets,
+            truncated: listing.truncated,
+        })))
+    }
+
+    /// Optimize an image asset
+    ///
+    /// Scales an image down to fit `max_width` and/or `max_height`, keeping its aspect ratio
+    /// (images already within them are left at their size), and with `format: "webp"`
+    /// converts it to lossless WebP next to the original, which is kept. A resize without a
+    /// conversion overwrites the original. Works on PNG, JPEG, GIF, WebP, BMP and ICO images.
+    /// The write is an editor edit, so `undo_edit` reverts it. Answers `409` when the `.webp`
+    /// file exists and `overwrite` isn't set.
+    #[oai(path = "/assets/optimize", method = "post")]
+    async fn optimize_asset_handler(&self, req: OpenApiJson<OptimizeAssetRequest>) -> Result<OptimizeAssetApiResponse, GalateaError> {
+        let req = req.0;
+        if let Err(exceeded) = quotas::check(&[QuotaMetric::EditsPerHour, QuotaMetric::BytesWritten]) {
+            return Err(GalateaError::TooManyRequests(exceeded.to_string()));
+        }
+        let to_webp = match req.format.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            None => false,
+            Some("webp") => true,
+            Some(other) => return Err(GalateaError::BadRequest(format!("Unknown format '{}'. Use webp.", other))),
+        };
+        if !to_webp && req.max_width.is_none() && req.max_height.is_none() {
+            return Err(GalateaError::BadRequest("Nothing to do: set max_width, max_height or format".to_string()));
+        }
+        let root = get_project_root()?;
+        let source = paths::resolve_path(&req.path).map_err(|e| GalateaError::NotFound(e.to_string()))?;
+        let optimization = assets::Optimization { max_width: req.max_width, max_height: req.max_height, to_webp };
+        let input = source.clone();
+        let optimized = tokio::task::spawn_blocking(move || assets::optimize(&input, &optimization))
+            .await
+            .map_err(|e| GalateaError::Internal(e.to_string()))?
+            .map_err(|e| GalateaError::BadRequest(format!("{:#}", e)))?;
+        let relative = |path: &std::path::Path| path.strip_prefix(&root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        if optimized.path != source && optimized.path.exists() && !req.overwrite.unwrap_or(false) {
+            return Err(GalateaError::Conflict(format!("'{}' already exists; set `overwrite` to replace it", relative(&optimized.path))));
+        }
+
+        let response = OptimizeAssetResponse {
+            path: relative(&optimized.path),
+            source: relative(&source),
+            size_bytes: optimized.content.len() as u64,
+            width: optimized.dimensions.0,
+            height: optimized.dimensions.1,
+            original_size_bytes: optimized.original_size_bytes,
+            original_width: optimized.original_dimensions.0,
+            original_height: optimized.original_dimensions.1,
+        };
+        checkpoints::before_edits(&format!("Before optimizing {}", response.source), 1).await;
+        let change = editor::FileChange::Write { path: optimized.path, content: optimized.content };
+        editor::apply_shared_tool_changes("optimize_asset", vec![change]).await.map_err(GalateaError::Internal)?;
+        quotas::charge(QuotaMetric::BytesWritten, response.size_bytes as f64);
+        Ok(OptimizeAssetApiResponse::Ok(OpenApiJson(response)))
     }
 }
 
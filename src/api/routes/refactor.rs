@@ -0,0 +1,176 @@
+use poem::Route;
+use poem_openapi::{
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+
+use crate::dev_operation::checkpoints;
+use crate::dev_operation::editor;
+use crate::dev_operation::refactor;
+use crate::dev_runtime::crash;
+use crate::dev_runtime::quotas::{self, QuotaMetric};
+use crate::file_system::paths::get_project_root;
+
+// Define an API struct
+pub struct RefactorApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize, Debug)]
+struct MoveFileRequest {
+    /// **Required.** File or directory to move, relative to the project root
+    from: String,
+
+    /// **Required.** New path, relative to the project root. Must not exist yet; missing parent
+    /// directories are created.
+    to: String,
+
+    /// **Optional.** Report what would change without moving or writing anything. Defaults to
+    /// false.
+    dry_run: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct MovedFileView {
+    /// Old path, relative to the project root
+    from: String,
+
+    /// New path, relative to the project root
+    to: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SpecifierRewriteView {
+    /// Line (1-indexed) of the specifier; 0 for alias targets in tsconfig.json
+    line: usize,
+
+    /// Specifier before the move
+    old: String,
+
+    /// Specifier after the move
+    new: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct UpdatedFileView {
+    /// Path relative to the project root, after the move
+    path: String,
+
+    /// Every specifier rewritten in the file
+    rewrites: Vec<SpecifierRewriteView>,
+
+    /// Unified diff of the file's changes
+    diff: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct MoveFileResponse {
+    /// Every file moved, one entry per file for directory moves
+    moved: Vec<MovedFileView>,
+
+    /// Files whose import specifiers (or tsconfig.json alias targets) were rewritten
+    updated_files: Vec<UpdatedFileView>,
+
+    /// Whether this was a dry run and nothing was changed
+    dry_run: bool,
+}
+
+#[derive(ApiResponse)]
+enum MoveFileApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<MoveFileResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 429)]
+    TooManyRequests(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[OpenApi]
+impl RefactorApi {
+    /// Health check endpoint for the Refactor API
+    ///
+    /// Returns a simple status message to verify that the Refactor API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn refactor_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Refactor API route is healthy".to_string()))
+    }
+
+    /// Move or rename a file or directory and update its imports
+    ///
+    /// Rewrites every import specifier that refers to a moved file across the project: static
+    /// imports and re-exports (including `index.ts` barrels), side-effect imports, dynamic
+    /// `import()`, `require()` and `jest.mock()`/`vi.mock()`. Specifiers keep their style:
+    /// tsconfig `paths` aliases stay aliases when the new location is reachable through one,
+    /// `baseUrl` and relative specifiers stay so, and extensions, `.js` suffixes for TypeScript
+    /// files and directory imports are kept. The moved files' own relative imports are fixed
+    /// too. Alias targets in tsconfig.json that point into a moved directory move with it.
+    ///
+    /// All files are changed as one transaction: if any write fails, the ones already made are
+    /// rolled back. Use `dry_run` to review the diffs first. The move is one edit per changed
+    /// file in the editor's history: `undo_edit` without a path and a `count` of the changed
+    /// files reverts it across all of them.
+    #[oai(path = "/move-file", method = "post")]
+    async fn move_file_handler(&self, req: OpenApiJson<MoveFileRequest>) -> MoveFileApiResponse {
+        let dry_run = req.0.dry_run.unwrap_or(false);
+        if !dry_run {
+            if let Err(exceeded) = quotas::check(&[QuotaMetric::EditsPerHour, QuotaMetric::BytesWritten]) {
+                return MoveFileApiResponse::TooManyRequests(PlainText(exceeded.to_string()));
+            }
+        }
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return MoveFileApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+        let _operation = crash::track_operation(format!("move {} {}", req.0.from, req.0.to));
+
+        if !dry_run {
+            // Every move writes the new file and deletes the old one, so it always changes several
+            checkpoints::before_edits(&format!("Before moving {} to {}", req.0.from, req.0.to), 2).await;
+        }
+        // The imports to rewrite can be anywhere in the project, so the move is planned and
+        // applied under the editor's exclusive lock, like undo and redo
+        let (from, to) = (req.0.from.clone(), req.0.to.clone());
+        let planned = editor::with_files(None, move |editor| {
+            let plan = refactor::plan_move(&project_root, &from, &to).map_err(|e| MoveFileApiResponse::BadRequest(PlainText(format!("{:#}", e))))?;
+            if !dry_run {
+                refactor::apply_move(editor, &plan).map_err(|e| MoveFileApiResponse::InternalServerError(PlainText(format!("{:#}", e))))?;
+            }
+            Ok(plan)
+        })
+        .await;
+        let plan = match planned {
+            Ok(Ok(plan)) => plan,
+            Ok(Err(response)) => return response,
+            Err(e) => return MoveFileApiResponse::InternalServerError(PlainText(e)),
+        };
+
+        MoveFileApiResponse::Ok(OpenApiJson(MoveFileResponse {
+            moved: plan.moves.into_iter().map(|(from, to)| MovedFileView { from, to }).collect(),
+            updated_files: plan
+                .updates
+                .into_iter()
+                .map(|u| UpdatedFileView {
+                    path: u.path,
+                    rewrites: u
+                        .rewrites
+                        .into_iter()
+                        .map(|r| SpecifierRewriteView { line: r.line, old: r.old, new: r.new })
+                        .collect(),
+                    diff: u.diff,
+                })
+                .collect(),
+            dry_run,
+        }))
+    }
+}
+
+pub fn refactor_routes() -> Route {
+    let api_service = OpenApiService::new(RefactorApi, "Refactor API", "1.0").server("/api/refactor");
+    Route::new().nest("/", api_service)
+}
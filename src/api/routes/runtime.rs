@@ -0,0 +1,641 @@
+use poem::Route;
+use poem_openapi::{
+    param::{Path as OpenApiPath, Query},
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+
+use crate::dev_runtime::capabilities::{self, CapabilityError};
+use crate::dev_runtime::events::{self, ServiceEvent, ServiceState};
+use crate::dev_runtime::nextjs_dev_server::{self, BuildIssue, BuildIssueSeverity, CompileRecord, DevServerPhase, DevServerReadiness};
+use crate::dev_runtime::supervisor::{self, ControlError, ServiceStatus};
+use crate::dev_runtime::watchdog::{self, Incident};
+
+// Define an API struct
+pub struct RuntimeApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct RuntimeEvent {
+    /// Sequence number of the event
+    ///
+    /// Increases monotonically across Galatea restarts. Pass the last seen value as
+    /// `after_seq` to fetch only newer events.
+    seq: u64,
+
+    /// Unix timestamp (seconds since epoch) of the state change
+    timestamp: u64,
+
+    /// Identifier of the Galatea process that recorded the event
+    ///
+    /// Changes every time Galatea restarts.
+    session: String,
+
+    /// Service the event belongs to
+    ///
+    /// `nextjs_dev_server`, `lsp`, or `mcp:<id>` for MCP servers (e.g. `mcp:project`).
+    service: String,
+
+    /// New state of the service
+    ///
+    /// One of `starting`, `running`, `stopped`, `failed`, or `lost`. `lost` is recorded on
+    /// startup for services a previous Galatea process left running.
+    kind: String,
+
+    /// Additional context such as the port, pid or error message
+    detail: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct RuntimeEventsResponse {
+    /// Matching events, oldest first
+    events: Vec<RuntimeEvent>,
+
+    /// Number of events returned
+    total_count: usize,
+}
+
+#[derive(Object, serde::Serialize)]
+struct RuntimeServiceState {
+    /// Service name (`nextjs_dev_server`, `lsp`, `mcp:<id>`)
+    service: String,
+
+    /// Current state, as of the service's most recent event
+    status: String,
+
+    /// Unix timestamp of the most recent state change
+    since: u64,
+
+    /// Unix timestamp the current run started at, while the service is running
+    running_since: Option<u64>,
+
+    /// Total seconds spent running across all recorded runs, including the current one
+    uptime_secs: u64,
+
+    /// Number of times the service was started
+    start_count: u32,
+
+    /// Number of starts after the first one
+    restart_count: u32,
+
+    /// Detail of the most recent failure, if any
+    last_error: Option<String>,
+
+    /// Detail attached to the most recent event
+    last_detail: Option<String>,
+
+    /// Port the service listens on, if it has one
+    port: Option<u16>,
+
+    /// Whether this Galatea process can start, stop and restart the service
+    ///
+    /// `false` for services only known from the event log, e.g. MCP servers of an earlier run.
+    controllable: bool,
+
+    /// Whether this Galatea process has the service running (or starting)
+    active: bool,
+
+    /// Result of a live check while active: HTTP readiness for the dev server, a TCP connect for
+    /// MCP servers, an initialized client for the LSP
+    healthy: Option<bool>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct RuntimeServicesResponse {
+    /// State of every service that has recorded an event, sorted by name
+    services: Vec<RuntimeServiceState>,
+}
+
+#[derive(Object, serde::Serialize)]
+pub struct DevServerReadinessResponse {
+    /// `ready` or `warming_up`
+    pub status: String,
+
+    /// `not_started`, `starting`, `compiling`, `ready` or `exited`
+    ///
+    /// `not_started` means Galatea did not launch the dev server itself; readiness is then
+    /// decided by the HTTP probe alone.
+    pub phase: String,
+
+    /// Seconds spent in the current phase
+    pub phase_secs: u64,
+
+    /// Status of the HTTP probe against the dev server, if it answered
+    pub http_status: Option<u16>,
+
+    /// How long the request waited for the dev server, in milliseconds
+    pub waited_ms: u64,
+
+    /// Suggested delay before retrying, while warming up
+    pub retry_after_secs: Option<u64>,
+
+    /// Most recent readiness-related line from the dev server log
+    pub last_log_line: Option<String>,
+
+    /// Human-readable explanation
+    pub detail: String,
+}
+
+/// Retry delay suggested to clients while the dev server warms up.
+pub const WARMING_UP_RETRY_SECS: u64 = 2;
+
+/// Body of the 503 an endpoint answers with when an optional subsystem it needs is missing.
+#[derive(Object, serde::Serialize, Debug)]
+pub struct CapabilityUnavailableResponse {
+    /// Always `capability_unavailable`
+    pub code: String,
+
+    /// The missing capability: `mcp`, `lsp`, `browser`, `embeddings` or `git`
+    ///
+    /// `GET /api/capabilities` shows the live state of all of them.
+    pub capability: String,
+
+    /// `starting`, `disabled`, `unavailable` or `failed`
+    pub state: String,
+
+    /// Human-readable explanation
+    pub message: String,
+
+    /// Suggested delay before retrying, while the capability is starting
+    pub retry_after_secs: Option<u64>,
+}
+
+impl From<CapabilityError> for CapabilityUnavailableResponse {
+    fn from(error: CapabilityError) -> Self {
+        Self {
+            code: capabilities::UNAVAILABLE_CODE.to_string(),
+            capability: error.capability.as_str().to_string(),
+            state: error.state.as_str().to_string(),
+            message: error.to_string(),
+            retry_after_secs: error.retry_after_secs,
+        }
+    }
+}
+
+impl CapabilityUnavailableResponse {
+    /// The same 503 for plain poem handlers, with `Retry-After` while the capability starts.
+    pub fn into_response(self) -> poem::Response {
+        let mut response = poem::Response::builder()
+            .status(poem::http::StatusCode::SERVICE_UNAVAILABLE)
+            .content_type("application/json");
+        if let Some(secs) = self.retry_after_secs {
+            response = response.header("Retry-After", secs.to_string());
+        }
+        response.body(serde_json::to_string(&self).unwrap_or_default())
+    }
+}
+
+impl From<DevServerReadiness> for DevServerReadinessResponse {
+    fn from(readiness: DevServerReadiness) -> Self {
+        Self {
+            status: if readiness.ready { "ready" } else { "warming_up" }.to_string(),
+            phase: readiness.phase.as_str().to_string(),
+            phase_secs: readiness.phase_secs,
+            http_status: readiness.http_status,
+            waited_ms: readiness.waited_ms,
+            retry_after_secs: (!readiness.ready).then_some(WARMING_UP_RETRY_SECS),
+            last_log_line: readiness.last_log_line,
+            detail: readiness.detail,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct BuildIssueView {
+    /// `error` or `warning`
+    severity: String,
+
+    /// Project-relative file the problem is in, when the dev server named one
+    file: Option<String>,
+
+    line: Option<u32>,
+
+    column: Option<u32>,
+
+    /// Error message with the code frame Next.js printed under it
+    message: String,
+
+    /// Stack frames of a runtime error, innermost first
+    stack: Vec<String>,
+
+    /// When it was printed (Unix milliseconds)
+    at_ms: u64,
+}
+
+impl From<BuildIssue> for BuildIssueView {
+    fn from(issue: BuildIssue) -> Self {
+        Self {
+            severity: match issue.severity {
+                BuildIssueSeverity::Error => "error",
+                BuildIssueSeverity::Warning => "warning",
+            }
+            .to_string(),
+            file: issue.file,
+            line: issue.line,
+            column: issue.column,
+            message: issue.message,
+            stack: issue.stack,
+            at_ms: issue.at_ms,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct CompileView {
+    /// Route compiled, e.g. `/dashboard`; absent for a full recompile
+    route: Option<String>,
+
+    duration_ms: Option<u64>,
+
+    /// When it finished (Unix milliseconds)
+    at_ms: u64,
+}
+
+impl From<CompileRecord> for CompileView {
+    fn from(c: CompileRecord) -> Self {
+        Self { route: c.route, duration_ms: c.duration_ms, at_ms: c.at_ms }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct NextjsStatusResponse {
+    /// `ok`, `errors`, `compiling` or `not_running`
+    status: String,
+
+    /// `not_started`, `starting`, `compiling`, `ready` or `exited`
+    phase: String,
+
+    /// Errors printed since the last successful compile, oldest first
+    errors: Vec<BuildIssueView>,
+
+    /// Warnings printed since the last successful compile
+    warnings: Vec<BuildIssueView>,
+
+    /// Latest finished compile
+    last_compile: Option<CompileView>,
+
+    /// Compiles since the dev server started; every one after the first is a hot reload
+    compile_count: u64,
+
+    /// When `errors` last changed (Unix milliseconds), to tell whether an edit made a difference
+    errors_changed_at_ms: Option<u64>,
+}
+
+#[derive(ApiResponse)]
+enum NextjsStatusApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<NextjsStatusResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct WatchdogIncident {
+    /// When it was detected (Unix seconds)
+    at: u64,
+
+    /// `crashed` (the process exited) or `hung` (it stopped answering, or never became ready)
+    kind: String,
+
+    detail: String,
+
+    /// Restarts in a row so far, including this one
+    attempt: u32,
+
+    /// How long the watchdog waits before restarting again
+    backoff_secs: u64,
+
+    /// Last warnings and errors the dev server printed before the restart
+    last_output: Vec<String>,
+
+    /// Why the restart failed, if it did
+    restart_error: Option<String>,
+}
+
+impl From<Incident> for WatchdogIncident {
+    fn from(i: Incident) -> Self {
+        Self {
+            at: i.at,
+            kind: i.kind.as_str().to_string(),
+            detail: i.detail,
+            attempt: i.attempt,
+            backoff_secs: i.backoff_secs,
+            last_output: i.last_output,
+            restart_error: i.restart_error,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct WatchdogStatusResponse {
+    /// Whether the watchdog is checking the dev server
+    running: bool,
+
+    /// Restarts since the dev server was last stable
+    restarts_in_row: u32,
+
+    /// Seconds until the watchdog may restart again, while backing off
+    backoff_remaining_secs: Option<u64>,
+
+    /// Restarts since Galatea started (the last 100), oldest first
+    incidents: Vec<WatchdogIncident>,
+}
+
+#[derive(ApiResponse)]
+enum WatchdogStatusApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<WatchdogStatusResponse>),
+}
+
+#[derive(ApiResponse)]
+enum DevServerReadinessApiResponse {
+    #[oai(status = 200)]
+    Ready(OpenApiJson<DevServerReadinessResponse>),
+    #[oai(status = 503)]
+    WarmingUp(OpenApiJson<DevServerReadinessResponse>),
+}
+
+#[derive(ApiResponse)]
+enum RuntimeEventsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<RuntimeEventsResponse>),
+}
+
+#[derive(ApiResponse)]
+enum RuntimeServicesApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<RuntimeServicesResponse>),
+}
+
+impl From<ServiceEvent> for RuntimeEvent {
+    fn from(event: ServiceEvent) -> Self {
+        Self {
+            seq: event.seq,
+            timestamp: event.timestamp,
+            session: event.session,
+            service: event.service,
+            kind: event.kind.as_str().to_string(),
+            detail: event.detail,
+        }
+    }
+}
+
+impl From<ServiceState> for RuntimeServiceState {
+    fn from(state: ServiceState) -> Self {
+        Self {
+            service: state.service,
+            status: state.status.as_str().to_string(),
+            since: state.since,
+            running_since: state.running_since,
+            uptime_secs: state.uptime_secs,
+            start_count: state.start_count,
+            restart_count: state.restart_count,
+            last_error: state.last_error,
+            last_detail: state.last_detail,
+            port: None,
+            controllable: false,
+            active: false,
+            healthy: None,
+        }
+    }
+}
+
+impl RuntimeServiceState {
+    fn with_status(mut self, status: &ServiceStatus) -> Self {
+        self.port = status.port;
+        self.controllable = true;
+        self.active = status.running;
+        self.healthy = status.healthy;
+        self
+    }
+}
+
+// Event-log state of every service, merged with what the supervisor knows about it
+async fn service_states() -> Vec<RuntimeServiceState> {
+    let statuses = supervisor::statuses().await;
+    let mut states: Vec<RuntimeServiceState> = events::service_states()
+        .into_iter()
+        .map(|state| {
+            let status = statuses.iter().find(|s| s.name == state.service);
+            let view = RuntimeServiceState::from(state);
+            match status {
+                Some(status) => view.with_status(status),
+                None => view,
+            }
+        })
+        .collect();
+    // Controllable services that haven't recorded anything yet (e.g. the LSP before first use)
+    for status in &statuses {
+        if !states.iter().any(|s| s.service == status.name) {
+            states.push(
+                RuntimeServiceState {
+                    service: status.name.clone(),
+                    status: "stopped".to_string(),
+                    since: 0,
+                    running_since: None,
+                    uptime_secs: 0,
+                    start_count: 0,
+                    restart_count: 0,
+                    last_error: None,
+                    last_detail: None,
+                    port: None,
+                    controllable: false,
+                    active: false,
+                    healthy: None,
+                }
+                .with_status(status),
+            );
+        }
+    }
+    states.sort_by(|a, b| a.service.cmp(&b.service));
+    states
+}
+
+#[derive(ApiResponse)]
+enum ServiceControlApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<Box<RuntimeServiceState>>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+async fn control_response(name: &str, result: Result<(), ControlError>) -> ServiceControlApiResponse {
+    match result {
+        Ok(()) => match service_states().await.into_iter().find(|s| s.service == name) {
+            Some(state) => ServiceControlApiResponse::Ok(OpenApiJson(Box::new(state))),
+            None => ServiceControlApiResponse::NotFound(PlainText(format!("Unknown service '{}'", name))),
+        },
+        Err(e @ ControlError::NotFound(_)) => ServiceControlApiResponse::NotFound(PlainText(e.to_string())),
+        Err(e @ ControlError::Conflict(_)) => ServiceControlApiResponse::Conflict(PlainText(e.to_string())),
+        Err(e @ ControlError::Failed(_)) => ServiceControlApiResponse::InternalServerError(PlainText(e.to_string())),
+    }
+}
+
+#[OpenApi]
+impl RuntimeApi {
+    /// Health check endpoint for the Runtime API
+    ///
+    /// Returns a simple status message to verify that the Runtime API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn runtime_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Runtime API route is healthy".to_string()))
+    }
+
+    /// Runtime service event history
+    ///
+    /// Every state change of the Next.js dev server, MCP servers and the LSP client is appended
+    /// to `galatea_files/runtime_events.jsonl`, so the history survives Galatea restarts.
+    ///
+    /// - `service`: only return events for this service (e.g. `nextjs_dev_server`, `mcp:project`)
+    /// - `after_seq`: only return events with a greater sequence number
+    /// - `limit`: return at most this many of the most recent matching events
+    #[oai(path = "/events", method = "get")]
+    async fn runtime_events_handler(
+        &self,
+        service: Query<Option<String>>,
+        after_seq: Query<Option<u64>>,
+        limit: Query<Option<usize>>,
+    ) -> RuntimeEventsApiResponse {
+        let mut matching = events::events(service.0.as_deref(), after_seq.0);
+        if let Some(limit) = limit.0 {
+            let skip = matching.len().saturating_sub(limit);
+            matching.drain(..skip);
+        }
+        let events: Vec<RuntimeEvent> = matching.into_iter().map(RuntimeEvent::from).collect();
+        RuntimeEventsApiResponse::Ok(OpenApiJson(RuntimeEventsResponse {
+            total_count: events.len(),
+            events,
+        }))
+    }
+
+    /// Current runtime service state
+    ///
+    /// Reconstructed by replaying the event log: current status, uptime across runs,
+    /// restart count and the last error of each service. Services this Galatea process
+    /// manages (the Next.js dev server, MCP servers it launched, the LSP) also report their
+    /// port and a live health check, and can be controlled with the `start`, `stop` and
+    /// `restart` endpoints.
+    #[oai(path = "/services", method = "get")]
+    async fn runtime_services_handler(&self) -> RuntimeServicesApiResponse {
+        RuntimeServicesApiResponse::Ok(OpenApiJson(RuntimeServicesResponse {
+            services: service_states().await,
+        }))
+    }
+
+    /// Start a service
+    ///
+    /// `name` is `nextjs_dev_server`, `lsp`, or `mcp:<id>` for an MCP server launched at
+    /// startup. Returns `409` if the service is already running. The LSP starts in the
+    /// background; poll `GET /services` until `healthy` is `true`.
+    #[oai(path = "/services/:name/start", method = "post")]
+    async fn start_service_handler(&self, name: OpenApiPath<String>) -> ServiceControlApiResponse {
+        let result = supervisor::start(&name.0).await;
+        control_response(&name.0, result).await
+    }
+
+    /// Stop a service
+    ///
+    /// Kills the service's process, and whatever else still holds its port. Returns `409` if
+    /// the service is not running.
+    #[oai(path = "/services/:name/stop", method = "post")]
+    async fn stop_service_handler(&self, name: OpenApiPath<String>) -> ServiceControlApiResponse {
+        let result = supervisor::stop(&name.0).await;
+        control_response(&name.0, result).await
+    }
+
+    /// Restart a service
+    ///
+    /// Stops the service if it is running and starts it again, e.g. to recover a wedged
+    /// Next.js dev server without restarting Galatea. Follow up with
+    /// `GET /dev-server/readiness?wait_secs=..` to wait for the dev server to come back.
+    #[oai(path = "/services/:name/restart", method = "post")]
+    async fn restart_service_handler(&self, name: OpenApiPath<String>) -> ServiceControlApiResponse {
+        let result = supervisor::restart(&name.0).await;
+        control_response(&name.0, result).await
+    }
+
+    /// Next.js dev server readiness
+    ///
+    /// The dev server counts as ready once it logged its ready line and answers an HTTP request
+    /// without a 5xx error. During the first compile after a start or restart, `503` is returned
+    /// with `status: warming_up` instead.
+    ///
+    /// - `wait_secs`: wait up to this long for the server to become ready. Defaults to 0 (check
+    ///   once); the `/preview` proxy waits for `dev_server_ready_timeout_secs` from config.toml.
+    #[oai(path = "/dev-server/readiness", method = "get")]
+    async fn dev_server_readiness_handler(&self, wait_secs: Query<Option<u64>>) -> DevServerReadinessApiResponse {
+        let readiness = match wait_secs.0 {
+            Some(secs) if secs > 0 => nextjs_dev_server::wait_until_ready(std::time::Duration::from_secs(secs)).await,
+            _ => nextjs_dev_server::probe_readiness().await,
+        };
+        if readiness.ready {
+            DevServerReadinessApiResponse::Ready(OpenApiJson(readiness.into()))
+        } else {
+            DevServerReadinessApiResponse::WarmingUp(OpenApiJson(readiness.into()))
+        }
+    }
+
+    /// Get the dev server watchdog's incidents
+    ///
+    /// The watchdog checks the Next.js dev server every few seconds. When its process exits on
+    /// its own, or it stops answering HTTP requests (`hung_after_secs`, default 60) or never
+    /// logs its ready line (`startup_timeout_secs`, default 180), the server is restarted and
+    /// the incident recorded here and in `/events`. Restarts in a row back off exponentially
+    /// (`initial_backoff_secs` 2 up to `max_backoff_secs` 300) until the server stays healthy
+    /// for `stable_secs`. A server stopped through `/services/{name}/stop` is left alone.
+    /// Settings live under `[dev_server_watchdog]` in config.toml.
+    #[oai(path = "/watchdog", method = "get")]
+    async fn watchdog_handler(&self) -> WatchdogStatusApiResponse {
+        let status = watchdog::status();
+        WatchdogStatusApiResponse::Ok(OpenApiJson(WatchdogStatusResponse {
+            running: status.running,
+            restarts_in_row: status.restarts_in_row,
+            backoff_remaining_secs: status.backoff_remaining_secs,
+            incidents: status.incidents.into_iter().map(WatchdogIncident::from).collect(),
+        }))
+    }
+
+    /// Get the Next.js build status
+    ///
+    /// Compile errors, runtime errors and warnings the dev server printed since its last
+    /// successful compile, each with its file, line, message and stack when the output gives
+    /// them, and the latest compile (Next.js recompiles on every hot reload). A clean compile
+    /// clears the list, so after an edit an agent can wait for `last_compile` to move past the
+    /// edit and check `errors` to learn whether it broke the build. `status` is `errors` while
+    /// any error is listed.
+    #[oai(path = "/nextjs/status", method = "get")]
+    async fn nextjs_status_handler(&self) -> NextjsStatusApiResponse {
+        let build = nextjs_dev_server::build_status();
+        let phase = nextjs_dev_server::phase();
+        let status = if matches!(phase, DevServerPhase::NotStarted | DevServerPhase::Exited) {
+            "not_running"
+        } else if !build.errors.is_empty() {
+            "errors"
+        } else if matches!(phase, DevServerPhase::Starting | DevServerPhase::Compiling) {
+            "compiling"
+        } else {
+            "ok"
+        };
+        NextjsStatusApiResponse::Ok(OpenApiJson(NextjsStatusResponse {
+            status: status.to_string(),
+            phase: phase.as_str().to_string(),
+            errors: build.errors.into_iter().map(BuildIssueView::from).collect(),
+            warnings: build.warnings.into_iter().map(BuildIssueView::from).collect(),
+            last_compile: build.last_compile.map(CompileView::from),
+            compile_count: build.compile_count,
+            errors_changed_at_ms: build.changed_at_ms,
+        }))
+    }
+}
+
+pub fn runtime_routes() -> Route {
+    let api_service = OpenApiService::new(RuntimeApi, "Runtime API", "1.0").server("/api/runtime");
+    Route::new().nest("/", api_service)
+}
@@ -0,0 +1,244 @@
+use poem::Route;
+use poem_openapi::{
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+use std::collections::BTreeMap;
+
+use crate::dev_setup::wizard::{self, StepAnswer, WizardPhase, WizardStatus};
+
+// Define an API struct
+pub struct SetupApi;
+
+#[derive(Object, serde::Serialize)]
+struct SetupAnswersView {
+    /// Template name (see `/api/project/templates`) or git URL
+    template: Option<String>,
+
+    /// Variables substituted into the template's `galatea.template.toml` placeholders
+    template_vars: BTreeMap<String, String>,
+
+    /// OpenAI API key, masked to its last four characters
+    openai_api_key: Option<String>,
+
+    /// OpenAI-compatible API base URL
+    openai_api_base: Option<String>,
+
+    /// `pnpm`, `npm`, `yarn` or `bun`
+    package_manager: Option<String>,
+
+    /// Whether the Galatea APIs are also exposed as MCP servers
+    mcp_enabled: Option<bool>,
+
+    /// Whether a Galatea API token is configured. The token itself is only returned by `POST /auth`.
+    token_configured: bool,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SetupStatusResponse {
+    /// `collecting`, `applying`, `done` or `failed`
+    phase: String,
+
+    /// Why applying failed, when `phase` is `failed`
+    error: Option<String>,
+
+    /// First unanswered step (`template`, `tokens`, `package_manager`, `mcp`, `auth`), or
+    /// `null` once every step is answered and setup can be applied
+    current_step: Option<String>,
+
+    /// Steps answered so far
+    completed_steps: Vec<String>,
+
+    answers: SetupAnswersView,
+
+    /// Project directory, once setup is done
+    project_dir: Option<String>,
+
+    /// The Galatea API token, only set in the response to `POST /auth`
+    token: Option<String>,
+}
+
+impl From<WizardStatus> for SetupStatusResponse {
+    fn from(status: WizardStatus) -> Self {
+        let error = match &status.phase {
+            WizardPhase::Failed(e) => Some(e.clone()),
+            _ => None,
+        };
+        Self {
+            phase: status.phase.as_str().to_string(),
+            error,
+            current_step: status.current_step.map(|s| s.as_str().to_string()),
+            completed_steps: status.completed_steps.iter().map(|s| s.as_str().to_string()).collect(),
+            answers: SetupAnswersView {
+                template: status.answers.template,
+                template_vars: status.answers.template_vars,
+                openai_api_key: status.answers.openai_api_key.as_deref().map(wizard::mask_secret),
+                openai_api_base: status.answers.openai_api_base,
+                package_manager: status.answers.package_manager,
+                mcp_enabled: status.answers.mcp_enabled,
+                token_configured: status.answers.token.is_some(),
+            },
+            project_dir: status.project_dir.map(|p| p.display().to_string()),
+            token: None,
+        }
+    }
+}
+
+#[derive(Object, serde::Deserialize)]
+struct TemplateStepRequest {
+    /// Template to scaffold the project from
+    ///
+    /// **Required.** A built-in template such as `nextjs` (see `/api/project/templates`), or a git URL.
+    #[oai(validator(min_length = 1))]
+    template: String,
+
+    /// Template variables
+    ///
+    /// **Optional.** Values for the placeholders declared in the template's
+    /// `galatea.template.toml`, same as `--template-var key=value`.
+    template_vars: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct TokensStepRequest {
+    /// OpenAI API key
+    ///
+    /// **Optional.** Used for embeddings and written to the project's `.env` as
+    /// `OPENAI_API_KEY`. Omit to skip.
+    openai_api_key: Option<String>,
+
+    /// OpenAI-compatible API base URL
+    ///
+    /// **Optional.** For self-hosted or proxy endpoints, e.g. `http://localhost:11434/v1`.
+    openai_api_base: Option<String>,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct PackageManagerStepRequest {
+    /// **Required.** `pnpm`, `npm`, `yarn` or `bun`
+    package_manager: String,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct McpStepRequest {
+    /// **Required.** Whether to expose the Galatea APIs as MCP servers, same as `--mcp-enabled`
+    enabled: bool,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct AuthStepRequest {
+    /// API token clients must send to reach protected endpoints
+    ///
+    /// **Optional.** At least 16 characters. Omit to have Galatea generate one; it is
+    /// returned once in the response.
+    token: Option<String>,
+}
+
+#[derive(ApiResponse)]
+enum SetupApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<Box<SetupStatusResponse>>),
+    #[oai(status = 202)]
+    Accepted(OpenApiJson<Box<SetupStatusResponse>>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+}
+
+fn answer_step(answer: StepAnswer) -> SetupApiResponse {
+    // Answers are locked while applying and once done; anything else is a bad answer
+    if matches!(wizard::status().phase, WizardPhase::Applying | WizardPhase::Done) {
+        return SetupApiResponse::Conflict(PlainText("Setup has already been applied".to_string()));
+    }
+    match wizard::answer(answer) {
+        Ok(status) => SetupApiResponse::Ok(OpenApiJson(Box::new(status.into()))),
+        Err(e) => SetupApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+    }
+}
+
+#[OpenApi]
+impl SetupApi {
+    /// Get the setup wizard state
+    ///
+    /// Returns the wizard phase, the next step to answer and the answers saved so far.
+    /// Answers are persisted to config.toml as they are given, so a restarted wizard resumes
+    /// where it left off.
+    ///
+    /// ## Flow:
+    /// 1. Start Galatea with `--setup-wizard`; only this API is served until setup is done
+    /// 2. Answer `POST /template`, `/tokens`, `/package-manager`, `/mcp` and `/auth`, in any
+    ///    order and as often as needed
+    /// 3. `POST /apply`, then poll this endpoint until `phase` is `done` (or `failed`, after
+    ///    which answers can be corrected and applied again)
+    #[oai(path = "/", method = "get")]
+    async fn get_setup_handler(&self) -> SetupApiResponse {
+        SetupApiResponse::Ok(OpenApiJson(Box::new(wizard::status().into())))
+    }
+
+    /// Choose the project template
+    #[oai(path = "/template", method = "post")]
+    async fn template_step_handler(&self, req: OpenApiJson<TemplateStepRequest>) -> SetupApiResponse {
+        answer_step(StepAnswer::Template {
+            template: req.0.template,
+            vars: req.0.template_vars.unwrap_or_default(),
+        })
+    }
+
+    /// Provide API tokens
+    #[oai(path = "/tokens", method = "post")]
+    async fn tokens_step_handler(&self, req: OpenApiJson<TokensStepRequest>) -> SetupApiResponse {
+        answer_step(StepAnswer::Tokens {
+            openai_api_key: req.0.openai_api_key,
+            openai_api_base: req.0.openai_api_base,
+        })
+    }
+
+    /// Pick the package manager
+    ///
+    /// Used to install dependencies, run the dev server and run project scripts. Without it the
+    /// package manager is picked from the project's lockfile, pnpm when there is none.
+    #[oai(path = "/package-manager", method = "post")]
+    async fn package_manager_step_handler(&self, req: OpenApiJson<PackageManagerStepRequest>) -> SetupApiResponse {
+        answer_step(StepAnswer::PackageManager(req.0.package_manager))
+    }
+
+    /// Enable or disable MCP servers
+    #[oai(path = "/mcp", method = "post")]
+    async fn mcp_step_handler(&self, req: OpenApiJson<McpStepRequest>) -> SetupApiResponse {
+        answer_step(StepAnswer::Mcp(req.0.enabled))
+    }
+
+    /// Set the API token
+    ///
+    /// The response carries the token in `token`, the only time it is returned.
+    #[oai(path = "/auth", method = "post")]
+    async fn auth_step_handler(&self, req: OpenApiJson<AuthStepRequest>) -> SetupApiResponse {
+        match answer_step(StepAnswer::Auth(req.0.token)) {
+            SetupApiResponse::Ok(OpenApiJson(mut status)) => {
+                status.token = crate::dev_setup::config_files::get_config_value("token");
+                SetupApiResponse::Ok(OpenApiJson(status))
+            }
+            other => other,
+        }
+    }
+
+    /// Apply the setup
+    ///
+    /// Scaffolds the project from the chosen template, installs its dependencies and prepares
+    /// `galatea_files` in the background, then starts the regular Galatea services. Returns
+    /// 202 right away; poll `GET /` for progress. Only available while Galatea runs with
+    /// `--setup-wizard`.
+    #[oai(path = "/apply", method = "post")]
+    async fn apply_handler(&self) -> SetupApiResponse {
+        match wizard::apply() {
+            Ok(status) => SetupApiResponse::Accepted(OpenApiJson(Box::new(status.into()))),
+            Err(e) => SetupApiResponse::Conflict(PlainText(format!("{:#}", e))),
+        }
+    }
+}
+
+pub fn setup_routes() -> Route {
+    let api_service = OpenApiService::new(SetupApi, "Setup API", "1.0").server("/api/setup");
+    Route::new().nest("/", api_service)
+}
@@ -0,0 +1,364 @@
+use poem::Route;
+use poem_openapi::{
+    param::{Path as OpenApiPath, Query},
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+
+use crate::dev_operation::suggestions::{self, Analyzer, Suggestion, SuggestionStatus};
+use crate::file_system::paths::get_project_root;
+
+// Define an API struct
+pub struct SuggestionsApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct SuggestionFixView {
+    /// What the fix does
+    description: String,
+
+    /// Line (1-indexed) where the replaced range starts
+    start_line: usize,
+
+    /// Column (0-indexed characters) where the replaced range starts
+    start_column: usize,
+
+    /// Line (1-indexed) where the replaced range ends
+    end_line: usize,
+
+    /// Column (0-indexed characters, exclusive) where the replaced range ends
+    end_column: usize,
+
+    /// Replacement text for the range
+    new_text: String,
+
+    /// Content hash of the file the fix was prepared against
+    ///
+    /// Together with the range this can be passed to the editor's `replace_range` command.
+    expected_hash: String,
+
+    /// Unified-style diff of the affected lines, for review
+    diff: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SuggestionView {
+    /// Suggestion identifier
+    id: u64,
+
+    /// Analyzer that produced the suggestion: `lint`, `typecheck` or `unused_exports`
+    analyzer: String,
+
+    /// `error`, `warning` or `info`
+    severity: String,
+
+    /// File path relative to the project root
+    path: String,
+
+    /// Affected entity (e.g. the exported symbol), if the analyzer knows it
+    entity: Option<String>,
+
+    /// Line (1-indexed) the problem was reported at
+    line: usize,
+
+    /// Column (0-indexed characters) the problem was reported at
+    column: usize,
+
+    /// Problem description
+    message: String,
+
+    /// Rule or diagnostic code, e.g. `no-unused-vars` or `TS2322`
+    rule: Option<String>,
+
+    /// Prepared fix, if the analyzer can provide one
+    fix: Option<SuggestionFixView>,
+
+    /// `open`, `resolved` or `dismissed`
+    status: String,
+
+    /// Unix timestamp when the suggestion was first reported
+    created_at: u64,
+
+    /// Unix timestamp when the suggestion was resolved or dismissed
+    resolved_at: Option<u64>,
+
+    /// How the suggestion was closed, e.g. `fix applied` or `no longer reported`
+    resolution: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SuggestionListResponse {
+    /// Matching suggestions, most severe first
+    suggestions: Vec<SuggestionView>,
+
+    /// Number of suggestions returned
+    total_count: usize,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct AnalyzeRequest {
+    /// Analyzers to run
+    ///
+    /// **Optional.** Any of `lint`, `typecheck`, `unused_exports`. Runs all of them when omitted.
+    analyzers: Option<Vec<String>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct AnalyzerRunResult {
+    /// Analyzer name
+    analyzer: String,
+
+    /// Whether the analyzer ran
+    success: bool,
+
+    /// Number of new suggestions queued by this run
+    added: usize,
+
+    /// Why the analyzer could not run
+    error: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct AnalyzeResponse {
+    /// Result per analyzer
+    results: Vec<AnalyzerRunResult>,
+
+    /// Open suggestions after the run
+    open_count: usize,
+}
+
+#[derive(Object, serde::Deserialize)]
+struct ResolveRequest {
+    /// Optional note on how the suggestion was handled
+    resolution: Option<String>,
+
+    /// Set to `true` to dismiss the suggestion instead of resolving it
+    dismiss: Option<bool>,
+}
+
+#[derive(ApiResponse)]
+enum SuggestionListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SuggestionListResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum SuggestionApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<Box<SuggestionView>>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    #[oai(status = 409)]
+    Conflict(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum AnalyzeApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<AnalyzeResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+impl From<Suggestion> for SuggestionView {
+    fn from(s: Suggestion) -> Self {
+        Self {
+            id: s.id,
+            analyzer: s.analyzer.as_str().to_string(),
+            severity: s.severity.as_str().to_string(),
+            path: s.path,
+            entity: s.entity,
+            line: s.line,
+            column: s.column,
+            message: s.message,
+            rule: s.rule,
+            fix: s.fix.map(|f| SuggestionFixView {
+                description: f.description,
+                start_line: f.range.start_line,
+                start_column: f.range.start_column,
+                end_line: f.range.end_line,
+                end_column: f.range.end_column,
+                new_text: f.new_text,
+                expected_hash: f.expected_hash,
+                diff: f.diff,
+            }),
+            status: s.status.as_str().to_string(),
+            created_at: s.created_at,
+            resolved_at: s.resolved_at,
+            resolution: s.resolution,
+        }
+    }
+}
+
+fn parse_status(status: &str) -> Option<SuggestionStatus> {
+    match status {
+        "open" => Some(SuggestionStatus::Open),
+        "resolved" => Some(SuggestionStatus::Resolved),
+        "dismissed" => Some(SuggestionStatus::Dismissed),
+        _ => None,
+    }
+}
+
+#[OpenApi]
+impl SuggestionsApi {
+    /// Health check endpoint for the Suggestions API
+    ///
+    /// Returns a simple status message to verify that the Suggestions API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn suggestions_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Suggestions API route is healthy".to_string()))
+    }
+
+    /// List queued suggestions
+    ///
+    /// Background analyzers (ESLint, `tsc`, unused-export detection) publish their findings here
+    /// as a work feed. Filter with `status` (`open` by default; `all` for everything),
+    /// `analyzer` and `path`.
+    #[oai(path = "/", method = "get")]
+    async fn list_suggestions_handler(
+        &self,
+        status: Query<Option<String>>,
+        analyzer: Query<Option<String>>,
+        path: Query<Option<String>>,
+    ) -> SuggestionListApiResponse {
+        let status_filter = match status.0.as_deref() {
+            None => Some(SuggestionStatus::Open),
+            Some("all") => None,
+            Some(s) => match parse_status(s) {
+                Some(st) => Some(st),
+                None => {
+                    return SuggestionListApiResponse::BadRequest(PlainText(format!(
+                        "Unknown status '{}'. Use open, resolved, dismissed or all.",
+                        s
+                    )))
+                }
+            },
+        };
+        let analyzer_filter = match analyzer.0.as_deref() {
+            None => None,
+            Some(name) => match Analyzer::from_name(name) {
+                Some(a) => Some(a),
+                None => {
+                    return SuggestionListApiResponse::BadRequest(PlainText(format!(
+                        "Unknown analyzer '{}'. Use lint, typecheck or unused_exports.",
+                        name
+                    )))
+                }
+            },
+        };
+        let suggestions: Vec<SuggestionView> =
+            suggestions::list_suggestions(status_filter, analyzer_filter, path.0.as_deref())
+                .into_iter()
+                .map(SuggestionView::from)
+                .collect();
+        SuggestionListApiResponse::Ok(OpenApiJson(SuggestionListResponse {
+            total_count: suggestions.len(),
+            suggestions,
+        }))
+    }
+
+    /// Run analyzers now
+    ///
+    /// Runs the requested analyzers against the project and merges their findings into the queue.
+    /// Problems that are no longer reported are resolved automatically. Fixes are prepared against
+    /// the current file contents, so re-run after editing files to refresh them.
+    #[oai(path = "/analyze", method = "post")]
+    async fn analyze_handler(&self, req: OpenApiJson<AnalyzeRequest>) -> AnalyzeApiResponse {
+        let analyzers = match &req.0.analyzers {
+            None => Analyzer::ALL.to_vec(),
+            Some(names) => {
+                let mut analyzers = Vec::new();
+                for name in names {
+                    match Analyzer::from_name(name) {
+                        Some(a) => analyzers.push(a),
+                        None => {
+                            return AnalyzeApiResponse::BadRequest(PlainText(format!(
+                                "Unknown analyzer '{}'. Use lint, typecheck or unused_exports.",
+                                name
+                            )))
+                        }
+                    }
+                }
+                analyzers
+            }
+        };
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return AnalyzeApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+
+        let results = suggestions::run_analyzers(&project_root, &analyzers)
+            .await
+            .into_iter()
+            .map(|(analyzer, result)| AnalyzerRunResult {
+                analyzer: analyzer.as_str().to_string(),
+                success: result.is_ok(),
+                added: *result.as_ref().unwrap_or(&0),
+                error: result.err().map(|e| format!("{:#}", e)),
+            })
+            .collect();
+        AnalyzeApiResponse::Ok(OpenApiJson(AnalyzeResponse {
+            results,
+            open_count: suggestions::list_suggestions(Some(SuggestionStatus::Open), None, None).len(),
+        }))
+    }
+
+    /// Get a suggestion
+    #[oai(path = "/:id", method = "get")]
+    async fn get_suggestion_handler(&self, id: OpenApiPath<u64>) -> SuggestionApiResponse {
+        match suggestions::get_suggestion(id.0) {
+            Some(s) => SuggestionApiResponse::Ok(OpenApiJson(Box::new(s.into()))),
+            None => SuggestionApiResponse::NotFound(PlainText(format!("Suggestion {} not found", id.0))),
+        }
+    }
+
+    /// Apply a suggestion's prepared fix
+    ///
+    /// Applies the fix with the editor's `replace_range` (so it can be reverted with `undo_edit`)
+    /// and marks the suggestion resolved. Returns 409 if the suggestion has no fix, is already
+    /// closed, or the file changed since the fix was prepared.
+    #[oai(path = "/:id/apply", method = "post")]
+    async fn apply_suggestion_handler(&self, id: OpenApiPath<u64>) -> SuggestionApiResponse {
+        if suggestions::get_suggestion(id.0).is_none() {
+            return SuggestionApiResponse::NotFound(PlainText(format!("Suggestion {} not found", id.0)));
+        }
+        match suggestions::apply_suggestion(id.0).await {
+            Ok(s) => SuggestionApiResponse::Ok(OpenApiJson(Box::new(s.into()))),
+            Err(e) => SuggestionApiResponse::Conflict(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Mark a suggestion resolved or dismissed
+    ///
+    /// Use after handling a suggestion manually, or with `dismiss: true` to ignore it.
+    #[oai(path = "/:id/resolve", method = "post")]
+    async fn resolve_suggestion_handler(
+        &self,
+        id: OpenApiPath<u64>,
+        req: OpenApiJson<ResolveRequest>,
+    ) -> SuggestionApiResponse {
+        let status = if req.0.dismiss.unwrap_or(false) {
+            SuggestionStatus::Dismissed
+        } else {
+            SuggestionStatus::Resolved
+        };
+        match suggestions::set_status(id.0, status, req.0.resolution.clone()) {
+            Ok(s) => SuggestionApiResponse::Ok(OpenApiJson(Box::new(s.into()))),
+            Err(e) => SuggestionApiResponse::NotFound(PlainText(e.to_string())),
+        }
+    }
+}
+
+pub fn suggestions_routes() -> Route {
+    let api_service = OpenApiService::new(SuggestionsApi, "Suggestions API", "1.0").server("/api/suggestions");
+    Route::new().nest("/", api_service)
+}
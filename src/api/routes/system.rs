@@ -0,0 +1,553 @@
+use poem::Route;
+use poem_openapi::{
+    param::{Path as OpenApiPath, Query},
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+
+use crate::dev_runtime::crash::{self, CrashBundle, CrashKind};
+use crate::dev_runtime::{db, limits, recovery};
+use crate::dev_setup::registry_cache;
+
+// Define an API struct
+pub struct SystemApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct LimitWarningView {
+    /// `rate_limit_nearly_exhausted`, `rate_limit_exceeded`, `edit_history_near_cap`,
+    /// `disk_nearly_full` or `log_buffer_dropping`
+    code: String,
+
+    /// Current usage, in the limit's unit (requests, rows, KiB or log entries)
+    used: u64,
+
+    /// The limit `used` is measured against
+    limit: u64,
+
+    message: String,
+}
+
+impl From<limits::LimitWarning> for LimitWarningView {
+    fn from(warning: limits::LimitWarning) -> Self {
+        Self {
+            code: warning.code.as_str().to_string(),
+            used: warning.used,
+            limit: warning.limit,
+            message: warning.message,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct LimitSettingsView {
+    /// Fraction of a limit at which warnings are raised
+    warning_ratio: f64,
+
+    /// Requests per client per minute on `/api`, or `null` when rate limiting is off
+    rate_limit_per_minute: Option<u32>,
+
+    /// Edit history rows kept before the oldest are pruned
+    edit_history_max_rows: u64,
+
+    /// Entries held by the in-memory log buffer
+    log_buffer_capacity: usize,
+
+    /// Disk usage fraction at which the disk counts as nearly full
+    disk_warning_ratio: f64,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LimitsResponse {
+    /// Process-wide limits currently being approached
+    warnings: Vec<LimitWarningView>,
+
+    settings: LimitSettingsView,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LimitEventView {
+    /// Sequence number; pass the last one seen as `since` to get only newer events
+    seq: u64,
+
+    /// Unix timestamp (seconds since epoch)
+    timestamp: u64,
+
+    /// `raised` or `cleared`
+    state: String,
+
+    warning: LimitWarningView,
+}
+
+#[derive(Object, serde::Serialize)]
+struct LimitEventsResponse {
+    events: Vec<LimitEventView>,
+}
+
+#[derive(ApiResponse)]
+enum LimitsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<LimitsResponse>),
+}
+
+#[derive(ApiResponse)]
+enum LimitEventsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<LimitEventsResponse>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct RegistryCacheResponse {
+    /// Whether installs currently go through the cache
+    enabled: bool,
+
+    /// Registry URL npm and pnpm are pointed at, while enabled
+    url: Option<String>,
+
+    /// Registry the cache fetches from (`registry_upstream`)
+    upstream: String,
+
+    /// Directory holding cached package documents and tarballs
+    cache_dir: String,
+
+    /// Cached package documents
+    packages: usize,
+
+    /// Cached tarballs
+    tarballs: usize,
+
+    /// Total size of the cache on disk
+    size_bytes: u64,
+
+    /// Requests answered from disk since startup
+    hits: u64,
+
+    /// Requests fetched from the upstream registry since startup
+    misses: u64,
+}
+
+#[derive(ApiResponse)]
+enum RegistryCacheApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<RegistryCacheResponse>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct CrashSummary {
+    /// Crash bundle identifier
+    ///
+    /// Use with `GET /crashes/{id}` to retrieve the full bundle.
+    id: String,
+
+    /// `panic` or `fatal_error`
+    kind: String,
+
+    /// Unix timestamp (seconds since epoch) when the crash was recorded
+    timestamp: u64,
+
+    /// Panic message or error chain
+    message: String,
+
+    /// Source location of the panic (`file:line:column`), if known
+    location: Option<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CrashListResponse {
+    /// Recorded crashes, newest first
+    crashes: Vec<CrashSummary>,
+
+    /// Number of crashes recorded
+    total_count: usize,
+}
+
+#[derive(Object, serde::Serialize)]
+struct CrashDetail {
+    /// Crash bundle identifier
+    id: String,
+
+    /// `panic` or `fatal_error`
+    kind: String,
+
+    /// Unix timestamp (seconds since epoch) when the crash was recorded
+    timestamp: u64,
+
+    /// Panic message or error chain
+    message: String,
+
+    /// Source location of the panic (`file:line:column`), if known
+    location: Option<String>,
+
+    /// Name of the thread that panicked, if it had one
+    thread: Option<String>,
+
+    /// Captured backtrace
+    backtrace: String,
+
+    /// Galatea version that crashed
+    version: String,
+
+    /// Operations that were in progress when the crash happened
+    active_operations: Vec<String>,
+
+    /// Tail of the in-memory log store at crash time
+    recent_logs: Vec<String>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SelfCheckItem {
+    /// Name of the check (e.g. `project_directory`, `tool_pnpm`)
+    name: String,
+
+    /// Whether the check passed
+    ok: bool,
+
+    /// Resolved path on success, reason on failure
+    detail: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct SelfCheckResponse {
+    /// `true` only if every check passed
+    healthy: bool,
+
+    /// Individual check results
+    checks: Vec<SelfCheckItem>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DbTableStats {
+    /// Table name
+    name: String,
+
+    /// Number of rows
+    rows: u64,
+}
+
+#[derive(Object, serde::Serialize)]
+struct DbStatsResponse {
+    /// Path of the SQLite database file
+    path: String,
+
+    /// Size on disk in bytes, including the write-ahead log
+    size_bytes: u64,
+
+    /// Number of schema migrations applied
+    schema_version: u32,
+
+    /// Row count per table
+    tables: Vec<DbTableStats>,
+}
+
+#[derive(ApiResponse)]
+enum DbStatsApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<DbStatsResponse>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct InterruptedJobView {
+    /// Job identifier
+    id: String,
+
+    /// Job kind, e.g. `validation`
+    kind: String,
+
+    /// Status the job had when Galatea went away: `queued` or `running`
+    previous_status: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct RecoveryResponse {
+    /// Unix timestamp (seconds since epoch) when recovery ran at startup
+    recovered_at: u64,
+
+    /// Whether an earlier Galatea process ended without a clean shutdown
+    recovered_from_crash: bool,
+
+    /// Ids of the sessions that ended without a clean shutdown
+    crashed_sessions: Vec<String>,
+
+    /// Jobs left unfinished by those sessions, now marked `interrupted`
+    interrupted_jobs: Vec<InterruptedJobView>,
+
+    /// Services that were active when the previous process went away
+    lost_services: Vec<String>,
+
+    /// Services relaunched to the state the previous process wanted, e.g. `mcp`
+    restored_services: Vec<String>,
+
+    /// Continuous syncs resumed under their previous ids
+    resumed_syncs: Vec<String>,
+
+    /// Continuous syncs closed instead of resumed
+    closed_syncs: Vec<String>,
+}
+
+#[derive(ApiResponse)]
+enum RecoveryApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<RecoveryResponse>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum CrashListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<CrashListResponse>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum CrashDetailApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<CrashDetail>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum SelfCheckApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<SelfCheckResponse>),
+}
+
+fn kind_str(kind: CrashKind) -> String {
+    match kind {
+        CrashKind::Panic => "panic".to_string(),
+        CrashKind::FatalError => "fatal_error".to_string(),
+    }
+}
+
+impl From<CrashBundle> for CrashDetail {
+    fn from(bundle: CrashBundle) -> Self {
+        Self {
+            id: bundle.id,
+            kind: kind_str(bundle.kind),
+            timestamp: bundle.timestamp,
+            message: bundle.message,
+            location: bundle.location,
+            thread: bundle.thread,
+            backtrace: bundle.backtrace,
+            version: bundle.version,
+            active_operations: bundle.active_operations,
+            recent_logs: bundle.recent_logs,
+        }
+    }
+}
+
+#[OpenApi]
+impl SystemApi {
+    /// Health check endpoint for the System API
+    ///
+    /// Returns a simple status message to verify that the System API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn system_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("System API route is healthy".to_string()))
+    }
+
+    /// Run the binary self-check
+    ///
+    /// Verifies that `galatea_files` is writable, the project directory exists and the
+    /// external tools Galatea relies on (`git`, `node`, `pnpm`) are on `PATH`.
+    #[oai(path = "/self-check", method = "get")]
+    async fn self_check_handler(&self) -> SelfCheckApiResponse {
+        let checks: Vec<SelfCheckItem> = crash::run_self_check()
+            .into_iter()
+            .map(|c| SelfCheckItem { name: c.name, ok: c.ok, detail: c.detail })
+            .collect();
+        SelfCheckApiResponse::Ok(OpenApiJson(SelfCheckResponse {
+            healthy: checks.iter().all(|c| c.ok),
+            checks,
+        }))
+    }
+
+    /// Metadata store statistics
+    ///
+    /// Galatea keeps its entity index cache, edit history, jobs, sessions and analytics in an
+    /// embedded SQLite database at `galatea_files/galatea.db`. Returns the schema version, file size
+    /// and row count per table.
+    #[oai(path = "/db-stats", method = "get")]
+    async fn db_stats_handler(&self) -> DbStatsApiResponse {
+        match db::with_db(|db| db.stats()) {
+            Ok(stats) => DbStatsApiResponse::Ok(OpenApiJson(DbStatsResponse {
+                path: stats.path.display().to_string(),
+                size_bytes: stats.size_bytes,
+                schema_version: stats.schema_version,
+                tables: stats
+                    .tables
+                    .into_iter()
+                    .map(|t| DbTableStats { name: t.name, rows: t.rows })
+                    .collect(),
+            })),
+            Err(e) => DbStatsApiResponse::InternalServerError(PlainText(format!(
+                "Failed to read database stats: {:#}",
+                e
+            ))),
+        }
+    }
+
+    /// Get the startup recovery report
+    ///
+    /// At startup Galatea closes out what a process that crashed or was killed left behind:
+    /// its session is ended as `crashed`, its queued and running jobs are marked `interrupted`,
+    /// and services and continuous syncs it had running are relaunched. Set
+    /// `restore_after_crash = "false"` in config.toml to leave them stopped instead.
+    ///
+    /// Returns `404` while recovery is still in progress.
+    #[oai(path = "/recovery", method = "get")]
+    async fn recovery_handler(&self) -> RecoveryApiResponse {
+        match recovery::last_recovery() {
+            Some(report) => RecoveryApiResponse::Ok(OpenApiJson(RecoveryResponse {
+                recovered_at: report.recovered_at,
+                recovered_from_crash: !report.crashed_sessions.is_empty(),
+                crashed_sessions: report.crashed_sessions,
+                interrupted_jobs: report
+                    .interrupted_jobs
+                    .into_iter()
+                    .map(|job| InterruptedJobView { id: job.id, kind: job.kind, previous_status: job.previous_status })
+                    .collect(),
+                lost_services: report.lost_services,
+                restored_services: report.restored_services,
+                resumed_syncs: report.resumed_syncs,
+                closed_syncs: report.closed_syncs,
+            })),
+            None => RecoveryApiResponse::NotFound(PlainText("Startup recovery has not finished yet".to_string())),
+        }
+    }
+
+    /// Get limits being approached
+    ///
+    /// Returns the process-wide warnings as of the last check (edit history near its cap, disk
+    /// nearly full, log buffer dropping entries) and the configured limits. The same warnings
+    /// are attached to every `/api` response as `X-Galatea-Limit-Warning: <code>; used=<n>; limit=<n>`
+    /// headers, alongside `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset`
+    /// when `rate_limit_per_minute` is set. Requests over the rate limit get `429` with
+    /// `{"code": "rate_limit_exceeded", ...}` and a `Retry-After` header.
+    #[oai(path = "/limits", method = "get")]
+    async fn limits_handler(&self) -> LimitsApiResponse {
+        let settings = limits::settings();
+        LimitsApiResponse::Ok(OpenApiJson(LimitsResponse {
+            warnings: limits::active_warnings().into_iter().map(Into::into).collect(),
+            settings: LimitSettingsView {
+                warning_ratio: settings.warning_ratio,
+                rate_limit_per_minute: settings.rate_limit_per_minute,
+                edit_history_max_rows: settings.edit_history_max_rows,
+                log_buffer_capacity: settings.log_buffer_capacity,
+                disk_warning_ratio: settings.disk_warning_ratio,
+            },
+        }))
+    }
+
+    /// List limit events
+    ///
+    /// Each time a limit warning is raised or cleared an event is recorded, so agents can poll
+    /// for them and back off, prune or free space before requests start failing. Keeps the
+    /// most recent 200 events.
+    #[oai(path = "/limit-events", method = "get")]
+    async fn limit_events_handler(
+        &self,
+        /// Only return events with a sequence number greater than this
+        since: Query<Option<u64>>,
+    ) -> LimitEventsApiResponse {
+        let events = limits::events(since.0)
+            .into_iter()
+            .map(|event| LimitEventView {
+                seq: event.seq,
+                timestamp: event.timestamp,
+                state: if event.raised { "raised" } else { "cleared" }.to_string(),
+                warning: event.warning.into(),
+            })
+            .collect();
+        LimitEventsApiResponse::Ok(OpenApiJson(LimitEventsResponse { events }))
+    }
+
+    /// Get registry cache statistics
+    ///
+    /// With `--registry-cache` (or `registry_cache = "true"` in config.toml) Galatea runs a
+    /// caching npm registry proxy on `127.0.0.1:4873` (`registry_cache_port`) and points project
+    /// and MCP server installs at it. Tarballs are kept indefinitely and package documents are
+    /// refreshed after `registry_metadata_ttl_secs` (300 by default), so repeated installs skip
+    /// the download and installs keep working offline for packages fetched before.
+    #[oai(path = "/registry-cache", method = "get")]
+    async fn registry_cache_handler(&self) -> RegistryCacheApiResponse {
+        match registry_cache::stats() {
+            Ok(stats) => RegistryCacheApiResponse::Ok(OpenApiJson(RegistryCacheResponse {
+                enabled: stats.url.is_some(),
+                url: stats.url,
+                upstream: stats.upstream,
+                cache_dir: stats.cache_dir,
+                packages: stats.packages,
+                tarballs: stats.tarballs,
+                size_bytes: stats.size_bytes,
+                hits: stats.hits,
+                misses: stats.misses,
+            })),
+            Err(e) => RegistryCacheApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// List recorded crashes
+    ///
+    /// Crash bundles are written to `galatea_files/crashes` when the process panics or exits
+    /// with a fatal error. Each bundle captures the message, backtrace, operations in progress
+    /// and the recent log tail, so failures in long-running sandboxes can be diagnosed later.
+    #[oai(path = "/crashes", method = "get")]
+    async fn list_crashes_handler(&self) -> CrashListApiResponse {
+        match crash::list_crashes() {
+            Ok(bundles) => {
+                let crashes: Vec<CrashSummary> = bundles
+                    .into_iter()
+                    .map(|b| CrashSummary {
+                        id: b.id,
+                        kind: kind_str(b.kind),
+                        timestamp: b.timestamp,
+                        message: b.message,
+                        location: b.location,
+                    })
+                    .collect();
+                CrashListApiResponse::Ok(OpenApiJson(CrashListResponse {
+                    total_count: crashes.len(),
+                    crashes,
+                }))
+            }
+            Err(e) => CrashListApiResponse::InternalServerError(PlainText(format!(
+                "Failed to list crashes: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Get a crash bundle
+    ///
+    /// Returns the full crash bundle, including backtrace, active operations and log tail.
+    #[oai(path = "/crashes/:id", method = "get")]
+    async fn get_crash_handler(&self, id: OpenApiPath<String>) -> CrashDetailApiResponse {
+        match crash::get_crash(&id.0) {
+            Ok(Some(bundle)) => CrashDetailApiResponse::Ok(OpenApiJson(bundle.into())),
+            Ok(None) => CrashDetailApiResponse::NotFound(PlainText(format!(
+                "Crash '{}' not found",
+                id.0
+            ))),
+            Err(e) => CrashDetailApiResponse::BadRequest(PlainText(e.to_string())),
+        }
+    }
+}
+
+pub fn system_routes() -> Route {
+    let api_service = OpenApiService::new(SystemApi, "System API", "1.0").server("/api/system");
+    Route::new().nest("/", api_service)
+}
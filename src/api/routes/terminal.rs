@@ -0,0 +1,388 @@
+use futures::{SinkExt, StreamExt};
+use poem::http::{HeaderMap, StatusCode};
+use poem::web::websocket::{Message, WebSocket, WebSocketStream};
+use poem::web::{Path as PoemPath, Query as PoemQuery};
+use poem::{get, handler, IntoResponse, Route};
+use poem_openapi::{
+    param::Path as OpenApiPath,
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::api::auth;
+use crate::dev_runtime::crash;
+use crate::dev_runtime::quotas::{self, QuotaMetric};
+use crate::file_system::paths::{get_project_root, resolve_path};
+use crate::terminal::exec::{self, ExecConfig, ExecRequest};
+use crate::terminal::session::{self, Attachment, SessionConfig, SessionOutput, TerminalSession};
+
+// Define an API struct
+pub struct TerminalApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize, Debug)]
+struct ExecCommandRequest {
+    /// **Required.** Program to run, e.g. `npx`. Must be a bare name listed in
+    /// `allowed_commands` under `[terminal_exec]` in config.toml.
+    command: String,
+
+    /// **Optional.** Arguments, passed as they are: there is no shell, so quoting, globs,
+    /// pipes and `$VARS` are not interpreted.
+    args: Option<Vec<String>>,
+
+    /// **Optional.** Directory to run in, relative to the project root. Defaults to the
+    /// project root.
+    working_dir: Option<String>,
+
+    /// **Optional.** Environment variables merged into the server's environment. Variables
+    /// that could run other code through a whitelisted command may not be set: `PATH`,
+    /// `LD_*`, `DYLD_*`, `GIT_*`, `npm_config_*`, `NODE_OPTIONS`, `BASH_ENV` and `ENV`.
+    env_vars: Option<HashMap<String, String>>,
+
+    /// **Optional.** Seconds before the command and everything it started are killed.
+    /// Defaults to `default_timeout_secs` (60) and is capped at `max_timeout_secs` (600).
+    timeout_secs: Option<u64>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ExecCommandResponse {
+    /// Whether the command exited with status 0
+    success: bool,
+
+    /// Exit code; absent when the command was killed
+    exit_code: Option<i32>,
+
+    /// Whether the command was killed for running past its timeout
+    timed_out: bool,
+
+    /// Standard output, cut from the front to `max_output_bytes`
+    stdout: String,
+
+    /// Standard error, cut from the front to `max_output_bytes`
+    stderr: String,
+
+    /// Whether stdout or stderr was cut
+    truncated: bool,
+
+    /// Run time in milliseconds
+    duration_ms: u64,
+}
+
+#[derive(ApiResponse)]
+enum ExecApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ExecCommandResponse>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    /// The command isn't whitelisted, or the request sets a protected environment variable
+    #[oai(status = 403)]
+    Forbidden(PlainText<String>),
+    /// A quota of the caller's API key or session is used up
+    #[oai(status = 429)]
+    TooManyRequests(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct TerminalSessionView {
+    /// Session id, for `/api/terminal/ws/{id}`
+    id: String,
+
+    /// The shell running in the session
+    shell: String,
+
+    /// Terminal width in columns
+    cols: u16,
+
+    /// Terminal height in rows
+    rows: u16,
+
+    /// Unix timestamp (seconds) the session was created at
+    created_at: u64,
+}
+
+impl From<&TerminalSession> for TerminalSessionView {
+    fn from(session: &TerminalSession) -> Self {
+        let (cols, rows) = session.size();
+        Self { id: session.id.clone(), shell: session.shell.clone(), cols, rows, created_at: session.created_at }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct TerminalSessionListResponse {
+    /// Live sessions, oldest first
+    sessions: Vec<TerminalSessionView>,
+}
+
+#[derive(ApiResponse)]
+enum TerminalSessionListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<TerminalSessionListResponse>),
+}
+
+#[derive(ApiResponse)]
+enum KillTerminalSessionApiResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+}
+
+#[OpenApi]
+impl TerminalApi {
+    /// Health check endpoint for the Terminal API
+    ///
+    /// Returns a simple status message to verify that the Terminal API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn terminal_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Terminal API route is healthy".to_string()))
+    }
+
+    /// Run a whitelisted command in the project
+    ///
+    /// Runs a program from the `[terminal_exec]` whitelist (by default git, node, npm, npx,
+    /// pnpm, yarn, tsc and prisma) inside the project root and returns its exit code and
+    /// output once it exits, e.g. `{"command": "npx", "args": ["prisma", "generate"]}`. Use it
+    /// for codegen tools and one-off commands that `/api/editor/script` doesn't cover.
+    ///
+    /// A non-zero exit is not an error: the response is 200 with `success: false`. Commands
+    /// past their timeout are killed together with their child processes. Runs and their CPU
+    /// time count against the caller's `script_runs_per_hour` and `cpu_seconds` quotas.
+    #[oai(path = "/exec", method = "post")]
+    async fn exec_handler(&self, req: OpenApiJson<ExecCommandRequest>) -> ExecApiResponse {
+        let req = req.0;
+        let config = ExecConfig::load();
+        let working_dir = match req.working_dir.as_deref() {
+            Some(dir) => match resolve_path(dir) {
+                Ok(path) if path.is_dir() => path,
+                Ok(_) => return ExecApiResponse::BadRequest(PlainText(format!("Working directory is not a directory: {}", dir))),
+                Err(e) => return ExecApiResponse::BadRequest(PlainText(format!("Failed to resolve working directory '{}': {}", dir, e))),
+            },
+            None => match get_project_root() {
+                Ok(root) => root,
+                Err(e) => return ExecApiResponse::InternalServerError(PlainText(e.to_string())),
+            },
+        };
+        let exec_req = ExecRequest {
+            program: req.command,
+            args: req.args.unwrap_or_default(),
+            working_dir,
+            env: req.env_vars.unwrap_or_default(),
+            timeout_secs: req.timeout_secs,
+        };
+        if let Err(rejection) = exec::validate(&config, &exec_req) {
+            return ExecApiResponse::Forbidden(PlainText(rejection.to_string()));
+        }
+        if let Err(exceeded) = quotas::check(&[QuotaMetric::ScriptRunsPerHour, QuotaMetric::CpuSeconds]) {
+            return ExecApiResponse::TooManyRequests(PlainText(exceeded.to_string()));
+        }
+        let _operation = crash::track_operation(format!("exec {} {}", exec_req.program, exec_req.args.join(" ")));
+
+        let cpu_before = quotas::children_cpu_seconds();
+        let result = exec::run(&config, &exec_req).await;
+        quotas::charge(QuotaMetric::ScriptRunsPerHour, 1.0);
+        quotas::charge(QuotaMetric::CpuSeconds, quotas::children_cpu_seconds() - cpu_before);
+        match result {
+            Ok(output) => ExecApiResponse::Ok(OpenApiJson(ExecCommandResponse {
+                success: output.exit_code == Some(0),
+                exit_code: output.exit_code,
+                timed_out: output.timed_out,
+                stdout: output.stdout,
+                stderr: output.stderr,
+                truncated: output.truncated,
+                duration_ms: output.duration_ms,
+            })),
+            Err(e) => ExecApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// List interactive terminal sessions
+    ///
+    /// Returns the shells started through `/api/terminal/ws/{id}` that are still running.
+    /// Sessions outlive the connections attached to them; they end when the shell exits or is
+    /// killed.
+    #[oai(path = "/sessions", method = "get")]
+    async fn list_sessions_handler(&self) -> TerminalSessionListApiResponse {
+        let sessions = session::list().iter().map(|s| TerminalSessionView::from(s.as_ref())).collect();
+        TerminalSessionListApiResponse::Ok(OpenApiJson(TerminalSessionListResponse { sessions }))
+    }
+
+    /// Kill an interactive terminal session
+    ///
+    /// Hangs up on the session's shell and kills it and everything running in it if it hasn't
+    /// exited two seconds later. Attached clients receive an `exit` message.
+    #[oai(path = "/sessions/:id", method = "delete")]
+    async fn kill_session_handler(&self, id: OpenApiPath<String>) -> KillTerminalSessionApiResponse {
+        if session::kill(&id.0) {
+            KillTerminalSessionApiResponse::Ok(PlainText(format!("Killing terminal session {}", id.0)))
+        } else {
+            KillTerminalSessionApiResponse::NotFound(PlainText(format!("No terminal session with id {}", id.0)))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct TerminalWsParams {
+    /// Width of a newly created terminal; defaults to 80
+    cols: Option<u16>,
+    /// Height of a newly created terminal; defaults to 24
+    rows: Option<u16>,
+}
+
+// Text messages a client sends; binary messages are written to the terminal as they are
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Input { data: String },
+    Resize { cols: u16, rows: u16 },
+    /// Replays the scrollback
+    Read,
+    Kill,
+}
+
+// Text messages sent to the client; terminal output is sent as binary messages
+#[derive(serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Session { id: String, shell: String, cols: u16, rows: u16, created: bool },
+    Exit { code: Option<i32> },
+    Error { message: String },
+}
+
+impl ServerMessage {
+    fn into_message(self) -> Message {
+        Message::text(serde_json::to_string(&self).unwrap_or_default())
+    }
+}
+
+/// Attach to an interactive terminal session over a WebSocket, creating it if it doesn't exist.
+///
+/// Served at `/api/terminal/ws/:session_id`. On connect the client receives a `session` text
+/// message and the scrollback as a binary message, then the terminal's output as binary
+/// messages. It sends keystrokes as binary messages or `{"type":"input","data":"ls\n"}`, and
+/// controls the session with `{"type":"resize","cols":120,"rows":40}`, `{"type":"read"}` (replay
+/// the scrollback) and `{"type":"kill"}`. Closing the socket leaves the shell running.
+///
+/// Upgrades from a page of another origin are refused, so a web page can't open a shell.
+#[handler]
+pub async fn terminal_ws_handler(
+    PoemPath(session_id): PoemPath<String>,
+    PoemQuery(params): PoemQuery<TerminalWsParams>,
+    headers: &HeaderMap,
+    ws: WebSocket,
+) -> poem::Result<impl IntoResponse> {
+    if auth::is_cross_origin(headers) {
+        return Err(poem::Error::from_string("Cross-origin terminal connections are not allowed", StatusCode::FORBIDDEN));
+    }
+    let (session, created) = match session::get(&session_id) {
+        Some(session) => (session, false),
+        None => {
+            let config = SessionConfig::load();
+            if !config.enabled {
+                return Err(poem::Error::from_string(
+                    "Terminal sessions are disabled (enabled under [terminal_sessions] in config.toml)",
+                    StatusCode::FORBIDDEN,
+                ));
+            }
+            if let Err(exceeded) = quotas::check(&[QuotaMetric::ScriptRunsPerHour]) {
+                return Err(poem::Error::from_string(exceeded.to_string(), StatusCode::TOO_MANY_REQUESTS));
+            }
+            let project_root = get_project_root().map_err(|e| poem::Error::from_string(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+            let (cols, rows) = (params.cols.unwrap_or(80), params.rows.unwrap_or(24));
+            let session = session::create(&config, &session_id, &project_root, cols, rows)
+                .map_err(|e| poem::Error::from_string(format!("{:#}", e), StatusCode::BAD_REQUEST))?;
+            quotas::charge(QuotaMetric::ScriptRunsPerHour, 1.0);
+            (session, true)
+        }
+    };
+    Ok(ws.on_upgrade(move |socket| attach_socket(socket, session, created)))
+}
+
+// Writes off the runtime, since a shell that isn't reading can block the terminal
+async fn write_to_session(session: &Arc<TerminalSession>, data: Vec<u8>) -> Result<(), String> {
+    let session = session.clone();
+    match tokio::task::spawn_blocking(move || session.write(&data)).await {
+        Ok(result) => result.map_err(|e| format!("{:#}", e)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+async fn attach_socket(socket: WebSocketStream, session: Arc<TerminalSession>, created: bool) {
+    let (mut sink, mut stream) = socket.split();
+    let Attachment { scrollback, exited, mut output } = session.attach();
+    let (cols, rows) = session.size();
+    let hello = ServerMessage::Session { id: session.id.clone(), shell: session.shell.clone(), cols, rows, created };
+    if sink.send(hello.into_message()).await.is_err() {
+        return;
+    }
+    if !scrollback.is_empty() && sink.send(Message::binary(scrollback)).await.is_err() {
+        return;
+    }
+    if let Some(code) = exited {
+        let _ = sink.send(ServerMessage::Exit { code }.into_message()).await;
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            event = output.recv() => match event {
+                Ok(SessionOutput::Data(data)) => {
+                    if sink.send(Message::binary(data)).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(SessionOutput::Exit(code)) => {
+                    let _ = sink.send(ServerMessage::Exit { code }.into_message()).await;
+                    break;
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    let message = format!("Fell behind the terminal; {} output chunks were skipped", skipped);
+                    let _ = sink.send(ServerMessage::Error { message }.into_message()).await;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = stream.next() => {
+                let reply = match message {
+                    Some(Ok(Message::Binary(data))) => write_to_session(&session, data).await.err(),
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Input { data }) => write_to_session(&session, data.into_bytes()).await.err(),
+                        Ok(ClientMessage::Resize { cols, rows }) => session.resize(cols, rows).err().map(|e| format!("{:#}", e)),
+                        Ok(ClientMessage::Read) => {
+                            if sink.send(Message::binary(session.attach().scrollback)).await.is_err() {
+                                break;
+                            }
+                            None
+                        }
+                        Ok(ClientMessage::Kill) => {
+                            session::kill(&session.id);
+                            None
+                        }
+                        Err(e) => Some(format!("Invalid message: {}", e)),
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => None,
+                };
+                if let Some(message) = reply {
+                    if sink.send(ServerMessage::Error { message }.into_message()).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    tracing::debug!(target: "api::terminal", session = %session.id, "Terminal client detached.");
+}
+
+pub fn terminal_routes() -> Route {
+    let api_service = OpenApiService::new(TerminalApi, "Terminal API", "1.0").server("/api/terminal");
+    Route::new().at("/ws/:session_id", get(terminal_ws_handler)).nest("/", api_service)
+}
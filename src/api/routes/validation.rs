@@ -0,0 +1,253 @@
+use poem::Route;
+use poem_openapi::{
+    param::Path as OpenApiPath,
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+
+use crate::dev_operation::validation::{self, StepKind, ValidationRun, ValidationStep};
+use crate::file_system::paths::get_project_root;
+
+// Define an API struct
+pub struct ValidationApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct ValidationRunRequest {
+    /// Steps to run, in order
+    ///
+    /// **Optional.** Any of `typecheck`, `lint`, `test`, `build`. Runs typecheck, lint and test
+    /// when omitted; `build` is opt-in since it is slow and shares `.next` with the dev server.
+    steps: Option<Vec<String>>,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ValidationStepView {
+    /// `typecheck`, `lint`, `test` or `build`
+    step: String,
+
+    /// `passed`, `failed` or `skipped`
+    ///
+    /// Steps are skipped when the project has no tooling for them (no `tsconfig.json`,
+    /// no `lint`/`test` script in package.json).
+    status: String,
+
+    /// Command that was run
+    command: Option<String>,
+
+    /// Exit code of the command
+    exit_code: Option<i32>,
+
+    /// How long the step took, in milliseconds
+    duration_ms: u64,
+
+    /// Combined stdout and stderr (tail only for long output), or why the step was skipped
+    output: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ValidationRunView {
+    /// Run identifier, used in `/reports/{run_id}`
+    id: String,
+
+    /// Unix timestamp when the run started
+    started_at: u64,
+
+    /// Unix timestamp when the run finished
+    finished_at: u64,
+
+    /// Whether no step failed
+    passed: bool,
+
+    /// Step results, in the order they ran
+    steps: Vec<ValidationStepView>,
+
+    /// `git diff HEAD --stat` at the time of the run
+    diff_stat: String,
+
+    /// `git diff HEAD` at the time of the run
+    diff: String,
+
+    /// Path of the HTML report for this run
+    report_url: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ValidationRunSummary {
+    /// Run identifier
+    id: String,
+
+    /// Unix timestamp when the run started
+    started_at: u64,
+
+    /// Whether no step failed
+    passed: bool,
+
+    /// `step: status` for each step, e.g. `lint: failed`
+    steps: Vec<String>,
+
+    /// Path of the HTML report for this run
+    report_url: String,
+}
+
+#[derive(Object, serde::Serialize)]
+struct ValidationRunListResponse {
+    /// Recorded runs, newest first
+    runs: Vec<ValidationRunSummary>,
+
+    /// Number of runs returned
+    total_count: usize,
+}
+
+#[derive(ApiResponse)]
+enum ValidationRunApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ValidationRunView>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(ApiResponse)]
+enum ValidationRunListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<ValidationRunListResponse>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+fn report_url(id: &str) -> String {
+    format!("/reports/{}", id)
+}
+
+impl From<ValidationStep> for ValidationStepView {
+    fn from(step: ValidationStep) -> Self {
+        Self {
+            step: step.kind.as_str().to_string(),
+            status: step.status.as_str().to_string(),
+            command: step.command,
+            exit_code: step.exit_code,
+            duration_ms: step.duration_ms,
+            output: step.output,
+        }
+    }
+}
+
+impl From<ValidationRun> for ValidationRunView {
+    fn from(run: ValidationRun) -> Self {
+        Self {
+            report_url: report_url(&run.id),
+            id: run.id,
+            started_at: run.started_at,
+            finished_at: run.finished_at,
+            passed: run.passed,
+            steps: run.steps.into_iter().map(ValidationStepView::from).collect(),
+            diff_stat: run.diff_stat,
+            diff: run.diff,
+        }
+    }
+}
+
+#[OpenApi]
+impl ValidationApi {
+    /// Health check endpoint for the Validation API
+    ///
+    /// Returns a simple status message to verify that the Validation API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn validation_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Validation API route is healthy".to_string()))
+    }
+
+    /// Run the validation pipeline
+    ///
+    /// Runs typecheck (`tsc --noEmit`), lint (`pnpm run lint`) and test (`pnpm run test`) against
+    /// the project, captures the uncommitted diff, and records the run. The response includes
+    /// `report_url`, a standalone HTML report that can be shared with people who don't use the API.
+    /// Each run also records a project health score, see `GET /api/project/health`.
+    #[oai(path = "/run", method = "post")]
+    async fn run_validation_handler(&self, req: OpenApiJson<ValidationRunRequest>) -> ValidationRunApiResponse {
+        let steps = match &req.0.steps {
+            None => StepKind::DEFAULT.to_vec(),
+            Some(names) => {
+                let mut steps = Vec::new();
+                for name in names {
+                    match StepKind::from_name(name) {
+                        Some(step) => steps.push(step),
+                        None => {
+                            return ValidationRunApiResponse::BadRequest(PlainText(format!(
+                                "Unknown step '{}'. Use typecheck, lint, test or build.",
+                                name
+                            )))
+                        }
+                    }
+                }
+                steps
+            }
+        };
+        let project_root = match get_project_root() {
+            Ok(root) => root,
+            Err(e) => return ValidationRunApiResponse::InternalServerError(PlainText(e.to_string())),
+        };
+
+        match validation::run_validation(&project_root, &steps).await {
+            Ok(run) => ValidationRunApiResponse::Ok(OpenApiJson(run.into())),
+            Err(e) => ValidationRunApiResponse::InternalServerError(PlainText(format!(
+                "Failed to record validation run: {:#}",
+                e
+            ))),
+        }
+    }
+
+    /// List validation runs
+    #[oai(path = "/runs", method = "get")]
+    async fn list_runs_handler(&self) -> ValidationRunListApiResponse {
+        match validation::list_runs() {
+            Ok(runs) => {
+                let runs: Vec<ValidationRunSummary> = runs
+                    .into_iter()
+                    .map(|run| ValidationRunSummary {
+                        report_url: report_url(&run.id),
+                        id: run.id,
+                        started_at: run.started_at,
+                        passed: run.passed,
+                        steps: run
+                            .steps
+                            .iter()
+                            .map(|s| format!("{}: {}", s.kind.as_str(), s.status.as_str()))
+                            .collect(),
+                    })
+                    .collect();
+                ValidationRunListApiResponse::Ok(OpenApiJson(ValidationRunListResponse {
+                    total_count: runs.len(),
+                    runs,
+                }))
+            }
+            Err(e) => ValidationRunListApiResponse::InternalServerError(PlainText(e.to_string())),
+        }
+    }
+
+    /// Get a validation run
+    ///
+    /// Use `latest` as the id for the most recent run.
+    #[oai(path = "/runs/:id", method = "get")]
+    async fn get_run_handler(&self, id: OpenApiPath<String>) -> ValidationRunApiResponse {
+        match validation::get_run(&id.0) {
+            Ok(Some(run)) => ValidationRunApiResponse::Ok(OpenApiJson(run.into())),
+            Ok(None) => ValidationRunApiResponse::NotFound(PlainText(format!("Validation run '{}' not found", id.0))),
+            Err(e) => ValidationRunApiResponse::BadRequest(PlainText(e.to_string())),
+        }
+    }
+}
+
+pub fn validation_routes() -> Route {
+    let api_service = OpenApiService::new(ValidationApi, "Validation API", "1.0").server("/api/validation");
+    Route::new().nest("/", api_service)
+}
@@ -0,0 +1,181 @@
+use poem::Route;
+use poem_openapi::{
+    param::Path as OpenApiPath,
+    payload::{Json as OpenApiJson, PlainText},
+    ApiResponse, Object, OpenApi, OpenApiService,
+};
+use std::collections::HashMap;
+
+use crate::dev_runtime::workspaces::{self, Workspace, DEFAULT_WORKSPACE};
+
+// Define an API struct
+pub struct WorkspacesApi;
+
+#[derive(ApiResponse)]
+enum HealthResponse {
+    #[oai(status = 200)]
+    Ok(PlainText<String>),
+}
+
+#[derive(Object, serde::Serialize)]
+struct WorkspaceView {
+    /// Workspace id, for the `x-galatea-workspace` header or the `/w/{id}` path prefix
+    id: String,
+
+    name: String,
+
+    /// Absolute path of the project root
+    root: String,
+
+    /// Whether it is the default project (`--project-dir`, else `project/` next to the
+    /// executable), used by requests that don't name a workspace
+    default: bool,
+
+    /// When it was registered (Unix seconds); absent for the default workspace
+    created_at: Option<i64>,
+
+    /// Prefix that scopes any API path to this workspace, e.g. `/w/{id}/api/editor/command`
+    path_prefix: String,
+}
+
+impl From<Workspace> for WorkspaceView {
+    fn from(w: Workspace) -> Self {
+        Self {
+            path_prefix: format!("/w/{}", w.id),
+            default: w.id == DEFAULT_WORKSPACE,
+            root: w.root.display().to_string(),
+            id: w.id,
+            name: w.name,
+            created_at: w.created_at,
+        }
+    }
+}
+
+#[derive(Object, serde::Serialize)]
+struct WorkspaceListResponse {
+    /// The default workspace first, then the others in the order they were added
+    workspaces: Vec<WorkspaceView>,
+}
+
+#[derive(ApiResponse)]
+enum WorkspaceListApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<WorkspaceListResponse>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[derive(Object, serde::Deserialize)]
+struct CreateWorkspaceRequest {
+    /// Display name; the id is derived from it
+    ///
+    /// **Required.**
+    name: String,
+
+    /// Absolute path of an existing project to register
+    ///
+    /// **Optional.** When omitted, a new project is scaffolded from `template`.
+    root: Option<String>,
+
+    /// Template to scaffold from: a built-in template name (see `/api/project/templates`) or a Git URL
+    ///
+    /// **Optional.** Defaults to `nextjs`. Ignored with `root`.
+    template: Option<String>,
+
+    /// Values for the template's variables
+    ///
+    /// **Optional.** Ignored with `root`.
+    variables: Option<HashMap<String, String>>,
+}
+
+#[derive(ApiResponse)]
+enum WorkspaceApiResponse {
+    #[oai(status = 200)]
+    Ok(OpenApiJson<WorkspaceView>),
+    #[oai(status = 400)]
+    BadRequest(PlainText<String>),
+    #[oai(status = 404)]
+    NotFound(PlainText<String>),
+    #[oai(status = 500)]
+    InternalServerError(PlainText<String>),
+}
+
+#[OpenApi]
+impl WorkspacesApi {
+    /// Health check endpoint for the Workspaces API
+    ///
+    /// Returns a simple status message to verify that the Workspaces API is running and accessible.
+    #[oai(path = "/health", method = "get")]
+    async fn workspaces_health(&self) -> HealthResponse {
+        HealthResponse::Ok(PlainText("Workspaces API route is healthy".to_string()))
+    }
+
+    /// List workspaces
+    ///
+    /// Every project root requests can be scoped to. A request names its workspace with the
+    /// `x-galatea-workspace` header or by prefixing its path with `/w/{id}`
+    /// (`/w/shop-1a2b3c/api/editor/command`); the editor, file search, scripts, git, language
+    /// servers and the other APIs then work in that project. Requests naming neither work in
+    /// the default workspace. The Next.js dev server, the file watcher and the code index only
+    /// run for the default workspace.
+    #[oai(path = "/", method = "get")]
+    async fn list_workspaces_handler(&self) -> WorkspaceListApiResponse {
+        match workspaces::list() {
+            Ok(list) => WorkspaceListApiResponse::Ok(OpenApiJson(WorkspaceListResponse {
+                workspaces: list.into_iter().map(WorkspaceView::from).collect(),
+            })),
+            Err(e) => WorkspaceListApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Add a workspace
+    ///
+    /// Registers an existing project with `root`, or scaffolds a new one from `template` into
+    /// `galatea_files/workspaces/`. Scaffolding clones the template and
+    /// installs its dependencies before answering, which can take minutes.
+    #[oai(path = "/", method = "post")]
+    async fn create_workspace_handler(&self, req: OpenApiJson<CreateWorkspaceRequest>) -> WorkspaceApiResponse {
+        let req = req.0;
+        let result = match &req.root {
+            Some(root) => workspaces::register(&req.name, std::path::Path::new(root)),
+            None => workspaces::scaffold(&req.name, req.template.as_deref(), &req.variables.unwrap_or_default()).await,
+        };
+        match result {
+            Ok(workspace) => WorkspaceApiResponse::Ok(OpenApiJson(workspace.into())),
+            Err(e) if req.root.is_some() => WorkspaceApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+            Err(e) => WorkspaceApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Get a workspace
+    #[oai(path = "/:id", method = "get")]
+    async fn get_workspace_handler(&self, id: OpenApiPath<String>) -> WorkspaceApiResponse {
+        match workspaces::get(&id.0) {
+            Ok(Some(workspace)) => WorkspaceApiResponse::Ok(OpenApiJson(workspace.into())),
+            Ok(None) => WorkspaceApiResponse::NotFound(PlainText(format!("No workspace '{}'", id.0))),
+            Err(e) => WorkspaceApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        }
+    }
+
+    /// Remove a workspace
+    ///
+    /// Unregisters the workspace and returns it. Its files stay on disk. The default workspace
+    /// can't be removed.
+    #[oai(path = "/:id", method = "delete")]
+    async fn delete_workspace_handler(&self, id: OpenApiPath<String>) -> WorkspaceApiResponse {
+        let workspace = match workspaces::get(&id.0) {
+            Ok(Some(workspace)) => workspace,
+            Ok(None) => return WorkspaceApiResponse::NotFound(PlainText(format!("No workspace '{}'", id.0))),
+            Err(e) => return WorkspaceApiResponse::InternalServerError(PlainText(format!("{:#}", e))),
+        };
+        match workspaces::unregister(&id.0) {
+            Ok(_) => WorkspaceApiResponse::Ok(OpenApiJson(workspace.into())),
+            Err(e) => WorkspaceApiResponse::BadRequest(PlainText(format!("{:#}", e))),
+        }
+    }
+}
+
+pub fn workspaces_routes() -> Route {
+    let api_service = OpenApiService::new(WorkspacesApi, "Workspaces API", "1.0").server("/api/workspaces");
+    Route::new().nest("/", api_service)
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::parser::CodeEntity;
+
+// Entity kinds a call or JSX element can land on
+const CALLABLE_TYPES: &[&str] = &["Function", "Method", "Function Component", "Class"];
+pub const MAX_DEPTH: usize = 5;
+
+/// A place in the project that refers to a symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceHit {
+    /// Path relative to the project root
+    pub path: String,
+    /// The entity containing the reference
+    pub entity: String,
+    /// "call", "jsx", "class" or "import"
+    pub kind: String,
+    pub line: usize,
+}
+
+/// A function, method, component or class in a call graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    /// `<relative path>#<name>`, with the class or impl as `Type.name` for methods
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub code_type: String,
+    pub line: usize,
+}
+
+/// `from` calls or renders `to`, first at `line` of `from`'s file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Callees,
+    Callers,
+    Both,
+}
+
+// Rust `use` paths aren't resolved, so an import without a target can still be a project item
+fn is_rust(file: &Path) -> bool {
+    file.extension().is_some_and(|ext| ext == "rs")
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+// Where `file` imports `name` from: Some(Some(path)) for a project file, Some(None) for a
+// package or an unresolved `use`, None when it doesn't import it
+fn import_source<'a>(entities: &'a [CodeEntity], name: &str) -> Option<Option<&'a str>> {
+    entities
+        .iter()
+        .flat_map(|e| &e.references)
+        .find(|r| r.kind == "import" && r.name == name)
+        .map(|r| r.target_path.as_deref())
+}
+
+/// Every reference to `symbol` in the project. With `defined_in`, files that get a symbol of
+/// the same name elsewhere (their own definition, or an import from another file) are left out.
+pub fn find_references(
+    snapshot: &[(PathBuf, Arc<Vec<CodeEntity>>)],
+    root: &Path,
+    symbol: &str,
+    defined_in: Option<&Path>,
+) -> Vec<ReferenceHit> {
+    let mut hits = Vec::new();
+    for (file, entities) in snapshot {
+        if let Some(definition) = defined_in.filter(|d| *d != file.as_path()) {
+            let unrelated = match import_source(entities, symbol) {
+                Some(source) => source.map_or(!is_rust(file), |s| Path::new(s) != definition),
+                None => entities.iter().any(|e| e.name == symbol && CALLABLE_TYPES.contains(&e.code_type.as_str())),
+            };
+            if unrelated {
+                continue;
+            }
+        }
+        for entity in entities.iter() {
+            for reference in entity.references.iter().filter(|r| r.name == symbol) {
+                hits.push(ReferenceHit {
+                    path: relative(root, file),
+                    entity: entity.name.clone(),
+                    kind: reference.kind.clone(),
+                    line: reference.line,
+                });
+            }
+        }
+    }
+    hits.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    hits.dedup();
+    hits
+}
+
+struct Graph {
+    nodes: BTreeMap<String, GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+fn node_id(root: &Path, file: &Path, entity: &CodeEntity) -> String {
+    match &entity.context.struct_name {
+        Some(owner) => format!("{}#{}.{}", relative(root, file), owner, entity.name),
+        None => format!("{}#{}", relative(root, file), entity.name),
+    }
+}
+
+// The whole project's graph. A reference resolves to a definition in the same file, else in
+// the file it is imported from, else to every definition of that name unless it comes from a
+// package.
+fn build_graph(snapshot: &[(PathBuf, Arc<Vec<CodeEntity>>)], root: &Path) -> Graph {
+    let mut nodes = BTreeMap::new();
+    let mut by_name: HashMap<&str, Vec<(&Path, String)>> = HashMap::new();
+    for (file, entities) in snapshot {
+        // Overloads and getter/setter pairs share an id; the first declaration stands for all
+        for entity in entities.iter().filter(|e| CALLABLE_TYPES.contains(&e.code_type.as_str())) {
+            let id = node_id(root, file, entity);
+            if nodes.contains_key(&id) {
+                continue;
+            }
+            by_name.entry(entity.name.as_str()).or_default().push((file.as_path(), id.clone()));
+            let node = GraphNode {
+                id: id.clone(),
+                name: entity.name.clone(),
+                path: relative(root, file),
+                code_type: entity.code_type.clone(),
+                line: entity.line,
+            };
+            nodes.insert(id, node);
+        }
+    }
+
+    let mut edges = Vec::new();
+    let mut seen = HashSet::new();
+    for (file, entities) in snapshot {
+        for entity in entities.iter().filter(|e| CALLABLE_TYPES.contains(&e.code_type.as_str())) {
+            let from = node_id(root, file, entity);
+            for reference in entity.references.iter().filter(|r| r.kind == "call" || r.kind == "jsx") {
+                let Some(definitions) = by_name.get(reference.name.as_str()) else { continue };
+                let local: Vec<&String> = definitions.iter().filter(|(f, _)| *f == file.as_path()).map(|(_, id)| id).collect();
+                let targets = if !local.is_empty() {
+                    local
+                } else {
+                    match import_source(entities, &reference.name) {
+                        Some(Some(source)) => definitions.iter().filter(|(f, _)| *f == Path::new(source)).map(|(_, id)| id).collect(),
+                        Some(None) if !is_rust(file) => Vec::new(),
+                        _ => definitions.iter().map(|(_, id)| id).collect(),
+                    }
+                };
+                for to in targets {
+                    if seen.insert((from.clone(), to.clone())) {
+                        edges.push(GraphEdge { from: from.clone(), to: to.clone(), kind: reference.kind.clone(), line: reference.line });
+                    }
+                }
+            }
+        }
+    }
+    Graph { nodes, edges }
+}
+
+/// The functions `symbol` calls or renders (callees) and/or those calling or rendering it
+/// (callers), following edges up to `depth` steps (at most `MAX_DEPTH`). `path` picks one
+/// definition when several share the name.
+pub fn call_graph(
+    snapshot: &[(PathBuf, Arc<Vec<CodeEntity>>)],
+    root: &Path,
+    symbol: &str,
+    path: Option<&Path>,
+    direction: Direction,
+    depth: usize,
+) -> CallGraph {
+    let graph = build_graph(snapshot, root);
+    let wanted_path = path.map(|p| relative(root, p));
+    let start: Vec<&String> = graph
+        .nodes
+        .values()
+        .filter(|n| n.name == symbol && wanted_path.as_ref().is_none_or(|p| &n.path == p))
+        .map(|n| &n.id)
+        .collect();
+
+    let mut visited: HashSet<&String> = start.iter().copied().collect();
+    let mut edge_indexes = BTreeSet::new();
+    for callees in [true, false] {
+        if (callees && direction == Direction::Callers) || (!callees && direction == Direction::Callees) {
+            continue;
+        }
+        let mut queue: VecDeque<(&String, usize)> = start.iter().map(|id| (*id, 0)).collect();
+        let mut expanded = HashSet::new();
+        while let Some((id, level)) = queue.pop_front() {
+            if level >= depth.min(MAX_DEPTH) || !expanded.insert(id) {
+                continue;
+            }
+            for (index, edge) in graph.edges.iter().enumerate() {
+                let next = match callees {
+                    true if &edge.from == id => &edge.to,
+                    false if &edge.to == id => &edge.from,
+                    _ => continue,
+                };
+                edge_indexes.insert(index);
+                visited.insert(next);
+                queue.push_back((next, level + 1));
+            }
+        }
+    }
+
+    CallGraph {
+        nodes: graph.nodes.values().filter(|n| visited.contains(&n.id)).cloned().collect(),
+        edges: edge_indexes.into_iter().map(|i| graph.edges[i].clone()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codebase_indexing::parser::extract_ts_entities;
+    use std::fs;
+
+    #[test]
+    fn test_references_and_call_graph_follow_imports() {
+        let dir = crate::test_support::project_tempdir();
+        let root = dir.path();
+        fs::write(root.join("package.json"), "{}").unwrap();
+        fs::write(root.join("util.ts"), "export function load() { return parse(); }\nfunction parse() { return 1; }\n").unwrap();
+        fs::write(root.join("other.ts"), "export function load() { return 2; }\n").unwrap();
+        fs::write(root.join("app.tsx"), "import { load } from './util';\nexport function App() { load(); return <View />; }\nfunction View() { return <div />; }\n").unwrap();
+        fs::write(root.join("cli.ts"), "import { load } from './other';\nexport function main() { load(); }\n").unwrap();
+
+        let snapshot: Vec<(PathBuf, Arc<Vec<CodeEntity>>)> = ["app.tsx", "cli.ts", "other.ts", "util.ts"]
+            .iter()
+            .map(|name| {
+                let file = root.join(name);
+                let entities = extract_ts_entities(&file, name.ends_with(".tsx"), None).unwrap();
+                (file, Arc::new(entities))
+            })
+            .collect();
+
+        let refs = find_references(&snapshot, root, "load", Some(&root.join("util.ts")));
+        assert_eq!(refs.iter().map(|r| (r.path.as_str(), r.kind.as_str())).collect::<Vec<_>>(), vec![("app.tsx", "import"), ("app.tsx", "call")]);
+
+        let callees = call_graph(&snapshot, root, "App", None, Direction::Callees, 2);
+        let edges: Vec<(&str, &str)> = callees.edges.iter().map(|e| (e.from.as_str(), e.to.as_str())).collect();
+        assert_eq!(edges, vec![("app.tsx#App", "util.ts#load"), ("app.tsx#App", "app.tsx#View"), ("util.ts#load", "util.ts#parse")]);
+
+        let callers = call_graph(&snapshot, root, "load", Some(&root.join("other.ts")), Direction::Callers, 1);
+        assert_eq!(callers.nodes.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), vec!["cli.ts#main", "other.ts#load"]);
+    }
+}
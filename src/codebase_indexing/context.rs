@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::parser::{CodeContext, CodeEntity};
+use super::structure::StructureNode;
+use crate::dev_operation::diagnostics::DiagnosticEntry;
+
+// Share of the budget the file tree may take
+const TREE_SHARE_PERCENT: usize = 15;
+// Share of the budget kept back from entities for the diagnostics of their files
+const DIAGNOSTICS_SHARE_PERCENT: usize = 15;
+// Tokens for the fields around an entity's snippet or a diagnostic's message
+const ENTRY_OVERHEAD_TOKENS: usize = 12;
+// Task words shorter than this don't count when matching entity names
+const MIN_KEYWORD_LEN: usize = 4;
+
+/// A code entity offered for a context package.
+#[derive(Debug, Clone)]
+pub struct ContextEntity {
+    /// Relative to the project root
+    pub path: String,
+    pub entity: CodeEntity,
+    /// `file` for an entity of a requested file, `semantic` or `keyword` for one matching the task
+    pub reason: &'static str,
+    /// How well it matches the task, for `semantic` (cosine similarity) and `keyword` matches
+    pub score: Option<f32>,
+}
+
+/// What fit into the token budget.
+#[derive(Debug, Clone)]
+pub struct ContextPackage {
+    pub entities: Vec<ContextEntity>,
+    /// Indented excerpt of the project tree around the entities' files
+    pub tree: String,
+    /// Diagnostics in the entities' files, most severe first
+    pub diagnostics: Vec<DiagnosticEntry>,
+    pub estimated_tokens: usize,
+    /// Candidates left out because they didn't fit, not counting ones inside an included entity
+    pub omitted_entities: usize,
+    pub omitted_diagnostics: usize,
+}
+
+/// Rough token count of `text`, at four characters a token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn entity_tokens(candidate: &ContextEntity) -> usize {
+    estimate_tokens(&candidate.path) + estimate_tokens(&candidate.entity.context.snippet) + ENTRY_OVERHEAD_TOKENS
+}
+
+fn diagnostic_tokens(diagnostic: &DiagnosticEntry) -> usize {
+    estimate_tokens(&diagnostic.path) + estimate_tokens(&diagnostic.message) + ENTRY_OVERHEAD_TOKENS
+}
+
+/// The top-level entities of a file, in source order, for including it as a whole.
+pub fn file_entities(path: &str, entities: &[CodeEntity]) -> Vec<ContextEntity> {
+    entities
+        .iter()
+        .filter(|e| e.code_type != "Import" && e.context.struct_name.is_none())
+        .map(|entity| ContextEntity { path: path.to_string(), entity: entity.clone(), reason: "file", score: None })
+        .collect()
+}
+
+/// A file the code index has no entities for (e.g. `package.json`), included as a whole.
+pub fn whole_file(path: &str, content: String) -> ContextEntity {
+    let name = path.rsplit('/').next().unwrap_or(path).to_string();
+    let entity = CodeEntity {
+        name: name.clone(),
+        signature: String::new(),
+        code_type: "File".to_string(),
+        docstring: None,
+        line: 1,
+        line_from: 1,
+        line_to: content.lines().count().max(1),
+        context: CodeContext { module: None, file_path: path.to_string(), file_name: name, struct_name: None, snippet: content },
+        embedding: None,
+        references: Vec::new(),
+    };
+    ContextEntity { path: path.to_string(), entity, reason: "file", score: None }
+}
+
+/// Up to `k` entities whose names contain the most words of `task`, for when semantic search
+/// is unavailable. `calculateCartTotal` matches `fix the cart total` on `cart` and `total`.
+pub fn keyword_matches(snapshot: &[(PathBuf, Arc<Vec<CodeEntity>>)], project_root: &Path, task: &str, k: usize) -> Vec<ContextEntity> {
+    let mut words: Vec<String> = task
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= MIN_KEYWORD_LEN)
+        .map(str::to_lowercase)
+        .collect();
+    words.sort_unstable();
+    words.dedup();
+    if words.is_empty() {
+        return Vec::new();
+    }
+    let mut matches: Vec<(usize, ContextEntity)> = Vec::new();
+    for (file, entities) in snapshot {
+        let path = file.strip_prefix(project_root).unwrap_or(file).to_string_lossy().replace('\\', "/");
+        for entity in entities.iter().filter(|e| e.code_type != "Import") {
+            let name = entity.name.to_lowercase();
+            let hits = words.iter().filter(|w| name.contains(w.as_str())).count();
+            if hits > 0 {
+                let score = Some(hits as f32 / words.len() as f32);
+                matches.push((hits, ContextEntity { path: path.clone(), entity: entity.clone(), reason: "keyword", score }));
+            }
+        }
+    }
+    matches.sort_by(|(a, x), (b, y)| b.cmp(a).then_with(|| (&x.path, x.entity.line).cmp(&(&y.path, y.entity.line))));
+    matches.into_iter().take(k).map(|(_, candidate)| candidate).collect()
+}
+
+fn render_tree(nodes: &[StructureNode], focus: &HashSet<&str>, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    for node in nodes {
+        match node {
+            StructureNode::Dir { name, path, file_count, children, .. } => {
+                let prefix = format!("{}/", path);
+                if focus.iter().any(|f| f.starts_with(&prefix)) {
+                    lines.push(format!("{}{}/", indent, name));
+                    render_tree(children, focus, depth + 1, lines);
+                } else {
+                    lines.push(format!("{}{}/ ({} files)", indent, name, file_count));
+                }
+            }
+            StructureNode::File { name, .. } => lines.push(format!("{}{}", indent, name)),
+        }
+    }
+}
+
+/// The project tree with only the directories leading to `focus` (project-relative file
+/// paths) expanded; the others are listed with their file counts.
+pub fn tree_excerpt(nodes: &[StructureNode], focus: &[&str]) -> Vec<String> {
+    let focus: HashSet<&str> = focus.iter().copied().collect();
+    let mut lines = Vec::new();
+    render_tree(nodes, &focus, 0, &mut lines);
+    lines
+}
+
+/// Packs candidates (best first) into `max_tokens`: the tree excerpt takes up to 15% of it,
+/// entities fill what the diagnostics of their files leave, and candidates that don't fit are
+/// skipped in favour of smaller ones further down. An entity inside one already included,
+/// like a method of an included class, is dropped as a duplicate.
+pub fn assemble(candidates: Vec<ContextEntity>, tree_lines: Vec<String>, diagnostics: Vec<DiagnosticEntry>, max_tokens: usize) -> ContextPackage {
+    let tree_budget = max_tokens * TREE_SHARE_PERCENT / 100;
+    let mut tree_tokens = 0;
+    let total_lines = tree_lines.len();
+    let mut tree = Vec::new();
+    for line in tree_lines {
+        let cost = estimate_tokens(&line) + 1;
+        if tree_tokens + cost > tree_budget {
+            break;
+        }
+        tree_tokens += cost;
+        tree.push(line);
+    }
+    if tree.len() < total_lines {
+        let note = format!("… ({} more entries)", total_lines - tree.len());
+        tree_tokens += estimate_tokens(&note) + 1;
+        tree.push(note);
+    }
+
+    let mut used = tree_tokens;
+    let entity_budget = max_tokens.saturating_sub(max_tokens * DIAGNOSTICS_SHARE_PERCENT / 100);
+    let mut entities: Vec<ContextEntity> = Vec::new();
+    let mut ranges: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+    let mut omitted_entities = 0;
+    for candidate in candidates {
+        let (from, to) = (candidate.entity.line_from.min(candidate.entity.line), candidate.entity.line_to);
+        let included = ranges.get(&candidate.path).is_some_and(|r| r.iter().any(|&(start, end)| start <= from && to <= end));
+        if included {
+            continue;
+        }
+        let cost = entity_tokens(&candidate);
+        if used + cost > entity_budget {
+            omitted_entities += 1;
+            continue;
+        }
+        used += cost;
+        ranges.entry(candidate.path.clone()).or_default().push((from, to));
+        entities.push(candidate);
+    }
+
+    let mut relevant: Vec<DiagnosticEntry> = diagnostics.into_iter().filter(|d| ranges.contains_key(&d.path)).collect();
+    relevant.sort_by(|a, b| (a.severity, &a.path, a.line).cmp(&(b.severity, &b.path, b.line)));
+    let mut kept = Vec::new();
+    let mut omitted_diagnostics = 0;
+    for diagnostic in relevant {
+        let cost = diagnostic_tokens(&diagnostic);
+        if used + cost > max_tokens {
+            omitted_diagnostics += 1;
+            continue;
+        }
+        used += cost;
+        kept.push(diagnostic);
+    }
+
+    ContextPackage { entities, tree: tree.join("\n"), diagnostics: kept, estimated_tokens: used, omitted_entities, omitted_diagnostics }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev_operation::diagnostics::DiagnosticSource;
+    use crate::dev_operation::suggestions::Severity;
+
+    fn entity(name: &str, line: usize, line_to: usize, snippet_len: usize) -> CodeEntity {
+        CodeEntity {
+            name: name.to_string(),
+            signature: format!("function {}()", name),
+            code_type: "Function".to_string(),
+            docstring: None,
+            line,
+            line_from: line,
+            line_to,
+            context: CodeContext {
+                module: None,
+                file_path: String::new(),
+                file_name: String::new(),
+                struct_name: None,
+                snippet: "x".repeat(snippet_len),
+            },
+            embedding: None,
+            references: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_assemble_fits_the_budget() {
+        let candidates = vec![
+            ContextEntity { path: "src/cart.ts".to_string(), entity: entity("Cart", 1, 40, 400), reason: "file", score: None },
+            ContextEntity { path: "src/cart.ts".to_string(), entity: entity("total", 10, 20, 100), reason: "semantic", score: Some(0.8) },
+            ContextEntity { path: "src/big.ts".to_string(), entity: entity("huge", 1, 900, 4000), reason: "semantic", score: Some(0.7) },
+            ContextEntity { path: "src/price.ts".to_string(), entity: entity("formatPrice", 3, 9, 200), reason: "semantic", score: Some(0.6) },
+        ];
+        let diagnostic = |path: &str, severity| DiagnosticEntry {
+            path: path.to_string(),
+            line: 12,
+            column: 0,
+            end_line: None,
+            end_column: None,
+            severity,
+            source: DiagnosticSource::Tsc,
+            rule: None,
+            message: "Object is possibly 'undefined'.".to_string(),
+        };
+        let diagnostics = vec![diagnostic("src/price.ts", Severity::Warning), diagnostic("src/other.ts", Severity::Error), diagnostic("src/cart.ts", Severity::Error)];
+        let tree = vec!["src/".to_string(), "  cart.ts".to_string(), "  price.ts".to_string()];
+
+        let package = assemble(candidates, tree, diagnostics, 400);
+        let names: Vec<&str> = package.entities.iter().map(|c| c.entity.name.as_str()).collect();
+        assert_eq!(names, vec!["Cart", "formatPrice"]);
+        assert_eq!(package.omitted_entities, 1);
+        assert_eq!(package.diagnostics.iter().map(|d| d.path.as_str()).collect::<Vec<_>>(), vec!["src/cart.ts", "src/price.ts"]);
+        assert_eq!(package.tree, "src/\n  cart.ts\n  price.ts");
+        assert!(package.estimated_tokens <= 400);
+    }
+
+    #[test]
+    fn test_tree_excerpt_expands_focused_dirs() {
+        let file = |name: &str, path: &str| StructureNode::File { name: name.to_string(), path: path.to_string(), language: None, size: 0, entity_count: 0, exports: Vec::new() };
+        let dir = |name: &str, path: &str, children: Vec<StructureNode>| StructureNode::Dir {
+            name: name.to_string(),
+            path: path.to_string(),
+            file_count: children.len(),
+            entity_count: 0,
+            children,
+        };
+        let tree = vec![
+            dir("src", "src", vec![dir("app", "src/app", vec![file("page.tsx", "src/app/page.tsx")]), dir("lib", "src/lib", vec![file("a.ts", "src/lib/a.ts")])]),
+            file("package.json", "package.json"),
+        ];
+        assert_eq!(tree_excerpt(&tree, &["src/app/page.tsx"]), vec!["src/", "  app/", "    page.tsx", "  lib/ (1 files)", "package.json"]);
+    }
+}
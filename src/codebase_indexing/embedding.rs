@@ -1,4 +1,5 @@
 use crate::codebase_indexing::parser::entities::CodeEntity;
+use crate::dev_setup::offline;
 use anyhow::{Context, Result};
 use async_openai::{
     config::OpenAIConfig,
@@ -48,6 +49,9 @@ pub async fn generate_embeddings(
     // 2. Initialize OpenAI Client
     let effective_api_key = api_key.or_else(|| std::env::var("OPENAI_API_KEY").ok());
     let effective_api_base = api_base.or_else(|| std::env::var("OPENAI_API_BASE").ok());
+    if entities.iter().any(|e| e.embedding.is_none()) {
+        offline::ensure_api_reachable("Generating embeddings", effective_api_base.as_deref())?;
+    }
     
     let mut config = OpenAIConfig::default();
     if let Some(key) = effective_api_key {
@@ -230,6 +234,7 @@ async fn generate_embeddings_core(
             return Ok(entities);
         }
     }
+    offline::ensure_api_reachable("Generating embeddings", effective_api_base.as_deref())?;
     if let Some(base) = effective_api_base { 
         openai_config = openai_config.with_api_base(base); 
     }
@@ -0,0 +1,320 @@
+use anyhow::{bail, Context, Result};
+use once_cell::sync::OnceCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::parser::{extract_entities_from_file, CodeEntity, SourceLanguage};
+use crate::dev_operation::symbols::INDEXED_EXTENSIONS;
+use crate::dev_runtime::db::{self, StoredEntity};
+use crate::dev_runtime::jobs::{self, JobHandle};
+use crate::dev_runtime::util::now_secs;
+use crate::file_system::dirs::SKIPPED_DIRS;
+use crate::file_system::search::find_files_by_extensions;
+use crate::file_system::watcher::{self, FsChangeKind};
+
+static GLOBAL: OnceCell<IndexManager> = OnceCell::new();
+
+// Files parsed between progress reports of a build running as a job
+const PROGRESS_EVERY: usize = 100;
+
+#[derive(Debug, Clone)]
+struct IndexedFile {
+    modified: SystemTime,
+    size: u64,
+    entities: Arc<Vec<CodeEntity>>,
+}
+
+/// Size and freshness of an index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexStats {
+    pub files: usize,
+    pub entities: usize,
+    /// Whether the first full scan has finished
+    pub ready: bool,
+    /// Unix timestamp (seconds) of the last change to the index, 0 before the first
+    pub last_updated: u64,
+}
+
+/// The parsed entities of every indexed file in a project (`.ts`, `.tsx` and `.rs` outside
+/// dependency and build directories), kept in memory and in the metadata store.
+///
+/// Files are parsed once and re-parsed only when their size or modification time changes, so
+/// symbol and entity queries read the index instead of parsing files per call. The project-wide
+/// instance started by `start` follows the file watcher to stay current.
+pub struct IndexManager {
+    root: PathBuf,
+    files: RwLock<BTreeMap<PathBuf, IndexedFile>>,
+    ready: AtomicBool,
+    last_updated: AtomicU64,
+}
+
+fn parse_entities(path: &Path) -> Result<Vec<CodeEntity>> {
+    match SourceLanguage::from_path(path) {
+        Some(_) => extract_entities_from_file(&path.to_path_buf(), None),
+        None => Ok(Vec::new()),
+    }
+}
+
+// The metadata store's copy of a file's entities, if the file hasn't changed since
+fn cached_entities(key: &str, modified: SystemTime, size: u64) -> Option<Vec<CodeEntity>> {
+    let cached = db::with_db(|db| db.cached_entities(key, modified, size)).ok()??;
+    cached.into_iter().map(|e| serde_json::from_str(e.record.as_deref()?).ok()).collect()
+}
+
+fn store_entities(key: &str, modified: SystemTime, size: u64, entities: &[CodeEntity]) {
+    let stored: Vec<StoredEntity> = entities
+        .iter()
+        .map(|e| StoredEntity {
+            name: e.name.clone(),
+            kind: e.code_type.clone(),
+            container_name: e.context.struct_name.clone(),
+            line: e.line,
+            line_to: e.line_to,
+            record: serde_json::to_string(e).ok(),
+        })
+        .collect();
+    if let Err(e) = db::with_db(|db| db.store_entities(key, modified, size, &stored)) {
+        tracing::debug!(target: "codebase_indexing::index", file = %key, error = ?e, "Failed to cache entities.");
+    }
+}
+
+impl IndexManager {
+    /// An empty index of the project at `root`; nothing is parsed until it is queried or built.
+    pub fn new(root: PathBuf) -> Self {
+        Self { root, files: RwLock::new(BTreeMap::new()), ready: AtomicBool::new(false), last_updated: AtomicU64::new(0) }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether `path` is a file type the index parses, outside the skipped directories.
+    pub fn is_indexable(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| INDEXED_EXTENSIONS.contains(&ext))
+            && !relative.components().any(|c| SKIPPED_DIRS.contains(&c.as_os_str().to_string_lossy().as_ref()))
+    }
+
+    /// The entities of `file`, from memory while the file is unchanged, else from the metadata
+    /// store or by parsing it.
+    pub fn file_entities(&self, file: &Path) -> Result<Arc<Vec<CodeEntity>>> {
+        let metadata = std::fs::metadata(file).with_context(|| format!("Failed to stat {}", file.display()))?;
+        let modified = metadata.modified()?;
+        let size = metadata.len();
+        {
+            let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+            if let Some(indexed) = files.get(file).filter(|f| f.modified == modified && f.size == size) {
+                return Ok(indexed.entities.clone());
+            }
+        }
+
+        let key = file.to_string_lossy();
+        let entities = match cached_entities(&key, modified, size) {
+            Some(entities) => entities,
+            None => {
+                let entities = parse_entities(file)?;
+                store_entities(&key, modified, size, &entities);
+                entities
+            }
+        };
+        let entities = Arc::new(entities);
+        let indexed = IndexedFile { modified, size, entities: entities.clone() };
+        self.files.write().unwrap_or_else(|e| e.into_inner()).insert(file.to_path_buf(), indexed);
+        self.last_updated.store(now_secs(), Ordering::SeqCst);
+        Ok(entities)
+    }
+
+    /// Brings one path up to date after it changed: re-indexes it if it is an indexable file,
+    /// otherwise drops it and anything indexed below it.
+    pub fn update_path(&self, path: &Path) {
+        if path.is_file() && self.is_indexable(path) {
+            if let Err(e) = self.file_entities(path) {
+                tracing::debug!(target: "codebase_indexing::index", file = %path.display(), error = ?e, "Failed to re-index file.");
+                self.remove_path(path);
+            }
+        } else if !path.is_dir() {
+            self.remove_path(path);
+        }
+    }
+
+    fn remove_path(&self, path: &Path) {
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        let before = files.len();
+        files.retain(|file, _| !file.starts_with(path));
+        if files.len() != before {
+            self.last_updated.store(now_secs(), Ordering::SeqCst);
+        }
+    }
+
+    /// Scans the whole project, indexing new and changed files and dropping deleted ones.
+    /// Unchanged files cost a `stat`.
+    pub fn build(&self) -> Result<IndexStats> {
+        self.build_as(None)
+    }
+
+    /// `build`, reporting progress to `job` and stopping when it is cancelled.
+    pub fn build_as(&self, job: Option<&JobHandle>) -> Result<IndexStats> {
+        let files = find_files_by_extensions(&self.root, INDEXED_EXTENSIONS, SKIPPED_DIRS)?;
+        let mut indexed = Vec::with_capacity(files.len());
+        for (index, file) in files.iter().enumerate() {
+            if let Some(job) = job.filter(|_| index % PROGRESS_EVERY == 0) {
+                if job.is_cancelled() {
+                    bail!("Cancelled after {} of {} files", index, files.len());
+                }
+                job.progress(index as u64, Some(files.len() as u64), "Parsing files");
+            }
+            // A single unparsable file shouldn't fail the whole index
+            match self.file_entities(file) {
+                Ok(_) => indexed.push(file.to_string_lossy().into_owned()),
+                Err(e) => tracing::debug!(target: "codebase_indexing::index", file = %file.display(), error = ?e, "Skipping file in the code index."),
+            }
+        }
+        {
+            let found: std::collections::HashSet<&PathBuf> = files.iter().collect();
+            self.files.write().unwrap_or_else(|e| e.into_inner()).retain(|file, _| found.contains(file));
+        }
+        // Forget files that were deleted or moved since the last scan
+        if let Err(e) = db::with_db(|db| db.retain_entity_files(&indexed)) {
+            tracing::debug!(target: "codebase_indexing::index", error = ?e, "Failed to prune the entity cache.");
+        }
+        self.ready.store(true, Ordering::SeqCst);
+        Ok(self.stats())
+    }
+
+    /// Every indexed file with its entities, in path order.
+    pub fn snapshot(&self) -> Vec<(PathBuf, Arc<Vec<CodeEntity>>)> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        files.iter().map(|(path, file)| (path.clone(), file.entities.clone())).collect()
+    }
+
+    /// Entities whose name contains `query`, case-insensitively, in path and line order.
+    pub fn find_entities(&self, query: &str, limit: usize) -> Vec<CodeEntity> {
+        let needle = query.to_lowercase();
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        files
+            .values()
+            .flat_map(|file| file.entities.iter())
+            .filter(|e| e.name.to_lowercase().contains(&needle))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn stats(&self) -> IndexStats {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        IndexStats {
+            files: files.len(),
+            entities: files.values().map(|f| f.entities.len()).sum(),
+            ready: self.ready.load(Ordering::SeqCst),
+            last_updated: self.last_updated.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// The project-wide index, once `start` ran.
+pub fn global() -> Option<&'static IndexManager> {
+    GLOBAL.get()
+}
+
+/// The project-wide index when it covers `project_root` and is current, else a one-off index
+/// built from the metadata store. Without a running file watcher the project-wide index is
+/// rescanned first, since nothing else would notice changes.
+pub fn current_snapshot(project_root: &Path) -> Result<Vec<(PathBuf, Arc<Vec<CodeEntity>>)>> {
+    if let Some(manager) = global().filter(|m| m.root == project_root) {
+        if !manager.ready.load(Ordering::SeqCst) || !watcher::is_watching() {
+            manager.build()?;
+        }
+        return Ok(manager.snapshot());
+    }
+    let manager = IndexManager::new(project_root.to_path_buf());
+    manager.build()?;
+    Ok(manager.snapshot())
+}
+
+/// The entities of one file, through the project-wide index when the file is in it.
+pub fn file_entities(file: &Path) -> Result<Arc<Vec<CodeEntity>>> {
+    match global().filter(|m| file.starts_with(&m.root)) {
+        Some(manager) => manager.file_entities(file),
+        None => IndexManager::new(file.parent().map(Path::to_path_buf).unwrap_or_default()).file_entities(file),
+    }
+}
+
+/// Builds the project-wide index in the background and keeps it current from the file
+/// watcher's change events. Call after `watcher::start`.
+pub fn start(project_dir: PathBuf) {
+    let manager = GLOBAL.get_or_init(|| IndexManager::new(project_dir));
+    // Subscribed before the first scan, so changes made during it aren't missed
+    let (_, mut changes) = watcher::subscribe(None);
+    // The first scan is the long one, so it runs as a job with progress in /api/jobs
+    let runtime = tokio::runtime::Handle::try_current();
+    let spawned = std::thread::Builder::new().name("code-index".to_string()).spawn(move || {
+        let built = match &runtime {
+            Ok(runtime) => runtime.block_on(jobs::run("index", "Build the code index".to_string(), move |job| async move {
+                let stats = tokio::task::spawn_blocking(move || manager.build_as(Some(&job))).await??;
+                let summary = format!("Indexed {} entities in {} files", stats.entities, stats.files);
+                Ok((stats, summary))
+            })),
+            Err(_) => manager.build(),
+        };
+        match built {
+            Ok(stats) => {
+                tracing::info!(target: "codebase_indexing::index", files = stats.files, entities = stats.entities, "Code index built.")
+            }
+            Err(e) => tracing::warn!(target: "codebase_indexing::index", error = ?e, "Failed to build the code index."),
+        }
+        loop {
+            match changes.blocking_recv() {
+                Ok(change) if change.kind == FsChangeKind::Rescan => {
+                    let _ = manager.build();
+                }
+                Ok(change) => manager.update_path(&manager.root.join(&change.path)),
+                Err(RecvError::Lagged(_)) => {
+                    let _ = manager.build();
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+    if let Err(e) = spawned {
+        tracing::warn!(target: "codebase_indexing::index", error = %e, "Failed to start the code index thread.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_updates_incrementally() {
+        let dir = crate::test_support::project_tempdir();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("node_modules")).unwrap();
+        std::fs::write(src.join("a.ts"), "export function alpha() {}\n").unwrap();
+        std::fs::write(src.join("node_modules/dep.ts"), "export function dep() {}\n").unwrap();
+        std::fs::write(src.join("notes.txt"), "notes\n").unwrap();
+
+        let manager = IndexManager::new(dir.path().to_path_buf());
+        let stats = manager.build().unwrap();
+        assert_eq!((stats.files, stats.ready), (1, true));
+        assert_eq!(manager.find_entities("ALP", 10)[0].name, "alpha");
+
+        // Same size, so only the modification time tells the change apart
+        std::fs::write(src.join("a.ts"), "export function gamma() {}\n").unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        std::fs::File::options().write(true).open(src.join("a.ts")).unwrap().set_modified(later).unwrap();
+        std::fs::write(src.join("b.ts"), "export const beta = 1;\n").unwrap();
+        manager.update_path(&src.join("a.ts"));
+        manager.update_path(&src.join("b.ts"));
+        manager.update_path(&src.join("notes.txt"));
+        let names: Vec<String> = manager.find_entities("", 10).into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["gamma", "beta"]);
+
+        std::fs::remove_dir_all(&src).unwrap();
+        manager.update_path(&src);
+        assert_eq!(manager.stats().files, 0);
+    }
+}
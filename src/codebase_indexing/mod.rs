@@ -1,5 +1,13 @@
+pub mod call_graph;
+pub mod context;
 pub mod embedding;
+pub mod index_manager;
 pub mod parser;
 pub mod pipeline;
 pub mod postprocessor;
-pub mod vector_db; 
\ No newline at end of file
+pub mod profiles;
+pub mod semantic;
+pub mod structure;
+pub mod styles;
+pub mod vector_db;
+pub mod vector_store; 
\ No newline at end of file
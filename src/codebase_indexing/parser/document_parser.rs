@@ -0,0 +1,289 @@
+use super::entities::{CodeContext, CodeEntity};
+use crate::codebase_indexing::postprocessor::split_entity;
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Generated files that would flood the index with dependency names
+const SKIPPED_DOCUMENTS: &[&str] = &["package-lock.json", "pnpm-lock.yaml", "npm-shrinkwrap.json", "composer.lock"];
+// Larger documents are data (fixtures, dumps), not something anyone searches by heading or key
+const MAX_DOCUMENT_BYTES: u64 = 512 * 1024;
+
+// An entity spanning 1-indexed lines `from..=to` of `lines`; shared with the stylesheet parser
+pub(super) fn document_entity(
+    file_path: &Path,
+    lines: &[&str],
+    name: String,
+    code_type: &str,
+    from: usize,
+    to: usize,
+    parent: Option<String>,
+) -> CodeEntity {
+    let to = to.clamp(from, lines.len().max(from));
+    let snippet = lines.get(from - 1..to).map(|l| l.join("\n")).unwrap_or_default();
+    CodeEntity {
+        name,
+        signature: lines.get(from - 1).map(|l| l.trim().to_string()).unwrap_or_default(),
+        code_type: code_type.to_string(),
+        docstring: None,
+        line: from,
+        line_from: from,
+        line_to: to,
+        context: CodeContext {
+            module: file_path.file_stem().map(|s| s.to_string_lossy().into_owned()),
+            file_path: file_path.to_string_lossy().to_string(),
+            file_name: file_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            struct_name: parent,
+            snippet,
+        },
+        embedding: None,
+        references: Vec::new(),
+    }
+}
+
+// Moves `to` (1-indexed, inclusive) up past trailing blank and comment lines
+fn trim_trailing(lines: &[&str], from: usize, mut to: usize) -> usize {
+    while to > from && lines.get(to - 1).is_some_and(|l| l.trim().is_empty() || l.trim_start().starts_with('#')) {
+        to -= 1;
+    }
+    to
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+// `(level, text)` of an ATX heading such as `## Setup ##`
+fn heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) || line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end().to_string()))
+}
+
+/// Frontmatter and headings of a Markdown or MDX document. Each heading's entity covers its
+/// section, down to the next heading of the same or a higher level, and names the enclosing
+/// heading as its parent.
+pub fn markdown_entities(source: &str, file_path: &Path) -> Vec<CodeEntity> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut entities = Vec::new();
+    let mut body_start = 0;
+    if lines.first().map(|l| l.trim_end()) == Some("---") {
+        if let Some(close) = lines.iter().skip(1).position(|l| l.trim_end() == "---") {
+            let frontmatter = &lines[1..close + 1];
+            let title = frontmatter
+                .iter()
+                .find_map(|l| l.strip_prefix("title:"))
+                .map(unquote)
+                .filter(|t| !t.is_empty())
+                .or_else(|| file_path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                .unwrap_or_default();
+            entities.push(document_entity(file_path, &lines, title, "Frontmatter", 1, close + 2, None));
+            body_start = close + 2;
+        }
+    }
+
+    let mut headings: Vec<(usize, usize, String)> = Vec::new();
+    let mut in_fence = false;
+    for (index, line) in lines.iter().enumerate().skip(body_start) {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            if let Some((level, text)) = heading(line) {
+                headings.push((index + 1, level, text));
+            }
+        }
+    }
+    for (i, (line, level, text)) in headings.iter().enumerate() {
+        let end = headings[i + 1..].iter().find(|(_, l, _)| l <= level).map_or(lines.len(), |(next, _, _)| next - 1);
+        let parent = headings[..i].iter().rev().find(|(_, l, _)| l < level).map(|(_, _, t)| t.clone());
+        entities.push(document_entity(file_path, &lines, text.clone(), "Heading", *line, end, parent));
+    }
+    entities
+}
+
+// Top-level keys of a JSON object with their 1-indexed lines, plus the line closing the object.
+// Tolerates the comments and trailing commas of tsconfig-style JSONC.
+fn json_top_level_keys(source: &str) -> (Vec<(String, usize)>, usize) {
+    let mut keys = Vec::new();
+    let (mut depth, mut line, mut close_line) = (0usize, 1usize, 0usize);
+    let mut pending: Option<(String, usize)> = None;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '"' => {
+                let start_line = line;
+                let mut text = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                text.push(escaped);
+                            }
+                        }
+                        '"' => break,
+                        '\n' => {
+                            line += 1;
+                            text.push(c);
+                        }
+                        _ => text.push(c),
+                    }
+                }
+                pending = (depth == 1).then_some((text, start_line));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            ':' if depth == 1 => {
+                if let Some(key) = pending.take() {
+                    keys.push(key);
+                }
+            }
+            '{' | '[' => {
+                depth += 1;
+                pending = None;
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    close_line = line;
+                    break;
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => pending = None,
+        }
+    }
+    (keys, close_line)
+}
+
+/// Top-level keys of a JSON (or JSONC) document, each covering its value. Documents whose root
+/// isn't an object have none.
+pub fn json_entities(source: &str, file_path: &Path) -> Vec<CodeEntity> {
+    if !source.trim_start().starts_with('{') {
+        return Vec::new();
+    }
+    let lines: Vec<&str> = source.lines().collect();
+    let (keys, close_line) = json_top_level_keys(source);
+    keys.iter()
+        .enumerate()
+        .map(|(i, (key, line))| {
+            let end = match keys.get(i + 1) {
+                Some((_, next)) => next.saturating_sub(1),
+                // The closing brace belongs to the object, not the last key
+                None if close_line > *line => close_line - 1,
+                None => close_line,
+            };
+            document_entity(file_path, &lines, key.clone(), "Config Key", *line, trim_trailing(&lines, *line, end), None)
+        })
+        .collect()
+}
+
+/// Top-level keys of a YAML document, each covering its nested block.
+pub fn yaml_entities(source: &str, file_path: &Path) -> Vec<CodeEntity> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut keys: Vec<(String, usize)> = Vec::new();
+    let mut boundaries = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if line.starts_with("---") || line.starts_with("...") {
+            boundaries.push(index + 1);
+            continue;
+        }
+        if line.starts_with(|c: char| c.is_whitespace() || c == '#' || c == '-') || line.is_empty() {
+            continue;
+        }
+        let key = line.split_once(": ").map(|(k, _)| k).or_else(|| line.trim_end().strip_suffix(':'));
+        if let Some(key) = key.map(unquote).filter(|k| !k.is_empty()) {
+            keys.push((key, index + 1));
+        }
+    }
+    keys.iter()
+        .enumerate()
+        .map(|(i, (key, line))| {
+            let next_key = keys.get(i + 1).map_or(lines.len() + 1, |(_, next)| *next);
+            let next = boundaries.iter().copied().find(|b| b > line && *b < next_key).unwrap_or(next_key);
+            document_entity(file_path, &lines, key.clone(), "Config Key", *line, trim_trailing(&lines, *line, next - 1), None)
+        })
+        .collect()
+}
+
+/// Extracts headings and frontmatter (Markdown/MDX) or top-level keys (JSON/YAML) of a docs or
+/// config file, so searches cover more than code. Lockfiles and very large documents yield none.
+pub fn extract_document_entities_from_file(file_path: &PathBuf, max_snippet_size: Option<usize>) -> Result<Vec<CodeEntity>> {
+    let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+    if SKIPPED_DOCUMENTS.contains(&file_name.as_ref()) || fs::metadata(file_path)?.len() > MAX_DOCUMENT_BYTES {
+        return Ok(Vec::new());
+    }
+    let source = fs::read_to_string(file_path)?;
+    let entities = match file_path.extension().and_then(|ext| ext.to_str()) {
+        Some("md" | "mdx") => markdown_entities(&source, file_path),
+        Some("json") => json_entities(&source, file_path),
+        Some("yaml" | "yml") => yaml_entities(&source, file_path),
+        _ => Vec::new(),
+    };
+    Ok(match max_snippet_size {
+        Some(max_size) => entities.into_iter().flat_map(|e| split_entity(e, max_size)).collect(),
+        None => entities,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(entities: &[CodeEntity]) -> Vec<(&str, &str, usize, usize)> {
+        entities.iter().map(|e| (e.name.as_str(), e.code_type.as_str(), e.line_from, e.line_to)).collect()
+    }
+
+    #[test]
+    fn test_markdown_frontmatter_and_sections() {
+        let source = "---\ntitle: \"Getting started\"\n---\n# Intro\ntext\n## Install\n```sh\n# not a heading\n```\n## Usage ##\nmore\n# API\n";
+        let entities = markdown_entities(source, Path::new("/p/docs/start.mdx"));
+        assert_eq!(
+            spans(&entities),
+            vec![
+                ("Getting started", "Frontmatter", 1, 3),
+                ("Intro", "Heading", 4, 11),
+                ("Install", "Heading", 6, 9),
+                ("Usage", "Heading", 10, 11),
+                ("API", "Heading", 12, 12),
+            ]
+        );
+        assert_eq!(entities[2].context.struct_name.as_deref(), Some("Intro"));
+        assert!(entities[2].context.snippet.contains("# not a heading"));
+    }
+
+    #[test]
+    fn test_json_and_yaml_top_level_keys() {
+        let json = "{\n  // compiler settings\n  \"compilerOptions\": {\n    \"strict\": true,\n    \"paths\": { \"@/*\": [\"./src/*\"] }\n  },\n  \"include\": [\"src\"],\n}\n";
+        let entities = json_entities(json, Path::new("/p/tsconfig.json"));
+        assert_eq!(spans(&entities), vec![("compilerOptions", "Config Key", 3, 6), ("include", "Config Key", 7, 7)]);
+
+        let yaml = "# CI\nname: build\non:\n  push:\n    branches: [main]\n\njobs:\n  test:\n    runs-on: ubuntu-latest\n---\nother: 1\n";
+        let entities = yaml_entities(yaml, Path::new("/p/ci.yml"));
+        assert_eq!(
+            spans(&entities),
+            vec![("name", "Config Key", 2, 2), ("on", "Config Key", 3, 5), ("jobs", "Config Key", 7, 9), ("other", "Config Key", 11, 11)]
+        );
+    }
+}
@@ -21,4 +21,15 @@ pub struct CodeEntity {
     pub context: CodeContext,
     #[serde(skip_serializing_if = "Option::is_none")] // Don't write embedding field if it's None
     pub embedding: Option<Vec<f32>>, // Added field for embedding vector
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<CodeReference>, // Calls, JSX usages, classes and imports made inside the entity
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CodeReference {
+    pub name: String, // Referenced identifier; for `import { a as b }` the exported `a`
+    pub kind: String, // "call", "jsx", "class" (a `className` entry) or "import"
+    pub line: usize,
+    pub source: Option<String>, // Import specifier as written, e.g. "@/components/button"
+    pub target_path: Option<String>, // File the import resolves to, when it is inside the project
 } 
\ No newline at end of file
@@ -0,0 +1,122 @@
+use super::document_parser::extract_document_entities_from_file;
+use super::entities::CodeEntity;
+use super::rust_entity_parser::extract_rust_entities_from_file;
+use super::style_parser::extract_style_entities_from_file;
+use super::ts_entity_parser::{extract_js_entities_from_file, extract_ts_entities_from_file};
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+/// File extensions the entity parser has a grammar for.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["rs", "ts", "tsx", "js", "jsx", "mjs", "cjs", "md", "mdx", "json", "yaml", "yml", "css", "scss"];
+
+/// The grammar a source file is parsed with, picked from its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceLanguage {
+    Rust,
+    TypeScript,
+    Tsx,
+    /// Plain and JSX JavaScript, as modules or CommonJS
+    JavaScript,
+    /// Markdown and MDX, indexed by frontmatter and headings
+    Markdown,
+    Json,
+    Yaml,
+    /// CSS and SCSS
+    Stylesheet,
+}
+
+impl SourceLanguage {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "rs" => Some(Self::Rust),
+            "ts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "md" | "mdx" => Some(Self::Markdown),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "css" | "scss" => Some(Self::Stylesheet),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension().and_then(|ext| ext.to_str()).and_then(Self::from_extension)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::TypeScript => "typescript",
+            Self::Tsx => "tsx",
+            Self::JavaScript => "javascript",
+            Self::Markdown => "markdown",
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Stylesheet => "css",
+        }
+    }
+
+    /// Whether the language has modules with exports (as opposed to docs, config and styles).
+    pub fn is_code(&self) -> bool {
+        matches!(self, Self::Rust | Self::TypeScript | Self::Tsx | Self::JavaScript)
+    }
+}
+
+/// Extracts the entities of `file_path` with the parser for its language. Fails for
+/// extensions without a grammar (see `SUPPORTED_EXTENSIONS`).
+pub fn extract_entities_from_file(file_path: &PathBuf, max_snippet_size: Option<usize>) -> Result<Vec<CodeEntity>> {
+    match SourceLanguage::from_path(file_path) {
+        Some(SourceLanguage::Rust) => extract_rust_entities_from_file(file_path, max_snippet_size),
+        Some(SourceLanguage::TypeScript) => extract_ts_entities_from_file(file_path, false, max_snippet_size),
+        Some(SourceLanguage::Tsx) => extract_ts_entities_from_file(file_path, true, max_snippet_size),
+        Some(SourceLanguage::JavaScript) => extract_js_entities_from_file(file_path, max_snippet_size),
+        Some(SourceLanguage::Markdown | SourceLanguage::Json | SourceLanguage::Yaml) => {
+            extract_document_entities_from_file(file_path, max_snippet_size)
+        }
+        Some(SourceLanguage::Stylesheet) => extract_style_entities_from_file(file_path, max_snippet_size),
+        None => Err(anyhow!("Unsupported file extension: {}", file_path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_javascript_files_are_parsed_with_the_js_grammar() {
+        let dir = crate::test_support::project_tempdir();
+        let file = dir.path().join("widget.jsx");
+        let code = r#"import { format } from "./format.mjs";
+
+/** Renders a price. */
+export function Price({ value }) {
+    return <span>{format(value)}</span>;
+}
+
+class Cart {
+    total() { return 0; }
+}
+
+const helper = () => 1;
+"#;
+        fs::write(&file, code).unwrap();
+        fs::write(dir.path().join("format.mjs"), "export const format = (v) => `$${v}`;\n").unwrap();
+
+        assert_eq!(SourceLanguage::from_path(Path::new("a/b.cjs")), Some(SourceLanguage::JavaScript));
+        assert_eq!(SourceLanguage::from_path(Path::new("a/b.py")), None);
+        assert_eq!(SourceLanguage::from_path(Path::new("docs/intro.mdx")), Some(SourceLanguage::Markdown));
+
+        let entities = extract_entities_from_file(&file, None).unwrap();
+        let kinds: Vec<(&str, &str)> = entities.iter().map(|e| (e.name.as_str(), e.code_type.as_str())).collect();
+        for expected in [("Price", "Function Component"), ("Cart", "Class"), ("total", "Method"), ("helper", "Function")] {
+            assert!(kinds.contains(&expected), "{:?} missing from {:?}", expected, kinds);
+        }
+        let price = entities.iter().find(|e| e.name == "Price").unwrap();
+        assert!(price.docstring.as_deref().unwrap_or_default().contains("Renders a price"));
+        assert!(price.references.iter().any(|r| r.kind == "call" && r.name == "format"));
+        let import = entities.iter().flat_map(|e| &e.references).find(|r| r.kind == "import").unwrap();
+        assert!(import.target_path.as_deref().unwrap().ends_with("format.mjs"));
+    }
+}
@@ -0,0 +1,291 @@
+use super::entities::{CodeEntity, CodeReference};
+use super::helpers::get_node_text;
+use std::path::{Component, Path, PathBuf};
+use tree_sitter::Node;
+
+const TS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+fn reference(name: String, kind: &str, node: Node) -> CodeReference {
+    CodeReference { name, kind: kind.to_string(), line: node.start_position().row + 1, source: None, target_path: None }
+}
+
+/// Lexically resolves `.` and `..` so resolved imports compare equal to indexed file paths.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn existing_module(candidate: &Path) -> Option<PathBuf> {
+    if candidate.is_file() {
+        return Some(candidate.to_path_buf());
+    }
+    // Specifiers like "./user.service" carry a dot, so extensions are appended rather than replaced
+    let appended = TS_EXTENSIONS.iter().map(|ext| PathBuf::from(format!("{}.{}", candidate.display(), ext)));
+    let index = TS_EXTENSIONS.iter().map(|ext| candidate.join(format!("index.{}", ext)));
+    appended.chain(index).find(|path| path.is_file())
+}
+
+/// The project file a TS/JS import specifier refers to. Relative specifiers resolve from the
+/// importing file; `@/` and `~/` resolve from `src/` (then the root) of the nearest directory
+/// with a tsconfig.json or package.json. Package imports resolve to nothing.
+pub fn resolve_ts_import(file_path: &Path, specifier: &str) -> Option<PathBuf> {
+    let dir = file_path.parent()?;
+    if specifier.starts_with("./") || specifier.starts_with("../") {
+        return existing_module(&normalize(&dir.join(specifier)));
+    }
+    let rest = specifier.strip_prefix("@/").or_else(|| specifier.strip_prefix("~/"))?;
+    let root = dir.ancestors().find(|a| a.join("tsconfig.json").is_file() || a.join("package.json").is_file())?;
+    existing_module(&root.join("src").join(rest)).or_else(|| existing_module(&root.join(rest)))
+}
+
+// Import references record the exported name; `aliases` maps local names of `import { a as b }`
+// back to it so calls through the alias name the same symbol
+fn collect_ts_import(node: Node, source_code: &str, file_path: &Path, references: &mut Vec<CodeReference>, aliases: &mut Vec<(String, String)>) {
+    let Some(source_node) = node.child_by_field_name("source") else { return };
+    let specifier = get_node_text(source_node, source_code).trim_matches(|c| c == '"' || c == '\'' || c == '`').to_string();
+    let target_path = resolve_ts_import(file_path, &specifier).map(|p| p.to_string_lossy().into_owned());
+
+    let mut names = Vec::new();
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+        let mut cursor = current.walk();
+        for child in current.named_children(&mut cursor) {
+            match child.kind() {
+                "import_clause" | "named_imports" => stack.push(child),
+                // Default import: the local name, which by convention matches the exported one
+                "identifier" if current.kind() == "import_clause" => names.push((get_node_text(child, source_code), child)),
+                "namespace_import" => {
+                    if let Some(alias) = child.named_child(0) {
+                        names.push((get_node_text(alias, source_code), child));
+                    }
+                }
+                "import_specifier" => {
+                    if let Some(name) = child.child_by_field_name("name") {
+                        let name = get_node_text(name, source_code);
+                        if let Some(alias) = child.child_by_field_name("alias") {
+                            aliases.push((get_node_text(alias, source_code), name.clone()));
+                        }
+                        names.push((name, child));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    names.sort_by_key(|(_, n)| n.start_byte());
+    for (name, name_node) in names {
+        let mut import = reference(name, "import", name_node);
+        import.source = Some(specifier.clone());
+        import.target_path = target_path.clone();
+        references.push(import);
+    }
+}
+
+fn collect_ts_references(node: Node, source_code: &str, file_path: &Path, references: &mut Vec<CodeReference>, aliases: &mut Vec<(String, String)>) {
+    match node.kind() {
+        "import_statement" => {
+            collect_ts_import(node, source_code, file_path, references, aliases);
+            return;
+        }
+        "call_expression" | "new_expression" => {
+            let callee = node.child_by_field_name("function").or_else(|| node.child_by_field_name("constructor"));
+            let name = callee.and_then(|callee| match callee.kind() {
+                "identifier" => Some(callee),
+                "member_expression" => callee.child_by_field_name("property"),
+                _ => None,
+            });
+            if let Some(name) = name {
+                references.push(reference(get_node_text(name, source_code), "call", node));
+            }
+        }
+        "jsx_opening_element" | "jsx_self_closing_element" => {
+            if let Some(name) = node.child_by_field_name("name") {
+                let text = get_node_text(name, source_code);
+                let component = text.rsplit('.').next().unwrap_or_default();
+                // Lowercase tags are intrinsic HTML elements
+                if component.starts_with(|c: char| c.is_ascii_uppercase()) {
+                    references.push(reference(component.to_string(), "jsx", node));
+                }
+            }
+        }
+        "jsx_attribute" => {
+            let is_class_attribute = node.named_child(0).is_some_and(|name| {
+                let name = get_node_text(name, source_code);
+                name == "className" || name == "class"
+            });
+            if is_class_attribute {
+                collect_class_names(node, source_code, references);
+            }
+        }
+        _ => {}
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_ts_references(child, source_code, file_path, references, aliases);
+    }
+}
+
+// Class names in the string literals of a `className` value, including those passed to helpers
+// like `cn(...)`/`clsx(...)` and in template literals; interpolations aren't class names
+fn collect_class_names(attribute: Node, source_code: &str, references: &mut Vec<CodeReference>) {
+    let mut seen = std::collections::HashSet::new();
+    let mut stack = vec![attribute];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "string_fragment" {
+            for class in get_node_text(node, source_code).split_whitespace() {
+                let valid = class.chars().all(|c| c.is_ascii_alphanumeric() || "-_:/[]().%#!&@".contains(c));
+                if valid && seen.insert(class.to_string()) {
+                    references.push(reference(class.to_string(), "class", node));
+                }
+            }
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+}
+
+fn collect_rust_use(node: Node, source_code: &str, path: &str, references: &mut Vec<CodeReference>) {
+    match node.kind() {
+        "identifier" => {
+            let name = get_node_text(node, source_code);
+            let mut import = reference(name, "import", node);
+            import.source = Some(path.to_string());
+            references.push(import);
+        }
+        "scoped_identifier" => {
+            if let Some(name) = node.child_by_field_name("name") {
+                collect_rust_use(name, source_code, path, references);
+            }
+        }
+        "use_as_clause" => {
+            if let Some(original) = node.child_by_field_name("path") {
+                collect_rust_use(original, source_code, path, references);
+            }
+        }
+        "scoped_use_list" | "use_list" => {
+            let list = if node.kind() == "scoped_use_list" { node.child_by_field_name("list") } else { Some(node) };
+            if let Some(list) = list {
+                let mut cursor = list.walk();
+                for item in list.named_children(&mut cursor) {
+                    collect_rust_use(item, source_code, path, references);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn rust_callee<'a>(callee: Node<'a>) -> Option<Node<'a>> {
+    match callee.kind() {
+        "identifier" => Some(callee),
+        "scoped_identifier" => callee.child_by_field_name("name"),
+        "field_expression" => callee.child_by_field_name("field"),
+        "generic_function" => callee.child_by_field_name("function").and_then(rust_callee),
+        _ => None,
+    }
+}
+
+fn collect_rust_references(node: Node, source_code: &str, references: &mut Vec<CodeReference>) {
+    match node.kind() {
+        "use_declaration" => {
+            if let Some(argument) = node.child_by_field_name("argument") {
+                let path = get_node_text(argument, source_code);
+                collect_rust_use(argument, source_code, &path, references);
+            }
+            return;
+        }
+        "call_expression" => {
+            if let Some(name) = node.child_by_field_name("function").and_then(rust_callee) {
+                references.push(reference(get_node_text(name, source_code), "call", node));
+            }
+        }
+        _ => {}
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_rust_references(child, source_code, references);
+    }
+}
+
+/// Gives each reference to the innermost entity spanning its line; references outside every
+/// entity (top-level statements) are dropped.
+fn attach(references: Vec<CodeReference>, entities: &mut [CodeEntity]) {
+    for reference in references {
+        let innermost = entities
+            .iter_mut()
+            .filter(|e| e.line_from <= reference.line && reference.line <= e.line_to)
+            .min_by_key(|e| e.line_to - e.line_from);
+        if let Some(entity) = innermost {
+            entity.references.push(reference);
+        }
+    }
+}
+
+/// Records the calls, capitalized JSX elements, `className` classes and imports in a TS/TSX
+/// file on its entities.
+pub fn attach_ts_references(root: Node, source_code: &str, file_path: &Path, entities: &mut [CodeEntity]) {
+    let mut references = Vec::new();
+    let mut aliases = Vec::new();
+    collect_ts_references(root, source_code, file_path, &mut references, &mut aliases);
+    for reference in references.iter_mut().filter(|r| r.kind != "import") {
+        if let Some((_, name)) = aliases.iter().find(|(alias, _)| *alias == reference.name) {
+            reference.name = name.clone();
+        }
+    }
+    attach(references, entities);
+}
+
+/// Records the calls and `use` imports in a Rust file on its entities. `use` paths are kept as
+/// written; they aren't resolved to files.
+pub fn attach_rust_references(root: Node, source_code: &str, entities: &mut [CodeEntity]) {
+    let mut references = Vec::new();
+    collect_rust_references(root, source_code, &mut references);
+    attach(references, entities);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ts_entity_parser::extract_ts_entities_from_file;
+    use std::fs;
+
+    #[test]
+    fn test_ts_references_resolve_imports_calls_and_jsx() {
+        let dir = crate::test_support::project_tempdir();
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        fs::create_dir_all(dir.path().join("src/components")).unwrap();
+        fs::write(dir.path().join("src/components/button.tsx"), "export function Button() { return <button />; }\n").unwrap();
+        fs::write(dir.path().join("src/format.ts"), "export function formatName(n: string) { return n; }\n").unwrap();
+        let page = dir.path().join("src/page.tsx");
+        let code = r#"import { Button } from "@/components/button";
+import { formatName as fmt } from "./format";
+import React from "react";
+
+export function Page() {
+    return <div><Button label={fmt("x")} /></div>;
+}
+"#;
+        fs::write(&page, code).unwrap();
+
+        let entities = extract_ts_entities_from_file(&page, true, None).unwrap();
+        let imports: Vec<_> = entities.iter().flat_map(|e| &e.references).filter(|r| r.kind == "import").collect();
+        let button = imports.iter().find(|r| r.name == "Button").unwrap();
+        assert_eq!(button.target_path.as_deref(), Some(dir.path().join("src/components/button.tsx").to_string_lossy().as_ref()));
+        let format = imports.iter().find(|r| r.name == "formatName").unwrap();
+        assert!(format.target_path.as_deref().unwrap().ends_with("src/format.ts"));
+        assert!(imports.iter().find(|r| r.name == "React").unwrap().target_path.is_none());
+
+        let page_entity = entities.iter().find(|e| e.name == "Page").unwrap();
+        let used: Vec<(&str, &str)> = page_entity.references.iter().map(|r| (r.kind.as_str(), r.name.as_str())).collect();
+        assert_eq!(used, vec![("jsx", "Button"), ("call", "formatName")]);
+    }
+}
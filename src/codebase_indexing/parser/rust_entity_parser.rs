@@ -1,5 +1,6 @@
 use super::helpers::*;
 use super::entities::{CodeContext, CodeEntity};
+use super::references::attach_rust_references;
 use crate::codebase_indexing::postprocessor::split_entity;
 use anyhow::Result;
 use std::fs;
@@ -120,6 +121,7 @@ fn collect_rust_entities_recursive(
                         snippet: get_node_text(node, source_code),
                     },
                     embedding: None,
+                    references: Vec::new(),
                 };
                 create_and_add_entity(entity, entities);
                 entity_created_for_this_node = true; // Mark as processed
@@ -149,6 +151,7 @@ fn collect_rust_entities_recursive(
                         snippet: get_node_text(node, source_code),
                     },
                     embedding: None,
+                    references: Vec::new(),
                 };
                 create_and_add_entity(entity, entities);
                 entity_created_for_this_node = true;
@@ -188,6 +191,7 @@ fn collect_rust_entities_recursive(
                     snippet: get_node_text(node, source_code),
                 },
                 embedding: None,
+                references: Vec::new(),
             };
             create_and_add_entity(entity, entities);
             entity_created_for_this_node = true; // Mark impl block as processed
@@ -234,6 +238,7 @@ fn collect_rust_entities_recursive(
                         snippet: get_node_text(node, source_code),
                     },
                     embedding: None,
+                    references: Vec::new(),
                 };
                 create_and_add_entity(entity, entities);
                 entity_created_for_this_node = true;
@@ -268,6 +273,7 @@ fn collect_rust_entities_recursive(
                         snippet: get_node_text(node, source_code),
                     },
                     embedding: None,
+                    references: Vec::new(),
                 };
                 create_and_add_entity(entity, entities);
                 entity_created_for_this_node = true;
@@ -310,6 +316,7 @@ fn collect_rust_entities_recursive(
                     snippet: get_node_text(node, source_code),
                 },
                 embedding: None,
+                references: Vec::new(),
             };
             create_and_add_entity(entity, entities);
             entity_created_for_this_node = true;
@@ -343,6 +350,7 @@ fn collect_rust_entities_recursive(
                         snippet: get_node_text(node, source_code),
                     },
                     embedding: None,
+                    references: Vec::new(),
                 };
                 create_and_add_entity(entity, entities);
                 entity_created_for_this_node = true;
@@ -397,5 +405,6 @@ pub fn extract_rust_entities_from_file(
         &mut entities,
         max_snippet_size,
     );
+    attach_rust_references(root_node, &source_code, &mut entities);
     Ok(entities)
 } 
\ No newline at end of file
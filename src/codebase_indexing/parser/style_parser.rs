@@ -0,0 +1,205 @@
+use super::document_parser::document_entity;
+use super::entities::CodeEntity;
+use crate::codebase_indexing::postprocessor::split_entity;
+use anyhow::Result;
+use std::fs;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+
+// Minified bundles and generated framework CSS aren't worth indexing rule by rule
+const MAX_STYLESHEET_BYTES: u64 = 512 * 1024;
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Entity kind and name of a block's prelude, e.g. `@mixin button($size)` is the Mixin `button`
+fn block_kind(prelude: &str) -> (&'static str, String) {
+    if let Some(rest) = prelude.strip_prefix("@mixin") {
+        let name = rest.trim().split(['(', ' ']).next().unwrap_or_default();
+        return ("Mixin", name.to_string());
+    }
+    if let Some(rest) = prelude.strip_prefix("@keyframes") {
+        return ("Keyframes", rest.trim().to_string());
+    }
+    if prelude.starts_with('@') {
+        return ("At Rule", prelude.to_string());
+    }
+    ("Selector", prelude.to_string())
+}
+
+// Kind and name of a declaration worth indexing: custom properties and SCSS variables
+fn declaration_kind(declaration: &str, scss: bool) -> Option<(&'static str, String)> {
+    let (name, _) = declaration.split_once(':')?;
+    let name = name.trim();
+    if name.starts_with("--") {
+        Some(("Custom Property", name.to_string()))
+    } else if scss && name.starts_with('$') {
+        Some(("Variable", name.to_string()))
+    } else {
+        None
+    }
+}
+
+// Copies a quoted string into `prelude`, counting the newlines it spans
+fn read_string(quote: char, chars: &mut Peekable<Chars>, prelude: &mut String, line: &mut usize) {
+    prelude.push(quote);
+    while let Some(c) = chars.next() {
+        prelude.push(c);
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    prelude.push(escaped);
+                }
+            }
+            '\n' => *line += 1,
+            c if c == quote => break,
+            _ => {}
+        }
+    }
+}
+
+/// Rules (selectors, at-rules, mixins, keyframes), custom properties and SCSS variables of a
+/// stylesheet. Nested rules and declarations name their enclosing rule as their parent.
+pub fn stylesheet_entities(source: &str, file_path: &Path, scss: bool) -> Vec<CodeEntity> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut entities = Vec::new();
+    // Open blocks: the entity they started (if indexed) and their name
+    let mut stack: Vec<(Option<usize>, String)> = Vec::new();
+    let mut prelude = String::new();
+    let mut prelude_line = 1;
+    let (mut line, mut parens) = (1usize, 0usize);
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        if prelude.trim().is_empty() && !c.is_whitespace() {
+            prelude_line = line;
+        }
+        match c {
+            '\n' => {
+                line += 1;
+                prelude.push(c);
+            }
+            '"' | '\'' => read_string(c, &mut chars, &mut prelude, &mut line),
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            // `//` comments are SCSS-only, and `url(https://...)` isn't one
+            '/' if scss && parens == 0 && chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '(' | ')' => {
+                parens = if c == '(' { parens + 1 } else { parens.saturating_sub(1) };
+                prelude.push(c);
+            }
+            // SCSS interpolation, `#{$name}`, is part of the selector
+            '{' if scss && prelude.ends_with('#') => {
+                prelude.push(c);
+                for c in chars.by_ref() {
+                    prelude.push(c);
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            '{' => {
+                let text = collapse_whitespace(&prelude);
+                let index = (!text.is_empty()).then(|| {
+                    let (kind, name) = block_kind(&text);
+                    let parent = stack.last().map(|(_, name)| name.clone());
+                    entities.push(document_entity(file_path, &lines, name, kind, prelude_line, line, parent));
+                    entities.len() - 1
+                });
+                stack.push((index, text));
+                prelude.clear();
+            }
+            '}' => {
+                if let Some((Some(index), _)) = stack.pop() {
+                    let entity = &mut entities[index];
+                    entity.line_to = line;
+                    entity.context.snippet = lines.get(entity.line_from - 1..line).map(|l| l.join("\n")).unwrap_or_default();
+                }
+                prelude.clear();
+            }
+            ';' => {
+                if let Some((kind, name)) = declaration_kind(&collapse_whitespace(&prelude), scss) {
+                    let parent = stack.last().map(|(_, name)| name.clone());
+                    entities.push(document_entity(file_path, &lines, name, kind, prelude_line, line, parent));
+                }
+                prelude.clear();
+            }
+            _ => prelude.push(c),
+        }
+    }
+    entities
+}
+
+/// Extracts the rules, custom properties and variables of a CSS or SCSS file.
+pub fn extract_style_entities_from_file(file_path: &PathBuf, max_snippet_size: Option<usize>) -> Result<Vec<CodeEntity>> {
+    if fs::metadata(file_path)?.len() > MAX_STYLESHEET_BYTES {
+        return Ok(Vec::new());
+    }
+    let source = fs::read_to_string(file_path)?;
+    let scss = file_path.extension().is_some_and(|ext| ext == "scss");
+    let entities = stylesheet_entities(&source, file_path, scss);
+    Ok(match max_snippet_size {
+        Some(max_size) => entities.into_iter().flat_map(|e| split_entity(e, max_size)).collect(),
+        None => entities,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stylesheet_rules_properties_and_nesting() {
+        let source = r#":root {
+  --brand: #0af; /* primary */
+}
+// layout
+$gap: 4px;
+@mixin card($pad) { padding: $pad; }
+.card {
+  background: url(https://example.com/a.png);
+  &:hover { color: var(--brand); }
+  .title-#{$gap} { margin: 0; }
+}
+@media (min-width: 640px) {
+  .card { display: flex; }
+}
+"#;
+        let entities = stylesheet_entities(source, Path::new("/p/app.scss"), true);
+        let spans: Vec<(&str, &str, usize, usize, Option<&str>)> = entities
+            .iter()
+            .map(|e| (e.name.as_str(), e.code_type.as_str(), e.line_from, e.line_to, e.context.struct_name.as_deref()))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (":root", "Selector", 1, 3, None),
+                ("--brand", "Custom Property", 2, 2, Some(":root")),
+                ("$gap", "Variable", 5, 5, None),
+                ("card", "Mixin", 6, 6, None),
+                (".card", "Selector", 7, 11, None),
+                ("&:hover", "Selector", 9, 9, Some(".card")),
+                (".title-#{$gap}", "Selector", 10, 10, Some(".card")),
+                ("@media (min-width: 640px)", "At Rule", 12, 14, None),
+                (".card", "Selector", 13, 13, Some("@media (min-width: 640px)")),
+            ]
+        );
+        assert!(entities[4].context.snippet.contains("&:hover"));
+    }
+}
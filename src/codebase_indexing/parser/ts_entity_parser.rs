@@ -1,10 +1,11 @@
 use super::helpers::*;
 use super::entities::{CodeContext, CodeEntity};
+use super::references::attach_ts_references;
 use crate::codebase_indexing::postprocessor::split_entity;
 use anyhow::Result;
 use std::fs;
 use std::path::{Path, PathBuf};
-use tree_sitter::{Node, Parser};
+use tree_sitter::{Language, Node, Parser};
 
 fn get_ts_docstring_and_start_line(node: Node, source_code: &str) -> (Option<String>, usize) {
     let mut potential_docstring: Option<String> = None;
@@ -146,6 +147,7 @@ fn collect_ts_entities_recursive(
                     snippet: get_node_text(node, source_code),
                 },
                 embedding: None,
+                references: Vec::new(),
             };
             create_and_add_entity(entity, entities);
             entity_created_for_this_node = true;
@@ -216,6 +218,7 @@ fn collect_ts_entities_recursive(
                             snippet: get_node_text(node, source_code),
                         },
                         embedding: None,
+                        references: Vec::new(),
                     };
                     create_and_add_entity(entity, entities);
                     entity_created_for_this_node = true;
@@ -279,6 +282,7 @@ fn collect_ts_entities_recursive(
                                         snippet: get_node_text(var_declarator, source_code),
                                     },
                                     embedding: None,
+                                    references: Vec::new(),
                                 };
                                 create_and_add_entity(entity, entities);
                                 processed = true;
@@ -311,6 +315,7 @@ fn collect_ts_entities_recursive(
                                     snippet: get_node_text(var_declarator, source_code),
                                 },
                                 embedding: None,
+                                references: Vec::new(),
                             };
                             create_and_add_entity(entity, entities);
                         }
@@ -348,6 +353,7 @@ fn collect_ts_entities_recursive(
                         snippet: get_node_text(node, source_code),
                     },
                     embedding: None,
+                    references: Vec::new(),
                 };
                 create_and_add_entity(entity, entities);
                 entity_created_for_this_node = true;
@@ -399,6 +405,7 @@ fn collect_ts_entities_recursive(
                         snippet: get_node_text(node, source_code),
                     },
                     embedding: None,
+                    references: Vec::new(),
                 };
                 create_and_add_entity(entity, entities);
                 entity_created_for_this_node = true;
@@ -429,19 +436,40 @@ pub fn extract_ts_entities_from_file(
     is_tsx: bool,
     max_snippet_size: Option<usize>,
 ) -> Result<Vec<CodeEntity>> {
-    let source_code = fs::read_to_string(file_path)?;
-    let mut parser = Parser::new();
     let language = if is_tsx {
         tree_sitter_typescript::LANGUAGE_TSX.into()
     } else {
         tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()
     };
+    extract_entities_with_grammar(file_path, &language, "TS/TSX", max_snippet_size)
+}
+
+/// Extracts entities from a JavaScript file. The JavaScript grammar parses JSX too, so this
+/// covers .js, .jsx, .mjs and .cjs alike.
+pub fn extract_js_entities_from_file(
+    file_path: &PathBuf,
+    max_snippet_size: Option<usize>,
+) -> Result<Vec<CodeEntity>> {
+    let language = tree_sitter_javascript::LANGUAGE.into();
+    extract_entities_with_grammar(file_path, &language, "JS/JSX", max_snippet_size)
+}
+
+// JavaScript trees use the same node kinds as TypeScript ones for everything collected here,
+// minus the type annotations, so both grammars share the collector
+fn extract_entities_with_grammar(
+    file_path: &PathBuf,
+    language: &Language,
+    grammar_name: &str,
+    max_snippet_size: Option<usize>,
+) -> Result<Vec<CodeEntity>> {
+    let source_code = fs::read_to_string(file_path)?;
+    let mut parser = Parser::new();
     parser
-        .set_language(&language)
-        .map_err(|e| anyhow::anyhow!("Error loading TS/TSX grammar: {}", e))?;
+        .set_language(language)
+        .map_err(|e| anyhow::anyhow!("Error loading {} grammar: {}", grammar_name, e))?;
     let tree = parser
         .parse(&source_code, None)
-        .ok_or_else(|| anyhow::anyhow!("Failed to parse TS/TSX code"))?;
+        .ok_or_else(|| anyhow::anyhow!("Failed to parse {} code", grammar_name))?;
 
     let mut entities = Vec::new();
     let root_node = tree.root_node();
@@ -459,6 +487,7 @@ pub fn extract_ts_entities_from_file(
         None,
         max_snippet_size,
     );
+    attach_ts_references(root_node, &source_code, file_path, &mut entities);
 
     Ok(entities)
 }
@@ -51,26 +51,11 @@ pub fn index_directory(
     // 2. Parse each file based on its extension
     for file_path in files_to_parse {
         println!("  Parsing: {}", file_path.display());
-        let extension = file_path.extension().and_then(|ext| ext.to_str());
-
-        let parse_result = match extension {
-            Some("rs") => {
-                // Call the function re-exported from parser_mod
-                parser::extract_rust_entities_from_file(&file_path, max_snippet_size)
-            }
-            Some("ts") => {
-                // Call the function re-exported (and renamed) from parser_mod
-                parser::extract_ts_entities(&file_path, false, max_snippet_size)
-            }
-            Some("tsx") => {
-                // Call the function re-exported (and renamed) from parser_mod
-                parser::extract_ts_entities(&file_path, true, max_snippet_size)
-            }
-            _ => {
-                println!("  -> Skipping file with unsupported extension.");
-                continue; // Skip this file
-            }
-        };
+        if parser::SourceLanguage::from_path(&file_path).is_none() {
+            println!("  -> Skipping file with unsupported extension.");
+            continue; // Skip this file
+        }
+        let parse_result = parser::extract_entities_from_file(&file_path, max_snippet_size);
 
         match parse_result {
             Ok(entities) => {
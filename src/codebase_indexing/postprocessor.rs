@@ -269,5 +269,6 @@ fn create_merged_entity(merge_candidates: Vec<CodeEntity>) -> CodeEntity {
             snippet: merged_snippet,
         },
         embedding: None,
+        references: merge_candidates.iter().flat_map(|e| e.references.iter().cloned()).collect(),
     }
 } 
\ No newline at end of file
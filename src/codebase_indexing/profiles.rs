@@ -0,0 +1,180 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::codebase_indexing::parser::SUPPORTED_EXTENSIONS;
+use crate::dev_operation::sync;
+use crate::dev_setup::config_files;
+
+// config.toml table holding the named profiles, e.g. `[analysis_profiles.docs]`
+const CONFIG_SECTION: &str = "analysis_profiles";
+// config.toml key selecting the profile used when a request names none
+const DEFAULT_PROFILE_KEY: &str = "analysis_profile";
+
+/// A named set of search and index filters, from `[analysis_profiles.<name>]` in config.toml.
+///
+/// ```toml
+/// analysis_profile = "code"   # workspace default, optional
+///
+/// [analysis_profiles.code]
+/// languages = ["rust", "typescript"]
+/// include = ["src", "app"]
+/// max_snippet_size = 2000
+///
+/// [analysis_profiles.docs]
+/// languages = ["markdown"]
+/// include = ["docs", "*.md"]
+/// embedding = false
+/// ```
+///
+/// Values given explicitly in a request take precedence over the profile.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AnalysisProfile {
+    /// Sync-style globs matched against paths relative to the searched directory; empty includes all
+    pub include: Vec<String>,
+    /// Directory names skipped while scanning
+    pub exclude_dirs: Vec<String>,
+    /// Language names (`rust`, `typescript`, ...) or file extensions (`rs`, `tsx`, ...); a
+    /// language the entity parser has no grammar for makes the profile invalid
+    pub languages: Vec<String>,
+    pub max_snippet_size: Option<usize>,
+    /// `fine`, `medium` or `coarse`
+    pub granularity: Option<String>,
+    /// Whether indexes built with this profile are embedded; defaults to true
+    pub embedding: Option<bool>,
+}
+
+impl AnalysisProfile {
+    /// File extensions covered by `languages`, without the leading dot.
+    pub fn extensions(&self) -> Vec<String> {
+        let mut extensions: Vec<String> = Vec::new();
+        for language in &self.languages {
+            for ext in language_extensions(language) {
+                if !extensions.contains(&ext) {
+                    extensions.push(ext);
+                }
+            }
+        }
+        extensions
+    }
+
+    /// Whether a `/`-separated relative path passes the `include` globs.
+    pub fn includes(&self, rel_path: &str) -> bool {
+        self.include.is_empty() || sync::is_excluded(rel_path, &self.include)
+    }
+
+    pub fn embedding_enabled(&self) -> bool {
+        self.embedding.unwrap_or(true)
+    }
+
+    // Languages the entity parser has no grammar for would silently match nothing
+    fn check_languages(&self) -> Result<(), String> {
+        let unsupported: Vec<&str> = self
+            .languages
+            .iter()
+            .filter(|language| language_extensions(language).iter().any(|ext| !SUPPORTED_EXTENSIONS.contains(&ext.as_str())))
+            .map(String::as_str)
+            .collect();
+        if unsupported.is_empty() {
+            return Ok(());
+        }
+        Err(format!("unsupported languages [{}]; supported extensions are [{}]", unsupported.join(", "), SUPPORTED_EXTENSIONS.join(", ")))
+    }
+}
+
+fn language_extensions(language: &str) -> Vec<String> {
+    let language = language.trim().trim_start_matches('.').to_lowercase();
+    let extensions: &[&str] = match language.as_str() {
+        "rust" => &["rs"],
+        "typescript" => &["ts", "tsx"],
+        "javascript" => &["js", "jsx", "mjs", "cjs"],
+        "markdown" => &["md", "mdx"],
+        "yaml" => &["yaml", "yml"],
+        _ => return vec![language],
+    };
+    extensions.iter().map(|e| e.to_string()).collect()
+}
+
+// Every profile in config.toml, or why it is invalid
+fn parse_profiles() -> BTreeMap<String, Result<AnalysisProfile, String>> {
+    let Some(toml::Value::Table(section)) = config_files::get_config_section(CONFIG_SECTION) else {
+        return BTreeMap::new();
+    };
+    section
+        .into_iter()
+        .map(|(name, value)| {
+            let profile = value
+                .try_into::<AnalysisProfile>()
+                .map_err(|e| e.to_string())
+                .and_then(|profile| profile.check_languages().map(|_| profile));
+            (name, profile)
+        })
+        .collect()
+}
+
+/// All profiles defined in config.toml, by name. Invalid profiles are skipped with a warning.
+pub fn load_profiles() -> BTreeMap<String, AnalysisProfile> {
+    parse_profiles()
+        .into_iter()
+        .filter_map(|(name, profile)| match profile {
+            Ok(profile) => Some((name, profile)),
+            Err(e) => {
+                tracing::warn!(target: "codebase_indexing::profiles", profile = %name, error = %e, "Invalid analysis profile in config.toml, ignoring it.");
+                None
+            }
+        })
+        .collect()
+}
+
+/// The profile to apply: the one named by the request, else the workspace default
+/// (`analysis_profile` in config.toml), else none.
+pub fn resolve_profile(requested: Option<&str>) -> Result<Option<(String, AnalysisProfile)>> {
+    let name = match requested {
+        Some(name) => name.to_string(),
+        None => match config_files::get_config_value(DEFAULT_PROFILE_KEY) {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+    };
+    let mut profiles = parse_profiles();
+    match profiles.remove(&name) {
+        Some(Ok(profile)) => Ok(Some((name, profile))),
+        Some(Err(e)) => bail!("Analysis profile '{}' in config.toml is invalid: {}", name, e),
+        None => bail!(
+            "Unknown analysis profile '{}'. Defined profiles: [{}]",
+            name,
+            profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_from_toml() {
+        let value: toml::Value = r#"
+            languages = ["typescript", "rs", ".md"]
+            include = ["src", "*.md"]
+            embedding = false
+        "#
+        .parse()
+        .unwrap();
+        let profile: AnalysisProfile = value.try_into().unwrap();
+        assert_eq!(profile.extensions(), vec!["ts", "tsx", "rs", "md"]);
+        assert!(!profile.embedding_enabled());
+        assert_eq!(profile.max_snippet_size, None);
+
+        assert!(profile.includes("src/app/page.tsx"));
+        assert!(profile.includes("README.md"));
+        assert!(!profile.includes("scripts/build.ts"));
+        assert!(AnalysisProfile::default().includes("scripts/build.ts"));
+        assert!(profile.check_languages().is_ok());
+
+        let python = AnalysisProfile { languages: vec!["python".to_string(), "rust".to_string()], ..AnalysisProfile::default() };
+        let err = python.check_languages().unwrap_err();
+        assert!(err.starts_with("unsupported languages [python]"), "{}", err);
+    }
+}
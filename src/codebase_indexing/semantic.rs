@@ -0,0 +1,332 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::index_manager;
+use super::parser::CodeEntity;
+use super::vector_store::VectorStore;
+use crate::dev_runtime::db;
+use crate::dev_runtime::util::{self, now_secs};
+use crate::dev_setup::{config_files, offline};
+
+// config.toml table selecting the embedding endpoint, e.g. `[embeddings]`
+const CONFIG_SECTION: &str = "embeddings";
+// Longer entity texts are cut before embedding; most embedding models stop at ~8k tokens
+const MAX_EMBED_CHARS: usize = 6000;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The embedding endpoint, from `[embeddings]` in config.toml. Any server implementing the
+/// OpenAI `/embeddings` API works, including local ones like Ollama or LM Studio.
+///
+/// ```toml
+/// [embeddings]
+/// api_base = "http://localhost:11434/v1"
+/// model = "nomic-embed-text"
+/// api_key_env = "OPENAI_API_KEY"   # variable holding the key; local servers need none
+/// batch_size = 64
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EmbeddingConfig {
+    /// Defaults to `OPENAI_API_BASE`, then `https://api.openai.com/v1`
+    pub api_base: String,
+    pub model: String,
+    /// Name of the environment variable holding the API key, so keys stay out of config.toml
+    pub api_key_env: String,
+    /// Texts sent per request
+    pub batch_size: usize,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            api_base: std::env::var("OPENAI_API_BASE").unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+            model: "text-embedding-3-small".to_string(),
+            api_key_env: "OPENAI_API_KEY".to_string(),
+            batch_size: 64,
+        }
+    }
+}
+
+impl EmbeddingConfig {
+    /// Loads the endpoint from config.toml.
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+
+    pub fn api_key(&self) -> Option<String> {
+        std::env::var(&self.api_key_env).ok().filter(|key| !key.is_empty())
+    }
+
+    /// Why embeddings can't be generated with this configuration, if they can't.
+    pub fn unavailable_reason(&self) -> Option<String> {
+        let local = offline::is_local_url(&self.api_base);
+        if self.api_key().is_none() && !local {
+            return Some(format!("{} is not set", self.api_key_env));
+        }
+        if offline::is_offline() && !local {
+            return Some("Offline mode and the [embeddings] api_base is not a local server".to_string());
+        }
+        None
+    }
+}
+
+/// Turns texts into embedding vectors.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Identifies the vector space; vectors of different models are never compared.
+    fn model(&self) -> &str;
+
+    /// One vector per text, in order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// An OpenAI-compatible `POST {api_base}/embeddings` endpoint.
+pub struct HttpEmbeddingProvider {
+    config: EmbeddingConfig,
+    client: reqwest::Client,
+}
+
+impl HttpEmbeddingProvider {
+    pub fn new(config: EmbeddingConfig) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().context("Failed to build the HTTP client")?;
+        Ok(Self { config, client })
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for HttpEmbeddingProvider {
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        offline::ensure_api_reachable("Generating embeddings", Some(&self.config.api_base))?;
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.config.batch_size.max(1)) {
+            let url = format!("{}/embeddings", self.config.api_base.trim_end_matches('/'));
+            let mut request = self.client.post(&url).json(&serde_json::json!({ "model": self.config.model, "input": batch }));
+            if let Some(key) = self.config.api_key() {
+                request = request.bearer_auth(key);
+            }
+            let response = request.send().await.with_context(|| format!("Embedding request to {} failed", url))?;
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                bail!("Embedding endpoint {} answered {}: {}", url, status, body.chars().take(500).collect::<String>());
+            }
+            let mut data = response.json::<EmbeddingsResponse>().await.context("Invalid embeddings response")?.data;
+            if data.len() != batch.len() {
+                bail!("Embedding endpoint returned {} vectors for {} texts", data.len(), batch.len());
+            }
+            data.sort_by_key(|d| d.index);
+            vectors.extend(data.into_iter().map(|d| d.embedding));
+        }
+        Ok(vectors)
+    }
+}
+
+/// A search result: an entity with its embedding and its similarity to the query.
+#[derive(Debug, Clone)]
+pub struct SemanticHit {
+    /// Path relative to the project root
+    pub path: String,
+    pub entity: CodeEntity,
+    pub similarity: f32,
+}
+
+/// What a sync of the semantic index did.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SyncStats {
+    /// Entities in the semantic index
+    pub entities: usize,
+    /// Entity texts embedded by this sync; the rest already had a stored vector
+    pub embedded: usize,
+}
+
+#[derive(Default)]
+struct SemanticIndex {
+    model: String,
+    root: PathBuf,
+    // `last_updated` of the code index when this was built; unchanged means nothing to sync
+    index_version: Option<u64>,
+    // Vectors by content hash, reused across syncs
+    vectors: HashMap<String, Vec<f32>>,
+    store: VectorStore<(String, CodeEntity)>,
+}
+
+static INDEX: Lazy<tokio::sync::Mutex<SemanticIndex>> = Lazy::new(|| tokio::sync::Mutex::new(SemanticIndex::default()));
+static LAST_STATUS: Lazy<Mutex<Option<(String, usize, u64)>>> = Lazy::new(|| Mutex::new(None));
+
+// The text embedded for an entity: what it is, its docs and its code
+fn embedding_text(entity: &CodeEntity) -> String {
+    let mut text = format!("{} {}\n", entity.code_type, entity.name);
+    if let Some(doc) = &entity.docstring {
+        text.push_str(doc);
+        text.push('\n');
+    }
+    text.push_str(&entity.context.snippet);
+    match text.char_indices().nth(MAX_EMBED_CHARS) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+// Stable across builds, so stored vectors stay addressable
+fn content_hash(text: &str) -> String {
+    format!("{}-{}", util::fnv1a_hex(text.as_bytes()), text.len())
+}
+
+/// Brings the semantic index of `project_root` up to date with the code index, embedding only
+/// entities whose text has no stored vector yet.
+pub async fn sync(provider: &dyn EmbeddingProvider, project_root: &Path) -> Result<SyncStats> {
+    let mut index = INDEX.lock().await;
+    let version = index_manager::global()
+        .filter(|m| m.root() == project_root && crate::file_system::watcher::is_watching())
+        .map(|m| m.stats().last_updated);
+    if index.model == provider.model() && index.root == project_root && version.is_some() && index.index_version == version {
+        return Ok(SyncStats { entities: index.store.len(), embedded: 0 });
+    }
+
+    let root = project_root.to_path_buf();
+    let snapshot = tokio::task::spawn_blocking(move || index_manager::current_snapshot(&root)).await??;
+    let mut entries = Vec::new();
+    for (file, entities) in snapshot {
+        let rel_path = file.strip_prefix(project_root).unwrap_or(&file).to_string_lossy().replace('\\', "/");
+        for entity in entities.iter().filter(|e| e.code_type != "Import" && !e.context.snippet.trim().is_empty()) {
+            let text = embedding_text(entity);
+            entries.push((rel_path.clone(), entity.clone(), content_hash(&text), text));
+        }
+    }
+
+    let model = provider.model().to_string();
+    let hashes: Vec<String> = entries.iter().map(|(_, _, hash, _)| hash.clone()).collect();
+    let mut vectors = if index.model == model { std::mem::take(&mut index.vectors) } else { HashMap::new() };
+    let known: HashSet<&String> = hashes.iter().collect();
+    vectors.retain(|hash, _| known.contains(hash));
+    let uncached: Vec<String> = hashes.iter().filter(|hash| !vectors.contains_key(*hash)).cloned().collect();
+    if !uncached.is_empty() {
+        vectors.extend(db::with_db(|db| db.cached_embeddings(&model, &uncached)).unwrap_or_default());
+    }
+
+    // Entities with identical text share one vector
+    let missing: HashMap<&String, &String> =
+        entries.iter().filter(|(_, _, hash, _)| !vectors.contains_key(hash)).map(|(_, _, hash, text)| (hash, text)).collect();
+    let (missing_hashes, texts): (Vec<String>, Vec<String>) = missing.into_iter().map(|(h, t)| (h.clone(), t.clone())).unzip();
+    if !texts.is_empty() {
+        tracing::info!(target: "codebase_indexing::semantic", count = texts.len(), model = %model, "Embedding code entities.");
+        let embedded: Vec<(String, Vec<f32>)> = missing_hashes.into_iter().zip(provider.embed(&texts).await?).collect();
+        if let Err(e) = db::with_db(|db| db.store_embeddings(&model, &embedded)) {
+            tracing::debug!(target: "codebase_indexing::semantic", error = ?e, "Failed to store embeddings.");
+        }
+        vectors.extend(embedded);
+    }
+    if let Err(e) = db::with_db(|db| db.retain_embeddings(&model, &hashes)) {
+        tracing::debug!(target: "codebase_indexing::semantic", error = ?e, "Failed to prune stored embeddings.");
+    }
+
+    let mut store = VectorStore::default();
+    for (path, mut entity, hash, _) in entries {
+        if let Some(vector) = vectors.get(&hash) {
+            entity.embedding = Some(vector.clone());
+            store.insert((path, entity), vector.clone());
+        }
+    }
+    let stats = SyncStats { entities: store.len(), embedded: texts.len() };
+    let now = now_secs();
+    *index = SemanticIndex { model: model.clone(), root: project_root.to_path_buf(), index_version: version, vectors, store };
+    *LAST_STATUS.lock().unwrap_or_else(|e| e.into_inner()) = Some((model, stats.entities, now));
+    Ok(stats)
+}
+
+/// The `k` entities of the project most similar to a natural-language `query`, syncing the
+/// semantic index first. `path_prefix` limits results to files under a directory.
+pub async fn search(
+    provider: &dyn EmbeddingProvider,
+    project_root: &Path,
+    query: &str,
+    k: usize,
+    path_prefix: Option<&str>,
+) -> Result<(Vec<SemanticHit>, SyncStats)> {
+    let stats = sync(provider, project_root).await?;
+    let query_vector = provider.embed(&[query.to_string()]).await?.pop().context("No embedding returned for the query")?;
+    let index = INDEX.lock().await;
+    let hits = index
+        .store
+        .top_k(&query_vector, k, |(path, _)| path_prefix.is_none_or(|prefix| path.starts_with(prefix)))
+        .into_iter()
+        .map(|((path, entity), similarity)| SemanticHit { path: path.clone(), entity: entity.clone(), similarity })
+        .collect();
+    Ok((hits, stats))
+}
+
+/// Model, entity count and time (Unix seconds) of the last sync, if there was one.
+pub fn last_sync() -> Option<(String, usize, u64)> {
+    LAST_STATUS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Embeds by counting a few keywords, so similarity follows shared vocabulary
+    struct KeywordProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for KeywordProvider {
+        fn model(&self) -> &str {
+            "keywords-test"
+        }
+
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            self.calls.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    let t = t.to_lowercase();
+                    ["user", "login", "price", "cart"].iter().map(|w| t.matches(w).count() as f32 + 0.01).collect()
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_embeds_once_and_ranks() {
+        let dir = crate::test_support::project_tempdir();
+        std::fs::create_dir_all(dir.path().join("src/auth")).unwrap();
+        std::fs::write(dir.path().join("src/auth/login.ts"), "export function loginUser(user: string) {\n  return user;\n}\n").unwrap();
+        std::fs::write(dir.path().join("src/cart.ts"), "export function cartTotal(price: number) {\n  return price;\n}\n").unwrap();
+
+        let provider = KeywordProvider { calls: AtomicUsize::new(0) };
+        let (hits, stats) = search(&provider, dir.path(), "how does a user login", 1, None).await.unwrap();
+        assert_eq!((hits[0].entity.name.as_str(), hits[0].path.as_str()), ("loginUser", "src/auth/login.ts"));
+        assert!(hits[0].entity.embedding.is_some());
+        assert_eq!(stats.entities, 2);
+
+        // Stored vectors are reused the second time; only the query is embedded
+        let before = provider.calls.load(Ordering::SeqCst);
+        let (hits, _) = search(&provider, dir.path(), "price of the cart", 5, Some("src/auth")).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), before + 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(content_hash("abc"), content_hash("abc"));
+    }
+}
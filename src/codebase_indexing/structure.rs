@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::index_manager;
+use super::parser::{CodeEntity, SourceLanguage};
+use crate::dev_operation::entity_search::is_exported;
+
+/// A directory or file of the project tree, with what the code index knows about it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StructureNode {
+    Dir {
+        name: String,
+        /// Relative to the project root, `/`-separated
+        path: String,
+        /// Files anywhere below the directory
+        file_count: usize,
+        /// Indexed entities anywhere below the directory
+        entity_count: usize,
+        /// Directories first, then files, each by name
+        children: Vec<StructureNode>,
+    },
+    File {
+        name: String,
+        path: String,
+        /// Parser language (`tsx`, `rust`, `markdown`, ...), or `None` for files that aren't indexed
+        language: Option<String>,
+        size: u64,
+        /// Indexed entities, not counting imports
+        entity_count: usize,
+        /// Names exported by a code file (`export`ed in TS/JS, `pub` in Rust), top-level only
+        exports: Vec<String>,
+    },
+}
+
+impl StructureNode {
+    pub fn entity_count(&self) -> usize {
+        match self {
+            Self::Dir { entity_count, .. } | Self::File { entity_count, .. } => *entity_count,
+        }
+    }
+
+    fn file_count(&self) -> usize {
+        match self {
+            Self::Dir { file_count, .. } => *file_count,
+            Self::File { .. } => 1,
+        }
+    }
+}
+
+// Exported top-level names of a code file, in source order
+fn file_exports(file: &Path, language: SourceLanguage, entities: &[CodeEntity]) -> Vec<String> {
+    if !language.is_code() {
+        return Vec::new();
+    }
+    let Ok(content) = fs::read_to_string(file) else { return Vec::new() };
+    let lines: Vec<&str> = content.lines().collect();
+    let ext = file.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let mut exports: Vec<String> = Vec::new();
+    for entity in entities.iter().filter(|e| e.context.struct_name.is_none() && e.code_type != "Import") {
+        let definition = lines.get(entity.line.saturating_sub(1)).copied().unwrap_or_default();
+        if is_exported(ext, definition, &entity.name, &lines) && !exports.contains(&entity.name) {
+            exports.push(entity.name.clone());
+        }
+    }
+    exports
+}
+
+fn walk(dir: &Path, root: &Path, skipped_dirs: &[&str], entities: &HashMap<PathBuf, Arc<Vec<CodeEntity>>>) -> Vec<StructureNode> {
+    let Ok(read_dir) = fs::read_dir(dir) else { return Vec::new() };
+    let mut entries: Vec<fs::DirEntry> = read_dir.filter_map(|e| e.ok()).collect();
+    // Symlinks are listed as files, so a link back up the tree can't loop the walk
+    entries.sort_by_key(|e| (!e.file_type().is_ok_and(|t| t.is_dir()), e.file_name()));
+
+    let mut nodes = Vec::new();
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let rel = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            if skipped_dirs.contains(&name.as_str()) {
+                continue;
+            }
+            let children = walk(&path, root, skipped_dirs, entities);
+            nodes.push(StructureNode::Dir {
+                name,
+                path: rel,
+                file_count: children.iter().map(StructureNode::file_count).sum(),
+                entity_count: children.iter().map(StructureNode::entity_count).sum(),
+                children,
+            });
+        } else {
+            let language = SourceLanguage::from_path(&path);
+            let file_entities = entities.get(&path).map(|e| e.as_slice()).unwrap_or_default();
+            nodes.push(StructureNode::File {
+                name,
+                path: rel,
+                language: language.map(|l| l.name().to_string()),
+                size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                entity_count: file_entities.iter().filter(|e| e.code_type != "Import").count(),
+                exports: language.map(|l| file_exports(&path, l, file_entities)).unwrap_or_default(),
+            });
+        }
+    }
+    nodes
+}
+
+/// The typed tree of the project below `project_root`, skipping directories named in
+/// `skipped_dirs`. Entity counts and exports come from the code index; files the index skips
+/// (or all of them, if the index can't be built) count zero.
+pub fn build_tree(project_root: &Path, skipped_dirs: &[&str]) -> Vec<StructureNode> {
+    let entities: HashMap<PathBuf, Arc<Vec<CodeEntity>>> = match index_manager::current_snapshot(project_root) {
+        Ok(snapshot) => snapshot.into_iter().collect(),
+        Err(e) => {
+            tracing::debug!(target: "codebase_indexing::structure", error = ?e, "Building the structure tree without the code index.");
+            HashMap::new()
+        }
+    };
+    walk(project_root, project_root, skipped_dirs, &entities)
+}
+
+/// `nodes` with directories deeper than `max_depth` (1 = top level only) left without children;
+/// their counts still cover everything below.
+pub fn truncate(nodes: Vec<StructureNode>, max_depth: usize) -> Vec<StructureNode> {
+    nodes
+        .into_iter()
+        .map(|node| match node {
+            StructureNode::Dir { name, path, file_count, entity_count, children } => StructureNode::Dir {
+                name,
+                path,
+                file_count,
+                entity_count,
+                children: if max_depth > 1 { truncate(children, max_depth - 1) } else { Vec::new() },
+            },
+            file => file,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_tree_counts_entities_and_exports() {
+        let dir = crate::test_support::project_tempdir();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/lib")).unwrap();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::write(root.join("src/lib/format.ts"), "import x from 'y';\nexport function price() {}\nfunction cents() {}\n").unwrap();
+        fs::write(root.join("src/page.tsx"), "const Page = () => <main />;\nexport default Page;\n").unwrap();
+        fs::write(root.join("README.md"), "# Shop\n").unwrap();
+        fs::write(root.join("node_modules/pkg/index.ts"), "export const a = 1;\n").unwrap();
+
+        let tree = build_tree(root, &["node_modules"]);
+        let names: Vec<&str> = tree
+            .iter()
+            .map(|n| match n {
+                StructureNode::Dir { name, .. } | StructureNode::File { name, .. } => name.as_str(),
+            })
+            .collect();
+        assert_eq!(names, vec!["src", "README.md"]);
+
+        let StructureNode::Dir { file_count, entity_count, children, .. } = &tree[0] else { panic!("src is a directory") };
+        assert_eq!((*file_count, *entity_count), (2, 3));
+        let StructureNode::Dir { children: lib, .. } = &children[0] else { panic!("lib is a directory") };
+        let StructureNode::File { language, exports, .. } = &lib[0] else { panic!("format.ts is a file") };
+        assert_eq!((language.as_deref(), exports.clone()), (Some("typescript"), vec!["price".to_string()]));
+        let StructureNode::File { exports, .. } = &children[1] else { panic!("page.tsx is a file") };
+        assert_eq!(exports, &vec!["Page".to_string()]);
+
+        let StructureNode::Dir { children, file_count, .. } = &truncate(tree, 1)[0] else { panic!() };
+        assert!(children.is_empty() && *file_count == 2);
+    }
+}
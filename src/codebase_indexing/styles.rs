@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::parser::{CodeEntity, SourceLanguage};
+
+// Stylesheet entity kinds that are declarations rather than rules
+const PROPERTY_TYPES: &[&str] = &["Custom Property", "Variable"];
+
+/// A stylesheet rule (selector, at-rule, mixin, keyframes) or a custom property / SCSS variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleRule {
+    /// Path relative to the project root
+    pub path: String,
+    pub name: String,
+    pub kind: String,
+    /// Enclosing rule, for nested rules and declarations
+    pub parent: Option<String>,
+    pub line: usize,
+    pub line_to: usize,
+    pub snippet: String,
+}
+
+/// The classes a component puts in its `className`s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentClasses {
+    /// Path relative to the project root
+    pub path: String,
+    pub component: String,
+    pub line: usize,
+    /// Distinct classes, in order of first use
+    pub classes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StyleMatches {
+    pub rules: Vec<StyleRule>,
+    pub custom_properties: Vec<StyleRule>,
+    pub components: Vec<ComponentClasses>,
+}
+
+// Whether a selector mentions `class` as a class, e.g. `.card:hover > .title` for `card`
+fn selector_uses_class(selector: &str, class: &str) -> bool {
+    selector.match_indices('.').any(|(i, _)| {
+        let rest = &selector[i + 1..];
+        rest.starts_with(class) && !rest[class.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// Stylesheet rules, custom properties and component class lists of the project matching
+/// `query`. A query names a selector, property or component (case-insensitive substring) or a
+/// class: `.btn` or `btn` finds the rules styling `.btn` and the components using `btn`.
+/// Without a query everything under `path_prefix` is returned.
+pub fn find_styles(
+    snapshot: &[(PathBuf, Arc<Vec<CodeEntity>>)],
+    root: &Path,
+    query: Option<&str>,
+    path_prefix: Option<&str>,
+) -> StyleMatches {
+    let query = query.map(str::trim).filter(|q| !q.is_empty());
+    let needle = query.map(|q| q.to_lowercase());
+    let class = query.map(|q| q.trim_start_matches('.'));
+    let matches_name = |name: &str| needle.as_ref().is_none_or(|n| name.to_lowercase().contains(n));
+
+    let mut found = StyleMatches::default();
+    for (file, entities) in snapshot {
+        let path = file.strip_prefix(root).unwrap_or(file).to_string_lossy().replace('\\', "/");
+        if path_prefix.is_some_and(|prefix| !path.starts_with(prefix)) {
+            continue;
+        }
+        match SourceLanguage::from_path(file) {
+            Some(SourceLanguage::Stylesheet) => {
+                for entity in entities.iter() {
+                    let by_class = class.is_some_and(|c| selector_uses_class(&entity.name, c));
+                    if !(matches_name(&entity.name) || by_class) {
+                        continue;
+                    }
+                    let rule = StyleRule {
+                        path: path.clone(),
+                        name: entity.name.clone(),
+                        kind: entity.code_type.clone(),
+                        parent: entity.context.struct_name.clone(),
+                        line: entity.line_from,
+                        line_to: entity.line_to,
+                        snippet: entity.context.snippet.clone(),
+                    };
+                    if PROPERTY_TYPES.contains(&entity.code_type.as_str()) {
+                        found.custom_properties.push(rule);
+                    } else {
+                        found.rules.push(rule);
+                    }
+                }
+            }
+            Some(SourceLanguage::Tsx | SourceLanguage::JavaScript) => {
+                for entity in entities.iter() {
+                    let mut classes = Vec::new();
+                    let mut seen = BTreeSet::new();
+                    for reference in entity.references.iter().filter(|r| r.kind == "class") {
+                        if seen.insert(reference.name.as_str()) {
+                            classes.push(reference.name.clone());
+                        }
+                    }
+                    let uses_class = class.is_some_and(|c| classes.iter().any(|used| used == c));
+                    if classes.is_empty() || !(matches_name(&entity.name) || uses_class) {
+                        continue;
+                    }
+                    found.components.push(ComponentClasses { path: path.clone(), component: entity.name.clone(), line: entity.line, classes });
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codebase_indexing::parser::extract_entities_from_file;
+    use std::fs;
+
+    #[test]
+    fn test_find_styles_links_classes_to_rules_and_components() {
+        let dir = crate::test_support::project_tempdir();
+        let root = dir.path();
+        fs::write(root.join("globals.css"), ":root { --radius: 4px; }\n.btn { border-radius: var(--radius); }\n.btn-primary:hover { color: red; }\n").unwrap();
+        let component = "export function Save({ busy }) {\n  return <button className={cn(\"btn px-4\", busy && `opacity-50 ${extra}`)}>Save</button>;\n}\n";
+        fs::write(root.join("save.tsx"), component).unwrap();
+        let snapshot: Vec<(PathBuf, Arc<Vec<CodeEntity>>)> = ["globals.css", "save.tsx"]
+            .iter()
+            .map(|name| (root.join(name), Arc::new(extract_entities_from_file(&root.join(name), None).unwrap())))
+            .collect();
+
+        let found = find_styles(&snapshot, root, Some(".btn"), None);
+        assert_eq!(found.rules.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec![".btn", ".btn-primary:hover"]);
+        assert_eq!(found.components.len(), 1);
+        assert_eq!(found.components[0].component, "Save");
+        assert_eq!(found.components[0].classes, vec!["btn", "px-4", "opacity-50"]);
+
+        let found = find_styles(&snapshot, root, Some("--radius"), None);
+        assert_eq!(found.custom_properties.iter().map(|r| (r.path.as_str(), r.parent.as_deref())).collect::<Vec<_>>(), vec![("globals.css", Some(":root"))]);
+        assert!(found.components.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::codebase_indexing::parser::entities::CodeEntity;
+use crate::dev_setup::offline;
 use anyhow::{Context, Result};
 use async_openai::{
     config::OpenAIConfig, types::CreateEmbeddingRequestArgs, Client as OpenAIClient,
@@ -133,10 +134,11 @@ pub async fn query(
     api_key: Option<String>,
     api_base: Option<String>,
     qdrant_url: &str,
-) -> Result<Vec<CodeEntity>> {
+) -> Result<Vec<(CodeEntity, f32)>> {
     // --- OpenAI Client Setup (similar to embedder.rs) ---
     let effective_api_key = api_key.or_else(|| std::env::var("OPENAI_API_KEY").ok());
     let effective_api_base = api_base.or_else(|| std::env::var("OPENAI_API_BASE").ok());
+    offline::ensure_api_reachable("Embedding the search query", effective_api_base.as_deref())?;
 
     let mut config = OpenAIConfig::default();
     // Require API key for querying
@@ -190,7 +192,7 @@ pub async fn query(
         .with_context(|| format!("Qdrant search failed in collection '{}'", collection_name))?;
     // --- End Qdrant Search ---
 
-    let mut entities: Vec<CodeEntity> = Vec::new();
+    let mut entities: Vec<(CodeEntity, f32)> = Vec::new();
     if response.result.is_empty() {
         info!(target: "galatea::hoarder", query = %query, "No results found for query.");
     } else {
@@ -202,11 +204,10 @@ pub async fn query(
                     // Try to deserialize the payload back into a CodeEntity
                     match serde_json::from_value::<CodeEntity>(json_value.clone()) {
                         Ok(mut entity) => {
-                            // Optionally, include score or other info from point if needed
-                            // For now, just reconstruct the entity. Embedding is not stored in payload by default.
+                            // Embedding is not stored in payload by default.
                             // If embedding needs to be returned, it should be handled here.
                             entity.embedding = None; // Clear any potentially stale embedding from payload if it was there.
-                            entities.push(entity);
+                            entities.push((entity, point.score)); // Keep the similarity for ranking
                         }
                         Err(e) => {
                             error!(target: "galatea::hoarder", error = ?e, payload = %json_value, "Failed to deserialize payload to CodeEntity.");
@@ -220,5 +221,5 @@ pub async fn query(
         }
     }
 
-    Ok(entities) // Return the collected entities
+    Ok(entities) // Return the collected entities with their similarity scores
 } 
\ No newline at end of file
@@ -0,0 +1,70 @@
+/// An in-memory vector store ranking items by cosine similarity to a query vector.
+///
+/// Vectors are normalized on insert, so a query costs one dot product per item; code indexes of
+/// a single project stay small enough that a linear scan beats maintaining an ANN structure.
+#[derive(Debug, Clone)]
+pub struct VectorStore<T> {
+    items: Vec<(T, Vec<f32>)>,
+}
+
+impl<T> Default for VectorStore<T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+impl<T> VectorStore<T> {
+    pub fn insert(&mut self, item: T, vector: Vec<f32>) {
+        self.items.push((item, normalized(vector)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The `k` items most similar to `query` with their cosine similarity, most similar first.
+    /// Items whose vector has a different dimension than the query are skipped.
+    pub fn top_k(&self, query: &[f32], k: usize, filter: impl Fn(&T) -> bool) -> Vec<(&T, f32)> {
+        let query = normalized(query.to_vec());
+        let mut scored: Vec<(&T, f32)> = self
+            .items
+            .iter()
+            .filter(|(item, vector)| vector.len() == query.len() && filter(item))
+            .map(|(item, vector)| (item, vector.iter().zip(&query).map(|(a, b)| a * b).sum()))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+        scored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_k_orders_by_cosine_similarity() {
+        let mut store = VectorStore::default();
+        store.insert("x", vec![10.0, 0.0]);
+        store.insert("diagonal", vec![1.0, 1.0]);
+        store.insert("y", vec![0.0, 3.0]);
+        store.insert("wrong dimension", vec![1.0, 0.0, 0.0]);
+
+        let hits = store.top_k(&[1.0, 0.2], 2, |_| true);
+        assert_eq!(hits.iter().map(|(item, _)| **item).collect::<Vec<_>>(), vec!["x", "diagonal"]);
+        assert!((hits[0].1 - 0.9806).abs() < 1e-3);
+        assert_eq!(store.top_k(&[1.0, 0.2], 5, |item| *item != "x").len(), 2);
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, ImageFormat};
+use serde::Deserialize;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::transfers;
+use crate::dev_setup::config_files;
+
+// config.toml table with the asset settings
+const CONFIG_SECTION: &str = "assets";
+
+/// Asset settings, from `[assets]` in config.toml.
+///
+/// ```toml
+/// [assets]
+/// dir = "public"          # relative to the project root
+/// max_entries = 2000
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AssetConfig {
+    pub dir: String,
+    pub max_entries: usize,
+}
+
+impl Default for AssetConfig {
+    fn default() -> Self {
+        Self { dir: "public".to_string(), max_entries: 2000 }
+    }
+}
+
+impl AssetConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+/// A file in the assets directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asset {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub content_type: &'static str,
+    /// Pixel size, for images the image crate reads (not SVG)
+    pub dimensions: Option<(u32, u32)>,
+    pub modified: Option<u64>, // Unix seconds
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetListing {
+    pub assets: Vec<Asset>,
+    /// Whether `max_entries` cut the listing short
+    pub truncated: bool,
+}
+
+/// What to do to an image.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Optimization {
+    /// Scales the image down, keeping its aspect ratio, to fit these bounds; never scales up
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Re-encodes the image as lossless WebP, written next to it with a `.webp` extension
+    pub to_webp: bool,
+}
+
+/// An image before and after `optimize`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Optimized {
+    pub path: PathBuf,
+    pub content: Vec<u8>,
+    pub dimensions: (u32, u32),
+    pub original_size_bytes: u64,
+    pub original_dimensions: (u32, u32),
+}
+
+fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    ImageFormat::from_path(path).ok()?;
+    image::image_dimensions(path).ok()
+}
+
+/// Files under `dir`, by path, hidden ones left out, at most `max_entries` of them.
+pub fn list(dir: &Path, max_entries: usize) -> Result<AssetListing> {
+    if !dir.is_dir() {
+        return Ok(AssetListing { assets: Vec::new(), truncated: false });
+    }
+    let mut assets = Vec::new();
+    let walker = WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'));
+    for entry in walker {
+        let entry = entry.context(format!("Failed to read {}", dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if assets.len() == max_entries {
+            return Ok(AssetListing { assets, truncated: true });
+        }
+        let metadata = entry.metadata().ok();
+        assets.push(Asset {
+            size_bytes: metadata.as_ref().map_or(0, |m| m.len()),
+            content_type: transfers::content_type(entry.path()),
+            dimensions: image_dimensions(entry.path()),
+            modified: metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            path: entry.into_path(),
+        });
+    }
+    Ok(AssetListing { assets, truncated: false })
+}
+
+// Encodes in `format`, dropping the alpha channel for formats without one
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut bytes = Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut bytes, format),
+        ImageFormat::WebP => DynamicImage::ImageRgba8(image.to_rgba8()).write_to(&mut bytes, format),
+        _ => image.write_to(&mut bytes, format),
+    }
+    .context(format!("Failed to encode the image as {:?}", format))?;
+    Ok(bytes.into_inner())
+}
+
+/// Resizes and/or converts the image at `path`, returning the new content and where it goes:
+/// over the original for a resize, next to it for a conversion. Nothing is written.
+pub fn optimize(path: &Path, optimization: &Optimization) -> Result<Optimized> {
+    let format = ImageFormat::from_path(path).ok().filter(|f| f.can_read() && f.can_write());
+    let Some(format) = format else {
+        bail!("'{}' is not an image that can be optimized (PNG, JPEG, GIF, WebP, BMP or ICO)", path.display());
+    };
+    let original_size_bytes = std::fs::metadata(path).context(format!("Failed to read {}", path.display()))?.len();
+    let mut image = image::open(path).context(format!("Failed to decode {}", path.display()))?;
+    let original_dimensions = (image.width(), image.height());
+
+    let max_width = optimization.max_width.unwrap_or(u32::MAX).max(1);
+    let max_height = optimization.max_height.unwrap_or(u32::MAX).max(1);
+    if image.width() > max_width || image.height() > max_height {
+        image = image.resize(max_width, max_height, image::imageops::FilterType::Lanczos3);
+    }
+    let (target, format) = if optimization.to_webp { (path.with_extension("webp"), ImageFormat::WebP) } else { (path.to_path_buf(), format) };
+    Ok(Optimized {
+        content: encode(&image, format)?,
+        path: target,
+        dimensions: (image.width(), image.height()),
+        original_size_bytes,
+        original_dimensions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_list_and_optimize_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let public = dir.path().join("public");
+        std::fs::create_dir_all(public.join("images")).unwrap();
+        RgbaImage::from_pixel(400, 200, Rgba([200, 40, 40, 255])).save(public.join("images/hero.png")).unwrap();
+        std::fs::write(public.join("robots.txt"), "User-agent: *").unwrap();
+        std::fs::write(public.join(".DS_Store"), "").unwrap();
+
+        let listing = list(&public, 10).unwrap();
+        let summary: Vec<(&str, Option<(u32, u32)>)> = listing
+            .assets
+            .iter()
+            .map(|a| (a.content_type, a.dimensions))
+            .collect();
+        assert_eq!(summary, vec![("image/png", Some((400, 200))), ("text/plain", None)]);
+        assert!(list(&public, 1).unwrap().truncated);
+
+        let hero = public.join("images/hero.png");
+        let resized = optimize(&hero, &Optimization { max_width: Some(100), ..Default::default() }).unwrap();
+        assert_eq!((resized.path.as_path(), resized.dimensions, resized.original_dimensions), (hero.as_path(), (100, 50), (400, 200)));
+
+        let webp = optimize(&hero, &Optimization { to_webp: true, ..Default::default() }).unwrap();
+        assert_eq!(webp.path, public.join("images/hero.webp"));
+        assert_eq!(image::load_from_memory(&webp.content).unwrap().width(), 400);
+        assert!(optimize(&public.join("robots.txt"), &Optimization::default()).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::git::SESSION_TRAILER;
+use crate::dev_runtime::crash;
+use crate::file_system::paths;
+use crate::terminal::git;
+
+const CHANGELOG_FILE_NAME: &str = "CHANGELOG.md";
+
+// Field/record separators used in the git log format so subjects and bodies can contain anything
+const RECORD_SEP: char = '\u{1e}';
+const FIELD_SEP: char = '\u{1f}';
+
+/// A single commit as read from `git log`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitRecord {
+    pub hash: String,
+    pub date: String, // YYYY-MM-DD
+    pub subject: String,
+    pub session: Option<String>, // From the `Galatea-Session` trailer
+    pub tasks: Vec<String>,      // From `Task:` trailers in the commit body
+    pub files: Vec<String>,
+}
+
+/// All work recorded in one session on one day, which is what a changelog entry covers.
+/// Commits made outside Galatea have no session and share one entry per day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangelogDay {
+    pub date: String,
+    pub session: Option<String>,
+    pub commits: Vec<CommitRecord>,
+    pub tasks: Vec<String>,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChangelogSummary {
+    pub path: PathBuf,
+    pub markdown: String,
+    pub days: Vec<ChangelogDay>,
+}
+
+pub fn changelog_path() -> Result<PathBuf> {
+    Ok(paths::galatea_files_dir()?.join(CHANGELOG_FILE_NAME))
+}
+
+/// Regenerates `galatea_files/CHANGELOG.md` from the project's git history.
+///
+/// `days` limits the history to the last N days; `None` covers the whole history.
+/// The file is rewritten on every run so it always mirrors the repository. A repository
+/// without commits gets an empty changelog.
+pub async fn generate_changelog(project_dir: &Path, days: Option<u32>) -> Result<ChangelogSummary> {
+    let _operation = crash::track_operation("changelog generation");
+    let format_arg = format!(
+        "--pretty=format:{}%H{}%ad{}%s{}%b{}",
+        RECORD_SEP, FIELD_SEP, FIELD_SEP, FIELD_SEP, FIELD_SEP
+    );
+    let mut args = vec!["log", "--date=short", "--name-only", format_arg.as_str()];
+    let since_arg;
+    if let Some(days) = days {
+        since_arg = format!("--since={}.days", days);
+        args.push(&since_arg);
+    }
+
+    let output = if git::git_output(project_dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).await.is_err() {
+        String::new()
+    } else {
+        git::git_output(project_dir, &args)
+            .await
+            .context("Failed to read git history for changelog")?
+    };
+    let days = group_by_day(parse_git_log(&output));
+    let markdown = render_markdown(&days);
+
+    let path = changelog_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create galatea_files directory")?;
+    }
+    fs::write(&path, &markdown).context(format!("Failed to write {}", path.display()))?;
+    tracing::info!(target: "dev_operation::changelog", path = %path.display(), days = days.len(), "Changelog regenerated.");
+
+    Ok(ChangelogSummary { path, markdown, days })
+}
+
+/// Regenerates the changelog every `interval` in a background task.
+pub fn spawn_changelog_scheduler(project_dir: PathBuf, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = generate_changelog(&project_dir, None).await {
+                tracing::warn!(target: "dev_operation::changelog", error = ?e, "Scheduled changelog generation failed.");
+            }
+        }
+    });
+}
+
+fn parse_git_log(output: &str) -> Vec<CommitRecord> {
+    output
+        .split(RECORD_SEP)
+        .filter(|record| !record.trim().is_empty())
+        .filter_map(|record| {
+            let mut fields = record.splitn(5, FIELD_SEP);
+            let hash = fields.next()?.trim().to_string();
+            let date = fields.next()?.trim().to_string();
+            let subject = fields.next()?.trim().to_string();
+            let body = fields.next().unwrap_or_default();
+            let file_list = fields.next().unwrap_or_default();
+
+            let trailers = |name: &'static str| {
+                body.lines()
+                    .filter_map(move |line| {
+                        let (key, value) = line.split_once(':')?;
+                        key.trim().eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+                    })
+                    .filter(|value| !value.is_empty())
+            };
+            let session = trailers(SESSION_TRAILER).next_back();
+            let tasks = trailers("task").collect();
+            let files = file_list
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect();
+
+            Some(CommitRecord { hash, date, subject, session, tasks, files })
+        })
+        .collect()
+}
+
+// Groups commits by date and session, newest day first and a day's sessions by their latest
+// commit; commits keep git's (newest first) order
+fn group_by_day(commits: Vec<CommitRecord>) -> Vec<ChangelogDay> {
+    let mut days: Vec<ChangelogDay> = Vec::new();
+    for commit in commits {
+        let day = match days.iter_mut().find(|d| d.date == commit.date && d.session == commit.session) {
+            Some(day) => day,
+            None => {
+                days.push(ChangelogDay {
+                    date: commit.date.clone(),
+                    session: commit.session.clone(),
+                    commits: Vec::new(),
+                    tasks: Vec::new(),
+                    files: Vec::new(),
+                });
+                days.last_mut().unwrap()
+            }
+        };
+        day.commits.push(commit);
+    }
+
+    for day in days.iter_mut() {
+        let mut tasks = Vec::new();
+        for task in day.commits.iter().flat_map(|c| c.tasks.iter()) {
+            if !tasks.contains(task) {
+                tasks.push(task.clone());
+            }
+        }
+        day.tasks = tasks;
+        day.files = day
+            .commits
+            .iter()
+            .flat_map(|c| c.files.iter().cloned())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+    }
+
+    days.sort_by(|a, b| b.date.cmp(&a.date));
+    days
+}
+
+fn render_markdown(days: &[ChangelogDay]) -> String {
+    let mut out = String::from("# Changelog\n\nGenerated by Galatea from the project's git history.\n");
+    if days.is_empty() {
+        out.push_str("\nNo changes recorded yet.\n");
+        return out;
+    }
+
+    for (i, day) in days.iter().enumerate() {
+        if i == 0 || days[i - 1].date != day.date {
+            out.push_str(&format!("\n## {}\n", day.date));
+        }
+        match &day.session {
+            Some(session) => out.push_str(&format!("\n### Session `{}`\n", session)),
+            None => out.push_str("\n### Outside Galatea sessions\n"),
+        }
+
+        if !day.tasks.is_empty() {
+            out.push_str("\n#### Tasks\n\n");
+            for task in &day.tasks {
+                out.push_str(&format!("- {}\n", task));
+            }
+        }
+
+        out.push_str("\n#### Commits\n\n");
+        for commit in &day.commits {
+            let short_hash: String = commit.hash.chars().take(7).collect();
+            out.push_str(&format!("- `{}` {}\n", short_hash, commit.subject));
+        }
+
+        if !day.files.is_empty() {
+            out.push_str(&format!("\n#### Files changed ({})\n\n", day.files.len()));
+            for file in &day.files {
+                out.push_str(&format!("- `{}`\n", file));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(hash: &str, date: &str, subject: &str, body: &str, files: &[&str]) -> String {
+        format!(
+            "{RECORD_SEP}{hash}{FIELD_SEP}{date}{FIELD_SEP}{subject}{FIELD_SEP}{body}{FIELD_SEP}\n{}\n",
+            files.join("\n")
+        )
+    }
+
+    #[test]
+    fn test_parse_and_group_git_log() {
+        let output = [
+            record("aaaaaaa111", "2024-05-02", "Add login page", "Task: Authentication\nGalatea-Session: s1\n", &["src/app/login/page.tsx"]),
+            record("bbbbbbb222", "2024-05-02", "Fix header", "Galatea-Session: s1\n", &["src/components/header.tsx", "src/app/login/page.tsx"]),
+            record("ddddddd444", "2024-05-02", "Bump deps", "", &["package.json"]),
+            record("ccccccc333", "2024-05-01", "Initial commit", "", &["package.json"]),
+        ]
+        .concat();
+
+        let commits = parse_git_log(&output);
+        assert_eq!(commits.len(), 4);
+        assert_eq!(commits[0].tasks, vec!["Authentication".to_string()]);
+        assert_eq!(commits[0].session.as_deref(), Some("s1"));
+        assert_eq!(commits[1].files.len(), 2);
+
+        let days = group_by_day(commits);
+        assert_eq!(days.len(), 3);
+        assert_eq!((days[0].date.as_str(), days[0].session.as_deref()), ("2024-05-02", Some("s1")));
+        assert_eq!(days[0].commits.len(), 2);
+        assert_eq!(days[0].files, vec!["src/app/login/page.tsx", "src/components/header.tsx"]);
+        assert_eq!((days[1].date.as_str(), days[1].session.as_deref()), ("2024-05-02", None));
+
+        let markdown = render_markdown(&days);
+        assert_eq!(markdown.matches("## 2024-05-02\n").count(), 1);
+        assert!(markdown.contains("### Session `s1`"));
+        assert!(markdown.contains("### Outside Galatea sessions"));
+        assert!(markdown.contains("- Authentication"));
+        assert!(markdown.contains("- `aaaaaaa` Add login page"));
+        assert!(markdown.contains("#### Files changed (2)"));
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+use super::git::{self, SESSION_TRAILER};
+use crate::dev_runtime::{events, util};
+use crate::dev_runtime::lsp_pool::{self, EditedFiles};
+use crate::dev_setup::config_files;
+use crate::file_system::paths::get_project_root;
+use crate::terminal::git::git_output;
+
+// config.toml table with the checkpoint settings
+const CONFIG_SECTION: &str = "checkpoints";
+// Checkpoints are commits kept under these refs, outside of any branch
+const REF_PREFIX: &str = "refs/galatea/checkpoints/";
+const FIELD: char = '\x1f';
+
+/// Checkpoint settings, from `[checkpoints]` in config.toml.
+///
+/// ```toml
+/// [checkpoints]
+/// auto = true      # checkpoint before editor changes
+/// idle_secs = 120  # edits closer together than this are one session, checkpointed once
+/// max = 50         # the oldest checkpoints beyond this are deleted
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CheckpointConfig {
+    pub auto: bool,
+    pub idle_secs: u64,
+    pub max: usize,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self { auto: true, idle_secs: 120, max: 50 }
+    }
+}
+
+impl CheckpointConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+/// A snapshot of the working tree: every file git doesn't ignore, tracked or not.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub id: String, // Creation time in Unix milliseconds, which orders checkpoints
+    pub hash: String,
+    pub tree: String,
+    pub label: String,
+    pub session: Option<String>,
+    pub created_at: i64, // Unix seconds
+}
+
+/// The outcome of restoring a checkpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Restored {
+    pub checkpoint: Checkpoint,
+    /// Checkpoint of the working tree as it was before the restore, to undo it
+    pub backup: Checkpoint,
+    /// Files created after the checkpoint, which the restore deleted
+    pub removed: Vec<String>,
+}
+
+// Creating and restoring share the scratch index, so they run one at a time
+static LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+static LAST_EDIT: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+// Runs git against a scratch index instead of the repository's own
+async fn git_with_index(dir: &Path, index: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .env("GIT_INDEX_FILE", index)
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+async fn git_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    let path = git_output(dir, &["rev-parse", "--git-path", name]).await?;
+    Ok(dir.join(path.trim()))
+}
+
+async fn head(dir: &Path) -> Option<String> {
+    git_output(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).await.ok().map(|h| h.trim().to_string())
+}
+
+// Writes the working tree as a tree object. Starts from a copy of the real index so git can
+// skip rehashing files whose stat data is unchanged.
+async fn snapshot_tree(dir: &Path) -> Result<String> {
+    let index = git_path(dir, "galatea-checkpoint.index").await?;
+    let real_index = git_path(dir, "index").await?;
+    if real_index.exists() {
+        fs::copy(&real_index, &index).context("Failed to copy the git index")?;
+    }
+    let result = async {
+        git_with_index(dir, &index, &["add", "--all"]).await?;
+        git_with_index(dir, &index, &["write-tree"]).await
+    }
+    .await;
+    let _ = fs::remove_file(&index);
+    Ok(result?.trim().to_string())
+}
+
+fn parse_entry(line: &str) -> Option<Checkpoint> {
+    let fields: Vec<&str> = line.split(FIELD).collect();
+    let [id, hash, tree, created_at, label, session] = fields.as_slice() else {
+        return None;
+    };
+    Some(Checkpoint {
+        id: id.to_string(),
+        hash: hash.to_string(),
+        tree: tree.to_string(),
+        label: label.to_string(),
+        session: Some(session.trim().to_string()).filter(|s| !s.is_empty()),
+        created_at: created_at.parse().ok()?,
+    })
+}
+
+/// Checkpoints of the repository at `dir`, newest first.
+pub async fn list(dir: &Path) -> Result<Vec<Checkpoint>> {
+    let format = format!(
+        "--format=%(refname:lstrip=3){f}%(objectname){f}%(tree){f}%(creatordate:unix){f}%(contents:subject){f}%(contents:trailers:key={},valueonly)",
+        SESSION_TRAILER,
+        f = FIELD
+    );
+    let output = git_output(dir, &["for-each-ref", "--sort=-refname", &format, REF_PREFIX]).await?;
+    Ok(output.lines().filter_map(parse_entry).collect())
+}
+
+async fn create_locked(dir: &Path, label: &str, max: usize) -> Result<Checkpoint> {
+    let label = label.lines().next().unwrap_or_default().trim();
+    let label = if label.is_empty() { "Checkpoint" } else { label };
+    let tree = snapshot_tree(dir).await?;
+    let existing = list(dir).await?;
+    // Nothing changed since the latest one
+    if let Some(latest) = existing.first().filter(|c| c.tree == tree) {
+        return Ok(latest.clone());
+    }
+
+    let message = format!("{}\n\n{}: {}", label, SESSION_TRAILER, events::session_id());
+    let mut args: Vec<&str> = git::identity_args(dir).await;
+    args.extend(["commit-tree", &tree, "-m", &message]);
+    let parent = head(dir).await;
+    if let Some(parent) = &parent {
+        args.extend(["-p", parent]);
+    }
+    let hash = git_output(dir, &args).await?.trim().to_string();
+    // Ids are strictly increasing even for checkpoints made within the same millisecond
+    let latest_id = existing.first().and_then(|c| c.id.parse::<u64>().ok()).unwrap_or(0);
+    let id = format!("{:013}", util::now_ms().max(latest_id + 1));
+    git_output(dir, &["update-ref", &format!("{}{}", REF_PREFIX, id), &hash]).await?;
+    tracing::info!(target: "dev_operation::checkpoints", id = %id, label = %label, "Checkpoint created.");
+
+    for old in existing.iter().skip(max.saturating_sub(1)) {
+        if let Err(e) = git_output(dir, &["update-ref", "-d", &format!("{}{}", REF_PREFIX, old.id)]).await {
+            tracing::warn!(target: "dev_operation::checkpoints", id = %old.id, error = %e, "Failed to delete an old checkpoint.");
+        }
+    }
+    let created = list(dir).await?.into_iter().find(|c| c.id == id);
+    created.context("The new checkpoint is missing")
+}
+
+/// Snapshots every file of the working tree that git doesn't ignore, untracked ones included,
+/// without touching the branch, the index or the files. When nothing changed since the latest
+/// checkpoint, returns that one instead.
+pub async fn create(dir: &Path, label: &str) -> Result<Checkpoint> {
+    let _lock = LOCK.lock().await;
+    create_locked(dir, label, CheckpointConfig::load().max).await
+}
+
+/// Brings the working tree back to checkpoint `id`: its files are rewritten and files created
+/// since are deleted. Ignored files, the branch and the index are left alone. The current state
+/// is checkpointed first, so a restore can itself be undone.
+pub async fn restore(dir: &Path, id: &str) -> Result<Restored> {
+    let _lock = LOCK.lock().await;
+    let checkpoints = list(dir).await?;
+    let Some(checkpoint) = checkpoints.into_iter().find(|c| c.id == id) else {
+        bail!("No checkpoint '{}'", id);
+    };
+    let backup = create_locked(dir, &format!("Before restoring checkpoint {}", id), CheckpointConfig::load().max).await?;
+
+    let added = git_output(dir, &["diff-tree", "-r", "-z", "--name-only", "--diff-filter=A", &checkpoint.tree, &backup.tree]).await?;
+    let removed: Vec<String> = added.split('\0').filter(|p| !p.is_empty()).map(str::to_string).collect();
+    for path in &removed {
+        if let Err(e) = fs::remove_file(dir.join(path)) {
+            tracing::warn!(target: "dev_operation::checkpoints", path = %path, error = %e, "Failed to delete a file created after the checkpoint.");
+        }
+    }
+    let index = git_path(dir, "galatea-checkpoint.index").await?;
+    let result = async {
+        git_with_index(dir, &index, &["read-tree", &checkpoint.tree]).await?;
+        git_with_index(dir, &index, &["checkout-index", "--all", "--force"]).await
+    }
+    .await;
+    let _ = fs::remove_file(&index);
+    lsp_pool::files_edited(EditedFiles::Unknown);
+    result?;
+    tracing::info!(target: "dev_operation::checkpoints", id = %id, removed = removed.len(), "Checkpoint restored.");
+    Ok(Restored { checkpoint, backup, removed })
+}
+
+/// Called before the editor changes files. With `auto` on, checkpoints the project before the
+/// first edit of a session (a run of edits less than `idle_secs` apart) and before every change
+/// to more than one file. Failures are logged and never hold the edit back.
+pub async fn before_edits(label: &str, files: usize) {
+    let config = CheckpointConfig::load();
+    let session_start = {
+        let mut last = LAST_EDIT.lock().unwrap_or_else(|e| e.into_inner());
+        let idle = last.is_none_or(|t| t.elapsed() >= Duration::from_secs(config.idle_secs));
+        *last = Some(Instant::now());
+        idle
+    };
+    if !config.auto || !(session_start || files > 1) {
+        return;
+    }
+    let Ok(root) = get_project_root() else {
+        return;
+    };
+    // Projects outside a git repository have no checkpoints
+    if git_output(&root, &["rev-parse", "--git-dir"]).await.is_err() {
+        return;
+    }
+    let _lock = LOCK.lock().await;
+    if let Err(e) = create_locked(&root, label, config.max).await {
+        tracing::warn!(target: "dev_operation::checkpoints", error = %e, "Failed to create an automatic checkpoint.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        git_output(root, &["init", "--quiet"]).await.unwrap();
+        fs::write(root.join(".gitignore"), "node_modules\n").unwrap();
+        fs::write(root.join("page.tsx"), "export default 1;\n").unwrap();
+
+        let first = create(root, "Before the edit").await.unwrap();
+        assert_eq!((first.label.as_str(), first.session.as_deref()), ("Before the edit", Some(events::session_id())));
+        assert_eq!(create(root, "Unchanged").await.unwrap(), first);
+
+        fs::write(root.join("page.tsx"), "export default 2;\n").unwrap();
+        fs::write(root.join("extra.ts"), "export {};\n").unwrap();
+        fs::create_dir(root.join("node_modules")).unwrap();
+        fs::write(root.join("node_modules/dep.js"), "").unwrap();
+        let restored = restore(root, &first.id).await.unwrap();
+        assert_eq!(restored.removed, vec!["extra.ts"]);
+        assert_eq!(fs::read_to_string(root.join("page.tsx")).unwrap(), "export default 1;\n");
+        assert!(!root.join("extra.ts").exists() && root.join("node_modules/dep.js").exists());
+        // The repository itself still has no commits and nothing staged
+        assert!(head(root).await.is_none());
+        assert!(git_output(root, &["diff", "--cached", "--name-only"]).await.unwrap().is_empty());
+
+        let ids: Vec<String> = list(root).await.unwrap().into_iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![restored.backup.id.clone(), first.id]);
+        restore(root, &restored.backup.id).await.unwrap();
+        assert_eq!(fs::read_to_string(root.join("extra.ts")).unwrap(), "export {};\n");
+    }
+}
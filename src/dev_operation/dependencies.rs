@@ -0,0 +1,269 @@
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::dev_runtime::util;
+use crate::dev_setup::offline;
+use crate::terminal::package_manager::PackageManager;
+
+// package.json sections, in the order they are listed
+const SECTIONS: [&str; 4] = ["dependencies", "devDependencies", "optionalDependencies", "peerDependencies"];
+// Output beyond this is cut, keeping the tail where package managers print errors
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+// One package manager run at a time: concurrent installs corrupt node_modules and the lockfile
+static PACKAGE_MANAGER_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// A dependency declared in package.json.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyInfo {
+    pub name: String,
+    /// package.json section: `dependencies`, `devDependencies`, ...
+    pub section: String,
+    /// Version range declared in package.json
+    pub wanted_range: String,
+    /// Version in node_modules, if installed
+    pub installed: Option<String>,
+    /// From the package manager's `outdated`, when it was checked
+    pub wanted: Option<String>,
+    pub latest: Option<String>,
+    pub deprecated: bool,
+}
+
+impl DependencyInfo {
+    pub fn outdated(&self) -> bool {
+        self.latest.is_some() && self.latest != self.installed
+    }
+}
+
+/// A package.json change a command made.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyChange {
+    pub name: String,
+    pub section: String,
+    /// Declared range before and after; `None` when not declared
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Result of running the package manager.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageManagerRun {
+    pub command: String,
+    pub success: bool,
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u64,
+    pub changes: Vec<DependencyChange>,
+}
+
+// Declared dependencies by (section, name)
+fn declared(project_dir: &Path) -> Result<BTreeMap<(String, String), String>> {
+    let path = project_dir.join("package.json");
+    let content = fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+    let manifest: serde_json::Value = serde_json::from_str(&content).context(format!("Failed to parse {}", path.display()))?;
+    let mut deps = BTreeMap::new();
+    for section in SECTIONS {
+        if let Some(entries) = manifest.get(section).and_then(|s| s.as_object()) {
+            for (name, range) in entries {
+                deps.insert((section.to_string(), name.clone()), range.as_str().unwrap_or_default().to_string());
+            }
+        }
+    }
+    Ok(deps)
+}
+
+fn installed_version(project_dir: &Path, name: &str) -> Option<String> {
+    let content = fs::read_to_string(project_dir.join("node_modules").join(name).join("package.json")).ok()?;
+    let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+    manifest.get("version")?.as_str().map(str::to_string)
+}
+
+/// Parses `pnpm outdated --format json` or `npm outdated --json`: an object keyed by package name
+/// with `current`, `wanted` and `latest` (pnpm adds `isDeprecated`).
+pub fn parse_outdated(output: &str) -> HashMap<String, (Option<String>, Option<String>, bool)> {
+    let Ok(serde_json::Value::Object(entries)) = serde_json::from_str::<serde_json::Value>(output.trim()) else {
+        return HashMap::new();
+    };
+    let text = |v: &serde_json::Value, key: &str| v.get(key).and_then(|s| s.as_str()).map(str::to_string);
+    entries
+        .into_iter()
+        .map(|(name, v)| {
+            let deprecated = v.get("isDeprecated").and_then(|d| d.as_bool()).unwrap_or(false);
+            (name, (text(&v, "wanted"), text(&v, "latest"), deprecated))
+        })
+        .collect()
+}
+
+async fn outdated(project_dir: &Path) -> Result<HashMap<String, (Option<String>, Option<String>, bool)>> {
+    let package_manager = PackageManager::detect(project_dir);
+    let Some(mut args) = package_manager.outdated_args() else {
+        bail!("{} can't list outdated packages as JSON", package_manager.name());
+    };
+    args.extend(offline::registry_args());
+    let output = package_manager.output(project_dir, &args).await?;
+    // Both exit with 1 when something is outdated, so only unparseable output is an error
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() && !stdout.trim_start().starts_with('{') {
+        bail!("{} outdated failed: {}", package_manager.name(), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_outdated(&stdout))
+}
+
+/// The project's declared dependencies with their installed versions, and with what
+/// `outdated` reports when `check_outdated` is set.
+pub async fn list(project_dir: &Path, check_outdated: bool) -> Result<Vec<DependencyInfo>> {
+    let outdated = if check_outdated { outdated(project_dir).await? } else { HashMap::new() };
+    Ok(declared(project_dir)?
+        .into_iter()
+        .map(|((section, name), wanted_range)| {
+            let installed = installed_version(project_dir, &name);
+            let (wanted, latest, deprecated) = match outdated.get(&name) {
+                Some((wanted, latest, deprecated)) => (wanted.clone(), latest.clone(), *deprecated),
+                // Checked and not listed: up to date
+                None if check_outdated => (installed.clone(), installed.clone(), false),
+                None => (None, None, false),
+            };
+            DependencyInfo { name, section, wanted_range, installed, wanted, latest, deprecated }
+        })
+        .collect())
+}
+
+/// Checks a package argument is a package name with an optional `@version`, not a flag or a
+/// path the package manager would interpret differently.
+pub fn validate_package_spec(spec: &str) -> Result<()> {
+    let (scope, rest) = match spec.strip_prefix('@') {
+        Some(rest) => match rest.split_once('/') {
+            Some((scope, rest)) => (Some(scope), rest),
+            None => bail!("Invalid package '{}': a scoped package looks like @scope/name", spec),
+        },
+        None => (None, spec),
+    };
+    let (name, version) = match rest.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (rest, None),
+    };
+    let valid_name = |part: &str| {
+        !part.is_empty()
+            && !part.starts_with(['.', '_', '-'])
+            && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-._~".contains(c))
+    };
+    if !scope.is_none_or(valid_name) || !valid_name(name) {
+        bail!("Invalid package name in '{}'", spec);
+    }
+    if let Some(version) = version {
+        if version.is_empty() || version.chars().any(|c| c.is_whitespace() || c == '/' || c == ':') {
+            bail!("Invalid version in '{}'", spec);
+        }
+    }
+    Ok(())
+}
+
+fn changes(before: &BTreeMap<(String, String), String>, after: &BTreeMap<(String, String), String>) -> Vec<DependencyChange> {
+    let mut keys: Vec<&(String, String)> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|(section, name)| DependencyChange {
+            name: name.clone(),
+            section: section.clone(),
+            before: before.get(&(section.clone(), name.clone())).cloned(),
+            after: after.get(&(section.clone(), name.clone())).cloned(),
+        })
+        .collect()
+}
+
+async fn run(project_dir: &Path, args: Vec<&str>) -> Result<PackageManagerRun> {
+    let _guard = PACKAGE_MANAGER_LOCK.lock().await;
+    let package_manager = PackageManager::detect(project_dir);
+    let before = declared(project_dir)?;
+    let command = format!("{} {}", package_manager.name(), args.join(" "));
+    tracing::info!(target: "dev_operation::dependencies", command = %command, "Running package manager.");
+
+    let started = Instant::now();
+    let output = package_manager.output(project_dir, &args).await?;
+    let after = declared(project_dir).unwrap_or_else(|_| before.clone());
+
+    Ok(PackageManagerRun {
+        command,
+        success: output.status.success(),
+        exit_code: output.status.code().unwrap_or(-1),
+        stdout: util::truncate_head(&String::from_utf8_lossy(&output.stdout), MAX_OUTPUT_BYTES),
+        stderr: util::truncate_head(&String::from_utf8_lossy(&output.stderr), MAX_OUTPUT_BYTES),
+        duration_ms: started.elapsed().as_millis() as u64,
+        changes: changes(&before, &after),
+    })
+}
+
+fn validate_all(packages: &[String]) -> Result<()> {
+    if packages.is_empty() {
+        bail!("No packages given");
+    }
+    packages.iter().try_for_each(|p| validate_package_spec(p))
+}
+
+/// Adds packages (`name` or `name@version`) and installs them.
+pub async fn add(project_dir: &Path, packages: &[String], dev: bool) -> Result<PackageManagerRun> {
+    validate_all(packages)?;
+    let mut args = PackageManager::detect(project_dir).add_args(dev);
+    args.extend(packages.iter().map(String::as_str));
+    args.extend(offline::registry_args());
+    run(project_dir, args).await
+}
+
+/// Removes packages from package.json and node_modules.
+pub async fn remove(project_dir: &Path, packages: &[String]) -> Result<PackageManagerRun> {
+    validate_all(packages)?;
+    let mut args = PackageManager::detect(project_dir).remove_args();
+    args.extend(packages.iter().map(String::as_str));
+    run(project_dir, args).await
+}
+
+/// Upgrades packages, all when `packages` is empty: within their declared ranges, or to the
+/// latest version (rewriting the ranges) with `latest`.
+pub async fn upgrade(project_dir: &Path, packages: &[String], latest: bool) -> Result<PackageManagerRun> {
+    packages.iter().try_for_each(|p| validate_package_spec(p))?;
+    let package_manager = PackageManager::detect(project_dir);
+    let latest_specs: Vec<String>;
+    let mut args = package_manager.update_args(latest);
+    if latest && package_manager == PackageManager::Npm {
+        // npm update never crosses declared ranges; installing `name@latest` does
+        let names = if packages.is_empty() {
+            declared(project_dir)?.into_keys().map(|(_, name)| name).collect()
+        } else {
+            packages.to_vec()
+        };
+        latest_specs = names.iter().map(|name| format!("{}@latest", name)).collect();
+        args = vec!["install"];
+        args.extend(latest_specs.iter().map(String::as_str));
+    } else {
+        args.extend(packages.iter().map(String::as_str));
+    }
+    args.extend(offline::registry_args());
+    run(project_dir, args).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_specs_and_outdated_output() {
+        for spec in ["react", "@types/node", "zod@^3.24.0", "@tanstack/react-query@5", "lodash.debounce@latest"] {
+            assert!(validate_package_spec(spec).is_ok(), "{}", spec);
+        }
+        for spec in ["--registry=http://evil", "", "@types", "../local", "React", "left pad", "x@file:../y", "git+https://x/y"] {
+            assert!(validate_package_spec(spec).is_err(), "{}", spec);
+        }
+
+        let pnpm = r#"{"next": {"current": "15.0.0", "latest": "15.1.0", "wanted": "15.0.3", "isDeprecated": false, "dependencyType": "dependencies"}}"#;
+        let outdated = parse_outdated(pnpm);
+        assert_eq!(outdated["next"], (Some("15.0.3".to_string()), Some("15.1.0".to_string()), false));
+        assert!(parse_outdated("").is_empty());
+    }
+}
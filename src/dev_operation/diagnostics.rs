@@ -0,0 +1,238 @@
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::language_features::{self, DiagnosticInfo};
+use super::suggestions::{self, Finding, Severity};
+use crate::dev_runtime::lsp_pool::LspPool;
+use crate::dev_runtime::util::now_secs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSource {
+    Eslint,
+    Tsc,
+    Lsp,
+}
+
+impl DiagnosticSource {
+    pub const ALL: [DiagnosticSource; 3] = [DiagnosticSource::Eslint, DiagnosticSource::Tsc, DiagnosticSource::Lsp];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticSource::Eslint => "eslint",
+            DiagnosticSource::Tsc => "tsc",
+            DiagnosticSource::Lsp => "lsp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == name)
+    }
+}
+
+/// A problem reported by ESLint, tsc or the language server, in one shape for all three.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticEntry {
+    pub path: String,  // Relative to the project root
+    pub line: usize,   // 1-indexed
+    pub column: usize, // 0-indexed characters
+    // End of the range, for sources that report one
+    pub end_line: Option<usize>,
+    pub end_column: Option<usize>,
+    pub severity: Severity,
+    pub source: DiagnosticSource,
+    pub rule: Option<String>, // ESLint rule id or TypeScript code (`TS2322`)
+    pub message: String,
+}
+
+// When a collection finished (Unix seconds) and every entry it found
+type Collection = (u64, Vec<DiagnosticEntry>);
+
+// The last collection that ran ESLint or tsc
+static RECENT: Lazy<Mutex<Option<Collection>>> = Lazy::new(|| Mutex::new(None));
+
+/// How a source fared in a collection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceStatus {
+    /// Ran and reported this many entries, before filtering
+    Ok(usize),
+    /// Not run, e.g. no tsconfig.json or no file to ask the language server about
+    Skipped(String),
+    Failed(String),
+}
+
+fn finding_entry(finding: Finding, source: DiagnosticSource, project_root: &Path) -> DiagnosticEntry {
+    DiagnosticEntry {
+        path: super::symbols::relative_path(&finding.path, project_root),
+        line: finding.line,
+        column: finding.column,
+        end_line: None,
+        end_column: None,
+        severity: finding.severity,
+        source,
+        rule: finding.rule,
+        message: finding.message,
+    }
+}
+
+fn lsp_entry(diagnostic: DiagnosticInfo) -> DiagnosticEntry {
+    let severity = match diagnostic.severity.as_deref() {
+        Some("error") => Severity::Error,
+        Some("warning") => Severity::Warning,
+        _ => Severity::Info,
+    };
+    // The TypeScript server reports bare numbers where tsc prints `TS2322`
+    let typescript = matches!(diagnostic.source.as_deref(), Some("typescript" | "ts"));
+    let rule = diagnostic.code.map(|code| match code.parse::<u32>() {
+        Ok(number) if typescript => format!("TS{}", number),
+        _ => code,
+    });
+    let range = diagnostic.range;
+    DiagnosticEntry {
+        path: range.path,
+        line: range.line as usize + 1,
+        column: range.character as usize,
+        end_line: Some(range.end_line as usize + 1),
+        end_column: Some(range.end_character as usize),
+        severity,
+        source: DiagnosticSource::Lsp,
+        rule,
+        message: diagnostic.message,
+    }
+}
+
+// Whether a project-relative path is `filter` or lies below it
+fn matches_path(path: &str, filter: &str) -> bool {
+    let filter = filter.trim_end_matches('/');
+    filter.is_empty() || path == filter || path.strip_prefix(filter).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Entries under `path` at least as severe as `severity`, sorted by location. The language
+/// server repeats what tsc reports, so an entry already seen at the same place with the same
+/// rule and message is dropped; the first source listed wins.
+pub fn filter_entries(entries: Vec<DiagnosticEntry>, path: Option<&str>, severity: Option<Severity>) -> Vec<DiagnosticEntry> {
+    let mut seen = HashSet::new();
+    let mut kept: Vec<DiagnosticEntry> = entries
+        .into_iter()
+        .filter(|e| path.is_none_or(|p| matches_path(&e.path, p)))
+        .filter(|e| severity.is_none_or(|s| e.severity <= s))
+        .filter(|e| seen.insert((e.path.clone(), e.line, e.column, e.rule.clone(), e.message.clone())))
+        .collect();
+    kept.sort_by(|a, b| (&a.path, a.line, a.column, a.severity).cmp(&(&b.path, b.line, b.column, b.severity)));
+    kept
+}
+
+async fn lsp_diagnostics(pool: &Arc<LspPool>, project_root: &Path, path: Option<&str>) -> Result<Vec<DiagnosticEntry>, SourceStatus> {
+    let file = path.map(|p| project_root.join(p)).filter(|p| p.is_file()).ok_or_else(|| {
+        SourceStatus::Skipped("The language server reports diagnostics per file; pass the path of a file".to_string())
+    })?;
+    let client = pool
+        .client_if_ready(project_root)
+        .ok_or_else(|| SourceStatus::Skipped("The language server is starting".to_string()))?;
+    match language_features::diagnostics(&client, &file).await {
+        Ok(diagnostics) => Ok(diagnostics.into_iter().map(lsp_entry).collect()),
+        Err(e) => Err(SourceStatus::Failed(format!("{:#}", e))),
+    }
+}
+
+/// Runs the given sources over the project and merges what they report (see `filter_entries`).
+///
+/// ESLint and tsc check the whole project and run concurrently; the language server is only
+/// asked about `path` when it names a file. A source that can't run is reported in the
+/// statuses and doesn't fail the others.
+pub async fn collect(
+    pool: &Arc<LspPool>,
+    project_root: &Path,
+    sources: &[DiagnosticSource],
+    path: Option<&str>,
+    severity: Option<Severity>,
+) -> (Vec<DiagnosticEntry>, Vec<(DiagnosticSource, SourceStatus)>) {
+    let wants = |source| sources.contains(&source);
+    let (eslint, tsc, lsp) = tokio::join!(
+        async { wants(DiagnosticSource::Eslint).then_some(suggestions::run_eslint(project_root).await) },
+        async { wants(DiagnosticSource::Tsc).then_some(suggestions::run_typecheck(project_root).await) },
+        async { wants(DiagnosticSource::Lsp).then_some(lsp_diagnostics(pool, project_root, path).await) },
+    );
+
+    let mut entries = Vec::new();
+    let mut statuses = Vec::new();
+    for (source, result) in [(DiagnosticSource::Tsc, tsc), (DiagnosticSource::Eslint, eslint)] {
+        let status = match result {
+            None => continue,
+            Some(Ok(findings)) => {
+                let count = findings.len();
+                entries.extend(findings.into_iter().map(|f| finding_entry(f, source, project_root)));
+                SourceStatus::Ok(count)
+            }
+            // Missing tooling (no tsconfig.json, eslint not installed) ends up here too
+            Some(Err(e)) => SourceStatus::Failed(format!("{:#}", e)),
+        };
+        statuses.push((source, status));
+    }
+    match lsp {
+        None => {}
+        Some(Ok(found)) => {
+            statuses.push((DiagnosticSource::Lsp, SourceStatus::Ok(found.len())));
+            entries.extend(found);
+        }
+        Some(Err(status)) => statuses.push((DiagnosticSource::Lsp, status)),
+    }
+    if statuses.iter().any(|(source, status)| *source != DiagnosticSource::Lsp && matches!(status, SourceStatus::Ok(_))) {
+        let now = now_secs();
+        *RECENT.lock().unwrap_or_else(|e| e.into_inner()) = Some((now, filter_entries(entries.clone(), None, None)));
+    }
+    (filter_entries(entries, path, severity), statuses)
+}
+
+/// The entries of the last collection that ran ESLint or tsc, unfiltered, with when it
+/// finished (Unix seconds); `None` if there wasn't one since Galatea started.
+pub fn recent() -> Option<Collection> {
+    RECENT.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev_operation::language_features::RangeInfo;
+
+    fn entry(path: &str, line: usize, severity: Severity, source: DiagnosticSource, rule: &str) -> DiagnosticEntry {
+        DiagnosticEntry {
+            path: path.to_string(),
+            line,
+            column: 4,
+            end_line: None,
+            end_column: None,
+            severity,
+            source,
+            rule: Some(rule.to_string()),
+            message: "Type 'string' is not assignable to type 'number'.".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_filter_entries_dedupes_lsp_against_tsc() {
+        let from_lsp = lsp_entry(DiagnosticInfo {
+            range: RangeInfo { path: "src/app/page.tsx".to_string(), line: 11, character: 4, end_line: 11, end_character: 9 },
+            severity: Some("error".to_string()),
+            code: Some("2322".to_string()),
+            source: Some("typescript".to_string()),
+            message: "Type 'string' is not assignable to type 'number'.".to_string(),
+        });
+        assert_eq!((from_lsp.line, from_lsp.end_column, from_lsp.rule.as_deref()), (12, Some(9), Some("TS2322")));
+
+        let entries = vec![
+            entry("src/app/page.tsx", 12, Severity::Error, DiagnosticSource::Tsc, "TS2322"),
+            entry("src/lib/a.ts", 3, Severity::Warning, DiagnosticSource::Eslint, "no-unused-vars"),
+            entry("src/app/layout.tsx", 1, Severity::Info, DiagnosticSource::Eslint, "no-console"),
+            from_lsp,
+        ];
+        let kept = filter_entries(entries.clone(), None, Some(Severity::Warning));
+        assert_eq!(kept.iter().map(|e| (e.path.as_str(), e.source)).collect::<Vec<_>>(), vec![
+            ("src/app/page.tsx", DiagnosticSource::Tsc),
+            ("src/lib/a.ts", DiagnosticSource::Eslint),
+        ]);
+        let kept = filter_entries(entries, Some("src/app/"), None);
+        assert_eq!(kept.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), vec!["src/app/layout.tsx", "src/app/page.tsx"]);
+    }
+}
@@ -0,0 +1,176 @@
+// Lines of unchanged context around each change, as `diff -u` prints by default
+const CONTEXT_LINES: usize = 3;
+
+// Past this many inserted and deleted lines the rest is reported as one replaced block, which
+// keeps rewrites of large files fast (and memory bounded) at the cost of a minimal diff
+const MAX_EDIT_DISTANCE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Unified diff between two versions of a file, as `diff -u` prints it with `a/` and `b/`
+/// labels. Empty when they are equal.
+pub fn unified_diff(rel_path: &str, before: &str, after: &str) -> String {
+    let old: Vec<&str> = before.split_inclusive('\n').collect();
+    let new: Vec<&str> = after.split_inclusive('\n').collect();
+    let ops = diff_lines(&old, &new);
+
+    // Each op with the old and new line index it applies at
+    let mut entries = Vec::with_capacity(ops.len());
+    let (mut o, mut n) = (0, 0);
+    for op in ops {
+        entries.push((op, o, n));
+        match op {
+            Op::Equal => (o, n) = (o + 1, n + 1),
+            Op::Delete => o += 1,
+            Op::Insert => n += 1,
+        }
+    }
+    let changes: Vec<usize> = (0..entries.len()).filter(|&i| entries[i].0 != Op::Equal).collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", rel_path, rel_path);
+    let mut next = 0;
+    while next < changes.len() {
+        // Changes separated by at most twice the context share a hunk
+        let first = changes[next];
+        let mut last = first;
+        next += 1;
+        while next < changes.len() && changes[next] - last <= 2 * CONTEXT_LINES + 1 {
+            last = changes[next];
+            next += 1;
+        }
+        let hunk = &entries[first.saturating_sub(CONTEXT_LINES)..(last + 1 + CONTEXT_LINES).min(entries.len())];
+        let old_count = hunk.iter().filter(|e| e.0 != Op::Insert).count();
+        let new_count = hunk.iter().filter(|e| e.0 != Op::Delete).count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(hunk[0].1, old_count),
+            hunk_range(hunk[0].2, new_count)
+        ));
+        for &(op, o, n) in hunk {
+            let (prefix, line) = match op {
+                Op::Equal => (' ', old[o]),
+                Op::Delete => ('-', old[o]),
+                Op::Insert => ('+', new[n]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+// `start,count` of a hunk; an empty range names the line before it
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+// The ops turning `old` into `new`, after setting aside their common prefix and suffix
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old_mid, new_mid) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut ops = vec![Op::Equal; prefix];
+    match myers(old_mid, new_mid) {
+        Some(middle) => ops.extend(middle),
+        None => {
+            ops.extend(std::iter::repeat_n(Op::Delete, old_mid.len()));
+            ops.extend(std::iter::repeat_n(Op::Insert, new_mid.len()));
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    ops
+}
+
+// Shortest edit script by Myers' algorithm, or `None` past `MAX_EDIT_DISTANCE`
+fn myers(old: &[&str], new: &[&str]) -> Option<Vec<Op>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    // Furthest old index reached on each diagonal k = x - y, at `offset + k`
+    let mut v = vec![0isize; 2 * max + 3];
+    // Diagonals -d..=d of `v` before each step d, to walk back from the end
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=max.min(MAX_EDIT_DISTANCE) as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) { v[i + 1] } else { v[i - 1] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                (x, y) = (x + 1, y + 1);
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Op> {
+    let (mut x, mut y) = (n, m);
+    let mut ops = Vec::new();
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+            (at(prev_k), at(prev_k) - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            (x, y) = (x - 1, y - 1);
+        }
+        if d > 0 {
+            ops.push(if x == prev_x { Op::Insert } else { Op::Delete });
+            (x, y) = (prev_x, prev_y);
+        }
+    }
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diffs_match_diff_u() {
+        let before = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let after = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nl\nm";
+        assert_eq!(
+            unified_diff("x.txt", before, after),
+            "--- a/x.txt\n+++ b/x.txt\n@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -8,5 +8,5 @@\n h\n i\n j\n-k\n l\n+m\n\\ No newline at end of file\n"
+        );
+        assert_eq!(unified_diff("new.ts", "", "x\n"), "--- a/new.ts\n+++ b/new.ts\n@@ -0,0 +1 @@\n+x\n");
+        assert_eq!(unified_diff("x.txt", "a\nb\n", "b\n"), "--- a/x.txt\n+++ b/x.txt\n@@ -1,2 +1 @@\n-a\n b\n");
+        assert!(unified_diff("x.txt", before, before).is_empty());
+
+        // Past the edit limit the middle is replaced as a whole, still a valid diff
+        let many_old: String = (0..3000).map(|i| format!("{}\n", i)).collect();
+        let many_new: String = (0..3000).map(|i| format!("{}\n", i * 7)).collect();
+        let diff = unified_diff("big.txt", &many_old, &many_new);
+        assert_eq!(diff.lines().filter(|l| l.starts_with('-') && !l.starts_with("---")).count(), 2999);
+    }
+}
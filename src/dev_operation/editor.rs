@@ -1,49 +1,435 @@
+use std::collections::{HashMap, VecDeque};
 use std::fs;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 use once_cell::sync::Lazy;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
 
-// Global shared editor state
-pub static SHARED_EDITOR: Lazy<Arc<Mutex<Editor>>> = Lazy::new(|| Arc::new(Mutex::new(Editor::new())));
+use super::diff;
+use super::editorconfig;
+use super::guardrails::{self, GuardrailViolation};
+use super::hooks::{self, HookConfig, HookOutcome, HookStage, HookTarget};
+use super::text_encoding::{TextEncoding, TextFormat};
+use crate::dev_runtime::lsp_pool::{self, EditedFiles};
+use crate::dev_runtime::quotas::{self, QuotaMetric};
+use crate::dev_runtime::{db, events, limits, util, workspaces};
+use crate::dev_setup::config_files;
 
-// Enum to represent the type of the last operation for undo functionality
+// Global shared editor state. Async callers go through `with_files`, which locks the files a
+// change touches instead of the whole editor.
+pub static SHARED_EDITOR: Lazy<Editor> = Lazy::new(Editor::new);
+
+// Most files a recursive directory removal deletes; their content is held in the undo history
+const MAX_REMOVED_FILES: usize = 1000;
+
+// Undo history depth used when `editor_undo_depth` isn't configured
+const DEFAULT_UNDO_DEPTH: usize = 50;
+
+// Largest file `view` reads whole when `editor_max_view_bytes` isn't configured
+const DEFAULT_MAX_VIEW_BYTES: u64 = 5 * 1024 * 1024;
+
+// Read size when scanning a large file for a line window
+const SCAN_BLOCK_BYTES: usize = 64 * 1024;
+
+// The state of a file before an edit; restoring it reverts the edit
 #[derive(Debug)]
-enum LastOperation {
-    None,
+enum FileSnapshot {
     Create {
         path: PathBuf,
-    }, // File was created, undo is deletion
+    }, // File didn't exist, restoring deletes it
     Overwrite {
         path: PathBuf,
         original_content: Vec<u8>,
     }, // File existed and was overwritten or modified
 }
 
-// Editor structure to hold state, like the last operation for undo
+impl FileSnapshot {
+    fn path(&self) -> &Path {
+        match self {
+            FileSnapshot::Create { path } | FileSnapshot::Overwrite { path, .. } => path,
+        }
+    }
+
+    // Captures the current state of `path`, to be restored later
+    fn capture(path: &Path) -> Result<Self, String> {
+        if path.is_dir() {
+            return Err(format!("Error: Path '{}' is a directory.", path.display()));
+        }
+        if !path.exists() {
+            return Ok(FileSnapshot::Create { path: path.to_path_buf() });
+        }
+        let original_content =
+            fs::read(path).map_err(|e| format!("Error reading file '{}': {}", path.display(), e))?;
+        Ok(FileSnapshot::Overwrite { path: path.to_path_buf(), original_content })
+    }
+
+    // Puts the file back into this state
+    fn restore(&self) -> Result<(), String> {
+        match self {
+            FileSnapshot::Create { path } => {
+                if path.exists() && path.is_file() {
+                    fs::remove_file(path).map_err(|e| {
+                        format!("Error undoing creation (deleting file '{}'): {}", path.display(), e)
+                    })?;
+                }
+            }
+            FileSnapshot::Overwrite { path, original_content } => {
+                // The file may have been deleted along with its directory, e.g. by a move
+                if let Some(parent) = path.parent().filter(|p| !p.exists()) {
+                    fs::create_dir_all(parent).map_err(|e| {
+                        format!("Error recreating directory '{}': {}", parent.display(), e)
+                    })?;
+                }
+                fs::write(path, original_content).map_err(|e| {
+                    format!("Error restoring content of '{}': {}", path.display(), e)
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Undo and redo history of one file. Entries carry a sequence number so that the most recent
+// edit across all files can be found for undos that don't name a file. The entries of a change
+// spanning several files (e.g. a move) share one number, so an undo without a path can't stop
+// halfway through it.
+#[derive(Debug, Default)]
+struct FileHistory {
+    // Oldest first, capped at the editor's undo depth
+    undo: VecDeque<(u64, FileSnapshot)>,
+    // Most recently undone last; cleared by any new edit of the file
+    redo: VecDeque<(u64, FileSnapshot)>,
+}
+
+impl FileHistory {
+    fn stack(&mut self, direction: HistoryDirection) -> &mut VecDeque<(u64, FileSnapshot)> {
+        match direction {
+            HistoryDirection::Undo => &mut self.undo,
+            HistoryDirection::Redo => &mut self.redo,
+        }
+    }
+
+    fn len(&self, direction: HistoryDirection) -> usize {
+        self.entries(direction).len()
+    }
+
+    fn entries(&self, direction: HistoryDirection) -> &VecDeque<(u64, FileSnapshot)> {
+        match direction {
+            HistoryDirection::Undo => &self.undo,
+            HistoryDirection::Redo => &self.redo,
+        }
+    }
+
+    fn newest_seq(&self, direction: HistoryDirection) -> Option<u64> {
+        self.entries(direction).back().map(|(seq, _)| *seq)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryDirection {
+    Undo,
+    Redo,
+}
+
+impl HistoryDirection {
+    fn opposite(self) -> Self {
+        match self {
+            HistoryDirection::Undo => HistoryDirection::Redo,
+            HistoryDirection::Redo => HistoryDirection::Undo,
+        }
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            HistoryDirection::Undo => "undo",
+            HistoryDirection::Redo => "redo",
+        }
+    }
+
+    fn past(self) -> &'static str {
+        match self {
+            HistoryDirection::Undo => "undone",
+            HistoryDirection::Redo => "redone",
+        }
+    }
+}
+
+/// Key the history of `path` is kept under: the canonical path, or the canonical parent joined
+/// with the file name for files that don't exist (e.g. after their creation was undone).
+fn history_key(path: &Path) -> PathBuf {
+    if let Ok(canonical) = dunce::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent().and_then(|p| dunce::canonicalize(p).ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+// The undo and redo history of each file the editor changed
+struct EditHistory {
+    histories: HashMap<PathBuf, FileHistory>,
+    next_seq: u64,
+    undo_depth: usize,
+}
+
+// Locks held while a command runs, released when dropped
+struct EditLock {
+    _shared: Option<OwnedRwLockReadGuard<()>>,
+    _exclusive: Option<OwnedRwLockWriteGuard<()>>,
+    _files: Vec<OwnedMutexGuard<()>>,
+}
+
+// Editor structure to hold state, like the undo and redo history of each file
 pub struct Editor {
-    last_op: LastOperation,
+    // Held while the history is read or updated; undo and redo also hold it while they restore
+    // files, which they do under the exclusive gate
+    history: Mutex<EditHistory>,
+    // Commands take it shared along with the locks of their files; undo and redo, which may
+    // touch any file, take it exclusively
+    gate: Arc<RwLock<()>>,
+    // Per-file locks by history key, dropped from the map once no command holds them
+    files: Mutex<HashMap<PathBuf, Weak<AsyncMutex<()>>>>,
 }
 
 impl Editor {
+    /// An editor whose undo depth comes from `editor_undo_depth` in config.toml (default 50).
     pub fn new() -> Self {
+        let depth = config_files::get_config_value("editor_undo_depth")
+            .and_then(|v| v.parse().ok())
+            .filter(|depth: &usize| *depth > 0)
+            .unwrap_or(DEFAULT_UNDO_DEPTH);
+        Self::with_undo_depth(depth)
+    }
+
+    /// An editor remembering up to `undo_depth` edits per file.
+    pub fn with_undo_depth(undo_depth: usize) -> Self {
         Editor {
-            last_op: LastOperation::None,
+            history: Mutex::new(EditHistory {
+                histories: HashMap::new(),
+                next_seq: 0,
+                undo_depth: undo_depth.max(1),
+            }),
+            gate: Arc::default(),
+            files: Mutex::default(),
         }
     }
 
+    fn history(&self) -> MutexGuard<'_, EditHistory> {
+        self.history.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Number of edits that can currently be undone and redone, for one file or for all of them.
+    /// A change spanning several files counts once per file.
+    pub fn history_len(&self, path: Option<&Path>) -> (usize, usize) {
+        self.history().history_len(path)
+    }
+
+    // Waits for the locks of `paths`, or of the whole editor when `None`. Files are locked in
+    // the same order every time, so two multi-file changes can't each wait for the other.
+    async fn lock(&self, paths: Option<&[PathBuf]>) -> EditLock {
+        let Some(paths) = paths else {
+            let exclusive = self.gate.clone().write_owned().await;
+            return EditLock { _shared: None, _exclusive: Some(exclusive), _files: Vec::new() };
+        };
+        let shared = self.gate.clone().read_owned().await;
+        let mut keys: Vec<PathBuf> = paths.iter().map(|p| history_key(p)).collect();
+        keys.sort();
+        keys.dedup();
+        let locks: Vec<Arc<AsyncMutex<()>>> = {
+            let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+            files.retain(|_, lock| lock.strong_count() > 0);
+            keys.into_iter()
+                .map(|key| match files.get(&key).and_then(Weak::upgrade) {
+                    Some(lock) => lock,
+                    None => {
+                        let lock = Arc::new(AsyncMutex::new(()));
+                        files.insert(key, Arc::downgrade(&lock));
+                        lock
+                    }
+                })
+                .collect()
+        };
+        let mut guards = Vec::with_capacity(locks.len());
+        for lock in locks {
+            guards.push(lock.lock_owned().await);
+        }
+        EditLock { _shared: Some(shared), _exclusive: None, _files: guards }
+    }
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs `f` with the shared editor on a blocking thread, holding the locks of `paths`: changes
+/// of different files run concurrently, changes of the same file one after the other. `None`
+/// locks the whole editor, for undo and redo. Fails only if `f` panics.
+pub async fn with_files<T, F>(paths: Option<Vec<PathBuf>>, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&Editor) -> T + Send + 'static,
+{
+    let editor: &'static Editor = &SHARED_EDITOR;
+    let lock = editor.lock(paths.as_deref()).await;
+    // The workspace scope is task-local and doesn't follow the closure onto the blocking thread
+    let root = workspaces::scoped_root();
+    // The locks move into the task, so a cancelled request can't release them mid-change
+    tokio::task::spawn_blocking(move || {
+        let _lock = lock;
+        match root {
+            Some(root) => workspaces::sync_scope(root, || f(editor)),
+            None => f(editor),
+        }
+    })
+    .await
+    .map_err(|e| format!("Error: The editor task failed: {}", e))
+}
+
+/// The files a command reads or writes, to lock with `with_files`. `None` for undo and redo,
+/// whose files are only known from the history.
+pub fn command_paths(args: &EditorArgs) -> Option<Vec<PathBuf>> {
+    if matches!(args.command, CommandType::UndoEdit | CommandType::RedoEdit) {
+        return None;
+    }
+    let paths = args.path.iter().chain(args.paths.iter().flatten()).chain(args.new_path.iter());
+    Some(paths.map(PathBuf::from).collect())
+}
+
+impl EditHistory {
+
     // Private helper to record an operation that modified a file
     fn record_write_op(&mut self, path: &Path, original_content: Option<Vec<u8>>) {
-        if let Some(content) = original_content {
-            self.last_op = LastOperation::Overwrite {
+        let snapshot = match original_content {
+            Some(content) => FileSnapshot::Overwrite {
                 path: path.to_path_buf(),
                 original_content: content,
-            };
-        } else {
+            },
             // File was newly created (or didn't exist before this op for create command)
-            self.last_op = LastOperation::Create {
+            None => FileSnapshot::Create {
                 path: path.to_path_buf(),
-            };
+            },
+        };
+        let key = history_key(path);
+        self.histories.entry(key.clone()).or_default().redo.clear();
+        self.push(&key, HistoryDirection::Undo, snapshot);
+    }
+
+    fn push(&mut self, key: &Path, direction: HistoryDirection, snapshot: FileSnapshot) {
+        let seq = self.allocate_seq();
+        self.push_entry(key, direction, seq, snapshot);
+    }
+
+    fn allocate_seq(&mut self) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+
+    fn push_entry(&mut self, key: &Path, direction: HistoryDirection, seq: u64, snapshot: FileSnapshot) {
+        let history = self.histories.entry(key.to_path_buf()).or_default();
+        history.stack(direction).push_back((seq, snapshot));
+        while history.undo.len() > self.undo_depth {
+            history.undo.pop_front();
+        }
+    }
+
+    fn history_len(&self, path: Option<&Path>) -> (usize, usize) {
+        let count = |direction| match path {
+            Some(path) => self.histories.get(&history_key(path)).map_or(0, |h| h.len(direction)),
+            None => self.histories.values().map(|h| h.len(direction)).sum(),
+        };
+        (count(HistoryDirection::Undo), count(HistoryDirection::Redo))
+    }
+
+    // Sequence number of the most recent entry in `direction`, across all files
+    fn most_recent(&self, direction: HistoryDirection) -> Option<u64> {
+        self.histories.values().filter_map(|h| h.newest_seq(direction)).max()
+    }
+
+    /// Undoes or redoes `count` edits of `path`, or the most recent edits across all files when
+    /// no path is given. Fails without changing anything if fewer edits are in the history, or
+    /// if, without a path, the edits would stop partway through a change of several files.
+    fn step_history(&mut self, path: Option<&Path>, count: usize, direction: HistoryDirection) -> Result<(), String> {
+        let (verb, past) = (direction.verb(), direction.past());
+        let scope = path.map(|p| format!(" for '{}'", p.display())).unwrap_or_default();
+        if count == 0 {
+            return Err(format!("Error: Number of edits to {} must be at least 1.", verb));
         }
+        let (undoable, redoable) = self.history_len(path);
+        let available = if direction == HistoryDirection::Undo { undoable } else { redoable };
+        if available == 0 {
+            return Err(format!("Error: No operation to {}{}.", verb, scope));
+        }
+        if count > available {
+            return Err(format!("Error: Only {} edit(s) can be {}{}, {} requested.", available, past, scope, count));
+        }
+        if path.is_none() {
+            self.check_whole_changes(count, direction)?;
+        }
+        let mut done = 0;
+        while done < count {
+            // The file's newest entry, or every file changed by the most recent edit
+            let mut entries: Vec<(PathBuf, u64, FileSnapshot)> = Vec::new();
+            match path {
+                Some(path) => {
+                    let key = history_key(path);
+                    let history = self.histories.get_mut(&key).expect("history length checked above");
+                    let (seq, snapshot) = history.stack(direction).pop_back().expect("history length checked above");
+                    entries.push((key, seq, snapshot));
+                }
+                None => {
+                    let seq = self.most_recent(direction).expect("history length checked above");
+                    for (key, history) in self.histories.iter_mut() {
+                        while history.newest_seq(direction) == Some(seq) {
+                            let (_, snapshot) = history.stack(direction).pop_back().expect("newest entry checked");
+                            entries.push((key.clone(), seq, snapshot));
+                        }
+                    }
+                }
+            }
+            let stepped = entries.len();
+            let opposite_seq = self.allocate_seq();
+            let mut entries = entries.into_iter();
+            while let Some((key, seq, snapshot)) = entries.next() {
+                let current = FileSnapshot::capture(snapshot.path())
+                    .and_then(|current| snapshot.restore().map(|_| current));
+                match current {
+                    Ok(current) => self.push_entry(&key, direction.opposite(), opposite_seq, current),
+                    Err(e) => {
+                        let failed_path = snapshot.path().display().to_string();
+                        for (key, seq, snapshot) in std::iter::once((key, seq, snapshot)).chain(entries) {
+                            self.histories.entry(key).or_default().stack(direction).push_back((seq, snapshot));
+                        }
+                        return Err(format!("{} ({} of {} edits {} before '{}' failed)", e, done, count, past, failed_path));
+                    }
+                }
+            }
+            done += stepped;
+        }
+        Ok(())
+    }
+
+    // Refuses a `count` that ends inside a change of several files, which would leave some of
+    // its files reverted and others not
+    fn check_whole_changes(&self, count: usize, direction: HistoryDirection) -> Result<(), String> {
+        let mut seqs: Vec<u64> = self.histories.values().flat_map(|h| h.entries(direction).iter().map(|(seq, _)| *seq)).collect();
+        seqs.sort_unstable_by(|a, b| b.cmp(a));
+        let last = seqs[count - 1];
+        if seqs.get(count) != Some(&last) {
+            return Ok(());
+        }
+        let (start, files) = (seqs.iter().position(|&s| s == last).unwrap_or(0), seqs.iter().filter(|&&s| s == last).count());
+        Err(format!(
+            "Error: {} edit(s) would stop partway through a change of {} files. Set `count` to {} to {} all of it, or name a file with `path`.",
+            count,
+            files,
+            start + files,
+            direction.verb()
+        ))
     }
 }
 
@@ -54,7 +440,54 @@ pub enum CommandType {
     Create,
     StrReplace,
     Insert,
+    ReplaceRange,
     UndoEdit,
+    RedoEdit,
+    DeleteFile,
+    Move,
+    Copy,
+}
+
+impl CommandType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandType::View => "view",
+            CommandType::Create => "create",
+            CommandType::StrReplace => "str_replace",
+            CommandType::Insert => "insert",
+            CommandType::ReplaceRange => "replace_range",
+            CommandType::UndoEdit => "undo_edit",
+            CommandType::RedoEdit => "redo_edit",
+            CommandType::DeleteFile => "delete_file",
+            CommandType::Move => "move",
+            CommandType::Copy => "copy",
+        }
+    }
+
+    /// Whether the command deletes, moves or copies a whole file rather than editing its text.
+    pub fn is_file_operation(&self) -> bool {
+        matches!(self, CommandType::DeleteFile | CommandType::Move | CommandType::Copy)
+    }
+
+    /// Whether the command modifies a file, which `expected_hash` can guard.
+    pub fn checks_expected_hash(&self) -> bool {
+        !matches!(self, CommandType::View | CommandType::UndoEdit | CommandType::RedoEdit)
+    }
+
+    /// Whether the command can be previewed with `dry_run`.
+    pub fn supports_dry_run(&self) -> bool {
+        matches!(self, CommandType::Create | CommandType::StrReplace | CommandType::Insert)
+    }
+}
+
+// A character-addressed span within a file. Lines are 1-indexed, columns are 0-indexed
+// character offsets within the line (not bytes), and the end position is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRange {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
 }
 
 // Arguments for the editor commands, derived from the schema
@@ -68,6 +501,17 @@ pub struct EditorArgs {
     pub new_str: Option<String>,        // For StrReplace (optional), Insert (required)
     pub old_str: Option<String>,        // For StrReplace (required)
     pub view_range: Option<Vec<isize>>, // For View (e.g., [1, 10] or [5, -1])
+    pub range: Option<TextRange>,       // For ReplaceRange
+    pub expected_hash: Option<String>,  // For mutations, required for ReplaceRange, see content_hash()
+    pub count: Option<usize>,           // For UndoEdit and RedoEdit, defaults to 1
+    pub use_regex: bool,                // For StrReplace: old_str is a regex, new_str may use $1/${name}
+    pub max_replacements: Option<usize>, // For StrReplace, replaces every match when unset
+    pub dry_run: bool,                  // For Create, StrReplace and Insert: preview without writing
+    pub new_path: Option<String>,       // For Move and Copy: destination, which must not exist
+    pub override_guardrails: bool,      // Apply mutations that trip the editor guardrails
+    pub encoding: TextEncoding,         // How file bytes are read and written as text
+    pub view_offset: Option<u64>,       // For View: byte offset of a chunk of the file
+    pub view_length: Option<u64>,       // For View: chunk size in bytes, capped at max_view_bytes()
 }
 
 // Output structure for multi-file view operations within the editor module
@@ -84,9 +528,151 @@ pub struct MultiFileViewOutput {
 pub enum EditorOperationResult {
     Single(Option<String>), // For non-view ops, or single file view content
     Multi(Vec<MultiFileViewOutput>), // For multi-file view
+    Preview(EditPreview), // For dry runs of create, str_replace and insert
+    ConfirmationRequired(GuardrailBlock), // Mutations held back by the guardrails, nothing written
+    Conflict(EditConflict), // Mutations whose expected_hash is stale, nothing written
+    Chunk(FileChunk), // For views of part of a large file
+}
+
+/// Part of a file, viewed by byte offset or, for files above `max_view_bytes()`, by lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChunk {
+    pub content: String,
+    /// Where the content is in the file, in bytes
+    pub offset: u64,
+    pub length: u64,
+    pub file_size: u64,
+    /// Lines of the whole file and the 1-indexed lines shown, for line windows
+    pub total_lines: Option<usize>,
+    pub lines: Option<(usize, usize)>,
+    /// Whether the file continues after the chunk
+    pub has_more: bool,
+}
+
+/// A mutation the editor guardrails held back. Retrying it with `override_guardrails` applies it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardrailBlock {
+    pub violations: Vec<GuardrailViolation>,
+    pub preview: EditPreview,
+}
+
+pub fn handle_command(editor: &Editor, args: EditorArgs) -> Result<EditorOperationResult, String> {
+    let command = args.command.clone();
+    let path = args.path.clone();
+    let span = tracing::info_span!(
+        target: "dev_operation::editor",
+        "editor.command",
+        galatea.command = command.as_str(),
+        galatea.path = path.as_deref().unwrap_or_default(),
+        galatea.session_id = events::session_id(),
+        otel.status_code = tracing::field::Empty,
+        otel.status_message = tracing::field::Empty,
+    );
+    let _entered = span.enter();
+    let dry_run = args.dry_run;
+    let new_path = args.new_path.clone();
+    // Moves and copies write their destination
+    let written_path = new_path.clone().or_else(|| path.clone());
+    let result = dispatch_command(editor, args);
+    if let Err(e) = &result {
+        span.record("otel.status_code", "ERROR");
+        span.record("otel.status_message", e.as_str());
+    }
+
+    // Successful modifications go into the edit history; a store failure must not fail the edit
+    if is_applied(&result) && command != CommandType::View && !dry_run {
+        let (command_name, recorded_path) = (command.as_str(), path.clone());
+        db::write_later(move |db| {
+            let recorded = db
+                .record_edit(events::session_id(), command_name, recorded_path.as_deref())
+                .and_then(|_| db.prune_edit_history(limits::edit_history_cap()));
+            if let Err(e) = recorded {
+                tracing::debug!(target: "dev_operation::editor", error = ?e, "Failed to record edit history.");
+            }
+        });
+        // Counted against the caller's quotas; the size of the file after the edit is what was written
+        quotas::charge(QuotaMetric::EditsPerHour, 1.0);
+        let written = written_path.as_deref().and_then(|p| fs::metadata(p).ok()).map_or(0, |m| m.len());
+        quotas::charge(QuotaMetric::BytesWritten, written as f64);
+        lsp_pool::files_edited(edited_files(&command, path.as_deref(), new_path.as_deref()));
+    }
+    result
+}
+
+// What an applied command changed, for the language servers
+fn edited_files(command: &CommandType, path: Option<&str>, new_path: Option<&str>) -> EditedFiles {
+    let paths = |p: Option<&str>| p.map(PathBuf::from).into_iter().collect::<Vec<_>>();
+    match command {
+        // An undo or redo may restore the other files of a multi-file change too
+        CommandType::UndoEdit | CommandType::RedoEdit => EditedFiles::Unknown,
+        CommandType::Create => EditedFiles::Paths { changed: Vec::new(), created: paths(path) },
+        CommandType::Move => EditedFiles::Paths { changed: paths(path), created: paths(new_path) },
+        CommandType::Copy => EditedFiles::Paths { changed: Vec::new(), created: paths(new_path) },
+        _ => EditedFiles::Paths { changed: paths(path), created: Vec::new() },
+    }
+}
+
+/// Runs a command with the configured editor hooks around it.
+///
+/// Returns the hook outcomes alongside the result. A blocking `pre` hook turns into an error
+/// and the edit is not applied; `post` hooks only run after a successful edit, and not after
+/// file operations, which leave no edited text to check.
+pub fn handle_command_with_hooks(
+    editor: &Editor,
+    args: EditorArgs,
+) -> (Result<EditorOperationResult, String>, Vec<HookOutcome>) {
+    let path = match &args.path {
+        // Dry runs write nothing for hooks to check or follow up on
+        Some(path) if args.command != CommandType::View && !args.dry_run => PathBuf::from(path),
+        _ => return (handle_command(editor, args), Vec::new()),
+    };
+    run_with_hooks(editor, args, path, &hooks::load_hooks())
+}
+
+fn run_with_hooks(
+    editor: &Editor,
+    args: EditorArgs,
+    path: PathBuf,
+    configured: &[HookConfig],
+) -> (Result<EditorOperationResult, String>, Vec<HookOutcome>) {
+    let root = match hooks::hook_root() {
+        Some(root) if !configured.is_empty() => root,
+        _ => return (handle_command(editor, args), Vec::new()),
+    };
+    let command = args.command.clone();
+    let target = HookTarget { command: &command, path: &path, project_root: &root };
+
+    let mut outcomes = hooks::run_hooks(configured, HookStage::Pre, &target, None);
+    if let Some(error) = hooks::blocking_error(&outcomes) {
+        return (Err(error), outcomes);
+    }
+
+    let run_post = !command.is_file_operation() && hooks::has_hooks(configured, HookStage::Post, &target);
+    let before = if run_post { fs::read_to_string(&path).unwrap_or_default() } else { String::new() };
+    let result = handle_command(editor, args);
+    if is_applied(&result) && run_post {
+        let after = fs::read_to_string(&path).unwrap_or_default();
+        let diff = diff::unified_diff(&target.rel_path(), &before, &after);
+        outcomes.extend(hooks::run_hooks(configured, HookStage::Post, &target, Some(&diff)));
+    }
+    (result, outcomes)
+}
+
+// Whether a command succeeded and wasn't held back by the guardrails
+fn is_applied(result: &Result<EditorOperationResult, String>) -> bool {
+    matches!(result, Ok(r) if !matches!(r, EditorOperationResult::ConfirmationRequired(_) | EditorOperationResult::Conflict(_)))
 }
 
-pub fn handle_command(editor: &mut Editor, args: EditorArgs) -> Result<EditorOperationResult, String> {
+fn dispatch_command(editor: &Editor, args: EditorArgs) -> Result<EditorOperationResult, String> {
+    if args.dry_run && !args.command.supports_dry_run() {
+        return Err(format!(
+            "Error: 'dry_run' is only supported for create, str_replace and insert, not '{}'.",
+            args.command.as_str()
+        ));
+    }
+    if let Some(conflict) = stale_edit(&args) {
+        return Ok(EditorOperationResult::Conflict(conflict));
+    }
     match args.command {
         CommandType::View => {
             if let Some(target_paths) = args.paths {
@@ -96,34 +682,94 @@ pub fn handle_command(editor: &mut Editor, args: EditorArgs) -> Result<EditorOpe
                 if target_paths.is_empty(){
                     return Err("Error: For 'view' command with 'paths', the list cannot be empty.".to_string());
                 }
-                view_multiple_files(&target_paths, args.view_range).map(EditorOperationResult::Multi)
+                view_multiple_files(&target_paths, args.view_range, args.encoding).map(EditorOperationResult::Multi)
             } else if let Some(target_path_str) = args.path {
                 let path_buf = PathBuf::from(&target_path_str);
-                view_file(&path_buf, args.view_range).map(EditorOperationResult::Single)
+                if args.view_offset.is_some() || args.view_length.is_some() {
+                    if args.view_range.is_some() {
+                        return Err("Error: Use either 'view_range' or 'view_offset'/'view_length', not both.".to_string());
+                    }
+                    let offset = args.view_offset.unwrap_or(0);
+                    return view_byte_chunk(&path_buf, offset, args.view_length, args.encoding).map(EditorOperationResult::Chunk);
+                }
+                let too_large = fs::metadata(&path_buf).is_ok_and(|m| m.is_file() && m.len() > max_view_bytes());
+                match args.view_range {
+                    Some(range) if too_large => view_line_window(&path_buf, &range, args.encoding).map(EditorOperationResult::Chunk),
+                    view_range => view_file(&path_buf, view_range, args.encoding).map(EditorOperationResult::Single),
+                }
             } else {
                 Err("Error: 'path' or 'paths' is required for 'view' command.".to_string())
             }
         }
-        CommandType::Create => {
-            let target_path_str = args.path.ok_or_else(|| "Error: 'path' is required for 'create' command.".to_string())?;
+        CommandType::Create | CommandType::StrReplace | CommandType::Insert | CommandType::ReplaceRange => {
+            let plan = plan_text_edit(&args)?;
+            finish_write(editor, plan, args.dry_run, args.override_guardrails)
+        }
+        CommandType::UndoEdit => {
+            let path = args.path.as_deref().map(Path::new);
+            undo_edits(editor, path, args.count.unwrap_or(1)).map(EditorOperationResult::Single)
+        }
+        CommandType::RedoEdit => {
+            let path = args.path.as_deref().map(Path::new);
+            redo_edits(editor, path, args.count.unwrap_or(1)).map(EditorOperationResult::Single)
+        }
+        CommandType::DeleteFile => {
+            let target_path_str = args.path.ok_or_else(|| "Error: 'path' is required for 'delete_file' command.".to_string())?;
             let path_buf = PathBuf::from(&target_path_str);
-            let content = args.file_text.ok_or_else(|| {
+            require_plain_file(&path_buf, "delete_file")?;
+            if !args.override_guardrails {
+                // Binary files have no lines to count
+                let before = fs::read_to_string(&path_buf).unwrap_or_default();
+                let diff = unified_diff(&path_buf, &before, "");
+                let violations = guardrails::enforce(&path_buf, &before, None, &diff);
+                if !violations.is_empty() {
+                    let preview = EditPreview { diff, content: String::new(), creates_file: false };
+                    return Ok(EditorOperationResult::ConfirmationRequired(GuardrailBlock { violations, preview }));
+                }
+            }
+            apply_changes(editor, &[FileChange::Delete { path: path_buf }])?;
+            Ok(EditorOperationResult::Single(None))
+        }
+        CommandType::Move | CommandType::Copy => {
+            let name = args.command.as_str();
+            let source_str = args.path.ok_or_else(|| format!("Error: 'path' is required for '{}' command.", name))?;
+            let destination_str = args.new_path.ok_or_else(|| format!("Error: 'new_path' is required for '{}' command.", name))?;
+            let source = PathBuf::from(&source_str);
+            let destination = PathBuf::from(&destination_str);
+            require_plain_file(&source, name)?;
+            if destination.exists() {
+                return Err(format!("Error: '{}' already exists. Delete it first or pick another 'new_path'.", destination.display()));
+            }
+            let content = fs::read(&source).map_err(|e| format!("Error reading file '{}': {}", source.display(), e))?;
+            let mut changes = vec![FileChange::Write { path: destination, content }];
+            if args.command == CommandType::Move {
+                changes.push(FileChange::Delete { path: source });
+            }
+            apply_changes(editor, &changes)?;
+            Ok(EditorOperationResult::Single(None))
+        }
+    }
+}
+
+// Plans create, str_replace, insert and replace_range
+fn plan_text_edit(args: &EditorArgs) -> Result<PlannedWrite, String> {
+    let name = args.command.as_str();
+    let path = args.path.as_deref().map(Path::new).ok_or_else(|| format!("Error: 'path' is required for '{}' command.", name))?;
+    match args.command {
+        CommandType::Create => {
+            let content = args.file_text.as_deref().ok_or_else(|| {
                 "Error: 'file_text' is required for 'create' command.".to_string()
             })?;
-            create_file(editor, &path_buf, &content).map(EditorOperationResult::Single)
+            plan_create(path, content, args.encoding)
         }
         CommandType::StrReplace => {
-            let target_path_str = args.path.ok_or_else(|| "Error: 'path' is required for 'str_replace' command.".to_string())?;
-            let path_buf = PathBuf::from(&target_path_str);
-            let old_s = args.old_str.ok_or_else(|| {
+            let old_s = args.old_str.as_deref().ok_or_else(|| {
                 "Error: 'old_str' is required for 'str_replace' command.".to_string()
             })?;
-            let new_s = args.new_str.unwrap_or_default();
-            str_replace_in_file(editor, &path_buf, &old_s, &new_s).map(EditorOperationResult::Single)
+            let new_s = args.new_str.as_deref().unwrap_or_default();
+            plan_str_replace(path, old_s, new_s, args.use_regex, args.max_replacements, args.encoding)
         }
         CommandType::Insert => {
-            let target_path_str = args.path.ok_or_else(|| "Error: 'path' is required for 'insert' command.".to_string())?;
-            let path_buf = PathBuf::from(&target_path_str);
             let line_num_1_indexed = args.insert_line.ok_or_else(|| {
                 "Error: 'insert_line' is required for 'insert' command.".to_string()
             })?;
@@ -132,14 +778,454 @@ pub fn handle_command(editor: &mut Editor, args: EditorArgs) -> Result<EditorOpe
             }
             let new_s = args
                 .new_str
+                .as_deref()
                 .ok_or_else(|| "Error: 'new_str' is required for 'insert' command.".to_string())?;
-            insert_into_file(editor, &path_buf, line_num_1_indexed - 1, &new_s).map(EditorOperationResult::Single)
+            plan_insert(path, line_num_1_indexed - 1, new_s, args.encoding)
         }
-        CommandType::UndoEdit => undo_last_edit(editor).map(EditorOperationResult::Single),
+        CommandType::ReplaceRange => {
+            let range = args.range.ok_or_else(|| {
+                "Error: 'start_line', 'start_column', 'end_line' and 'end_column' are required for 'replace_range' command.".to_string()
+            })?;
+            // Checked against the file in stale_edit()
+            if args.expected_hash.is_none() {
+                return Err("Error: 'expected_hash' is required for 'replace_range' command. View the file first to obtain it.".to_string());
+            }
+            plan_replace_range(path, range, args.new_str.as_deref().unwrap_or_default(), args.encoding)
+        }
+        _ => Err(format!("Error: '{}' doesn't edit text.", name)),
     }
 }
 
-fn view_file_core(path: &Path, view_range: Option<Vec<isize>>) -> Result<Option<String>, String> {
+/// A mutation whose `expected_hash` no longer matches the file, nothing written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditConflict {
+    pub expected_hash: String,
+    /// `content_hash()` of the file now, `None` when it doesn't exist
+    pub current_hash: Option<String>,
+    /// What the mutation would do to the current content; empty for moves and copies, and
+    /// for edits that no longer apply
+    pub diff: String,
+}
+
+// The file's content as `view` shows it, lossily decoded when that fails
+fn current_content(path: &Path, encoding: TextEncoding) -> Option<String> {
+    if !path.is_file() {
+        return None;
+    }
+    read_text(path, encoding)
+        .ok()
+        .or_else(|| fs::read(path).ok().map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+// Another writer (a person, HMR codegen) changed the file since the caller hashed it
+fn stale_edit(args: &EditorArgs) -> Option<EditConflict> {
+    let expected = args.expected_hash.as_deref().filter(|_| args.command.checks_expected_hash())?;
+    let path = Path::new(args.path.as_deref()?);
+    let current = current_content(path, args.encoding);
+    let current_hash = current.as_deref().map(content_hash);
+    if current_hash.as_deref() == Some(expected) {
+        return None;
+    }
+    let diff = match args.command {
+        CommandType::DeleteFile => current.map(|content| unified_diff(path, &content, "")).unwrap_or_default(),
+        CommandType::Move | CommandType::Copy => String::new(),
+        _ => plan_text_edit(args).map(|plan| plan.preview().diff).unwrap_or_default(),
+    };
+    Some(EditConflict { expected_hash: expected.to_string(), current_hash, diff })
+}
+
+// File operations work on single files; whole directories are moved through the refactor API,
+// which also rewrites their imports
+fn require_plain_file(path: &Path, command: &str) -> Result<(), String> {
+    if path.is_dir() {
+        return Err(format!(
+            "Error: '{}' is a directory. '{}' only works on files; use POST /api/refactor/move-file to move directories.",
+            path.display(),
+            command
+        ));
+    }
+    if !path.is_file() {
+        return Err(format!("Error: File not found: {}", path.display()));
+    }
+    Ok(())
+}
+
+/// A file write computed by a mutation, not yet applied.
+#[derive(Debug, Clone, PartialEq)]
+struct PlannedWrite {
+    path: PathBuf,
+    // Current content, `None` when the file doesn't exist yet
+    original: Option<Vec<u8>>,
+    // Current content as decoded text, empty for a new file
+    before: String,
+    content: String,
+    // How `content` is encoded when written
+    format: TextFormat,
+}
+
+impl PlannedWrite {
+    fn preview(&self) -> EditPreview {
+        let diff = unified_diff(&self.path, &self.before, &self.content);
+        EditPreview { diff, content: self.content.clone(), creates_file: self.original.is_none() }
+    }
+
+    // Writes the file (with any missing parent directories) and records it for undo
+    fn apply(self, editor: &Editor) -> Result<(), String> {
+        let bytes = self
+            .format
+            .encode(&self.content)
+            .map_err(|e| format!("Error: Cannot write '{}': {}", self.path.display(), e))?;
+        if self.original.as_deref() == Some(bytes.as_slice()) {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent().filter(|p| !p.exists()) {
+            fs::create_dir_all(parent).map_err(|e| {
+                format!("Error creating parent directories for '{}': {}", self.path.display(), e)
+            })?;
+        }
+        fs::write(&self.path, &bytes)
+            .map_err(|e| format!("Error writing file '{}': {}", self.path.display(), e))?;
+        editor.history().record_write_op(&self.path, self.original);
+        Ok(())
+    }
+}
+
+/// What a dry run of a mutation would do to its file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditPreview {
+    /// Unified diff from the current content, empty when nothing would change
+    pub diff: String,
+    /// Content the file would have after the mutation
+    pub content: String,
+    /// Whether the mutation would create the file
+    pub creates_file: bool,
+}
+
+// Path shown in diffs: relative to the project root when inside it
+fn display_path(path: &Path) -> String {
+    hooks::hook_root()
+        .and_then(|root| path.strip_prefix(root).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Unified diff of an edit to `path`, labelled with its project-relative path. Empty when the
+/// content didn't change.
+pub fn unified_diff(path: &Path, before: &str, after: &str) -> String {
+    if before == after {
+        return String::new();
+    }
+    diff::unified_diff(&display_path(path), before, after)
+}
+
+/// Lines (1-indexed, in the new content) a unified diff adds or changes. A deletion with
+/// nothing added in its place marks the line that now sits where the deleted text was.
+pub fn changed_lines(diff: &str) -> Vec<usize> {
+    fn mark(lines: &mut Vec<usize>, line: usize) {
+        let line = line.max(1);
+        if lines.last() != Some(&line) {
+            lines.push(line);
+        }
+    }
+
+    let mut lines = Vec::new();
+    // Next line number in the new content; `None` until the first hunk header
+    let mut new_line: Option<usize> = None;
+    let mut pending_deletion = false;
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("@@ ") {
+            if std::mem::take(&mut pending_deletion) {
+                mark(&mut lines, new_line.unwrap_or(1).saturating_sub(1));
+            }
+            // "@@ -a,b +c,d @@": the hunk's first line in the new content is c
+            let start = header
+                .split_whitespace()
+                .find_map(|part| part.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|n| n.parse::<usize>().ok());
+            new_line = Some(start.unwrap_or(1));
+            continue;
+        }
+        let Some(current) = new_line else { continue };
+        if line.starts_with('+') {
+            pending_deletion = false;
+            mark(&mut lines, current);
+            new_line = Some(current + 1);
+        } else if line.starts_with('-') {
+            pending_deletion = true;
+        } else if line.starts_with(' ') || line.is_empty() {
+            if std::mem::take(&mut pending_deletion) {
+                mark(&mut lines, current);
+            }
+            new_line = Some(current + 1);
+        }
+    }
+    if pending_deletion {
+        mark(&mut lines, new_line.unwrap_or(1).saturating_sub(1));
+    }
+    lines
+}
+
+fn finish_write(
+    editor: &Editor,
+    plan: PlannedWrite,
+    dry_run: bool,
+    override_guardrails: bool,
+) -> Result<EditorOperationResult, String> {
+    if dry_run {
+        return Ok(EditorOperationResult::Preview(plan.preview()));
+    }
+    // New files have nothing to lose
+    if plan.original.is_some() && !override_guardrails {
+        let preview = plan.preview();
+        let violations = guardrails::enforce(&plan.path, &plan.before, Some(&plan.content), &preview.diff);
+        if !violations.is_empty() {
+            return Ok(EditorOperationResult::ConfirmationRequired(GuardrailBlock { violations, preview }));
+        }
+    }
+    plan.apply(editor)?;
+    Ok(EditorOperationResult::Single(None)) // Mutations themselves don't return content
+}
+
+/// One file of a multi-file change.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileChange {
+    /// Creates or overwrites the file, creating missing parent directories
+    Write { path: PathBuf, content: Vec<u8> },
+    Delete { path: PathBuf },
+}
+
+impl FileChange {
+    pub fn path(&self) -> &Path {
+        match self {
+            FileChange::Write { path, .. } | FileChange::Delete { path } => path,
+        }
+    }
+
+    fn apply(&self) -> Result<(), String> {
+        match self {
+            FileChange::Write { path, content } => {
+                if let Some(parent) = path.parent().filter(|p| !p.exists()) {
+                    fs::create_dir_all(parent).map_err(|e| {
+                        format!("Error creating parent directories for '{}': {}", path.display(), e)
+                    })?;
+                }
+                fs::write(path, content).map_err(|e| format!("Error writing file '{}': {}", path.display(), e))
+            }
+            FileChange::Delete { path } => {
+                fs::remove_file(path).map_err(|e| format!("Error deleting file '{}': {}", path.display(), e))
+            }
+        }
+    }
+}
+
+/// Applies `changes` in order, all or nothing: when one fails, the files already changed are
+/// restored and the error is returned. On success every change is one entry in the undo
+/// history, so `undo_edit` without a path and a `count` of the returned number reverts them all.
+pub fn apply_changes(editor: &Editor, changes: &[FileChange]) -> Result<usize, String> {
+    let mut applied: Vec<FileSnapshot> = Vec::with_capacity(changes.len());
+    for change in changes {
+        let result = FileSnapshot::capture(change.path()).and_then(|snapshot| change.apply().map(|_| snapshot));
+        match result {
+            Ok(snapshot) => applied.push(snapshot),
+            Err(e) => {
+                for snapshot in applied.iter().rev() {
+                    if let Err(restore_error) = snapshot.restore() {
+                        tracing::warn!(target: "dev_operation::editor", path = %snapshot.path().display(), error = %restore_error, "Failed to roll back a file of a multi-file change.");
+                    }
+                }
+                return Err(e);
+            }
+        }
+    }
+    let created = applied.iter().filter(|s| matches!(s, FileSnapshot::Create { .. })).map(|s| s.path().to_path_buf()).collect();
+    let changed = applied.iter().filter(|s| matches!(s, FileSnapshot::Overwrite { .. })).map(|s| s.path().to_path_buf()).collect();
+    lsp_pool::files_edited(EditedFiles::Paths { changed, created });
+    let count = applied.len();
+    let mut history = editor.history();
+    let seq = history.allocate_seq();
+    for snapshot in applied {
+        let key = history_key(snapshot.path());
+        history.histories.entry(key.clone()).or_default().redo.clear();
+        history.push_entry(&key, HistoryDirection::Undo, seq, snapshot);
+    }
+    Ok(count)
+}
+
+/// Applies changes computed by a tool (a formatter, lint autofixes) through `apply_changes`,
+/// recorded as `command` and charged like editor commands, one edit per file.
+pub fn apply_tool_changes(editor: &Editor, command: &str, changes: &[FileChange]) -> Result<(), String> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    apply_changes(editor, changes)?;
+    record_edit(command, changes[0].path());
+    quotas::charge(QuotaMetric::EditsPerHour, changes.len() as f64);
+    Ok(())
+}
+
+// Queues an edit of `path` for the edit history
+fn record_edit(command: &str, path: &Path) {
+    let (command, path) = (command.to_string(), path.to_string_lossy().into_owned());
+    db::write_later(move |db| {
+        if let Err(e) = db.record_edit(events::session_id(), &command, Some(&path)) {
+            tracing::debug!(target: "dev_operation::editor", error = ?e, "Failed to record edit history.");
+        }
+    });
+}
+
+// Directory operations are recorded and charged like editor commands
+fn record_dir_operation(command: &str, path: &Path) {
+    record_edit(command, path);
+    quotas::charge(QuotaMetric::EditsPerHour, 1.0);
+}
+
+/// Creates a directory and any missing parents. Returns whether it didn't exist yet.
+pub fn create_dir(path: &Path) -> Result<bool, String> {
+    if path.is_dir() {
+        return Ok(false);
+    }
+    if path.exists() {
+        return Err(format!("Error: '{}' exists and is not a directory.", path.display()));
+    }
+    fs::create_dir_all(path).map_err(|e| format!("Error creating directory '{}': {}", path.display(), e))?;
+    record_dir_operation("create_dir", path);
+    Ok(true)
+}
+
+/// `apply_tool_changes` through the shared editor, under the locks of the changed files. Hands
+/// the changes back for the caller's response.
+pub async fn apply_shared_tool_changes(command: &'static str, changes: Vec<FileChange>) -> Result<Vec<FileChange>, String> {
+    let paths = changes.iter().map(|c| c.path().to_path_buf()).collect();
+    with_files(Some(paths), move |editor| apply_tool_changes(editor, command, &changes).map(|_| changes)).await?
+}
+
+/// Removes a directory. Without `recursive` it must be empty; with it, its files are deleted
+/// through `apply_changes`, so `undo_edit` without a path and a `count` of the returned number
+/// brings them back (empty subdirectories aren't restored). Symlinks are never followed: a
+/// symlinked `path` is refused and links inside it are unlinked, not restored by undo. Returns
+/// the number of files deleted.
+pub fn remove_dir(editor: &Editor, path: &Path, recursive: bool) -> Result<usize, String> {
+    if !fs::symlink_metadata(path).is_ok_and(|m| m.is_dir()) {
+        return Err(format!("Error: '{}' is not a directory.", path.display()));
+    }
+    if !recursive {
+        fs::remove_dir(path).map_err(|e| {
+            format!("Error removing directory '{}': {}. Set 'recursive' to remove it with its contents.", path.display(), e)
+        })?;
+        record_dir_operation("remove_dir", path);
+        return Ok(0);
+    }
+    let mut files = Vec::new();
+    let mut links = Vec::new();
+    let mut dirs = Vec::new();
+    // Entry types come from symlink_metadata, so a link to a directory isn't descended into
+    for entry in walkdir::WalkDir::new(path).follow_links(false) {
+        let entry = entry.map_err(|e| format!("Error reading directory '{}': {}", path.display(), e))?;
+        if entry.file_type().is_symlink() {
+            links.push(entry.into_path());
+        } else if entry.file_type().is_dir() {
+            dirs.push(entry.into_path());
+        } else {
+            files.push(FileChange::Delete { path: entry.into_path() });
+        }
+    }
+    if files.len() > MAX_REMOVED_FILES {
+        return Err(format!(
+            "Error: '{}' holds {} files, more than the {} that can be removed (and undone) at once.",
+            path.display(),
+            files.len(),
+            MAX_REMOVED_FILES
+        ));
+    }
+    if !files.is_empty() {
+        apply_changes(editor, &files)?;
+    }
+    for link in links {
+        fs::remove_file(&link).map_err(|e| format!("Error removing symlink '{}': {}", link.display(), e))?;
+    }
+    // Deepest first, so every directory is empty by the time it is removed
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+    for dir in dirs {
+        fs::remove_dir(&dir).map_err(|e| format!("Error removing directory '{}': {}", dir.display(), e))?;
+    }
+    record_dir_operation("remove_dir", path);
+    Ok(files.len())
+}
+
+/// Stable hash of a file's content (64-bit FNV-1a, hex encoded).
+///
+/// Used to detect that a file changed between an agent viewing it and editing it.
+pub fn content_hash(content: &str) -> String {
+    util::fnv1a_hex(content.as_bytes())
+}
+
+// Converts a (1-indexed line, 0-indexed character column) position into a byte offset.
+// Line terminators are not addressable, so the column is bounded by the line's visible length.
+fn position_to_byte_offset(content: &str, line: usize, column: usize) -> Result<usize, String> {
+    if line == 0 {
+        return Err("Error: Line numbers in 'replace_range' are 1-indexed.".to_string());
+    }
+    let mut line_start = 0;
+    for _ in 1..line {
+        match content[line_start..].find('\n') {
+            Some(idx) => line_start += idx + 1,
+            None => {
+                return Err(format!(
+                    "Error: Line {} is beyond the end of file ({} lines).",
+                    line,
+                    content.split('\n').count()
+                ))
+            }
+        }
+    }
+    let line_end = content[line_start..]
+        .find('\n')
+        .map_or(content.len(), |idx| line_start + idx);
+    let line_text = content[line_start..line_end].trim_end_matches('\r');
+
+    let line_chars = line_text.chars().count();
+    if column > line_chars {
+        return Err(format!(
+            "Error: Column {} is out of bounds for line {} ({} characters).",
+            column, line, line_chars
+        ));
+    }
+    let byte_in_line = line_text
+        .char_indices()
+        .nth(column)
+        .map_or(line_text.len(), |(idx, _)| idx);
+    Ok(line_start + byte_in_line)
+}
+
+// Reads a file as the text the editor works on, with the bytes read and the format to write
+// edits back with
+fn read_decoded(path: &Path, encoding: TextEncoding) -> Result<(Vec<u8>, String, TextFormat), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Error reading file '{}': {}", path.display(), e))?;
+    let (text, format) =
+        TextFormat::decode(&bytes, encoding).map_err(|e| format!("Error: File '{}' is {}", path.display(), e))?;
+    Ok((bytes, text, format))
+}
+
+/// The text of a file as `view` shows it: decoded with `encoding`, without a BOM, and with
+/// `\n` line endings when the file uses CRLF throughout.
+pub fn read_text(path: &Path, encoding: TextEncoding) -> Result<String, String> {
+    read_decoded(path, encoding).map(|(_, text, _)| text)
+}
+
+// Base64 content is one opaque string, which line-based edits can't address
+fn require_text_encoding(encoding: TextEncoding, command: &str) -> Result<(), String> {
+    if encoding == TextEncoding::Base64 {
+        return Err(format!(
+            "Error: '{}' doesn't work on base64 content. Use 'view' and 'create' to read and write binary files.",
+            command
+        ));
+    }
+    Ok(())
+}
+
+fn view_file_core(path: &Path, view_range: Option<Vec<isize>>, encoding: TextEncoding) -> Result<Option<String>, String> {
     if !path.exists() {
         return Err(format!("Error: File not found at '{}'", path.display()));
     }
@@ -147,10 +1233,22 @@ fn view_file_core(path: &Path, view_range: Option<Vec<isize>>) -> Result<Option<
         return Err(format!("Error: Path '{}' is not a file.", path.display()));
     }
 
-    let file_content = fs::read_to_string(path)
-        .map_err(|e| format!("Error reading file '{}': {}", path.display(), e))?;
+    let size = fs::metadata(path).map_err(|e| format!("Error reading file '{}': {}", path.display(), e))?.len();
+    let max_bytes = max_view_bytes();
+    if size > max_bytes {
+        return Err(format!(
+            "Error: File '{}' is {} bytes, above the {}-byte view limit. View it in parts with 'view_range' or 'view_offset'/'view_length'.",
+            path.display(),
+            size,
+            max_bytes
+        ));
+    }
+    let file_content = read_text(path, encoding)?;
 
     match view_range {
+        Some(_) if encoding == TextEncoding::Base64 => {
+            Err("Error: 'view_range' cannot be used with the base64 encoding.".to_string())
+        }
         Some(range) => {
             if range.len() != 2 {
                 return Err("Error: 'view_range' must contain exactly two elements: [start_line, end_line].".to_string());
@@ -218,16 +1316,158 @@ fn view_file_core(path: &Path, view_range: Option<Vec<isize>>) -> Result<Option<
     }
 }
 
+/// Largest file `view` reads whole, and the most one chunk of a larger file returns, from
+/// `editor_max_view_bytes` in config.toml (default 5 MiB).
+pub fn max_view_bytes() -> u64 {
+    config_files::get_config_value("editor_max_view_bytes")
+        .and_then(|v| v.parse().ok())
+        .filter(|bytes: &u64| *bytes > 0)
+        .unwrap_or(DEFAULT_MAX_VIEW_BYTES)
+}
+
+// Reads `length` bytes (at most max_view_bytes()) from `offset`. UTF-8 chunks are moved to
+// character boundaries: continuation bytes at the start belong to the previous chunk, and a
+// character cut off at the end is left to the next one.
+fn view_byte_chunk(path: &Path, offset: u64, length: Option<u64>, encoding: TextEncoding) -> Result<FileChunk, String> {
+    require_plain_file(path, "view")?;
+    let read_error = |e: std::io::Error| format!("Error reading file '{}': {}", path.display(), e);
+    let mut file = fs::File::open(path).map_err(read_error)?;
+    let file_size = file.metadata().map_err(read_error)?.len();
+    if offset > file_size {
+        return Err(format!("Error: 'view_offset' {} is beyond the end of the file ({} bytes).", offset, file_size));
+    }
+    let length = length.unwrap_or(u64::MAX).min(max_view_bytes()).min(file_size - offset);
+    if length == 0 && offset < file_size {
+        return Err("Error: 'view_length' must be at least 1.".to_string());
+    }
+    let mut bytes = Vec::with_capacity(length as usize);
+    file.seek(SeekFrom::Start(offset)).map_err(read_error)?;
+    (&mut file).take(length).read_to_end(&mut bytes).map_err(read_error)?;
+
+    let (mut start, mut end) = (0, bytes.len());
+    if encoding == TextEncoding::Utf8 {
+        start = bytes.iter().take(3).take_while(|b| *b & 0xC0 == 0x80).count();
+        // A chunk shorter than its first character is extended to the end of it, so that
+        // paging with small lengths still advances by at least one character
+        while bytes.len() > start
+            && std::str::from_utf8(&bytes[start..]).is_err_and(|e| e.error_len().is_none() && e.valid_up_to() == 0)
+        {
+            let mut next = [0u8; 1];
+            if file.read(&mut next).map_err(read_error)? == 0 {
+                break;
+            }
+            bytes.push(next[0]);
+        }
+        end = bytes.len();
+        if let Err(e) = std::str::from_utf8(&bytes[start..]) {
+            if e.error_len().is_none() && offset + (bytes.len() as u64) < file_size {
+                end = start + e.valid_up_to();
+            }
+        }
+    }
+    let (content, _) = TextFormat::decode(&bytes[start..end], encoding)
+        .map_err(|e| format!("Error: Chunk of '{}' is {}", path.display(), e))?;
+    let chunk_offset = offset + start as u64;
+    let chunk_length = (end - start) as u64;
+    Ok(FileChunk {
+        content,
+        offset: chunk_offset,
+        length: chunk_length,
+        file_size,
+        total_lines: None,
+        lines: None,
+        has_more: chunk_offset + chunk_length < file_size,
+    })
+}
+
+// Streams a file too large to read whole for the lines of `view_range`, counting all its lines.
+// Reads in blocks rather than lines, so a huge single-line bundle doesn't end up in memory.
+fn view_line_window(path: &Path, view_range: &[isize], encoding: TextEncoding) -> Result<FileChunk, String> {
+    if view_range.len() != 2 {
+        return Err("Error: 'view_range' must contain exactly two elements: [start_line, end_line].".to_string());
+    }
+    let (start_line, end_line) = (view_range[0], view_range[1]);
+    if start_line <= 0 {
+        return Err("Error: Start line in 'view_range' must be positive.".to_string());
+    }
+    if end_line == 0 || (end_line != -1 && end_line < start_line) {
+        return Err(format!("Error: End line {} is invalid for start line {}.", end_line, start_line));
+    }
+    let (start_line, end_line) = (start_line as usize, (end_line > 0).then_some(end_line as usize));
+
+    let read_error = |e: std::io::Error| format!("Error reading file '{}': {}", path.display(), e);
+    let mut file = fs::File::open(path).map_err(read_error)?;
+    let max_bytes = max_view_bytes();
+    let mut block = vec![0u8; SCAN_BLOCK_BYTES];
+    let mut selected = Vec::new();
+    let mut window_offset = None;
+    let (mut line, mut position, mut last_byte) = (1, 0u64, None);
+    loop {
+        let read = file.read(&mut block).map_err(read_error)?;
+        if read == 0 {
+            break;
+        }
+        for &byte in &block[..read] {
+            if line >= start_line && end_line.is_none_or(|end| line <= end) {
+                if selected.len() as u64 == max_bytes {
+                    return Err(format!(
+                        "Error: Lines from {} of '{}' are above the {}-byte view limit. Request fewer lines, or use 'view_offset'/'view_length'.",
+                        start_line,
+                        path.display(),
+                        max_bytes
+                    ));
+                }
+                window_offset.get_or_insert(position);
+                selected.push(byte);
+            }
+            if byte == b'\n' {
+                line += 1;
+            }
+            position += 1;
+        }
+        last_byte = Some(block[read - 1]);
+    }
+    // A final line break doesn't start another line, as with `str::lines`
+    let total_lines = if last_byte == Some(b'\n') { line - 1 } else { line };
+    if start_line > total_lines {
+        return Err(format!(
+            "Error: Start line {} is beyond the end of file ({} lines).",
+            start_line, total_lines
+        ));
+    }
+    let last_line = end_line.map_or(total_lines, |end| end.min(total_lines));
+    let length = selected.len() as u64;
+    // Lines are shown joined by line breaks, without the last one's terminator
+    if selected.last() == Some(&b'\n') {
+        selected.pop();
+        if selected.last() == Some(&b'\r') {
+            selected.pop();
+        }
+    }
+    let (content, _) = TextFormat::decode(&selected, encoding)
+        .map_err(|e| format!("Error: Lines {}-{} of '{}' are {}", start_line, last_line, path.display(), e))?;
+    let offset = window_offset.unwrap_or(position);
+    Ok(FileChunk {
+        content,
+        offset,
+        length,
+        file_size: position,
+        total_lines: Some(total_lines),
+        lines: Some((start_line, last_line)),
+        has_more: last_line < total_lines,
+    })
+}
+
 // Wrapper for view_file_core to match expected signature in handle_command for single file views
-fn view_file(path: &Path, view_range: Option<Vec<isize>>) -> Result<Option<String>, String> {
-    view_file_core(path, view_range)
+fn view_file(path: &Path, view_range: Option<Vec<isize>>, encoding: TextEncoding) -> Result<Option<String>, String> {
+    view_file_core(path, view_range, encoding)
 }
 
-fn view_multiple_files(paths: &[String], view_range: Option<Vec<isize>>) -> Result<Vec<MultiFileViewOutput>, String> {
+fn view_multiple_files(paths: &[String], view_range: Option<Vec<isize>>, encoding: TextEncoding) -> Result<Vec<MultiFileViewOutput>, String> {
     let mut results = Vec::new();
     for path_str in paths {
         let path_buf = PathBuf::from(path_str);
-        match view_file_core(&path_buf, view_range.clone()) { // Use core logic
+        match view_file_core(&path_buf, view_range.clone(), encoding) { // Use core logic
             Ok(Some(content)) => {
                 let line_count = Some(content.lines().count());
                 results.push(MultiFileViewOutput {
@@ -258,7 +1498,7 @@ fn view_multiple_files(paths: &[String], view_range: Option<Vec<isize>>) -> Resu
     Ok(results)
 }
 
-fn create_file(editor: &mut Editor, path: &Path, content: &str) -> Result<Option<String>, String> {
+fn plan_create(path: &Path, content: &str, encoding: TextEncoding) -> Result<PlannedWrite, String> {
     let original_content = if path.exists() {
         if path.is_dir() {
             return Err(format!(
@@ -277,32 +1517,49 @@ fn create_file(editor: &mut Editor, path: &Path, content: &str) -> Result<Option
         None
     };
 
-    // Create parent directories if they don't exist
-    if let Some(parent) = path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| {
-                format!(
-                    "Error creating parent directories for '{}': {}",
-                    path.display(),
-                    e
-                )
-            })?;
-        }
+    // An overwritten file keeps its BOM and line endings; one that isn't text in `encoding`
+    // is simply replaced
+    let (before, existing_format) = match &original_content {
+        Some(bytes) => TextFormat::decode(bytes, encoding)
+            .unwrap_or_else(|_| (String::from_utf8_lossy(bytes).into_owned(), TextFormat::new(encoding))),
+        None => (String::new(), TextFormat::new(encoding)),
+    };
+    if encoding == TextEncoding::Base64 {
+        let format = TextFormat::new(encoding);
+        format.encode(content).map_err(|e| format!("Error: 'file_text' {}", e))?;
+        return Ok(PlannedWrite { path: path.to_path_buf(), original: original_content, before, content: content.to_string(), format });
     }
 
-    fs::write(path, content)
-        .map_err(|e| format!("Error writing file '{}': {}", path.display(), e))?;
+    // Match project conventions from .editorconfig, if any apply to this path
+    let settings = editorconfig::resolve_for_path(path)?;
+    let content = if settings.is_empty() {
+        content.to_string()
+    } else {
+        editorconfig::apply_to_content(&editorconfig::apply_indentation(content, &settings), &settings)
+    };
+    // The text may carry its own BOM and CRLF endings, from the caller or from .editorconfig
+    let (content, mut format) = TextFormat::decode(content.as_bytes(), TextEncoding::Utf8)?;
+    format.encoding = encoding;
+    format.bom &= encoding == TextEncoding::Utf8;
+    if settings.charset.is_none() {
+        format.bom |= existing_format.bom;
+    }
+    if settings.end_of_line.is_none() && !content.contains('\r') {
+        format.crlf |= existing_format.crlf;
+    }
 
-    editor.record_write_op(path, original_content);
-    Ok(None) // Create operation itself doesn't return content
+    Ok(PlannedWrite { path: path.to_path_buf(), original: original_content, before, content, format })
 }
 
-fn str_replace_in_file(
-    editor: &mut Editor,
+fn plan_str_replace(
     path: &Path,
     old_str: &str,
     new_str: &str,
-) -> Result<Option<String>, String> {
+    use_regex: bool,
+    max_replacements: Option<usize>,
+    encoding: TextEncoding,
+) -> Result<PlannedWrite, String> {
+    require_text_encoding(encoding, "str_replace")?;
     if !path.exists() {
         return Err(format!("Error: File not found at '{}'", path.display()));
     }
@@ -312,30 +1569,45 @@ fn str_replace_in_file(
     if old_str.is_empty() {
         return Err("Error: 'old_str' for replacement cannot be empty.".to_string());
     }
+    if max_replacements == Some(0) {
+        return Err("Error: 'max_replacements' must be at least 1.".to_string());
+    }
+    let pattern = if use_regex {
+        let re = regex::Regex::new(old_str).map_err(|e| format!("Error: 'old_str' is not a valid regex: {}", e))?;
+        // A pattern matching nothing would insert `new_str` between every character
+        if re.is_match("") {
+            return Err("Error: 'old_str' regex must not match the empty string.".to_string());
+        }
+        Some(re)
+    } else {
+        None
+    };
 
-    let original_content_bytes =
-        fs::read(path).map_err(|e| format!("Error reading file '{}': {}", path.display(), e))?;
-
-    let original_content_str = String::from_utf8(original_content_bytes.clone())
-        .map_err(|e| format!("Error: File '{}' is not valid UTF-8: {}", path.display(), e))?;
-
-    let modified_content = original_content_str.replace(old_str, new_str);
+    let (original_content_bytes, original_content_str, format) = read_decoded(path, encoding)?;
 
-    if modified_content != original_content_str {
-        fs::write(path, &modified_content)
-            .map_err(|e| format!("Error writing to file '{}': {}", path.display(), e))?;
-        editor.record_write_op(path, Some(original_content_bytes));
-    }
+    let modified_content = match (&pattern, max_replacements) {
+        // A limit of 0 makes `replacen` replace every match
+        (Some(re), limit) => re.replacen(&original_content_str, limit.unwrap_or(0), new_str).into_owned(),
+        (None, Some(limit)) => original_content_str.replacen(old_str, new_str, limit),
+        (None, None) => original_content_str.replace(old_str, new_str),
+    };
 
-    Ok(None) // StrReplace operation itself doesn't return content
+    Ok(PlannedWrite {
+        path: path.to_path_buf(),
+        original: Some(original_content_bytes),
+        before: original_content_str,
+        content: modified_content,
+        format,
+    })
 }
 
-fn insert_into_file(
-    editor: &mut Editor,
+fn plan_insert(
     path: &Path,
     insert_line_0_indexed: usize,
     text_to_insert: &str,
-) -> Result<Option<String>, String> {
+    encoding: TextEncoding,
+) -> Result<PlannedWrite, String> {
+    require_text_encoding(encoding, "insert")?;
     if !path.exists() {
         return Err(format!(
             "Error: File not found at '{}' for insert operation.",
@@ -346,10 +1618,7 @@ fn insert_into_file(
         return Err(format!("Error: Path '{}' is not a file.", path.display()));
     }
 
-    let original_content_bytes =
-        fs::read(path).map_err(|e| format!("Error reading file '{}': {}", path.display(), e))?;
-    let original_content_str = String::from_utf8(original_content_bytes.clone())
-        .map_err(|e| format!("Error: File '{}' is not valid UTF-8: {}", path.display(), e))?;
+    let (original_content_bytes, original_content_str, mut format) = read_decoded(path, encoding)?;
 
     let mut lines: Vec<String> = original_content_str.lines().map(String::from).collect();
 
@@ -360,71 +1629,80 @@ fn insert_into_file(
         ));
     }
 
-    if lines.is_empty() && insert_line_0_indexed == 0 {
-        lines.push(text_to_insert.to_string());
-    } else if insert_line_0_indexed == lines.len() {
-        lines.push(text_to_insert.to_string());
+    let settings = editorconfig::resolve_for_path(path)?;
+    let text_to_insert = editorconfig::apply_to_inserted_text(text_to_insert, &settings);
+    // Keep the file's existing line endings unless .editorconfig says otherwise. The text is
+    // joined with `\n` like the decoded content, and `TextFormat::encode` writes CRLF, so the
+    // diff only shows the inserted line.
+    let eol = settings
+        .end_of_line
+        .map(|e| e.as_str())
+        .unwrap_or_else(|| if format.crlf { "\r\n" } else { editorconfig::detect_eol(&original_content_str) });
+    format.crlf = eol == "\r\n";
+
+    if insert_line_0_indexed == lines.len() {
+        lines.push(text_to_insert);
     } else {
-        lines.insert(insert_line_0_indexed + 1, text_to_insert.to_string());
+        lines.insert(insert_line_0_indexed + 1, text_to_insert);
     }
 
     let mut modified_content = lines.join("\n");
-    if !original_content_str.is_empty()
-        && original_content_str.ends_with('\n')
-        && !lines.is_empty()
-        && !modified_content.ends_with('\n')
-    {
+    let wants_final_newline = settings
+        .insert_final_newline
+        .unwrap_or(!original_content_str.is_empty() && original_content_str.ends_with('\n'));
+    if wants_final_newline && !lines.is_empty() && !modified_content.ends_with('\n') {
         modified_content.push('\n');
     }
 
-    if modified_content != original_content_str {
-        fs::write(path, &modified_content)
-            .map_err(|e| format!("Error writing to file '{}': {}", path.display(), e))?;
-        editor.record_write_op(path, Some(original_content_bytes));
+    Ok(PlannedWrite {
+        path: path.to_path_buf(),
+        original: Some(original_content_bytes),
+        before: original_content_str,
+        content: modified_content,
+        format,
+    })
+}
+
+fn plan_replace_range(path: &Path, range: TextRange, new_str: &str, encoding: TextEncoding) -> Result<PlannedWrite, String> {
+    require_text_encoding(encoding, "replace_range")?;
+    if !path.exists() {
+        return Err(format!("Error: File not found at '{}'", path.display()));
+    }
+    if !path.is_file() {
+        return Err(format!("Error: Path '{}' is not a file.", path.display()));
     }
 
-    Ok(None) // Insert operation itself doesn't return content
-}
+    let (original_content_bytes, original_content_str, format) = read_decoded(path, encoding)?;
 
-fn undo_last_edit(editor: &mut Editor) -> Result<Option<String>, String> {
-    match std::mem::replace(&mut editor.last_op, LastOperation::None) {
-        LastOperation::None => Err("Error: No operation to undo.".to_string()),
-        LastOperation::Create { path } => {
-            if path.exists() && path.is_file() {
-                fs::remove_file(&path).map_err(|e| {
-                    format!(
-                        "Error undoing creation (deleting file '{}'): {}",
-                        path.display(),
-                        e
-                    )
-                })?;
-            }
-            Ok(None)
-        }
-        LastOperation::Overwrite {
-            path,
-            original_content,
-        } => {
-            if path.is_dir() {
-                editor.last_op = LastOperation::Overwrite {
-                    path: path.clone(),
-                    original_content,
-                };
-                return Err(format!(
-                    "Error undoing overwrite: Path '{}' is a directory.",
-                    path.display()
-                ));
-            }
-            fs::write(&path, original_content).map_err(|e| {
-                format!(
-                    "Error undoing overwrite (writing original content to '{}'): {}",
-                    path.display(),
-                    e
-                )
-            })?;
-            Ok(None)
-        }
+    if (range.end_line, range.end_column) < (range.start_line, range.start_column) {
+        return Err(format!(
+            "Error: Range end {}:{} is before range start {}:{}.",
+            range.end_line, range.end_column, range.start_line, range.start_column
+        ));
     }
+    let start = position_to_byte_offset(&original_content_str, range.start_line, range.start_column)?;
+    let end = position_to_byte_offset(&original_content_str, range.end_line, range.end_column)?;
+
+    let mut modified_content = String::with_capacity(original_content_str.len() + new_str.len());
+    modified_content.push_str(&original_content_str[..start]);
+    modified_content.push_str(new_str);
+    modified_content.push_str(&original_content_str[end..]);
+
+    Ok(PlannedWrite {
+        path: path.to_path_buf(),
+        original: Some(original_content_bytes),
+        before: original_content_str,
+        content: modified_content,
+        format,
+    })
+}
+
+fn undo_edits(editor: &Editor, path: Option<&Path>, count: usize) -> Result<Option<String>, String> {
+    editor.history().step_history(path, count, HistoryDirection::Undo).map(|_| None)
+}
+
+fn redo_edits(editor: &Editor, path: Option<&Path>, count: usize) -> Result<Option<String>, String> {
+    editor.history().step_history(path, count, HistoryDirection::Redo).map(|_| None)
 }
 
 #[cfg(test)]
@@ -443,6 +1721,17 @@ mod tests {
             new_str: None,
             old_str: None,
             view_range: None,
+            range: None,
+            expected_hash: None,
+            count: None,
+            use_regex: false,
+            max_replacements: None,
+            dry_run: false,
+            new_path: None,
+            override_guardrails: false,
+            encoding: TextEncoding::Utf8,
+            view_offset: None,
+            view_length: None,
         }
     }
 
@@ -450,7 +1739,7 @@ mod tests {
     fn test_create_view_and_undo_create() {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("test_cvu.txt");
-        let mut editor = Editor::new();
+        let editor = Editor::new();
         let file_path_str = file_path.to_str().unwrap();
 
         // Create
@@ -458,13 +1747,13 @@ mod tests {
             file_text: Some("Hello\nWorld".to_string()),
             ..make_args_struct(CommandType::Create, file_path_str)
         };
-        handle_command(&mut editor, create_args).unwrap();
+        handle_command(&editor, create_args).unwrap();
         assert!(file_path.exists());
         assert_eq!(fs::read_to_string(&file_path).unwrap(), "Hello\nWorld");
 
         // View
         let view_args = make_args_struct(CommandType::View, file_path_str);
-        match handle_command(&mut editor, view_args).unwrap() {
+        match handle_command(&editor, view_args).unwrap() {
             EditorOperationResult::Single(Some(content)) => {
                 assert_eq!(content, "Hello\nWorld");
             }
@@ -472,20 +1761,20 @@ mod tests {
         }
 
         // Undo Create
-        let undo_args = make_args_struct(CommandType::UndoEdit, file_path_str); // Path in args not used by undo
-        handle_command(&mut editor, undo_args).unwrap();
+        let undo_args = make_args_struct(CommandType::UndoEdit, file_path_str); // Undoes the last edit of this file
+        handle_command(&editor, undo_args).unwrap();
         assert!(!file_path.exists());
 
         // Undo again (should fail)
         let undo_again_args = make_args_struct(CommandType::UndoEdit, file_path_str);
-        assert!(handle_command(&mut editor, undo_again_args).is_err());
+        assert!(handle_command(&editor, undo_again_args).is_err());
     }
 
     #[test]
     fn test_overwrite_and_undo() {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("test_ow.txt");
-        let mut editor = Editor::new();
+        let editor = Editor::new();
         let file_path_str = file_path.to_str().unwrap();
 
         fs::write(&file_path, "Original").unwrap();
@@ -495,12 +1784,12 @@ mod tests {
             file_text: Some("New Content".to_string()),
             ..make_args_struct(CommandType::Create, file_path_str)
         };
-        handle_command(&mut editor, overwrite_args).unwrap();
+        handle_command(&editor, overwrite_args).unwrap();
         assert_eq!(fs::read_to_string(&file_path).unwrap(), "New Content");
 
         // Undo Overwrite
         let undo_args = make_args_struct(CommandType::UndoEdit, file_path_str);
-        handle_command(&mut editor, undo_args).unwrap();
+        handle_command(&editor, undo_args).unwrap();
         assert_eq!(fs::read_to_string(&file_path).unwrap(), "Original");
     }
 
@@ -508,7 +1797,7 @@ mod tests {
     fn test_str_replace_and_undo() {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("test_sr.txt");
-        let mut editor = Editor::new();
+        let editor = Editor::new();
         let file_path_str = file_path.to_str().unwrap();
 
         fs::write(&file_path, "hello world, hello moon").unwrap();
@@ -519,7 +1808,7 @@ mod tests {
             new_str: Some("bye".to_string()),
             ..make_args_struct(CommandType::StrReplace, file_path_str)
         };
-        handle_command(&mut editor, replace_args).unwrap();
+        handle_command(&editor, replace_args).unwrap();
         assert_eq!(
             fs::read_to_string(&file_path).unwrap(),
             "bye world, bye moon"
@@ -527,18 +1816,95 @@ mod tests {
 
         // Undo Replace
         let undo_args = make_args_struct(CommandType::UndoEdit, file_path_str);
-        handle_command(&mut editor, undo_args).unwrap();
+        handle_command(&editor, undo_args).unwrap();
         assert_eq!(
             fs::read_to_string(&file_path).unwrap(),
             "hello world, hello moon"
         );
     }
 
+    #[test]
+    fn test_regex_str_replace() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("imports.ts");
+        let editor = Editor::new();
+        let file_path_str = file_path.to_str().unwrap();
+        fs::write(&file_path, "import a from './old/a';\nimport b from \"./old/b\";\nimport c from './old/c';\n").unwrap();
+
+        let regex_args = |old: &str, new: &str, max: Option<usize>| EditorArgs {
+            old_str: Some(old.to_string()),
+            new_str: Some(new.to_string()),
+            use_regex: true,
+            max_replacements: max,
+            ..make_args_struct(CommandType::StrReplace, file_path_str)
+        };
+        handle_command(&editor, regex_args(r#"(['"])\./old/(\w+)"#, "${1}@/new/$2", Some(2))).unwrap();
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "import a from '@/new/a';\nimport b from \"@/new/b\";\nimport c from './old/c';\n"
+        );
+
+        assert!(handle_command(&editor, regex_args("(unclosed", "", None)).unwrap_err().contains("not a valid regex"));
+        assert!(handle_command(&editor, regex_args("x*", "y", None)).unwrap_err().contains("empty string"));
+        assert!(handle_command(&editor, regex_args("a", "b", Some(0))).is_err());
+    }
+
+    #[test]
+    fn test_dry_run_leaves_file_untouched() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("preview.txt");
+        let editor = Editor::new();
+        let file_path_str = file_path.to_str().unwrap();
+        fs::write(&file_path, "alpha\nbeta\n").unwrap();
+
+        let preview_args = EditorArgs {
+            old_str: Some("beta".to_string()),
+            new_str: Some("gamma".to_string()),
+            dry_run: true,
+            ..make_args_struct(CommandType::StrReplace, file_path_str)
+        };
+        let preview = match handle_command(&editor, preview_args).unwrap() {
+            EditorOperationResult::Preview(preview) => preview,
+            other => panic!("expected a preview, got {:?}", other),
+        };
+        assert_eq!(preview.content, "alpha\ngamma\n");
+        assert!(preview.diff.contains("-beta\n+gamma"));
+        assert!(!preview.creates_file);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "alpha\nbeta\n");
+        assert_eq!(editor.history_len(None), (0, 0));
+
+        let new_file = dir.path().join("new/file.txt");
+        let create_args = EditorArgs {
+            file_text: Some("hi\n".to_string()),
+            dry_run: true,
+            ..make_args_struct(CommandType::Create, new_file.to_str().unwrap())
+        };
+        assert!(matches!(handle_command(&editor, create_args).unwrap(), EditorOperationResult::Preview(p) if p.creates_file));
+        assert!(!new_file.parent().unwrap().exists());
+
+        let undo_args = EditorArgs { dry_run: true, ..make_args_struct(CommandType::UndoEdit, file_path_str) };
+        assert!(handle_command(&editor, undo_args).is_err());
+    }
+
+    #[test]
+    fn test_changed_lines_from_diff() {
+        let before = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let after = "a\nB\nc\nd\ne\nf\ng\nh\nj\nk\nl\n";
+        let diff = unified_diff(Path::new("x.txt"), before, after);
+        assert!(diff.contains("+++ b/x.txt"));
+        // b changed on line 2, i was deleted (j moved up to line 9), k and l were appended
+        assert_eq!(changed_lines(&diff), vec![2, 9, 10, 11]);
+
+        let removed = unified_diff(Path::new("x.txt"), "a\n-- note\nb\n", "a\nb\n");
+        assert_eq!(changed_lines(&removed), vec![2]);
+        assert!(unified_diff(Path::new("x.txt"), before, before).is_empty());
+    }
+
     #[test]
     fn test_insert_and_undo() {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("test_ins.txt");
-        let mut editor = Editor::new();
+        let editor = Editor::new();
         let file_path_str = file_path.to_str().unwrap();
 
         fs::write(&file_path, "Line 1\nLine 3").unwrap();
@@ -549,7 +1915,7 @@ mod tests {
             new_str: Some("Line 2".to_string()),
             ..make_args_struct(CommandType::Insert, file_path_str)
         };
-        handle_command(&mut editor, insert_args).unwrap();
+        handle_command(&editor, insert_args).unwrap();
         assert_eq!(
             fs::read_to_string(&file_path).unwrap(),
             "Line 1\nLine 2\nLine 3"
@@ -557,7 +1923,7 @@ mod tests {
 
         // Undo Insert
         let undo_args = make_args_struct(CommandType::UndoEdit, file_path_str);
-        handle_command(&mut editor, undo_args).unwrap();
+        handle_command(&editor, undo_args).unwrap();
         assert_eq!(fs::read_to_string(&file_path).unwrap(), "Line 1\nLine 3");
     }
 
@@ -566,7 +1932,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("view_range.txt");
         fs::write(&file_path, "L1\nL2\nL3\nL4\nL5").unwrap();
-        let mut editor = Editor::new();
+        let editor = Editor::new();
         let path_str = file_path.to_str().unwrap();
 
         // Test cases
@@ -603,7 +1969,7 @@ mod tests {
         for (range, expected) in test_cases {
             let mut args = make_args_struct(CommandType::View, path_str);
             args.view_range = range.clone();
-            let result = handle_command(&mut editor, args);
+            let result = handle_command(&editor, args);
             match expected {
                 Ok(exp_str) => match result.unwrap() {
                     EditorOperationResult::Single(Some(content)) => {
@@ -631,19 +1997,19 @@ mod tests {
 
         let mut args_empty = make_args_struct(CommandType::View, empty_path_str);
         args_empty.view_range = Some(vec![1, 1]);
-        match handle_command(&mut editor, args_empty.clone()).unwrap() {
+        match handle_command(&editor, args_empty.clone()).unwrap() {
             EditorOperationResult::Single(Some(content)) => assert_eq!(content, ""),
             _ => panic!("Expected empty content for view [1,1] on empty file"),
         }
 
         args_empty.view_range = Some(vec![1, -1]);
-        match handle_command(&mut editor, args_empty.clone()).unwrap() {
+        match handle_command(&editor, args_empty.clone()).unwrap() {
             EditorOperationResult::Single(Some(content)) => assert_eq!(content, ""),
             _ => panic!("Expected empty content for view [1,-1] on empty file"),
         }
 
         args_empty.view_range = Some(vec![2, 2]);
-        assert!(handle_command(&mut editor, args_empty.clone())
+        assert!(handle_command(&editor, args_empty.clone())
             .unwrap_err()
             .contains("Start line 2 is beyond the end of an empty file"));
     }
@@ -652,7 +2018,7 @@ mod tests {
     fn test_insert_into_empty_file_and_append() {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("insert_empty_append.txt");
-        let mut editor = Editor::new();
+        let editor = Editor::new();
         let path_str = file_path.to_str().unwrap();
 
         // Create empty file
@@ -664,13 +2030,13 @@ mod tests {
             new_str: Some("First Line".to_string()),
             ..make_args_struct(CommandType::Insert, path_str)
         };
-        handle_command(&mut editor, args.clone()).unwrap();
+        handle_command(&editor, args.clone()).unwrap();
         assert_eq!(fs::read_to_string(&file_path).unwrap(), "First Line");
 
         // Insert after current line 1 (becomes line 2)
         args.insert_line = Some(1); // After "First Line"
         args.new_str = Some("Second Line".to_string());
-        handle_command(&mut editor, args.clone()).unwrap();
+        handle_command(&editor, args.clone()).unwrap();
         // "First Line", then "Second Line" inserted after it.
         assert_eq!(
             fs::read_to_string(&file_path).unwrap(),
@@ -680,7 +2046,7 @@ mod tests {
         // Append (insert after line 2, which is current last line)
         args.insert_line = Some(2); // After "Second Line"
         args.new_str = Some("Third Line".to_string());
-        handle_command(&mut editor, args.clone()).unwrap();
+        handle_command(&editor, args.clone()).unwrap();
         assert_eq!(
             fs::read_to_string(&file_path).unwrap(),
             "First Line\nSecond Line\nThird Line"
@@ -691,7 +2057,7 @@ mod tests {
     fn test_insert_error_conditions() {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("insert_errors.txt");
-        let mut editor = Editor::new();
+        let editor = Editor::new();
         let path_str = file_path.to_str().unwrap();
 
         fs::write(&file_path, "Line A\nLine B").unwrap(); // 2 lines
@@ -702,14 +2068,14 @@ mod tests {
             new_str: Some("fail".to_string()),
             ..make_args_struct(CommandType::Insert, path_str)
         };
-        assert!(handle_command(&mut editor, args.clone())
+        assert!(handle_command(&editor, args.clone())
             .unwrap_err()
             .contains("'insert_line' must be 1-indexed"));
 
         // insert_line out of bounds (too high)
         // File has 2 lines (0, 1). insert_line: Some(4) -> 0-indexed 3. lines.len() = 2. 3 > 2 -> Error.
         args.insert_line = Some(4);
-        let err_msg = handle_command(&mut editor, args.clone()).unwrap_err();
+        let err_msg = handle_command(&editor, args.clone()).unwrap_err();
         assert!(err_msg.contains("is out of bounds for file with 2 lines"));
         assert!(err_msg.contains("'insert_line' 4 (0-indexed: 3)"));
 
@@ -717,7 +2083,7 @@ mod tests {
         let non_existent_path = dir.path().join("ghost.txt").to_str().unwrap().to_string();
         args.path = Some(non_existent_path);
         args.insert_line = Some(1);
-        assert!(handle_command(&mut editor, args.clone())
+        assert!(handle_command(&editor, args.clone())
             .unwrap_err()
             .contains("File not found"));
     }
@@ -728,32 +2094,90 @@ mod tests {
         let file_path = dir.path().join("replace_no_change.txt");
         let initial_content = "no match here";
         fs::write(&file_path, initial_content).unwrap();
-        let mut editor = Editor::new();
+        let editor = Editor::new();
 
         // Record a dummy op to see if it gets overwritten
-        editor.last_op = LastOperation::Create {
-            path: PathBuf::from("dummy"),
-        };
+        let dummy = PathBuf::from("dummy");
+        editor.history().push(&dummy, HistoryDirection::Undo, FileSnapshot::Create { path: dummy.clone() });
 
         let replace_args = EditorArgs {
             old_str: Some("nonexistent".to_string()),
             new_str: Some("replacement".to_string()),
             ..make_args_struct(CommandType::StrReplace, file_path.to_str().unwrap())
         };
-        handle_command(&mut editor, replace_args).unwrap();
+        handle_command(&editor, replace_args).unwrap();
 
         assert_eq!(fs::read_to_string(&file_path).unwrap(), initial_content); // Content unchanged
-                                                                              // Ensure last_op was NOT updated because no change was made
-        match editor.last_op {
-            LastOperation::Create { ref path } if path.to_str() == Some("dummy") => {}
-            _ => panic!("last_op should not have been updated by a no-op replace"),
+        // Ensure the history was NOT updated because no change was made
+        assert_eq!(editor.history_len(None), (1, 0));
+        assert_eq!(editor.history_len(Some(&file_path)), (0, 0));
+    }
+
+    #[test]
+    fn test_multi_level_undo_and_redo() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("history.txt");
+        let path_str = file_path.to_str().unwrap();
+        let editor = Editor::with_undo_depth(3);
+
+        for content in ["v1", "v2", "v3", "v4"] {
+            let args = EditorArgs { file_text: Some(content.to_string()), ..make_args_struct(CommandType::Create, path_str) };
+            handle_command(&editor, args).unwrap();
         }
+        // The creation of v1 fell off the bounded history
+        assert_eq!(editor.history_len(None), (3, 0));
+
+        let undo = |count| EditorArgs { count: Some(count), ..make_args_struct(CommandType::UndoEdit, path_str) };
+        let redo = |count| EditorArgs { count: Some(count), ..make_args_struct(CommandType::RedoEdit, path_str) };
+        assert!(handle_command(&editor, undo(4)).unwrap_err().contains("Only 3 edit(s)"));
+        handle_command(&editor, undo(2)).unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "v2");
+        handle_command(&editor, redo(1)).unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "v3");
+        assert_eq!(editor.history_len(None), (2, 1));
+
+        // A new edit discards what could be redone
+        let args = EditorArgs { file_text: Some("v5".to_string()), ..make_args_struct(CommandType::Create, path_str) };
+        handle_command(&editor, args).unwrap();
+        assert!(handle_command(&editor, redo(1)).unwrap_err().contains("No operation to redo"));
+        handle_command(&editor, undo(3)).unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "v1");
+    }
+
+    #[test]
+    fn test_undo_is_tracked_per_file() {
+        let dir = tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        let (a_str, b_str) = (a.to_str().unwrap(), b.to_str().unwrap());
+        fs::write(&a, "a0").unwrap();
+        fs::write(&b, "b0").unwrap();
+        let editor = Editor::new();
+
+        let edit = |path: &str, text: &str| EditorArgs { file_text: Some(text.to_string()), ..make_args_struct(CommandType::Create, path) };
+        handle_command(&editor, edit(a_str, "a1")).unwrap();
+        handle_command(&editor, edit(b_str, "b1")).unwrap();
+        handle_command(&editor, edit(a_str, "a2")).unwrap();
+
+        // Undoing b leaves the newer edit of a alone
+        handle_command(&editor, make_args_struct(CommandType::UndoEdit, b_str)).unwrap();
+        assert_eq!(fs::read_to_string(&b).unwrap(), "b0");
+        assert_eq!(fs::read_to_string(&a).unwrap(), "a2");
+        assert!(handle_command(&editor, make_args_struct(CommandType::UndoEdit, b_str)).unwrap_err().contains("No operation to undo for"));
+
+        // Without a path, the most recent edit of any file is undone, then redone
+        let global = |command| EditorArgs { path: None, ..make_args_struct(command, a_str) };
+        handle_command(&editor, global(CommandType::UndoEdit)).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "a1");
+        handle_command(&editor, global(CommandType::RedoEdit)).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "a2");
+        assert_eq!(editor.history_len(Some(&a)), (2, 0));
+        assert_eq!(editor.history_len(Some(&b)), (0, 1));
     }
 
     #[test]
     fn test_create_with_parent_directories() {
         let dir = tempdir().unwrap();
-        let mut editor = Editor::new();
+        let editor = Editor::new();
         
         // Test creating a file in nested directories that don't exist
         let nested_file_path = dir.path().join("level1").join("level2").join("level3").join("test.txt");
@@ -769,7 +2193,7 @@ mod tests {
         };
         
         // This should succeed and create all parent directories
-        handle_command(&mut editor, create_args).unwrap();
+        handle_command(&editor, create_args).unwrap();
         
         // Verify the file was created
         assert!(nested_file_path.exists());
@@ -782,9 +2206,427 @@ mod tests {
         
         // Test undo - should remove the file but leave directories
         let undo_args = make_args_struct(CommandType::UndoEdit, file_path_str);
-        handle_command(&mut editor, undo_args).unwrap();
+        handle_command(&editor, undo_args).unwrap();
         assert!(!nested_file_path.exists());
         // Parent directories should still exist after undo
         assert!(nested_file_path.parent().unwrap().exists());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_edits_keep_bom_crlf_and_latin1() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("windows.txt");
+        let file_path_str = file_path.to_str().unwrap();
+        let editor = Editor::new();
+        fs::write(&file_path, "\u{feff}first\r\nsecond\r\n").unwrap();
+
+        let view = handle_command(&editor, make_args_struct(CommandType::View, file_path_str)).unwrap();
+        assert!(matches!(view, EditorOperationResult::Single(Some(ref c)) if c == "first\nsecond\n"));
+        let replace = EditorArgs {
+            old_str: Some("first\nsecond".to_string()),
+            new_str: Some("1st\n2nd".to_string()),
+            ..make_args_struct(CommandType::StrReplace, file_path_str)
+        };
+        handle_command(&editor, replace).unwrap();
+        let insert = EditorArgs {
+            insert_line: Some(1),
+            new_str: Some("between".to_string()),
+            ..make_args_struct(CommandType::Insert, file_path_str)
+        };
+        handle_command(&editor, insert).unwrap();
+        assert_eq!(fs::read(&file_path).unwrap(), "\u{feff}1st\r\nbetween\r\n2nd\r\n".as_bytes());
+
+        // Inserting into a long CRLF file changes one line, not the whole file
+        let long_path = dir.path().join("long.txt");
+        let long_path_str = long_path.to_str().unwrap();
+        fs::write(&long_path, (1..=30).map(|i| format!("line {}\r\n", i)).collect::<String>()).unwrap();
+        let insert = EditorArgs {
+            insert_line: Some(10),
+            new_str: Some("inserted".to_string()),
+            ..make_args_struct(CommandType::Insert, long_path_str)
+        };
+        match handle_command(&editor, EditorArgs { dry_run: true, ..insert.clone() }).unwrap() {
+            EditorOperationResult::Preview(preview) => {
+                assert_eq!(preview.diff.lines().filter(|l| l.starts_with(['+', '-'])).count(), 3, "{}", preview.diff);
+                assert_eq!(changed_lines(&preview.diff), vec![11]);
+            }
+            other => panic!("expected a preview, got {:?}", other),
+        }
+        assert!(matches!(handle_command(&editor, insert).unwrap(), EditorOperationResult::Single(_)));
+        let written = fs::read_to_string(&long_path).unwrap();
+        assert!(written.contains("line 10\r\ninserted\r\nline 11\r\n"));
+        assert_eq!(written.matches("\r\n").count(), 31);
+
+        let latin_path = dir.path().join("latin.txt");
+        let latin_path_str = latin_path.to_str().unwrap();
+        fs::write(&latin_path, b"caf\xE9\n").unwrap();
+        let utf8_replace = EditorArgs {
+            old_str: Some("caf".to_string()),
+            ..make_args_struct(CommandType::StrReplace, latin_path_str)
+        };
+        assert!(handle_command(&editor, utf8_replace.clone()).unwrap_err().contains("latin1"));
+        let latin_replace = EditorArgs {
+            new_str: Some("thé".to_string()),
+            encoding: TextEncoding::Latin1,
+            ..utf8_replace
+        };
+        handle_command(&editor, latin_replace).unwrap();
+        assert_eq!(fs::read(&latin_path).unwrap(), b"th\xE9\xE9\n");
+    }
+
+    #[test]
+    fn test_expected_hash_guards_mutations() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("page.tsx");
+        let file_path_str = file_path.to_str().unwrap();
+        let editor = Editor::new();
+        fs::write(&file_path, "title\nbody\n").unwrap();
+        let viewed_hash = content_hash("title\nbody\n");
+        // Someone else saves the file after it was viewed
+        fs::write(&file_path, "Title\nbody\n").unwrap();
+
+        let replace = EditorArgs {
+            old_str: Some("body".to_string()),
+            new_str: Some("content".to_string()),
+            expected_hash: Some(viewed_hash),
+            ..make_args_struct(CommandType::StrReplace, file_path_str)
+        };
+        match handle_command(&editor, replace.clone()).unwrap() {
+            EditorOperationResult::Conflict(conflict) => {
+                assert_eq!(conflict.current_hash, Some(content_hash("Title\nbody\n")));
+                assert_eq!(changed_lines(&conflict.diff), vec![2]);
+            }
+            other => panic!("Expected a conflict, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "Title\nbody\n");
+
+        let current = EditorArgs { expected_hash: Some(content_hash("Title\nbody\n")), ..replace };
+        handle_command(&editor, current).unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "Title\ncontent\n");
+
+        let delete = EditorArgs {
+            expected_hash: Some(content_hash("Title\nbody\n")),
+            ..make_args_struct(CommandType::DeleteFile, file_path_str)
+        };
+        assert!(matches!(handle_command(&editor, delete).unwrap(), EditorOperationResult::Conflict(_)));
+        assert!(file_path.exists());
+    }
+
+    #[test]
+    fn test_chunked_views() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("bundle.js");
+        fs::write(&file_path, "héllo\nworld\nlast\n").unwrap();
+
+        // Byte 2 is inside `é`, which stays whole in the first chunk
+        let first = view_byte_chunk(&file_path, 0, Some(2), TextEncoding::Utf8).unwrap();
+        assert_eq!((first.content.as_str(), first.length, first.has_more), ("h", 1, true));
+        let second = view_byte_chunk(&file_path, 1, Some(4), TextEncoding::Utf8).unwrap();
+        assert_eq!((second.content.as_str(), second.offset + second.length), ("éll", 5));
+        let rest = view_byte_chunk(&file_path, 2, None, TextEncoding::Utf8).unwrap();
+        assert_eq!((rest.offset, rest.content.as_str(), rest.has_more), (3, "llo\nworld\nlast\n", false));
+        // A length shorter than the character at the offset still returns the whole character
+        let narrow = view_byte_chunk(&file_path, 1, Some(1), TextEncoding::Utf8).unwrap();
+        assert_eq!((narrow.content.as_str(), narrow.offset, narrow.length), ("é", 1, 2));
+        assert!(view_byte_chunk(&file_path, 100, None, TextEncoding::Utf8).is_err());
+
+        let window = view_line_window(&file_path, &[2, -1], TextEncoding::Utf8).unwrap();
+        assert_eq!(window.content, "world\nlast");
+        assert_eq!((window.offset, window.total_lines, window.lines, window.has_more), (7, Some(3), Some((2, 3)), false));
+        let window = view_line_window(&file_path, &[1, 1], TextEncoding::Utf8).unwrap();
+        assert_eq!((window.content.as_str(), window.has_more), ("héllo", true));
+        assert!(view_line_window(&file_path, &[4, -1], TextEncoding::Utf8).is_err());
+    }
+
+    #[test]
+    fn test_replace_range_within_line_and_across_lines() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test_rr.txt");
+        let editor = Editor::new();
+        let file_path_str = file_path.to_str().unwrap();
+
+        let original = "const greeting = \"héllo\";\nlet x = 1;\nlet y = 2;\n";
+        fs::write(&file_path, original).unwrap();
+
+        // Sub-line edit: replace `héllo` (columns are characters, not bytes)
+        let args = EditorArgs {
+            range: Some(TextRange { start_line: 1, start_column: 18, end_line: 1, end_column: 23 }),
+            new_str: Some("bye".to_string()),
+            expected_hash: Some(content_hash(original)),
+            ..make_args_struct(CommandType::ReplaceRange, file_path_str)
+        };
+        handle_command(&editor, args).unwrap();
+        let after_first = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(after_first, "const greeting = \"bye\";\nlet x = 1;\nlet y = 2;\n");
+
+        // Multi-line edit: from after `let x = ` to before `2;`
+        let args = EditorArgs {
+            range: Some(TextRange { start_line: 2, start_column: 8, end_line: 3, end_column: 8 }),
+            new_str: Some("3;\nlet z = ".to_string()),
+            expected_hash: Some(content_hash(&after_first)),
+            ..make_args_struct(CommandType::ReplaceRange, file_path_str)
+        };
+        handle_command(&editor, args).unwrap();
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "const greeting = \"bye\";\nlet x = 3;\nlet z = 2;\n"
+        );
+
+        // Undo restores the previous content
+        handle_command(&editor, make_args_struct(CommandType::UndoEdit, file_path_str)).unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), after_first);
+    }
+
+    #[test]
+    fn test_replace_range_rejects_stale_hash_and_bad_positions() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test_rr_err.txt");
+        let editor = Editor::new();
+        let file_path_str = file_path.to_str().unwrap();
+
+        fs::write(&file_path, "abc\ndef").unwrap();
+        let hash = content_hash("abc\ndef");
+
+        let stale = EditorArgs {
+            range: Some(TextRange { start_line: 1, start_column: 0, end_line: 1, end_column: 1 }),
+            expected_hash: Some(content_hash("something else")),
+            ..make_args_struct(CommandType::ReplaceRange, file_path_str)
+        };
+        match handle_command(&editor, stale).unwrap() {
+            EditorOperationResult::Conflict(conflict) => {
+                assert_eq!(conflict.current_hash.as_deref(), Some(hash.as_str()));
+                assert_eq!(changed_lines(&conflict.diff), vec![1]);
+            }
+            other => panic!("Expected a conflict, got {:?}", other),
+        }
+
+        let bad_column = EditorArgs {
+            range: Some(TextRange { start_line: 1, start_column: 0, end_line: 1, end_column: 4 }),
+            expected_hash: Some(hash.clone()),
+            ..make_args_struct(CommandType::ReplaceRange, file_path_str)
+        };
+        assert!(handle_command(&editor, bad_column).unwrap_err().contains("out of bounds"));
+
+        let reversed = EditorArgs {
+            range: Some(TextRange { start_line: 2, start_column: 0, end_line: 1, end_column: 0 }),
+            expected_hash: Some(hash),
+            ..make_args_struct(CommandType::ReplaceRange, file_path_str)
+        };
+        assert!(handle_command(&editor, reversed).unwrap_err().contains("is before range start"));
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "abc\ndef");
+    }
+
+    #[test]
+    fn test_apply_changes_is_all_or_nothing() {
+        let dir = tempdir().unwrap();
+        let (old, new) = (dir.path().join("old.ts"), dir.path().join("nested/new.ts"));
+        let importer = dir.path().join("main.ts");
+        fs::write(&old, "export const a = 1;").unwrap();
+        fs::write(&importer, "import { a } from './old';").unwrap();
+        let editor = Editor::new();
+
+        // The failing delete rolls back the write before it
+        let failing = [
+            FileChange::Write { path: importer.clone(), content: b"changed".to_vec() },
+            FileChange::Delete { path: dir.path().join("missing.ts") },
+        ];
+        assert!(apply_changes(&editor, &failing).is_err());
+        assert_eq!(fs::read_to_string(&importer).unwrap(), "import { a } from './old';");
+        assert_eq!(editor.history_len(None), (0, 0));
+
+        let moved = [
+            FileChange::Write { path: new.clone(), content: fs::read(&old).unwrap() },
+            FileChange::Delete { path: old.clone() },
+            FileChange::Write { path: importer.clone(), content: b"import { a } from './nested/new';".to_vec() },
+        ];
+        assert_eq!(apply_changes(&editor, &moved).unwrap(), 3);
+        assert!(!old.exists() && new.exists());
+
+        // Undoing part of the change without a path is refused; naming a file undoes just it
+        let err = handle_command(&editor, EditorArgs { path: None, ..make_args_struct(CommandType::UndoEdit, "") }).unwrap_err();
+        assert!(err.contains("change of 3 files") && err.contains("`count` to 3"), "{}", err);
+        let importer_str = importer.to_str().unwrap();
+        handle_command(&editor, make_args_struct(CommandType::UndoEdit, importer_str)).unwrap();
+        assert_eq!(fs::read_to_string(&importer).unwrap(), "import { a } from './old';");
+        assert!(!old.exists() && new.exists());
+        handle_command(&editor, make_args_struct(CommandType::RedoEdit, importer_str)).unwrap();
+
+        handle_command(&editor, EditorArgs { count: Some(3), path: None, ..make_args_struct(CommandType::UndoEdit, "") }).unwrap();
+        assert!(old.exists() && !new.exists());
+        assert_eq!(fs::read_to_string(&importer).unwrap(), "import { a } from './old';");
+    }
+
+    #[test]
+    fn test_file_operations_and_undo() {
+        let dir = tempdir().unwrap();
+        let editor = Editor::new();
+        let source = dir.path().join("a.txt");
+        let moved = dir.path().join("nested/b.txt");
+        let copied = dir.path().join("c.txt");
+        fs::write(&source, "content\n").unwrap();
+        let with_new_path = |command, from: &Path, to: &Path| EditorArgs {
+            new_path: Some(to.to_str().unwrap().to_string()),
+            ..make_args_struct(command, from.to_str().unwrap())
+        };
+
+        handle_command(&editor, with_new_path(CommandType::Move, &source, &moved)).unwrap();
+        assert!(!source.exists());
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "content\n");
+        handle_command(&editor, with_new_path(CommandType::Copy, &moved, &copied)).unwrap();
+        assert_eq!(fs::read_to_string(&copied).unwrap(), "content\n");
+        handle_command(&editor, make_args_struct(CommandType::DeleteFile, moved.to_str().unwrap())).unwrap();
+        assert!(!moved.exists());
+
+        // Destinations must not exist, and directories are left to the refactor API
+        let err = handle_command(&editor, with_new_path(CommandType::Copy, &copied, &copied)).unwrap_err();
+        assert!(err.contains("already exists"), "{}", err);
+        let err = handle_command(&editor, make_args_struct(CommandType::DeleteFile, dir.path().to_str().unwrap())).unwrap_err();
+        assert!(err.contains("is a directory"), "{}", err);
+
+        // Undo the delete, the copy and the move (two files), newest first
+        handle_command(&editor, EditorArgs { path: None, ..make_args_struct(CommandType::UndoEdit, "") }).unwrap();
+        assert!(moved.exists());
+        handle_command(&editor, EditorArgs { path: None, ..make_args_struct(CommandType::UndoEdit, "") }).unwrap();
+        assert!(!copied.exists());
+        handle_command(&editor, EditorArgs { count: Some(2), path: None, ..make_args_struct(CommandType::UndoEdit, "") }).unwrap();
+        assert!(!moved.exists());
+        assert_eq!(fs::read_to_string(&source).unwrap(), "content\n");
+    }
+
+    #[test]
+    fn test_guardrails_only_warn_by_default() {
+        let dir = tempdir().unwrap();
+        let editor = Editor::new();
+        let path = dir.path().join("util.ts");
+        let original: String = (0..30).map(|i| format!("export const v{} = {};\n", i, i)).collect();
+        fs::write(&path, &original).unwrap();
+        let path_str = path.to_str().unwrap();
+        let after = "const internal = 1;\n";
+        let rewrite = EditorArgs { file_text: Some(after.to_string()), ..make_args_struct(CommandType::Create, path_str) };
+
+        // Trips the guardrails, which in the default `warn` mode are logged, not enforced
+        let tripped = guardrails::GuardrailConfig::default().check(&path, &original, Some(after), &unified_diff(&path, &original, after));
+        assert!(tripped.iter().any(|v| v.rule == guardrails::GuardrailRule::ExportRemoval));
+        assert!(matches!(handle_command(&editor, rewrite).unwrap(), EditorOperationResult::Single(_)));
+        assert_eq!(fs::read_to_string(&path).unwrap(), after);
+        assert_eq!(editor.history_len(None), (1, 0));
+    }
+
+    #[test]
+    fn test_remove_dir_recursively_and_undo() {
+        let dir = tempdir().unwrap();
+        let editor = Editor::new();
+        let target = dir.path().join("components");
+        fs::create_dir_all(target.join("ui")).unwrap();
+        fs::write(target.join("ui/button.tsx"), "button").unwrap();
+        fs::write(target.join("card.tsx"), "card").unwrap();
+
+        let err = remove_dir(&editor, &target, false).unwrap_err();
+        assert!(err.contains("recursive"), "{}", err);
+        assert_eq!(remove_dir(&editor, &target, true).unwrap(), 2);
+        assert!(!target.exists());
+
+        handle_command(&editor, EditorArgs { count: Some(2), path: None, ..make_args_struct(CommandType::UndoEdit, "") }).unwrap();
+        assert_eq!(fs::read_to_string(target.join("ui/button.tsx")).unwrap(), "button");
+        assert_eq!(fs::read_to_string(target.join("card.tsx")).unwrap(), "card");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_dir_unlinks_symlinks_without_following_them() {
+        let (dir, outside) = (tempdir().unwrap(), tempdir().unwrap());
+        let editor = Editor::new();
+        fs::write(outside.path().join("keep.ts"), "keep").unwrap();
+        let target = dir.path().join("components");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("card.tsx"), "card").unwrap();
+        std::os::unix::fs::symlink(outside.path(), target.join("shared")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("keep.ts"), target.join("keep.ts")).unwrap();
+
+        let linked = dir.path().join("linked");
+        std::os::unix::fs::symlink(outside.path(), &linked).unwrap();
+        let err = remove_dir(&editor, &linked, true).unwrap_err();
+        assert!(err.contains("not a directory"), "{}", err);
+
+        assert_eq!(remove_dir(&editor, &target, true).unwrap(), 1);
+        assert!(!target.exists());
+        assert_eq!(fs::read_to_string(outside.path().join("keep.ts")).unwrap(), "keep");
+    }
+
+    #[tokio::test]
+    async fn test_locks_only_serialize_changes_of_the_same_file() {
+        let editor = Editor::new();
+        let dir = tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.ts"), dir.path().join("b.ts"));
+        let wait = std::time::Duration::from_millis(50);
+
+        let holding_a = editor.lock(Some(std::slice::from_ref(&a))).await;
+        assert!(tokio::time::timeout(wait, editor.lock(Some(std::slice::from_ref(&b)))).await.is_ok());
+        assert!(tokio::time::timeout(wait, editor.lock(Some(&[b.clone(), a.clone()]))).await.is_err());
+        assert!(tokio::time::timeout(wait, editor.lock(None)).await.is_err());
+        drop(holding_a);
+
+        let whole = editor.lock(None).await;
+        assert!(tokio::time::timeout(wait, editor.lock(Some(std::slice::from_ref(&b)))).await.is_err());
+        drop(whole);
+        assert!(tokio::time::timeout(wait, editor.lock(Some(&[a.clone(), a]))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hooked_edit_runs_in_the_scoped_workspace() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let target = root.join("src/page.ts");
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::write(&target, "old\n").unwrap();
+        let config: toml::Value = toml::from_str(
+            r#"
+            [[editor_hooks]]
+            stage = "pre"
+            action = "shell"
+            command = "pwd"
+            patterns = ["src/*.ts"]
+
+            [[editor_hooks]]
+            stage = "post"
+            action = "shell"
+            command = "cat"
+            "#,
+        )
+        .unwrap();
+        let configured: Vec<HookConfig> = config.get("editor_hooks").unwrap().clone().try_into().unwrap();
+
+        let args = EditorArgs {
+            old_str: Some("old".to_string()),
+            new_str: Some("new".to_string()),
+            ..make_args_struct(CommandType::StrReplace, target.to_str().unwrap())
+        };
+        let path = target.clone();
+        let (result, outcomes, diff) = workspaces::scope(root.clone(), async move {
+            with_files(Some(vec![path.clone()]), move |editor| {
+                let (result, outcomes) = run_with_hooks(editor, args, path.clone(), &configured);
+                (result, outcomes, unified_diff(&path, "old\n", "new\n"))
+            })
+            .await
+            .unwrap()
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new\n");
+        // The pre hook only matches project-relative paths and runs in the workspace root
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].output.trim(), root.to_str().unwrap());
+        assert!(outcomes[1].output.contains("+++ b/src/page.ts"));
+        assert!(diff.contains("--- a/src/page.ts"));
+    }
+
+    #[test]
+    fn test_edited_files_for_language_servers() {
+        let moved = edited_files(&CommandType::Move, Some("/p/a.ts"), Some("/p/b.ts"));
+        assert_eq!(moved, EditedFiles::Paths { changed: vec![PathBuf::from("/p/a.ts")], created: vec![PathBuf::from("/p/b.ts")] });
+        let replaced = edited_files(&CommandType::StrReplace, Some("/p/a.ts"), None);
+        assert_eq!(replaced, EditedFiles::Paths { changed: vec![PathBuf::from("/p/a.ts")], created: Vec::new() });
+        assert_eq!(edited_files(&CommandType::UndoEdit, Some("/p/a.ts"), None), EditedFiles::Unknown);
+    }
+}
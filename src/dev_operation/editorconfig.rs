@@ -0,0 +1,430 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Minimal `.editorconfig` support for the editor: resolves the effective settings for a file
+// and applies them to text the editor writes. Only the properties the editor can act on are
+// recognised; anything else in the file is ignored.
+
+const EDITORCONFIG_FILE_NAME: &str = ".editorconfig";
+const UTF8_BOM: &str = "\u{feff}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Space,
+    Tab,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndOfLine {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl EndOfLine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EndOfLine::Lf => "\n",
+            EndOfLine::Crlf => "\r\n",
+            EndOfLine::Cr => "\r",
+        }
+    }
+}
+
+// Effective settings for a single path. `None` means the property is unset (or `unset`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorConfigSettings {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub end_of_line: Option<EndOfLine>,
+    pub charset: Option<String>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    // .editorconfig files that contributed to the result, closest last
+    pub sources: Vec<PathBuf>,
+}
+
+impl EditorConfigSettings {
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    // Width of one indentation level in columns, following the spec's fallbacks
+    fn indent_width(&self) -> usize {
+        self.indent_size.or(self.tab_width).unwrap_or(4)
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.to_ascii_lowercase();
+        let unset = value == "unset";
+        match key {
+            "indent_style" => {
+                self.indent_style = match value.as_str() {
+                    "space" => Some(IndentStyle::Space),
+                    "tab" => Some(IndentStyle::Tab),
+                    _ => None,
+                };
+            }
+            "indent_size" => {
+                // "tab" means: use tab_width, resolved lazily in indent_width()
+                self.indent_size = if unset || value == "tab" { None } else { value.parse().ok() };
+            }
+            "tab_width" => self.tab_width = value.parse().ok(),
+            "end_of_line" => {
+                self.end_of_line = match value.as_str() {
+                    "lf" => Some(EndOfLine::Lf),
+                    "crlf" => Some(EndOfLine::Crlf),
+                    "cr" => Some(EndOfLine::Cr),
+                    _ => None,
+                };
+            }
+            "charset" => self.charset = if unset { None } else { Some(value) },
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = parse_bool(&value),
+            "insert_final_newline" => self.insert_final_newline = parse_bool(&value),
+            _ => {}
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+struct Section {
+    pattern: String,
+    properties: Vec<(String, String)>,
+}
+
+struct ParsedFile {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+fn parse_editorconfig(content: &str) -> ParsedFile {
+    let mut parsed = ParsedFile { root: false, sections: Vec::new() };
+
+    for raw_line in content.lines() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            parsed.sections.push(Section {
+                pattern: line[1..line.len() - 1].to_string(),
+                properties: Vec::new(),
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_string();
+        match parsed.sections.last_mut() {
+            Some(section) => section.properties.push((key, value)),
+            // Preamble: only `root` is meaningful before the first section
+            None if key == "root" => parsed.root = value.eq_ignore_ascii_case("true"),
+            None => {}
+        }
+    }
+    parsed
+}
+
+/// Resolves the effective `.editorconfig` settings for `path`.
+///
+/// Walks from the file's directory upwards, stopping after a file declaring `root = true`.
+/// Closer files and later sections take precedence, as in the specification.
+pub fn resolve_for_path(path: &Path) -> Result<EditorConfigSettings, String> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map_err(|e| format!("Error resolving current directory: {}", e))?
+            .join(path)
+    };
+
+    // Collect config files closest-first, then apply them farthest-first
+    let mut configs: Vec<(PathBuf, ParsedFile)> = Vec::new();
+    let mut dir = absolute.parent();
+    while let Some(current) = dir {
+        let candidate = current.join(EDITORCONFIG_FILE_NAME);
+        if candidate.is_file() {
+            let content = fs::read_to_string(&candidate)
+                .map_err(|e| format!("Error reading '{}': {}", candidate.display(), e))?;
+            let parsed = parse_editorconfig(&content);
+            let is_root = parsed.root;
+            configs.push((candidate, parsed));
+            if is_root {
+                break;
+            }
+        }
+        dir = current.parent();
+    }
+
+    let mut settings = EditorConfigSettings::default();
+    for (config_path, parsed) in configs.into_iter().rev() {
+        let config_dir = config_path.parent().unwrap_or(Path::new("/"));
+        let Ok(relative) = absolute.strip_prefix(config_dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+
+        let mut matched_any = false;
+        for section in &parsed.sections {
+            if section_matches(&section.pattern, &relative) {
+                matched_any = true;
+                for (key, value) in &section.properties {
+                    settings.set(key, value);
+                }
+            }
+        }
+        if matched_any {
+            settings.sources.push(config_path);
+        }
+    }
+    Ok(settings)
+}
+
+// Patterns without a slash match the file name in any directory; patterns with one are
+// anchored to the directory containing the .editorconfig file.
+fn section_matches(pattern: &str, relative_path: &str) -> bool {
+    let pattern = pattern.trim();
+    if pattern.contains('/') {
+        glob_matches(pattern.trim_start_matches('/'), relative_path)
+    } else {
+        let file_name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+        glob_matches(pattern, file_name)
+    }
+}
+
+// Glob matcher covering the subset of editorconfig syntax seen in practice:
+// `*`, `**`, `?`, `[abc]`, `[!abc]` and `{a,b,c}` (non-nested).
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    if let Some(open) = pattern.find('{') {
+        if let Some(close_offset) = pattern[open..].find('}') {
+            let close = open + close_offset;
+            let prefix = &pattern[..open];
+            let suffix = &pattern[close + 1..];
+            return pattern[open + 1..close]
+                .split(',')
+                .any(|alt| glob_matches(&format!("{}{}{}", prefix, alt, suffix), text));
+        }
+    }
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    match_from(&p, &t)
+}
+
+fn match_from(p: &[char], t: &[char]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            let rest = &p[2..];
+            // `**/` also matches zero directories
+            if rest.first() == Some(&'/') && match_from(&rest[1..], t) {
+                return true;
+            }
+            (0..=t.len()).any(|i| match_from(rest, &t[i..]))
+        }
+        Some('*') => {
+            let rest = &p[1..];
+            for i in 0..=t.len() {
+                if match_from(rest, &t[i..]) {
+                    return true;
+                }
+                if i < t.len() && t[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => !t.is_empty() && t[0] != '/' && match_from(&p[1..], &t[1..]),
+        Some('[') => {
+            let Some(close) = p.iter().position(|&c| c == ']') else {
+                return !t.is_empty() && t[0] == '[' && match_from(&p[1..], &t[1..]);
+            };
+            if t.is_empty() {
+                return false;
+            }
+            let (negated, class) = match p.get(1) {
+                Some('!') => (true, &p[2..close]),
+                _ => (false, &p[1..close]),
+            };
+            (class.contains(&t[0]) != negated) && match_from(&p[close + 1..], &t[1..])
+        }
+        Some(&c) => !t.is_empty() && t[0] == c && match_from(&p[1..], &t[1..]),
+    }
+}
+
+/// Re-indents leading whitespace of each line according to `indent_style`.
+///
+/// With `tab`, every full indentation level of spaces becomes a tab; with `space`,
+/// every leading tab becomes `indent_size` spaces.
+pub fn apply_indentation(text: &str, settings: &EditorConfigSettings) -> String {
+    let Some(style) = settings.indent_style else {
+        return text.to_string();
+    };
+    let width = settings.indent_width().max(1);
+
+    let convert = |line: &str| -> String {
+        let body = line.trim_start_matches([' ', '\t']);
+        let leading = &line[..line.len() - body.len()];
+        let columns: usize = leading
+            .chars()
+            .map(|c| if c == '\t' { width } else { 1 })
+            .sum();
+        let indent = match style {
+            IndentStyle::Space => " ".repeat(columns),
+            IndentStyle::Tab => format!("{}{}", "\t".repeat(columns / width), " ".repeat(columns % width)),
+        };
+        format!("{}{}", indent, body)
+    };
+
+    split_lines(text).iter().map(|l| convert(l)).collect::<Vec<_>>().join("\n")
+}
+
+/// Prepares text inserted into an existing file: indentation and trailing whitespace only,
+/// so the rest of the file is left as it was.
+pub fn apply_to_inserted_text(text: &str, settings: &EditorConfigSettings) -> String {
+    let text = apply_indentation(text, settings);
+    if settings.trim_trailing_whitespace == Some(true) {
+        split_lines(&text)
+            .iter()
+            .map(|l| l.trim_end_matches([' ', '\t']))
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        text
+    }
+}
+
+/// Applies whole-file settings: trailing whitespace, line endings, final newline and BOM.
+pub fn apply_to_content(content: &str, settings: &EditorConfigSettings) -> String {
+    let had_bom = content.starts_with(UTF8_BOM);
+    let content = content.trim_start_matches(UTF8_BOM);
+
+    let mut lines = split_lines(content);
+    let ends_with_newline = lines.last().is_some_and(|l| l.is_empty()) && lines.len() > 1;
+    if ends_with_newline {
+        lines.pop();
+    }
+
+    if settings.trim_trailing_whitespace == Some(true) {
+        for line in lines.iter_mut() {
+            *line = line.trim_end_matches([' ', '\t']).to_string();
+        }
+    }
+
+    let eol = settings
+        .end_of_line
+        .map(|e| e.as_str())
+        .unwrap_or_else(|| detect_eol(content));
+    let mut result = lines.join(eol);
+
+    let final_newline = settings.insert_final_newline.unwrap_or(ends_with_newline);
+    if final_newline && (ends_with_newline || !result.is_empty()) {
+        result.push_str(eol);
+    }
+
+    let bom = match settings.charset.as_deref() {
+        Some("utf-8-bom") => true,
+        Some(_) => false,
+        None => had_bom,
+    };
+    if bom {
+        result.insert_str(0, UTF8_BOM);
+    }
+    result
+}
+
+/// Line ending already used by `content`, defaulting to `\n`.
+pub fn detect_eol(content: &str) -> &'static str {
+    if content.contains("\r\n") {
+        "\r\n"
+    } else if content.contains('\r') {
+        "\r"
+    } else {
+        "\n"
+    }
+}
+
+// Splits on \r\n, \n or \r, keeping a trailing empty element when text ends with a newline
+fn split_lines(text: &str) -> Vec<String> {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .split('\n')
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_glob_matching() {
+        assert!(section_matches("*", "src/app/page.tsx"));
+        assert!(section_matches("*.{ts,tsx}", "src/app/page.tsx"));
+        assert!(!section_matches("*.{ts,tsx}", "src/app/page.md"));
+        assert!(section_matches("src/**.rs", "src/a/b/lib.rs"));
+        assert!(section_matches("src/*.rs", "src/lib.rs"));
+        assert!(!section_matches("src/*.rs", "src/a/lib.rs"));
+        assert!(section_matches("**/Makefile", "Makefile"));
+        assert!(section_matches("[Mm]akefile", "makefile"));
+        assert!(section_matches("file?.txt", "file1.txt"));
+    }
+
+    #[test]
+    fn test_resolve_merges_nested_configs_and_stops_at_root() {
+        let dir = tempdir().unwrap();
+        let outer = dir.path().join("outer");
+        let project = outer.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+
+        // Ignored: the project-level file declares root = true
+        fs::write(outer.join(".editorconfig"), "[*]\ncharset = latin1\n").unwrap();
+        fs::write(
+            project.join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 2\nend_of_line = lf\ninsert_final_newline = true\n\n[*.md]\ntrim_trailing_whitespace = false\n",
+        )
+        .unwrap();
+        fs::write(project.join("src").join(".editorconfig"), "[*.ts]\nindent_size = 4\n").unwrap();
+
+        let settings = resolve_for_path(&project.join("src").join("index.ts")).unwrap();
+        assert_eq!(settings.indent_style, Some(IndentStyle::Space));
+        assert_eq!(settings.indent_size, Some(4));
+        assert_eq!(settings.end_of_line, Some(EndOfLine::Lf));
+        assert_eq!(settings.insert_final_newline, Some(true));
+        assert_eq!(settings.trim_trailing_whitespace, None);
+        assert_eq!(settings.charset, None);
+        assert_eq!(settings.sources.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_to_content_and_indentation() {
+        let settings = EditorConfigSettings {
+            indent_style: Some(IndentStyle::Tab),
+            indent_size: Some(2),
+            end_of_line: Some(EndOfLine::Crlf),
+            trim_trailing_whitespace: Some(true),
+            insert_final_newline: Some(true),
+            ..Default::default()
+        };
+        let indented = apply_indentation("fn a() {\n    body();  \n}", &settings);
+        assert_eq!(indented, "fn a() {\n\t\tbody();  \n}");
+        assert_eq!(
+            apply_to_content(&indented, &settings),
+            "fn a() {\r\n\t\tbody();\r\n}\r\n"
+        );
+
+        // Unset settings leave content untouched
+        let untouched = "a  \r\nb";
+        assert_eq!(apply_to_content(untouched, &EditorConfigSettings::default()), untouched);
+    }
+}
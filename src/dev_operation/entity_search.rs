@@ -0,0 +1,389 @@
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::symbols::{self, INDEXED_EXTENSIONS};
+use super::sync;
+use crate::codebase_indexing::profiles::AnalysisProfile;
+use crate::file_system::dirs::SKIPPED_DIRS;
+use crate::file_system::ranking::{self, RankSignals, Ranked, RankingWeights};
+use crate::file_system::search::find_files_by_extensions;
+
+// Matching lines reported per entity
+const MAX_MATCH_LINES: usize = 5;
+
+/// A parsed entity search query.
+///
+/// Whitespace-separated terms are matched against the entity's code; `"quoted phrases"` match
+/// literally. `key:value` tokens filter on the entity index:
+///
+/// - `kind:function|class|interface|method|variable|constant|struct|trait|module|hook|component`
+/// - `exported:true|false`
+/// - `lang:ts|tsx|rs`
+/// - `name:<substring>`
+/// - `path:<glob>` (sync-style globs, e.g. `path:src/hooks`)
+/// - `calls:<identifier>`: the body contains a call to the identifier
+///
+/// e.g. `kind:hook exported:true calls:fetch` for "exported hooks that call fetch".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntityQuery {
+    pub kinds: Vec<String>,
+    pub exported: Option<bool>,
+    pub langs: Vec<String>,
+    pub name: Option<String>,
+    pub paths: Vec<String>,
+    pub calls: Vec<String>,
+    pub terms: Vec<String>,
+    pub phrases: Vec<String>,
+}
+
+impl EntityQuery {
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parsed = EntityQuery::default();
+        let mut rest = query.trim();
+        while !rest.is_empty() {
+            let token;
+            if let Some(stripped) = rest.strip_prefix('"') {
+                let end = stripped.find('"').unwrap_or(stripped.len());
+                parsed.phrases.push(stripped[..end].to_lowercase());
+                rest = stripped.get(end + 1..).unwrap_or_default().trim_start();
+                continue;
+            }
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (token, rest) = (&rest[..end], rest[end..].trim_start());
+
+            let Some((key, value)) = token.split_once(':').filter(|(k, v)| !k.is_empty() && !v.is_empty()) else {
+                parsed.terms.extend(tokenize(token));
+                continue;
+            };
+            let value = value.to_lowercase();
+            match key.to_lowercase().as_str() {
+                "kind" => parsed.kinds.extend(value.split(',').map(str::to_string)),
+                "exported" => {
+                    parsed.exported = Some(match value.as_str() {
+                        "true" | "yes" => true,
+                        "false" | "no" => false,
+                        _ => bail!("Invalid value '{}' for exported:, use true or false", value),
+                    })
+                }
+                "lang" => parsed.langs.extend(value.split(',').map(str::to_string)),
+                "name" => parsed.name = Some(value),
+                "path" => parsed.paths.push(value),
+                "calls" => parsed.calls.push(value),
+                other => bail!("Unknown filter '{}:'. Use kind, exported, lang, name, path or calls", other),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn matches_kind(&self, entity: &IndexedEntity) -> bool {
+        self.kinds.is_empty() || self.kinds.iter().any(|kind| entity.has_kind(kind))
+    }
+}
+
+/// An entity from the index, with the code it spans.
+#[derive(Debug, Clone)]
+pub struct IndexedEntity {
+    pub name: String,
+    pub kind: String,
+    pub container_name: Option<String>,
+    pub path: String,
+    pub lang: String,
+    pub line: usize,
+    pub line_to: usize,
+    pub exported: bool,
+    body: Vec<String>,
+}
+
+impl IndexedEntity {
+    // Function-valued consts (`const useThing = () => ...`) count as functions too
+    fn is_function_like(&self) -> bool {
+        match self.kind.as_str() {
+            "Function" | "Method" => true,
+            "Variable" | "Constant" => self
+                .body
+                .first()
+                .is_some_and(|first| first.contains("=>") || first.contains("function")),
+            _ => false,
+        }
+    }
+
+    fn has_kind(&self, kind: &str) -> bool {
+        match kind {
+            "function" => self.is_function_like(),
+            // React conventions: `useX` hooks and PascalCase components in .tsx files
+            "hook" => self.is_function_like() && is_hook_name(&self.name),
+            "component" => {
+                self.lang == "tsx" && self.is_function_like() && self.name.starts_with(|c: char| c.is_ascii_uppercase())
+            }
+            other => self.kind.eq_ignore_ascii_case(other),
+        }
+    }
+}
+
+/// A search hit: the entity and the lines of its body that matched.
+#[derive(Debug, Clone)]
+pub struct EntityMatch {
+    pub entity: IndexedEntity,
+    /// `(1-indexed line, text)` of matching lines
+    pub matched_lines: Vec<(usize, String)>,
+    /// Share of the query terms found, in `0.0..=1.0`
+    pub relevance: f64,
+}
+
+fn is_hook_name(name: &str) -> bool {
+    name.strip_prefix("use").is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()))
+}
+
+/// Splits code into lowercase identifier parts: `fetchUserData(id)` → `fetch`, `user`, `data`,
+/// `fetchuserdata`, `id`. Both the whole identifier and its camelCase/snake_case parts are kept.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for ident in text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).filter(|s| !s.is_empty()) {
+        let mut parts = Vec::new();
+        let mut current = String::new();
+        let chars: Vec<char> = ident.chars().collect();
+        for (i, &c) in chars.iter().enumerate() {
+            let boundary = c == '_'
+                || (c.is_uppercase()
+                    && i > 0
+                    && (chars[i - 1].is_lowercase() || chars.get(i + 1).is_some_and(|n| n.is_lowercase())));
+            if boundary && !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            if c != '_' {
+                current.extend(c.to_lowercase());
+            }
+        }
+        if !current.is_empty() {
+            parts.push(current);
+        }
+        let whole = ident.to_lowercase();
+        if parts.len() > 1 || parts.first() != Some(&whole) {
+            tokens.push(whole);
+        }
+        tokens.extend(parts);
+    }
+    tokens
+}
+
+// `callee(`, `callee<T>(` or a Rust macro call `callee!(`, not preceded by another identifier char
+fn calls(line: &str, callee: &str) -> bool {
+    let line = line.to_lowercase();
+    line.match_indices(callee).any(|(start, _)| {
+        let before = line[..start].chars().next_back();
+        let after = line[start + callee.len()..].trim_start();
+        before.is_none_or(|c| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            && (after.starts_with('(') || after.starts_with("<") || after.starts_with("!("))
+    })
+}
+
+fn lang_for_path(path: &Path) -> String {
+    path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_string()
+}
+
+// `export ...`/`pub ...` on the definition line, or a later `export { name }` / `export default name`
+pub(crate) fn is_exported(lang: &str, definition: &str, name: &str, file_lines: &[&str]) -> bool {
+    let definition = definition.trim_start();
+    if lang == "rs" {
+        return definition.starts_with("pub ") || definition.starts_with("pub(");
+    }
+    definition.starts_with("export ")
+        || file_lines.iter().any(|line| {
+            let line = line.trim_start();
+            (line.starts_with("export {") || line.starts_with("export default "))
+                && tokenize_exact(line).contains(&name)
+        })
+}
+
+fn tokenize_exact(text: &str) -> Vec<&str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Loads the indexed entities of one file with their bodies.
+fn file_indexed_entities(file: &Path, project_root: &Path) -> Result<Vec<IndexedEntity>> {
+    let content = std::fs::read_to_string(file)?;
+    let file_lines: Vec<&str> = content.lines().collect();
+    let rel_path = symbols::relative_path(file, project_root);
+    let lang = lang_for_path(file);
+    Ok(symbols::file_entities(file)?
+        .into_iter()
+        .map(|e| {
+            let start = e.line.saturating_sub(1).min(file_lines.len());
+            let end = e.line_to.clamp(start, file_lines.len());
+            let definition = file_lines.get(start).copied().unwrap_or_default();
+            IndexedEntity {
+                exported: is_exported(&lang, definition, &e.name, &file_lines),
+                body: file_lines[start..end].iter().map(|l| l.to_string()).collect(),
+                name: e.name,
+                kind: e.kind,
+                container_name: e.container_name,
+                path: rel_path.clone(),
+                lang: lang.clone(),
+                line: e.line,
+                line_to: e.line_to,
+            }
+        })
+        .collect())
+}
+
+/// Matches one entity against the query, returning `None` when a filter or term fails.
+pub fn match_entity(entity: IndexedEntity, query: &EntityQuery) -> Option<EntityMatch> {
+    if !query.matches_kind(&entity)
+        || query.exported.is_some_and(|exported| exported != entity.exported)
+        || (!query.langs.is_empty() && !query.langs.contains(&entity.lang))
+        || query.name.as_ref().is_some_and(|name| !entity.name.to_lowercase().contains(name))
+        || (!query.paths.is_empty() && !sync::is_excluded(&entity.path.to_lowercase(), &query.paths))
+    {
+        return None;
+    }
+
+    let mut matched_lines = Vec::new();
+    let mut found_terms = vec![false; query.terms.len()];
+    let mut found_calls = vec![false; query.calls.len()];
+    let mut found_phrases = vec![false; query.phrases.len()];
+    // The entity name counts as part of its text
+    let name_tokens = tokenize(&entity.name);
+    for (i, term) in query.terms.iter().enumerate() {
+        found_terms[i] = name_tokens.contains(term);
+    }
+    for (offset, line) in entity.body.iter().enumerate() {
+        let tokens = tokenize(line);
+        let lower = line.to_lowercase();
+        let mut hit = false;
+        for (i, term) in query.terms.iter().enumerate() {
+            if tokens.contains(term) {
+                found_terms[i] = true;
+                hit = true;
+            }
+        }
+        for (i, callee) in query.calls.iter().enumerate() {
+            if calls(line, callee) {
+                found_calls[i] = true;
+                hit = true;
+            }
+        }
+        for (i, phrase) in query.phrases.iter().enumerate() {
+            if lower.contains(phrase.as_str()) {
+                found_phrases[i] = true;
+                hit = true;
+            }
+        }
+        if hit && matched_lines.len() < MAX_MATCH_LINES {
+            matched_lines.push((entity.line + offset, line.trim().to_string()));
+        }
+    }
+    // Calls and phrases are filters; free terms only need one hit and rank by coverage
+    if found_calls.contains(&false) || found_phrases.contains(&false) {
+        return None;
+    }
+    let relevance = if query.terms.is_empty() {
+        1.0
+    } else {
+        let found = found_terms.iter().filter(|f| **f).count();
+        if found == 0 {
+            return None;
+        }
+        found as f64 / query.terms.len() as f64
+    };
+    Some(EntityMatch { entity, matched_lines, relevance })
+}
+
+/// Searches the entity index of the project, combining filters with text matching.
+///
+/// An analysis `profile` narrows the scanned files by its languages, include globs and
+/// excluded directories.
+pub fn search_entities(
+    project_root: &Path,
+    query: &EntityQuery,
+    profile: Option<&AnalysisProfile>,
+    limit: usize,
+) -> Result<Vec<Ranked<EntityMatch>>> {
+    let exclude_dirs: Vec<&str> = match profile {
+        Some(profile) if !profile.exclude_dirs.is_empty() => profile.exclude_dirs.iter().map(|d| d.as_str()).collect(),
+        _ => SKIPPED_DIRS.to_vec(),
+    };
+    let profile_langs = profile.map(|p| p.extensions()).unwrap_or_default();
+    let files = find_files_by_extensions(project_root, INDEXED_EXTENSIONS, &exclude_dirs)?;
+    let mut matches = Vec::new();
+    let mut skipped: HashMap<String, usize> = HashMap::new();
+    for file in files {
+        let lang = lang_for_path(&file);
+        if !query.langs.is_empty() && !query.langs.contains(&lang) {
+            continue;
+        }
+        if !profile_langs.is_empty() && !profile_langs.contains(&lang) {
+            continue;
+        }
+        if profile.is_some_and(|p| !p.includes(&symbols::relative_path(&file, project_root).replace('\\', "/"))) {
+            continue;
+        }
+        match file_indexed_entities(&file, project_root) {
+            Ok(entities) => matches.extend(entities.into_iter().filter_map(|e| match_entity(e, query))),
+            Err(e) => {
+                tracing::debug!(target: "dev_operation::entity_search", file = %file.display(), error = ?e, "Skipping file in entity search.");
+                *skipped.entry(lang).or_default() += 1;
+            }
+        }
+    }
+    if !skipped.is_empty() {
+        tracing::debug!(target: "dev_operation::entity_search", ?skipped, "Some files could not be indexed.");
+    }
+
+    let weights = RankingWeights::load();
+    let mut ranked = ranking::rank(matches, &weights, |m| RankSignals {
+        path: &m.entity.path,
+        modified: ranking::modified_time(project_root, &m.entity.path),
+        is_definition: true,
+        relevance: m.relevance,
+    });
+    ranked.truncate(limit);
+    Ok(ranked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_and_tokenize() {
+        let query = EntityQuery::parse(r#"kind:hook exported:true calls:fetch userData "api/v1""#).unwrap();
+        assert_eq!(query.kinds, vec!["hook"]);
+        assert_eq!(query.exported, Some(true));
+        assert_eq!(query.calls, vec!["fetch"]);
+        assert_eq!(query.terms, vec!["userdata", "user", "data"]);
+        assert_eq!(query.phrases, vec!["api/v1"]);
+        assert!(EntityQuery::parse("exported:maybe").is_err());
+        assert!(EntityQuery::parse("owner:me").is_err());
+
+        assert_eq!(tokenize("fetchJSON(url)"), vec!["fetchjson", "fetch", "json", "url"]);
+        assert_eq!(tokenize("use_state"), vec!["use_state", "use", "state"]);
+    }
+
+    #[test]
+    fn test_search_exported_hooks_that_call_fetch() {
+        let dir = crate::test_support::project_tempdir();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("hooks.ts"),
+            "export function useUser(id: string) {\n  return fetch(`/api/users/${id}`);\n}\n\n\
+             function useLocal() {\n  return fetch('/api/local');\n}\n\n\
+             export function useCached() {\n  return cache.get('user');\n}\n\n\
+             export function loadUser() {\n  return fetch('/api/user');\n}\n",
+        )
+        .unwrap();
+
+        let query = EntityQuery::parse("kind:hook exported:true calls:fetch").unwrap();
+        let results = search_entities(dir.path(), &query, None, 10).unwrap();
+        let names: Vec<&str> = results.iter().map(|r| r.item.entity.name.as_str()).collect();
+        assert_eq!(names, vec!["useUser"]);
+        assert_eq!(results[0].item.matched_lines[0].0, 2);
+
+        let query = EntityQuery::parse("lang:ts user").unwrap();
+        let results = search_entities(dir.path(), &query, None, 10).unwrap();
+        assert_eq!(results.len(), 3, "useUser, useCached (body) and loadUser match 'user'");
+    }
+}
@@ -0,0 +1,1060 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{Number, Value};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path};
+use tree_sitter::{Node, Parser};
+
+use super::symbols::relative_path;
+use crate::codebase_indexing::parser::helpers::get_node_text;
+use crate::file_system::dirs::SKIPPED_DIRS;
+use crate::file_system::search::find_files_by_extensions;
+
+// Nesting depth after which references stop being expanded, so recursive types terminate
+const MAX_DEPTH: usize = 4;
+
+// Chance that an optional property is present in a fixture
+const OPTIONAL_PRESENCE: f64 = 0.7;
+
+// 2024-01-01T00:00:00Z; generated timestamps fall in the two years after it
+const EPOCH_START: i64 = 1_704_067_200;
+const EPOCH_SPAN: i64 = 2 * 365 * 24 * 3600;
+
+const FIRST_NAMES: &[&str] = &[
+    "Ada", "Grace", "Alan", "Linus", "Margaret", "Ken", "Barbara", "Dennis", "Frances", "Edsger",
+    "Radia", "Tim", "Hedy", "Guido", "Katherine", "Yukihiro",
+];
+const LAST_NAMES: &[&str] = &[
+    "Lovelace", "Hopper", "Turing", "Torvalds", "Hamilton", "Thompson", "Liskov", "Ritchie",
+    "Allen", "Dijkstra", "Perlman", "Berners-Lee", "Lamarr", "van Rossum", "Johnson", "Matsumoto",
+];
+const WORDS: &[&str] = &[
+    "amber", "harbor", "quartz", "meadow", "signal", "lantern", "orbit", "cedar", "summit",
+    "ripple", "canvas", "ember", "falcon", "prism", "willow", "beacon", "delta", "nova",
+];
+const CITIES: &[&str] = &["Lisbon", "Toronto", "Osaka", "Nairobi", "Berlin", "Austin", "Melbourne", "Bogotá"];
+const COUNTRIES: &[&str] = &["Portugal", "Canada", "Japan", "Kenya", "Germany", "United States", "Australia", "Colombia"];
+const COMPANIES: &[&str] = &["Acme Corp", "Globex", "Initech", "Umbrella Labs", "Stark Industries", "Hooli"];
+const DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+/// A string format that decides how a string is generated, from a zod refinement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringFormat {
+    Email,
+    Uuid,
+    Url,
+    DateTime,
+}
+
+/// What fixture generation needs to know about a TypeScript type or zod schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeShape {
+    String(Option<StringFormat>),
+    Number { integer: bool },
+    Boolean,
+    Date,
+    Null,
+    /// `any`, `unknown` and types that can't be modelled
+    Unknown,
+    Literal(Value),
+    Array(Box<TypeShape>),
+    Tuple(Vec<TypeShape>),
+    /// `Record<string, T>`
+    Record(Box<TypeShape>),
+    Union(Vec<TypeShape>),
+    Intersection(Vec<TypeShape>),
+    Object(Vec<Property>),
+    /// `Pick<T, keys>` (`keep`) and `Omit<T, keys>`
+    Filtered { base: Box<TypeShape>, keys: Vec<String>, keep: bool },
+    /// A type or schema declared elsewhere, by name
+    Ref(String),
+    /// Methods and function-typed members, which hold no data
+    Function,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub shape: TypeShape,
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelKind {
+    Interface,
+    TypeAlias,
+    Enum,
+    /// A zod schema assigned to a variable
+    Zod,
+}
+
+impl ModelKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelKind::Interface => "interface",
+            ModelKind::TypeAlias => "type",
+            ModelKind::Enum => "enum",
+            ModelKind::Zod => "zod",
+        }
+    }
+}
+
+/// A type or schema fixtures can be generated for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    pub name: String,
+    pub kind: ModelKind,
+    /// File path relative to the project root
+    pub path: String,
+    pub line: usize,
+    pub exported: bool,
+    pub shape: TypeShape,
+}
+
+// --- Parsing ---
+
+fn unquote(text: &str) -> String {
+    text.trim_matches(|c| c == '"' || c == '\'' || c == '`').to_string()
+}
+
+fn named_children(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor).filter(|n| n.kind() != "comment").collect()
+}
+
+fn literal_value(node: Node, source: &str) -> Value {
+    let text = get_node_text(node, source);
+    match node.kind() {
+        "string" | "template_string" => Value::String(unquote(&text)),
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "number" | "unary_expression" => number_value(&text),
+        "literal_type" => named_children(node).first().map(|n| literal_value(*n, source)).unwrap_or(Value::Null),
+        _ => Value::Null,
+    }
+}
+
+fn number_value(text: &str) -> Value {
+    let text = text.replace('_', "");
+    if let Ok(n) = text.parse::<i64>() {
+        return Value::Number(n.into());
+    }
+    text.parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number).unwrap_or(Value::Null)
+}
+
+fn type_shape(node: Node, source: &str) -> TypeShape {
+    let children = named_children(node);
+    match node.kind() {
+        "type_annotation" | "parenthesized_type" | "readonly_type" | "optional_type" => {
+            children.first().map(|n| type_shape(*n, source)).unwrap_or(TypeShape::Unknown)
+        }
+        "predefined_type" => match get_node_text(node, source).as_str() {
+            "string" => TypeShape::String(None),
+            "number" => TypeShape::Number { integer: false },
+            "bigint" => TypeShape::Number { integer: true },
+            "boolean" => TypeShape::Boolean,
+            "null" | "undefined" | "void" => TypeShape::Null,
+            _ => TypeShape::Unknown,
+        },
+        "type_identifier" => named_type(&get_node_text(node, source), &[], source),
+        "nested_type_identifier" => named_type(&get_node_text(node, source), &[], source),
+        "generic_type" => {
+            let name = node.child_by_field_name("name").map(|n| get_node_text(n, source)).unwrap_or_default();
+            let args = node.child_by_field_name("type_arguments").map(named_children).unwrap_or_default();
+            named_type(&name, &args, source)
+        }
+        "array_type" => TypeShape::Array(Box::new(
+            children.first().map(|n| type_shape(*n, source)).unwrap_or(TypeShape::Unknown),
+        )),
+        "tuple_type" => TypeShape::Tuple(children.iter().map(|n| type_shape(*n, source)).collect()),
+        "union_type" => TypeShape::Union(
+            children
+                .iter()
+                .flat_map(|n| match type_shape(*n, source) {
+                    TypeShape::Union(variants) => variants,
+                    shape => vec![shape],
+                })
+                .collect(),
+        ),
+        "intersection_type" => TypeShape::Intersection(children.iter().map(|n| type_shape(*n, source)).collect()),
+        "literal_type" => match literal_value(node, source) {
+            Value::Null => TypeShape::Null,
+            value => TypeShape::Literal(value),
+        },
+        "object_type" | "interface_body" => object_type_shape(node, source),
+        // `typeof userSchema`
+        "type_query" => children
+            .last()
+            .map(|n| TypeShape::Ref(get_node_text(*n, source)))
+            .unwrap_or(TypeShape::Unknown),
+        "template_literal_type" => TypeShape::String(None),
+        "function_type" | "constructor_type" => TypeShape::Function,
+        _ => TypeShape::Unknown,
+    }
+}
+
+// Built-in and utility types by name; anything else refers to a model
+fn named_type(name: &str, args: &[Node], source: &str) -> TypeShape {
+    let arg = |i: usize| args.get(i).map(|n| type_shape(*n, source)).unwrap_or(TypeShape::Unknown);
+    let keys = |i: usize| match arg(i) {
+        TypeShape::Literal(Value::String(key)) => vec![key],
+        TypeShape::Union(variants) => variants
+            .into_iter()
+            .filter_map(|v| match v {
+                TypeShape::Literal(Value::String(key)) => Some(key),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    match name {
+        "Date" => TypeShape::Date,
+        "String" => TypeShape::String(None),
+        "Number" => TypeShape::Number { integer: false },
+        "Boolean" => TypeShape::Boolean,
+        "Array" | "ReadonlyArray" | "Set" => TypeShape::Array(Box::new(arg(0))),
+        "Record" | "Map" => TypeShape::Record(Box::new(arg(1))),
+        "Partial" | "Required" | "Readonly" | "NonNullable" | "Promise" | "Awaited" => arg(0),
+        "Pick" => TypeShape::Filtered { base: Box::new(arg(0)), keys: keys(1), keep: true },
+        "Omit" => TypeShape::Filtered { base: Box::new(arg(0)), keys: keys(1), keep: false },
+        // `z.infer<typeof userSchema>`
+        "z.infer" | "z.input" | "z.output" | "infer" => arg(0),
+        _ => TypeShape::Ref(name.rsplit('.').next().unwrap_or(name).to_string()),
+    }
+}
+
+fn object_type_shape(body: Node, source: &str) -> TypeShape {
+    let mut properties = Vec::new();
+    for member in named_children(body) {
+        let Some(name) = member.child_by_field_name("name") else { continue };
+        let name = unquote(&get_node_text(name, source));
+        match member.kind() {
+            "property_signature" => {
+                let mut cursor = member.walk();
+                let optional = member.children(&mut cursor).any(|c| c.kind() == "?");
+                let shape = member
+                    .child_by_field_name("type")
+                    .map(|t| type_shape(t, source))
+                    .unwrap_or(TypeShape::Unknown);
+                properties.push(Property { name, shape, optional });
+            }
+            "method_signature" => properties.push(Property { name, shape: TypeShape::Function, optional: true }),
+            _ => {}
+        }
+    }
+    TypeShape::Object(properties)
+}
+
+fn enum_shape(body: Node, source: &str) -> TypeShape {
+    let mut next_value = 0i64;
+    let mut variants = Vec::new();
+    for member in named_children(body) {
+        let value = match member.kind() {
+            "enum_assignment" => member.child_by_field_name("value").map(|v| literal_value(v, source)),
+            _ => None,
+        };
+        let value = match value {
+            Some(Value::Number(n)) => {
+                next_value = n.as_i64().unwrap_or(next_value) + 1;
+                Value::Number(n)
+            }
+            Some(value) => value,
+            None => {
+                next_value += 1;
+                Value::Number((next_value - 1).into())
+            }
+        };
+        variants.push(TypeShape::Literal(value));
+    }
+    TypeShape::Union(variants)
+}
+
+// Whether an initializer is a zod schema, i.e. built from the `z` namespace
+fn is_zod_expression(text: &str) -> bool {
+    text.starts_with("z.") || text.starts_with("z\n")
+}
+
+fn zod_arguments(call: Node) -> Vec<Node> {
+    call.child_by_field_name("arguments").map(named_children).unwrap_or_default()
+}
+
+fn zod_array_elements<'t>(node: Option<&Node<'t>>) -> Vec<Node<'t>> {
+    node.filter(|n| n.kind() == "array").map(|n| named_children(*n)).unwrap_or_default()
+}
+
+/// The shape of a zod schema expression and whether it is `.optional()`.
+fn zod_shape(node: Node, source: &str) -> (TypeShape, bool) {
+    match node.kind() {
+        "parenthesized_expression" => named_children(node)
+            .first()
+            .map(|n| zod_shape(*n, source))
+            .unwrap_or((TypeShape::Unknown, false)),
+        "identifier" => (TypeShape::Ref(get_node_text(node, source)), false),
+        "call_expression" => {
+            let Some(function) = node.child_by_field_name("function").filter(|f| f.kind() == "member_expression")
+            else {
+                return (TypeShape::Unknown, false);
+            };
+            let method = function.child_by_field_name("property").map(|p| get_node_text(p, source)).unwrap_or_default();
+            let Some(object) = function.child_by_field_name("object") else { return (TypeShape::Unknown, false) };
+            let args = zod_arguments(node);
+            let object_text = get_node_text(object, source);
+            if object_text == "z" || object_text == "z.coerce" {
+                (zod_constructor(&method, &args, source), false)
+            } else {
+                zod_method(zod_shape(object, source), &method, &args, source)
+            }
+        }
+        _ => (TypeShape::Unknown, false),
+    }
+}
+
+// `z.<method>(args)`
+fn zod_constructor(method: &str, args: &[Node], source: &str) -> TypeShape {
+    let arg = |i: usize| args.get(i).map(|n| zod_shape(*n, source).0).unwrap_or(TypeShape::Unknown);
+    match method {
+        "string" => TypeShape::String(None),
+        "email" => TypeShape::String(Some(StringFormat::Email)),
+        "uuid" | "guid" | "cuid" | "cuid2" => TypeShape::String(Some(StringFormat::Uuid)),
+        "url" => TypeShape::String(Some(StringFormat::Url)),
+        "number" => TypeShape::Number { integer: false },
+        "int" | "bigint" => TypeShape::Number { integer: true },
+        "boolean" => TypeShape::Boolean,
+        "date" => TypeShape::Date,
+        "null" | "undefined" | "void" => TypeShape::Null,
+        "literal" => TypeShape::Literal(args.first().map(|n| literal_value(*n, source)).unwrap_or(Value::Null)),
+        "enum" => TypeShape::Union(
+            zod_array_elements(args.first()).iter().map(|n| TypeShape::Literal(literal_value(*n, source))).collect(),
+        ),
+        "nativeEnum" => args.first().map(|n| TypeShape::Ref(get_node_text(*n, source))).unwrap_or(TypeShape::Unknown),
+        "object" | "strictObject" | "looseObject" => args.first().map(|n| zod_object(*n, source)).unwrap_or(TypeShape::Unknown),
+        "array" | "set" => TypeShape::Array(Box::new(arg(0))),
+        "record" | "map" => TypeShape::Record(Box::new(args.last().map(|n| zod_shape(*n, source).0).unwrap_or(TypeShape::Unknown))),
+        "union" | "discriminatedUnion" => TypeShape::Union(
+            zod_array_elements(args.last()).iter().map(|n| zod_shape(*n, source).0).collect(),
+        ),
+        "intersection" => TypeShape::Intersection(vec![arg(0), arg(1)]),
+        "tuple" => TypeShape::Tuple(zod_array_elements(args.first()).iter().map(|n| zod_shape(*n, source).0).collect()),
+        "optional" | "nullable" | "nullish" => arg(0),
+        "function" => TypeShape::Function,
+        _ => TypeShape::Unknown,
+    }
+}
+
+// `<schema>.<method>(args)`
+fn zod_method((inner, optional): (TypeShape, bool), method: &str, args: &[Node], source: &str) -> (TypeShape, bool) {
+    let with_format = |format: StringFormat| match &inner {
+        TypeShape::String(_) => TypeShape::String(Some(format)),
+        _ => inner.clone(),
+    };
+    let object_keys = || -> Vec<String> {
+        args.first()
+            .filter(|n| n.kind() == "object")
+            .map(|n| {
+                named_children(*n)
+                    .into_iter()
+                    .filter_map(|pair| pair.child_by_field_name("key").map(|k| unquote(&get_node_text(k, source))))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let shape = match method {
+        "optional" | "nullish" => return (inner, true),
+        "email" => with_format(StringFormat::Email),
+        "uuid" | "cuid" | "cuid2" => with_format(StringFormat::Uuid),
+        "url" => with_format(StringFormat::Url),
+        "datetime" => with_format(StringFormat::DateTime),
+        "int" => TypeShape::Number { integer: true },
+        "array" => TypeShape::Array(Box::new(inner)),
+        "extend" => TypeShape::Intersection(vec![
+            inner,
+            args.first().map(|n| zod_object(*n, source)).unwrap_or(TypeShape::Unknown),
+        ]),
+        "merge" | "and" => TypeShape::Intersection(vec![
+            inner,
+            args.first().map(|n| zod_shape(*n, source).0).unwrap_or(TypeShape::Unknown),
+        ]),
+        "or" => TypeShape::Union(vec![inner, args.first().map(|n| zod_shape(*n, source).0).unwrap_or(TypeShape::Unknown)]),
+        "pick" => TypeShape::Filtered { base: Box::new(inner), keys: object_keys(), keep: true },
+        "omit" => TypeShape::Filtered { base: Box::new(inner), keys: object_keys(), keep: false },
+        // Refinements, defaults, descriptions and the like don't change the data's shape
+        _ => inner,
+    };
+    (shape, optional)
+}
+
+fn zod_object(node: Node, source: &str) -> TypeShape {
+    if node.kind() != "object" {
+        return zod_shape(node, source).0;
+    }
+    let properties = named_children(node)
+        .into_iter()
+        .filter_map(|member| match member.kind() {
+            "pair" => {
+                let name = unquote(&get_node_text(member.child_by_field_name("key")?, source));
+                let (shape, optional) = zod_shape(member.child_by_field_name("value")?, source);
+                Some(Property { name, shape, optional })
+            }
+            "shorthand_property_identifier" => {
+                let name = get_node_text(member, source);
+                Some(Property { shape: TypeShape::Ref(name.clone()), name, optional: false })
+            }
+            _ => None,
+        })
+        .collect();
+    TypeShape::Object(properties)
+}
+
+fn collect_models(node: Node, source: &str, path: &str, exported: bool, models: &mut Vec<Model>) {
+    let mut push = |name: Node, kind: ModelKind, shape: TypeShape| {
+        models.push(Model {
+            name: get_node_text(name, source),
+            kind,
+            path: path.to_string(),
+            line: node.start_position().row + 1,
+            exported,
+            shape,
+        })
+    };
+    match node.kind() {
+        "export_statement" => {
+            for child in named_children(node) {
+                collect_models(child, source, path, true, models);
+            }
+        }
+        "interface_declaration" => {
+            let Some(name) = node.child_by_field_name("name") else { return };
+            let mut parts: Vec<TypeShape> = named_children(node)
+                .into_iter()
+                .filter(|c| c.kind() == "extends_type_clause")
+                .flat_map(named_children)
+                .map(|t| type_shape(t, source))
+                .collect();
+            if let Some(body) = node.child_by_field_name("body") {
+                parts.push(object_type_shape(body, source));
+            }
+            let shape = if parts.len() == 1 { parts.remove(0) } else { TypeShape::Intersection(parts) };
+            push(name, ModelKind::Interface, shape);
+        }
+        "type_alias_declaration" => {
+            let (Some(name), Some(value)) = (node.child_by_field_name("name"), node.child_by_field_name("value")) else {
+                return;
+            };
+            push(name, ModelKind::TypeAlias, type_shape(value, source));
+        }
+        "enum_declaration" => {
+            let (Some(name), Some(body)) = (node.child_by_field_name("name"), node.child_by_field_name("body")) else {
+                return;
+            };
+            push(name, ModelKind::Enum, enum_shape(body, source));
+        }
+        "lexical_declaration" | "variable_declaration" => {
+            for declarator in named_children(node).into_iter().filter(|d| d.kind() == "variable_declarator") {
+                let (Some(name), Some(value)) = (declarator.child_by_field_name("name"), declarator.child_by_field_name("value"))
+                else {
+                    continue;
+                };
+                if name.kind() == "identifier" && is_zod_expression(&get_node_text(value, source)) {
+                    push(name, ModelKind::Zod, zod_shape(value, source).0);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Extracts the top-level interfaces, type aliases, enums and zod schemas of a TypeScript source.
+pub fn parse_models(source: &str, path: &str, is_tsx: bool) -> Result<Vec<Model>> {
+    let mut parser = Parser::new();
+    let language = if is_tsx {
+        tree_sitter_typescript::LANGUAGE_TSX.into()
+    } else {
+        tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()
+    };
+    parser
+        .set_language(&language)
+        .map_err(|e| anyhow!("Error loading TS/TSX grammar: {}", e))?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| anyhow!("Failed to parse {}", path))?;
+
+    let mut models = Vec::new();
+    for node in named_children(tree.root_node()) {
+        collect_models(node, source, path, false, &mut models);
+    }
+    Ok(models)
+}
+
+/// The models declared across a project.
+#[derive(Debug, Clone, Default)]
+pub struct ModelIndex {
+    models: Vec<Model>,
+}
+
+impl ModelIndex {
+    pub fn new(models: Vec<Model>) -> Self {
+        Self { models }
+    }
+
+    /// Parses every `.ts` and `.tsx` file of the project, skipping dependencies and build output.
+    pub fn scan(project_root: &Path) -> Result<Self> {
+        let files = find_files_by_extensions(project_root, &["ts", "tsx"], SKIPPED_DIRS)?;
+        let mut models = Vec::new();
+        for file in files {
+            let rel_path = relative_path(&file, project_root);
+            let is_tsx = file.extension().is_some_and(|ext| ext == "tsx");
+            // A single unparsable file shouldn't hide the models of the others
+            let parsed = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {}", rel_path))
+                .and_then(|source| parse_models(&source, &rel_path, is_tsx));
+            match parsed {
+                Ok(found) => models.extend(found),
+                Err(e) => {
+                    tracing::debug!(target: "dev_operation::fixtures", file = %rel_path, error = ?e, "Skipping file while collecting models.")
+                }
+            }
+        }
+        Ok(Self { models })
+    }
+
+    pub fn models(&self) -> &[Model] {
+        &self.models
+    }
+
+    /// Finds a model by name, optionally within one file. `User` also finds a `UserSchema` or
+    /// `userSchema` zod schema when no type of that name exists.
+    pub fn find(&self, name: &str, path: Option<&str>) -> Result<&Model> {
+        let in_path = |m: &&Model| path.is_none_or(|p| m.path == p.trim_start_matches("./"));
+        let schema_name = format!("{}schema", name.to_lowercase());
+        let mut candidates: Vec<&Model> = self.models.iter().filter(in_path).filter(|m| m.name == name).collect();
+        if candidates.is_empty() {
+            candidates = self
+                .models
+                .iter()
+                .filter(in_path)
+                .filter(|m| m.kind == ModelKind::Zod && m.name.to_lowercase() == schema_name)
+                .collect();
+        }
+        match candidates.as_slice() {
+            [model] => Ok(model),
+            [] => bail!("No interface, type, enum or zod schema named '{}' found in the project", name),
+            _ => bail!(
+                "'{}' is declared in several files ({}); pass `path` to pick one",
+                name,
+                candidates.iter().map(|m| m.path.as_str()).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+
+    // Resolves a reference from a model in `from_path`, preferring declarations in the same file
+    fn resolve(&self, name: &str, from_path: &str) -> Option<&Model> {
+        let mut matches = self.models.iter().filter(|m| m.name == name);
+        let first = matches.next()?;
+        if first.path == from_path {
+            return Some(first);
+        }
+        Some(matches.find(|m| m.path == from_path).unwrap_or(first))
+    }
+}
+
+// --- Generation ---
+
+/// A generated value. Dates are kept apart from strings so factories can construct them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sample {
+    Json(Value),
+    Date(String),
+    Array(Vec<Sample>),
+    Object(Vec<(String, Sample)>),
+}
+
+impl Sample {
+    pub fn to_json(&self) -> Value {
+        match self {
+            Sample::Json(value) => value.clone(),
+            Sample::Date(iso) => Value::String(iso.clone()),
+            Sample::Array(items) => Value::Array(items.iter().map(Sample::to_json).collect()),
+            Sample::Object(fields) => Value::Object(fields.iter().map(|(k, v)| (k.clone(), v.to_json())).collect()),
+        }
+    }
+
+    /// The value as a TypeScript expression, indented to `indent` levels.
+    pub fn to_ts(&self, indent: usize) -> String {
+        let pad = "  ".repeat(indent + 1);
+        let close = "  ".repeat(indent);
+        match self {
+            Sample::Json(value) => value.to_string(),
+            Sample::Date(iso) => format!("new Date({})", Value::String(iso.clone())),
+            Sample::Array(items) if items.is_empty() => "[]".to_string(),
+            Sample::Array(items) => {
+                let items: Vec<String> = items.iter().map(|i| format!("{}{},", pad, i.to_ts(indent + 1))).collect();
+                format!("[\n{}\n{}]", items.join("\n"), close)
+            }
+            Sample::Object(fields) if fields.is_empty() => "{}".to_string(),
+            Sample::Object(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(k, v)| format!("{}{}: {},", pad, ts_key(k), v.to_ts(indent + 1)))
+                    .collect();
+                format!("{{\n{}\n{}}}", fields.join("\n"), close)
+            }
+        }
+    }
+}
+
+fn ts_key(key: &str) -> String {
+    let is_identifier = key.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+// SplitMix64: small, seedable and plenty for fixture data
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Inclusive range
+    fn range(&mut self, low: i64, high: i64) -> i64 {
+        low + (self.next_u64() % (high - low + 1) as u64) as i64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        (self.next_u64() as f64 / u64::MAX as f64) < probability
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.next_u64() as usize % items.len()]
+    }
+}
+
+/// Generates fixtures for models of an index. The same seed always produces the same fixtures.
+pub struct FixtureGenerator<'a> {
+    index: &'a ModelIndex,
+    rng: Rng,
+    sequence: i64,
+    unresolved: BTreeSet<String>,
+}
+
+impl<'a> FixtureGenerator<'a> {
+    pub fn new(index: &'a ModelIndex, seed: u64) -> Self {
+        Self { index, rng: Rng(seed), sequence: 0, unresolved: BTreeSet::new() }
+    }
+
+    /// `count` fixtures of `model`.
+    pub fn generate(&mut self, model: &Model, count: usize) -> Vec<Sample> {
+        (0..count).map(|_| self.sample(&model.shape, &model.name, &model.path, 0)).collect()
+    }
+
+    /// References that matched no model and were generated as `null`, e.g. imported library types.
+    pub fn unresolved(&self) -> Vec<String> {
+        self.unresolved.iter().cloned().collect()
+    }
+
+    // The properties of an object-like shape, with intersections merged and references followed
+    fn properties(&mut self, shape: &TypeShape, from_path: &str, depth: usize) -> Option<Vec<Property>> {
+        match shape {
+            TypeShape::Object(properties) => Some(properties.clone()),
+            TypeShape::Ref(name) if depth < MAX_DEPTH => {
+                let model = self.index.resolve(name, from_path)?.clone();
+                self.properties(&model.shape, &model.path, depth + 1)
+            }
+            TypeShape::Intersection(parts) => {
+                let mut merged: Vec<Property> = Vec::new();
+                for part in parts {
+                    for property in self.properties(part, from_path, depth)? {
+                        merged.retain(|p| p.name != property.name);
+                        merged.push(property);
+                    }
+                }
+                Some(merged)
+            }
+            TypeShape::Filtered { base, keys, keep } => {
+                let properties = self.properties(base, from_path, depth)?;
+                Some(properties.into_iter().filter(|p| keys.contains(&p.name) == *keep).collect())
+            }
+            _ => None,
+        }
+    }
+
+    fn object(&mut self, properties: Vec<Property>, from_path: &str, depth: usize) -> Sample {
+        let mut fields = Vec::new();
+        for property in properties {
+            if property.shape == TypeShape::Function {
+                continue;
+            }
+            if property.optional && (depth >= MAX_DEPTH || !self.rng.chance(OPTIONAL_PRESENCE)) {
+                continue;
+            }
+            let value = self.sample(&property.shape, &property.name, from_path, depth + 1);
+            fields.push((property.name, value));
+        }
+        Sample::Object(fields)
+    }
+
+    // `name` is the property (or model) the value is for, which decides what realistic looks like
+    fn sample(&mut self, shape: &TypeShape, name: &str, from_path: &str, depth: usize) -> Sample {
+        match shape {
+            TypeShape::String(format) => Sample::Json(Value::String(self.string_for(name, *format))),
+            TypeShape::Number { integer } => Sample::Json(self.number_for(name, *integer)),
+            TypeShape::Boolean => Sample::Json(Value::Bool(self.rng.chance(0.5))),
+            TypeShape::Date => Sample::Date(self.timestamp()),
+            TypeShape::Null | TypeShape::Unknown | TypeShape::Function => Sample::Json(Value::Null),
+            TypeShape::Literal(value) => Sample::Json(value.clone()),
+            TypeShape::Array(item) => {
+                let len = if depth >= MAX_DEPTH { 0 } else { self.rng.range(1, 3) as usize };
+                Sample::Array((0..len).map(|_| self.sample(item, &singular(name), from_path, depth + 1)).collect())
+            }
+            TypeShape::Tuple(items) => {
+                Sample::Array(items.iter().map(|item| self.sample(item, name, from_path, depth + 1)).collect())
+            }
+            TypeShape::Record(value) => {
+                let len = if depth >= MAX_DEPTH { 0 } else { self.rng.range(1, 2) as usize };
+                let fields = (0..len)
+                    .map(|_| (self.rng.pick(WORDS).to_string(), self.sample(value, name, from_path, depth + 1)))
+                    .collect();
+                Sample::Object(fields)
+            }
+            TypeShape::Union(variants) => {
+                // Absent values make poor fixtures, so `T | null` generates a T
+                let present: Vec<&TypeShape> = variants.iter().filter(|v| **v != TypeShape::Null).collect();
+                match present.as_slice() {
+                    [] => Sample::Json(Value::Null),
+                    variants => {
+                        let variant = *self.rng.pick(variants);
+                        self.sample(variant, name, from_path, depth)
+                    }
+                }
+            }
+            TypeShape::Object(properties) => self.object(properties.clone(), from_path, depth),
+            TypeShape::Intersection(_) | TypeShape::Filtered { .. } => match self.properties(shape, from_path, depth) {
+                Some(properties) => self.object(properties, from_path, depth),
+                None => Sample::Json(Value::Null),
+            },
+            TypeShape::Ref(reference) => {
+                if depth >= MAX_DEPTH {
+                    return Sample::Json(Value::Null);
+                }
+                match self.index.resolve(reference, from_path).cloned() {
+                    Some(model) => self.sample(&model.shape, name, &model.path, depth + 1),
+                    None => {
+                        self.unresolved.insert(reference.clone());
+                        Sample::Json(Value::Null)
+                    }
+                }
+            }
+        }
+    }
+
+    fn timestamp(&mut self) -> String {
+        let secs = EPOCH_START + self.rng.range(0, EPOCH_SPAN);
+        chrono::DateTime::from_timestamp(secs, 0)
+            .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .unwrap_or_default()
+    }
+
+    fn uuid(&mut self) -> String {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.rng.next_u64().to_le_bytes());
+        bytes[8..].copy_from_slice(&self.rng.next_u64().to_le_bytes());
+        uuid::Builder::from_random_bytes(bytes).into_uuid().to_string()
+    }
+
+    fn words(&mut self, count: usize) -> Vec<&'static str> {
+        (0..count).map(|_| *self.rng.pick(WORDS)).collect()
+    }
+
+    fn string_for(&mut self, name: &str, format: Option<StringFormat>) -> String {
+        let key = name.to_lowercase().replace(['_', '-'], "");
+        let first = *self.rng.pick(FIRST_NAMES);
+        let last = *self.rng.pick(LAST_NAMES);
+        match format {
+            Some(StringFormat::Email) => return self.email(first, last),
+            Some(StringFormat::Uuid) => return self.uuid(),
+            Some(StringFormat::Url) => return format!("https://{}/{}", self.rng.pick(DOMAINS), self.words(2).join("-")),
+            Some(StringFormat::DateTime) => return self.timestamp(),
+            None => {}
+        }
+        if key.contains("email") {
+            self.email(first, last)
+        } else if key == "id" || key == "uuid" || key.ends_with("id") && !key.ends_with("valid") && !key.ends_with("paid") {
+            self.uuid()
+        } else if ["avatar", "image", "photo", "picture", "thumbnail"].iter().any(|k| key.contains(k)) {
+            format!("https://picsum.photos/seed/{}/200", self.rng.range(1, 1000))
+        } else if ["url", "website", "link", "href", "homepage"].iter().any(|k| key.contains(k)) {
+            format!("https://{}/{}", self.rng.pick(DOMAINS), self.words(2).join("-"))
+        } else if key.contains("birth") || key == "dob" {
+            format!("{}-{:02}-{:02}", self.rng.range(1950, 2005), self.rng.range(1, 12), self.rng.range(1, 28))
+        } else if name.ends_with("At") || key.ends_with("at") && name.contains('_') || ["date", "time", "timestamp"].iter().any(|k| key.ends_with(k)) {
+            self.timestamp()
+        } else if key == "firstname" || key == "givenname" {
+            first.to_string()
+        } else if key == "lastname" || key == "surname" || key == "familyname" {
+            last.to_string()
+        } else if ["username", "handle", "login", "nickname"].contains(&key.as_str()) {
+            format!("{}{}", first.to_lowercase(), self.rng.range(1, 99))
+        } else if key.contains("name") || key == "author" || key == "owner" {
+            format!("{} {}", first, last)
+        } else if key.contains("phone") || key == "mobile" {
+            format!("+1-555-01{:02}", self.rng.range(0, 99))
+        } else if key == "city" {
+            self.rng.pick(CITIES).to_string()
+        } else if key == "country" {
+            self.rng.pick(COUNTRIES).to_string()
+        } else if key.contains("address") || key == "street" {
+            let street = *self.rng.pick(WORDS);
+            format!("{} {} Street", self.rng.range(1, 999), capitalize(street))
+        } else if key.contains("zip") || key.contains("postal") {
+            format!("{:05}", self.rng.range(10000, 99999))
+        } else if key.contains("company") || key.contains("organization") {
+            self.rng.pick(COMPANIES).to_string()
+        } else if ["title", "subject", "heading", "label", "headline"].iter().any(|k| key.contains(k)) {
+            capitalize(&self.words(3).join(" "))
+        } else if ["description", "bio", "summary", "content", "body", "text", "message", "comment", "note"].iter().any(|k| key.contains(k)) {
+            format!("{}.", capitalize(&self.words(8).join(" ")))
+        } else if key.contains("slug") {
+            self.words(3).join("-")
+        } else if key.contains("color") || key.contains("colour") {
+            format!("#{:06x}", self.rng.range(0, 0xFF_FFFF))
+        } else if ["password", "token", "secret", "hash"].iter().any(|k| key.contains(k)) {
+            format!("{:016x}{:016x}", self.rng.next_u64(), self.rng.next_u64())
+        } else if key.contains("currency") {
+            self.rng.pick(&["USD", "EUR", "JPY", "GBP"]).to_string()
+        } else if key.contains("locale") || key.contains("language") || key == "lang" {
+            self.rng.pick(&["en-US", "pt-PT", "ja-JP", "de-DE"]).to_string()
+        } else {
+            self.words(2).join(" ")
+        }
+    }
+
+    fn email(&mut self, first: &str, last: &str) -> String {
+        let last: String = last.chars().filter(|c| c.is_alphanumeric()).collect();
+        format!("{}.{}@{}", first, last, self.rng.pick(DOMAINS)).to_lowercase()
+    }
+
+    fn number_for(&mut self, name: &str, integer: bool) -> Value {
+        let key = name.to_lowercase().replace(['_', '-'], "");
+        let decimal = |cents: i64| Number::from_f64(cents as f64 / 100.0).map(Value::Number).unwrap_or(Value::Null);
+        let (low, high) = if key == "id" || key.ends_with("id") {
+            self.sequence += 1;
+            return Value::Number(self.sequence.into());
+        } else if key.contains("age") && !key.contains("page") && !key.contains("image") {
+            (18, 80)
+        } else if ["price", "amount", "cost", "balance", "salary", "fee"].iter().any(|k| key.contains(k)) {
+            if integer {
+                (1, 1000)
+            } else {
+                return decimal(self.rng.range(100, 100_000));
+            }
+        } else if ["rating", "score", "stars"].iter().any(|k| key.contains(k)) {
+            (1, 5)
+        } else if ["percent", "progress"].iter().any(|k| key.contains(k)) {
+            (0, 100)
+        } else if key == "lat" || key == "latitude" {
+            return Number::from_f64(self.rng.range(-90_000, 90_000) as f64 / 1000.0).map(Value::Number).unwrap_or(Value::Null);
+        } else if key == "lng" || key == "lon" || key == "longitude" {
+            return Number::from_f64(self.rng.range(-180_000, 180_000) as f64 / 1000.0).map(Value::Number).unwrap_or(Value::Null);
+        } else if key.contains("year") {
+            (1990, 2025)
+        } else if ["count", "quantity", "qty", "stock", "total", "size", "length", "page", "index", "order", "position"].iter().any(|k| key.contains(k)) {
+            (0, 50)
+        } else if ["width", "height", "duration"].iter().any(|k| key.contains(k)) {
+            (100, 1000)
+        } else {
+            (1, 1000)
+        };
+        Value::Number(self.rng.range(low, high).into())
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// Items of `tags` are generated as a `tag`
+fn singular(name: &str) -> String {
+    name.strip_suffix("ies")
+        .map(|s| format!("{}y", s))
+        .or_else(|| name.strip_suffix('s').filter(|s| !s.ends_with('s')).map(str::to_string))
+        .unwrap_or_else(|| name.to_string())
+}
+
+// --- Factory output ---
+
+// `UserSchema` -> `User`
+fn base_name(model: &Model) -> String {
+    let name = match model.kind {
+        ModelKind::Zod => model.name.strip_suffix("Schema").or_else(|| model.name.strip_suffix("schema")).unwrap_or(&model.name),
+        _ => &model.name,
+    };
+    let name = if name.is_empty() { &model.name } else { name };
+    capitalize(name)
+}
+
+fn lower_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) => c.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Import specifier for `target` from a file at `from` (both project-relative; `None` for the
+/// project root), without the extension.
+pub fn import_specifier(from: Option<&str>, target: &str) -> String {
+    let target = Path::new(target).with_extension("");
+    let from_dir = from.and_then(|f| Path::new(f).parent()).unwrap_or(Path::new(""));
+    let target_parts: Vec<Component> = target.components().collect();
+    let from_parts: Vec<Component> = from_dir.components().collect();
+    let common = target_parts.iter().zip(&from_parts).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from_parts.len() - common];
+    parts.extend(target_parts[common..].iter().map(|c| c.as_os_str().to_string_lossy().into_owned()));
+    let specifier = parts.join("/");
+    if specifier.starts_with("..") {
+        specifier
+    } else {
+        format!("./{}", specifier)
+    }
+}
+
+/// A TypeScript module exporting the fixtures as `<model>Fixtures` and a `build<Model>(overrides)`
+/// factory cycling through them. `output_path` (project-relative) is where the module will live,
+/// for the import of the model; without it the import is relative to the project root.
+pub fn render_factory(model: &Model, samples: &[Sample], output_path: Option<&str>) -> Result<String> {
+    if !model.exported {
+        bail!("'{}' in {} is not exported, so a factory can't import it; export it or use the json format", model.name, model.path);
+    }
+    let base = base_name(model);
+    let specifier = import_specifier(output_path, &model.path);
+    let (imports, type_name) = match model.kind {
+        ModelKind::Zod => (
+            format!("import type {{ z }} from \"zod\";\nimport {{ {} }} from \"{}\";\n\ntype {} = z.infer<typeof {}>;", model.name, specifier, base, model.name),
+            base.clone(),
+        ),
+        _ => (format!("import type {{ {} }} from \"{}\";", model.name, specifier), model.name.clone()),
+    };
+    let fixtures_name = format!("{}Fixtures", lower_first(&base));
+    let counter_name = format!("next{}", base);
+    Ok(format!(
+        "// Fixtures for `{name}` ({path}), generated by Galatea. Regenerate them when the type changes.\n\
+         {imports}\n\n\
+         export const {fixtures}: {ty}[] = {values};\n\n\
+         let {counter} = 0;\n\n\
+         /** Returns the next fixture with `overrides` applied. */\n\
+         export function build{base}(overrides: Partial<{ty}> = {{}}): {ty} {{\n\
+         \x20 const fixture = {fixtures}[{counter}++ % {fixtures}.length];\n\
+         \x20 return {{ ...fixture, ...overrides }};\n\
+         }}\n",
+        name = model.name,
+        path = model.path,
+        imports = imports,
+        fixtures = fixtures_name,
+        ty = type_name,
+        values = Sample::Array(samples.to_vec()).to_ts(0),
+        counter = counter_name,
+        base = base,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+import { z } from "zod";
+
+export enum Role { Admin = "admin", Member = "member" }
+
+interface Entity { id: string; createdAt: Date }
+
+export interface User extends Entity {
+  email: string;
+  name: string;
+  age?: number;
+  role: Role;
+  tags: string[];
+  address: { city: string; zip: string } | null;
+  onSave(): void;
+}
+
+export type UserSummary = Pick<User, "id" | "name">;
+
+export const postSchema = z.object({
+  id: z.string().uuid(),
+  title: z.string().min(3),
+  author: z.string().email(),
+  status: z.enum(["draft", "published"]),
+  views: z.number().int().optional(),
+});
+"#;
+
+    fn index() -> ModelIndex {
+        ModelIndex::new(parse_models(SOURCE, "src/models.ts", false).unwrap())
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let index = index();
+        let names: Vec<(&str, ModelKind, bool)> =
+            index.models().iter().map(|m| (m.name.as_str(), m.kind, m.exported)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Role", ModelKind::Enum, true),
+                ("Entity", ModelKind::Interface, false),
+                ("User", ModelKind::Interface, true),
+                ("UserSummary", ModelKind::TypeAlias, true),
+                ("postSchema", ModelKind::Zod, true),
+            ]
+        );
+        // `Post` finds the zod schema
+        let post = index.find("Post", None).unwrap();
+        let TypeShape::Object(properties) = &post.shape else { panic!("expected an object, got {:?}", post.shape) };
+        assert_eq!(properties[0].shape, TypeShape::String(Some(StringFormat::Uuid)));
+        assert!(properties[4].optional);
+        assert!(index.find("Missing", None).is_err());
+    }
+
+    #[test]
+    fn test_generate_fixtures() {
+        let index = index();
+        let user = index.find("User", None).unwrap();
+        let samples = FixtureGenerator::new(&index, 7).generate(user, 5);
+        assert_eq!(samples, FixtureGenerator::new(&index, 7).generate(user, 5));
+
+        for sample in &samples {
+            let json = sample.to_json();
+            assert!(json["email"].as_str().unwrap().contains('@'));
+            assert_eq!(json["id"].as_str().unwrap().len(), 36);
+            assert!(["admin", "member"].contains(&json["role"].as_str().unwrap()));
+            assert!(json["address"]["city"].is_string());
+            assert!(json.get("onSave").is_none());
+            assert!(json.get("age").is_none_or(|age| (18..=80).contains(&age.as_i64().unwrap())));
+        }
+
+        let summary = FixtureGenerator::new(&index, 1).generate(index.find("UserSummary", None).unwrap(), 1);
+        let keys: Vec<String> = summary[0].to_json().as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, vec!["id", "name"]);
+
+        let factory = render_factory(user, &samples[..1], Some("src/test/fixtures/user.ts")).unwrap();
+        assert!(factory.contains("import type { User } from \"../../models\";"));
+        assert!(factory.contains("createdAt: new Date(\""));
+        assert!(factory.contains("export function buildUser(overrides: Partial<User> = {}): User {"));
+        assert!(render_factory(index.find("Entity", None).unwrap(), &samples, None).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use super::editor::FileChange;
+use crate::terminal::package_manager::PackageManager;
+
+/// Most files one request may check or format; larger sets are what `pnpm run format` is for.
+pub const MAX_FILES: usize = 100;
+// Per prettier invocation, so a hanging plugin can't block the request
+const PRETTIER_TIMEOUT: Duration = Duration::from_secs(60);
+
+async fn prettier(root: &Path, args: &[&str], stdin: Option<&str>) -> Result<std::process::Output> {
+    let mut command = PackageManager::detect(root).exec(root, "prettier");
+    command.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+    command.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+    let run = async {
+        let mut child = command.spawn().context("Failed to run prettier")?;
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input.as_bytes()).await.context("Failed to write to prettier")?;
+        }
+        child.wait_with_output().await.context("Failed to run prettier")
+    };
+    match tokio::time::timeout(PRETTIER_TIMEOUT, run).await {
+        Ok(output) => output,
+        Err(_) => bail!("prettier timed out after {}s", PRETTIER_TIMEOUT.as_secs()),
+    }
+}
+
+/// The files among `files` that Prettier would format differently. Files it ignores (through
+/// `.prettierignore` or because it has no parser for them) are never listed.
+pub async fn unformatted_files(root: &Path, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    let paths: Vec<String> = files.iter().map(|f| f.to_string_lossy().into_owned()).collect();
+    let mut args = vec!["--list-different", "--ignore-unknown"];
+    args.extend(paths.iter().map(String::as_str));
+    // Exits with 1 when some file differs and 2 when prettier itself fails
+    let output = prettier(root, &args, None).await?;
+    if output.status.code().unwrap_or(2) >= 2 {
+        bail!("prettier failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| root.join(line))
+        .collect())
+}
+
+/// Prettier's formatting of `files`, as changes for the files that differ.
+pub async fn format_changes(root: &Path, files: &[PathBuf]) -> Result<Vec<FileChange>> {
+    let mut changes = Vec::new();
+    for path in unformatted_files(root, files).await? {
+        let content = fs::read_to_string(&path).context(format!("Failed to read file {}", path.display()))?;
+        let path_arg = path.to_string_lossy().into_owned();
+        let output = prettier(root, &["--stdin-filepath", &path_arg], Some(&content)).await?;
+        if !output.status.success() {
+            bail!("prettier failed on {}: {}", path.display(), String::from_utf8_lossy(&output.stderr).trim());
+        }
+        changes.push(FileChange::Write { path, content: output.stdout });
+    }
+    Ok(changes)
+}
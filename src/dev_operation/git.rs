@@ -0,0 +1,337 @@
+use anyhow::{anyhow, bail, Result};
+use std::path::Path;
+
+use crate::dev_runtime::{events, util};
+use crate::dev_runtime::lsp_pool::{self, EditedFiles};
+use crate::terminal::git::git_output;
+
+/// Trailer naming the session that made a commit, added to every commit made through Galatea.
+pub const SESSION_TRAILER: &str = "Galatea-Session";
+// Diffs beyond this are cut, keeping the head
+const MAX_DIFF_BYTES: usize = 512 * 1024;
+// Used when the repository has no identity configured, so commits don't fail on a fresh machine
+const FALLBACK_IDENTITY: [&str; 4] = ["-c", "user.name=Galatea", "-c", "user.email=galatea@localhost"];
+// Separators for `--format` output; neither can appear in names or subjects
+const FIELD: char = '\x1f';
+const RECORD: char = '\x1e';
+
+/// A changed file, with its index and working tree codes as in `git status --short`: `M`, `A`,
+/// `D`, `R`, `C`, `U`, `?` for untracked, or a space when unchanged on that side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStatus {
+    pub path: String,
+    pub orig_path: Option<String>, // Source of a rename or copy
+    pub index: char,
+    pub worktree: char,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepoStatus {
+    pub branch: Option<String>, // `None` on a detached HEAD
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    pub files: Vec<FileStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub short_hash: String,
+    pub author: String,
+    pub email: String,
+    pub timestamp: i64, // Author date, Unix seconds
+    pub subject: String,
+    pub session: Option<String>, // Value of the `Galatea-Session` trailer
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchInfo {
+    pub name: String,
+    pub current: bool,
+    pub upstream: Option<String>,
+    pub short_hash: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StashEntry {
+    pub index: usize,
+    pub message: String,
+}
+
+/// What a diff compares the working tree or index with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffTarget<'a> {
+    /// Unstaged changes: working tree against the index
+    Worktree,
+    /// Staged changes: index against HEAD
+    Staged,
+    /// Working tree against a commit, branch or tag
+    Rev(&'a str),
+}
+
+// Refuses values git would read as options
+fn check_arg(value: &str, what: &str) -> Result<()> {
+    if value.trim().is_empty() || value.starts_with('-') {
+        bail!("Invalid {} '{}'", what, value);
+    }
+    Ok(())
+}
+
+// Identity options for commands that record an author (commit, stash)
+pub(crate) async fn identity_args(dir: &Path) -> Vec<&'static str> {
+    match git_output(dir, &["config", "user.email"]).await {
+        Ok(_) => Vec::new(),
+        Err(_) => FALLBACK_IDENTITY.to_vec(),
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|v| !v.is_empty())
+}
+
+// Parses the `## branch...upstream [ahead 1, behind 2]` header of `git status --branch`
+fn parse_branch_header(header: &str, status: &mut RepoStatus) {
+    if let Some(branch) = header.strip_prefix("No commits yet on ").or_else(|| header.strip_prefix("Initial commit on ")) {
+        status.branch = Some(branch.to_string());
+        return;
+    }
+    let (refs, tracking) = header.split_once(" [").unwrap_or((header, ""));
+    let (branch, upstream) = refs.split_once("...").unwrap_or((refs, ""));
+    status.branch = Some(branch.to_string()).filter(|b| b != "HEAD (no branch)");
+    status.upstream = non_empty(upstream);
+    for part in tracking.trim_end_matches(']').split(", ") {
+        if let Some(n) = part.strip_prefix("ahead ") {
+            status.ahead = n.parse().unwrap_or(0);
+        } else if let Some(n) = part.strip_prefix("behind ") {
+            status.behind = n.parse().unwrap_or(0);
+        }
+    }
+}
+
+/// Parses `git status --porcelain=v1 --branch -z`.
+pub fn parse_status(output: &str) -> RepoStatus {
+    let mut status = RepoStatus::default();
+    let mut entries = output.split('\0').filter(|e| !e.is_empty());
+    while let Some(entry) = entries.next() {
+        if let Some(header) = entry.strip_prefix("## ") {
+            parse_branch_header(header, &mut status);
+            continue;
+        }
+        let mut codes = entry.chars();
+        let (Some(index), Some(worktree)) = (codes.next(), codes.next()) else { continue };
+        let path = entry.get(3..).unwrap_or_default().to_string();
+        // Renames and copies are followed by their source path
+        let orig_path = matches!(index, 'R' | 'C').then(|| entries.next().map(str::to_string)).flatten();
+        status.files.push(FileStatus { path, orig_path, index, worktree });
+    }
+    status
+}
+
+pub async fn status(dir: &Path) -> Result<RepoStatus> {
+    Ok(parse_status(&git_output(dir, &["status", "--porcelain=v1", "--branch", "-z"]).await?))
+}
+
+/// The unified diff against `target`, limited to `paths` when given, and whether it was cut
+/// at 512 KiB.
+pub async fn diff(dir: &Path, target: DiffTarget<'_>, paths: &[String]) -> Result<(String, bool)> {
+    let mut args = vec!["diff", "--no-color"];
+    match target {
+        DiffTarget::Worktree => {}
+        DiffTarget::Staged => args.push("--cached"),
+        DiffTarget::Rev(rev) => {
+            check_arg(rev, "revision")?;
+            args.push(rev);
+        }
+    }
+    args.push("--");
+    args.extend(paths.iter().map(String::as_str));
+    let mut diff = git_output(dir, &args).await?;
+    if diff.len() <= MAX_DIFF_BYTES {
+        return Ok((diff, false));
+    }
+    diff.truncate(util::truncate_tail(&diff, MAX_DIFF_BYTES).len());
+    Ok((diff, true))
+}
+
+/// Stages `paths`, or every change (including new and deleted files) when empty.
+pub async fn stage(dir: &Path, paths: &[String]) -> Result<()> {
+    let mut args = vec!["add", "--all", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git_output(dir, &args).await.map(drop)
+}
+
+/// Unstages `paths`, or everything when empty, keeping the working tree as it is.
+pub async fn unstage(dir: &Path, paths: &[String]) -> Result<()> {
+    let mut args = vec!["reset", "--quiet", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git_output(dir, &args).await.map(drop)
+}
+
+/// Commits what is staged (with `all`, every change first) with the session trailer added,
+/// and returns the new commit.
+pub async fn commit(dir: &Path, message: &str, all: bool) -> Result<CommitInfo> {
+    if message.trim().is_empty() {
+        bail!("The commit message must not be empty");
+    }
+    if all {
+        stage(dir, &[]).await?;
+    }
+    let message = format!("{}\n\n{}: {}", message.trim_end(), SESSION_TRAILER, events::session_id());
+    let mut args = identity_args(dir).await;
+    args.extend(["commit", "--quiet", "--message", &message]);
+    git_output(dir, &args).await?;
+    let mut head = log(dir, 1, None, None).await?;
+    head.pop().ok_or_else(|| anyhow!("The new commit is not in the log"))
+}
+
+fn parse_log(output: &str) -> Vec<CommitInfo> {
+    output
+        .split(RECORD)
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim_start_matches('\n').split(FIELD).collect();
+            let [hash, short_hash, author, email, timestamp, subject, session] = fields[..] else { return None };
+            Some(CommitInfo {
+                hash: hash.to_string(),
+                short_hash: short_hash.to_string(),
+                author: author.to_string(),
+                email: email.to_string(),
+                timestamp: timestamp.parse().unwrap_or(0),
+                subject: subject.to_string(),
+                session: non_empty(session.trim()),
+            })
+        })
+        .collect()
+}
+
+/// The latest `limit` commits of HEAD, newest first, optionally only those touching `path` or
+/// made in `session`. A repository without commits has an empty log.
+pub async fn log(dir: &Path, limit: usize, path: Option<&str>, session: Option<&str>) -> Result<Vec<CommitInfo>> {
+    if git_output(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).await.is_err() {
+        return Ok(Vec::new());
+    }
+    let limit = format!("--max-count={}", limit);
+    let format = format!(
+        "--format=%H{f}%h{f}%an{f}%ae{f}%at{f}%s{f}%(trailers:key={},valueonly,separator=){r}",
+        SESSION_TRAILER,
+        f = "%x1f",
+        r = "%x1e"
+    );
+    let mut args = vec!["log", limit.as_str(), format.as_str()];
+    let grep = session.map(|s| format!("--grep={}: {}", SESSION_TRAILER, s));
+    if let Some(grep) = &grep {
+        args.extend(["--fixed-strings", grep.as_str()]);
+    }
+    args.push("--");
+    args.extend(path);
+    Ok(parse_log(&git_output(dir, &args).await?))
+}
+
+pub async fn branches(dir: &Path) -> Result<Vec<BranchInfo>> {
+    let format = "--format=%(HEAD)%1f%(refname:short)%1f%(upstream:short)%1f%(objectname:short)%1f%(contents:subject)";
+    let output = git_output(dir, &["for-each-ref", format, "refs/heads"]).await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(FIELD).collect();
+            let [head, name, upstream, short_hash, subject] = fields[..] else { return None };
+            Some(BranchInfo {
+                name: name.to_string(),
+                current: head == "*",
+                upstream: non_empty(upstream),
+                short_hash: short_hash.to_string(),
+                subject: subject.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Creates branch `name` at `start_point` (HEAD by default) without switching to it.
+pub async fn create_branch(dir: &Path, name: &str, start_point: Option<&str>) -> Result<()> {
+    check_arg(name, "branch name")?;
+    let mut args = vec!["branch", name];
+    if let Some(start) = start_point {
+        check_arg(start, "start point")?;
+        args.push(start);
+    }
+    git_output(dir, &args).await.map(drop)
+}
+
+/// Switches to `target` (a branch, tag or commit), creating it as a new branch first with
+/// `create`. Git refuses when uncommitted changes would be overwritten.
+pub async fn checkout(dir: &Path, target: &str, create: bool) -> Result<()> {
+    check_arg(target, "checkout target")?;
+    let args = if create { vec!["checkout", "--quiet", "-b", target] } else { vec!["checkout", "--quiet", target, "--"] };
+    git_output(dir, &args).await?;
+    lsp_pool::files_edited(EditedFiles::Unknown);
+    Ok(())
+}
+
+/// Stashes the uncommitted changes (with `include_untracked`, new files too). Returns false
+/// when there was nothing to stash.
+pub async fn stash_push(dir: &Path, message: Option<&str>, include_untracked: bool) -> Result<bool> {
+    let mut args = identity_args(dir).await;
+    args.extend(["stash", "push"]);
+    if include_untracked {
+        args.push("--include-untracked");
+    }
+    if let Some(message) = message {
+        args.extend(["--message", message]);
+    }
+    let output = git_output(dir, &args).await?;
+    lsp_pool::files_edited(EditedFiles::Unknown);
+    Ok(!output.contains("No local changes to save"))
+}
+
+/// Applies stash `index` (0 is the latest) and drops it. On conflicts git keeps the stash.
+pub async fn stash_pop(dir: &Path, index: usize) -> Result<()> {
+    let stash = format!("stash@{{{}}}", index);
+    let result = git_output(dir, &["stash", "pop", "--quiet", &stash]).await;
+    // A conflicting pop still changes files
+    lsp_pool::files_edited(EditedFiles::Unknown);
+    result.map(drop)
+}
+
+pub async fn stash_list(dir: &Path) -> Result<Vec<StashEntry>> {
+    let output = git_output(dir, &["stash", "list", "--format=%s"]).await?;
+    Ok(output.lines().enumerate().map(|(index, message)| StashEntry { index, message: message.to_string() }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_status() {
+        let output = "## main...origin/main [ahead 2, behind 1]\0 M src/app/page.tsx\0R  src/lib/new.ts\0src/lib/old.ts\0?? notes.md\0";
+        let status = parse_status(output);
+        assert_eq!((status.branch.as_deref(), status.upstream.as_deref(), status.ahead, status.behind), (Some("main"), Some("origin/main"), 2, 1));
+        assert_eq!(status.files[1], FileStatus { path: "src/lib/new.ts".to_string(), orig_path: Some("src/lib/old.ts".to_string()), index: 'R', worktree: ' ' });
+        assert_eq!((status.files[2].index, status.files[2].worktree), ('?', '?'));
+        assert_eq!(parse_status("## HEAD (no branch)\0").branch, None);
+        assert_eq!(parse_status("## No commits yet on main\0").branch.as_deref(), Some("main"));
+    }
+
+    #[tokio::test]
+    async fn test_commit_and_log_by_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        git_output(root, &["init", "--quiet", "--initial-branch=main"]).await.unwrap();
+        assert!(log(root, 10, None, None).await.unwrap().is_empty());
+        fs::write(root.join("page.tsx"), "export default 1;\n").unwrap();
+
+        let commit = commit(root, "Add the page", true).await.unwrap();
+        assert_eq!((commit.subject.as_str(), commit.session.as_deref()), ("Add the page", Some(events::session_id())));
+        assert_eq!(log(root, 10, None, Some(events::session_id())).await.unwrap().len(), 1);
+        assert!(log(root, 10, None, Some("another-session")).await.unwrap().is_empty());
+
+        fs::write(root.join("page.tsx"), "export default 2;\n").unwrap();
+        let (diff, truncated) = diff(root, DiffTarget::Worktree, &[]).await.unwrap();
+        assert!(diff.contains("+export default 2;") && !truncated);
+        assert!(stash_push(root, Some("wip"), false).await.unwrap());
+        assert_eq!(stash_list(root).await.unwrap()[0].message, "On main: wip");
+        assert!(status(root).await.unwrap().files.is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::dev_setup::config_files;
+
+// config.toml table holding the guardrail thresholds, e.g. `[editor_guardrails]`
+const CONFIG_SECTION: &str = "editor_guardrails";
+
+// Files whose exports the export rule watches
+const MODULE_EXTENSIONS: &[&str] = &["js", "jsx", "ts", "tsx", "mjs", "cjs", "mts", "cts"];
+
+// ES module exports and CommonJS `module.exports` / `exports.name =` assignments
+static EXPORT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^\s*export\b|\bmodule\.exports\b|\bexports\.[A-Za-z_$][\w$]*\s*=").unwrap());
+
+/// What happens to an edit that trips a guardrail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardrailAction {
+    /// Hold the edit back until it is retried with `override_guardrails`
+    Block,
+    /// Apply the edit and log a warning
+    #[default]
+    Warn,
+    Off,
+}
+
+/// Thresholds for suspicious editor mutations, from `[editor_guardrails]` in config.toml.
+///
+/// ```toml
+/// [editor_guardrails]
+/// action = "warn"             # or "block", "off"
+/// max_changed_percent = 60
+/// min_file_lines = 20
+/// max_deleted_lines = 200
+/// block_export_removal = true
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct GuardrailConfig {
+    pub action: GuardrailAction,
+    /// Largest share of a file's lines, in percent, one edit may replace or delete
+    pub max_changed_percent: f64,
+    /// Files with fewer lines are exempt from `max_changed_percent`
+    pub min_file_lines: usize,
+    /// Most lines one edit may delete, including deleting the whole file
+    pub max_deleted_lines: usize,
+    /// Flag edits that leave a JavaScript or TypeScript module without any exports
+    pub block_export_removal: bool,
+}
+
+impl Default for GuardrailConfig {
+    fn default() -> Self {
+        Self {
+            action: GuardrailAction::Warn,
+            max_changed_percent: 60.0,
+            min_file_lines: 20,
+            max_deleted_lines: 200,
+            block_export_removal: true,
+        }
+    }
+}
+
+/// The guardrail an edit tripped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardrailRule {
+    ChangedPercent,
+    DeletedLines,
+    ExportRemoval,
+}
+
+impl GuardrailRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GuardrailRule::ChangedPercent => "max_changed_percent",
+            GuardrailRule::DeletedLines => "max_deleted_lines",
+            GuardrailRule::ExportRemoval => "block_export_removal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardrailViolation {
+    pub rule: GuardrailRule,
+    pub message: String,
+}
+
+// Lines a unified diff removes from the old content
+fn removed_lines(diff: &str) -> usize {
+    diff.lines()
+        .skip_while(|line| !line.starts_with("@@"))
+        .filter(|line| line.starts_with('-'))
+        .count()
+}
+
+fn is_module(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|ext| MODULE_EXTENSIONS.contains(&ext))
+}
+
+impl GuardrailConfig {
+    /// Loads the thresholds from config.toml.
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+
+    /// Checks an edit of an existing file. `after` is `None` when the file is deleted, and
+    /// `diff` is the unified diff of the edit.
+    pub fn check(&self, path: &Path, before: &str, after: Option<&str>, diff: &str) -> Vec<GuardrailViolation> {
+        if self.action == GuardrailAction::Off {
+            return Vec::new();
+        }
+        let total = before.lines().count();
+        let removed = match after {
+            Some(_) => removed_lines(diff),
+            None => total,
+        };
+        let mut violations = Vec::new();
+
+        if removed > self.max_deleted_lines {
+            let what = if after.is_none() { "Deleting the file removes" } else { "The edit replaces or deletes" };
+            violations.push(GuardrailViolation {
+                rule: GuardrailRule::DeletedLines,
+                message: format!("{} {} lines (limit {}).", what, removed, self.max_deleted_lines),
+            });
+        }
+        if let Some(after) = after {
+            let percent = if total == 0 { 0.0 } else { removed as f64 * 100.0 / total as f64 };
+            if total >= self.min_file_lines && percent > self.max_changed_percent {
+                violations.push(GuardrailViolation {
+                    rule: GuardrailRule::ChangedPercent,
+                    message: format!(
+                        "The edit replaces or deletes {:.0}% of the file's {} lines (limit {:.0}%).",
+                        percent, total, self.max_changed_percent
+                    ),
+                });
+            }
+            if self.block_export_removal && is_module(path) && EXPORT_RE.is_match(before) && !EXPORT_RE.is_match(after) {
+                violations.push(GuardrailViolation {
+                    rule: GuardrailRule::ExportRemoval,
+                    message: "The edit removes every export of the module, which breaks its importers.".to_string(),
+                });
+            }
+        }
+        violations
+    }
+
+    /// Checks an edit and returns the violations that hold it back. In `warn` mode violations
+    /// are only logged and nothing is returned.
+    pub fn enforce(&self, path: &Path, before: &str, after: Option<&str>, diff: &str) -> Vec<GuardrailViolation> {
+        let violations = self.check(path, before, after, diff);
+        if self.action == GuardrailAction::Warn {
+            for violation in &violations {
+                tracing::warn!(target: "dev_operation::guardrails", path = %path.display(), rule = violation.rule.as_str(), "{}", violation.message);
+            }
+            return Vec::new();
+        }
+        violations
+    }
+}
+
+/// Checks an edit against the guardrails configured in config.toml; see `GuardrailConfig::enforce`.
+pub fn enforce(path: &Path, before: &str, after: Option<&str>, diff: &str) -> Vec<GuardrailViolation> {
+    GuardrailConfig::load().enforce(path, before, after, diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_flags_large_and_export_removing_edits() {
+        let config = GuardrailConfig { max_deleted_lines: 5, ..GuardrailConfig::default() };
+        let path = Path::new("src/lib/util.ts");
+        let before: String = (0..30).map(|i| format!("export const v{} = {};\n", i, i)).collect();
+        let rules = |after: Option<&str>, diff: &str| -> Vec<&'static str> {
+            config.check(path, &before, after, diff).iter().map(|v| v.rule.as_str()).collect()
+        };
+
+        // A one-line change passes
+        let diff = "--- a/util.ts\n+++ b/util.ts\n@@ -1,1 +1,1 @@\n-export const v0 = 0;\n+export const v0 = 1;\n";
+        assert!(rules(Some("export const v0 = 1;\n"), diff).is_empty());
+
+        // Replacing every line trips all three rules
+        let diff: String = "--- a/util.ts\n+++ b/util.ts\n@@ -1,30 +1,1 @@\n".to_string()
+            + &before.lines().map(|l| format!("-{}\n", l)).collect::<String>()
+            + "+const internal = 1;\n";
+        assert_eq!(
+            rules(Some("const internal = 1;\n"), &diff),
+            vec!["max_deleted_lines", "max_changed_percent", "block_export_removal"]
+        );
+
+        // Deleting the file counts all of its lines; small files are exempt from the percentage
+        assert_eq!(rules(None, ""), vec!["max_deleted_lines"]);
+        assert!(config.check(Path::new("a.ts"), "export const a = 1;\n", Some("export const a = 2;\n"), "@@ -1 +1 @@\n-export const a = 1;\n+export const a = 2;\n").is_empty());
+
+        let off = GuardrailConfig { action: GuardrailAction::Off, ..config.clone() };
+        assert!(off.check(path, &before, None, "").is_empty());
+
+        // Only `block` holds edits back; the default warns and lets them through
+        assert_eq!(config.action, GuardrailAction::Warn);
+        assert!(config.enforce(path, &before, None, "").is_empty());
+        let block = GuardrailConfig { action: GuardrailAction::Block, ..config.clone() };
+        assert_eq!(block.enforce(path, &before, None, "").len(), 1);
+    }
+}
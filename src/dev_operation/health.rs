@@ -0,0 +1,284 @@
+use anyhow::Result;
+use std::path::Path;
+
+use super::validation::{StepKind, StepStatus, ValidationRun, ValidationStep};
+use crate::dev_runtime::db::{self, StoredHealthSample};
+
+// Weight of each component in the score; components a run didn't measure are left out
+const BUILD_WEIGHT: f64 = 25.0;
+const TEST_WEIGHT: f64 = 30.0;
+const TYPE_WEIGHT: f64 = 20.0;
+const LINT_WEIGHT: f64 = 15.0;
+const BUNDLE_WEIGHT: f64 = 10.0;
+
+// Error counts at which the type and lint components drop to half
+const TYPE_ERRORS_HALF_SCORE: f64 = 10.0;
+const LINT_ERRORS_HALF_SCORE: f64 = 20.0;
+
+// Build output directories measured for the bundle size, first one found wins
+const BUNDLE_DIRS: &[&str] = &[".next/static", "dist", "build"];
+
+/// What a validation run measured. `None` means the run didn't measure it (step skipped or
+/// not selected, or its output couldn't be parsed).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HealthMetrics {
+    pub build_passed: Option<bool>,
+    pub test_pass_rate: Option<f64>,
+    pub tests_failed: Option<u32>,
+    pub lint_errors: Option<u32>,
+    pub type_errors: Option<u32>,
+    pub bundle_bytes: Option<u64>,
+}
+
+/// The health score recorded for a validation run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthSample {
+    pub run_id: String,
+    pub timestamp: u64,
+    /// 0-100, higher is healthier
+    pub score: f64,
+    pub metrics: HealthMetrics,
+}
+
+impl From<StoredHealthSample> for HealthSample {
+    fn from(stored: StoredHealthSample) -> Self {
+        Self {
+            run_id: stored.run_id,
+            timestamp: stored.timestamp as u64,
+            score: stored.score,
+            metrics: HealthMetrics {
+                build_passed: stored.build_passed,
+                test_pass_rate: stored.test_pass_rate,
+                tests_failed: stored.tests_failed,
+                lint_errors: stored.lint_errors,
+                type_errors: stored.type_errors,
+                bundle_bytes: stored.bundle_bytes,
+            },
+        }
+    }
+}
+
+// Strips ANSI color codes test runners and linters emit even with CI=true
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// The number right before `word` (or its plural) in `line`, e.g. 3 in "3 failed" or "3 errors"
+fn count_before(line: &str, word: &str) -> Option<u32> {
+    let tokens: Vec<&str> = line.split(|c: char| !c.is_ascii_alphanumeric()).filter(|t| !t.is_empty()).collect();
+    tokens
+        .windows(2)
+        .find(|pair| pair[1] == word || pair[1].strip_suffix('s') == Some(word))
+        .and_then(|pair| pair[0].parse().ok())
+}
+
+// Jest: "Tests:       1 failed, 5 passed, 6 total"; Vitest: "Tests  1 failed | 5 passed (6)"
+fn parse_test_counts(output: &str) -> Option<(u32, u32)> {
+    let line = output.lines().rev().find(|line| {
+        let line = line.trim_start();
+        line.starts_with("Tests:") || line.starts_with("Tests ")
+    })?;
+    let passed = count_before(line, "passed");
+    let failed = count_before(line, "failed");
+    if passed.is_none() && failed.is_none() {
+        return None;
+    }
+    Some((passed.unwrap_or(0), failed.unwrap_or(0)))
+}
+
+// ESLint's summary: "✖ 3 problems (2 errors, 1 warning)"
+fn parse_lint_errors(output: &str) -> Option<u32> {
+    output
+        .lines()
+        .rev()
+        .find(|line| line.contains("problem"))
+        .and_then(|line| count_before(line, "error"))
+}
+
+fn parse_type_errors(output: &str) -> u32 {
+    output.lines().filter(|line| line.contains("error TS")).count() as u32
+}
+
+fn bundle_bytes(project_dir: &Path) -> Option<u64> {
+    let dir = BUNDLE_DIRS.iter().map(|d| project_dir.join(d)).find(|d| d.is_dir())?;
+    Some(
+        walkdir::WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.metadata().ok())
+            .map(|m| m.len())
+            .sum(),
+    )
+}
+
+/// Extracts health metrics from a run's step results.
+pub fn metrics_from_run(project_dir: &Path, run: &ValidationRun) -> HealthMetrics {
+    let step = |kind: StepKind| -> Option<&ValidationStep> {
+        run.steps.iter().find(|s| s.kind == kind && s.status != StepStatus::Skipped)
+    };
+    let mut metrics = HealthMetrics::default();
+
+    if let Some(build) = step(StepKind::Build) {
+        metrics.build_passed = Some(build.status == StepStatus::Passed);
+        if build.status == StepStatus::Passed {
+            metrics.bundle_bytes = bundle_bytes(project_dir);
+        }
+    }
+    if let Some(test) = step(StepKind::Test) {
+        let passed = test.status == StepStatus::Passed;
+        match parse_test_counts(&strip_ansi(&test.output)) {
+            Some((ok, failed)) if ok + failed > 0 => {
+                metrics.test_pass_rate = Some(ok as f64 / (ok + failed) as f64);
+                metrics.tests_failed = Some(failed);
+            }
+            _ => metrics.test_pass_rate = Some(if passed { 1.0 } else { 0.0 }),
+        }
+    }
+    if let Some(lint) = step(StepKind::Lint) {
+        metrics.lint_errors = match parse_lint_errors(&strip_ansi(&lint.output)) {
+            Some(errors) => Some(errors),
+            None if lint.status == StepStatus::Passed => Some(0),
+            None => None,
+        };
+    }
+    if let Some(typecheck) = step(StepKind::Typecheck) {
+        let errors = parse_type_errors(&typecheck.output);
+        // A failed typecheck without `error TS` lines still counts as broken
+        metrics.type_errors = Some(if typecheck.status == StepStatus::Failed { errors.max(1) } else { errors });
+    }
+    metrics
+}
+
+/// Composite 0-100 score. The bundle component compares against the previous bundle size, so
+/// growth costs points and shrinking doesn't earn extra.
+pub fn score(metrics: &HealthMetrics, previous_bundle_bytes: Option<u64>) -> f64 {
+    let decay = |count: u32, half: f64| half / (half + count as f64);
+    let bundle = match (metrics.bundle_bytes, previous_bundle_bytes) {
+        (Some(current), Some(previous)) if current > 0 => Some((previous as f64 / current as f64).min(1.0)),
+        (Some(_), _) => Some(1.0),
+        (None, _) => None,
+    };
+    let components = [
+        (metrics.build_passed.map(|p| if p { 1.0 } else { 0.0 }), BUILD_WEIGHT),
+        (metrics.test_pass_rate, TEST_WEIGHT),
+        (metrics.type_errors.map(|n| decay(n, TYPE_ERRORS_HALF_SCORE)), TYPE_WEIGHT),
+        (metrics.lint_errors.map(|n| decay(n, LINT_ERRORS_HALF_SCORE)), LINT_WEIGHT),
+        (bundle, BUNDLE_WEIGHT),
+    ];
+    let (total, weights) = components
+        .iter()
+        .filter_map(|(value, weight)| value.map(|v| (v * weight, *weight)))
+        .fold((0.0, 0.0), |(total, weights), (v, w)| (total + v, weights + w));
+    if weights == 0.0 {
+        return 0.0;
+    }
+    (total / weights * 1000.0).round() / 10.0
+}
+
+/// Scores a finished validation run and appends it to the health time series.
+pub fn record_run(project_dir: &Path, run: &ValidationRun) -> Result<HealthSample> {
+    let metrics = metrics_from_run(project_dir, run);
+    db::with_db(|db| {
+        let previous_bundle = db
+            .health_samples(50)?
+            .into_iter()
+            .find_map(|s| s.bundle_bytes);
+        let sample = HealthSample {
+            run_id: run.id.clone(),
+            timestamp: run.finished_at,
+            score: score(&metrics, previous_bundle),
+            metrics,
+        };
+        db.record_health_sample(&StoredHealthSample {
+            run_id: sample.run_id.clone(),
+            timestamp: sample.timestamp as i64,
+            score: sample.score,
+            build_passed: sample.metrics.build_passed,
+            test_pass_rate: sample.metrics.test_pass_rate,
+            tests_failed: sample.metrics.tests_failed,
+            lint_errors: sample.metrics.lint_errors,
+            type_errors: sample.metrics.type_errors,
+            bundle_bytes: sample.metrics.bundle_bytes,
+        })?;
+        tracing::info!(target: "dev_operation::health", run_id = %sample.run_id, score = sample.score, "Recorded project health score.");
+        Ok(sample)
+    })
+}
+
+/// The most recent `limit` samples, newest first.
+pub fn history(limit: usize) -> Result<Vec<HealthSample>> {
+    db::with_db(|db| db.health_samples(limit)).map(|samples| samples.into_iter().map(Into::into).collect())
+}
+
+/// Ways `current` is worse than `previous`, as readable sentences. Metrics only one of the two
+/// samples measured are not compared.
+pub fn regressions(current: &HealthSample, previous: &HealthSample) -> Vec<String> {
+    let (now, before) = (&current.metrics, &previous.metrics);
+    let mut found = Vec::new();
+    if before.build_passed == Some(true) && now.build_passed == Some(false) {
+        found.push("Build started failing".to_string());
+    }
+    if let (Some(now), Some(before)) = (now.test_pass_rate, before.test_pass_rate) {
+        if now < before {
+            found.push(format!("Test pass rate dropped from {:.0}% to {:.0}%", before * 100.0, now * 100.0));
+        }
+    }
+    let counts = [("Type errors", now.type_errors, before.type_errors), ("Lint errors", now.lint_errors, before.lint_errors)];
+    for (label, now, before) in counts {
+        if let (Some(now), Some(before)) = (now, before) {
+            if now > before {
+                found.push(format!("{} increased from {} to {}", label, before, now));
+            }
+        }
+    }
+    if let (Some(now), Some(before)) = (now.bundle_bytes, before.bundle_bytes) {
+        // Small fluctuations are noise (hashes, timestamps in chunks)
+        if now as f64 > before as f64 * 1.05 {
+            found.push(format!("Bundle grew from {} to {} bytes", before, now));
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_runner_output() {
+        assert_eq!(parse_test_counts("Tests:       1 failed, 5 passed, 6 total\nTime: 2s"), Some((5, 1)));
+        assert_eq!(parse_test_counts(&strip_ansi(" \u{1b}[2m     Tests \u{1b}[22m 3 passed (3)")), Some((3, 0)));
+        assert_eq!(parse_lint_errors("\n✖ 3 problems (2 errors, 1 warning)\n"), Some(2));
+        assert_eq!(parse_lint_errors("✔ No ESLint warnings or errors"), None);
+        assert_eq!(parse_type_errors("a.ts(1,1): error TS2304: x\nb.ts(2,2): error TS2322: y\n"), 2);
+    }
+
+    #[test]
+    fn test_score_and_regressions() {
+        let healthy = HealthMetrics { test_pass_rate: Some(1.0), lint_errors: Some(0), type_errors: Some(0), ..Default::default() };
+        assert_eq!(score(&healthy, None), 100.0);
+        assert_eq!(score(&HealthMetrics::default(), None), 0.0);
+
+        let worse = HealthMetrics { test_pass_rate: Some(0.5), lint_errors: Some(20), ..healthy.clone() };
+        let worse_score = score(&worse, None);
+        assert!(worse_score < 100.0 && worse_score > 0.0);
+
+        let sample = |metrics: HealthMetrics| HealthSample { run_id: "r".to_string(), timestamp: 0, score: 0.0, metrics };
+        let found = regressions(&sample(worse), &sample(healthy));
+        assert_eq!(found, vec!["Test pass rate dropped from 100% to 50%", "Lint errors increased from 0 to 20"]);
+    }
+}
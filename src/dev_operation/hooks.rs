@@ -0,0 +1,413 @@
+use serde::Deserialize;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use super::editor::CommandType;
+use super::sync;
+use crate::dev_setup::config_files;
+use crate::file_system::paths::get_project_root;
+use crate::file_system::ranking::{classify_path, PathClass};
+use crate::terminal::package_manager::PackageManager;
+
+const CONFIG_SECTION: &str = "editor_hooks";
+
+// Hook output is attached to editor responses, keep only its tail
+const MAX_OUTPUT_CHARS: usize = 4000;
+
+// Markers that tools put at the top of files that must not be edited by hand
+const GENERATED_MARKERS: &[&str] = &["@generated", "do not edit", "auto-generated", "autogenerated"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookStage {
+    Pre,
+    Post,
+}
+
+impl HookStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStage::Pre => "pre",
+            HookStage::Post => "post",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookAction {
+    /// `pnpm exec prettier --write <file>`, with the project's package manager
+    Format,
+    /// `pnpm exec eslint <file>`, likewise
+    Lint,
+    /// Refuses edits to generated files (generated directories or an `@generated`-style header)
+    BlockGenerated,
+    /// Custom shell command, with the unified diff on stdin for `post` hooks
+    Shell,
+}
+
+impl HookAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookAction::Format => "format",
+            HookAction::Lint => "lint",
+            HookAction::BlockGenerated => "block_generated",
+            HookAction::Shell => "shell",
+        }
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+/// One `[[editor_hooks]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookConfig {
+    pub name: Option<String>,
+    pub stage: HookStage,
+    pub action: HookAction,
+    /// Globs matched against the project-relative path; all files when empty
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Editor commands the hook applies to (`create`, `str_replace`, ...); all mutations when empty
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Shell command for the `shell` action
+    pub command: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl HookConfig {
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("{}:{}", self.stage.as_str(), self.action.as_str()))
+    }
+
+    fn applies_to(&self, stage: HookStage, command: &CommandType, rel_path: &str) -> bool {
+        // Same glob semantics as sync excludes: slash-less patterns match any path component
+        self.stage == stage
+            && (self.commands.is_empty() || self.commands.iter().any(|c| c == command.as_str()))
+            && (self.patterns.is_empty() || sync::is_excluded(rel_path, &self.patterns))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStatus {
+    Passed,
+    Failed,
+    Blocked,
+    TimedOut,
+}
+
+impl HookStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStatus::Passed => "passed",
+            HookStatus::Failed => "failed",
+            HookStatus::Blocked => "blocked",
+            HookStatus::TimedOut => "timed_out",
+        }
+    }
+}
+
+/// Result of running one hook.
+#[derive(Debug, Clone)]
+pub struct HookOutcome {
+    pub name: String,
+    pub stage: HookStage,
+    pub action: HookAction,
+    pub status: HookStatus,
+    pub output: String,
+    pub duration_ms: u64,
+}
+
+/// The file an edit targets, as seen by hooks.
+pub struct HookTarget<'a> {
+    pub command: &'a CommandType,
+    pub path: &'a Path,
+    pub project_root: &'a Path,
+}
+
+impl HookTarget<'_> {
+    pub fn rel_path(&self) -> String {
+        self.path
+            .strip_prefix(self.project_root)
+            .unwrap_or(self.path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+}
+
+/// Loads the hooks that run before and after editor mutations, ignoring an invalid section.
+///
+/// Hooks are declared in config.toml and matched against the edited path and command:
+///
+/// ```toml
+/// [[editor_hooks]]
+/// name = "no-generated-edits"
+/// stage = "pre"
+/// action = "block_generated"
+///
+/// [[editor_hooks]]
+/// stage = "post"
+/// action = "format"
+/// patterns = ["**/*.ts", "**/*.tsx"]
+///
+/// [[editor_hooks]]
+/// stage = "post"
+/// action = "shell"
+/// command = "./scripts/check-diff.sh"   # receives the unified diff on stdin
+/// ```
+///
+/// A failing `pre` hook blocks the edit; `post` hook results are reported alongside the edit.
+pub fn load_hooks() -> Vec<HookConfig> {
+    config_files::load_section(CONFIG_SECTION)
+}
+
+/// Project root used to resolve hook paths and run hook commands.
+pub fn hook_root() -> Option<PathBuf> {
+    get_project_root().ok()
+}
+
+/// Whether any hook of `stage` applies to the target.
+pub fn has_hooks(hooks: &[HookConfig], stage: HookStage, target: &HookTarget) -> bool {
+    let rel_path = target.rel_path();
+    hooks.iter().any(|h| h.applies_to(stage, target.command, &rel_path))
+}
+
+/// Runs the matching hooks of `stage` in config order.
+///
+/// `diff` is the unified diff of the edit, passed to `shell` hooks on stdin. Pre hooks stop at
+/// the first one that blocks.
+pub fn run_hooks(hooks: &[HookConfig], stage: HookStage, target: &HookTarget, diff: Option<&str>) -> Vec<HookOutcome> {
+    let rel_path = target.rel_path();
+    let mut outcomes = Vec::new();
+    for hook in hooks.iter().filter(|h| h.applies_to(stage, target.command, &rel_path)) {
+        let started = Instant::now();
+        let (status, output) = run_action(hook, target, &rel_path, diff);
+        let outcome = HookOutcome {
+            name: hook.display_name(),
+            stage,
+            action: hook.action,
+            status,
+            output: tail(&output),
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        tracing::debug!(target: "dev_operation::hooks", hook = %outcome.name, status = outcome.status.as_str(), path = %rel_path, "Editor hook finished.");
+        let blocked = outcome.status == HookStatus::Blocked;
+        outcomes.push(outcome);
+        if blocked {
+            break;
+        }
+    }
+    outcomes
+}
+
+/// The first blocking outcome, formatted as an editor error.
+pub fn blocking_error(outcomes: &[HookOutcome]) -> Option<String> {
+    outcomes.iter().find(|o| o.status == HookStatus::Blocked).map(|o| {
+        format!("Error: Edit blocked by hook '{}': {}", o.name, o.output.trim())
+    })
+}
+
+fn run_action(hook: &HookConfig, target: &HookTarget, rel_path: &str, diff: Option<&str>) -> (HookStatus, String) {
+    let timeout = Duration::from_secs(hook.timeout_secs);
+    let path = target.path.to_string_lossy();
+    let package_manager = PackageManager::detect(target.project_root);
+    let command: Vec<String> = match hook.action {
+        HookAction::BlockGenerated => {
+            return match generated_reason(target.path, rel_path) {
+                Some(reason) => (HookStatus::Blocked, reason),
+                None => (HookStatus::Passed, String::new()),
+            };
+        }
+        HookAction::Format => package_manager.exec_command("prettier", &["--write", &path]),
+        HookAction::Lint => package_manager.exec_command("eslint", &[&path]),
+        HookAction::Shell => match hook.command.as_deref() {
+            Some(command) => vec!["bash".to_string(), "-c".to_string(), command.to_string()],
+            None => return (HookStatus::Failed, "Shell hook has no 'command' configured".to_string()),
+        },
+    };
+
+    let mut cmd = Command::new(&command[0]);
+    cmd.args(&command[1..])
+        .current_dir(target.project_root)
+        .env("GALATEA_HOOK_STAGE", hook.stage.as_str())
+        .env("GALATEA_HOOK_COMMAND", target.command.as_str())
+        .env("GALATEA_HOOK_PATH", rel_path);
+    let stdin = if hook.action == HookAction::Shell { diff.unwrap_or_default() } else { "" };
+    match run_with_timeout(cmd, stdin, timeout) {
+        Ok(Some((true, output))) => (HookStatus::Passed, output),
+        // A failing pre hook vetoes the edit; a failing post hook is only reported
+        Ok(Some((false, output))) if hook.stage == HookStage::Pre => (HookStatus::Blocked, output),
+        Ok(Some((false, output))) => (HookStatus::Failed, output),
+        Ok(None) => (HookStatus::TimedOut, format!("Hook timed out after {}s", hook.timeout_secs)),
+        Err(e) => (HookStatus::Failed, format!("Failed to run hook: {}", e)),
+    }
+}
+
+// Runs the command with `stdin` piped in, returning (success, combined output), or None on timeout.
+fn run_with_timeout(mut cmd: Command, stdin: &str, timeout: Duration) -> std::io::Result<Option<(bool, String)>> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Read both pipes on threads so a chatty hook can't fill them and stall
+    let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+        child.stdout.take().map(|s| Box::new(s) as _),
+        child.stderr.take().map(|s| Box::new(s) as _),
+    ];
+    let readers: Vec<_> = pipes
+        .into_iter()
+        .flatten()
+        .map(|mut pipe| {
+            std::thread::spawn(move || {
+                let mut buf = Vec::new();
+                pipe.read_to_end(&mut buf).ok();
+                String::from_utf8_lossy(&buf).into_owned()
+            })
+        })
+        .collect();
+    // Written on a thread too: a hook that never reads stdin would block a large write, and the
+    // timeout with it. The thread ends once the hook exits and the pipe closes.
+    if let Some(mut pipe) = child.stdin.take() {
+        let stdin = stdin.to_string();
+        std::thread::spawn(move || pipe.write_all(stdin.as_bytes()).ok());
+    }
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            child.kill().ok();
+            child.wait().ok();
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    let output: String = readers.into_iter().filter_map(|r| r.join().ok()).collect();
+    Ok(status.map(|s| (s.success(), output)))
+}
+
+fn generated_reason(path: &Path, rel_path: &str) -> Option<String> {
+    if classify_path(rel_path) == PathClass::Generated {
+        return Some(format!("{} is a generated file", rel_path));
+    }
+    let content = fs::read_to_string(path).ok()?;
+    let header = content.lines().take(5).collect::<Vec<_>>().join("\n").to_lowercase();
+    GENERATED_MARKERS
+        .iter()
+        .find(|marker| header.contains(*marker))
+        .map(|marker| format!("{} is marked '{}' in its header", rel_path, marker))
+}
+
+fn tail(output: &str) -> String {
+    let count = output.chars().count();
+    if count <= MAX_OUTPUT_CHARS {
+        return output.to_string();
+    }
+    let tail: String = output.chars().skip(count - MAX_OUTPUT_CHARS).collect();
+    format!("...{}", tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks(config: &str) -> Vec<HookConfig> {
+        let value: toml::Value = toml::from_str(config).unwrap();
+        value.get(CONFIG_SECTION).unwrap().clone().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_hook_matching_and_block_generated() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/api.ts"), "// @generated by openapi\nexport {}\n").unwrap();
+        fs::write(root.join("src/page.tsx"), "export default function Page() {}\n").unwrap();
+
+        let hooks = hooks(
+            r#"
+            [[editor_hooks]]
+            stage = "pre"
+            action = "block_generated"
+
+            [[editor_hooks]]
+            stage = "post"
+            action = "lint"
+            patterns = ["*.tsx"]
+            commands = ["create"]
+            "#,
+        );
+        let command = CommandType::StrReplace;
+        let generated = HookTarget { command: &command, path: &root.join("src/api.ts"), project_root: root };
+        let page = HookTarget { command: &command, path: &root.join("src/page.tsx"), project_root: root };
+
+        let outcomes = run_hooks(&hooks, HookStage::Pre, &generated, None);
+        assert_eq!(outcomes[0].status, HookStatus::Blocked);
+        assert!(blocking_error(&outcomes).unwrap().contains("@generated"));
+        assert!(blocking_error(&run_hooks(&hooks, HookStage::Pre, &page, None)).is_none());
+
+        // The lint hook is limited to create commands
+        assert!(!has_hooks(&hooks, HookStage::Post, &page));
+        let create = CommandType::Create;
+        assert!(has_hooks(&hooks, HookStage::Post, &HookTarget { command: &create, ..page }));
+    }
+
+    #[test]
+    fn test_shell_hook_receives_diff_and_can_veto() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let hooks = hooks(
+            r#"
+            [[editor_hooks]]
+            name = "echo-diff"
+            stage = "post"
+            action = "shell"
+            command = "cat; echo \"path=$GALATEA_HOOK_PATH\""
+
+            [[editor_hooks]]
+            name = "veto"
+            stage = "pre"
+            action = "shell"
+            command = "echo no edits today; exit 1"
+            "#,
+        );
+        let command = CommandType::Create;
+        let target = HookTarget { command: &command, path: &root.join("a.txt"), project_root: root };
+
+        let diff = crate::dev_operation::diff::unified_diff("a.txt", "old line\n", "new line\n");
+        let outcomes = run_hooks(&hooks, HookStage::Post, &target, Some(&diff));
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].status, HookStatus::Passed);
+        assert!(outcomes[0].output.contains("+new line"));
+        assert!(outcomes[0].output.contains("path=a.txt"));
+
+        let outcomes = run_hooks(&hooks, HookStage::Pre, &target, None);
+        assert_eq!(outcomes[0].status, HookStatus::Blocked);
+        assert_eq!(blocking_error(&outcomes).unwrap(), "Error: Edit blocked by hook 'veto': no edits today");
+
+        // A hook that never reads a diff larger than the pipe buffer still times out
+        let stuck = self::hooks(
+            r#"
+            [[editor_hooks]]
+            stage = "post"
+            action = "shell"
+            command = "sleep 30"
+            timeout_secs = 1
+            "#,
+        );
+        let large = "x".repeat(256 * 1024);
+        let outcomes = run_hooks(&stuck, HookStage::Post, &target, Some(&large));
+        assert_eq!(outcomes[0].status, HookStatus::TimedOut);
+    }
+}
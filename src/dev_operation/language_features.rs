@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use lsp_types::{
+    CompletionResponse, Diagnostic, DiagnosticSeverity, DocumentChangeOperation, DocumentChanges,
+    Documentation, HoverContents, MarkedString, NumberOrString, OneOf, Position, Range, TextEdit, Uri,
+    WorkspaceEdit,
+};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use super::symbols::{file_uri_for_path, language_id_for_path, uri_to_relative_path};
+use crate::dev_runtime::lsp_client::LspClient;
+use crate::file_system;
+
+// How long to wait for a server that pushes diagnostics instead of answering pull requests
+const DIAGNOSTICS_WAIT: Duration = Duration::from_secs(5);
+
+/// A range in a project file. Lines and characters are 0-indexed, as in LSP.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeInfo {
+    /// Relative to the project root
+    pub path: String,
+    pub line: u32,
+    pub character: u32,
+    pub end_line: u32,
+    pub end_character: u32,
+}
+
+impl RangeInfo {
+    fn new(path: String, range: Range) -> Self {
+        Self {
+            path,
+            line: range.start.line,
+            character: range.start.character,
+            end_line: range.end.line,
+            end_character: range.end.character,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionInfo {
+    pub label: String,
+    /// PascalCase `CompletionItemKind`, e.g. `Function` or `Property`
+    pub kind: Option<String>,
+    pub detail: Option<String>,
+    pub documentation: Option<String>,
+    /// Text to insert when it differs from `label`
+    pub insert_text: Option<String>,
+}
+
+/// The edits a rename makes to one file, in the order the server listed them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileEdits {
+    /// Relative to the project root
+    pub path: String,
+    pub edits: Vec<(RangeInfo, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticInfo {
+    pub range: RangeInfo,
+    /// `error`, `warning`, `information` or `hint`
+    pub severity: Option<String>,
+    pub code: Option<String>,
+    /// Reporting tool, e.g. `typescript`
+    pub source: Option<String>,
+    pub message: String,
+}
+
+// Sends the file's current content to the server, so answers match what is on disk
+async fn sync_file(client: &LspClient, path: &Path) -> Result<Uri> {
+    let uri = file_uri_for_path(path)?;
+    let content = std::fs::read_to_string(path).context(format!("Failed to read file {}", path.display()))?;
+    client.sync_document(uri.clone(), language_id_for_path(path), content).await?;
+    Ok(uri)
+}
+
+fn marked_string_text(marked: MarkedString) -> String {
+    match marked {
+        MarkedString::String(text) => text,
+        MarkedString::LanguageString(code) => format!("```{}\n{}\n```", code.language, code.value),
+    }
+}
+
+/// Hover contents as a single Markdown string.
+pub(crate) fn hover_text(contents: HoverContents) -> String {
+    match contents {
+        HoverContents::Scalar(marked) => marked_string_text(marked),
+        HoverContents::Array(parts) => parts.into_iter().map(marked_string_text).collect::<Vec<_>>().join("\n\n"),
+        HoverContents::Markup(markup) => markup.value,
+    }
+}
+
+/// Type information and docs for the symbol at `position`, or `None` when there is nothing there.
+pub async fn hover(client: &LspClient, path: &Path, position: Position) -> Result<Option<String>> {
+    let uri = sync_file(client, path).await?;
+    Ok(client.hover(uri, position).await?.map(|hover| hover_text(hover.contents)).filter(|text| !text.trim().is_empty()))
+}
+
+/// Completions at `position`, at most `limit` in the server's order, and whether the server
+/// reported the list as incomplete (or it was cut at `limit`).
+pub async fn completion(client: &LspClient, path: &Path, position: Position, limit: usize) -> Result<(Vec<CompletionInfo>, bool)> {
+    let uri = sync_file(client, path).await?;
+    let (mut items, incomplete) = match client.completion(uri, position).await? {
+        Some(CompletionResponse::Array(items)) => (items, false),
+        Some(CompletionResponse::List(list)) => (list.items, list.is_incomplete),
+        None => (Vec::new(), false),
+    };
+    // Servers send items unordered, with `sort_text` giving their rank
+    items.sort_by(|a, b| a.sort_text.as_ref().unwrap_or(&a.label).cmp(b.sort_text.as_ref().unwrap_or(&b.label)));
+    let truncated = items.len() > limit;
+    let completions = items
+        .into_iter()
+        .take(limit)
+        .map(|item| CompletionInfo {
+            kind: item.kind.map(|kind| format!("{:?}", kind)),
+            detail: item.detail,
+            documentation: item.documentation.map(|doc| match doc {
+                Documentation::String(text) => text,
+                Documentation::MarkupContent(markup) => markup.value,
+            }),
+            insert_text: item.insert_text.filter(|text| *text != item.label),
+            label: item.label,
+        })
+        .collect();
+    Ok((completions, incomplete || truncated))
+}
+
+/// Every reference to the symbol at `position` across the project.
+pub async fn references(client: &LspClient, path: &Path, position: Position, include_declaration: bool) -> Result<Vec<RangeInfo>> {
+    let project_root = file_system::get_project_root()?;
+    let uri = sync_file(client, path).await?;
+    let locations = client.references(uri, position, include_declaration).await?.unwrap_or_default();
+    Ok(locations
+        .into_iter()
+        .map(|location| RangeInfo::new(uri_to_relative_path(&location.uri, &project_root), location.range))
+        .collect())
+}
+
+/// The edits of a workspace edit, grouped by file in path order. File creations, renames and
+/// deletions are left out; renaming a symbol doesn't produce them.
+pub(crate) fn workspace_edit_files(edit: WorkspaceEdit, project_root: &Path) -> Vec<FileEdits> {
+    let mut files: BTreeMap<String, Vec<(RangeInfo, String)>> = BTreeMap::new();
+    let mut add = |uri: &Uri, edits: Vec<TextEdit>| {
+        let path = uri_to_relative_path(uri, project_root);
+        let file = files.entry(path.clone()).or_default();
+        file.extend(edits.into_iter().map(|edit| (RangeInfo::new(path.clone(), edit.range), edit.new_text)));
+    };
+    let annotated = |edits: Vec<OneOf<TextEdit, lsp_types::AnnotatedTextEdit>>| -> Vec<TextEdit> {
+        edits
+            .into_iter()
+            .map(|edit| match edit {
+                OneOf::Left(edit) => edit,
+                OneOf::Right(annotated) => annotated.text_edit,
+            })
+            .collect()
+    };
+    // Servers send either form; `document_changes` wins when both are present
+    match edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => {
+            for edit in edits {
+                add(&edit.text_document.uri, annotated(edit.edits));
+            }
+        }
+        Some(DocumentChanges::Operations(operations)) => {
+            for operation in operations {
+                if let DocumentChangeOperation::Edit(edit) = operation {
+                    add(&edit.text_document.uri, annotated(edit.edits));
+                }
+            }
+        }
+        None => {
+            for (uri, edits) in edit.changes.unwrap_or_default() {
+                add(&uri, edits);
+            }
+        }
+    }
+    files.into_iter().map(|(path, edits)| FileEdits { path, edits }).collect()
+}
+
+/// The edits renaming the symbol at `position` to `new_name` would make. Nothing is written.
+pub async fn rename(client: &LspClient, path: &Path, position: Position, new_name: &str) -> Result<Vec<FileEdits>> {
+    let project_root = file_system::get_project_root()?;
+    let uri = sync_file(client, path).await?;
+    Ok(client
+        .rename(uri, position, new_name)
+        .await?
+        .map(|edit| workspace_edit_files(edit, &project_root))
+        .unwrap_or_default())
+}
+
+fn diagnostic_info(diagnostic: Diagnostic, path: &str) -> DiagnosticInfo {
+    let severity = diagnostic.severity.and_then(|severity| match severity {
+        DiagnosticSeverity::ERROR => Some("error"),
+        DiagnosticSeverity::WARNING => Some("warning"),
+        DiagnosticSeverity::INFORMATION => Some("information"),
+        DiagnosticSeverity::HINT => Some("hint"),
+        _ => None,
+    });
+    DiagnosticInfo {
+        range: RangeInfo::new(path.to_string(), diagnostic.range),
+        severity: severity.map(str::to_string),
+        code: diagnostic.code.map(|code| match code {
+            NumberOrString::Number(n) => n.to_string(),
+            NumberOrString::String(s) => s,
+        }),
+        source: diagnostic.source,
+        message: diagnostic.message,
+    }
+}
+
+/// Errors, warnings and hints the language server reports for the file as it is on disk.
+pub async fn diagnostics(client: &LspClient, path: &Path) -> Result<Vec<DiagnosticInfo>> {
+    let project_root = file_system::get_project_root()?;
+    let rel_path = super::symbols::relative_path(path, &project_root);
+    let uri = sync_file(client, path).await?;
+    let diagnostics = client.diagnostics(uri, DIAGNOSTICS_WAIT).await?;
+    Ok(diagnostics.into_iter().map(|d| diagnostic_info(d, &rel_path)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{LanguageString, MarkupContent, MarkupKind, TextDocumentEdit, OptionalVersionedTextDocumentIdentifier};
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    fn edit(line: u32, new_text: &str) -> TextEdit {
+        TextEdit { range: Range::new(Position::new(line, 4), Position::new(line, 9)), new_text: new_text.to_string() }
+    }
+
+    #[test]
+    fn test_hover_text_and_workspace_edit_files() {
+        let contents = HoverContents::Array(vec![
+            MarkedString::LanguageString(LanguageString { language: "typescript".to_string(), value: "const price: number".to_string() }),
+            MarkedString::String("Price in cents".to_string()),
+        ]);
+        assert_eq!(hover_text(contents), "```typescript\nconst price: number\n```\n\nPrice in cents");
+        let markup = HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value: "**x**".to_string() });
+        assert_eq!(hover_text(markup), "**x**");
+
+        let root = Path::new("/p");
+        let page = Uri::from_str("file:///p/src/page.tsx").unwrap();
+        let lib = Uri::from_str("file:///p/src/lib.ts").unwrap();
+        let changes = WorkspaceEdit { changes: Some(HashMap::from([(page.clone(), vec![edit(3, "total")]), (lib.clone(), vec![edit(0, "total"), edit(7, "total")])])), ..Default::default() };
+        let files = workspace_edit_files(changes, root);
+        assert_eq!(files.iter().map(|f| (f.path.as_str(), f.edits.len())).collect::<Vec<_>>(), vec![("src/lib.ts", 2), ("src/page.tsx", 1)]);
+        assert_eq!(files[1].edits[0].0, RangeInfo { path: "src/page.tsx".to_string(), line: 3, character: 4, end_line: 3, end_character: 9 });
+
+        let document_changes = WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Edits(vec![TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri: page, version: Some(2) },
+                edits: vec![OneOf::Left(edit(1, "total"))],
+            }])),
+            ..Default::default()
+        };
+        assert_eq!(workspace_edit_files(document_changes, root)[0].edits[0].1, "total");
+    }
+}
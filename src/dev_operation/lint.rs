@@ -0,0 +1,114 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use super::editor::{self, FileChange};
+use crate::terminal::package_manager::PackageManager;
+
+/// ESLint's report for one file, as printed by `--format json`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EslintResult {
+    pub file_path: String, // Absolute
+    pub messages: Vec<EslintMessage>,
+    #[serde(default)]
+    pub error_count: usize,
+    #[serde(default)]
+    pub warning_count: usize,
+    #[serde(default)]
+    pub fixable_error_count: usize,
+    #[serde(default)]
+    pub fixable_warning_count: usize,
+    // The fixed source, with `--fix-dry-run` when any fix applies; `messages` are what is left
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EslintMessage {
+    pub rule_id: Option<String>,
+    pub severity: u8, // 1 = warning, 2 = error
+    pub message: String,
+    pub line: Option<usize>,   // 1-indexed
+    pub column: Option<usize>, // 1-indexed
+    pub end_line: Option<usize>,
+    pub end_column: Option<usize>,
+    // Set for parse errors and other problems that stopped ESLint from checking the file
+    #[serde(default)]
+    pub fatal: bool,
+    pub fix: Option<EslintFix>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct EslintFix {
+    pub range: [usize; 2], // UTF-16 offsets into the file
+    pub text: String,
+}
+
+pub fn parse_output(output: &str) -> Result<Vec<EslintResult>> {
+    serde_json::from_str(output.trim()).context("Failed to parse eslint JSON output")
+}
+
+/// Lints `files` (the whole project when empty) with ESLint.
+///
+/// With `fix`, ESLint computes its autofixes without writing them (`--fix-dry-run`): each
+/// fixed file's new content is in `output` and its `messages` are the problems the fixes leave.
+/// Apply them with `apply_fixes`.
+pub async fn run_eslint(project_dir: &Path, files: &[PathBuf], fix: bool) -> Result<Vec<EslintResult>> {
+    let mut command = PackageManager::detect(project_dir).exec(project_dir, "eslint");
+    command.args(["--format", "json"]);
+    if fix {
+        command.arg("--fix-dry-run");
+    }
+    if files.is_empty() {
+        command.arg(".");
+    } else {
+        command.args(files);
+    }
+    // ESLint exits non-zero when it reports problems, so only the JSON output matters
+    let output = command.output().await.context("Failed to run eslint")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        bail!("eslint printed no results: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    parse_output(&stdout).with_context(|| format!("Unexpected eslint output: {}", String::from_utf8_lossy(&output.stderr).trim()))
+}
+
+/// Writes the fixed sources of a `fix` run through the editor, as one change that a single
+/// `undo_edit` reverts. Returns the files written.
+pub fn apply_fixes(editor: &editor::Editor, results: &[EslintResult]) -> Result<Vec<PathBuf>> {
+    let changes: Vec<FileChange> = results
+        .iter()
+        .filter_map(|r| Some(FileChange::Write { path: PathBuf::from(&r.file_path), content: r.output.clone()?.into_bytes() }))
+        .collect();
+    editor::apply_tool_changes(editor, "eslint_fix", &changes).map_err(|e| anyhow!(e))?;
+    Ok(changes.iter().map(|c| c.path().to_path_buf()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_output_and_apply_fixes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("page.tsx");
+        fs::write(&file, "var total = 1\n").unwrap();
+        let json = format!(
+            r#"[{{"filePath": "{}", "messages": [
+                {{"ruleId": "no-unused-vars", "severity": 1, "message": "'total' is assigned a value but never used.", "line": 1, "column": 5, "endLine": 1, "endColumn": 10}}
+            ], "errorCount": 0, "warningCount": 1, "fixableErrorCount": 0, "fixableWarningCount": 0, "output": "let total = 1\n"}},
+            {{"filePath": "{}", "messages": [], "errorCount": 0, "warningCount": 0}}]"#,
+            file.display(),
+            dir.path().join("clean.ts").display()
+        );
+        let results = parse_output(&json).unwrap();
+        assert_eq!(results[0].messages[0].end_column, Some(10));
+        assert_eq!(results[1].output, None);
+
+        let editor = editor::Editor::new();
+        assert_eq!(apply_fixes(&editor, &results).unwrap(), vec![file.clone()]);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "let total = 1\n");
+    }
+}
@@ -0,0 +1,604 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::file_system;
+use crate::terminal::package_manager::PackageManager;
+
+const FLAT_CONFIGS: &[&str] = &["eslint.config.mjs", "eslint.config.js", "eslint.config.cjs", "eslint.config.ts"];
+const LEGACY_JSON_CONFIGS: &[&str] = &[".eslintrc.json", ".eslintrc"];
+const LEGACY_JS_CONFIGS: &[&str] = &[".eslintrc.js", ".eslintrc.cjs", ".eslintrc.yaml", ".eslintrc.yml"];
+const PRETTIER_JSON_CONFIGS: &[&str] = &[".prettierrc.json", ".prettierrc"];
+const PRETTIER_OTHER_CONFIGS: &[&str] = &[
+    ".prettierrc.js",
+    ".prettierrc.cjs",
+    ".prettierrc.mjs",
+    ".prettierrc.yaml",
+    ".prettierrc.yml",
+    ".prettierrc.toml",
+    "prettier.config.js",
+    "prettier.config.cjs",
+    "prettier.config.mjs",
+];
+const PRETTIER_IGNORE_FILE: &str = ".prettierignore";
+
+// Flat configs are JavaScript, so Galatea only edits a block it owns and layers it over the
+// project's own config rather than rewriting arbitrary code
+const BLOCK_START: &str = "// galatea:lint-policy:start (managed by Galatea, edit through /api/editor/lint-policy)";
+const BLOCK_END: &str = "// galatea:lint-policy:end";
+const POLICY_DECL: &str = "const galateaPolicy = ";
+const BASE_DECL: &str = "const galateaBaseConfig = ";
+
+// Hard cap on the validation run, so a hanging eslint can't block the request
+const VALIDATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How the project's ESLint configuration is stored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EslintConfig {
+    /// `eslint.config.*`; Galatea manages a policy block appended to it
+    Flat(PathBuf),
+    /// `.eslintrc.json` or a JSON `.eslintrc`
+    LegacyJson(PathBuf),
+    /// The `eslintConfig` key of package.json
+    PackageJson(PathBuf),
+    /// A JavaScript or YAML `.eslintrc.*`, which is read-only here
+    Unsupported(PathBuf),
+    Missing,
+}
+
+impl EslintConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EslintConfig::Flat(_) => "flat",
+            EslintConfig::LegacyJson(_) => "legacy_json",
+            EslintConfig::PackageJson(_) => "package_json",
+            EslintConfig::Unsupported(_) => "unsupported",
+            EslintConfig::Missing => "missing",
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            EslintConfig::Flat(p)
+            | EslintConfig::LegacyJson(p)
+            | EslintConfig::PackageJson(p)
+            | EslintConfig::Unsupported(p) => Some(p),
+            EslintConfig::Missing => None,
+        }
+    }
+}
+
+/// Finds the ESLint configuration at the project root, preferring flat config like ESLint 9 does.
+pub fn find_eslint_config(root: &Path) -> EslintConfig {
+    if let Some(p) = first_existing(root, FLAT_CONFIGS) {
+        return EslintConfig::Flat(p);
+    }
+    if let Some(p) = first_existing(root, LEGACY_JSON_CONFIGS) {
+        return EslintConfig::LegacyJson(p);
+    }
+    if let Some(p) = first_existing(root, LEGACY_JS_CONFIGS) {
+        return EslintConfig::Unsupported(p);
+    }
+    let package_json = root.join("package.json");
+    if read_json(&package_json).is_ok_and(|v| v.get("eslintConfig").is_some()) {
+        return EslintConfig::PackageJson(package_json);
+    }
+    EslintConfig::Missing
+}
+
+fn first_existing(root: &Path, names: &[&str]) -> Option<PathBuf> {
+    names.iter().map(|n| root.join(n)).find(|p| p.is_file())
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("{} is not valid JSON", path.display()))
+}
+
+/// Rules and ignore patterns Galatea can see and edit. For flat configs these are only the
+/// managed block's; the rules ESLint actually applies come from [`effective_rules`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EslintPolicy {
+    pub rules: Map<String, Value>,
+    pub ignore_patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrettierPolicy {
+    pub config_path: Option<PathBuf>,
+    /// Whether `options` can be edited; false for JavaScript, YAML and TOML configs
+    pub editable: bool,
+    pub options: Map<String, Value>,
+    pub ignore_patterns: Vec<String>,
+}
+
+/// A structured edit to the lint and format policy. Empty fields leave that part untouched.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyChange {
+    /// Rule name to ESLint rule entry (`"off"`, `"warn"`, `"error"` or `[severity, ...options]`)
+    pub set_rules: Vec<(String, Value)>,
+    pub remove_rules: Vec<String>,
+    pub add_ignores: Vec<String>,
+    pub remove_ignores: Vec<String>,
+    pub set_prettier_options: Vec<(String, Value)>,
+    pub remove_prettier_options: Vec<String>,
+    pub add_prettier_ignores: Vec<String>,
+    pub remove_prettier_ignores: Vec<String>,
+}
+
+impl PolicyChange {
+    fn touches_eslint(&self) -> bool {
+        !(self.set_rules.is_empty()
+            && self.remove_rules.is_empty()
+            && self.add_ignores.is_empty()
+            && self.remove_ignores.is_empty())
+    }
+
+    fn touches_prettier_options(&self) -> bool {
+        !(self.set_prettier_options.is_empty() && self.remove_prettier_options.is_empty())
+    }
+
+    fn touches_prettier_ignores(&self) -> bool {
+        !(self.add_prettier_ignores.is_empty() && self.remove_prettier_ignores.is_empty())
+    }
+}
+
+/// Builds an ESLint rule entry, checking the severity is one ESLint accepts.
+pub fn rule_entry(severity: &str, options: Option<Vec<Value>>) -> Result<Value> {
+    let severity = match severity.trim().to_lowercase().as_str() {
+        "off" | "0" => "off",
+        "warn" | "1" => "warn",
+        "error" | "2" => "error",
+        other => bail!("Invalid rule severity '{}': expected off, warn or error", other),
+    };
+    Ok(match options {
+        Some(options) if !options.is_empty() => {
+            Value::Array(std::iter::once(Value::from(severity)).chain(options).collect())
+        }
+        _ => Value::from(severity),
+    })
+}
+
+pub fn read_eslint_policy(config: &EslintConfig) -> Result<EslintPolicy> {
+    let object = match config {
+        EslintConfig::Flat(path) => {
+            let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            return Ok(parse_managed_block(&content)?.unwrap_or_default());
+        }
+        EslintConfig::LegacyJson(path) => read_json(path)?,
+        EslintConfig::PackageJson(path) => read_json(path)?.get("eslintConfig").cloned().unwrap_or_default(),
+        EslintConfig::Unsupported(_) | EslintConfig::Missing => return Ok(EslintPolicy::default()),
+    };
+    Ok(policy_from_legacy(&object))
+}
+
+fn policy_from_legacy(object: &Value) -> EslintPolicy {
+    EslintPolicy {
+        rules: object.get("rules").and_then(Value::as_object).cloned().unwrap_or_default(),
+        ignore_patterns: string_list(object.get("ignorePatterns")),
+    }
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Array(items)) => items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect(),
+        Some(Value::String(s)) => vec![s.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// The managed block's policy, if the flat config has one.
+fn parse_managed_block(content: &str) -> Result<Option<EslintPolicy>> {
+    let Some(start) = content.find(BLOCK_START) else {
+        return Ok(None);
+    };
+    let block = &content[start..];
+    let end = block.find(BLOCK_END).context("Galatea lint policy block is missing its end marker")?;
+    let decl = block[..end].find(POLICY_DECL).context("Galatea lint policy block is malformed")?;
+    // The JSON literal is followed by the export, so only the first value is read
+    let value: Value = serde_json::Deserializer::from_str(&block[decl + POLICY_DECL.len()..end])
+        .into_iter()
+        .next()
+        .context("Galatea lint policy block is empty")?
+        .context("Galatea lint policy block is not valid JSON")?;
+    Ok(Some(EslintPolicy {
+        rules: value.get("rules").and_then(Value::as_object).cloned().unwrap_or_default(),
+        ignore_patterns: string_list(value.get("ignores")),
+    }))
+}
+
+fn render_managed_block(policy: &EslintPolicy, module: bool) -> String {
+    let value = serde_json::json!({ "rules": policy.rules, "ignores": policy.ignore_patterns });
+    let export = if module { "export default" } else { "module.exports =" };
+    format!(
+        "{BLOCK_START}\n{POLICY_DECL}{};\n{export} [\n  ...[].concat(galateaBaseConfig),\n  {{ rules: galateaPolicy.rules }},\n  ...(galateaPolicy.ignores.length ? [{{ ignores: galateaPolicy.ignores }}] : []),\n];\n{BLOCK_END}\n",
+        serde_json::to_string_pretty(&value).unwrap_or_default()
+    )
+}
+
+/// Rewrites a flat config so its export is layered under the managed policy block.
+fn write_managed_block(content: &str, policy: &EslintPolicy) -> Result<String> {
+    if let Some(start) = content.find(BLOCK_START) {
+        let end = content[start..].find(BLOCK_END).context("Galatea lint policy block is missing its end marker")?
+            + start
+            + BLOCK_END.len();
+        let module = content[start..end].contains("export default");
+        let rest = content[end..].trim_start_matches('\n');
+        return Ok(format!("{}{}{}", &content[..start], render_managed_block(policy, module), rest));
+    }
+
+    let (pattern, module) = if content.matches("export default ").count() == 1 {
+        ("export default ", true)
+    } else if content.matches("module.exports = ").count() == 1 {
+        ("module.exports = ", false)
+    } else {
+        bail!("Cannot find a single `export default` or `module.exports =` in the ESLint config to layer the policy over");
+    };
+    let mut updated = content.replacen(pattern, BASE_DECL, 1);
+    if !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push('\n');
+    updated.push_str(&render_managed_block(policy, module));
+    Ok(updated)
+}
+
+fn apply_to_policy(policy: &mut EslintPolicy, change: &PolicyChange) {
+    for (rule, entry) in &change.set_rules {
+        policy.rules.insert(rule.clone(), entry.clone());
+    }
+    for rule in &change.remove_rules {
+        policy.rules.remove(rule);
+    }
+    merge_patterns(&mut policy.ignore_patterns, &change.add_ignores, &change.remove_ignores);
+}
+
+fn merge_patterns(patterns: &mut Vec<String>, add: &[String], remove: &[String]) {
+    patterns.retain(|p| !remove.contains(p));
+    for pattern in add {
+        if !patterns.contains(pattern) {
+            patterns.push(pattern.clone());
+        }
+    }
+}
+
+pub fn find_prettier_config(root: &Path) -> Option<PathBuf> {
+    first_existing(root, PRETTIER_JSON_CONFIGS)
+        .or_else(|| first_existing(root, PRETTIER_OTHER_CONFIGS))
+        .or_else(|| {
+            let package_json = root.join("package.json");
+            read_json(&package_json).is_ok_and(|v| v.get("prettier").is_some()).then_some(package_json)
+        })
+}
+
+fn is_package_json(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n == "package.json")
+}
+
+pub fn read_prettier_policy(root: &Path) -> Result<PrettierPolicy> {
+    let config_path = find_prettier_config(root);
+    let (editable, options) = match &config_path {
+        Some(path) if is_package_json(path) => {
+            (true, read_json(path)?.get("prettier").and_then(Value::as_object).cloned().unwrap_or_default())
+        }
+        // A bare `.prettierrc` may also be YAML, which is left alone
+        Some(path) if PRETTIER_JSON_CONFIGS.iter().any(|n| path.ends_with(n)) => match read_json(path) {
+            Ok(value) => (true, value.as_object().cloned().unwrap_or_default()),
+            Err(_) => (false, Map::new()),
+        },
+        Some(_) => (false, Map::new()),
+        None => (true, Map::new()),
+    };
+    let ignore_patterns = fs::read_to_string(root.join(PRETTIER_IGNORE_FILE))
+        .map(|content| {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(PrettierPolicy { config_path, editable, options, ignore_patterns })
+}
+
+/// Original contents of the files a change wrote, used to roll it back.
+#[derive(Debug, Default)]
+pub struct Backup {
+    files: Vec<(PathBuf, Option<String>)>,
+}
+
+impl Backup {
+    fn save(&mut self, path: &Path) {
+        if !self.files.iter().any(|(p, _)| p == path) {
+            self.files.push((path.to_path_buf(), fs::read_to_string(path).ok()));
+        }
+    }
+
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|(p, _)| p.clone()).collect()
+    }
+
+    /// Puts every file back the way it was, removing files the change created.
+    pub fn restore(self) -> Result<()> {
+        for (path, original) in self.files {
+            match original {
+                Some(content) => fs::write(&path, content),
+                None => fs::remove_file(&path),
+            }
+            .with_context(|| format!("Failed to restore {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes `change` to the project's ESLint and Prettier configuration. Nothing is written
+/// unless every part of the change can be applied.
+pub fn apply_change(root: &Path, change: &PolicyChange) -> Result<Backup> {
+    let mut writes: Vec<(PathBuf, String)> = Vec::new();
+
+    if change.touches_eslint() {
+        let config = find_eslint_config(root);
+        match &config {
+            EslintConfig::Flat(path) => {
+                let content = fs::read_to_string(path)?;
+                let mut policy = parse_managed_block(&content)?.unwrap_or_default();
+                apply_to_policy(&mut policy, change);
+                writes.push((path.clone(), write_managed_block(&content, &policy)?));
+            }
+            EslintConfig::LegacyJson(path) => {
+                let mut value = read_json(path)?;
+                apply_to_legacy(&mut value, change)?;
+                writes.push((path.clone(), serde_json::to_string_pretty(&value)? + "\n"));
+            }
+            EslintConfig::PackageJson(path) => {
+                let mut package = read_json(path)?;
+                let object = package.get_mut("eslintConfig").context("package.json has no eslintConfig")?;
+                apply_to_legacy(object, change)?;
+                writes.push((path.clone(), serde_json::to_string_pretty(&package)? + "\n"));
+            }
+            EslintConfig::Unsupported(path) => bail!(
+                "ESLint config {} can't be edited structurally; convert it to eslint.config.mjs or .eslintrc.json",
+                path.display()
+            ),
+            EslintConfig::Missing => bail!("The project has no ESLint configuration"),
+        }
+    }
+
+    if change.touches_prettier_options() {
+        let current = read_prettier_policy(root)?;
+        if !current.editable {
+            bail!(
+                "Prettier config {} can't be edited structurally; convert it to .prettierrc.json",
+                current.config_path.as_deref().unwrap_or(Path::new("")).display()
+            );
+        }
+        let path = current.config_path.unwrap_or_else(|| root.join(".prettierrc.json"));
+        // package.json may already be queued for an ESLint edit
+        let mut document = match writes.iter().find(|(p, _)| *p == path) {
+            Some((_, content)) => serde_json::from_str(content)?,
+            None if path.exists() => read_json(&path)?,
+            None => Value::Object(Map::new()),
+        };
+        let options = if is_package_json(&path) {
+            document.as_object_mut().context("package.json is not an object")?.entry("prettier").or_insert_with(|| Value::Object(Map::new()))
+        } else {
+            &mut document
+        };
+        let options = options.as_object_mut().context("Prettier config is not an object")?;
+        for (key, value) in &change.set_prettier_options {
+            options.insert(key.clone(), value.clone());
+        }
+        for key in &change.remove_prettier_options {
+            options.remove(key);
+        }
+        let content = serde_json::to_string_pretty(&document)? + "\n";
+        writes.retain(|(p, _)| *p != path);
+        writes.push((path, content));
+    }
+
+    if change.touches_prettier_ignores() {
+        let path = root.join(PRETTIER_IGNORE_FILE);
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        let mut lines: Vec<String> = existing
+            .lines()
+            .filter(|l| !change.remove_prettier_ignores.iter().any(|r| r == l.trim()))
+            .map(str::to_string)
+            .collect();
+        for pattern in &change.add_prettier_ignores {
+            if !lines.iter().any(|l| l.trim() == pattern) {
+                lines.push(pattern.clone());
+            }
+        }
+        writes.push((path, lines.join("\n") + "\n"));
+    }
+
+    let mut backup = Backup::default();
+    for (path, content) in writes {
+        backup.save(&path);
+        if let Err(e) = fs::write(&path, content) {
+            let _ = backup.restore();
+            return Err(e).with_context(|| format!("Failed to write {}", path.display()));
+        }
+    }
+    Ok(backup)
+}
+
+fn apply_to_legacy(object: &mut Value, change: &PolicyChange) -> Result<()> {
+    let mut policy = policy_from_legacy(object);
+    apply_to_policy(&mut policy, change);
+    let object = object.as_object_mut().context("ESLint config is not an object")?;
+    object.insert("rules".to_string(), Value::Object(policy.rules));
+    if policy.ignore_patterns.is_empty() {
+        object.remove("ignorePatterns");
+    } else {
+        object.insert("ignorePatterns".to_string(), policy.ignore_patterns.into());
+    }
+    Ok(())
+}
+
+/// Outcome of a quick lint and format run against the edited configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Validation {
+    /// False when ESLint or Prettier could not load the configuration
+    pub ok: bool,
+    pub sample_file: Option<String>,
+    pub errors: usize,
+    pub warnings: usize,
+    /// Diagnostic output when a tool failed
+    pub output: String,
+}
+
+/// A small, representative file to lint: `requested` when given, else the first source file.
+pub fn sample_file(root: &Path, requested: Option<&str>) -> Result<Option<PathBuf>> {
+    if let Some(requested) = requested {
+        return file_system::paths::resolve_path(requested).map(Some);
+    }
+    let excludes = ["node_modules", ".next", "dist", "build", ".git"];
+    let mut files = file_system::search::find_files_by_extensions(root, &["tsx", "ts", "jsx", "js"], &excludes)?;
+    files.sort();
+    Ok(files.into_iter().find(|f| !f.file_name().is_some_and(|n| n.to_string_lossy().contains(".config."))))
+}
+
+async fn run_tool(root: &Path, args: &[&str]) -> Result<std::process::Output> {
+    let run = PackageManager::detect(root).exec(root, args[0]).args(&args[1..]).output();
+    match tokio::time::timeout(VALIDATION_TIMEOUT, run).await {
+        Ok(output) => output.with_context(|| format!("Failed to run {}", args[0])),
+        Err(_) => bail!("{} timed out after {}s", args[0], VALIDATION_TIMEOUT.as_secs()),
+    }
+}
+
+/// Lints (and format-checks) one sample file with the current configuration. ESLint exits
+/// with 1 when it finds problems and 2 when it can't run, e.g. on an invalid config.
+pub async fn validate(root: &Path, sample: Option<&Path>, check_prettier: bool) -> Result<Validation> {
+    let Some(sample) = sample else {
+        return Ok(Validation { ok: true, output: "No source file to lint".to_string(), ..Default::default() });
+    };
+    let sample_arg = sample.to_string_lossy().into_owned();
+    let mut validation = Validation { ok: true, sample_file: Some(sample_arg.clone()), ..Default::default() };
+
+    if !matches!(find_eslint_config(root), EslintConfig::Missing) {
+        let output = run_tool(root, &["eslint", "--format", "json", "--no-warn-ignored", &sample_arg]).await?;
+        if output.status.code().unwrap_or(2) >= 2 {
+            validation.ok = false;
+            validation.output = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Ok(validation);
+        }
+        let results: Value = serde_json::from_slice(&output.stdout).unwrap_or_default();
+        for file in results.as_array().into_iter().flatten() {
+            validation.errors += file.get("errorCount").and_then(Value::as_u64).unwrap_or(0) as usize;
+            validation.warnings += file.get("warningCount").and_then(Value::as_u64).unwrap_or(0) as usize;
+        }
+    }
+
+    if check_prettier {
+        // `--check` exits with 1 for unformatted files and 2 when prettier itself fails
+        let output = run_tool(root, &["prettier", "--check", &sample_arg]).await?;
+        if output.status.code().unwrap_or(2) >= 2 {
+            validation.ok = false;
+            validation.output = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        }
+    }
+    Ok(validation)
+}
+
+/// The rules ESLint applies to `sample`, after every config layer is merged.
+pub async fn effective_rules(root: &Path, sample: &Path) -> Result<Map<String, Value>> {
+    let output = run_tool(root, &["eslint", "--print-config", &sample.to_string_lossy()]).await?;
+    if !output.status.success() {
+        bail!("eslint --print-config failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let config: Value = serde_json::from_slice(&output.stdout).context("Failed to parse eslint --print-config output")?;
+    Ok(config.get("rules").and_then(Value::as_object).cloned().unwrap_or_default())
+}
+
+/// Writes the change, validates it, and keeps it only if the tools still load the config.
+/// With `dry_run` the change is always rolled back after validating.
+pub async fn apply_and_validate(
+    root: &Path,
+    change: &PolicyChange,
+    sample: Option<&Path>,
+    dry_run: bool,
+) -> Result<(Vec<PathBuf>, Validation, bool)> {
+    let backup = apply_change(root, change)?;
+    let changed = backup.changed_files();
+    let check_prettier = change.touches_prettier_options() || change.touches_prettier_ignores();
+    let validation = match validate(root, sample, check_prettier).await {
+        Ok(v) => v,
+        Err(e) => {
+            backup.restore()?;
+            return Err(e);
+        }
+    };
+    let applied = validation.ok && !dry_run;
+    if !applied {
+        backup.restore()?;
+    }
+    tracing::info!(target: "dev_operation::lint_policy", applied, dry_run, valid = validation.ok, files = changed.len(), "Lint policy change validated.");
+    Ok((changed, validation, applied))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_config_managed_block() {
+        let original = "import next from \"eslint-config-next\";\n\nexport default [...next];\n";
+        let mut policy = EslintPolicy::default();
+        apply_to_policy(
+            &mut policy,
+            &PolicyChange {
+                set_rules: vec![("no-console".to_string(), rule_entry("warn", None).unwrap())],
+                add_ignores: vec!["generated/**".to_string()],
+                ..Default::default()
+            },
+        );
+        let updated = write_managed_block(original, &policy).unwrap();
+        assert!(updated.contains("const galateaBaseConfig = [...next];"));
+        assert_eq!(updated.matches("export default").count(), 1);
+        assert_eq!(parse_managed_block(&updated).unwrap(), Some(policy.clone()));
+
+        // Rewriting replaces the block in place instead of appending another
+        policy.rules.remove("no-console");
+        let rewritten = write_managed_block(&updated, &policy).unwrap();
+        assert_eq!(rewritten.matches(BLOCK_START).count(), 1);
+        assert_eq!(parse_managed_block(&rewritten).unwrap().unwrap().rules.len(), 0);
+
+        assert!(rule_entry("fatal", None).is_err());
+        assert_eq!(
+            rule_entry("2", Some(vec![Value::from("always")])).unwrap(),
+            serde_json::json!(["error", "always"])
+        );
+    }
+
+    #[test]
+    fn test_legacy_json_change_and_restore() {
+        let dir = std::env::temp_dir().join(format!("galatea_lint_policy_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join(".eslintrc.json");
+        fs::write(&config, r#"{"extends": "next", "rules": {"semi": "error"}}"#).unwrap();
+
+        let backup = apply_change(
+            &dir,
+            &PolicyChange {
+                remove_rules: vec!["semi".to_string()],
+                add_ignores: vec!["out/".to_string()],
+                add_prettier_ignores: vec!["out/".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let policy = read_eslint_policy(&find_eslint_config(&dir)).unwrap();
+        assert!(policy.rules.is_empty());
+        assert_eq!(policy.ignore_patterns, vec!["out/"]);
+        assert_eq!(read_prettier_policy(&dir).unwrap().ignore_patterns, vec!["out/"]);
+
+        backup.restore().unwrap();
+        assert!(fs::read_to_string(&config).unwrap().contains("\"semi\""));
+        assert!(!dir.join(PRETTIER_IGNORE_FILE).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
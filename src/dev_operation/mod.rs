@@ -1,3 +1,32 @@
+pub mod assets;
+pub mod changelog;
+pub mod checkpoints;
+pub mod dependencies;
+pub mod diagnostics;
+pub mod diff;
 pub mod editor;
+pub mod editorconfig;
+pub mod entity_search;
+pub mod fixtures;
+pub mod format;
+pub mod git;
+pub mod guardrails;
+pub mod health;
+pub mod hooks;
+pub mod language_features;
+pub mod lint;
+pub mod lint_policy;
+pub mod patch;
+pub mod refactor;
+pub mod reset;
+pub mod snapshots;
+pub mod structure;
+pub mod suggestions;
+pub mod validation;
+pub mod symbols;
+pub mod sync;
+pub mod text_encoding;
+pub mod transfers;
+pub mod typecheck;
 // pub mod models;
 // pub mod script_runner; 
\ No newline at end of file
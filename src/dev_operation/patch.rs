@@ -0,0 +1,538 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::editor::{self, FileChange};
+
+/// Default number of context lines a hunk may drop at each end when its context doesn't match.
+pub const DEFAULT_FUZZ: usize = 2;
+// Lines of a hunk quoted in the error when it can't be placed
+const MAX_QUOTED_LINES: usize = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Hunk {
+    // 0-indexed line where the old side starts, from the `@@` header; `None` for a bare `@@`
+    old_start: Option<usize>,
+    lines: Vec<HunkLine>,
+    // `\ No newline at end of file` after the hunk's last old or new line
+    old_no_newline: bool,
+    new_no_newline: bool,
+}
+
+impl Hunk {
+    fn old_side(lines: &[HunkLine]) -> Vec<&str> {
+        lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Remove(s) => Some(s.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// The changes a patch makes to one file. A `None` path is `/dev/null`: `old_path` for a new
+/// file, `new_path` for a deleted one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    hunks: Vec<Hunk>,
+    // Announced by `diff --git`, whose `---`/`+++` lines (if any) belong to it
+    git_header: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    Modify,
+    Create,
+    Delete,
+    Rename,
+}
+
+impl PatchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatchKind::Modify => "modify",
+            PatchKind::Create => "create",
+            PatchKind::Delete => "delete",
+            PatchKind::Rename => "rename",
+        }
+    }
+}
+
+/// Where a hunk was applied and how loosely it matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HunkReport {
+    /// Line (1-indexed) where the hunk matched, after the hunks before it were applied
+    pub line: usize,
+    /// Lines between where the header said the hunk starts and where it matched
+    pub offset: isize,
+    /// Context lines ignored at either end of the hunk to make it match
+    pub fuzz: usize,
+    /// Whether it only matched with whitespace differences ignored
+    pub whitespace: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchedFile {
+    pub path: PathBuf,
+    pub old_path: Option<PathBuf>, // Set for renames
+    pub kind: PatchKind,
+    pub hunks: Vec<HunkReport>,
+    pub diff: String,
+}
+
+/// A patch checked against the files on disk, ready to be applied with `editor::apply_changes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchPlan {
+    pub files: Vec<PatchedFile>,
+    pub changes: Vec<FileChange>,
+}
+
+// `--- a/src/page.tsx\t2024-01-01 ...` -> `a/src/page.tsx`; `None` for /dev/null
+fn header_path(value: &str) -> Option<String> {
+    let path = value.split('\t').next().unwrap_or_default().trim().trim_matches('"');
+    (path != "/dev/null" && !path.is_empty()).then(|| path.to_string())
+}
+
+// `diff --git a/x b/y`
+fn git_header_paths(rest: &str) -> (Option<String>, Option<String>) {
+    match rest.strip_prefix("a/").and_then(|r| r.rsplit_once(" b/")) {
+        Some((old, new)) => (Some(old.to_string()), Some(new.to_string())),
+        None => {
+            let mut parts = rest.split_whitespace();
+            (parts.next().map(str::to_string), parts.next().map(str::to_string))
+        }
+    }
+}
+
+// `@@ -12,3 +12,4 @@ fn name` -> where the old side starts. An empty old side (`-5,0`) is an
+// insertion after line 5.
+fn hunk_start(header: &str) -> Option<usize> {
+    let old = header.trim_start_matches('@').split_whitespace().next()?.strip_prefix('-')?;
+    let (start, count) = old.split_once(',').unwrap_or((old, "1"));
+    let start: usize = start.parse().ok()?;
+    Some(if count == "0" { start } else { start.saturating_sub(1) })
+}
+
+// `@@ -12,3 +12,4 @@` -> `(3, 4)`, the lines on the old and new side; `None` for a bare `@@`
+fn hunk_counts(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.trim_start_matches('@').split_whitespace();
+    let count = |range: Option<&str>, sign: char| -> Option<usize> {
+        let range = range?.strip_prefix(sign)?;
+        match range.split_once(',') {
+            Some((start, count)) => start.parse::<usize>().ok().and(count.parse().ok()),
+            None => range.parse::<usize>().ok().map(|_| 1),
+        }
+    };
+    Some((count(ranges.next(), '-')?, count(ranges.next(), '+')?))
+}
+
+fn is_file_header(lines: &[&str], i: usize) -> bool {
+    lines[i].starts_with("diff --git ")
+        || (lines[i].starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ ")))
+}
+
+// Reads the hunk whose `@@` header is at `start`. While the header's line counts last, every
+// line belongs to the hunk, so a removed `-- x` over an added `++ y` isn't taken for a file
+// header. Past them (or without them, for a bare `@@`) the hunk goes on to the next header or
+// the first line that isn't part of a hunk, since hand-written patches often get the counts
+// wrong. Returns the hunk and the index after it.
+fn parse_hunk(lines: &[&str], start: usize) -> (Hunk, usize) {
+    let mut hunk = Hunk { old_start: hunk_start(lines[start]), lines: Vec::new(), old_no_newline: false, new_no_newline: false };
+    let (mut old_left, mut new_left) = hunk_counts(lines[start]).unwrap_or((0, 0));
+    let mut trailing_blank = 0;
+    let mut i = start + 1;
+    while i < lines.len() {
+        let counted = old_left > 0 || new_left > 0;
+        if !counted && (lines[i].starts_with("@@") || is_file_header(lines, i)) {
+            break;
+        }
+        let line = lines[i];
+        let mut chars = line.chars();
+        match chars.next() {
+            // Editors strip the space of empty context lines
+            None | Some(' ') => {
+                hunk.lines.push(HunkLine::Context(chars.as_str().to_string()));
+                (old_left, new_left) = (old_left.saturating_sub(1), new_left.saturating_sub(1));
+            }
+            Some('-') => {
+                hunk.lines.push(HunkLine::Remove(chars.as_str().to_string()));
+                old_left = old_left.saturating_sub(1);
+            }
+            Some('+') => {
+                hunk.lines.push(HunkLine::Add(chars.as_str().to_string()));
+                new_left = new_left.saturating_sub(1);
+            }
+            Some('\\') => match hunk.lines.last() {
+                Some(HunkLine::Remove(_)) => hunk.old_no_newline = true,
+                Some(HunkLine::Add(_)) => hunk.new_no_newline = true,
+                _ => (hunk.old_no_newline, hunk.new_no_newline) = (true, true),
+            },
+            _ => break,
+        }
+        trailing_blank = if line.is_empty() && !counted { trailing_blank + 1 } else { 0 };
+        i += 1;
+    }
+    // Blank lines closing the hunk are more likely separators than context
+    hunk.lines.truncate(hunk.lines.len() - trailing_blank);
+    (hunk, i)
+}
+
+/// Parses a unified diff, as printed by `git diff` or `diff -u` or written by hand: `---`/`+++`
+/// headers (with or without `diff --git`), `@@` hunks with or without line numbers, renames,
+/// new and deleted files. Anything around the diff, like Markdown fences, is ignored.
+pub fn parse_patch(text: &str) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let (old_path, new_path) = git_header_paths(rest);
+            patches.push(FilePatch { old_path, new_path, hunks: Vec::new(), git_header: true });
+        } else if line.starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ ")) {
+            let mut old_path = header_path(&line[4..]);
+            let mut new_path = header_path(&lines[i + 1][4..]);
+            // git's `a/` and `b/` prefixes, when both sides have them
+            let prefixed = |path: &Option<String>, prefix: &str| path.as_ref().is_none_or(|p| p.starts_with(prefix));
+            if prefixed(&old_path, "a/") && prefixed(&new_path, "b/") {
+                old_path = old_path.map(|p| p[2..].to_string());
+                new_path = new_path.map(|p| p[2..].to_string());
+            }
+            match patches.last_mut() {
+                Some(patch) if patch.git_header && patch.hunks.is_empty() => {
+                    patch.old_path = old_path;
+                    patch.new_path = new_path;
+                    patch.git_header = false;
+                }
+                _ => patches.push(FilePatch { old_path, new_path, hunks: Vec::new(), git_header: false }),
+            }
+            i += 2;
+            continue;
+        } else if line.starts_with("@@") {
+            let patch = patches.last_mut().ok_or_else(|| anyhow!("Hunk at line {} comes before any `--- a/path` and `+++ b/path` header", i + 1))?;
+            let (hunk, next) = parse_hunk(&lines, i);
+            patch.hunks.push(hunk);
+            i = next;
+            continue;
+        } else if let Some(patch) = patches.last_mut().filter(|p| p.git_header) {
+            // Extended headers of `diff --git`
+            if let Some(path) = line.strip_prefix("rename from ") {
+                patch.old_path = Some(path.to_string());
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                patch.new_path = Some(path.to_string());
+            } else if line.starts_with("new file mode") {
+                patch.old_path = None;
+            } else if line.starts_with("deleted file mode") {
+                patch.new_path = None;
+            } else if line.starts_with("GIT binary patch") || line.starts_with("Binary files ") {
+                bail!("Binary patches are not supported ({})", patch.new_path.as_deref().or(patch.old_path.as_deref()).unwrap_or("?"));
+            }
+        }
+        i += 1;
+    }
+    if patches.is_empty() {
+        bail!("No file changes found; expected a unified diff with `--- a/path`, `+++ b/path` and `@@` hunks");
+    }
+    Ok(patches)
+}
+
+// A file as lines, remembering its line endings
+#[derive(Debug, Clone)]
+struct Text {
+    lines: Vec<String>,
+    eol: &'static str,
+    final_newline: bool,
+}
+
+impl Text {
+    fn new(content: &str) -> Self {
+        Self {
+            lines: content.lines().map(str::to_string).collect(),
+            eol: if content.contains("\r\n") { "\r\n" } else { "\n" },
+            final_newline: content.is_empty() || content.ends_with('\n'),
+        }
+    }
+
+    fn content(&self) -> String {
+        let mut content = self.lines.join(self.eol);
+        if self.final_newline && !self.lines.is_empty() {
+            content.push_str(self.eol);
+        }
+        content
+    }
+}
+
+struct Located {
+    pos: usize,
+    lead: usize,
+    trail: usize,
+    loose: bool,
+}
+
+fn lines_match(file: &[String], old: &[&str], loose: bool) -> bool {
+    file.iter().zip(old).all(|(a, b)| if loose { a.split_whitespace().eq(b.split_whitespace()) } else { a == b })
+}
+
+// Finds where `old` occurs at or after `min_pos`, nearest to `expected` first
+fn search(file: &[String], old: &[&str], expected: Option<usize>, min_pos: usize, loose: bool) -> Option<usize> {
+    if old.is_empty() {
+        return Some(expected.unwrap_or(file.len()).clamp(min_pos, file.len()));
+    }
+    let last = file.len().checked_sub(old.len())?;
+    let mut candidates: Vec<usize> = (min_pos..=last).collect();
+    if let Some(expected) = expected {
+        candidates.sort_by_key(|&pos| (pos.abs_diff(expected), pos));
+    }
+    candidates.into_iter().find(|&pos| lines_match(&file[pos..pos + old.len()], old, loose))
+}
+
+// Places a hunk: exactly first, then ignoring whitespace, then dropping up to `fuzz` context
+// lines at each end, like `patch --fuzz`
+fn locate(file: &[String], hunk: &Hunk, expected: Option<usize>, min_pos: usize, fuzz: usize) -> Option<Located> {
+    let leading = hunk.lines.iter().take_while(|l| matches!(l, HunkLine::Context(_))).count();
+    let trailing = hunk.lines.iter().rev().take_while(|l| matches!(l, HunkLine::Context(_))).count();
+    let mut tried = None;
+    for f in 0..=fuzz {
+        let lead = f.min(leading);
+        let trail = f.min(trailing).min(hunk.lines.len() - lead);
+        if tried == Some((lead, trail)) {
+            break;
+        }
+        tried = Some((lead, trail));
+        let old = Hunk::old_side(&hunk.lines[lead..hunk.lines.len() - trail]);
+        for loose in [false, true] {
+            if let Some(pos) = search(file, &old, expected.map(|e| e + lead), min_pos, loose) {
+                return Some(Located { pos, lead, trail, loose });
+            }
+        }
+    }
+    None
+}
+
+fn apply_hunks(path: &str, text: &mut Text, hunks: &[Hunk], fuzz: usize) -> Result<Vec<HunkReport>> {
+    let mut reports = Vec::new();
+    // Lines added minus removed by earlier hunks, plus how far off they were
+    let mut delta: isize = 0;
+    let mut min_pos = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let expected = hunk.old_start.map(|s| (s as isize + delta).max(0) as usize);
+        let Some(Located { pos, lead, trail, loose }) = locate(&text.lines, hunk, expected, min_pos, fuzz) else {
+            let old = Hunk::old_side(&hunk.lines);
+            let quoted: Vec<&str> = old.iter().take(MAX_QUOTED_LINES).copied().collect();
+            bail!(
+                "Hunk {} of {} doesn't match the file{}; it expects:\n{}{}",
+                n + 1,
+                path,
+                hunk.old_start.map(|s| format!(" near line {}", s + 1)).unwrap_or_default(),
+                quoted.join("\n"),
+                if old.len() > quoted.len() { "\n..." } else { "" }
+            );
+        };
+        let slice = &hunk.lines[lead..hunk.lines.len() - trail];
+        let old_len = Hunk::old_side(slice).len();
+        // Context lines keep the file's own text, which may differ in whitespace from the hunk's
+        let mut file_line = pos;
+        let mut new = Vec::new();
+        for line in slice {
+            match line {
+                HunkLine::Context(_) => {
+                    new.push(text.lines[file_line].clone());
+                    file_line += 1;
+                }
+                HunkLine::Remove(_) => file_line += 1,
+                HunkLine::Add(s) => new.push(s.clone()),
+            }
+        }
+        let at_eof = pos + old_len == text.lines.len();
+        let new_len = new.len();
+        text.lines.splice(pos..pos + old_len, new);
+        if at_eof && trail == 0 && (hunk.old_no_newline || hunk.new_no_newline) {
+            text.final_newline = !hunk.new_no_newline;
+        }
+
+        let offset = expected.map_or(0, |e| pos as isize - (e + lead) as isize);
+        delta += offset + new_len as isize - old_len as isize;
+        min_pos = pos + new_len;
+        reports.push(HunkReport { line: pos + 1, offset, fuzz: lead.max(trail), whitespace: loose });
+    }
+    Ok(reports)
+}
+
+// Resolves a path from the patch inside `root`, refusing ones that leave it
+fn project_path(root: &Path, path: &str) -> Result<PathBuf> {
+    let requested = Path::new(path);
+    let relative = if requested.is_absolute() {
+        requested.strip_prefix(root).map_err(|_| anyhow!("'{}' is outside the project root", path))?
+    } else {
+        requested
+    };
+    if relative.components().any(|c| matches!(c, Component::ParentDir | Component::RootDir | Component::Prefix(_))) {
+        bail!("'{}' is outside the project root", path);
+    }
+    let candidate = root.join(relative);
+    let existing = candidate.ancestors().find(|p| p.exists()).unwrap_or(root);
+    let canonical_root = dunce::canonicalize(root).context("Failed to resolve the project root")?;
+    if !dunce::canonicalize(existing).is_ok_and(|p| p.starts_with(&canonical_root)) {
+        bail!("'{}' is outside the project root", path);
+    }
+    Ok(candidate)
+}
+
+// Contents as the patch goes: earlier file patches may have touched a path already
+struct Staged {
+    files: Vec<(PathBuf, Option<String>, Option<String>)>, // Path, content on disk, patched content
+}
+
+impl Staged {
+    fn entry(&mut self, path: &Path) -> Result<&mut (PathBuf, Option<String>, Option<String>)> {
+        if let Some(i) = self.files.iter().position(|(p, _, _)| p == path) {
+            return Ok(&mut self.files[i]);
+        }
+        let on_disk = if path.is_file() {
+            Some(fs::read_to_string(path).with_context(|| format!("Failed to read {} as text", path.display()))?)
+        } else {
+            None
+        };
+        self.files.push((path.to_path_buf(), on_disk.clone(), on_disk));
+        Ok(self.files.last_mut().expect("just pushed"))
+    }
+}
+
+/// The files a parsed patch reads or writes under `root`, to lock with `editor::with_files`
+/// while it is planned and applied.
+pub fn patch_paths(root: &Path, patches: &[FilePatch]) -> Vec<PathBuf> {
+    patches
+        .iter()
+        .flat_map(|p| p.old_path.iter().chain(p.new_path.iter()))
+        .map(|path| root.join(path))
+        .collect()
+}
+
+/// Checks a parsed patch against the files under `root` and computes the changes, without
+/// writing anything. Every hunk must match (see `HunkReport` for how loosely); otherwise
+/// nothing is planned. `fuzz` is how many context lines a hunk may drop at each end.
+pub fn plan_patch(root: &Path, patches: &[FilePatch], fuzz: usize) -> Result<PatchPlan> {
+    let mut staged = Staged { files: Vec::new() };
+    let mut files = Vec::new();
+    for patch in patches {
+        let (old, new) = (patch.old_path.as_deref(), patch.new_path.as_deref());
+        let label = new.or(old).unwrap_or_default();
+        let (path, old_path, kind) = match (old, new) {
+            (None, None) => bail!("A file patch has /dev/null on both sides"),
+            (None, Some(new)) => (project_path(root, new)?, None, PatchKind::Create),
+            (Some(old), None) => (project_path(root, old)?, None, PatchKind::Delete),
+            (Some(old), Some(new)) if old == new => (project_path(root, new)?, None, PatchKind::Modify),
+            (Some(old), Some(new)) => (project_path(root, new)?, Some(project_path(root, old)?), PatchKind::Rename),
+        };
+        let source = old_path.clone().unwrap_or_else(|| path.clone());
+        let before = staged.entry(&source)?.2.clone();
+        let before = match (kind, before) {
+            (PatchKind::Create, Some(_)) => bail!("{} already exists", label),
+            (PatchKind::Create, None) => String::new(),
+            (_, Some(content)) => content,
+            (_, None) => bail!("{} does not exist", old.unwrap_or(label)),
+        };
+        if kind == PatchKind::Rename && staged.entry(&path)?.2.is_some() {
+            bail!("Can't rename {} to {}: it already exists", old.unwrap_or_default(), label);
+        }
+
+        let mut text = Text::new(&before);
+        let hunks = apply_hunks(label, &mut text, &patch.hunks, fuzz)?;
+        let after = match kind {
+            PatchKind::Delete => None,
+            _ => Some(text.content()),
+        };
+        let diff = editor::unified_diff(&path, &before, after.as_deref().unwrap_or_default());
+        if kind == PatchKind::Rename {
+            staged.entry(&source)?.2 = None;
+        }
+        staged.entry(&path)?.2 = after;
+        files.push(PatchedFile { path, old_path, kind, hunks, diff });
+    }
+
+    let changes = staged
+        .files
+        .into_iter()
+        .filter(|(_, on_disk, patched)| on_disk != patched)
+        .map(|(path, _, patched)| match patched {
+            Some(content) => FileChange::Write { path, content: content.into_bytes() },
+            None => FileChange::Delete { path },
+        })
+        .collect();
+    Ok(PatchPlan { files, changes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_patch() {
+        let patch = "Here is the fix:\n```diff\ndiff --git a/src/old.ts b/src/new.ts\nsimilarity index 90%\nrename from src/old.ts\nrename to src/new.ts\n\
+                     diff --git a/src/gone.ts b/src/gone.ts\ndeleted file mode 100644\n--- a/src/gone.ts\n+++ /dev/null\n@@ -1 +0,0 @@\n-export {};\n\
+                     --- src/app/page.tsx\t2024-05-01 10:00:00\n+++ src/app/page.tsx\n@@ -3,0 +4,2 @@\n+a\n+b\n@@\n context\n-old\n+new\n\n```\n";
+        let patches = parse_patch(patch).unwrap();
+        assert_eq!(patches.len(), 3);
+        assert_eq!((patches[0].old_path.as_deref(), patches[0].new_path.as_deref()), (Some("src/old.ts"), Some("src/new.ts")));
+        assert_eq!((patches[1].old_path.as_deref(), patches[1].new_path.as_deref()), (Some("src/gone.ts"), None));
+        assert_eq!(patches[2].old_path.as_deref(), Some("src/app/page.tsx"));
+        assert_eq!(patches[2].hunks[0].old_start, Some(3));
+        assert_eq!(patches[2].hunks[1].old_start, None);
+        assert_eq!(patches[2].hunks[1].lines.len(), 3);
+        assert!(parse_patch("no diff here").is_err());
+
+        // Counted lines stay in the hunk even when they look like a file header
+        let patches = parse_patch("--- a/notes.md\n+++ b/notes.md\n@@ -1,2 +1,2 @@\n--- old rule\n+++ new rule\n keep\n").unwrap();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(
+            patches[0].hunks[0].lines,
+            vec![HunkLine::Remove("-- old rule".into()), HunkLine::Add("++ new rule".into()), HunkLine::Context("keep".into())]
+        );
+    }
+
+    #[test]
+    fn test_plan_patch_with_fuzz_and_offsets() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("page.tsx"), "// header\n\nimport a from 'a';\nimport b from 'b';\n\nexport function Page() {\n  return a + b;\n}\n").unwrap();
+        fs::write(root.join("crlf.ts"), "one\r\ntwo\r\nthree").unwrap();
+        // Line numbers off by one, indentation changed and a wrong leading context line
+        let patch = "--- a/page.tsx\n+++ b/page.tsx\n@@ -4,4 +4,4 @@\n wrong context\n export function Page() {\n-    return a + b;\n+  return a * b;\n }\n\
+                     --- a/crlf.ts\n+++ b/crlf.ts\n@@ -2,2 +2,3 @@\n two\n-three\n\\ No newline at end of file\n+three\n+four\n\
+                     --- /dev/null\n+++ b/lib/util.ts\n@@ -0,0 +1 @@\n+export const x = 1;\n";
+        let plan = plan_patch(root, &parse_patch(patch).unwrap(), DEFAULT_FUZZ).unwrap();
+        assert_eq!(plan.files[0].hunks, vec![HunkReport { line: 6, offset: 1, fuzz: 1, whitespace: true }]);
+        assert_eq!(plan.files[2].kind, PatchKind::Create);
+        let written: Vec<String> = plan
+            .changes
+            .iter()
+            .map(|c| match c {
+                FileChange::Write { content, .. } => String::from_utf8(content.clone()).unwrap(),
+                FileChange::Delete { .. } => String::new(),
+            })
+            .collect();
+        assert!(written[0].contains("\nexport function Page() {\n  return a * b;\n}\n"));
+        assert_eq!(written[1], "one\r\ntwo\r\nthree\r\nfour\r\n");
+        assert_eq!(written[2], "export const x = 1;\n");
+
+        // Matched ignoring whitespace, the context keeps the file's tabs
+        fs::write(root.join("tabs.ts"), "\tfoo();\n\tbar();\n").unwrap();
+        let plan = plan_patch(root, &parse_patch("--- a/tabs.ts\n+++ b/tabs.ts\n@@ -1,2 +1,2 @@\n   foo();\n-  bar();\n+\tbaz();\n").unwrap(), 0).unwrap();
+        assert!(matches!(&plan.changes[0], FileChange::Write { content, .. } if content == b"\tfoo();\n\tbaz();\n"));
+
+        let mismatch = "--- a/page.tsx\n+++ b/page.tsx\n@@ -7 +7 @@\n-  return a - b;\n+  return 0;\n";
+        let error = plan_patch(root, &parse_patch(mismatch).unwrap(), DEFAULT_FUZZ).unwrap_err().to_string();
+        assert!(error.contains("Hunk 1 of page.tsx doesn't match the file near line 7"));
+        assert!(plan_patch(root, &parse_patch("--- a/../x\n+++ b/../x\n@@\n-a\n+b\n").unwrap(), 0).is_err());
+    }
+}
@@ -0,0 +1,643 @@
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::editor::{self, FileChange};
+use super::symbols::relative_path;
+use crate::dev_runtime::quotas::{self, QuotaMetric};
+use crate::dev_runtime::{db, events};
+use crate::file_system::dirs::SKIPPED_DIRS;
+use crate::file_system::search::find_files_by_extensions;
+
+// Files whose import specifiers are rewritten
+const SOURCE_EXTENSIONS: &[&str] = &["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+// Extensions tried, in order, when a specifier leaves the extension out
+const RESOLVE_EXTENSIONS: &[&str] = &["ts", "tsx", "d.ts", "js", "jsx", "mts", "cts", "mjs", "cjs", "json"];
+
+// Config files that may declare path aliases, in order of preference
+const TSCONFIG_FILES: &[&str] = &["tsconfig.json", "jsconfig.json"];
+
+// Static imports and re-exports, side-effect imports, dynamic imports, `require` and module mocks
+static IMPORT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?:\b(?:import|export)\b[^;'"`]*?\bfrom\s*|\bimport\s*|\b(?:import|require|require\.resolve|jest\.mock|vi\.mock)\s*\(\s*)(?:"([^"\n]+)"|'([^'\n]+)'|`([^`$\n]+)`)"#,
+    )
+    .expect("import pattern is valid")
+});
+
+/// How a specifier names the file it imports, kept when the specifier is rewritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpecifierForm {
+    /// `./button.tsx`
+    WithExtension,
+    /// `./button`
+    Extensionless,
+    /// `./button.js` for `button.ts`, as ESM TypeScript projects write it
+    JsForTs,
+    /// `./components` for `components/index.ts`
+    Directory,
+}
+
+/// What a specifier is relative to.
+#[derive(Debug, Clone, PartialEq)]
+enum SpecifierBase {
+    Relative,
+    /// A tsconfig `paths` alias, by index
+    Alias(usize),
+    /// A bare path resolved against `baseUrl`
+    BaseUrl,
+}
+
+/// A `compilerOptions.paths` entry, e.g. `@/*` -> `src/*` with targets relative to the root.
+#[derive(Debug, Clone, PartialEq)]
+struct PathAlias {
+    pattern: String,
+    targets: Vec<String>,
+}
+
+fn split_wildcard(pattern: &str) -> (&str, Option<&str>) {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => (prefix, Some(suffix)),
+        None => (pattern, None),
+    }
+}
+
+/// Import resolution for a project: relative specifiers, tsconfig `paths` aliases and `baseUrl`.
+#[derive(Debug, Clone, Default)]
+pub struct ImportResolver {
+    root: PathBuf,
+    base_url: Option<PathBuf>,
+    aliases: Vec<PathAlias>,
+}
+
+// Comments and trailing commas are allowed in tsconfig.json
+fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    static TRAILING_COMMA: Lazy<Regex> = Lazy::new(|| Regex::new(r",(\s*[}\]])").expect("valid pattern"));
+    TRAILING_COMMA.replace_all(&out, "$1").into_owned()
+}
+
+impl ImportResolver {
+    /// Reads the aliases from the project's tsconfig.json (or jsconfig.json). A project without
+    /// one only has relative imports.
+    pub fn load(root: &Path) -> Result<Self> {
+        let mut resolver = Self { root: root.to_path_buf(), ..Self::default() };
+        let Some(config_path) = TSCONFIG_FILES.iter().map(|f| root.join(f)).find(|p| p.is_file()) else {
+            return Ok(resolver);
+        };
+        let text = fs::read_to_string(&config_path).with_context(|| format!("Failed to read {}", config_path.display()))?;
+        let config: serde_json::Value = serde_json::from_str(&strip_jsonc(&text))
+            .with_context(|| format!("Failed to parse {}", config_path.display()))?;
+        let options = &config["compilerOptions"];
+        let base_url = options["baseUrl"].as_str().map(|b| root.join(b));
+        // Alias targets are relative to baseUrl, or to the config file without one
+        let alias_base = base_url.clone().unwrap_or_else(|| root.to_path_buf());
+        if let Some(paths) = options["paths"].as_object() {
+            for (pattern, targets) in paths {
+                let targets = targets
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|t| t.as_str())
+                    .map(|t| relative_path(&normalize(&alias_base.join(t)), root))
+                    .collect();
+                resolver.aliases.push(PathAlias { pattern: pattern.clone(), targets });
+            }
+        }
+        resolver.base_url = base_url.map(|b| normalize(&b));
+        Ok(resolver)
+    }
+
+    // The file a module path names, and how the specifier named it
+    fn resolve_file(&self, base: &Path) -> Option<(PathBuf, SpecifierForm)> {
+        if base.is_file() {
+            return Some((base.to_path_buf(), SpecifierForm::WithExtension));
+        }
+        let with_extension = |path: &Path, ext: &str| {
+            let mut name = path.file_name()?.to_os_string();
+            name.push(".");
+            name.push(ext);
+            Some(path.with_file_name(name))
+        };
+        let file_name = base.file_name()?.to_string_lossy();
+        for (js, ts_exts) in [("js", &["ts", "tsx"][..]), ("jsx", &["tsx"][..]), ("mjs", &["mts"][..]), ("cjs", &["cts"][..])] {
+            if let Some(stem) = file_name.strip_suffix(&format!(".{}", js)) {
+                for ts in ts_exts {
+                    let candidate = base.with_file_name(format!("{}.{}", stem, ts));
+                    if candidate.is_file() {
+                        return Some((candidate, SpecifierForm::JsForTs));
+                    }
+                }
+            }
+        }
+        for ext in RESOLVE_EXTENSIONS {
+            let candidate = with_extension(base, ext)?;
+            if candidate.is_file() {
+                return Some((candidate, SpecifierForm::Extensionless));
+            }
+        }
+        if base.is_dir() {
+            for ext in RESOLVE_EXTENSIONS {
+                let candidate = base.join(format!("index.{}", ext));
+                if candidate.is_file() {
+                    return Some((candidate, SpecifierForm::Directory));
+                }
+            }
+        }
+        None
+    }
+
+    /// The project file `specifier` imports from `importer`. Package imports resolve to `None`.
+    fn resolve(&self, importer: &Path, specifier: &str) -> Option<(PathBuf, SpecifierForm, SpecifierBase)> {
+        if specifier.starts_with("./") || specifier.starts_with("../") || specifier == "." || specifier == ".." {
+            let base = normalize(&importer.parent()?.join(specifier));
+            return self.resolve_file(&base).map(|(file, form)| (file, form, SpecifierBase::Relative));
+        }
+        for (index, alias) in self.aliases.iter().enumerate() {
+            let (prefix, suffix) = split_wildcard(&alias.pattern);
+            let captured = match suffix {
+                Some(suffix) => specifier.strip_prefix(prefix).and_then(|rest| rest.strip_suffix(suffix)),
+                None => (specifier == alias.pattern).then_some(""),
+            };
+            let Some(captured) = captured else { continue };
+            for target in &alias.targets {
+                let target = target.replacen('*', captured, 1);
+                if let Some((file, form)) = self.resolve_file(&self.root.join(target)) {
+                    return Some((file, form, SpecifierBase::Alias(index)));
+                }
+            }
+        }
+        let base_url = self.base_url.as_ref()?;
+        self.resolve_file(&base_url.join(specifier)).map(|(file, form)| (file, form, SpecifierBase::BaseUrl))
+    }
+
+    /// The specifier for `target` from `importer`, in the given form and relative to the same
+    /// base where possible, falling back to a relative specifier.
+    fn specifier(&self, importer: &Path, target: &Path, form: SpecifierForm, base: &SpecifierBase) -> String {
+        let module = module_path(target, form);
+        match base {
+            SpecifierBase::Alias(index) => {
+                // Prefer the alias the import used, then any other that reaches the target
+                let preferred = self.aliases.get(*index).into_iter();
+                for alias in preferred.chain(self.aliases.iter()) {
+                    if let Some(specifier) = self.alias_specifier(alias, &module) {
+                        return specifier;
+                    }
+                }
+            }
+            SpecifierBase::BaseUrl => {
+                if let Some(rest) = self.base_url.as_ref().and_then(|b| module.strip_prefix(b).ok()) {
+                    return to_slashes(rest);
+                }
+            }
+            SpecifierBase::Relative => {}
+        }
+        relative_specifier(importer.parent().unwrap_or(&self.root), &module)
+    }
+
+    fn alias_specifier(&self, alias: &PathAlias, module: &Path) -> Option<String> {
+        let module = to_slashes(module.strip_prefix(&self.root).ok()?);
+        let (pattern_prefix, pattern_suffix) = split_wildcard(&alias.pattern);
+        alias.targets.iter().find_map(|target| match (split_wildcard(target), pattern_suffix) {
+            ((target_prefix, Some(target_suffix)), Some(pattern_suffix)) => {
+                let captured = module.strip_prefix(target_prefix)?.strip_suffix(target_suffix)?;
+                Some(format!("{}{}{}", pattern_prefix, captured, pattern_suffix))
+            }
+            ((exact, None), None) => {
+                let exact = Path::new(exact);
+                (Path::new(&module) == exact || Path::new(&module) == exact.with_extension("")).then(|| alias.pattern.clone())
+            }
+            _ => None,
+        })
+    }
+
+    // Moves alias targets that point into a moved directory along with it
+    fn move_aliases(&mut self, from: &str, to: &str) -> Vec<(String, String)> {
+        let mut moved = Vec::new();
+        for alias in &mut self.aliases {
+            for target in &mut alias.targets {
+                if let Some(rest) = strip_dir_prefix(target, from) {
+                    let new_target = format!("{}{}", to, rest);
+                    moved.push((target.clone(), new_target.clone()));
+                    *target = new_target;
+                }
+            }
+        }
+        moved
+    }
+}
+
+fn strip_dir_prefix<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(dir)?;
+    (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+}
+
+// Lexically resolves `.` and `..`, as module resolution does
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn to_slashes(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+// The path a specifier of `form` spells out for `file`
+fn module_path(file: &Path, form: SpecifierForm) -> PathBuf {
+    let name = file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let stem = name.strip_suffix(".d.ts").map(str::to_string).unwrap_or_else(|| {
+        file.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+    });
+    match form {
+        SpecifierForm::WithExtension => file.to_path_buf(),
+        SpecifierForm::Directory if stem == "index" => file.parent().unwrap_or(file).to_path_buf(),
+        SpecifierForm::Extensionless | SpecifierForm::Directory => file.with_file_name(stem),
+        SpecifierForm::JsForTs => {
+            let js = match file.extension().and_then(|e| e.to_str()) {
+                Some("tsx") => "jsx",
+                Some("mts") => "mjs",
+                Some("cts") => "cjs",
+                _ => "js",
+            };
+            file.with_file_name(format!("{}.{}", stem, js))
+        }
+    }
+}
+
+/// `./x` style specifier for `target` from a file in `from_dir`.
+fn relative_specifier(from_dir: &Path, target: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = target.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(to[common..].iter().map(|c| c.as_os_str().to_string_lossy().into_owned()));
+    match parts.first().map(String::as_str) {
+        None => ".".to_string(),
+        Some("..") => parts.join("/"),
+        Some(_) => format!("./{}", parts.join("/")),
+    }
+}
+
+/// One import specifier a move rewrites.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecifierRewrite {
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// A file whose imports a move rewrites.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileUpdate {
+    /// Project-relative path, after the move
+    pub path: String,
+    pub rewrites: Vec<SpecifierRewrite>,
+    /// Unified diff of the rewrites
+    pub diff: String,
+}
+
+/// Everything a move changes, computed before anything is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovePlan {
+    /// Project-relative `(from, to)` of every moved file
+    pub moves: Vec<(String, String)>,
+    pub updates: Vec<FileUpdate>,
+    changes: Vec<FileChange>,
+    // Directory the move empties, removed once it is applied
+    emptied_dir: Option<PathBuf>,
+}
+
+impl MovePlan {
+    /// Every file the move writes or deletes.
+    pub fn changed_paths(&self) -> Vec<PathBuf> {
+        self.changes.iter().map(|c| c.path().to_path_buf()).collect()
+    }
+}
+
+// A project-relative path made of plain components only, which no symlink leads out of the
+// project
+fn project_path(root: &Path, path: &str) -> Result<PathBuf> {
+    let trimmed = path.trim().trim_start_matches("./").trim_end_matches('/');
+    let relative = Path::new(trimmed);
+    if trimmed.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        bail!("'{}' must be a relative path inside the project", path);
+    }
+    let candidate = root.join(relative);
+    let existing = candidate.ancestors().find(|p| p.exists()).unwrap_or(root);
+    let canonical_root = dunce::canonicalize(root).context("Failed to resolve the project root")?;
+    if !dunce::canonicalize(existing).is_ok_and(|p| p.starts_with(&canonical_root)) {
+        bail!("'{}' is outside the project root", path);
+    }
+    Ok(candidate)
+}
+
+fn rewrite_imports(
+    resolver: &ImportResolver,
+    moved_resolver: &ImportResolver,
+    content: &str,
+    old_path: &Path,
+    new_path: &Path,
+    moves: &HashMap<PathBuf, PathBuf>,
+) -> (String, Vec<SpecifierRewrite>) {
+    let mut rewritten = String::with_capacity(content.len());
+    let mut rewrites = Vec::new();
+    let mut last = 0;
+    for captures in IMPORT_RE.captures_iter(content) {
+        let Some(specifier) = captures.get(1).or_else(|| captures.get(2)).or_else(|| captures.get(3)) else {
+            continue;
+        };
+        let Some((target, form, base)) = resolver.resolve(old_path, specifier.as_str()) else { continue };
+        let new_target = moves.get(&target).unwrap_or(&target);
+        if new_target == &target && (old_path == new_path || base != SpecifierBase::Relative) {
+            continue;
+        }
+        let new_specifier = moved_resolver.specifier(new_path, new_target, form, &base);
+        if new_specifier == specifier.as_str() {
+            continue;
+        }
+        rewritten.push_str(&content[last..specifier.start()]);
+        rewritten.push_str(&new_specifier);
+        last = specifier.end();
+        rewrites.push(SpecifierRewrite {
+            line: content[..specifier.start()].matches('\n').count() + 1,
+            old: specifier.as_str().to_string(),
+            new: new_specifier,
+        });
+    }
+    rewritten.push_str(&content[last..]);
+    (rewritten, rewrites)
+}
+
+/// Plans moving the file or directory `from` to `to` (both project-relative), rewriting every
+/// import of the moved files across the project and the moved files' own relative imports.
+/// tsconfig `paths` aliases pointing into a moved directory move with it, so imports through
+/// them keep working unchanged.
+pub fn plan_move(root: &Path, from: &str, to: &str) -> Result<MovePlan> {
+    let from_path = project_path(root, from)?;
+    let to_path = project_path(root, to)?;
+    if !from_path.exists() {
+        bail!("'{}' does not exist", from);
+    }
+    if to_path.exists() {
+        bail!("'{}' already exists", to);
+    }
+    if to_path.starts_with(&from_path) {
+        bail!("Cannot move '{}' into itself", from);
+    }
+
+    let mut moves: HashMap<PathBuf, PathBuf> = HashMap::new();
+    if from_path.is_dir() {
+        for entry in WalkDir::new(&from_path).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+            let rest = entry.path().strip_prefix(&from_path).context("Walked outside the moved directory")?;
+            moves.insert(entry.path().to_path_buf(), to_path.join(rest));
+        }
+    } else {
+        moves.insert(from_path.clone(), to_path.clone());
+    }
+
+    let resolver = ImportResolver::load(root)?;
+    let mut moved_resolver = resolver.clone();
+    let (from_rel, to_rel) = (relative_path(&from_path, root), relative_path(&to_path, root));
+    let moved_aliases = moved_resolver.move_aliases(&to_slashes(Path::new(&from_rel)), &to_slashes(Path::new(&to_rel)));
+
+    let mut sources = find_files_by_extensions(root, SOURCE_EXTENSIONS, SKIPPED_DIRS)?;
+    sources.retain(|f| !moves.contains_key(f));
+    let mut moved_files: Vec<(&PathBuf, &PathBuf)> = moves.iter().collect();
+    moved_files.sort();
+
+    let mut changes = Vec::new();
+    let mut updates = Vec::new();
+    let mut record_update = |new_path: &Path, before: &str, after: &str, rewrites: Vec<SpecifierRewrite>| {
+        if !rewrites.is_empty() {
+            updates.push(FileUpdate {
+                path: relative_path(new_path, root),
+                diff: editor::unified_diff(new_path, before, after),
+                rewrites,
+            });
+        }
+    };
+
+    // Moved files are written at their new location, with their own imports fixed up
+    for (old, new) in &moved_files {
+        let bytes = fs::read(old).with_context(|| format!("Failed to read {}", old.display()))?;
+        let is_source = old.extension().and_then(|e| e.to_str()).is_some_and(|e| SOURCE_EXTENSIONS.contains(&e));
+        let content = match std::str::from_utf8(&bytes) {
+            Ok(text) if is_source => {
+                let (after, rewrites) = rewrite_imports(&resolver, &moved_resolver, text, old, new, &moves);
+                record_update(new, text, &after, rewrites);
+                after.into_bytes()
+            }
+            _ => bytes,
+        };
+        changes.push(FileChange::Write { path: new.to_path_buf(), content });
+    }
+    for (old, _) in &moved_files {
+        changes.push(FileChange::Delete { path: old.to_path_buf() });
+    }
+
+    for source in &sources {
+        let Ok(text) = fs::read_to_string(source) else { continue };
+        let (after, rewrites) = rewrite_imports(&resolver, &moved_resolver, &text, source, source, &moves);
+        if !rewrites.is_empty() {
+            record_update(source, &text, &after, rewrites);
+            changes.push(FileChange::Write { path: source.clone(), content: after.into_bytes() });
+        }
+    }
+
+    if let Some(config_path) = TSCONFIG_FILES.iter().map(|f| root.join(f)).find(|p| p.is_file()) {
+        if !moved_aliases.is_empty() {
+            let text = fs::read_to_string(&config_path)?;
+            let after = rewrite_alias_targets(&text, &moved_aliases);
+            if after != text {
+                let rewrites = moved_aliases
+                    .iter()
+                    .map(|(old, new)| SpecifierRewrite { line: 0, old: old.clone(), new: new.clone() })
+                    .collect();
+                record_update(&config_path, &text, &after, rewrites);
+                changes.push(FileChange::Write { path: config_path, content: after.into_bytes() });
+            }
+        }
+    }
+
+    Ok(MovePlan {
+        moves: moved_files.iter().map(|(old, new)| (relative_path(old, root), relative_path(new, root))).collect(),
+        updates,
+        changes,
+        emptied_dir: from_path.is_dir().then_some(from_path),
+    })
+}
+
+// Rewrites moved alias targets in tsconfig.json's text, keeping its comments and formatting
+fn rewrite_alias_targets(text: &str, moved: &[(String, String)]) -> String {
+    let mut text = text.to_string();
+    for (old, new) in moved {
+        for prefix in ["", "./"] {
+            text = text.replace(&format!("\"{}{}\"", prefix, old), &format!("\"{}{}\"", prefix, new));
+        }
+    }
+    text
+}
+
+/// Applies a plan as one transaction through the editor, one undo entry per changed file.
+pub fn apply_move(editor: &editor::Editor, plan: &MovePlan) -> Result<()> {
+    editor::apply_changes(editor, &plan.changes).map_err(|e| anyhow!(e))?;
+
+    // Recorded and charged like editor commands, one edit per changed file
+    let from = plan.moves.first().map(|(from, _)| from.clone());
+    db::write_later(move |db| {
+        if let Err(e) = db.record_edit(events::session_id(), "move_file", from.as_deref()) {
+            tracing::debug!(target: "dev_operation::refactor", error = ?e, "Failed to record edit history.");
+        }
+    });
+    quotas::charge(QuotaMetric::EditsPerHour, plan.changes.len() as f64);
+    let written: usize = plan
+        .changes
+        .iter()
+        .map(|c| match c {
+            FileChange::Write { content, .. } => content.len(),
+            FileChange::Delete { .. } => 0,
+        })
+        .sum();
+    quotas::charge(QuotaMetric::BytesWritten, written as f64);
+
+    // Remove the directories the move left empty, deepest first
+    if let Some(dir) = &plan.emptied_dir {
+        let mut dirs: Vec<PathBuf> = WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+            .map(|e| e.into_path())
+            .collect();
+        dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+        for dir in dirs {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_strip_jsonc() {
+        let text = "{\n  // comment\n  \"a\": \"http://x\", /* block */\n  \"b\": [1, 2,],\n}";
+        let value: serde_json::Value = serde_json::from_str(&strip_jsonc(text)).unwrap();
+        assert_eq!(value, serde_json::json!({ "a": "http://x", "b": [1, 2] }));
+    }
+
+    #[test]
+    fn test_move_rewrites_relative_alias_and_dynamic_imports() {
+        let dir = crate::test_support::project_tempdir();
+        let root = dunce::canonicalize(dir.path()).unwrap();
+        write(&root, "tsconfig.json", r#"{ "compilerOptions": { "baseUrl": ".", "paths": { "@/*": ["./src/*"] } } }"#);
+        write(&root, "src/lib/format.ts", "import { clamp } from '../util/math';\nexport const format = 1;\n");
+        write(&root, "src/util/math.ts", "export const clamp = 1;\n");
+        write(&root, "src/lib/index.ts", "export * from './format';\n");
+        write(
+            &root,
+            "src/app/page.tsx",
+            "import { format } from \"@/lib/format\";\nimport { other } from \"../lib/format.js\";\nconst lazy = () => import('../lib/format');\n",
+        );
+
+        let plan = plan_move(&root, "src/lib/format.ts", "src/shared/text/format.ts").unwrap();
+        assert_eq!(plan.moves, vec![("src/lib/format.ts".to_string(), "src/shared/text/format.ts".to_string())]);
+
+        let editor = editor::Editor::new();
+        apply_move(&editor, &plan).unwrap();
+        let read = |p: &str| fs::read_to_string(root.join(p)).unwrap();
+        assert!(!root.join("src/lib/format.ts").exists());
+        assert_eq!(read("src/shared/text/format.ts"), "import { clamp } from '../../util/math';\nexport const format = 1;\n");
+        assert_eq!(read("src/lib/index.ts"), "export * from '../shared/text/format';\n");
+        assert_eq!(
+            read("src/app/page.tsx"),
+            "import { format } from \"@/shared/text/format\";\nimport { other } from \"../shared/text/format.js\";\nconst lazy = () => import('../shared/text/format');\n"
+        );
+
+        // Moving a directory an alias points into moves the alias instead of rewriting imports
+        write(&root, "tsconfig.json", r#"{ "compilerOptions": { "paths": { "@ui/*": ["src/ui/*"] } } }"#);
+        write(&root, "src/ui/button.tsx", "export const Button = 1;\n");
+        write(&root, "src/app/form.tsx", "import { Button } from '@ui/button';\nimport { Button as B } from '../ui/button';\n");
+        let plan = plan_move(&root, "src/ui", "src/components/ui").unwrap();
+        apply_move(&editor, &plan).unwrap();
+        assert!(!root.join("src/ui").exists());
+        assert!(read("tsconfig.json").contains("\"src/components/ui/*\""));
+        assert_eq!(read("src/app/form.tsx"), "import { Button } from '@ui/button';\nimport { Button as B } from '../components/ui/button';\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_move_refuses_symlinks_out_of_the_project() {
+        let dir = crate::test_support::project_tempdir();
+        let root = dunce::canonicalize(dir.path()).unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        write(outside.path(), "secret.ts", "export const secret = 1;\n");
+        write(&root, "src/app.ts", "export const app = 1;\n");
+        std::os::unix::fs::symlink(outside.path(), root.join("linked")).unwrap();
+
+        let err = plan_move(&root, "linked/secret.ts", "src/secret.ts").unwrap_err();
+        assert!(err.to_string().contains("outside the project root"), "{}", err);
+        let err = plan_move(&root, "src/app.ts", "linked/app.ts").unwrap_err();
+        assert!(err.to_string().contains("outside the project root"), "{}", err);
+        assert!(root.join("src/app.ts").exists());
+    }
+}
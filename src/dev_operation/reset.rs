@@ -0,0 +1,346 @@
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::dev_runtime::events::DEV_SERVER_SERVICE;
+use crate::dev_runtime::supervisor;
+use crate::dev_runtime::util::now_secs;
+use crate::dev_setup::nextjs::{self, ScaffoldStage};
+use crate::dev_setup::{config_files, template};
+use crate::terminal::git::git_output;
+
+// config.toml section
+const CONFIG_SECTION: &str = "project_reset";
+// Ref recording the template as it was cloned
+const TEMPLATE_REF: &str = "refs/remotes/origin/HEAD";
+
+/// Project reset settings, from `[project_reset]` in config.toml:
+///
+/// ```toml
+/// [project_reset]
+/// require_confirmation = true   # reset only with the token an unconfirmed request answers with
+/// confirmation_ttl_secs = 120   # how long that token is valid
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ResetConfig {
+    pub require_confirmation: bool,
+    pub confirmation_ttl_secs: u64,
+}
+
+impl Default for ResetConfig {
+    fn default() -> Self {
+        Self { require_confirmation: true, confirmation_ttl_secs: 120 }
+    }
+}
+
+impl ResetConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetMode {
+    /// Delete the project and scaffold it again from the template
+    Rescaffold,
+    /// Reset the project's git repository to the template commit it was cloned from
+    TemplateInitial,
+}
+
+impl ResetMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResetMode::Rescaffold => "rescaffold",
+            ResetMode::TemplateInitial => "template_initial",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rescaffold" => Some(ResetMode::Rescaffold),
+            "template_initial" => Some(ResetMode::TemplateInitial),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetStage {
+    StoppingDevServer,
+    Cloning,
+    ResettingGit,
+    Installing,
+    RestartingDevServer,
+    Done,
+    Failed,
+}
+
+impl ResetStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResetStage::StoppingDevServer => "stopping_dev_server",
+            ResetStage::Cloning => "cloning",
+            ResetStage::ResettingGit => "resetting_git",
+            ResetStage::Installing => "installing",
+            ResetStage::RestartingDevServer => "restarting_dev_server",
+            ResetStage::Done => "done",
+            ResetStage::Failed => "failed",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, ResetStage::Done | ResetStage::Failed)
+    }
+}
+
+/// A reset, running or finished, with what it did so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResetProgress {
+    pub id: String,
+    pub mode: ResetMode,
+    pub template_url: String,
+    pub stage: ResetStage,
+    /// Stages passed through, with Unix seconds of when each began
+    pub history: Vec<(ResetStage, u64)>,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResetOptions {
+    pub mode: Option<ResetMode>,
+    /// Template to scaffold from when rescaffolding; the configured one by default
+    pub template: Option<String>,
+    pub template_vars: HashMap<String, String>,
+}
+
+/// What a reset would discard, answered with the token that confirms it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResetConfirmation {
+    pub token: String,
+    pub expires_in_secs: u64,
+    pub mode: ResetMode,
+    /// Uncommitted files in the project, when it is a git repository
+    pub uncommitted_files: Option<usize>,
+    /// Commits made on top of the template, when the template commit is known
+    pub commits_since_template: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum ResetError {
+    InProgress(ResetProgress),
+    ConfirmationRequired(ResetConfirmation),
+    InvalidConfirmation,
+    /// The reset cannot be done in this project, e.g. `template_initial` without a template commit
+    Unavailable(String),
+    Failed(anyhow::Error),
+}
+
+impl std::fmt::Display for ResetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResetError::InProgress(progress) => write!(f, "Reset {} is still running ({})", progress.id, progress.stage.as_str()),
+            ResetError::ConfirmationRequired(_) => write!(f, "Resetting the project requires confirmation"),
+            ResetError::InvalidConfirmation => write!(f, "The confirmation token is unknown, expired, or was issued for another mode"),
+            ResetError::Unavailable(reason) => write!(f, "{}", reason),
+            ResetError::Failed(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+struct PendingConfirmation {
+    token: String,
+    mode: ResetMode,
+    expires_at: Instant,
+}
+
+static CURRENT: Lazy<Mutex<Option<ResetProgress>>> = Lazy::new(|| Mutex::new(None));
+static PENDING: Lazy<Mutex<Option<PendingConfirmation>>> = Lazy::new(|| Mutex::new(None));
+
+fn set_stage(stage: ResetStage) {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(progress) = current.as_mut() {
+        tracing::info!(target: "dev_operation::reset", reset_id = %progress.id, stage = stage.as_str(), "Project reset stage.");
+        progress.stage = stage;
+        progress.history.push((stage, now_secs()));
+    }
+}
+
+fn finish(error: Option<String>) {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(progress) = current.as_mut() {
+        let stage = if error.is_some() { ResetStage::Failed } else { ResetStage::Done };
+        progress.stage = stage;
+        progress.history.push((stage, now_secs()));
+        progress.finished_at = Some(now_secs());
+        progress.error = error;
+    }
+}
+
+/// The running or last reset. While the scaffold clones and installs, the stage follows its
+/// marker file.
+pub fn progress() -> Option<ResetProgress> {
+    let mut progress = CURRENT.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+    if progress.stage == ResetStage::Cloning {
+        let installing = crate::file_system::get_project_root()
+            .ok()
+            .and_then(|root| nextjs::read_scaffold_marker(&root))
+            .is_some_and(|marker| marker.stage == ScaffoldStage::Installing);
+        if installing {
+            progress.stage = ResetStage::Installing;
+        }
+    }
+    Some(progress)
+}
+
+/// The commit the project's template was cloned at.
+async fn template_commit(project_root: &Path) -> Result<String> {
+    if !project_root.join(".git").exists() {
+        bail!("The project is not a git repository, so its template state is unknown. Reset with mode `rescaffold` instead.");
+    }
+    git_output(project_root, &["rev-parse", "--verify", "--quiet", TEMPLATE_REF])
+        .await
+        .map(|hash| hash.trim().to_string())
+        .context("The project has no record of the template commit it was cloned at (origin/HEAD). Reset with mode `rescaffold` instead.")
+}
+
+async fn confirmation(project_root: &Path, mode: ResetMode, ttl: Duration) -> ResetConfirmation {
+    let uncommitted_files = crate::dev_operation::git::status(project_root).await.ok().map(|status| status.files.len());
+    let commits_since_template = match template_commit(project_root).await {
+        Ok(commit) => git_output(project_root, &["rev-list", "--count", &format!("{}..HEAD", commit)])
+            .await
+            .ok()
+            .and_then(|count| count.trim().parse().ok()),
+        Err(_) => None,
+    };
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    *PENDING.lock().unwrap_or_else(|e| e.into_inner()) =
+        Some(PendingConfirmation { token: token.clone(), mode, expires_at: Instant::now() + ttl });
+    ResetConfirmation { token, expires_in_secs: ttl.as_secs(), mode, uncommitted_files, commits_since_template }
+}
+
+// Tokens are single-use and only confirm the mode they were issued for
+fn take_confirmation(token: &str, mode: ResetMode) -> bool {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    let valid = pending
+        .as_ref()
+        .is_some_and(|p| p.token == token && p.mode == mode && Instant::now() < p.expires_at);
+    if valid {
+        *pending = None;
+    }
+    valid
+}
+
+/// Starts resetting the project in the background. Unless confirmation is turned off, a call
+/// without a token answers `ConfirmationRequired` with one, to pass on a second call.
+pub async fn start(options: ResetOptions, confirm_token: Option<&str>) -> Result<ResetProgress, ResetError> {
+    let mode = options.mode.unwrap_or(ResetMode::Rescaffold);
+    if let Some(progress) = progress().filter(|p| !p.stage.is_finished()) {
+        return Err(ResetError::InProgress(progress));
+    }
+    let project_root = crate::file_system::get_project_root().map_err(ResetError::Failed)?;
+    if mode == ResetMode::TemplateInitial {
+        template_commit(&project_root).await.map_err(|e| ResetError::Unavailable(format!("{:#}", e)))?;
+    }
+
+    let config = ResetConfig::load();
+    if config.require_confirmation {
+        match confirm_token {
+            None => {
+                let ttl = Duration::from_secs(config.confirmation_ttl_secs.max(1));
+                return Err(ResetError::ConfirmationRequired(confirmation(&project_root, mode, ttl).await));
+            }
+            Some(token) if !take_confirmation(token, mode) => return Err(ResetError::InvalidConfirmation),
+            Some(_) => {}
+        }
+    }
+
+    let template_name = options.template.clone().or_else(|| config_files::get_config_value("template"));
+    let progress = ResetProgress {
+        id: uuid::Uuid::new_v4().simple().to_string()[..12].to_string(),
+        mode,
+        template_url: template::resolve_template_url(template_name.as_deref()).to_string(),
+        stage: ResetStage::StoppingDevServer,
+        history: vec![(ResetStage::StoppingDevServer, now_secs())],
+        started_at: now_secs(),
+        finished_at: None,
+        error: None,
+    };
+    {
+        let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+        // Checked again under the lock: two confirmed requests may race
+        if let Some(running) = current.as_ref().filter(|p| !p.stage.is_finished()) {
+            return Err(ResetError::InProgress(running.clone()));
+        }
+        *current = Some(progress.clone());
+    }
+    tracing::warn!(target: "dev_operation::reset", reset_id = %progress.id, mode = mode.as_str(), template_url = %progress.template_url, "Resetting the project.");
+
+    let template_url = progress.template_url.clone();
+    tokio::spawn(async move {
+        let result = run(project_root, mode, template_url, options.template_vars).await;
+        if let Err(e) = &result {
+            tracing::error!(target: "dev_operation::reset", error = %format!("{:#}", e), "Project reset failed.");
+        }
+        finish(result.err().map(|e| format!("{:#}", e)));
+    });
+    Ok(progress)
+}
+
+async fn run(project_root: PathBuf, mode: ResetMode, template_url: String, template_vars: HashMap<String, String>) -> Result<()> {
+    // Not running, or not supervised at all, is fine: nothing holds the project open then
+    let dev_server_was_running = supervisor::stop(DEV_SERVER_SERVICE).await.is_ok();
+
+    match mode {
+        ResetMode::Rescaffold => {
+            set_stage(ResetStage::Cloning);
+            nextjs::rescaffold_nextjs_project(&project_root, &template_url, &template_vars).await?;
+        }
+        ResetMode::TemplateInitial => {
+            set_stage(ResetStage::ResettingGit);
+            let commit = template_commit(&project_root).await?;
+            git_output(&project_root, &["reset", "--hard", &commit]).await.context("git reset failed")?;
+            // Ignored files (node_modules, .env files) are kept
+            git_output(&project_root, &["clean", "-fd"]).await.context("git clean failed")?;
+            set_stage(ResetStage::Installing);
+            nextjs::install_dependencies(&project_root).await?;
+        }
+    }
+
+    if dev_server_was_running {
+        set_stage(ResetStage::RestartingDevServer);
+        supervisor::start(DEV_SERVER_SERVICE).await.map_err(|e| anyhow::anyhow!("Failed to restart the dev server: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmation_tokens_are_single_use_and_mode_bound() {
+        *PENDING.lock().unwrap() = Some(PendingConfirmation {
+            token: "abc".to_string(),
+            mode: ResetMode::TemplateInitial,
+            expires_at: Instant::now() + Duration::from_secs(60),
+        });
+        assert!(!take_confirmation("abc", ResetMode::Rescaffold));
+        assert!(!take_confirmation("abd", ResetMode::TemplateInitial));
+        assert!(take_confirmation("abc", ResetMode::TemplateInitial));
+        assert!(!take_confirmation("abc", ResetMode::TemplateInitial));
+
+        *PENDING.lock().unwrap() = Some(PendingConfirmation {
+            token: "old".to_string(),
+            mode: ResetMode::Rescaffold,
+            expires_at: Instant::now() - Duration::from_secs(1),
+        });
+        assert!(!take_confirmation("old", ResetMode::Rescaffold));
+    }
+}
@@ -0,0 +1,339 @@
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use walkdir::WalkDir;
+
+use crate::dev_runtime::{db, events, util};
+use crate::dev_runtime::lsp_pool::{self, EditedFiles};
+use crate::dev_setup::config_files;
+use crate::file_system::paths;
+
+// config.toml table with the snapshot settings
+const CONFIG_SECTION: &str = "snapshots";
+// Under galatea_files: one directory per snapshot, named after its id
+const SNAPSHOTS_DIR: &str = "snapshots";
+const PROJECT_ARCHIVE: &str = "project.tar.gz";
+const GALATEA_FILES_ARCHIVE: &str = "galatea_files.tar.gz";
+const METADATA_FILE: &str = "snapshot.json";
+// Top-level galatea_files entries never snapshotted nor touched by a restore: the snapshots
+// themselves, the event log, caches and the other workspaces, and the live database (`db::DB_FILES`)
+const GALATEA_FILES_EXCLUDED: &[&str] = &[SNAPSHOTS_DIR, "cache", "workspaces", "runtime_events.jsonl"];
+
+/// Snapshot settings, from `[snapshots]` in config.toml.
+///
+/// ```toml
+/// [snapshots]
+/// max = 10                             # the oldest snapshots beyond this are deleted
+/// exclude = ["node_modules", ".next"]  # directories left out of the project, at any depth
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    pub max: usize,
+    pub exclude: Vec<String>,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self { max: 10, exclude: vec!["node_modules".to_string(), ".next".to_string()] }
+    }
+}
+
+impl SnapshotConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+/// The whole project, untracked and ignored files included, and the galatea_files state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String, // Creation time in Unix milliseconds, which orders snapshots
+    pub label: String,
+    pub created_at: i64, // Unix seconds
+    /// Galatea process that took it
+    pub session: String,
+    /// Project root the snapshot was taken of, and is restored to
+    pub project_root: String,
+    pub project_files: usize,
+    pub galatea_files_files: usize,
+    /// Compressed size of both archives
+    pub size_bytes: u64,
+    /// Directory names left out of the project, and left alone when restoring it
+    pub excluded: Vec<String>,
+}
+
+/// The outcome of restoring a snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Restored {
+    pub snapshot: Snapshot,
+    /// Snapshot of the state before the restore, to undo it
+    pub backup: Snapshot,
+    /// Files created after the snapshot, which the restore deleted, as `project/<path>` or
+    /// `galatea_files/<path>`
+    pub removed: Vec<String>,
+}
+
+// Snapshots are taken and restored one at a time
+static LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+// `path` relative to `root`, if it lies inside it
+fn nested(root: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?;
+    (!rel.as_os_str().is_empty()).then(|| rel.to_string_lossy().replace('\\', "/"))
+}
+
+// Archives `root` into `archive`. `anchored` are paths relative to `root`, `names` directory
+// names matched at any depth.
+async fn archive(root: &Path, archive: &Path, anchored: &[String], names: &[String]) -> Result<()> {
+    let mut cmd = Command::new("tar");
+    cmd.arg("-czf").arg(archive).arg("-C").arg(root);
+    for path in anchored {
+        cmd.arg(format!("--exclude=./{}", path));
+    }
+    for name in names {
+        cmd.arg(format!("--exclude={}", name));
+    }
+    let output = cmd.arg(".").output().await.context("Failed to run tar")?;
+    // 1 means a file changed while it was read, e.g. under a running dev server
+    match output.status.code() {
+        Some(0) => Ok(()),
+        Some(1) => {
+            tracing::warn!(target: "dev_operation::snapshots", root = %root.display(), stderr = %String::from_utf8_lossy(&output.stderr).trim(), "Files changed while being archived.");
+            Ok(())
+        }
+        _ => bail!("tar failed for {}: {}", root.display(), String::from_utf8_lossy(&output.stderr).trim()),
+    }
+}
+
+// Paths in an archive, relative to its root; directories end with `/`
+async fn members(archive: &Path) -> Result<Vec<String>> {
+    let output = Command::new("tar").arg("-tzf").arg(archive).output().await.context("Failed to run tar")?;
+    if !output.status.success() {
+        bail!("Failed to read {}: {}", archive.display(), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim_start_matches("./").to_string())
+        .filter(|path| !path.is_empty())
+        .collect())
+}
+
+async fn file_count(archive: &Path) -> Result<usize> {
+    Ok(members(archive).await?.iter().filter(|m| !m.ends_with('/')).count())
+}
+
+// Archives for the project and galatea_files, each leaving out the other where one is inside it
+fn exclusions(galatea_files: &Path, project_root: &Path) -> (Vec<String>, Vec<String>) {
+    let project = nested(project_root, galatea_files).into_iter().collect();
+    let mut data: Vec<String> = GALATEA_FILES_EXCLUDED.iter().chain(db::DB_FILES).map(|s| s.to_string()).collect();
+    data.extend(nested(galatea_files, project_root));
+    (project, data)
+}
+
+async fn create_in(galatea_files: &Path, project_root: &Path, label: &str, excluded: &[String]) -> Result<Snapshot> {
+    let root = galatea_files.join(SNAPSHOTS_DIR);
+    let mut id = util::now_ms();
+    while root.join(id.to_string()).exists() {
+        id += 1;
+    }
+    let dir = root.join(id.to_string());
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+    let (project_anchored, data_anchored) = exclusions(galatea_files, project_root);
+    let result = async {
+        archive(project_root, &dir.join(PROJECT_ARCHIVE), &project_anchored, excluded).await?;
+        archive(galatea_files, &dir.join(GALATEA_FILES_ARCHIVE), &data_anchored, &[]).await?;
+        let size_bytes = [PROJECT_ARCHIVE, GALATEA_FILES_ARCHIVE].iter().filter_map(|a| fs::metadata(dir.join(a)).ok()).map(|m| m.len()).sum();
+        let snapshot = Snapshot {
+            id: id.to_string(),
+            label: label.to_string(),
+            created_at: (id / 1000) as i64,
+            session: events::session_id().to_string(),
+            project_root: project_root.to_string_lossy().into_owned(),
+            project_files: file_count(&dir.join(PROJECT_ARCHIVE)).await?,
+            galatea_files_files: file_count(&dir.join(GALATEA_FILES_ARCHIVE)).await?,
+            size_bytes,
+            excluded: excluded.to_vec(),
+        };
+        fs::write(dir.join(METADATA_FILE), serde_json::to_string_pretty(&snapshot)?)?;
+        Ok(snapshot)
+    }
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&dir);
+    }
+    result
+}
+
+fn list_in(galatea_files: &Path) -> Result<Vec<Snapshot>> {
+    let root = galatea_files.join(SNAPSHOTS_DIR);
+    let entries = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context(format!("Failed to read {}", root.display())),
+    };
+    // A directory without metadata is a snapshot still being taken, or one that failed
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| fs::read_to_string(e.path().join(METADATA_FILE)).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect();
+    snapshots.sort_by(|a, b| b.id.len().cmp(&a.id.len()).then_with(|| b.id.cmp(&a.id)));
+    Ok(snapshots)
+}
+
+// Deletes the oldest snapshots beyond `max`
+fn prune_in(galatea_files: &Path, max: usize) -> Result<()> {
+    for snapshot in list_in(galatea_files)?.into_iter().skip(max.max(1)) {
+        fs::remove_dir_all(galatea_files.join(SNAPSHOTS_DIR).join(&snapshot.id)).context(format!("Failed to delete snapshot {}", snapshot.id))?;
+    }
+    Ok(())
+}
+
+// Extracts `archive` over `root`, then deletes what the archive doesn't have. Paths `skip`
+// (relative to `root`) and directories named in `names` are left alone.
+async fn restore_tree(root: &Path, archive: &Path, skip: &[String], names: &[String], prefix: &str) -> Result<Vec<String>> {
+    let members: HashSet<String> = members(archive).await?.into_iter().map(|m| m.trim_end_matches('/').to_string()).collect();
+    fs::create_dir_all(root).context(format!("Failed to create {}", root.display()))?;
+    let output = Command::new("tar").arg("-xzf").arg(archive).arg("-C").arg(root).output().await.context("Failed to run tar")?;
+    if !output.status.success() {
+        bail!("Failed to extract {}: {}", archive.display(), String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    // Pre-order, so left-alone directories are never descended into; directories go last, deepest first
+    let stale: Vec<walkdir::DirEntry> = WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| {
+            let excluded_dir = e.file_type().is_dir() && names.iter().any(|n| e.file_name() == n.as_str());
+            !excluded_dir && !skip.contains(&nested(root, e.path()).unwrap_or_default())
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| !members.contains(&nested(root, e.path()).unwrap_or_default()))
+        .collect();
+    let mut removed = Vec::new();
+    for entry in stale.iter().filter(|e| !e.file_type().is_dir()) {
+        match fs::remove_file(entry.path()) {
+            Ok(()) => removed.push(format!("{}/{}", prefix, nested(root, entry.path()).unwrap_or_default())),
+            Err(e) => tracing::warn!(target: "dev_operation::snapshots", path = %entry.path().display(), error = %e, "Failed to delete a file created after the snapshot."),
+        }
+    }
+    for entry in stale.iter().rev().filter(|e| e.file_type().is_dir()) {
+        // Fails when it still holds something left alone
+        let _ = fs::remove_dir(entry.path());
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+async fn restore_in(galatea_files: &Path, id: &str) -> Result<Restored> {
+    let snapshot = list_in(galatea_files)?.into_iter().find(|s| s.id == id).ok_or_else(|| anyhow!("No snapshot '{}'", id))?;
+    let dir = galatea_files.join(SNAPSHOTS_DIR).join(id);
+    let project_root = PathBuf::from(&snapshot.project_root);
+    let backup = create_in(galatea_files, &project_root, &format!("Before restoring snapshot {}", id), &snapshot.excluded).await?;
+
+    let (project_anchored, data_anchored) = exclusions(galatea_files, &project_root);
+    let mut removed = restore_tree(&project_root, &dir.join(PROJECT_ARCHIVE), &project_anchored, &snapshot.excluded, "project").await?;
+    removed.extend(restore_tree(galatea_files, &dir.join(GALATEA_FILES_ARCHIVE), &data_anchored, &[], "galatea_files").await?);
+    lsp_pool::files_edited(EditedFiles::Unknown);
+    tracing::info!(target: "dev_operation::snapshots", id = %id, removed = removed.len(), "Snapshot restored.");
+    Ok(Restored { snapshot, backup, removed })
+}
+
+/// Archives the current project (every file, untracked and ignored ones included, except the
+/// directories named in `[snapshots] exclude`) and galatea_files (except the snapshots, the
+/// database, caches and other workspaces). The oldest snapshots beyond `max` are deleted.
+pub async fn create(label: &str) -> Result<Snapshot> {
+    let _lock = LOCK.lock().await;
+    let config = SnapshotConfig::load();
+    let galatea_files = paths::galatea_files_dir()?;
+    let snapshot = create_in(&galatea_files, &paths::get_project_root()?, label, &config.exclude).await?;
+    prune_in(&galatea_files, config.max)?;
+    tracing::info!(target: "dev_operation::snapshots", id = %snapshot.id, files = snapshot.project_files, "Snapshot created.");
+    Ok(snapshot)
+}
+
+/// Snapshots, newest first.
+pub fn list() -> Result<Vec<Snapshot>> {
+    list_in(&paths::galatea_files_dir()?)
+}
+
+/// Brings the project the snapshot was taken of and galatea_files back to snapshot `id`: their
+/// files are rewritten and files created since are deleted. What snapshots leave out is left
+/// alone. The current state is snapshotted first, so a restore can itself be undone.
+pub async fn restore(id: &str) -> Result<Restored> {
+    let _lock = LOCK.lock().await;
+    let galatea_files = paths::galatea_files_dir()?;
+    let restored = restore_in(&galatea_files, id).await?;
+    // Only now, so the backup never pushes out the snapshot being restored
+    prune_in(&galatea_files, SnapshotConfig::load().max)?;
+    Ok(restored)
+}
+
+/// Returns whether the snapshot existed.
+pub async fn delete(id: &str) -> Result<bool> {
+    let _lock = LOCK.lock().await;
+    let galatea_files = paths::galatea_files_dir()?;
+    if !list_in(&galatea_files)?.iter().any(|s| s.id == id) {
+        return Ok(false);
+    }
+    fs::remove_dir_all(galatea_files.join(SNAPSHOTS_DIR).join(id)).context(format!("Failed to delete snapshot {}", id))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_restore_brings_back_files_and_deletes_new_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join("galatea_files");
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(project.join("node_modules/react")).unwrap();
+        fs::create_dir_all(&data).unwrap();
+        fs::write(project.join("src/page.tsx"), "v1").unwrap();
+        fs::write(project.join(".env"), "KEY=1").unwrap();
+        fs::write(project.join("node_modules/react/index.js"), "react").unwrap();
+        fs::write(data.join("config.toml"), "port = 3051").unwrap();
+        for file in db::DB_FILES {
+            fs::write(data.join(file), "live").unwrap();
+        }
+
+        let excluded = vec!["node_modules".to_string()];
+        let snapshot = create_in(&data, &project, "Before the experiment", &excluded).await.unwrap();
+        assert_eq!((snapshot.project_files, snapshot.galatea_files_files), (2, 1));
+
+        fs::write(project.join("src/page.tsx"), "v2").unwrap();
+        fs::remove_file(project.join(".env")).unwrap();
+        fs::create_dir_all(project.join("src/new")).unwrap();
+        fs::write(project.join("src/new/extra.ts"), "new").unwrap();
+        fs::write(project.join("node_modules/react/index.js"), "react 19").unwrap();
+        fs::write(data.join("config.toml"), "port = 4000").unwrap();
+        // The database keeps writing after the snapshot; a restore must leave it alone
+        for file in db::DB_FILES {
+            fs::write(data.join(file), "live, later").unwrap();
+        }
+
+        let restored = restore_in(&data, &snapshot.id).await.unwrap();
+        assert_eq!(restored.removed, vec!["project/src/new/extra.ts"]);
+        assert_eq!(fs::read_to_string(project.join("src/page.tsx")).unwrap(), "v1");
+        assert_eq!(fs::read_to_string(project.join(".env")).unwrap(), "KEY=1");
+        assert!(!project.join("src/new").exists());
+        assert_eq!(fs::read_to_string(project.join("node_modules/react/index.js")).unwrap(), "react 19");
+        assert_eq!(fs::read_to_string(data.join("config.toml")).unwrap(), "port = 3051");
+        for file in db::DB_FILES {
+            assert_eq!(fs::read_to_string(data.join(file)).unwrap(), "live, later");
+        }
+
+        let ids: Vec<String> = list_in(&data).unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![restored.backup.id.clone(), snapshot.id.clone()]);
+        prune_in(&data, 1).unwrap();
+        assert_eq!(list_in(&data).unwrap()[0].id, restored.backup.id);
+    }
+}
@@ -0,0 +1,338 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use walkdir::WalkDir;
+
+use crate::codebase_indexing::structure::{self as structure_tree, StructureNode};
+use crate::dev_runtime::util::now_secs;
+use crate::file_system::paths;
+
+const STRUCTURE_FILE_NAME: &str = "project_structure.json";
+const CHANGES_FILE_NAME: &str = "structure_changes.jsonl";
+pub const SKIPPED_DIRS: &[&str] = &["node_modules", ".git", ".next", "dist", "build", "out", "coverage", ".turbo"];
+const PAGE_EXTENSIONS: &[&str] = &["tsx", "ts", "jsx", "js", "mdx"];
+
+/// Contents of `galatea_files/project_structure.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProjectStructure {
+    pub generated_at: u64,
+    /// Page routes of the app and pages routers, e.g. `/blog/[slug]`
+    pub routes: Vec<String>,
+    /// Route handlers (`app/**/route.ts`) and `pages/api` endpoints
+    pub api_routes: Vec<String>,
+    /// Component files under any `components` directory, relative to the project root
+    pub components: Vec<String>,
+    pub directories: Vec<String>,
+    /// Directories and files with entity counts and exports; not part of `StructureDiff`
+    pub tree: Vec<StructureNode>,
+}
+
+/// What changed between two structure snapshots.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StructureDiff {
+    pub routes_added: Vec<String>,
+    pub routes_removed: Vec<String>,
+    pub api_routes_added: Vec<String>,
+    pub api_routes_removed: Vec<String>,
+    pub components_added: Vec<String>,
+    pub components_removed: Vec<String>,
+    pub directories_added: Vec<String>,
+    pub directories_removed: Vec<String>,
+}
+
+impl StructureDiff {
+    pub fn between(old: &ProjectStructure, new: &ProjectStructure) -> Self {
+        let (routes_added, routes_removed) = set_diff(&old.routes, &new.routes);
+        let (api_routes_added, api_routes_removed) = set_diff(&old.api_routes, &new.api_routes);
+        let (components_added, components_removed) = set_diff(&old.components, &new.components);
+        let (directories_added, directories_removed) = set_diff(&old.directories, &new.directories);
+        Self {
+            routes_added,
+            routes_removed,
+            api_routes_added,
+            api_routes_removed,
+            components_added,
+            components_removed,
+            directories_added,
+            directories_removed,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+fn set_diff(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let old: BTreeSet<&String> = old.iter().collect();
+    let new: BTreeSet<&String> = new.iter().collect();
+    (
+        new.difference(&old).map(|s| s.to_string()).collect(),
+        old.difference(&new).map(|s| s.to_string()).collect(),
+    )
+}
+
+/// One line of `galatea_files/structure_changes.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StructureChange {
+    pub seq: u64,
+    pub timestamp: u64,
+    /// What triggered the refresh: `startup`, `api`, `scheduled` or `file_update`
+    pub trigger: String,
+    pub diff: StructureDiff,
+}
+
+struct ChangeLog {
+    changes: Vec<StructureChange>,
+    next_seq: u64,
+}
+
+static CHANGE_LOG: Lazy<Mutex<ChangeLog>> = Lazy::new(|| {
+    // Malformed lines (e.g. a write cut short by a crash) are skipped
+    let changes: Vec<StructureChange> = changes_path()
+        .ok()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .map(|content| content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+        .unwrap_or_default();
+    let next_seq = changes.last().map_or(1, |c| c.seq + 1);
+    Mutex::new(ChangeLog { changes, next_seq })
+});
+
+static CHANGE_EVENTS: Lazy<broadcast::Sender<StructureChange>> = Lazy::new(|| broadcast::channel(64).0);
+
+// Serializes refreshes, so two of them can't diff against the same previous snapshot
+static REFRESH_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+fn galatea_files_dir() -> Result<PathBuf> {
+    paths::galatea_files_dir()
+}
+
+pub fn structure_path() -> Result<PathBuf> {
+    Ok(galatea_files_dir()?.join(STRUCTURE_FILE_NAME))
+}
+
+fn changes_path() -> Result<PathBuf> {
+    Ok(galatea_files_dir()?.join(CHANGES_FILE_NAME))
+}
+
+/// Scans the project for its routes, components and directories, and builds its tree.
+pub fn scan(project_root: &Path) -> ProjectStructure {
+    let mut structure = ProjectStructure {
+        generated_at: now_secs(),
+        tree: structure_tree::build_tree(project_root, SKIPPED_DIRS),
+        ..Default::default()
+    };
+    let walker = WalkDir::new(project_root).min_depth(1).into_iter().filter_entry(|entry| {
+        !(entry.file_type().is_dir() && SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+    });
+    for entry in walker.filter_map(|e| e.ok()) {
+        let Ok(rel) = entry.path().strip_prefix(project_root) else { continue };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        if entry.file_type().is_dir() {
+            structure.directories.push(rel);
+            continue;
+        }
+        match classify_route(&rel) {
+            Some(RouteKind::Page(route)) => structure.routes.push(route),
+            Some(RouteKind::Api(route)) => structure.api_routes.push(route),
+            None => {}
+        }
+        let is_component_file = rel.ends_with(".tsx") || rel.ends_with(".jsx");
+        if is_component_file && rel.split('/').any(|segment| segment == "components") {
+            structure.components.push(rel);
+        }
+    }
+    for list in [&mut structure.routes, &mut structure.api_routes, &mut structure.components, &mut structure.directories] {
+        list.sort();
+        list.dedup();
+    }
+    structure
+}
+
+enum RouteKind {
+    Page(String),
+    Api(String),
+}
+
+fn classify_route(rel_path: &str) -> Option<RouteKind> {
+    let rel_path = rel_path.strip_prefix("src/").unwrap_or(rel_path);
+    let (stem, ext) = rel_path.rsplit_once('.')?;
+    if !PAGE_EXTENSIONS.contains(&ext) {
+        return None;
+    }
+    if let Some(rest) = stem.strip_prefix("app/") {
+        let (dir, file) = rest.rsplit_once('/').unwrap_or(("", rest));
+        let route = route_from_segments(dir);
+        return match file {
+            "page" => Some(RouteKind::Page(route)),
+            "route" => Some(RouteKind::Api(route)),
+            _ => None,
+        };
+    }
+    let rest = stem.strip_prefix("pages/")?;
+    let file = rest.rsplit('/').next().unwrap_or(rest);
+    if file.starts_with('_') {
+        return None;
+    }
+    let rest = rest.strip_suffix("/index").or_else(|| (rest == "index").then_some("")).unwrap_or(rest);
+    let route = route_from_segments(&format!("/{}", rest));
+    if route == "/api" || route.starts_with("/api/") {
+        Some(RouteKind::Api(route))
+    } else {
+        Some(RouteKind::Page(route))
+    }
+}
+
+// Route groups `(name)` and parallel route slots `@name` don't appear in the URL
+fn route_from_segments(dir: &str) -> String {
+    let is_hidden = |s: &str| s.is_empty() || s.starts_with('@') || (s.starts_with('(') && s.ends_with(')'));
+    let segments: Vec<&str> = dir.split('/').filter(|s| !is_hidden(s)).collect();
+    format!("/{}", segments.join("/"))
+}
+
+/// The last structure written to project_structure.json, if it holds one.
+pub fn load_structure() -> Option<ProjectStructure> {
+    let content = fs::read_to_string(structure_path().ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Rescans the project, rewrites project_structure.json and publishes what changed since the
+/// previous snapshot. Returns `None` when nothing changed.
+pub async fn refresh(project_root: &Path, trigger: &str) -> Result<Option<StructureChange>> {
+    let _guard = REFRESH_LOCK.lock().await;
+    let previous = load_structure();
+    let root = project_root.to_path_buf();
+    let current = tokio::task::spawn_blocking(move || scan(&root)).await.context("Structure scan panicked")?;
+
+    let path = structure_path()?;
+    let content = serde_json::to_string_pretty(&current).context("Failed to serialize project structure")?;
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    // The first snapshot has nothing to compare against
+    let Some(previous) = previous else {
+        tracing::info!(target: "dev_operation::structure", routes = current.routes.len(), components = current.components.len(), "Project structure recorded.");
+        return Ok(None);
+    };
+    let diff = StructureDiff::between(&previous, &current);
+    if diff.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(publish(trigger, diff)))
+}
+
+fn publish(trigger: &str, diff: StructureDiff) -> StructureChange {
+    let mut log = CHANGE_LOG.lock().unwrap_or_else(|e| e.into_inner());
+    let change = StructureChange { seq: log.next_seq, timestamp: now_secs(), trigger: trigger.to_string(), diff };
+    log.next_seq += 1;
+    if let Err(e) = append_change(&change) {
+        tracing::warn!(target: "dev_operation::structure", error = ?e, "Failed to persist structure change.");
+    }
+    log.changes.push(change.clone());
+    // No subscribers is fine; the change is still kept for polling
+    let _ = CHANGE_EVENTS.send(change.clone());
+    tracing::info!(
+        target: "dev_operation::structure",
+        seq = change.seq,
+        trigger,
+        routes_added = change.diff.routes_added.len(),
+        routes_removed = change.diff.routes_removed.len(),
+        components_added = change.diff.components_added.len(),
+        components_removed = change.diff.components_removed.len(),
+        "Project structure changed."
+    );
+    change
+}
+
+fn append_change(change: &StructureChange) -> Result<()> {
+    let path = changes_path()?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .context(format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(change)?).context(format!("Failed to append to {}", path.display()))
+}
+
+/// Publishes the difference a client-written project_structure.json makes to `previous`.
+pub fn record_file_update(previous: Option<ProjectStructure>, content: &str) -> Option<StructureChange> {
+    let current: ProjectStructure = serde_json::from_str(content).ok()?;
+    let diff = StructureDiff::between(&previous?, &current);
+    (!diff.is_empty()).then(|| publish("file_update", diff))
+}
+
+/// Recorded structure changes, oldest first, after sequence number `after_seq` when given.
+pub fn changes(after_seq: Option<u64>, limit: usize) -> Vec<StructureChange> {
+    let log = CHANGE_LOG.lock().unwrap_or_else(|e| e.into_inner());
+    let matching: Vec<&StructureChange> = log.changes.iter().filter(|c| after_seq.is_none_or(|seq| c.seq > seq)).collect();
+    // Without a cursor the most recent changes are the interesting ones
+    let skip = if after_seq.is_none() { matching.len().saturating_sub(limit) } else { 0 };
+    matching.into_iter().skip(skip).take(limit).cloned().collect()
+}
+
+/// Receives every structure change published from now on.
+pub fn subscribe() -> broadcast::Receiver<StructureChange> {
+    CHANGE_EVENTS.subscribe()
+}
+
+/// Refreshes the structure on a fixed interval, publishing changes as they are found.
+pub fn spawn_structure_refresher(project_dir: PathBuf, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = refresh(&project_dir, "scheduled").await {
+                tracing::warn!(target: "dev_operation::structure", error = ?e, "Scheduled structure refresh failed.");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_and_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "src/app/page.tsx",
+            "src/app/(marketing)/about/page.tsx",
+            "src/app/blog/[slug]/page.tsx",
+            "src/app/api/users/route.ts",
+            "src/components/ui/button.tsx",
+            "pages/_app.tsx",
+            "pages/legacy/index.tsx",
+            "node_modules/pkg/components/x.tsx",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let before = scan(root);
+        assert_eq!(before.routes, vec!["/", "/about", "/blog/[slug]", "/legacy"]);
+        assert_eq!(before.api_routes, vec!["/api/users"]);
+        assert_eq!(before.components, vec!["src/components/ui/button.tsx"]);
+        assert!(!before.directories.iter().any(|d| d.starts_with("node_modules")));
+
+        fs::remove_file(root.join("src/components/ui/button.tsx")).unwrap();
+        fs::create_dir_all(root.join("src/app/shop")).unwrap();
+        fs::write(root.join("src/app/shop/page.tsx"), "").unwrap();
+        let diff = StructureDiff::between(&before, &scan(root));
+        assert_eq!(diff.routes_added, vec!["/shop"]);
+        assert_eq!(diff.components_removed, vec!["src/components/ui/button.tsx"]);
+        assert_eq!(diff.directories_added, vec!["src/app/shop"]);
+        assert!(diff.api_routes_added.is_empty() && diff.routes_removed.is_empty());
+        assert!(StructureDiff::between(&before, &before).is_empty());
+    }
+}
@@ -0,0 +1,567 @@
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::editor::{self, CommandType, EditorArgs, TextRange};
+use super::text_encoding::TextEncoding;
+use super::lint::{self, EslintResult};
+use super::typecheck::{self, TypeError};
+use crate::dev_runtime::crash;
+use crate::dev_runtime::util::now_secs;
+use crate::file_system;
+
+const SOURCE_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+const EXCLUDE_DIRS: &[&str] = &["node_modules", ".next", "dist", "build", "out", "coverage"];
+
+// Next.js loads these files by convention, so their exports are used even if nothing imports them
+const NEXTJS_ENTRY_FILE_STEMS: &[&str] = &[
+    "page", "layout", "loading", "error", "not-found", "template", "default", "route",
+    "middleware", "global-error", "instrumentation",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Analyzer {
+    Lint,
+    Typecheck,
+    UnusedExports,
+}
+
+impl Analyzer {
+    pub const ALL: [Analyzer; 3] = [Analyzer::Lint, Analyzer::Typecheck, Analyzer::UnusedExports];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Analyzer::Lint => "lint",
+            Analyzer::Typecheck => "typecheck",
+            Analyzer::UnusedExports => "unused_exports",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.as_str() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Severity::Error, Severity::Warning, Severity::Info].into_iter().find(|s| s.as_str() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestionStatus {
+    Open,
+    Resolved,
+    Dismissed,
+}
+
+impl SuggestionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuggestionStatus::Open => "open",
+            SuggestionStatus::Resolved => "resolved",
+            SuggestionStatus::Dismissed => "dismissed",
+        }
+    }
+}
+
+/// A prepared edit that resolves a suggestion, applied with the editor's `replace_range`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuggestedFix {
+    pub description: String,
+    pub range: TextRange,
+    pub new_text: String,
+    pub expected_hash: String, // Content hash of the file the range was computed against
+    pub diff: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub id: u64,
+    pub analyzer: Analyzer,
+    pub severity: Severity,
+    pub path: String, // Relative to the project root
+    pub entity: Option<String>,
+    pub line: usize,   // 1-indexed
+    pub column: usize, // 0-indexed characters
+    pub message: String,
+    pub rule: Option<String>,
+    pub fix: Option<SuggestedFix>,
+    pub status: SuggestionStatus,
+    pub created_at: u64,
+    pub resolved_at: Option<u64>,
+    pub resolution: Option<String>,
+}
+
+/// A problem reported by an analyzer run, before it is merged into the queue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub rule: Option<String>,
+    pub entity: Option<String>,
+    pub fix: Option<SuggestedFix>,
+}
+
+#[derive(Default)]
+struct SuggestionQueue {
+    items: Vec<Suggestion>,
+    next_id: u64,
+}
+
+static SUGGESTIONS: Lazy<Mutex<SuggestionQueue>> = Lazy::new(|| Mutex::new(SuggestionQueue::default()));
+
+fn relative_path(project_root: &Path, path: &Path) -> String {
+    path.strip_prefix(project_root).unwrap_or(path).to_string_lossy().into_owned()
+}
+
+impl SuggestionQueue {
+    // Merges a full analyzer run: known problems keep their id, new ones are queued, and open
+    // suggestions the analyzer no longer reports are resolved.
+    fn publish(&mut self, analyzer: Analyzer, project_root: &Path, findings: Vec<Finding>) -> usize {
+        let now = now_secs();
+        let mut reported = HashSet::new();
+        let mut added = 0;
+        for finding in findings {
+            let path = relative_path(project_root, &finding.path);
+            let existing = self.items.iter_mut().find(|s| {
+                s.status == SuggestionStatus::Open
+                    && s.analyzer == analyzer
+                    && s.path == path
+                    && s.line == finding.line
+                    && s.column == finding.column
+                    && s.message == finding.message
+            });
+            match existing {
+                Some(suggestion) => {
+                    suggestion.fix = finding.fix;
+                    reported.insert(suggestion.id);
+                }
+                None => {
+                    self.next_id += 1;
+                    self.items.push(Suggestion {
+                        id: self.next_id,
+                        analyzer,
+                        severity: finding.severity,
+                        path,
+                        entity: finding.entity,
+                        line: finding.line,
+                        column: finding.column,
+                        message: finding.message,
+                        rule: finding.rule,
+                        fix: finding.fix,
+                        status: SuggestionStatus::Open,
+                        created_at: now,
+                        resolved_at: None,
+                        resolution: None,
+                    });
+                    reported.insert(self.next_id);
+                    added += 1;
+                }
+            }
+        }
+        for suggestion in self.items.iter_mut() {
+            if suggestion.analyzer == analyzer
+                && suggestion.status == SuggestionStatus::Open
+                && !reported.contains(&suggestion.id)
+            {
+                suggestion.status = SuggestionStatus::Resolved;
+                suggestion.resolved_at = Some(now);
+                suggestion.resolution = Some("no longer reported".to_string());
+            }
+        }
+        added
+    }
+}
+
+/// Lists queued suggestions, most severe first.
+pub fn list_suggestions(
+    status: Option<SuggestionStatus>,
+    analyzer: Option<Analyzer>,
+    path: Option<&str>,
+) -> Vec<Suggestion> {
+    let queue = SUGGESTIONS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut items: Vec<Suggestion> = queue
+        .items
+        .iter()
+        .filter(|s| status.is_none_or(|st| s.status == st))
+        .filter(|s| analyzer.is_none_or(|a| s.analyzer == a))
+        .filter(|s| path.is_none_or(|p| s.path == p))
+        .cloned()
+        .collect();
+    items.sort_by(|a, b| a.severity.cmp(&b.severity).then(a.id.cmp(&b.id)));
+    items
+}
+
+pub fn get_suggestion(id: u64) -> Option<Suggestion> {
+    let queue = SUGGESTIONS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    queue.items.iter().find(|s| s.id == id).cloned()
+}
+
+/// Marks a suggestion as resolved (or dismissed) by the agent.
+pub fn set_status(id: u64, status: SuggestionStatus, resolution: Option<String>) -> Result<Suggestion> {
+    let mut queue = SUGGESTIONS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let suggestion = queue
+        .items
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or_else(|| anyhow!("Suggestion {} not found", id))?;
+    suggestion.status = status;
+    suggestion.resolved_at = (status != SuggestionStatus::Open).then(now_secs);
+    suggestion.resolution = resolution;
+    Ok(suggestion.clone())
+}
+
+/// Applies a suggestion's prepared fix through the editor and marks it resolved.
+///
+/// Fails without touching the file if it changed since the fix was prepared.
+pub async fn apply_suggestion(id: u64) -> Result<Suggestion> {
+    let suggestion = get_suggestion(id).ok_or_else(|| anyhow!("Suggestion {} not found", id))?;
+    if suggestion.status != SuggestionStatus::Open {
+        bail!("Suggestion {} is already {}", id, suggestion.status.as_str());
+    }
+    let fix = suggestion
+        .fix
+        .ok_or_else(|| anyhow!("Suggestion {} has no prepared fix", id))?;
+    let path = file_system::resolve_path(&suggestion.path)?;
+
+    let args = EditorArgs {
+        command: CommandType::ReplaceRange,
+        path: Some(path.to_string_lossy().into_owned()),
+        paths: None,
+        file_text: None,
+        insert_line: None,
+        new_str: Some(fix.new_text),
+        old_str: None,
+        view_range: None,
+        range: Some(fix.range),
+        expected_hash: Some(fix.expected_hash),
+        count: None,
+        use_regex: false,
+        max_replacements: None,
+        dry_run: false,
+        new_path: None,
+        // Applying a suggestion is the confirmation
+        override_guardrails: true,
+        encoding: TextEncoding::Utf8,
+        view_offset: None,
+        view_length: None,
+    };
+    let paths = editor::command_paths(&args);
+    let result = editor::with_files(paths, move |editor| editor::handle_command(editor, args))
+        .await
+        .and_then(|r| r)
+        .map_err(|e| anyhow!(e))?;
+    if let editor::EditorOperationResult::Conflict(_) = result {
+        bail!("{} changed since the fix for suggestion {} was prepared", suggestion.path, id);
+    }
+    set_status(id, SuggestionStatus::Resolved, Some("fix applied".to_string()))
+}
+
+/// Runs the given analyzers against the project and merges their results into the queue.
+///
+/// Returns the number of new suggestions per analyzer. An analyzer that cannot run (e.g. the
+/// project has no ESLint) is logged and leaves its previous suggestions untouched.
+pub async fn run_analyzers(project_dir: &Path, analyzers: &[Analyzer]) -> Vec<(Analyzer, Result<usize>)> {
+    let _operation = crash::track_operation("suggestion analyzers");
+    let mut results = Vec::new();
+    for &analyzer in analyzers {
+        let findings = match analyzer {
+            Analyzer::Lint => run_eslint(project_dir).await,
+            Analyzer::Typecheck => run_typecheck(project_dir).await,
+            Analyzer::UnusedExports => find_unused_exports(project_dir),
+        };
+        let result = findings.map(|findings| {
+            let mut queue = SUGGESTIONS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            queue.publish(analyzer, project_dir, findings)
+        });
+        match &result {
+            Ok(added) => tracing::info!(target: "dev_operation::suggestions", analyzer = analyzer.as_str(), added, "Analyzer run complete."),
+            Err(e) => tracing::warn!(target: "dev_operation::suggestions", analyzer = analyzer.as_str(), error = ?e, "Analyzer run failed."),
+        }
+        results.push((analyzer, result));
+    }
+    results
+}
+
+/// Runs all analyzers every `interval` in a background task.
+pub fn spawn_suggestion_scheduler(project_dir: PathBuf, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            run_analyzers(&project_dir, &Analyzer::ALL).await;
+        }
+    });
+}
+
+// --- Fix helpers ---
+
+// Converts a UTF-16 offset (as used by JavaScript tooling) into a 1-indexed line and character column
+fn utf16_offset_to_position(content: &str, offset: usize) -> (usize, usize) {
+    let (mut line, mut column, mut units) = (1, 0, 0);
+    for c in content.chars() {
+        if units >= offset {
+            break;
+        }
+        units += c.len_utf16();
+        if c == '\n' {
+            line += 1;
+            column = 0;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+fn prepare_fix(path: &Path, range: TextRange, new_text: String, description: String) -> Option<SuggestedFix> {
+    let content = editor::read_text(path, TextEncoding::Utf8).ok()?;
+    let lines: Vec<&str> = content.split('\n').map(|l| l.trim_end_matches('\r')).collect();
+    let first = lines.get(range.start_line - 1)?;
+    let last = lines.get(range.end_line - 1)?;
+    let prefix: String = first.chars().take(range.start_column).collect();
+    let suffix: String = last.chars().skip(range.end_column).collect();
+
+    let mut diff = format!("--- a/{0}\n+++ b/{0}\n@@ -{1} @@\n", path.display(), range.start_line);
+    for line in &lines[range.start_line - 1..range.end_line] {
+        diff.push_str(&format!("-{}\n", line));
+    }
+    for line in format!("{}{}{}", prefix, new_text, suffix).split('\n') {
+        diff.push_str(&format!("+{}\n", line));
+    }
+
+    Some(SuggestedFix {
+        description,
+        range,
+        new_text,
+        expected_hash: editor::content_hash(&content),
+        diff,
+    })
+}
+
+// --- Lint (ESLint) ---
+
+pub(crate) async fn run_eslint(project_dir: &Path) -> Result<Vec<Finding>> {
+    Ok(eslint_findings(lint::run_eslint(project_dir, &[], false).await?))
+}
+
+fn eslint_findings(results: Vec<EslintResult>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for file in results {
+        let path = PathBuf::from(&file.file_path);
+        let content = file
+            .messages
+            .iter()
+            .any(|m| m.fix.is_some())
+            .then(|| fs::read_to_string(&path).ok())
+            .flatten();
+        for message in file.messages {
+            let fix = match (&message.fix, &content) {
+                (Some(fix), Some(content)) => {
+                    let (start_line, start_column) = utf16_offset_to_position(content, fix.range[0]);
+                    let (end_line, end_column) = utf16_offset_to_position(content, fix.range[1]);
+                    prepare_fix(
+                        &path,
+                        TextRange { start_line, start_column, end_line, end_column },
+                        fix.text.clone(),
+                        format!("ESLint autofix for {}", message.rule_id.as_deref().unwrap_or("problem")),
+                    )
+                }
+                _ => None,
+            };
+            findings.push(Finding {
+                severity: if message.severity >= 2 { Severity::Error } else { Severity::Warning },
+                path: path.clone(),
+                line: message.line.unwrap_or(1),
+                column: message.column.unwrap_or(1).saturating_sub(1),
+                message: message.message,
+                rule: message.rule_id,
+                entity: None,
+                fix,
+            });
+        }
+    }
+    findings
+}
+
+// --- Typecheck (tsc) ---
+
+pub(crate) async fn run_typecheck(project_dir: &Path) -> Result<Vec<Finding>> {
+    let (run, _cached) = typecheck::typecheck(project_dir, false).await?;
+    if run.errors.is_empty() && !run.passed() {
+        bail!("tsc failed: {}", run.other_output);
+    }
+    Ok(typecheck_findings(project_dir, &run.errors))
+}
+
+fn typecheck_findings(project_dir: &Path, errors: &[TypeError]) -> Vec<Finding> {
+    errors
+        .iter()
+        .map(|error| Finding {
+            severity: match error.category.as_str() {
+                "error" => Severity::Error,
+                "warning" => Severity::Warning,
+                _ => Severity::Info,
+            },
+            path: project_dir.join(&error.path),
+            line: error.line,
+            column: error.column.saturating_sub(1),
+            message: error.message.clone(),
+            rule: Some(error.code.clone()),
+            entity: None,
+            fix: None,
+        })
+        .collect()
+}
+
+// --- Unused exports ---
+
+fn identifier_tokens(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|token| !token.is_empty())
+}
+
+// Returns (line index, column of `export`, exported name) for named declarations
+fn exported_declarations(content: &str) -> Vec<(usize, usize, String)> {
+    let mut exports = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let indent = line.len() - line.trim_start().len();
+        let Some(rest) = line.trim_start().strip_prefix("export ") else {
+            continue;
+        };
+        let mut words = rest.split_whitespace();
+        let mut keyword = words.next();
+        if keyword == Some("async") || keyword == Some("declare") || keyword == Some("abstract") {
+            keyword = words.next();
+        }
+        if !matches!(
+            keyword,
+            Some("const" | "let" | "var" | "function" | "function*" | "class" | "type" | "interface" | "enum")
+        ) {
+            continue;
+        }
+        if let Some(name) = words.next().and_then(|w| identifier_tokens(w).next()) {
+            exports.push((index, line[..indent].chars().count(), name.to_string()));
+        }
+    }
+    exports
+}
+
+fn find_unused_exports(project_dir: &Path) -> Result<Vec<Finding>> {
+    let files = file_system::find_files_by_extensions(project_dir, SOURCE_EXTENSIONS, EXCLUDE_DIRS)?;
+    let contents: HashMap<PathBuf, String> = files
+        .into_iter()
+        .filter_map(|path| fs::read_to_string(&path).ok().map(|c| (path, c)))
+        .collect();
+
+    // How many files mention each identifier
+    let mut mentions: HashMap<&str, usize> = HashMap::new();
+    for content in contents.values() {
+        for token in identifier_tokens(content).collect::<HashSet<_>>() {
+            *mentions.entry(token).or_default() += 1;
+        }
+    }
+
+    let mut findings = Vec::new();
+    for (path, content) in &contents {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        if NEXTJS_ENTRY_FILE_STEMS.contains(&stem) || stem.contains(".config") || stem.ends_with(".d") {
+            continue;
+        }
+        for (line_index, column, name) in exported_declarations(content) {
+            // Mentioned only by the declaring file
+            if mentions.get(name.as_str()).copied().unwrap_or(0) > 1 {
+                continue;
+            }
+            let line = line_index + 1;
+            let fix = prepare_fix(
+                path,
+                TextRange { start_line: line, start_column: column, end_line: line, end_column: column + "export ".len() },
+                String::new(),
+                format!("Remove the `export` keyword from `{}`", name),
+            );
+            findings.push(Finding {
+                severity: Severity::Info,
+                path: path.clone(),
+                line,
+                column,
+                message: format!("`{}` is exported but not used by any other file", name),
+                rule: Some("unused-export".to_string()),
+                entity: Some(name),
+                fix,
+            });
+        }
+    }
+    findings.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unused_exports_with_fix_and_queue_merge() {
+        let dir = crate::test_support::project_tempdir();
+        let lib = dir.path().join("lib.ts");
+        fs::write(&lib, "export const used = 1;\nexport function unusedHelper() {}\n").unwrap();
+        fs::write(dir.path().join("app.ts"), "import { used } from './lib';\nconsole.log(used);\n").unwrap();
+
+        let findings = find_unused_exports(dir.path()).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].entity.as_deref(), Some("unusedHelper"));
+        assert_eq!(findings[0].line, 2);
+        let fix = findings[0].fix.clone().unwrap();
+        assert!(fix.diff.contains("-export function unusedHelper() {}\n+function unusedHelper() {}"));
+
+        let mut queue = SuggestionQueue::default();
+        assert_eq!(queue.publish(Analyzer::UnusedExports, dir.path(), findings.clone()), 1);
+        assert_eq!(queue.publish(Analyzer::UnusedExports, dir.path(), findings), 0);
+        assert_eq!(queue.items[0].path, "lib.ts");
+        queue.publish(Analyzer::UnusedExports, dir.path(), Vec::new());
+        assert_eq!(queue.items[0].status, SuggestionStatus::Resolved);
+    }
+
+    #[test]
+    fn test_parse_tsc_and_eslint_output() {
+        let tsc = "src/app/page.tsx(12,5): error TS2322: Type 'string' is not assignable to type 'number'.\nFound 1 error.\n";
+        let findings = typecheck_findings(Path::new("/project"), &typecheck::parse_output(tsc).0);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].path, Path::new("/project/src/app/page.tsx"));
+        assert_eq!((findings[0].line, findings[0].column), (12, 4));
+        assert_eq!(findings[0].rule.as_deref(), Some("TS2322"));
+
+        let eslint = r#"[{"filePath": "/nonexistent/a.ts", "messages": [
+            {"ruleId": "no-unused-vars", "severity": 1, "message": "'x' is unused.", "line": 3, "column": 7}
+        ]}]"#;
+        let findings = eslint_findings(lint::parse_output(eslint).unwrap());
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!((findings[0].line, findings[0].column), (3, 6));
+
+        assert_eq!(utf16_offset_to_position("ab\ncé😀d", 7), (2, 3));
+    }
+}
@@ -0,0 +1,408 @@
+use anyhow::{Context, Result};
+use lsp_types::{
+    DocumentSymbol, DocumentSymbolResponse, Location, OneOf, SymbolInformation, Uri,
+    WorkspaceSymbol, WorkspaceSymbolResponse,
+};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::codebase_indexing::index_manager;
+use crate::codebase_indexing::parser::CodeEntity;
+use crate::dev_runtime::db::{self, StoredEntity};
+use crate::dev_runtime::events;
+use crate::dev_runtime::lsp_client::LspClient;
+use crate::dev_runtime::lsp_pool::LspPool;
+use crate::file_system::ranking::{self, RankSignals, Ranked, RankingWeights};
+use crate::file_system;
+
+// Extensions the tree-sitter index knows how to parse
+pub(crate) const INDEXED_EXTENSIONS: &[&str] = crate::codebase_indexing::parser::SUPPORTED_EXTENSIONS;
+
+/// Which backend produced a set of symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolBackend {
+    Lsp,
+    Index,
+}
+
+impl SymbolBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SymbolBackend::Lsp => "lsp",
+            SymbolBackend::Index => "index",
+        }
+    }
+}
+
+/// A symbol in the unified shape shared by the LSP and index backends.
+///
+/// Lines are 1-indexed. `kind` uses the same PascalCase names as `CodeEntity::code_type`
+/// (e.g. "Function", "Class", "Interface").
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub kind: String,
+    pub container_name: Option<String>,
+    pub path: String,
+    pub line: usize,
+    pub line_to: usize,
+}
+
+/// Maps a file extension to the LSP language identifier used in `didOpen`.
+pub fn language_id_for_path(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("ts") => "typescript",
+        Some("tsx") => "typescriptreact",
+        Some("js" | "mjs" | "cjs") => "javascript",
+        Some("jsx") => "javascriptreact",
+        Some("json") => "json",
+        _ => "plaintext",
+    }
+}
+
+/// Builds a `file://` URI for an absolute path.
+pub fn file_uri_for_path(path: &Path) -> Result<Uri> {
+    Uri::from_str(&format!("file://{}", path.display()))
+        .context(format!("Failed to convert path {} to URI", path.display()))
+}
+
+/// Searches symbols across the project, preferring the language server.
+///
+/// Falls back to the tree-sitter index when the project's language server in `pool` is still
+/// starting, errors out, or returns nothing. Results are ranked best first (see
+/// `file_system::ranking`) before `limit` is applied.
+pub async fn workspace_symbols(pool: &Arc<LspPool>, query: &str, limit: usize) -> Result<(Vec<Ranked<SymbolInfo>>, SymbolBackend)> {
+    let project_root = file_system::get_project_root()?;
+    let started = Instant::now();
+
+    if let Some(client) = pool.client_if_ready(&project_root) {
+        match client.workspace_symbol(query).await {
+            Ok(Some(response)) => {
+                let symbols = symbols_from_workspace_response(response, &project_root);
+                if !symbols.is_empty() {
+                    let ranked = rank_symbols(symbols, query, &project_root, limit);
+                    record_search_analytics(SymbolBackend::Lsp, ranked.len(), started);
+                    return Ok((ranked, SymbolBackend::Lsp));
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(target: "dev_operation::symbols", error = ?e, "LSP workspace/symbol failed, falling back to index.");
+            }
+        }
+    }
+
+    let symbols = index_workspace_symbols(&project_root, query)?;
+    let ranked = rank_symbols(symbols, query, &project_root, limit);
+    record_search_analytics(SymbolBackend::Index, ranked.len(), started);
+    Ok((ranked, SymbolBackend::Index))
+}
+
+fn record_search_analytics(backend: SymbolBackend, results: usize, started: Instant) {
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let detail = format!("backend={} results={}", backend.as_str(), results);
+    db::write_later(move |db| {
+        if let Err(e) = db.record_analytics(events::session_id(), "workspace_symbols", Some(latency_ms), Some(&detail)) {
+            tracing::debug!(target: "dev_operation::symbols", error = ?e, "Failed to record search analytics.");
+        }
+    });
+}
+
+// Symbols are always definitions; name match, file location and recency decide the order
+fn rank_symbols(symbols: Vec<SymbolInfo>, query: &str, project_root: &Path, limit: usize) -> Vec<Ranked<SymbolInfo>> {
+    let weights = RankingWeights::load();
+    let mut ranked = ranking::rank(symbols, &weights, |s| RankSignals {
+        path: &s.path,
+        modified: ranking::modified_time(project_root, &s.path),
+        is_definition: true,
+        relevance: ranking::name_match_quality(&s.name, query),
+    });
+    ranked.truncate(limit);
+    ranked
+}
+
+/// Lists the symbols declared in a single file, preferring the language server.
+pub async fn document_symbols(pool: &Arc<LspPool>, path: &Path) -> Result<(Vec<SymbolInfo>, SymbolBackend)> {
+    let project_root = file_system::get_project_root()?;
+
+    if let Some(client) = pool.client_if_ready(&project_root) {
+        match lsp_document_symbols(&client, path, &project_root).await {
+            Ok(symbols) if !symbols.is_empty() => return Ok((symbols, SymbolBackend::Lsp)),
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(target: "dev_operation::symbols", error = ?e, "LSP documentSymbol failed, falling back to index.");
+            }
+        }
+    }
+
+    let symbols = index_document_symbols(path, &project_root)?;
+    Ok((symbols, SymbolBackend::Index))
+}
+
+async fn lsp_document_symbols(
+    client: &LspClient,
+    path: &Path,
+    project_root: &Path,
+) -> Result<Vec<SymbolInfo>> {
+    let uri = file_uri_for_path(path)?;
+    let content = std::fs::read_to_string(path)
+        .context(format!("Failed to read file {}", path.display()))?;
+    client
+        .sync_document(uri.clone(), language_id_for_path(path), content)
+        .await?;
+
+    let rel_path = relative_path(path, project_root);
+    let symbols = match client.document_symbol(uri).await? {
+        Some(DocumentSymbolResponse::Nested(nested)) => {
+            let mut out = Vec::new();
+            flatten_document_symbols(&nested, None, &rel_path, &mut out);
+            out
+        }
+        Some(DocumentSymbolResponse::Flat(flat)) => flat
+            .into_iter()
+            .map(|s| from_symbol_information(s, project_root))
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok(symbols)
+}
+
+/// Resolves the definition(s) of the symbol at `position` as `(relative path, line, character)`.
+///
+/// Positions are 0-indexed as in LSP.
+pub async fn goto_definition(
+    client: &LspClient,
+    path: &Path,
+    position: lsp_types::Position,
+) -> Result<Vec<(String, u32, u32)>> {
+    let project_root = file_system::get_project_root()?;
+    let uri = file_uri_for_path(path)?;
+    let content = std::fs::read_to_string(path)
+        .context(format!("Failed to read file {}", path.display()))?;
+    client
+        .sync_document(uri.clone(), language_id_for_path(path), content)
+        .await?;
+
+    let locations: Vec<(Uri, lsp_types::Position)> = match client.goto_definition(uri, position).await? {
+        Some(lsp_types::GotoDefinitionResponse::Scalar(loc)) => vec![(loc.uri, loc.range.start)],
+        Some(lsp_types::GotoDefinitionResponse::Array(locs)) => {
+            locs.into_iter().map(|loc| (loc.uri, loc.range.start)).collect()
+        }
+        Some(lsp_types::GotoDefinitionResponse::Link(links)) => links
+            .into_iter()
+            .map(|link| (link.target_uri, link.target_selection_range.start))
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok(locations
+        .into_iter()
+        .map(|(uri, pos)| (uri_to_relative_path(&uri, &project_root), pos.line, pos.character))
+        .collect())
+}
+
+fn symbols_from_workspace_response(response: WorkspaceSymbolResponse, project_root: &Path) -> Vec<SymbolInfo> {
+    match response {
+        WorkspaceSymbolResponse::Flat(flat) => flat
+            .into_iter()
+            .map(|s| from_symbol_information(s, project_root))
+            .collect(),
+        WorkspaceSymbolResponse::Nested(nested) => nested
+            .into_iter()
+            .map(|s| from_workspace_symbol(s, project_root))
+            .collect(),
+    }
+}
+
+fn from_symbol_information(symbol: SymbolInformation, project_root: &Path) -> SymbolInfo {
+    let (path, line, line_to) = location_parts(&symbol.location, project_root);
+    SymbolInfo {
+        name: symbol.name,
+        kind: format!("{:?}", symbol.kind),
+        container_name: symbol.container_name,
+        path,
+        line,
+        line_to,
+    }
+}
+
+fn from_workspace_symbol(symbol: WorkspaceSymbol, project_root: &Path) -> SymbolInfo {
+    let (path, line, line_to) = match &symbol.location {
+        OneOf::Left(location) => location_parts(location, project_root),
+        OneOf::Right(ws_location) => (uri_to_relative_path(&ws_location.uri, project_root), 0, 0),
+    };
+    SymbolInfo {
+        name: symbol.name,
+        kind: format!("{:?}", symbol.kind),
+        container_name: symbol.container_name,
+        path,
+        line,
+        line_to,
+    }
+}
+
+fn flatten_document_symbols(
+    symbols: &[DocumentSymbol],
+    container: Option<&str>,
+    rel_path: &str,
+    out: &mut Vec<SymbolInfo>,
+) {
+    for symbol in symbols {
+        out.push(SymbolInfo {
+            name: symbol.name.clone(),
+            kind: format!("{:?}", symbol.kind),
+            container_name: container.map(str::to_string),
+            path: rel_path.to_string(),
+            line: symbol.range.start.line as usize + 1,
+            line_to: symbol.range.end.line as usize + 1,
+        });
+        if let Some(children) = &symbol.children {
+            flatten_document_symbols(children, Some(&symbol.name), rel_path, out);
+        }
+    }
+}
+
+fn location_parts(location: &Location, project_root: &Path) -> (String, usize, usize) {
+    (
+        uri_to_relative_path(&location.uri, project_root),
+        location.range.start.line as usize + 1,
+        location.range.end.line as usize + 1,
+    )
+}
+
+pub(crate) fn uri_to_relative_path(uri: &Uri, project_root: &Path) -> String {
+    let raw = uri.as_str();
+    let path = PathBuf::from(raw.strip_prefix("file://").unwrap_or(raw));
+    relative_path(&path, project_root)
+}
+
+pub(crate) fn relative_path(path: &Path, project_root: &Path) -> String {
+    path.strip_prefix(project_root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+// --- Index fallback ---
+
+fn stored_entity(entity: &CodeEntity) -> StoredEntity {
+    StoredEntity {
+        name: entity.name.clone(),
+        kind: entity.code_type.clone(),
+        container_name: entity.context.struct_name.clone(),
+        line: entity.line,
+        line_to: entity.line_to,
+        record: None,
+    }
+}
+
+fn index_document_symbols(path: &Path, project_root: &Path) -> Result<Vec<SymbolInfo>> {
+    let rel_path = relative_path(path, project_root);
+    let symbols = file_entities(path)?
+        .into_iter()
+        .map(|e| SymbolInfo {
+            name: e.name,
+            kind: e.kind,
+            container_name: e.container_name,
+            path: rel_path.clone(),
+            line: e.line,
+            line_to: e.line_to,
+        })
+        .collect();
+    Ok(symbols)
+}
+
+// A file's entities from the code index, which only re-parses files that changed
+pub(crate) fn file_entities(file: &Path) -> Result<Vec<StoredEntity>> {
+    let entities = index_manager::file_entities(file)?;
+    Ok(entities.iter().filter(|e| e.code_type != "Import").map(stored_entity).collect())
+}
+
+fn index_workspace_symbols(project_root: &Path, query: &str) -> Result<Vec<SymbolInfo>> {
+    let needle = query.to_lowercase();
+    let mut symbols = Vec::new();
+    for (file, entities) in index_manager::current_snapshot(project_root)? {
+        let rel_path = relative_path(&file, project_root);
+        symbols.extend(
+            entities
+                .iter()
+                .filter(|e| e.code_type != "Import" && e.name.to_lowercase().contains(&needle))
+                .map(|e| SymbolInfo {
+                    name: e.name.clone(),
+                    kind: e.code_type.clone(),
+                    container_name: e.context.struct_name.clone(),
+                    path: rel_path.clone(),
+                    line: e.line,
+                    line_to: e.line_to,
+                }),
+        );
+    }
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, Range, SymbolKind};
+
+    fn range(start: u32, end: u32) -> Range {
+        Range {
+            start: Position { line: start, character: 0 },
+            end: Position { line: end, character: 0 },
+        }
+    }
+
+    #[allow(deprecated)]
+    fn document_symbol(name: &str, kind: SymbolKind, children: Option<Vec<DocumentSymbol>>) -> DocumentSymbol {
+        DocumentSymbol {
+            name: name.to_string(),
+            detail: None,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: range(2, 10),
+            selection_range: range(2, 2),
+            children,
+        }
+    }
+
+    #[test]
+    fn test_flatten_document_symbols_tracks_container_and_lines() {
+        let nested = vec![document_symbol(
+            "Widget",
+            SymbolKind::CLASS,
+            Some(vec![document_symbol("render", SymbolKind::METHOD, None)]),
+        )];
+        let mut out = Vec::new();
+        flatten_document_symbols(&nested, None, "src/widget.ts", &mut out);
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].kind, "Class");
+        assert_eq!(out[0].container_name, None);
+        assert_eq!(out[0].line, 3);
+        assert_eq!(out[1].name, "render");
+        assert_eq!(out[1].kind, "Method");
+        assert_eq!(out[1].container_name.as_deref(), Some("Widget"));
+    }
+
+    #[test]
+    fn test_index_workspace_symbols_matches_case_insensitively() {
+        let dir = crate::test_support::project_tempdir();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(
+            src.join("lib.rs"),
+            "use std::fmt;\n\npub struct FooBar;\n\npub fn make_foo() -> FooBar { FooBar }\n\nfn unrelated() {}\n",
+        )
+        .unwrap();
+
+        let symbols = index_workspace_symbols(dir.path(), "foo").unwrap();
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+
+        assert!(names.contains(&"FooBar"));
+        assert!(names.contains(&"make_foo"));
+        assert!(!names.contains(&"unrelated"));
+        assert!(symbols.iter().all(|s| s.path == "src/lib.rs"));
+    }
+}
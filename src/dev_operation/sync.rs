@@ -0,0 +1,860 @@
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use walkdir::WalkDir;
+
+use crate::dev_runtime::util::now_secs;
+use crate::dev_runtime::{crash, db, events};
+use crate::dev_setup::config_files;
+use crate::file_system::paths;
+
+// config.toml table with the sync settings
+const CONFIG_SECTION: &str = "sync";
+
+/// Directory names never synced, in addition to the caller's excludes.
+pub const DEFAULT_EXCLUDES: &[&str] = &["node_modules", ".next", ".git", ".turbo"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDirection {
+    /// Project → target
+    Push,
+    /// Target → project
+    Pull,
+    /// Changes flow both ways
+    Both,
+}
+
+impl SyncDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncDirection::Push => "push",
+            SyncDirection::Pull => "pull",
+            SyncDirection::Both => "both",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [SyncDirection::Push, SyncDirection::Pull, SyncDirection::Both]
+            .into_iter()
+            .find(|d| d.as_str() == name)
+    }
+}
+
+/// What to do when a file changed on both sides since the last sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the most recently modified copy
+    Newer,
+    /// Keep the project's copy
+    Project,
+    /// Keep the target's copy
+    Target,
+    /// Leave both copies alone and report the conflict
+    Skip,
+}
+
+impl ConflictPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Newer => "newer",
+            ConflictPolicy::Project => "project",
+            ConflictPolicy::Target => "target",
+            ConflictPolicy::Skip => "skip",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ConflictPolicy::Newer, ConflictPolicy::Project, ConflictPolicy::Target, ConflictPolicy::Skip]
+            .into_iter()
+            .find(|p| p.as_str() == name)
+    }
+}
+
+/// Sync settings, from `[sync]` in config.toml.
+///
+/// ```toml
+/// [sync]
+/// allowed_roots = ["/srv/mirrors"]            # local targets must be inside one of these
+/// allowed_remotes = ["deploy@build-host"]     # hosts (or user@host) remote targets may name
+/// ```
+///
+/// Both lists are empty by default, which disables local and remote sync respectively. Targets
+/// never reach galatea_files or the directory Galatea is installed in.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub allowed_roots: Vec<String>,
+    pub allowed_remotes: Vec<String>,
+}
+
+impl SyncConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    /// Local directory, or an rsync-style remote (`host:/path`, `user@host:/path`)
+    pub target: String,
+    pub direction: SyncDirection,
+    pub conflict_policy: ConflictPolicy,
+    /// Glob patterns relative to the synced root; patterns without `/` match any path component
+    pub excludes: Vec<String>,
+    /// Propagate deletions
+    pub delete: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncConflict {
+    pub path: String,
+    pub resolution: String, // "kept project", "kept target" or "skipped"
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub copied_to_target: Vec<String>,
+    pub copied_to_project: Vec<String>,
+    pub deleted: Vec<String>,
+    pub conflicts: Vec<SyncConflict>,
+    pub unchanged: usize,
+    pub duration_ms: u64,
+}
+
+/// A continuous sync started via `/api/project/sync`.
+#[derive(Debug, Clone)]
+pub struct SyncSessionInfo {
+    pub id: String,
+    pub options: SyncOptions,
+    pub interval: Duration,
+    pub started_at: u64,
+    pub passes: u64,
+    pub last_report: Option<SyncReport>,
+    pub last_error: Option<String>,
+}
+
+struct SyncSession {
+    info: SyncSessionInfo,
+    handle: JoinHandle<()>,
+}
+
+// Size and modification time of a file, compared against the state recorded at the last sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Signature {
+    modified_ms: u128,
+    size: u64,
+}
+
+// Per file: (project signature, target signature) right after the last successful sync
+type Baseline = HashMap<String, (Signature, Signature)>;
+
+// Baselines by (project, target), so repeated one-shot syncs also detect conflicts
+static BASELINES: Lazy<Mutex<HashMap<(PathBuf, PathBuf), Baseline>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static SESSIONS: Lazy<Mutex<HashMap<String, SyncSession>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Whether `target` is an rsync-style remote rather than a local path.
+pub fn is_remote(target: &str) -> bool {
+    match target.split_once(':') {
+        // A colon before the first slash means `host:path`; `C:` style drive letters are local
+        Some((host, _)) => !host.is_empty() && !host.contains('/') && host.len() > 1,
+        None => false,
+    }
+}
+
+// --- Exclude patterns ---
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) if rest.first() == Some(&b'*') => {
+            let rest = rest[1..].strip_prefix(b"/").unwrap_or(&rest[1..]);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some((b'*', rest)) => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        Some((b'?', rest)) => !text.is_empty() && text[0] != b'/' && glob_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Whether a `/`-separated relative path is excluded by any of the patterns.
+pub fn is_excluded(rel_path: &str, excludes: &[String]) -> bool {
+    excludes.iter().any(|pattern| {
+        let pattern = pattern.trim_end_matches('/');
+        if pattern.contains('/') {
+            let pattern = pattern.trim_start_matches('/');
+            glob_match(pattern.as_bytes(), rel_path.as_bytes())
+                || glob_match(format!("{}/**", pattern).as_bytes(), rel_path.as_bytes())
+        } else {
+            rel_path.split('/').any(|component| glob_match(pattern.as_bytes(), component.as_bytes()))
+        }
+    })
+}
+
+fn effective_excludes(options: &SyncOptions) -> Vec<String> {
+    DEFAULT_EXCLUDES
+        .iter()
+        .map(|s| s.to_string())
+        .chain(options.excludes.iter().cloned())
+        .collect()
+}
+
+// --- Local sync ---
+
+fn signature(metadata: &fs::Metadata) -> Signature {
+    Signature {
+        modified_ms: metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis())
+            .unwrap_or_default(),
+        size: metadata.len(),
+    }
+}
+
+fn scan(root: &Path, excludes: &[String]) -> Result<BTreeMap<String, Signature>> {
+    let mut files = BTreeMap::new();
+    if !root.exists() {
+        return Ok(files);
+    }
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        let rel = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        rel.as_os_str().is_empty() || !is_excluded(&rel.to_string_lossy().replace('\\', "/"), excludes)
+    });
+    for entry in walker {
+        let entry = entry.context(format!("Failed to scan {}", root.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let metadata = entry.metadata().context(format!("Failed to stat {}", entry.path().display()))?;
+        files.insert(rel.to_string_lossy().replace('\\', "/"), signature(&metadata));
+    }
+    Ok(files)
+}
+
+// Files untouched on both sides since the last sync are equal without reading them
+fn same_content(a: &Path, b: &Path, sig_a: Signature, sig_b: Signature, base: Option<(Signature, Signature)>) -> bool {
+    if base == Some((sig_a, sig_b)) {
+        return true;
+    }
+    if sig_a.size != sig_b.size {
+        return false;
+    }
+    matches!((fs::read(a), fs::read(b)), (Ok(x), Ok(y)) if x == y)
+}
+
+// Copies a file and its modification time, so both sides end up with the same signature
+fn copy_file(from: &Path, to: &Path) -> Result<Signature> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).context(format!("Failed to create {}", parent.display()))?;
+    }
+    fs::copy(from, to).context(format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    let modified = fs::metadata(from)?.modified()?;
+    fs::File::options().write(true).open(to)?.set_modified(modified)?;
+    Ok(signature(&fs::metadata(to)?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    Project,
+    Target,
+}
+
+fn resolve_conflict(policy: ConflictPolicy, project: Option<Signature>, target: Option<Signature>) -> Option<Side> {
+    match policy {
+        ConflictPolicy::Project => Some(Side::Project),
+        ConflictPolicy::Target => Some(Side::Target),
+        ConflictPolicy::Skip => None,
+        ConflictPolicy::Newer => {
+            let modified = |sig: Option<Signature>| sig.map(|s| s.modified_ms).unwrap_or_default();
+            Some(if modified(project) >= modified(target) { Side::Project } else { Side::Target })
+        }
+    }
+}
+
+/// One sync pass between two local directories.
+fn sync_local(project: &Path, target: &Path, options: &SyncOptions, baseline: &mut Baseline) -> Result<SyncReport> {
+    let started = Instant::now();
+    let excludes = effective_excludes(options);
+    let project_files = scan(project, &excludes)?;
+    let target_files = scan(target, &excludes)?;
+    let paths: BTreeSet<&String> = project_files.keys().chain(target_files.keys()).collect();
+
+    let mut report = SyncReport::default();
+    let mut next_baseline = Baseline::new();
+    for path in paths {
+        let p_sig = project_files.get(path).copied();
+        let t_sig = target_files.get(path).copied();
+        let base = baseline.get(path).copied();
+        let p_file = project.join(path);
+        let t_file = target.join(path);
+
+        if let (Some(p), Some(t)) = (p_sig, t_sig) {
+            if same_content(&p_file, &t_file, p, t, base) {
+                next_baseline.insert(path.clone(), (p, t));
+                report.unchanged += 1;
+                continue;
+            }
+        }
+
+        let p_changed = p_sig != base.map(|b| b.0);
+        let t_changed = t_sig != base.map(|b| b.1);
+        // The side whose state should win, before applying the direction and conflict policy
+        let winner = match (options.direction, base.is_some()) {
+            (SyncDirection::Push, true) if t_changed && p_changed => None,
+            (SyncDirection::Push, _) => Some(Side::Project),
+            (SyncDirection::Pull, true) if t_changed && p_changed => None,
+            (SyncDirection::Pull, _) => Some(Side::Target),
+            // Without a baseline a file present on one side only counts as changed there
+            (SyncDirection::Both, _) if p_changed != t_changed => {
+                Some(if p_changed { Side::Project } else { Side::Target })
+            }
+            (SyncDirection::Both, _) => None,
+        };
+        let winner = match winner {
+            Some(side) => Some(side),
+            None => {
+                let resolved = resolve_conflict(options.conflict_policy, p_sig, t_sig);
+                report.conflicts.push(SyncConflict {
+                    path: path.clone(),
+                    resolution: match resolved {
+                        Some(Side::Project) => "kept project",
+                        Some(Side::Target) => "kept target",
+                        None => "skipped",
+                    }
+                    .to_string(),
+                });
+                resolved
+            }
+        };
+
+        let allowed = |side: Side| match options.direction {
+            SyncDirection::Push => side == Side::Project,
+            SyncDirection::Pull => side == Side::Target,
+            SyncDirection::Both => true,
+        };
+        match winner {
+            Some(side) if allowed(side) => {
+                let (from, to, from_sig) = match side {
+                    Side::Project => (&p_file, &t_file, p_sig),
+                    Side::Target => (&t_file, &p_file, t_sig),
+                };
+                match from_sig {
+                    Some(sig) => {
+                        let copied = copy_file(from, to)?;
+                        let state = match side {
+                            Side::Project => {
+                                report.copied_to_target.push(path.clone());
+                                (sig, copied)
+                            }
+                            Side::Target => {
+                                report.copied_to_project.push(path.clone());
+                                (copied, sig)
+                            }
+                        };
+                        next_baseline.insert(path.clone(), state);
+                    }
+                    // The winning side deleted the file
+                    None if options.delete => {
+                        fs::remove_file(to).context(format!("Failed to delete {}", to.display()))?;
+                        report.deleted.push(path.clone());
+                    }
+                    None => {}
+                }
+            }
+            // The direction keeps the other side's copy; accept the current state as synced
+            Some(_) => {
+                if let (Some(p), Some(t)) = (p_sig, t_sig) {
+                    next_baseline.insert(path.clone(), (p, t));
+                }
+            }
+            // Skipped conflict: keep the old baseline so it is reported again instead of overwritten
+            None => {
+                if let Some(b) = base {
+                    next_baseline.insert(path.clone(), b);
+                }
+            }
+        }
+    }
+
+    *baseline = next_baseline;
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    Ok(report)
+}
+
+// `path` with symlinks resolved as far as it exists, the rest appended as is
+fn resolve_existing(path: &Path) -> Result<PathBuf> {
+    if path.components().any(|c| c == Component::ParentDir) {
+        bail!("Sync target {} must not contain '..'", path.display());
+    }
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let (Some(name), Some(parent)) = (existing.file_name(), existing.parent()) else {
+            bail!("Sync target {} has no existing parent directory", path.display());
+        };
+        missing.push(name);
+        existing = parent;
+    }
+    let mut resolved = dunce::canonicalize(existing).context(format!("Failed to resolve {}", existing.display()))?;
+    resolved.extend(missing.into_iter().rev());
+    Ok(resolved)
+}
+
+// Directories no sync target may equal, contain or lie inside: galatea_files, with config.toml
+// and its API tokens, and the install directory around it
+fn protected_dirs() -> Result<Vec<PathBuf>> {
+    [paths::galatea_files_dir()?, paths::install_dir()?].iter().map(|dir| resolve_existing(dir)).collect()
+}
+
+// The local target to sync with: an absolute path inside one of `allowed_roots`, neither
+// containing nor inside the project or one of the `protected` directories
+fn local_target(project: &Path, target: &str, allowed_roots: &[String], protected: &[PathBuf]) -> Result<PathBuf> {
+    if allowed_roots.is_empty() {
+        bail!("Local sync is disabled; list the directories targets may be in under allowed_roots in [sync]");
+    }
+    if !Path::new(target).is_absolute() {
+        bail!("Sync target '{}' must be an absolute path", target);
+    }
+    let project = dunce::canonicalize(project)?;
+    let target = resolve_existing(Path::new(target))?;
+    let roots: Vec<PathBuf> = allowed_roots.iter().filter_map(|r| resolve_existing(Path::new(r)).ok()).collect();
+    if !roots.iter().any(|root| target.starts_with(root)) {
+        bail!(
+            "Sync target {} is outside the allowed directories ({}); add its directory to allowed_roots in [sync]",
+            target.display(),
+            roots.iter().map(|r| r.display().to_string()).collect::<Vec<_>>().join(", ")
+        );
+    }
+    if project.starts_with(&target) || target.starts_with(&project) {
+        bail!("Sync target {} must not contain or be inside the project directory", target.display());
+    }
+    if let Some(dir) = protected.iter().find(|dir| dir.starts_with(&target) || target.starts_with(dir)) {
+        bail!("Sync target {} must not contain or be inside Galatea's own directory {}", target.display(), dir.display());
+    }
+    fs::create_dir_all(&target).context(format!("Failed to create sync target {}", target.display()))?;
+    Ok(target)
+}
+
+// --- Remote sync (rsync) ---
+
+// Checks the host of a `host:path` or `user@host:path` target against `allowed_remotes`, whose
+// entries are a host (any user) or a `user@host`
+fn check_remote(target: &str, allowed_remotes: &[String]) -> Result<()> {
+    if allowed_remotes.is_empty() {
+        bail!("Remote sync is disabled; list the hosts targets may name under allowed_remotes in [sync]");
+    }
+    let login = target.split_once(':').map_or(target, |(login, _)| login);
+    let host = login.rsplit_once('@').map_or(login, |(_, host)| host);
+    if !allowed_remotes.iter().any(|allowed| allowed == login || allowed == host) {
+        bail!("Sync target host '{}' is not in allowed_remotes in [sync]", login);
+    }
+    Ok(())
+}
+
+async fn sync_remote(project: &Path, options: &SyncOptions) -> Result<SyncReport> {
+    let started = Instant::now();
+    let project_arg = format!("{}/", project.display());
+    let target_arg = format!("{}/", options.target.trim_end_matches('/'));
+    let (from, to) = match options.direction {
+        SyncDirection::Push => (project_arg, target_arg),
+        SyncDirection::Pull => (target_arg, project_arg),
+        SyncDirection::Both => bail!("Bidirectional sync needs a local target; use push or pull for remote targets"),
+    };
+
+    let mut args: Vec<String> = vec!["-az".into(), "--out-format=%n".into()];
+    if options.delete {
+        args.push("--delete".into());
+    }
+    // rsync has no baseline to tell conflicts apart, so the policy applies to every file on both
+    // sides: the receiving side keeps its copy unless the policy lets the sending side win
+    let receiver_wins = match (options.conflict_policy, options.direction) {
+        (ConflictPolicy::Newer, _) => {
+            args.push("--update".into());
+            false
+        }
+        (ConflictPolicy::Skip, _) | (ConflictPolicy::Target, SyncDirection::Push) | (ConflictPolicy::Project, SyncDirection::Pull) => true,
+        _ => false,
+    };
+    if receiver_wins {
+        args.push("--ignore-existing".into());
+    }
+    for pattern in effective_excludes(options) {
+        args.push(format!("--exclude={}", pattern));
+    }
+    args.push("--".into());
+    args.push(from);
+    args.push(to);
+
+    let output = Command::new("rsync")
+        .args(&args)
+        .output()
+        .await
+        .context("Failed to run rsync. Remote sync targets require rsync on PATH.")?;
+    if !output.status.success() {
+        bail!("rsync failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let mut report = SyncReport::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let line = line.trim();
+        if line.is_empty() || line.ends_with('/') {
+            continue;
+        }
+        match (line.strip_prefix("deleting "), options.direction) {
+            (Some(path), _) => report.deleted.push(path.to_string()),
+            (None, SyncDirection::Pull) => report.copied_to_project.push(line.to_string()),
+            (None, _) => report.copied_to_target.push(line.to_string()),
+        }
+    }
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    Ok(report)
+}
+
+// --- Entry points ---
+
+/// Runs a single sync pass between the project and `options.target`.
+pub async fn sync_once(project: &Path, options: &SyncOptions) -> Result<SyncReport> {
+    let _operation = crash::track_operation("project sync");
+    if options.target.starts_with('-') {
+        bail!("Sync target '{}' must not start with '-'", options.target);
+    }
+    let config = SyncConfig::load();
+    if is_remote(&options.target) {
+        check_remote(&options.target, &config.allowed_remotes)?;
+        return sync_remote(project, options).await;
+    }
+
+    let target = local_target(project, &options.target, &config.allowed_roots, &protected_dirs()?)?;
+    let project = project.to_path_buf();
+    let options = options.clone();
+    tokio::task::spawn_blocking(move || {
+        let key = (project.clone(), target.clone());
+        let mut baseline = BASELINES
+            .lock()
+            .map_err(|_| anyhow!("Sync baseline mutex poisoned"))?
+            .remove(&key)
+            .unwrap_or_default();
+        let result = sync_local(&project, &target, &options, &mut baseline);
+        if let Ok(mut baselines) = BASELINES.lock() {
+            baselines.insert(key, baseline);
+        }
+        result
+    })
+    .await
+    .context("Sync task panicked")?
+}
+
+/// Starts a continuous sync that runs a pass every `interval` until stopped.
+///
+/// The first pass runs before returning so configuration errors surface immediately.
+pub async fn start_session(project: PathBuf, options: SyncOptions, interval: Duration) -> Result<SyncSessionInfo> {
+    let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    start_session_with_id(id, project, options, interval).await
+}
+
+async fn start_session_with_id(id: String, project: PathBuf, options: SyncOptions, interval: Duration) -> Result<SyncSessionInfo> {
+    let first = sync_once(&project, &options).await?;
+    let info = SyncSessionInfo {
+        id: id.clone(),
+        options: options.clone(),
+        interval,
+        started_at: now_secs(),
+        passes: 1,
+        last_report: Some(first),
+        last_error: None,
+    };
+
+    persist_session(&id, &project, &options, interval, "active");
+    let session_id = id.clone();
+    let handle = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await; // The first tick completes immediately; the first pass already ran
+        loop {
+            ticker.tick().await;
+            let result = sync_once(&project, &options).await;
+            let Ok(mut sessions) = SESSIONS.lock() else { break };
+            let Some(session) = sessions.get_mut(&session_id) else { break };
+            session.info.passes += 1;
+            match result {
+                Ok(report) => {
+                    let changed = report.copied_to_target.len() + report.copied_to_project.len() + report.deleted.len();
+                    if changed > 0 || !report.conflicts.is_empty() {
+                        tracing::info!(target: "dev_operation::sync", session = %session_id, changed, conflicts = report.conflicts.len(), "Sync pass applied changes.");
+                    }
+                    session.info.last_report = Some(report);
+                    session.info.last_error = None;
+                }
+                Err(e) => {
+                    tracing::warn!(target: "dev_operation::sync", session = %session_id, error = ?e, "Sync pass failed.");
+                    session.info.last_error = Some(format!("{:#}", e));
+                }
+            }
+        }
+    });
+
+    SESSIONS
+        .lock()
+        .map_err(|_| anyhow!("Sync session mutex poisoned"))?
+        .insert(id, SyncSession { info: info.clone(), handle });
+    Ok(info)
+}
+
+/// Lists running continuous syncs.
+pub fn list_sessions() -> Vec<SyncSessionInfo> {
+    let mut sessions: Vec<SyncSessionInfo> = SESSIONS
+        .lock()
+        .map(|s| s.values().map(|session| session.info.clone()).collect())
+        .unwrap_or_default();
+    sessions.sort_by_key(|s| s.started_at);
+    sessions
+}
+
+/// Stops a continuous sync. Returns its final state, or `None` if no such session exists.
+pub fn stop_session(id: &str) -> Option<SyncSessionInfo> {
+    let session = SESSIONS.lock().ok()?.remove(id)?;
+    session.handle.abort();
+    set_persisted_status(id, "stopped");
+    tracing::info!(target: "dev_operation::sync", session = %id, "Continuous sync stopped.");
+    Some(session.info)
+}
+
+// --- Persistence across restarts ---
+
+// A continuous sync as stored in the metadata store, so it can be resumed after a restart
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct PersistedSync {
+    project: PathBuf,
+    target: String,
+    direction: String,
+    conflict_policy: String,
+    excludes: Vec<String>,
+    delete: bool,
+    interval_ms: u64,
+}
+
+impl PersistedSync {
+    fn new(project: &Path, options: &SyncOptions, interval: Duration) -> Self {
+        PersistedSync {
+            project: project.to_path_buf(),
+            target: options.target.clone(),
+            direction: options.direction.as_str().to_string(),
+            conflict_policy: options.conflict_policy.as_str().to_string(),
+            excludes: options.excludes.clone(),
+            delete: options.delete,
+            interval_ms: interval.as_millis() as u64,
+        }
+    }
+
+    fn options(&self) -> Option<SyncOptions> {
+        Some(SyncOptions {
+            target: self.target.clone(),
+            direction: SyncDirection::from_name(&self.direction)?,
+            conflict_policy: ConflictPolicy::from_name(&self.conflict_policy)?,
+            excludes: self.excludes.clone(),
+            delete: self.delete,
+        })
+    }
+}
+
+fn persist_session(id: &str, project: &Path, options: &SyncOptions, interval: Duration, status: &str) {
+    let spec = match serde_json::to_string(&PersistedSync::new(project, options, interval)) {
+        Ok(spec) => spec,
+        Err(e) => {
+            tracing::warn!(target: "dev_operation::sync", session = %id, error = ?e, "Failed to persist sync session; it will not survive a restart.");
+            return;
+        }
+    };
+    let (id, status) = (id.to_string(), status.to_string());
+    db::write_later(move |db| {
+        if let Err(e) = db.save_sync_session(events::session_id(), &id, &spec, &status) {
+            tracing::warn!(target: "dev_operation::sync", session = %id, error = ?e, "Failed to persist sync session; it will not survive a restart.");
+        }
+    });
+}
+
+fn set_persisted_status(id: &str, status: &str) {
+    let (id, status) = (id.to_string(), status.to_string());
+    db::write_later(move |db| {
+        if let Err(e) = db.set_sync_session_status(&id, &status) {
+            tracing::debug!(target: "dev_operation::sync", session = %id, error = ?e, "Failed to update persisted sync session.");
+        }
+    });
+}
+
+/// Continuous syncs an earlier Galatea process left running, and what happened to them.
+#[derive(Debug, Clone, Default)]
+pub struct SyncRecovery {
+    /// Restarted under their previous ids
+    pub resumed: Vec<String>,
+    /// Closed, because resuming was disabled or failed
+    pub closed: Vec<String>,
+}
+
+/// Resumes the continuous syncs a crashed Galatea process left active, under their previous
+/// ids, or closes them when `resume` is false.
+pub async fn recover_sessions(resume: bool) -> SyncRecovery {
+    let mut recovery = SyncRecovery::default();
+    let stale = match db::with_db(|db| db.stale_sync_sessions(events::session_id())) {
+        Ok(stale) => stale,
+        Err(e) => {
+            tracing::warn!(target: "dev_operation::sync", error = ?e, "Failed to read persisted sync sessions.");
+            return recovery;
+        }
+    };
+    for stored in stale {
+        let spec: Option<PersistedSync> = serde_json::from_str(&stored.spec).ok();
+        let resumable = spec.as_ref().and_then(|spec| spec.options().map(|options| (spec, options)));
+        let resumed = match resumable {
+            Some((spec, options)) if resume => {
+                let interval = Duration::from_millis(spec.interval_ms.max(1));
+                match start_session_with_id(stored.id.clone(), spec.project.clone(), options, interval).await {
+                    Ok(_) => true,
+                    Err(e) => {
+                        tracing::warn!(target: "dev_operation::sync", session = %stored.id, error = ?e, "Failed to resume continuous sync.");
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
+        if resumed {
+            tracing::info!(target: "dev_operation::sync", session = %stored.id, "Resumed continuous sync after restart.");
+            recovery.resumed.push(stored.id);
+        } else {
+            set_persisted_status(&stored.id, "interrupted");
+            recovery.closed.push(stored.id);
+        }
+    }
+    recovery
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(target: &Path, direction: SyncDirection, conflict_policy: ConflictPolicy) -> SyncOptions {
+        SyncOptions {
+            target: target.display().to_string(),
+            direction,
+            conflict_policy,
+            excludes: vec!["*.log".to_string(), "tmp/cache".to_string()],
+            delete: true,
+        }
+    }
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let excludes: Vec<String> = ["node_modules", "*.log", "src/generated", "docs/**/*.png"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(is_excluded("node_modules/react/index.js", &excludes));
+        assert!(is_excluded("app/debug.log", &excludes));
+        assert!(is_excluded("src/generated/types.ts", &excludes));
+        assert!(is_excluded("docs/img/a/b.png", &excludes));
+        assert!(!is_excluded("src/app/page.tsx", &excludes));
+        assert!(!is_excluded("docs/readme.md", &excludes));
+
+        assert!(is_remote("user@host:/srv/app"));
+        assert!(!is_remote("/tmp/mirror"));
+        assert!(!is_remote("C:/mirror"));
+    }
+
+    #[test]
+    fn test_local_targets_stay_inside_allowed_roots() {
+        let root = tempfile::tempdir().unwrap();
+        let canonical = dunce::canonicalize(root.path()).unwrap();
+        let project = root.path().join("app");
+        let install = root.path().join("install");
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(install.join("galatea_files")).unwrap();
+        let protected = vec![canonical.join("install/galatea_files"), canonical.join("install")];
+        let allowed = vec![root.path().display().to_string()];
+        let target = |rel: &str| root.path().join(rel).display().to_string();
+
+        let mirror = local_target(&project, &target("mirror"), &allowed, &protected).unwrap();
+        assert_eq!(mirror, canonical.join("mirror"));
+        assert!(mirror.is_dir());
+        assert!(local_target(&project, &target("mirror"), &[], &protected).is_err());
+        assert!(local_target(&project, "mirror", &allowed, &protected).is_err());
+        assert!(local_target(&project, &target("app/nested"), &allowed, &protected).is_err());
+        assert!(local_target(&project, &target("mirror/../escape"), &allowed, &protected).is_err());
+        assert!(local_target(&project, "/etc/galatea-sync", &allowed, &protected).is_err());
+        // Galatea's own directories, whether the target is them, inside them or around them
+        assert!(local_target(&project, &target("install/galatea_files"), &allowed, &protected).is_err());
+        assert!(local_target(&project, &target("install/galatea_files/audit"), &allowed, &protected).is_err());
+        assert!(local_target(&project, &target("install"), &allowed, &protected).is_err());
+        assert!(local_target(&project, &target(""), &allowed, &protected).is_err());
+
+        let elsewhere = tempfile::tempdir().unwrap();
+        let allowed = vec![elsewhere.path().display().to_string()];
+        assert!(local_target(&project, &elsewhere.path().join("copy").display().to_string(), &allowed, &protected).is_ok());
+        assert!(local_target(&project, &target("mirror"), &allowed, &protected).is_err());
+    }
+
+    #[test]
+    fn test_remote_targets_need_an_allowed_host() {
+        assert!(check_remote("deploy@build-host:/srv/app", &[]).is_err());
+        let allowed = vec!["build-host".to_string(), "ci@mirror".to_string()];
+        assert!(check_remote("deploy@build-host:/srv/app", &allowed).is_ok());
+        assert!(check_remote("build-host:/srv/app", &allowed).is_ok());
+        assert!(check_remote("ci@mirror:/srv/app", &allowed).is_ok());
+        assert!(check_remote("root@mirror:/srv/app", &allowed).is_err());
+        assert!(check_remote("evil.example:/tmp", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_local_push_then_bidirectional_conflict() {
+        let project = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        write(project.path(), "src/a.ts", "a");
+        write(project.path(), "src/b.ts", "b");
+        write(project.path(), "debug.log", "noise");
+        write(project.path(), "tmp/cache/x", "x");
+        write(target.path(), "stale.ts", "old");
+
+        let mut baseline = Baseline::new();
+        let push = options(target.path(), SyncDirection::Push, ConflictPolicy::Skip);
+        let report = sync_local(project.path(), target.path(), &push, &mut baseline).unwrap();
+        assert_eq!(report.copied_to_target, vec!["src/a.ts", "src/b.ts"]);
+        assert_eq!(report.deleted, vec!["stale.ts"]);
+        assert!(!target.path().join("debug.log").exists());
+        assert!(!target.path().join("tmp/cache/x").exists());
+
+        // a.ts edited on both sides, b.ts only on the target
+        write(project.path(), "src/a.ts", "project edit");
+        write(target.path(), "src/a.ts", "target edit!");
+        write(target.path(), "src/b.ts", "target b");
+        let both = options(target.path(), SyncDirection::Both, ConflictPolicy::Skip);
+        let report = sync_local(project.path(), target.path(), &both, &mut baseline).unwrap();
+        assert_eq!(report.copied_to_project, vec!["src/b.ts"]);
+        assert_eq!(
+            report.conflicts,
+            vec![SyncConflict { path: "src/a.ts".to_string(), resolution: "skipped".to_string() }]
+        );
+        assert_eq!(fs::read_to_string(project.path().join("src/b.ts")).unwrap(), "target b");
+        assert_eq!(fs::read_to_string(project.path().join("src/a.ts")).unwrap(), "project edit");
+
+        let keep_project = options(target.path(), SyncDirection::Both, ConflictPolicy::Project);
+        let report = sync_local(project.path(), target.path(), &keep_project, &mut baseline).unwrap();
+        assert_eq!(report.copied_to_target, vec!["src/a.ts"]);
+        assert_eq!(fs::read_to_string(target.path().join("src/a.ts")).unwrap(), "project edit");
+    }
+}
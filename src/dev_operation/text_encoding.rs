@@ -0,0 +1,118 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// How the editor turns a file's bytes into the text it views and edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1: every byte is one character, so any file can be read
+    Latin1,
+    /// The whole file as standard base64, for binary files
+    Base64,
+}
+
+impl TextEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Latin1 => "latin1",
+            TextEncoding::Base64 => "base64",
+        }
+    }
+}
+
+/// Byte-level conventions of a file that its decoded text doesn't show, so that writing the
+/// edited text back only changes what was edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextFormat {
+    pub encoding: TextEncoding,
+    /// Starts with a UTF-8 byte order mark
+    pub bom: bool,
+    /// Every line ends in `\r\n`; the text has `\n` instead
+    pub crlf: bool,
+}
+
+impl TextFormat {
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self { encoding, ..Self::default() }
+    }
+
+    /// Decodes `bytes` into text and the format to write it back with. A UTF-8 BOM is
+    /// stripped, and CRLF line endings become `\n` when the file uses them throughout; files
+    /// mixing line endings are left as they are.
+    pub fn decode(bytes: &[u8], encoding: TextEncoding) -> Result<(String, TextFormat), String> {
+        let mut format = TextFormat::new(encoding);
+        let text = match encoding {
+            TextEncoding::Base64 => return Ok((STANDARD.encode(bytes), format)),
+            TextEncoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+            TextEncoding::Utf8 => {
+                let bytes = match bytes.strip_prefix(UTF8_BOM) {
+                    Some(rest) => {
+                        format.bom = true;
+                        rest
+                    }
+                    None => bytes,
+                };
+                String::from_utf8(bytes.to_vec()).map_err(|e| {
+                    format!("not valid UTF-8 ({}); use the 'latin1' or 'base64' encoding", e)
+                })?
+            }
+        };
+        let line_feeds = text.matches('\n').count();
+        if line_feeds > 0 && text.matches("\r\n").count() == line_feeds {
+            format.crlf = true;
+            return Ok((text.replace("\r\n", "\n"), format));
+        }
+        Ok((text, format))
+    }
+
+    /// Encodes `text` with this format's encoding, line endings and BOM.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        if self.encoding == TextEncoding::Base64 {
+            let compact: String = text.split_whitespace().collect();
+            return STANDARD.decode(compact).map_err(|e| format!("content is not valid base64: {}", e));
+        }
+        let text = if self.crlf { text.replace("\r\n", "\n").replace('\n', "\r\n") } else { text.to_string() };
+        let mut bytes = if self.bom { UTF8_BOM.to_vec() } else { Vec::new() };
+        match self.encoding {
+            TextEncoding::Latin1 => {
+                for c in text.chars() {
+                    let byte = u8::try_from(u32::from(c))
+                        .map_err(|_| format!("'{}' cannot be written in latin1", c))?;
+                    bytes.push(byte);
+                }
+            }
+            _ => bytes.extend_from_slice(text.as_bytes()),
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_keep_bom_line_endings_and_bytes() {
+        let windows = b"\xEF\xBB\xBFone\r\ntwo\r\n";
+        let (text, format) = TextFormat::decode(windows, TextEncoding::Utf8).unwrap();
+        assert_eq!((text.as_str(), format.bom, format.crlf), ("one\ntwo\n", true, true));
+        assert_eq!(format.encode("one\n2\nthree\n").unwrap(), b"\xEF\xBB\xBFone\r\n2\r\nthree\r\n");
+
+        let (mixed, format) = TextFormat::decode(b"a\r\nb\n", TextEncoding::Utf8).unwrap();
+        assert_eq!((mixed.as_str(), format.crlf), ("a\r\nb\n", false));
+
+        assert!(TextFormat::decode(b"caf\xE9", TextEncoding::Utf8).is_err());
+        let (latin, format) = TextFormat::decode(b"caf\xE9", TextEncoding::Latin1).unwrap();
+        assert_eq!(latin, "café");
+        assert_eq!(format.encode("déjà").unwrap(), b"d\xE9j\xE0");
+        assert!(format.encode("€").is_err());
+
+        let binary = [0u8, 159, 146, 150, 13, 10];
+        let (encoded, format) = TextFormat::decode(&binary, TextEncoding::Base64).unwrap();
+        assert_eq!(format.encode(&encoded).unwrap(), binary);
+    }
+}
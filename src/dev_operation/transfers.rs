@@ -0,0 +1,92 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::dev_setup::config_files;
+
+// config.toml table with the upload and download limits
+const CONFIG_SECTION: &str = "editor_transfers";
+
+/// Size limits of file uploads and downloads, from `[editor_transfers]` in config.toml.
+///
+/// ```toml
+/// [editor_transfers]
+/// max_upload_bytes = 26214400     # all files of one upload together
+/// max_download_bytes = 104857600
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TransferConfig {
+    pub max_upload_bytes: u64,
+    pub max_download_bytes: u64,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self { max_upload_bytes: 25 * 1024 * 1024, max_download_bytes: 100 * 1024 * 1024 }
+    }
+}
+
+impl TransferConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+/// The media type a file is served with, from its extension.
+pub fn content_type(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "wasm" => "application/wasm",
+        "json" | "map" => "application/json",
+        "js" | "mjs" | "cjs" => "text/javascript",
+        "css" => "text/css",
+        "html" | "htm" => "text/html",
+        "xml" => "application/xml",
+        "csv" => "text/csv",
+        "txt" | "md" | "ts" | "tsx" | "jsx" | "mts" | "cts" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The file name an uploaded file is saved as: the last component of the name the client sent,
+/// or `None` when nothing usable is left (`..`, an empty name).
+pub fn upload_file_name(sent: &str) -> Option<String> {
+    let name = sent.rsplit(['/', '\\']).next()?.trim();
+    (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_types_and_upload_names() {
+        assert_eq!(content_type(Path::new("public/logo.PNG")), "image/png");
+        assert_eq!(content_type(Path::new("public/fonts/inter.woff2")), "font/woff2");
+        assert_eq!(content_type(Path::new("LICENSE")), "application/octet-stream");
+
+        assert_eq!(upload_file_name("C:\\Users\\me\\hero image.jpg").as_deref(), Some("hero image.jpg"));
+        assert_eq!(upload_file_name("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(upload_file_name("images/.."), None);
+        assert_eq!(upload_file_name(""), None);
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::dev_runtime::util::{self, now_secs};
+use crate::terminal::package_manager::PackageManager;
+
+// Files whose changes can change what tsc reports
+const INPUT_EXTENSIONS: &[&str] = &["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs", "json"];
+const EXCLUDE_DIRS: &[&str] = &["node_modules", ".next", ".git", "dist", "build", "out", "coverage"];
+// Non-diagnostic output kept with a run (config errors, crash traces); the tail is kept
+const MAX_OTHER_OUTPUT_BYTES: usize = 16 * 1024;
+
+/// One diagnostic printed by `tsc`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub path: String,     // As printed, relative to the project root
+    pub line: usize,      // 1-indexed
+    pub column: usize,    // 1-indexed, as printed
+    pub code: String,     // e.g. `TS2322`
+    pub category: String, // `error`, `warning` or `message`
+    pub message: String,  // With the indented elaboration lines that follow it, if any
+}
+
+/// The result of one `tsc --noEmit` run.
+#[derive(Debug, Clone)]
+pub struct TypecheckRun {
+    pub errors: Vec<TypeError>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub finished_at: u64, // Unix seconds
+    pub other_output: String,
+}
+
+impl TypecheckRun {
+    pub fn passed(&self) -> bool {
+        self.exit_code == Some(0) && !self.errors.iter().any(|e| e.category == "error")
+    }
+}
+
+struct CachedRun {
+    fingerprint: u64,
+    run: Arc<TypecheckRun>,
+}
+
+static CACHE: Lazy<Mutex<HashMap<PathBuf, CachedRun>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// One tsc at a time; a caller arriving during a run waits for it and gets its cached result
+static RUNNING: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+// Paths, sizes and modification times of the files tsc reads; any edit, creation, deletion or
+// rename changes it
+fn inputs_fingerprint(project_dir: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let walker = walkdir::WalkDir::new(project_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !(e.file_type().is_dir() && EXCLUDE_DIRS.contains(&e.file_name().to_string_lossy().as_ref())));
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let is_input = entry.path().extension().and_then(|e| e.to_str()).is_some_and(|e| INPUT_EXTENSIONS.contains(&e));
+        if !is_input {
+            continue;
+        }
+        entry.path().hash(&mut hasher);
+        if let Ok(metadata) = entry.metadata() {
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Parses `tsc --pretty false` output: lines like
+/// `src/app/page.tsx(12,5): error TS2322: Type 'string' is not assignable ...`, each optionally
+/// followed by indented lines elaborating on it. Returns the diagnostics and the lines that
+/// aren't part of one (other than the closing `Found N errors` summary).
+pub fn parse_output(output: &str) -> (Vec<TypeError>, Vec<String>) {
+    let mut errors: Vec<TypeError> = Vec::new();
+    let mut other = Vec::new();
+    for line in output.lines() {
+        if line.starts_with(' ') {
+            if let Some(last) = errors.last_mut() {
+                last.message.push('\n');
+                last.message.push_str(line.trim());
+                continue;
+            }
+        }
+        match parse_line(line) {
+            Some(error) => errors.push(error),
+            None if line.trim().is_empty() || line.starts_with("Found ") => {}
+            None => other.push(line.to_string()),
+        }
+    }
+    (errors, other)
+}
+
+fn parse_line(line: &str) -> Option<TypeError> {
+    let (location, rest) = line.split_once("): ")?;
+    let (file, position) = location.rsplit_once('(')?;
+    let (line_no, column) = position.split_once(',')?;
+    let (category_and_code, message) = rest.split_once(": ")?;
+    let (category, code) = category_and_code.split_once(' ')?;
+    if !code.starts_with("TS") {
+        return None;
+    }
+    Some(TypeError {
+        path: file.to_string(),
+        line: line_no.trim().parse().ok()?,
+        column: column.trim().parse().ok()?,
+        code: code.to_string(),
+        category: category.to_string(),
+        message: message.trim().to_string(),
+    })
+}
+
+/// Type-checks the project with `tsc --noEmit`.
+///
+/// Results are cached per project and reused until a TypeScript, JavaScript or JSON file
+/// outside `node_modules` and build output changes; `force` runs tsc regardless. Returns the
+/// run and whether it came from the cache.
+pub async fn typecheck(project_dir: &Path, force: bool) -> Result<(Arc<TypecheckRun>, bool)> {
+    if !project_dir.join("tsconfig.json").exists() {
+        bail!("No tsconfig.json in {}", project_dir.display());
+    }
+    let _running = RUNNING.lock().await;
+    let dir = project_dir.to_path_buf();
+    let fingerprint = tokio::task::spawn_blocking(move || inputs_fingerprint(&dir)).await?;
+    if !force {
+        let cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache.get(project_dir).filter(|c| c.fingerprint == fingerprint) {
+            return Ok((cached.run.clone(), true));
+        }
+    }
+
+    let started = Instant::now();
+    let output = PackageManager::detect(project_dir)
+        .exec(project_dir, "tsc")
+        .args(["--noEmit", "--pretty", "false"])
+        .output()
+        .await
+        .context("Failed to run tsc")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (errors, other) = parse_output(&stdout);
+    if !output.status.success() && errors.is_empty() && other.is_empty() {
+        bail!("tsc failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let run = Arc::new(TypecheckRun {
+        errors,
+        exit_code: output.status.code(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        finished_at: now_secs(),
+        other_output: util::truncate_head(&other.join("\n"), MAX_OTHER_OUTPUT_BYTES),
+    });
+    tracing::info!(target: "dev_operation::typecheck", errors = run.errors.len(), duration_ms = run.duration_ms, "Typecheck finished.");
+    CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(project_dir.to_path_buf(), CachedRun { fingerprint, run: run.clone() });
+    Ok((run, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_output_and_fingerprint() {
+        let output = "src/app/page.tsx(12,5): error TS2322: Type 'string' is not assignable to type 'number'.\n\
+                      src/lib/api.ts(3,1): error TS2345: Argument of type '{ id: string; }' is not assignable to parameter of type 'User'.\n  \
+                      Property 'name' is missing in type '{ id: string; }' but required in type 'User'.\n\
+                      error TS5023: Unknown compiler option 'strictest'.\n\
+                      \n\
+                      Found 3 errors in 2 files.\n";
+        let (errors, other) = parse_output(output);
+        assert_eq!(errors.len(), 2);
+        assert_eq!((errors[0].path.as_str(), errors[0].line, errors[0].column, errors[0].code.as_str()), ("src/app/page.tsx", 12, 5, "TS2322"));
+        assert!(errors[1].message.ends_with("\nProperty 'name' is missing in type '{ id: string; }' but required in type 'User'."));
+        assert_eq!(other, vec!["error TS5023: Unknown compiler option 'strictest'."]);
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        fs::write(dir.path().join("page.tsx"), "export default 1;\n").unwrap();
+        let before = inputs_fingerprint(dir.path());
+        fs::write(dir.path().join("node_modules/pkg/index.d.ts"), "export {};\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# Shop\n").unwrap();
+        assert_eq!(inputs_fingerprint(dir.path()), before);
+        fs::rename(dir.path().join("page.tsx"), dir.path().join("home.tsx")).unwrap();
+        assert_ne!(inputs_fingerprint(dir.path()), before);
+    }
+}
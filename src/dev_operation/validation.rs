@@ -0,0 +1,423 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::process::Command;
+
+use super::health;
+use crate::dev_runtime::util::{self, now_secs};
+use crate::dev_runtime::{crash, db, events};
+use crate::file_system::paths;
+use crate::terminal::git;
+use crate::terminal::package_manager::PackageManager;
+
+// Step output beyond this is cut from the stored run (the tail is kept, that's where failures are)
+const MAX_STEP_OUTPUT_BYTES: usize = 64 * 1024;
+// Diffs beyond this are cut from the stored run
+const MAX_DIFF_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepKind {
+    Typecheck,
+    Lint,
+    Test,
+    Build,
+}
+
+impl StepKind {
+    pub const ALL: [StepKind; 4] = [StepKind::Typecheck, StepKind::Lint, StepKind::Test, StepKind::Build];
+    // Build is opt-in: it is slow and writes the same `.next` directory the dev server uses
+    pub const DEFAULT: [StepKind; 3] = [StepKind::Typecheck, StepKind::Lint, StepKind::Test];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepKind::Typecheck => "typecheck",
+            StepKind::Lint => "lint",
+            StepKind::Test => "test",
+            StepKind::Build => "build",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl StepStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepStatus::Passed => "passed",
+            StepStatus::Failed => "failed",
+            StepStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Result of one pipeline step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationStep {
+    pub kind: StepKind,
+    pub status: StepStatus,
+    pub command: Option<String>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub output: String, // Combined stdout/stderr, or why the step was skipped
+}
+
+/// A validation pipeline run as written to `galatea_files/validation_runs/<id>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationRun {
+    pub id: String,
+    pub started_at: u64, // Unix seconds
+    pub finished_at: u64,
+    pub passed: bool, // No step failed
+    pub steps: Vec<ValidationStep>,
+    pub diff_stat: String,
+    pub diff: String, // `git diff HEAD` at the time of the run
+}
+
+pub fn validation_runs_dir() -> Result<PathBuf> {
+    Ok(paths::galatea_files_dir()?.join("validation_runs"))
+}
+
+fn package_script<'a>(package_json: &'a serde_json::Value, name: &str) -> Option<&'a str> {
+    package_json.get("scripts")?.get(name)?.as_str()
+}
+
+// The command for a step, or why the project has nothing to run for it
+fn step_command(project_dir: &Path, package_manager: PackageManager, kind: StepKind) -> Result<Vec<String>, String> {
+    let package_json: Option<serde_json::Value> = fs::read_to_string(project_dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let has_script = |name: &str| package_json.as_ref().and_then(|p| package_script(p, name)).is_some();
+    match kind {
+        StepKind::Typecheck if project_dir.join("tsconfig.json").exists() => {
+            Ok(package_manager.exec_command("tsc", &["--noEmit", "--pretty", "false"]))
+        }
+        StepKind::Typecheck => Err("No tsconfig.json in the project".to_string()),
+        StepKind::Lint if has_script("lint") => Ok(package_manager.script_command("lint", &[])),
+        StepKind::Test if has_script("test") => Ok(package_manager.script_command("test", &[])),
+        StepKind::Build if has_script("build") => Ok(package_manager.script_command("build", &[])),
+        StepKind::Lint | StepKind::Test | StepKind::Build => Err(format!("No `{}` script in package.json", kind.as_str())),
+    }
+}
+
+#[tracing::instrument(name = "process.run", skip_all, fields(galatea.validation_step = kind.as_str(), process.command = tracing::field::Empty, process.exit_code = tracing::field::Empty))]
+async fn run_step(project_dir: &Path, kind: StepKind) -> ValidationStep {
+    let command = match step_command(project_dir, PackageManager::detect(project_dir), kind) {
+        Ok(command) => command,
+        Err(reason) => {
+            return ValidationStep {
+                kind,
+                status: StepStatus::Skipped,
+                command: None,
+                exit_code: None,
+                duration_ms: 0,
+                output: reason,
+            }
+        }
+    };
+
+    let span = tracing::Span::current();
+    span.record("process.command", command.join(" "));
+    let started = Instant::now();
+    let result = Command::new(&command[0])
+        .current_dir(project_dir)
+        .args(&command[1..])
+        // Test runners default to watch mode outside CI
+        .env("CI", "true")
+        .output()
+        .await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let (status, exit_code, output) = match result {
+        Ok(output) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            let status = if output.status.success() { StepStatus::Passed } else { StepStatus::Failed };
+            (status, output.status.code(), text)
+        }
+        Err(e) => (StepStatus::Failed, None, format!("Failed to run {}: {}", command.join(" "), e)),
+    };
+    if let Some(code) = exit_code {
+        span.record("process.exit_code", code);
+    }
+    ValidationStep {
+        kind,
+        status,
+        command: Some(command.join(" ")),
+        exit_code,
+        duration_ms,
+        output: util::truncate_head(&output, MAX_STEP_OUTPUT_BYTES),
+    }
+}
+
+/// Runs the given pipeline steps against the project, records the working tree diff, and
+/// saves the run under `galatea_files/validation_runs`.
+///
+/// Steps the project has no tooling for (no tsconfig.json, no `lint`/`test` script) are skipped.
+pub async fn run_validation(project_dir: &Path, steps: &[StepKind]) -> Result<ValidationRun> {
+    let _operation = crash::track_operation("validation pipeline");
+    let started_at = now_secs();
+    let id = format!("{}-{}", started_at, &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    record_job(&id, "running", None);
+
+    let mut results = Vec::new();
+    for kind in steps {
+        let step = run_step(project_dir, *kind).await;
+        tracing::info!(target: "dev_operation::validation", step = kind.as_str(), status = step.status.as_str(), duration_ms = step.duration_ms, "Validation step finished.");
+        results.push(step);
+    }
+
+    // Not being a git repository shouldn't fail the run; the report just has no diff
+    let diff_stat = git::git_output(project_dir, &["diff", "HEAD", "--stat"]).await.unwrap_or_default();
+    let diff = git::git_output(project_dir, &["diff", "HEAD"]).await.unwrap_or_default();
+
+    let run = ValidationRun {
+        id,
+        started_at,
+        finished_at: now_secs(),
+        passed: results.iter().all(|s| s.status != StepStatus::Failed),
+        steps: results,
+        diff_stat,
+        diff: util::truncate_head(&diff, MAX_DIFF_BYTES),
+    };
+    save_run(&run)?;
+    if let Err(e) = health::record_run(project_dir, &run) {
+        tracing::warn!(target: "dev_operation::validation", run_id = %run.id, error = ?e, "Failed to record the project health score.");
+    }
+
+    let status = if run.passed { "passed" } else { "failed" };
+    let summary: Vec<String> = run
+        .steps
+        .iter()
+        .map(|s| format!("{}: {}", s.kind.as_str(), s.status.as_str()))
+        .collect();
+    record_job(&run.id, status, Some(&summary.join(", ")));
+    let duration = run.finished_at.saturating_sub(run.started_at) as f64;
+    db::write_later(move |db| {
+        if let Err(e) = db.record_analytics(events::session_id(), "validation_run", Some(duration), Some(status)) {
+            tracing::debug!(target: "dev_operation::validation", error = ?e, "Failed to record validation analytics.");
+        }
+    });
+    Ok(run)
+}
+
+// Mirrors the run's progress into the metadata store's job table
+fn record_job(id: &str, status: &str, detail: Option<&str>) {
+    let (id, status, detail) = (id.to_string(), status.to_string(), detail.map(str::to_string));
+    db::write_later(move |db| {
+        if let Err(e) = db.upsert_job(events::session_id(), &id, "validation", &status, detail.as_deref()) {
+            tracing::debug!(target: "dev_operation::validation", error = ?e, "Failed to record validation job.");
+        }
+    });
+}
+
+fn save_run(run: &ValidationRun) -> Result<PathBuf> {
+    let dir = validation_runs_dir()?;
+    fs::create_dir_all(&dir).context("Failed to create validation runs directory")?;
+    let path = dir.join(format!("{}.json", run.id));
+    let json = serde_json::to_string_pretty(run).context("Failed to serialize validation run")?;
+    fs::write(&path, json).context(format!("Failed to write validation run {}", path.display()))?;
+    Ok(path)
+}
+
+/// Lists recorded runs, newest first. Unreadable files are skipped.
+pub fn list_runs() -> Result<Vec<ValidationRun>> {
+    let dir = validation_runs_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut runs: Vec<ValidationRun> = fs::read_dir(&dir)
+        .context(format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            let content = fs::read_to_string(entry.path()).ok()?;
+            serde_json::from_str(&content).ok()
+        })
+        .collect();
+    runs.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| b.id.cmp(&a.id)));
+    Ok(runs)
+}
+
+/// Loads a run by id; `latest` resolves to the most recent run.
+pub fn get_run(id: &str) -> Result<Option<ValidationRun>> {
+    if id == "latest" {
+        return Ok(list_runs()?.into_iter().next());
+    }
+    paths::validate_id(id, "validation run")?;
+    let path = validation_runs_dir()?.join(format!("{}.json", id));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+    let run = serde_json::from_str(&content).context("Failed to parse validation run")?;
+    Ok(Some(run))
+}
+
+// --- HTML report ---
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn format_timestamp(secs: u64) -> String {
+    chrono::DateTime::from_timestamp(secs as i64, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| secs.to_string())
+}
+
+fn render_diff(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let class = if line.starts_with("+++") || line.starts_with("---") || line.starts_with("diff ") {
+                "meta"
+            } else if line.starts_with('+') {
+                "add"
+            } else if line.starts_with('-') {
+                "del"
+            } else if line.starts_with("@@") {
+                "hunk"
+            } else {
+                "ctx"
+            };
+            format!("<span class=\"{}\">{}</span>", class, escape_html(line))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+const REPORT_STYLE: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; margin: 2rem auto; max-width: 1100px; color: #1f2328; }
+h1 { margin-bottom: 0.25rem; }
+.meta-line { color: #656d76; margin-top: 0; }
+.badge { display: inline-block; padding: 0.1rem 0.6rem; border-radius: 1rem; font-size: 0.85rem; font-weight: 600; color: #fff; }
+.passed { background: #1a7f37; } .failed { background: #cf222e; } .skipped { background: #8c959f; }
+table { border-collapse: collapse; width: 100%; margin: 1rem 0; }
+th, td { text-align: left; padding: 0.4rem 0.6rem; border-bottom: 1px solid #d0d7de; }
+details { margin: 0.5rem 0; } summary { cursor: pointer; font-weight: 600; }
+pre { background: #f6f8fa; padding: 0.75rem; overflow-x: auto; font-size: 0.8rem; line-height: 1.4; }
+.add { color: #1a7f37; } .del { color: #cf222e; } .hunk { color: #8250df; } .meta { color: #656d76; font-weight: 600; }
+"#;
+
+/// Renders a run as a standalone HTML page (inline styles, no external assets).
+pub fn render_html(run: &ValidationRun) -> String {
+    let overall = if run.passed { StepStatus::Passed } else { StepStatus::Failed };
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Validation report {id}</title>\n<style>{style}</style>\n</head>\n<body>\n\
+         <h1>Validation report <span class=\"badge {status}\">{status}</span></h1>\n\
+         <p class=\"meta-line\">Run <code>{id}</code> &middot; started {started} &middot; took {secs}s</p>\n",
+        id = escape_html(&run.id),
+        style = REPORT_STYLE,
+        status = overall.as_str(),
+        started = format_timestamp(run.started_at),
+        secs = run.finished_at.saturating_sub(run.started_at),
+    );
+
+    html.push_str("<h2>Steps</h2>\n<table>\n<tr><th>Step</th><th>Status</th><th>Command</th><th>Exit code</th><th>Duration</th></tr>\n");
+    for step in &run.steps {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td><span class=\"badge {}\">{}</span></td><td><code>{}</code></td><td>{}</td><td>{:.1}s</td></tr>",
+            step.kind.as_str(),
+            step.status.as_str(),
+            step.status.as_str(),
+            escape_html(step.command.as_deref().unwrap_or("-")),
+            step.exit_code.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string()),
+            step.duration_ms as f64 / 1000.0,
+        );
+    }
+    html.push_str("</table>\n");
+
+    for step in &run.steps {
+        // Failed steps are expanded so the reader sees the problem first
+        let _ = writeln!(
+            html,
+            "<details{}><summary>{} output</summary>\n<pre>{}</pre>\n</details>",
+            if step.status == StepStatus::Failed { " open" } else { "" },
+            step.kind.as_str(),
+            escape_html(step.output.trim_end()),
+        );
+    }
+
+    html.push_str("<h2>Changes</h2>\n");
+    if run.diff.trim().is_empty() {
+        html.push_str("<p>No uncommitted changes.</p>\n");
+    } else {
+        let _ = writeln!(html, "<pre>{}</pre>", escape_html(run.diff_stat.trim_end()));
+        let _ = writeln!(html, "<details open><summary>Diff</summary>\n<pre>{}</pre>\n</details>", render_diff(&run.diff));
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_command_skips_missing_tooling() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(step_command(dir.path(), PackageManager::Pnpm, StepKind::Typecheck).is_err());
+        assert!(step_command(dir.path(), PackageManager::Pnpm, StepKind::Lint).is_err());
+
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(dir.path().join("package.json"), r#"{"scripts": {"lint": "next lint"}}"#).unwrap();
+        assert_eq!(step_command(dir.path(), PackageManager::Pnpm, StepKind::Lint).unwrap(), vec!["pnpm", "run", "lint"]);
+        assert!(step_command(dir.path(), PackageManager::Pnpm, StepKind::Typecheck).is_ok());
+        assert!(step_command(dir.path(), PackageManager::Pnpm, StepKind::Test).unwrap_err().contains("`test` script"));
+    }
+
+    #[test]
+    fn test_render_html_escapes_output_and_marks_diff() {
+        let run = ValidationRun {
+            id: "1700000000-abcd1234".to_string(),
+            started_at: 1_700_000_000,
+            finished_at: 1_700_000_012,
+            passed: false,
+            steps: vec![ValidationStep {
+                kind: StepKind::Typecheck,
+                status: StepStatus::Failed,
+                command: Some("pnpm exec tsc --noEmit".to_string()),
+                exit_code: Some(2),
+                duration_ms: 1500,
+                output: "src/a.ts(1,1): error TS2322: Type '<T>' is not assignable".to_string(),
+            }],
+            diff_stat: " src/a.ts | 2 +-".to_string(),
+            diff: "--- a/src/a.ts\n+++ b/src/a.ts\n@@ -1 +1 @@\n-const a = 1;\n+const a = \"<b>\";".to_string(),
+        };
+        let html = render_html(&run);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<span class=\"badge failed\">failed</span>"));
+        assert!(html.contains("<details open><summary>typecheck output</summary>"));
+        assert!(html.contains("Type &#39;&lt;T&gt;&#39;"));
+        assert!(html.contains("<span class=\"add\">+const a = &quot;&lt;b&gt;&quot;;</span>"));
+        assert!(!html.contains("<b>"));
+    }
+}
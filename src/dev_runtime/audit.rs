@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+
+use super::db;
+use crate::file_system::paths;
+
+// Directory in galatea_files where earlier versions kept one `audit-YYYY-MM-DD.jsonl` file per UTC day
+const LEGACY_DIR: &str = "audit";
+const LEGACY_PREFIX: &str = "audit-";
+const LEGACY_SUFFIX: &str = ".jsonl";
+// Longer string parameters (file contents, patches) are cut to this many characters
+const MAX_PARAM_CHARS: usize = 500;
+const MAX_ERROR_CHARS: usize = 2000;
+// Parameter names whose values are never written to the log
+const SECRET_KEY_MARKERS: &[&str] = &["key", "token", "secret", "password"];
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+pub const MAX_QUERY_LIMIT: usize = 1000;
+
+static LEGACY_IMPORT: Once = Once::new();
+
+/// One state-changing API call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: u64, // Unix seconds when the request arrived
+    /// Who made the call: `token:<name>` for a named API token, `key:<hash>` for an unnamed one,
+    /// `loopback` or `anonymous`
+    pub principal: String,
+    /// Client session from `x-galatea-session`, if sent
+    pub client_session: Option<String>,
+    /// Dotted name such as `editor.command.str_replace`, `git.commit` or `editor.script`
+    pub operation: String,
+    pub method: String,
+    pub path: String,
+    /// Root of the workspace the call was scoped to, if not the default project
+    pub workspace: Option<String>,
+    /// Body and query parameters, with secrets masked and long strings cut
+    pub params: Value,
+    pub status: u16,
+    /// Response body of a failed call
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Filters for `query`; every one that is set must match.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub principal: Option<String>,
+    /// Operation prefix: `git` matches `git.commit` and `git.stash.pop`
+    pub operation: Option<String>,
+    /// `true` for failed calls (status 400 and above) only, `false` for successful ones only
+    pub failed: Option<bool>,
+    pub limit: Option<usize>,
+}
+
+/// Name of the operation an API call performs, from its path (and the editor command).
+pub fn operation_name(path: &str, params: &Value) -> String {
+    let path = path.strip_prefix("/api/").unwrap_or(path).trim_matches('/');
+    let mut operation = path.replace('/', ".");
+    if operation == "editor.command" {
+        if let Some(command) = params.get("command").and_then(Value::as_str) {
+            operation = format!("{}.{}", operation, command);
+        }
+    }
+    operation
+}
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_KEY_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+fn sanitize(value: Value) -> Value {
+    match value {
+        Value::String(s) if s.chars().count() > MAX_PARAM_CHARS => {
+            let kept: String = s.chars().take(MAX_PARAM_CHARS).collect();
+            Value::String(format!("{}… ({} chars)", kept, s.chars().count()))
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sanitize).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = if is_secret(&k) && !v.is_null() { Value::String("********".to_string()) } else { sanitize(v) };
+                    (k, v)
+                })
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// The parameters worth recording of a call: its JSON body (or its size, for other bodies) and
+/// its query string.
+pub fn request_params(body: &[u8], query: Option<&str>) -> Value {
+    let mut params = match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Object(map)) => map,
+        Ok(other) => Map::from_iter([("body".to_string(), other)]),
+        Err(_) if body.is_empty() => Map::new(),
+        Err(_) => Map::from_iter([("body_bytes".to_string(), Value::from(body.len()))]),
+    };
+    for (key, value) in query.map(|q| url::form_urlencoded::parse(q.as_bytes())).into_iter().flatten() {
+        params.entry(key.into_owned()).or_insert(Value::String(value.into_owned()));
+    }
+    sanitize(Value::Object(params))
+}
+
+/// The parameters of a call whose body isn't read (uploads, MCP proxy calls): the size it
+/// declares and its query string.
+pub fn streamed_request_params(length: Option<u64>, query: Option<&str>) -> Value {
+    let mut params = request_params(&[], query);
+    if let (Some(length), Value::Object(map)) = (length.filter(|l| *l > 0), &mut params) {
+        map.insert("body_bytes".to_string(), Value::from(length));
+    }
+    params
+}
+
+/// Cuts the response body of a failed call to what is kept in the log.
+pub fn error_excerpt(body: &str) -> String {
+    let body = body.trim();
+    match body.char_indices().nth(MAX_ERROR_CHARS) {
+        Some((end, _)) => format!("{}…", &body[..end]),
+        None => body.to_string(),
+    }
+}
+
+/// Queues `entry` for the audit log in the metadata store.
+pub fn record(entry: AuditEntry) {
+    ensure_legacy_imported();
+    db::write_later(move |db| {
+        if let Err(e) = db.record_audit(std::slice::from_ref(&entry)) {
+            tracing::warn!(target: "dev_runtime::audit", error = %format!("{:#}", e), operation = %entry.operation, "Failed to write audit entry.");
+        }
+    });
+}
+
+/// Entries matching `filter`, newest first.
+pub fn query(filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+    ensure_legacy_imported();
+    let limit = filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+    db::with_db(|db| db.audit_entries(filter, limit))
+}
+
+// Moves the entries of the daily files earlier versions wrote into the store, deleting each
+// file once its entries are in
+fn import_legacy_files() -> Result<usize> {
+    let dir = paths::galatea_files_dir()?.join(LEGACY_DIR);
+    let mut files: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(LEGACY_PREFIX) && n.ends_with(LEGACY_SUFFIX)))
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).context(format!("Failed to read {}", dir.display())),
+    };
+    files.sort();
+    let mut imported = 0;
+    for path in files {
+        let content = fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+        // Malformed lines (e.g. a write cut short by a crash) are skipped
+        let entries: Vec<AuditEntry> = content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+        db::with_db(|db| db.record_audit(&entries))?;
+        fs::remove_file(&path).context(format!("Failed to remove {}", path.display()))?;
+        imported += entries.len();
+    }
+    // Left alone if anything else is in it
+    let _ = fs::remove_dir(&dir);
+    Ok(imported)
+}
+
+fn ensure_legacy_imported() {
+    LEGACY_IMPORT.call_once(|| match import_legacy_files() {
+        Ok(0) => {}
+        Ok(imported) => tracing::info!(target: "dev_runtime::audit", imported, "Moved the audit log files into the database."),
+        Err(e) => tracing::warn!(target: "dev_runtime::audit", error = ?e, "Failed to import the audit log files."),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_params_and_operation_names() {
+        let body = serde_json::to_vec(&json!({
+            "command": "create",
+            "path": "src/a.ts",
+            "file_text": "x".repeat(MAX_PARAM_CHARS + 10),
+            "openai_api_key": "sk-123",
+        }))
+        .unwrap();
+        let params = request_params(&body, Some("dry_run=true&path=ignored"));
+        assert_eq!(params["path"], "src/a.ts");
+        assert_eq!(params["dry_run"], "true");
+        assert_eq!(params["openai_api_key"], "********");
+        assert!(params["file_text"].as_str().unwrap().ends_with(&format!("({} chars)", MAX_PARAM_CHARS + 10)));
+        assert_eq!(request_params(b"\x00\x01", None), json!({"body_bytes": 2}));
+        assert_eq!(streamed_request_params(Some(26_214_400), Some("path=a.png")), json!({"path": "a.png", "body_bytes": 26_214_400}));
+
+        assert_eq!(operation_name("/api/editor/command", &params), "editor.command.create");
+        assert_eq!(operation_name("/api/git/stash/pop", &Value::Null), "git.stash.pop");
+    }
+
+    #[test]
+    fn test_filters_are_applied_in_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = db::Database::open(&dir.path().join("galatea.sqlite3")).unwrap();
+        let entry = |timestamp: u64, operation: &str, status: u16| AuditEntry {
+            timestamp,
+            principal: "token:agent".to_string(),
+            client_session: None,
+            operation: operation.to_string(),
+            method: "POST".to_string(),
+            path: format!("/api/{}", operation.replace('.', "/")),
+            workspace: None,
+            params: json!({}),
+            status,
+            error: (status >= 400).then(|| "conflict".to_string()),
+            duration_ms: 12,
+        };
+        db.record_audit(&[entry(1_700_000_000, "git.stash.pop", 409), entry(1_700_000_100, "git.commit", 200), entry(1_700_000_200, "gitignore", 200)])
+            .unwrap();
+
+        let query = |f: AuditFilter| db.audit_entries(&f, 10).unwrap().into_iter().map(|e| e.operation).collect::<Vec<_>>();
+        assert_eq!(query(AuditFilter { operation: Some("git".to_string()), ..Default::default() }), vec!["git.commit", "git.stash.pop"]);
+        assert_eq!(query(AuditFilter { failed: Some(true), ..Default::default() }), vec!["git.stash.pop"]);
+        assert_eq!(query(AuditFilter { since: Some(1_700_000_001), until: Some(1_700_000_100), ..Default::default() }), vec!["git.commit"]);
+        assert!(query(AuditFilter { principal: Some("token:ci".to_string()), ..Default::default() }).is_empty());
+        assert_eq!(db.audit_entries(&AuditFilter::default(), 10).unwrap()[2], entry(1_700_000_000, "git.stash.pop", 409));
+    }
+}
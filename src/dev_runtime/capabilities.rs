@@ -0,0 +1,293 @@
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use super::util::now_secs;
+use crate::codebase_indexing::semantic::EmbeddingConfig;
+use crate::dev_setup::config_files;
+
+/// Code of the 503 body endpoints answer with when a capability they need is missing.
+pub const UNAVAILABLE_CODE: &str = "capability_unavailable";
+
+// How long clients should wait before retrying a capability that is starting up
+const STARTING_RETRY_SECS: u64 = 5;
+
+// Executables probed for the headless browser when `browser_path` isn't configured
+const BROWSER_EXECUTABLES: &[&str] = &["chromium", "chromium-browser", "google-chrome", "google-chrome-stable", "chrome"];
+
+/// An optional subsystem Galatea can run without.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Capability {
+    /// Generated MCP servers and the `/api/<id>/mcp` proxy
+    Mcp,
+    /// The TypeScript language server
+    Lsp,
+    /// A headless Chromium for browser automation
+    Browser,
+    /// OpenAI-compatible embeddings for semantic code search
+    Embeddings,
+    /// The `git` executable
+    Git,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] =
+        [Capability::Mcp, Capability::Lsp, Capability::Browser, Capability::Embeddings, Capability::Git];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Mcp => "mcp",
+            Capability::Lsp => "lsp",
+            Capability::Browser => "browser",
+            Capability::Embeddings => "embeddings",
+            Capability::Git => "git",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Capability::Mcp => "MCP",
+            Capability::Lsp => "The language server",
+            Capability::Browser => "The headless browser",
+            Capability::Embeddings => "Embeddings",
+            Capability::Git => "Git",
+        }
+    }
+}
+
+/// Live state of a capability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapabilityState {
+    /// Not checked yet
+    Unknown,
+    /// Not running, started on first use
+    Idle,
+    Starting,
+    Available,
+    /// Turned off by configuration or a command-line flag
+    Disabled,
+    /// A dependency is missing (executable, API key, network)
+    Unavailable,
+    /// Was started or probed and failed
+    Failed,
+}
+
+impl CapabilityState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CapabilityState::Unknown => "unknown",
+            CapabilityState::Idle => "idle",
+            CapabilityState::Starting => "starting",
+            CapabilityState::Available => "available",
+            CapabilityState::Disabled => "disabled",
+            CapabilityState::Unavailable => "unavailable",
+            CapabilityState::Failed => "failed",
+        }
+    }
+
+    // Whether work depending on the capability may go ahead. Unknown and idle capabilities
+    // aren't known to be broken, so the work itself finds out.
+    fn is_usable(&self) -> bool {
+        matches!(self, CapabilityState::Available | CapabilityState::Idle | CapabilityState::Unknown)
+    }
+}
+
+/// A capability's state and why it is in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapabilityStatus {
+    pub capability: Capability,
+    pub state: CapabilityState,
+    pub reason: Option<String>,
+    /// Unix timestamp of the last state change
+    pub since: u64,
+}
+
+/// Work refused because a capability it needs is missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapabilityError {
+    pub capability: Capability,
+    pub state: CapabilityState,
+    pub reason: Option<String>,
+    /// Set while the capability is starting
+    pub retry_after_secs: Option<u64>,
+}
+
+impl std::fmt::Display for CapabilityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is {}", self.capability.label(), self.state.as_str())?;
+        match (&self.reason, self.retry_after_secs) {
+            (Some(reason), _) => write!(f, ": {}", reason),
+            (None, Some(secs)) => write!(f, ", retry in {}s", secs),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for CapabilityError {}
+
+#[derive(Debug, Default)]
+struct Registry {
+    statuses: BTreeMap<Capability, CapabilityStatus>,
+}
+
+impl Registry {
+    fn status(&self, capability: Capability) -> CapabilityStatus {
+        self.statuses.get(&capability).cloned().unwrap_or(CapabilityStatus {
+            capability,
+            state: CapabilityState::Unknown,
+            reason: None,
+            since: 0,
+        })
+    }
+
+    // Returns whether the state changed
+    fn set(&mut self, capability: Capability, state: CapabilityState, reason: Option<String>) -> bool {
+        let current = self.status(capability);
+        if current.state == state && current.reason == reason {
+            return false;
+        }
+        self.statuses.insert(capability, CapabilityStatus { capability, state, reason, since: now_secs() });
+        true
+    }
+
+    fn require(&self, capability: Capability) -> Result<(), CapabilityError> {
+        let status = self.status(capability);
+        if status.state.is_usable() {
+            return Ok(());
+        }
+        Err(CapabilityError {
+            capability,
+            state: status.state,
+            reason: status.reason,
+            retry_after_secs: (status.state == CapabilityState::Starting).then_some(STARTING_RETRY_SECS),
+        })
+    }
+}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::default()));
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Records a capability's state. Subsystems call this as they start, fail or stop.
+pub fn set(capability: Capability, state: CapabilityState, reason: Option<String>) {
+    if !registry().set(capability, state, reason.clone()) {
+        return;
+    }
+    let reason = reason.unwrap_or_default();
+    match state {
+        CapabilityState::Failed | CapabilityState::Unavailable => {
+            tracing::warn!(target: "dev_runtime::capabilities", capability = capability.as_str(), state = state.as_str(), reason = %reason, "Capability is not available.")
+        }
+        _ => tracing::info!(target: "dev_runtime::capabilities", capability = capability.as_str(), state = state.as_str(), "Capability state changed."),
+    }
+}
+
+pub fn status(capability: Capability) -> CapabilityStatus {
+    registry().status(capability)
+}
+
+/// Every capability, in a stable order.
+pub fn statuses() -> Vec<CapabilityStatus> {
+    let registry = registry();
+    Capability::ALL.iter().map(|c| registry.status(*c)).collect()
+}
+
+/// Fails with the capability's state when work depending on it can't go ahead.
+pub fn require(capability: Capability) -> Result<(), CapabilityError> {
+    registry().require(capability)
+}
+
+/// The error for a capability its caller found unusable, e.g. the LSP client while it starts.
+pub fn unavailable(capability: Capability) -> CapabilityError {
+    let status = status(capability);
+    let state = if status.state.is_usable() { CapabilityState::Unavailable } else { status.state };
+    CapabilityError {
+        capability,
+        state,
+        reason: status.reason,
+        retry_after_secs: (state == CapabilityState::Starting).then_some(STARTING_RETRY_SECS),
+    }
+}
+
+fn on_path(executable: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else { return false };
+    std::env::split_paths(&path).any(|dir| dir.join(executable).is_file())
+}
+
+fn probe_git() -> (CapabilityState, Option<String>) {
+    match std::process::Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => (CapabilityState::Available, None),
+        Ok(output) => (
+            CapabilityState::Failed,
+            Some(format!("`git --version` failed: {}", String::from_utf8_lossy(&output.stderr).trim())),
+        ),
+        Err(_) => (CapabilityState::Unavailable, Some("git is not installed or not on PATH".to_string())),
+    }
+}
+
+fn probe_browser() -> (CapabilityState, Option<String>) {
+    if let Some(path) = config_files::get_config_value("browser_path") {
+        return if std::path::Path::new(&path).is_file() {
+            (CapabilityState::Idle, None)
+        } else {
+            (CapabilityState::Unavailable, Some(format!("`browser_path` {} does not exist", path)))
+        };
+    }
+    if BROWSER_EXECUTABLES.iter().any(|exe| on_path(exe)) {
+        (CapabilityState::Idle, None)
+    } else {
+        (
+            CapabilityState::Unavailable,
+            Some("No Chromium or Chrome on PATH; install one or set `browser_path` in config.toml".to_string()),
+        )
+    }
+}
+
+fn probe_embeddings() -> (CapabilityState, Option<String>) {
+    match EmbeddingConfig::load().unavailable_reason() {
+        Some(reason) => (CapabilityState::Unavailable, Some(reason)),
+        None => (CapabilityState::Available, None),
+    }
+}
+
+/// Checks the capabilities that depend on the machine rather than a running service: git, the
+/// headless browser and embeddings. The LSP starts out idle; MCP is set when its servers launch.
+pub fn probe_environment() {
+    for (capability, (state, reason)) in [
+        (Capability::Git, probe_git()),
+        (Capability::Browser, probe_browser()),
+        (Capability::Embeddings, probe_embeddings()),
+    ] {
+        set(capability, state, reason);
+    }
+    if status(Capability::Lsp).state == CapabilityState::Unknown {
+        set(Capability::Lsp, CapabilityState::Idle, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_require_reflects_state() {
+        let mut registry = Registry::default();
+        assert!(registry.require(Capability::Git).is_ok());
+
+        assert!(registry.set(Capability::Lsp, CapabilityState::Starting, None));
+        assert!(!registry.set(Capability::Lsp, CapabilityState::Starting, None));
+        let err = registry.require(Capability::Lsp).unwrap_err();
+        assert_eq!(err.retry_after_secs, Some(STARTING_RETRY_SECS));
+        assert_eq!(err.to_string(), "The language server is starting, retry in 5s");
+
+        registry.set(Capability::Git, CapabilityState::Unavailable, Some("git is not installed".to_string()));
+        let err = registry.require(Capability::Git).unwrap_err();
+        assert_eq!((err.state, err.retry_after_secs), (CapabilityState::Unavailable, None));
+        assert_eq!(err.to_string(), "Git is unavailable: git is not installed");
+
+        registry.set(Capability::Lsp, CapabilityState::Available, None);
+        assert!(registry.require(Capability::Lsp).is_ok());
+    }
+}
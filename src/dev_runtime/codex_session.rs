@@ -0,0 +1,693 @@
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc as std_mpsc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::{broadcast, mpsc};
+
+use super::util::now_secs;
+use super::{crash, db, events};
+use crate::dev_setup::config_files;
+use crate::file_system::{get_project_root, paths};
+#[cfg(unix)]
+use crate::terminal::stream;
+
+// config.toml table holding the agent settings
+const CONFIG_SECTION: &str = "codex";
+
+// Under galatea_files: `<id>.json` holds a session's record, `<id>.events.jsonl` its events
+const SESSIONS_DIR: &str = "codex_sessions";
+
+// Events buffered for a streaming client that falls behind; it skips ahead after that
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+// Time a terminated agent's processes get to exit after SIGTERM before they are killed
+const KILL_GRACE: Duration = Duration::from_secs(3);
+
+// Node.js version the CLI is installed for by `dev_setup::codex`
+const NODE_VERSION: &str = "22";
+
+/// Settings for Codex agent sessions, from `[codex]` in config.toml.
+///
+/// ```toml
+/// [codex]
+/// enabled = true
+/// command = "codex"                # the CLI, run with `proto`
+/// model = "o4-mini"                # the CLI's default when unset
+/// approval_policy = "on-request"   # untrusted, on-failure, on-request or never
+/// max_sessions = 2
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CodexConfig {
+    pub enabled: bool,
+    pub command: String,
+    pub model: Option<String>,
+    /// When the agent asks before running a command or applying a patch
+    pub approval_policy: String,
+    /// Sessions running at once; starting another fails until one finishes
+    pub max_sessions: usize,
+}
+
+impl Default for CodexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            command: "codex".to_string(),
+            model: None,
+            approval_policy: "on-request".to_string(),
+            max_sessions: 2,
+        }
+    }
+}
+
+impl CodexConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStatus {
+    Running,
+    /// The agent finished its task and exited
+    Completed,
+    Failed,
+    Terminated,
+    /// Still running when Galatea stopped
+    Interrupted,
+}
+
+impl SessionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionStatus::Running => "running",
+            SessionStatus::Completed => "completed",
+            SessionStatus::Failed => "failed",
+            SessionStatus::Terminated => "terminated",
+            SessionStatus::Interrupted => "interrupted",
+        }
+    }
+}
+
+/// How a proposed command or patch is answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    Approve,
+    /// Approve, and the same command for the rest of the session
+    ApproveForSession,
+    Deny,
+}
+
+impl ApprovalDecision {
+    pub fn parse(decision: &str) -> Option<Self> {
+        match decision {
+            "approve" => Some(ApprovalDecision::Approve),
+            "approve_for_session" => Some(ApprovalDecision::ApproveForSession),
+            "deny" => Some(ApprovalDecision::Deny),
+            _ => None,
+        }
+    }
+
+    // The CLI's name for it
+    fn protocol_name(self) -> &'static str {
+        match self {
+            ApprovalDecision::Approve => "approved",
+            ApprovalDecision::ApproveForSession => "approved_for_session",
+            ApprovalDecision::Deny => "denied",
+        }
+    }
+}
+
+/// A command or patch the agent asked to run or apply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Approval {
+    pub call_id: String,
+    /// `exec` for a command, `patch` for file changes
+    pub kind: String,
+    /// The command line, or the files the patch changes
+    pub summary: String,
+    pub reason: Option<String>,
+    pub requested_at: u64,
+    /// `approved`, `approved_for_session` or `denied`; `None` while pending
+    pub decision: Option<String>,
+    pub decided_at: Option<u64>,
+    // The submission the request belongs to, which the answer has to name
+    submission_id: String,
+}
+
+/// A session as persisted under `galatea_files/codex_sessions`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub id: String,
+    pub prompt: String,
+    pub model: Option<String>,
+    pub status: SessionStatus,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    /// `None` while running, or when the agent was killed by a signal
+    pub exit_code: Option<i32>,
+    /// The agent's final message, once it completed its task
+    pub last_message: Option<String>,
+    /// The last error the agent reported
+    pub error: Option<String>,
+    pub approvals: Vec<Approval>,
+    pub event_count: u64,
+}
+
+/// Something the agent or its process did, in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionEvent {
+    /// Position in the session, from 1
+    pub seq: u64,
+    pub at: u64,
+    /// The CLI's event type, e.g. `agent_message`, `exec_command_begin` or
+    /// `exec_approval_request`; `approval_decision` for answers given through Galatea; `stderr`
+    /// and `exit` for the process
+    pub kind: String,
+    pub data: Value,
+}
+
+/// Why a session operation was refused.
+#[derive(Debug)]
+pub enum CodexError {
+    NotFound(String),
+    Conflict(String),
+    Invalid(String),
+    /// Sessions are disabled, or as many as allowed are running
+    Unavailable(String),
+    Failed(anyhow::Error),
+}
+
+impl std::fmt::Display for CodexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodexError::NotFound(msg) | CodexError::Conflict(msg) | CodexError::Invalid(msg) | CodexError::Unavailable(msg) => write!(f, "{}", msg),
+            CodexError::Failed(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+// A running session, until its process exits
+struct LiveSession {
+    record: SessionRecord,
+    // Lines written to the agent's stdin
+    input: mpsc::UnboundedSender<String>,
+    output: broadcast::Sender<SessionEvent>,
+    next_submission: u64,
+    // Leads the agent's process group, so terminating reaches the commands it runs
+    pid: Option<u32>,
+}
+
+impl LiveSession {
+    // Sends an operation to the agent, returning the submission id it was given
+    fn submit(&mut self, op: Value) -> String {
+        self.next_submission += 1;
+        let id = self.next_submission.to_string();
+        let _ = self.input.send(json!({ "id": id, "op": op }).to_string());
+        id
+    }
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<String, LiveSession>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn sessions() -> std::sync::MutexGuard<'static, HashMap<String, LiveSession>> {
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Sessions between passing the `max_sessions` check and being inserted; only raised while
+// `SESSIONS` is locked, so concurrent starts can't both take the last slot
+static STARTING: AtomicUsize = AtomicUsize::new(0);
+
+// A slot counted against `max_sessions` until the started session is inserted, or the start fails
+struct SlotReservation;
+
+impl Drop for SlotReservation {
+    fn drop(&mut self) {
+        STARTING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn reserve_slot(max_sessions: usize) -> Option<SlotReservation> {
+    let live = sessions();
+    if live.len() + STARTING.load(Ordering::SeqCst) >= max_sessions {
+        return None;
+    }
+    STARTING.fetch_add(1, Ordering::SeqCst);
+    Some(SlotReservation)
+}
+
+enum PendingWrite {
+    Record(SessionRecord),
+    Event(String, SessionEvent),
+    // Answered once the writes queued before it are on disk
+    Flush(std_mpsc::Sender<()>),
+}
+
+// Records and events to write, in order on a thread of their own so agent output never does
+// file I/O on the runtime or while `SESSIONS` is locked; `None` if the thread couldn't be
+// started, in which case they are written by the caller
+static WRITER: Lazy<Option<Mutex<std_mpsc::Sender<PendingWrite>>>> = Lazy::new(|| {
+    let (sender, receiver) = std_mpsc::channel::<PendingWrite>();
+    let spawned = std::thread::Builder::new().name("galatea-codex-writer".to_string()).spawn(move || {
+        for write in receiver {
+            apply_write(write);
+        }
+    });
+    match spawned {
+        Ok(_) => Some(Mutex::new(sender)),
+        Err(e) => {
+            tracing::warn!(target: "dev_runtime::codex_session", error = %e, "Failed to start the session writer thread, writing inline.");
+            None
+        }
+    }
+});
+
+fn write_later(write: PendingWrite) {
+    let Some(writer) = WRITER.as_ref() else {
+        return apply_write(write);
+    };
+    if let Err(std_mpsc::SendError(write)) = writer.lock().unwrap_or_else(|e| e.into_inner()).send(write) {
+        apply_write(write);
+    }
+}
+
+// Waits until the writes queued so far are on disk, before reading records or events back
+fn flush_writes() {
+    let (done, written) = std_mpsc::channel();
+    write_later(PendingWrite::Flush(done));
+    let _ = written.recv();
+}
+
+fn apply_write(write: PendingWrite) {
+    match write {
+        PendingWrite::Record(record) => {
+            if let Err(e) = write_record(&record) {
+                tracing::warn!(target: "dev_runtime::codex_session", session_id = %record.id, error = ?e, "Failed to persist codex session.");
+            }
+        }
+        PendingWrite::Event(id, event) => {
+            if let Err(e) = write_event(&id, &event) {
+                tracing::debug!(target: "dev_runtime::codex_session", session_id = %id, error = ?e, "Failed to persist codex event.");
+            }
+        }
+        PendingWrite::Flush(done) => {
+            let _ = done.send(());
+        }
+    }
+}
+
+fn write_record(record: &SessionRecord) -> Result<()> {
+    let dir = sessions_dir()?;
+    fs::create_dir_all(&dir).context("Failed to create the codex_sessions directory")?;
+    fs::write(dir.join(format!("{}.json", record.id)), serde_json::to_string_pretty(record)?)
+        .context("Failed to write the session record")
+}
+
+fn write_event(id: &str, event: &SessionEvent) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(sessions_dir()?.join(format!("{}.events.jsonl", id)))
+        .context("Failed to open the session's event log")?;
+    writeln!(file, "{}", serde_json::to_string(event)?).context("Failed to append to the session's event log")
+}
+
+pub fn sessions_dir() -> Result<PathBuf> {
+    Ok(paths::galatea_files_dir()?.join(SESSIONS_DIR))
+}
+
+fn save_record(record: &SessionRecord) {
+    write_later(PendingWrite::Record(record.clone()));
+    // Mirrored into the metadata store as a job, so a restart marks it interrupted
+    let (id, status, prompt) = (record.id.clone(), record.status.as_str(), record.prompt.clone());
+    db::write_later(move |db| {
+        if let Err(e) = db.upsert_job(events::session_id(), &id, "codex", status, Some(&prompt)) {
+            tracing::debug!(target: "dev_runtime::codex_session", error = ?e, "Failed to record codex session.");
+        }
+    });
+}
+
+fn append_event(session: &mut LiveSession, kind: &str, data: Value) {
+    session.record.event_count += 1;
+    let event = SessionEvent { seq: session.record.event_count, at: now_secs(), kind: kind.to_string(), data };
+    write_later(PendingWrite::Event(session.record.id.clone(), event.clone()));
+    // No subscribers is fine
+    let _ = session.output.send(event);
+}
+
+/// What an approval request from the agent asks for, or `None` for other events.
+fn approval_request(kind: &str, msg: &Value, submission_id: &str) -> Option<Approval> {
+    let summary = match kind {
+        "exec_approval_request" => msg.get("command")?.as_array()?.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" "),
+        "apply_patch_approval_request" => {
+            let mut files: Vec<&str> = msg.get("changes")?.as_object()?.keys().map(String::as_str).collect();
+            files.sort_unstable();
+            files.join(", ")
+        }
+        _ => return None,
+    };
+    Some(Approval {
+        call_id: msg.get("call_id")?.as_str()?.to_string(),
+        kind: if kind == "exec_approval_request" { "exec" } else { "patch" }.to_string(),
+        summary,
+        reason: msg.get("reason").and_then(Value::as_str).map(str::to_string),
+        requested_at: now_secs(),
+        decision: None,
+        decided_at: None,
+        submission_id: submission_id.to_string(),
+    })
+}
+
+/// The operation answering an approval request.
+fn approval_op(approval: &Approval, decision: ApprovalDecision) -> Value {
+    let op = if approval.kind == "exec" { "exec_approval" } else { "patch_approval" };
+    json!({ "type": op, "id": approval.submission_id, "decision": decision.protocol_name() })
+}
+
+// Handles one line the agent printed: `{"id": <submission>, "msg": {"type": ..., ...}}`
+fn handle_agent_line(id: &str, line: &str) {
+    let mut live = sessions();
+    let Some(session) = live.get_mut(id) else { return };
+    let Ok(event) = serde_json::from_str::<Value>(line) else {
+        // Not protocol output, e.g. a warning the CLI printed before starting
+        append_event(session, "output", json!({ "line": line }));
+        return;
+    };
+    let msg = event.get("msg").cloned().unwrap_or(Value::Null);
+    let kind = msg.get("type").and_then(Value::as_str).unwrap_or("unknown").to_string();
+    let submission_id = event.get("id").and_then(Value::as_str).unwrap_or_default();
+    if let Some(approval) = approval_request(&kind, &msg, submission_id) {
+        tracing::info!(target: "dev_runtime::codex_session", session_id = %id, call_id = %approval.call_id, summary = %approval.summary, "Agent asks for approval.");
+        session.record.approvals.push(approval);
+        save_record(&session.record);
+    }
+    match kind.as_str() {
+        "task_complete" => {
+            session.record.last_message = msg.get("last_agent_message").and_then(Value::as_str).map(str::to_string);
+            // One task per session: the agent exits once it is done
+            session.submit(json!({ "type": "shutdown" }));
+        }
+        "error" => session.record.error = msg.get("message").and_then(Value::as_str).map(str::to_string),
+        _ => {}
+    }
+    append_event(session, &kind, msg);
+}
+
+fn finish(id: &str, code: Option<i32>) {
+    let Some(mut session) = sessions().remove(id) else { return };
+    let record = &mut session.record;
+    if record.status == SessionStatus::Running {
+        record.status = if code == Some(0) && record.error.is_none() { SessionStatus::Completed } else { SessionStatus::Failed };
+    }
+    record.exit_code = code;
+    record.finished_at = Some(now_secs());
+    append_event(&mut session, "exit", json!({ "code": code }));
+    save_record(&session.record);
+    tracing::info!(target: "dev_runtime::codex_session", session_id = %id, status = session.record.status.as_str(), "Codex session finished.");
+}
+
+fn agent_command(config: &CodexConfig, project_root: &std::path::Path) -> Command {
+    // The CLI is installed for Node.js 22 through nvm; without nvm the one on PATH is used
+    let script = format!(
+        "source ~/.nvm/nvm.sh >/dev/null 2>&1 && nvm use {} >/dev/null 2>&1; exec \"$0\" \"$@\"",
+        NODE_VERSION
+    );
+    let mut cmd = Command::new("bash");
+    cmd.arg("-c").arg(script).arg(&config.command).arg("proto");
+    cmd.arg("-c").arg(format!("approval_policy={}", config.approval_policy));
+    if let Some(model) = &config.model {
+        cmd.arg("-c").arg(format!("model={}", model));
+    }
+    cmd.current_dir(project_root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd
+}
+
+/// Starts an agent session in the project working on `prompt`. Its events are persisted as
+/// they arrive; the agent exits once it completes the task.
+pub fn start(prompt: &str, model: Option<String>) -> Result<SessionRecord, CodexError> {
+    let mut config = CodexConfig::load();
+    if !config.enabled {
+        return Err(CodexError::Unavailable("Codex sessions are disabled by `enabled = false` under [codex] in config.toml".to_string()));
+    }
+    if prompt.trim().is_empty() {
+        return Err(CodexError::Invalid("The prompt is empty".to_string()));
+    }
+    let Some(reservation) = reserve_slot(config.max_sessions) else {
+        return Err(CodexError::Unavailable(format!(
+            "{} codex session(s) are already running, the most `max_sessions` allows; wait for one to finish or terminate one",
+            config.max_sessions
+        )));
+    };
+    if model.is_some() {
+        config.model = model;
+    }
+    let project_root = get_project_root().map_err(CodexError::Failed)?;
+    fs::create_dir_all(sessions_dir().map_err(CodexError::Failed)?)
+        .context("Failed to create the codex_sessions directory")
+        .map_err(CodexError::Failed)?;
+
+    let mut child = agent_command(&config, &project_root)
+        .spawn()
+        .with_context(|| format!("Failed to start {}. Is the Codex CLI installed?", config.command))
+        .map_err(CodexError::Failed)?;
+    let (Some(mut stdin), Some(stdout), Some(stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take()) else {
+        return Err(CodexError::Failed(anyhow!("Failed to capture the agent's stdio")));
+    };
+
+    let id = format!("{}-{}", now_secs(), &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let (input, mut input_rx) = mpsc::unbounded_channel::<String>();
+    let mut session = LiveSession {
+        record: SessionRecord {
+            id: id.clone(),
+            prompt: prompt.to_string(),
+            model: config.model.clone(),
+            status: SessionStatus::Running,
+            created_at: now_secs(),
+            finished_at: None,
+            exit_code: None,
+            last_message: None,
+            error: None,
+            approvals: Vec::new(),
+            event_count: 0,
+        },
+        input,
+        output: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        next_submission: 0,
+        pid: child.id(),
+    };
+    session.submit(json!({ "type": "user_input", "items": [{ "type": "text", "text": prompt }] }));
+    save_record(&session.record);
+    let record = session.record.clone();
+    let mut live = sessions();
+    live.insert(id.clone(), session);
+    drop(reservation);
+    drop(live);
+    tracing::info!(target: "dev_runtime::codex_session", session_id = %id, "Started codex session.");
+
+    tokio::spawn(async move {
+        while let Some(line) = input_rx.recv().await {
+            if stdin.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    });
+    let session_id = id.clone();
+    tokio::spawn(async move {
+        let _operation = crash::track_operation(format!("codex session {}", session_id));
+        let stderr_id = session_id.clone();
+        let stderr_task = tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Some(session) = sessions().get_mut(&stderr_id) {
+                    append_event(session, "stderr", json!({ "line": line }));
+                }
+            }
+        });
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            handle_agent_line(&session_id, &line);
+        }
+        let _ = stderr_task.await;
+        let code = match child.wait().await {
+            Ok(status) => status.code(),
+            Err(e) => {
+                tracing::warn!(target: "dev_runtime::codex_session", session_id = %session_id, error = %e, "Failed to wait for the agent.");
+                None
+            }
+        };
+        finish(&session_id, code);
+    });
+    Ok(record)
+}
+
+/// Answers an approval request the agent is waiting on.
+pub fn decide(id: &str, call_id: &str, decision: ApprovalDecision) -> Result<SessionRecord, CodexError> {
+    let mut live = sessions();
+    let Some(session) = live.get_mut(id) else {
+        drop(live);
+        return match get(id) {
+            Some(_) => Err(CodexError::Conflict(format!("Session '{}' is no longer running", id))),
+            None => Err(CodexError::NotFound(format!("No codex session '{}'", id))),
+        };
+    };
+    let Some(index) = session.record.approvals.iter().position(|a| a.call_id == call_id) else {
+        return Err(CodexError::NotFound(format!("Session '{}' has no approval request '{}'", id, call_id)));
+    };
+    if let Some(decided) = &session.record.approvals[index].decision {
+        return Err(CodexError::Conflict(format!("Approval request '{}' was already answered: {}", call_id, decided)));
+    }
+    let op = approval_op(&session.record.approvals[index], decision);
+    session.submit(op);
+    let approval = &mut session.record.approvals[index];
+    approval.decision = Some(decision.protocol_name().to_string());
+    approval.decided_at = Some(now_secs());
+    let data = json!({ "call_id": call_id, "decision": decision.protocol_name() });
+    save_record(&session.record);
+    append_event(session, "approval_decision", data);
+    tracing::info!(target: "dev_runtime::codex_session", session_id = %id, call_id = %call_id, decision = decision.protocol_name(), "Answered approval request.");
+    Ok(session.record.clone())
+}
+
+/// Terminates a running session: the agent's process group gets SIGTERM, then SIGKILL if it
+/// is still running after a grace period. Finished sessions are returned unchanged.
+pub fn terminate(id: &str) -> Result<SessionRecord, CodexError> {
+    let mut live = sessions();
+    let Some(session) = live.get_mut(id) else {
+        drop(live);
+        return get(id).ok_or_else(|| CodexError::NotFound(format!("No codex session '{}'", id)));
+    };
+    if session.record.status != SessionStatus::Running {
+        return Ok(session.record.clone());
+    }
+    session.record.status = SessionStatus::Terminated;
+    save_record(&session.record);
+    tracing::info!(target: "dev_runtime::codex_session", session_id = %id, "Terminating codex session.");
+    #[cfg(unix)]
+    if let Some(pid) = session.pid {
+        stream::signal_process_group(pid, libc::SIGTERM);
+        let id = id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(KILL_GRACE).await;
+            // Only while the exit hasn't been seen, so a reused pid is never signalled
+            if sessions().contains_key(&id) {
+                stream::signal_process_group(pid, libc::SIGKILL);
+            }
+        });
+    }
+    Ok(session.record.clone())
+}
+
+// Callers flush the queued writes first
+fn read_record(id: &str) -> Option<SessionRecord> {
+    let content = fs::read_to_string(sessions_dir().ok()?.join(format!("{}.json", id))).ok()?;
+    let mut record: SessionRecord = serde_json::from_str(&content).ok()?;
+    // Persisted as running but not running in this process: Galatea stopped under it
+    if record.status == SessionStatus::Running {
+        record.status = SessionStatus::Interrupted;
+    }
+    Some(record)
+}
+
+/// A session, running or persisted.
+pub fn get(id: &str) -> Option<SessionRecord> {
+    if paths::validate_id(id, "session").is_err() {
+        return None;
+    }
+    if let Some(session) = sessions().get(id) {
+        return Some(session.record.clone());
+    }
+    flush_writes();
+    read_record(id)
+}
+
+/// Every session, running or persisted, newest first.
+pub fn list() -> Vec<SessionRecord> {
+    let mut records: HashMap<String, SessionRecord> = HashMap::new();
+    flush_writes();
+    if let Ok(entries) = sessions_dir().and_then(|dir| fs::read_dir(dir).map_err(Into::into)) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(record) = name.strip_suffix(".json").and_then(read_record) {
+                records.insert(record.id.clone(), record);
+            }
+        }
+    }
+    for session in sessions().values() {
+        records.insert(session.record.id.clone(), session.record.clone());
+    }
+    let mut records: Vec<SessionRecord> = records.into_values().collect();
+    records.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+    records
+}
+
+/// A session's persisted events after `after`, at most `limit` of them.
+pub fn events(id: &str, after: u64, limit: usize) -> Result<Vec<SessionEvent>> {
+    flush_writes();
+    let path = sessions_dir()?.join(format!("{}.events.jsonl", id));
+    let file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", path.display())),
+    };
+    Ok(std::io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<SessionEvent>(&line).ok())
+        .filter(|event| event.seq > after)
+        .take(limit)
+        .collect())
+}
+
+/// Live events of a running session; `None` once it has finished. Subscribe before reading
+/// the persisted events, then skip the live ones already read.
+pub fn subscribe(id: &str) -> Option<broadcast::Receiver<SessionEvent>> {
+    sessions().get(id).map(|session| session.output.subscribe())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approval_requests_and_answers() {
+        let exec = json!({"type": "exec_approval_request", "call_id": "call_1", "command": ["npm", "test"], "cwd": "/p", "reason": "run the tests"});
+        let approval = approval_request("exec_approval_request", &exec, "1").unwrap();
+        assert_eq!((approval.kind.as_str(), approval.summary.as_str(), approval.reason.as_deref()), ("exec", "npm test", Some("run the tests")));
+        assert_eq!(approval_op(&approval, ApprovalDecision::ApproveForSession), json!({"type": "exec_approval", "id": "1", "decision": "approved_for_session"}));
+
+        let patch = json!({"type": "apply_patch_approval_request", "call_id": "call_2", "changes": {"src/b.ts": {}, "src/a.ts": {}}});
+        let approval = approval_request("apply_patch_approval_request", &patch, "1").unwrap();
+        assert_eq!(approval.summary, "src/a.ts, src/b.ts");
+        assert_eq!(approval_op(&approval, ApprovalDecision::Deny)["type"], "patch_approval");
+
+        assert!(approval_request("agent_message", &json!({"type": "agent_message", "message": "hi"}), "1").is_none());
+        assert_eq!(ApprovalDecision::parse("approved"), None);
+        assert!(paths::validate_id("../config", "session").is_err());
+    }
+
+    #[test]
+    fn test_slots_are_reserved_until_the_start_finishes() {
+        let reservation = reserve_slot(1).unwrap();
+        assert!(reserve_slot(1).is_none());
+        let second = reserve_slot(2).unwrap();
+        drop((reservation, second));
+        assert!(reserve_slot(1).is_some());
+    }
+}
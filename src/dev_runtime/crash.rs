@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::log::SHARED_LOG_STORE;
+use super::util::now_secs;
+use crate::file_system::paths;
+use crate::terminal::package_manager::PackageManager;
+
+// Number of in-memory log entries copied into each crash bundle
+const LOG_TAIL_LEN: usize = 50;
+
+/// A crash bundle as written to `galatea_files/crashes/<id>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashBundle {
+    pub id: String,
+    pub kind: CrashKind,
+    pub timestamp: u64, // Unix seconds
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: String,
+    pub version: String,
+    pub active_operations: Vec<String>,
+    pub recent_logs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashKind {
+    Panic,
+    FatalError,
+}
+
+// --- Active operations ---
+
+static ACTIVE_OPERATIONS: Lazy<DashMap<u64, String>> = Lazy::new(DashMap::new);
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Keeps an operation listed as active until dropped.
+pub struct OperationGuard {
+    id: u64,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        ACTIVE_OPERATIONS.remove(&self.id);
+    }
+}
+
+/// Registers a long-running operation so it shows up in crash bundles if the process dies mid-way.
+pub fn track_operation(description: impl Into<String>) -> OperationGuard {
+    let id = NEXT_OPERATION_ID.fetch_add(1, Ordering::SeqCst);
+    ACTIVE_OPERATIONS.insert(id, description.into());
+    OperationGuard { id }
+}
+
+pub fn active_operations() -> Vec<String> {
+    let mut ops: Vec<(u64, String)> = ACTIVE_OPERATIONS
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().clone()))
+        .collect();
+    ops.sort_by_key(|(id, _)| *id);
+    ops.into_iter().map(|(_, description)| description).collect()
+}
+
+// --- Crash bundles ---
+
+pub fn crashes_dir() -> Result<PathBuf> {
+    Ok(paths::galatea_files_dir()?.join("crashes"))
+}
+
+// Copies the tail of the shared log store without blocking; the panicking thread may hold the lock
+fn recent_log_tail() -> Vec<String> {
+    match SHARED_LOG_STORE.try_lock() {
+        Ok(store) => store
+            .iter()
+            .rev()
+            .take(LOG_TAIL_LEN)
+            .rev()
+            .map(|entry| format!("[{:?}] [{:?}] {}", entry.level, entry.source, entry.message))
+            .collect(),
+        Err(_) => vec!["<log store unavailable>".to_string()],
+    }
+}
+
+fn build_bundle(kind: CrashKind, message: String, location: Option<String>, backtrace: String) -> CrashBundle {
+    let now = now_secs();
+    CrashBundle {
+        id: format!("{}-{}", now, uuid::Uuid::new_v4().simple()),
+        kind,
+        timestamp: now,
+        message,
+        location,
+        thread: std::thread::current().name().map(String::from),
+        backtrace,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        active_operations: active_operations(),
+        recent_logs: recent_log_tail(),
+    }
+}
+
+fn write_bundle(bundle: &CrashBundle) -> Result<PathBuf> {
+    let dir = crashes_dir()?;
+    fs::create_dir_all(&dir).context("Failed to create crashes directory")?;
+    let path = dir.join(format!("{}.json", bundle.id));
+    let json = serde_json::to_string_pretty(bundle).context("Failed to serialize crash bundle")?;
+    fs::write(&path, json).context(format!("Failed to write crash bundle {}", path.display()))?;
+    Ok(path)
+}
+
+/// Installs a panic hook that writes a crash bundle before running the default hook.
+pub fn install_panic_hook() {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = info.payload().downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "<non-string panic payload>".to_string()
+        };
+        let location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        let bundle = build_bundle(
+            CrashKind::Panic,
+            message,
+            location,
+            Backtrace::force_capture().to_string(),
+        );
+        match write_bundle(&bundle) {
+            Ok(path) => eprintln!("[CRASH] Panic recorded in {}", path.display()),
+            Err(e) => eprintln!("[CRASH] Failed to record panic: {:?}", e),
+        }
+        previous_hook(info);
+    }));
+    tracing::info!(target: "dev_runtime::crash", "Crash reporter panic hook installed.");
+}
+
+/// Writes a crash bundle for an error that is about to terminate the process.
+pub fn record_fatal_error(error: &anyhow::Error) -> Result<PathBuf> {
+    let bundle = build_bundle(
+        CrashKind::FatalError,
+        format!("{:#}", error),
+        None,
+        error.backtrace().to_string(),
+    );
+    write_bundle(&bundle)
+}
+
+/// Lists crash bundles, newest first. Unreadable files are skipped.
+pub fn list_crashes() -> Result<Vec<CrashBundle>> {
+    let dir = crashes_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut bundles: Vec<CrashBundle> = fs::read_dir(&dir)
+        .context(format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            let content = fs::read_to_string(entry.path()).ok()?;
+            serde_json::from_str(&content).ok()
+        })
+        .collect();
+    bundles.sort_by_key(|b| std::cmp::Reverse(b.timestamp));
+    Ok(bundles)
+}
+
+/// Loads a single crash bundle by id.
+pub fn get_crash(id: &str) -> Result<Option<CrashBundle>> {
+    paths::validate_id(id, "crash")?;
+    let path = crashes_dir()?.join(format!("{}.json", id));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+    let bundle = serde_json::from_str(&content).context("Failed to parse crash bundle")?;
+    Ok(Some(bundle))
+}
+
+// --- Self-check ---
+
+#[derive(Debug, Clone)]
+pub struct SelfCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Checks the things Galatea needs at runtime: its working directories and external tools.
+pub fn run_self_check() -> Vec<SelfCheck> {
+    let mut checks = Vec::new();
+
+    let galatea_files_check = crashes_dir().and_then(|dir| {
+        fs::create_dir_all(&dir).context("Failed to create crashes directory")?;
+        let probe = dir.join(".write_probe");
+        fs::write(&probe, b"ok").context("Failed to write probe file")?;
+        let _ = fs::remove_file(&probe);
+        Ok(dir)
+    });
+    checks.push(match galatea_files_check {
+        Ok(dir) => SelfCheck {
+            name: "galatea_files_writable".to_string(),
+            ok: true,
+            detail: dir.display().to_string(),
+        },
+        Err(e) => SelfCheck {
+            name: "galatea_files_writable".to_string(),
+            ok: false,
+            detail: format!("{:#}", e),
+        },
+    });
+
+    checks.push(match crate::file_system::get_project_root() {
+        Ok(root) => SelfCheck {
+            name: "project_directory".to_string(),
+            ok: true,
+            detail: root.display().to_string(),
+        },
+        Err(e) => SelfCheck {
+            name: "project_directory".to_string(),
+            ok: false,
+            detail: format!("{:#}", e),
+        },
+    });
+
+    for tool in ["git", "node", PackageManager::current().name()] {
+        checks.push(match find_in_path(tool) {
+            Some(path) => SelfCheck {
+                name: format!("tool_{}", tool),
+                ok: true,
+                detail: path.display().to_string(),
+            },
+            None => SelfCheck {
+                name: format!("tool_{}", tool),
+                ok: false,
+                detail: format!("'{}' not found in PATH", tool),
+            },
+        });
+    }
+
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_operation_is_removed_on_drop() {
+        let guard = track_operation("test: indexing project");
+        assert!(active_operations().contains(&"test: indexing project".to_string()));
+        drop(guard);
+        assert!(!active_operations().contains(&"test: indexing project".to_string()));
+    }
+
+    #[test]
+    fn test_get_crash_rejects_path_traversal() {
+        assert!(get_crash("../config").is_err());
+        assert!(get_crash("").is_err());
+    }
+}
@@ -0,0 +1,951 @@
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::audit::{AuditEntry, AuditFilter};
+use super::util;
+#[cfg(not(test))]
+use crate::file_system::paths;
+
+const DB_FILE_NAME: &str = "galatea.db";
+/// Files of the live database in `db_dir()`: the database and, in WAL mode, its write-ahead
+/// log and shared-memory index.
+pub const DB_FILES: &[&str] = &[DB_FILE_NAME, "galatea.db-wal", "galatea.db-shm"];
+
+// Schema migrations, applied in order. The applied version is tracked in `PRAGMA user_version`;
+// append new entries, never edit existing ones.
+const MIGRATIONS: &[&str] = &[
+    // 1: entity index, edit history, jobs, sessions, analytics
+    r#"
+    CREATE TABLE entity_files (
+        path TEXT PRIMARY KEY,
+        modified_ms INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        indexed_at INTEGER NOT NULL
+    );
+    CREATE TABLE entities (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL REFERENCES entity_files(path) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        container_name TEXT,
+        line INTEGER NOT NULL,
+        line_to INTEGER NOT NULL
+    );
+    CREATE INDEX entities_path ON entities(path);
+    CREATE INDEX entities_name ON entities(name COLLATE NOCASE);
+
+    CREATE TABLE edit_history (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        session TEXT NOT NULL,
+        command TEXT NOT NULL,
+        path TEXT
+    );
+    CREATE INDEX edit_history_path ON edit_history(path);
+
+    CREATE TABLE jobs (
+        id TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        status TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL,
+        detail TEXT
+    );
+
+    CREATE TABLE sessions (
+        id TEXT PRIMARY KEY,
+        started_at INTEGER NOT NULL,
+        version TEXT NOT NULL
+    );
+
+    CREATE TABLE analytics (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        session TEXT NOT NULL,
+        event TEXT NOT NULL,
+        value REAL,
+        detail TEXT
+    );
+    CREATE INDEX analytics_event ON analytics(event, timestamp);
+    "#,
+    // 2: crash-safe jobs, sessions and supervisor state
+    r#"
+    ALTER TABLE jobs ADD COLUMN session TEXT;
+    ALTER TABLE sessions ADD COLUMN ended_at INTEGER;
+    ALTER TABLE sessions ADD COLUMN end_reason TEXT;
+
+    CREATE TABLE service_intents (
+        service TEXT PRIMARY KEY,
+        desired TEXT NOT NULL,
+        session TEXT NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE sync_sessions (
+        id TEXT PRIMARY KEY,
+        session TEXT NOT NULL,
+        spec TEXT NOT NULL,
+        status TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    "#,
+    // 3: project health score per validation run
+    r#"
+    CREATE TABLE health_samples (
+        id INTEGER PRIMARY KEY,
+        run_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        score REAL NOT NULL,
+        build_passed INTEGER,
+        test_pass_rate REAL,
+        tests_failed INTEGER,
+        lint_errors INTEGER,
+        type_errors INTEGER,
+        bundle_bytes INTEGER
+    );
+    CREATE INDEX health_samples_timestamp ON health_samples(timestamp);
+    "#,
+    // 4: full entity records for the in-memory code index
+    r#"
+    ALTER TABLE entities ADD COLUMN record TEXT;
+    "#,
+    // 5: embedding vectors for semantic search, keyed by the embedded text
+    r#"
+    CREATE TABLE embeddings (
+        model TEXT NOT NULL,
+        content_hash TEXT NOT NULL,
+        vector BLOB NOT NULL,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (model, content_hash)
+    );
+    "#,
+    // 6: cached entity records predate symbol references; drop them so files are re-parsed
+    r#"
+    DELETE FROM entity_files;
+    "#,
+    // 7: re-parse again for `className` class references and stylesheet entities
+    r#"
+    DELETE FROM entity_files;
+    "#,
+    // 8: project roots registered as workspaces, besides the default `project/`
+    r#"
+    CREATE TABLE workspaces (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        root TEXT NOT NULL UNIQUE,
+        created_at INTEGER NOT NULL
+    );
+    "#,
+    // 9: audit log (was daily JSONL files), full job records (was jobs.json), edit history by time
+    r#"
+    CREATE TABLE audit_log (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        principal TEXT NOT NULL,
+        operation TEXT NOT NULL,
+        status INTEGER NOT NULL,
+        entry TEXT NOT NULL
+    );
+    CREATE INDEX audit_log_timestamp ON audit_log(timestamp);
+    CREATE INDEX audit_log_operation ON audit_log(operation, timestamp);
+
+    ALTER TABLE jobs ADD COLUMN record TEXT;
+
+    CREATE INDEX edit_history_timestamp ON edit_history(timestamp);
+    "#,
+];
+
+// Tables reported by `/api/system/db-stats`
+const TABLES: &[&str] = &[
+    "entity_files",
+    "entities",
+    "edit_history",
+    "jobs",
+    "sessions",
+    "analytics",
+    "service_intents",
+    "sync_sessions",
+    "health_samples",
+    "embeddings",
+    "workspaces",
+    "audit_log",
+];
+
+/// Job statuses that mean the job has not finished yet.
+pub const UNFINISHED_JOB_STATUSES: &[&str] = &["queued", "running"];
+
+/// An indexed code entity, as cached per file in the `entities` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredEntity {
+    pub name: String,
+    pub kind: String,
+    pub container_name: Option<String>,
+    pub line: usize,
+    pub line_to: usize,
+    /// The parsed entity as JSON, so the code index can be restored without re-parsing;
+    /// absent in rows cached before it was added
+    pub record: Option<String>,
+}
+
+/// A job left unfinished by an earlier Galatea process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterruptedJob {
+    pub id: String,
+    pub kind: String,
+    pub previous_status: String,
+}
+
+/// A continuous sync as persisted in the `sync_sessions` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredSyncSession {
+    pub id: String,
+    pub session: String,
+    pub spec: String, // JSON, owned by dev_operation::sync
+    pub status: String,
+}
+
+/// A registered workspace, as stored in the `workspaces` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredWorkspace {
+    pub id: String,
+    pub name: String,
+    pub root: String,
+    pub created_at: i64,
+}
+
+/// A project health score sample, as stored in the `health_samples` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredHealthSample {
+    pub run_id: String,
+    pub timestamp: i64,
+    pub score: f64,
+    pub build_passed: Option<bool>,
+    pub test_pass_rate: Option<f64>,
+    pub tests_failed: Option<u32>,
+    pub lint_errors: Option<u32>,
+    pub type_errors: Option<u32>,
+    pub bundle_bytes: Option<u64>,
+}
+
+/// A row of the `edit_history` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredEdit {
+    pub timestamp: i64,
+    pub session: String,
+    pub command: String,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableStats {
+    pub name: String,
+    pub rows: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DbStats {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub schema_version: u32,
+    pub tables: Vec<TableStats>,
+}
+
+/// The metadata store at `galatea_files/galatea.db`. Validation runs (`validation_runs/<id>.json`)
+/// and runtime events (`runtime_events.jsonl`) stay in their files: reports and `tail -f` read
+/// them directly.
+pub struct Database {
+    conn: Connection,
+    path: PathBuf,
+}
+
+static DATABASE: Lazy<Mutex<Option<Database>>> = Lazy::new(|| Mutex::new(None));
+
+type Write = Box<dyn FnOnce(&mut Database) + Send>;
+
+// Writes queued with `write_later`, applied in order on a thread of their own; `None` if the
+// thread couldn't be started, in which case writes are applied by the caller
+static WRITER: Lazy<Option<Mutex<mpsc::Sender<Write>>>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel::<Write>();
+    let spawned = std::thread::Builder::new().name("galatea-db-writer".to_string()).spawn(move || {
+        for write in receiver {
+            apply_write(write);
+            PENDING_WRITES.fetch_sub(1, Ordering::SeqCst);
+        }
+    });
+    match spawned {
+        Ok(_) => Some(Mutex::new(sender)),
+        Err(e) => {
+            tracing::warn!(target: "dev_runtime::db", error = %e, "Failed to start the database writer thread, writing inline.");
+            None
+        }
+    }
+});
+static PENDING_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(not(test))]
+pub fn db_dir() -> Result<PathBuf> {
+    paths::galatea_files_dir()
+}
+
+// Tests get a database of their own rather than the one in galatea_files
+#[cfg(test)]
+pub fn db_dir() -> Result<PathBuf> {
+    static TEST_DB_DIR: Lazy<tempfile::TempDir> = Lazy::new(|| tempfile::tempdir().expect("failed to create the test database directory"));
+    Ok(TEST_DB_DIR.path().to_path_buf())
+}
+
+fn system_time_ms(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64
+}
+
+/// Runs `f` against the shared database, opening it (and applying migrations) on first use.
+/// Writes queued with `write_later` are applied first, so `f` sees them.
+pub fn with_db<T>(f: impl FnOnce(&mut Database) -> Result<T>) -> Result<T> {
+    if PENDING_WRITES.load(Ordering::SeqCst) > 0 {
+        flush_writes();
+    }
+    open_db(f)
+}
+
+/// Queues a write whose result nobody waits on (edit history, job rows, analytics) for the
+/// writer thread, so async handlers don't block on SQLite; `f` handles its own errors.
+pub fn write_later(f: impl FnOnce(&mut Database) + Send + 'static) {
+    let write: Write = Box::new(f);
+    let Some(writer) = WRITER.as_ref() else {
+        return apply_write(write);
+    };
+    PENDING_WRITES.fetch_add(1, Ordering::SeqCst);
+    if let Err(mpsc::SendError(write)) = writer.lock().unwrap_or_else(|e| e.into_inner()).send(write) {
+        PENDING_WRITES.fetch_sub(1, Ordering::SeqCst);
+        apply_write(write);
+    }
+}
+
+fn apply_write(write: Write) {
+    let opened = open_db(|db| {
+        write(db);
+        Ok(())
+    });
+    if let Err(e) = opened {
+        tracing::warn!(target: "dev_runtime::db", error = %format!("{:#}", e), "Failed to open the database for a queued write.");
+    }
+}
+
+// Waits until the writes queued so far are applied
+fn flush_writes() {
+    let Some(writer) = WRITER.as_ref() else {
+        return;
+    };
+    let (done, applied) = mpsc::channel();
+    PENDING_WRITES.fetch_add(1, Ordering::SeqCst);
+    let marker: Write = Box::new(move |_| {
+        let _ = done.send(());
+    });
+    if writer.lock().unwrap_or_else(|e| e.into_inner()).send(marker).is_err() {
+        PENDING_WRITES.fetch_sub(1, Ordering::SeqCst);
+        return;
+    }
+    // Also returns if the marker is dropped unrun, when the database can't be opened
+    let _ = applied.recv();
+}
+
+fn open_db<T>(f: impl FnOnce(&mut Database) -> Result<T>) -> Result<T> {
+    let mut guard = DATABASE.lock().map_err(|_| anyhow!("Database mutex poisoned"))?;
+    if guard.is_none() {
+        let dir = db_dir()?;
+        std::fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+        *guard = Some(Database::open(&dir.join(DB_FILE_NAME))?);
+    }
+    f(guard.as_mut().expect("database initialized above"))
+}
+
+impl Database {
+    /// Opens (or creates) the database at `path` and brings its schema up to date.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).context(format!("Failed to open database {}", path.display()))?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let mut db = Self { conn, path: path.to_path_buf() };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn schema_version(&self) -> Result<u32> {
+        Ok(self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let current = self.schema_version()? as usize;
+        if current > MIGRATIONS.len() {
+            return Err(anyhow!(
+                "Database schema version {} is newer than this Galatea build supports ({})",
+                current,
+                MIGRATIONS.len()
+            ));
+        }
+        for (index, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+            let version = index + 1;
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(sql).context(format!("Failed to apply database migration {}", version))?;
+            tx.pragma_update(None, "user_version", version as u32)?;
+            tx.commit()?;
+            tracing::info!(target: "dev_runtime::db", version, "Applied database migration.");
+        }
+        Ok(())
+    }
+
+    // --- Entity index ---
+
+    /// Returns the cached entities of a file if it hasn't changed since it was indexed.
+    pub fn cached_entities(&self, path: &str, modified: SystemTime, size: u64) -> Result<Option<Vec<StoredEntity>>> {
+        let fresh: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM entity_files WHERE path = ?1 AND modified_ms = ?2 AND size = ?3",
+                params![path, system_time_ms(modified), size as i64],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if fresh.is_none() {
+            return Ok(None);
+        }
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT name, kind, container_name, line, line_to, record FROM entities WHERE path = ?1 ORDER BY line, id",
+        )?;
+        let entities = stmt
+            .query_map(params![path], |row| {
+                Ok(StoredEntity {
+                    name: row.get(0)?,
+                    kind: row.get(1)?,
+                    container_name: row.get(2)?,
+                    line: row.get::<_, i64>(3)? as usize,
+                    line_to: row.get::<_, i64>(4)? as usize,
+                    record: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Some(entities))
+    }
+
+    /// Replaces the cached entities of a file.
+    pub fn store_entities(&mut self, path: &str, modified: SystemTime, size: u64, entities: &[StoredEntity]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM entity_files WHERE path = ?1", params![path])?;
+        tx.execute(
+            "INSERT INTO entity_files (path, modified_ms, size, indexed_at) VALUES (?1, ?2, ?3, ?4)",
+            params![path, system_time_ms(modified), size as i64, util::now_secs() as i64],
+        )?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO entities (path, name, kind, container_name, line, line_to, record) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for e in entities {
+                stmt.execute(params![path, e.name, e.kind, e.container_name, e.line as i64, e.line_to as i64, e.record])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drops cached files that no longer exist in the project.
+    pub fn retain_entity_files(&mut self, existing: &[String]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        tx.execute_batch("CREATE TEMP TABLE IF NOT EXISTS existing_files (path TEXT PRIMARY KEY); DELETE FROM existing_files;")?;
+        {
+            let mut stmt = tx.prepare_cached("INSERT OR IGNORE INTO existing_files (path) VALUES (?1)")?;
+            for path in existing {
+                stmt.execute(params![path])?;
+            }
+        }
+        let removed = tx.execute("DELETE FROM entity_files WHERE path NOT IN (SELECT path FROM existing_files)", [])?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    // --- Embeddings ---
+
+    /// The stored vectors of `model` for the given content hashes; hashes without one are left out.
+    pub fn cached_embeddings(&self, model: &str, hashes: &[String]) -> Result<HashMap<String, Vec<f32>>> {
+        let mut stmt = self.conn.prepare_cached("SELECT vector FROM embeddings WHERE model = ?1 AND content_hash = ?2")?;
+        let mut found = HashMap::new();
+        for hash in hashes {
+            let vector: Option<Vec<u8>> = stmt.query_row(params![model, hash], |row| row.get(0)).optional()?;
+            if let Some(bytes) = vector {
+                found.insert(hash.clone(), bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect());
+            }
+        }
+        Ok(found)
+    }
+
+    pub fn store_embeddings(&mut self, model: &str, vectors: &[(String, Vec<f32>)]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO embeddings (model, content_hash, vector, created_at) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (hash, vector) in vectors {
+                let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+                stmt.execute(params![model, hash, bytes, util::now_secs() as i64])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drops the vectors of `model` whose text is no longer in the project.
+    pub fn retain_embeddings(&mut self, model: &str, hashes: &[String]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        tx.execute_batch("CREATE TEMP TABLE IF NOT EXISTS current_hashes (hash TEXT PRIMARY KEY); DELETE FROM current_hashes;")?;
+        {
+            let mut stmt = tx.prepare_cached("INSERT OR IGNORE INTO current_hashes (hash) VALUES (?1)")?;
+            for hash in hashes {
+                stmt.execute(params![hash])?;
+            }
+        }
+        let removed = tx.execute(
+            "DELETE FROM embeddings WHERE model = ?1 AND content_hash NOT IN (SELECT hash FROM current_hashes)",
+            params![model],
+        )?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    // --- Edit history, jobs, sessions, analytics ---
+
+    pub fn record_edit(&self, session: &str, command: &str, path: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO edit_history (timestamp, session, command, path) VALUES (?1, ?2, ?3, ?4)",
+            params![util::now_secs() as i64, session, command, path],
+        )?;
+        Ok(())
+    }
+
+    pub fn edit_history_len(&self) -> Result<u64> {
+        let rows: i64 = self.conn.query_row("SELECT COUNT(*) FROM edit_history", [], |row| row.get(0))?;
+        Ok(rows as u64)
+    }
+
+    /// Drops the oldest edit history rows beyond the newest `keep`.
+    pub fn prune_edit_history(&self, keep: u64) -> Result<usize> {
+        let removed = self.conn.execute(
+            "DELETE FROM edit_history WHERE id <= (SELECT MAX(id) FROM edit_history) - ?1",
+            params![keep as i64],
+        )?;
+        Ok(removed)
+    }
+
+    /// Edits to `path`, or to anything under it when it is a directory, between `since` and
+    /// `until` (Unix seconds, inclusive), newest first.
+    pub fn edit_history(&self, path: Option<&str>, since: Option<i64>, until: Option<i64>, limit: usize) -> Result<Vec<StoredEdit>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT timestamp, session, command, path FROM edit_history
+             WHERE (?1 IS NULL OR path = ?1 OR substr(path, 1, length(?1) + 1) = ?1 || '/')
+             AND (?2 IS NULL OR timestamp >= ?2) AND (?3 IS NULL OR timestamp <= ?3)
+             ORDER BY timestamp DESC, id DESC LIMIT ?4",
+        )?;
+        let rows = stmt.query_map(params![path, since, until, limit as i64], |row| {
+            Ok(StoredEdit { timestamp: row.get(0)?, session: row.get(1)?, command: row.get(2)?, path: row.get(3)? })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Creates or updates a job record owned by `session`.
+    pub fn upsert_job(&self, session: &str, id: &str, kind: &str, status: &str, detail: Option<&str>) -> Result<()> {
+        let now = util::now_secs() as i64;
+        self.conn.execute(
+            "INSERT INTO jobs (id, kind, status, created_at, updated_at, detail, session) VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET status = excluded.status, updated_at = excluded.updated_at,
+             detail = excluded.detail, session = excluded.session",
+            params![id, kind, status, now, detail, session],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the full record (JSON, owned by `jobs`) of a job `upsert_job` created.
+    pub fn store_job_record(&self, id: &str, record: &str) -> Result<()> {
+        self.conn.execute("UPDATE jobs SET record = ?2 WHERE id = ?1", params![id, record])?;
+        Ok(())
+    }
+
+    /// The most recently created `limit` job records, newest first.
+    pub fn job_records(&self, limit: usize) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT record FROM jobs WHERE record IS NOT NULL ORDER BY created_at DESC, rowid DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Marks jobs that other sessions left queued or running as `interrupted`.
+    pub fn interrupt_stale_jobs(&mut self, current_session: &str) -> Result<Vec<InterruptedJob>> {
+        let tx = self.conn.transaction()?;
+        let placeholders = vec!["?"; UNFINISHED_JOB_STATUSES.len()].join(", ");
+        let filter = format!("status IN ({}) AND (session IS NULL OR session != ?)", placeholders);
+        let mut bindings: Vec<&dyn rusqlite::ToSql> = UNFINISHED_JOB_STATUSES.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+        bindings.push(&current_session);
+        let jobs = {
+            let mut stmt = tx.prepare(&format!("SELECT id, kind, status FROM jobs WHERE {} ORDER BY created_at", filter))?;
+            let rows = stmt.query_map(bindings.as_slice(), |row| {
+                Ok(InterruptedJob { id: row.get(0)?, kind: row.get(1)?, previous_status: row.get(2)? })
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        let now = util::now_secs() as i64;
+        for job in &jobs {
+            tx.execute(
+                "UPDATE jobs SET status = 'interrupted', updated_at = ?2,
+                 detail = 'Galatea restarted while the job was ' || status WHERE id = ?1",
+                params![job.id, now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(jobs)
+    }
+
+    pub fn record_session(&self, session: &str, version: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO sessions (id, started_at, version) VALUES (?1, ?2, ?3)",
+            params![session, util::now_secs() as i64, version],
+        )?;
+        Ok(())
+    }
+
+    /// Records that a session ended, e.g. with reason `shutdown`.
+    pub fn end_session(&self, session: &str, reason: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET ended_at = ?2, end_reason = ?3 WHERE id = ?1 AND ended_at IS NULL",
+            params![session, util::now_secs() as i64, reason],
+        )?;
+        Ok(())
+    }
+
+    /// Ends every other session that never recorded an end as `crashed`. Returns their ids.
+    pub fn end_stale_sessions(&mut self, current_session: &str) -> Result<Vec<String>> {
+        let tx = self.conn.transaction()?;
+        let sessions = {
+            let mut stmt = tx.prepare("SELECT id FROM sessions WHERE ended_at IS NULL AND id != ?1 ORDER BY started_at")?;
+            let rows = stmt.query_map(params![current_session], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<Vec<String>>>()?
+        };
+        tx.execute(
+            "UPDATE sessions SET ended_at = ?2, end_reason = 'crashed' WHERE ended_at IS NULL AND id != ?1",
+            params![current_session, util::now_secs() as i64],
+        )?;
+        tx.commit()?;
+        Ok(sessions)
+    }
+
+    // --- Supervisor state ---
+
+    /// Records whether `service` should be running (`desired` is `running` or `stopped`).
+    pub fn set_service_intent(&self, session: &str, service: &str, desired: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO service_intents (service, desired, session, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(service) DO UPDATE SET desired = excluded.desired, session = excluded.session,
+             updated_at = excluded.updated_at",
+            params![service, desired, session, util::now_secs() as i64],
+        )?;
+        Ok(())
+    }
+
+    pub fn service_intent(&self, service: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row("SELECT desired FROM service_intents WHERE service = ?1", params![service], |row| row.get(0))
+            .optional()?)
+    }
+
+    pub fn save_sync_session(&self, session: &str, id: &str, spec: &str, status: &str) -> Result<()> {
+        let now = util::now_secs() as i64;
+        self.conn.execute(
+            "INSERT INTO sync_sessions (id, session, spec, status, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+             ON CONFLICT(id) DO UPDATE SET session = excluded.session, spec = excluded.spec,
+             status = excluded.status, updated_at = excluded.updated_at",
+            params![id, session, spec, status, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_sync_session_status(&self, id: &str, status: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sync_sessions SET status = ?2, updated_at = ?3 WHERE id = ?1",
+            params![id, status, util::now_secs() as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Continuous syncs still marked active by sessions other than the current one.
+    pub fn stale_sync_sessions(&self, current_session: &str) -> Result<Vec<StoredSyncSession>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, session, spec, status FROM sync_sessions WHERE status = 'active' AND session != ?1 ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map(params![current_session], |row| {
+            Ok(StoredSyncSession { id: row.get(0)?, session: row.get(1)?, spec: row.get(2)?, status: row.get(3)? })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // --- Workspaces ---
+
+    pub fn add_workspace(&self, id: &str, name: &str, root: &str) -> Result<StoredWorkspace> {
+        let created_at = util::now_secs() as i64;
+        self.conn.execute(
+            "INSERT INTO workspaces (id, name, root, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, name, root, created_at],
+        )?;
+        Ok(StoredWorkspace { id: id.to_string(), name: name.to_string(), root: root.to_string(), created_at })
+    }
+
+    /// Registered workspaces, oldest first.
+    pub fn workspaces(&self) -> Result<Vec<StoredWorkspace>> {
+        let mut stmt = self.conn.prepare("SELECT id, name, root, created_at FROM workspaces ORDER BY created_at, id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(StoredWorkspace { id: row.get(0)?, name: row.get(1)?, root: row.get(2)?, created_at: row.get(3)? })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Returns whether the workspace existed.
+    pub fn remove_workspace(&self, id: &str) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM workspaces WHERE id = ?1", params![id])? > 0)
+    }
+
+    pub fn record_analytics(&self, session: &str, event: &str, value: Option<f64>, detail: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO analytics (timestamp, session, event, value, detail) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![util::now_secs() as i64, session, event, value, detail],
+        )?;
+        Ok(())
+    }
+
+    // --- Audit log ---
+
+    pub fn record_audit(&mut self, entries: &[AuditEntry]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for entry in entries {
+            tx.execute(
+                "INSERT INTO audit_log (timestamp, principal, operation, status, entry) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![entry.timestamp as i64, entry.principal, entry.operation, entry.status, serde_json::to_string(entry)?],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Audit entries matching `filter`, newest first, at most `limit` of them.
+    pub fn audit_entries(&self, filter: &AuditFilter, limit: usize) -> Result<Vec<AuditEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT entry FROM audit_log
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2) AND (?3 IS NULL OR principal = ?3)
+             AND (?4 IS NULL OR operation = ?4 OR substr(operation, 1, length(?4) + 1) = ?4 || '.')
+             AND (?5 IS NULL OR (status >= 400) = ?5)
+             ORDER BY timestamp DESC, id DESC LIMIT ?6",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                filter.since.map(|s| s as i64),
+                filter.until.map(|u| u as i64),
+                filter.principal,
+                filter.operation,
+                filter.failed,
+                limit as i64,
+            ],
+            |row| row.get::<_, String>(0),
+        )?;
+        let mut entries = Vec::new();
+        for entry in rows {
+            entries.push(serde_json::from_str(&entry?).context("Corrupt audit log entry")?);
+        }
+        Ok(entries)
+    }
+
+    // --- Health samples ---
+
+    pub fn record_health_sample(&self, sample: &StoredHealthSample) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO health_samples (run_id, timestamp, score, build_passed, test_pass_rate, tests_failed, lint_errors, type_errors, bundle_bytes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                sample.run_id,
+                sample.timestamp,
+                sample.score,
+                sample.build_passed,
+                sample.test_pass_rate,
+                sample.tests_failed,
+                sample.lint_errors,
+                sample.type_errors,
+                sample.bundle_bytes.map(|b| b as i64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The most recent `limit` health samples, newest first.
+    pub fn health_samples(&self, limit: usize) -> Result<Vec<StoredHealthSample>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, timestamp, score, build_passed, test_pass_rate, tests_failed, lint_errors, type_errors, bundle_bytes
+             FROM health_samples ORDER BY timestamp DESC, id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| {
+            Ok(StoredHealthSample {
+                run_id: row.get(0)?,
+                timestamp: row.get(1)?,
+                score: row.get(2)?,
+                build_passed: row.get(3)?,
+                test_pass_rate: row.get(4)?,
+                tests_failed: row.get(5)?,
+                lint_errors: row.get(6)?,
+                type_errors: row.get(7)?,
+                bundle_bytes: row.get::<_, Option<i64>>(8)?.map(|b| b as u64),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Row counts per table, schema version and on-disk size (including the WAL).
+    pub fn stats(&self) -> Result<DbStats> {
+        let mut tables = Vec::new();
+        for table in TABLES {
+            let rows: i64 = self
+                .conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+            tables.push(TableStats { name: table.to_string(), rows: rows as u64 });
+        }
+        let size_bytes = ["", "-wal"]
+            .iter()
+            .filter_map(|suffix| {
+                let mut file = self.path.clone().into_os_string();
+                file.push(suffix);
+                std::fs::metadata(file).ok()
+            })
+            .map(|m| m.len())
+            .sum();
+        Ok(DbStats {
+            path: self.path.clone(),
+            size_bytes,
+            schema_version: self.schema_version()?,
+            tables,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(name: &str, line: usize) -> StoredEntity {
+        StoredEntity {
+            name: name.to_string(),
+            kind: "Function".to_string(),
+            container_name: None,
+            line,
+            line_to: line + 2,
+            record: None,
+        }
+    }
+
+    #[test]
+    fn test_migrations_are_applied_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DB_FILE_NAME);
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap() as usize, MIGRATIONS.len());
+        db.record_session("s1", "0.1.0").unwrap();
+        drop(db);
+
+        // Reopening must not re-run migrations (CREATE TABLE would fail) or lose data
+        let db = Database::open(&path).unwrap();
+        let stats = db.stats().unwrap();
+        assert_eq!(stats.schema_version as usize, MIGRATIONS.len());
+        let sessions = stats.tables.iter().find(|t| t.name == "sessions").unwrap();
+        assert_eq!(sessions.rows, 1);
+        assert!(stats.size_bytes > 0);
+    }
+
+    #[test]
+    fn test_restart_interrupts_jobs_and_ends_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&dir.path().join(DB_FILE_NAME)).unwrap();
+        db.record_session("old", "0.1.0").unwrap();
+        db.upsert_job("old", "v1", "validation", "running", None).unwrap();
+        db.upsert_job("old", "v2", "validation", "passed", Some("lint passed")).unwrap();
+        db.save_sync_session("old", "s1", "{}", "active").unwrap();
+        db.save_sync_session("old", "s2", "{}", "stopped").unwrap();
+        db.record_session("new", "0.1.0").unwrap();
+        db.upsert_job("new", "v3", "validation", "running", None).unwrap();
+
+        let jobs = db.interrupt_stale_jobs("new").unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!((jobs[0].id.as_str(), jobs[0].previous_status.as_str()), ("v1", "running"));
+        assert!(db.interrupt_stale_jobs("new").unwrap().is_empty());
+
+        assert_eq!(db.end_stale_sessions("new").unwrap(), vec!["old".to_string()]);
+        assert!(db.end_stale_sessions("new").unwrap().is_empty());
+
+        let syncs = db.stale_sync_sessions("new").unwrap();
+        assert_eq!(syncs.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), vec!["s1"]);
+
+        db.set_service_intent("old", "mcp", "running").unwrap();
+        assert_eq!(db.service_intent("mcp").unwrap().as_deref(), Some("running"));
+        assert_eq!(db.service_intent("lsp").unwrap(), None);
+    }
+
+    #[test]
+    fn test_edit_history_by_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(&dir.path().join(DB_FILE_NAME)).unwrap();
+        db.record_edit("s1", "create", Some("/p/src/app/page.tsx")).unwrap();
+        db.record_edit("s1", "str_replace", Some("/p/src/app.ts")).unwrap();
+        db.record_edit("s1", "undo_edit", None).unwrap();
+        db.record_edit("s1", "insert", Some("/p/src/app/page.tsx")).unwrap();
+
+        let commands = |path: Option<&str>, since: Option<i64>| -> Vec<String> {
+            db.edit_history(path, since, None, 10).unwrap().into_iter().map(|e| e.command).collect()
+        };
+        assert_eq!(commands(Some("/p/src/app/page.tsx"), None), vec!["insert", "create"]);
+        // A directory covers what is under it, not files sharing its name as a prefix
+        assert_eq!(commands(Some("/p/src/app"), None), vec!["insert", "create"]);
+        assert_eq!(commands(None, None).len(), 4);
+        assert!(commands(None, Some(util::now_secs() as i64 + 60)).is_empty());
+    }
+
+    #[test]
+    fn test_queued_writes_are_seen_by_reads() {
+        assert!(db_dir().unwrap().starts_with(std::env::temp_dir()));
+        for i in 0..20 {
+            write_later(move |db| db.record_edit("queued", "create", Some(&format!("/queued/{}.ts", i))).unwrap());
+        }
+        let edits = with_db(|db| db.edit_history(Some("/queued"), None, None, 100)).unwrap();
+        assert_eq!(edits.len(), 20);
+        assert_eq!(edits[0].path.as_deref(), Some("/queued/19.ts"));
+    }
+
+    #[test]
+    fn test_entity_cache_invalidates_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = Database::open(&dir.path().join(DB_FILE_NAME)).unwrap();
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        assert_eq!(db.cached_entities("src/a.ts", modified, 10).unwrap(), None);
+        db.store_entities("src/a.ts", modified, 10, &[entity("b", 5), entity("a", 1)]).unwrap();
+        db.store_entities("src/old.ts", modified, 3, &[entity("gone", 1)]).unwrap();
+
+        let cached = db.cached_entities("src/a.ts", modified, 10).unwrap().unwrap();
+        assert_eq!(cached, vec![entity("a", 1), entity("b", 5)]);
+        assert_eq!(db.cached_entities("src/a.ts", modified + Duration::from_secs(1), 10).unwrap(), None);
+        assert_eq!(db.cached_entities("src/a.ts", modified, 11).unwrap(), None);
+
+        assert_eq!(db.retain_entity_files(&["src/a.ts".to_string()]).unwrap(), 1);
+        let entities = db.stats().unwrap().tables.into_iter().find(|t| t.name == "entities").unwrap();
+        assert_eq!(entities.rows, 2);
+    }
+}
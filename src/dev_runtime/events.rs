@@ -0,0 +1,365 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex};
+
+use super::util::now_secs;
+use crate::file_system::paths;
+
+const EVENT_LOG_FILE_NAME: &str = "runtime_events.jsonl";
+
+/// Service names used in the event log. MCP servers are recorded as `mcp:<id>`.
+pub const DEV_SERVER_SERVICE: &str = "nextjs_dev_server";
+pub const LSP_SERVICE: &str = "lsp";
+
+pub fn mcp_service_name(server_id: &str) -> String {
+    format!("mcp:{}", server_id)
+}
+
+/// A state change of a runtime service.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceEventKind {
+    Starting,
+    Running,
+    Stopped,
+    Failed,
+    // The Galatea process that owned the service went away without recording a stop
+    Lost,
+}
+
+impl ServiceEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServiceEventKind::Starting => "starting",
+            ServiceEventKind::Running => "running",
+            ServiceEventKind::Stopped => "stopped",
+            ServiceEventKind::Failed => "failed",
+            ServiceEventKind::Lost => "lost",
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        matches!(self, ServiceEventKind::Starting | ServiceEventKind::Running)
+    }
+}
+
+/// One line of `galatea_files/runtime_events.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServiceEvent {
+    pub seq: u64,
+    pub timestamp: u64,  // Unix seconds
+    pub session: String, // Identifies the Galatea process that recorded the event
+    pub service: String,
+    pub kind: ServiceEventKind,
+    pub detail: Option<String>,
+}
+
+/// State of a service, reconstructed by replaying its events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceState {
+    pub service: String,
+    pub status: ServiceEventKind,
+    pub since: u64,                 // Timestamp of the last state change
+    pub running_since: Option<u64>, // Set while the service is running
+    pub uptime_secs: u64,           // Accumulated time spent running, including the current run
+    pub start_count: u32,
+    pub restart_count: u32,
+    pub last_error: Option<String>,
+    pub last_detail: Option<String>,
+}
+
+struct EventLog {
+    events: Vec<ServiceEvent>,
+    next_seq: u64,
+    path: Option<PathBuf>,
+}
+
+impl EventLog {
+    fn append(&mut self, service: &str, kind: ServiceEventKind, detail: Option<String>, timestamp: u64) -> ServiceEvent {
+        let event = ServiceEvent {
+            seq: self.next_seq,
+            timestamp,
+            session: SESSION_ID.clone(),
+            service: service.to_string(),
+            kind,
+            detail,
+        };
+        self.next_seq += 1;
+        if let Some(path) = &self.path {
+            persist_later(path.clone(), event.clone());
+        }
+        self.events.push(event.clone());
+        event
+    }
+}
+
+static SESSION_ID: Lazy<String> = Lazy::new(|| uuid::Uuid::new_v4().simple().to_string());
+
+/// Identifier of this Galatea process, shared by everything it records.
+pub fn session_id() -> &'static str {
+    &SESSION_ID
+}
+
+static EVENT_LOG: Lazy<Mutex<EventLog>> = Lazy::new(|| Mutex::new(load_event_log()));
+
+type PendingEvent = (PathBuf, ServiceEvent);
+
+// Events to append to the log file, written in order on a thread of their own so recording one
+// never does file I/O on the caller's (often async) thread; `None` if the thread couldn't be
+// started, in which case events are written by the caller
+static APPENDER: Lazy<Option<Mutex<mpsc::Sender<PendingEvent>>>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel::<PendingEvent>();
+    let spawned = std::thread::Builder::new().name("galatea-event-writer".to_string()).spawn(move || {
+        for (path, event) in receiver {
+            persist(&path, &event);
+        }
+    });
+    match spawned {
+        Ok(_) => Some(Mutex::new(sender)),
+        Err(e) => {
+            tracing::warn!(target: "dev_runtime::events", error = %e, "Failed to start the event writer thread, writing inline.");
+            None
+        }
+    }
+});
+
+pub fn event_log_path() -> Result<PathBuf> {
+    Ok(paths::galatea_files_dir()?.join(EVENT_LOG_FILE_NAME))
+}
+
+fn persist_later(path: PathBuf, event: ServiceEvent) {
+    let Some(appender) = APPENDER.as_ref() else {
+        return persist(&path, &event);
+    };
+    if let Err(mpsc::SendError((path, event))) = appender.lock().unwrap_or_else(|e| e.into_inner()).send((path, event)) {
+        persist(&path, &event);
+    }
+}
+
+fn persist(path: &PathBuf, event: &ServiceEvent) {
+    if let Err(e) = append_to_file(path, event) {
+        tracing::warn!(target: "dev_runtime::events", error = ?e, "Failed to persist runtime event.");
+    }
+}
+
+fn append_to_file(path: &PathBuf, event: &ServiceEvent) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create galatea_files directory")?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("Failed to open {}", path.display()))?;
+    let line = serde_json::to_string(event).context("Failed to serialize runtime event")?;
+    writeln!(file, "{}", line).context(format!("Failed to append to {}", path.display()))?;
+    Ok(())
+}
+
+// Malformed lines (e.g. a write cut short by a crash) are skipped
+fn parse_events(content: &str) -> Vec<ServiceEvent> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+// Loads the persisted log and closes out services a previous Galatea process left active
+fn load_event_log() -> EventLog {
+    let path = match event_log_path() {
+        Ok(path) => Some(path),
+        Err(e) => {
+            tracing::warn!(target: "dev_runtime::events", error = ?e, "Runtime events will not be persisted.");
+            None
+        }
+    };
+    let events = path
+        .as_ref()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .map(|content| parse_events(&content))
+        .unwrap_or_default();
+    let next_seq = events.last().map_or(1, |e| e.seq + 1);
+    let mut log = EventLog { events, next_seq, path };
+
+    for (service, timestamp) in orphaned_services(&log.events, &SESSION_ID) {
+        log.append(
+            &service,
+            ServiceEventKind::Lost,
+            Some("Galatea restarted while the service was active".to_string()),
+            timestamp,
+        );
+    }
+    if !log.events.is_empty() {
+        tracing::info!(target: "dev_runtime::events", events = log.events.len(), "Runtime event log loaded.");
+    }
+    log
+}
+
+// Services still active in an earlier session. The stop is dated at the last event that
+// session recorded, since that is the last time it was known to be alive.
+fn orphaned_services(events: &[ServiceEvent], current_session: &str) -> Vec<(String, u64)> {
+    let mut last_seen_per_session: BTreeMap<&str, u64> = BTreeMap::new();
+    for event in events {
+        let last_seen = last_seen_per_session.entry(event.session.as_str()).or_default();
+        *last_seen = (*last_seen).max(event.timestamp);
+    }
+    let mut last_event_per_service: BTreeMap<&str, &ServiceEvent> = BTreeMap::new();
+    for event in events {
+        last_event_per_service.insert(event.service.as_str(), event);
+    }
+    last_event_per_service
+        .into_values()
+        .filter(|event| event.kind.is_active() && event.session != current_session)
+        .map(|event| (event.service.clone(), last_seen_per_session[event.session.as_str()]))
+        .collect()
+}
+
+/// Appends a state change for `service` to the event log.
+pub fn record_event(service: &str, kind: ServiceEventKind, detail: Option<String>) {
+    let mut log = match EVENT_LOG.lock() {
+        Ok(log) => log,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let event = log.append(service, kind, detail, now_secs());
+    tracing::debug!(target: "dev_runtime::events", service = %event.service, kind = event.kind.as_str(), seq = event.seq, "Runtime event recorded.");
+}
+
+/// Returns recorded events, oldest first, optionally filtered by service and by sequence number.
+pub fn events(service: Option<&str>, after_seq: Option<u64>) -> Vec<ServiceEvent> {
+    let log = match EVENT_LOG.lock() {
+        Ok(log) => log,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    log.events
+        .iter()
+        .filter(|e| service.is_none_or(|s| e.service == s))
+        .filter(|e| after_seq.is_none_or(|seq| e.seq > seq))
+        .cloned()
+        .collect()
+}
+
+/// Current state of every service that has ever recorded an event.
+pub fn service_states() -> Vec<ServiceState> {
+    replay(&events(None, None), now_secs())
+}
+
+/// Folds events into per-service state. `now` closes the uptime of services still running.
+pub fn replay(events: &[ServiceEvent], now: u64) -> Vec<ServiceState> {
+    let mut states: BTreeMap<String, ServiceState> = BTreeMap::new();
+    for event in events {
+        let state = states.entry(event.service.clone()).or_insert_with(|| ServiceState {
+            service: event.service.clone(),
+            status: event.kind,
+            since: event.timestamp,
+            running_since: None,
+            uptime_secs: 0,
+            start_count: 0,
+            restart_count: 0,
+            last_error: None,
+            last_detail: None,
+        });
+
+        if let Some(running_since) = state.running_since.take() {
+            if event.kind != ServiceEventKind::Running {
+                state.uptime_secs += event.timestamp.saturating_sub(running_since);
+            } else {
+                state.running_since = Some(running_since);
+            }
+        }
+
+        match event.kind {
+            ServiceEventKind::Starting => {
+                if state.start_count > 0 {
+                    state.restart_count += 1;
+                }
+                state.start_count += 1;
+            }
+            ServiceEventKind::Running => {
+                if state.running_since.is_none() {
+                    state.running_since = Some(event.timestamp);
+                }
+            }
+            ServiceEventKind::Failed => state.last_error = event.detail.clone(),
+            ServiceEventKind::Stopped | ServiceEventKind::Lost => {}
+        }
+        state.status = event.kind;
+        state.since = event.timestamp;
+        state.last_detail = event.detail.clone();
+    }
+
+    states
+        .into_values()
+        .map(|mut state| {
+            if let Some(running_since) = state.running_since {
+                state.uptime_secs += now.saturating_sub(running_since);
+            }
+            state
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(seq: u64, timestamp: u64, session: &str, service: &str, kind: ServiceEventKind) -> ServiceEvent {
+        ServiceEvent {
+            seq,
+            timestamp,
+            session: session.to_string(),
+            service: service.to_string(),
+            kind,
+            detail: (kind == ServiceEventKind::Failed).then(|| "exit status 1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_replay_accounts_uptime_and_restarts() {
+        use ServiceEventKind::*;
+        let events = vec![
+            event(1, 100, "a", DEV_SERVER_SERVICE, Starting),
+            event(2, 110, "a", DEV_SERVER_SERVICE, Running),
+            event(3, 150, "a", DEV_SERVER_SERVICE, Failed),
+            event(4, 160, "a", DEV_SERVER_SERVICE, Starting),
+            event(5, 170, "a", DEV_SERVER_SERVICE, Running),
+            event(6, 120, "a", LSP_SERVICE, Starting),
+        ];
+        let states = replay(&events, 200);
+        assert_eq!(states.len(), 2);
+
+        let dev = states.iter().find(|s| s.service == DEV_SERVER_SERVICE).unwrap();
+        assert_eq!(dev.status, Running);
+        assert_eq!(dev.uptime_secs, 40 + 30);
+        assert_eq!(dev.start_count, 2);
+        assert_eq!(dev.restart_count, 1);
+        assert_eq!(dev.running_since, Some(170));
+        assert_eq!(dev.last_error.as_deref(), Some("exit status 1"));
+
+        let lsp = states.iter().find(|s| s.service == LSP_SERVICE).unwrap();
+        assert_eq!(lsp.status, Starting);
+        assert_eq!(lsp.uptime_secs, 0);
+    }
+
+    #[test]
+    fn test_orphaned_services_from_previous_session() {
+        use ServiceEventKind::*;
+        let content = [
+            serde_json::to_string(&event(1, 100, "old", DEV_SERVER_SERVICE, Running)).unwrap(),
+            serde_json::to_string(&event(2, 130, "old", "mcp:project", Stopped)).unwrap(),
+            "{\"seq\": 3, \"truncated".to_string(),
+        ]
+        .join("\n");
+        let events = parse_events(&content);
+        assert_eq!(events.len(), 2);
+
+        let orphaned = orphaned_services(&events, "new");
+        assert_eq!(orphaned, vec![(DEV_SERVER_SERVICE.to_string(), 130)]);
+        assert!(orphaned_services(&events, "old").is_empty());
+    }
+}
@@ -0,0 +1,523 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::sync::{oneshot, watch, Semaphore};
+
+use super::quotas::{self, QuotaMetric};
+use super::util::now_secs;
+use super::{crash, db, events, workspaces};
+use crate::terminal::stream::{self, ProcessEvent};
+
+// Output beyond this is cut from the front of a job's stdout and stderr (failures are at the end)
+const MAX_OUTPUT_BYTES: usize = 256 * 1024;
+// Finished jobs kept for GET /api/jobs; the oldest are dropped first
+const MAX_FINISHED_JOBS: usize = 50;
+// Time a cancelled job's processes get to exit after SIGTERM before they are killed
+const KILL_GRACE: Duration = Duration::from_secs(5);
+// In-process jobs running at once; the others wait in the queue
+const MAX_RUNNING_TASKS: usize = 2;
+// Output kept per job in the metadata store, from the end
+const PERSISTED_OUTPUT_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// An in-process job waiting for one of the others to finish
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    /// Queued or running when Galatea stopped
+    Interrupted,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Interrupted => "interrupted",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// How far an in-process job has got, as it last reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub done: u64,
+    /// `None` when the job can't tell how much there is
+    pub total: Option<u64>,
+    /// What it is doing, e.g. `Generating project_mcp`
+    pub message: Option<String>,
+}
+
+/// A background process or in-process task, and what it has printed so far.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    /// What started the job, e.g. `script`, `index` or `mcp_generation`
+    pub kind: String,
+    /// The command line, or what an in-process job does
+    pub description: String,
+    pub status: JobStatus,
+    /// When it started running, or while queued, when it was submitted
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    /// `None` while running, or when the process was killed by a signal
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Reported by in-process jobs while they run
+    pub progress: Option<JobProgress>,
+    /// Why an in-process job failed, or was interrupted
+    pub error: Option<String>,
+    // Leads the job's process group, so cancelling reaches everything it started
+    #[serde(skip)]
+    pid: Option<u32>,
+}
+
+static JOBS: Lazy<Mutex<BTreeMap<String, Job>>> = Lazy::new(|| Mutex::new(load_persisted()));
+
+// Cancellation signals of unfinished in-process jobs
+static CANCELLERS: Lazy<Mutex<HashMap<String, watch::Sender<bool>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static TASK_SLOTS: Semaphore = Semaphore::const_new(MAX_RUNNING_TASKS);
+
+fn jobs() -> std::sync::MutexGuard<'static, BTreeMap<String, Job>> {
+    JOBS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn new_id() -> String {
+    format!("{}-{}", now_secs(), &uuid::Uuid::new_v4().simple().to_string()[..8])
+}
+
+// The jobs an earlier Galatea process left in the metadata store; unfinished ones are marked interrupted
+fn load_persisted() -> BTreeMap<String, Job> {
+    let records = match db::with_db(|db| db.job_records(MAX_FINISHED_JOBS)) {
+        Ok(records) => records,
+        Err(e) => {
+            tracing::warn!(target: "dev_runtime::jobs", error = ?e, "Failed to load earlier jobs.");
+            return BTreeMap::new();
+        }
+    };
+    let mut jobs: Vec<Job> = records.iter().filter_map(|record| serde_json::from_str(record).ok()).collect();
+    for job in jobs.iter_mut().filter(|j| !j.status.is_finished()) {
+        job.error = Some(format!("Galatea stopped while the job was {}", job.status.as_str()));
+        job.status = JobStatus::Interrupted;
+        record_job(job);
+    }
+    jobs.into_iter().map(|job| (job.id.clone(), job)).collect()
+}
+
+// Keeps the tail of an output buffer, starting at a line
+fn output_tail(buffer: &str, max: usize) -> String {
+    if buffer.len() <= max {
+        return buffer.to_string();
+    }
+    let start = buffer.len() - max;
+    let cut = buffer[start..].find('\n').map_or(buffer.len(), |i| start + i + 1);
+    buffer[cut..].to_string()
+}
+
+// Appends a line, dropping whole lines from the front once the buffer is over the cap
+fn append_line(buffer: &mut String, line: &str) {
+    buffer.push_str(line);
+    buffer.push('\n');
+    if buffer.len() > MAX_OUTPUT_BYTES {
+        let excess = buffer.len() - MAX_OUTPUT_BYTES;
+        let cut = buffer[excess..].find('\n').map_or(buffer.len(), |i| excess + i + 1);
+        buffer.drain(..cut);
+    }
+}
+
+// Mirrors the job into the metadata store on each status change, so a restart marks it
+// interrupted and finished jobs survive one
+fn record_job(job: &Job) {
+    let detail = match (&job.error, job.exit_code) {
+        (Some(error), _) => Some(format!("{}: {}", job.description, error)),
+        (None, Some(code)) => Some(format!("{} (exit {})", job.description, code)),
+        (None, None) => None,
+    };
+    let detail = detail.unwrap_or_else(|| job.description.clone());
+    let record = Job {
+        stdout: output_tail(&job.stdout, PERSISTED_OUTPUT_BYTES),
+        stderr: output_tail(&job.stderr, PERSISTED_OUTPUT_BYTES),
+        ..job.clone()
+    };
+    db::write_later(move |db| {
+        let recorded = serde_json::to_string(&record).map_err(anyhow::Error::from).and_then(|json| {
+            db.upsert_job(events::session_id(), &record.id, &record.kind, record.status.as_str(), Some(&detail))?;
+            db.store_job_record(&record.id, &json)
+        });
+        if let Err(e) = recorded {
+            tracing::debug!(target: "dev_runtime::jobs", error = ?e, "Failed to record job.");
+        }
+    });
+}
+
+// Drops the oldest finished jobs beyond the cap
+fn prune(jobs: &mut BTreeMap<String, Job>) {
+    let mut finished: Vec<(u64, String)> = jobs
+        .values()
+        .filter(|j| j.status.is_finished())
+        .map(|j| (j.started_at, j.id.clone()))
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    for (_, id) in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+        jobs.remove(id);
+    }
+}
+
+/// Starts `cmd` as a background job in its own process group and returns the job id. Output
+/// is collected until the process exits; CPU time is charged to the caller's quota then.
+pub fn spawn(kind: &str, description: String, mut cmd: Command) -> Result<String> {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    let process = stream::spawn_streaming(cmd)?;
+    let id = new_id();
+    let job = Job {
+        id: id.clone(),
+        kind: kind.to_string(),
+        description,
+        status: JobStatus::Running,
+        started_at: now_secs(),
+        finished_at: None,
+        exit_code: None,
+        duration_ms: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        progress: None,
+        error: None,
+        pid: process.pid,
+    };
+    record_job(&job);
+    tracing::info!(target: "dev_runtime::jobs", job_id = %id, command = %job.description, "Started job.");
+    jobs().insert(id.clone(), job);
+
+    let cpu_before = quotas::children_cpu_seconds();
+    let job_id = id.clone();
+    let mut events = process.events;
+    // Charged to whoever started the job, after its request has returned
+    let principals = quotas::current_principals();
+    tokio::spawn(quotas::scope(principals, async move {
+        let _operation = crash::track_operation(format!("job {}", job_id));
+        while let Some(event) = events.recv().await {
+            let mut jobs = jobs();
+            let Some(job) = jobs.get_mut(&job_id) else { return };
+            match event {
+                ProcessEvent::Stdout(line) => append_line(&mut job.stdout, &line),
+                ProcessEvent::Stderr(line) => append_line(&mut job.stderr, &line),
+                ProcessEvent::Exit { code, duration_ms } => {
+                    if job.status == JobStatus::Running {
+                        job.status = if code == Some(0) { JobStatus::Succeeded } else { JobStatus::Failed };
+                    }
+                    job.exit_code = code;
+                    job.duration_ms = Some(duration_ms);
+                    job.finished_at = Some(now_secs());
+                    record_job(job);
+                    tracing::info!(target: "dev_runtime::jobs", job_id = %job_id, status = job.status.as_str(), "Job finished.");
+                    prune(&mut jobs);
+                }
+            }
+        }
+        quotas::charge(QuotaMetric::CpuSeconds, quotas::children_cpu_seconds() - cpu_before);
+    }));
+    Ok(id)
+}
+
+/// Handed to an in-process job, to report progress and notice cancellation.
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    id: String,
+    cancelled: watch::Receiver<bool>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Reports that `done` of `total` steps (when known) are done, and what is being done.
+    pub fn progress(&self, done: u64, total: Option<u64>, message: impl Into<String>) {
+        if let Some(job) = jobs().get_mut(&self.id) {
+            job.progress = Some(JobProgress { done, total, message: Some(message.into()) });
+        }
+    }
+
+    /// Appends a line to the job's output.
+    pub fn log(&self, line: &str) {
+        if let Some(job) = jobs().get_mut(&self.id) {
+            append_line(&mut job.stdout, line);
+        }
+    }
+
+    /// Whether the job was cancelled. Cancelling drops the job's future at its next await, so
+    /// only blocking work (e.g. in `spawn_blocking`) needs to check this between steps.
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+}
+
+// Records how an in-process job ended: with its result, or `None` when it was cancelled
+fn finish_task(id: &str, outcome: Option<Result<String>>, started: Option<Instant>) {
+    CANCELLERS.lock().unwrap_or_else(|e| e.into_inner()).remove(id);
+    let mut jobs = jobs();
+    let Some(job) = jobs.get_mut(id) else { return };
+    match outcome {
+        Some(Ok(summary)) => {
+            job.status = JobStatus::Succeeded;
+            if !summary.is_empty() {
+                append_line(&mut job.stdout, &summary);
+            }
+        }
+        Some(Err(e)) => {
+            job.status = JobStatus::Failed;
+            job.error = Some(format!("{:#}", e));
+        }
+        None => job.status = JobStatus::Cancelled,
+    }
+    job.finished_at = Some(now_secs());
+    job.duration_ms = started.map(|s| s.elapsed().as_millis() as u64);
+    record_job(job);
+    tracing::info!(target: "dev_runtime::jobs", job_id = %id, status = job.status.as_str(), "Job finished.");
+    prune(&mut jobs);
+}
+
+/// Queues `task` as an in-process job and returns the job id. Two such jobs run at once; the
+/// others stay `queued` until one finishes. The task gets a `JobHandle` for reporting
+/// progress; a summary it returns is added to the job's output, an error fails the job.
+pub fn submit<F, Fut>(kind: &str, description: String, task: F) -> String
+where
+    F: FnOnce(JobHandle) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String>> + Send + 'static,
+{
+    let id = new_id();
+    let job = Job {
+        id: id.clone(),
+        kind: kind.to_string(),
+        description,
+        status: JobStatus::Queued,
+        started_at: now_secs(),
+        finished_at: None,
+        exit_code: None,
+        duration_ms: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        progress: None,
+        error: None,
+        pid: None,
+    };
+    record_job(&job);
+    let (cancel, mut cancelled) = watch::channel(false);
+    CANCELLERS.lock().unwrap_or_else(|e| e.into_inner()).insert(id.clone(), cancel);
+    jobs().insert(id.clone(), job);
+
+    let handle = JobHandle { id: id.clone(), cancelled: cancelled.clone() };
+    let job_id = id.clone();
+    // The task works in the submitting request's workspace and is charged to its principals
+    let (principals, root) = (quotas::current_principals(), workspaces::scoped_root());
+    tokio::spawn(quotas::scope(principals, workspaces::rescope(root, async move {
+        let _operation = crash::track_operation(format!("job {}", job_id));
+        let permit = tokio::select! {
+            permit = TASK_SLOTS.acquire() => permit.ok(),
+            _ = cancelled.wait_for(|c| *c) => None,
+        };
+        let Some(_permit) = permit else { return finish_task(&job_id, None, None) };
+        {
+            let mut jobs = jobs();
+            let Some(job) = jobs.get_mut(&job_id) else { return };
+            job.status = JobStatus::Running;
+            job.started_at = now_secs();
+            record_job(job);
+        }
+        tracing::info!(target: "dev_runtime::jobs", job_id = %job_id, "Started job.");
+        let started = Instant::now();
+        let outcome = tokio::select! {
+            result = task(handle) => Some(result),
+            _ = cancelled.wait_for(|c| *c) => None,
+        };
+        finish_task(&job_id, outcome, Some(started));
+    })));
+    id
+}
+
+/// Runs `task` as an in-process job (see `submit`) and waits for the value it produces, for
+/// callers that need the result but should still show up in `/api/jobs`. Fails when the task
+/// fails or the job is cancelled.
+pub async fn run<T, F, Fut>(kind: &str, description: String, task: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(JobHandle) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(T, String)>> + Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    let id = submit(kind, description, move |job| async move {
+        match task(job).await {
+            Ok((value, summary)) => {
+                let _ = sender.send(Ok(value));
+                Ok(summary)
+            }
+            Err(e) => {
+                let message = format!("{:#}", e);
+                let _ = sender.send(Err(anyhow!(message.clone())));
+                Err(anyhow!(message))
+            }
+        }
+    });
+    receiver.await.unwrap_or_else(|_| Err(anyhow!("Job {} was cancelled", id)))
+}
+
+pub fn get(id: &str) -> Option<Job> {
+    jobs().get(id).cloned()
+}
+
+/// Every job still held, newest first.
+pub fn list() -> Vec<Job> {
+    let mut jobs: Vec<Job> = jobs().values().cloned().collect();
+    jobs.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| b.id.cmp(&a.id)));
+    jobs
+}
+
+/// Cancels a queued or running job. A process job's whole process group gets SIGTERM, then
+/// SIGKILL if it is still running after a grace period; an in-process job is dropped at its
+/// next await. Returns the job, or `None` if there is no such job. Finished jobs are returned
+/// unchanged.
+pub fn cancel(id: &str) -> Option<Job> {
+    let mut jobs = jobs();
+    let job = jobs.get_mut(id)?;
+    if job.status.is_finished() {
+        return Some(job.clone());
+    }
+    job.status = JobStatus::Cancelled;
+    record_job(job);
+    tracing::info!(target: "dev_runtime::jobs", job_id = %id, "Cancelling job.");
+    if let Some(cancel) = CANCELLERS.lock().unwrap_or_else(|e| e.into_inner()).get(id) {
+        let _ = cancel.send(true);
+    }
+    #[cfg(unix)]
+    if let Some(pid) = job.pid {
+        stream::signal_process_group(pid, libc::SIGTERM);
+        let id = id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(KILL_GRACE).await;
+            // Only while the exit hasn't been seen, so a reused pid is never signalled
+            if get(&id).is_some_and(|j| j.finished_at.is_none()) {
+                stream::signal_process_group(pid, libc::SIGKILL);
+            }
+        });
+    }
+    Some(job.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_job_runs_and_cancel_kills_process_group() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo started; sleep 30 & wait"]);
+        let id = spawn("script", "sleep".to_string(), cmd).unwrap();
+        for _ in 0..50 {
+            if get(&id).unwrap().stdout.contains("started") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        assert_eq!(cancel(&id).unwrap().status, JobStatus::Cancelled);
+        for _ in 0..100 {
+            if get(&id).unwrap().finished_at.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let job = get(&id).unwrap();
+        assert_eq!((job.status, job.stdout.as_str()), (JobStatus::Cancelled, "started\n"));
+        assert!(job.finished_at.is_some(), "the background sleep should have been killed with its shell");
+        assert!(cancel("missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_task_jobs_report_progress_and_cancel() {
+        let sum = run("test", "add".to_string(), |job| async move {
+            job.progress(1, Some(1), "adding");
+            Ok((2 + 2, "added".to_string()))
+        })
+        .await
+        .unwrap();
+        assert_eq!(sum, 4);
+        let failed = run::<(), _, _>("test", "fail".to_string(), |_| async { anyhow::bail!("no luck") }).await;
+        assert_eq!(failed.unwrap_err().to_string(), "no luck");
+
+        let id = submit("test", "wait".to_string(), |job| async move {
+            job.progress(0, None, "waiting");
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(String::new())
+        });
+        for _ in 0..50 {
+            if get(&id).unwrap().progress.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(cancel(&id).unwrap().status, JobStatus::Cancelled);
+        for _ in 0..50 {
+            if get(&id).unwrap().finished_at.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let job = get(&id).unwrap();
+        assert_eq!((job.status, job.progress.and_then(|p| p.message).as_deref()), (JobStatus::Cancelled, Some("waiting")));
+        assert!(job.finished_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_task_jobs_keep_the_workspace_and_principals_of_their_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let principals = vec!["session:agent-1".to_string()];
+        let (root, charged) = quotas::scope(
+            principals.clone(),
+            workspaces::scope(dir.path().to_path_buf(), async {
+                run("test", "where".to_string(), |_| async {
+                    let root = crate::file_system::paths::get_project_root()?;
+                    Ok(((root, quotas::current_principals()), String::new()))
+                })
+                .await
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(root, dir.path());
+        assert_eq!(charged, principals);
+    }
+
+    #[test]
+    fn test_append_line_keeps_the_tail() {
+        let mut buffer = String::new();
+        let line = "x".repeat(1000);
+        for _ in 0..300 {
+            append_line(&mut buffer, &line);
+        }
+        assert!(buffer.len() <= MAX_OUTPUT_BYTES);
+        assert!(buffer.starts_with('x') && buffer.ends_with("x\n"));
+    }
+}
@@ -0,0 +1,326 @@
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use super::util::now_secs;
+use super::{db, log};
+use crate::dev_setup::config_files;
+
+const DEFAULT_WARNING_RATIO: f64 = 0.8;
+const DEFAULT_EDIT_HISTORY_MAX_ROWS: u64 = 50_000;
+const DEFAULT_DISK_WARNING_RATIO: f64 = 0.9;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const MONITOR_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_STORED_EVENTS: usize = 200;
+
+/// Response header carrying one `<code>; used=<n>; limit=<n>` value per active warning.
+pub const WARNING_HEADER: &str = "x-galatea-limit-warning";
+
+/// Structured code of a limit clients are approaching (or have hit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitCode {
+    RateLimitNearlyExhausted,
+    RateLimitExceeded,
+    EditHistoryNearCap,
+    DiskNearlyFull,
+    LogBufferDropping,
+}
+
+impl LimitCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitCode::RateLimitNearlyExhausted => "rate_limit_nearly_exhausted",
+            LimitCode::RateLimitExceeded => "rate_limit_exceeded",
+            LimitCode::EditHistoryNearCap => "edit_history_near_cap",
+            LimitCode::DiskNearlyFull => "disk_nearly_full",
+            LimitCode::LogBufferDropping => "log_buffer_dropping",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitWarning {
+    pub code: LimitCode,
+    pub used: u64,
+    pub limit: u64,
+    pub message: String,
+}
+
+impl LimitWarning {
+    /// Value of an `X-Galatea-Limit-Warning` header, e.g. `disk_nearly_full; used=93; limit=100`.
+    pub fn header_value(&self) -> String {
+        format!("{}; used={}; limit={}", self.code.as_str(), self.used, self.limit)
+    }
+}
+
+/// A limit warning being raised or cleared.
+#[derive(Debug, Clone)]
+pub struct LimitEvent {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub raised: bool,
+    pub warning: LimitWarning,
+}
+
+/// Limits from config.toml, read once at startup.
+#[derive(Debug, Clone)]
+pub struct LimitSettings {
+    /// Fraction of a limit at which clients are warned (`limit_warning_ratio`)
+    pub warning_ratio: f64,
+    /// Requests per client per minute on `/api` (`rate_limit_per_minute`); unset disables it
+    pub rate_limit_per_minute: Option<u32>,
+    /// Edit history rows kept before the oldest are pruned (`edit_history_max_rows`)
+    pub edit_history_max_rows: u64,
+    /// Entries the in-memory log store holds (`log_buffer_capacity`)
+    pub log_buffer_capacity: usize,
+    /// Disk usage fraction that counts as nearly full (`disk_warning_ratio`)
+    pub disk_warning_ratio: f64,
+}
+
+impl LimitSettings {
+    fn load() -> Self {
+        fn value<T: std::str::FromStr>(key: &str) -> Option<T> {
+            config_files::get_config_value(key).and_then(|v| v.trim().parse().ok())
+        }
+        let ratio = |key: &str, default: f64| value::<f64>(key).filter(|r| *r > 0.0 && *r <= 1.0).unwrap_or(default);
+        Self {
+            warning_ratio: ratio("limit_warning_ratio", DEFAULT_WARNING_RATIO),
+            rate_limit_per_minute: value("rate_limit_per_minute").filter(|n| *n > 0),
+            edit_history_max_rows: value("edit_history_max_rows").filter(|n| *n > 0).unwrap_or(DEFAULT_EDIT_HISTORY_MAX_ROWS),
+            log_buffer_capacity: value("log_buffer_capacity").filter(|n| *n > 0).unwrap_or(log::DEFAULT_LOG_BUFFER_CAPACITY),
+            disk_warning_ratio: ratio("disk_warning_ratio", DEFAULT_DISK_WARNING_RATIO),
+        }
+    }
+}
+
+static SETTINGS: Lazy<LimitSettings> = Lazy::new(LimitSettings::load);
+
+pub fn settings() -> &'static LimitSettings {
+    &SETTINGS
+}
+
+pub fn edit_history_cap() -> u64 {
+    SETTINGS.edit_history_max_rows
+}
+
+struct EventStore {
+    events: VecDeque<LimitEvent>,
+    next_seq: u64,
+    active: Vec<LimitWarning>,
+    // Log entries dropped as of the previous check, to tell whether dropping is ongoing
+    last_dropped: u64,
+}
+
+static EVENTS: Lazy<Mutex<EventStore>> = Lazy::new(|| {
+    Mutex::new(EventStore { events: VecDeque::new(), next_seq: 1, active: Vec::new(), last_dropped: 0 })
+});
+
+static EVENT_CHANNEL: Lazy<broadcast::Sender<LimitEvent>> = Lazy::new(|| broadcast::channel(64).0);
+
+fn record(store: &mut EventStore, warning: LimitWarning, raised: bool) {
+    let event = LimitEvent { seq: store.next_seq, timestamp: now_secs(), raised, warning };
+    store.next_seq += 1;
+    if raised {
+        tracing::warn!(target: "dev_runtime::limits", code = event.warning.code.as_str(), used = event.warning.used, limit = event.warning.limit, "{}", event.warning.message);
+    } else {
+        tracing::info!(target: "dev_runtime::limits", code = event.warning.code.as_str(), "Limit warning cleared.");
+    }
+    if store.events.len() >= MAX_STORED_EVENTS {
+        store.events.pop_front();
+    }
+    store.events.push_back(event.clone());
+    // No subscribers is fine; events are kept for polling
+    let _ = EVENT_CHANNEL.send(event);
+}
+
+/// Limit events, oldest first, after sequence number `after_seq` when given.
+pub fn events(after_seq: Option<u64>) -> Vec<LimitEvent> {
+    let store = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    store.events.iter().filter(|e| after_seq.is_none_or(|seq| e.seq > seq)).cloned().collect()
+}
+
+pub fn subscribe() -> broadcast::Receiver<LimitEvent> {
+    EVENT_CHANNEL.subscribe()
+}
+
+/// Process-wide warnings as of the last check (edit history, disk, log buffer).
+pub fn active_warnings() -> Vec<LimitWarning> {
+    EVENTS.lock().unwrap_or_else(|e| e.into_inner()).active.clone()
+}
+
+fn near(used: u64, limit: u64, ratio: f64) -> bool {
+    limit > 0 && used as f64 >= limit as f64 * ratio
+}
+
+// Used and total disk space in KiB of the filesystem holding `path`, from `df`
+fn disk_usage(path: &Path) -> Option<(u64, u64)> {
+    let output = std::process::Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = stdout.lines().nth(1)?.split_whitespace().collect();
+    let used: u64 = fields.get(2)?.parse().ok()?;
+    let available: u64 = fields.get(3)?.parse().ok()?;
+    Some((used, used + available))
+}
+
+/// Re-evaluates the process-wide limits, raising and clearing warnings as they change.
+pub fn check(disk_path: &Path) -> Vec<LimitWarning> {
+    let settings = settings();
+    let mut warnings = Vec::new();
+
+    if let Ok(rows) = db::with_db(|db| db.edit_history_len()) {
+        let cap = settings.edit_history_max_rows;
+        if near(rows, cap, settings.warning_ratio) {
+            warnings.push(LimitWarning {
+                code: LimitCode::EditHistoryNearCap,
+                used: rows,
+                limit: cap,
+                message: format!("Edit history holds {} of {} rows; the oldest entries are pruned beyond that.", rows, cap),
+            });
+        }
+    }
+
+    if let Some((used, total)) = disk_usage(disk_path) {
+        if near(used, total, settings.disk_warning_ratio) {
+            warnings.push(LimitWarning {
+                code: LimitCode::DiskNearlyFull,
+                used,
+                limit: total,
+                message: format!("Disk holding {} is {}% full.", disk_path.display(), used * 100 / total.max(1)),
+            });
+        }
+    }
+
+    let (len, capacity, dropped) = log::log_buffer_usage();
+    let mut store = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    if dropped > store.last_dropped || near(len as u64, capacity as u64, settings.warning_ratio) {
+        warnings.push(LimitWarning {
+            code: LimitCode::LogBufferDropping,
+            used: len as u64,
+            limit: capacity as u64,
+            message: format!(
+                "Log buffer holds {} of {} entries; {} older entries have been dropped so far.",
+                len, capacity, dropped
+            ),
+        });
+    }
+    store.last_dropped = dropped;
+
+    let previous = std::mem::take(&mut store.active);
+    for warning in &warnings {
+        if !previous.iter().any(|p| p.code == warning.code) {
+            record(&mut store, warning.clone(), true);
+        }
+    }
+    for warning in previous {
+        if !warnings.iter().any(|w| w.code == warning.code) {
+            record(&mut store, warning, false);
+        }
+    }
+    store.active = warnings.clone();
+    warnings
+}
+
+/// Applies the configured log buffer size and checks process-wide limits periodically.
+pub fn start_monitor(disk_path: PathBuf) {
+    log::set_log_buffer_capacity(settings().log_buffer_capacity);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(MONITOR_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let path = disk_path.clone();
+            // `df` and the database are blocking
+            if let Err(e) = tokio::task::spawn_blocking(move || check(&path)).await {
+                tracing::warn!(target: "dev_runtime::limits", error = ?e, "Limit check panicked.");
+            }
+        }
+    });
+}
+
+/// Outcome of counting a request against a client's rate limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the window resets
+    pub reset_secs: u64,
+    pub exceeded: bool,
+    /// Set when the client is close to (or past) the limit
+    pub warning: Option<LimitWarning>,
+}
+
+struct RateWindow {
+    started: Instant,
+    count: u32,
+    warned: bool,
+}
+
+static RATE_WINDOWS: Lazy<Mutex<HashMap<String, RateWindow>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Counts a request from `client` against the per-minute limit; `None` when rate limiting is off.
+pub fn check_rate(client: &str) -> Option<RateStatus> {
+    let limit = settings().rate_limit_per_minute?;
+    let status = {
+        let mut windows = RATE_WINDOWS.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        windows.retain(|_, w| now.duration_since(w.started) < RATE_WINDOW);
+        let window = windows.entry(client.to_string()).or_insert(RateWindow { started: now, count: 0, warned: false });
+        window.count = window.count.saturating_add(1);
+        let reset_secs = RATE_WINDOW.saturating_sub(now.duration_since(window.started)).as_secs().max(1);
+        let status = rate_status(limit, window.count, reset_secs, settings().warning_ratio);
+        // Only the first warning per window is published as an event
+        let first_warning = status.warning.is_some() && !window.warned;
+        window.warned |= status.warning.is_some();
+        (status, first_warning)
+    };
+    if let (Some(warning), true) = (&status.0.warning, status.1) {
+        let mut store = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+        record(&mut store, warning.clone(), true);
+    }
+    Some(status.0)
+}
+
+fn rate_status(limit: u32, count: u32, reset_secs: u64, warning_ratio: f64) -> RateStatus {
+    let exceeded = count > limit;
+    let warning = if exceeded {
+        Some(LimitWarning {
+            code: LimitCode::RateLimitExceeded,
+            used: count as u64,
+            limit: limit as u64,
+            message: format!("Rate limit of {} requests per minute exceeded; retry in {}s.", limit, reset_secs),
+        })
+    } else if near(count as u64, limit as u64, warning_ratio) {
+        Some(LimitWarning {
+            code: LimitCode::RateLimitNearlyExhausted,
+            used: count as u64,
+            limit: limit as u64,
+            message: format!("{} of {} requests this minute used; the window resets in {}s.", count, limit, reset_secs),
+        })
+    } else {
+        None
+    };
+    RateStatus { limit, remaining: limit.saturating_sub(count), reset_secs, exceeded, warning }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_status_thresholds() {
+        let status = rate_status(10, 5, 30, 0.8);
+        assert_eq!(status.remaining, 5);
+        assert!(status.warning.is_none() && !status.exceeded);
+
+        let status = rate_status(10, 8, 30, 0.8);
+        assert_eq!(status.warning.as_ref().map(|w| w.code), Some(LimitCode::RateLimitNearlyExhausted));
+        assert_eq!(status.warning.unwrap().header_value(), "rate_limit_nearly_exhausted; used=8; limit=10");
+
+        let status = rate_status(10, 11, 30, 0.8);
+        assert!(status.exceeded);
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.warning.map(|w| w.code), Some(LimitCode::RateLimitExceeded));
+    }
+}
@@ -1,5 +1,6 @@
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 use anyhow::{Result, anyhow};
@@ -79,7 +80,24 @@ pub struct LogEntry {
 pub static SHARED_LOG_STORE: Lazy<Arc<Mutex<Vec<LogEntry>>>> =
     Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
 
+/// Entries the shared store holds before the oldest are dropped.
+pub const DEFAULT_LOG_BUFFER_CAPACITY: usize = 10_000;
+
+static LOG_BUFFER_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_LOG_BUFFER_CAPACITY);
+static DROPPED_LOG_ENTRIES: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_log_buffer_capacity(capacity: usize) {
+    LOG_BUFFER_CAPACITY.store(capacity.max(1), Ordering::Relaxed);
+}
+
+/// Entries held, capacity, and entries dropped so far to stay within it.
+pub fn log_buffer_usage() -> (usize, usize, u64) {
+    let len = SHARED_LOG_STORE.lock().map(|store| store.len()).unwrap_or_default();
+    (len, LOG_BUFFER_CAPACITY.load(Ordering::Relaxed), DROPPED_LOG_ENTRIES.load(Ordering::Relaxed))
+}
+
 pub fn add_log_entry(source: LogSource, level: LogLevel, message: String) {
+    super::log_hub::push(hub_source(&source), hub_stream(&source), level, None, message.as_str());
     let entry = LogEntry {
         timestamp: SystemTime::now(),
         source: source.clone(),
@@ -87,6 +105,14 @@ pub fn add_log_entry(source: LogSource, level: LogLevel, message: String) {
         message: message.clone(),
     };
     if let Ok(mut store) = SHARED_LOG_STORE.lock() {
+        // Drop the oldest tenth at once, so a full buffer doesn't shift on every entry
+        let capacity = LOG_BUFFER_CAPACITY.load(Ordering::Relaxed);
+        if store.len() >= capacity {
+            let excess = store.len() + 1 - capacity;
+            let dropped = excess.max(capacity / 10).min(store.len());
+            store.drain(..dropped);
+            DROPPED_LOG_ENTRIES.fetch_add(dropped as u64, Ordering::Relaxed);
+        }
         store.push(entry);
     } else {
         eprintln!(
@@ -96,6 +122,38 @@ pub fn add_log_entry(source: LogSource, level: LogLevel, message: String) {
     }
 }
 
+// Source the line is filed under in the log hub
+fn hub_source(source: &LogSource) -> &'static str {
+    match source {
+        LogSource::WatcherLspClientRequest
+        | LogSource::WatcherLspClientResponse
+        | LogSource::WatcherLspClientNotification
+        | LogSource::WatcherLspClientError
+        | LogSource::WatcherLspClientLifecycle
+        | LogSource::WatcherLspServerStdout
+        | LogSource::WatcherLspServerStderr
+        | LogSource::WatcherLspServerLifecycle => super::events::LSP_SERVICE,
+        LogSource::DebuggerNpmStdout
+        | LogSource::DebuggerNpmStderr
+        | LogSource::DebuggerPnpmStdout
+        | LogSource::DebuggerPnpmStderr
+        | LogSource::DebuggerGeneral => super::events::DEV_SERVER_SERVICE,
+        LogSource::WatcherEslint
+        | LogSource::WatcherPrettier
+        | LogSource::ScriptRunnerEslint
+        | LogSource::ScriptRunnerPrettier => "scripts",
+    }
+}
+
+fn hub_stream(source: &LogSource) -> super::log_hub::LogStream {
+    use super::log_hub::LogStream;
+    match source {
+        LogSource::DebuggerNpmStdout | LogSource::DebuggerPnpmStdout | LogSource::WatcherLspServerStdout => LogStream::Stdout,
+        LogSource::DebuggerNpmStderr | LogSource::DebuggerPnpmStderr | LogSource::WatcherLspServerStderr => LogStream::Stderr,
+        _ => LogStream::Event,
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct LogFilterOptions {
     pub sources: Option<Vec<LogSource>>,
@@ -0,0 +1,337 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::layer::{Context, Layer};
+
+use super::log::LogLevel;
+use super::util::now_ms;
+use crate::dev_setup::config_files;
+
+// config.toml section, e.g. `[logs] per_source_capacity = 5000`
+const CONFIG_SECTION: &str = "logs";
+/// Source of Galatea's own tracing events.
+pub const GALATEA_SOURCE: &str = "galatea";
+pub const DEFAULT_PER_SOURCE_CAPACITY: usize = 2000;
+// Tracing targets child output is logged under; that output is captured at the process instead
+const CHILD_OUTPUT_TARGETS: &[&str] = &[
+    "dev_runtime::nextjs::pnpm_stdout",
+    "dev_runtime::nextjs::pnpm_stderr",
+    "dev_runtime::run_stdout",
+    "dev_runtime::run_stderr",
+];
+
+static PER_SOURCE_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_PER_SOURCE_CAPACITY);
+static HUB: Lazy<Mutex<Hub>> = Lazy::new(|| Mutex::new(Hub { sources: HashMap::new(), next_seq: 1 }));
+static LIVE: Lazy<broadcast::Sender<HubEntry>> = Lazy::new(|| broadcast::channel(1024).0);
+
+/// Which output of a source a line came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    /// A tracing event or another message Galatea logged about the source
+    Event,
+}
+
+impl LogStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+            LogStream::Event => "event",
+        }
+    }
+}
+
+/// One line in the hub.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HubEntry {
+    /// Increases across all sources, so it orders entries and serves as a tail cursor
+    pub seq: u64,
+    pub timestamp_ms: u64,
+    /// `galatea`, `nextjs_dev_server`, `lsp` or `mcp:<server id>`
+    pub source: String,
+    pub stream: LogStream,
+    pub level: LogLevel,
+    /// Tracing target, for `galatea` events
+    pub target: Option<String>,
+    pub message: String,
+}
+
+struct Buffer {
+    entries: VecDeque<HubEntry>,
+    dropped: u64,
+}
+
+struct Hub {
+    sources: HashMap<String, Buffer>,
+    next_seq: u64,
+}
+
+/// How much one source holds and has lost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceStats {
+    pub name: String,
+    pub entries: usize,
+    /// Entries dropped to stay within the per-source capacity
+    pub dropped: u64,
+    pub last_seq: u64,
+}
+
+/// `[logs]` in config.toml.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogHubConfig {
+    pub per_source_capacity: Option<usize>,
+}
+
+/// Applies `[logs]` from config.toml. Called once at startup; until then the defaults apply.
+pub fn load_config() {
+    let Some(section) = config_files::get_config_section(CONFIG_SECTION) else {
+        return;
+    };
+    match section.try_into::<LogHubConfig>() {
+        Ok(config) => {
+            if let Some(capacity) = config.per_source_capacity {
+                PER_SOURCE_CAPACITY.store(capacity.max(1), Ordering::Relaxed);
+            }
+        }
+        Err(e) => {
+            tracing::warn!(target: "dev_runtime::log_hub", error = %e, "Invalid [logs] section in config.toml, using the defaults.")
+        }
+    }
+}
+
+fn severity(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Error => 0,
+        LogLevel::Warn => 1,
+        LogLevel::Info => 2,
+        LogLevel::Debug => 3,
+        LogLevel::Trace => 4,
+    }
+}
+
+pub fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Error => "error",
+        LogLevel::Warn => "warn",
+        LogLevel::Info => "info",
+        LogLevel::Debug => "debug",
+        LogLevel::Trace => "trace",
+    }
+}
+
+pub fn parse_level(name: &str) -> Option<LogLevel> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "error" => Some(LogLevel::Error),
+        "warn" | "warning" => Some(LogLevel::Warn),
+        "info" => Some(LogLevel::Info),
+        "debug" => Some(LogLevel::Debug),
+        "trace" => Some(LogLevel::Trace),
+        _ => None,
+    }
+}
+
+/// Adds a line to `source`'s buffer, dropping its oldest line when full, and sends it to
+/// streaming subscribers. Must not log through tracing, since the tracing layer calls it.
+pub fn push(source: &str, stream: LogStream, level: LogLevel, target: Option<&str>, message: impl Into<String>) {
+    let capacity = PER_SOURCE_CAPACITY.load(Ordering::Relaxed);
+    let entry = {
+        let mut hub = HUB.lock().unwrap_or_else(|e| e.into_inner());
+        let seq = hub.next_seq;
+        hub.next_seq += 1;
+        let entry = HubEntry {
+            seq,
+            timestamp_ms: now_ms(),
+            source: source.to_string(),
+            stream,
+            level,
+            target: target.map(str::to_string),
+            message: message.into(),
+        };
+        let buffer = hub
+            .sources
+            .entry(entry.source.clone())
+            .or_insert_with(|| Buffer { entries: VecDeque::new(), dropped: 0 });
+        while buffer.entries.len() >= capacity {
+            buffer.entries.pop_front();
+            buffer.dropped += 1;
+        }
+        buffer.entries.push_back(entry.clone());
+        entry
+    };
+    // No subscribers is the common case, not an error
+    let _ = LIVE.send(entry);
+}
+
+/// Level of a line a child process printed: what it says it is when it starts with or contains
+/// an obvious marker, otherwise info for stdout and warn for stderr.
+pub fn output_level(stream: LogStream, line: &str) -> LogLevel {
+    let lower = line.trim_start().to_ascii_lowercase();
+    if lower.starts_with("error") || lower.starts_with('⨯') || lower.contains(" error ") || lower.contains("error:") {
+        LogLevel::Error
+    } else if lower.starts_with("warn") || lower.starts_with('⚠') || lower.contains("warning:") || stream == LogStream::Stderr {
+        LogLevel::Warn
+    } else {
+        LogLevel::Info
+    }
+}
+
+/// Adds a line a child process printed.
+pub fn push_output(source: &str, stream: LogStream, line: &str) {
+    push(source, stream, output_level(stream, line), None, line);
+}
+
+/// Filters for `query` and `subscribe`; every one that is set must match.
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery {
+    /// Source names; `mcp` matches every `mcp:<id>`. Empty matches all sources
+    pub sources: Vec<String>,
+    /// Least severe level kept: `warn` keeps warnings and errors
+    pub min_level: Option<LogLevel>,
+    /// Unix milliseconds
+    pub since_ms: Option<u64>,
+    /// Only entries after this `seq`
+    pub after_seq: Option<u64>,
+    /// Case-insensitive substring of the message
+    pub contains: Option<String>,
+}
+
+impl LogQuery {
+    pub fn matches(&self, entry: &HubEntry) -> bool {
+        (self.sources.is_empty()
+            || self.sources.iter().any(|s| {
+                entry.source == *s || entry.source.strip_prefix(s.as_str()).is_some_and(|rest| rest.starts_with(':'))
+            }))
+            && self.min_level.is_none_or(|min| severity(entry.level) <= severity(min))
+            && self.since_ms.is_none_or(|since| entry.timestamp_ms >= since)
+            && self.after_seq.is_none_or(|after| entry.seq > after)
+            && self
+                .contains
+                .as_ref()
+                .is_none_or(|needle| entry.message.to_lowercase().contains(&needle.to_lowercase()))
+    }
+}
+
+/// The last `limit` matching entries, oldest first.
+pub fn query(filter: &LogQuery, limit: usize) -> Vec<HubEntry> {
+    let hub = HUB.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries: Vec<HubEntry> = hub
+        .sources
+        .values()
+        .flat_map(|buffer| buffer.entries.iter().rev().filter(|e| filter.matches(e)).take(limit))
+        .cloned()
+        .collect();
+    drop(hub);
+    entries.sort_unstable_by_key(|e| e.seq);
+    let skip = entries.len().saturating_sub(limit);
+    entries.split_off(skip)
+}
+
+/// Every source that logged something, by name.
+pub fn sources() -> Vec<SourceStats> {
+    let hub = HUB.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stats: Vec<SourceStats> = hub
+        .sources
+        .iter()
+        .map(|(name, buffer)| SourceStats {
+            name: name.clone(),
+            entries: buffer.entries.len(),
+            dropped: buffer.dropped,
+            last_seq: buffer.entries.back().map_or(0, |e| e.seq),
+        })
+        .collect();
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    stats
+}
+
+/// Entries pushed from now on, for streaming. Subscribe before querying the backlog so no
+/// entry falls between the two; skip live entries the backlog already had by `seq`.
+pub fn subscribe() -> broadcast::Receiver<HubEntry> {
+    LIVE.subscribe()
+}
+
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push(format!("{}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// Tracing layer copying Galatea's own events into the hub as the `galatea` source.
+pub struct LogHubLayer;
+
+pub fn layer() -> LogHubLayer {
+    LogHubLayer
+}
+
+impl<S: tracing::Subscriber> Layer<S> for LogHubLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let target = event.metadata().target();
+        if CHILD_OUTPUT_TARGETS.contains(&target) {
+            return;
+        }
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        let mut message = visitor.message;
+        for field in visitor.fields {
+            if !message.is_empty() {
+                message.push(' ');
+            }
+            message.push_str(&field);
+        }
+        push(GALATEA_SOURCE, LogStream::Event, LogLevel::from(*event.metadata().level()), Some(target), message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_query_and_levels() {
+        // Unique sources, since the hub is shared with every other test
+        push_output("mcp:hub-test", LogStream::Stdout, "listening on 3001");
+        push_output("mcp:hub-test", LogStream::Stderr, "Error: boom");
+        push_output("hub-test-other", LogStream::Stdout, "⚠ slow compile");
+
+        let mcp = LogQuery { sources: vec!["mcp".to_string()], ..Default::default() };
+        let entries: Vec<HubEntry> = query(&mcp, 100).into_iter().filter(|e| e.source == "mcp:hub-test").collect();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].seq < entries[1].seq);
+        assert_eq!(entries[1].level, LogLevel::Error);
+
+        let warnings = LogQuery { sources: vec!["hub-test-other".to_string()], min_level: Some(LogLevel::Warn), ..Default::default() };
+        assert_eq!(query(&warnings, 100).len(), 1);
+        let after = LogQuery { after_seq: Some(entries[1].seq), ..mcp.clone() };
+        assert!(query(&after, 100).iter().all(|e| e.source != "mcp:hub-test"));
+        assert_eq!(query(&LogQuery { sources: vec!["mcp:hub".to_string()], ..Default::default() }, 100).len(), 0);
+
+        assert_eq!(output_level(LogStream::Stdout, "  ✓ Ready in 2.1s"), LogLevel::Info);
+        assert_eq!(output_level(LogStream::Stderr, "npm notice"), LogLevel::Warn);
+        assert_eq!(parse_level("WARNING"), Some(LogLevel::Warn));
+    }
+}
@@ -1,44 +1,225 @@
 use anyhow::{anyhow, Context, Result};
-use lsp_types::notification::Notification;
-use lsp_types::request::Request;
+use lsp_types::notification::{Notification, PublishDiagnostics};
+use lsp_types::request::{Completion, DocumentDiagnosticRequest, HoverRequest, References, Rename, Request};
 use lsp_types::{
-    ClientCapabilities, DidOpenTextDocumentParams, GotoDefinitionParams, InitializeParams,
-    PartialResultParams, Position, TextDocumentIdentifier, TextDocumentItem,
-    TextDocumentPositionParams, Uri, WorkDoneProgressParams, WorkspaceFolder,
+    ClientCapabilities, CompletionParams, CompletionResponse, Diagnostic, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentDiagnosticParams, DocumentDiagnosticReport,
+    DocumentDiagnosticReportResult, DocumentSymbolParams, GotoDefinitionParams, Hover, HoverParams,
+    InitializeParams, InitializedParams, Location, PartialResultParams, Position, PublishDiagnosticsParams,
+    ReferenceContext, ReferenceParams, RenameParams, TextDocumentContentChangeEvent, TextDocumentIdentifier,
+    TextDocumentItem, TextDocumentPositionParams, Uri, VersionedTextDocumentIdentifier,
+    WorkDoneProgressParams, WorkspaceEdit, WorkspaceFolder, WorkspaceSymbolParams,
 };
 use serde_json::Value; // For params and results
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Command as TokioCommand; // Renamed to avoid conflict if std::process::Command is used
-use tokio::sync::mpsc;
+use tokio::sync::{oneshot, watch, Mutex as AsyncMutex};
 use tracing;
 use jsonrpc_lite::{Id, JsonRpc, Params}; // Ensure this is the only JsonRpc import
 
-use crate::file_system;
 use crate::dev_runtime::log::{self, LogLevel, LogSource};
+use crate::dev_runtime::lsp_trace::{self, TraceDirection};
+use crate::terminal::package_manager::PackageManager;
 
 // --- Language Server (typescript-language-server) Interaction ---
 
+// JSON-RPC id as shown in LSP traces
+fn trace_id(id: Option<Id>) -> Option<String> {
+    match id? {
+        Id::Num(n) => Some(n.to_string()),
+        Id::Str(s) => Some(s),
+        Id::None(_) => None,
+    }
+}
+
+// Reads Content-Length framed messages until the server's stdout closes
+async fn read_messages(stdout: tokio::process::ChildStdout, connection: &Connection) {
+    let mut reader = BufReader::new(stdout);
+    let mut buffer = String::new(); // Read lines into a string buffer
+    loop {
+        buffer.clear();
+        let mut content_length: Option<usize> = None;
+
+        // Read headers
+        loop {
+            match reader.read_line(&mut buffer).await {
+                Ok(0) => { // EOF
+                    log::add_log_entry(LogSource::WatcherLspServerStdout, LogLevel::Warn, "LSP stdout EOF reached while reading headers.".to_string());
+                    tracing::warn!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "LSP stdout EOF reached while reading headers.");
+                    return;
+                }
+                Ok(_) => {
+                    let line = buffer.trim_end(); // Keep buffer for next line
+                    if line.is_empty() { // Empty line signifies end of headers
+                        buffer.clear(); // Clear buffer for body reading
+                        break;
+                    }
+                    if line.starts_with("Content-Length:") {
+                        if let Some(val_str) = line.split(':').nth(1) {
+                            content_length = val_str.trim().parse::<usize>().ok();
+                        }
+                    }
+                    // Clear buffer for the next header line
+                    buffer.clear();
+                }
+                Err(e) => {
+                    log::add_log_entry(LogSource::WatcherLspServerStdout, LogLevel::Error, format!("Error reading LSP stdout headers: {}", e));
+                    tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "Error reading LSP stdout headers: {}", e);
+                    return;
+                }
+            }
+        }
+
+        if let Some(len) = content_length {
+            let mut body_buffer = vec![0; len];
+            if let Err(e) = reader.read_exact(&mut body_buffer).await {
+                log::add_log_entry(LogSource::WatcherLspServerStdout, LogLevel::Error, format!("Error reading LSP content (length {}): {}", len, e));
+                tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "Error reading LSP content (length {}): {}", len, e);
+                continue; // Try to recover by reading next message
+            }
+
+            match std::str::from_utf8(&body_buffer) {
+                Ok(json_str) => {
+                    match serde_json::from_str::<JsonRpc>(json_str) { // Use serde_json::from_str
+                        Ok(rpc) => connection.dispatch(rpc).await,
+                        Err(e) => {
+                            log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, format!("Error parsing LSP JSON-RPC (Content-Length: {}): {}. Content: '{}'", len, e, json_str));
+                            tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "Error parsing LSP JSON-RPC (Content-Length: {}): {}. Content: '{}'", len, e, json_str);
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, format!("LSP message body (Content-Length: {}) was not valid UTF-8: {}", len, e));
+                    tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "LSP message body (Content-Length: {}) was not valid UTF-8: {}", len, e);
+                }
+            }
+        } else {
+            log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Warn, "LSP message received without Content-Length header.".to_string());
+            tracing::warn!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "LSP message without Content-Length header received.");
+            // This is likely an error in message framing from the server or our reader.
+            // We might lose sync here. Consider if we should attempt to resync or just error out.
+        }
+    }
+}
+
+// Routes what the language server writes to whoever is waiting for it
+struct Connection {
+    writer: AsyncMutex<tokio::process::ChildStdin>,
+    // Requests awaiting their response, by JSON-RPC id
+    pending: StdMutex<HashMap<String, oneshot::Sender<JsonRpc>>>,
+    // Flips to true once the server's stdout closes, i.e. the process exited or crashed
+    exited: watch::Sender<bool>,
+    // Latest `textDocument/publishDiagnostics` per document URI, for servers without pull diagnostics
+    published_diagnostics: StdMutex<HashMap<String, Vec<Diagnostic>>>,
+}
+
+impl Connection {
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashMap<String, oneshot::Sender<JsonRpc>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn write(&self, rpc: &JsonRpc) -> Result<()> {
+        let rpc_string = serde_json::to_string(rpc).context("Failed to serialize JsonRpc to string")?;
+        let message = format!("Content-Length: {}\r\n\r\n{}", rpc_string.len(), rpc_string);
+
+        log::add_log_entry(LogSource::WatcherLspClientRequest, LogLevel::Debug, format!("Sending LSP RPC: Method '{:?}', ID '{:?}'", rpc.get_method(), rpc.get_id()));
+        tracing::trace!(target: "galatea::dev_runtime::lsp_client", "Sending LSP message: {}", message);
+        lsp_trace::record(TraceDirection::Sent, rpc.get_method(), trace_id(rpc.get_id()), &rpc_string);
+
+        // One writer at a time, so concurrent requests can't interleave their frames
+        self.writer
+            .lock()
+            .await
+            .write_all(message.as_bytes())
+            .await
+            .context("Failed to write to LSP stdin")
+    }
+
+    // Hands a message read from the server to the request it answers, or logs it
+    async fn dispatch(&self, rpc: JsonRpc) {
+        match (rpc.get_method(), trace_id(rpc.get_id())) {
+            (None, Some(id)) => {
+                let waiter = self.pending().remove(&id);
+                match waiter {
+                    // The waiter may have timed out in the meantime
+                    Some(waiter) => drop(waiter.send(rpc)),
+                    None => {
+                        log::add_log_entry(LogSource::WatcherLspClientResponse, LogLevel::Debug, format!("Received LSP response for ID {} nobody is waiting for: {:?}", id, rpc));
+                        tracing::debug!(target: "galatea::dev_runtime::lsp_client", id, "Received an LSP response nobody is waiting for.");
+                    }
+                }
+            }
+            (Some(method), Some(id)) => {
+                // Server requests (e.g. `window/workDoneProgress/create`) get an empty result,
+                // so the server doesn't stall waiting for a client that won't act on them
+                log::add_log_entry(LogSource::WatcherLspClientNotification, LogLevel::Debug, format!("Answering LSP server request {} (ID {}) with null.", method, id));
+                tracing::debug!(target: "galatea::dev_runtime::lsp_client", method, id, "Answering LSP server request with null.");
+                if let Some(request_id) = rpc.get_id() {
+                    if let Err(e) = self.write(&JsonRpc::success(request_id, &Value::Null)).await {
+                        tracing::warn!(target: "galatea::dev_runtime::lsp_client", error = ?e, method, "Failed to answer LSP server request.");
+                    }
+                }
+            }
+            (Some(PublishDiagnostics::METHOD), None) => {
+                let published = rpc.get_params().and_then(|params| serde_json::to_value(params).ok()).and_then(|value| serde_json::from_value::<PublishDiagnosticsParams>(value).ok());
+                if let Some(published) = published {
+                    self.published_diagnostics
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(published.uri.as_str().to_string(), published.diagnostics);
+                }
+            }
+            (method, None) => {
+                let method_name = method.unwrap_or("[unknown_method]");
+                let params_detail = rpc.get_params().map_or("[no_params]".to_string(), |params| match params {
+                    Params::Array(arr) => format!("Array({})", arr.len()),
+                    Params::Map(map) => format!("Map({{ {} }})", map.keys().cloned().collect::<Vec<_>>().join(", ")),
+                    Params::None(_) => "None".to_string(),
+                });
+                log::add_log_entry(
+                    LogSource::WatcherLspClientNotification, // Server-initiated notification
+                    LogLevel::Debug,
+                    format!("Received LSP notification (Method: {}). Params: {}", method_name, params_detail),
+                );
+                tracing::debug!(target: "galatea::dev_runtime::lsp_client", method = method_name, "Received LSP notification.");
+            }
+        }
+    }
+
+    // Fails every pending request and tells watchers the server is gone
+    fn mark_exited(&self) {
+        self.pending().clear();
+        self.exited.send_replace(true);
+    }
+}
+
+/// A running language server. Requests can be made concurrently from several tasks: each gets
+/// its own JSON-RPC id and the stdout reader routes responses back by id.
 pub struct LspClient {
-    writer: tokio::process::ChildStdin,
-    response_rx: mpsc::Receiver<JsonRpc>,
+    connection: Arc<Connection>,
     request_id_counter: AtomicI64,
-    child_process: tokio::process::Child, // Keep the child process to manage its lifecycle
+    // Versions of the documents opened on the server, by URI
+    open_documents: StdMutex<HashMap<String, i32>>,
+    child_process: AsyncMutex<tokio::process::Child>, // Keep the child process to manage its lifecycle
+    workspace: PathBuf,
 }
 
 impl LspClient {
-    // Note: The actual spawning of the LSP server process (pnpm run lsp)
-    // should ideally be managed by a higher-level process supervisor (e.g., in dev_runtime)
-    // This `new` function will assume the process is started elsewhere and pipes are provided,
-    // or it could be adapted to take a pre-spawned Child process.
-    // For now, to simplify the initial move, we'll keep the spawning logic here but acknowledge it should move.
-    pub async fn new() -> Result<Self> {
-        let project_dir = file_system::get_project_root()?;
+    /// Spawns the language server (the project's `lsp` script) in `workspace`.
+    pub async fn new(workspace: &Path) -> Result<Self> {
+        let project_dir = workspace.to_path_buf();
 
+        let lsp_command = PackageManager::detect(&project_dir).script_command("lsp", &[]);
         let msg_spawn = format!(
-            "Spawning LSP server (pnpm run lsp) in {}",
+            "Spawning LSP server ({}) in {}",
+            lsp_command.join(" "),
             project_dir.display()
         );
         log::add_log_entry(
@@ -48,16 +229,18 @@ impl LspClient {
         );
         tracing::info!(target: "galatea::dev_runtime::lsp_client", source_process = "lsp_server_spawner", "{}", msg_spawn);
 
-        let mut cmd = TokioCommand::new("pnpm");
+        let mut cmd = TokioCommand::new(&lsp_command[0]);
         cmd.current_dir(&project_dir)
-            .args(&["run", "lsp"]) // The script "lsp": "typescript-language-server --stdio"
+            .args(&lsp_command[1..]) // The script "lsp": "typescript-language-server --stdio"
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
 
         let mut child = cmd.spawn().with_context(|| {
             format!(
-                "Failed to spawn 'pnpm run lsp' in project dir: {}",
+                "Failed to spawn '{}' in project dir: {}",
+                lsp_command.join(" "),
                 project_dir.display()
             )
         })?;
@@ -65,93 +248,29 @@ impl LspClient {
         let stdin = child
             .stdin
             .take()
-            .ok_or_else(|| anyhow!("Failed to get LSP stdin after 'pnpm run lsp'"))?;
+            .ok_or_else(|| anyhow!("Failed to get LSP stdin after spawning the lsp script"))?;
         let stdout = child
             .stdout
             .take()
-            .ok_or_else(|| anyhow!("Failed to get LSP stdout after 'pnpm run lsp'"))?;
+            .ok_or_else(|| anyhow!("Failed to get LSP stdout after spawning the lsp script"))?;
         let stderr_reader = BufReader::new(
             child
                 .stderr
                 .take()
-                .ok_or_else(|| anyhow!("Failed to get LSP stderr after 'pnpm run lsp'"))?,
+                .ok_or_else(|| anyhow!("Failed to get LSP stderr after spawning the lsp script"))?,
         );
 
-        let (response_tx, response_rx) = mpsc::channel(128);
+        let connection = Arc::new(Connection {
+            writer: AsyncMutex::new(stdin),
+            pending: StdMutex::new(HashMap::new()),
+            exited: watch::channel(false).0,
+            published_diagnostics: StdMutex::new(HashMap::new()),
+        });
 
+        let reader_connection = connection.clone();
         tokio::spawn(async move {
-            let mut reader = BufReader::new(stdout);
-            let mut buffer = String::new(); // Read lines into a string buffer
-            loop {
-                buffer.clear();
-                let mut content_length: Option<usize> = None;
-
-                // Read headers
-                loop {
-                    match reader.read_line(&mut buffer).await {
-                        Ok(0) => { // EOF
-                            log::add_log_entry(LogSource::WatcherLspServerStdout, LogLevel::Warn, "LSP stdout EOF reached while reading headers.".to_string());
-                            tracing::warn!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "LSP stdout EOF reached while reading headers.");
-                            return;
-                        }
-                        Ok(_) => {
-                            let line = buffer.trim_end(); // Keep buffer for next line
-                            if line.is_empty() { // Empty line signifies end of headers
-                                buffer.clear(); // Clear buffer for body reading
-                                break;
-                            }
-                            if line.starts_with("Content-Length:") {
-                                if let Some(val_str) = line.split(':').nth(1) {
-                                    content_length = val_str.trim().parse::<usize>().ok();
-                                }
-                            }
-                            // Clear buffer for the next header line
-                            buffer.clear();
-                        }
-                        Err(e) => {
-                            log::add_log_entry(LogSource::WatcherLspServerStdout, LogLevel::Error, format!("Error reading LSP stdout headers: {}", e));
-                            tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "Error reading LSP stdout headers: {}", e);
-                            return;
-                        }
-                    }
-                }
-
-                if let Some(len) = content_length {
-                    let mut body_buffer = vec![0; len];
-                    if let Err(e) = reader.read_exact(&mut body_buffer).await {
-                        log::add_log_entry(LogSource::WatcherLspServerStdout, LogLevel::Error, format!("Error reading LSP content (length {}): {}", len, e));
-                        tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "Error reading LSP content (length {}): {}", len, e);
-                        continue; // Try to recover by reading next message
-                    }
-                    
-                    match std::str::from_utf8(&body_buffer) {
-                        Ok(json_str) => {
-                            match serde_json::from_str::<JsonRpc>(json_str) { // Use serde_json::from_str
-                                Ok(rpc) => {
-                                    if response_tx.send(rpc).await.is_err() {
-                                        log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, "Failed to send parsed LSP RPC to internal channel (receiver dropped).".to_string());
-                                        tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "Failed to send parsed LSP RPC to internal channel (receiver dropped).");
-                                        return; // Channel closed, stop task
-                                    }
-                                }
-                                Err(e) => {
-                                    log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, format!("Error parsing LSP JSON-RPC (Content-Length: {}): {}. Content: '{}'", len, e, json_str));
-                                    tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "Error parsing LSP JSON-RPC (Content-Length: {}): {}. Content: '{}'", len, e, json_str);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, format!("LSP message body (Content-Length: {}) was not valid UTF-8: {}", len, e));
-                            tracing::error!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "LSP message body (Content-Length: {}) was not valid UTF-8: {}", len, e);
-                        }
-                    }
-                } else {
-                    log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Warn, "LSP message received without Content-Length header.".to_string());
-                    tracing::warn!(target: "galatea::dev_runtime::lsp_client::stdout_reader", "LSP message without Content-Length header received.");
-                    // This is likely an error in message framing from the server or our reader.
-                    // We might lose sync here. Consider if we should attempt to resync or just error out.
-                }
-            }
+            read_messages(stdout, &reader_connection).await;
+            reader_connection.mark_exited();
         });
 
         tokio::spawn(async move {
@@ -165,47 +284,62 @@ impl LspClient {
         });
 
         Ok(Self {
-            writer: stdin,
-            response_rx,
+            connection,
             request_id_counter: AtomicI64::new(0),
-            child_process: child,
+            open_documents: StdMutex::new(HashMap::new()),
+            child_process: AsyncMutex::new(child),
+            workspace: project_dir,
         })
     }
 
-    fn next_request_id(&self) -> Id {
-        Id::Num(self.request_id_counter.fetch_add(1, Ordering::SeqCst) as i64) // Id::Num takes i64
+    /// The directory the server was started in.
+    pub fn workspace(&self) -> &Path {
+        &self.workspace
     }
 
-    async fn send_rpc(&mut self, rpc: JsonRpc) -> Result<()> {
-        let rpc_string = serde_json::to_string(&rpc).context("Failed to serialize JsonRpc to string")?; // Use serde_json::to_string
-        let message = format!("Content-Length: {}\\r\\n\\r\\n{}", rpc_string.len(), rpc_string);
+    /// Whether the server process has gone away (its stdout closed).
+    pub fn has_exited(&self) -> bool {
+        *self.connection.exited.borrow()
+    }
 
-        log::add_log_entry(LogSource::WatcherLspClientRequest, LogLevel::Debug, format!("Sending LSP RPC: Method '{:?}', ID '{:?}'", rpc.get_method(), rpc.get_id()));
-        tracing::trace!(target: "galatea::dev_runtime::lsp_client", "Sending LSP message: {}", message);
-        
-        self.writer
-            .write_all(message.as_bytes())
-            .await
-            .context("Failed to write to LSP stdin")
+    /// Resolves once the server process has gone away.
+    pub async fn wait_for_exit(&self) {
+        let mut exited = self.connection.exited.subscribe();
+        // An error means the sender was dropped, which only happens with the client itself
+        let _ = exited.wait_for(|exited| *exited).await;
+    }
+
+    fn next_request_id(&self) -> Id {
+        Id::Num(self.request_id_counter.fetch_add(1, Ordering::SeqCst) as i64) // Id::Num takes i64
     }
 
-    async fn send_request(&mut self, method: &str, params_value: Value) -> Result<Id> {
+    async fn send_request(&self, method: &str, params_value: Value) -> Result<(Id, oneshot::Receiver<JsonRpc>)> {
+        if self.has_exited() {
+            return Err(anyhow!("The LSP server has exited"));
+        }
         let id = self.next_request_id();
         let params = Params::from(params_value);
         let rpc = JsonRpc::request_with_params(id.clone(), method, params.clone());
-        self.send_rpc(rpc).await.with_context(|| {
-            format!(
-                "Failed to send LSP request {} with params {:?}",
-                method, params
-            )
-        })?;
-        Ok(id)
+        // Registered before sending, so a fast response can't arrive ahead of its waiter
+        let (response_tx, response_rx) = oneshot::channel();
+        let key = trace_id(Some(id.clone())).unwrap_or_default();
+        self.connection.pending().insert(key.clone(), response_tx);
+        if let Err(e) = self.connection.write(&rpc).await {
+            self.connection.pending().remove(&key);
+            return Err(e).with_context(|| {
+                format!(
+                    "Failed to send LSP request {} with params {:?}",
+                    method, params
+                )
+            });
+        }
+        Ok((id, response_rx))
     }
 
-    async fn send_notification(&mut self, method: &str, params_value: Value) -> Result<()> {
+    async fn send_notification(&self, method: &str, params_value: Value) -> Result<()> {
         let params = Params::from(params_value);
         let rpc = JsonRpc::notification_with_params(method, params.clone());
-        self.send_rpc(rpc).await.with_context(|| {
+        self.connection.write(&rpc).await.with_context(|| {
             format!(
                 "Failed to send LSP notification {} with params {:?}",
                 method, params
@@ -214,110 +348,46 @@ impl LspClient {
         Ok(())
     }
 
-    async fn wait_for_response(&mut self, request_id: &Id, timeout_secs: u64) -> Result<JsonRpc> {
-      let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(timeout_secs);
-      loop {
-          match tokio::time::timeout_at(deadline, self.response_rx.recv()).await {
-              Ok(Some(response_candidate)) => {
-                  match response_candidate.get_id() {
-                      Some(id) if id == request_id.clone() => {
-                          log::add_log_entry(
-                              LogSource::WatcherLspClientResponse,
-                              LogLevel::Debug, 
-                              format!("Received matching LSP response for ID {:?}: {:?}", request_id, response_candidate)
-                          );
-                          return Ok(response_candidate);
-                      }
-                      Some(other_id) => {
-                          let log_level = if response_candidate.get_method().is_some() { LogLevel::Warn } else { LogLevel::Debug };
-                          log::add_log_entry(
-                              if response_candidate.get_method().is_some() { LogSource::WatcherLspClientNotification } else { LogSource::WatcherLspClientResponse }, 
-                              log_level, 
-                              format!(
-                                  "Received LSP message for different ID ({:?}) while waiting for ID {:?}. Full message: {:?}", 
-                                  other_id, request_id, response_candidate
-                              )
-                          );
-                          response_candidate.get_method()
-                              .map_or_else(
-                                  || {
-                                      tracing::debug!(target: "galatea::watcher::lsp_client_logic", source_component = "lsp_client", 
-                                                    "Received response for a different ID ({:?}) while waiting for ID {:?}. Full message: {:?}", 
-                                                    other_id, request_id, response_candidate);
-                                  },
-                                  |method| {
-                                      tracing::warn!(target: "galatea::watcher::lsp_client_logic", source_component = "lsp_client", 
-                                                   "Received a SERVER REQUEST (Method: {}, ID: {:?}) while waiting for response to ID {:?}. This is unexpected while polling for a specific response.", 
-                                                   method, other_id, request_id);
-                                  }
-                              );
-                      }
-                      None => {
-                          // No ID typically means it's a Notification
-                          let method_name = response_candidate
-                              .get_method()
-                              .unwrap_or("[unknown_method]");
-                          
-                          // Define params_detail here before it's used
-                          let params_detail = response_candidate.get_params().map_or(
-                              "[no_params]".to_string(),
-                              |params| match params {
-                                  Params::Array(arr) => {
-                                      let items = arr.iter()
-                                          .map(|v| format!("{:?}", v))
-                                          .collect::<Vec<_>>()
-                                          .join(", ");
-                                      format!("Array([{}])", items)
-                                  },
-                                  Params::Map(map) => {
-                                      let items = map.iter()
-                                          .map(|(k, v)| format!("{}: {:?}", k, v))
-                                          .collect::<Vec<_>>()
-                                          .join(", ");
-                                      format!("Map({{ {} }})", items)
-                                  },
-                                  Params::None(_) => "None".to_string(),
-                              },
-                          );
-
-                          log::add_log_entry(
-                              LogSource::WatcherLspClientNotification, // Server-initiated notification
-                              LogLevel::Debug,
-                              format!(
-                                  "Received LSP notification (Method: {}) while waiting for response to ID {:?}. Params: {}", 
-                                  method_name, request_id, params_detail // Now params_detail is in scope
-                              )
-                          );
-
-                          tracing::debug!(target: "galatea::watcher::lsp_client_logic", source_component = "lsp_client", 
-                                        "Received notification (Method: {}) while waiting for response to ID {:?}. Params: {}", 
-                                        method_name, request_id, params_detail);
-                      }
-                  }
-              }
-              Ok(None) => {
-                  let err_msg = format!(
-                      "LSP response channel closed while waiting for request ID {:?}",
-                      request_id
-                  );
-                  log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, err_msg.clone());
-                  return Err(anyhow!(err_msg))
-              }
-              Err(_) => {
-                  let timeout_msg = format!(
-                      "Timeout waiting for LSP response for request ID {:?}",
-                      request_id
-                  );
-                  log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, timeout_msg.clone());
-                  return Err(anyhow!(timeout_msg))
-              } // Elapsed
-          }
-      }
-  }
+    async fn wait_for_response(&self, request: (Id, oneshot::Receiver<JsonRpc>), timeout_secs: u64) -> Result<JsonRpc> {
+        let (request_id, response_rx) = request;
+        let timeout = tokio::time::Duration::from_secs(timeout_secs);
+        match tokio::time::timeout(timeout, response_rx).await {
+            Ok(Ok(response)) => {
+                if lsp_trace::is_active() {
+                    let body = serde_json::to_string(&response).unwrap_or_default();
+                    lsp_trace::record(TraceDirection::Received, response.get_method(), trace_id(response.get_id()), &body);
+                }
+                log::add_log_entry(
+                    LogSource::WatcherLspClientResponse,
+                    LogLevel::Debug,
+                    format!("Received matching LSP response for ID {:?}: {:?}", request_id, response)
+                );
+                Ok(response)
+            }
+            Ok(Err(_)) => {
+                let err_msg = format!(
+                    "LSP server exited while waiting for request ID {:?}",
+                    request_id
+                );
+                log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, err_msg.clone());
+                Err(anyhow!(err_msg))
+            }
+            Err(_) => {
+                self.connection.pending().remove(&trace_id(Some(request_id.clone())).unwrap_or_default());
+                let timeout_msg = format!(
+                    "Timeout waiting for LSP response for request ID {:?}",
+                    request_id
+                );
+                log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, timeout_msg.clone());
+                Err(anyhow!(timeout_msg))
+            }
+        }
+    }
 
   #[allow(deprecated)] // Suppress warnings for deprecated fields used in InitializeParams
+  #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "initialize"))]
   pub async fn initialize(
-      &mut self,
+      &self,
       root_uri: Uri, // This uri is used to derive workspace_folder.uri
       client_capabilities: ClientCapabilities,
   ) -> Result<lsp_types::InitializeResult> {
@@ -347,7 +417,7 @@ impl LspClient {
           work_done_progress_params: WorkDoneProgressParams::default(),
       };
       log::add_log_entry(LogSource::WatcherLspClientLifecycle, LogLevel::Info, "Sending LSP Initialize request".to_string());
-      let request_id = self
+      let request = self
           .send_request(
               lsp_types::request::Initialize::METHOD,
                   serde_json::to_value(params)
@@ -357,7 +427,7 @@ impl LspClient {
           .context("Sending Initialize request to LSP failed")?;
 
       let response_rpc = self
-          .wait_for_response(&request_id, 10)
+          .wait_for_response(request, 10)
           .await
           .context("Waiting for Initialize response from LSP failed")?;
 
@@ -381,7 +451,7 @@ impl LspClient {
   }
 
   pub async fn notify_did_open(
-      &mut self,
+      &self,
       uri: Uri,
       language_id: &str,
       version: i32,
@@ -407,8 +477,9 @@ impl LspClient {
       .await
   }
 
+  #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "textDocument/definition"))]
   pub async fn goto_definition(
-      &mut self,
+      &self,
       uri: Uri,
       position: Position,
   ) -> Result<Option<lsp_types::GotoDefinitionResponse>> {
@@ -425,7 +496,7 @@ impl LspClient {
           LogLevel::Info, 
           format!("Sending LSP GotoDefinition request for {:?}:({},{})", uri, position.line, position.character)
       );
-      let request_id = self
+      let request = self
           .send_request(
               lsp_types::request::GotoDefinition::METHOD,
                   serde_json::to_value(params)
@@ -436,7 +507,7 @@ impl LspClient {
           .context("Sending GotoDefinition request to LSP failed")?;
 
       let response_rpc = self
-          .wait_for_response(&request_id, 5)
+          .wait_for_response(request, 5)
           .await
           .context("Waiting for GotoDefinition response from LSP failed")?;
 
@@ -459,28 +530,282 @@ impl LspClient {
       }
   }
 
-    pub async fn close(mut self) -> Result<()> {
+    pub async fn notify_initialized(&self) -> Result<()> {
+        log::add_log_entry(LogSource::WatcherLspClientLifecycle, LogLevel::Info, "Sending LSP Initialized notification".to_string());
+        self.send_notification(
+            lsp_types::notification::Initialized::METHOD,
+            serde_json::to_value(InitializedParams {}).context("Serialize InitializedParams error")?,
+        )
+        .await
+    }
+
+    #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "workspace/symbol"))]
+    pub async fn workspace_symbol(
+        &self,
+        query: &str,
+    ) -> Result<Option<lsp_types::WorkspaceSymbolResponse>> {
+        let params = WorkspaceSymbolParams {
+            partial_result_params: PartialResultParams::default(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            query: query.to_string(),
+        };
+        log::add_log_entry(
+            LogSource::WatcherLspClientRequest,
+            LogLevel::Info,
+            format!("Sending LSP WorkspaceSymbol request for query '{}'", query)
+        );
+        let request = self
+            .send_request(
+                lsp_types::request::WorkspaceSymbolRequest::METHOD,
+                serde_json::to_value(params).context("Serialize WorkspaceSymbolParams error for LSP")?,
+            )
+            .await
+            .context("Sending WorkspaceSymbol request to LSP failed")?;
+
+        let response_rpc = self
+            .wait_for_response(request, 5)
+            .await
+            .context("Waiting for WorkspaceSymbol response from LSP failed")?;
+
+        match response_rpc.get_result() {
+            Some(result_value) => serde_json::from_value(result_value.clone())
+                .context("Failed to parse WorkspaceSymbolResponse from LSP response"),
+            None => {
+                if let JsonRpc::Error(e) = response_rpc {
+                    Err(anyhow!("LSP WorkspaceSymbol error: {:?}", e))
+                } else {
+                    Err(anyhow!("LSP WorkspaceSymbol: Did not receive a success or error response, or result was absent."))
+                }
+            }
+        }
+    }
+
+    #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "textDocument/documentSymbol"))]
+    pub async fn document_symbol(
+        &self,
+        uri: Uri,
+    ) -> Result<Option<lsp_types::DocumentSymbolResponse>> {
+        let params = DocumentSymbolParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        log::add_log_entry(
+            LogSource::WatcherLspClientRequest,
+            LogLevel::Info,
+            format!("Sending LSP DocumentSymbol request for {:?}", uri)
+        );
+        let request = self
+            .send_request(
+                lsp_types::request::DocumentSymbolRequest::METHOD,
+                serde_json::to_value(params).context("Serialize DocumentSymbolParams error for LSP")?,
+            )
+            .await
+            .context("Sending DocumentSymbol request to LSP failed")?;
+
+        let response_rpc = self
+            .wait_for_response(request, 5)
+            .await
+            .context("Waiting for DocumentSymbol response from LSP failed")?;
+
+        match response_rpc.get_result() {
+            Some(result_value) => serde_json::from_value(result_value.clone())
+                .context("Failed to parse DocumentSymbolResponse from LSP response"),
+            None => {
+                if let JsonRpc::Error(e) = response_rpc {
+                    Err(anyhow!("LSP DocumentSymbol error: {:?}", e))
+                } else {
+                    Err(anyhow!("LSP DocumentSymbol: Did not receive a success or error response, or result was absent."))
+                }
+            }
+        }
+    }
+
+    // Sends request `R` and decodes its result; for the requests added after the ones above
+    async fn request<R: Request>(&self, params: R::Params, timeout_secs: u64) -> Result<R::Result> {
+        let params = serde_json::to_value(params).with_context(|| format!("Serialize {} params error for LSP", R::METHOD))?;
+        let request = self
+            .send_request(R::METHOD, params)
+            .await
+            .with_context(|| format!("Sending {} request to LSP failed", R::METHOD))?;
+        let response_rpc = self
+            .wait_for_response(request, timeout_secs)
+            .await
+            .with_context(|| format!("Waiting for {} response from LSP failed", R::METHOD))?;
+        match response_rpc.get_result() {
+            Some(result_value) => serde_json::from_value(result_value.clone())
+                .with_context(|| format!("Failed to parse {} response from LSP", R::METHOD)),
+            None => {
+                if let JsonRpc::Error(e) = response_rpc {
+                    Err(anyhow!("LSP {} error: {:?}", R::METHOD, e))
+                } else {
+                    Err(anyhow!("LSP {}: Did not receive a success or error response, or result was absent.", R::METHOD))
+                }
+            }
+        }
+    }
+
+    fn position_params(uri: Uri, position: Position) -> TextDocumentPositionParams {
+        TextDocumentPositionParams { text_document: TextDocumentIdentifier { uri }, position }
+    }
+
+    #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "textDocument/hover"))]
+    pub async fn hover(&self, uri: Uri, position: Position) -> Result<Option<Hover>> {
+        let params = HoverParams {
+            text_document_position_params: Self::position_params(uri, position),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        self.request::<HoverRequest>(params, 5).await
+    }
+
+    #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "textDocument/completion"))]
+    pub async fn completion(&self, uri: Uri, position: Position) -> Result<Option<CompletionResponse>> {
+        let params = CompletionParams {
+            text_document_position: Self::position_params(uri, position),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: None,
+        };
+        self.request::<Completion>(params, 10).await
+    }
+
+    #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "textDocument/references"))]
+    pub async fn references(&self, uri: Uri, position: Position, include_declaration: bool) -> Result<Option<Vec<Location>>> {
+        let params = ReferenceParams {
+            text_document_position: Self::position_params(uri, position),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: ReferenceContext { include_declaration },
+        };
+        self.request::<References>(params, 10).await
+    }
+
+    #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "textDocument/rename"))]
+    pub async fn rename(&self, uri: Uri, position: Position, new_name: &str) -> Result<Option<WorkspaceEdit>> {
+        let params = RenameParams {
+            text_document_position: Self::position_params(uri, position),
+            new_name: new_name.to_string(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        self.request::<Rename>(params, 10).await
+    }
+
+    /// Diagnostics of a synced document: pulled with `textDocument/diagnostic`, or, for servers
+    /// that only push them (typescript-language-server does), the next `publishDiagnostics`
+    /// for it, waiting up to `wait`.
+    #[tracing::instrument(name = "lsp.request", skip_all, fields(rpc.system = "jsonrpc", rpc.method = "textDocument/diagnostic"))]
+    pub async fn diagnostics(&self, uri: Uri, wait: std::time::Duration) -> Result<Vec<Diagnostic>> {
+        let params = DocumentDiagnosticParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            identifier: None,
+            previous_result_id: None,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        match self.request::<DocumentDiagnosticRequest>(params, 10).await {
+            Ok(DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(report))) => {
+                return Ok(report.full_document_diagnostic_report.items)
+            }
+            // Only returned for a `previous_result_id`, which isn't sent
+            Ok(_) => return Ok(Vec::new()),
+            Err(e) => {
+                tracing::debug!(target: "galatea::dev_runtime::lsp_client", error = ?e, "Pull diagnostics unavailable; waiting for published ones.");
+            }
+        }
+
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let published = self.connection.published_diagnostics.lock().unwrap_or_else(|e| e.into_inner()).get(uri.as_str()).cloned();
+            if let Some(diagnostics) = published {
+                return Ok(diagnostics);
+            }
+            if tokio::time::Instant::now() >= deadline || self.has_exited() {
+                return Err(anyhow!("The LSP server published no diagnostics for {} within {:?}", uri.as_str(), wait));
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    }
+
+    /// Opens `uri` on the server with `text`, or replaces its content if it is already open.
+    ///
+    /// The server is long-lived and shared, so a document opened by one request may be asked
+    /// about again later; re-sending `didOpen` for it would be a protocol error.
+    pub async fn sync_document(&self, uri: Uri, language_id: &str, text: String) -> Result<()> {
+        let version = {
+            let mut open_documents = self.open_documents.lock().unwrap_or_else(|e| e.into_inner());
+            let version = open_documents.entry(uri.as_str().to_string()).and_modify(|v| *v += 1).or_insert(1);
+            *version
+        };
+        // Whatever was published for the previous content is stale now
+        self.connection.published_diagnostics.lock().unwrap_or_else(|e| e.into_inner()).remove(uri.as_str());
+        if version == 1 {
+            return self.notify_did_open(uri, language_id, version, text).await;
+        }
+        let params = DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier { uri, version },
+            content_changes: vec![TextDocumentContentChangeEvent { range: None, range_length: None, text }],
+        };
+        self.send_notification(
+            lsp_types::notification::DidChangeTextDocument::METHOD,
+            serde_json::to_value(params).context("Serialize DidChangeTextDocumentParams error")?,
+        )
+        .await
+    }
+
+    /// Closes `uri` on the server if it is open; the next `sync_document` reopens it.
+    pub async fn close_document(&self, uri: Uri) -> Result<()> {
+        if self.open_documents.lock().unwrap_or_else(|e| e.into_inner()).remove(uri.as_str()).is_none() {
+            return Ok(());
+        }
+        self.connection.published_diagnostics.lock().unwrap_or_else(|e| e.into_inner()).remove(uri.as_str());
+        let params = DidCloseTextDocumentParams { text_document: TextDocumentIdentifier { uri } };
+        self.send_notification(
+            lsp_types::notification::DidCloseTextDocument::METHOD,
+            serde_json::to_value(params).context("Serialize DidCloseTextDocumentParams error")?,
+        )
+        .await
+    }
+
+    /// URIs of the documents open on the server.
+    pub fn open_document_uris(&self) -> Vec<String> {
+        self.open_documents.lock().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect()
+    }
+
+    /// Brings the server's copy of the file at `path` in line with the disk after it was
+    /// changed outside of the server: an open document gets the new content, or is closed when
+    /// the file is gone. A document that isn't open is only opened with `open_if_closed`.
+    pub async fn sync_from_disk(&self, path: &Path, language_id: &str, open_if_closed: bool) -> Result<()> {
+        let uri = Uri::from_str(&format!("file://{}", path.display())).context(format!("Failed to convert path {} to URI", path.display()))?;
+        let is_open = self.open_documents.lock().unwrap_or_else(|e| e.into_inner()).contains_key(uri.as_str());
+        match std::fs::read_to_string(path) {
+            Ok(text) if is_open || open_if_closed => self.sync_document(uri, language_id, text).await,
+            Err(_) if is_open && !path.exists() => self.close_document(uri).await,
+            Err(e) if is_open => Err(e).context(format!("Failed to read file {}", path.display())),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn close(&self) -> Result<()> {
         log::add_log_entry(LogSource::WatcherLspServerLifecycle, LogLevel::Info, "Closing LSP client and attempting to kill server process.".to_string());
         tracing::info!(target: "galatea::dev_runtime::lsp_client", "Closing LSP client and attempting to kill server process.");
-        
+
         let exit_params_value = serde_json::Value::Null;
         let params = Params::from(exit_params_value);
         let rpc = JsonRpc::notification_with_params(lsp_types::notification::Exit::METHOD, params.clone());
-        if let Err(e) = self.send_rpc(rpc).await {
+        if let Err(e) = self.connection.write(&rpc).await {
             log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Warn, format!("Failed to send exit notification to LSP server (proceeding with kill): {}",e));
             tracing::warn!(target: "galatea::dev_runtime::lsp_client", "Failed to send exit notification to LSP server: {}", e);
         }
 
-        drop(self.writer);
-
-        match self.child_process.try_wait() {
+        let mut child_process = self.child_process.lock().await;
+        match child_process.try_wait() {
             Ok(Some(status)) => {
                 log::add_log_entry(LogSource::WatcherLspServerLifecycle, LogLevel::Info, format!("LSP server process exited with status: {}", status));
                 tracing::info!(target: "galatea::dev_runtime::lsp_client", "LSP server process already exited with status: {}", status);
             }
             Ok(None) => {
                 tracing::info!(target: "galatea::dev_runtime::lsp_client", "LSP server process still running, attempting to kill.");
-                if let Err(e) = self.child_process.kill().await {
+                if let Err(e) = child_process.kill().await {
                     log::add_log_entry(LogSource::WatcherLspServerLifecycle, LogLevel::Error, format!("Failed to kill LSP server process: {}", e));
                     return Err(anyhow!("Failed to kill LSP server process: {}", e));
                 } else {
@@ -495,4 +820,51 @@ impl LspClient {
         }
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+/// Spawns the language server in `workspace` and completes the initialize handshake against it.
+pub async fn start_initialized_client(workspace: &Path) -> Result<LspClient> {
+    let root_uri = Uri::from_str(&format!("file://{}", workspace.display()))
+        .context(format!("Failed to convert workspace {} to URI", workspace.display()))?;
+
+    let client = LspClient::new(workspace).await?;
+    client
+        .initialize(root_uri, ClientCapabilities::default())
+        .await
+        .context("LSP initialize handshake failed")?;
+    client.notify_initialized().await?;
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dispatch_routes_responses_by_id() {
+        let mut sink = TokioCommand::new("cat").stdin(Stdio::piped()).stdout(Stdio::null()).kill_on_drop(true).spawn().unwrap();
+        let connection = Connection {
+            writer: AsyncMutex::new(sink.stdin.take().unwrap()),
+            pending: StdMutex::new(HashMap::new()),
+            exited: watch::channel(false).0,
+            published_diagnostics: StdMutex::new(HashMap::new()),
+        };
+        let (first_tx, first_rx) = oneshot::channel();
+        let (second_tx, second_rx) = oneshot::channel();
+        connection.pending().insert("1".to_string(), first_tx);
+        connection.pending().insert("2".to_string(), second_tx);
+
+        // Answered out of order, as concurrent requests can be
+        connection.dispatch(JsonRpc::success(Id::Num(2), &Value::from("second"))).await;
+        connection.dispatch(JsonRpc::notification("$/progress")).await;
+        connection.dispatch(JsonRpc::success(Id::Num(1), &Value::from("first"))).await;
+        assert_eq!(first_rx.await.unwrap().get_result(), Some(&Value::from("first")));
+        assert_eq!(second_rx.await.unwrap().get_result(), Some(&Value::from("second")));
+
+        let (third_tx, third_rx) = oneshot::channel();
+        connection.pending().insert("3".to_string(), third_tx);
+        connection.mark_exited();
+        assert!(third_rx.await.is_err());
+        assert!(*connection.exited.borrow());
+    }
+}
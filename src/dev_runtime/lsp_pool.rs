@@ -0,0 +1,240 @@
+use once_cell::sync::{Lazy, OnceCell};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use super::capabilities::{self, Capability, CapabilityState};
+use super::events::{self, ServiceEventKind, LSP_SERVICE};
+use super::log::{self, LogLevel, LogSource};
+use super::lsp_client::{self, LspClient};
+use crate::dev_operation::symbols::language_id_for_path;
+
+// Crashes tolerated within `CRASH_WINDOW` before the pool stops restarting a workspace's server
+const MAX_RESTARTS: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(10 * 60);
+// Delay before the first restart, doubled for each recent crash
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct Workspace {
+    client: Option<Arc<LspClient>>,
+    starting: bool,
+    // When the workspace's server last crashed, oldest first, within `CRASH_WINDOW`
+    crashes: Vec<Instant>,
+}
+
+/// Long-lived language servers, one per workspace, shared by every caller.
+///
+/// Clients are handed out as `Arc`s and can be used concurrently. A server that exits without
+/// being shut down through the pool is restarted with a growing delay, up to `MAX_RESTARTS`
+/// times in ten minutes.
+#[derive(Default)]
+pub struct LspPool {
+    workspaces: Mutex<HashMap<PathBuf, Workspace>>,
+}
+
+static SHARED_POOL: Lazy<Arc<LspPool>> = Lazy::new(|| Arc::new(LspPool::default()));
+
+/// The process-wide pool, also registered as request data for the LSP routes.
+pub fn shared() -> Arc<LspPool> {
+    SHARED_POOL.clone()
+}
+
+/// Files an applied edit changed on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditedFiles {
+    /// Files written or removed. `created` ones are opened on the servers, the others are only
+    /// updated (or closed when gone) if a server has them open.
+    Paths { changed: Vec<PathBuf>, created: Vec<PathBuf> },
+    /// Unknown files, e.g. an undo spanning several of them: every open document is re-read.
+    Unknown,
+}
+
+// Feeds edits to the running servers one at a time, so a file's updates arrive in edit order
+static EDIT_QUEUE: OnceCell<mpsc::UnboundedSender<EditedFiles>> = OnceCell::new();
+
+/// Tells the running language servers about files an edit changed, so their answers don't lag
+/// behind the disk. Returns immediately; outside of a Tokio runtime it does nothing.
+pub fn files_edited(edited: EditedFiles) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else { return };
+    let queue = EDIT_QUEUE.get_or_init(|| {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        runtime.spawn(async move {
+            while let Some(edited) = receiver.recv().await {
+                shared().sync_edited(&edited).await;
+            }
+        });
+        sender
+    });
+    let _ = queue.send(edited);
+}
+
+impl LspPool {
+    fn workspaces(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Workspace>> {
+        self.workspaces.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the workspace's client if its server is running.
+    ///
+    /// Otherwise a background start is kicked off and `None` is returned immediately, so callers
+    /// can answer from a fallback while the language server warms up. A server that kept
+    /// crashing is left stopped until `ensure_started`.
+    pub fn client_if_ready(self: &Arc<Self>, workspace: &Path) -> Option<Arc<LspClient>> {
+        let mut workspaces = self.workspaces();
+        let entry = workspaces.entry(workspace.to_path_buf()).or_default();
+        if let Some(client) = entry.client.as_ref().filter(|c| !c.has_exited()) {
+            return Some(client.clone());
+        }
+        let gave_up = entry.crashes.iter().filter(|at| at.elapsed() < CRASH_WINDOW).count() > MAX_RESTARTS;
+        if !entry.starting && !gave_up {
+            entry.starting = true;
+            // Set before returning, so callers answering 503 report the start
+            capabilities::set(Capability::Lsp, CapabilityState::Starting, None);
+            tokio::spawn(self.clone().run(workspace.to_path_buf(), Duration::ZERO));
+        }
+        None
+    }
+
+    /// Whether any workspace has a running server.
+    pub fn any_running(&self) -> bool {
+        self.workspaces().values().any(|w| w.client.as_ref().is_some_and(|c| !c.has_exited()))
+    }
+
+    /// Starts the workspace's server if it isn't running or starting, also after the pool gave
+    /// up restarting it.
+    pub fn ensure_started(self: &Arc<Self>, workspace: &Path) {
+        self.workspaces().entry(workspace.to_path_buf()).or_default().crashes.clear();
+        drop(self.client_if_ready(workspace));
+    }
+
+    /// Stops every server; the next request for a workspace starts a fresh one.
+    pub async fn shutdown_all(&self) {
+        // Taken out first, so the crash monitors see an intentional stop
+        let clients: Vec<Arc<LspClient>> = self
+            .workspaces()
+            .values_mut()
+            .filter_map(|w| {
+                w.crashes.clear();
+                w.client.take()
+            })
+            .collect();
+        if clients.is_empty() {
+            return;
+        }
+        events::record_event(LSP_SERVICE, ServiceEventKind::Stopped, None);
+        capabilities::set(Capability::Lsp, CapabilityState::Idle, None);
+        for client in clients {
+            if let Err(e) = client.close().await {
+                tracing::warn!(target: "dev_runtime::lsp_pool", error = ?e, workspace = %client.workspace().display(), "Failed to close LSP client cleanly.");
+            }
+        }
+    }
+
+    // Sends the edited files' new content to every running server whose workspace holds them
+    async fn sync_edited(&self, edited: &EditedFiles) {
+        let clients: Vec<Arc<LspClient>> =
+            self.workspaces().values().filter_map(|w| w.client.clone()).filter(|c| !c.has_exited()).collect();
+        for client in clients {
+            let files: Vec<(PathBuf, bool)> = match edited {
+                EditedFiles::Paths { changed, created } => changed
+                    .iter()
+                    .map(|path| (path.clone(), false))
+                    .chain(created.iter().map(|path| (path.clone(), true)))
+                    .filter(|(path, _)| path.starts_with(client.workspace()))
+                    .collect(),
+                EditedFiles::Unknown => client
+                    .open_document_uris()
+                    .iter()
+                    .filter_map(|uri| uri.strip_prefix("file://"))
+                    .map(|path| (PathBuf::from(path), false))
+                    .collect(),
+            };
+            for (path, created) in files {
+                let language_id = language_id_for_path(&path);
+                // Files the server has no language for aren't worth opening
+                let open = created && language_id != "plaintext";
+                if let Err(e) = client.sync_from_disk(&path, language_id, open).await {
+                    tracing::warn!(target: "dev_runtime::lsp_pool", error = ?e, path = %path.display(), "Failed to sync an edited file to the LSP server.");
+                }
+            }
+        }
+    }
+
+    // Starts the workspace's server after `delay`, then keeps restarting it while it crashes
+    async fn run(self: Arc<Self>, workspace: PathBuf, mut delay: Duration) {
+        loop {
+            tokio::time::sleep(delay).await;
+            let Some(client) = self.start(&workspace).await else { return };
+            client.wait_for_exit().await;
+            match self.restart_delay(&client) {
+                Some(next) => delay = next,
+                None => return,
+            }
+        }
+    }
+
+    async fn start(&self, workspace: &Path) -> Option<Arc<LspClient>> {
+        events::record_event(LSP_SERVICE, ServiceEventKind::Starting, None);
+        let started = lsp_client::start_initialized_client(workspace).await;
+        let mut workspaces = self.workspaces();
+        let entry = workspaces.entry(workspace.to_path_buf()).or_default();
+        entry.starting = false;
+        match started {
+            Ok(client) => {
+                let client = Arc::new(client);
+                entry.client = Some(client.clone());
+                drop(workspaces);
+                events::record_event(LSP_SERVICE, ServiceEventKind::Running, None);
+                capabilities::set(Capability::Lsp, CapabilityState::Available, None);
+                tracing::info!(target: "dev_runtime::lsp_pool", workspace = %workspace.display(), "LSP server is ready.");
+                Some(client)
+            }
+            Err(e) => {
+                drop(workspaces);
+                events::record_event(LSP_SERVICE, ServiceEventKind::Failed, Some(format!("{:#}", e)));
+                capabilities::set(Capability::Lsp, CapabilityState::Failed, Some(format!("{:#}", e)));
+                log::add_log_entry(LogSource::WatcherLspClientError, LogLevel::Error, format!("Failed to start LSP server in {}: {}", workspace.display(), e));
+                tracing::error!(target: "dev_runtime::lsp_pool", error = ?e, workspace = %workspace.display(), "Failed to start LSP server.");
+                None
+            }
+        }
+    }
+
+    // How long to wait before restarting an exited client's server; `None` when it was shut down
+    // through the pool or keeps crashing
+    fn restart_delay(&self, client: &Arc<LspClient>) -> Option<Duration> {
+        let workspace = client.workspace();
+        let crashes = {
+            let mut workspaces = self.workspaces();
+            let entry = workspaces.get_mut(workspace)?;
+            if !entry.client.as_ref().is_some_and(|current| Arc::ptr_eq(current, client)) {
+                return None;
+            }
+            entry.client = None;
+            entry.crashes.retain(|at| at.elapsed() < CRASH_WINDOW);
+            entry.crashes.push(Instant::now());
+            entry.starting = entry.crashes.len() <= MAX_RESTARTS;
+            entry.crashes.len()
+        };
+
+        if crashes > MAX_RESTARTS {
+            let detail = format!(
+                "The language server crashed more than {} times in {} minutes; start it again through the runtime API",
+                MAX_RESTARTS,
+                CRASH_WINDOW.as_secs() / 60
+            );
+            events::record_event(LSP_SERVICE, ServiceEventKind::Failed, Some(detail.clone()));
+            capabilities::set(Capability::Lsp, CapabilityState::Failed, Some(detail));
+            tracing::error!(target: "dev_runtime::lsp_pool", workspace = %workspace.display(), "LSP server keeps crashing; giving up on restarts.");
+            return None;
+        }
+        let delay = RESTART_BACKOFF * 2u32.pow(crashes as u32 - 1);
+        let detail = format!("The language server exited unexpectedly; restarting in {}s", delay.as_secs());
+        events::record_event(LSP_SERVICE, ServiceEventKind::Failed, Some(detail.clone()));
+        capabilities::set(Capability::Lsp, CapabilityState::Starting, Some(detail));
+        tracing::warn!(target: "dev_runtime::lsp_pool", workspace = %workspace.display(), delay_secs = delay.as_secs(), "LSP server exited unexpectedly; restarting.");
+        Some(delay)
+    }
+}
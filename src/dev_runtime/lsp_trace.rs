@@ -0,0 +1,181 @@
+use once_cell::sync::Lazy;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use super::util::{now_secs, truncate_tail};
+use crate::dev_setup::config_files;
+
+/// Longest message body kept in a trace, in bytes.
+pub const MAX_BODY_BYTES: usize = 2000;
+
+// Traces are kept in memory for retrieval by id, oldest evicted first
+const MAX_STORED_TRACES: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+    /// Galatea → language server
+    Sent,
+    /// Language server → Galatea
+    Received,
+}
+
+impl TraceDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraceDirection::Sent => "sent",
+            TraceDirection::Received => "received",
+        }
+    }
+}
+
+/// One JSON-RPC message exchanged during a traced call.
+#[derive(Debug, Clone)]
+pub struct LspTraceMessage {
+    pub direction: TraceDirection,
+    pub method: Option<String>,
+    pub id: Option<String>,
+    /// Milliseconds since the traced call started
+    pub elapsed_ms: u64,
+    pub body: String,
+    pub truncated: bool,
+}
+
+/// The JSON-RPC conversation of one API call.
+#[derive(Debug, Clone)]
+pub struct LspTrace {
+    pub id: String,
+    pub operation: String,
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub messages: Vec<LspTraceMessage>,
+}
+
+struct ActiveTrace {
+    started: Instant,
+    messages: Vec<LspTraceMessage>,
+}
+
+tokio::task_local! {
+    // Set for the duration of a traced call, so the shared client records into the caller's trace
+    static ACTIVE_TRACE: RefCell<ActiveTrace>;
+}
+
+static TRACES: Lazy<Mutex<VecDeque<LspTrace>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Whether a call should be traced: the request's `debug` flag, else `lsp_debug = "true"` in config.
+pub fn debug_enabled(requested: Option<bool>) -> bool {
+    requested.unwrap_or_else(|| config_files::get_config_value("lsp_debug").is_some_and(|v| v == "true"))
+}
+
+/// Whether the current task is being traced; lets callers skip serializing messages otherwise.
+pub fn is_active() -> bool {
+    ACTIVE_TRACE.try_with(|_| ()).is_ok()
+}
+
+/// Records a message into the current task's trace, if any.
+pub fn record(direction: TraceDirection, method: Option<&str>, id: Option<String>, body: &str) {
+    let _ = ACTIVE_TRACE.try_with(|trace| {
+        let mut trace = trace.borrow_mut();
+        let (body, truncated) = truncate_body(body);
+        let message = LspTraceMessage {
+            direction,
+            method: method.map(str::to_string),
+            id,
+            elapsed_ms: trace.started.elapsed().as_millis() as u64,
+            body,
+            truncated,
+        };
+        trace.messages.push(message);
+    });
+}
+
+fn truncate_body(body: &str) -> (String, bool) {
+    match truncate_tail(body, MAX_BODY_BYTES) {
+        kept if kept.len() < body.len() => (format!("{}...", kept), true),
+        kept => (kept.to_string(), false),
+    }
+}
+
+/// Runs `fut`, capturing the JSON-RPC messages it exchanges with the language server when
+/// `enabled`. The trace is returned and kept for [`get_trace`].
+pub async fn capture<F: Future>(operation: &str, enabled: bool, fut: F) -> (F::Output, Option<LspTrace>) {
+    if !enabled {
+        return (fut.await, None);
+    }
+    let started_at = now_secs();
+    let active = RefCell::new(ActiveTrace { started: Instant::now(), messages: Vec::new() });
+    let (output, active) = ACTIVE_TRACE
+        .scope(active, async move {
+            let output = fut.await;
+            (output, ACTIVE_TRACE.with(|t| t.replace(ActiveTrace { started: Instant::now(), messages: Vec::new() })))
+        })
+        .await;
+
+    let trace = LspTrace {
+        id: uuid::Uuid::new_v4().to_string(),
+        operation: operation.to_string(),
+        started_at,
+        duration_ms: active.started.elapsed().as_millis() as u64,
+        messages: active.messages,
+    };
+    tracing::debug!(target: "dev_runtime::lsp_trace", trace_id = %trace.id, operation, messages = trace.messages.len(), "Captured LSP trace.");
+    let mut traces = TRACES.lock().unwrap_or_else(|e| e.into_inner());
+    if traces.len() >= MAX_STORED_TRACES {
+        traces.pop_front();
+    }
+    traces.push_back(trace.clone());
+    (output, Some(trace))
+}
+
+/// A stored trace by id.
+pub fn get_trace(id: &str) -> Option<LspTrace> {
+    TRACES.lock().unwrap_or_else(|e| e.into_inner()).iter().find(|t| t.id == id).cloned()
+}
+
+/// Stored traces, newest first.
+pub fn list_traces() -> Vec<LspTrace> {
+    TRACES.lock().unwrap_or_else(|e| e.into_inner()).iter().rev().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_capture_records_and_stores_trace() {
+        let (value, trace) = capture("test/op", true, async {
+            assert!(is_active());
+            record(TraceDirection::Sent, Some("workspace/symbol"), Some("1".to_string()), r#"{"id":1}"#);
+            record(TraceDirection::Received, None, Some("1".to_string()), r#"{"id":1,"result":[]}"#);
+            42
+        })
+        .await;
+        assert_eq!(value, 42);
+        assert!(!is_active());
+
+        let trace = trace.unwrap();
+        assert_eq!(trace.messages.len(), 2);
+        assert_eq!(trace.messages[0].direction, TraceDirection::Sent);
+        assert_eq!(trace.messages[0].method.as_deref(), Some("workspace/symbol"));
+        assert_eq!(get_trace(&trace.id).unwrap().messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_capture_and_truncation() {
+        let (_, trace) = capture("test/off", false, async {
+            assert!(!is_active());
+            record(TraceDirection::Sent, None, None, "ignored");
+        })
+        .await;
+        assert!(trace.is_none());
+
+        let long = "é".repeat(MAX_BODY_BYTES);
+        let (body, truncated) = truncate_body(&long);
+        assert!(truncated);
+        assert_eq!(body, format!("{}...", "é".repeat(MAX_BODY_BYTES / 2)));
+        assert_eq!(truncate_body("{}"), ("{}".to_string(), false));
+    }
+}
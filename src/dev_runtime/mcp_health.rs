@@ -0,0 +1,334 @@
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::events;
+use super::supervisor::{self, TaskState};
+use super::types::McpServiceDefinition;
+use super::util::now_secs;
+use crate::dev_setup::config_files;
+
+// config.toml section
+const CONFIG_SECTION: &str = "mcp_health";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+// Starting servers are polled this often, so they count as ready soon after they listen
+const STARTING_POLL: Duration = Duration::from_secs(1);
+
+/// MCP server health settings, from `[mcp_health]` in config.toml:
+///
+/// ```toml
+/// [mcp_health]
+/// interval_secs = 10        # how often ready servers are checked
+/// unhealthy_after = 3       # failed checks in a row before a ready server counts as unhealthy
+/// ready_timeout_secs = 30   # how long a proxied request waits for its server to become ready
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct McpHealthConfig {
+    pub interval_secs: u64,
+    pub unhealthy_after: u32,
+    pub ready_timeout_secs: u64,
+}
+
+impl Default for McpHealthConfig {
+    fn default() -> Self {
+        Self { interval_secs: 10, unhealthy_after: 3, ready_timeout_secs: 30 }
+    }
+}
+
+impl McpHealthConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+/// Where an MCP server is in its life, as far as Galatea can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum McpServerState {
+    /// Running `npm install` and `npm run build`
+    Building,
+    /// Launched, `/mcp` not answering yet
+    Starting,
+    Ready,
+    /// Was ready, then stopped answering
+    Unhealthy,
+    /// Stopped through the API, or exited cleanly
+    Stopped,
+    /// The build failed or the process exited with an error
+    Failed,
+}
+
+impl McpServerState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            McpServerState::Building => "building",
+            McpServerState::Starting => "starting",
+            McpServerState::Ready => "ready",
+            McpServerState::Unhealthy => "unhealthy",
+            McpServerState::Stopped => "stopped",
+            McpServerState::Failed => "failed",
+        }
+    }
+}
+
+/// Health of one launched MCP server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpServerStatus {
+    pub id: String,
+    pub name: String,
+    pub port: u16,
+    pub state: McpServerState,
+    /// Seconds spent in the current state
+    pub state_secs: u64,
+    /// Why the last build, run or check failed
+    pub last_error: Option<String>,
+    /// Unix seconds of the last check and of the last successful one
+    pub last_check_at: Option<u64>,
+    pub last_ready_at: Option<u64>,
+    /// Times the server was launched, counting restarts
+    pub starts: u32,
+}
+
+struct Tracked {
+    name: String,
+    port: u16,
+    state: McpServerState,
+    since: Instant,
+    last_error: Option<String>,
+    last_check_at: Option<u64>,
+    last_ready_at: Option<u64>,
+    failures: u32,
+    starts: u32,
+}
+
+impl Tracked {
+    fn set_state(&mut self, state: McpServerState) {
+        if self.state != state {
+            self.state = state;
+            self.since = Instant::now();
+        }
+    }
+
+    // Applies a check's outcome
+    fn record_probe(&mut self, result: Result<(), String>, unhealthy_after: u32) {
+        let now = now_secs();
+        self.last_check_at = Some(now);
+        match result {
+            Ok(()) => {
+                self.failures = 0;
+                self.last_ready_at = Some(now);
+                if matches!(self.state, McpServerState::Starting | McpServerState::Unhealthy) {
+                    self.set_state(McpServerState::Ready);
+                }
+            }
+            Err(e) => {
+                self.failures += 1;
+                if self.state == McpServerState::Ready && self.failures >= unhealthy_after.max(1) {
+                    self.set_state(McpServerState::Unhealthy);
+                }
+                // Refused connections are expected while a server starts
+                if self.state != McpServerState::Starting {
+                    self.last_error = Some(e);
+                }
+            }
+        }
+    }
+}
+
+static SERVERS: Lazy<Mutex<BTreeMap<String, Tracked>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+fn servers() -> std::sync::MutexGuard<'static, BTreeMap<String, Tracked>> {
+    SERVERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Starts tracking a server about to be launched.
+pub fn register(definition: &McpServiceDefinition) {
+    servers().entry(definition.id.clone()).or_insert_with(|| Tracked {
+        name: definition.name.clone(),
+        port: definition.port,
+        state: McpServerState::Building,
+        since: Instant::now(),
+        last_error: None,
+        last_check_at: None,
+        last_ready_at: None,
+        failures: 0,
+        starts: 0,
+    });
+}
+
+fn update(id: &str, f: impl FnOnce(&mut Tracked)) {
+    if let Some(tracked) = servers().get_mut(id) {
+        f(tracked);
+    }
+}
+
+/// The server is being installed and built, at launch or restart.
+pub fn mark_building(id: &str) {
+    update(id, |t| {
+        t.starts += 1;
+        t.failures = 0;
+        t.set_state(McpServerState::Building);
+    });
+}
+
+/// The server's process was started; it is ready once `/mcp` answers.
+pub fn mark_launched(id: &str) {
+    update(id, |t| t.set_state(McpServerState::Starting));
+}
+
+/// The build failed or the process exited, with `error` unless it exited cleanly.
+pub fn mark_exited(id: &str, error: Option<String>) {
+    update(id, |t| {
+        t.set_state(if error.is_some() { McpServerState::Failed } else { McpServerState::Stopped });
+        if error.is_some() {
+            t.last_error = error;
+        }
+    });
+}
+
+/// Checks that the server answers on `/mcp`. Any HTTP answer counts: without a session the
+/// streamable-HTTP transport rejects a plain GET, which still shows it is listening.
+pub async fn probe(port: u16) -> Result<(), String> {
+    let client = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build().map_err(|e| e.to_string())?;
+    match client.get(format!("http://127.0.0.1:{}/mcp", port)).send().await {
+        Ok(_) => Ok(()),
+        Err(e) if e.is_timeout() => Err(format!("No answer on port {} within {}s", port, PROBE_TIMEOUT.as_secs())),
+        Err(e) if e.is_connect() => Err(format!("Port {} refused the connection", port)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// A server stopped through the API keeps its last state here; the supervisor knows better
+fn reconcile(id: &str, tracked: &mut Tracked) {
+    let task = supervisor::task_state(&events::mcp_service_name(id));
+    if task == Some(TaskState::Stopped) {
+        tracked.set_state(McpServerState::Stopped);
+    }
+}
+
+/// Checks a launched server now, if it is one that should be answering.
+pub async fn check(id: &str, unhealthy_after: u32) -> Option<McpServerStatus> {
+    let port = {
+        let mut servers = servers();
+        let tracked = servers.get_mut(id)?;
+        reconcile(id, tracked);
+        matches!(tracked.state, McpServerState::Starting | McpServerState::Ready | McpServerState::Unhealthy).then_some(tracked.port)
+    };
+    if let Some(port) = port {
+        let result = probe(port).await;
+        update(id, |t| t.record_probe(result, unhealthy_after));
+    }
+    status(id)
+}
+
+fn status_of(id: &str, tracked: &Tracked) -> McpServerStatus {
+    McpServerStatus {
+        id: id.to_string(),
+        name: tracked.name.clone(),
+        port: tracked.port,
+        state: tracked.state,
+        state_secs: tracked.since.elapsed().as_secs(),
+        last_error: tracked.last_error.clone(),
+        last_check_at: tracked.last_check_at,
+        last_ready_at: tracked.last_ready_at,
+        starts: tracked.starts,
+    }
+}
+
+/// Every launched server, by id.
+pub fn statuses() -> Vec<McpServerStatus> {
+    let mut servers = servers();
+    servers
+        .iter_mut()
+        .map(|(id, tracked)| {
+            reconcile(id, tracked);
+            status_of(id, tracked)
+        })
+        .collect()
+}
+
+pub fn status(id: &str) -> Option<McpServerStatus> {
+    let mut servers = servers();
+    let tracked = servers.get_mut(id)?;
+    reconcile(id, tracked);
+    Some(status_of(id, tracked))
+}
+
+/// Waits until the server is past building and starting, or `timeout` passes, checking it as it
+/// starts. Returns its status at that point.
+pub async fn wait_until_ready(id: &str, timeout: Duration) -> Option<McpServerStatus> {
+    let config = McpHealthConfig::load();
+    let started = Instant::now();
+    loop {
+        let status = check(id, config.unhealthy_after).await?;
+        let settled = !matches!(status.state, McpServerState::Building | McpServerState::Starting);
+        if settled || started.elapsed() + STARTING_POLL > timeout {
+            return Some(status);
+        }
+        tokio::time::sleep(STARTING_POLL).await;
+    }
+}
+
+/// Checks every launched server in the background: starting ones every second until they
+/// answer, ready ones every `interval_secs`.
+pub fn start_monitor() {
+    let config = McpHealthConfig::load();
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(config.interval_secs.max(1));
+        let mut last_checked: BTreeMap<String, Instant> = BTreeMap::new();
+        loop {
+            for status in statuses() {
+                let due = match status.state {
+                    McpServerState::Starting => true,
+                    McpServerState::Ready | McpServerState::Unhealthy => {
+                        last_checked.get(&status.id).is_none_or(|at| at.elapsed() >= interval)
+                    }
+                    _ => false,
+                };
+                if !due {
+                    continue;
+                }
+                last_checked.insert(status.id.clone(), Instant::now());
+                if let Some(checked) = check(&status.id, config.unhealthy_after).await {
+                    if checked.state != status.state {
+                        tracing::info!(target: "dev_runtime::mcp_health", server_id = %checked.id, from = status.state.as_str(), to = checked.state.as_str(), error = ?checked.last_error, "MCP server health changed.");
+                    }
+                }
+            }
+            tokio::time::sleep(STARTING_POLL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_transitions() {
+        let definition = McpServiceDefinition {
+            id: "health_test_api".to_string(),
+            name: "Health Test API".to_string(),
+            port: 1,
+            openapi_spec_path_on_mcp: "/openapi.json".to_string(),
+        };
+        register(&definition);
+        mark_building(&definition.id);
+        mark_launched(&definition.id);
+        update(&definition.id, |t| t.record_probe(Err("refused".to_string()), 2));
+        let status = status(&definition.id).unwrap();
+        assert_eq!((status.state, status.last_error, status.starts), (McpServerState::Starting, None, 1));
+
+        update(&definition.id, |t| t.record_probe(Ok(()), 2));
+        update(&definition.id, |t| t.record_probe(Err("timeout".to_string()), 2));
+        assert_eq!(super::status(&definition.id).unwrap().state, McpServerState::Ready);
+        update(&definition.id, |t| t.record_probe(Err("timeout".to_string()), 2));
+        let status = super::status(&definition.id).unwrap();
+        assert_eq!((status.state, status.last_error.as_deref()), (McpServerState::Unhealthy, Some("timeout")));
+
+        mark_exited(&definition.id, Some("exit status 1".to_string()));
+        assert_eq!(super::status(&definition.id).unwrap().state, McpServerState::Failed);
+    }
+}
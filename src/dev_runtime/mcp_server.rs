@@ -1,26 +1,47 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use tokio::process::Command;
 use tracing;
 use crate::terminal::port::{is_port_available, ensure_port_is_free};
-use crate::dev_runtime::util; // Still needed for spawn_background_command_in_dir
-use crate::terminal::npm; // Import the npm module
+use crate::dev_runtime::events::{self, ServiceEventKind};
+use crate::dev_runtime::jobs::JobHandle;
+use crate::dev_runtime::{mcp_health, supervisor, util};
+use crate::terminal::package_manager::PackageManager;
+use crate::dev_setup::config_files;
+use crate::file_system::paths;
 use crate::dev_runtime::types::McpServiceDefinition; // Import the definition
 use tokio::time::{timeout, Duration};
 
 const STARTING_MCP_PORT: u16 = 3060;
 const MCP_OPENAPI_SPEC_PATH: &str = "/openapi.json"; // Assumed path on the MCP server
+// Recorded in a server's directory after npm install and npm run build, so restarts can skip them
+const DEPS_FINGERPRINT_FILE: &str = ".galatea_deps_fingerprint";
+const BUILD_FINGERPRINT_FILE: &str = ".galatea_build_fingerprint";
+// Where openapi-mcp-generator's tsconfig.json compiles to
+const BUILD_OUTPUT_DIR: &str = "build";
+// Under mcp_servers/, holds node_modules while a server is regenerated
+const INSTALL_STASH_DIR: &str = ".install_stash";
+const STASHED_INSTALL_FILES: [&str; 3] = ["node_modules", "package-lock.json", DEPS_FINGERPRINT_FILE];
+
+// One regeneration at a time: two would generate into the same directories
+static REGENERATION_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// The id of the MCP server generated from a spec, used for routing: `project` for
+/// `project_api.json`.
+fn server_id(spec_file_stem: &str) -> String {
+    spec_file_stem.strip_suffix("_api").unwrap_or(spec_file_stem).to_string()
+}
 
 /// Launches MCP (Model-Centric Proxy) servers for each OpenAPI specification file found.
 /// Each server is first generated, then built, and finally run as a separate process.
 /// Returns a list of definitions for successfully initiated servers.
-pub async fn create_mcp_servers(use_sudo: bool) -> Result<Vec<McpServiceDefinition>> {
+pub async fn create_mcp_servers(use_sudo: bool, job: &JobHandle) -> Result<Vec<McpServiceDefinition>> {
     tracing::info!(target: "dev_runtime::mcp_server", "Initiating MCP server launch sequence...");
 
-    let exe_path = std::env::current_exe().context("Failed to get current executable path")?;
-    let exe_dir = exe_path.parent().context("Failed to get executable directory")?;
-    let galatea_files_dir = exe_dir.join("galatea_files");
+    let galatea_files_dir = paths::galatea_files_dir()?;
     let openapi_spec_dir = galatea_files_dir.join("openapi_specification");
     let mcp_servers_base_dir = galatea_files_dir.join("mcp_servers");
 
@@ -92,6 +113,7 @@ pub async fn create_mcp_servers(use_sudo: bool) -> Result<Vec<McpServiceDefiniti
 
     let mut current_port = STARTING_MCP_PORT;
     let mut mcp_definitions = Vec::new();
+    let mut processed = 0;
 
     for entry in fs::read_dir(&openapi_spec_dir).context(format!("Failed to read OpenAPI specification directory at {}", openapi_spec_dir.display()))? {
         let entry = entry.context("Failed to read directory entry in openapi_specification")?;
@@ -109,21 +131,12 @@ pub async fn create_mcp_servers(use_sudo: bool) -> Result<Vec<McpServiceDefiniti
             tracing::info!(target: "dev_runtime::mcp_server", path = %spec_file_path.display(), "Processing OpenAPI specification file.");
 
             let file_stem = spec_file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown");
+            let server_id = server_id(file_stem);
             // Convert "project_api.json" to "project_mcp"
-            let server_name = if file_stem.ends_with("_api") {
-                format!("{}_mcp", &file_stem[..file_stem.len() - 4])
-            } else {
-                format!("{}_mcp", file_stem)
-            };
-            
-            // The ID used for routing (e.g., "project" for project_api.json)
-            let server_id = if file_stem.ends_with("_api") {
-                file_stem[..file_stem.len() - 4].to_string()
-            } else {
-                file_stem.to_string()
-            };
+            let server_name = format!("{}_mcp", server_id);
             
             let dedicated_project_path = mcp_servers_base_dir.join(&server_name);
+            let install_stash_path = mcp_servers_base_dir.join(INSTALL_STASH_DIR).join(&server_name);
 
             let assigned_port = loop {
                 if is_port_available(current_port).await {
@@ -139,216 +152,418 @@ pub async fn create_mcp_servers(use_sudo: bool) -> Result<Vec<McpServiceDefiniti
             };
             current_port += 1; 
 
-            let need_generate;
-            let spec_metadata = match fs::metadata(&spec_file_path) {
-                Ok(meta) => Some(meta),
-                Err(e) => {
-                    tracing::info!(target: "dev_runtime::mcp_server", path = %spec_file_path.display(), error = ?e, "Failed to get metadata for spec file. Skipping regeneration check.");
-                    if let Err(remove_err) = fs::remove_dir_all(&dedicated_project_path) {
-                        if remove_err.kind() != std::io::ErrorKind::NotFound {
-                            tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?remove_err, "Failed to delete old server directory before regeneration.");
-                        }
+            job.progress(processed, Some(spec_count as u64), format!("Generating {}", server_name));
+            processed += 1;
+            if !ensure_generated(&spec_file_path, &dedicated_project_path, &install_stash_path, &server_name, assigned_port, use_sudo).await {
+                continue;
+            }
+
+            let definition = McpServiceDefinition {
+                id: server_id,
+                name: server_name,
+                port: assigned_port,
+                openapi_spec_path_on_mcp: MCP_OPENAPI_SPEC_PATH.to_string(),
+            };
+            // Build and run the server under the supervisor, so it can be stopped and restarted alone
+            mcp_health::register(&definition);
+            supervisor::supervise_mcp_server(definition.clone(), dedicated_project_path, use_sudo);
+            mcp_definitions.push(definition);
+        }
+    }
+
+    if mcp_definitions.is_empty() {
+        tracing::info!(target: "dev_runtime::mcp_server", "No valid OpenAPI specifications found to generate and launch MCP servers.");
+    } else {
+        tracing::info!(target: "dev_runtime::mcp_server", count = mcp_definitions.len(), "All requested MCP server generation and launch tasks have been initiated and definitions collected.");
+    }
+    
+    Ok(mcp_definitions)
+}
+
+/// Generates the MCP server project for a spec with openapi-mcp-generator, unless it was
+/// generated from the spec's current contents already. Returns whether the project is ready
+/// to be installed, built and run.
+async fn ensure_generated(
+    spec_file_path: &Path,
+    dedicated_project_path: &Path,
+    install_stash_path: &Path,
+    server_name: &str,
+    assigned_port: u16,
+    use_sudo: bool,
+) -> bool {
+    // A server is stale when the spec it was generated from has changed since
+    let spec_fingerprint = fs::read(spec_file_path).ok().map(|content| config_files::spec_fingerprint(&content));
+    let fingerprint_path = dedicated_project_path.join(config_files::MCP_SPEC_FINGERPRINT_FILE);
+    let generated_from = fs::read_to_string(&fingerprint_path).ok();
+    let need_generate = match (&spec_fingerprint, &generated_from) {
+        (Some(current), Some(built)) => current != built.trim(),
+        _ => true,
+    };
+    if need_generate {
+        if dedicated_project_path.exists() {
+            tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Spec changed since the server was generated. Deleting and regenerating server.");
+            // npm install dominates a regeneration; its output survives if the dependencies don't change
+            if let Err(e) = stash_install(dedicated_project_path, install_stash_path) {
+                tracing::warn!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to keep node_modules aside; the regenerated server will install from scratch.");
+            }
+        }
+        if let Err(e) = fs::remove_dir_all(dedicated_project_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to delete old server directory before regeneration.");
+                return false;
+            }
+        }
+    } else {
+        tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Server was generated from the current spec, skipping openapi-mcp-generator step.");
+    }
+
+    if need_generate {
+        let spec_file_path_str = spec_file_path.to_string_lossy().to_string();
+        
+        if use_sudo {
+            // Use sudo to run as root
+            let generator_command_str = format!(
+                "sudo openapi-mcp-generator --input '{}' --output '{}' --transport=streamable-http --port={}",
+                spec_file_path_str,
+                dedicated_project_path.to_string_lossy(),
+                assigned_port
+            );
+            let mut generator_cmd = Command::new("bash");
+            generator_cmd.arg("-c").arg(&generator_command_str)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, command = %generator_command_str, "Running openapi-mcp-generator as root (sudo)...");
+            match generator_cmd.output().await {
+                Ok(generator_output) => {
+                    if !generator_output.status.success() {
+                        tracing::error!(target: "dev_runtime::mcp_server", 
+                            server_name = %server_name, 
+                            status = %generator_output.status,
+                            stdout = %String::from_utf8_lossy(&generator_output.stdout),
+                            stderr = %String::from_utf8_lossy(&generator_output.stderr),
+                            "openapi-mcp-generator failed for {}. Skipping server launch.", server_name);
+                        return false; 
                     }
-                    None
+                    tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "openapi-mcp-generator completed successfully.");
                 }
-            };
-            let server_metadata = match fs::metadata(&dedicated_project_path) {
-                Ok(meta) => Some(meta),
                 Err(e) => {
-                    tracing::info!(target: "dev_runtime::mcp_server", path = %dedicated_project_path.display(), error = ?e, "Failed to get metadata for server directory. Forcing regeneration.");
-                    if let Err(remove_err) = fs::remove_dir_all(&dedicated_project_path) {
-                        if remove_err.kind() != std::io::ErrorKind::NotFound {
-                            tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?remove_err, "Failed to delete old server directory before regeneration.");
-                        }
-                    }
-                    None
+                    tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to execute openapi-mcp-generator. Skipping server launch.");
+                    return false;
                 }
-            };
-            let spec_modified = spec_metadata.as_ref().and_then(|m| m.modified().ok());
-            let server_modified = server_metadata.as_ref().and_then(|m| m.modified().ok());
-            if let (Some(spec_time), Some(server_time)) = (spec_modified, server_modified) {
-                if spec_time > server_time {
-                    tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Spec file is newer than server directory. Deleting and regenerating server.");
-                    if let Err(e) = fs::remove_dir_all(&dedicated_project_path) {
-                        if e.kind() != std::io::ErrorKind::NotFound {
-                            tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to delete old server directory before regeneration.");
-                            continue;
-                        }
+            }
+        } else {
+            // Run openapi-mcp-generator normally (without sudo to avoid password prompt)
+            let mut generator_cmd = Command::new("openapi-mcp-generator");
+            generator_cmd.arg("--input")
+               .arg(&spec_file_path_str)
+               .arg("--output")
+               .arg(dedicated_project_path.to_string_lossy().as_ref())
+               .arg("--transport=streamable-http")
+               .arg(format!("--port={}", assigned_port))
+               .stdout(Stdio::piped())
+               .stderr(Stdio::piped());
+            
+            tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Running openapi-mcp-generator...");
+            match generator_cmd.output().await {
+                Ok(generator_output) => {
+                    if !generator_output.status.success() {
+                        tracing::error!(target: "dev_runtime::mcp_server", 
+                            server_name = %server_name, 
+                            status = %generator_output.status,
+                            stdout = %String::from_utf8_lossy(&generator_output.stdout),
+                            stderr = %String::from_utf8_lossy(&generator_output.stderr),
+                            "openapi-mcp-generator failed for {}. Skipping server launch.", server_name);
+                        return false; 
                     }
-                    need_generate = true;
-                } else {
-                    tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Project directory already exists and is up to date, skipping openapi-mcp-generator step.");
-                    need_generate = false;
+                    tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "openapi-mcp-generator completed successfully.");
                 }
-            } else {
-                // If we can't get modification times, force regeneration
-                tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Could not determine modification times. Forcing regeneration.");
-                if let Err(e) = fs::remove_dir_all(&dedicated_project_path) {
-                    if e.kind() != std::io::ErrorKind::NotFound {
-                        tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to delete old server directory before regeneration.");
-                        continue;
-                    }
+                Err(e) => {
+                    tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to execute openapi-mcp-generator. Skipping server launch.");
+                    return false;
                 }
-                need_generate = true;
             }
+        }
+        
+        // Fix permissions on the generated directory to ensure npm can write to it
+        let chmod_command = if use_sudo {
+            format!("sudo chmod -R 777 {}", dedicated_project_path.to_string_lossy())
+        } else {
+            format!("chmod -R 777 {}", dedicated_project_path.to_string_lossy())
+        };
+        
+        tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, path = %dedicated_project_path.display(), command = %chmod_command, "Setting permissions on generated MCP server directory...");
+        let chmod_status = Command::new("bash")
+            .arg("-c")
+            .arg(&chmod_command)
+            .status()
+            .await;
+        match chmod_status {
+            Ok(status) if status.success() => {
+                tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Permissions set successfully.");
+            }
+            Ok(status) => {
+                tracing::warn!(target: "dev_runtime::mcp_server", server_name = %server_name, status = %status, "Failed to set permissions, but continuing anyway.");
+            }
+            Err(e) => {
+                tracing::warn!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to execute chmod command, but continuing anyway.");
+            }
+        }
 
-            if need_generate {
-                let spec_file_path_str = spec_file_path.to_string_lossy().to_string();
-                
-                if use_sudo {
-                    // Use sudo to run as root
-                    let generator_command_str = format!(
-                        "sudo openapi-mcp-generator --input '{}' --output '{}' --transport=streamable-http --port={}",
-                        spec_file_path_str,
-                        dedicated_project_path.to_string_lossy(),
-                        assigned_port
-                    );
-                    let mut generator_cmd = Command::new("bash");
-                    generator_cmd.arg("-c").arg(&generator_command_str)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped());
-                    tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, command = %generator_command_str, "Running openapi-mcp-generator as root (sudo)...");
-                    match generator_cmd.output().await {
-                        Ok(generator_output) => {
-                            if !generator_output.status.success() {
-                                tracing::error!(target: "dev_runtime::mcp_server", 
-                                    server_name = %server_name, 
-                                    status = %generator_output.status,
-                                    stdout = %String::from_utf8_lossy(&generator_output.stdout),
-                                    stderr = %String::from_utf8_lossy(&generator_output.stderr),
-                                    "openapi-mcp-generator failed for {}. Skipping server launch.", server_name);
-                                continue; 
-                            }
-                            tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "openapi-mcp-generator completed successfully.");
-                        }
-                        Err(e) => {
-                            tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to execute openapi-mcp-generator. Skipping server launch.");
-                            continue;
-                        }
-                    }
-                } else {
-                    // Run openapi-mcp-generator normally (without sudo to avoid password prompt)
-                    let mut generator_cmd = Command::new("openapi-mcp-generator");
-                    generator_cmd.arg("--input")
-                       .arg(&spec_file_path_str)
-                       .arg("--output")
-                       .arg(dedicated_project_path.to_string_lossy().as_ref())
-                       .arg("--transport=streamable-http")
-                       .arg(format!("--port={}", assigned_port))
-                       .stdout(Stdio::piped())
-                       .stderr(Stdio::piped());
-                    
-                    tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Running openapi-mcp-generator...");
-                    match generator_cmd.output().await {
-                        Ok(generator_output) => {
-                            if !generator_output.status.success() {
-                                tracing::error!(target: "dev_runtime::mcp_server", 
-                                    server_name = %server_name, 
-                                    status = %generator_output.status,
-                                    stdout = %String::from_utf8_lossy(&generator_output.stdout),
-                                    stderr = %String::from_utf8_lossy(&generator_output.stderr),
-                                    "openapi-mcp-generator failed for {}. Skipping server launch.", server_name);
-                                continue; 
-                            }
-                            tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "openapi-mcp-generator completed successfully.");
-                        }
-                        Err(e) => {
-                            tracing::error!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to execute openapi-mcp-generator. Skipping server launch.");
-                            continue;
-                        }
-                    }
-                }
-                
-                // Fix permissions on the generated directory to ensure npm can write to it
-                let chmod_command = if use_sudo {
-                    format!("sudo chmod -R 777 {}", dedicated_project_path.to_string_lossy())
-                } else {
-                    format!("chmod -R 777 {}", dedicated_project_path.to_string_lossy())
-                };
-                
-                tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, path = %dedicated_project_path.display(), command = %chmod_command, "Setting permissions on generated MCP server directory...");
-                let chmod_status = Command::new("bash")
-                    .arg("-c")
-                    .arg(&chmod_command)
-                    .status()
-                    .await;
-                match chmod_status {
-                    Ok(status) if status.success() => {
-                        tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Permissions set successfully.");
-                    }
-                    Ok(status) => {
-                        tracing::warn!(target: "dev_runtime::mcp_server", server_name = %server_name, status = %status, "Failed to set permissions, but continuing anyway.");
-                    }
-                    Err(e) => {
-                        tracing::warn!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to execute chmod command, but continuing anyway.");
-                    }
-                }
+        if restore_install(dedicated_project_path, install_stash_path) {
+            tracing::info!(target: "dev_runtime::mcp_server", server_name = %server_name, "Dependencies are unchanged, reusing the previous node_modules.");
+        }
+
+        if let Some(fingerprint) = &spec_fingerprint {
+            if let Err(e) = fs::write(&fingerprint_path, fingerprint) {
+                tracing::warn!(target: "dev_runtime::mcp_server", server_name = %server_name, error = ?e, "Failed to record the spec fingerprint; the server will be regenerated next start.");
             }
+        }
+    }
+    true
+}
 
-            // Always spawn a task to build and run this specific server
-            let dedicated_project_path_clone = dedicated_project_path.clone();
-            let server_id_clone = server_id.clone();
-            let server_name_clone = server_name.clone();
-            let assigned_port_clone = assigned_port;
-            let use_sudo_clone = use_sudo;
-            tokio::spawn(async move {
-                let proj_path = dedicated_project_path_clone;
-                let s_id = server_id_clone;
-                let s_name = server_name_clone;
-
-                if use_sudo_clone {
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, path = %proj_path.display(), "Running npm install with sudo...");
-                    if let Err(e) = npm::run_npm_command_with_sudo(&proj_path, &["install"], false).await {
-                        tracing::error!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, error = ?e, "npm install with sudo failed. Aborting launch for this server.");
-                        return;
-                    }
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "npm install completed.");
+/// Regenerates the MCP servers launched at startup whose spec file (a name in
+/// `openapi_specification/`) is among `changed_specs`, then starts them again unless they
+/// were stopped through the runtime API. A removed spec leaves its server stopped; specs of
+/// servers that weren't launched are picked up at the next start. Returns each server's id
+/// with the outcome.
+pub async fn regenerate_servers(changed_specs: &[String], job: &JobHandle) -> Result<Vec<(String, Result<()>)>> {
+    let _guard = REGENERATION_LOCK.lock().await;
+    let galatea_files_dir = paths::galatea_files_dir()?;
+    let openapi_spec_dir = galatea_files_dir.join("openapi_specification");
+    let mcp_servers_base_dir = galatea_files_dir.join("mcp_servers");
 
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, path = %proj_path.display(), "Running npm run build with sudo...");
-                    if let Err(e) = npm::run_npm_command_with_sudo(&proj_path, &["run", "build"], false).await {
-                        tracing::error!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, error = ?e, "npm run build with sudo failed. Aborting launch for this server.");
-                        return; 
-                    }
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "npm run build completed.");
-                } else {
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, path = %proj_path.display(), "Running npm install...");
-                    if let Err(e) = npm::run_npm_command(&proj_path, &["install"], false).await {
-                        tracing::error!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, error = ?e, "npm install failed. Aborting launch for this server.");
-                        return;
-                    }
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "npm install completed.");
+    let mut results = Vec::new();
+    for (index, file_name) in changed_specs.iter().enumerate() {
+        job.progress(index as u64, Some(changed_specs.len() as u64), format!("Regenerating from {}", file_name));
+        let Some(file_stem) = Path::new(file_name).file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let id = server_id(file_stem);
+        let Some((definition, project_path, use_sudo)) = supervisor::mcp_launch(&id) else {
+            continue;
+        };
+        let service = events::mcp_service_name(&id);
+        let stopped_by_user = supervisor::task_state(&service) == Some(supervisor::TaskState::Stopped);
+        let result = async {
+            if supervisor::task_state(&service) == Some(supervisor::TaskState::Running) {
+                supervisor::stop(&service).await.map_err(|e| anyhow!("Failed to stop {}: {}", service, e))?;
+            }
+            let spec_file_path = openapi_spec_dir.join(file_name);
+            if !spec_file_path.exists() {
+                bail!("{} was removed; the server stays stopped", file_name);
+            }
+            let install_stash_path = mcp_servers_base_dir.join(INSTALL_STASH_DIR).join(&definition.name);
+            if !ensure_generated(&spec_file_path, &project_path, &install_stash_path, &definition.name, definition.port, use_sudo).await {
+                bail!("openapi-mcp-generator failed for {}; see the logs", file_name);
+            }
+            if !stopped_by_user {
+                supervisor::start(&service).await.map_err(|e| anyhow!("Failed to start {}: {}", service, e))?;
+            }
+            Ok(())
+        }
+        .await;
+        match &result {
+            Ok(()) => tracing::info!(target: "dev_runtime::mcp_server", server_id = %id, "Regenerated MCP server from its changed spec."),
+            Err(e) => tracing::warn!(target: "dev_runtime::mcp_server", server_id = %id, error = %e, "Failed to regenerate MCP server."),
+        }
+        results.push((id, result));
+    }
+    Ok(results)
+}
 
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, path = %proj_path.display(), "Running npm run build...");
-                    if let Err(e) = npm::run_npm_command(&proj_path, &["run", "build"], false).await {
-                        tracing::error!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, error = ?e, "npm run build failed. Aborting launch for this server.");
-                        return; 
-                    }
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "npm run build completed.");
-                }
+/// Fingerprint of the dependencies a generated project's package.json declares. Names and
+/// descriptions change with the spec, so only the dependency sections count.
+fn dependencies_fingerprint(project: &Path) -> Option<String> {
+    let content = fs::read(project.join("package.json")).ok()?;
+    let manifest: serde_json::Value = serde_json::from_slice(&content).ok()?;
+    let sections: Vec<&serde_json::Value> = ["dependencies", "devDependencies", "optionalDependencies"]
+        .iter()
+        .map(|key| manifest.get(key).unwrap_or(&serde_json::Value::Null))
+        .collect();
+    Some(config_files::spec_fingerprint(serde_json::to_string(&sections).ok()?.as_bytes()))
+}
 
-                tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, path = %proj_path.display(), port = assigned_port_clone, "Running npm run start:http...");
-                if let Err(e) = util::spawn_background_command_in_dir(&proj_path, "npm", &["run", "start:http"], &format!("MCP Server {} ({})", s_name, s_id), None).await {
-                    tracing::error!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, error = ?e, "Failed to spawn 'npm run start:http'.");
-                } else {
-                    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, port = assigned_port_clone, "MCP server '{}' ({}) initiated on port {}.", s_name, s_id, assigned_port_clone);
-                }
-            });
-            
-            // Add definition after successfully initiating the generation and spawning the launch task
-            mcp_definitions.push(McpServiceDefinition {
-                id: server_id,
-                name: server_name,
-                port: assigned_port,
-                openapi_spec_path_on_mcp: MCP_OPENAPI_SPEC_PATH.to_string(),
-            });
+/// Fingerprint of everything `npm run build` compiles: package.json, tsconfig.json and `src/`.
+fn sources_fingerprint(project: &Path) -> Option<String> {
+    let mut files = vec![project.join("package.json"), project.join("tsconfig.json")];
+    let mut dirs = vec![project.join("src")];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).ok()?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    let mut content = Vec::new();
+    for file in files {
+        content.extend_from_slice(file.strip_prefix(project).unwrap_or(&file).to_string_lossy().as_bytes());
+        content.push(0);
+        content.extend(fs::read(&file).ok()?);
+        content.push(0);
+    }
+    Some(config_files::spec_fingerprint(&content))
+}
+
+fn fingerprint_matches(project: &Path, file_name: &str, current: Option<String>) -> bool {
+    match (current, fs::read_to_string(project.join(file_name))) {
+        (Some(current), Ok(recorded)) => current == recorded.trim(),
+        _ => false,
+    }
+}
+
+fn record_fingerprint(project: &Path, file_name: &str, fingerprint: Option<String>) {
+    if let Some(fingerprint) = fingerprint {
+        if let Err(e) = fs::write(project.join(file_name), fingerprint) {
+            tracing::warn!(target: "dev_runtime::mcp_server", path = %project.display(), error = ?e, "Failed to record {}; the next start will redo the step.", file_name);
         }
     }
+}
 
-    if mcp_definitions.is_empty() {
-        tracing::info!(target: "dev_runtime::mcp_server", "No valid OpenAPI specifications found to generate and launch MCP servers.");
+/// Whether node_modules was installed for the dependencies package.json declares now.
+fn install_is_current(project: &Path) -> bool {
+    project.join("node_modules").is_dir() && fingerprint_matches(project, DEPS_FINGERPRINT_FILE, dependencies_fingerprint(project))
+}
+
+/// Whether the build output was compiled from the current sources.
+fn build_is_current(project: &Path) -> bool {
+    project.join(BUILD_OUTPUT_DIR).is_dir() && fingerprint_matches(project, BUILD_FINGERPRINT_FILE, sources_fingerprint(project))
+}
+
+/// Moves a project's installed dependencies to `stash` before the project is deleted.
+fn stash_install(project: &Path, stash: &Path) -> Result<()> {
+    if !project.join("node_modules").is_dir() {
+        return Ok(());
+    }
+    if stash.exists() {
+        fs::remove_dir_all(stash).context(format!("Failed to clear {}", stash.display()))?;
+    }
+    fs::create_dir_all(stash).context(format!("Failed to create {}", stash.display()))?;
+    for name in STASHED_INSTALL_FILES {
+        let from = project.join(name);
+        if from.exists() {
+            fs::rename(&from, stash.join(name)).context(format!("Failed to move {} to {}", from.display(), stash.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves stashed dependencies into the regenerated project if they were installed for the same
+/// dependencies it declares, and returns whether it did. The stash is removed either way.
+fn restore_install(project: &Path, stash: &Path) -> bool {
+    let reusable = stash.join("node_modules").is_dir() && fingerprint_matches(stash, DEPS_FINGERPRINT_FILE, dependencies_fingerprint(project));
+    let restored = reusable
+        && STASHED_INSTALL_FILES.iter().all(|name| {
+            let from = stash.join(name);
+            !from.exists() || fs::rename(&from, project.join(name)).is_ok()
+        });
+    if stash.exists() {
+        if let Err(e) = fs::remove_dir_all(stash) {
+            tracing::warn!(target: "dev_runtime::mcp_server", path = %stash.display(), error = ?e, "Failed to remove stashed node_modules.");
+        }
+    }
+    restored
+}
+
+/// Installs, builds and runs one generated MCP server until it exits, recording its lifecycle in
+/// the runtime event log.
+pub async fn run_mcp_server(proj_path: PathBuf, s_id: String, s_name: String, port: u16, use_sudo: bool) {
+    let service = events::mcp_service_name(&s_id);
+    events::record_event(&service, ServiceEventKind::Starting, Some(format!("port {}", port)));
+    mcp_health::mark_building(&s_id);
+
+    let sudo_note = if use_sudo { " with sudo" } else { "" };
+    // Generated servers come with npm scripts and are stashed with their package-lock.json,
+    // whatever the project itself is installed with
+    let npm = PackageManager::Npm;
+    if install_is_current(&proj_path) {
+        tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "node_modules was installed for the current dependencies, skipping npm install.");
     } else {
-        tracing::info!(target: "dev_runtime::mcp_server", count = mcp_definitions.len(), "All requested MCP server generation and launch tasks have been initiated and definitions collected.");
+        tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, path = %proj_path.display(), "Running npm install{}...", sudo_note);
+        if let Err(e) = npm.install(&proj_path, use_sudo).await {
+            tracing::error!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, error = ?e, "npm install{} failed. Aborting launch for this server.", sudo_note);
+            events::record_event(&service, ServiceEventKind::Failed, Some(format!("npm install failed: {:#}", e)));
+            mcp_health::mark_exited(&s_id, Some(format!("npm install failed: {:#}", e)));
+            return;
+        }
+        record_fingerprint(&proj_path, DEPS_FINGERPRINT_FILE, dependencies_fingerprint(&proj_path));
+        tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "npm install completed.");
+    }
+
+    if build_is_current(&proj_path) {
+        tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "Build output is up to date with the sources, skipping npm run build.");
+    } else {
+        tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, path = %proj_path.display(), "Running npm run build{}...", sudo_note);
+        if let Err(e) = npm.run_script(&proj_path, "build", use_sudo).await {
+            tracing::error!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, error = ?e, "npm run build{} failed. Aborting launch for this server.", sudo_note);
+            events::record_event(&service, ServiceEventKind::Failed, Some(format!("npm run build failed: {:#}", e)));
+            mcp_health::mark_exited(&s_id, Some(format!("npm run build failed: {:#}", e)));
+            return;
+        }
+        record_fingerprint(&proj_path, BUILD_FINGERPRINT_FILE, sources_fingerprint(&proj_path));
+        tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "npm run build completed.");
+    }
+
+    tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, path = %proj_path.display(), port = port, "Running npm run start:http...");
+    events::record_event(&service, ServiceEventKind::Running, Some(format!("port {}", port)));
+    mcp_health::mark_launched(&s_id);
+    // Runs for the lifetime of the server, so its exit can be recorded
+    match util::run_command_in_dir(&proj_path, npm.name(), &["run", "start:http"], &format!("MCP Server {} ({})", s_name, s_id), None, Some(&service)).await {
+        Ok(()) => {
+            tracing::info!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, "MCP server exited.");
+            events::record_event(&service, ServiceEventKind::Stopped, None);
+            mcp_health::mark_exited(&s_id, None);
+        }
+        Err(e) => {
+            tracing::error!(target: "dev_runtime::mcp_server::lifecycle", server_id = %s_id, server_name = %s_name, error = ?e, "MCP server 'npm run start:http' failed.");
+            events::record_event(&service, ServiceEventKind::Failed, Some(format!("{:#}", e)));
+            mcp_health::mark_exited(&s_id, Some(format!("{:#}", e)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_modules_survive_regeneration_with_same_dependencies() {
+        let root = crate::test_support::project_tempdir();
+        let project = root.path().join("todo_mcp");
+        let stash = root.path().join(INSTALL_STASH_DIR).join("todo_mcp");
+        let generate = |title: &str, zod: &str| {
+            fs::create_dir_all(&project).unwrap();
+            let manifest = format!(r#"{{"name":"{}","dependencies":{{"zod":"{}"}}}}"#, title, zod);
+            fs::write(project.join("package.json"), manifest).unwrap();
+        };
+
+        generate("todo-v1", "^3.24.0");
+        fs::create_dir_all(project.join("node_modules/zod")).unwrap();
+        record_fingerprint(&project, DEPS_FINGERPRINT_FILE, dependencies_fingerprint(&project));
+        assert!(install_is_current(&project));
+
+        // Only the name changed: the install is reused
+        stash_install(&project, &stash).unwrap();
+        fs::remove_dir_all(&project).unwrap();
+        generate("todo-v2", "^3.24.0");
+        assert!(restore_install(&project, &stash));
+        assert!(install_is_current(&project) && !stash.exists());
+
+        // A dependency changed: it is installed from scratch
+        stash_install(&project, &stash).unwrap();
+        fs::remove_dir_all(&project).unwrap();
+        generate("todo-v2", "^3.25.0");
+        assert!(!restore_install(&project, &stash));
+        assert!(!install_is_current(&project) && !stash.exists());
+    }
+
+    #[test]
+    fn test_server_id_from_spec_name() {
+        assert_eq!(server_id("project_api"), "project");
+        assert_eq!(server_id("petstore"), "petstore");
     }
-    
-    Ok(mcp_definitions)
 }
@@ -1,13 +1,33 @@
+pub mod audit;
+pub mod capabilities;
+pub mod codex_session;
+pub mod crash;
+pub mod db;
+pub mod events;
+pub mod jobs;
+pub mod limits;
 pub mod log;
+pub mod log_hub;
 pub mod lsp_client;
+pub mod lsp_pool;
+pub mod lsp_trace;
+pub mod mcp_health;
 pub mod mcp_server;
 pub mod nextjs_dev_server;
+pub mod project_manifest;
+pub mod quotas;
+pub mod recovery;
+pub mod supervisor;
+pub mod telemetry;
 pub mod types;
 pub mod util;
+pub mod watchdog;
+pub mod workspaces;
 
 use anyhow::{Context, Result};
 use std::path::PathBuf;
 use tracing;
+use capabilities::{Capability, CapabilityState};
 use types::McpServiceDefinition;
 
 /// Launches the primary development runtime services.
@@ -24,24 +44,90 @@ pub async fn launch_runtime_services(
 ) -> Result<Vec<McpServiceDefinition>> {
     tracing::info!(target: "dev_runtime", "Starting runtime services...");
 
-    // Launch Next.js dev server as a detached task
-    let nextjs_project_dir_clone = project_dir.clone();
+    // Open the metadata store early so migrations run before anything records into it
+    if let Err(e) = db::with_db(|db| db.record_session(events::session_id(), env!("CARGO_PKG_VERSION"))) {
+        tracing::warn!(target: "dev_runtime", error = ?e, "Failed to open the metadata store; history and analytics will not be recorded.");
+    }
+
+    // Close out jobs and sessions a crashed predecessor left behind, and restore what it was running
+    let recovery = recovery::recover_after_restart();
+    let restore_mcp = !mcp_enabled && recovery::should_restore(&recovery, recovery::MCP_SERVICES);
+    if restore_mcp {
+        tracing::info!(target: "dev_runtime", "Relaunching MCP servers, which were running when Galatea last went away.");
+    }
+    let mcp_enabled = mcp_enabled || restore_mcp;
+    recovery::set_intent(recovery::MCP_SERVICES, mcp_enabled);
     tokio::spawn(async move {
-        tracing::info!(target: "dev_runtime", path = %nextjs_project_dir_clone.display(), "Attempting to start the Next.js development server in a background task...");
-        match nextjs_dev_server::launch_dev_server(&nextjs_project_dir_clone).await {
-            Ok(_) => {
-                tracing::info!(target: "dev_runtime", "Next.js development server process has finished or was fully spawned.")
-            }
-            Err(e) => {
-                tracing::error!(target: "dev_runtime", error = ?e, "Failed to start or monitor the Next.js development server.")
-            }
+        let mut recovery = recovery;
+        if restore_mcp {
+            recovery.restored_services.push(recovery::MCP_SERVICES.to_string());
+        }
+        recovery::finish_recovery(recovery).await;
+    });
+
+    // Watch edit history, disk and log buffer usage so clients are warned before limits hit
+    limits::start_monitor(project_dir.clone());
+
+    // Publish external edits (e.g. from Codex) to /api/fs so clients don't have to rescan
+    crate::file_system::watcher::start(project_dir.clone());
+    // Parse the project once up front; the watcher's changes keep the code index current
+    crate::codebase_indexing::index_manager::start(project_dir.clone());
+
+    // Endpoints needing git, a browser or embeddings check these instead of failing opaquely
+    capabilities::probe_environment();
+
+    // Launch the Next.js dev server under the supervisor, so it can be restarted through the API
+    supervisor::supervise_dev_server(project_dir.clone());
+    // Restart it when it crashes or hangs, instead of leaving the preview refusing connections
+    watchdog::start();
+
+    // Periodically regenerate galatea_files/CHANGELOG.md if configured
+    if let Some(minutes) = crate::dev_setup::config_files::get_config_value("changelog_interval_minutes")
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|m| *m > 0)
+    {
+        tracing::info!(target: "dev_runtime", minutes, "Scheduling periodic changelog generation.");
+        crate::dev_operation::changelog::spawn_changelog_scheduler(
+            project_dir.clone(),
+            std::time::Duration::from_secs(minutes * 60),
+        );
+    }
+
+    // Snapshot the project structure, publishing what changed while Galatea was down
+    let structure_project_dir = project_dir.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::dev_operation::structure::refresh(&structure_project_dir, "startup").await {
+            tracing::warn!(target: "dev_runtime", error = ?e, "Failed to refresh the project structure.");
         }
     });
+    if let Some(minutes) = crate::dev_setup::config_files::get_config_value("structure_refresh_interval_minutes")
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|m| *m > 0)
+    {
+        tracing::info!(target: "dev_runtime", minutes, "Scheduling periodic project structure refresh.");
+        crate::dev_operation::structure::spawn_structure_refresher(
+            project_dir.clone(),
+            std::time::Duration::from_secs(minutes * 60),
+        );
+    }
+
+    // Periodically run background analyzers that feed /api/suggestions, if configured
+    if let Some(minutes) = crate::dev_setup::config_files::get_config_value("suggestion_interval_minutes")
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|m| *m > 0)
+    {
+        tracing::info!(target: "dev_runtime", minutes, "Scheduling background suggestion analyzers.");
+        crate::dev_operation::suggestions::spawn_suggestion_scheduler(
+            project_dir.clone(),
+            std::time::Duration::from_secs(minutes * 60),
+        );
+    }
 
     let mut mcp_definitions = Vec::new();
 
     if mcp_enabled {
         tracing::info!(target: "dev_runtime", "MCP flag is enabled. Attempting to launch MCP servers...");
+        capabilities::set(Capability::Mcp, CapabilityState::Starting, None);
 
         // Ensure openapi-mcp-generator is installed
         match crate::dev_setup::mcp_converter::ensure_openapi_mcp_generator_installed(use_sudo).await {
@@ -50,23 +136,38 @@ pub async fn launch_runtime_services(
             }
             Err(e) => {
                 tracing::error!(target: "dev_runtime", error = ?e, "Failed to ensure openapi-mcp-generator is installed.");
+                capabilities::set(Capability::Mcp, CapabilityState::Failed, Some(format!("Installing openapi-mcp-generator failed: {:#}", e)));
                 return Err(e).context("Failed to ensure openapi-mcp-generator is installed");
             }
         }
 
-        // Await MCP server creation to get their definitions
-        match mcp_server::create_mcp_servers(use_sudo).await {
+        // Await MCP server creation to get their definitions; it's listed in /api/jobs meanwhile
+        let creation = jobs::run("mcp_generation", "Generate and start MCP servers from the OpenAPI specs".to_string(), move |job| async move {
+            let definitions = mcp_server::create_mcp_servers(use_sudo, &job).await?;
+            let summary = format!("Started {} MCP server(s)", definitions.len());
+            Ok((definitions, summary))
+        });
+        match creation.await {
             Ok(definitions) => {
                 tracing::info!(target: "dev_runtime", count = definitions.len(), "MCP server creation process completed.");
+                if definitions.is_empty() {
+                    capabilities::set(Capability::Mcp, CapabilityState::Unavailable, Some("No OpenAPI specifications to generate MCP servers from".to_string()));
+                } else {
+                    capabilities::set(Capability::Mcp, CapabilityState::Available, None);
+                }
+                // Poll each server until /mcp answers, then keep checking it
+                mcp_health::start_monitor();
                 mcp_definitions = definitions;
             }
             Err(e) => {
+                // Galatea keeps running without MCP; the proxy reports why
                 tracing::error!(target: "dev_runtime", error = ?e, "Failed to complete MCP server creation.");
-                // Depending on desired behavior, you might want to propagate this error
+                capabilities::set(Capability::Mcp, CapabilityState::Failed, Some(format!("Creating MCP servers failed: {:#}", e)));
             }
         }
     } else {
         tracing::info!(target: "dev_runtime", "MCP flag is not enabled. Skipping MCP server launch.");
+        capabilities::set(Capability::Mcp, CapabilityState::Disabled, Some("Start Galatea with --mcp to launch MCP servers".to_string()));
     }
 
     Ok(mcp_definitions)
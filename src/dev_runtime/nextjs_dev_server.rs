@@ -1,49 +1,576 @@
 use anyhow::{anyhow, Context, Result};
-use std::path::Path;
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command as TokioCommand;
 use tracing;
 
+use super::events::{self, ServiceEventKind, DEV_SERVER_SERVICE};
+use super::log_hub::{self, LogStream};
+use super::project_manifest;
+use super::util::now_ms;
+use crate::dev_setup::config_files;
 use crate::terminal;
 
+/// Port the dev server listens on, from the project manifest.
+pub fn dev_server_port() -> u16 {
+    project_manifest::current().port
+}
+
+const DEFAULT_READY_TIMEOUT_SECS: u64 = 30;
+
+// A single HTTP probe; the first request after startup blocks until the initial compile is done
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+const PROBE_INTERVAL: Duration = Duration::from_millis(500);
+
+// Bounds on what one build problem keeps, and on how many are kept
+const MAX_ISSUES: usize = 50;
+const MAX_MESSAGE_LINES: usize = 40;
+const MAX_STACK_FRAMES: usize = 30;
+
+/// Where the dev server is in its startup, as far as Galatea can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevServerPhase {
+    /// Not launched by this Galatea process (it may still be running externally)
+    NotStarted,
+    /// Spawned, no "ready" line yet
+    Starting,
+    /// A compile is in progress
+    Compiling,
+    /// Listening and done compiling
+    Ready,
+    /// The process exited
+    Exited,
+}
+
+impl DevServerPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DevServerPhase::NotStarted => "not_started",
+            DevServerPhase::Starting => "starting",
+            DevServerPhase::Compiling => "compiling",
+            DevServerPhase::Ready => "ready",
+            DevServerPhase::Exited => "exited",
+        }
+    }
+}
+
+struct ReadinessState {
+    phase: DevServerPhase,
+    since: Instant,
+    last_log_line: Option<String>,
+    /// A probe found the server serving since it last started; later requests skip probing
+    confirmed: bool,
+}
+
+static READINESS: Lazy<Mutex<ReadinessState>> = Lazy::new(|| {
+    Mutex::new(ReadinessState { phase: DevServerPhase::NotStarted, since: Instant::now(), last_log_line: None, confirmed: false })
+});
+
+// Shared by all probes so they reuse connections
+static PROBE_CLIENT: Lazy<reqwest::Client> =
+    Lazy::new(|| reqwest::Client::builder().timeout(PROBE_TIMEOUT).build().unwrap_or_default());
+
+/// Result of checking whether the dev server can serve requests.
+#[derive(Debug, Clone)]
+pub struct DevServerReadiness {
+    pub ready: bool,
+    pub phase: DevServerPhase,
+    /// Seconds spent in the current phase
+    pub phase_secs: u64,
+    /// Status of the last HTTP probe, if it got a response
+    pub http_status: Option<u16>,
+    /// How long the caller waited for readiness
+    pub waited_ms: u64,
+    pub last_log_line: Option<String>,
+    pub detail: String,
+}
+
+fn set_phase(phase: DevServerPhase, line: Option<&str>) {
+    let mut state = READINESS.lock().unwrap_or_else(|e| e.into_inner());
+    if state.phase != phase {
+        state.phase = phase;
+        state.since = Instant::now();
+    }
+    // A restarted or exited server has to be probed again; recompiles don't stop it serving
+    if matches!(phase, DevServerPhase::Starting | DevServerPhase::Exited) {
+        state.confirmed = false;
+    }
+    if let Some(line) = line {
+        state.last_log_line = Some(line.to_string());
+    }
+}
+
+// Recognizes the readiness lines of Next.js 12 (`ready - started server on`) through 15
+// (`✓ Ready in 1.2s`, `○ Compiling /page ...`, `✓ Compiled /page in 3s`)
+fn phase_from_log_line(line: &str) -> Option<DevServerPhase> {
+    let line = line.to_lowercase();
+    if line.contains("compiling") {
+        Some(DevServerPhase::Compiling)
+    } else if line.contains("ready in") || line.contains("ready -") || line.contains("started server on") || line.contains("compiled") {
+        Some(DevServerPhase::Ready)
+    } else {
+        None
+    }
+}
+
+/// Whether a build problem stops the page from rendering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildIssueSeverity {
+    Error,
+    Warning,
+}
+
+/// A compile error, runtime error or warning the dev server printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildIssue {
+    pub severity: BuildIssueSeverity,
+    /// Project-relative file the problem is in, when the output names one
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// The error message with its code frame, as printed
+    pub message: String,
+    /// `at ...` frames of a runtime error
+    pub stack: Vec<String>,
+    pub at_ms: u64, // Unix milliseconds when it was printed
+}
+
+/// A finished compile, which the dev server does on every hot reload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileRecord {
+    /// Route compiled, e.g. `/dashboard`; `None` for a full recompile
+    pub route: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub at_ms: u64,
+}
+
+/// Build health of the dev server, from its output since it last started.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BuildStatus {
+    /// Problems printed since the last successful compile, oldest first
+    pub errors: Vec<BuildIssue>,
+    pub warnings: Vec<BuildIssue>,
+    pub last_compile: Option<CompileRecord>,
+    /// Compiles finished since the server started; each one after the first is a hot reload
+    pub compile_count: u64,
+    /// When the errors last changed (Unix milliseconds)
+    pub changed_at_ms: Option<u64>,
+}
+
+struct BuildTracker {
+    status: BuildStatus,
+    project_dir: Option<PathBuf>,
+    /// Issue whose message and stack the following lines continue
+    open: Option<BuildIssue>,
+}
+
+static BUILD: Lazy<Mutex<BuildTracker>> =
+    Lazy::new(|| Mutex::new(BuildTracker { status: BuildStatus::default(), project_dir: None, open: None }));
+
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // CSI sequences end at their first letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// `./app/page.tsx:5:1`, `app/page.tsx (12:11) @ Page`, `/abs/app/page.tsx:3:1` -> (file, line, column)
+fn parse_location(text: &str) -> Option<(String, Option<u32>, Option<u32>)> {
+    let text = text.trim();
+    let (path, rest) = match text.find(" (") {
+        Some(i) => (&text[..i], text[i + 2..].split(')').next().unwrap_or("")),
+        None => (text.split(' ').next().unwrap_or(""), ""),
+    };
+    let mut parts = path.split(':');
+    let file = parts.next()?.trim_start_matches("./");
+    let has_extension = Path::new(file).extension().is_some_and(|ext| {
+        matches!(ext.to_str(), Some("ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" | "css" | "scss" | "json" | "mdx"))
+    });
+    if file.is_empty() || !has_extension {
+        return None;
+    }
+    let (mut line, mut column) = (parts.next().and_then(|p| p.parse().ok()), parts.next().and_then(|p| p.parse().ok()));
+    if line.is_none() {
+        let mut pos = rest.split(':');
+        line = pos.next().and_then(|p| p.trim().parse().ok());
+        column = pos.next().and_then(|p| p.trim().parse().ok());
+    }
+    Some((file.to_string(), line, column))
+}
+
+// What an output line means for the build
+enum BuildLine<'a> {
+    /// First line of an issue, with the rest of the line
+    IssueStart(BuildIssueSeverity, &'a str),
+    CompileStart,
+    Compiled { route: Option<String>, duration_ms: Option<u64> },
+    Other,
+}
+
+// `1.2s`, `312ms` -> milliseconds
+fn parse_duration_ms(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if let Some(ms) = text.strip_suffix("ms") {
+        return ms.trim().parse::<f64>().ok().map(|ms| ms as u64);
+    }
+    text.strip_suffix('s')?.trim().parse::<f64>().ok().map(|s| (s * 1000.0) as u64)
+}
+
+// Next.js 13-15 prefix lines with `⨯` (error), `⚠` (warning) and `✓ Compiled /route in 1.2s`;
+// Next.js 12 with `error -`, `warn  -` and `event - compiled client and server successfully in 312 ms`
+fn classify_build_line(line: &str) -> BuildLine<'_> {
+    let trimmed = line.trim_start();
+    if let Some(rest) = trimmed.strip_prefix('⨯').or_else(|| trimmed.strip_prefix("error -")) {
+        return BuildLine::IssueStart(BuildIssueSeverity::Error, rest.trim());
+    }
+    if trimmed.starts_with("Failed to compile") {
+        return BuildLine::IssueStart(BuildIssueSeverity::Error, "");
+    }
+    if let Some(rest) = trimmed.strip_prefix('⚠').or_else(|| trimmed.strip_prefix("warn  -")) {
+        return BuildLine::IssueStart(BuildIssueSeverity::Warning, rest.trim());
+    }
+    if trimmed.starts_with("○ Compiling") || trimmed.starts_with("wait  - compiling") {
+        return BuildLine::CompileStart;
+    }
+    if let Some(rest) = trimmed.strip_prefix("✓ Compiled").or_else(|| trimmed.strip_prefix("event - compiled")) {
+        let (target, duration) = match rest.rsplit_once(" in ") {
+            Some((target, duration)) => {
+                // `312ms (512 modules)` or `312 ms (123 modules)`
+                let mut words = duration.split_whitespace();
+                let amount = words.next().unwrap_or("");
+                let duration = if amount.ends_with(|c: char| c.is_ascii_digit()) {
+                    format!("{}{}", amount, words.next().unwrap_or(""))
+                } else {
+                    amount.to_string()
+                };
+                (target, duration)
+            }
+            None => (rest, String::new()),
+        };
+        let route = target.split_whitespace().find(|word| word.starts_with('/')).map(str::to_string);
+        return BuildLine::Compiled { route, duration_ms: parse_duration_ms(&duration) };
+    }
+    BuildLine::Other
+}
+
+impl BuildTracker {
+    fn relative(&self, file: &str) -> String {
+        let file = file.trim_start_matches("./");
+        match &self.project_dir {
+            Some(root) => Path::new(file).strip_prefix(root).map_or_else(|_| file.to_string(), |p| p.to_string_lossy().into_owned()),
+            None => file.to_string(),
+        }
+    }
+
+    fn locate(&self, issue: &mut BuildIssue, text: &str) {
+        if issue.file.is_none() {
+            if let Some((file, line, column)) = parse_location(text) {
+                issue.file = Some(self.relative(&file));
+                issue.line = line;
+                issue.column = column;
+            }
+        }
+    }
+
+    fn close_open(&mut self) {
+        let Some(mut issue) = self.open.take() else { return };
+        issue.message = issue.message.trim_end().to_string();
+        if issue.message.is_empty() && issue.file.is_none() {
+            return;
+        }
+        let list = match issue.severity {
+            BuildIssueSeverity::Error => &mut self.status.errors,
+            BuildIssueSeverity::Warning => &mut self.status.warnings,
+        };
+        // Next.js repeats a runtime error on every request for the page
+        if list.iter().any(|known| known.file == issue.file && known.message == issue.message) {
+            return;
+        }
+        if list.len() >= MAX_ISSUES {
+            list.remove(0);
+        }
+        if issue.severity == BuildIssueSeverity::Error {
+            self.status.changed_at_ms = Some(issue.at_ms);
+        }
+        list.push(issue);
+    }
+
+    fn observe(&mut self, raw: &str) {
+        let line = strip_ansi(raw);
+        match classify_build_line(&line) {
+            BuildLine::IssueStart(severity, rest) => {
+                self.close_open();
+                let mut issue = BuildIssue {
+                    severity,
+                    file: None,
+                    line: None,
+                    column: None,
+                    message: String::new(),
+                    stack: Vec::new(),
+                    at_ms: now_ms(),
+                };
+                self.locate(&mut issue, rest);
+                if issue.file.is_none() && !rest.is_empty() {
+                    issue.message = format!("{}\n", rest);
+                }
+                self.open = Some(issue);
+            }
+            BuildLine::CompileStart => self.close_open(),
+            BuildLine::Compiled { route, duration_ms } => {
+                self.close_open();
+                if !self.status.errors.is_empty() {
+                    self.status.changed_at_ms = Some(now_ms());
+                }
+                // A clean compile supersedes the problems printed before it
+                self.status.errors.clear();
+                self.status.warnings.clear();
+                self.status.compile_count += 1;
+                self.status.last_compile = Some(CompileRecord { route, duration_ms, at_ms: now_ms() });
+            }
+            BuildLine::Other => {
+                let mut open = match self.open.take() {
+                    Some(open) => open,
+                    None => return,
+                };
+                let trimmed = line.trim();
+                if trimmed.is_empty() && !open.message.trim().is_empty() {
+                    self.open = Some(open);
+                    self.close_open();
+                    return;
+                }
+                if let Some(frame) = trimmed.strip_prefix("at ") {
+                    if open.stack.len() < MAX_STACK_FRAMES {
+                        open.stack.push(frame.to_string());
+                    }
+                    // The first frame with a source location places an error that didn't name its file
+                    // (`webpack-internal:///(rsc)/./app/page.tsx:12:11`)
+                    let location = frame.rsplit_once(" (").map_or(frame, |(_, loc)| loc.trim_end_matches(')'));
+                    let location = location.rsplit_once(")/").map_or(location, |(_, path)| path);
+                    let location = location.strip_prefix("file://").unwrap_or(location);
+                    self.locate(&mut open, location);
+                } else if open.message.lines().count() < MAX_MESSAGE_LINES {
+                    // swc code frames name the file as `,-[/abs/app/page.tsx:3:1]`
+                    if let Some(location) = trimmed.strip_prefix(",-[").and_then(|l| l.strip_suffix(']')) {
+                        self.locate(&mut open, location);
+                    } else if open.message.is_empty() && open.file.is_none() {
+                        self.locate(&mut open, trimmed);
+                        if open.file.is_some() {
+                            self.open = Some(open);
+                            return;
+                        }
+                    }
+                    open.message.push_str(line.trim_end());
+                    open.message.push('\n');
+                }
+                self.open = Some(open);
+            }
+        }
+    }
+}
+
+fn reset_build_status(project_dir: &Path) {
+    let mut tracker = BUILD.lock().unwrap_or_else(|e| e.into_inner());
+    tracker.status = BuildStatus::default();
+    tracker.open = None;
+    tracker.project_dir = Some(dunce::canonicalize(project_dir).unwrap_or_else(|_| project_dir.to_path_buf()));
+}
+
+fn observe_build_line(line: &str) {
+    BUILD.lock().unwrap_or_else(|e| e.into_inner()).observe(line);
+}
+
+/// Compile errors, runtime errors and warnings the dev server printed since its last successful
+/// compile, and its latest compile.
+pub fn build_status() -> BuildStatus {
+    let mut tracker = BUILD.lock().unwrap_or_else(|e| e.into_inner());
+    // An issue is complete once its output stops, so don't leave the last one out
+    tracker.close_open();
+    tracker.status.clone()
+}
+
+/// How long requests wait for the dev server, from `dev_server_ready_timeout_secs` in config.toml.
+pub fn ready_timeout() -> Duration {
+    let secs = config_files::get_config_section("dev_server_ready_timeout_secs")
+        .and_then(|v| v.as_integer().map(|i| i.max(0) as u64).or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+        .unwrap_or(DEFAULT_READY_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Checks readiness once: the log must report the server ready (unless it was started outside
+/// Galatea) and an HTTP request must get a non-5xx response.
+pub async fn probe_readiness() -> DevServerReadiness {
+    let (phase, phase_secs, last_log_line) = {
+        let state = READINESS.lock().unwrap_or_else(|e| e.into_inner());
+        (state.phase, state.since.elapsed().as_secs(), state.last_log_line.clone())
+    };
+    let mut readiness = DevServerReadiness {
+        ready: false,
+        phase,
+        phase_secs,
+        http_status: None,
+        waited_ms: 0,
+        last_log_line,
+        detail: String::new(),
+    };
+    match phase {
+        DevServerPhase::Starting => {
+            readiness.detail = "Dev server is starting, waiting for its ready log line".to_string();
+            return readiness;
+        }
+        DevServerPhase::Exited => {
+            readiness.detail = "Dev server process has exited".to_string();
+            return readiness;
+        }
+        _ => {}
+    }
+
+    match PROBE_CLIENT.get(format!("http://127.0.0.1:{}/", dev_server_port())).send().await {
+        Ok(resp) => {
+            let status = resp.status().as_u16();
+            readiness.http_status = Some(status);
+            readiness.ready = status < 500;
+            readiness.detail = if readiness.ready {
+                "Dev server is ready".to_string()
+            } else {
+                format!("Dev server answered with HTTP {}", status)
+            };
+        }
+        Err(e) if e.is_timeout() => readiness.detail = "Dev server is still compiling".to_string(),
+        Err(e) => readiness.detail = format!("Dev server is not accepting connections: {}", e),
+    }
+    READINESS.lock().unwrap_or_else(|e| e.into_inner()).confirmed = readiness.ready;
+    readiness
+}
+
+/// Forgets that the dev server was found ready, so the next request probes it again; for when
+/// a request couldn't connect to it.
+pub fn mark_unreachable() {
+    READINESS.lock().unwrap_or_else(|e| e.into_inner()).confirmed = false;
+}
+
+/// Polls until the dev server is ready, it exits, or `timeout` passes. Answers at once, without
+/// probing, when an earlier probe found it ready and it hasn't restarted since.
+pub async fn wait_until_ready(timeout: Duration) -> DevServerReadiness {
+    {
+        let state = READINESS.lock().unwrap_or_else(|e| e.into_inner());
+        if state.confirmed {
+            return DevServerReadiness {
+                ready: true,
+                phase: state.phase,
+                phase_secs: state.since.elapsed().as_secs(),
+                http_status: None,
+                waited_ms: 0,
+                last_log_line: state.last_log_line.clone(),
+                detail: "Dev server is ready".to_string(),
+            };
+        }
+    }
+    let started = Instant::now();
+    loop {
+        let mut readiness = probe_readiness().await;
+        readiness.waited_ms = started.elapsed().as_millis() as u64;
+        if readiness.ready || readiness.phase == DevServerPhase::Exited || started.elapsed() + PROBE_INTERVAL > timeout {
+            return readiness;
+        }
+        tokio::time::sleep(PROBE_INTERVAL).await;
+    }
+}
+
+/// Current startup phase, without probing the server.
+pub fn phase() -> DevServerPhase {
+    READINESS.lock().unwrap_or_else(|e| e.into_inner()).phase
+}
+
+/// Marks the dev server as exited, for when it was stopped without its process exiting on its own.
+pub fn mark_stopped() {
+    set_phase(DevServerPhase::Exited, None);
+}
+
+/// Runs the project's dev command (`pnpm run dev` for Next.js) until it exits, recording its lifecycle in the runtime event log.
 pub async fn launch_dev_server(project_dir: &Path) -> Result<()> {
-    terminal::port::ensure_port_is_free(3000, "Next.js dev server")
+    events::record_event(DEV_SERVER_SERVICE, ServiceEventKind::Starting, None);
+    set_phase(DevServerPhase::Starting, None);
+    reset_build_status(project_dir);
+    let result = run_dev_server(project_dir).await;
+    set_phase(DevServerPhase::Exited, None);
+    match &result {
+        Ok(()) => events::record_event(DEV_SERVER_SERVICE, ServiceEventKind::Stopped, None),
+        Err(e) => events::record_event(DEV_SERVER_SERVICE, ServiceEventKind::Failed, Some(format!("{:#}", e))),
+    }
+    result
+}
+
+async fn run_dev_server(project_dir: &Path) -> Result<()> {
+    let manifest = project_manifest::load(project_dir);
+    terminal::port::ensure_port_is_free(manifest.port, "Next.js dev server")
         .await
-        .context("Failed to ensure Next.js dev server port (3000) is free before starting")?;
+        .context(format!("Failed to ensure dev server port ({}) is free before starting", manifest.port))?;
 
+    let dev_command = manifest.dev.join(" ");
     tracing::info!(
         target: "dev_runtime::nextjs",
         project_dir = %project_dir.display(),
-        "Attempting to start 'pnpm run dev'"
+        framework = %manifest.framework,
+        port = manifest.port,
+        "Attempting to start '{}'", dev_command
     );
 
-    let mut cmd = TokioCommand::new("pnpm");
+    let (program, args) = manifest.dev.split_first().context("The project's dev command is empty")?;
+    let mut cmd = TokioCommand::new(program);
     cmd.current_dir(project_dir);
-    cmd.args(&["run", "dev"]);
+    cmd.args(args);
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
+    // Stopping the server through the supervisor cancels this task
+    cmd.kill_on_drop(true);
 
     let mut child = cmd.spawn().with_context(|| {
         format!(
-            "dev_runtime::nextjs: Failed to spawn 'pnpm run dev' in {}. Ensure pnpm is installed and the script exists.",
-            project_dir.display()
+            "dev_runtime::nextjs: Failed to spawn '{}' in {}. Ensure {} is installed and the script exists.",
+            dev_command,
+            project_dir.display(),
+            program
         )
     })?;
+    events::record_event(
+        DEV_SERVER_SERVICE,
+        ServiceEventKind::Running,
+        Some(format!("pid {}, port {}", child.id().unwrap_or_default(), manifest.port)),
+    );
 
     let stdout = child
         .stdout
         .take()
-        .context("dev_runtime::nextjs: Failed to capture stdout from 'pnpm run dev'")?;
+        .context("dev_runtime::nextjs: Failed to capture stdout from the dev server")?;
     let stderr = child
         .stderr
         .take()
-        .context("dev_runtime::nextjs: Failed to capture stderr from 'pnpm run dev'")?;
+        .context("dev_runtime::nextjs: Failed to capture stderr from the dev server")?;
 
     let stdout_task = tokio::spawn(async move {
         let mut reader = BufReader::new(stdout).lines();
         while let Ok(Some(line)) = reader.next_line().await {
             tracing::info!(target: "dev_runtime::nextjs::pnpm_stdout", source_process = "next_dev_server", "{}", line);
+            log_hub::push_output(DEV_SERVER_SERVICE, LogStream::Stdout, &line);
+            observe_build_line(&line);
+            if let Some(phase) = phase_from_log_line(&line) {
+                set_phase(phase, Some(&line));
+            }
         }
     });
 
@@ -51,27 +578,80 @@ pub async fn launch_dev_server(project_dir: &Path) -> Result<()> {
         let mut reader = BufReader::new(stderr).lines();
         while let Ok(Some(line)) = reader.next_line().await {
             tracing::warn!(target: "dev_runtime::nextjs::pnpm_stderr", source_process = "next_dev_server", "{}", line);
+            log_hub::push_output(DEV_SERVER_SERVICE, LogStream::Stderr, &line);
+            observe_build_line(&line);
         }
     });
 
     let status = child
         .wait()
         .await
-        .with_context(|| "dev_runtime::nextjs: 'pnpm run dev' process failed to wait")?;
+        .with_context(|| format!("dev_runtime::nextjs: '{}' process failed to wait", dev_command))?;
 
     let _ = stdout_task.await;
     let _ = stderr_task.await;
 
     if status.success() {
-        let success_msg = format!("'pnpm run dev' completed successfully (status: {}).", status);
+        let success_msg = format!("'{}' completed successfully (status: {}).", dev_command, status);
         tracing::info!(target: "dev_runtime::nextjs", source_process = "next_dev_server", "{}", success_msg);
         Ok(())
     } else {
         let err_msg = format!(
-            "dev_runtime::nextjs: 'pnpm run dev' exited with status: {}. Check output above for details.",
+            "dev_runtime::nextjs: '{}' exited with status: {}. Check output above for details.",
+            dev_command,
             status
         );
         tracing::error!(target: "dev_runtime::nextjs", source_process = "next_dev_server", "{}", err_msg);
         Err(anyhow!("{}", err_msg))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_from_log_line() {
+        assert_eq!(phase_from_log_line("   ✓ Ready in 2.1s"), Some(DevServerPhase::Ready));
+        assert_eq!(phase_from_log_line("ready - started server on 0.0.0.0:3000, url: http://localhost:3000"), Some(DevServerPhase::Ready));
+        assert_eq!(phase_from_log_line(" ○ Compiling / ..."), Some(DevServerPhase::Compiling));
+        assert_eq!(phase_from_log_line(" ✓ Compiled / in 3.4s (512 modules)"), Some(DevServerPhase::Ready));
+        assert_eq!(phase_from_log_line("   - Local:        http://localhost:3000"), None);
+    }
+
+    #[test]
+    fn test_build_tracker() {
+        let mut tracker = BuildTracker { status: BuildStatus::default(), project_dir: Some(PathBuf::from("/work/shop")), open: None };
+        let output = [
+            " ○ Compiling / ...",
+            " ⨯ ./src/app/page.tsx:5:1",
+            "Module not found: Can't resolve './missing'",
+            "",
+            " ⨯ Error: boom",
+            "    at Page (webpack-internal:///(rsc)/./src/app/about/page.tsx:12:11)",
+            "    at stringify (<anonymous>)",
+            " ⨯ Error: boom",
+            "    at Page (webpack-internal:///(rsc)/./src/app/about/page.tsx:12:11)",
+            " ⚠ \u{1b}[33mFast Refresh had to perform a full reload\u{1b}[39m",
+        ];
+        for line in output {
+            tracker.observe(line);
+        }
+        tracker.close_open();
+        let errors = &tracker.status.errors;
+        assert_eq!(errors.len(), 2);
+        assert_eq!((errors[0].file.as_deref(), errors[0].line, errors[0].column), (Some("src/app/page.tsx"), Some(5), Some(1)));
+        assert_eq!(errors[0].message, "Module not found: Can't resolve './missing'");
+        assert_eq!(errors[1].message, "Error: boom");
+        assert_eq!((errors[1].file.as_deref(), errors[1].line), (Some("src/app/about/page.tsx"), Some(12)));
+        assert_eq!(errors[1].stack.len(), 2);
+        assert_eq!(tracker.status.warnings[0].message, "Fast Refresh had to perform a full reload");
+
+        tracker.observe(" ✓ Compiled /about in 312ms (512 modules)");
+        assert!(tracker.status.errors.is_empty());
+        let compile = tracker.status.last_compile.clone().unwrap();
+        assert_eq!((compile.route.as_deref(), compile.duration_ms), (Some("/about"), Some(312)));
+        assert_eq!(parse_location("/work/shop/app/page.tsx:3:1").map(|(f, ..)| tracker.relative(&f)).as_deref(), Some("app/page.tsx"));
+        assert_eq!(parse_location("app/page.tsx (12:11) @ Page"), Some(("app/page.tsx".to_string(), Some(12), Some(11))));
+    }
+}
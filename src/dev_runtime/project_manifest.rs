@@ -0,0 +1,187 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::dev_setup::template_registry::{self, TemplateDefinition};
+use crate::dev_setup::config_files;
+use crate::file_system::get_project_root;
+use crate::terminal::package_manager::PackageManager;
+
+// config.toml section
+const CONFIG_SECTION: &str = "project";
+
+/// Commands declared under `[project]` in config.toml, each replacing the detected one. Commands
+/// are split on whitespace, without shell quoting:
+///
+/// ```toml
+/// [project]
+/// dev = "pnpm exec vite --host 127.0.0.1 --port 8080"
+/// port = 8080
+/// test = "pnpm exec vitest run"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProjectConfig {
+    pub framework: Option<String>,
+    pub dev: Option<String>,
+    pub build: Option<String>,
+    pub lint: Option<String>,
+    pub test: Option<String>,
+    pub format: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl ProjectConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+/// How to run a project: its dev server, scripts and the port the dev server listens on. Each
+/// command is the program followed by its arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectManifest {
+    /// `nextjs`, `vite`, `remix`, `astro`, or `unknown`
+    pub framework: String,
+    pub dev: Vec<String>,
+    pub build: Option<Vec<String>>,
+    pub lint: Option<Vec<String>>,
+    pub test: Option<Vec<String>>,
+    pub format: Option<Vec<String>>,
+    pub port: u16,
+}
+
+// Framework from the project's dependencies, most specific first: Remix and Astro also use Vite
+fn detect_framework(package_json: &serde_json::Value) -> Option<&'static str> {
+    let has_dependency = |name: &str| {
+        ["dependencies", "devDependencies"].iter().any(|section| package_json.get(section).and_then(|deps| deps.get(name)).is_some())
+    };
+    if has_dependency("next") {
+        Some("nextjs")
+    } else if has_dependency("astro") {
+        Some("astro")
+    } else if has_dependency("@react-router/dev") || has_dependency("@remix-run/dev") {
+        Some("remix")
+    } else if has_dependency("vite") {
+        Some("vite")
+    } else {
+        None
+    }
+}
+
+fn split_command(command: &str) -> Vec<String> {
+    command.split_whitespace().map(str::to_string).collect()
+}
+
+/// Works out how to run the project in `project_dir` from its package.json. The dev server runs
+/// like the built-in template for its framework, preferring `template` when that matches; a
+/// project with no recognisable framework runs like `template`.
+pub fn detect(project_dir: &Path, package_manager: PackageManager, template: &TemplateDefinition) -> ProjectManifest {
+    let package_json: serde_json::Value = fs::read_to_string(project_dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let has_script = |name: &str| package_json.get("scripts").and_then(|scripts| scripts.get(name)).is_some();
+    let script = |name: &str| has_script(name).then(|| package_manager.script_command(name, &[]));
+
+    let framework = detect_framework(&package_json);
+    let runs_like = match framework {
+        Some(framework) if framework != template.framework => template_registry::all().iter().find(|t| t.framework == framework).unwrap_or(template),
+        _ => template,
+    };
+    let dev = if !has_script("dev") && has_script("start") {
+        package_manager.script_command("start", &[])
+    } else {
+        runs_like.dev_command(package_manager)
+    };
+
+    ProjectManifest {
+        framework: framework.unwrap_or("unknown").to_string(),
+        dev,
+        build: script("build"),
+        lint: script("lint"),
+        test: script("test"),
+        format: script("format"),
+        port: runs_like.port,
+    }
+}
+
+impl ProjectManifest {
+    /// Replaces detected commands with the ones declared in config.toml.
+    pub fn with_overrides(mut self, config: &ProjectConfig) -> Self {
+        let command = |declared: &Option<String>| declared.as_deref().map(split_command).filter(|c| !c.is_empty());
+        if let Some(framework) = &config.framework {
+            self.framework = framework.clone();
+        }
+        if let Some(dev) = command(&config.dev) {
+            self.dev = dev;
+        }
+        for (detected, declared) in [
+            (&mut self.build, &config.build),
+            (&mut self.lint, &config.lint),
+            (&mut self.test, &config.test),
+            (&mut self.format, &config.format),
+        ] {
+            if let Some(declared) = command(declared) {
+                *detected = Some(declared);
+            }
+        }
+        if let Some(port) = config.port {
+            self.port = port;
+        }
+        self
+    }
+}
+
+/// The manifest of the project in `dir`, with config.toml's `[project]` applied if `dir` is the
+/// project root.
+pub fn load(dir: &Path) -> ProjectManifest {
+    let manifest = detect(dir, PackageManager::detect(dir), template_registry::current());
+    let is_root = get_project_root().is_ok_and(|root| root == dir);
+    if is_root {
+        manifest.with_overrides(&ProjectConfig::load())
+    } else {
+        manifest
+    }
+}
+
+/// The manifest of the project Galatea serves.
+pub fn current() -> ProjectManifest {
+    match get_project_root() {
+        Ok(root) => load(&root),
+        Err(_) => detect(Path::new("."), PackageManager::current(), template_registry::current())
+            .with_overrides(&ProjectConfig::load()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_framework_commands_and_applies_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let nextjs = template_registry::find("nextjs").unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"scripts": {"dev": "vite", "build": "vite build", "lint": "eslint ."}, "devDependencies": {"vite": "^6.0.0"}}"#,
+        )
+        .unwrap();
+
+        let manifest = detect(dir.path(), PackageManager::Pnpm, nextjs);
+        assert_eq!((manifest.framework.as_str(), manifest.port), ("vite", 5173));
+        assert_eq!(manifest.dev[..4], ["pnpm", "run", "dev", "--host"]);
+        assert_eq!(manifest.lint, Some(vec!["pnpm".to_string(), "run".to_string(), "lint".to_string()]));
+        assert_eq!(manifest.test, None);
+
+        let config = ProjectConfig { test: Some("pnpm exec vitest run".to_string()), port: Some(8080), ..Default::default() };
+        let manifest = manifest.with_overrides(&config);
+        assert_eq!(manifest.test.unwrap(), ["pnpm", "exec", "vitest", "run"]);
+        assert_eq!(manifest.port, 8080);
+
+        // Nothing to go on: runs like the template
+        let empty = tempfile::tempdir().unwrap();
+        let manifest = detect(empty.path(), PackageManager::Npm, nextjs);
+        assert_eq!((manifest.dev.join(" ").as_str(), manifest.port), ("npm run dev", 3000));
+    }
+}
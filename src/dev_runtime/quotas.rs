@@ -0,0 +1,312 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::util::now_secs;
+use crate::dev_operation::editor;
+use crate::dev_setup::config_files;
+
+const HOURLY_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Request header naming the client session usage is also tracked for.
+pub const SESSION_HEADER: &str = "x-galatea-session";
+
+// Principal of requests carrying neither an API key nor a session
+const ANONYMOUS: &str = "anonymous";
+
+/// A resource quotas are enforced on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuotaMetric {
+    /// Editor mutations in the current hour
+    EditsPerHour,
+    /// `/api/editor/script` and `/api/terminal/exec` runs in the current hour
+    ScriptRunsPerHour,
+    /// CPU time of script subprocesses, in total
+    CpuSeconds,
+    /// Bytes written by editor mutations, in total
+    BytesWritten,
+}
+
+impl QuotaMetric {
+    pub const ALL: [QuotaMetric; 4] =
+        [QuotaMetric::EditsPerHour, QuotaMetric::ScriptRunsPerHour, QuotaMetric::CpuSeconds, QuotaMetric::BytesWritten];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaMetric::EditsPerHour => "edits_per_hour",
+            QuotaMetric::ScriptRunsPerHour => "script_runs_per_hour",
+            QuotaMetric::CpuSeconds => "cpu_seconds",
+            QuotaMetric::BytesWritten => "bytes_written",
+        }
+    }
+
+    fn is_hourly(&self) -> bool {
+        matches!(self, QuotaMetric::EditsPerHour | QuotaMetric::ScriptRunsPerHour)
+    }
+}
+
+/// Quotas from config.toml (`quota_<metric>`, e.g. `quota_edits_per_hour = "500"`), read once at
+/// startup. Unset or zero means unlimited. Every API key and every session gets the full quota.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuotaSettings {
+    limits: HashMap<QuotaMetric, f64>,
+}
+
+impl QuotaSettings {
+    fn load() -> Self {
+        let limits = QuotaMetric::ALL
+            .iter()
+            .filter_map(|metric| {
+                let value = config_files::get_config_value(&format!("quota_{}", metric.as_str()))?;
+                let limit: f64 = value.trim().parse().ok().filter(|n: &f64| *n > 0.0)?;
+                Some((*metric, limit))
+            })
+            .collect();
+        Self { limits }
+    }
+
+    pub fn limit(&self, metric: QuotaMetric) -> Option<f64> {
+        self.limits.get(&metric).copied()
+    }
+}
+
+static SETTINGS: Lazy<QuotaSettings> = Lazy::new(QuotaSettings::load);
+
+pub fn settings() -> &'static QuotaSettings {
+    &SETTINGS
+}
+
+#[derive(Debug, Clone)]
+struct Usage {
+    window_started: Instant,
+    edits: f64,
+    script_runs: f64,
+    cpu_seconds: f64,
+    bytes_written: f64,
+    last_active: u64,
+}
+
+impl Usage {
+    fn new() -> Self {
+        Self { window_started: Instant::now(), edits: 0.0, script_runs: 0.0, cpu_seconds: 0.0, bytes_written: 0.0, last_active: now_secs() }
+    }
+
+    // Starts a new hour once the current one is over
+    fn roll_window(&mut self) {
+        if self.window_started.elapsed() >= HOURLY_WINDOW {
+            self.window_started = Instant::now();
+            self.edits = 0.0;
+            self.script_runs = 0.0;
+        }
+    }
+
+    fn get(&self, metric: QuotaMetric) -> f64 {
+        match metric {
+            QuotaMetric::EditsPerHour => self.edits,
+            QuotaMetric::ScriptRunsPerHour => self.script_runs,
+            QuotaMetric::CpuSeconds => self.cpu_seconds,
+            QuotaMetric::BytesWritten => self.bytes_written,
+        }
+    }
+
+    fn get_mut(&mut self, metric: QuotaMetric) -> &mut f64 {
+        match metric {
+            QuotaMetric::EditsPerHour => &mut self.edits,
+            QuotaMetric::ScriptRunsPerHour => &mut self.script_runs,
+            QuotaMetric::CpuSeconds => &mut self.cpu_seconds,
+            QuotaMetric::BytesWritten => &mut self.bytes_written,
+        }
+    }
+
+    fn window_resets_in(&self) -> u64 {
+        HOURLY_WINDOW.saturating_sub(self.window_started.elapsed()).as_secs().max(1)
+    }
+}
+
+static USAGE: Lazy<Mutex<HashMap<String, Usage>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+tokio::task_local! {
+    // Principals of the API request being handled
+    static PRINCIPALS: Vec<String>;
+}
+
+/// Principals a request is accounted to: `key:<hash>` for the API key it carries (the Galatea
+/// token or `Authorization` header, hashed so it never shows up in usage reports) and
+/// `session:<id>` for the `x-galatea-session` header. `anonymous` when it has neither.
+pub fn principals_for(api_key: Option<&str>, session: Option<&str>) -> Vec<String> {
+    let mut principals = Vec::new();
+    if let Some(key) = api_key.filter(|k| !k.is_empty()) {
+        principals.push(format!("key:{}", &editor::content_hash(key)[..12]));
+    }
+    if let Some(session) = session.map(str::trim).filter(|s| !s.is_empty()) {
+        principals.push(format!("session:{}", session));
+    }
+    if principals.is_empty() {
+        principals.push(ANONYMOUS.to_string());
+    }
+    principals
+}
+
+/// Runs `future` (an API request) with its usage accounted to `principals`.
+pub async fn scope<F: Future>(principals: Vec<String>, future: F) -> F::Output {
+    PRINCIPALS.scope(principals, future).await
+}
+
+/// Principals of the request being handled. Empty outside a request (e.g. background jobs),
+/// whose work is neither charged nor limited.
+pub fn current_principals() -> Vec<String> {
+    PRINCIPALS.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// A quota a request ran into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaExceeded {
+    pub metric: QuotaMetric,
+    pub principal: String,
+    pub used: f64,
+    pub limit: f64,
+    /// When the hourly window resets; `None` for total quotas, which never reset
+    pub reset_secs: Option<u64>,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Quota '{}' exhausted for {}: {} of {} used", self.metric.as_str(), self.principal, self.used, self.limit)?;
+        match self.reset_secs {
+            Some(secs) => write!(f, "; the window resets in {}s.", secs),
+            None => write!(f, "; ask the operator to raise `quota_{}` in config.toml.", self.metric.as_str()),
+        }
+    }
+}
+
+fn check_usage(
+    usage: &mut HashMap<String, Usage>,
+    settings: &QuotaSettings,
+    principals: &[String],
+    metrics: &[QuotaMetric],
+) -> Result<(), QuotaExceeded> {
+    for principal in principals {
+        let Some(entry) = usage.get_mut(principal) else { continue };
+        entry.roll_window();
+        for metric in metrics {
+            let Some(limit) = settings.limit(*metric) else { continue };
+            let used = entry.get(*metric);
+            if used >= limit {
+                return Err(QuotaExceeded {
+                    metric: *metric,
+                    principal: principal.clone(),
+                    used,
+                    limit,
+                    reset_secs: metric.is_hourly().then(|| entry.window_resets_in()),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fails if any principal of the current request has used up one of `metrics`.
+pub fn check(metrics: &[QuotaMetric]) -> Result<(), QuotaExceeded> {
+    let principals = current_principals();
+    let mut usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    let result = check_usage(&mut usage, settings(), &principals, metrics);
+    if let Err(exceeded) = &result {
+        tracing::warn!(target: "dev_runtime::quotas", principal = %exceeded.principal, metric = exceeded.metric.as_str(), "Request refused: quota exhausted.");
+    }
+    result
+}
+
+fn charge_usage(usage: &mut HashMap<String, Usage>, principals: &[String], metric: QuotaMetric, amount: f64) {
+    for principal in principals {
+        let entry = usage.entry(principal.clone()).or_insert_with(Usage::new);
+        entry.roll_window();
+        *entry.get_mut(metric) += amount;
+        entry.last_active = now_secs();
+    }
+}
+
+/// Adds `amount` of `metric` to every principal of the current request.
+pub fn charge(metric: QuotaMetric, amount: f64) {
+    let principals = current_principals();
+    if principals.is_empty() || amount <= 0.0 {
+        return;
+    }
+    let mut usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    charge_usage(&mut usage, &principals, metric, amount);
+}
+
+/// CPU seconds used by reaped child processes so far. Differences around a subprocess run
+/// approximate its CPU time; children of concurrent requests finishing meanwhile are included.
+pub fn children_cpu_seconds() -> f64 {
+    #[cfg(unix)]
+    {
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        // SAFETY: getrusage only writes into the struct it is given
+        if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) } == 0 {
+            let secs = |t: libc::timeval| t.tv_sec as f64 + t.tv_usec as f64 / 1_000_000.0;
+            return secs(usage.ru_utime) + secs(usage.ru_stime);
+        }
+    }
+    0.0
+}
+
+/// What one principal has used, against the configured quotas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrincipalUsage {
+    pub principal: String,
+    pub used: Vec<(QuotaMetric, f64)>,
+    /// Seconds until the hourly counters reset
+    pub window_resets_in: u64,
+    pub last_active: u64,
+}
+
+/// Usage of every principal seen since startup, most recently active first.
+pub fn usage() -> Vec<PrincipalUsage> {
+    let mut usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut report: Vec<PrincipalUsage> = usage
+        .iter_mut()
+        .map(|(principal, entry)| {
+            entry.roll_window();
+            PrincipalUsage {
+                principal: principal.clone(),
+                used: QuotaMetric::ALL.iter().map(|m| (*m, entry.get(*m))).collect(),
+                window_resets_in: entry.window_resets_in(),
+                last_active: entry.last_active,
+            }
+        })
+        .collect();
+    report.sort_by(|a, b| b.last_active.cmp(&a.last_active).then_with(|| a.principal.cmp(&b.principal)));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quotas_apply_to_each_principal() {
+        let settings = QuotaSettings {
+            limits: HashMap::from([(QuotaMetric::EditsPerHour, 2.0), (QuotaMetric::BytesWritten, 100.0)]),
+        };
+        let mut usage = HashMap::new();
+        let agent = principals_for(Some("secret-token"), Some("run-1"));
+        assert_eq!(agent.len(), 2);
+        assert!(!agent[0].contains("secret"));
+        assert_eq!(principals_for(None, Some("  ")), vec![ANONYMOUS.to_string()]);
+
+        let edit = [QuotaMetric::EditsPerHour, QuotaMetric::BytesWritten];
+        charge_usage(&mut usage, &agent, QuotaMetric::EditsPerHour, 2.0);
+        let exceeded = check_usage(&mut usage, &settings, &agent, &edit).unwrap_err();
+        assert_eq!(exceeded.metric, QuotaMetric::EditsPerHour);
+        assert!(exceeded.reset_secs.is_some());
+        // Script runs are unlimited, and another session under a different key is unaffected
+        assert!(check_usage(&mut usage, &settings, &agent, &[QuotaMetric::ScriptRunsPerHour]).is_ok());
+        assert!(check_usage(&mut usage, &settings, &principals_for(Some("other"), Some("run-2")), &edit).is_ok());
+
+        // The same session under a new key is still limited by its session usage
+        let exceeded = check_usage(&mut usage, &settings, &principals_for(Some("other"), Some("run-1")), &edit).unwrap_err();
+        assert_eq!(exceeded.principal, "session:run-1");
+    }
+}
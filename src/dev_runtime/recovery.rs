@@ -0,0 +1,116 @@
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use super::db::{self, InterruptedJob};
+use super::events::{self, ServiceEventKind};
+use super::util::now_secs;
+use crate::dev_operation::sync;
+use crate::dev_setup::config_files;
+
+/// Supervisor intent for the MCP servers, which are only launched on request.
+pub const MCP_SERVICES: &str = "mcp";
+
+// config.toml key; set to "false" to keep services and syncs stopped after a crash
+const RESTORE_CONFIG_KEY: &str = "restore_after_crash";
+
+/// What was recovered from the state an earlier Galatea process left behind.
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    pub recovered_at: u64,
+    /// Earlier sessions that ended without a clean shutdown
+    pub crashed_sessions: Vec<String>,
+    /// Jobs those sessions left queued or running, now marked `interrupted`
+    pub interrupted_jobs: Vec<InterruptedJob>,
+    /// Services that were active when the previous process went away
+    pub lost_services: Vec<String>,
+    /// Services relaunched because the previous process wanted them running
+    pub restored_services: Vec<String>,
+    pub resumed_syncs: Vec<String>,
+    pub closed_syncs: Vec<String>,
+}
+
+static LAST_RECOVERY: Lazy<Mutex<Option<RecoveryReport>>> = Lazy::new(|| Mutex::new(None));
+
+fn restore_enabled() -> bool {
+    config_files::get_config_value(RESTORE_CONFIG_KEY).is_none_or(|v| v != "false")
+}
+
+/// Closes out what a crashed predecessor left behind: its session is ended as `crashed` and
+/// its unfinished jobs are marked `interrupted`. Call once at startup, after the current
+/// session is recorded.
+pub fn recover_after_restart() -> RecoveryReport {
+    let session = events::session_id();
+    let mut report = RecoveryReport {
+        recovered_at: now_secs(),
+        ..Default::default()
+    };
+    match db::with_db(|db| Ok((db.end_stale_sessions(session)?, db.interrupt_stale_jobs(session)?))) {
+        Ok((sessions, jobs)) => {
+            report.crashed_sessions = sessions;
+            report.interrupted_jobs = jobs;
+        }
+        Err(e) => tracing::warn!(target: "dev_runtime::recovery", error = ?e, "Failed to recover jobs and sessions."),
+    }
+    // The event log closes services orphaned by the previous process as `lost` when it loads
+    report.lost_services = events::events(None, None)
+        .into_iter()
+        .filter(|e| e.kind == ServiceEventKind::Lost && e.session == session)
+        .map(|e| e.service)
+        .collect();
+
+    if !report.crashed_sessions.is_empty() || !report.interrupted_jobs.is_empty() {
+        tracing::warn!(
+            target: "dev_runtime::recovery",
+            crashed_sessions = report.crashed_sessions.len(),
+            interrupted_jobs = report.interrupted_jobs.len(),
+            lost_services = ?report.lost_services,
+            "Recovered state left by a Galatea process that did not shut down cleanly."
+        );
+    }
+    report
+}
+
+/// Whether `service` should be relaunched: a crashed predecessor wanted it running.
+pub fn should_restore(report: &RecoveryReport, service: &str) -> bool {
+    if report.crashed_sessions.is_empty() || !restore_enabled() {
+        return false;
+    }
+    db::with_db(|db| db.service_intent(service)).ok().flatten().as_deref() == Some("running")
+}
+
+/// Records whether this process wants `service` running, for the next restart to restore.
+pub fn set_intent(service: &str, running: bool) {
+    let desired = if running { "running" } else { "stopped" };
+    let service = service.to_string();
+    db::write_later(move |db| {
+        if let Err(e) = db.set_service_intent(events::session_id(), &service, desired) {
+            tracing::debug!(target: "dev_runtime::recovery", service, error = ?e, "Failed to record service intent.");
+        }
+    });
+}
+
+/// Resumes continuous syncs left active by a crashed predecessor, then publishes the report.
+pub async fn finish_recovery(mut report: RecoveryReport) {
+    let syncs = sync::recover_sessions(!report.crashed_sessions.is_empty() && restore_enabled()).await;
+    report.resumed_syncs = syncs.resumed;
+    report.closed_syncs = syncs.closed;
+    if let Ok(mut last) = LAST_RECOVERY.lock() {
+        *last = Some(report);
+    }
+}
+
+/// The report of the recovery run at startup, once it has finished.
+pub fn last_recovery() -> Option<RecoveryReport> {
+    LAST_RECOVERY.lock().ok().and_then(|last| last.clone())
+}
+
+/// Marks the current session as cleanly shut down, so the next start doesn't treat it as a crash.
+pub fn mark_clean_shutdown() {
+    // Continuous syncs stopped on purpose are not resumed
+    for session in sync::list_sessions() {
+        sync::stop_session(&session.id);
+    }
+    if let Err(e) = db::with_db(|db| db.end_session(events::session_id(), "shutdown")) {
+        tracing::warn!(target: "dev_runtime::recovery", error = ?e, "Failed to record clean shutdown.");
+    }
+}
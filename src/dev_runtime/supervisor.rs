@@ -0,0 +1,267 @@
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::events::{self, ServiceEventKind, DEV_SERVER_SERVICE, LSP_SERVICE};
+use super::types::McpServiceDefinition;
+use super::{lsp_pool, mcp_server, nextjs_dev_server};
+use crate::terminal;
+
+// How long a health check may take per service
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+enum ServiceSpec {
+    DevServer { project_dir: PathBuf },
+    Mcp { definition: McpServiceDefinition, project_path: PathBuf, use_sudo: bool },
+}
+
+impl ServiceSpec {
+    fn port(&self) -> Option<u16> {
+        match self {
+            ServiceSpec::DevServer { .. } => Some(nextjs_dev_server::dev_server_port()),
+            ServiceSpec::Mcp { definition, .. } => Some(definition.port),
+        }
+    }
+
+    fn spawn(&self) -> JoinHandle<()> {
+        match self.clone() {
+            ServiceSpec::DevServer { project_dir } => tokio::spawn(async move {
+                tracing::info!(target: "dev_runtime::supervisor", path = %project_dir.display(), "Starting the Next.js development server...");
+                match nextjs_dev_server::launch_dev_server(&project_dir).await {
+                    Ok(_) => tracing::info!(target: "dev_runtime::supervisor", "Next.js development server process has finished."),
+                    Err(e) => tracing::error!(target: "dev_runtime::supervisor", error = ?e, "Failed to start or monitor the Next.js development server."),
+                }
+            }),
+            ServiceSpec::Mcp { definition, project_path, use_sudo } => tokio::spawn(mcp_server::run_mcp_server(
+                project_path,
+                definition.id,
+                definition.name,
+                definition.port,
+                use_sudo,
+            )),
+        }
+    }
+}
+
+// A process-backed service and the task that runs it
+struct Supervised {
+    spec: ServiceSpec,
+    task: Option<JoinHandle<()>>,
+}
+
+impl Supervised {
+    fn is_running(&self) -> bool {
+        self.task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+}
+
+static SERVICES: Lazy<Mutex<BTreeMap<String, Supervised>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+fn services() -> std::sync::MutexGuard<'static, BTreeMap<String, Supervised>> {
+    SERVICES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn supervise(name: String, spec: ServiceSpec) {
+    let task = spec.spawn();
+    services().insert(name, Supervised { spec, task: Some(task) });
+}
+
+/// Launches the Next.js dev server under supervision.
+pub fn supervise_dev_server(project_dir: PathBuf) {
+    supervise(DEV_SERVER_SERVICE.to_string(), ServiceSpec::DevServer { project_dir });
+}
+
+/// Installs, builds and runs a generated MCP server under supervision.
+pub fn supervise_mcp_server(definition: McpServiceDefinition, project_path: PathBuf, use_sudo: bool) {
+    let name = events::mcp_service_name(&definition.id);
+    supervise(name, ServiceSpec::Mcp { definition, project_path, use_sudo });
+}
+
+/// How a supervised MCP server was launched: its definition, generated project and whether
+/// with sudo. `None` if no server with that id was launched.
+pub fn mcp_launch(id: &str) -> Option<(McpServiceDefinition, PathBuf, bool)> {
+    match &services().get(&events::mcp_service_name(id))?.spec {
+        ServiceSpec::Mcp { definition, project_path, use_sudo } => Some((definition.clone(), project_path.clone(), *use_sudo)),
+        ServiceSpec::DevServer { .. } => None,
+    }
+}
+
+/// Whether a supervised service's task is running, and if not, why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    Running,
+    /// The process exited (or failed to start) on its own
+    Exited,
+    /// Stopped through the runtime API
+    Stopped,
+}
+
+/// State of a supervised service's task, or `None` if it isn't supervised.
+pub fn task_state(name: &str) -> Option<TaskState> {
+    services().get(name).map(|service| match &service.task {
+        Some(task) if !task.is_finished() => TaskState::Running,
+        Some(_) => TaskState::Exited,
+        None => TaskState::Stopped,
+    })
+}
+
+/// A service that can be controlled through the supervisor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub port: Option<u16>,
+    /// Whether Galatea currently has the service running (or starting)
+    pub running: bool,
+    /// Result of a live check: HTTP readiness for the dev server, a TCP connect for MCP servers,
+    /// an initialized client for the LSP. `None` while not running.
+    pub healthy: Option<bool>,
+}
+
+async fn port_accepts_connections(port: u16) -> bool {
+    matches!(
+        tokio::time::timeout(HEALTH_TIMEOUT, tokio::net::TcpStream::connect(("127.0.0.1", port))).await,
+        Ok(Ok(_))
+    )
+}
+
+async fn lsp_running() -> bool {
+    lsp_pool::shared().any_running()
+}
+
+async fn status_of(name: &str, port: Option<u16>, running: bool) -> ServiceStatus {
+    let healthy = if !running {
+        None
+    } else if name == DEV_SERVER_SERVICE {
+        Some(nextjs_dev_server::probe_readiness().await.ready)
+    } else if let Some(port) = port {
+        Some(port_accepts_connections(port).await)
+    } else {
+        None
+    };
+    ServiceStatus { name: name.to_string(), port, running, healthy }
+}
+
+/// Every controllable service: the dev server, MCP servers launched by this process, and the LSP.
+pub async fn statuses() -> Vec<ServiceStatus> {
+    let supervised: Vec<(String, Option<u16>, bool)> = services()
+        .iter()
+        .map(|(name, service)| (name.clone(), service.spec.port(), service.is_running()))
+        .collect();
+    let mut statuses = futures::future::join_all(
+        supervised.iter().map(|(name, port, running)| status_of(name, *port, *running)),
+    )
+    .await;
+    let lsp = lsp_running().await;
+    statuses.push(ServiceStatus { name: LSP_SERVICE.to_string(), port: None, running: lsp, healthy: lsp.then_some(true) });
+    statuses.sort_by(|a, b| a.name.cmp(&b.name));
+    statuses
+}
+
+/// Status of one service, or `None` if it isn't known to the supervisor.
+pub async fn status(name: &str) -> Option<ServiceStatus> {
+    statuses().await.into_iter().find(|s| s.name == name)
+}
+
+/// Why a start or stop was refused.
+#[derive(Debug)]
+pub enum ControlError {
+    NotFound(String),
+    Conflict(String),
+    Failed(anyhow::Error),
+}
+
+impl std::fmt::Display for ControlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ControlError::NotFound(msg) | ControlError::Conflict(msg) => write!(f, "{}", msg),
+            ControlError::Failed(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+fn not_found(name: &str) -> ControlError {
+    ControlError::NotFound(format!(
+        "Unknown service '{}'. Use `{}`, `{}` or `mcp:<id>` for MCP servers launched at startup.",
+        name, DEV_SERVER_SERVICE, LSP_SERVICE
+    ))
+}
+
+/// Starts a stopped service.
+pub async fn start(name: &str) -> Result<(), ControlError> {
+    if name == LSP_SERVICE {
+        if lsp_running().await {
+            return Err(ControlError::Conflict("The language server is already running".to_string()));
+        }
+        // Kicks off a background start; the client is ready once `healthy` turns true
+        let project_root = crate::file_system::get_project_root().map_err(ControlError::Failed)?;
+        lsp_pool::shared().ensure_started(&project_root);
+        return Ok(());
+    }
+    let mut services = services();
+    let service = services.get_mut(name).ok_or_else(|| not_found(name))?;
+    if service.is_running() {
+        return Err(ControlError::Conflict(format!("Service '{}' is already running", name)));
+    }
+    tracing::info!(target: "dev_runtime::supervisor", service = name, "Starting service.");
+    service.task = Some(service.spec.spawn());
+    Ok(())
+}
+
+/// Stops a running service: its task is cancelled, which kills the process, and whatever still
+/// holds its port (e.g. a `next-server` child) is killed too.
+pub async fn stop(name: &str) -> Result<(), ControlError> {
+    if name == LSP_SERVICE {
+        if !lsp_running().await {
+            return Err(ControlError::Conflict("The language server is not running".to_string()));
+        }
+        lsp_pool::shared().shutdown_all().await;
+        return Ok(());
+    }
+    let (task, port) = {
+        let mut services = services();
+        let service = services.get_mut(name).ok_or_else(|| not_found(name))?;
+        if !service.is_running() {
+            return Err(ControlError::Conflict(format!("Service '{}' is not running", name)));
+        }
+        (service.task.take(), service.spec.port())
+    };
+    tracing::info!(target: "dev_runtime::supervisor", service = name, "Stopping service.");
+    if let Some(task) = task {
+        task.abort();
+        // Cancelled tasks resolve with a JoinError, which is expected here
+        let _ = task.await;
+    }
+    if let Some(port) = port {
+        terminal::port::ensure_port_is_free(port, name).await.map_err(ControlError::Failed)?;
+    }
+    if name == DEV_SERVER_SERVICE {
+        nextjs_dev_server::mark_stopped();
+    }
+    events::record_event(name, ServiceEventKind::Stopped, Some("Stopped through the runtime API".to_string()));
+    Ok(())
+}
+
+/// Stops the service if it is running, then starts it again.
+pub async fn restart(name: &str) -> Result<(), ControlError> {
+    match stop(name).await {
+        Ok(()) | Err(ControlError::Conflict(_)) => {}
+        Err(e) => return Err(e),
+    }
+    start(name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_services_are_rejected() {
+        assert!(matches!(start("mcp:missing").await, Err(ControlError::NotFound(_))));
+        assert!(matches!(stop("mcp:missing").await, Err(ControlError::NotFound(_))));
+        assert!(status("mcp:missing").await.is_none());
+    }
+}
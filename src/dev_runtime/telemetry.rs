@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use poem::http::HeaderMap;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use super::events;
+use crate::dev_setup::config_files;
+
+/// Request header clients set to tag a request's spans with the task it belongs to.
+pub const TASK_HEADER: &str = "x-galatea-task";
+
+// Path OTLP/HTTP collectors receive traces on
+const TRACES_PATH: &str = "/v1/traces";
+
+const DEFAULT_SERVICE_NAME: &str = "galatea";
+
+static PROVIDER: OnceCell<SdkTracerProvider> = OnceCell::new();
+
+/// OTLP/HTTP endpoint to export traces to: `otlp_endpoint` in config.toml, else the standard
+/// `OTEL_EXPORTER_OTLP_ENDPOINT`. Tracing export is off when neither is set.
+pub fn otlp_endpoint() -> Option<String> {
+    config_files::get_config_value("otlp_endpoint")
+        .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+}
+
+// Collectors are usually configured by base URL, e.g. http://collector:4318
+fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with(TRACES_PATH) {
+        endpoint.to_string()
+    } else {
+        format!("{}{}", endpoint, TRACES_PATH)
+    }
+}
+
+/// Whether spans are being exported.
+pub fn is_enabled() -> bool {
+    PROVIDER.get().is_some()
+}
+
+/// Builds the layer exporting spans over OTLP/HTTP, or `None` when no endpoint is configured or
+/// the exporter can't be created. Each instance is identified by `otlp_service_name` (default
+/// `galatea`) and its session id as `service.instance.id`.
+pub fn layer<S>() -> Option<OpenTelemetryLayer<S, SdkTracer>>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    let endpoint = otlp_endpoint()?;
+    match build_provider(&traces_url(&endpoint)) {
+        Ok(provider) => {
+            let tracer = provider.tracer("galatea");
+            opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+            let _ = PROVIDER.set(provider);
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        Err(e) => {
+            // The subscriber isn't installed yet, so this can't go through tracing
+            eprintln!("Failed to set up OpenTelemetry export to {}: {:#}", endpoint, e);
+            None
+        }
+    }
+}
+
+fn build_provider(url: &str) -> Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(url)
+        .build()
+        .context("Failed to create the OTLP span exporter")?;
+    let service_name = config_files::get_config_value("otlp_service_name")
+        .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string());
+    let resource = Resource::builder()
+        .with_service_name(service_name)
+        .with_attribute(KeyValue::new("service.instance.id", events::session_id()))
+        .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+        .build();
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build())
+}
+
+/// Flushes pending spans. Called once before the process exits.
+pub fn shutdown() {
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!(target: "dev_runtime::telemetry", error = %e, "Failed to flush OpenTelemetry spans.");
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// Span for an incoming API request. A W3C `traceparent` header makes it part of the caller's
+/// trace; the task id comes from the `x-galatea-task` header.
+pub fn request_span(method: &str, path: &str, headers: &HeaderMap) -> tracing::Span {
+    let task = headers.get(TASK_HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let span = tracing::info_span!(
+        target: "dev_runtime::telemetry",
+        "http.request",
+        otel.name = %format!("{} {}", method, path),
+        otel.kind = "server",
+        otel.status_code = tracing::field::Empty,
+        http.request.method = %method,
+        url.path = %path,
+        http.response.status_code = tracing::field::Empty,
+        galatea.session_id = events::session_id(),
+        galatea.task_id = task,
+    );
+    if is_enabled() {
+        let parent = opentelemetry::global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(headers)));
+        let _ = span.set_parent(parent);
+    }
+    span
+}
+
+/// Records the outcome of a request on its span.
+pub fn record_status(span: &tracing::Span, status: u16) {
+    span.record("http.response.status_code", status);
+    if status >= 500 {
+        span.record("otel.status_code", "ERROR");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces_url() {
+        assert_eq!(traces_url("http://collector:4318"), "http://collector:4318/v1/traces");
+        assert_eq!(traces_url("http://collector:4318/"), "http://collector:4318/v1/traces");
+        assert_eq!(traces_url("http://collector:4318/v1/traces"), "http://collector:4318/v1/traces");
+    }
+}
@@ -1,18 +1,65 @@
 use anyhow::{anyhow, Context, Result};
 use std::path::Path;
 use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command as TokioCommand;
 use tracing;
 
+use super::log_hub::{self, LogStream};
+
+/// Current Unix time in seconds.
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Current Unix time in milliseconds.
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// Stable 64-bit FNV-1a hash of `bytes`, hex encoded. Unlike std's hasher it is the same across
+/// builds, so it can be stored and compared later.
+pub fn fnv1a_hex(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+/// Cuts the head off `text`, keeping its last `max` bytes on a char boundary after a line
+/// saying how many bytes were cut; `text` itself when it fits.
+pub fn truncate_head(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut start = text.len() - max;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("[... {} bytes truncated ...]\n{}", start, &text[start..])
+}
+
+/// Cuts the tail off `text`, keeping its first `max` bytes on a char boundary.
+pub fn truncate_tail(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
 /// Executes a command in the specified directory, waits for it to complete, and logs its output.
 /// This function is intended for commands that need to finish before proceeding (e.g., build steps).
+#[tracing::instrument(name = "process.run", skip_all, fields(process.command = %program, process.command_args = ?args, galatea.description = %command_description))]
 pub async fn run_command_in_dir(
     dir: &Path,
     program: &str,
     args: &[&str],
     command_description: &str,
     port_env: Option<u16>, // For passing PORT environment variable if needed by the command
+    log_source: Option<&str>, // Log hub source for the output; Galatea's own when `None`
 ) -> Result<()> {
     tracing::info!(
         target: "dev_runtime::util::run",
@@ -31,6 +78,8 @@ pub async fn run_command_in_dir(
     }
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
+    // Don't leave the process behind if the caller's task is cancelled
+    cmd.kill_on_drop(true);
 
     let mut child = cmd.spawn().with_context(|| {
         format!(
@@ -52,10 +101,14 @@ pub async fn run_command_in_dir(
     let log_target_stdout = format!("dev_runtime::run_stdout::{}", command_description.to_lowercase().replace(|c: char| !c.is_alphanumeric(), "_"));
     let log_target_stderr = format!("dev_runtime::run_stderr::{}", command_description.to_lowercase().replace(|c: char| !c.is_alphanumeric(), "_"));
 
+    let hub_source = log_source.unwrap_or(log_hub::GALATEA_SOURCE).to_string();
+    let stdout_source = hub_source.clone();
+
     let stdout_task = tokio::spawn(async move {
         let mut reader = BufReader::new(stdout).lines();
         while let Ok(Some(line)) = reader.next_line().await {
             tracing::info!(target: "dev_runtime::run_stdout", command_log_target = %log_target_stdout, "{}", line);
+            log_hub::push_output(&stdout_source, LogStream::Stdout, &line);
         }
     });
 
@@ -63,6 +116,7 @@ pub async fn run_command_in_dir(
         let mut reader = BufReader::new(stderr).lines();
         while let Ok(Some(line)) = reader.next_line().await {
             tracing::info!(target: "dev_runtime::run_stderr", command_log_target = %log_target_stderr, "{}", line);
+            log_hub::push_output(&hub_source, LogStream::Stderr, &line);
         }
     });
 
@@ -174,4 +228,23 @@ pub async fn spawn_background_command_in_dir(
         }
     });
     Ok(())
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation_keeps_char_boundaries() {
+        assert_eq!(truncate_head("short", 10), "short");
+        assert_eq!(truncate_head("aé-tail", 5), "[... 3 bytes truncated ...]\n-tail");
+        assert_eq!(truncate_head("héllo", 4), "[... 3 bytes truncated ...]\nllo");
+        assert_eq!(truncate_tail("short", 10), "short");
+        assert_eq!(truncate_tail("héllo", 2), "h");
+    }
+
+    #[test]
+    fn test_fnv1a_hex() {
+        assert_eq!(fnv1a_hex(b""), "cbf29ce484222325");
+        assert_eq!(fnv1a_hex(b"a"), "af63dc4c8601ec8c");
+    }
+}
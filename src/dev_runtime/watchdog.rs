@@ -0,0 +1,327 @@
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::events::{self, ServiceEventKind, DEV_SERVER_SERVICE};
+use super::log::LogLevel;
+use super::log_hub::{self, LogQuery};
+use super::nextjs_dev_server::{self, dev_server_port, DevServerPhase};
+use super::supervisor::{self, TaskState};
+use super::util::now_secs;
+use crate::dev_setup::config_files;
+
+// config.toml section
+const CONFIG_SECTION: &str = "dev_server_watchdog";
+// Any HTTP answer, even a 500 from a broken build, shows the server is alive
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_INCIDENTS: usize = 100;
+// Dev server output kept with an incident
+const INCIDENT_OUTPUT_LINES: usize = 10;
+
+/// Dev server watchdog settings, from `[dev_server_watchdog]` in config.toml:
+///
+/// ```toml
+/// [dev_server_watchdog]
+/// enabled = true
+/// interval_secs = 5            # how often the dev server is checked
+/// hung_after_secs = 60         # unanswered HTTP requests for this long count as hung
+/// startup_timeout_secs = 180   # a start that never logs its ready line counts as hung
+/// initial_backoff_secs = 2     # wait before the second restart in a row, doubling after
+/// max_backoff_secs = 300
+/// stable_secs = 60             # healthy this long resets the backoff
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    pub hung_after_secs: u64,
+    pub startup_timeout_secs: u64,
+    pub initial_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    pub stable_secs: u64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 5,
+            hung_after_secs: 60,
+            startup_timeout_secs: 180,
+            initial_backoff_secs: 2,
+            max_backoff_secs: 300,
+            stable_secs: 60,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+
+    // Wait after the `restarts`-th restart in a row before another one
+    fn backoff(&self, restarts: u32) -> Duration {
+        let factor = 2u64.saturating_pow(restarts.saturating_sub(1));
+        Duration::from_secs(self.initial_backoff_secs.saturating_mul(factor).min(self.max_backoff_secs))
+    }
+}
+
+/// Why the watchdog restarted the dev server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncidentKind {
+    /// The process exited on its own
+    Crashed,
+    /// The process ran but stopped answering HTTP requests, or never finished starting
+    Hung,
+}
+
+impl IncidentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IncidentKind::Crashed => "crashed",
+            IncidentKind::Hung => "hung",
+        }
+    }
+}
+
+/// A crash or hang the watchdog restarted the dev server for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub at: u64, // Unix seconds
+    pub kind: IncidentKind,
+    pub detail: String,
+    /// Restarts in a row so far, including this one
+    pub attempt: u32,
+    /// Wait before another restart is allowed
+    pub backoff_secs: u64,
+    /// Last warnings and errors the dev server printed
+    pub last_output: Vec<String>,
+    /// Error from the restart itself, if it failed
+    pub restart_error: Option<String>,
+}
+
+// What one check found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Observation {
+    Healthy,
+    /// Running but not answering HTTP requests
+    Unresponsive,
+    /// Running and still before its ready line
+    Starting,
+    Exited,
+    /// Stopped on purpose, or not supervised
+    Stopped,
+}
+
+#[derive(Default)]
+struct Tracker {
+    unhealthy_since: Option<Instant>,
+    healthy_since: Option<Instant>,
+    restarts_in_row: u32,
+    next_restart_at: Option<Instant>,
+}
+
+impl Tracker {
+    // Decides whether the observation calls for a restart, and records it if so
+    fn step(&mut self, config: &WatchdogConfig, observation: Observation, now: Instant) -> Option<(IncidentKind, String)> {
+        let unhealthy_for = |since: &mut Option<Instant>| now.duration_since(*since.get_or_insert(now));
+        let incident = match observation {
+            Observation::Stopped => {
+                self.unhealthy_since = None;
+                self.healthy_since = None;
+                return None;
+            }
+            Observation::Healthy => {
+                self.unhealthy_since = None;
+                let healthy_for = unhealthy_for(&mut self.healthy_since);
+                if healthy_for >= Duration::from_secs(config.stable_secs) {
+                    self.restarts_in_row = 0;
+                }
+                return None;
+            }
+            Observation::Exited => (IncidentKind::Crashed, "The dev server process exited".to_string()),
+            Observation::Starting => {
+                self.healthy_since = None;
+                let starting_for = unhealthy_for(&mut self.unhealthy_since);
+                if starting_for < Duration::from_secs(config.startup_timeout_secs) {
+                    return None;
+                }
+                (IncidentKind::Hung, format!("No ready line after {}s", starting_for.as_secs()))
+            }
+            Observation::Unresponsive => {
+                self.healthy_since = None;
+                let unresponsive_for = unhealthy_for(&mut self.unhealthy_since);
+                if unresponsive_for < Duration::from_secs(config.hung_after_secs) {
+                    return None;
+                }
+                (IncidentKind::Hung, format!("No HTTP answer for {}s", unresponsive_for.as_secs()))
+            }
+        };
+        if self.next_restart_at.is_some_and(|at| now < at) {
+            return None;
+        }
+        self.restarts_in_row += 1;
+        self.next_restart_at = Some(now + config.backoff(self.restarts_in_row));
+        self.unhealthy_since = None;
+        self.healthy_since = None;
+        Some(incident)
+    }
+}
+
+struct WatchdogState {
+    tracker: Tracker,
+    incidents: VecDeque<Incident>,
+    running: bool,
+}
+
+static STATE: Lazy<Mutex<WatchdogState>> =
+    Lazy::new(|| Mutex::new(WatchdogState { tracker: Tracker::default(), incidents: VecDeque::new(), running: false }));
+
+async fn observe() -> Observation {
+    match supervisor::task_state(DEV_SERVER_SERVICE) {
+        None | Some(TaskState::Stopped) => Observation::Stopped,
+        Some(TaskState::Exited) => Observation::Exited,
+        Some(TaskState::Running) if nextjs_dev_server::phase() == DevServerPhase::Starting => Observation::Starting,
+        Some(TaskState::Running) => {
+            let client = match reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() {
+                Ok(client) => client,
+                Err(_) => return Observation::Healthy,
+            };
+            match client.get(format!("http://127.0.0.1:{}/", dev_server_port())).send().await {
+                Ok(_) => Observation::Healthy,
+                Err(_) => Observation::Unresponsive,
+            }
+        }
+    }
+}
+
+fn last_output() -> Vec<String> {
+    let query = LogQuery {
+        sources: vec![DEV_SERVER_SERVICE.to_string()],
+        min_level: Some(LogLevel::Warn),
+        ..Default::default()
+    };
+    log_hub::query(&query, INCIDENT_OUTPUT_LINES).into_iter().map(|e| e.message).collect()
+}
+
+async fn check(config: &WatchdogConfig) {
+    let observation = observe().await;
+    let (kind, detail, attempt, backoff) = {
+        let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((kind, detail)) = state.tracker.step(config, observation, Instant::now()) else {
+            return;
+        };
+        let attempt = state.tracker.restarts_in_row;
+        (kind, detail, attempt, config.backoff(attempt))
+    };
+    tracing::warn!(target: "dev_runtime::watchdog", kind = kind.as_str(), attempt, detail = %detail, "Restarting the Next.js dev server.");
+    let mut incident = Incident {
+        at: now_secs(),
+        kind,
+        detail,
+        attempt,
+        backoff_secs: backoff.as_secs(),
+        last_output: last_output(),
+        restart_error: None,
+    };
+    events::record_event(
+        DEV_SERVER_SERVICE,
+        ServiceEventKind::Failed,
+        Some(format!("Watchdog: {} ({}); restart attempt {}", kind.as_str(), incident.detail, attempt)),
+    );
+    if let Err(e) = supervisor::restart(DEV_SERVER_SERVICE).await {
+        tracing::error!(target: "dev_runtime::watchdog", error = %e, "Watchdog failed to restart the Next.js dev server.");
+        incident.restart_error = Some(e.to_string());
+    }
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    if state.incidents.len() >= MAX_INCIDENTS {
+        state.incidents.pop_front();
+    }
+    state.incidents.push_back(incident);
+}
+
+/// Starts checking the supervised dev server in the background, restarting it when it crashes
+/// or hangs. Does nothing when disabled in config.toml or already started.
+pub fn start() {
+    let config = WatchdogConfig::load();
+    if !config.enabled {
+        tracing::info!(target: "dev_runtime::watchdog", "Dev server watchdog is disabled.");
+        return;
+    }
+    {
+        let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+        if state.running {
+            return;
+        }
+        state.running = true;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            check(&config).await;
+        }
+    });
+}
+
+/// What the watchdog is doing, for the runtime API.
+#[derive(Debug, Clone)]
+pub struct WatchdogStatus {
+    pub running: bool,
+    pub restarts_in_row: u32,
+    /// Seconds until another restart is allowed, while backing off
+    pub backoff_remaining_secs: Option<u64>,
+    /// Oldest first
+    pub incidents: Vec<Incident>,
+}
+
+pub fn status() -> WatchdogStatus {
+    let state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    WatchdogStatus {
+        running: state.running,
+        restarts_in_row: state.tracker.restarts_in_row,
+        backoff_remaining_secs: state.tracker.next_restart_at.filter(|at| *at > now).map(|at| (at - now).as_secs()),
+        incidents: state.incidents.iter().cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restarts_back_off_until_stable() {
+        let config = WatchdogConfig::default();
+        let mut tracker = Tracker::default();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(tracker.step(&config, Observation::Exited, at(0)).map(|i| i.0), Some(IncidentKind::Crashed));
+        // Backing off 2s, then 4s
+        assert!(tracker.step(&config, Observation::Exited, at(1)).is_none());
+        assert!(tracker.step(&config, Observation::Exited, at(2)).is_some());
+        assert!(tracker.step(&config, Observation::Exited, at(5)).is_none());
+        assert!(tracker.step(&config, Observation::Exited, at(6)).is_some());
+        assert_eq!(tracker.restarts_in_row, 3);
+
+        // A hang needs `hung_after_secs` of silence; a slow start gets `startup_timeout_secs`
+        assert!(tracker.step(&config, Observation::Starting, at(10)).is_none());
+        assert!(tracker.step(&config, Observation::Healthy, at(20)).is_none());
+        assert!(tracker.step(&config, Observation::Unresponsive, at(30)).is_none());
+        assert!(tracker.step(&config, Observation::Unresponsive, at(89)).is_none());
+        assert_eq!(tracker.step(&config, Observation::Unresponsive, at(90)).map(|i| i.0), Some(IncidentKind::Hung));
+        assert_eq!(tracker.restarts_in_row, 4);
+
+        assert!(tracker.step(&config, Observation::Healthy, at(100)).is_none());
+        assert!(tracker.step(&config, Observation::Healthy, at(160)).is_none());
+        assert_eq!(tracker.restarts_in_row, 0);
+        assert!(tracker.step(&config, Observation::Stopped, at(500)).is_none());
+        assert_eq!(config.backoff(20), Duration::from_secs(300));
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+use super::db::{self, StoredWorkspace};
+use crate::dev_setup::{nextjs, template};
+use crate::file_system::paths;
+
+/// Header naming the workspace an API request works in.
+pub const WORKSPACE_HEADER: &str = "x-galatea-workspace";
+/// Id of the default project (see `paths::project_dir`), which every install has.
+pub const DEFAULT_WORKSPACE: &str = "default";
+// Directory in galatea_files that scaffolded workspaces are created in
+const WORKSPACES_DIR: &str = "workspaces";
+
+tokio::task_local! {
+    // Root of the workspace the API request being handled is scoped to
+    static CURRENT_ROOT: PathBuf;
+}
+
+/// A project root API requests can be scoped to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub root: PathBuf,
+    pub created_at: Option<i64>, // Unix seconds; `None` for the default workspace
+}
+
+impl From<StoredWorkspace> for Workspace {
+    fn from(stored: StoredWorkspace) -> Self {
+        Self { id: stored.id, name: stored.name, root: PathBuf::from(stored.root), created_at: Some(stored.created_at) }
+    }
+}
+
+fn default_workspace() -> Result<Workspace> {
+    let root = paths::default_project_root()?;
+    Ok(Workspace {
+        id: DEFAULT_WORKSPACE.to_string(),
+        name: root.file_name().map_or_else(|| "project".to_string(), |name| name.to_string_lossy().into_owned()),
+        root,
+        created_at: None,
+    })
+}
+
+/// Every workspace: the default one (when its directory exists) first, then the registered
+/// ones, oldest first.
+pub fn list() -> Result<Vec<Workspace>> {
+    let mut workspaces: Vec<Workspace> = default_workspace().into_iter().collect();
+    workspaces.extend(db::with_db(|db| db.workspaces())?.into_iter().map(Workspace::from));
+    Ok(workspaces)
+}
+
+pub fn get(id: &str) -> Result<Option<Workspace>> {
+    if id == DEFAULT_WORKSPACE {
+        return default_workspace().map(Some);
+    }
+    Ok(db::with_db(|db| db.workspaces())?.into_iter().find(|w| w.id == id).map(Workspace::from))
+}
+
+fn workspaces_dir() -> Result<PathBuf> {
+    Ok(paths::galatea_files_dir()?.join(WORKSPACES_DIR))
+}
+
+// `My Shop!` -> `my-shop-1a2b3c`
+fn new_id(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug: String = slug.chars().take(32).collect();
+    let suffix = &uuid::Uuid::new_v4().simple().to_string()[..6];
+    if slug.is_empty() {
+        suffix.to_string()
+    } else {
+        format!("{}-{}", slug.trim_end_matches('-'), suffix)
+    }
+}
+
+fn check_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Workspace name must not be empty");
+    }
+    Ok(name)
+}
+
+/// Registers an existing directory as a workspace.
+pub fn register(name: &str, root: &Path) -> Result<Workspace> {
+    let name = check_name(name)?;
+    let root = dunce::canonicalize(root).with_context(|| format!("'{}' does not exist", root.display()))?;
+    if !root.is_dir() {
+        bail!("'{}' is not a directory", root.display());
+    }
+    if let Some(existing) = list()?.into_iter().find(|w| w.root == root) {
+        bail!("'{}' is already workspace '{}'", root.display(), existing.id);
+    }
+    let root_str = root.to_string_lossy();
+    let stored = db::with_db(|db| db.add_workspace(&new_id(name), name, &root_str))?;
+    tracing::info!(target: "dev_runtime::workspaces", id = %stored.id, root = %stored.root, "Workspace registered.");
+    Ok(stored.into())
+}
+
+/// Scaffolds a new project from `template` (the default template when `None`) in
+/// `galatea_files/workspaces/` and registers it. Takes as long as cloning
+/// the template and installing its dependencies.
+pub async fn scaffold(name: &str, template_name: Option<&str>, vars: &HashMap<String, String>) -> Result<Workspace> {
+    let name = check_name(name)?;
+    let id = new_id(name);
+    let dir = workspaces_dir()?;
+    std::fs::create_dir_all(&dir).context("Failed to create the workspaces directory")?;
+    let root = dir.join(&id);
+    if let Err(e) = nextjs::scaffold_nextjs_project(&root, template::resolve_template_url(template_name), vars).await {
+        let _ = std::fs::remove_dir_all(&root);
+        return Err(e.context(format!("Failed to scaffold workspace '{}'", name)));
+    }
+    let root_str = root.to_string_lossy();
+    let stored = db::with_db(|db| db.add_workspace(&id, name, &root_str))?;
+    tracing::info!(target: "dev_runtime::workspaces", id = %stored.id, root = %stored.root, "Workspace scaffolded.");
+    Ok(stored.into())
+}
+
+/// Unregisters a workspace, leaving its files in place. Returns whether it existed. The default
+/// workspace can't be removed.
+pub fn unregister(id: &str) -> Result<bool> {
+    if id == DEFAULT_WORKSPACE {
+        bail!("The default workspace can't be removed");
+    }
+    db::with_db(|db| db.remove_workspace(id))
+}
+
+/// Runs `future` (an API request) with `get_project_root` answering `root`.
+pub async fn scope<F: Future>(root: PathBuf, future: F) -> F::Output {
+    CURRENT_ROOT.scope(root, future).await
+}
+
+/// Runs `future` in the workspace a request was scoped to, as captured with `scoped_root`, for
+/// work a request hands to a task of its own; unscoped when `root` is `None`.
+pub async fn rescope<F: Future>(root: Option<PathBuf>, future: F) -> F::Output {
+    match root {
+        Some(root) => scope(root, future).await,
+        None => future.await,
+    }
+}
+
+/// Runs `f` with `get_project_root` answering `root`, for work a request moves to a blocking
+/// thread, which the task-local scope of `scope` doesn't reach.
+pub fn sync_scope<R>(root: PathBuf, f: impl FnOnce() -> R) -> R {
+    CURRENT_ROOT.sync_scope(root, f)
+}
+
+/// Root of the workspace the current request is scoped to; `None` outside a scoped request,
+/// which works in the default workspace.
+pub fn scoped_root() -> Option<PathBuf> {
+    CURRENT_ROOT.try_with(Clone::clone).ok()
+}
+
+/// Splits a workspace path prefix off a request path: `/w/shop-1a2b3c/api/editor/command` ->
+/// (`shop-1a2b3c`, `/api/editor/command`).
+pub fn split_path(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix("/w/")?;
+    let slash = rest.find('/')?;
+    let (id, inner) = rest.split_at(slash);
+    (!id.is_empty()).then_some((id, inner))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ids_paths_and_scope() {
+        let id = new_id("My Shop!");
+        assert!(id.starts_with("my-shop-") && id.len() == "my-shop-".len() + 6);
+        assert_eq!(split_path("/w/shop-1a2b3c/api/editor/command"), Some(("shop-1a2b3c", "/api/editor/command")));
+        assert_eq!(split_path("/api/editor/command"), None);
+        assert_eq!(split_path("/w//api"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(scoped_root(), None);
+        let root = scope(dir.path().to_path_buf(), async { paths::get_project_root().unwrap() }).await;
+        assert_eq!(root, dir.path());
+    }
+}
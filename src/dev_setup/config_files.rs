@@ -1,7 +1,28 @@
 use crate::api::routes::editor_api::EditorApi;
+use crate::api::routes::logs_api::LogsApi;
+use crate::api::routes::lsp_api::LspApi;
 use crate::api::routes::project::ProjectApi;
+use crate::api::routes::refactor::RefactorApi;
+use crate::api::routes::jobs::JobsApi;
+use crate::api::routes::terminal::TerminalApi;
+use crate::api::routes::fs::FsApi;
+use crate::api::routes::git::GitApi;
+use crate::api::routes::code_intel::CodeIntelApi;
+use crate::api::routes::mcp::McpApi;
+use crate::api::routes::runtime::RuntimeApi;
+use crate::api::routes::setup::SetupApi;
+use crate::api::routes::suggestions::SuggestionsApi;
+use crate::api::routes::codegen::CodegenApi;
+use crate::api::routes::validation::ValidationApi;
+use crate::api::routes::workspaces::WorkspacesApi;
+use crate::api::routes::system::SystemApi;
+use crate::dev_runtime::util;
+use crate::file_system::paths;
 use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
 use poem_openapi::OpenApiService;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use toml::{map::Map as TomlMap, Value as TomlValue};
@@ -10,13 +31,7 @@ use tracing;
 /// Creates a 'galatea_files' folder in the same directory as the executable
 /// containing config.toml, project_structure.json, and developer_note.md
 pub fn create_galatea_files_folder() -> Result<PathBuf> {
-    let exe_path = std::env::current_exe().context("Failed to get current executable path")?;
-
-    let exe_dir = exe_path
-        .parent()
-        .context("Failed to get executable directory")?;
-
-    let galatea_files_dir = exe_dir.join("galatea_files");
+    let galatea_files_dir = paths::galatea_files_dir()?;
 
     // Create the galatea_files directory if it doesn't exist
     if !galatea_files_dir.exists() {
@@ -44,8 +59,13 @@ pub fn create_galatea_files_folder() -> Result<PathBuf> {
             .context("Failed to create openapi_specification directory")?;
         tracing::info!(target: "config_files", "Created openapi_specification directory at: {}", openapi_dir.display());
     }
-    // Always write/overwrite OpenAPI spec files to ensure they are up-to-date
-    write_openapi_spec_files(&openapi_dir)?;
+    // Keep the specs of Galatea's own APIs in sync with the routes compiled into this binary
+    let changed_specs = write_openapi_spec_files(&openapi_dir)?;
+    if changed_specs.is_empty() {
+        tracing::info!(target: "config_files", "Galatea API specs are up to date.");
+    } else {
+        tracing::info!(target: "config_files", specs = ?changed_specs, "Galatea API specs changed; their MCP servers will be regenerated.");
+    }
 
     tracing::info!(target: "config_files",
         "Successfully ensured galatea_files folder and its contents are up to date."
@@ -54,22 +74,93 @@ pub fn create_galatea_files_folder() -> Result<PathBuf> {
     Ok(galatea_files_dir)
 }
 
-fn write_openapi_spec_files(openapi_dir: &Path) -> Result<()> {
-    // Project API
-    let project_api_service = OpenApiService::new(ProjectApi, "Project API", "1.0")
-        .server("http://127.0.0.1:3051/api/project");
-    let project_spec = project_api_service.spec();
-    fs::write(openapi_dir.join("project_api.json"), project_spec)
-        .context("Failed to write project_api.json")?;
+/// Manifest of the spec files Galatea generated for its own APIs, with their fingerprints.
+/// Spec files not listed here were put there by the user and are never touched.
+const MANAGED_SPECS_MANIFEST: &str = ".galatea_managed_specs.json";
 
-    // Editor API
-    let editor_api_service = OpenApiService::new(EditorApi, "Editor API", "1.0")
-        .server("http://127.0.0.1:3051/api/editor");
-    let editor_spec = editor_api_service.spec();
-    fs::write(openapi_dir.join("editor_api.json"), editor_spec)
-        .context("Failed to write editor_api.json")?;
+/// Name of the file an MCP server directory records its source spec's fingerprint in.
+pub const MCP_SPEC_FINGERPRINT_FILE: &str = ".galatea_spec_fingerprint";
 
-    Ok(())
+/// Stable fingerprint (64-bit FNV-1a, hex) of a spec file's contents. Used instead of mtimes to
+/// decide whether a spec, and the MCP server generated from it, are out of date.
+pub fn spec_fingerprint(content: &[u8]) -> String {
+    util::fnv1a_hex(content)
+}
+
+fn api_spec<T: poem_openapi::OpenApi>(api: T, title: &str, route: &str) -> String {
+    OpenApiService::new(api, title, "1.0")
+        .server(format!("http://127.0.0.1:3051/api/{}", route))
+        .spec()
+}
+
+/// The specs of Galatea's own APIs as compiled into this binary, keyed by file name.
+fn managed_specs() -> Vec<(&'static str, String)> {
+    vec![
+        ("project_api.json", api_spec(ProjectApi, "Project API", "project")),
+        ("editor_api.json", api_spec(EditorApi, "Editor API", "editor")),
+        ("lsp_api.json", api_spec(LspApi, "LSP API", "lsp")),
+        ("system_api.json", api_spec(SystemApi, "System API", "system")),
+        ("runtime_api.json", api_spec(RuntimeApi, "Runtime API", "runtime")),
+        ("suggestions_api.json", api_spec(SuggestionsApi, "Suggestions API", "suggestions")),
+        ("codegen_api.json", api_spec(CodegenApi, "Codegen API", "codegen")),
+        ("refactor_api.json", api_spec(RefactorApi, "Refactor API", "refactor")),
+        ("jobs_api.json", api_spec(JobsApi, "Jobs API", "jobs")),
+        ("terminal_api.json", api_spec(TerminalApi, "Terminal API", "terminal")),
+        ("fs_api.json", api_spec(FsApi, "File System API", "fs")),
+        ("git_api.json", api_spec(GitApi, "Git API", "git")),
+        ("code_intel_api.json", api_spec(CodeIntelApi, "Code Intel API", "code-intel")),
+        ("validation_api.json", api_spec(ValidationApi, "Validation API", "validation")),
+        ("workspaces_api.json", api_spec(WorkspacesApi, "Workspaces API", "workspaces")),
+        ("logs_api.json", api_spec(LogsApi, "Logs API", "logs")),
+        ("setup_api.json", api_spec(SetupApi, "Setup API", "setup")),
+        ("mcp_api.json", api_spec(McpApi, "MCP API", "mcp")),
+    ]
+}
+
+/// Rewrites the specs of Galatea's own APIs in `openapi_specification/` from the routes
+/// compiled into this binary. Returns the names of the files written or removed.
+pub fn refresh_openapi_specs() -> Result<Vec<String>> {
+    let openapi_dir = paths::galatea_files_dir()?.join("openapi_specification");
+    fs::create_dir_all(&openapi_dir).context("Failed to create openapi_specification directory")?;
+    write_openapi_spec_files(&openapi_dir)
+}
+
+/// Regenerates the Galatea-managed spec files from the running binary's routes. A file is only
+/// rewritten when its contents changed, and managed files for APIs that no longer exist are
+/// removed. Returns the names of the files that were written or removed.
+fn write_openapi_spec_files(openapi_dir: &Path) -> Result<Vec<String>> {
+    let manifest_path = openapi_dir.join(MANAGED_SPECS_MANIFEST);
+    let previous: BTreeMap<String, String> = fs::read_to_string(&manifest_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    let mut manifest = BTreeMap::new();
+    let mut changed = Vec::new();
+    for (file_name, spec) in managed_specs() {
+        let path = openapi_dir.join(file_name);
+        let fingerprint = spec_fingerprint(spec.as_bytes());
+        let on_disk = fs::read(&path).ok().map(|content| spec_fingerprint(&content));
+        if on_disk.as_deref() != Some(fingerprint.as_str()) {
+            fs::write(&path, &spec).with_context(|| format!("Failed to write {}", file_name))?;
+            changed.push(file_name.to_string());
+        }
+        manifest.insert(file_name.to_string(), fingerprint);
+    }
+
+    for file_name in previous.keys().filter(|name| !manifest.contains_key(*name)) {
+        match fs::remove_file(openapi_dir.join(file_name)) {
+            Ok(()) => changed.push(file_name.clone()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to remove stale {}", file_name)),
+        }
+    }
+
+    if manifest != previous {
+        fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+            .context("Failed to write the managed specs manifest")?;
+    }
+    Ok(changed)
 }
 
 /// Helper to create an empty file with the given name in the specified directory
@@ -85,13 +176,163 @@ fn create_empty_file(dir: &Path, filename: &str) -> Result<()> {
     Ok(())
 }
 
+// Environment selected with `--env` at startup; takes precedence over GALATEA_ENV
+static CONFIG_ENV_FLAG: OnceCell<String> = OnceCell::new();
+
+const CONFIG_ENV_VAR: &str = "GALATEA_ENV";
+
+// Leaf keys whose values are masked when the effective config is shown
+const SECRET_KEY_MARKERS: &[&str] = &["key", "token", "secret", "password"];
+
+/// Placeholder shown instead of a secret value.
+pub const MASKED_VALUE: &str = "********";
+
+/// Whether values under this key (the last part of a dotted path) are masked when shown.
+pub fn is_secret_key(key: &str) -> bool {
+    let leaf = key.rsplit('.').next().unwrap_or(key).to_ascii_lowercase();
+    SECRET_KEY_MARKERS.iter().any(|marker| leaf.contains(marker))
+}
+
+fn is_valid_environment(env: &str) -> bool {
+    !env.is_empty() && env.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Selects the config overlay for the rest of the process (the `--env` flag).
+pub fn set_config_environment(env: &str) -> Result<()> {
+    if !is_valid_environment(env) {
+        anyhow::bail!("Invalid config environment '{}': use letters, digits, '-' and '_' only", env);
+    }
+    CONFIG_ENV_FLAG
+        .set(env.to_string())
+        .map_err(|_| anyhow::anyhow!("The config environment is already set"))
+}
+
+/// The selected config environment: `--env`, else `GALATEA_ENV`. `None` means no overlay.
+pub fn config_environment() -> Option<String> {
+    if let Some(env) = CONFIG_ENV_FLAG.get() {
+        return Some(env.clone());
+    }
+    let env = std::env::var(CONFIG_ENV_VAR).ok()?;
+    let env = env.trim();
+    if env.is_empty() {
+        return None;
+    }
+    if !is_valid_environment(env) {
+        tracing::warn!(target: "config_files", env = %env, "Ignoring invalid {} value.", CONFIG_ENV_VAR);
+        return None;
+    }
+    Some(env.to_string())
+}
+
+fn galatea_files_dir() -> Option<PathBuf> {
+    paths::galatea_files_dir().ok()
+}
+
+/// One file contributing to the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigLayer {
+    /// File name, e.g. `config.toml` or `config.ci.toml`
+    pub name: String,
+    pub path: PathBuf,
+    pub present: bool,
+    /// Set when the file exists but isn't valid TOML; the layer is then ignored
+    pub error: Option<String>,
+}
+
+/// The config files in precedence order, lowest first: `config.toml`, then the
+/// `config.{env}.toml` overlay of the selected environment.
+fn config_layers(dir: &Path, env: Option<&str>) -> Vec<(ConfigLayer, TomlMap<String, TomlValue>)> {
+    let mut names = vec!["config.toml".to_string()];
+    if let Some(env) = env {
+        names.push(format!("config.{}.toml", env));
+    }
+    names
+        .into_iter()
+        .map(|name| {
+            let path = dir.join(&name);
+            let (table, error) = match fs::read_to_string(&path) {
+                Ok(content) => match content.parse::<TomlValue>() {
+                    Ok(TomlValue::Table(table)) => (table, None),
+                    Ok(_) => (TomlMap::new(), Some("Not a TOML table".to_string())),
+                    Err(e) => (TomlMap::new(), Some(e.to_string())),
+                },
+                Err(_) => (TomlMap::new(), None),
+            };
+            if let Some(error) = &error {
+                tracing::warn!(target: "config_files", path = %path.display(), error = %error, "Ignoring invalid config file.");
+            }
+            let layer = ConfigLayer { present: path.is_file(), name, path, error };
+            (layer, table)
+        })
+        .collect()
+}
+
+// Overlay tables are merged key by key; any other overlay value replaces the base value
+fn merge_tables(base: &mut TomlMap<String, TomlValue>, overlay: TomlMap<String, TomlValue>) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(TomlValue::Table(base_table)), TomlValue::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn merged_layers(layers: Vec<(ConfigLayer, TomlMap<String, TomlValue>)>) -> TomlMap<String, TomlValue> {
+    let mut merged = TomlMap::new();
+    for (_, table) in layers {
+        merge_tables(&mut merged, table);
+    }
+    merged
+}
+
+/// The configuration with the environment overlay applied.
+pub fn merged_config() -> Option<TomlMap<String, TomlValue>> {
+    let dir = galatea_files_dir()?;
+    Some(merged_layers(config_layers(&dir, config_environment().as_deref())))
+}
+
+/// What the configuration would be with `base` as config.toml, the environment overlay applied.
+pub fn merged_config_with_base(base: TomlMap<String, TomlValue>) -> Result<TomlMap<String, TomlValue>> {
+    let dir = galatea_files_dir().context("Failed to locate the galatea_files directory")?;
+    let mut layers = config_layers(&dir, config_environment().as_deref());
+    layers[0].1 = base;
+    Ok(merged_layers(layers))
+}
+
+/// config.toml as written, without the overlay. Unlike the readers, fails on invalid TOML so
+/// that a rewrite can't drop what the file had.
+pub fn base_config() -> Result<TomlMap<String, TomlValue>> {
+    let config_path = paths::galatea_files_dir()?.join("config.toml");
+    if !config_path.exists() {
+        return Ok(TomlMap::new());
+    }
+    let content = fs::read_to_string(&config_path).context("Failed to read config.toml")?;
+    match content.parse::<TomlValue>().context("config.toml is not valid TOML")? {
+        TomlValue::Table(table) => Ok(table),
+        _ => anyhow::bail!("config.toml is not a TOML table"),
+    }
+}
+
+/// Replaces config.toml.
+pub fn write_base_config(config: TomlMap<String, TomlValue>) -> Result<()> {
+    let config_path = paths::galatea_files_dir()?.join("config.toml");
+    fs::write(&config_path, TomlValue::Table(config).to_string()).context("Failed to write config.toml")
+}
+
 /// Write or update a key-value pair in config.toml
 pub fn set_config_value(key: &str, value: &str) -> Result<()> {
-    let exe_path = std::env::current_exe().context("Failed to get current executable path")?;
-    let exe_dir = exe_path
-        .parent()
-        .context("Failed to get executable directory")?;
-    let config_path = exe_dir.join("galatea_files").join("config.toml");
+    set_config_section(key, TomlValue::String(value.to_string()))
+}
+
+/// Write or replace a raw TOML value (e.g. a table) in config.toml.
+///
+/// Always writes the base file; a value set in the active `config.{env}.toml` overlay still wins.
+pub fn set_config_section(key: &str, value: TomlValue) -> Result<()> {
+    let config_path = paths::galatea_files_dir()?.join("config.toml");
 
     // Read existing config if present
     let mut config: TomlMap<String, TomlValue> = if config_path.exists() {
@@ -107,23 +348,107 @@ pub fn set_config_value(key: &str, value: &str) -> Result<()> {
         TomlMap::new()
     };
 
-    config.insert(key.to_string(), TomlValue::String(value.to_string()));
+    config.insert(key.to_string(), value);
     let new_content = TomlValue::Table(config).to_string();
     std::fs::write(&config_path, new_content).context("Failed to write config.toml")?;
     Ok(())
 }
 
-/// Get a value by key from config.toml
+/// Get a value by key from config.toml, with the environment overlay applied
 pub fn get_config_value(key: &str) -> Option<String> {
-    let exe_path = std::env::current_exe().ok()?;
-    let exe_dir = exe_path.parent()?;
-    let config_path = exe_dir.join("galatea_files").join("config.toml");
-    if !config_path.exists() {
-        return None;
+    merged_config()?.get(key)?.as_str().map(|s| s.to_string())
+}
+
+/// Get a raw TOML value (e.g. a table) by key from config.toml, with the environment overlay applied
+pub fn get_config_section(key: &str) -> Option<toml::Value> {
+    merged_config()?.remove(key)
+}
+
+/// Deserializes the `key` section of config.toml, falling back to the defaults when it is
+/// missing or invalid; an invalid section is logged.
+pub fn load_section<T: DeserializeOwned + Default>(key: &str) -> T {
+    match get_config_section(key) {
+        Some(section) => section.try_into().unwrap_or_else(|e| {
+            tracing::warn!(target: "config_files", section = key, error = %e, "Invalid [{}] section in config.toml, using the defaults.", key);
+            T::default()
+        }),
+        None => T::default(),
+    }
+}
+
+/// A leaf of the merged configuration and the layer it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectiveValue {
+    /// Dotted path, e.g. `search_ranking.recency`
+    pub key: String,
+    pub value: TomlValue,
+    /// Name of the layer that set the value
+    pub source: String,
+    /// Whether the value was replaced by a placeholder because the key looks like a secret
+    pub masked: bool,
+}
+
+/// The merged configuration and where each value came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectiveConfig {
+    pub environment: Option<String>,
+    /// Lowest precedence first
+    pub layers: Vec<ConfigLayer>,
+    pub values: Vec<EffectiveValue>,
+}
+
+// Leaves of a table by dotted path; arrays count as leaves
+fn flatten(prefix: &str, table: &TomlMap<String, TomlValue>, out: &mut BTreeMap<String, TomlValue>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            TomlValue::Table(inner) => flatten(&path, inner, out),
+            other => {
+                out.insert(path, other.clone());
+            }
+        }
     }
-    let content = std::fs::read_to_string(&config_path).ok()?;
-    let value: toml::Value = content.parse().ok()?;
-    value.get(key)?.as_str().map(|s| s.to_string())
+}
+
+fn effective_from_layers(environment: Option<String>, layers: Vec<(ConfigLayer, TomlMap<String, TomlValue>)>) -> EffectiveConfig {
+    let flattened: Vec<(String, BTreeMap<String, TomlValue>)> = layers
+        .iter()
+        .map(|(layer, table)| {
+            let mut leaves = BTreeMap::new();
+            flatten("", table, &mut leaves);
+            (layer.name.clone(), leaves)
+        })
+        .collect();
+    let layer_infos = layers.iter().map(|(layer, _)| layer.clone()).collect();
+    let mut merged = BTreeMap::new();
+    flatten("", &merged_layers(layers), &mut merged);
+
+    let values = merged
+        .into_iter()
+        .map(|(key, value)| {
+            // A leaf of the merged config was set by the last layer defining that exact path
+            let source = flattened
+                .iter()
+                .rev()
+                .find(|(_, leaves)| leaves.contains_key(&key))
+                .map(|(name, _)| name.clone())
+                .unwrap_or_default();
+            let masked = is_secret_key(&key);
+            let value = if masked { TomlValue::String(MASKED_VALUE.to_string()) } else { value };
+            EffectiveValue { key, value, source, masked }
+        })
+        .collect();
+    EffectiveConfig { environment, layers: layer_infos, values }
+}
+
+/// The merged configuration, layer by layer. Precedence, lowest first: `config.toml`, then
+/// `config.{env}.toml` for the environment chosen with `--env` or `GALATEA_ENV`. Tables are
+/// merged key by key, other values are replaced. Values of secret-looking keys are masked.
+pub fn effective_config() -> Result<EffectiveConfig> {
+    let dir = galatea_files_dir().context("Failed to locate the galatea_files directory")?;
+    let environment = config_environment();
+    let layers = config_layers(&dir, environment.as_deref());
+    Ok(effective_from_layers(environment, layers))
 }
 
 #[cfg(test)]
@@ -152,4 +477,47 @@ mod tests {
         // Verify file was created
         assert!(galatea_files_dir.join("config.toml").exists());
     }
+
+    #[test]
+    fn test_managed_specs_are_only_rewritten_when_changed() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        // A managed spec left over from an API this binary no longer has
+        fs::write(dir.join(MANAGED_SPECS_MANIFEST), r#"{"retired_api.json": "0"}"#).unwrap();
+        fs::write(dir.join("retired_api.json"), "{}").unwrap();
+        fs::write(dir.join("custom_api.json"), "{}").unwrap();
+
+        let changed = write_openapi_spec_files(dir).unwrap();
+        assert!(changed.contains(&"project_api.json".to_string()));
+        assert!(changed.contains(&"retired_api.json".to_string()));
+        assert!(!dir.join("retired_api.json").exists());
+        assert!(dir.join("custom_api.json").exists());
+
+        assert!(write_openapi_spec_files(dir).unwrap().is_empty());
+        assert_eq!(spec_fingerprint(b"abc"), spec_fingerprint(b"abc"));
+        assert_ne!(spec_fingerprint(b"abc"), spec_fingerprint(b"abd"));
+    }
+
+    #[test]
+    fn test_environment_overlay_precedence() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("config.toml"), "template = \"nextjs\"\noffline = \"false\"\nopenai_api_key = \"sk-1\"\n[search_ranking]\nrecency = 1.0\npath = 2.0\n").unwrap();
+        fs::write(dir.join("config.ci.toml"), "offline = \"true\"\n[search_ranking]\nrecency = 0.5\n").unwrap();
+
+        let config = effective_from_layers(Some("ci".to_string()), config_layers(dir, Some("ci")));
+        let value = |key: &str| config.values.iter().find(|v| v.key == key).unwrap();
+        assert_eq!(value("offline").value.as_str(), Some("true"));
+        assert_eq!(value("offline").source, "config.ci.toml");
+        assert_eq!(value("template").source, "config.toml");
+        assert_eq!(value("search_ranking.recency").value.as_float(), Some(0.5));
+        assert_eq!(value("search_ranking.path").source, "config.toml");
+        assert!(value("openai_api_key").masked);
+        assert!(config.layers.iter().all(|l| l.present && l.error.is_none()));
+
+        // Without an environment only the base file applies
+        let base = merged_layers(config_layers(dir, None));
+        assert_eq!(base.get("offline").and_then(|v| v.as_str()), Some("false"));
+        assert!(!is_valid_environment("../etc"));
+    }
 }
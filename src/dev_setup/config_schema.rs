@@ -0,0 +1,417 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use toml::{map::Map as TomlMap, Value as TomlValue};
+
+use super::wizard::MIN_TOKEN_LENGTH;
+use super::{config_files, template_registry};
+use crate::api::auth::ApiAuthConfig;
+use crate::api::mcp_proxy::McpProxyPolicy;
+use crate::codebase_indexing::profiles::AnalysisProfile;
+use crate::codebase_indexing::semantic::EmbeddingConfig;
+use crate::dev_operation::assets::AssetConfig;
+use crate::dev_operation::checkpoints::CheckpointConfig;
+use crate::dev_operation::guardrails::GuardrailConfig;
+use crate::dev_operation::hooks::HookConfig;
+use crate::dev_operation::reset::ResetConfig;
+use crate::dev_operation::snapshots::SnapshotConfig;
+use crate::dev_operation::sync::SyncConfig;
+use crate::dev_operation::transfers::TransferConfig;
+use crate::dev_runtime::codex_session::CodexConfig;
+use crate::dev_runtime::events::DEV_SERVER_SERVICE;
+use crate::dev_runtime::log_hub::{self, LogHubConfig};
+use crate::dev_runtime::mcp_health::McpHealthConfig;
+use crate::dev_runtime::project_manifest::ProjectConfig;
+use crate::dev_runtime::supervisor::{self, TaskState};
+use crate::dev_runtime::watchdog::WatchdogConfig;
+use crate::file_system::ranking::RankingWeights;
+use crate::file_system::watcher::WatcherConfig;
+use crate::terminal::exec::ExecConfig;
+use crate::terminal::package_manager::PackageManager;
+use crate::terminal::session::SessionConfig;
+
+// Keys only read at startup, so changing them takes a restart
+const RESTART_KEYS: &[&str] = &[
+    "changelog_interval_minutes",
+    "dev_server_watchdog",
+    "disk_warning_ratio",
+    "edit_history_max_rows",
+    "editor_undo_depth",
+    "fs_watcher",
+    "limit_warning_ratio",
+    "log_buffer_capacity",
+    "mcp_enabled",
+    "mcp_health",
+    "mcp_proxy",
+    "otlp_endpoint",
+    "otlp_service_name",
+    "project_dir",
+    "quota_bytes_written",
+    "quota_cpu_seconds",
+    "quota_edits_per_hour",
+    "quota_script_runs_per_hour",
+    "rate_limit_per_minute",
+    "registry_cache",
+    "registry_cache_port",
+    "structure_refresh_interval_minutes",
+    "suggestion_interval_minutes",
+];
+
+/// Everything Galatea reads from config.toml, typed. Missing keys take the defaults of the
+/// component reading them. Top-level values are stored as strings (`offline = "true"`), as
+/// `set_config_value` writes them; native TOML values are accepted too. Sections are checked
+/// with the types their components load them into.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GalateaConfig {
+    pub project_dir: Option<String>,
+    pub template: Option<String>,
+    pub template_vars: Option<BTreeMap<String, String>>,
+    pub package_manager: Option<String>,
+    #[serde(deserialize_with = "lenient")]
+    pub offline: Option<bool>,
+    #[serde(deserialize_with = "lenient")]
+    pub mcp_enabled: Option<bool>,
+    pub openai_api_key: Option<String>,
+    pub openai_api_base: Option<String>,
+    pub token: Option<String>,
+    pub browser_path: Option<String>,
+    pub analysis_profile: Option<String>,
+    #[serde(deserialize_with = "lenient")]
+    pub lsp_debug: Option<bool>,
+    #[serde(deserialize_with = "lenient")]
+    pub editor_undo_depth: Option<usize>,
+    #[serde(deserialize_with = "lenient")]
+    pub editor_max_view_bytes: Option<u64>,
+    #[serde(deserialize_with = "lenient")]
+    pub changelog_interval_minutes: Option<u64>,
+    #[serde(deserialize_with = "lenient")]
+    pub structure_refresh_interval_minutes: Option<u64>,
+    #[serde(deserialize_with = "lenient")]
+    pub suggestion_interval_minutes: Option<u64>,
+    #[serde(deserialize_with = "lenient")]
+    pub dev_server_ready_timeout_secs: Option<u64>,
+    pub otlp_endpoint: Option<String>,
+    pub otlp_service_name: Option<String>,
+    #[serde(deserialize_with = "lenient")]
+    pub registry_cache: Option<bool>,
+    #[serde(deserialize_with = "lenient")]
+    pub registry_cache_port: Option<u16>,
+    pub registry_upstream: Option<String>,
+    #[serde(deserialize_with = "lenient")]
+    pub registry_metadata_ttl_secs: Option<u64>,
+    #[serde(deserialize_with = "lenient")]
+    pub restore_after_crash: Option<bool>,
+    pub setup_steps: Option<String>,
+    #[serde(deserialize_with = "lenient")]
+    pub setup_completed: Option<bool>,
+
+    #[serde(deserialize_with = "lenient")]
+    pub limit_warning_ratio: Option<f64>,
+    #[serde(deserialize_with = "lenient")]
+    pub rate_limit_per_minute: Option<u32>,
+    #[serde(deserialize_with = "lenient")]
+    pub edit_history_max_rows: Option<u64>,
+    #[serde(deserialize_with = "lenient")]
+    pub log_buffer_capacity: Option<usize>,
+    #[serde(deserialize_with = "lenient")]
+    pub disk_warning_ratio: Option<f64>,
+    #[serde(deserialize_with = "lenient")]
+    pub quota_edits_per_hour: Option<f64>,
+    #[serde(deserialize_with = "lenient")]
+    pub quota_script_runs_per_hour: Option<f64>,
+    #[serde(deserialize_with = "lenient")]
+    pub quota_cpu_seconds: Option<f64>,
+    #[serde(deserialize_with = "lenient")]
+    pub quota_bytes_written: Option<f64>,
+
+    pub project: Option<ProjectConfig>,
+    pub project_reset: Option<ResetConfig>,
+    pub terminal_exec: Option<ExecConfig>,
+    pub terminal_sessions: Option<SessionConfig>,
+    pub fs_watcher: Option<WatcherConfig>,
+    pub search_ranking: Option<RankingWeights>,
+    pub embeddings: Option<EmbeddingConfig>,
+    pub analysis_profiles: Option<BTreeMap<String, AnalysisProfile>>,
+    pub editor_hooks: Option<Vec<HookConfig>>,
+    pub editor_guardrails: Option<GuardrailConfig>,
+    pub editor_transfers: Option<TransferConfig>,
+    pub checkpoints: Option<CheckpointConfig>,
+    pub snapshots: Option<SnapshotConfig>,
+    pub assets: Option<AssetConfig>,
+    pub sync: Option<SyncConfig>,
+    pub logs: Option<LogHubConfig>,
+    pub dev_server_watchdog: Option<WatchdogConfig>,
+    pub mcp_health: Option<McpHealthConfig>,
+    pub codex: Option<CodexConfig>,
+    // Parsed per server and with a legacy fallback, so checked in `validate`
+    pub api_auth: Option<TomlValue>,
+    pub mcp_proxy: Option<TomlTable>,
+}
+
+type TomlTable = TomlMap<String, TomlValue>;
+
+// A string to parse, or the native TOML value it stands for
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let text = match TomlValue::deserialize(deserializer)? {
+        TomlValue::String(s) => s,
+        other => other.to_string(),
+    };
+    text.trim().parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Something wrong with one top-level key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(key: &str, message: impl Into<String>) -> Self {
+        Self { key: key.to_string(), message: message.into() }
+    }
+}
+
+fn check_url(problems: &mut Vec<ConfigProblem>, key: &str, value: &Option<String>) {
+    if let Some(url) = value {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            problems.push(ConfigProblem::new(key, format!("'{}' is not an http(s) URL", url)));
+        }
+    }
+}
+
+impl GalateaConfig {
+    /// Problems the types don't catch: names that must exist, URLs, ranges.
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        if let Some(template) = &self.template {
+            let is_url = template.contains("://") || template.starts_with("git@");
+            if !is_url && template_registry::find(template).is_none() {
+                problems.push(ConfigProblem::new("template", format!("Unknown template '{}'", template)));
+            }
+        }
+        if let Some(name) = &self.package_manager {
+            if PackageManager::parse(name).is_none() {
+                problems.push(ConfigProblem::new("package_manager", format!("Unsupported package manager '{}'", name)));
+            }
+        }
+        check_url(&mut problems, "openai_api_base", &self.openai_api_base);
+        check_url(&mut problems, "otlp_endpoint", &self.otlp_endpoint);
+        check_url(&mut problems, "registry_upstream", &self.registry_upstream);
+        if self.token.as_ref().is_some_and(|t| t.trim().len() < MIN_TOKEN_LENGTH) {
+            problems.push(ConfigProblem::new("token", format!("Must be at least {} characters", MIN_TOKEN_LENGTH)));
+        }
+        if self.registry_cache_port == Some(0) {
+            problems.push(ConfigProblem::new("registry_cache_port", "Must not be 0"));
+        }
+        for (key, ratio) in [("limit_warning_ratio", self.limit_warning_ratio), ("disk_warning_ratio", self.disk_warning_ratio)] {
+            if ratio.is_some_and(|r| r <= 0.0 || r > 1.0) {
+                problems.push(ConfigProblem::new(key, "Must be above 0 and at most 1"));
+            }
+        }
+        if let Some(section) = &self.api_auth {
+            if let Err(e) = ApiAuthConfig::from_config_value(Some(section), None) {
+                problems.push(ConfigProblem::new("api_auth", format!("{:#}", e)));
+            }
+        }
+        if let Some(section) = &self.mcp_proxy {
+            let value = TomlValue::Table(section.clone());
+            for server_id in section.keys() {
+                if let Err(e) = McpProxyPolicy::from_config_value(Some(&value), server_id) {
+                    problems.push(ConfigProblem::new("mcp_proxy", format!("{:#}", e)));
+                }
+            }
+        }
+        problems
+    }
+}
+
+/// Types and validates a merged configuration, reporting every problem by key. Keys Galatea
+/// doesn't know are problems too, unless `allow_unknown`.
+pub fn check(config: &TomlTable, allow_unknown: bool) -> (GalateaConfig, Vec<ConfigProblem>) {
+    let mut problems = Vec::new();
+    let mut known = TomlTable::new();
+    // One key at a time, so one bad value doesn't hide the others
+    for (key, value) in config {
+        let single = TomlTable::from_iter([(key.clone(), value.clone())]);
+        match TomlValue::Table(single).try_into::<GalateaConfig>() {
+            Ok(_) => {
+                known.insert(key.clone(), value.clone());
+            }
+            Err(e) if e.to_string().contains("unknown field") => {
+                if !allow_unknown {
+                    problems.push(ConfigProblem::new(key, "Unknown key"));
+                }
+            }
+            Err(e) => problems.push(ConfigProblem::new(key, e.to_string().trim().to_string())),
+        }
+    }
+    let typed: GalateaConfig = TomlValue::Table(known).try_into().unwrap_or_default();
+    problems.extend(typed.validate());
+    (typed, problems)
+}
+
+/// The current configuration and its problems.
+pub fn current() -> (GalateaConfig, Vec<ConfigProblem>) {
+    check(&config_files::merged_config().unwrap_or_default(), true)
+}
+
+fn mask_value(key: &str, value: &TomlValue) -> TomlValue {
+    match value {
+        TomlValue::Table(inner) => TomlValue::Table(masked(inner)),
+        TomlValue::Array(items) => TomlValue::Array(items.iter().map(|item| mask_value(key, item)).collect()),
+        _ if config_files::is_secret_key(key) => TomlValue::String(config_files::MASKED_VALUE.to_string()),
+        other => other.clone(),
+    }
+}
+
+/// Copy of `config` with secret-looking values replaced by `MASKED_VALUE`.
+pub fn masked(config: &TomlTable) -> TomlTable {
+    config.iter().map(|(key, value)| (key.clone(), mask_value(key, value))).collect()
+}
+
+fn contains_masked(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::String(s) => s == config_files::MASKED_VALUE,
+        serde_json::Value::Array(items) => items.iter().any(contains_masked),
+        serde_json::Value::Object(entries) => entries.values().any(contains_masked),
+        _ => false,
+    }
+}
+
+/// Applies a JSON merge patch (RFC 7386) to a config table: objects merge key by key, `null`
+/// removes a key, anything else replaces it. `MASKED_VALUE` keeps the current value, so a shown
+/// config can be sent back as is; inside an array, which is replaced whole, it is refused.
+/// Top-level scalars are stored as strings.
+pub fn apply_patch(config: &mut TomlTable, patch: &serde_json::Map<String, serde_json::Value>, top_level: bool) -> Result<()> {
+    for (key, value) in patch {
+        match value {
+            serde_json::Value::Null => {
+                config.remove(key);
+            }
+            serde_json::Value::String(s) if s == config_files::MASKED_VALUE => {}
+            serde_json::Value::Object(inner) => {
+                if !matches!(config.get(key), Some(TomlValue::Table(_))) {
+                    config.insert(key.clone(), TomlValue::Table(TomlTable::new()));
+                }
+                if let Some(TomlValue::Table(table)) = config.get_mut(key) {
+                    apply_patch(table, inner, false)?;
+                }
+            }
+            serde_json::Value::Bool(_) | serde_json::Value::Number(_) if top_level => {
+                config.insert(key.clone(), TomlValue::String(value.to_string()));
+            }
+            other if contains_masked(other) => {
+                anyhow::bail!("'{}' contains masked values; send the array with the real values", key)
+            }
+            other => {
+                let value = TomlValue::try_from(other).context(format!("'{}' can't be written to TOML", key))?;
+                config.insert(key.clone(), value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Why a config update was refused.
+#[derive(Debug)]
+pub enum ConfigUpdateError {
+    /// Problems with the changed keys; nothing was written
+    Invalid(Vec<ConfigProblem>),
+    Failed(anyhow::Error),
+}
+
+/// What an update changed and what it took to apply it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigUpdate {
+    /// Top-level keys whose effective value changed
+    pub changed: Vec<String>,
+    /// Components reloaded to pick up the change
+    pub reloaded: Vec<String>,
+    /// Changed keys only read at startup
+    pub restart_required: Vec<String>,
+}
+
+/// Patches config.toml, refusing changes that leave a changed key invalid. Problems with keys
+/// the patch doesn't touch don't block it.
+pub async fn update(patch: &serde_json::Map<String, serde_json::Value>, allow_unknown: bool) -> Result<ConfigUpdate, ConfigUpdateError> {
+    let before = config_files::merged_config().unwrap_or_default();
+    let mut base = config_files::base_config().map_err(ConfigUpdateError::Failed)?;
+    apply_patch(&mut base, patch, true).map_err(ConfigUpdateError::Failed)?;
+    let after = config_files::merged_config_with_base(base.clone()).map_err(ConfigUpdateError::Failed)?;
+
+    let (_, problems) = check(&after, allow_unknown);
+    let problems: Vec<ConfigProblem> = problems.into_iter().filter(|p| patch.contains_key(&p.key)).collect();
+    if !problems.is_empty() {
+        return Err(ConfigUpdateError::Invalid(problems));
+    }
+    config_files::write_base_config(base).map_err(ConfigUpdateError::Failed)?;
+
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    let changed: Vec<String> = keys.into_iter().filter(|key| before.get(*key) != after.get(*key)).cloned().collect();
+    tracing::info!(target: "dev_setup::config_schema", changed = ?changed, "Updated config.toml.");
+    let mut update = ConfigUpdate {
+        restart_required: changed.iter().filter(|key| RESTART_KEYS.contains(&key.as_str())).cloned().collect(),
+        ..Default::default()
+    };
+    for key in &changed {
+        match key.as_str() {
+            "logs" => {
+                log_hub::load_config();
+                update.reloaded.push("log_hub".to_string());
+            }
+            // The dev server's command and port
+            "project" | "package_manager" if supervisor::task_state(DEV_SERVER_SERVICE) == Some(TaskState::Running) => {
+                match supervisor::restart(DEV_SERVER_SERVICE).await {
+                    Ok(()) => update.reloaded.push(DEV_SERVER_SERVICE.to_string()),
+                    Err(e) => tracing::warn!(target: "dev_setup::config_schema", error = %e, "Failed to restart the dev server after a config change."),
+                }
+            }
+            _ => {}
+        }
+    }
+    update.reloaded.dedup();
+    update.changed = changed;
+    Ok(update)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_and_patch() {
+        let config: TomlTable = toml::from_str(
+            "offline = \"true\"\nregistry_cache_port = 4873\npackage_manager = \"pip\"\ntypo_key = \"x\"\n[dev_server_watchdog]\ninterval_secs = \"often\"\n",
+        )
+        .unwrap();
+        let (typed, problems) = check(&config, false);
+        assert_eq!((typed.offline, typed.registry_cache_port), (Some(true), Some(4873)));
+        let keys: Vec<&str> = problems.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, ["dev_server_watchdog", "typo_key", "package_manager"]);
+        assert_eq!(check(&config, true).1.len(), 2);
+
+        let mut config = config;
+        let patch = serde_json::json!({
+            "offline": false,
+            "typo_key": null,
+            "openai_api_key": config_files::MASKED_VALUE,
+            "dev_server_watchdog": {"interval_secs": 10},
+        });
+        apply_patch(&mut config, patch.as_object().unwrap(), true).unwrap();
+        assert_eq!(config["offline"].as_str(), Some("false"));
+        assert!(!config.contains_key("typo_key") && !config.contains_key("openai_api_key"));
+        assert_eq!(config["dev_server_watchdog"]["interval_secs"].as_integer(), Some(10));
+        assert_eq!(masked(&toml::from_str("[api_auth]\ntoken = \"abc\"\n").unwrap())["api_auth"]["token"].as_str(), Some(config_files::MASKED_VALUE));
+    }
+}
@@ -1,4 +1,3 @@
-use crate::terminal::npm::run_npm_command;
 use anyhow::{Context, Result};
 use std::process::Stdio;
 use tokio::process::Command;
@@ -21,17 +20,25 @@ pub async fn ensure_openapi_mcp_generator_installed(use_sudo: bool) -> Result<()
             Ok(())
         }
         _ => {
-            let install_command = if use_sudo {
-                "sudo npm install -g openapi-mcp-generator"
+            // The registry cache may still have the package while offline
+            let registry = super::registry_cache::registry_url();
+            if registry.is_none() {
+                super::offline::ensure_online("Installing 'openapi-mcp-generator'")?;
+            }
+            let mut install_command = if use_sudo {
+                "sudo npm install -g openapi-mcp-generator".to_string()
             } else {
-                "npm install -g openapi-mcp-generator"
+                "npm install -g openapi-mcp-generator".to_string()
             };
+            if let Some(url) = registry {
+                install_command.push_str(&format!(" --registry {}", url));
+            }
             
             tracing::info!(target: "dev_setup::mcp_converter", command = %install_command, "'openapi-mcp-generator' not found. Installing globally with npm...");
             
             let install_status = Command::new("bash")
                 .arg("-c")
-                .arg(install_command)
+                .arg(&install_command)
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
                 .status()
@@ -1,40 +1,48 @@
 pub mod codex;
 pub mod config_files;
+pub mod config_schema;
 pub mod env;
 pub mod nextjs;
 pub mod mcp_converter;
+pub mod offline;
+pub mod registry_cache;
+pub mod template;
+pub mod template_registry;
+pub mod wizard;
 
 use anyhow::{Context, Result};
+use crate::file_system::paths;
+use std::collections::HashMap;
 use tracing;
 use std::process::Stdio;
 use tokio::process::Command;
 
 pub async fn ensure_development_environment(
     template: Option<String>,
+    template_vars: &HashMap<String, String>,
     use_sudo: bool,
+    force_rescaffold: bool,
 ) -> Result<std::path::PathBuf> {
     tracing::info!(target: "dev_setup", "Attempting to ensure development environment...");
 
     // Check and ensure Node.js version 20+ is available
     ensure_node_version_20_or_higher().await?;
 
-    // Get current working directory and determine project_dir_path
-    let exe_path = std::env::current_exe().context("Failed to get current executable path")?;
-    let exe_dir = exe_path
-        .parent()
-        .context("Failed to get executable directory")?;
-    let project_dir_path = exe_dir.join("project");
-    let galatea_files_dir = exe_dir.join("galatea_files");
+    let project_dir_path = paths::project_dir()?;
+    let galatea_files_dir = paths::galatea_files_dir()?;
 
     // Use custom template if provided, otherwise use default
-    let template_url = match template.as_deref() {
-        Some("nextjs") => "https://github.com/Svring/nextjs-project",
-        Some(url) => url,
-        None => "https://github.com/Svring/nextjs-project", // Default template
-    };
-
-    // If galatea_files does not exist, (re)create the project from template, even if project_dir_path exists
-    if !galatea_files_dir.exists() {
+    let template_url = template::resolve_template_url(template.as_deref());
+
+    if force_rescaffold {
+        tracing::info!(target: "dev_setup", "--force-rescaffold given. Re-scaffolding Next.js project from template: {}", template_url);
+        nextjs::rescaffold_nextjs_project(&project_dir_path, template_url, template_vars)
+            .await
+            .context("Failed to re-scaffold Next.js project")?;
+        tracing::info!(target: "dev_setup", path = %project_dir_path.display(), "Next.js project re-scaffolded successfully.");
+    } else if !galatea_files_dir.exists() && !paths::is_project_dir_overridden() {
+        // If galatea_files does not exist, (re)create the project from template, even if project_dir_path exists.
+        // A project directory chosen explicitly is the user's own and is never wiped
         tracing::info!(target: "dev_setup", 
             "galatea_files directory does not exist. (Re)scaffolding Next.js project from template: {}", 
             template_url
@@ -44,7 +52,7 @@ pub async fn ensure_development_environment(
             tracing::info!(target: "dev_setup", "Removing existing project directory at {} before scaffolding.", project_dir_path.display());
             std::fs::remove_dir_all(&project_dir_path).ok();
         }
-        nextjs::scaffold_nextjs_project(&project_dir_path, template_url)
+        nextjs::scaffold_nextjs_project(&project_dir_path, template_url, template_vars)
             .await
             .context("Failed to scaffold Next.js project")?;
         tracing::info!(target: "dev_setup", path = %project_dir_path.display(), "Next.js project scaffolded successfully.");
@@ -53,10 +61,19 @@ pub async fn ensure_development_environment(
             "Project directory {} does not exist. Scaffolding Next.js project from template: {}", 
             project_dir_path.display(), template_url
         );
-        nextjs::scaffold_nextjs_project(&project_dir_path, template_url)
+        nextjs::scaffold_nextjs_project(&project_dir_path, template_url, template_vars)
             .await
             .context("Failed to scaffold Next.js project")?;
         tracing::info!(target: "dev_setup", path = %project_dir_path.display(), "Next.js project scaffolded successfully.");
+    } else if nextjs::is_scaffold_incomplete(&project_dir_path) {
+        tracing::warn!(target: "dev_setup",
+            "Project directory {} was left half-scaffolded by an interrupted run. Resuming scaffolding.",
+            project_dir_path.display()
+        );
+        nextjs::scaffold_nextjs_project(&project_dir_path, template_url, template_vars)
+            .await
+            .context("Failed to resume scaffolding Next.js project")?;
+        tracing::info!(target: "dev_setup", path = %project_dir_path.display(), "Next.js project scaffolded successfully.");
     } else {
         tracing::info!(target: "dev_setup", 
             "Both galatea_files and project directory {} already exist. Skipping Next.js project scaffolding.", 
@@ -111,6 +128,8 @@ async fn ensure_node_version_20_or_higher() -> Result<()> {
         }
     }
     
+    offline::ensure_online("Installing Node.js 20 with nvm")?;
+
     // Try to install Node.js 20 using nvm
     tracing::info!(target: "dev_setup", "Installing Node.js 20 using nvm...");
     let nvm_install = Command::new("bash")
@@ -170,7 +189,7 @@ mod tests {
             fs::remove_dir_all(&galatea_files_dir).unwrap();
         }
 
-        let result = ensure_development_environment(Some("nextjs".to_string()), false).await;
+        let result = ensure_development_environment(Some("nextjs".to_string()), &HashMap::new(), false, false).await;
         assert!(
             result.is_ok(),
             "ensure_development_environment failed: {:?}",
@@ -1,10 +1,143 @@
-use crate::terminal;
+use crate::terminal::package_manager::PackageManager;
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing;
 
-pub async fn scaffold_nextjs_project(project_root: &Path, template_url: &str) -> Result<()> {
+// Clone and install are retried this many times before scaffolding gives up
+const SCAFFOLD_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// How far an interrupted scaffold got, recorded in a marker file next to the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaffoldStage {
+    /// The template clone may be partial; the project directory is discarded on resume
+    Cloning,
+    /// The clone finished and variables were applied; only the install is re-run
+    Installing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScaffoldMarker {
+    pub template_url: String,
+    pub stage: ScaffoldStage,
+}
+
+/// Marker recording an in-progress scaffold; removed once the project is fully set up.
+///
+/// It lives beside the project directory so a fresh clone can still target an empty path.
+pub fn scaffold_marker_path(project_root: &Path) -> PathBuf {
+    let name = project_root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "project".to_string());
+    project_root.with_file_name(format!(".{}.scaffold.json", name))
+}
+
+/// The marker of an interrupted scaffold, if any.
+pub fn read_scaffold_marker(project_root: &Path) -> Option<ScaffoldMarker> {
+    let content = fs::read_to_string(scaffold_marker_path(project_root)).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(marker) => Some(marker),
+        // An unreadable marker still means the scaffold never completed
+        Err(_) => Some(ScaffoldMarker { template_url: String::new(), stage: ScaffoldStage::Cloning }),
+    }
+}
+
+/// Whether the project at `project_root` was left half-scaffolded by an interrupted run.
+pub fn is_scaffold_incomplete(project_root: &Path) -> bool {
+    read_scaffold_marker(project_root).is_some()
+}
+
+fn write_scaffold_marker(project_root: &Path, template_url: &str, stage: ScaffoldStage) -> Result<()> {
+    let marker = ScaffoldMarker { template_url: template_url.to_string(), stage };
+    let path = scaffold_marker_path(project_root);
+    fs::write(&path, serde_json::to_string_pretty(&marker)?)
+        .with_context(|| format!("Failed to write scaffold marker {}", path.display()))
+}
+
+fn clear_scaffold_marker(project_root: &Path) {
+    let _ = fs::remove_file(scaffold_marker_path(project_root));
+}
+
+/// Where an interrupted scaffold should resume, given its marker and the template now requested.
+fn resume_stage(marker: Option<&ScaffoldMarker>, template_url: &str, project_exists: bool) -> Option<ScaffoldStage> {
+    match marker {
+        // A different template invalidates whatever was cloned
+        Some(marker) if marker.template_url != template_url => Some(ScaffoldStage::Cloning),
+        Some(marker) if marker.stage == ScaffoldStage::Installing && !project_exists => Some(ScaffoldStage::Cloning),
+        Some(marker) => Some(marker.stage),
+        None => None,
+    }
+}
+
+fn remove_project_dir(project_root: &Path) -> Result<()> {
+    if project_root.exists() {
+        fs::remove_dir_all(project_root)
+            .with_context(|| format!("Failed to remove project directory {}", project_root.display()))?;
+    }
+    Ok(())
+}
+
+async fn clone_template(project_root: &Path, template_url: &str) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        match super::template::clone_source(template_url, project_root).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < SCAFFOLD_ATTEMPTS => {
+                tracing::warn!(target: "dev_setup::nextjs", attempt, error = %e, "Template clone failed; removing the partial clone and retrying.");
+                remove_project_dir(project_root)?;
+                tokio::time::sleep(RETRY_BASE_DELAY * attempt).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.context(format!("Failed to clone template after {} attempts", attempt))),
+        }
+    }
+}
+
+/// Installs the project's dependencies with its package manager, retrying failed installs.
+pub async fn install_dependencies(project_root: &Path) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        // Offline, pnpm resolves everything from the store populated by `--prewarm`
+        match PackageManager::detect(project_root).install(project_root, false).await {
+            Ok(_) => return Ok(()),
+            // pnpm picks up where an interrupted install left off, so no cleanup is needed
+            Err(e) if attempt < SCAFFOLD_ATTEMPTS => {
+                tracing::warn!(target: "dev_setup::nextjs", attempt, error = %e, "Dependency install failed; retrying.");
+                tokio::time::sleep(RETRY_BASE_DELAY * attempt).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.context(format!("Failed to install dependencies after {} attempts", attempt))),
+        }
+    }
+}
+
+/// Removes the project and scaffolds it again from `template_url`, ignoring any existing state.
+pub async fn rescaffold_nextjs_project(
+    project_root: &Path,
+    template_url: &str,
+    template_vars: &HashMap<String, String>,
+) -> Result<()> {
+    tracing::info!(target: "dev_setup::nextjs", path = %project_root.display(), "Forcing a fresh scaffold of the project.");
+    clear_scaffold_marker(project_root);
+    remove_project_dir(project_root)?;
+    scaffold_nextjs_project(project_root, template_url, template_vars).await
+}
+
+/// Clones `template_url` into `project_root` and installs its dependencies.
+///
+/// Progress is recorded in a marker file, so a run interrupted mid-clone or mid-install is
+/// resumed (or cleaned up and restarted) the next time this is called.
+pub async fn scaffold_nextjs_project(
+    project_root: &Path,
+    template_url: &str,
+    template_vars: &HashMap<String, String>,
+) -> Result<()> {
     tracing::info!(
         target: "dev_setup::nextjs",
         path = %project_root.display(),
@@ -12,6 +145,15 @@ pub async fn scaffold_nextjs_project(project_root: &Path, template_url: &str) ->
         "Scaffolding Next.js project: Cloning template to desired project location."
     );
 
+    let marker = read_scaffold_marker(project_root);
+    let resume = resume_stage(marker.as_ref(), template_url, project_root.exists());
+    if let Some(stage) = resume {
+        tracing::warn!(target: "dev_setup::nextjs", path = %project_root.display(), ?stage, "Found an interrupted scaffold; resuming.");
+    }
+    if resume == Some(ScaffoldStage::Cloning) {
+        remove_project_dir(project_root)?;
+    }
+
     // Only create the project directory if it does not exist
     if !project_root.exists() {
         // Ensure the parent directory exists
@@ -23,29 +165,35 @@ pub async fn scaffold_nextjs_project(project_root: &Path, template_url: &str) ->
                 )
             })?;
         }
+        write_scaffold_marker(project_root, template_url, ScaffoldStage::Cloning)?;
         tracing::info!(
             target: "dev_setup::nextjs",
             path = %project_root.display(),
             template_url = template_url,
             "Cloning Next.js project template from GitHub..."
         );
-        tracing::info!("Cloning template repo...");
-        terminal::git::clone_repository(template_url, project_root).await?;
+        clone_template(project_root, template_url).await?;
+
+        // Substitute galatea.template.toml variables before installing, package.json may use them
+        super::template::apply_template_variables(project_root, template_vars)
+            .context("dev_setup::nextjs: Failed to apply template variables")?;
+        write_scaffold_marker(project_root, template_url, ScaffoldStage::Installing)?;
         tracing::info!("Clone complete. Installing dependencies...");
     } else {
         tracing::info!(target: "dev_setup::nextjs", path = %project_root.display(), "Project directory already exists. Skipping clone.");
     }
 
-    // Change to the project directory and run pnpm install
+    // Change to the project directory and install with the configured package manager
     tracing::info!(
         target: "dev_setup::nextjs",
         path = %project_root.display(),
-        "Installing dependencies with pnpm..."
+        package_manager = PackageManager::detect(project_root).name(),
+        "Installing dependencies..."
     );
-
-    terminal::pnpm::run_pnpm_command(project_root, &["install"], false)
+    install_dependencies(project_root)
         .await
-        .context("dev_setup::nextjs: Failed to install dependencies with pnpm")?;
+        .context(format!("dev_setup::nextjs: Failed to install dependencies with {}", PackageManager::detect(project_root).name()))?;
+    clear_scaffold_marker(project_root);
 
     tracing::info!(target: "dev_setup::nextjs", path = %project_root.display(), "Next.js project scaffolded successfully with template and dependencies installed.");
     Ok(())
@@ -63,7 +211,7 @@ mod tests {
         let template_url = "https://github.com/Svring/nextjs-project";
 
         // Run the scaffold function
-        let result = scaffold_nextjs_project(&project_root, template_url).await;
+        let result = scaffold_nextjs_project(&project_root, template_url, &HashMap::new()).await;
         assert!(
             result.is_ok(),
             "scaffold_nextjs_project failed: {:?}",
@@ -84,5 +232,28 @@ mod tests {
             node_modules.exists(),
             "node_modules was not created (pnpm install may have failed)"
         );
+        assert!(!is_scaffold_incomplete(&project_root), "scaffold marker was not removed");
+    }
+
+    #[test]
+    fn test_scaffold_marker_and_resume_stage() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let project_root = temp_dir.path().join("project");
+        let url = "https://github.com/Svring/nextjs-project";
+        assert_eq!(scaffold_marker_path(&project_root), temp_dir.path().join(".project.scaffold.json"));
+        assert!(!is_scaffold_incomplete(&project_root));
+
+        write_scaffold_marker(&project_root, url, ScaffoldStage::Installing).unwrap();
+        let marker = read_scaffold_marker(&project_root).unwrap();
+        assert_eq!(marker.stage, ScaffoldStage::Installing);
+
+        assert_eq!(resume_stage(Some(&marker), url, true), Some(ScaffoldStage::Installing));
+        // The clone vanished or the template changed: start over
+        assert_eq!(resume_stage(Some(&marker), url, false), Some(ScaffoldStage::Cloning));
+        assert_eq!(resume_stage(Some(&marker), "https://example.com/other", true), Some(ScaffoldStage::Cloning));
+        assert_eq!(resume_stage(None, url, true), None);
+
+        clear_scaffold_marker(&project_root);
+        assert!(!is_scaffold_incomplete(&project_root));
     }
 }
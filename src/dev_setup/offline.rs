@@ -0,0 +1,162 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{config_files, mcp_converter, registry_cache, template};
+use crate::file_system::paths;
+use crate::terminal;
+use crate::terminal::package_manager::PackageManager;
+
+// Set from the `--offline` flag at startup
+static OFFLINE_FLAG: AtomicBool = AtomicBool::new(false);
+
+const OFFLINE_ENV_VAR: &str = "GALATEA_OFFLINE";
+
+/// Enables offline mode for the rest of the process.
+pub fn set_offline(offline: bool) {
+    OFFLINE_FLAG.store(offline, Ordering::Relaxed);
+}
+
+/// Whether network access is disabled, via `--offline`, `GALATEA_OFFLINE=1` or `offline = "true"`
+/// in config.toml.
+pub fn is_offline() -> bool {
+    OFFLINE_FLAG.load(Ordering::Relaxed)
+        || std::env::var(OFFLINE_ENV_VAR).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        || config_files::get_config_value("offline").is_some_and(|v| v == "true")
+}
+
+/// Fails fast with a clear error if `operation` needs the network and offline mode is on.
+pub fn ensure_online(operation: &str) -> Result<()> {
+    if is_offline() {
+        bail!(
+            "{} requires network access, but Galatea is running in offline mode. \
+             Run `galatea --prewarm` while online to populate the local caches, or disable offline mode.",
+            operation
+        );
+    }
+    Ok(())
+}
+
+/// Whether an API base URL points at this machine (e.g. a local embedding server).
+pub fn is_local_url(url: &str) -> bool {
+    let host = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', ':'])
+        .next()
+        .unwrap_or_default();
+    matches!(host, "localhost" | "127.0.0.1" | "0.0.0.0") || url.contains("://[::1]")
+}
+
+/// Fails fast if an HTTP API at `api_base` can't be reached in offline mode.
+///
+/// Local endpoints stay usable; the default (hosted) provider does not.
+pub fn ensure_api_reachable(operation: &str, api_base: Option<&str>) -> Result<()> {
+    match api_base {
+        Some(base) if is_local_url(base) => Ok(()),
+        _ => ensure_online(operation),
+    }
+}
+
+// --- Template cache ---
+
+fn cache_key(url: &str) -> String {
+    url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+/// Directory holding the cached clone of a template repository.
+pub fn template_cache_path(url: &str) -> Result<PathBuf> {
+    Ok(paths::cache_dir()?.join("templates").join(cache_key(url)))
+}
+
+/// Where to clone a template from: the URL itself, or the local cache in offline mode.
+pub fn template_source(url: &str) -> Result<String> {
+    // Local templates don't need the network
+    if !is_offline() || Path::new(url).exists() {
+        return Ok(url.to_string());
+    }
+    let cached = template_cache_path(url)?;
+    if !cached.join(".git").exists() {
+        bail!(
+            "Template {} is not cached at {}, and Galatea is running in offline mode. \
+             Run `galatea --prewarm` while online to cache it.",
+            url,
+            cached.display()
+        );
+    }
+    Ok(cached.to_string_lossy().into_owned())
+}
+
+/// Flags for package manager commands that resolve packages (`install`, `add`, `update`,
+/// `outdated`): through the registry cache when it runs, otherwise restricted to the local store
+/// offline.
+pub fn registry_args() -> Vec<&'static str> {
+    match registry_cache::registry_url() {
+        // The cache answers from disk while offline, so installs can still resolve through it
+        Some(url) => vec!["--registry", url],
+        None if is_offline() => vec!["--offline"],
+        None => Vec::new(),
+    }
+}
+
+/// Populates the caches offline mode relies on: the template clone, the pnpm store with the
+/// template's dependencies, and the globally installed MCP generator.
+pub async fn prewarm(template_name: Option<&str>, use_sudo: bool) -> Result<PathBuf> {
+    if is_offline() {
+        bail!("Cannot prewarm caches in offline mode");
+    }
+    let (url, template_dir) = template::split_source(template::resolve_template_url(template_name));
+    template::check_repo(url)?;
+    let cached = template_cache_path(url)?;
+
+    if cached.join(".git").exists() {
+        tracing::info!(target: "dev_setup::offline", path = %cached.display(), "Updating cached template.");
+        terminal::git::run_git_command(&cached, &["pull", "--ff-only"], false)
+            .await
+            .context(format!("Failed to update cached template {}", url))?;
+    } else {
+        std::fs::create_dir_all(cached.parent().unwrap_or(&cached))
+            .context("Failed to create template cache directory")?;
+        tracing::info!(target: "dev_setup::offline", url, path = %cached.display(), "Caching template.");
+        terminal::git::clone_repository(url, &cached).await?;
+    }
+
+    // `pnpm fetch` downloads everything in the lockfile into the store without linking node_modules
+    tracing::info!(target: "dev_setup::offline", "Fetching template dependencies into the pnpm store.");
+    let mut fetch_args = vec!["fetch"];
+    if let Some(url) = registry_cache::registry_url() {
+        fetch_args.extend(["--registry", url]);
+    }
+    PackageManager::Pnpm
+        .run(&cached.join(template_dir.unwrap_or_default()), &fetch_args, false)
+        .await
+        .context("Failed to fetch template dependencies into the pnpm store")?;
+
+    mcp_converter::ensure_openapi_mcp_generator_installed(use_sudo).await?;
+
+    tracing::info!(target: "dev_setup::offline", path = %cached.display(), "Offline caches are ready.");
+    Ok(cached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_urls_and_cache_keys() {
+        assert!(is_local_url("http://localhost:11434/v1"));
+        assert!(is_local_url("http://127.0.0.1:8080"));
+        assert!(is_local_url("http://[::1]:8080/v1"));
+        assert!(!is_local_url("https://api.openai.com/v1"));
+        assert!(!is_local_url("https://localhost.example.com/v1"));
+
+        assert_eq!(
+            cache_key("https://github.com/Svring/nextjs-project.git"),
+            "https___github.com_Svring_nextjs-project"
+        );
+    }
+}
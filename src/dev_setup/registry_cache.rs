@@ -0,0 +1,342 @@
+use anyhow::{bail, Context, Result};
+use once_cell::sync::{Lazy, OnceCell};
+use poem::http::StatusCode;
+use poem::listener::{Acceptor, Listener, TcpListener};
+use poem::{handler, Body, Request, Response, Route, Server};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use super::{config_files, offline};
+use crate::file_system::paths;
+
+const DEFAULT_UPSTREAM: &str = "https://registry.npmjs.org";
+const DEFAULT_PORT: u16 = 4873;
+const DEFAULT_METADATA_TTL_SECS: u64 = 300;
+const ABBREVIATED_METADATA: &str = "application/vnd.npm.install-v1+json";
+
+// Local URL of the running cache, set once it is listening
+static REGISTRY_URL: OnceCell<String> = OnceCell::new();
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default()
+});
+
+/// Whether the cache should run: `--registry-cache`, or `registry_cache = "true"` in config.toml.
+pub fn is_enabled(cli_flag: bool) -> bool {
+    cli_flag || config_files::get_config_value("registry_cache").is_some_and(|v| v == "true")
+}
+
+/// URL npm and pnpm should use as their registry, once the cache is running.
+pub fn registry_url() -> Option<&'static str> {
+    REGISTRY_URL.get().map(String::as_str)
+}
+
+fn upstream() -> String {
+    config_files::get_config_value("registry_upstream")
+        .unwrap_or_else(|| DEFAULT_UPSTREAM.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn metadata_ttl() -> Duration {
+    Duration::from_secs(
+        config_files::get_config_value("registry_metadata_ttl_secs")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_METADATA_TTL_SECS),
+    )
+}
+
+/// Where packuments and tarballs are kept. Outside galatea_files, so they survive re-scaffolds.
+pub fn cache_root() -> Result<PathBuf> {
+    Ok(paths::cache_dir()?.join("registry"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RegistryRequest {
+    /// Package document (`/<name>`), abbreviated when the client asked for install metadata
+    Metadata { name: String, abbreviated: bool },
+    /// Tarball (`/<name>/-/<file>.tgz`)
+    Tarball { name: String, file: String },
+    /// Anything else (audit, search, login...), forwarded without caching
+    Passthrough,
+}
+
+fn classify(path: &str, accept: &str) -> RegistryRequest {
+    let path = path.trim_start_matches('/').replace("%2f", "/").replace("%2F", "/").replace("%40", "@");
+    if path.is_empty() || path.starts_with("-/") {
+        return RegistryRequest::Passthrough;
+    }
+    let segments: Vec<&str> = path.split('/').collect();
+    let name_len = if segments[0].starts_with('@') { 2 } else { 1 };
+    if segments.len() < name_len || segments.iter().any(|s| s.is_empty() || *s == "." || *s == "..") {
+        return RegistryRequest::Passthrough;
+    }
+    let name = segments[..name_len].join("/");
+    match &segments[name_len..] {
+        [] => RegistryRequest::Metadata { name, abbreviated: accept.contains(ABBREVIATED_METADATA) },
+        ["-", file] if file.ends_with(".tgz") => RegistryRequest::Tarball { name, file: file.to_string() },
+        _ => RegistryRequest::Passthrough,
+    }
+}
+
+fn cache_path(root: &Path, relative: &str) -> Result<PathBuf> {
+    let relative = Path::new(relative);
+    if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+        bail!("Invalid cache path: {}", relative.display());
+    }
+    Ok(root.join(relative))
+}
+
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create registry cache directory")?;
+    }
+    let tmp = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&tmp, bytes).context("Failed to write registry cache entry")?;
+    std::fs::rename(&tmp, path).context("Failed to move registry cache entry into place")
+}
+
+fn is_fresh(path: &Path, ttl: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < ttl)
+}
+
+// Tarball URLs in package documents point at the upstream registry; send clients back here instead
+fn rewrite_tarball_urls(document: &str, upstream: &str, local: &str) -> String {
+    document.replace(&format!("\"{}/", upstream), &format!("\"{}/", local))
+}
+
+async fn fetch_upstream(url: &str, accept: Option<&str>) -> Result<Option<Vec<u8>>> {
+    let mut request = CLIENT.get(url);
+    if let Some(accept) = accept {
+        request = request.header("accept", accept);
+    }
+    let response = request.send().await.with_context(|| format!("Failed to reach {}", url))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let response = response.error_for_status().with_context(|| format!("Upstream registry rejected {}", url))?;
+    Ok(Some(response.bytes().await.with_context(|| format!("Failed to read {}", url))?.to_vec()))
+}
+
+fn json_response(body: String) -> Response {
+    Response::builder().content_type("application/json").body(body)
+}
+
+fn not_found() -> Response {
+    Response::builder().status(StatusCode::NOT_FOUND).content_type("application/json").body(r#"{"error":"Not found"}"#)
+}
+
+async fn serve_metadata(root: &Path, name: &str, abbreviated: bool) -> Result<Response> {
+    let file = format!("metadata/{}{}.json", name, if abbreviated { ".install" } else { "" });
+    let path = cache_path(root, &file)?;
+    let (upstream, local) = (upstream(), registry_url().unwrap_or_default().to_string());
+
+    if path.exists() && (offline::is_offline() || is_fresh(&path, metadata_ttl())) {
+        HITS.fetch_add(1, Ordering::Relaxed);
+        let document = std::fs::read_to_string(&path).context("Failed to read cached package document")?;
+        return Ok(json_response(rewrite_tarball_urls(&document, &upstream, &local)));
+    }
+    if offline::is_offline() {
+        MISSES.fetch_add(1, Ordering::Relaxed);
+        return Ok(not_found());
+    }
+
+    let accept = abbreviated.then_some(ABBREVIATED_METADATA);
+    match fetch_upstream(&format!("{}/{}", upstream, name.replace('/', "%2f")), accept).await {
+        Ok(Some(bytes)) => {
+            MISSES.fetch_add(1, Ordering::Relaxed);
+            write_atomically(&path, &bytes)?;
+            Ok(json_response(rewrite_tarball_urls(&String::from_utf8_lossy(&bytes), &upstream, &local)))
+        }
+        Ok(None) => Ok(not_found()),
+        // Upstream is unreachable; a stale document beats failing the install
+        Err(e) if path.exists() => {
+            tracing::warn!(target: "dev_setup::registry_cache", package = name, error = ?e, "Upstream registry unavailable; serving stale package document.");
+            HITS.fetch_add(1, Ordering::Relaxed);
+            let document = std::fs::read_to_string(&path).context("Failed to read cached package document")?;
+            Ok(json_response(rewrite_tarball_urls(&document, &upstream, &local)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn serve_tarball(root: &Path, name: &str, file: &str) -> Result<Response> {
+    let path = cache_path(root, &format!("tarballs/{}/{}", name, file))?;
+    // Published versions are immutable, so a cached tarball never goes stale
+    if path.exists() {
+        HITS.fetch_add(1, Ordering::Relaxed);
+        let bytes = tokio::fs::read(&path).await.context("Failed to read cached tarball")?;
+        return Ok(Response::builder().content_type("application/octet-stream").body(bytes));
+    }
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    if offline::is_offline() {
+        return Ok(not_found());
+    }
+    match fetch_upstream(&format!("{}/{}/-/{}", upstream(), name, file), None).await? {
+        Some(bytes) => {
+            write_atomically(&path, &bytes)?;
+            Ok(Response::builder().content_type("application/octet-stream").body(bytes))
+        }
+        None => Ok(not_found()),
+    }
+}
+
+async fn passthrough(req: &Request, body: Body) -> Result<Response> {
+    offline::ensure_online("Forwarding a registry request")?;
+    let mut url = format!("{}{}", upstream(), req.uri().path());
+    if let Some(query) = req.uri().query() {
+        url.push('?');
+        url.push_str(query);
+    }
+    let mut request = CLIENT.request(req.method().clone(), &url);
+    for (key, value) in req.headers() {
+        if key != "host" && key != "accept-encoding" {
+            request = request.header(key, value);
+        }
+    }
+    let response = request
+        .body(body.into_bytes().await.context("Failed to read request body")?)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", url))?;
+    let mut builder = Response::builder().status(response.status());
+    if let Some(content_type) = response.headers().get("content-type") {
+        builder = builder.header("content-type", content_type);
+    }
+    Ok(builder.body(response.bytes().await.context("Failed to read upstream response")?))
+}
+
+#[handler]
+async fn registry(req: &Request, body: Body) -> Response {
+    let accept = req.headers().get("accept").and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let request = if req.method() == poem::http::Method::GET {
+        classify(req.uri().path(), accept)
+    } else {
+        RegistryRequest::Passthrough
+    };
+    let result = match cache_root() {
+        Ok(root) => match &request {
+            RegistryRequest::Metadata { name, abbreviated } => serve_metadata(&root, name, *abbreviated).await,
+            RegistryRequest::Tarball { name, file } => serve_tarball(&root, name, file).await,
+            RegistryRequest::Passthrough => passthrough(req, body).await,
+        },
+        Err(e) => Err(e),
+    };
+    result.unwrap_or_else(|e| {
+        tracing::warn!(target: "dev_setup::registry_cache", path = %req.uri().path(), error = ?e, "Registry request failed.");
+        Response::builder()
+            .status(StatusCode::BAD_GATEWAY)
+            .content_type("application/json")
+            .body(serde_json::json!({ "error": format!("{:#}", e) }).to_string())
+    })
+}
+
+/// Starts the registry cache on `127.0.0.1` (port `registry_cache_port`, 4873 by default) and
+/// returns its URL. npm and pnpm installs run by Galatea are pointed at it from then on.
+pub async fn start() -> Result<&'static str> {
+    if let Some(url) = registry_url() {
+        return Ok(url);
+    }
+    let port = config_files::get_config_value("registry_cache_port")
+        .and_then(|v| v.parse::<u16>().ok())
+        .unwrap_or(DEFAULT_PORT);
+    let acceptor = TcpListener::bind(format!("127.0.0.1:{}", port))
+        .into_acceptor()
+        .await
+        .with_context(|| format!("Failed to bind the registry cache to port {}", port))?;
+    let port = acceptor
+        .local_addr()
+        .first()
+        .and_then(|addr| addr.as_socket_addr().map(|a| a.port()))
+        .unwrap_or(port);
+    let url = REGISTRY_URL.get_or_init(|| format!("http://127.0.0.1:{}", port));
+
+    tokio::spawn(async move {
+        let app = Route::new().at("/*path", registry).at("/", registry);
+        if let Err(e) = Server::new_with_acceptor(acceptor).run(app).await {
+            tracing::error!(target: "dev_setup::registry_cache", error = ?e, "Registry cache server stopped.");
+        }
+    });
+    tracing::info!(target: "dev_setup::registry_cache", url = %url, upstream = %upstream(), "Registry cache is running.");
+    Ok(url)
+}
+
+/// Cache location, size and hit counts since startup.
+#[derive(Debug, Clone)]
+pub struct RegistryCacheStats {
+    pub url: Option<String>,
+    pub upstream: String,
+    pub cache_dir: String,
+    pub packages: usize,
+    pub tarballs: usize,
+    pub size_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+pub fn stats() -> Result<RegistryCacheStats> {
+    let root = cache_root()?;
+    let (mut packages, mut tarballs, mut size_bytes) = (0, 0, 0);
+    for entry in walkdir::WalkDir::new(&root).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        size_bytes += entry.metadata().map(|m| m.len()).unwrap_or_default();
+        let name = entry.file_name().to_string_lossy();
+        if name.ends_with(".tgz") {
+            tarballs += 1;
+        } else if name.ends_with(".json") && !name.ends_with(".install.json") {
+            packages += 1;
+        }
+    }
+    Ok(RegistryCacheStats {
+        url: registry_url().map(str::to_string),
+        upstream: upstream(),
+        cache_dir: root.display().to_string(),
+        packages,
+        tarballs,
+        size_bytes,
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_registry_paths() {
+        assert_eq!(
+            classify("/react", "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8"),
+            RegistryRequest::Metadata { name: "react".to_string(), abbreviated: true }
+        );
+        assert_eq!(
+            classify("/@types%2fnode", "application/json"),
+            RegistryRequest::Metadata { name: "@types/node".to_string(), abbreviated: false }
+        );
+        assert_eq!(
+            classify("/@types/node/-/node-20.1.0.tgz", ""),
+            RegistryRequest::Tarball { name: "@types/node".to_string(), file: "node-20.1.0.tgz".to_string() }
+        );
+        assert_eq!(classify("/-/npm/v1/security/advisories/bulk", ""), RegistryRequest::Passthrough);
+        assert_eq!(classify("/../etc/-/passwd.tgz", ""), RegistryRequest::Passthrough);
+
+        let document = r#"{"dist":{"tarball":"https://registry.npmjs.org/react/-/react-19.0.0.tgz"}}"#;
+        assert_eq!(
+            rewrite_tarball_urls(document, "https://registry.npmjs.org", "http://127.0.0.1:4873"),
+            r#"{"dist":{"tarball":"http://127.0.0.1:4873/react/-/react-19.0.0.tgz"}}"#
+        );
+    }
+}
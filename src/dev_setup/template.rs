@@ -0,0 +1,390 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::terminal;
+
+/// File inside a template repository that declares its variables.
+pub const TEMPLATE_METADATA_FILE: &str = "galatea.template.toml";
+
+pub const DEFAULT_TEMPLATE_URL: &str = "https://github.com/Svring/nextjs-project";
+
+// Directories never touched by variable substitution
+const SUBSTITUTION_EXCLUDE_DIRS: &[&str] = &["node_modules", ".git", ".next", "dist", "build"];
+
+// Files larger than this are assumed not to be hand-written templates
+const MAX_SUBSTITUTION_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    String,
+    Number,
+    Bool,
+    Color,
+    Choice,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "type", default = "default_variable_type")]
+    pub var_type: VariableType,
+    #[serde(default)]
+    pub default: Option<toml::Value>,
+    // Allowed values for `choice` variables
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+fn default_variable_type() -> VariableType {
+    VariableType::String
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TemplateInfo {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Parsed `galatea.template.toml`.
+///
+/// ```toml
+/// [template]
+/// name = "nextjs"
+///
+/// [[variables]]
+/// name = "brand_color"
+/// type = "color"
+/// default = "#0f172a"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TemplateMetadata {
+    #[serde(default)]
+    pub template: TemplateInfo,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+}
+
+impl TemplateVariable {
+    /// Default value rendered as the string substituted into files.
+    pub fn default_as_string(&self) -> Option<String> {
+        self.default.as_ref().map(|value| match value {
+            toml::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+    }
+
+    fn validate(&self, value: &str) -> Result<()> {
+        match self.var_type {
+            VariableType::String => Ok(()),
+            VariableType::Number => value
+                .parse::<f64>()
+                .map(|_| ())
+                .map_err(|_| anyhow!("Variable '{}' must be a number, got '{}'", self.name, value)),
+            VariableType::Bool => match value {
+                "true" | "false" => Ok(()),
+                _ => bail!("Variable '{}' must be 'true' or 'false', got '{}'", self.name, value),
+            },
+            VariableType::Color => {
+                let hex = value.strip_prefix('#').unwrap_or("");
+                if (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    Ok(())
+                } else {
+                    bail!("Variable '{}' must be a hex color like '#1e40af', got '{}'", self.name, value)
+                }
+            }
+            VariableType::Choice => {
+                if self.options.iter().any(|o| o == value) {
+                    Ok(())
+                } else {
+                    bail!(
+                        "Variable '{}' must be one of [{}], got '{}'",
+                        self.name,
+                        self.options.join(", "),
+                        value
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Maps a template name (as passed to `--template`) to its source: a built-in template's, or the
+/// value itself as a git URL.
+pub fn resolve_template_url(template: Option<&str>) -> &str {
+    match template {
+        None => DEFAULT_TEMPLATE_URL,
+        Some(name) => super::template_registry::find(name).map_or(name, |t| t.source),
+    }
+}
+
+/// Splits a template source into the repository and, for `<repo>#<dir>`, the directory inside it
+/// holding the template.
+pub fn split_source(source: &str) -> (&str, Option<&str>) {
+    match source.split_once('#') {
+        Some((repo, dir)) if !dir.is_empty() => (repo, Some(dir.trim_matches('/'))),
+        _ => (source.trim_end_matches('#'), None),
+    }
+}
+
+/// Checks that a template repository is an `https://` URL or an existing local directory, so a
+/// user-supplied template can't smuggle in a git option (`--upload-pack=...`) or a remote helper
+/// (`ext::sh -c ...`).
+pub fn check_repo(repo: &str) -> Result<()> {
+    let is_https = repo
+        .strip_prefix("https://")
+        .is_some_and(|rest| !rest.is_empty() && !rest.chars().any(char::is_whitespace));
+    if is_https || (!repo.starts_with('-') && !repo.contains("::") && Path::new(repo).is_dir()) {
+        return Ok(());
+    }
+    bail!("Template repository '{}' must be an https:// URL or a local directory", repo)
+}
+
+/// Clones a template source into `target`: the whole repository, or only the directory it names.
+pub async fn clone_source(source: &str, target: &Path) -> Result<()> {
+    let (repo, dir) = split_source(source);
+    check_repo(repo)?;
+    let repo = super::offline::template_source(repo)?;
+    let Some(dir) = dir else {
+        return terminal::git::clone_repository(&repo, target).await;
+    };
+
+    // The rest of the repository is dropped, so a shallow clone beside the target is enough
+    let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let staging = target.with_file_name(format!(".{}.template-repo", name));
+    if staging.exists() {
+        fs::remove_dir_all(&staging).context(format!("Failed to remove {}", staging.display()))?;
+    }
+    let parent = target.parent().unwrap_or_else(|| Path::new("."));
+    let staging_name = staging.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let result = async {
+        terminal::git::run_git_command(parent, &["clone", "--depth", "1", "--", &repo, &staging_name], false)
+            .await
+            .context(format!("Failed to clone template repository {}", repo))?;
+        let template_dir = staging.join(dir);
+        if !template_dir.is_dir() {
+            bail!("Template directory '{}' does not exist in {}", dir, repo);
+        }
+        fs::rename(&template_dir, target)
+            .context(format!("Failed to move {} to {}", template_dir.display(), target.display()))
+    }
+    .await;
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+pub fn parse_metadata(content: &str) -> Result<TemplateMetadata> {
+    toml::from_str(content).context(format!("Failed to parse {}", TEMPLATE_METADATA_FILE))
+}
+
+/// Reads the template metadata from a checked-out template, if it has any.
+pub fn read_metadata(template_dir: &Path) -> Result<Option<TemplateMetadata>> {
+    let path = template_dir.join(TEMPLATE_METADATA_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+    parse_metadata(&content).map(Some)
+}
+
+/// Fetches the metadata of a template without scaffolding it, via a shallow clone.
+pub async fn fetch_template_metadata(template: &str) -> Result<Option<TemplateMetadata>> {
+    let (repo, dir) = split_source(resolve_template_url(Some(template)));
+    check_repo(repo)?;
+    let url = super::offline::template_source(repo)?;
+    let url = url.as_str();
+    let temp_dir = tempfile::tempdir().context("Failed to create temporary directory for template")?;
+    let checkout_dir = temp_dir.path().join("template");
+    terminal::git::run_git_command(
+        temp_dir.path(),
+        &["clone", "--depth", "1", "--", url, "template"],
+        true,
+    )
+    .await
+    .context(format!("Failed to fetch template {}", url))?;
+    read_metadata(&checkout_dir.join(dir.unwrap_or_default()))
+}
+
+/// Validates provided values against the metadata and fills in defaults.
+pub fn resolve_values(
+    metadata: &TemplateMetadata,
+    provided: &HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    for key in provided.keys() {
+        if !metadata.variables.iter().any(|v| &v.name == key) {
+            tracing::warn!(target: "dev_setup::template", variable = %key, "Ignoring value for variable not declared by the template.");
+        }
+    }
+
+    let mut values = HashMap::new();
+    for variable in &metadata.variables {
+        let value = match provided.get(&variable.name).cloned().or_else(|| variable.default_as_string()) {
+            Some(value) => value,
+            None if variable.required => bail!("Missing value for required template variable '{}'", variable.name),
+            None => continue,
+        };
+        variable.validate(&value)?;
+        values.insert(variable.name.clone(), value);
+    }
+    Ok(values)
+}
+
+/// Replaces `{{variable}}` placeholders in the project's text files.
+///
+/// Returns the number of files changed.
+pub fn substitute_variables(project_dir: &Path, values: &HashMap<String, String>) -> Result<usize> {
+    if values.is_empty() {
+        return Ok(0);
+    }
+
+    let mut changed = 0;
+    let walker = WalkDir::new(project_dir).into_iter().filter_entry(|entry| {
+        !(entry.file_type().is_dir()
+            && entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| SUBSTITUTION_EXCLUDE_DIRS.contains(&name)))
+    });
+    for entry in walker {
+        let entry = entry.context("Failed to walk project directory")?;
+        if !entry.file_type().is_file()
+            || entry.file_name() == TEMPLATE_METADATA_FILE
+            || entry.metadata().map(|m| m.len() > MAX_SUBSTITUTION_FILE_SIZE).unwrap_or(true)
+        {
+            continue;
+        }
+        // Binary or non-UTF-8 files are left alone
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        if !content.contains("{{") {
+            continue;
+        }
+        let mut updated = content.clone();
+        for (name, value) in values {
+            updated = updated.replace(&format!("{{{{{}}}}}", name), value);
+        }
+        if updated != content {
+            fs::write(entry.path(), updated)
+                .context(format!("Failed to write {}", entry.path().display()))?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Applies template variables to a freshly cloned template, if it declares any.
+pub fn apply_template_variables(project_dir: &Path, provided: &HashMap<String, String>) -> Result<()> {
+    let Some(metadata) = read_metadata(project_dir)? else {
+        if !provided.is_empty() {
+            tracing::warn!(target: "dev_setup::template", "Template variables were provided but the template has no {}.", TEMPLATE_METADATA_FILE);
+        }
+        return Ok(());
+    };
+    let values = resolve_values(&metadata, provided)?;
+    let changed = substitute_variables(project_dir, &values)?;
+    tracing::info!(target: "dev_setup::template", variables = values.len(), files_changed = changed, "Applied template variables.");
+    Ok(())
+}
+
+/// Parses `key=value` pairs as given on the command line.
+pub fn parse_variable_args(args: &[String]) -> Result<HashMap<String, String>> {
+    args.iter()
+        .map(|arg| {
+            arg.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.to_string()))
+                .ok_or_else(|| anyhow!("Invalid template variable '{}', expected key=value", arg))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const METADATA: &str = r##"
+[template]
+name = "nextjs"
+
+[[variables]]
+name = "project_name"
+required = true
+
+[[variables]]
+name = "brand_color"
+type = "color"
+default = "#0f172a"
+
+[[variables]]
+name = "enable_auth"
+type = "bool"
+default = false
+
+[[variables]]
+name = "ui_kit"
+type = "choice"
+options = ["shadcn", "none"]
+"##;
+
+    #[test]
+    fn test_resolve_values_applies_defaults_and_validates() {
+        let metadata = parse_metadata(METADATA).unwrap();
+        assert_eq!(metadata.variables.len(), 4);
+        assert_eq!(metadata.variables[0].var_type, VariableType::String);
+
+        let provided = HashMap::from([("project_name".to_string(), "acme".to_string())]);
+        let values = resolve_values(&metadata, &provided).unwrap();
+        assert_eq!(values["project_name"], "acme");
+        assert_eq!(values["brand_color"], "#0f172a");
+        assert_eq!(values["enable_auth"], "false");
+        assert!(!values.contains_key("ui_kit"));
+
+        assert!(resolve_values(&metadata, &HashMap::new()).is_err());
+        let bad_color = HashMap::from([
+            ("project_name".to_string(), "acme".to_string()),
+            ("brand_color".to_string(), "blue".to_string()),
+        ]);
+        assert!(resolve_values(&metadata, &bad_color).is_err());
+    }
+
+    #[test]
+    fn test_check_repo_rejects_options_and_remote_helpers() {
+        assert!(check_repo(DEFAULT_TEMPLATE_URL).is_ok());
+        assert!(check_repo(tempdir().unwrap().path().to_str().unwrap()).is_ok());
+        for repo in ["--upload-pack=touch /tmp/pwned", "ext::sh -c touch% /tmp/pwned", "git@github.com:a/b.git", "file:///etc", "https://"] {
+            assert!(check_repo(repo).is_err(), "{}", repo);
+        }
+    }
+
+    #[test]
+    fn test_apply_template_variables_substitutes_placeholders() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(TEMPLATE_METADATA_FILE), METADATA).unwrap();
+        fs::write(dir.path().join("package.json"), r#"{"name": "{{project_name}}"}"#).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules")).unwrap();
+        fs::write(dir.path().join("node_modules").join("x.js"), "{{project_name}}").unwrap();
+
+        let provided = HashMap::from([("project_name".to_string(), "acme".to_string())]);
+        apply_template_variables(dir.path(), &provided).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("package.json")).unwrap(),
+            r#"{"name": "acme"}"#
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("node_modules").join("x.js")).unwrap(),
+            "{{project_name}}"
+        );
+    }
+}
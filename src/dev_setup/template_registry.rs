@@ -0,0 +1,150 @@
+use super::config_files;
+use super::template::DEFAULT_TEMPLATE_URL;
+use crate::terminal::package_manager::PackageManager;
+
+/// A project template Galatea knows by name, for `--template <name>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateDefinition {
+    pub name: &'static str,
+    pub display_name: &'static str,
+    pub description: &'static str,
+    pub framework: &'static str,
+    /// Git repository to clone, with `#<dir>` when the template is one directory of it
+    pub source: &'static str,
+    /// Arguments passed to the `dev` script, so the server listens where Galatea expects it
+    pub dev_args: &'static [&'static str],
+    /// Port the dev server listens on; the preview is proxied to it
+    pub port: u16,
+    /// package.json scripts the template provides, with what they do
+    pub scripts: &'static [(&'static str, &'static str)],
+}
+
+// Vite-based dev servers bind to `localhost`, which may resolve to IPv6 only
+const VITE_DEV_ARGS: &[&str] = &["--host", "127.0.0.1", "--port", "5173", "--strictPort"];
+
+static BUILTIN_TEMPLATES: &[TemplateDefinition] = &[
+    TemplateDefinition {
+        name: "nextjs",
+        display_name: "Next.js",
+        description: "Next.js app router project with TypeScript and Tailwind CSS. The default.",
+        framework: "nextjs",
+        source: DEFAULT_TEMPLATE_URL,
+        dev_args: &[],
+        port: 3000,
+        scripts: &[
+            ("dev", "Start the dev server"),
+            ("build", "Build for production"),
+            ("start", "Serve the production build"),
+            ("lint", "Run ESLint"),
+        ],
+    },
+    TemplateDefinition {
+        name: "nextjs-shadcn",
+        display_name: "Next.js + shadcn/ui",
+        description: "Next.js app router project with shadcn/ui components, Tailwind CSS and dark mode.",
+        framework: "nextjs",
+        source: "https://github.com/shadcn-ui/next-template",
+        dev_args: &[],
+        port: 3000,
+        scripts: &[
+            ("dev", "Start the dev server"),
+            ("build", "Build for production"),
+            ("start", "Serve the production build"),
+            ("lint", "Run ESLint"),
+            ("typecheck", "Type-check with tsc"),
+        ],
+    },
+    TemplateDefinition {
+        name: "vite-react",
+        display_name: "Vite + React",
+        description: "Single-page React app with TypeScript, built with Vite.",
+        framework: "vite",
+        source: "https://github.com/vitejs/vite#packages/create-vite/template-react-ts",
+        dev_args: VITE_DEV_ARGS,
+        port: 5173,
+        scripts: &[
+            ("dev", "Start the dev server"),
+            ("build", "Type-check and build for production"),
+            ("preview", "Serve the production build"),
+            ("lint", "Run ESLint"),
+        ],
+    },
+    TemplateDefinition {
+        name: "remix",
+        display_name: "Remix",
+        description: "Full-stack React app on React Router's framework mode, the successor to Remix.",
+        framework: "remix",
+        source: "https://github.com/remix-run/react-router-templates#default",
+        dev_args: VITE_DEV_ARGS,
+        port: 5173,
+        scripts: &[
+            ("dev", "Start the dev server"),
+            ("build", "Build for production"),
+            ("start", "Serve the production build"),
+            ("typecheck", "Generate route types and type-check with tsc"),
+        ],
+    },
+    TemplateDefinition {
+        name: "astro",
+        display_name: "Astro",
+        description: "Content-focused Astro site, the basics starter.",
+        framework: "astro",
+        source: "https://github.com/withastro/astro#examples/basics",
+        dev_args: &["--host", "127.0.0.1", "--port", "4321"],
+        port: 4321,
+        scripts: &[
+            ("dev", "Start the dev server"),
+            ("build", "Build the static site"),
+            ("preview", "Serve the built site"),
+        ],
+    },
+];
+
+pub const DEFAULT_TEMPLATE: &str = "nextjs";
+
+impl TemplateDefinition {
+    /// Command that starts the dev server with `package_manager`.
+    pub fn dev_command(&self, package_manager: PackageManager) -> Vec<String> {
+        package_manager.script_command("dev", self.dev_args)
+    }
+}
+
+/// Every built-in template, the default first.
+pub fn all() -> &'static [TemplateDefinition] {
+    BUILTIN_TEMPLATES
+}
+
+pub fn find(name: &str) -> Option<&'static TemplateDefinition> {
+    BUILTIN_TEMPLATES.iter().find(|t| t.name == name)
+}
+
+/// The template the project was scaffolded from. Projects from a custom template URL are
+/// assumed to run like the default Next.js template.
+pub fn current() -> &'static TemplateDefinition {
+    config_files::get_config_value("template")
+        .and_then(|name| find(&name))
+        .unwrap_or(&BUILTIN_TEMPLATES[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev_setup::template::{resolve_template_url, split_source};
+
+    #[test]
+    fn test_registry_lookup_and_dev_command() {
+        assert_eq!(all()[0].name, DEFAULT_TEMPLATE);
+        assert_eq!(resolve_template_url(None), DEFAULT_TEMPLATE_URL);
+        assert_eq!(resolve_template_url(Some("nextjs-shadcn")), "https://github.com/shadcn-ui/next-template");
+        assert_eq!(resolve_template_url(Some("https://example.com/t.git")), "https://example.com/t.git");
+        assert_eq!(
+            split_source(resolve_template_url(Some("astro"))),
+            ("https://github.com/withastro/astro", Some("examples/basics"))
+        );
+
+        let vite = find("vite-react").unwrap();
+        assert_eq!(vite.dev_command(PackageManager::Pnpm)[..4], ["pnpm", "run", "dev", "--host"]);
+        assert_eq!(vite.dev_command(PackageManager::Npm)[..4], ["npm", "run", "dev", "--"]);
+        assert_eq!(find("nextjs").unwrap().dev_command(PackageManager::Npm), ["npm", "run", "dev"]);
+    }
+}
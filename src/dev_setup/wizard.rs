@@ -0,0 +1,354 @@
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+use super::{config_files, env};
+use crate::terminal::package_manager;
+use crate::file_system::paths;
+
+// config.toml key listing the steps answered so far, so a restarted wizard picks up where it was
+const STEPS_CONFIG_KEY: &str = "setup_steps";
+// config.toml key set once the wizard has set the environment up
+const COMPLETED_CONFIG_KEY: &str = "setup_completed";
+const TEMPLATE_VARS_SECTION: &str = "template_vars";
+pub const MIN_TOKEN_LENGTH: usize = 16;
+
+/// Steps of the first-run setup wizard, in the order a frontend walks them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardStep {
+    Template,
+    Tokens,
+    PackageManager,
+    Mcp,
+    Auth,
+}
+
+impl WizardStep {
+    pub const ALL: [WizardStep; 5] = [
+        WizardStep::Template,
+        WizardStep::Tokens,
+        WizardStep::PackageManager,
+        WizardStep::Mcp,
+        WizardStep::Auth,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WizardStep::Template => "template",
+            WizardStep::Tokens => "tokens",
+            WizardStep::PackageManager => "package_manager",
+            WizardStep::Mcp => "mcp",
+            WizardStep::Auth => "auth",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|step| step.as_str() == s)
+    }
+}
+
+/// Where the wizard is overall. Answers can be changed until `Applying`, and again after `Failed`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WizardPhase {
+    Collecting,
+    Applying,
+    Done,
+    Failed(String),
+}
+
+impl WizardPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WizardPhase::Collecting => "collecting",
+            WizardPhase::Applying => "applying",
+            WizardPhase::Done => "done",
+            WizardPhase::Failed(_) => "failed",
+        }
+    }
+}
+
+/// An answer to one wizard step.
+#[derive(Debug, Clone)]
+pub enum StepAnswer {
+    Template { template: String, vars: BTreeMap<String, String> },
+    Tokens { openai_api_key: Option<String>, openai_api_base: Option<String> },
+    PackageManager(String),
+    Mcp(bool),
+    /// `None` generates a token
+    Auth(Option<String>),
+}
+
+impl StepAnswer {
+    fn step(&self) -> WizardStep {
+        match self {
+            StepAnswer::Template { .. } => WizardStep::Template,
+            StepAnswer::Tokens { .. } => WizardStep::Tokens,
+            StepAnswer::PackageManager(_) => WizardStep::PackageManager,
+            StepAnswer::Mcp(_) => WizardStep::Mcp,
+            StepAnswer::Auth(_) => WizardStep::Auth,
+        }
+    }
+}
+
+/// Answers given so far, as persisted in config.toml.
+#[derive(Debug, Clone, Default)]
+pub struct WizardAnswers {
+    pub template: Option<String>,
+    pub template_vars: BTreeMap<String, String>,
+    pub openai_api_key: Option<String>,
+    pub openai_api_base: Option<String>,
+    pub package_manager: Option<String>,
+    pub mcp_enabled: Option<bool>,
+    pub token: Option<String>,
+}
+
+impl WizardAnswers {
+    fn load() -> Self {
+        let template_vars = match config_files::get_config_section(TEMPLATE_VARS_SECTION) {
+            Some(toml::Value::Table(table)) => table
+                .into_iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k, v.to_string())))
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+        Self {
+            template: config_files::get_config_value("template"),
+            template_vars,
+            openai_api_key: config_files::get_config_value("openai_api_key"),
+            openai_api_base: config_files::get_config_value("openai_api_base"),
+            package_manager: config_files::get_config_value("package_manager"),
+            mcp_enabled: config_files::get_config_value("mcp_enabled").map(|v| v == "true"),
+            token: config_files::get_config_value("token"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WizardStatus {
+    pub phase: WizardPhase,
+    /// First unanswered step, or `None` once every step is answered
+    pub current_step: Option<WizardStep>,
+    pub completed_steps: Vec<WizardStep>,
+    pub answers: WizardAnswers,
+    pub project_dir: Option<PathBuf>,
+}
+
+struct WizardState {
+    phase: WizardPhase,
+    completed: Vec<WizardStep>,
+    project_dir: Option<PathBuf>,
+}
+
+static STATE: Lazy<Mutex<WizardState>> = Lazy::new(|| {
+    let completed = config_files::get_config_value(STEPS_CONFIG_KEY)
+        .map(|steps| steps.split(',').filter_map(WizardStep::parse).collect())
+        .unwrap_or_default();
+    let phase = if is_setup_completed() { WizardPhase::Done } else { WizardPhase::Collecting };
+    Mutex::new(WizardState { phase, completed, project_dir: None })
+});
+
+// Resolves to the project directory once the wizard has set the environment up
+static FINISHED: Lazy<watch::Sender<Option<PathBuf>>> = Lazy::new(|| watch::channel(None).0);
+
+// Set by `begin`: applying is only possible while Galatea waits on the wizard at startup
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static USE_SUDO: AtomicBool = AtomicBool::new(false);
+// Whether galatea_files was absent when the wizard started, i.e. any existing project is stale
+static FRESH_INSTALL: AtomicBool = AtomicBool::new(false);
+
+/// Whether an earlier wizard run (or an explicit `setup_completed = "true"`) finished setup.
+pub fn is_setup_completed() -> bool {
+    config_files::get_config_value(COMPLETED_CONFIG_KEY).is_some_and(|v| v == "true")
+}
+
+/// Prepares the wizard to collect answers. Creates galatea_files so answers can be persisted.
+pub fn begin(use_sudo: bool) -> Result<()> {
+    USE_SUDO.store(use_sudo, Ordering::Relaxed);
+    let galatea_files = paths::galatea_files_dir()?;
+    FRESH_INSTALL.store(!galatea_files.exists(), Ordering::Relaxed);
+    std::fs::create_dir_all(&galatea_files).context("Failed to create galatea_files directory")?;
+    ACTIVE.store(true, Ordering::Relaxed);
+    tracing::info!(target: "dev_setup::wizard", "Setup wizard is waiting for answers at /api/setup.");
+    Ok(())
+}
+
+pub fn status() -> WizardStatus {
+    let state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    WizardStatus {
+        phase: state.phase.clone(),
+        current_step: WizardStep::ALL.into_iter().find(|s| !state.completed.contains(s)),
+        completed_steps: WizardStep::ALL.into_iter().filter(|s| state.completed.contains(s)).collect(),
+        answers: WizardAnswers::load(),
+        project_dir: state.project_dir.clone(),
+    }
+}
+
+/// Validates and persists the answer to one step. Steps can be answered in any order and
+/// answered again to change them, until the wizard is applied.
+pub fn answer(answer: StepAnswer) -> Result<WizardStatus> {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    if matches!(state.phase, WizardPhase::Applying | WizardPhase::Done) {
+        bail!("Setup is already {}; answers can no longer be changed", state.phase.as_str());
+    }
+    let step = answer.step();
+    persist_answer(answer)?;
+    if !state.completed.contains(&step) {
+        state.completed.push(step);
+    }
+    let steps: Vec<&str> = state.completed.iter().map(|s| s.as_str()).collect();
+    config_files::set_config_value(STEPS_CONFIG_KEY, &steps.join(","))?;
+    tracing::info!(target: "dev_setup::wizard", step = step.as_str(), "Setup wizard step answered.");
+    drop(state);
+    Ok(status())
+}
+
+fn persist_answer(answer: StepAnswer) -> Result<()> {
+    match answer {
+        StepAnswer::Template { template, vars } => {
+            let template = template.trim();
+            if template.is_empty() {
+                bail!("Template must not be empty");
+            }
+            config_files::set_config_value("template", template)?;
+            let vars = vars.into_iter().map(|(k, v)| (k, toml::Value::String(v))).collect();
+            config_files::set_config_section(TEMPLATE_VARS_SECTION, toml::Value::Table(vars))
+        }
+        StepAnswer::Tokens { openai_api_key, openai_api_base } => {
+            if let Some(base) = openai_api_base.as_deref().map(str::trim).filter(|b| !b.is_empty()) {
+                if !base.starts_with("http://") && !base.starts_with("https://") {
+                    bail!("openai_api_base must be an http(s) URL");
+                }
+                config_files::set_config_value("openai_api_base", base)?;
+            }
+            if let Some(key) = openai_api_key.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+                config_files::set_config_value("openai_api_key", key)?;
+            }
+            Ok(())
+        }
+        StepAnswer::PackageManager(manager) => {
+            if package_manager::PackageManager::parse(&manager).is_none() {
+                let names: Vec<&str> = package_manager::ALL.iter().map(|pm| pm.name()).collect();
+                bail!("Unsupported package manager '{}', expected one of: {}", manager, names.join(", "));
+            }
+            config_files::set_config_value("package_manager", &manager)
+        }
+        StepAnswer::Mcp(enabled) => config_files::set_config_value("mcp_enabled", if enabled { "true" } else { "false" }),
+        StepAnswer::Auth(token) => {
+            let token = match token {
+                Some(token) if token.trim().len() < MIN_TOKEN_LENGTH => {
+                    bail!("Token must be at least {} characters", MIN_TOKEN_LENGTH)
+                }
+                Some(token) => token.trim().to_string(),
+                None => uuid::Uuid::new_v4().simple().to_string(),
+            };
+            config_files::set_config_value("token", &token)
+        }
+    }
+}
+
+fn set_phase(phase: WizardPhase) {
+    STATE.lock().unwrap_or_else(|e| e.into_inner()).phase = phase;
+}
+
+/// Starts setting the environment up from the answers in the background. Poll [`status`] for
+/// the outcome; a failed run can be corrected and applied again.
+pub fn apply() -> Result<WizardStatus> {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        bail!("Setup can only be applied while Galatea runs with --setup-wizard; saved answers take effect on the next start");
+    }
+    {
+        let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+        match &state.phase {
+            WizardPhase::Applying => bail!("Setup is already being applied"),
+            WizardPhase::Done => bail!("Setup has already been completed"),
+            WizardPhase::Collecting | WizardPhase::Failed(_) => {}
+        }
+        let missing: Vec<&str> = WizardStep::ALL
+            .into_iter()
+            .filter(|s| !state.completed.contains(s))
+            .map(|s| s.as_str())
+            .collect();
+        if !missing.is_empty() {
+            bail!("Answer every step before applying; missing: {}", missing.join(", "));
+        }
+        state.phase = WizardPhase::Applying;
+    }
+    tokio::spawn(async {
+        match run_setup().await {
+            Ok(project_dir) => {
+                {
+                    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+                    state.phase = WizardPhase::Done;
+                    state.project_dir = Some(project_dir.clone());
+                }
+                tracing::info!(target: "dev_setup::wizard", path = %project_dir.display(), "Setup wizard completed.");
+                FINISHED.send_replace(Some(project_dir));
+            }
+            Err(e) => {
+                tracing::error!(target: "dev_setup::wizard", error = ?e, "Setup wizard failed to set the environment up.");
+                set_phase(WizardPhase::Failed(format!("{:#}", e)));
+            }
+        }
+    });
+    Ok(status())
+}
+
+async fn run_setup() -> Result<PathBuf> {
+    let answers = WizardAnswers::load();
+    let vars: HashMap<String, String> = answers.template_vars.into_iter().collect();
+    // Like a first start without galatea_files: a project left from an earlier install is replaced
+    let force_rescaffold = FRESH_INSTALL.load(Ordering::Relaxed)
+        && !paths::is_project_dir_overridden()
+        && paths::project_dir()?.exists();
+    let project_dir = super::ensure_development_environment(
+        answers.template,
+        &vars,
+        USE_SUDO.load(Ordering::Relaxed),
+        force_rescaffold,
+    )
+    .await?;
+    // Written after setup, since a fresh scaffold replaces the project directory
+    env::ensure_env_file(&project_dir, answers.openai_api_key.as_deref()).await?;
+    config_files::set_config_value(COMPLETED_CONFIG_KEY, "true")?;
+    Ok(project_dir)
+}
+
+/// Waits until the wizard has set the environment up, returning the project directory.
+pub async fn wait_until_finished() -> Result<PathBuf> {
+    let mut finished = FINISHED.subscribe();
+    let project_dir = finished
+        .wait_for(Option::is_some)
+        .await
+        .context("Setup wizard stopped before finishing")?
+        .clone();
+    project_dir.context("Setup wizard finished without a project directory")
+}
+
+/// Masks a secret for display, keeping only its last four characters.
+pub fn mask_secret(secret: &str) -> String {
+    let visible: String = secret.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+    if secret.chars().count() <= 8 {
+        "*".repeat(secret.chars().count())
+    } else {
+        format!("****{}", visible)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_and_masking() {
+        assert_eq!(WizardStep::parse("package_manager"), Some(WizardStep::PackageManager));
+        assert_eq!(WizardStep::parse("unknown"), None);
+        assert_eq!(StepAnswer::Mcp(true).step(), WizardStep::Mcp);
+
+        assert_eq!(mask_secret("sk-abcdefghijklmnop"), "****mnop");
+        assert_eq!(mask_secret("short"), "*****");
+    }
+}
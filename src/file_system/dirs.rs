@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Directories listed but not descended into unless they are the listed directory itself.
+pub const SKIPPED_DIRS: &[&str] = &["node_modules", ".git", ".next", "dist", "build", "out", "coverage", "target", ".turbo"];
+
+/// One entry of a directory listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirEntry {
+    /// Path relative to the project root, with `/` separators
+    pub path: String,
+    pub is_dir: bool,
+    /// 1 for the listed directory's own entries
+    pub depth: usize,
+    /// File size in bytes; `None` for directories
+    pub size_bytes: Option<u64>,
+    /// Number of entries in a directory, `None` for files
+    pub child_count: Option<usize>,
+    /// A directory whose contents weren't listed: in `SKIPPED_DIRS`, or at the depth limit
+    pub collapsed: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirListing {
+    pub entries: Vec<DirEntry>,
+    /// Whether `max_entries` cut the listing short
+    pub truncated: bool,
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+/// Lists `dir` depth first, directories before files and each group by name, down to
+/// `max_depth` levels. Hidden entries are left out unless `include_hidden` is set.
+pub fn list_dir(project_root: &Path, dir: &Path, max_depth: usize, include_hidden: bool, max_entries: usize) -> Result<DirListing> {
+    let mut listing = DirListing::default();
+    walk(project_root, dir, 1, max_depth.max(1), include_hidden, max_entries, &mut listing)
+        .with_context(|| format!("Failed to list directory: {}", dir.display()))?;
+    Ok(listing)
+}
+
+fn sorted_children(dir: &Path, include_hidden: bool) -> Result<Vec<(String, fs::Metadata)>> {
+    let mut children = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !include_hidden && is_hidden(&name) {
+            continue;
+        }
+        // Follows symlinks, so a linked directory lists like a directory
+        let Ok(metadata) = fs::metadata(entry.path()) else { continue };
+        children.push((name, metadata));
+    }
+    children.sort_by(|(a, a_meta), (b, b_meta)| b_meta.is_dir().cmp(&a_meta.is_dir()).then_with(|| a.cmp(b)));
+    Ok(children)
+}
+
+fn walk(
+    project_root: &Path,
+    dir: &Path,
+    depth: usize,
+    max_depth: usize,
+    include_hidden: bool,
+    max_entries: usize,
+    listing: &mut DirListing,
+) -> Result<()> {
+    for (name, metadata) in sorted_children(dir, include_hidden)? {
+        if listing.entries.len() >= max_entries {
+            listing.truncated = true;
+            return Ok(());
+        }
+        let path = dir.join(&name);
+        let relative = path.strip_prefix(project_root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        if !metadata.is_dir() {
+            listing.entries.push(DirEntry {
+                path: relative,
+                is_dir: false,
+                depth,
+                size_bytes: Some(metadata.len()),
+                child_count: None,
+                collapsed: false,
+            });
+            continue;
+        }
+        let descend = depth < max_depth && !SKIPPED_DIRS.contains(&name.as_str());
+        let child_count = fs::read_dir(&path)
+            .map(|entries| entries.filter_map(|e| e.ok()).filter(|e| include_hidden || !is_hidden(&e.file_name().to_string_lossy())).count())
+            .unwrap_or(0);
+        listing.entries.push(DirEntry {
+            path: relative,
+            is_dir: true,
+            depth,
+            size_bytes: None,
+            child_count: Some(child_count),
+            collapsed: !descend && child_count > 0,
+        });
+        if descend {
+            walk(project_root, &path, depth + 1, max_depth, include_hidden, max_entries, listing)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_dir_orders_and_limits_depth() {
+        let root = tempfile::tempdir().unwrap();
+        for (path, content) in [("src/app/page.tsx", "x"), ("src/index.ts", "xy"), ("src/.env", ""), ("node_modules/react/index.js", ""), ("README.md", "")] {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let listing = list_dir(root.path(), root.path(), 2, false, 100).unwrap();
+        let paths: Vec<(&str, usize, bool)> = listing.entries.iter().map(|e| (e.path.as_str(), e.depth, e.collapsed)).collect();
+        assert_eq!(
+            paths,
+            vec![("node_modules", 1, true), ("src", 1, false), ("src/app", 2, true), ("src/index.ts", 2, false), ("README.md", 1, false)]
+        );
+        assert_eq!(listing.entries[1].child_count, Some(2));
+        assert_eq!(listing.entries[3].size_bytes, Some(2));
+
+        let listing = list_dir(root.path(), &root.path().join("src"), 1, true, 2).unwrap();
+        assert_eq!(listing.entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), vec!["src/app", "src/.env"]);
+        assert!(listing.truncated);
+    }
+}
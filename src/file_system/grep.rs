@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::Path;
+
+use super::dirs::SKIPPED_DIRS;
+
+// Files larger than this are skipped, like minified bundles and lockfiles usually are
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+// A NUL byte this early marks a file as binary
+const BINARY_PROBE_BYTES: usize = 8000;
+// Longer lines are cut to a window around the match
+const MAX_SNIPPET_CHARS: usize = 300;
+
+/// What to search for and where, like a `rg` invocation.
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    pub pattern: String,
+    /// Treat `pattern` as a regular expression instead of a literal string
+    pub regex: bool,
+    pub case_insensitive: bool,
+    /// Globs a file must match, e.g. `*.tsx` or `src/**`; empty means every file
+    pub include: Vec<String>,
+    /// Globs of files and directories to leave out
+    pub exclude: Vec<String>,
+    /// Lines of context before and after each match
+    pub context_lines: usize,
+    pub max_matches: usize,
+    /// Search hidden files and directories too
+    pub include_hidden: bool,
+}
+
+/// One matching line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrepMatch {
+    /// Path relative to the project root, with `/` separators
+    pub path: String,
+    /// 1-indexed line
+    pub line: usize,
+    /// 1-indexed column of the first match on the line, in characters
+    pub column: usize,
+    /// The matching line, cut around the match when it is long
+    pub snippet: String,
+    /// Text of the first match on the line
+    pub matched: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GrepResult {
+    pub matches: Vec<GrepMatch>,
+    pub files_searched: usize,
+    /// Whether `max_matches` cut the search short
+    pub truncated: bool,
+}
+
+fn build_regex(options: &GrepOptions) -> Result<Regex> {
+    let pattern = if options.regex { options.pattern.clone() } else { regex::escape(&options.pattern) };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(options.case_insensitive)
+        .build()
+        .with_context(|| format!("Invalid search pattern: {}", options.pattern))
+}
+
+// Cuts a long line to a window starting a little before the match
+fn snippet(line: &str, match_start: usize) -> String {
+    if line.chars().count() <= MAX_SNIPPET_CHARS {
+        return line.to_string();
+    }
+    let start_char = line[..match_start].chars().count().saturating_sub(MAX_SNIPPET_CHARS / 4);
+    line.chars().skip(start_char).take(MAX_SNIPPET_CHARS).collect()
+}
+
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    if bytes[..bytes.len().min(BINARY_PROBE_BYTES)].contains(&0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Searches the text files under `dir` for `options.pattern`, in path order. Files ignored
+/// by `.gitignore`, dependency and build directories, binary files and files over 2 MB are
+/// skipped.
+pub fn search(project_root: &Path, dir: &Path, options: &GrepOptions) -> Result<GrepResult> {
+    let regex = build_regex(options)?;
+    let mut overrides = OverrideBuilder::new(dir);
+    for glob in &options.include {
+        overrides.add(glob).with_context(|| format!("Invalid include glob: {}", glob))?;
+    }
+    for glob in &options.exclude {
+        overrides.add(&format!("!{}", glob)).with_context(|| format!("Invalid exclude glob: {}", glob))?;
+    }
+    let overrides = overrides.build().context("Invalid search globs")?;
+
+    let walker = WalkBuilder::new(dir)
+        .hidden(!options.include_hidden)
+        .require_git(false)
+        .overrides(overrides)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_some_and(|t| t.is_dir())
+                || !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+        })
+        .build();
+
+    let mut result = GrepResult::default();
+    for entry in walker {
+        let Ok(entry) = entry else { continue };
+        if !entry.file_type().is_some_and(|t| t.is_file()) || entry.metadata().map_or(true, |m| m.len() > MAX_FILE_BYTES) {
+            continue;
+        }
+        let Some(text) = read_text(entry.path()) else { continue };
+        result.files_searched += 1;
+        let relative = entry.path().strip_prefix(project_root).unwrap_or(entry.path()).to_string_lossy().replace('\\', "/");
+        let lines: Vec<&str> = text.lines().collect();
+        for (index, line) in lines.iter().enumerate() {
+            let Some(found) = regex.find(line) else { continue };
+            if result.matches.len() >= options.max_matches {
+                result.truncated = true;
+                return Ok(result);
+            }
+            let context_start = index.saturating_sub(options.context_lines);
+            let context_end = (index + 1 + options.context_lines).min(lines.len());
+            result.matches.push(GrepMatch {
+                path: relative.clone(),
+                line: index + 1,
+                column: line[..found.start()].chars().count() + 1,
+                snippet: snippet(line, found.start()),
+                matched: found.as_str().to_string(),
+                before: lines[context_start..index].iter().map(|l| l.to_string()).collect(),
+                after: lines[index + 1..context_end].iter().map(|l| l.to_string()).collect(),
+            });
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_literal_regex_globs_and_context() {
+        let root = crate::test_support::project_tempdir();
+        for (path, content) in [
+            ("src/app/page.tsx", "import { Button } from './button';\nexport default function Page() {\n  return <Button />;\n}\n"),
+            ("src/app/button.ts", "export const Button = () => null; // TODO(a.b)\n"),
+            ("src/util.js", "const todo = 'TODO(a.b)';\n"),
+            ("node_modules/lib/index.js", "TODO(a.b)\n"),
+            ("dist/out.js", "TODO(a.b)\n"),
+            (".gitignore", "dist\n"),
+        ] {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let options = GrepOptions {
+            pattern: "TODO(a.b)".to_string(),
+            regex: false,
+            case_insensitive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            context_lines: 0,
+            max_matches: 100,
+            include_hidden: false,
+        };
+        let found = |options: &GrepOptions| -> Vec<(String, usize, usize)> {
+            search(root.path(), root.path(), options).unwrap().matches.into_iter().map(|m| (m.path, m.line, m.column)).collect()
+        };
+
+        // Literal, so `(` and `.` aren't special; ignored and skipped directories aren't searched
+        assert_eq!(found(&options), vec![("src/app/button.ts".to_string(), 1, 38), ("src/util.js".to_string(), 1, 15)]);
+        let only_ts = GrepOptions { include: vec!["*.ts".to_string()], ..options.clone() };
+        assert_eq!(found(&only_ts).len(), 1);
+        let no_app = GrepOptions { exclude: vec!["src/app".to_string()], ..options.clone() };
+        assert_eq!(found(&no_app), vec![("src/util.js".to_string(), 1, 15)]);
+
+        let regex = GrepOptions { pattern: r"<button\s*/>".to_string(), regex: true, case_insensitive: true, context_lines: 1, ..options.clone() };
+        let result = search(root.path(), root.path(), &regex).unwrap();
+        let m = &result.matches[0];
+        assert_eq!((m.path.as_str(), m.line, m.column, m.matched.as_str()), ("src/app/page.tsx", 3, 10, "<Button />"));
+        assert_eq!((m.before.len(), m.after.as_slice()), (1, &["}".to_string()][..]));
+
+        let capped = GrepOptions { max_matches: 1, ..options.clone() };
+        assert!(search(root.path(), root.path(), &capped).unwrap().truncated);
+        assert!(search(root.path(), root.path(), &GrepOptions { pattern: "(".to_string(), regex: true, ..options }).is_err());
+    }
+}
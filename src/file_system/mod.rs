@@ -1,5 +1,9 @@
+pub mod dirs;
+pub mod grep;
 pub mod search;
 pub mod paths; // Added paths module
+pub mod ranking;
+pub mod watcher;
 // pub mod operations; // For future file read/write utilities
 
 // Re-export common functions for convenience
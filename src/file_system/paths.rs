@@ -1,22 +1,141 @@
 use anyhow::{anyhow, ensure, Context, Result};
 use lsp_types::Uri;
+use once_cell::sync::OnceCell;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 // Changed from crate::file_system to super::search for relative import within the same module
 use super::search;
-
-// Transplanted from watcher.rs
-pub fn get_project_root() -> Result<PathBuf> {
-    let project_dir = std::env::current_exe()
+use crate::dev_runtime::workspaces;
+use crate::dev_setup::config_files;
+
+/// Environment variable overriding the project directory (below `--project-dir`).
+pub const PROJECT_DIR_VAR: &str = "GALATEA_PROJECT_DIR";
+/// Environment variable overriding the galatea_files directory (below `--data-dir`).
+pub const DATA_DIR_VAR: &str = "GALATEA_DATA_DIR";
+/// config.toml key overriding the project directory (below the flag and the environment).
+/// Relative values are resolved against the galatea_files directory.
+pub const PROJECT_DIR_CONFIG_KEY: &str = "project_dir";
+
+// Directories chosen with `--project-dir` and `--data-dir` at startup; take precedence over
+// the environment variables
+static PROJECT_DIR_FLAG: OnceCell<PathBuf> = OnceCell::new();
+static DATA_DIR_FLAG: OnceCell<PathBuf> = OnceCell::new();
+
+/// Directory holding the Galatea executable.
+pub fn install_dir() -> Result<PathBuf> {
+    Ok(std::env::current_exe()
         .context("Failed to get current executable path")?
         .parent()
         .ok_or_else(|| anyhow!("Executable has no parent directory"))?
-        .join("project");
+        .to_path_buf())
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    std::path::absolute(path).with_context(|| format!("Failed to make '{}' absolute", path.display()))
+}
+
+fn env_dir(var: &str) -> Result<Option<PathBuf>> {
+    match std::env::var_os(var) {
+        Some(value) if !value.is_empty() => absolute(Path::new(&value)).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Sets the project directory for the rest of the process (the `--project-dir` flag).
+pub fn set_project_dir(dir: &Path) -> Result<()> {
+    PROJECT_DIR_FLAG
+        .set(absolute(dir)?)
+        .map_err(|_| anyhow!("The project directory is already set"))
+}
+
+/// Sets the galatea_files directory for the rest of the process (the `--data-dir` flag).
+pub fn set_data_dir(dir: &Path) -> Result<()> {
+    DATA_DIR_FLAG
+        .set(absolute(dir)?)
+        .map_err(|_| anyhow!("The data directory is already set"))
+}
+
+/// Directory holding config.toml, the database, logs and the other files Galatea keeps:
+/// `--data-dir`, else `GALATEA_DATA_DIR`, else `galatea_files/` next to the executable.
+pub fn galatea_files_dir() -> Result<PathBuf> {
+    if let Some(dir) = DATA_DIR_FLAG.get() {
+        return Ok(dir.clone());
+    }
+    if let Some(dir) = env_dir(DATA_DIR_VAR)? {
+        return Ok(dir);
+    }
+    Ok(install_dir()?.join("galatea_files"))
+}
+
+/// Where downloaded templates and package tarballs are cached for `--offline`: `galatea_cache/`
+/// next to the executable, or `cache/` inside a data directory chosen with `--data-dir` or
+/// `GALATEA_DATA_DIR`.
+pub fn cache_dir() -> Result<PathBuf> {
+    if DATA_DIR_FLAG.get().is_some() || env_dir(DATA_DIR_VAR)?.is_some() {
+        return Ok(galatea_files_dir()?.join("cache"));
+    }
+    Ok(install_dir()?.join("galatea_cache"))
+}
+
+/// Checks the id of a record stored as `<id>.json` under galatea_files. Ids are generated by
+/// Galatea but arrive in request paths too, so anything other than 1 to 64 ASCII letters,
+/// digits and `-` is rejected before it can name a file outside the record's directory.
+pub fn validate_id(id: &str, what: &str) -> Result<()> {
+    ensure!(
+        !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+        "Invalid {} id '{}'",
+        what,
+        id
+    );
+    Ok(())
+}
+
+fn project_dir_override() -> Result<Option<PathBuf>> {
+    if let Some(dir) = PROJECT_DIR_FLAG.get() {
+        return Ok(Some(dir.clone()));
+    }
+    if let Some(dir) = env_dir(PROJECT_DIR_VAR)? {
+        return Ok(Some(dir));
+    }
+    match config_files::get_config_value(PROJECT_DIR_CONFIG_KEY) {
+        Some(value) if !value.trim().is_empty() => Ok(Some(galatea_files_dir()?.join(value.trim()))),
+        _ => Ok(None),
+    }
+}
+
+/// Whether the project directory was chosen with `--project-dir`, `GALATEA_PROJECT_DIR` or
+/// `project_dir` in config.toml rather than defaulting to `project/` next to the executable.
+pub fn is_project_dir_overridden() -> bool {
+    project_dir_override().is_ok_and(|dir| dir.is_some())
+}
+
+/// Directory of the default project, whether or not it exists yet: `--project-dir`, else
+/// `GALATEA_PROJECT_DIR`, else `project_dir` in config.toml, else `project/` next to the
+/// executable.
+pub fn project_dir() -> Result<PathBuf> {
+    match project_dir_override()? {
+        Some(dir) => Ok(dir),
+        None => Ok(install_dir()?.join("project")),
+    }
+}
+
+/// Root of the project the current request works in: its workspace's root when it is scoped to
+/// one (see `dev_runtime::workspaces`), the default project otherwise.
+pub fn get_project_root() -> Result<PathBuf> {
+    if let Some(root) = workspaces::scoped_root() {
+        return Ok(root);
+    }
+    default_project_root()
+}
+
+/// The default project's directory (see `project_dir`), which must exist.
+pub fn default_project_root() -> Result<PathBuf> {
+    let project_dir = project_dir()?;
 
     ensure!(
         project_dir.is_dir(),
-        "'project' subdirectory not found in {}.",
+        "Project directory {} not found.",
         project_dir.display()
     );
 
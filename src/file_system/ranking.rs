@@ -0,0 +1,319 @@
+use serde::Deserialize;
+use std::path::{Component, Path};
+use std::time::SystemTime;
+
+use crate::dev_setup::config_files;
+
+// config.toml table holding the tunable weights, e.g. `[search_ranking]`
+const CONFIG_SECTION: &str = "search_ranking";
+
+// Directory names whose contents are build output rather than source
+const GENERATED_DIRS: &[&str] = &[".next", "dist", "build", "out", "generated", "__generated__", "coverage", "node_modules", "target"];
+// Directory names holding tests
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "__mocks__", "e2e", "spec"];
+// Directory names holding application source
+const SOURCE_DIRS: &[&str] = &["src", "app", "pages", "components", "lib"];
+
+/// Weights of the ranking signals, from `[search_ranking]` in config.toml.
+///
+/// ```toml
+/// [search_ranking]
+/// relevance = 1.0
+/// path_priority = 0.5
+/// recency = 0.2
+/// definition = 0.3
+/// recency_half_life_days = 14
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RankingWeights {
+    /// How well the result matches the query (name match or embedding similarity)
+    pub relevance: f64,
+    /// Source files over tests over generated output
+    pub path_priority: f64,
+    /// Recently modified files first
+    pub recency: f64,
+    /// Definitions over usages
+    pub definition: f64,
+    /// Age at which the recency signal drops to half
+    pub recency_half_life_days: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            relevance: 1.0,
+            path_priority: 0.5,
+            recency: 0.2,
+            definition: 0.3,
+            recency_half_life_days: 14.0,
+        }
+    }
+}
+
+impl RankingWeights {
+    /// Loads the weights from config.toml; missing values keep their defaults.
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+}
+
+/// Where a file sits in the project, for the path priority signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathClass {
+    Source,
+    Other,
+    Test,
+    Generated,
+}
+
+impl PathClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PathClass::Source => "source",
+            PathClass::Other => "other",
+            PathClass::Test => "test",
+            PathClass::Generated => "generated",
+        }
+    }
+
+    fn priority(&self) -> f64 {
+        match self {
+            PathClass::Source => 1.0,
+            PathClass::Other => 0.6,
+            PathClass::Test => 0.3,
+            PathClass::Generated => 0.0,
+        }
+    }
+}
+
+/// Classifies a (preferably project-relative) path as source, test, generated or other.
+pub fn classify_path(path: &str) -> PathClass {
+    let dirs: Vec<String> = Path::new(path)
+        .parent()
+        .map(|p| {
+            p.components()
+                .filter_map(|c| match c {
+                    Component::Normal(s) => Some(s.to_string_lossy().to_lowercase()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|f| f.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if dirs.iter().any(|d| GENERATED_DIRS.contains(&d.as_str())) || file_name.ends_with(".d.ts") {
+        return PathClass::Generated;
+    }
+    if dirs.iter().any(|d| TEST_DIRS.contains(&d.as_str()))
+        || [".test.", ".spec.", "_test."].iter().any(|m| file_name.contains(m))
+    {
+        return PathClass::Test;
+    }
+    if dirs.iter().any(|d| SOURCE_DIRS.contains(&d.as_str())) {
+        return PathClass::Source;
+    }
+    PathClass::Other
+}
+
+/// Scores how well a symbol name matches a query: exact, prefix, substring, or fuzzy.
+pub fn name_match_quality(name: &str, query: &str) -> f64 {
+    let name = name.to_lowercase();
+    let query = query.to_lowercase();
+    if query.is_empty() {
+        0.5
+    } else if name == query {
+        1.0
+    } else if name.starts_with(&query) {
+        0.8
+    } else if name.contains(&query) {
+        0.6
+    } else {
+        0.3
+    }
+}
+
+/// Whether a line of TS/JS or Rust source declares `symbol` rather than just using it.
+pub fn is_definition_line(line: &str, symbol: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "function", "class", "interface", "type", "enum", "const", "let", "var", "fn", "struct", "trait", "mod",
+        "impl",
+    ];
+    let tokens: Vec<&str> = line
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|t| !t.is_empty())
+        .collect();
+    tokens
+        .windows(2)
+        .any(|pair| KEYWORDS.contains(&pair[0]) && pair[1] == symbol)
+}
+
+/// Inputs for scoring a single search result.
+#[derive(Debug, Clone)]
+pub struct RankSignals<'a> {
+    /// Path of the file the result is in, relative to the project root where possible
+    pub path: &'a str,
+    /// Last modification time of that file
+    pub modified: Option<SystemTime>,
+    /// Whether the result is a definition (as opposed to a usage)
+    pub is_definition: bool,
+    /// Query match quality in `0.0..=1.0`
+    pub relevance: f64,
+}
+
+/// Contribution of one signal to a result's score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreComponent {
+    /// Signal name: `relevance`, `path_priority`, `recency` or `definition`
+    pub signal: &'static str,
+    /// Signal value in `0.0..=1.0`
+    pub value: f64,
+    /// Configured weight of the signal
+    pub weight: f64,
+    /// Human-readable reason for the value
+    pub reason: String,
+}
+
+impl ScoreComponent {
+    pub fn contribution(&self) -> f64 {
+        self.value * self.weight
+    }
+}
+
+/// A search result with its score and the breakdown that produced it.
+#[derive(Debug, Clone)]
+pub struct Ranked<T> {
+    pub item: T,
+    pub score: f64,
+    pub explanation: Vec<ScoreComponent>,
+}
+
+/// Scores a result; returns the weighted sum and the per-signal breakdown.
+pub fn score(signals: &RankSignals, weights: &RankingWeights, now: SystemTime) -> (f64, Vec<ScoreComponent>) {
+    let class = classify_path(signals.path);
+    let age_days = signals
+        .modified
+        .and_then(|m| now.duration_since(m).ok())
+        .map(|d| d.as_secs_f64() / 86_400.0);
+    let recency = match age_days {
+        Some(days) if weights.recency_half_life_days > 0.0 => 0.5f64.powf(days / weights.recency_half_life_days),
+        _ => 0.0,
+    };
+
+    let components = vec![
+        ScoreComponent {
+            signal: "relevance",
+            value: signals.relevance.clamp(0.0, 1.0),
+            weight: weights.relevance,
+            reason: format!("query match {:.2}", signals.relevance),
+        },
+        ScoreComponent {
+            signal: "path_priority",
+            value: class.priority(),
+            weight: weights.path_priority,
+            reason: format!("{} file", class.as_str()),
+        },
+        ScoreComponent {
+            signal: "recency",
+            value: recency,
+            weight: weights.recency,
+            reason: match age_days {
+                Some(days) => format!("modified {:.1} days ago", days),
+                None => "modification time unknown".to_string(),
+            },
+        },
+        ScoreComponent {
+            signal: "definition",
+            value: if signals.is_definition { 1.0 } else { 0.0 },
+            weight: weights.definition,
+            reason: if signals.is_definition { "definition" } else { "usage" }.to_string(),
+        },
+    ];
+    let total = components.iter().map(ScoreComponent::contribution).sum();
+    (total, components)
+}
+
+/// Scores every item and sorts them best first. Ties keep their original order.
+pub fn rank<T>(
+    items: Vec<T>,
+    weights: &RankingWeights,
+    signals: impl Fn(&T) -> RankSignals,
+) -> Vec<Ranked<T>> {
+    let now = SystemTime::now();
+    let mut ranked: Vec<Ranked<T>> = items
+        .into_iter()
+        .map(|item| {
+            let (score, explanation) = score(&signals(&item), weights, now);
+            Ranked { item, score, explanation }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranked
+}
+
+/// Modification time of `path`, resolved against `project_root` when relative.
+pub fn modified_time(project_root: &Path, path: &str) -> Option<SystemTime> {
+    let path = Path::new(path);
+    let full = if path.is_absolute() { path.to_path_buf() } else { project_root.join(path) };
+    std::fs::metadata(full).and_then(|m| m.modified()).ok()
+}
+
+/// Formats each component of a score breakdown, e.g. `path_priority 1.00×0.50 = 0.500 (source file)`.
+pub fn explain(components: &[ScoreComponent]) -> Vec<String> {
+    components
+        .iter()
+        .map(|c| format!("{} {:.2}×{:.2} = {:.3} ({})", c.signal, c.value, c.weight, c.contribution(), c.reason))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_classify_path_and_definition_lines() {
+        assert_eq!(classify_path("src/components/Button.tsx"), PathClass::Source);
+        assert_eq!(classify_path("src/components/Button.test.tsx"), PathClass::Test);
+        assert_eq!(classify_path("tests/button.rs"), PathClass::Test);
+        assert_eq!(classify_path(".next/server/app/page.js"), PathClass::Generated);
+        assert_eq!(classify_path("types/global.d.ts"), PathClass::Generated);
+        assert_eq!(classify_path("next.config.js"), PathClass::Other);
+
+        assert!(is_definition_line("export function Button(props) {", "Button"));
+        assert!(is_definition_line("pub struct Button;", "Button"));
+        assert!(!is_definition_line("return <Button label=\"x\" />;", "Button"));
+        assert!(!is_definition_line("const label = Button.name;", "Button"));
+    }
+
+    #[test]
+    fn test_rank_prefers_source_definitions_and_explains_scores() {
+        let weights: RankingWeights = "path_priority = 1.0\nrecency = 0.0".parse::<toml::Value>().unwrap().try_into().unwrap();
+        assert_eq!(weights.relevance, 1.0);
+        assert_eq!(weights.path_priority, 1.0);
+
+        let now = SystemTime::now();
+        let items = vec![
+            ("dist/button.js", true),
+            ("tests/button.test.ts", true),
+            ("src/usage.ts", false),
+            ("src/button.ts", true),
+        ];
+        let ranked = rank(items, &weights, |(path, is_definition)| RankSignals {
+            path,
+            modified: Some(now - Duration::from_secs(3_600)),
+            is_definition: *is_definition,
+            relevance: 1.0,
+        });
+        let order: Vec<&str> = ranked.iter().map(|r| r.item.0).collect();
+        assert_eq!(order, vec!["src/button.ts", "src/usage.ts", "tests/button.test.ts", "dist/button.js"]);
+
+        let best = &ranked[0];
+        assert_eq!(best.explanation.len(), 4);
+        assert!((best.score - 2.3).abs() < 1e-9);
+        assert!(explain(&best.explanation)[1].contains("source file"));
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Context, Result};
 use dunce;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -21,15 +22,123 @@ pub fn find_files_by_extensions(
     exclude_dirs: &[&str],
 ) -> Result<Vec<PathBuf>> {
     let mut matching_files = Vec::new();
-    find_files_recursive(start_path, extensions, exclude_dirs, &mut matching_files).context(
+    let matches_extension = |path: &Path| {
+        path.extension()
+            .and_then(|ext| ext.to_str()) // Get Option<&str> for the extension
+            .is_some_and(|ext_str| extensions.contains(&ext_str)) // Check if non-empty extension is in extensions
+    };
+    find_files_recursive(start_path, &matches_extension, exclude_dirs, &mut matching_files).context(
         anyhow!("Failed to scan directory: {}", start_path.display()),
     )?;
     Ok(matching_files)
 }
 
+/// Which files `find_files` returns. Each filter that is set must match; within a filter,
+/// any one entry is enough.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Extensions without the leading dot, e.g. `ts`; `*` matches any file
+    pub extensions: Vec<String>,
+    /// Globs such as `src/**/*.test.ts`, matched against the path relative to the search
+    /// directory. Globs without a `/` match the file name, so `*.test.ts` matches at any depth.
+    pub globs: Vec<String>,
+    /// Substrings of the file name, matched case-insensitively
+    pub name_contains: Vec<String>,
+}
+
+impl FileFilter {
+    /// Checks that the filter asks for something and its globs parse.
+    pub fn validate(&self) -> Result<()> {
+        if self.extensions.is_empty() && self.globs.is_empty() && self.name_contains.is_empty() {
+            return Err(anyhow!("Specify at least one extension, glob or name filter"));
+        }
+        build_glob_set(&self.globs).map(|_| ())
+    }
+}
+
+// `*` and `?` stop at `/`, `**` crosses directories, and `{a,b}` alternates, as in most tools
+fn build_glob_set(globs: &[String]) -> Result<(GlobSet, GlobSet)> {
+    let (mut path_globs, mut name_globs) = (GlobSetBuilder::new(), GlobSetBuilder::new());
+    for pattern in globs {
+        let trimmed = pattern.trim().trim_start_matches("./");
+        let glob = GlobBuilder::new(trimmed)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid glob: {}", pattern))?;
+        if trimmed.contains('/') {
+            path_globs.add(glob);
+        } else {
+            name_globs.add(glob);
+        }
+    }
+    Ok((path_globs.build()?, name_globs.build()?))
+}
+
+/// Recursively finds the files under `start_path` matching `filter`, skipping hidden
+/// directories and those named in `exclude_dirs`. Fails for filters `FileFilter::validate`
+/// rejects, so an empty filter never lists every file.
+pub fn find_files(start_path: &Path, filter: &FileFilter, exclude_dirs: &[&str]) -> Result<Vec<PathBuf>> {
+    filter.validate()?;
+    let (path_globs, name_globs) = build_glob_set(&filter.globs)?;
+    let name_contains: Vec<String> = filter.name_contains.iter().map(|s| s.to_lowercase()).collect();
+    let matches = |path: &Path| {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let extension_ok = filter.extensions.is_empty()
+            || filter.extensions.iter().any(|ext| {
+                ext == "*" || path.extension().is_some_and(|e| e.to_string_lossy() == ext.trim_start_matches('.'))
+            });
+        let glob_ok = filter.globs.is_empty() || name_globs.is_match(name.as_ref()) || {
+            let relative = path.strip_prefix(start_path).unwrap_or(path).to_string_lossy().replace('\\', "/");
+            path_globs.is_match(relative)
+        };
+        let name_ok = name_contains.is_empty() || {
+            let name = name.to_lowercase();
+            name_contains.iter().any(|needle| name.contains(needle.as_str()))
+        };
+        extension_ok && glob_ok && name_ok
+    };
+    let mut matching_files = Vec::new();
+    find_files_recursive(start_path, &matches, exclude_dirs, &mut matching_files)
+        .context(anyhow!("Failed to scan directory: {}", start_path.display()))?;
+    Ok(matching_files)
+}
+
+/// Order of `find_files` results for `sort_files`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileSort {
+    #[default]
+    Path,
+    Size,
+    Modified,
+}
+
+/// Sorts files in place. Ties (and files whose metadata can't be read) fall back to path
+/// order, so the order is stable between calls and pages can be taken from it.
+pub fn sort_files(files: &mut [PathBuf], sort: FileSort, descending: bool) {
+    let key = |path: &Path| -> u128 {
+        let metadata = fs::metadata(path).ok();
+        match sort {
+            FileSort::Path => 0,
+            FileSort::Size => metadata.map_or(0, |m| m.len() as u128),
+            FileSort::Modified => metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos()),
+        }
+    };
+    let mut keyed: Vec<(u128, PathBuf)> = files.iter().map(|p| (key(p), p.clone())).collect();
+    keyed.sort_by(|(a_key, a), (b_key, b)| {
+        let order = a_key.cmp(b_key).then_with(|| a.cmp(b));
+        if descending { order.reverse() } else { order }
+    });
+    for (slot, (_, path)) in files.iter_mut().zip(keyed) {
+        *slot = path;
+    }
+}
+
 fn find_files_recursive(
     current_path: &Path,
-    extensions: &[&str],
+    matches: &dyn Fn(&Path) -> bool,
     exclude_dirs: &[&str],
     matching_files: &mut Vec<PathBuf>,
 ) -> Result<()> {
@@ -51,21 +160,14 @@ fn find_files_recursive(
         // If the entry is a directory, recurse into it.
         // Then, `continue` to the next entry in the current directory.
         if path.is_dir() {
-            find_files_recursive(&path, extensions, exclude_dirs, matching_files)?;
+            find_files_recursive(&path, matches, exclude_dirs, matching_files)?;
             continue;
         }
 
         // If the entry is a file (and not a directory, due to `continue` above),
-        // check if its extension matches the desired extensions.
-        if path.is_file() {
-            let matches_suffix = path
-                .extension()
-                .and_then(|ext| ext.to_str()) // Get Option<&str> for the extension
-                .map_or(false, |ext_str| extensions.contains(&ext_str)); // Check if non-empty extension is in extensions
-
-            if matches_suffix {
-                matching_files.push(path);
-            }
+        // check if it is one of the files asked for.
+        if path.is_file() && matches(&path) {
+            matching_files.push(path);
         }
         // Other types of file system entries (e.g., symlinks not pointing to dirs/files) are ignored.
     }
@@ -178,7 +280,7 @@ mod tests {
 
     #[test]
     fn test_find_files_with_exclusion() -> Result<()> {
-        let dir = tempdir()?;
+        let dir = crate::test_support::project_tempdir();
         let root = dir.path();
         let sub1 = root.join("subdir1");
         let sub2 = root.join("node_modules");
@@ -234,7 +336,7 @@ mod tests {
 
     #[test]
     fn test_find_files() -> Result<()> {
-        let dir = tempdir()?;
+        let dir = crate::test_support::project_tempdir();
         let root = dir.path();
         let sub = root.join("subdir");
         fs::create_dir(&sub)?;
@@ -276,7 +378,7 @@ mod tests {
 
     #[test]
     fn test_find_file_by_suffix() -> Result<()> {
-        let dir = tempdir()?;
+        let dir = crate::test_support::project_tempdir();
         let project_root = dir.path();
 
         // Setup a file structure
@@ -406,4 +508,62 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_find_files_with_globs_and_name_filters() -> Result<()> {
+        let dir = crate::test_support::project_tempdir();
+        let root = dir.path();
+        for path in ["src/lib/auth.ts", "src/lib/auth.test.ts", "src/app/LoginForm.tsx", "src/app/login.test.tsx", "e2e/login.test.ts", "node_modules/x/a.test.ts"] {
+            fs::create_dir_all(root.join(path).parent().unwrap())?;
+            File::create(root.join(path))?;
+        }
+        let found = |filter: FileFilter| -> Vec<String> {
+            let mut files: Vec<String> = find_files(root, &filter, &["node_modules"])
+                .unwrap()
+                .iter()
+                .map(|p| p.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            files.sort();
+            files
+        };
+
+        let globs = |globs: &[&str]| FileFilter { globs: globs.iter().map(|g| g.to_string()).collect(), ..FileFilter::default() };
+        assert_eq!(found(globs(&["src/**/*.test.ts"])), vec!["src/lib/auth.test.ts"]);
+        assert_eq!(found(globs(&["*.test.{ts,tsx}"])), vec!["e2e/login.test.ts", "src/app/login.test.tsx", "src/lib/auth.test.ts"]);
+        assert_eq!(found(globs(&["src/*.ts"])), Vec::<String>::new());
+
+        // Filters combine: a `.tsx` file with "login" in its name, in any case
+        let filter = FileFilter { extensions: vec!["tsx".to_string()], name_contains: vec!["LOGIN".to_string()], ..FileFilter::default() };
+        assert_eq!(found(filter), vec!["src/app/LoginForm.tsx", "src/app/login.test.tsx"]);
+
+        assert!(find_files(root, &FileFilter::default(), &[]).is_err());
+        assert!(find_files(root, &globs(&["src/[a"]), &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_files() -> Result<()> {
+        let dir = tempdir()?;
+        let files: Vec<PathBuf> = [("b.ts", "12345"), ("a.ts", "1"), ("c.ts", "12345")]
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.path().join(name);
+                fs::write(&path, content).unwrap();
+                path
+            })
+            .collect();
+        let names = |files: &[PathBuf]| -> Vec<String> {
+            files.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect()
+        };
+
+        let mut sorted = files.clone();
+        sort_files(&mut sorted, FileSort::Path, false);
+        assert_eq!(names(&sorted), vec!["a.ts", "b.ts", "c.ts"]);
+        // Equal sizes keep path order
+        sort_files(&mut sorted, FileSort::Size, false);
+        assert_eq!(names(&sorted), vec!["a.ts", "b.ts", "c.ts"]);
+        sort_files(&mut sorted, FileSort::Size, true);
+        assert_eq!(names(&sorted), vec!["c.ts", "b.ts", "a.ts"]);
+        Ok(())
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,353 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::Instant;
+
+use super::dirs::SKIPPED_DIRS;
+use crate::dev_runtime::util::now_secs;
+use crate::dev_setup::config_files;
+
+// config.toml table holding the watcher settings, e.g. `[fs_watcher]`
+const CONFIG_SECTION: &str = "fs_watcher";
+const MAX_STORED_EVENTS: usize = 1000;
+// Changes are published at least this often, even while files keep changing
+const MAX_DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+
+/// Settings for the project file watcher, from `[fs_watcher]` in config.toml.
+///
+/// ```toml
+/// [fs_watcher]
+/// enabled = true
+/// debounce_ms = 200
+/// ignore = ["generated", "tmp"]   # on top of node_modules, .git, .next, dist, ...
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WatcherConfig {
+    pub enabled: bool,
+    /// Quiet time after a change before it is published, so a save or a `git checkout`
+    /// arrives as one batch
+    pub debounce_ms: u64,
+    /// File or directory names to ignore anywhere in the project
+    pub ignore: Vec<String>,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self { enabled: true, debounce_ms: 200, ignore: Vec::new() }
+    }
+}
+
+impl WatcherConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+
+    /// Whether a path relative to the project root is in an ignored directory or is ignored itself.
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        relative.components().any(|c| {
+            let name = c.as_os_str().to_string_lossy();
+            SKIPPED_DIRS.contains(&name.as_ref()) || self.ignore.iter().any(|i| *i == name)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsChangeKind {
+    Created,
+    Modified,
+    Removed,
+    /// Changes were lost (e.g. the kernel's event queue overflowed); rescan what you track
+    Rescan,
+}
+
+impl FsChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FsChangeKind::Created => "created",
+            FsChangeKind::Modified => "modified",
+            FsChangeKind::Removed => "removed",
+            FsChangeKind::Rescan => "rescan",
+        }
+    }
+}
+
+/// A debounced change to a file or directory in the project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FsEvent {
+    pub seq: u64,
+    pub timestamp: u64,
+    /// Path relative to the project root, with `/` separators; `.` for `Rescan`
+    pub path: String,
+    pub kind: FsChangeKind,
+    pub is_dir: bool,
+}
+
+struct EventStore {
+    events: VecDeque<FsEvent>,
+    next_seq: u64,
+}
+
+static EVENTS: Lazy<Mutex<EventStore>> = Lazy::new(|| Mutex::new(EventStore { events: VecDeque::new(), next_seq: 1 }));
+static EVENT_CHANNEL: Lazy<broadcast::Sender<FsEvent>> = Lazy::new(|| broadcast::channel(1024).0);
+static WATCHING: AtomicBool = AtomicBool::new(false);
+
+fn publish(path: String, kind: FsChangeKind, is_dir: bool) {
+    let mut store = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let event = FsEvent { seq: store.next_seq, timestamp: now_secs(), path, kind, is_dir };
+    store.next_seq += 1;
+    store.events.push_back(event.clone());
+    while store.events.len() > MAX_STORED_EVENTS {
+        store.events.pop_front();
+    }
+    // Sent under the lock, so a subscriber that read `events` first sees no gap or repeat
+    let _ = EVENT_CHANNEL.send(event);
+}
+
+/// Stored changes, oldest first, after sequence number `after_seq` when given, at most `limit`.
+/// Keeps the most recent 1000.
+pub fn events(after_seq: Option<u64>, limit: usize) -> Vec<FsEvent> {
+    let store = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    store.events.iter().filter(|e| after_seq.is_none_or(|seq| e.seq > seq)).take(limit).cloned().collect()
+}
+
+/// Sequence number of the newest change, 0 before the first.
+pub fn latest_seq() -> u64 {
+    EVENTS.lock().unwrap_or_else(|e| e.into_inner()).next_seq - 1
+}
+
+/// Stored changes after `after_seq` together with a subscription to the ones that follow.
+pub fn subscribe(after_seq: Option<u64>) -> (Vec<FsEvent>, broadcast::Receiver<FsEvent>) {
+    let store = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let backlog = store.events.iter().filter(|e| after_seq.is_some_and(|seq| e.seq > seq)).cloned().collect();
+    (backlog, EVENT_CHANNEL.subscribe())
+}
+
+/// Whether the watcher is running.
+pub fn is_watching() -> bool {
+    WATCHING.load(Ordering::SeqCst)
+}
+
+// Folds a change into the one still pending for the same path; `None` when they cancel out
+fn coalesce(pending: Option<FsChangeKind>, next: FsChangeKind) -> Option<FsChangeKind> {
+    use FsChangeKind::*;
+    match (pending, next) {
+        (None, next) => Some(next),
+        (Some(Created), Removed) => None,
+        (Some(Created), _) => Some(Created),
+        (Some(Removed), Created | Modified) => Some(Modified),
+        (Some(_), next) => Some(next),
+    }
+}
+
+// The changes one raw notify event stands for
+fn changes_of(event: &Event) -> Vec<(PathBuf, FsChangeKind)> {
+    let all = |kind: FsChangeKind| event.paths.iter().map(|p| (p.clone(), kind)).collect();
+    match &event.kind {
+        EventKind::Create(_) => all(FsChangeKind::Created),
+        EventKind::Remove(_) => all(FsChangeKind::Removed),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => all(FsChangeKind::Removed),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => all(FsChangeKind::Created),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => match event.paths.as_slice() {
+            [from, to] => vec![(from.clone(), FsChangeKind::Removed), (to.clone(), FsChangeKind::Created)],
+            _ => Vec::new(),
+        },
+        // One side of a rename the platform didn't pair up
+        EventKind::Modify(ModifyKind::Name(_)) => event
+            .paths
+            .iter()
+            .map(|p| (p.clone(), if p.exists() { FsChangeKind::Created } else { FsChangeKind::Removed }))
+            .collect(),
+        // Permission and timestamp changes are noise for editors and indexers
+        EventKind::Modify(ModifyKind::Metadata(_)) => Vec::new(),
+        EventKind::Modify(_) => all(FsChangeKind::Modified),
+        _ => Vec::new(),
+    }
+}
+
+struct WatchState {
+    root: PathBuf,
+    config: WatcherConfig,
+    watcher: RecommendedWatcher,
+    // Watched one by one rather than recursively, so ignored trees like node_modules cost no watches
+    watched_dirs: HashSet<PathBuf>,
+    pending: BTreeMap<PathBuf, (FsChangeKind, bool)>,
+    first_pending: Option<Instant>,
+}
+
+impl WatchState {
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root).unwrap_or(path).to_path_buf()
+    }
+
+    // Watches `dir` and the directories below it; returns the files found, for a new directory
+    fn watch_tree(&mut self, dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let root = self.root.clone();
+        let config = self.config.clone();
+        let walker = walkdir::WalkDir::new(dir)
+            .into_iter()
+            .filter_entry(|e| !config.is_ignored(e.path().strip_prefix(&root).unwrap_or(e.path())));
+        for entry in walker.filter_map(|e| e.ok()) {
+            if !entry.file_type().is_dir() {
+                files.push(entry.into_path());
+                continue;
+            }
+            if let Err(e) = self.watcher.watch(entry.path(), RecursiveMode::NonRecursive) {
+                tracing::debug!(target: "file_system::watcher", path = %entry.path().display(), error = %e, "Failed to watch directory.");
+                continue;
+            }
+            self.watched_dirs.insert(entry.into_path());
+        }
+        files
+    }
+
+    fn record(&mut self, path: PathBuf, kind: FsChangeKind, is_dir: bool) {
+        let pending = self.pending.get(&path).map(|(kind, _)| *kind);
+        match coalesce(pending, kind) {
+            Some(kind) => {
+                self.pending.insert(path, (kind, is_dir));
+            }
+            None => {
+                self.pending.remove(&path);
+            }
+        }
+        self.first_pending.get_or_insert_with(Instant::now);
+    }
+
+    fn handle(&mut self, event: Event) {
+        if event.need_rescan() {
+            self.record(self.root.clone(), FsChangeKind::Rescan, true);
+        }
+        for (path, kind) in changes_of(&event) {
+            if self.config.is_ignored(&self.relative(&path)) {
+                continue;
+            }
+            let is_dir = match kind {
+                FsChangeKind::Removed => {
+                    let was_dir = self.watched_dirs.contains(&path);
+                    self.watched_dirs.retain(|d| !d.starts_with(&path));
+                    was_dir
+                }
+                _ => path.is_dir(),
+            };
+            // Files can land in a new directory before its watch is added, so report what is already there
+            if is_dir && kind == FsChangeKind::Created && !self.watched_dirs.contains(&path) {
+                for file in self.watch_tree(&path) {
+                    self.record(file, FsChangeKind::Created, false);
+                }
+            }
+            self.record(path, kind, is_dir);
+        }
+    }
+
+    fn flush(&mut self) {
+        for (path, (kind, is_dir)) in std::mem::take(&mut self.pending) {
+            let relative = self.relative(&path).to_string_lossy().replace('\\', "/");
+            publish(if relative.is_empty() { ".".to_string() } else { relative }, kind, is_dir);
+        }
+        self.first_pending = None;
+    }
+}
+
+/// Starts watching `project_dir` in the background. Changes are debounced, filtered through
+/// the ignore rules and published to `events` and `subscribe`.
+pub fn start(project_dir: PathBuf) {
+    let config = WatcherConfig::load();
+    if !config.enabled {
+        tracing::info!(target: "file_system::watcher", "File watching is disabled in config.toml.");
+        return;
+    }
+    let (tx, rx) = mpsc::unbounded_channel();
+    let watcher = match notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            tracing::warn!(target: "file_system::watcher", error = %e, "Failed to start the file watcher; /api/fs will not report changes.");
+            return;
+        }
+    };
+    let state = WatchState {
+        root: project_dir,
+        config,
+        watcher,
+        watched_dirs: HashSet::new(),
+        pending: BTreeMap::new(),
+        first_pending: None,
+    };
+    tokio::spawn(run(state, rx));
+}
+
+async fn run(mut state: WatchState, mut rx: mpsc::UnboundedReceiver<notify::Result<Event>>) {
+    let root = state.root.clone();
+    state.watch_tree(&root);
+    WATCHING.store(true, Ordering::SeqCst);
+    tracing::info!(target: "file_system::watcher", directories = state.watched_dirs.len(), "Watching the project for changes.");
+
+    let debounce = Duration::from_millis(state.config.debounce_ms);
+    loop {
+        let received = match state.first_pending {
+            None => rx.recv().await,
+            Some(first) => {
+                let deadline = (Instant::now() + debounce).min(first + MAX_DEBOUNCE_DELAY);
+                match tokio::time::timeout_at(deadline, rx.recv()).await {
+                    Ok(received) => received,
+                    Err(_) => {
+                        state.flush();
+                        continue;
+                    }
+                }
+            }
+        };
+        match received {
+            Some(Ok(event)) => state.handle(event),
+            Some(Err(e)) => tracing::debug!(target: "file_system::watcher", error = %e, "File watcher error."),
+            None => break,
+        }
+        if state.first_pending.is_some_and(|first| first.elapsed() >= MAX_DEBOUNCE_DELAY) {
+            state.flush();
+        }
+    }
+    WATCHING.store(false, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, MetadataKind, RemoveKind};
+
+    #[test]
+    fn test_raw_events_coalesce_into_changes() {
+        let event = |kind: EventKind, paths: &[&str]| Event { kind, paths: paths.iter().map(PathBuf::from).collect(), attrs: Default::default() };
+        let rename = event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &["/p/a.ts", "/p/b.ts"]);
+        assert_eq!(
+            changes_of(&rename),
+            vec![(PathBuf::from("/p/a.ts"), FsChangeKind::Removed), (PathBuf::from("/p/b.ts"), FsChangeKind::Created)]
+        );
+        assert!(changes_of(&event(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Permissions)), &["/p/a.ts"])).is_empty());
+
+        // An editor's save: create a temp file, write it, rename it over the original
+        let fold = |kinds: &[FsChangeKind]| kinds.iter().fold(None, |pending, kind| coalesce(pending, *kind));
+        use FsChangeKind::*;
+        assert_eq!(fold(&[Created, Modified, Removed]), None);
+        assert_eq!(fold(&[Removed, Created]), Some(Modified));
+        assert_eq!(fold(&[Created, Modified]), Some(Created));
+        assert_eq!(fold(&[Modified, Removed]), Some(Removed));
+        assert_eq!(changes_of(&event(EventKind::Create(CreateKind::File), &["/p/c.ts"]))[0].1, Created);
+        assert_eq!(changes_of(&event(EventKind::Remove(RemoveKind::Any), &["/p/c.ts"]))[0].1, Removed);
+        assert_eq!(changes_of(&event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), &["/p/c.ts"]))[0].1, Modified);
+
+        let config = WatcherConfig { ignore: vec!["generated".to_string()], ..WatcherConfig::default() };
+        assert!(config.is_ignored(Path::new("node_modules/react/index.js")));
+        assert!(config.is_ignored(Path::new("src/generated/api.ts")));
+        assert!(!config.is_ignored(Path::new("src/app/page.tsx")));
+    }
+}
@@ -6,6 +6,8 @@ pub mod terminal;
 pub mod file_system;
 pub mod dev_setup;
 pub mod dev_runtime;
+#[cfg(test)]
+mod test_support;
 
 // Potentially remove these if they are fully merged into terminal
 // pub mod utils;
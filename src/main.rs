@@ -1,25 +1,48 @@
 use anyhow::{Context, Result};
 use clap::Parser; // Added for command-line argument parsing
-use std::time::Instant;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use tracing::info;
 
 // Tracing subscriber imports for layered logging
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
 // Use modules
 use galatea::dev_runtime; // Existing, contains logging, nextjs
 use galatea::dev_setup;
+use galatea::file_system::paths;
 use galatea::terminal; // Added for port utilities
 
 // Add Poem imports
-use poem::{http::Method, listener::TcpListener, middleware::Cors, EndpointExt, Route, Server};
+use poem::{get, http::Method, listener::TcpListener, middleware::Cors, EndpointExt, Route, Server};
 use poem_openapi::{OpenApi, OpenApiService};
 
 // Import the individual API structs
 use galatea::api::routes::editor_api::EditorApi;
+use galatea::api::routes::logs_api::{logs_ws_handler, LogsApi};
+use galatea::api::routes::lsp_api::LspApi;
+use galatea::api::routes::mcp::McpApi;
 use galatea::api::routes::project::ProjectApi;
+use galatea::api::routes::runtime::{CapabilityUnavailableResponse, DevServerReadinessResponse, RuntimeApi, WARMING_UP_RETRY_SECS};
+use galatea::api::routes::setup::SetupApi;
+use galatea::api::routes::suggestions::SuggestionsApi;
+use galatea::api::routes::codegen::CodegenApi;
+use galatea::api::routes::refactor::RefactorApi;
+use galatea::api::routes::jobs::JobsApi;
+use galatea::api::routes::terminal::{terminal_ws_handler, TerminalApi};
+use galatea::api::routes::fs::{fs_events_ws_handler, FsApi};
+use galatea::api::routes::git::GitApi;
+use galatea::api::routes::code_intel::CodeIntelApi;
+use galatea::api::routes::system::SystemApi;
+use galatea::api::routes::validation::ValidationApi;
+use galatea::api::routes::workspaces::WorkspacesApi;
+use galatea::dev_operation::validation;
 
 // Import for MCP proxy functionality
+use galatea::api::auth::{ApiAuthConfig, ApiPrincipal};
+use galatea::api::mcp_proxy::{AuthOutcome, McpProxyPolicy};
 use poem::http::StatusCode;
 use poem::{handler, web::Path as PoemPath, Response};
 
@@ -29,12 +52,108 @@ use poem::{handler, web::Path as PoemPath, Response};
 struct Cli {
     #[clap(long)]
     token: Option<String>,
-    #[clap(long, default_value = "nextjs")]
+    /// Template name or git URL; defaults to the one saved in config.toml, else `nextjs`
+    #[clap(long)]
     template: Option<String>,
     #[clap(long, default_value_t = false)]
     mcp_enabled: bool,
     #[clap(long, default_value_t = false)]
     use_sudo: bool,
+    #[clap(long, default_value_t = false)]
+    disable_crash_reports: bool,
+    /// Template variable as key=value (repeatable), see galatea.template.toml
+    #[clap(long = "template-var")]
+    template_vars: Vec<String>,
+    /// Never touch the network: use local caches or fail fast (also GALATEA_OFFLINE=1)
+    #[clap(long, default_value_t = false)]
+    offline: bool,
+    /// Populate the template, pnpm and npm caches used by --offline, then exit
+    #[clap(long, default_value_t = false)]
+    prewarm: bool,
+    /// Discard the existing project and scaffold it again from the template
+    #[clap(long, default_value_t = false)]
+    force_rescaffold: bool,
+    /// Serve only the setup wizard API (/api/setup) until a frontend has configured Galatea
+    #[clap(long, default_value_t = false)]
+    setup_wizard: bool,
+    /// Serve npm/pnpm installs through a local caching registry proxy (also `registry_cache = "true"`)
+    #[clap(long, default_value_t = false)]
+    registry_cache: bool,
+    /// Config environment: galatea_files/config.{env}.toml overrides config.toml (also GALATEA_ENV)
+    #[clap(long = "env")]
+    config_env: Option<String>,
+    /// Project directory, instead of `project/` next to the executable (also GALATEA_PROJECT_DIR
+    /// or `project_dir` in config.toml). An existing directory is used as is, never re-scaffolded
+    #[clap(long)]
+    project_dir: Option<PathBuf>,
+    /// Directory for config.toml, the database and Galatea's other files, instead of
+    /// `galatea_files/` next to the executable (also GALATEA_DATA_DIR)
+    #[clap(long)]
+    data_dir: Option<PathBuf>,
+}
+
+/// A configured quota.
+#[derive(Debug, poem_openapi::Object)]
+struct QuotaLimit {
+    /// **Required.** `edits_per_hour`, `script_runs_per_hour`, `cpu_seconds` or `bytes_written`
+    metric: String,
+    /// **Optional.** Maximum per API key and per session; absent means unlimited
+    limit: Option<f64>,
+    /// **Required.** Whether usage resets every hour (otherwise it counts since startup)
+    hourly: bool,
+}
+
+/// What one API key or session has used.
+#[derive(Debug, poem_openapi::Object)]
+struct PrincipalUsageView {
+    /// **Required.** `key:<hash of the API key>`, `session:<id>` or `anonymous`
+    principal: String,
+    /// **Required.** Editor mutations this hour
+    edits_per_hour: f64,
+    /// **Required.** Script runs this hour
+    script_runs_per_hour: f64,
+    /// **Required.** CPU seconds of script subprocesses since startup
+    cpu_seconds: f64,
+    /// **Required.** Bytes written by editor mutations since startup
+    bytes_written: f64,
+    /// **Required.** Seconds until the hourly counters reset
+    window_resets_in: u64,
+    /// **Required.** Unix timestamp of the last charged operation
+    last_active: u64,
+}
+
+/// Quotas and usage since startup.
+#[derive(Debug, poem_openapi::Object)]
+struct UsageResponse {
+    /// **Required.** Configured quotas (`quota_<metric>` in config.toml)
+    limits: Vec<QuotaLimit>,
+    /// **Required.** Principals this request is accounted to
+    caller: Vec<String>,
+    /// **Required.** Usage of every principal seen, most recently active first
+    usage: Vec<PrincipalUsageView>,
+}
+
+/// An optional subsystem and its live state.
+#[derive(Debug, poem_openapi::Object)]
+struct CapabilityView {
+    /// **Required.** `mcp`, `lsp`, `browser`, `embeddings` or `git`
+    name: String,
+    /// **Required.** `unknown`, `idle` (started on first use), `starting`, `available`,
+    /// `disabled`, `unavailable` or `failed`
+    state: String,
+    /// **Required.** Whether endpoints depending on it currently accept requests
+    usable: bool,
+    /// **Optional.** Why the capability is disabled, unavailable or failed
+    reason: Option<String>,
+    /// **Required.** Unix timestamp of the last state change, 0 if never checked
+    since: u64,
+}
+
+/// Live state of Galatea's optional subsystems.
+#[derive(Debug, poem_openapi::Object)]
+struct CapabilitiesResponse {
+    /// **Required.** Every capability, in a stable order
+    capabilities: Vec<CapabilityView>,
 }
 
 // Combined API struct
@@ -47,6 +166,400 @@ impl GalateaApi {
     async fn health(&self) -> poem_openapi::payload::PlainText<String> {
         poem_openapi::payload::PlainText("Galatea is online.".to_string())
     }
+
+    /// Optional subsystems
+    ///
+    /// Live state of the subsystems Galatea can run without: MCP servers, the language server,
+    /// the headless browser, embeddings and git. Endpoints needing one that isn't usable answer
+    /// 503 with code `capability_unavailable` and the capability's name and state.
+    #[oai(path = "/capabilities", method = "get")]
+    async fn capabilities(&self) -> poem_openapi::payload::Json<CapabilitiesResponse> {
+        use dev_runtime::capabilities;
+
+        let capabilities = capabilities::statuses()
+            .into_iter()
+            .map(|status| CapabilityView {
+                name: status.capability.as_str().to_string(),
+                state: status.state.as_str().to_string(),
+                usable: capabilities::require(status.capability).is_ok(),
+                reason: status.reason,
+                since: status.since,
+            })
+            .collect();
+        poem_openapi::payload::Json(CapabilitiesResponse { capabilities })
+    }
+
+    /// Quota usage
+    ///
+    /// Usage of each API key and client session against the configured quotas (edits and
+    /// script runs per hour, script CPU seconds and bytes written). Requests are accounted to
+    /// their API key and to the session named by the `x-galatea-session` header; a request is
+    /// refused with 429 once either has used up a quota.
+    #[oai(path = "/usage", method = "get")]
+    async fn usage(&self) -> poem_openapi::payload::Json<UsageResponse> {
+        use dev_runtime::quotas::{self, QuotaMetric};
+
+        let settings = quotas::settings();
+        let limits = QuotaMetric::ALL
+            .iter()
+            .map(|metric| QuotaLimit {
+                metric: metric.as_str().to_string(),
+                limit: settings.limit(*metric),
+                hourly: matches!(metric, QuotaMetric::EditsPerHour | QuotaMetric::ScriptRunsPerHour),
+            })
+            .collect();
+        let usage = quotas::usage()
+            .into_iter()
+            .map(|entry| {
+                let used = |metric: QuotaMetric| entry.used.iter().find(|(m, _)| *m == metric).map_or(0.0, |(_, v)| *v);
+                PrincipalUsageView {
+                    edits_per_hour: used(QuotaMetric::EditsPerHour),
+                    script_runs_per_hour: used(QuotaMetric::ScriptRunsPerHour),
+                    cpu_seconds: used(QuotaMetric::CpuSeconds),
+                    bytes_written: used(QuotaMetric::BytesWritten),
+                    window_resets_in: entry.window_resets_in,
+                    last_active: entry.last_active,
+                    principal: entry.principal,
+                }
+            })
+            .collect();
+        poem_openapi::payload::Json(UsageResponse { limits, caller: quotas::current_principals(), usage })
+    }
+}
+
+// Validation report handler: standalone HTML for a validation pipeline run (`latest` for the newest)
+#[handler]
+async fn validation_report(PoemPath(run_id): PoemPath<String>) -> poem::Result<poem::web::Html<String>> {
+    match validation::get_run(&run_id) {
+        Ok(Some(run)) => Ok(poem::web::Html(validation::render_html(&run))),
+        Ok(None) => Err(poem::Error::from_string(
+            format!("Validation run '{}' not found", run_id),
+            StatusCode::NOT_FOUND,
+        )),
+        Err(e) => Err(poem::Error::from_string(e.to_string(), StatusCode::BAD_REQUEST)),
+    }
+}
+
+// 503 with a structured body while the dev server compiles, instead of a bare 502
+fn dev_server_warming_up(readiness: dev_runtime::nextjs_dev_server::DevServerReadiness) -> Response {
+    let body = DevServerReadinessResponse::from(readiness);
+    Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header("Retry-After", WARMING_UP_RETRY_SECS.to_string())
+        .content_type("application/json")
+        .body(serde_json::to_string(&body).unwrap_or_default())
+}
+
+// One client for all proxied dev server requests, so they reuse connections
+static DEV_SERVER_CLIENT: once_cell::sync::Lazy<reqwest::Client> = once_cell::sync::Lazy::new(reqwest::Client::new);
+
+// Next.js dev server proxy: /preview/{path} -> http://127.0.0.1:3000/{path}, once the server is ready.
+// Also serves /_next/* (assets, HMR), which pages reference from the root, and forwards WebSocket
+// upgrades so hot reloading works with only Galatea's port exposed.
+#[handler]
+async fn dev_server_proxy(req: &poem::Request, body: poem::Body) -> poem::Result<Response> {
+    use dev_runtime::nextjs_dev_server;
+    use galatea::api::{mcp_proxy, preview_proxy};
+
+    let readiness = nextjs_dev_server::wait_until_ready(nextjs_dev_server::ready_timeout()).await;
+    if !readiness.ready {
+        return Ok(dev_server_warming_up(readiness));
+    }
+
+    let target = preview_proxy::upstream_path(req.uri().path(), req.uri().query());
+    if mcp_proxy::is_upgrade_request(req.headers()) {
+        return mcp_proxy::tunnel_upgrade(req, &target, nextjs_dev_server::dev_server_port(), req.headers())
+            .await
+            .map_err(|e| poem::Error::from_string(format!("Proxy error: {:#}", e), StatusCode::BAD_GATEWAY));
+    }
+    let target_url = format!("http://127.0.0.1:{}{}", nextjs_dev_server::dev_server_port(), target);
+
+    let mut proxy_req = DEV_SERVER_CLIENT.request(req.method().clone(), &target_url);
+    for (key, value) in req.headers() {
+        if key != "host" && !galatea::api::mcp_proxy::is_hop_by_hop(req.headers(), key.as_str()) {
+            proxy_req = proxy_req.header(key, value);
+        }
+    }
+    proxy_req = proxy_req.body(mcp_proxy::stream_request_body(body));
+
+    let resp = match proxy_req.send().await {
+        Ok(resp) => resp,
+        // The server went away since it was found ready, e.g. a restart
+        Err(e) if e.is_connect() => {
+            nextjs_dev_server::mark_unreachable();
+            return Ok(dev_server_warming_up(nextjs_dev_server::probe_readiness().await));
+        }
+        Err(e) => return Err(poem::Error::from_string(format!("Proxy error: {}", e), StatusCode::BAD_GATEWAY)),
+    };
+
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let mut response = Response::builder().status(status);
+    for (key, value) in headers.iter() {
+        if key != "content-length" && !galatea::api::mcp_proxy::is_hop_by_hop(&headers, key.as_str()) {
+            response = response.header(key, value);
+        }
+    }
+    Ok(response.body(mcp_proxy::stream_response_body(resp)))
+}
+
+// Runs each /api request in a span, exported when OpenTelemetry is configured
+async fn request_tracing<E: poem::Endpoint>(next: std::sync::Arc<E>, req: poem::Request) -> poem::Result<Response> {
+    use tracing::Instrument;
+
+    if !req.uri().path().starts_with("/api/") {
+        return Ok(next.get_response(req).await);
+    }
+    let span = dev_runtime::telemetry::request_span(req.method().as_str(), req.uri().path(), req.headers());
+    let response = next.get_response(req).instrument(span.clone()).await;
+    dev_runtime::telemetry::record_status(&span, response.status().as_u16());
+    Ok(response)
+}
+
+// Accounts the work of each /api request to its API key and session for quota enforcement
+async fn quota_scope<E: poem::Endpoint>(next: std::sync::Arc<E>, req: poem::Request) -> poem::Result<Response> {
+    use dev_runtime::quotas;
+
+    if !req.uri().path().starts_with("/api/") {
+        return Ok(next.get_response(req).await);
+    }
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let api_key = header(galatea::api::mcp_proxy::TOKEN_HEADER).or_else(|| header("authorization"));
+    let principals = quotas::principals_for(api_key.as_deref(), header(quotas::SESSION_HEADER).as_deref());
+    Ok(quotas::scope(principals, next.get_response(req)).await)
+}
+
+// Scopes requests that name a workspace, through the `x-galatea-workspace` header or a
+// `/w/{id}` path prefix, to that workspace's root; the prefix is stripped before routing
+async fn workspace_scope<E: poem::Endpoint>(next: std::sync::Arc<E>, mut req: poem::Request) -> poem::Result<Response> {
+    use dev_runtime::workspaces;
+
+    let prefixed = workspaces::split_path(req.uri().path()).map(|(id, rest)| (id.to_string(), rest.to_string()));
+    let id = match &prefixed {
+        Some((id, _)) => id.clone(),
+        None => match req.headers().get(workspaces::WORKSPACE_HEADER).and_then(|v| v.to_str().ok()) {
+            Some(id) if !id.trim().is_empty() => id.trim().to_string(),
+            _ => return Ok(next.get_response(req).await),
+        },
+    };
+    let workspace = match workspaces::get(&id) {
+        Ok(Some(workspace)) => workspace,
+        Ok(None) => {
+            return Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(format!("No workspace '{}'; see GET /api/workspaces", id)))
+        }
+        Err(e) => {
+            return Ok(Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(format!("Failed to look up workspace '{}': {:#}", id, e)))
+        }
+    };
+    if let Some((_, rest)) = prefixed {
+        let path_and_query = match req.uri().query() {
+            Some(query) => format!("{}?{}", rest, query),
+            None => rest,
+        };
+        match path_and_query.parse() {
+            Ok(uri) => *req.uri_mut() = uri,
+            Err(_) => return Ok(Response::builder().status(StatusCode::BAD_REQUEST).body("Invalid request path")),
+        }
+    }
+    Ok(workspaces::scope(workspace.root, next.get_response(req)).await)
+}
+
+// Room above `[editor_transfers] max_upload_bytes` for the multipart framing of an upload
+const BODY_LIMIT_SLACK: u64 = 1024 * 1024;
+
+// Refuses request bodies over the upload limit before anything buffers them: by
+// Content-Length up front, or once a chunked body has streamed past it
+async fn body_limit<E: poem::Endpoint>(next: std::sync::Arc<E>, mut req: poem::Request) -> poem::Result<Response> {
+    use futures::StreamExt;
+
+    let limit = galatea::dev_operation::transfers::TransferConfig::load().max_upload_bytes.saturating_add(BODY_LIMIT_SLACK);
+    let declared = req.headers().get(poem::http::header::CONTENT_LENGTH).and_then(|v| v.to_str().ok()).and_then(|v| v.parse::<u64>().ok());
+    match declared {
+        Some(length) if length > limit => {
+            return Ok(Response::builder()
+                .status(StatusCode::PAYLOAD_TOO_LARGE)
+                .body(format!("The request body is {} bytes, over the {} byte limit", length, limit)))
+        }
+        Some(_) => {}
+        None => {
+            let mut read = 0u64;
+            let stream = req.take_body().into_bytes_stream().map(move |chunk| {
+                let chunk = chunk?;
+                read += chunk.len() as u64;
+                if read > limit {
+                    return Err(std::io::Error::other(format!("The request body is over the {} byte limit", limit)));
+                }
+                Ok(chunk)
+            });
+            req.set_body(poem::Body::from_bytes_stream(stream));
+        }
+    }
+    Ok(next.get_response(req).await)
+}
+
+// Checks the API token of each /api request against the scope it needs, once `[api_auth]`
+// tokens are configured; the token's principal is left in the request for later handlers
+async fn api_auth<E: poem::Endpoint>(next: std::sync::Arc<E>, mut req: poem::Request) -> poem::Result<Response> {
+    use galatea::api::auth::{self, AuthDecision};
+
+    let path = req.uri().path().to_string();
+    let path = dev_runtime::workspaces::split_path(&path).map_or(path.as_str(), |(_, rest)| rest);
+    if !path.starts_with("/api/") {
+        return Ok(next.get_response(req).await);
+    }
+    let config = match ApiAuthConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            return Ok(Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(format!("Invalid API token configuration: {:#}", e)))
+        }
+    };
+    if !config.is_enforced() {
+        return Ok(next.get_response(req).await);
+    }
+    let body = if auth::needs_body(req.method(), path) {
+        let bytes = req.take_body().into_bytes().await?;
+        let body = serde_json::from_slice::<serde_json::Value>(&bytes).ok();
+        req.set_body(bytes);
+        body
+    } else {
+        None
+    };
+    let required = auth::required_scope(req.method(), path, body.as_ref());
+    let loopback = req.remote_addr().as_socket_addr().is_some_and(|addr| addr.ip().is_loopback());
+    match config.authorize(req.headers(), loopback, required) {
+        AuthDecision::Allowed(principal) => {
+            if let Some(principal) = principal {
+                req.extensions_mut().insert(principal);
+            }
+            Ok(next.get_response(req).await)
+        }
+        AuthDecision::Unauthenticated(message) => Ok(Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header("WWW-Authenticate", "Bearer")
+            .body(message)),
+        AuthDecision::Forbidden(message) => Ok(Response::builder().status(StatusCode::FORBIDDEN).body(message)),
+    }
+}
+
+// Records each state-changing /api request (anything a read-only token couldn't do) in the
+// audit log, with who made it, its parameters and how it ended
+async fn audit_log<E: poem::Endpoint>(next: std::sync::Arc<E>, mut req: poem::Request) -> poem::Result<Response> {
+    use dev_runtime::{audit, quotas, workspaces};
+    use galatea::api::auth::{self, Scope, LOOPBACK_PRINCIPAL};
+
+    let path = req.uri().path().to_string();
+    if !path.starts_with("/api/") {
+        return Ok(next.get_response(req).await);
+    }
+    // Uploads and MCP proxy calls stream their bodies; only JSON bodies outside the proxy are
+    // read, and only needed up front when the scope depends on them
+    let is_json = req.content_type().is_some_and(|t| t.starts_with("application/json"));
+    let read_body = auth::needs_body(req.method(), &path)
+        || (is_json && !auth::is_mcp_proxy(&path) && !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS));
+    let body = if read_body {
+        let body = req.take_body().into_vec().await?;
+        req.set_body(body.clone());
+        Some(body)
+    } else {
+        None
+    };
+    let json = body.as_deref().and_then(|b| serde_json::from_slice::<serde_json::Value>(b).ok());
+    if auth::required_scope(req.method(), &path, json.as_ref()) == Scope::Read {
+        return Ok(next.get_response(req).await);
+    }
+
+    let params = match &body {
+        Some(body) => audit::request_params(body, req.uri().query()),
+        None => {
+            let length = req.headers().get(poem::http::header::CONTENT_LENGTH).and_then(|v| v.to_str().ok()).and_then(|v| v.parse::<u64>().ok());
+            audit::streamed_request_params(length, req.uri().query())
+        }
+    };
+    let principal = match req.extensions().get::<ApiPrincipal>() {
+        Some(p) if p.name == LOOPBACK_PRINCIPAL => p.name.clone(),
+        Some(p) => format!("token:{}", p.name),
+        None => quotas::principals_for(galatea::api::mcp_proxy::provided_token(req.headers()), None).remove(0),
+    };
+    let mut entry = audit::AuditEntry {
+        timestamp: dev_runtime::util::now_secs(),
+        principal,
+        client_session: req.headers().get(quotas::SESSION_HEADER).and_then(|v| v.to_str().ok()).map(str::to_string),
+        operation: audit::operation_name(&path, &params),
+        method: req.method().to_string(),
+        path,
+        workspace: workspaces::scoped_root().map(|root| root.display().to_string()),
+        params,
+        status: 0,
+        error: None,
+        duration_ms: 0,
+    };
+    let started = Instant::now();
+    let mut response = next.get_response(req).await;
+    entry.duration_ms = started.elapsed().as_millis() as u64;
+    entry.status = response.status().as_u16();
+    if entry.status >= 400 {
+        let (parts, body) = response.into_parts();
+        let body = body.into_vec().await.unwrap_or_default();
+        entry.error = Some(audit::error_excerpt(&String::from_utf8_lossy(&body)));
+        response = Response::from_parts(parts, poem::Body::from_vec(body));
+    }
+    audit::record(entry);
+    Ok(response)
+}
+
+// Counts /api requests against the rate limit and attaches limit warnings to responses
+async fn limit_notifications<E: poem::Endpoint>(next: std::sync::Arc<E>, req: poem::Request) -> poem::Result<Response> {
+    use dev_runtime::limits;
+
+    if !req.uri().path().starts_with("/api/") {
+        return Ok(next.get_response(req).await);
+    }
+    // Clients sharing the Galatea token share a budget; anonymous clients are told apart by address
+    let client = req
+        .headers()
+        .get(galatea::api::mcp_proxy::TOKEN_HEADER)
+        .or_else(|| req.headers().get("authorization"))
+        .and_then(|v| v.to_str().ok())
+        .map(|v| format!("token:{}", v))
+        .or_else(|| req.remote_addr().as_socket_addr().map(|addr| format!("addr:{}", addr.ip())))
+        .unwrap_or_default();
+    let rate = limits::check_rate(&client);
+
+    let mut response = match &rate {
+        Some(rate) if rate.exceeded => {
+            let body = serde_json::json!({
+                "code": limits::LimitCode::RateLimitExceeded.as_str(),
+                "message": rate.warning.as_ref().map(|w| w.message.clone()),
+                "retry_after_secs": rate.reset_secs,
+            });
+            Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header("Retry-After", rate.reset_secs.to_string())
+                .content_type("application/json")
+                .body(body.to_string())
+        }
+        _ => next.get_response(req).await,
+    };
+
+    let headers = response.headers_mut();
+    if let Some(rate) = &rate {
+        headers.insert("x-ratelimit-limit", rate.limit.into());
+        headers.insert("x-ratelimit-remaining", rate.remaining.into());
+        headers.insert("x-ratelimit-reset", rate.reset_secs.into());
+    }
+    let warnings = limits::active_warnings().into_iter().chain(rate.and_then(|r| r.warning));
+    for warning in warnings {
+        if let Ok(value) = warning.header_value().parse() {
+            headers.append(limits::WARNING_HEADER, value);
+        }
+    }
+    Ok(response)
 }
 
 // MCP Proxy handler
@@ -66,6 +579,9 @@ async fn mcp_proxy(req: &poem::Request, body: poem::Body) -> poem::Result<Respon
     }
 
     let api_type = path_parts[2];
+    if let Err(e) = dev_runtime::capabilities::require(dev_runtime::capabilities::Capability::Mcp) {
+        return Ok(CapabilityUnavailableResponse::from(e).into_response());
+    }
     let subpath = if path_parts.len() > 4 {
         path_parts[4..].join("/")
     } else {
@@ -93,106 +609,325 @@ async fn mcp_proxy(req: &poem::Request, body: poem::Body) -> poem::Result<Respon
             )
         })?;
 
-    // Build the target URL
-    let target_url = if subpath.is_empty() {
-        format!("http://127.0.0.1:{}/mcp", mcp_def.port)
-    } else {
-        format!("http://127.0.0.1:{}/mcp/{}", mcp_def.port, subpath)
+    // Per-server proxy policy from config.toml ([mcp_proxy.<id>])
+    let policy = McpProxyPolicy::load(&mcp_def.id).map_err(|e| {
+        poem::Error::from_string(
+            format!("Invalid MCP proxy configuration: {:#}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+    })?;
+    let expected_token = galatea::dev_setup::config_files::get_config_value("token");
+    // A request the API tokens already let through (see `api_auth`) needs no second check
+    let outcome = match req.extensions().get::<ApiPrincipal>() {
+        Some(_) => AuthOutcome::Allowed,
+        None => policy.check_auth(req.headers(), expected_token.as_deref()),
     };
+    match outcome {
+        AuthOutcome::Allowed => {}
+        AuthOutcome::Missing => {
+            return Err(poem::Error::from_string(
+                "Missing Galatea token (send 'Authorization: Bearer <token>' or 'X-Galatea-Token')",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        AuthOutcome::Invalid => {
+            return Err(poem::Error::from_string("Invalid Galatea token", StatusCode::UNAUTHORIZED))
+        }
+        AuthOutcome::NotConfigured => {
+            return Err(poem::Error::from_string(
+                format!("MCP server '{}' requires a token but Galatea has none configured (start with --token)", mcp_def.id),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ))
+        }
+    }
 
-    // Create HTTP client
-    let client = reqwest::Client::new();
+    // Wait for a server that is still building or starting, rather than have the request refused
+    let health_config = dev_runtime::mcp_health::McpHealthConfig::load();
+    let ready_timeout = std::time::Duration::from_secs(health_config.ready_timeout_secs);
+    if let Some(status) = dev_runtime::mcp_health::wait_until_ready(&mcp_def.id, ready_timeout).await {
+        // An unhealthy server may have recovered; the request itself will tell
+        use dev_runtime::mcp_health::McpServerState;
+        if !matches!(status.state, McpServerState::Ready | McpServerState::Unhealthy) {
+            let mut message = format!("MCP server '{}' is {}", mcp_def.id, status.state.as_str());
+            if let Some(error) = &status.last_error {
+                message.push_str(&format!(": {}", error));
+            }
+            message.push_str(". See /api/mcp/status.");
+            return Ok(Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header("Retry-After", WARMING_UP_RETRY_SECS.to_string())
+                .body(message));
+        }
+    }
 
-    // Forward the request
-    let mut proxy_req = client.request(req.method().clone(), &target_url);
+    // Build the target path
+    let mut target = if subpath.is_empty() { "/mcp".to_string() } else { format!("/mcp/{}", subpath) };
+    if let Some(query) = req.uri().query() {
+        target.push('?');
+        target.push_str(query);
+    }
 
-    // Copy headers
-    for (key, value) in req.headers() {
-        if key != "host" {
-            proxy_req = proxy_req.header(key, value);
-        }
+    // WebSocket transports get a byte tunnel to the server
+    let upgrade = galatea::api::mcp_proxy::is_upgrade_request(req.headers());
+    let upstream_headers = policy.upstream_headers(req.headers(), upgrade);
+    if upgrade {
+        return galatea::api::mcp_proxy::tunnel_upgrade(req, &target, mcp_def.port, &upstream_headers)
+            .await
+            .map_err(|e| poem::Error::from_string(format!("Proxy error: {:#}", e), StatusCode::BAD_GATEWAY));
     }
+    let target_url = format!("http://127.0.0.1:{}{}", mcp_def.port, target);
 
-    // Forward body
-    let body_bytes = body.into_bytes().await?;
-    proxy_req = proxy_req.body(body_bytes);
+    // Create HTTP client
+    let mut client_builder = reqwest::Client::builder();
+    if let Some(connect_timeout) = policy.connect_timeout {
+        client_builder = client_builder.connect_timeout(connect_timeout);
+    }
+    let client = client_builder.build().map_err(|e| {
+        poem::Error::from_string(format!("Failed to build proxy client: {}", e), StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
 
-    // Send request
-    let resp = proxy_req.send().await.map_err(|e| {
-        poem::Error::from_string(format!("Proxy error: {}", e), StatusCode::BAD_GATEWAY)
+    // Forward the request, streaming its body
+    let proxy_req = client
+        .request(req.method().clone(), &target_url)
+        .headers(upstream_headers)
+        .body(galatea::api::mcp_proxy::stream_request_body(body));
+
+    // The timeout covers waiting for the response to start; an SSE stream may then stay open
+    let sent = match policy.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, proxy_req.send()).await.map_err(|_| {
+            poem::Error::from_string(format!("Proxy error: no response within {}s", timeout.as_secs()), StatusCode::GATEWAY_TIMEOUT)
+        })?,
+        None => proxy_req.send().await,
+    };
+    let resp = sent.map_err(|e| {
+        let status = if e.is_timeout() { StatusCode::GATEWAY_TIMEOUT } else { StatusCode::BAD_GATEWAY };
+        poem::Error::from_string(format!("Proxy error: {}", e), status)
     })?;
 
     // Build response
     let status = resp.status();
     let headers = resp.headers().clone();
-    let body = resp.bytes().await.map_err(|e| {
-        poem::Error::from_string(
-            format!("Failed to read response body: {}", e),
-            StatusCode::BAD_GATEWAY,
-        )
-    })?;
-
     let mut response = Response::builder().status(status);
 
-    // Copy response headers
-    for (key, value) in headers {
-        if let Some(key) = key {
+    // Copy end-to-end response headers; the body is streamed, so its length isn't known up front
+    for (key, value) in headers.iter() {
+        if key != "content-length" && !galatea::api::mcp_proxy::is_hop_by_hop(&headers, key.as_str()) {
             response = response.header(key, value);
         }
     }
 
-    Ok(response.body(body))
+    Ok(response.body(galatea::api::mcp_proxy::stream_response_body(resp)))
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    // Selected before anything reads config.toml, including the telemetry setup below
+    if let Some(dir) = &cli.data_dir {
+        paths::set_data_dir(dir)?;
+    }
+    if let Some(dir) = &cli.project_dir {
+        paths::set_project_dir(dir)?;
+    }
+    if let Some(env) = &cli.config_env {
+        dev_setup::config_files::set_config_environment(env)?;
+    }
+
     // Initialize tracing with a default filter if RUST_LOG is not set
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")); // Default to info level for all targets
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(dev_runtime::telemetry::layer())
+        .with(dev_runtime::log_hub::layer())
+        .init();
+    dev_runtime::log_hub::load_config();
 
     info!(target: "galatea::main", "Galatea application starting...");
+    if let Some(endpoint) = dev_runtime::telemetry::otlp_endpoint().filter(|_| dev_runtime::telemetry::is_enabled()) {
+        info!(target: "galatea::main", endpoint = %endpoint, "Exporting traces over OTLP.");
+    }
 
-    let cli = Cli::parse();
+    if !cli.disable_crash_reports {
+        dev_runtime::crash::install_panic_hook();
+    }
+    let crash_reports_enabled = !cli.disable_crash_reports;
 
-    let now_init_env = Instant::now();
-    let project_directory = dev_setup::ensure_development_environment(cli.template.clone(), cli.use_sudo)
+    let result = run(cli).await;
+    dev_runtime::telemetry::shutdown();
+    if let Err(e) = &result {
+        if crash_reports_enabled {
+            match dev_runtime::crash::record_fatal_error(e) {
+                Ok(path) => eprintln!("[CRASH] Fatal error recorded in {}", path.display()),
+                Err(write_err) => eprintln!("[CRASH] Failed to record fatal error: {:?}", write_err),
+            }
+        }
+    }
+    result
+}
+
+// Resolves on Ctrl-C or SIGTERM, letting the server drain and the session end cleanly
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!(target: "galatea::main", "Shutdown signal received.");
+}
+
+fn cors() -> Cors {
+    Cors::new()
+        .allow_credentials(true)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            "Content-Type",
+            "Authorization",
+            galatea::api::mcp_proxy::TOKEN_HEADER,
+            "traceparent",
+            "tracestate",
+            dev_runtime::telemetry::TASK_HEADER,
+            dev_runtime::quotas::SESSION_HEADER,
+            dev_runtime::workspaces::WORKSPACE_HEADER,
+        ])
+        .expose_headers([
+            dev_runtime::limits::WARNING_HEADER,
+            "X-RateLimit-Limit",
+            "X-RateLimit-Remaining",
+            "X-RateLimit-Reset",
+            "Retry-After",
+        ])
+        .allow_origin("*")
+}
+
+// Serves only the setup API until the wizard has set the environment up. Returns the project
+// directory, or `None` if Galatea was asked to stop first.
+async fn run_setup_wizard(host: &str, port: u16, use_sudo: bool) -> Result<Option<PathBuf>> {
+    dev_setup::wizard::begin(use_sudo)?;
+    let setup_api_service = OpenApiService::new(SetupApi, "Setup API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/setup", port));
+    let mcp_api_service = OpenApiService::new(McpApi, "MCP API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/mcp", port));
+    let setup_api_scalar = setup_api_service.scalar();
+    let setup_api_spec = setup_api_service.spec_endpoint();
+    let mcp_api_scalar = mcp_api_service.scalar();
+    let mcp_api_spec = mcp_api_service.spec_endpoint();
+    let app = Route::new()
+        .nest("/api/setup", setup_api_service)
+        .nest("/api/setup/scalar", setup_api_scalar)
+        .at("/api/setup/spec", setup_api_spec)
+        // MCP API; status and controls for the launched MCP servers, which are proxied below
+        .nest("/api/mcp", mcp_api_service)
+        .nest("/api/mcp/scalar", mcp_api_scalar)
+        .at("/api/mcp/spec", mcp_api_spec)
+        .with(cors());
+
+    terminal::port::ensure_port_is_free(port, "Galatea setup wizard")
         .await
-        .map_err(|e| {
-            eprintln!(
-                "[ERROR] Failed to verify and set up project environment (duration: {}ms): {:?}. Server will not start.",
-                now_init_env.elapsed().as_millis(),
-                e
-            );
-            e
-        })?;
+        .context("Failed to ensure Galatea server port was free before serving the setup wizard")?;
+    info!(target: "galatea::main", host, port, "Waiting for setup through the wizard at http://{}:{}/api/setup", host, port);
 
-    // Write CLI arguments to config.toml (after galatea_files is created)
-    if let Some(token) = &cli.token {
-        galatea::dev_setup::config_files::set_config_value("token", token)?;
+    let finished = async {
+        tokio::select! {
+            _ = dev_setup::wizard::wait_until_finished() => {},
+            _ = shutdown_signal() => {},
+        }
+    };
+    Server::new(TcpListener::bind(format!("{}:{}", host, port)))
+        .run_with_graceful_shutdown(app, finished, Some(Duration::from_secs(5)))
+        .await
+        .map_err(|e| anyhow::anyhow!("Setup wizard server error: {}", e))?;
+    Ok(dev_setup::wizard::status().project_dir)
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    if let Some(env) = dev_setup::config_files::config_environment() {
+        info!(target: "galatea::main", env = %env, "Using config overlay config.{}.toml.", env);
+    }
+    // Started first so prewarm and the initial project install go through it too
+    if dev_setup::registry_cache::is_enabled(cli.registry_cache) {
+        if let Err(e) = dev_setup::registry_cache::start().await {
+            tracing::warn!(target: "galatea::main", error = ?e, "Failed to start the registry cache; installs will use the registry directly.");
+        }
+    }
+    if cli.prewarm {
+        let cached = dev_setup::offline::prewarm(cli.template.as_deref(), cli.use_sudo).await?;
+        info!(target: "galatea::main", template = %cached.display(), "Offline caches are ready.");
+        return Ok(());
     }
-    if let Some(template) = &cli.template {
-        galatea::dev_setup::config_files::set_config_value("template", template)?;
+    if cli.offline {
+        dev_setup::offline::set_offline(true);
+        info!(target: "galatea::main", "Offline mode enabled; network operations use local caches or fail fast.");
     }
 
+    let host = "0.0.0.0";
+    let port = 3051;
+
+    let now_init_env = Instant::now();
+    let project_directory = if cli.setup_wizard && !dev_setup::wizard::is_setup_completed() {
+        match run_setup_wizard(host, port, cli.use_sudo).await? {
+            Some(project_directory) => project_directory,
+            None => {
+                info!(target: "galatea::main", "Stopped before the setup wizard finished.");
+                return Ok(());
+            }
+        }
+    } else {
+        let template = cli.template.clone().or_else(|| dev_setup::config_files::get_config_value("template"));
+        let template_vars = dev_setup::template::parse_variable_args(&cli.template_vars)?;
+        let project_directory = dev_setup::ensure_development_environment(template, &template_vars, cli.use_sudo, cli.force_rescaffold)
+            .await
+            .map_err(|e| {
+                eprintln!(
+                    "[ERROR] Failed to verify and set up project environment (duration: {}ms): {:?}. Server will not start.",
+                    now_init_env.elapsed().as_millis(),
+                    e
+                );
+                e
+            })?;
+
+        // Write CLI arguments to config.toml (after galatea_files is created)
+        if let Some(token) = &cli.token {
+            galatea::dev_setup::config_files::set_config_value("token", token)?;
+        }
+        if let Some(template) = &cli.template {
+            galatea::dev_setup::config_files::set_config_value("template", template)?;
+        }
+        project_directory
+    };
+    // Saved by the setup wizard; the flag still forces MCP on
+    let mcp_enabled = cli.mcp_enabled
+        || dev_setup::config_files::get_config_value("mcp_enabled").is_some_and(|v| v == "true");
+
     info!(target: "galatea::main", source_component = "bootstrap", path = %project_directory.display(), duration_ms = now_init_env.elapsed().as_millis(), "Project environment verified and set up successfully.");
 
     info!(target: "galatea::main", "Phase 2: Launching runtime services (Next.js and MCP servers if enabled)...");
 
     // Launch runtime services and get MCP definitions
     let mcp_definitions =
-        dev_runtime::launch_runtime_services(project_directory.clone(), cli.mcp_enabled, cli.use_sudo)
+        dev_runtime::launch_runtime_services(project_directory.clone(), mcp_enabled, cli.use_sudo)
             .await
             .context("Failed to launch runtime services")?;
 
     if !mcp_definitions.is_empty() {
+        // They build and start in the background; proxied requests wait for their server to be ready
         info!(target: "galatea::main", count = mcp_definitions.len(), "MCP servers initiated: {:?}", mcp_definitions);
-        // Give MCP servers time to start up
-        info!(target: "galatea::main", "Waiting 3 seconds for MCP servers to initialize...");
-        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
     }
 
-    let host = "0.0.0.0";
-    let port = 3051;
     let _span = tracing::info_span!(target: "galatea::main", "start_server", host, port).entered();
 
     // --- OpenAPI Services ---
@@ -202,6 +937,36 @@ async fn main() -> Result<()> {
         .server(format!("http://127.0.0.1:{}/api/project", port));
     let editor_api_service = OpenApiService::new(EditorApi, "Editor API", "1.0")
         .server(format!("http://127.0.0.1:{}/api/editor", port));
+    let lsp_api_service = OpenApiService::new(LspApi, "LSP API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/lsp", port));
+    let system_api_service = OpenApiService::new(SystemApi, "System API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/system", port));
+    let runtime_api_service = OpenApiService::new(RuntimeApi, "Runtime API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/runtime", port));
+    let suggestions_api_service = OpenApiService::new(SuggestionsApi, "Suggestions API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/suggestions", port));
+    let codegen_api_service = OpenApiService::new(CodegenApi, "Codegen API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/codegen", port));
+    let refactor_api_service = OpenApiService::new(RefactorApi, "Refactor API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/refactor", port));
+    let jobs_api_service = OpenApiService::new(JobsApi, "Jobs API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/jobs", port));
+    let terminal_api_service = OpenApiService::new(TerminalApi, "Terminal API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/terminal", port));
+    let fs_api_service = OpenApiService::new(FsApi, "File System API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/fs", port));
+    let git_api_service = OpenApiService::new(GitApi, "Git API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/git", port));
+    let code_intel_api_service = OpenApiService::new(CodeIntelApi, "Code Intel API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/code-intel", port));
+    let validation_api_service = OpenApiService::new(ValidationApi, "Validation API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/validation", port));
+    let workspaces_api_service = OpenApiService::new(WorkspacesApi, "Workspaces API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/workspaces", port));
+    let logs_api_service = OpenApiService::new(LogsApi, "Logs API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/logs", port));
+    let setup_api_service = OpenApiService::new(SetupApi, "Setup API", "1.0")
+        .server(format!("http://127.0.0.1:{}/api/setup", port));
 
     // --- Scalar UI & Spec Endpoints ---
     let main_api_scalar = main_api_service.scalar();
@@ -210,6 +975,36 @@ async fn main() -> Result<()> {
     let project_api_spec = project_api_service.spec_endpoint();
     let editor_api_scalar = editor_api_service.scalar();
     let editor_api_spec = editor_api_service.spec_endpoint();
+    let lsp_api_scalar = lsp_api_service.scalar();
+    let lsp_api_spec = lsp_api_service.spec_endpoint();
+    let system_api_scalar = system_api_service.scalar();
+    let system_api_spec = system_api_service.spec_endpoint();
+    let runtime_api_scalar = runtime_api_service.scalar();
+    let runtime_api_spec = runtime_api_service.spec_endpoint();
+    let suggestions_api_scalar = suggestions_api_service.scalar();
+    let suggestions_api_spec = suggestions_api_service.spec_endpoint();
+    let codegen_api_scalar = codegen_api_service.scalar();
+    let codegen_api_spec = codegen_api_service.spec_endpoint();
+    let refactor_api_scalar = refactor_api_service.scalar();
+    let refactor_api_spec = refactor_api_service.spec_endpoint();
+    let jobs_api_scalar = jobs_api_service.scalar();
+    let jobs_api_spec = jobs_api_service.spec_endpoint();
+    let terminal_api_scalar = terminal_api_service.scalar();
+    let terminal_api_spec = terminal_api_service.spec_endpoint();
+    let fs_api_scalar = fs_api_service.scalar();
+    let fs_api_spec = fs_api_service.spec_endpoint();
+    let git_api_scalar = git_api_service.scalar();
+    let git_api_spec = git_api_service.spec_endpoint();
+    let code_intel_api_scalar = code_intel_api_service.scalar();
+    let code_intel_api_spec = code_intel_api_service.spec_endpoint();
+    let validation_api_scalar = validation_api_service.scalar();
+    let validation_api_spec = validation_api_service.spec_endpoint();
+    let workspaces_api_scalar = workspaces_api_service.scalar();
+    let workspaces_api_spec = workspaces_api_service.spec_endpoint();
+    let logs_api_scalar = logs_api_service.scalar();
+    let logs_api_spec = logs_api_service.spec_endpoint();
+    let setup_api_scalar = setup_api_service.scalar();
+    let setup_api_spec = setup_api_service.spec_endpoint();
 
     // --- Route Setup ---
     let mut app = Route::new()
@@ -224,7 +1019,78 @@ async fn main() -> Result<()> {
         // Editor API
         .nest("/api/editor", editor_api_service)
         .nest("/api/editor/scalar", editor_api_scalar)
-        .at("/api/editor/spec", editor_api_spec);
+        .at("/api/editor/spec", editor_api_spec)
+        // LSP API
+        .nest("/api/lsp", lsp_api_service)
+        .nest("/api/lsp/scalar", lsp_api_scalar)
+        .at("/api/lsp/spec", lsp_api_spec)
+        // System API
+        .nest("/api/system", system_api_service)
+        .nest("/api/system/scalar", system_api_scalar)
+        .at("/api/system/spec", system_api_spec)
+        // Runtime API
+        .nest("/api/runtime", runtime_api_service)
+        .nest("/api/runtime/scalar", runtime_api_scalar)
+        .at("/api/runtime/spec", runtime_api_spec)
+        // Suggestions API
+        .nest("/api/suggestions", suggestions_api_service)
+        .nest("/api/suggestions/scalar", suggestions_api_scalar)
+        .at("/api/suggestions/spec", suggestions_api_spec)
+        // Codegen API
+        .nest("/api/codegen", codegen_api_service)
+        .nest("/api/codegen/scalar", codegen_api_scalar)
+        .at("/api/codegen/spec", codegen_api_spec)
+        // Refactor API
+        .nest("/api/refactor", refactor_api_service)
+        .nest("/api/refactor/scalar", refactor_api_scalar)
+        .at("/api/refactor/spec", refactor_api_spec)
+        // Jobs API
+        .nest("/api/jobs", jobs_api_service)
+        .nest("/api/jobs/scalar", jobs_api_scalar)
+        .at("/api/jobs/spec", jobs_api_spec)
+        // Terminal API; interactive sessions attach over a WebSocket outside the OpenAPI service
+        .at("/api/terminal/ws/:session_id", get(terminal_ws_handler))
+        .nest("/api/terminal", terminal_api_service)
+        .nest("/api/terminal/scalar", terminal_api_scalar)
+        .at("/api/terminal/spec", terminal_api_spec)
+        // File System API; change notifications are also pushed over a WebSocket
+        .at("/api/fs/events", get(fs_events_ws_handler))
+        .nest("/api/fs", fs_api_service)
+        .nest("/api/fs/scalar", fs_api_scalar)
+        .at("/api/fs/spec", fs_api_spec)
+        // Git API
+        .nest("/api/git", git_api_service)
+        .nest("/api/git/scalar", git_api_scalar)
+        .at("/api/git/spec", git_api_spec)
+        // Code Intel API
+        .nest("/api/code-intel", code_intel_api_service)
+        .nest("/api/code-intel/scalar", code_intel_api_scalar)
+        .at("/api/code-intel/spec", code_intel_api_spec)
+        // Validation API
+        .nest("/api/validation", validation_api_service)
+        .nest("/api/validation/scalar", validation_api_scalar)
+        .at("/api/validation/spec", validation_api_spec)
+        // Workspaces API
+        .nest("/api/workspaces", workspaces_api_service)
+        .nest("/api/workspaces/scalar", workspaces_api_scalar)
+        .at("/api/workspaces/spec", workspaces_api_spec)
+        // Logs API; the live console also streams over a WebSocket
+        .at("/api/logs/ws", get(logs_ws_handler))
+        .nest("/api/logs", logs_api_service)
+        .nest("/api/logs/scalar", logs_api_scalar)
+        .at("/api/logs/spec", logs_api_spec)
+        // Setup API
+        .nest("/api/setup", setup_api_service)
+        .nest("/api/setup/scalar", setup_api_scalar)
+        .at("/api/setup/spec", setup_api_spec)
+        // HTML reports for validation runs
+        .at("/reports/:run_id", validation_report)
+        // Next.js dev server, gated on readiness
+        .at("/preview", dev_server_proxy)
+        .at("/preview/*", dev_server_proxy)
+        .at("/_next/*", dev_server_proxy)
+        .at("/__nextjs_original-stack-frame", dev_server_proxy)
+        .at("/__nextjs_source-map", dev_server_proxy);
 
     // Add MCP proxy routes dynamically based on definitions
     for mcp_def in &mcp_definitions {
@@ -236,13 +1102,7 @@ async fn main() -> Result<()> {
     }
 
     // Build final app with data and middleware
-    let app = app.data(mcp_definitions).with(
-        Cors::new()
-            .allow_credentials(true)
-            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::OPTIONS])
-            .allow_headers(["Content-Type", "Authorization"])
-            .allow_origin("*"),
-    );
+    let app = app.data(mcp_definitions).data(dev_runtime::lsp_pool::shared()).around(quota_scope).around(limit_notifications).around(request_tracing).around(audit_log).around(workspace_scope).around(api_auth).around(body_limit).with(cors());
 
     terminal::port::ensure_port_is_free(port, "Galatea main server (pre-bind check)")
         .await
@@ -251,10 +1111,11 @@ async fn main() -> Result<()> {
     info!(target: "galatea::main", source_component = "server_startup", host, port, "Starting Galatea server with OpenAPI documentation at http://{}:{}/", host, port);
 
     Server::new(TcpListener::bind(format!("{}:{}", host, port)))
-        .run(app)
+        .run_with_graceful_shutdown(app, shutdown_signal(), Some(Duration::from_secs(5)))
         .await
         .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
 
+    dev_runtime::recovery::mark_clean_shutdown();
     info!(target: "galatea::main", "Galatea application shutdown.");
     Ok(())
 }
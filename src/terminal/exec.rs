@@ -0,0 +1,226 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+
+use crate::dev_setup::config_files;
+
+// config.toml table holding the whitelist and limits, e.g. `[terminal_exec]`
+const CONFIG_SECTION: &str = "terminal_exec";
+
+// Variables that would change which binary a whitelisted name runs, or inject code into it:
+// preloaded libraries, `node --require`, git's ssh/editor/pager commands, npm's script shell
+// and the startup files of non-interactive shells. Matched case-insensitively.
+const PROTECTED_ENV_PREFIXES: &[&str] = &["PATH", "LD_", "DYLD_", "GIT_", "NPM_CONFIG_"];
+const PROTECTED_ENV_NAMES: &[&str] = &["NODE_OPTIONS", "BASH_ENV", "ENV"];
+
+/// Commands `/api/terminal/exec` may run, from `[terminal_exec]` in config.toml.
+///
+/// ```toml
+/// [terminal_exec]
+/// allowed_commands = ["git", "node", "pnpm", "npx"]
+/// default_timeout_secs = 60
+/// max_timeout_secs = 600
+/// max_output_bytes = 1048576
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ExecConfig {
+    /// Program names that may be run; looked up on `PATH`
+    pub allowed_commands: Vec<String>,
+    pub default_timeout_secs: u64,
+    /// Upper bound for a request's own timeout
+    pub max_timeout_secs: u64,
+    /// Most bytes kept of each of stdout and stderr; the rest is cut from the front
+    pub max_output_bytes: usize,
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        Self {
+            allowed_commands: ["git", "node", "npm", "npx", "pnpm", "yarn", "bun", "tsc", "prisma"].iter().map(|c| c.to_string()).collect(),
+            default_timeout_secs: 60,
+            max_timeout_secs: 600,
+            max_output_bytes: 1024 * 1024,
+        }
+    }
+}
+
+impl ExecConfig {
+    /// Loads the whitelist from config.toml.
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+
+    /// Whether `program` is a bare name on the whitelist. Paths are never allowed, so a
+    /// whitelisted name can't be swapped for a binary elsewhere.
+    pub fn is_allowed(&self, program: &str) -> bool {
+        !program.contains(['/', '\\']) && self.allowed_commands.iter().any(|c| c == program)
+    }
+}
+
+/// Why a command was refused before it ran.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecRejection {
+    NotAllowed(String),
+    ProtectedEnv(String),
+}
+
+impl std::fmt::Display for ExecRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecRejection::NotAllowed(program) => {
+                write!(f, "'{}' is not in the allowed commands (allowed_commands under [terminal_exec] in config.toml)", program)
+            }
+            ExecRejection::ProtectedEnv(key) => write!(f, "Environment variable {} may not be set", key),
+        }
+    }
+}
+
+/// A command to run, already resolved to a directory inside the project.
+#[derive(Debug, Clone)]
+pub struct ExecRequest {
+    pub program: String,
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    /// Merged into the server's environment
+    pub env: HashMap<String, String>,
+    /// Capped at `max_timeout_secs`; `None` uses `default_timeout_secs`
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` when the process was killed, by the timeout or a signal
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    /// Whether stdout or stderr was cut to `max_output_bytes`
+    pub truncated: bool,
+    pub duration_ms: u64,
+}
+
+/// Checks a request against the whitelist and the protected environment variables.
+pub fn validate(config: &ExecConfig, req: &ExecRequest) -> Result<(), ExecRejection> {
+    if !config.is_allowed(&req.program) {
+        return Err(ExecRejection::NotAllowed(req.program.clone()));
+    }
+    let protected = |key: &str| {
+        let key = key.to_uppercase();
+        PROTECTED_ENV_PREFIXES.iter().any(|p| key.starts_with(p)) || PROTECTED_ENV_NAMES.contains(&key.as_str())
+    };
+    if let Some(key) = req.env.keys().find(|k| protected(k)) {
+        return Err(ExecRejection::ProtectedEnv(key.clone()));
+    }
+    Ok(())
+}
+
+// Reads everything, keeping only the last `max_bytes`
+async fn read_tail<R: AsyncRead + Unpin>(mut reader: R, max_bytes: usize) -> (Vec<u8>, bool) {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut chunk = [0u8; 8192];
+    while let Ok(n) = reader.read(&mut chunk).await {
+        if n == 0 {
+            break;
+        }
+        kept.extend_from_slice(&chunk[..n]);
+        if kept.len() > max_bytes {
+            kept.drain(..kept.len() - max_bytes);
+            truncated = true;
+        }
+    }
+    (kept, truncated)
+}
+
+/// Runs a validated request without a shell and captures its output. On timeout the command
+/// and everything it started are killed, and the output read so far is returned.
+pub async fn run(config: &ExecConfig, req: &ExecRequest) -> Result<ExecOutput> {
+    let timeout = Duration::from_secs(req.timeout_secs.unwrap_or(config.default_timeout_secs).clamp(1, config.max_timeout_secs.max(1)));
+    let mut cmd = Command::new(&req.program);
+    cmd.args(&req.args)
+        .current_dir(&req.working_dir)
+        .envs(&req.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    tracing::debug!(target: "terminal::exec", command = %req.program, args = ?req.args, cwd = %req.working_dir.display(), "Running command.");
+    let started = Instant::now();
+    let mut child = cmd.spawn().with_context(|| format!("terminal::exec: Failed to spawn {}", req.program))?;
+    let pid = child.id();
+    let stdout = child.stdout.take().context("terminal::exec: Failed to capture stdout")?;
+    let stderr = child.stderr.take().context("terminal::exec: Failed to capture stderr")?;
+    let stdout = tokio::spawn(read_tail(stdout, config.max_output_bytes));
+    let stderr = tokio::spawn(read_tail(stderr, config.max_output_bytes));
+
+    let (exit_code, timed_out) = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => (status.with_context(|| format!("terminal::exec: Failed to wait for {}", req.program))?.code(), false),
+        Err(_) => {
+            tracing::warn!(target: "terminal::exec", command = %req.program, timeout_secs = timeout.as_secs(), "Command timed out, killing it.");
+            #[cfg(unix)]
+            if let Some(pid) = pid {
+                super::stream::signal_process_group(pid, libc::SIGKILL);
+            }
+            let _ = child.kill().await;
+            (None, true)
+        }
+    };
+    // The pipes close once every process holding them is gone
+    let (stdout, stdout_truncated) = stdout.await.unwrap_or_default();
+    let (stderr, stderr_truncated) = stderr.await.unwrap_or_default();
+
+    Ok(ExecOutput {
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        exit_code,
+        timed_out,
+        truncated: stdout_truncated || stderr_truncated,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(program: &str, args: &[&str], env: &[(&str, &str)], timeout_secs: Option<u64>) -> ExecRequest {
+        ExecRequest {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            working_dir: std::env::temp_dir(),
+            env: env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            timeout_secs,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_whitelist_output_and_timeout() {
+        let config = ExecConfig { allowed_commands: vec!["sh".to_string()], max_output_bytes: 8, ..ExecConfig::default() };
+        assert_eq!(validate(&config, &request("git", &[], &[], None)), Err(ExecRejection::NotAllowed("git".to_string())));
+        assert_eq!(validate(&config, &request("/bin/sh", &[], &[], None)), Err(ExecRejection::NotAllowed("/bin/sh".to_string())));
+        assert_eq!(validate(&config, &request("sh", &[], &[("Path", "/tmp")], None)), Err(ExecRejection::ProtectedEnv("Path".to_string())));
+        for key in ["NODE_OPTIONS", "GIT_SSH_COMMAND", "npm_config_script_shell", "BASH_ENV", "ENV"] {
+            assert_eq!(validate(&config, &request("sh", &[], &[(key, "x")], None)), Err(ExecRejection::ProtectedEnv(key.to_string())));
+        }
+        assert!(validate(&config, &request("sh", &[], &[("ENVIRONMENT", "test")], None)).is_ok());
+
+        let req = request("sh", &["-c", "echo $GREETING; echo 0123456789 >&2; exit 2"], &[("GREETING", "hi")], None);
+        assert!(validate(&config, &req).is_ok());
+        let output = run(&config, &req).await.unwrap();
+        assert_eq!((output.stdout.as_str(), output.stderr.as_str()), ("hi\n", "3456789\n"));
+        assert_eq!((output.exit_code, output.timed_out, output.truncated), (Some(2), false, true));
+
+        // The backgrounded sleep holds the pipes open, so this only returns if the whole group is killed
+        let output = run(&config, &request("sh", &["-c", "sleep 30 & echo started; wait"], &[], Some(1))).await.unwrap();
+        assert_eq!((output.stdout.as_str(), output.exit_code, output.timed_out), ("started\n", None, true));
+    }
+}
@@ -6,6 +6,7 @@ use tracing;
 use tokio::io::{AsyncBufReadExt, BufReader};
 
 /// Runs a git command in the specified directory
+#[tracing::instrument(name = "process.run", skip_all, fields(process.command = "git", process.command_args = ?args))]
 pub async fn run_git_command(project_dir: &Path, args: &[&str], suppress_output: bool) -> Result<()> {
     let mut cmd = Command::new("git");
     cmd.current_dir(project_dir);
@@ -92,6 +93,35 @@ pub async fn run_git_command(project_dir: &Path, args: &[&str], suppress_output:
     }
 }
 
+/// Runs a git command in the specified directory and returns its stdout
+pub async fn git_output(project_dir: &Path, args: &[&str]) -> Result<String> {
+    tracing::debug!(target: "terminal::git", command = format!("git {}", args.join(" ")), cwd = %project_dir.display(), "Running git command for output");
+
+    let output = Command::new("git")
+        .current_dir(project_dir)
+        .args(args)
+        .output()
+        .await
+        .with_context(|| {
+            format!(
+                "terminal::git: Failed to spawn git command (git {}). Ensure git is installed and in PATH.",
+                args.join(" ")
+            )
+        })?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr_text = String::from_utf8_lossy(&output.stderr);
+        Err(anyhow!(
+            "terminal::git: git command failed with status: {}.\nCommand: git {}\nStderr: {}",
+            output.status,
+            args.join(" "),
+            stderr_text
+        ))
+    }
+}
+
 /// Clone a git repository to the specified directory
 pub async fn clone_repository(repo_url: &str, target_dir: &Path) -> Result<()> {
     tracing::info!(target: "terminal::git", repo_url = repo_url, target_dir = %target_dir.display(), "Cloning git repository");
@@ -100,7 +130,7 @@ pub async fn clone_repository(repo_url: &str, target_dir: &Path) -> Result<()> {
     let parent_dir = target_dir.parent().unwrap_or_else(|| Path::new("."));
     
     // Clone the repository
-    run_git_command(parent_dir, &["clone", "--verbose", "--progress", repo_url, &target_dir.file_name().unwrap().to_string_lossy()], false).await
+    run_git_command(parent_dir, &["clone", "--verbose", "--progress", "--", repo_url, &target_dir.file_name().unwrap().to_string_lossy()], false).await
         .context(format!("Failed to clone repository {} to {}", repo_url, target_dir.display()))?;
     
     tracing::info!(target: "terminal::git", repo_url = repo_url, target_dir = %target_dir.display(), "Repository cloned successfully");
@@ -1,5 +1,7 @@
-pub mod npm;
 pub mod port;
 pub mod nvm;
 pub mod git;
-pub mod pnpm; 
\ No newline at end of file
+pub mod package_manager;
+pub mod stream;
+pub mod exec;
+pub mod session;
@@ -0,0 +1,253 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::process::{Output, Stdio};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+use crate::dev_setup::{config_files, offline};
+use crate::file_system::paths;
+
+// config.toml key that overrides detection
+const CONFIG_KEY: &str = "package_manager";
+
+/// A Node.js package manager the project can be installed and run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Pnpm,
+    Npm,
+    Yarn,
+    Bun,
+}
+
+/// Every supported package manager, the default first.
+pub const ALL: [PackageManager; 4] = [PackageManager::Pnpm, PackageManager::Npm, PackageManager::Yarn, PackageManager::Bun];
+
+impl PackageManager {
+    /// The name it is configured by, which is also its executable.
+    pub fn name(self) -> &'static str {
+        match self {
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Npm => "npm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Bun => "bun",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        ALL.into_iter().find(|pm| pm.name() == name.trim())
+    }
+
+    /// Lockfiles it writes, newest format first.
+    pub fn lockfiles(self) -> &'static [&'static str] {
+        match self {
+            PackageManager::Pnpm => &["pnpm-lock.yaml"],
+            PackageManager::Npm => &["package-lock.json"],
+            PackageManager::Yarn => &["yarn.lock"],
+            PackageManager::Bun => &["bun.lock", "bun.lockb"],
+        }
+    }
+
+    /// The package manager whose lockfile is in `dir`, if any.
+    pub fn from_lockfile(dir: &Path) -> Option<Self> {
+        ALL.into_iter().find(|pm| pm.lockfiles().iter().any(|lockfile| dir.join(lockfile).exists()))
+    }
+
+    /// The one set by `package_manager` in config.toml, if any.
+    pub fn configured() -> Option<Self> {
+        let name = config_files::get_config_value(CONFIG_KEY)?;
+        let configured = Self::parse(&name);
+        if configured.is_none() {
+            tracing::warn!(target: "terminal::package_manager", name = %name, "Unsupported package_manager in config.toml, ignoring it.");
+        }
+        configured
+    }
+
+    /// The package manager for the project in `dir`: the one set in config.toml, otherwise the
+    /// one whose lockfile the project has, otherwise pnpm.
+    pub fn detect(dir: &Path) -> Self {
+        Self::configured().or_else(|| Self::from_lockfile(dir)).unwrap_or(PackageManager::Pnpm)
+    }
+
+    /// The package manager for the project Galatea serves.
+    pub fn current() -> Self {
+        match paths::project_dir() {
+            Ok(dir) => Self::detect(&dir),
+            Err(_) => Self::configured().unwrap_or(PackageManager::Pnpm),
+        }
+    }
+
+    /// Arguments that install the project's dependencies, through the registry cache or
+    /// offline when Galatea is.
+    pub fn install_args(self) -> Vec<&'static str> {
+        let mut args = vec!["install"];
+        args.extend(offline::registry_args());
+        args
+    }
+
+    /// Arguments that add packages, as `devDependencies` with `dev`. Followed by the packages,
+    /// then `offline::registry_args`.
+    pub fn add_args(self, dev: bool) -> Vec<&'static str> {
+        let mut args = match self {
+            PackageManager::Npm => vec!["install"],
+            _ => vec!["add"],
+        };
+        if dev {
+            args.push(match self {
+                PackageManager::Npm | PackageManager::Pnpm => "--save-dev",
+                PackageManager::Yarn | PackageManager::Bun => "--dev",
+            });
+        }
+        args
+    }
+
+    pub fn remove_args(self) -> Vec<&'static str> {
+        match self {
+            PackageManager::Npm => vec!["uninstall"],
+            _ => vec!["remove"],
+        }
+    }
+
+    /// Arguments that upgrade packages within their declared ranges, or across them with
+    /// `latest`. npm has no such flag; `name@latest` has to be added instead.
+    pub fn update_args(self, latest: bool) -> Vec<&'static str> {
+        let mut args = match self {
+            PackageManager::Yarn => vec!["upgrade"],
+            _ => vec!["update"],
+        };
+        if latest && self != PackageManager::Npm {
+            args.push("--latest");
+        }
+        args
+    }
+
+    /// Arguments that list outdated packages as a JSON object keyed by package name, for the
+    /// package managers that print one.
+    pub fn outdated_args(self) -> Option<Vec<&'static str>> {
+        match self {
+            PackageManager::Pnpm => Some(vec!["outdated", "--format", "json"]),
+            PackageManager::Npm => Some(vec!["outdated", "--json"]),
+            PackageManager::Yarn | PackageManager::Bun => None,
+        }
+    }
+
+    /// Command that runs a package.json script, passing `args` on to it.
+    pub fn script_command(self, script: &str, args: &[&str]) -> Vec<String> {
+        let mut command = vec![self.name(), "run", script];
+        // npm only forwards arguments to the script after `--`; the others forward them as is
+        if self == PackageManager::Npm && !args.is_empty() {
+            command.push("--");
+        }
+        command.extend(args);
+        command.into_iter().map(str::to_string).collect()
+    }
+
+    /// Command that runs a binary installed in the project's node_modules.
+    pub fn exec_command(self, bin: &str, args: &[&str]) -> Vec<String> {
+        let prefix: &[&str] = match self {
+            PackageManager::Pnpm => &["pnpm", "exec"],
+            PackageManager::Npm => &["npm", "exec", "--"],
+            PackageManager::Yarn => &["yarn", "run"],
+            PackageManager::Bun => &["bun", "x"],
+        };
+        prefix.iter().copied().chain([bin]).chain(args.iter().copied()).map(str::to_string).collect()
+    }
+
+    /// A command for `exec_command`, to configure further before running it.
+    pub fn exec(self, dir: &Path, bin: &str) -> Command {
+        let command = self.exec_command(bin, &[]);
+        let mut cmd = Command::new(&command[0]);
+        cmd.current_dir(dir).args(&command[1..]);
+        cmd
+    }
+
+    /// Runs the package manager in `dir` and captures its output, whether or not it succeeds.
+    pub async fn output(self, dir: &Path, args: &[&str]) -> Result<Output> {
+        Command::new(self.name())
+            .current_dir(dir)
+            .args(args)
+            .output()
+            .await
+            .with_context(|| format!("Failed to run {} {}. Ensure {} is installed and in PATH.", self.name(), args.join(" "), self.name()))
+    }
+
+    /// Runs the package manager in `dir`, logging its output as it prints it, and fails when it
+    /// exits with an error.
+    #[tracing::instrument(name = "process.run", skip_all, fields(process.command = self.name(), process.command_args = ?args))]
+    pub async fn run(self, dir: &Path, args: &[&str], use_sudo: bool) -> Result<()> {
+        let command_line = format!("{}{} {}", if use_sudo { "sudo " } else { "" }, self.name(), args.join(" "));
+        let mut cmd = if use_sudo {
+            let mut cmd = Command::new("sudo");
+            cmd.arg(self.name());
+            cmd
+        } else {
+            Command::new(self.name())
+        };
+        cmd.current_dir(dir).args(args).stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true);
+
+        tracing::debug!(target: "terminal::package_manager", command = %command_line, cwd = %dir.display(), "Spawning package manager command");
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to spawn {}. Ensure {} is installed and in PATH.", command_line, self.name()))?;
+
+        let stdout = child.stdout.take().context("Failed to capture stdout from the package manager")?;
+        let stderr = child.stderr.take().context("Failed to capture stderr from the package manager")?;
+        let stdout_task = tokio::spawn(async move {
+            let mut reader = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = reader.next_line().await {
+                tracing::info!(target: "terminal::package_manager::stdout", "{}", line);
+            }
+        });
+        // Kept for the error, where package managers explain what went wrong
+        let stderr_task = tokio::spawn(async move {
+            let mut reader = BufReader::new(stderr).lines();
+            let mut lines = Vec::new();
+            while let Ok(Some(line)) = reader.next_line().await {
+                tracing::warn!(target: "terminal::package_manager::stderr", "{}", line);
+                lines.push(line);
+            }
+            lines
+        });
+
+        let status = child.wait().await.with_context(|| format!("Failed to wait for {}", command_line))?;
+        let _ = stdout_task.await;
+        let stderr_lines = stderr_task.await.unwrap_or_default();
+        if status.success() {
+            return Ok(());
+        }
+        tracing::error!(target: "terminal::package_manager", command = %command_line, status = %status, "Package manager command failed");
+        Err(anyhow!("{} failed with status: {}\nStderr: {}", command_line, status, stderr_lines.join("\n")))
+    }
+
+    /// Installs the dependencies of the project in `dir`.
+    pub async fn install(self, dir: &Path, use_sudo: bool) -> Result<()> {
+        self.run(dir, &self.install_args(), use_sudo).await
+    }
+
+    /// Runs a package.json script in `dir` to completion.
+    pub async fn run_script(self, dir: &Path, script: &str, use_sudo: bool) -> Result<()> {
+        self.run(dir, &["run", script], use_sudo).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockfile_detection_and_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(PackageManager::from_lockfile(dir.path()), None);
+        std::fs::write(dir.path().join("yarn.lock"), "").unwrap();
+        assert_eq!(PackageManager::from_lockfile(dir.path()), Some(PackageManager::Yarn));
+        assert_eq!(PackageManager::parse("bun"), Some(PackageManager::Bun));
+        assert_eq!(PackageManager::parse("deno"), None);
+
+        assert_eq!(PackageManager::Npm.script_command("dev", &["--port", "3000"]), ["npm", "run", "dev", "--", "--port", "3000"]);
+        assert_eq!(PackageManager::Pnpm.script_command("dev", &["--port", "3000"]), ["pnpm", "run", "dev", "--port", "3000"]);
+        assert_eq!(PackageManager::Yarn.exec_command("tsc", &["--noEmit"]), ["yarn", "run", "tsc", "--noEmit"]);
+        assert_eq!(PackageManager::Npm.add_args(true), ["install", "--save-dev"]);
+        assert_eq!(PackageManager::Bun.add_args(true), ["add", "--dev"]);
+        assert_eq!(PackageManager::Yarn.update_args(true), ["upgrade", "--latest"]);
+        assert_eq!(PackageManager::Npm.update_args(true), ["update"]);
+    }
+}
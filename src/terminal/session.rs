@@ -0,0 +1,388 @@
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::dev_runtime::util::now_secs;
+use crate::dev_runtime::{db, events};
+use crate::dev_setup::config_files;
+use crate::file_system::paths;
+
+// config.toml table holding the session settings, e.g. `[terminal_sessions]`
+const CONFIG_SECTION: &str = "terminal_sessions";
+
+// Output chunks buffered for an attached client that falls behind; it skips ahead after that
+const OUTPUT_CHANNEL_CAPACITY: usize = 1024;
+
+// Time a killed shell gets to exit after SIGHUP before its process group is killed
+const KILL_GRACE: Duration = Duration::from_secs(2);
+
+/// Settings for interactive terminal sessions, from `[terminal_sessions]` in config.toml.
+///
+/// ```toml
+/// [terminal_sessions]
+/// enabled = true
+/// shell = "/bin/bash"         # defaults to $SHELL, then /bin/sh
+/// max_sessions = 8
+/// scrollback_bytes = 262144
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub enabled: bool,
+    pub shell: Option<String>,
+    /// Sessions alive at once; creating another fails until one is killed or exits
+    pub max_sessions: usize,
+    /// Output kept per session and replayed to clients that attach
+    pub scrollback_bytes: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self { enabled: true, shell: None, max_sessions: 8, scrollback_bytes: 256 * 1024 }
+    }
+}
+
+impl SessionConfig {
+    pub fn load() -> Self {
+        config_files::load_section(CONFIG_SECTION)
+    }
+
+    fn shell(&self) -> String {
+        self.shell
+            .clone()
+            .or_else(|| std::env::var("SHELL").ok())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "/bin/sh".to_string())
+    }
+}
+
+/// What an attached client receives.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionOutput {
+    /// Raw bytes the terminal printed, including escape sequences
+    Data(Vec<u8>),
+    /// The shell exited; always the last message. `None` when it was killed by a signal.
+    Exit(Option<i32>),
+}
+
+/// A shell running on its own pseudo-terminal, kept alive across connections.
+pub struct TerminalSession {
+    pub id: String,
+    pub shell: String,
+    pub created_at: u64,
+    pid: u32,
+    master: Mutex<File>,
+    child: Mutex<Child>,
+    size: Mutex<(u16, u16)>,
+    // Output so far, capped at `scrollback_bytes`, and whether the shell has exited
+    scrollback: Mutex<(Vec<u8>, Option<Option<i32>>)>,
+    scrollback_bytes: usize,
+    output: broadcast::Sender<SessionOutput>,
+}
+
+/// Output replayed to a client as it attaches, and the live output after it.
+pub struct Attachment {
+    pub scrollback: Vec<u8>,
+    /// `Some` if the shell has already exited, with its exit code
+    pub exited: Option<Option<i32>>,
+    pub output: broadcast::Receiver<SessionOutput>,
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<String, Arc<TerminalSession>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn sessions() -> std::sync::MutexGuard<'static, HashMap<String, Arc<TerminalSession>>> {
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Mirrors the session into the metadata store as a job, so a restart marks it interrupted
+fn record_session(id: &str, status: &str, shell: &str) {
+    let (id, status, shell) = (id.to_string(), status.to_string(), shell.to_string());
+    db::write_later(move |db| {
+        if let Err(e) = db.upsert_job(events::session_id(), &id, "terminal", &status, Some(&shell)) {
+            tracing::debug!(target: "terminal::session", error = ?e, "Failed to record terminal session.");
+        }
+    });
+}
+
+#[cfg(unix)]
+mod pty {
+    use anyhow::{bail, Context, Result};
+    use std::fs::File;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
+    use std::process::{Child, Command, Stdio};
+
+    fn winsize(cols: u16, rows: u16) -> libc::winsize {
+        libc::winsize { ws_row: rows, ws_col: cols, ws_xpixel: 0, ws_ypixel: 0 }
+    }
+
+    /// Starts `shell` on a new pseudo-terminal and returns the master side and the child.
+    pub fn spawn(shell: &str, cwd: &Path, cols: u16, rows: u16) -> Result<(File, Child)> {
+        let (mut master, mut slave) = (0, 0);
+        let size = winsize(cols, rows);
+        // SAFETY: openpty writes two fds into the given pointers and reads the winsize
+        if unsafe { libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size) } != 0 {
+            bail!("terminal::session: Failed to allocate a pseudo-terminal: {}", std::io::Error::last_os_error());
+        }
+        // SAFETY: openpty succeeded, so both fds are open and owned by nobody else
+        let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+        // Keeps the shell and other children from inheriting them; the shell gets the slave as 0, 1 and 2
+        for fd in [&master, &slave] {
+            // SAFETY: F_SETFD on an open fd
+            unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+        }
+
+        let mut cmd = Command::new(shell);
+        cmd.current_dir(cwd)
+            .env("TERM", "xterm-256color")
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+        // SAFETY: only async-signal-safe calls between fork and exec
+        unsafe {
+            cmd.pre_exec(|| {
+                // A new session with the terminal as its controlling terminal, so job control and ^C work
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = cmd.spawn().with_context(|| format!("terminal::session: Failed to spawn {}", shell))?;
+        Ok((File::from(master), child))
+    }
+
+    pub fn resize(master: &File, cols: u16, rows: u16) -> Result<()> {
+        let size = winsize(cols, rows);
+        // SAFETY: TIOCSWINSZ only reads the winsize
+        if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ as _, &size) } == -1 {
+            bail!("terminal::session: Failed to resize the terminal: {}", std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+mod pty {
+    use anyhow::{bail, Result};
+    use std::fs::File;
+    use std::path::Path;
+    use std::process::Child;
+
+    pub fn spawn(_shell: &str, _cwd: &Path, _cols: u16, _rows: u16) -> Result<(File, Child)> {
+        bail!("terminal::session: Terminal sessions need a Unix host")
+    }
+
+    pub fn resize(_master: &File, _cols: u16, _rows: u16) -> Result<()> {
+        bail!("terminal::session: Terminal sessions need a Unix host")
+    }
+}
+
+impl TerminalSession {
+    pub fn size(&self) -> (u16, u16) {
+        *self.size.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.scrollback.lock().unwrap_or_else(|e| e.into_inner()).1.is_some()
+    }
+
+    /// Replays the output so far and subscribes to what follows, without gaps or repeats.
+    pub fn attach(&self) -> Attachment {
+        let scrollback = self.scrollback.lock().unwrap_or_else(|e| e.into_inner());
+        Attachment { scrollback: scrollback.0.clone(), exited: scrollback.1, output: self.output.subscribe() }
+    }
+
+    /// Writes keystrokes (or pasted text) to the terminal. May block while the shell isn't
+    /// reading, so call it off the async runtime.
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        if self.has_exited() {
+            bail!("terminal::session: Session {} has exited", self.id);
+        }
+        let mut master = self.master.lock().unwrap_or_else(|e| e.into_inner());
+        master.write_all(data).and_then(|_| master.flush()).context("terminal::session: Failed to write to the terminal")
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        if cols == 0 || rows == 0 {
+            bail!("terminal::session: Terminal size must be at least 1x1");
+        }
+        pty::resize(&self.master.lock().unwrap_or_else(|e| e.into_inner()), cols, rows)?;
+        *self.size.lock().unwrap_or_else(|e| e.into_inner()) = (cols, rows);
+        Ok(())
+    }
+
+    fn push_output(&self, data: &[u8]) {
+        let mut scrollback = self.scrollback.lock().unwrap_or_else(|e| e.into_inner());
+        scrollback.0.extend_from_slice(data);
+        if scrollback.0.len() > self.scrollback_bytes {
+            let excess = scrollback.0.len() - self.scrollback_bytes;
+            scrollback.0.drain(..excess);
+        }
+        // Sent under the lock, so `attach` sees each chunk either in the scrollback or live
+        let _ = self.output.send(SessionOutput::Data(data.to_vec()));
+    }
+
+    fn finish(&self, code: Option<i32>) {
+        let mut scrollback = self.scrollback.lock().unwrap_or_else(|e| e.into_inner());
+        scrollback.1 = Some(code);
+        let _ = self.output.send(SessionOutput::Exit(code));
+    }
+
+    // Hangs up on the shell, then kills its process group if it is still around
+    fn terminate(&self) {
+        #[cfg(unix)]
+        {
+            super::stream::signal_process_group(self.pid, libc::SIGHUP);
+            let session = self.id.clone();
+            let pid = self.pid;
+            std::thread::spawn(move || {
+                std::thread::sleep(KILL_GRACE);
+                if get(&session).is_some_and(|s| !s.has_exited()) {
+                    super::stream::signal_process_group(pid, libc::SIGKILL);
+                }
+            });
+        }
+        #[cfg(not(unix))]
+        let _ = self.child.lock().unwrap_or_else(|e| e.into_inner()).kill();
+    }
+}
+
+// Copies terminal output into the session until the shell closes the terminal, then reaps it
+fn read_output(session: Arc<TerminalSession>, mut reader: File) {
+    let mut buffer = [0u8; 8192];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => session.push_output(&buffer[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            // EIO once every process holding the terminal has exited
+            Err(_) => break,
+        }
+    }
+    let code = match session.child.lock().unwrap_or_else(|e| e.into_inner()).wait() {
+        Ok(status) => status.code(),
+        Err(e) => {
+            tracing::warn!(target: "terminal::session", session = %session.id, error = %e, "Failed to wait for the shell.");
+            None
+        }
+    };
+    session.finish(code);
+    sessions().remove(&session.id);
+    record_session(&session.id, if code == Some(0) { "succeeded" } else { "failed" }, &session.shell);
+    tracing::info!(target: "terminal::session", session = %session.id, code = ?code, "Terminal session exited.");
+}
+
+/// Starts a shell in `cwd` on a new `cols` x `rows` terminal, registered under `id`.
+pub fn create(config: &SessionConfig, id: &str, cwd: &Path, cols: u16, rows: u16) -> Result<Arc<TerminalSession>> {
+    if !config.enabled {
+        bail!("Terminal sessions are disabled (enabled under [terminal_sessions] in config.toml)");
+    }
+    paths::validate_id(id, "session")?;
+    let mut sessions = sessions();
+    if sessions.contains_key(id) {
+        bail!("Terminal session {} already exists", id);
+    }
+    if sessions.len() >= config.max_sessions {
+        bail!("Too many terminal sessions ({}); kill one first", config.max_sessions);
+    }
+
+    let shell = config.shell();
+    let (cols, rows) = (cols.max(1), rows.max(1));
+    let (master, child) = pty::spawn(&shell, cwd, cols, rows)?;
+    let reader = master.try_clone().context("terminal::session: Failed to clone the terminal")?;
+    let session = Arc::new(TerminalSession {
+        id: id.to_string(),
+        shell: shell.clone(),
+        created_at: now_secs(),
+        pid: child.id(),
+        master: Mutex::new(master),
+        child: Mutex::new(child),
+        size: Mutex::new((cols, rows)),
+        scrollback: Mutex::new((Vec::new(), None)),
+        scrollback_bytes: config.scrollback_bytes,
+        output: broadcast::channel(OUTPUT_CHANNEL_CAPACITY).0,
+    });
+    sessions.insert(id.to_string(), session.clone());
+    drop(sessions);
+
+    let reading = session.clone();
+    std::thread::Builder::new()
+        .name(format!("terminal-{}", id))
+        .spawn(move || read_output(reading, reader))
+        .map_err(|e| anyhow!("terminal::session: Failed to start the output reader: {}", e))?;
+    record_session(id, "running", &shell);
+    tracing::info!(target: "terminal::session", session = %id, shell = %shell, pid = session.pid, "Started terminal session.");
+    Ok(session)
+}
+
+pub fn get(id: &str) -> Option<Arc<TerminalSession>> {
+    sessions().get(id).cloned()
+}
+
+/// Live sessions, oldest first.
+pub fn list() -> Vec<Arc<TerminalSession>> {
+    let mut sessions: Vec<_> = sessions().values().cloned().collect();
+    sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+    sessions
+}
+
+/// Kills a session's shell and everything running in it. Returns whether the session existed;
+/// it is removed once the shell has exited.
+pub fn kill(id: &str) -> bool {
+    match get(id) {
+        Some(session) => {
+            tracing::info!(target: "terminal::session", session = %id, "Killing terminal session.");
+            session.terminate();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_runs_commands_and_is_killed() {
+        let config = SessionConfig { shell: Some("/bin/sh".to_string()), ..SessionConfig::default() };
+        let id = format!("test-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+        let session = create(&config, &id, &std::env::temp_dir(), 80, 24).unwrap();
+        assert!(create(&config, &id, &std::env::temp_dir(), 80, 24).is_err());
+        let err = create(&config, "bad id", &std::env::temp_dir(), 80, 24).err().unwrap();
+        assert_eq!(err.to_string(), "Invalid session id 'bad id'");
+
+        session.resize(100, 30).unwrap();
+        session.write(b"stty size; echo done-$((40 + 2))\n").unwrap();
+        let Attachment { scrollback, mut output, .. } = session.attach();
+        let mut printed = String::from_utf8_lossy(&scrollback).into_owned();
+        while !printed.contains("done-42") {
+            match tokio::time::timeout(Duration::from_secs(5), output.recv()).await {
+                Ok(Ok(SessionOutput::Data(data))) => printed.push_str(&String::from_utf8_lossy(&data)),
+                other => panic!("no output before {:?}: {}", other, printed),
+            }
+        }
+        assert!(printed.contains("30 100"), "{}", printed);
+
+        assert!(kill(&id));
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), output.recv()).await {
+                Ok(Ok(SessionOutput::Exit(_))) => break,
+                Ok(Ok(SessionOutput::Data(_))) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                other => panic!("shell didn't exit: {:?}", other),
+            }
+        }
+        assert!(session.has_exited() && session.write(b"x").is_err());
+        assert!(!kill("missing"));
+    }
+}
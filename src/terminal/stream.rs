@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+// Lines buffered between the process and a slow consumer before reading pauses
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Output of a process run by `spawn_streaming`, in the order it was read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessEvent {
+    Stdout(String),
+    Stderr(String),
+    /// Always the last event. `code` is `None` when the process was killed by a signal.
+    Exit { code: Option<i32>, duration_ms: u64 },
+}
+
+/// A process started by `spawn_streaming`.
+#[derive(Debug)]
+pub struct StreamingProcess {
+    /// Process id, also the process group id when the command was given its own group
+    pub pid: Option<u32>,
+    pub events: mpsc::Receiver<ProcessEvent>,
+}
+
+async fn forward_lines<R: AsyncRead + Unpin>(reader: R, tx: mpsc::Sender<ProcessEvent>, wrap: fn(String) -> ProcessEvent) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if tx.send(wrap(line)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Sends `signal` to every process in the group led by `pid`, e.g. a command spawned with
+/// `process_group(0)` and everything it started.
+#[cfg(unix)]
+pub fn signal_process_group(pid: u32, signal: libc::c_int) {
+    // SAFETY: kill only sends a signal; a negative pid addresses the process group
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+    }
+}
+
+/// Spawns `cmd` with piped output and streams its stdout and stderr lines, followed by its
+/// exit status. Dropping the receiver kills the process, so a client that disconnects
+/// doesn't leave a build running.
+pub fn spawn_streaming(mut cmd: Command) -> Result<StreamingProcess> {
+    let program = cmd.as_std().get_program().to_string_lossy().into_owned();
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true);
+    let started = Instant::now();
+    let mut child = cmd.spawn().with_context(|| format!("terminal::stream: Failed to spawn {}", program))?;
+    let stdout = child.stdout.take().context("terminal::stream: Failed to capture stdout")?;
+    let stderr = child.stderr.take().context("terminal::stream: Failed to capture stderr")?;
+    let pid = child.id();
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        let forward = async {
+            tokio::join!(
+                forward_lines(stdout, tx.clone(), ProcessEvent::Stdout),
+                forward_lines(stderr, tx.clone(), ProcessEvent::Stderr),
+            )
+        };
+        tokio::select! {
+            _ = forward => {}
+            // Returning drops the child, which kills it
+            _ = tx.closed() => {
+                tracing::debug!(target: "terminal::stream", program = %program, "Output receiver dropped, killing the process.");
+                return;
+            }
+        }
+        let code = match child.wait().await {
+            Ok(status) => status.code(),
+            Err(e) => {
+                tracing::warn!(target: "terminal::stream", program = %program, error = %e, "Failed to wait for the process.");
+                None
+            }
+        };
+        let _ = tx.send(ProcessEvent::Exit { code, duration_ms: started.elapsed().as_millis() as u64 }).await;
+    });
+    Ok(StreamingProcess { pid, events: rx })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spawn_streaming_emits_lines_then_exit() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo one; echo oops >&2; echo two; exit 3"]);
+        let mut rx = spawn_streaming(cmd).unwrap().events;
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+
+        let stdout: Vec<_> = events.iter().filter_map(|e| match e { ProcessEvent::Stdout(l) => Some(l.as_str()), _ => None }).collect();
+        assert_eq!(stdout, vec!["one", "two"]);
+        assert!(events.contains(&ProcessEvent::Stderr("oops".to_string())));
+        assert!(matches!(events.last(), Some(ProcessEvent::Exit { code: Some(3), .. })));
+    }
+}
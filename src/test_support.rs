@@ -0,0 +1,7 @@
+use tempfile::TempDir;
+
+/// A temporary directory to lay out a project in. Not `tempfile::tempdir()`: its `.tmp` prefix
+/// makes the root a hidden directory, which the project scanners skip.
+pub fn project_tempdir() -> TempDir {
+    tempfile::Builder::new().prefix("galatea").tempdir().expect("Failed to create a temporary project directory")
+}